@@ -1,6 +1,36 @@
 use anyhow::Result;
+use std::fs;
 use vergen::{vergen, Config};
 
 fn main() -> Result<()> {
+    // Expose the version of the newest migration of each backend, so that we can
+    // tell whether a wallet database was created by a newer release of
+    // full-service.
+    println!(
+        "cargo:rustc-env=MC_LATEST_MIGRATION_VERSION={}",
+        latest_migration_version("migrations")?
+    );
+    println!(
+        "cargo:rustc-env=MC_LATEST_POSTGRES_MIGRATION_VERSION={}",
+        latest_migration_version("postgres-migrations")?
+    );
+
     vergen(Config::default())
 }
+
+fn latest_migration_version(dir: &str) -> Result<String> {
+    println!("cargo:rerun-if-changed={}", dir);
+    Ok(fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            // Diesel uses the leading timestamp of the directory name, without
+            // separators, as the migration version.
+            name.split('_')
+                .next()
+                .map(|version| version.replace('-', ""))
+        })
+        .filter(|version| !version.is_empty() && version.chars().all(|c| c.is_ascii_digit()))
+        .max()
+        .unwrap_or_default())
+}
@@ -0,0 +1,5 @@
+CREATE TABLE wallet_events (
+    id INTEGER NOT NULL PRIMARY KEY,
+    event_type TEXT NOT NULL,
+    payload TEXT NOT NULL
+);
@@ -0,0 +1,9 @@
+CREATE TABLE transaction_watches (
+    id INTEGER NOT NULL PRIMARY KEY,
+    watch_id_hex TEXT NOT NULL UNIQUE,
+    tx_public_keys BLOB NOT NULL,
+    tombstone_block_index BIGINT,
+    status TEXT NOT NULL,
+    finalized_block_index BIGINT,
+    comment TEXT NOT NULL DEFAULT ''
+);
@@ -0,0 +1 @@
+ALTER TABLE txos DROP COLUMN ledger_txo_index;
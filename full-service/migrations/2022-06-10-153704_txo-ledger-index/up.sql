@@ -0,0 +1 @@
+ALTER TABLE txos ADD COLUMN ledger_txo_index BIGINT;
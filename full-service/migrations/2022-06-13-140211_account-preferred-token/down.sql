@@ -0,0 +1 @@
+ALTER TABLE accounts DROP COLUMN preferred_token_id;
@@ -0,0 +1 @@
+ALTER TABLE accounts ADD COLUMN preferred_token_id BIGINT NOT NULL DEFAULT 0;
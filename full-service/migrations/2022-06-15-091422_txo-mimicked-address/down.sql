@@ -0,0 +1 @@
+ALTER TABLE txos DROP COLUMN mimicked_address_b58;
@@ -0,0 +1 @@
+ALTER TABLE txos ADD COLUMN mimicked_address_b58 TEXT;
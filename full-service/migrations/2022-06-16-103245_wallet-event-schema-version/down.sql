@@ -0,0 +1 @@
+ALTER TABLE wallet_events DROP COLUMN schema_version;
@@ -0,0 +1 @@
+ALTER TABLE wallet_events ADD COLUMN schema_version INTEGER NOT NULL DEFAULT 1;
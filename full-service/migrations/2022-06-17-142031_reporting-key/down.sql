@@ -0,0 +1 @@
+DROP TABLE IF EXISTS reporting_keys;
@@ -0,0 +1,4 @@
+CREATE TABLE reporting_keys (
+    id INTEGER NOT NULL PRIMARY KEY,
+    private_key BLOB NOT NULL
+);
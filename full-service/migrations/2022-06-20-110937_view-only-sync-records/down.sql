@@ -0,0 +1 @@
+DROP TABLE IF EXISTS view_only_sync_records;
@@ -0,0 +1,9 @@
+CREATE TABLE view_only_sync_records (
+    id INTEGER NOT NULL PRIMARY KEY,
+    view_only_account_id_hex TEXT NOT NULL,
+    record_type TEXT NOT NULL,
+    payload_hash TEXT NOT NULL,
+    txo_count INTEGER NOT NULL,
+    request_record_id INTEGER,
+    created_time BIGINT NOT NULL
+);
@@ -0,0 +1,2 @@
+DROP TABLE IF EXISTS account_rotations;
+ALTER TABLE accounts DROP COLUMN archived;
@@ -0,0 +1,10 @@
+ALTER TABLE accounts ADD COLUMN archived BOOLEAN NOT NULL DEFAULT FALSE;
+
+CREATE TABLE account_rotations (
+    id INTEGER NOT NULL PRIMARY KEY,
+    old_account_id_hex TEXT NOT NULL UNIQUE,
+    new_account_id_hex TEXT NOT NULL,
+    status TEXT NOT NULL,
+    sweep_transaction_log_ids TEXT NOT NULL DEFAULT '[]',
+    failure_message TEXT
+);
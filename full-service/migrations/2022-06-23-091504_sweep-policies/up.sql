@@ -0,0 +1,12 @@
+CREATE TABLE sweep_policies (
+    id INTEGER NOT NULL PRIMARY KEY,
+    account_id_hex TEXT NOT NULL UNIQUE,
+    destination_address TEXT NOT NULL,
+    token_id BIGINT NOT NULL,
+    threshold BIGINT NOT NULL,
+    interval_seconds BIGINT NOT NULL,
+    next_run_time BIGINT NOT NULL,
+    last_run_time BIGINT,
+    last_transaction_log_id TEXT,
+    last_failure_message TEXT
+);
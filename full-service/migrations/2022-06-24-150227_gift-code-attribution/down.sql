@@ -0,0 +1,4 @@
+ALTER TABLE gift_codes DROP COLUMN claimed_time;
+ALTER TABLE gift_codes DROP COLUMN claim_transaction_log_id;
+ALTER TABLE gift_codes DROP COLUMN claimer_account_id_hex;
+ALTER TABLE gift_codes DROP COLUMN funding_account_id_hex;
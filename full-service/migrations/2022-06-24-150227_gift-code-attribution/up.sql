@@ -0,0 +1,4 @@
+ALTER TABLE gift_codes ADD COLUMN funding_account_id_hex TEXT;
+ALTER TABLE gift_codes ADD COLUMN claimer_account_id_hex TEXT;
+ALTER TABLE gift_codes ADD COLUMN claim_transaction_log_id TEXT;
+ALTER TABLE gift_codes ADD COLUMN claimed_time BIGINT;
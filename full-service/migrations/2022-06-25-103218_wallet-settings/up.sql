@@ -0,0 +1,4 @@
+CREATE TABLE wallet_settings (
+    name TEXT NOT NULL PRIMARY KEY,
+    value TEXT NOT NULL
+);
@@ -0,0 +1,7 @@
+CREATE TABLE quarantined_blocks (
+    id INTEGER NOT NULL PRIMARY KEY,
+    block_index BIGINT NOT NULL UNIQUE,
+    reason TEXT NOT NULL,
+    quarantined_time BIGINT NOT NULL,
+    skipped BOOLEAN NOT NULL DEFAULT FALSE
+);
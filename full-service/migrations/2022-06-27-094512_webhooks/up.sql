@@ -0,0 +1,9 @@
+CREATE TABLE webhooks (
+    id INTEGER NOT NULL PRIMARY KEY,
+    url TEXT NOT NULL,
+    account_id_hex TEXT,
+    last_event_id INTEGER NOT NULL DEFAULT 0,
+    failed_attempts INTEGER NOT NULL DEFAULT 0,
+    last_failure_message TEXT,
+    created_time BIGINT NOT NULL
+);
@@ -0,0 +1,2 @@
+DROP INDEX idx_assigned_subaddresses__account_id_hex__subaddress_index;
+DROP INDEX idx_gift_codes__gift_code_b58;
@@ -0,0 +1,5 @@
+-- Keep the first record of any gift code saved more than once, so that each
+-- gift code has one record to attribute its claim to.
+DELETE FROM gift_codes WHERE id NOT IN (SELECT MIN(id) FROM gift_codes GROUP BY gift_code_b58);
+CREATE UNIQUE INDEX idx_gift_codes__gift_code_b58 ON gift_codes (gift_code_b58);
+CREATE UNIQUE INDEX idx_assigned_subaddresses__account_id_hex__subaddress_index ON assigned_subaddresses (account_id_hex, subaddress_index);
@@ -0,0 +1 @@
+ALTER TABLE view_only_accounts DROP COLUMN hardware_backed;
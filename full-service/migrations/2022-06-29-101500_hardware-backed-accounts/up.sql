@@ -0,0 +1 @@
+ALTER TABLE view_only_accounts ADD COLUMN hardware_backed BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,11 @@
+CREATE TABLE account_sync_records (
+    id INTEGER NOT NULL PRIMARY KEY,
+    account_id_hex TEXT NOT NULL,
+    start_block_index BIGINT NOT NULL,
+    end_block_index BIGINT NOT NULL,
+    txos_received INTEGER NOT NULL,
+    txos_spent INTEGER NOT NULL,
+    duration_ms BIGINT NOT NULL,
+    created_time BIGINT NOT NULL
+);
+CREATE INDEX idx_account_sync_records__account_id_hex ON account_sync_records (account_id_hex);
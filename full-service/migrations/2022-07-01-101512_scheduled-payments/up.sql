@@ -0,0 +1,18 @@
+CREATE TABLE scheduled_payments (
+    id INTEGER NOT NULL PRIMARY KEY,
+    account_id_hex TEXT NOT NULL,
+    recipient_address TEXT NOT NULL,
+    value BIGINT NOT NULL,
+    token_id BIGINT NOT NULL,
+    interval_seconds BIGINT,
+    block_height BIGINT,
+    next_run_time BIGINT NOT NULL,
+    status TEXT NOT NULL,
+    comment TEXT NOT NULL,
+    payments_submitted INTEGER NOT NULL DEFAULT 0,
+    last_run_time BIGINT,
+    last_transaction_log_id TEXT,
+    last_failure_message TEXT,
+    created_time BIGINT NOT NULL
+);
+CREATE INDEX idx_scheduled_payments__account_id_hex ON scheduled_payments (account_id_hex);
@@ -0,0 +1,15 @@
+CREATE TABLE payment_requests (
+    id INTEGER NOT NULL PRIMARY KEY,
+    account_id_hex TEXT NOT NULL,
+    subaddress_index BIGINT NOT NULL,
+    value BIGINT NOT NULL,
+    token_id BIGINT NOT NULL,
+    memo TEXT NOT NULL,
+    expiry_block_index BIGINT,
+    status TEXT NOT NULL,
+    paid_txo_id_hex TEXT,
+    paid_value BIGINT,
+    paid_block_index BIGINT,
+    created_time BIGINT NOT NULL
+);
+CREATE INDEX idx_payment_requests__account_id_hex ON payment_requests (account_id_hex);
@@ -0,0 +1,2 @@
+ALTER TABLE txos DROP COLUMN destination_address_hash;
+ALTER TABLE txos DROP COLUMN sender_address_hash;
@@ -0,0 +1,2 @@
+ALTER TABLE txos ADD COLUMN sender_address_hash TEXT;
+ALTER TABLE txos ADD COLUMN destination_address_hash TEXT;
@@ -0,0 +1,2 @@
+DROP TABLE IF EXISTS db_size_samples;
+DROP TABLE IF EXISTS rpc_usage;
@@ -0,0 +1,15 @@
+CREATE TABLE rpc_usage (
+    id INTEGER NOT NULL PRIMARY KEY,
+    hour_start BIGINT NOT NULL,
+    method TEXT NOT NULL,
+    calls BIGINT NOT NULL,
+    errors BIGINT NOT NULL,
+    total_duration_ms BIGINT NOT NULL,
+    UNIQUE (hour_start, method)
+);
+CREATE TABLE db_size_samples (
+    id INTEGER NOT NULL PRIMARY KEY,
+    wallet_db_bytes BIGINT,
+    ledger_db_bytes BIGINT,
+    created_time BIGINT NOT NULL
+);
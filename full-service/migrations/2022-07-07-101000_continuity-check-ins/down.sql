@@ -0,0 +1 @@
+DROP TABLE IF EXISTS continuity_check_ins;
@@ -0,0 +1,6 @@
+CREATE TABLE continuity_check_ins (
+    id INTEGER NOT NULL PRIMARY KEY,
+    created_time BIGINT NOT NULL,
+    exported_time BIGINT,
+    export_path TEXT
+);
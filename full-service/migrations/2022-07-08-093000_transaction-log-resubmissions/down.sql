@@ -0,0 +1 @@
+ALTER TABLE transaction_logs DROP COLUMN resubmitted_transaction_id_hex;
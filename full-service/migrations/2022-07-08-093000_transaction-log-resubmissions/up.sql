@@ -0,0 +1 @@
+ALTER TABLE transaction_logs ADD COLUMN resubmitted_transaction_id_hex TEXT;
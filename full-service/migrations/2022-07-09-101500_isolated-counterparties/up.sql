@@ -0,0 +1,7 @@
+CREATE TABLE isolated_counterparties (
+    id INTEGER NOT NULL PRIMARY KEY,
+    account_id_hex TEXT NOT NULL,
+    address_hash TEXT NOT NULL,
+    created_time BIGINT NOT NULL,
+    UNIQUE (account_id_hex, address_hash)
+);
@@ -0,0 +1 @@
+ALTER TABLE txos ADD COLUMN frozen BOOLEAN NOT NULL DEFAULT FALSE;
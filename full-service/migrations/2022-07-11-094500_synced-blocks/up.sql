@@ -0,0 +1,5 @@
+CREATE TABLE synced_blocks (
+    id INTEGER NOT NULL PRIMARY KEY,
+    block_index BIGINT NOT NULL UNIQUE,
+    block_id_hex TEXT NOT NULL
+);
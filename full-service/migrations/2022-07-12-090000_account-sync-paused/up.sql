@@ -0,0 +1 @@
+ALTER TABLE accounts ADD COLUMN sync_paused BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,2 @@
+ALTER TABLE gift_codes DROP COLUMN expired;
+ALTER TABLE gift_codes DROP COLUMN expiry_time;
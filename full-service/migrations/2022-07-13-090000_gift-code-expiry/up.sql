@@ -0,0 +1,2 @@
+ALTER TABLE gift_codes ADD COLUMN expiry_time BIGINT;
+ALTER TABLE gift_codes ADD COLUMN expired BOOLEAN NOT NULL DEFAULT FALSE;
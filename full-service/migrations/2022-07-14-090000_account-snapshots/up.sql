@@ -0,0 +1,10 @@
+CREATE TABLE account_snapshots (
+    id INTEGER NOT NULL PRIMARY KEY,
+    account_id_hex TEXT NOT NULL,
+    block_index BIGINT NOT NULL,
+    token_id BIGINT NOT NULL,
+    unspent_value TEXT NOT NULL,
+    unspent_txo_count BIGINT NOT NULL,
+    spent_txo_count BIGINT NOT NULL,
+    UNIQUE (account_id_hex, block_index, token_id)
+);
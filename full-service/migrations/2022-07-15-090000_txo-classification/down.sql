@@ -0,0 +1 @@
+ALTER TABLE txos DROP COLUMN classification;
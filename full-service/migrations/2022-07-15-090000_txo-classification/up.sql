@@ -0,0 +1 @@
+ALTER TABLE txos ADD COLUMN classification TEXT;
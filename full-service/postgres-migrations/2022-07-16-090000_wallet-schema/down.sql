@@ -0,0 +1,27 @@
+DROP TABLE IF EXISTS account_snapshots;
+DROP TABLE IF EXISTS synced_blocks;
+DROP TABLE IF EXISTS isolated_counterparties;
+DROP TABLE IF EXISTS continuity_check_ins;
+DROP TABLE IF EXISTS db_size_samples;
+DROP TABLE IF EXISTS rpc_usage;
+DROP TABLE IF EXISTS payment_requests;
+DROP TABLE IF EXISTS scheduled_payments;
+DROP TABLE IF EXISTS account_sync_records;
+DROP TABLE IF EXISTS webhooks;
+DROP TABLE IF EXISTS quarantined_blocks;
+DROP TABLE IF EXISTS wallet_settings;
+DROP TABLE IF EXISTS sweep_policies;
+DROP TABLE IF EXISTS account_rotations;
+DROP TABLE IF EXISTS view_only_sync_records;
+DROP TABLE IF EXISTS reporting_keys;
+DROP TABLE IF EXISTS transaction_watches;
+DROP TABLE IF EXISTS wallet_events;
+DROP TABLE IF EXISTS view_only_subaddresses;
+DROP TABLE IF EXISTS view_only_txos;
+DROP TABLE IF EXISTS view_only_accounts;
+DROP TABLE IF EXISTS gift_codes;
+DROP TABLE IF EXISTS transaction_txo_types;
+DROP TABLE IF EXISTS transaction_logs;
+DROP TABLE IF EXISTS txos;
+DROP TABLE IF EXISTS assigned_subaddresses;
+DROP TABLE IF EXISTS accounts;
@@ -0,0 +1,308 @@
+-- The wallet schema as of the SQLite migrations up to 2022-07-15, for wallet
+-- databases stored in Postgres. Later schema changes need a migration here as
+-- well as in migrations/.
+
+CREATE TABLE accounts (
+    id SERIAL PRIMARY KEY,
+    account_id_hex TEXT NOT NULL UNIQUE,
+    account_key BYTEA NOT NULL,
+    entropy BYTEA NOT NULL,
+    key_derivation_version INTEGER NOT NULL DEFAULT 1,
+    main_subaddress_index BIGINT NOT NULL,
+    change_subaddress_index BIGINT NOT NULL,
+    next_subaddress_index BIGINT NOT NULL,
+    first_block_index BIGINT NOT NULL,
+    next_block_index BIGINT NOT NULL,
+    import_block_index BIGINT,
+    name TEXT NOT NULL DEFAULT '',
+    fog_enabled BOOLEAN NOT NULL DEFAULT FALSE,
+    preferred_token_id BIGINT NOT NULL DEFAULT 0,
+    archived BOOLEAN NOT NULL DEFAULT FALSE,
+    sync_paused BOOLEAN NOT NULL DEFAULT FALSE
+);
+
+CREATE TABLE assigned_subaddresses (
+    id SERIAL PRIMARY KEY,
+    assigned_subaddress_b58 TEXT NOT NULL UNIQUE,
+    account_id_hex TEXT NOT NULL REFERENCES accounts(account_id_hex),
+    address_book_entry BIGINT,
+    public_address BYTEA NOT NULL,
+    subaddress_index BIGINT NOT NULL,
+    comment TEXT NOT NULL DEFAULT '',
+    subaddress_spend_key BYTEA NOT NULL,
+    UNIQUE (account_id_hex, subaddress_index)
+);
+
+CREATE TABLE txos (
+    id SERIAL PRIMARY KEY,
+    txo_id_hex TEXT NOT NULL UNIQUE,
+    value BIGINT NOT NULL,
+    token_id BIGINT NOT NULL DEFAULT 0,
+    target_key BYTEA NOT NULL,
+    public_key BYTEA NOT NULL,
+    e_fog_hint BYTEA NOT NULL,
+    txo BYTEA NOT NULL,
+    subaddress_index BIGINT,
+    key_image BYTEA,
+    received_block_index BIGINT,
+    pending_tombstone_block_index BIGINT,
+    spent_block_index BIGINT,
+    confirmation BYTEA,
+    recipient_public_address_b58 TEXT NOT NULL DEFAULT '',
+    minted_account_id_hex TEXT,
+    received_account_id_hex TEXT,
+    ledger_txo_index BIGINT,
+    mimicked_address_b58 TEXT,
+    sender_address_hash TEXT,
+    destination_address_hash TEXT,
+    frozen BOOLEAN NOT NULL DEFAULT FALSE,
+    classification TEXT
+);
+
+CREATE TABLE transaction_logs (
+    id SERIAL PRIMARY KEY,
+    transaction_id_hex TEXT NOT NULL UNIQUE,
+    account_id_hex TEXT NOT NULL REFERENCES accounts(account_id_hex),
+    assigned_subaddress_b58 TEXT REFERENCES assigned_subaddresses(assigned_subaddress_b58),
+    value BIGINT NOT NULL,
+    fee BIGINT,
+    status TEXT NOT NULL,
+    sent_time BIGINT,
+    submitted_block_index BIGINT,
+    finalized_block_index BIGINT,
+    comment TEXT NOT NULL DEFAULT '',
+    direction TEXT NOT NULL,
+    tx BYTEA,
+    resubmitted_transaction_id_hex TEXT
+);
+
+CREATE TABLE transaction_txo_types (
+    transaction_id_hex TEXT NOT NULL REFERENCES transaction_logs(transaction_id_hex),
+    txo_id_hex TEXT NOT NULL REFERENCES txos(txo_id_hex),
+    transaction_txo_type TEXT NOT NULL,
+    PRIMARY KEY (transaction_id_hex, txo_id_hex)
+);
+
+CREATE TABLE gift_codes (
+    id SERIAL PRIMARY KEY,
+    gift_code_b58 TEXT NOT NULL UNIQUE,
+    value BIGINT NOT NULL,
+    funding_account_id_hex TEXT,
+    claimer_account_id_hex TEXT,
+    claim_transaction_log_id TEXT,
+    claimed_time BIGINT,
+    expiry_time BIGINT,
+    expired BOOLEAN NOT NULL DEFAULT FALSE
+);
+
+CREATE TABLE view_only_accounts (
+    id SERIAL PRIMARY KEY,
+    account_id_hex TEXT NOT NULL UNIQUE,
+    view_private_key BYTEA NOT NULL,
+    first_block_index BIGINT NOT NULL,
+    next_block_index BIGINT NOT NULL,
+    main_subaddress_index BIGINT NOT NULL DEFAULT 0,
+    change_subaddress_index BIGINT NOT NULL DEFAULT 1,
+    next_subaddress_index BIGINT NOT NULL DEFAULT 2,
+    import_block_index BIGINT NOT NULL,
+    name TEXT NOT NULL DEFAULT '',
+    hardware_backed BOOLEAN NOT NULL DEFAULT FALSE
+);
+
+CREATE TABLE view_only_txos (
+    id SERIAL PRIMARY KEY,
+    txo_id_hex TEXT NOT NULL UNIQUE,
+    txo BYTEA NOT NULL,
+    key_image BYTEA,
+    subaddress_index BIGINT,
+    value BIGINT NOT NULL,
+    token_id BIGINT NOT NULL DEFAULT 0,
+    public_key BYTEA NOT NULL,
+    view_only_account_id_hex TEXT NOT NULL REFERENCES view_only_accounts(account_id_hex),
+    submitted_block_index BIGINT,
+    pending_tombstone_block_index BIGINT,
+    received_block_index BIGINT,
+    spent_block_index BIGINT
+);
+
+CREATE TABLE view_only_subaddresses (
+    id SERIAL PRIMARY KEY,
+    public_address_b58 TEXT NOT NULL UNIQUE,
+    subaddress_index BIGINT NOT NULL,
+    view_only_account_id_hex TEXT NOT NULL REFERENCES view_only_accounts(account_id_hex),
+    comment TEXT NOT NULL DEFAULT '',
+    public_spend_key BYTEA NOT NULL
+);
+
+CREATE TABLE wallet_events (
+    id SERIAL PRIMARY KEY,
+    event_type TEXT NOT NULL,
+    payload TEXT NOT NULL,
+    schema_version INTEGER NOT NULL DEFAULT 1
+);
+
+CREATE TABLE transaction_watches (
+    id SERIAL PRIMARY KEY,
+    watch_id_hex TEXT NOT NULL UNIQUE,
+    tx_public_keys BYTEA NOT NULL,
+    tombstone_block_index BIGINT,
+    status TEXT NOT NULL,
+    finalized_block_index BIGINT,
+    comment TEXT NOT NULL DEFAULT ''
+);
+
+CREATE TABLE reporting_keys (
+    id SERIAL PRIMARY KEY,
+    private_key BYTEA NOT NULL
+);
+
+CREATE TABLE view_only_sync_records (
+    id SERIAL PRIMARY KEY,
+    view_only_account_id_hex TEXT NOT NULL,
+    record_type TEXT NOT NULL,
+    payload_hash TEXT NOT NULL,
+    txo_count INTEGER NOT NULL,
+    request_record_id INTEGER,
+    created_time BIGINT NOT NULL
+);
+
+CREATE TABLE account_rotations (
+    id SERIAL PRIMARY KEY,
+    old_account_id_hex TEXT NOT NULL UNIQUE,
+    new_account_id_hex TEXT NOT NULL,
+    status TEXT NOT NULL,
+    sweep_transaction_log_ids TEXT NOT NULL DEFAULT '[]',
+    failure_message TEXT
+);
+
+CREATE TABLE sweep_policies (
+    id SERIAL PRIMARY KEY,
+    account_id_hex TEXT NOT NULL UNIQUE,
+    destination_address TEXT NOT NULL,
+    token_id BIGINT NOT NULL,
+    threshold BIGINT NOT NULL,
+    interval_seconds BIGINT NOT NULL,
+    next_run_time BIGINT NOT NULL,
+    last_run_time BIGINT,
+    last_transaction_log_id TEXT,
+    last_failure_message TEXT
+);
+
+CREATE TABLE wallet_settings (
+    name TEXT NOT NULL PRIMARY KEY,
+    value TEXT NOT NULL
+);
+
+CREATE TABLE quarantined_blocks (
+    id SERIAL PRIMARY KEY,
+    block_index BIGINT NOT NULL UNIQUE,
+    reason TEXT NOT NULL,
+    quarantined_time BIGINT NOT NULL,
+    skipped BOOLEAN NOT NULL DEFAULT FALSE
+);
+
+CREATE TABLE webhooks (
+    id SERIAL PRIMARY KEY,
+    url TEXT NOT NULL,
+    account_id_hex TEXT,
+    last_event_id INTEGER NOT NULL DEFAULT 0,
+    failed_attempts INTEGER NOT NULL DEFAULT 0,
+    last_failure_message TEXT,
+    created_time BIGINT NOT NULL
+);
+
+CREATE TABLE account_sync_records (
+    id SERIAL PRIMARY KEY,
+    account_id_hex TEXT NOT NULL,
+    start_block_index BIGINT NOT NULL,
+    end_block_index BIGINT NOT NULL,
+    txos_received INTEGER NOT NULL,
+    txos_spent INTEGER NOT NULL,
+    duration_ms BIGINT NOT NULL,
+    created_time BIGINT NOT NULL
+);
+CREATE INDEX idx_account_sync_records__account_id_hex ON account_sync_records (account_id_hex);
+
+CREATE TABLE scheduled_payments (
+    id SERIAL PRIMARY KEY,
+    account_id_hex TEXT NOT NULL,
+    recipient_address TEXT NOT NULL,
+    value BIGINT NOT NULL,
+    token_id BIGINT NOT NULL,
+    interval_seconds BIGINT,
+    block_height BIGINT,
+    next_run_time BIGINT NOT NULL,
+    status TEXT NOT NULL,
+    comment TEXT NOT NULL,
+    payments_submitted INTEGER NOT NULL DEFAULT 0,
+    last_run_time BIGINT,
+    last_transaction_log_id TEXT,
+    last_failure_message TEXT,
+    created_time BIGINT NOT NULL
+);
+CREATE INDEX idx_scheduled_payments__account_id_hex ON scheduled_payments (account_id_hex);
+
+CREATE TABLE payment_requests (
+    id SERIAL PRIMARY KEY,
+    account_id_hex TEXT NOT NULL,
+    subaddress_index BIGINT NOT NULL,
+    value BIGINT NOT NULL,
+    token_id BIGINT NOT NULL,
+    memo TEXT NOT NULL,
+    expiry_block_index BIGINT,
+    status TEXT NOT NULL,
+    paid_txo_id_hex TEXT,
+    paid_value BIGINT,
+    paid_block_index BIGINT,
+    created_time BIGINT NOT NULL
+);
+CREATE INDEX idx_payment_requests__account_id_hex ON payment_requests (account_id_hex);
+
+CREATE TABLE rpc_usage (
+    id SERIAL PRIMARY KEY,
+    hour_start BIGINT NOT NULL,
+    method TEXT NOT NULL,
+    calls BIGINT NOT NULL,
+    errors BIGINT NOT NULL,
+    total_duration_ms BIGINT NOT NULL,
+    UNIQUE (hour_start, method)
+);
+
+CREATE TABLE db_size_samples (
+    id SERIAL PRIMARY KEY,
+    wallet_db_bytes BIGINT,
+    ledger_db_bytes BIGINT,
+    created_time BIGINT NOT NULL
+);
+
+CREATE TABLE continuity_check_ins (
+    id SERIAL PRIMARY KEY,
+    created_time BIGINT NOT NULL,
+    exported_time BIGINT,
+    export_path TEXT
+);
+
+CREATE TABLE isolated_counterparties (
+    id SERIAL PRIMARY KEY,
+    account_id_hex TEXT NOT NULL,
+    address_hash TEXT NOT NULL,
+    created_time BIGINT NOT NULL,
+    UNIQUE (account_id_hex, address_hash)
+);
+
+CREATE TABLE synced_blocks (
+    id SERIAL PRIMARY KEY,
+    block_index BIGINT NOT NULL UNIQUE,
+    block_id_hex TEXT NOT NULL
+);
+
+CREATE TABLE account_snapshots (
+    id SERIAL PRIMARY KEY,
+    account_id_hex TEXT NOT NULL,
+    block_index BIGINT NOT NULL,
+    token_id BIGINT NOT NULL,
+    unspent_value TEXT NOT NULL,
+    unspent_txo_count BIGINT NOT NULL,
+    spent_txo_count BIGINT NOT NULL,
+    UNIQUE (account_id_hex, block_index, token_id)
+);
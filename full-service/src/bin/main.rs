@@ -3,7 +3,7 @@
 //! MobileCoin wallet service
 
 #![feature(proc_macro_hygiene, decl_macro)]
-use diesel::{prelude::*, SqliteConnection};
+use diesel::prelude::*;
 use dotenv::dotenv;
 use mc_attest_verifier::{MrSignerVerifier, Verifier, DEBUG_ENCLAVE};
 use mc_common::logger::{create_app_logger, log, o, Logger};
@@ -13,14 +13,26 @@ use mc_fog_report_validation::FogResolver;
 use mc_full_service::{
     check_host,
     config::APIConfig,
-    wallet::{consensus_backed_rocket, validator_backed_rocket, APIKeyState, WalletState},
-    ValidatorLedgerSyncThread, WalletDb, WalletService,
+    consensus_pool::ConsensusPoolKeeper,
+    db::DbConnection,
+    disk_space_monitor::LedgerSyncFactory,
+    json_rpc::{api_scope::ApiScope, capture::RequestCapture},
+    service::{
+        diagnostics::{DiagnosticCheck, DiagnosticStatus, DiagnosticsService},
+        scheduler::SchedulerThread,
+    },
+    tls_reload::{check_tls_files, TlsReloadThread},
+    wallet::{
+        consensus_backed_rocket, validator_backed_rocket, APIKeyState, ApiPresetState,
+        CanonicalJsonState, MethodAllowlistState, MetricsState, ScopedAPIKeysState, WalletState,
+    },
+    DiskSpaceMonitor, ValidatorLedgerSyncThread, WalletDb, WalletService, WalletServiceOptions,
 };
 use mc_ledger_sync::{LedgerSyncServiceThread, PollingNetworkState, ReqwestTransactionsFetcher};
 use mc_validator_api::ValidatorUri;
 use mc_validator_connection::ValidatorConnection;
 use std::{
-    env,
+    path::PathBuf,
     process::exit,
     sync::{Arc, RwLock},
 };
@@ -34,6 +46,10 @@ extern crate diesel_migrations;
 const EXIT_NO_DATABASE_CONNECTION: i32 = 2;
 const EXIT_WRONG_PASSWORD: i32 = 3;
 const EXIT_INVALID_HOST: i32 = 4;
+const EXIT_INVALID_TLS_CONFIG: i32 = 5;
+const EXIT_INVALID_API_KEY_CONFIG: i32 = 6;
+#[cfg(feature = "postgres")]
+const EXIT_UNSUPPORTED_DATABASE_PASSWORD: i32 = 7;
 
 fn main() {
     dotenv().ok();
@@ -55,25 +71,113 @@ fn main() {
 
     let (logger, _global_logger_guard) = create_app_logger(o!());
 
-    let rocket_config: rocket::Config =
+    if config.api_key.as_deref().unwrap_or_default().is_empty()
+        && !config.get_scoped_api_keys().is_empty()
+    {
+        eprintln!(
+            "MC_VIEW_ONLY_API_KEY or MC_SIGNER_API_KEY is set without an API key. Set one with --api-key or MC_API_KEY."
+        );
+        exit(EXIT_INVALID_API_KEY_CONFIG);
+    }
+
+    if config.api_key.as_deref().unwrap_or_default().is_empty() {
+        log::warn!(
+            logger,
+            "No API key is set, so anyone who can reach {}:{} can spend from the wallet. Set one with --api-key or MC_API_KEY.",
+            config.listen_host,
+            config.listen_port
+        );
+    }
+
+    if let Some(preset) = config.preset {
+        log::info!(logger, "Limiting the API to the {} preset", preset);
+    }
+
+    if let Some(allowlist) = config.method_allowlist.as_ref() {
+        log::info!(
+            logger,
+            "Serving only the {} methods in the method allowlist",
+            allowlist.0.len()
+        );
+    }
+
+    if let Some(path) = config.capture_requests.as_ref() {
+        log::warn!(
+            logger,
+            "Capturing API requests and responses to {:?}. Secrets are redacted, but the capture still shows the wallet's accounts and activity.",
+            path
+        );
+    }
+
+    let mut rocket_config: rocket::Config =
         rocket::Config::build(rocket::config::Environment::Development)
             .address(&config.listen_host)
             .port(config.listen_port)
             .unwrap();
 
-    // Connect to the database and run the migrations
-    let conn =
-        SqliteConnection::establish(config.wallet_db.to_str().unwrap()).unwrap_or_else(|err| {
-            eprintln!("Cannot open database {:?}: {:?}", config.wallet_db, err);
-            exit(EXIT_NO_DATABASE_CONNECTION);
-        });
-    WalletDb::set_db_encryption_key_from_env(&conn);
-    WalletDb::try_change_db_encryption_key_from_env(&conn);
-    if !WalletDb::check_database_connectivity(&conn) {
-        eprintln!("Incorrect password for database {:?}.", config.wallet_db);
-        exit(EXIT_WRONG_PASSWORD);
+    // Serve HTTPS directly if given a certificate, restarting on SIGHUP to
+    // pick up a renewed one.
+    let _tls_reload_thread = match (config.tls_cert.as_ref(), config.tls_key.as_ref()) {
+        (Some(cert_path), Some(key_path)) => {
+            if let Err(err) = check_tls_files(cert_path, key_path) {
+                eprintln!("Invalid TLS certificate or key: {}", err);
+                exit(EXIT_INVALID_TLS_CONFIG);
+            }
+            rocket_config
+                .set_tls(
+                    cert_path.to_str().expect("Could not get tls_cert path"),
+                    key_path.to_str().expect("Could not get tls_key path"),
+                )
+                .unwrap_or_else(|err| {
+                    eprintln!("Invalid TLS certificate or key: {}", err);
+                    exit(EXIT_INVALID_TLS_CONFIG);
+                });
+            Some(TlsReloadThread::start(
+                cert_path.clone(),
+                key_path.clone(),
+                logger.clone(),
+            ))
+        }
+        _ => None,
     };
-    WalletDb::run_migrations(&conn);
+
+    // Postgres databases are not encrypted by full-service, so refuse a password
+    // rather than leave the operator thinking the database is encrypted.
+    #[cfg(feature = "postgres")]
+    if config.wallet_db_password.is_some() {
+        eprintln!(
+            "--wallet-db-password and MC_PASSWORD are not supported with Postgres. Use the encryption and access controls of the Postgres server instead."
+        );
+        exit(EXIT_UNSUPPORTED_DATABASE_PASSWORD);
+    }
+
+    // The database password is read from MC_PASSWORD wherever a connection is
+    // opened, so pass on one given with --wallet-db-password.
+    #[cfg(not(feature = "postgres"))]
+    if let Some(password) = config.wallet_db_password.as_ref() {
+        std::env::set_var("MC_PASSWORD", password);
+    }
+
+    // Connect to the database and run the migrations. The connection is closed
+    // once they are done, so that the pool holds the only connections to the
+    // database if its password is changed.
+    {
+        let conn =
+            DbConnection::establish(config.wallet_db.to_str().unwrap()).unwrap_or_else(|err| {
+                eprintln!("Cannot open database {:?}: {:?}", config.wallet_db, err);
+                exit(EXIT_NO_DATABASE_CONNECTION);
+            });
+        #[cfg(not(feature = "postgres"))]
+        {
+            WalletDb::set_db_encryption_key_from_env(&conn);
+            WalletDb::try_change_db_encryption_key_from_env(&conn);
+        }
+        if !WalletDb::check_database_connectivity(&conn) {
+            eprintln!("Incorrect password for database {:?}.", config.wallet_db);
+            exit(EXIT_WRONG_PASSWORD);
+        };
+        WalletDb::run_migrations(&conn);
+    }
     log::info!(logger, "Connected to database.");
 
     let wallet_db = WalletDb::new_from_url(
@@ -109,7 +213,11 @@ fn consensus_backed_full_service(
 
     log::debug!(logger, "Verifier: {:?}", verifier);
 
-    // Create peer manager.
+    // Create peer manager, and the pool of warm connections transactions are
+    // submitted over.
+    let consensus_pool = config
+        .peers_config
+        .create_connection_pool(verifier.clone(), &logger);
     let peer_manager = config.peers_config.create_peer_manager(verifier, &logger);
 
     // Create network state, transactions fetcher and ledger sync.
@@ -140,19 +248,49 @@ fn consensus_backed_full_service(
         &logger,
     );
 
-    // Start ledger sync thread unless running in offline mode.
-    let _ledger_sync_service_thread = if config.offline {
+    // Start ledger sync thread unless running in offline mode. The disk space
+    // monitor owns the thread, so that it can pause syncing when the disk is
+    // nearly full.
+    let ledger_sync_factory: Option<LedgerSyncFactory> = if config.offline {
         None
     } else {
-        Some(LedgerSyncServiceThread::new(
-            ledger_db.clone(),
-            peer_manager.clone(),
-            network_state.clone(),
-            transactions_fetcher,
-            config.poll_interval,
-            logger.clone(),
-        ))
+        let ledger_db = ledger_db.clone();
+        let peer_manager = peer_manager.clone();
+        let network_state = network_state.clone();
+        let transactions_fetcher = transactions_fetcher.clone();
+        let poll_interval = config.poll_interval;
+        let logger = logger.clone();
+        Some(Box::new(move || {
+            LedgerSyncServiceThread::new(
+                ledger_db.clone(),
+                peer_manager.clone(),
+                network_state.clone(),
+                transactions_fetcher.clone(),
+                poll_interval,
+                logger.clone(),
+            )
+        }))
     };
+    let disk_space_monitor = DiskSpaceMonitor::new(
+        disk_space_volumes(config),
+        config.get_disk_space_thresholds(),
+        config.poll_interval,
+        ledger_sync_factory,
+        logger.clone(),
+    );
+
+    // Attest the pooled connections ahead of submissions, unless offline.
+    let _consensus_pool_keeper = consensus_pool
+        .clone()
+        .filter(|_| !config.offline)
+        .map(|pool| {
+            ConsensusPoolKeeper::start(
+                pool,
+                config.poll_interval,
+                config.peers_config.peer_reattest_interval,
+                logger.clone(),
+            )
+        });
 
     let service = WalletService::new(
         wallet_db,
@@ -160,14 +298,47 @@ fn consensus_backed_full_service(
         peer_manager,
         network_state,
         config.get_fog_resolver_factory(logger.clone()),
-        config.offline,
+        WalletServiceOptions {
+            ledger_db_path: Some(config.ledger_db_config.ledger_db.clone()),
+            disk_space: Some(disk_space_monitor.state()),
+            consensus_pool,
+            offline: config.offline,
+            spending_lock: config.get_spending_lock(),
+            maintenance_mode: config.get_maintenance_mode(),
+            token_metadata_overrides: config.get_token_metadata_overrides(),
+            large_send_guard: config.get_large_send_guard(),
+            transaction_signer: config.get_transaction_signer(),
+            continuity_export: config.get_continuity_export(),
+            txo_classifier: config.get_txo_classifier(),
+            sync_threads: config.sync_threads,
+            sync_chunk_size: config.sync_chunk_size,
+        },
         logger,
     );
+    log_diagnostics(&service.run_diagnostics(), &service.logger);
+    let _scheduler_thread = (!config.disable_scheduler).then(|| {
+        SchedulerThread::start(
+            service.background_handle(),
+            config.enable_webhooks,
+            config.resubmit_expired_transactions,
+            config.account_snapshot_interval_blocks,
+            service.logger.clone(),
+        )
+    });
     let state = WalletState { service };
 
-    let rocket = consensus_backed_rocket(rocket_config, state);
-    let api_key = env::var("MC_API_KEY").unwrap_or_default();
-    rocket.manage(APIKeyState(api_key)).launch();
+    let rocket = with_request_capture(consensus_backed_rocket(rocket_config, state), config);
+    rocket
+        .manage(APIKeyState(config.api_key.clone().unwrap_or_default()))
+        .manage(CanonicalJsonState(config.canonical_json))
+        .manage(ScopedAPIKeysState(config.get_scoped_api_keys()))
+        .manage(ApiPresetState(config.preset.unwrap_or(ApiScope::Full)))
+        .manage(MethodAllowlistState(
+            config.method_allowlist.clone().map(Arc::new),
+        ))
+        .manage(MetricsState(config.enable_metrics))
+        .manage(config.get_balance_badge_state())
+        .launch();
 }
 
 fn validator_backed_full_service(
@@ -177,7 +348,11 @@ fn validator_backed_full_service(
     rocket_config: rocket::Config,
     logger: Logger,
 ) {
-    let validator_conn = ValidatorConnection::new(validator_uri, logger.clone());
+    let validator_conn = ValidatorConnection::new_with_options(
+        validator_uri,
+        config.get_validator_connection_options(),
+        logger.clone(),
+    );
 
     // Create the ledger_db.
     let ledger_db = config.ledger_db_config.create_or_open_ledger_db(
@@ -208,12 +383,21 @@ fn validator_backed_full_service(
         logger.clone(),
     )));
 
-    // Create the ledger sync thread.
+    // Create the ledger sync thread, which pauses itself when the disk is
+    // nearly full.
+    let disk_space_monitor = DiskSpaceMonitor::new(
+        disk_space_volumes(config),
+        config.get_disk_space_thresholds(),
+        config.poll_interval,
+        None,
+        logger.clone(),
+    );
     let _ledger_sync_thread = ValidatorLedgerSyncThread::new(
-        validator_uri,
+        validator_conn.clone(),
         config.poll_interval,
         ledger_db.clone(),
         network_state.clone(),
+        disk_space_monitor.state(),
         logger.clone(),
     );
 
@@ -247,12 +431,91 @@ fn validator_backed_full_service(
                 )
             }
         }),
-        false,
+        WalletServiceOptions {
+            ledger_db_path: Some(config.ledger_db_config.ledger_db.clone()),
+            disk_space: Some(disk_space_monitor.state()),
+            spending_lock: config.get_spending_lock(),
+            maintenance_mode: config.get_maintenance_mode(),
+            token_metadata_overrides: config.get_token_metadata_overrides(),
+            large_send_guard: config.get_large_send_guard(),
+            transaction_signer: config.get_transaction_signer(),
+            continuity_export: config.get_continuity_export(),
+            txo_classifier: config.get_txo_classifier(),
+            sync_threads: config.sync_threads,
+            sync_chunk_size: config.sync_chunk_size,
+            ..Default::default()
+        },
         logger,
     );
+    log_diagnostics(&service.run_diagnostics(), &service.logger);
+    let _scheduler_thread = (!config.disable_scheduler).then(|| {
+        SchedulerThread::start(
+            service.background_handle(),
+            config.enable_webhooks,
+            config.resubmit_expired_transactions,
+            config.account_snapshot_interval_blocks,
+            service.logger.clone(),
+        )
+    });
     let state = WalletState { service };
 
-    let rocket = validator_backed_rocket(rocket_config, state);
-    let api_key = env::var("MC_API_KEY").unwrap_or_default();
-    rocket.manage(APIKeyState(api_key)).launch();
+    let rocket = with_request_capture(validator_backed_rocket(rocket_config, state), config);
+    rocket
+        .manage(APIKeyState(config.api_key.clone().unwrap_or_default()))
+        .manage(CanonicalJsonState(config.canonical_json))
+        .manage(ScopedAPIKeysState(config.get_scoped_api_keys()))
+        .manage(ApiPresetState(config.preset.unwrap_or(ApiScope::Full)))
+        .manage(MethodAllowlistState(
+            config.method_allowlist.clone().map(Arc::new),
+        ))
+        .manage(MetricsState(config.enable_metrics))
+        .manage(config.get_balance_badge_state())
+        .launch();
+}
+
+/// Capture API requests to the configured file, if any.
+fn with_request_capture(rocket: rocket::Rocket, config: &APIConfig) -> rocket::Rocket {
+    match config.capture_requests.as_ref() {
+        Some(path) => rocket
+            .manage(RequestCapture::create(path).expect("Could not open request capture file")),
+        None => rocket,
+    }
+}
+
+/// The database volumes to monitor for free space.
+fn disk_space_volumes(config: &APIConfig) -> Vec<(String, PathBuf, Option<u64>)> {
+    let mut volumes = vec![(
+        "ledger_db".to_string(),
+        config.ledger_db_config.ledger_db.clone(),
+        Some(config.ledger_db_config.get_max_size_bytes()),
+    )];
+    // A Postgres wallet database is given as a connection URL, and is stored
+    // on the Postgres server rather than on a local volume.
+    if !cfg!(feature = "postgres") {
+        volumes.push(("wallet_db".to_string(), config.wallet_db.clone(), None));
+    }
+    volumes
+}
+
+/// Log the outcome of the startup preflight checks, so that configuration
+/// problems are visible before the first API request.
+fn log_diagnostics(checks: &[DiagnosticCheck], logger: &Logger) {
+    for check in checks {
+        match check.status {
+            DiagnosticStatus::Passed | DiagnosticStatus::Skipped => {
+                log::info!(logger, "Preflight check {}: {}", check.name, check.message)
+            }
+            DiagnosticStatus::Warning => {
+                log::warn!(logger, "Preflight check {}: {}", check.name, check.message)
+            }
+            DiagnosticStatus::Failed => {
+                log::error!(
+                    logger,
+                    "Preflight check {} failed: {}",
+                    check.name,
+                    check.message
+                )
+            }
+        }
+    }
 }
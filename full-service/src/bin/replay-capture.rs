@@ -0,0 +1,117 @@
+// Copyright (c) 2020-2022 MobileCoin Inc.
+
+//! Replays API requests captured with --capture-requests against a wallet,
+//! reporting where its responses differ from the captured ones.
+
+use mc_full_service::{
+    json_rpc::capture::{diff_responses, read_capture_file},
+    wallet::API_KEY_HEADER,
+};
+use reqwest::{
+    blocking::Client,
+    header::{HeaderValue, CONTENT_TYPE},
+};
+use std::{path::PathBuf, process::exit, thread, time::Duration};
+use structopt::StructOpt;
+
+#[derive(Clone, Debug, StructOpt)]
+#[structopt(
+    name = "replay-capture",
+    about = "Replay captured API requests against a full-service wallet"
+)]
+struct Opts {
+    /// The capture file written by full-service.
+    #[structopt(parse(from_os_str))]
+    capture_file: PathBuf,
+
+    /// The wallet API to replay the requests to.
+    #[structopt(long, default_value = "http://127.0.0.1:9090/wallet")]
+    url: String,
+
+    /// API key of the wallet being replayed to.
+    #[structopt(long, env = "MC_API_KEY", hide_env_values = true)]
+    api_key: Option<String>,
+
+    /// Wait between requests as long as the captured requests were apart.
+    #[structopt(long)]
+    preserve_timing: bool,
+
+    /// Also replay requests whose secrets were redacted, which are otherwise
+    /// skipped, since they can't be made as they were captured.
+    #[structopt(long)]
+    include_redacted: bool,
+}
+
+fn main() {
+    let opts = Opts::from_args();
+
+    let exchanges = read_capture_file(&opts.capture_file).unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        exit(1);
+    });
+    let client = Client::builder()
+        .use_rustls_tls()
+        .build()
+        .expect("Could not build HTTP client");
+
+    let mut replayed_count = 0;
+    let mut mismatches = 0;
+    let mut previous_received_at_ms = None;
+    for (i, exchange) in exchanges.iter().enumerate() {
+        if exchange.redacted && !opts.include_redacted {
+            println!(
+                "{} {}: skipped, secrets were redacted",
+                i + 1,
+                exchange.method
+            );
+            continue;
+        }
+
+        if opts.preserve_timing {
+            if let Some(previous) = previous_received_at_ms {
+                let gap_ms = (exchange.received_at_ms - previous).max(0) as u64;
+                thread::sleep(Duration::from_millis(gap_ms));
+            }
+        }
+        previous_received_at_ms = Some(exchange.received_at_ms);
+
+        let mut request = client
+            .post(&opts.url)
+            .header(CONTENT_TYPE, HeaderValue::from_static("application/json"))
+            .body(exchange.request.to_string());
+        if let Some(api_key) = opts.api_key.as_ref() {
+            request = request.header(API_KEY_HEADER, api_key.as_str());
+        }
+        let replayed: serde_json::Value = request
+            .send()
+            .and_then(|response| response.text())
+            .map_err(|err| err.to_string())
+            .and_then(|body| serde_json::from_str(&body).map_err(|err| err.to_string()))
+            .unwrap_or_else(|err| {
+                eprintln!("{} {}: request failed: {}", i + 1, exchange.method, err);
+                exit(1);
+            });
+        replayed_count += 1;
+
+        let differences = diff_responses(&exchange.response, &replayed);
+        if differences.is_empty() {
+            println!("{} {}: matched", i + 1, exchange.method);
+        } else {
+            mismatches += 1;
+            println!(
+                "{} {}: differs at {}",
+                i + 1,
+                exchange.method,
+                differences.join(", ")
+            );
+        }
+    }
+
+    println!(
+        "Replayed {} requests, {} differed",
+        replayed_count, mismatches
+    );
+    if mismatches > 0 {
+        exit(2);
+    }
+}
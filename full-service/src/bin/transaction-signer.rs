@@ -13,7 +13,8 @@ use mc_full_service::{
         view_only_account::{ViewOnlyAccountJSON, ViewOnlyAccountSecretsJSON},
         view_only_subaddress::ViewOnlySubaddressJSON,
     },
-    unsigned_tx::UnsignedTx,
+    sync_proof::ViewOnlySyncProof,
+    unsigned_tx::{OfflineSigningRequest, UnsignedTx},
     util::b58,
 };
 use std::{convert::TryFrom, fs};
@@ -55,6 +56,10 @@ enum Opts {
     ViewOnlyImportPackage {
         secret_mnemonic: String,
     },
+    VerifySyncProof {
+        secret_mnemonic: String,
+        sync_proof: String,
+    },
 }
 
 fn main() {
@@ -89,6 +94,12 @@ fn main() {
         } => {
             sign_transaction(secret_mnemonic, request);
         }
+        Opts::VerifySyncProof {
+            ref secret_mnemonic,
+            ref sync_proof,
+        } => {
+            verify_sync_proof(secret_mnemonic, sync_proof);
+        }
     }
 }
 
@@ -150,6 +161,7 @@ fn generate_view_only_import_package(secret_mnemonic: &str) {
         main_subaddress_index: DEFAULT_SUBADDRESS_INDEX.to_string(),
         change_subaddress_index: CHANGE_SUBADDRESS_INDEX.to_string(),
         next_subaddress_index: 2.to_string(),
+        hardware_backed: false,
     };
 
     let account_secrets_json = ViewOnlyAccountSecretsJSON {
@@ -300,9 +312,40 @@ fn sign_transaction(secret_mnemonic: &str, request: &str) {
         fs::read_to_string(request).expect("Could not open generate subaddresses request file.");
     let request_json: serde_json::Value =
         serde_json::from_str(&request_data).expect("Malformed generate subaddresses request.");
+    // The result of export_signing_request holds the request itself.
+    let request_json = request_json
+        .get("signing_request")
+        .cloned()
+        .unwrap_or(request_json);
     let account_id = request_json.get("account_id").unwrap().as_str().unwrap();
     assert_eq!(account_secrets.account_id, account_id);
 
+    // Signing requests from export_signing_request are answered with the
+    // signed transaction for import_signed_transaction.
+    if request_json
+        .get("object")
+        .and_then(|object| object.as_str())
+        == Some("offline_signing_request")
+    {
+        let signing_request: OfflineSigningRequest =
+            serde_json::from_value(request_json).expect("Malformed signing request.");
+        let signed_transaction = match signing_request.sign(&account_key) {
+            Ok(signed_transaction) => signed_transaction,
+            Err(err) => {
+                println!("Could not sign the transaction: {}", err);
+                std::process::exit(1);
+            }
+        };
+        let json_command_request = JsonCommandRequest::import_signed_transaction {
+            signed_transaction,
+            comment: None,
+        };
+
+        let filename = format!("{}_completed.json", request.trim_end_matches(".json"));
+        write_json_command_request_to_file(&json_command_request, &filename);
+        return;
+    }
+
     let unsigned_tx: UnsignedTx = serde_json::from_value(
         request_json
             .get("unsigned_tx")
@@ -331,6 +374,51 @@ fn sign_transaction(secret_mnemonic: &str, request: &str) {
     write_json_command_request_to_file(&json_command_request, &filename);
 }
 
+fn verify_sync_proof(secret_mnemonic: &str, sync_proof: &str) {
+    // Load account key.
+    let mnemonic_json =
+        fs::read_to_string(secret_mnemonic).expect("Could not open secret mnemonic file.");
+    let account_secrets: AccountSecrets = serde_json::from_str(&mnemonic_json).unwrap();
+    let account_key = account_key_from_mnemonic_phrase(&account_secrets.mnemonic.unwrap());
+
+    // Load sync proof.
+    let sync_proof_data = fs::read_to_string(sync_proof).expect("Could not open sync proof file.");
+    let sync_proof_json: serde_json::Value =
+        serde_json::from_str(&sync_proof_data).expect("Malformed sync proof.");
+    let proof: ViewOnlySyncProof = serde_json::from_value(
+        sync_proof_json
+            .get("sync_proof")
+            .expect("Could not find \"sync_proof\".")
+            .clone(),
+    )
+    .expect("Malformed sync proof.");
+    assert_eq!(account_secrets.account_id, proof.account_id);
+
+    let verification = match proof.verify(account_key.view_private_key()) {
+        Ok(verification) => verification,
+        Err(err) => {
+            println!("Sync proof is invalid: {}", err);
+            std::process::exit(1);
+        }
+    };
+
+    println!(
+        "Checked blocks {} to {}, ending with block id {}",
+        verification.first_block_index, verification.last_block_index, verification.last_block_id
+    );
+    println!("Compare the block id against another copy of the ledger to be sure the blocks are genuine.");
+    for txo_id in &verification.missing_txo_ids {
+        println!("Received txo {} was not reported by the wallet", txo_id);
+    }
+    for txo_id in &verification.unexpected_txo_ids {
+        println!("Reported txo {} was not received by this account", txo_id);
+    }
+    if !verification.is_complete() {
+        std::process::exit(1);
+    }
+    println!("The wallet reported every txo received by this account.");
+}
+
 fn write_json_command_request_to_file(json_command_request: &JsonCommandRequest, filename: &str) {
     let src_json: serde_json::Value = serde_json::json!(json_command_request);
     let method = src_json.get("method").unwrap().as_str().unwrap();
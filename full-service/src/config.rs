@@ -2,6 +2,27 @@
 
 //! Config definition and processing for Wallet Service.
 
+use crate::{
+    consensus_pool::ConsensusConnectionPool,
+    db::account::AccountID,
+    hardware::ExternalSigner,
+    json_rpc::{
+        api_scope::{parse_api_preset, ApiScope, MethodAllowlist},
+        balance_badge::BalanceBadgeState,
+    },
+    service::{
+        continuity::ContinuityExportConfig,
+        large_send::{LargeSendGuard, LargeSendThreshold},
+        ledger_db::{validate_ledger_db_max_size, LEDGER_DB_MAP_SIZE_BYTES},
+        maintenance::MaintenanceMode,
+        spending_lock::SpendingLock,
+        sync::TxoClassifier,
+        token::TokenMetadataOverride,
+        transaction::TransactionSigner,
+    },
+    txo_classifier::ExternalTxoClassifier,
+    util::disk_space::DiskSpaceThresholds,
+};
 use mc_attest_verifier::{MrSignerVerifier, Verifier, DEBUG_ENCLAVE};
 use mc_common::{
     logger::{log, Logger},
@@ -9,6 +30,7 @@ use mc_common::{
 };
 use mc_connection::{ConnectionManager, HardcodedCredentialsProvider, ThickClient};
 use mc_consensus_scp::QuorumSet;
+use mc_crypto_keys::RistrettoPublic;
 use mc_fog_report_connection::GrpcFogReportConnection;
 use mc_fog_report_validation::FogResolver;
 use mc_ledger_db::{Ledger, LedgerDB};
@@ -17,10 +39,11 @@ use mc_transaction_core::BlockData;
 use mc_util_parse::parse_duration_in_seconds;
 use mc_util_uri::{ConnectionUri, ConsensusClientUri, FogUri};
 use mc_validator_api::ValidatorUri;
+use mc_validator_connection::{BasicCredentials, ValidatorConnectionOptions};
 
 use std::{
     convert::TryFrom,
-    fs,
+    env, fs,
     path::{Path, PathBuf},
     sync::Arc,
     time::Duration,
@@ -42,6 +65,17 @@ pub struct APIConfig {
     #[structopt(long, parse(from_os_str))]
     pub wallet_db: PathBuf,
 
+    /// API key clients must send in the X-API-KEY header. Without one, anyone
+    /// who can reach the listen port can spend from the wallet. Prefer setting
+    /// MC_API_KEY, so that it is not visible in the process list.
+    #[structopt(long, env = "MC_API_KEY", hide_env_values = true)]
+    pub api_key: Option<String>,
+
+    /// Password the wallet database is encrypted with, using SQLCipher. Prefer
+    /// setting MC_PASSWORD, so that it is not visible in the process list.
+    #[structopt(long, env = "MC_PASSWORD", hide_env_values = true)]
+    pub wallet_db_password: Option<String>,
+
     #[structopt(flatten)]
     pub ledger_db_config: LedgerDbConfig,
 
@@ -65,8 +99,191 @@ pub struct APIConfig {
     /// network directly.
     #[structopt(long)]
     pub validator: Option<ValidatorUri>,
+
+    /// PEM file containing the certificate(s) the validator's TLS certificate
+    /// must chain to. Only these certificates are trusted when set.
+    #[structopt(long, parse(try_from_str=load_pem_file), requires = "validator")]
+    pub validator_tls_ca_cert: Option<Vec<u8>>,
+
+    /// Hostname to expect in the validator's TLS certificate, if it differs
+    /// from the host in the validator URI.
+    #[structopt(long, requires = "validator")]
+    pub validator_tls_hostname: Option<String>,
+
+    /// Username to authenticate to the validator with. The password or shared
+    /// secret is read from the MC_VALIDATOR_SECRET environment variable.
+    #[structopt(long, requires = "validator")]
+    pub validator_username: Option<String>,
+
+    /// Number of times to retry a request to the validator which failed with
+    /// a transient status, such as UNAVAILABLE. Defaults to 3.
+    #[structopt(long, requires = "validator")]
+    pub validator_retry_attempts: Option<usize>,
+
+    /// How many seconds to wait before retrying a failed request to the
+    /// validator. Doubles for each subsequent attempt, up to 30 seconds.
+    /// Defaults to 1.
+    #[structopt(long, parse(try_from_str=parse_duration_in_seconds), requires = "validator")]
+    pub validator_retry_backoff: Option<Duration>,
+
+    /// Free space, in megabytes, below which to warn about the ledger or
+    /// wallet database volumes running out of space.
+    #[structopt(long, default_value = "1024", parse(try_from_str=parse_disk_space_mb))]
+    pub disk_space_warning_mb: u64,
+
+    /// Free space, in megabytes, below which to stop downloading blocks, to
+    /// avoid corrupting the ledger.
+    #[structopt(long, default_value = "100", parse(try_from_str=parse_disk_space_mb))]
+    pub disk_space_critical_mb: u64,
+
+    /// Start with spending disabled. Spending can be re-enabled with the
+    /// enable_spending API call and the admin key, which is read from the
+    /// MC_ADMIN_API_KEY environment variable.
+    #[structopt(long)]
+    pub disable_spending: bool,
+
+    /// JSON file listing token metadata, which overrides the built-in token
+    /// table and the minimum fees reported by the network. Each entry has a
+    /// token_id, and optionally a symbol, decimals and minimum_fee.
+    #[structopt(long, parse(try_from_str=load_token_metadata_file))]
+    pub token_metadata: Option<TokenMetadataOverrides>,
+
+    /// Log a warning for sends above this threshold, given either as a value
+    /// in the smallest unit of the token being sent, or as a percentage of the
+    /// account's balance, e.g. "50%".
+    #[structopt(long)]
+    pub large_send_warn_threshold: Option<LargeSendThreshold>,
+
+    /// Reject sends above this threshold unless they are made with
+    /// confirm_large_send. Given in the same form as
+    /// --large-send-warn-threshold.
+    #[structopt(long)]
+    pub large_send_deny_threshold: Option<LargeSendThreshold>,
+
+    /// Write API responses and exports as canonical JSON, with sorted keys and
+    /// normalized numbers, unless a request sets the X-Canonical-JSON header
+    /// to "false".
+    #[structopt(long)]
+    pub canonical_json: bool,
+
+    /// POST transaction outcomes and received txos to the URLs registered
+    /// with register_webhook.
+    #[structopt(long)]
+    pub enable_webhooks: bool,
+
+    /// Resubmit sent transactions which passed their tombstone block without
+    /// landing, once each, with a fresh tombstone block.
+    #[structopt(long)]
+    pub resubmit_expired_transactions: bool,
+
+    /// Serve the total this account has received, without an API key, at
+    /// /balance_badge. The badge is disabled unless an account is given.
+    #[structopt(long)]
+    pub balance_badge_account_id: Option<String>,
+
+    /// How many requests each client may make to /balance_badge per minute.
+    #[structopt(long, default_value = "10")]
+    pub balance_badge_requests_per_minute: u32,
+
+    /// Identify /balance_badge clients by the X-Real-IP header. Only set this
+    /// behind a reverse proxy which sets the header, as clients can set it
+    /// themselves.
+    #[structopt(long)]
+    pub balance_badge_trust_proxy: bool,
+
+    /// Serve Prometheus metrics, including what each account has received and
+    /// sent, without an API key at /metrics. Accounts are labelled with a hash
+    /// of their ID.
+    #[structopt(long)]
+    pub enable_metrics: bool,
+
+    /// Serve HTTPS with this PEM certificate chain, rather than HTTP. Send
+    /// full-service SIGHUP to reload it once it has been renewed.
+    #[structopt(long, parse(from_os_str), requires = "tls-key")]
+    pub tls_cert: Option<PathBuf>,
+
+    /// The PEM private key for --tls-cert.
+    #[structopt(long, parse(from_os_str), requires = "tls-cert")]
+    pub tls_key: Option<PathBuf>,
+
+    /// URL of the external signer which signs transactions for hardware
+    /// backed view only accounts, such as a bridge to a Ledger device.
+    #[structopt(long)]
+    pub transaction_signer_url: Option<String>,
+
+    /// URL which is sent each txo the sync credits to an account, and answers
+    /// with a label for it, such as "payroll".
+    #[structopt(long)]
+    pub txo_classifier_url: Option<String>,
+
+    /// Limit the API to a preset group of methods, whichever API key is used.
+    /// "receive-only" allows only what is needed to process deposits, for
+    /// wallets which should never send.
+    #[structopt(long, parse(try_from_str=parse_api_preset))]
+    pub preset: Option<ApiScope>,
+
+    /// Serve only the JSON-RPC methods listed in this file, one per line,
+    /// whichever API key is used. Every other method returns the
+    /// MethodDisabled error. Lines starting with # are comments.
+    #[structopt(long, parse(try_from_str=load_method_allowlist_file))]
+    pub method_allowlist: Option<MethodAllowlist>,
+
+    /// Record each API request with its response and timing to this file,
+    /// one JSON object per line, for replaying with replay-capture. Secrets
+    /// are redacted, but the file still shows the wallet's accounts and
+    /// activity, so only enable this while reproducing a problem.
+    #[structopt(long, parse(from_os_str))]
+    pub capture_requests: Option<PathBuf>,
+
+    /// Write the view only keys of every account, encrypted to
+    /// --continuity-export-public-key, to this directory if the operator
+    /// doesn't check in with continuity_check_in within the check-in period.
+    /// Spend keys are never exported.
+    #[structopt(long, parse(from_os_str), requires = "continuity-export-public-key")]
+    pub continuity_export_dir: Option<PathBuf>,
+
+    /// Hex encoded Ristretto public key of the beneficiary continuity exports
+    /// are encrypted to.
+    #[structopt(
+        long,
+        parse(try_from_str=parse_ristretto_public_key),
+        requires = "continuity-export-dir"
+    )]
+    pub continuity_export_public_key: Option<RistrettoPublic>,
+
+    /// How many days the operator may go without checking in before a
+    /// continuity export is written.
+    #[structopt(long, default_value = "30")]
+    pub continuity_check_in_days: u64,
+
+    /// How many accounts to sync at once. Wallets with many accounts catch up
+    /// faster with more, at the cost of more CPU while they do.
+    #[structopt(long, default_value = "1", parse(try_from_str=parse_sync_threads))]
+    pub sync_threads: usize,
+
+    /// How many blocks to sync an account by at a time. Each chunk's blocks are
+    /// held in memory while they are scanned, and the account's progress is
+    /// saved after each, so smaller chunks use less memory and lose less
+    /// progress if the wallet stops, at the cost of a slower sync.
+    #[structopt(long, default_value = "1000", parse(try_from_str=parse_sync_chunk_size))]
+    pub sync_chunk_size: u64,
+
+    /// How many blocks apart to snapshot each account's balances and txo
+    /// counts, for charting its history with get_account_snapshots.
+    #[structopt(long, default_value = "1000", parse(try_from_str=parse_account_snapshot_interval))]
+    pub account_snapshot_interval_blocks: u64,
+
+    /// Don't run scheduled jobs, such as sweeps, scheduled payments and
+    /// webhook deliveries, so that only one of several instances sharing a
+    /// Postgres wallet database runs them.
+    #[structopt(long)]
+    pub disable_scheduler: bool,
 }
 
+/// The contents of a token metadata file.
+#[derive(Clone, Debug)]
+pub struct TokenMetadataOverrides(pub Vec<TokenMetadataOverride>);
+
 fn parse_quorum_set_from_json(src: &str) -> Result<QuorumSet<ResponderId>, String> {
     let quorum_set: QuorumSet<ResponderId> = serde_json::from_str(src)
         .map_err(|err| format!("Error parsing quorum set {}: {:?}", src, err))?;
@@ -78,6 +295,65 @@ fn parse_quorum_set_from_json(src: &str) -> Result<QuorumSet<ResponderId>, Strin
     Ok(quorum_set)
 }
 
+fn parse_ledger_db_max_size_mb(src: &str) -> Result<u64, String> {
+    let max_size_mb: u64 = src
+        .parse()
+        .map_err(|err| format!("Invalid ledger DB max size {}: {}", src, err))?;
+    validate_ledger_db_max_size(megabytes_to_bytes(max_size_mb).unwrap_or(u64::MAX))
+        .map_err(|err| err.to_string())?;
+    Ok(max_size_mb)
+}
+
+fn parse_disk_space_mb(src: &str) -> Result<u64, String> {
+    let disk_space_mb: u64 = src
+        .parse()
+        .map_err(|err| format!("Invalid disk space {}: {}", src, err))?;
+    megabytes_to_bytes(disk_space_mb)
+        .ok_or_else(|| format!("Disk space of {} MB is too large", disk_space_mb))?;
+    Ok(disk_space_mb)
+}
+
+/// The number of bytes in the given number of megabytes, if it fits in a u64.
+fn megabytes_to_bytes(megabytes: u64) -> Option<u64> {
+    megabytes.checked_mul(1024 * 1024)
+}
+
+fn parse_sync_threads(src: &str) -> Result<usize, String> {
+    let sync_threads: usize = src
+        .parse()
+        .map_err(|err| format!("Invalid sync threads {}: {}", src, err))?;
+    if sync_threads == 0 {
+        return Err("At least one sync thread is needed".to_string());
+    }
+    Ok(sync_threads)
+}
+
+fn parse_sync_chunk_size(src: &str) -> Result<u64, String> {
+    let sync_chunk_size: u64 = src
+        .parse()
+        .map_err(|err| format!("Invalid sync chunk size {}: {}", src, err))?;
+    if sync_chunk_size == 0 {
+        return Err("Accounts must be synced by at least one block at a time".to_string());
+    }
+    Ok(sync_chunk_size)
+}
+
+fn parse_account_snapshot_interval(src: &str) -> Result<u64, String> {
+    let interval_blocks: u64 = src
+        .parse()
+        .map_err(|err| format!("Invalid account snapshot interval {}: {}", src, err))?;
+    if interval_blocks == 0 {
+        return Err("Accounts must be snapshotted at least one block apart".to_string());
+    }
+    Ok(interval_blocks)
+}
+
+fn parse_ristretto_public_key(src: &str) -> Result<RistrettoPublic, String> {
+    let bytes = hex::decode(src).map_err(|err| format!("Invalid public key {}: {}", src, err))?;
+    RistrettoPublic::try_from(&bytes[..])
+        .map_err(|err| format!("Invalid public key {}: {:?}", src, err))
+}
+
 fn load_css_file(filename: &str) -> Result<Signature, String> {
     let bytes =
         fs::read(filename).map_err(|err| format!("Failed reading file '{}': {}", filename, err))?;
@@ -86,6 +362,30 @@ fn load_css_file(filename: &str) -> Result<Signature, String> {
     Ok(signature)
 }
 
+fn load_token_metadata_file(filename: &str) -> Result<TokenMetadataOverrides, String> {
+    let bytes =
+        fs::read(filename).map_err(|err| format!("Failed reading file '{}': {}", filename, err))?;
+    let overrides = serde_json::from_slice(&bytes)
+        .map_err(|err| format!("Failed parsing token metadata file '{}': {}", filename, err))?;
+    Ok(TokenMetadataOverrides(overrides))
+}
+
+fn load_method_allowlist_file(filename: &str) -> Result<MethodAllowlist, String> {
+    let contents = fs::read_to_string(filename)
+        .map_err(|err| format!("Failed reading file '{}': {}", filename, err))?;
+    MethodAllowlist::parse(&contents)
+        .map_err(|err| format!("Failed parsing method allowlist '{}': {}", filename, err))
+}
+
+fn load_pem_file(filename: &str) -> Result<Vec<u8>, String> {
+    let bytes =
+        fs::read(filename).map_err(|err| format!("Failed reading file '{}': {}", filename, err))?;
+    if !String::from_utf8_lossy(&bytes).contains("-----BEGIN CERTIFICATE-----") {
+        return Err(format!("No PEM certificate found in '{}'", filename));
+    }
+    Ok(bytes)
+}
+
 impl APIConfig {
     /// Get the attestation verifier used to verify fog reports when sending to
     /// fog recipients.
@@ -107,6 +407,123 @@ impl APIConfig {
         })
     }
 
+    /// Get the options used when connecting to the validator service.
+    pub fn get_validator_connection_options(&self) -> ValidatorConnectionOptions {
+        let secret = env::var("MC_VALIDATOR_SECRET").unwrap_or_default();
+        ValidatorConnectionOptions {
+            tls_root_certs: self.validator_tls_ca_cert.clone(),
+            tls_hostname: self.validator_tls_hostname.clone(),
+            credentials: BasicCredentials::new(
+                self.validator_username.as_deref().unwrap_or_default(),
+                &secret,
+            ),
+            retry_attempts: self.validator_retry_attempts.unwrap_or(3),
+            retry_backoff: self
+                .validator_retry_backoff
+                .unwrap_or_else(|| Duration::from_secs(1)),
+        }
+    }
+
+    /// Get the free space thresholds for the database volumes.
+    pub fn get_disk_space_thresholds(&self) -> DiskSpaceThresholds {
+        DiskSpaceThresholds {
+            warning_bytes: megabytes_to_bytes(self.disk_space_warning_mb).unwrap_or(u64::MAX),
+            critical_bytes: megabytes_to_bytes(self.disk_space_critical_mb).unwrap_or(u64::MAX),
+        }
+    }
+
+    /// Get the switch which blocks transaction submission while spending is
+    /// disabled.
+    pub fn get_spending_lock(&self) -> SpendingLock {
+        let admin_key = env::var("MC_ADMIN_API_KEY").ok();
+        SpendingLock::new(self.disable_spending, admin_key)
+    }
+
+    /// Get the switch which limits the wallet to reads during maintenance,
+    /// which is toggled with the admin key read from the
+    /// MC_MAINTENANCE_ADMIN_KEY environment variable.
+    pub fn get_maintenance_mode(&self) -> MaintenanceMode {
+        MaintenanceMode::new(env::var("MC_MAINTENANCE_ADMIN_KEY").ok())
+    }
+
+    /// Get the API keys limited to a scope, which are read from the
+    /// MC_VIEW_ONLY_API_KEY and MC_SIGNER_API_KEY environment variables.
+    pub fn get_scoped_api_keys(&self) -> Vec<(String, ApiScope)> {
+        [
+            ("MC_VIEW_ONLY_API_KEY", ApiScope::ViewOnly),
+            ("MC_SIGNER_API_KEY", ApiScope::Signer),
+        ]
+        .iter()
+        .filter_map(|(var, scope)| {
+            env::var(var)
+                .ok()
+                .filter(|key| !key.is_empty())
+                .map(|key| (key, *scope))
+        })
+        .collect()
+    }
+
+    /// Get the state of the public balance badge.
+    pub fn get_balance_badge_state(&self) -> BalanceBadgeState {
+        BalanceBadgeState::new(
+            self.balance_badge_account_id.clone().map(AccountID),
+            self.balance_badge_requests_per_minute,
+            self.balance_badge_trust_proxy,
+        )
+    }
+
+    /// Get the locally configured token metadata.
+    pub fn get_token_metadata_overrides(&self) -> Vec<TokenMetadataOverride> {
+        self.token_metadata
+            .as_ref()
+            .map(|overrides| overrides.0.clone())
+            .unwrap_or_default()
+    }
+
+    /// Get the thresholds above which sends are logged, or must be confirmed.
+    pub fn get_large_send_guard(&self) -> LargeSendGuard {
+        LargeSendGuard::new(
+            self.large_send_warn_threshold,
+            self.large_send_deny_threshold,
+        )
+    }
+
+    /// Get the signer for hardware backed accounts, if one is configured.
+    pub fn get_transaction_signer(&self) -> Option<Arc<dyn TransactionSigner>> {
+        self.transaction_signer_url.as_ref().map(|url| {
+            let signer: Arc<dyn TransactionSigner> = Arc::new(
+                ExternalSigner::new(url).expect("Could not create the transaction signer client"),
+            );
+            signer
+        })
+    }
+
+    /// Get the classifier which labels received txos, if one is configured.
+    pub fn get_txo_classifier(&self) -> Option<Arc<dyn TxoClassifier>> {
+        self.txo_classifier_url.as_ref().map(|url| {
+            let classifier: Arc<dyn TxoClassifier> = Arc::new(
+                ExternalTxoClassifier::new(url)
+                    .expect("Could not create the txo classifier client"),
+            );
+            classifier
+        })
+    }
+
+    /// Get where and when to write continuity exports, if they are enabled.
+    pub fn get_continuity_export(&self) -> Option<ContinuityExportConfig> {
+        match (
+            &self.continuity_export_dir,
+            &self.continuity_export_public_key,
+        ) {
+            (Some(dir), Some(public_key)) => Some(ContinuityExportConfig {
+                dir: dir.clone(),
+                public_key: *public_key,
+                check_in_period: Duration::from_secs(self.continuity_check_in_days * 24 * 60 * 60),
+            }),
+            _ => None,
+        }
+    }
+
     /// Get the function which creates FogResolver given a list of recipient
     /// addresses.
     ///
@@ -167,6 +584,17 @@ pub struct PeersConfig {
     /// For example: https://s3-us-west-1.amazonaws.com/mobilecoin.chain/node1.test.mobilecoin.com/
     #[structopt(long = "tx-source-url", required_unless_one = &["offline", "validator"], conflicts_with_all = &["offline", "validator"])]
     pub tx_source_urls: Option<Vec<String>>,
+
+    /// Number of attested connections to keep warm to each peer, which
+    /// transactions are submitted over so that submissions don't wait for
+    /// attestation. 0 submits over the peer manager's connections instead.
+    #[structopt(long, default_value = "2")]
+    pub peer_pool_size: usize,
+
+    /// How often pooled peer connections are re-attested, in seconds, so that
+    /// their sessions are renewed before the peer expires them.
+    #[structopt(long, default_value = "600", parse(try_from_str=parse_duration_in_seconds))]
+    pub peer_reattest_interval: Duration,
 }
 
 impl PeersConfig {
@@ -241,6 +669,29 @@ impl PeersConfig {
 
         ConnectionManager::new(peers, logger.clone())
     }
+
+    /// Create the pool of connections transactions are submitted over, or None
+    /// if pooling is disabled.
+    pub fn create_connection_pool(
+        &self,
+        verifier: Verifier,
+        logger: &Logger,
+    ) -> Option<ConsensusConnectionPool<ThickClient<HardcodedCredentialsProvider>>> {
+        if self.peer_pool_size == 0 {
+            return None;
+        }
+        let grpc_env = Arc::new(
+            grpcio::EnvBuilder::new()
+                .cq_count(1)
+                .name_prefix("peer-pool")
+                .build(),
+        );
+        let connections = (0..self.peer_pool_size)
+            .flat_map(|_| self.create_peers(verifier.clone(), grpc_env.clone(), logger.clone()))
+            .collect();
+
+        Some(ConsensusConnectionPool::new(connections, logger.clone()))
+    }
 }
 
 #[derive(Clone, Debug, StructOpt)]
@@ -254,9 +705,23 @@ pub struct LedgerDbConfig {
     /// initializing new ledger dbs.
     #[structopt(long)]
     pub ledger_db_bootstrap: Option<String>,
+
+    /// Maximum size in MB the ledger db may grow to. Ledger sync pauses as the
+    /// ledger nears it, rather than failing writes once LMDB's map is full.
+    /// Defaults to the map size LedgerDB opens LMDB with, which is also the
+    /// largest size allowed.
+    #[structopt(long, parse(try_from_str=parse_ledger_db_max_size_mb))]
+    pub ledger_db_max_size_mb: Option<u64>,
 }
 
 impl LedgerDbConfig {
+    /// Get the size in bytes the ledger db may grow to.
+    pub fn get_max_size_bytes(&self) -> u64 {
+        self.ledger_db_max_size_mb
+            .and_then(megabytes_to_bytes)
+            .unwrap_or(LEDGER_DB_MAP_SIZE_BYTES)
+    }
+
     pub fn create_or_open_ledger_db(
         &self,
         get_origin_block_and_transactions: impl Fn() -> Result<BlockData, String>,
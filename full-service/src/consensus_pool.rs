@@ -0,0 +1,475 @@
+// Copyright (c) 2020-2022 MobileCoin Inc.
+
+//! A pool of warm, attested connections to each consensus validator.
+//!
+//! Submitting a transaction needs an attested connection to the validator,
+//! and attesting takes several round trips, which a submission otherwise pays
+//! for whenever its connection has not attested yet, or the validator has
+//! dropped the session. The pool keeps several connections to each peer, and
+//! a keeper thread attests them ahead of time, and re-attests them
+//! periodically so that sessions are renewed before the validator expires
+//! them. Submissions go to a warm connection when one is available.
+//!
+//! Each attestation is also checked against the last one from the same peer.
+//! The verifier accepts any enclave signed by the expected signer, so a peer
+//! moving to a different enclave measurement would otherwise go unnoticed.
+//! The keeper queues a change whenever a peer's measurement changes, or when
+//! a peer which was attesting fails to, for the wallet to report.
+
+use crate::metrics;
+use mc_attest_core::{VerificationReport, VerificationReportData};
+use mc_common::{
+    logger::{log, Logger},
+    ResponderId,
+};
+use mc_connection::{AttestedConnection, Connection, SyncConnection};
+use mc_util_uri::ConnectionUri;
+use std::{
+    convert::TryFrom,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+/// What the keeper last learned from a peer's attestations.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PeerAttestation {
+    /// The hex-encoded MRENCLAVE the peer last attested with.
+    pub measurement: Option<String>,
+
+    /// The error from the peer's latest attestation, if it failed.
+    pub error: Option<String>,
+}
+
+/// A change in a peer's attestation, for the wallet to report.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PeerAttestationChange {
+    /// The peer attested with a different enclave measurement than before.
+    MeasurementChanged { previous: String, current: String },
+
+    /// The peer failed attestation, having passed it before, or never having
+    /// attested.
+    VerificationFailed { error: String },
+}
+
+impl PeerAttestation {
+    /// Record the outcome of an attestation, which is the peer's measurement
+    /// or the error it failed with, returning the change to report, if any.
+    ///
+    /// Repeated failures are only reported once, until the peer attests
+    /// again.
+    pub fn record(&mut self, outcome: Result<String, String>) -> Option<PeerAttestationChange> {
+        match outcome {
+            Ok(measurement) => {
+                self.error = None;
+                let change = match self.measurement.take() {
+                    Some(previous) if previous != measurement => {
+                        Some(PeerAttestationChange::MeasurementChanged {
+                            previous,
+                            current: measurement.clone(),
+                        })
+                    }
+                    _ => None,
+                };
+                self.measurement = Some(measurement);
+                change
+            }
+            Err(error) => {
+                let change = match self.error {
+                    Some(_) => None,
+                    None => Some(PeerAttestationChange::VerificationFailed {
+                        error: error.clone(),
+                    }),
+                };
+                self.error = Some(error);
+                change
+            }
+        }
+    }
+}
+
+/// The hex-encoded MRENCLAVE of the enclave a verification report attests.
+pub fn report_measurement(report: &VerificationReport) -> Result<String, String> {
+    let report_data = VerificationReportData::try_from(report).map_err(|err| err.to_string())?;
+    let report_body = report_data
+        .quote
+        .report_body()
+        .map_err(|err| err.to_string())?;
+    Ok(hex::encode(report_body.mr_enclave()))
+}
+
+/// A pooled connection, and whether the keeper has it attested.
+struct PooledConnection<T: Connection> {
+    conn: SyncConnection<T>,
+    warm: AtomicBool,
+    attested_at: Mutex<Option<Instant>>,
+}
+
+/// The pooled connections to one peer.
+struct PooledPeer<T: Connection> {
+    responder_id: ResponderId,
+    connections: Vec<PooledConnection<T>>,
+    next: AtomicUsize,
+    attestation: Mutex<PeerAttestation>,
+    changes: Mutex<Vec<PeerAttestationChange>>,
+}
+
+impl<T: Connection> PooledPeer<T> {
+    /// Record the outcome of an attestation to the peer, queueing any change.
+    fn record_attestation(&self, outcome: Result<String, String>, logger: &Logger) {
+        let peer_label = self.responder_id.to_string();
+        metrics::set_attestation_verified(&peer_label, outcome.is_ok());
+        let change = self
+            .attestation
+            .lock()
+            .expect("lock poisoned")
+            .record(outcome);
+        if let Some(change) = change {
+            if let PeerAttestationChange::MeasurementChanged { previous, current } = &change {
+                metrics::record_measurement_change(&peer_label);
+                log::warn!(
+                    logger,
+                    "{} changed enclave measurement from {} to {}",
+                    peer_label,
+                    previous,
+                    current
+                );
+            }
+            self.changes.lock().expect("lock poisoned").push(change);
+        }
+    }
+}
+
+/// Connections to each consensus peer, shared between the wallet and the
+/// keeper thread.
+pub struct ConsensusConnectionPool<T: Connection> {
+    peers: Arc<Vec<PooledPeer<T>>>,
+}
+
+impl<T: Connection> Clone for ConsensusConnectionPool<T> {
+    fn clone(&self) -> Self {
+        Self {
+            peers: self.peers.clone(),
+        }
+    }
+}
+
+impl<T: Connection> ConsensusConnectionPool<T> {
+    /// Pool the given connections by the peer they connect to.
+    pub fn new(connections: Vec<T>, logger: Logger) -> Self {
+        let mut peers: Vec<PooledPeer<T>> = Vec::new();
+        for conn in connections {
+            let responder_id = conn
+                .uri()
+                .responder_id()
+                .expect("Could not get responder_id from uri");
+            let pooled = PooledConnection {
+                conn: SyncConnection::new(conn, logger.clone()),
+                warm: AtomicBool::new(false),
+                attested_at: Mutex::new(None),
+            };
+            match peers
+                .iter_mut()
+                .find(|peer| peer.responder_id == responder_id)
+            {
+                Some(peer) => peer.connections.push(pooled),
+                None => peers.push(PooledPeer {
+                    responder_id,
+                    connections: vec![pooled],
+                    next: AtomicUsize::new(0),
+                    attestation: Mutex::new(PeerAttestation::default()),
+                    changes: Mutex::new(Vec::new()),
+                }),
+            }
+        }
+        Self {
+            peers: Arc::new(peers),
+        }
+    }
+
+    /// Get a connection to the peer, taking turns between its warm
+    /// connections, or between all of them if none are warm.
+    pub fn conn(&self, responder_id: &ResponderId) -> Option<SyncConnection<T>> {
+        self.pick(responder_id).map(|pooled| pooled.conn.clone())
+    }
+
+    fn pick(&self, responder_id: &ResponderId) -> Option<&PooledConnection<T>> {
+        let peer = self
+            .peers
+            .iter()
+            .find(|peer| &peer.responder_id == responder_id)?;
+        let warm: Vec<&PooledConnection<T>> = peer
+            .connections
+            .iter()
+            .filter(|pooled| pooled.warm.load(Ordering::SeqCst))
+            .collect();
+        let candidates = if warm.is_empty() {
+            peer.connections.iter().collect()
+        } else {
+            warm
+        };
+        let index = peer.next.fetch_add(1, Ordering::SeqCst) % candidates.len();
+        Some(candidates[index])
+    }
+
+    /// The number of warm connections to each peer.
+    pub fn warm_connections(&self) -> Vec<(ResponderId, usize)> {
+        self.peers
+            .iter()
+            .map(|peer| {
+                let warm = peer
+                    .connections
+                    .iter()
+                    .filter(|pooled| pooled.warm.load(Ordering::SeqCst))
+                    .count();
+                (peer.responder_id.clone(), warm)
+            })
+            .collect()
+    }
+
+    /// What the keeper last learned from each peer's attestations.
+    pub fn peer_attestations(&self) -> Vec<(ResponderId, PeerAttestation)> {
+        self.peers
+            .iter()
+            .map(|peer| {
+                let attestation = peer.attestation.lock().expect("lock poisoned").clone();
+                (peer.responder_id.clone(), attestation)
+            })
+            .collect()
+    }
+
+    /// Take the attestation changes the keeper has queued since they were
+    /// last taken, in the order they happened for each peer.
+    pub fn take_attestation_changes(&self) -> Vec<(ResponderId, PeerAttestationChange)> {
+        self.peers
+            .iter()
+            .flat_map(|peer| {
+                let changes = std::mem::take(&mut *peer.changes.lock().expect("lock poisoned"));
+                changes
+                    .into_iter()
+                    .map(move |change| (peer.responder_id.clone(), change))
+            })
+            .collect()
+    }
+}
+
+/// Background thread which keeps the pooled connections attested.
+pub struct ConsensusPoolKeeper {
+    join_handle: Option<thread::JoinHandle<()>>,
+    stop_requested: Arc<AtomicBool>,
+}
+
+impl ConsensusPoolKeeper {
+    /// Check the pool's connections every poll interval, attesting those
+    /// which are not attested, and re-attesting those which were attested
+    /// longer than the re-attest interval ago.
+    pub fn start<T: AttestedConnection + 'static>(
+        pool: ConsensusConnectionPool<T>,
+        poll_interval: Duration,
+        reattest_interval: Duration,
+        logger: Logger,
+    ) -> Self {
+        let stop_requested = Arc::new(AtomicBool::new(false));
+        let thread_stop_requested = stop_requested.clone();
+        let join_handle = Some(
+            thread::Builder::new()
+                .name("ConsensusPoolKeeper".into())
+                .spawn(move || {
+                    log::info!(logger, "ConsensusPoolKeeper thread started");
+                    loop {
+                        if thread_stop_requested.load(Ordering::SeqCst) {
+                            log::debug!(logger, "ConsensusPoolKeeper stop requested.");
+                            break;
+                        }
+                        Self::check(&pool, reattest_interval, &logger);
+                        thread::sleep(poll_interval);
+                    }
+                })
+                .expect("Failed spawning ConsensusPoolKeeper thread"),
+        );
+
+        Self {
+            join_handle,
+            stop_requested,
+        }
+    }
+
+    pub fn stop(&mut self) {
+        self.stop_requested.store(true, Ordering::SeqCst);
+        if let Some(thread) = self.join_handle.take() {
+            thread.join().expect("thread join failed");
+        }
+    }
+
+    fn check<T: AttestedConnection>(
+        pool: &ConsensusConnectionPool<T>,
+        reattest_interval: Duration,
+        logger: &Logger,
+    ) {
+        for peer in pool.peers.iter() {
+            let peer_label = peer.responder_id.to_string();
+            for pooled in &peer.connections {
+                let mut attested_at = pooled.attested_at.lock().expect("lock poisoned");
+                let is_attested = pooled.conn.read().is_attested();
+
+                // A connection may have attested, or dropped its session,
+                // while submitting a transaction.
+                if !is_attested {
+                    *attested_at = None;
+                } else if attested_at.is_none() {
+                    *attested_at = Some(Instant::now());
+                }
+                let due = attested_at.map_or(true, |at| at.elapsed() >= reattest_interval);
+                if !due {
+                    pooled.warm.store(true, Ordering::SeqCst);
+                    continue;
+                }
+
+                // Submissions avoid the connection while it attests.
+                pooled.warm.store(false, Ordering::SeqCst);
+                let mut conn = pooled.conn.write();
+                if is_attested {
+                    conn.deattest();
+                }
+                let start = Instant::now();
+                match conn.attest() {
+                    Ok(report) => {
+                        metrics::record_attestation(&peer_label, start.elapsed(), true);
+                        *attested_at = Some(Instant::now());
+                        pooled.warm.store(true, Ordering::SeqCst);
+                        peer.record_attestation(report_measurement(&report), logger);
+                    }
+                    Err(err) => {
+                        metrics::record_attestation(&peer_label, start.elapsed(), false);
+                        *attested_at = None;
+                        log::warn!(logger, "Failed attesting to {}: {:?}", peer_label, err);
+                        peer.record_attestation(Err(format!("{:?}", err)), logger);
+                    }
+                }
+            }
+        }
+
+        for (responder_id, warm) in pool.warm_connections() {
+            metrics::set_warm_connections(&responder_id.to_string(), warm);
+        }
+    }
+}
+
+impl Drop for ConsensusPoolKeeper {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::get_test_ledger;
+    use mc_common::logger::{test_with_logger, Logger};
+    use mc_connection_test_utils::{test_client_uri, MockBlockchainConnection};
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test_with_logger]
+    fn test_pool_prefers_warm_connections(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+        let ledger_db = get_test_ledger(5, &[], 1, &mut rng);
+        let connections = (0..3)
+            .map(|_| MockBlockchainConnection::new(test_client_uri(1), ledger_db.clone(), 0))
+            .chain(std::iter::once(MockBlockchainConnection::new(
+                test_client_uri(2),
+                ledger_db.clone(),
+                0,
+            )))
+            .collect();
+        let pool = ConsensusConnectionPool::new(connections, logger);
+
+        let peer1 = test_client_uri(1).responder_id().unwrap();
+        let peer2 = test_client_uri(2).responder_id().unwrap();
+        assert_eq!(
+            pool.warm_connections(),
+            vec![(peer1.clone(), 0), (peer2.clone(), 0)]
+        );
+        assert!(pool.conn(&peer2).is_some());
+        assert!(pool
+            .conn(&test_client_uri(3).responder_id().unwrap())
+            .is_none());
+
+        // Once a connection is warm, submissions only use it.
+        pool.peers[0].connections[1]
+            .warm
+            .store(true, Ordering::SeqCst);
+        assert_eq!(pool.warm_connections()[0], (peer1.clone(), 1));
+        for _ in 0..3 {
+            assert!(std::ptr::eq(
+                pool.pick(&peer1).unwrap(),
+                &pool.peers[0].connections[1]
+            ));
+        }
+
+        // Changes are queued for the wallet to report, and only taken once.
+        pool.peers[0].record_attestation(Ok("aa".to_string()), &logger);
+        pool.peers[0].record_attestation(Ok("bb".to_string()), &logger);
+        pool.peers[1].record_attestation(Err("timeout".to_string()), &logger);
+        assert_eq!(
+            pool.take_attestation_changes(),
+            vec![
+                (
+                    peer1.clone(),
+                    PeerAttestationChange::MeasurementChanged {
+                        previous: "aa".to_string(),
+                        current: "bb".to_string(),
+                    }
+                ),
+                (
+                    peer2.clone(),
+                    PeerAttestationChange::VerificationFailed {
+                        error: "timeout".to_string(),
+                    }
+                ),
+            ]
+        );
+        assert!(pool.take_attestation_changes().is_empty());
+        assert_eq!(
+            pool.peer_attestations()[0].1.measurement,
+            Some("bb".to_string())
+        );
+    }
+
+    #[test]
+    fn test_peer_attestation_changes() {
+        let mut attestation = PeerAttestation::default();
+
+        // The first measurement is not a change.
+        assert_eq!(attestation.record(Ok("aa".to_string())), None);
+        assert_eq!(attestation.record(Ok("aa".to_string())), None);
+
+        // Failures are reported once, until the peer attests again.
+        assert_eq!(
+            attestation.record(Err("bad quote".to_string())),
+            Some(PeerAttestationChange::VerificationFailed {
+                error: "bad quote".to_string()
+            })
+        );
+        assert_eq!(attestation.record(Err("bad quote".to_string())), None);
+        assert_eq!(attestation.error, Some("bad quote".to_string()));
+
+        // The measurement is compared to the last one seen, across failures.
+        assert_eq!(
+            attestation.record(Ok("bb".to_string())),
+            Some(PeerAttestationChange::MeasurementChanged {
+                previous: "aa".to_string(),
+                current: "bb".to_string(),
+            })
+        );
+        assert_eq!(
+            attestation,
+            PeerAttestation {
+                measurement: Some("bb".to_string()),
+                error: None,
+            }
+        );
+    }
+}
@@ -4,8 +4,18 @@
 
 use crate::{
     db::{
+        account_snapshot::AccountSnapshotModel,
+        account_sync_record::AccountSyncRecordModel,
         assigned_subaddress::AssignedSubaddressModel,
-        models::{Account, AssignedSubaddress, NewAccount, TransactionLog, Txo, ViewOnlyAccount},
+        isolated_counterparty::IsolatedCounterpartyModel,
+        models::{
+            Account, AccountSnapshot, AccountSyncRecord, AssignedSubaddress, IsolatedCounterparty,
+            NewAccount, PaymentRequest, ScheduledPayment, SweepPolicy, TransactionLog, Txo,
+            ViewOnlyAccount,
+        },
+        payment_request::PaymentRequestModel,
+        scheduled_payment::ScheduledPaymentModel,
+        sweep_policy::SweepPolicyModel,
         transaction_log::TransactionLogModel,
         txo::TxoModel,
         view_only_account::ViewOnlyAccountModel,
@@ -162,6 +172,30 @@ pub trait AccountModel {
         conn: &Conn,
     ) -> Result<(), WalletDbError>;
 
+    /// Update the block index this account starts scanning from.
+    fn update_first_block_index(
+        &self,
+        first_block_index: u64,
+        conn: &Conn,
+    ) -> Result<(), WalletDbError>;
+
+    /// Update the token this account uses when none is specified.
+    fn update_preferred_token_id(
+        &self,
+        preferred_token_id: u64,
+        conn: &Conn,
+    ) -> Result<(), WalletDbError>;
+
+    /// Mark the account as archived.
+    fn archive(&self, conn: &Conn) -> Result<(), WalletDbError>;
+
+    /// Pause or resume syncing the account.
+    fn update_sync_paused(&self, sync_paused: bool, conn: &Conn) -> Result<(), WalletDbError>;
+
+    /// Whether the account is still scanning the blocks which were in the
+    /// ledger when it was imported.
+    fn is_importing(&self) -> bool;
+
     /// Delete an account.
     fn delete(self, conn: &Conn) -> Result<(), WalletDbError>;
 }
@@ -436,6 +470,58 @@ impl AccountModel for Account {
         Ok(())
     }
 
+    fn update_first_block_index(
+        &self,
+        first_block_index: u64,
+        conn: &Conn,
+    ) -> Result<(), WalletDbError> {
+        use crate::db::schema::accounts::dsl::{account_id_hex, accounts};
+        diesel::update(accounts.filter(account_id_hex.eq(&self.account_id_hex)))
+            .set(crate::db::schema::accounts::first_block_index.eq(first_block_index as i64))
+            .execute(conn)?;
+        Ok(())
+    }
+
+    fn update_preferred_token_id(
+        &self,
+        preferred_token_id: u64,
+        conn: &Conn,
+    ) -> Result<(), WalletDbError> {
+        use crate::db::schema::accounts::dsl::{account_id_hex, accounts};
+        diesel::update(accounts.filter(account_id_hex.eq(&self.account_id_hex)))
+            .set(crate::db::schema::accounts::preferred_token_id.eq(preferred_token_id as i64))
+            .execute(conn)?;
+        Ok(())
+    }
+
+    fn archive(&self, conn: &Conn) -> Result<(), WalletDbError> {
+        use crate::db::schema::accounts::dsl::{account_id_hex, accounts};
+        diesel::update(accounts.filter(account_id_hex.eq(&self.account_id_hex)))
+            .set(crate::db::schema::accounts::archived.eq(true))
+            .execute(conn)?;
+        Ok(())
+    }
+
+    fn update_sync_paused(&self, sync_paused: bool, conn: &Conn) -> Result<(), WalletDbError> {
+        use crate::db::schema::accounts::dsl::{account_id_hex, accounts};
+        diesel::update(accounts.filter(account_id_hex.eq(&self.account_id_hex)))
+            .set(crate::db::schema::accounts::sync_paused.eq(sync_paused))
+            .execute(conn)?;
+        Ok(())
+    }
+
+    fn is_importing(&self) -> bool {
+        // Accounts created by this wallet start scanning at the import block,
+        // so have nothing to catch up on.
+        match self.import_block_index {
+            Some(import_block_index) => {
+                self.first_block_index < import_block_index
+                    && self.next_block_index <= import_block_index
+            }
+            None => false,
+        }
+    }
+
     fn delete(self, conn: &Conn) -> Result<(), WalletDbError> {
         use crate::db::schema::accounts::dsl::{account_id_hex, accounts};
 
@@ -445,6 +531,24 @@ impl AccountModel for Account {
         // Delete associated assigned subaddresses
         AssignedSubaddress::delete_all(&self.account_id_hex, conn)?;
 
+        // Stop sweeping the account.
+        SweepPolicy::delete_for_account(&self.account_id_hex, conn)?;
+
+        // Stop paying from the account.
+        ScheduledPayment::delete_for_account(&self.account_id_hex, conn)?;
+
+        // Stop matching payments to the account's requests.
+        PaymentRequest::delete_for_account(&self.account_id_hex, conn)?;
+
+        // Delete the account's sync history.
+        AccountSyncRecord::delete_for_account(&self.account_id_hex, conn)?;
+
+        // Delete the account's snapshots.
+        AccountSnapshot::delete_for_account(&self.account_id_hex, conn)?;
+
+        // Forget which counterparties the account isolates.
+        IsolatedCounterparty::delete_for_account(&self.account_id_hex, conn)?;
+
         // Delete references to the account in the Txos table.
         Txo::scrub_account(&self.account_id_hex, conn)?;
 
@@ -514,6 +618,9 @@ mod tests {
             import_block_index: None,
             name: "Alice's Main Account".to_string(),
             fog_enabled: false,
+            preferred_token_id: 0,
+            archived: false,
+            sync_paused: false,
         };
         assert_eq!(expected_account, acc);
 
@@ -579,6 +686,9 @@ mod tests {
             import_block_index: Some(50),
             name: "".to_string(),
             fog_enabled: false,
+            preferred_token_id: 0,
+            archived: false,
+            sync_paused: false,
         };
         assert_eq!(expected_account_secondary, acc_secondary);
 
@@ -702,6 +812,9 @@ mod tests {
             import_block_index: None,
             name: "Alice's FOG Account".to_string(),
             fog_enabled: true,
+            preferred_token_id: 0,
+            archived: false,
+            sync_paused: false,
         };
         assert_eq!(expected_account, acc);
     }
@@ -0,0 +1,151 @@
+// Copyright (c) 2020-2022 MobileCoin Inc.
+
+//! The Account Rotation Model.
+//!
+//! An account rotation tracks moving all of an account's funds to a new
+//! account, one sweep transaction at a time, before the old account is
+//! archived.
+
+use crate::db::{
+    models::{AccountRotation, NewAccountRotation, ROTATION_STATUS_SWEEPING},
+    Conn, WalletDbError,
+};
+use diesel::prelude::*;
+
+pub trait AccountRotationModel {
+    /// Start rotating an account's funds to a new account.
+    fn create(
+        old_account_id_hex: &str,
+        new_account_id_hex: &str,
+        conn: &Conn,
+    ) -> Result<AccountRotation, WalletDbError>;
+
+    /// Get the rotation of an account by the old account's id.
+    fn get(old_account_id_hex: &str, conn: &Conn) -> Result<AccountRotation, WalletDbError>;
+
+    /// The ids of the sweep transactions submitted so far, oldest first.
+    fn sweep_transaction_log_ids(&self) -> Vec<String>;
+
+    /// Record a submitted sweep transaction.
+    fn add_sweep(&self, transaction_log_id: &str, conn: &Conn) -> Result<(), WalletDbError>;
+
+    /// Update the status of the rotation.
+    fn update_status(
+        &self,
+        status: &str,
+        failure_message: Option<&str>,
+        conn: &Conn,
+    ) -> Result<(), WalletDbError>;
+}
+
+impl AccountRotationModel for AccountRotation {
+    fn create(
+        old_account_id_hex: &str,
+        new_account_id_hex: &str,
+        conn: &Conn,
+    ) -> Result<AccountRotation, WalletDbError> {
+        use crate::db::schema::account_rotations;
+
+        let new_rotation = NewAccountRotation {
+            old_account_id_hex,
+            new_account_id_hex,
+            status: ROTATION_STATUS_SWEEPING,
+        };
+        diesel::insert_into(account_rotations::table)
+            .values(&new_rotation)
+            .execute(conn)?;
+
+        AccountRotation::get(old_account_id_hex, conn)
+    }
+
+    fn get(old_account_id_hex: &str, conn: &Conn) -> Result<AccountRotation, WalletDbError> {
+        use crate::db::schema::account_rotations;
+
+        match account_rotations::table
+            .filter(account_rotations::old_account_id_hex.eq(old_account_id_hex))
+            .get_result::<AccountRotation>(conn)
+        {
+            Ok(rotation) => Ok(rotation),
+            // Match on NotFound to get a more informative NotFound Error
+            Err(diesel::result::Error::NotFound) => Err(WalletDbError::AccountRotationNotFound(
+                old_account_id_hex.to_string(),
+            )),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn sweep_transaction_log_ids(&self) -> Vec<String> {
+        serde_json::from_str(&self.sweep_transaction_log_ids).unwrap_or_default()
+    }
+
+    fn add_sweep(&self, transaction_log_id: &str, conn: &Conn) -> Result<(), WalletDbError> {
+        use crate::db::schema::account_rotations;
+
+        let mut sweep_transaction_log_ids = self.sweep_transaction_log_ids();
+        sweep_transaction_log_ids.push(transaction_log_id.to_string());
+        diesel::update(account_rotations::table.filter(account_rotations::id.eq(self.id)))
+            .set(
+                account_rotations::sweep_transaction_log_ids
+                    .eq(serde_json::to_string(&sweep_transaction_log_ids).unwrap_or_default()),
+            )
+            .execute(conn)?;
+        Ok(())
+    }
+
+    fn update_status(
+        &self,
+        status: &str,
+        failure_message: Option<&str>,
+        conn: &Conn,
+    ) -> Result<(), WalletDbError> {
+        use crate::db::schema::account_rotations;
+
+        diesel::update(account_rotations::table.filter(account_rotations::id.eq(self.id)))
+            .set((
+                account_rotations::status.eq(status),
+                account_rotations::failure_message.eq(failure_message),
+            ))
+            .execute(conn)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{db::models::ROTATION_STATUS_FAILED, test_utils::WalletDbTestContext};
+    use mc_common::logger::{test_with_logger, Logger};
+
+    #[test_with_logger]
+    fn test_account_rotation_crud(logger: Logger) {
+        let db_test_context = WalletDbTestContext::default();
+        let wallet_db = db_test_context.get_db_instance(logger);
+        let conn = wallet_db.get_conn().unwrap();
+
+        let rotation = AccountRotation::create("old", "new", &conn).unwrap();
+        assert_eq!(rotation.new_account_id_hex, "new");
+        assert_eq!(rotation.status, ROTATION_STATUS_SWEEPING);
+        assert!(rotation.sweep_transaction_log_ids().is_empty());
+
+        // An account can only be rotated once.
+        assert!(AccountRotation::create("old", "other", &conn).is_err());
+
+        rotation.add_sweep("aaaa", &conn).unwrap();
+        let rotation = AccountRotation::get("old", &conn).unwrap();
+        rotation.add_sweep("bbbb", &conn).unwrap();
+        let rotation = AccountRotation::get("old", &conn).unwrap();
+        assert_eq!(rotation.sweep_transaction_log_ids(), vec!["aaaa", "bbbb"]);
+
+        rotation
+            .update_status(ROTATION_STATUS_FAILED, Some("no peers"), &conn)
+            .unwrap();
+        let rotation = AccountRotation::get("old", &conn).unwrap();
+        assert_eq!(rotation.status, ROTATION_STATUS_FAILED);
+        assert_eq!(rotation.failure_message, Some("no peers".to_string()));
+
+        match AccountRotation::get("missing", &conn) {
+            Err(WalletDbError::AccountRotationNotFound(_)) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+}
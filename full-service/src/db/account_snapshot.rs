@@ -0,0 +1,296 @@
+// Copyright (c) 2020-2022 MobileCoin Inc.
+
+//! The Account Snapshot Model.
+//!
+//! An account's balance of each token, and how many of its txos were unspent
+//! and spent, is recorded as of every so many blocks, so that a chart of the
+//! account's history can be drawn without going over its txos for each point.
+//! A snapshot is taken from the account's txos once the account has synced
+//! past its block, and is removed if the account rescans that block.
+
+use crate::db::{
+    models::{AccountSnapshot, NewAccountSnapshot},
+    Conn, WalletDbError,
+};
+use diesel::prelude::*;
+use mc_transaction_core::{tokens::Mob, Token};
+use std::collections::BTreeMap;
+
+/// The totals of a token making up a snapshot.
+#[derive(Default)]
+struct SnapshotTotals {
+    unspent_value: u128,
+    unspent_txo_count: i64,
+    spent_txo_count: i64,
+}
+
+pub trait AccountSnapshotModel {
+    /// Take the account's snapshots as of each of the blocks, which the
+    /// account must have synced past, from the txos it has received.
+    ///
+    /// A token the account had not received any of by a block has no snapshot
+    /// for it, except for MOB, so that every block has at least one.
+    fn create_for_blocks(
+        account_id_hex: &str,
+        block_indices: &[u64],
+        conn: &Conn,
+    ) -> Result<(), WalletDbError>;
+
+    /// The block of the account's latest snapshot, if it has any.
+    fn latest_block_index(account_id_hex: &str, conn: &Conn) -> Result<Option<u64>, WalletDbError>;
+
+    /// List the account's snapshots from one block up to and including
+    /// another, oldest first, optionally only those of a token.
+    fn list_for_account(
+        account_id_hex: &str,
+        token_id: Option<u64>,
+        from_block_index: Option<u64>,
+        to_block_index: Option<u64>,
+        conn: &Conn,
+    ) -> Result<Vec<AccountSnapshot>, WalletDbError>;
+
+    /// Delete the account's snapshots at or after the block index.
+    fn delete_from(
+        account_id_hex: &str,
+        block_index: u64,
+        conn: &Conn,
+    ) -> Result<(), WalletDbError>;
+
+    /// Delete all snapshots of an account.
+    fn delete_for_account(account_id_hex: &str, conn: &Conn) -> Result<(), WalletDbError>;
+}
+
+impl AccountSnapshotModel for AccountSnapshot {
+    fn create_for_blocks(
+        account_id_hex: &str,
+        block_indices: &[u64],
+        conn: &Conn,
+    ) -> Result<(), WalletDbError> {
+        use crate::db::schema::{account_snapshots, txos};
+
+        if block_indices.is_empty() {
+            return Ok(());
+        }
+
+        // Orphaned txos are left out, as they are from the unspent balance.
+        let received: Vec<(i64, i64, Option<i64>, Option<i64>)> = txos::table
+            .filter(txos::received_account_id_hex.eq(account_id_hex))
+            .filter(txos::subaddress_index.is_not_null())
+            .filter(txos::received_block_index.is_not_null())
+            .select((
+                txos::value,
+                txos::token_id,
+                txos::received_block_index,
+                txos::spent_block_index,
+            ))
+            .load(conn)?;
+
+        for block_index in block_indices {
+            let block_index = *block_index as i64;
+            let mut totals: BTreeMap<i64, SnapshotTotals> = BTreeMap::new();
+            totals.insert(*Mob::ID as i64, SnapshotTotals::default());
+            for (value, token_id, received_block_index, spent_block_index) in &received {
+                if received_block_index.map_or(true, |received| received > block_index) {
+                    continue;
+                }
+                let token_totals = totals.entry(*token_id).or_default();
+                match spent_block_index {
+                    Some(spent) if *spent <= block_index => token_totals.spent_txo_count += 1,
+                    _ => {
+                        token_totals.unspent_value += *value as u64 as u128;
+                        token_totals.unspent_txo_count += 1;
+                    }
+                }
+            }
+
+            for (token_id, token_totals) in totals {
+                diesel::delete(
+                    account_snapshots::table
+                        .filter(account_snapshots::account_id_hex.eq(account_id_hex))
+                        .filter(account_snapshots::block_index.eq(block_index))
+                        .filter(account_snapshots::token_id.eq(token_id)),
+                )
+                .execute(conn)?;
+                diesel::insert_into(account_snapshots::table)
+                    .values(&NewAccountSnapshot {
+                        account_id_hex,
+                        block_index,
+                        token_id,
+                        unspent_value: &token_totals.unspent_value.to_string(),
+                        unspent_txo_count: token_totals.unspent_txo_count,
+                        spent_txo_count: token_totals.spent_txo_count,
+                    })
+                    .execute(conn)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn latest_block_index(account_id_hex: &str, conn: &Conn) -> Result<Option<u64>, WalletDbError> {
+        use crate::db::schema::account_snapshots;
+
+        let latest: Option<i64> = account_snapshots::table
+            .filter(account_snapshots::account_id_hex.eq(account_id_hex))
+            .select(diesel::dsl::max(account_snapshots::block_index))
+            .first(conn)?;
+        Ok(latest.map(|block_index| block_index as u64))
+    }
+
+    fn list_for_account(
+        account_id_hex: &str,
+        token_id: Option<u64>,
+        from_block_index: Option<u64>,
+        to_block_index: Option<u64>,
+        conn: &Conn,
+    ) -> Result<Vec<AccountSnapshot>, WalletDbError> {
+        use crate::db::schema::account_snapshots;
+
+        let mut query = account_snapshots::table
+            .filter(account_snapshots::account_id_hex.eq(account_id_hex))
+            .into_boxed();
+        if let Some(token_id) = token_id {
+            query = query.filter(account_snapshots::token_id.eq(token_id as i64));
+        }
+        if let Some(from_block_index) = from_block_index {
+            query = query.filter(account_snapshots::block_index.ge(from_block_index as i64));
+        }
+        if let Some(to_block_index) = to_block_index {
+            query = query.filter(account_snapshots::block_index.le(to_block_index as i64));
+        }
+        Ok(query
+            .order((
+                account_snapshots::block_index.asc(),
+                account_snapshots::token_id.asc(),
+            ))
+            .load(conn)?)
+    }
+
+    fn delete_from(
+        account_id_hex: &str,
+        block_index: u64,
+        conn: &Conn,
+    ) -> Result<(), WalletDbError> {
+        use crate::db::schema::account_snapshots;
+
+        diesel::delete(
+            account_snapshots::table
+                .filter(account_snapshots::account_id_hex.eq(account_id_hex))
+                .filter(account_snapshots::block_index.ge(block_index as i64)),
+        )
+        .execute(conn)?;
+        Ok(())
+    }
+
+    fn delete_for_account(account_id_hex: &str, conn: &Conn) -> Result<(), WalletDbError> {
+        use crate::db::schema::account_snapshots;
+
+        diesel::delete(
+            account_snapshots::table.filter(account_snapshots::account_id_hex.eq(account_id_hex)),
+        )
+        .execute(conn)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        db::{account::AccountID, models::Txo, txo::TxoModel},
+        test_utils::{create_test_received_txo, WalletDbTestContext},
+    };
+    use mc_account_keys::{AccountKey, RootIdentity};
+    use mc_common::logger::{test_with_logger, Logger};
+    use mc_transaction_core::{Amount, TokenId};
+    use mc_util_from_random::FromRandom;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test_with_logger]
+    fn test_account_snapshots(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let db_test_context = WalletDbTestContext::default();
+        let wallet_db = db_test_context.get_db_instance(logger);
+        let conn = wallet_db.get_conn().unwrap();
+
+        let account_key = AccountKey::from(&RootIdentity::from_random(&mut rng));
+        let account_id_hex = AccountID::from(&account_key).to_string();
+
+        create_test_received_txo(
+            &account_key,
+            0,
+            Amount::new(100, Mob::ID),
+            2,
+            &mut rng,
+            &wallet_db,
+        );
+        let (spent_txo_id, _, _) = create_test_received_txo(
+            &account_key,
+            0,
+            Amount::new(50, Mob::ID),
+            5,
+            &mut rng,
+            &wallet_db,
+        );
+        Txo::update_to_spent(&spent_txo_id, 7, &conn).unwrap();
+        create_test_received_txo(
+            &account_key,
+            0,
+            Amount::new(30, TokenId::from(1)),
+            8,
+            &mut rng,
+            &wallet_db,
+        );
+
+        AccountSnapshot::create_for_blocks(&account_id_hex, &[1, 5, 7, 10], &conn).unwrap();
+        assert_eq!(
+            AccountSnapshot::latest_block_index(&account_id_hex, &conn).unwrap(),
+            Some(10)
+        );
+
+        let snapshots =
+            AccountSnapshot::list_for_account(&account_id_hex, None, None, None, &conn).unwrap();
+        let summary: Vec<(i64, i64, &str, i64, i64)> = snapshots
+            .iter()
+            .map(|snapshot| {
+                (
+                    snapshot.block_index,
+                    snapshot.token_id,
+                    snapshot.unspent_value.as_str(),
+                    snapshot.unspent_txo_count,
+                    snapshot.spent_txo_count,
+                )
+            })
+            .collect();
+        // Nothing had been received by block 1, which has an empty snapshot of
+        // MOB.
+        assert_eq!(
+            summary,
+            vec![
+                (1, 0, "0", 0, 0),
+                (5, 0, "150", 2, 0),
+                (7, 0, "100", 1, 1),
+                (10, 0, "100", 1, 1),
+                (10, 1, "30", 1, 0),
+            ]
+        );
+
+        let filtered =
+            AccountSnapshot::list_for_account(&account_id_hex, Some(0), Some(6), Some(9), &conn)
+                .unwrap();
+        assert_eq!(filtered.len(), 1);
+        assert_eq!(filtered[0].block_index, 7);
+
+        AccountSnapshot::delete_from(&account_id_hex, 7, &conn).unwrap();
+        assert_eq!(
+            AccountSnapshot::latest_block_index(&account_id_hex, &conn).unwrap(),
+            Some(5)
+        );
+
+        AccountSnapshot::delete_for_account(&account_id_hex, &conn).unwrap();
+        assert_eq!(
+            AccountSnapshot::latest_block_index(&account_id_hex, &conn).unwrap(),
+            None
+        );
+    }
+}
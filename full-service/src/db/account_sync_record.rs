@@ -0,0 +1,210 @@
+// Copyright (c) 2020-2022 MobileCoin Inc.
+
+//! The Account Sync Record Model.
+//!
+//! Each chunk of blocks the sync thread scans for an account is recorded, with
+//! how long it took, so that a slowdown in scanning, for example as the wallet
+//! database grows, shows up in the account's history rather than only as a
+//! lagging block height. Only the most recent records for each account are
+//! kept.
+
+use crate::db::{
+    models::{AccountSyncRecord, NewAccountSyncRecord},
+    Conn, WalletDbError,
+};
+use chrono::Utc;
+use diesel::prelude::*;
+use std::time::Duration;
+
+/// The number of records kept for each account.
+pub const MAX_SYNC_RECORDS_PER_ACCOUNT: u64 = 1000;
+
+pub trait AccountSyncRecordModel {
+    /// Record a chunk of blocks scanned for an account, dropping its oldest
+    /// records beyond the most recent MAX_SYNC_RECORDS_PER_ACCOUNT.
+    fn create(
+        account_id_hex: &str,
+        start_block_index: u64,
+        end_block_index: u64,
+        txos_received: usize,
+        txos_spent: usize,
+        duration: Duration,
+        conn: &Conn,
+    ) -> Result<(), WalletDbError>;
+
+    /// List the sync records for an account, oldest first.
+    fn list_for_account(
+        account_id_hex: &str,
+        offset: Option<u64>,
+        limit: Option<u64>,
+        conn: &Conn,
+    ) -> Result<Vec<AccountSyncRecord>, WalletDbError>;
+
+    /// List the sync records of every account made since the given time.
+    fn list_since(created_since: i64, conn: &Conn)
+        -> Result<Vec<AccountSyncRecord>, WalletDbError>;
+
+    /// Delete all sync records for an account.
+    fn delete_for_account(account_id_hex: &str, conn: &Conn) -> Result<(), WalletDbError>;
+
+    /// The number of blocks scanned.
+    fn num_blocks(&self) -> u64;
+
+    /// The number of blocks scanned per second.
+    fn blocks_per_second(&self) -> f64;
+}
+
+impl AccountSyncRecordModel for AccountSyncRecord {
+    fn create(
+        account_id_hex: &str,
+        start_block_index: u64,
+        end_block_index: u64,
+        txos_received: usize,
+        txos_spent: usize,
+        duration: Duration,
+        conn: &Conn,
+    ) -> Result<(), WalletDbError> {
+        use crate::db::schema::account_sync_records;
+
+        let new_record = NewAccountSyncRecord {
+            account_id_hex,
+            start_block_index: start_block_index as i64,
+            end_block_index: end_block_index as i64,
+            txos_received: txos_received as i32,
+            txos_spent: txos_spent as i32,
+            duration_ms: duration.as_millis() as i64,
+            created_time: Utc::now().timestamp(),
+        };
+        diesel::insert_into(account_sync_records::table)
+            .values(&new_record)
+            .execute(conn)?;
+
+        let oldest_kept: Option<i32> = account_sync_records::table
+            .filter(account_sync_records::account_id_hex.eq(account_id_hex))
+            .order(account_sync_records::id.desc())
+            .offset(MAX_SYNC_RECORDS_PER_ACCOUNT as i64 - 1)
+            .select(account_sync_records::id)
+            .first(conn)
+            .optional()?;
+        if let Some(oldest_kept) = oldest_kept {
+            diesel::delete(
+                account_sync_records::table
+                    .filter(account_sync_records::account_id_hex.eq(account_id_hex))
+                    .filter(account_sync_records::id.lt(oldest_kept)),
+            )
+            .execute(conn)?;
+        }
+
+        Ok(())
+    }
+
+    fn list_for_account(
+        account_id_hex: &str,
+        offset: Option<u64>,
+        limit: Option<u64>,
+        conn: &Conn,
+    ) -> Result<Vec<AccountSyncRecord>, WalletDbError> {
+        use crate::db::schema::account_sync_records;
+
+        let mut query = account_sync_records::table
+            .filter(account_sync_records::account_id_hex.eq(account_id_hex))
+            .order(account_sync_records::id.asc())
+            .into_boxed();
+
+        if let (Some(o), Some(l)) = (offset, limit) {
+            query = query.offset(o as i64).limit(l as i64);
+        }
+
+        Ok(query.load(conn)?)
+    }
+
+    fn list_since(
+        created_since: i64,
+        conn: &Conn,
+    ) -> Result<Vec<AccountSyncRecord>, WalletDbError> {
+        use crate::db::schema::account_sync_records;
+
+        Ok(account_sync_records::table
+            .filter(account_sync_records::created_time.ge(created_since))
+            .order(account_sync_records::id.asc())
+            .load(conn)?)
+    }
+
+    fn delete_for_account(account_id_hex: &str, conn: &Conn) -> Result<(), WalletDbError> {
+        use crate::db::schema::account_sync_records;
+
+        diesel::delete(
+            account_sync_records::table
+                .filter(account_sync_records::account_id_hex.eq(account_id_hex)),
+        )
+        .execute(conn)?;
+        Ok(())
+    }
+
+    fn num_blocks(&self) -> u64 {
+        (self.end_block_index - self.start_block_index + 1) as u64
+    }
+
+    fn blocks_per_second(&self) -> f64 {
+        // Chunks of a block or two can finish within a millisecond.
+        self.num_blocks() as f64 * 1000.0 / self.duration_ms.max(1) as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::WalletDbTestContext;
+    use mc_common::logger::{test_with_logger, Logger};
+
+    #[test_with_logger]
+    fn test_account_sync_record_crud(logger: Logger) {
+        let db_test_context = WalletDbTestContext::default();
+        let wallet_db = db_test_context.get_db_instance(logger);
+        let conn = wallet_db.get_conn().unwrap();
+
+        AccountSyncRecord::create("abcd", 0, 999, 4, 1, Duration::from_millis(500), &conn).unwrap();
+        AccountSyncRecord::create("abcd", 1000, 1001, 0, 0, Duration::from_micros(10), &conn)
+            .unwrap();
+        AccountSyncRecord::create("ef01", 0, 9, 0, 0, Duration::from_millis(1), &conn).unwrap();
+
+        let records = AccountSyncRecord::list_for_account("abcd", None, None, &conn).unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].num_blocks(), 1000);
+        assert_eq!(records[0].txos_received, 4);
+        assert_eq!(records[0].blocks_per_second(), 2000.0);
+        assert_eq!(records[1].end_block_index, 1001);
+        assert_eq!(records[1].duration_ms, 0);
+        assert_eq!(records[1].blocks_per_second(), 2000.0);
+
+        // Only the most recent records are kept.
+        for i in 0..MAX_SYNC_RECORDS_PER_ACCOUNT {
+            AccountSyncRecord::create("abcd", 1002 + i, 1002 + i, 0, 0, Duration::default(), &conn)
+                .unwrap();
+        }
+        let records = AccountSyncRecord::list_for_account("abcd", None, None, &conn).unwrap();
+        assert_eq!(records.len() as u64, MAX_SYNC_RECORDS_PER_ACCOUNT);
+        assert_eq!(records[0].start_block_index, 1002);
+        let records =
+            AccountSyncRecord::list_for_account("abcd", Some(10), Some(5), &conn).unwrap();
+        assert_eq!(records.len(), 5);
+        assert_eq!(records[0].start_block_index, 1012);
+
+        AccountSyncRecord::delete_for_account("abcd", &conn).unwrap();
+        assert!(
+            AccountSyncRecord::list_for_account("abcd", None, None, &conn)
+                .unwrap()
+                .is_empty()
+        );
+        assert_eq!(
+            AccountSyncRecord::list_for_account("ef01", None, None, &conn)
+                .unwrap()
+                .len(),
+            1
+        );
+        assert_eq!(AccountSyncRecord::list_since(0, &conn).unwrap().len(), 1);
+        assert!(AccountSyncRecord::list_since(i64::MAX, &conn)
+            .unwrap()
+            .is_empty());
+    }
+}
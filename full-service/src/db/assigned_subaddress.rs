@@ -22,6 +22,7 @@ use mc_ledger_db::{Ledger, LedgerDB};
 
 use crate::db::{Conn, WalletDbError};
 use diesel::prelude::*;
+use std::collections::HashSet;
 
 pub trait AssignedSubaddressModel {
     /// Assign a subaddress to a contact.
@@ -45,7 +46,8 @@ pub trait AssignedSubaddressModel {
         conn: &Conn,
     ) -> Result<String, WalletDbError>;
 
-    /// Create the next subaddress for a given account.
+    /// Create the next subaddress for a given account. Indices which were
+    /// already assigned out of sequence are skipped.
     ///
     /// Returns:
     /// * (assigned_subaddress_b58, subaddress_index)
@@ -56,6 +58,58 @@ pub trait AssignedSubaddressModel {
         conn: &Conn,
     ) -> Result<(String, i64), WalletDbError>;
 
+    /// Assign the subaddress at a given index for an account, recovering any
+    /// orphaned txos sent to it. The account's next subaddress index is moved
+    /// past it, so that later assignments don't collide with it.
+    ///
+    /// Returns:
+    /// * (assigned_subaddress_b58, subaddress_index)
+    fn create_for_account_at_index(
+        account_id_hex: &str,
+        subaddress_index: u64,
+        comment: &str,
+        ledger_db: &LedgerDB,
+        conn: &Conn,
+    ) -> Result<(String, i64), WalletDbError>;
+
+    /// Assign the subaddress at a given index for an account, recovering any
+    /// orphaned txos sent to it, without moving the account's next subaddress
+    /// index. This lets integrators pre-assign addresses at indices of their
+    /// own choosing, however far ahead of those assigned in sequence.
+    ///
+    /// Returns:
+    /// * (assigned_subaddress_b58, subaddress_index)
+    fn create_sparse_for_account_at_index(
+        account_id_hex: &str,
+        subaddress_index: u64,
+        comment: &str,
+        ledger_db: &LedgerDB,
+        conn: &Conn,
+    ) -> Result<(String, i64), WalletDbError>;
+
+    /// Assign the subaddresses of an account which its orphaned txos were sent
+    /// to, recovering the txos. Indices are checked from the account's next
+    /// subaddress index until `gap_limit` unfunded indices in a row are
+    /// found, so that an account imported without knowing how many addresses
+    /// it had assigned finds them again.
+    ///
+    /// Returns:
+    /// * The subaddress indices which were assigned
+    fn recover_orphaned_for_account(
+        account_id_hex: &str,
+        gap_limit: u64,
+        ledger_db: &LedgerDB,
+        conn: &Conn,
+    ) -> Result<Vec<u64>, WalletDbError>;
+
+    /// List an account's orphaned txos which were sent to the subaddress at a
+    /// given index.
+    fn list_orphaned_txos_at_index(
+        account_id_hex: &str,
+        subaddress_index: u64,
+        conn: &Conn,
+    ) -> Result<Vec<Txo>, WalletDbError>;
+
     /// Get the AssignedSubaddress for a given assigned_subaddress_b58
     fn get(public_address_b58: &str, conn: &Conn) -> Result<AssignedSubaddress, WalletDbError>;
 
@@ -84,6 +138,9 @@ pub trait AssignedSubaddressModel {
         conn: &Conn,
     ) -> Result<Vec<AssignedSubaddress>, WalletDbError>;
 
+    /// Replace the comment of an AssignedSubaddress.
+    fn update_comment(&self, comment: &str, conn: &Conn) -> Result<(), WalletDbError>;
+
     /// Delete all AssignedSubaddresses for a given account.
     fn delete_all(account_id_hex: &str, conn: &Conn) -> Result<(), WalletDbError>;
 }
@@ -128,10 +185,6 @@ impl AssignedSubaddressModel for AssignedSubaddress {
         use crate::db::schema::{
             accounts::dsl::{account_id_hex as dsl_account_id_hex, accounts},
             assigned_subaddresses,
-            transaction_logs::dsl::{
-                account_id_hex as tx_log_account_id_hex,
-                transaction_id_hex as tx_log_transaction_id_hex, transaction_logs,
-            },
         };
 
         let account = Account::get(&AccountID(account_id_hex.to_string()), conn)?;
@@ -141,8 +194,29 @@ impl AssignedSubaddressModel for AssignedSubaddress {
         }
 
         let account_key: AccountKey = mc_util_serial::decode(&account.account_key)?;
-        let view_private_key = account_key.view_private_key();
-        let subaddress_index = account.next_subaddress_index;
+
+        // Claim the next subaddress index with an atomic increment in the
+        // database, rather than writing back the value read above, so that
+        // concurrent assignments can never be handed the same index. The
+        // increment takes the write lock, so the read after it sees our claim.
+        // Indices which were pre-assigned out of sequence are passed over.
+        let subaddress_index = loop {
+            let claimed_subaddress_index = conn.transaction::<i64, WalletDbError, _>(|| {
+                use crate::db::schema::accounts::next_subaddress_index;
+
+                diesel::update(accounts.filter(dsl_account_id_hex.eq(account_id_hex)))
+                    .set(next_subaddress_index.eq(next_subaddress_index + 1))
+                    .execute(conn)?;
+                let claimed_next_subaddress_index: i64 = accounts
+                    .filter(dsl_account_id_hex.eq(account_id_hex))
+                    .select(next_subaddress_index)
+                    .first(conn)?;
+                Ok(claimed_next_subaddress_index - 1)
+            })?;
+            if !is_index_assigned(account_id_hex, claimed_subaddress_index, conn)? {
+                break claimed_subaddress_index;
+            }
+        };
         let subaddress = account_key.subaddress(subaddress_index as u64);
 
         let subaddress_b58 = b58_encode_public_address(&subaddress)?;
@@ -161,70 +235,162 @@ impl AssignedSubaddressModel for AssignedSubaddress {
             .values(&subaddress_entry)
             .execute(conn)?;
 
-        // Update the next subaddress index for the account
-        diesel::update(accounts.filter(dsl_account_id_hex.eq(account_id_hex)))
-            .set((crate::db::schema::accounts::next_subaddress_index.eq(subaddress_index + 1),))
-            .execute(conn)?;
-
         // Find and repair orphaned txos at this subaddress.
-        let orphaned_txos = Txo::list_orphaned(account_id_hex, None, conn)?;
+        repair_orphaned_txos(
+            &account_key,
+            account_id_hex,
+            subaddress_index as u64,
+            &subaddress_b58,
+            ledger_db,
+            conn,
+        )?;
 
-        for orphaned_txo in orphaned_txos.iter() {
-            let tx_out_target_key: RistrettoPublic =
-                mc_util_serial::decode(&orphaned_txo.target_key).unwrap();
-            let tx_public_key: RistrettoPublic =
-                mc_util_serial::decode(&orphaned_txo.public_key).unwrap();
-            let txo_public_key = CompressedRistrettoPublic::from(tx_public_key);
+        Ok((subaddress_b58, subaddress_index))
+    }
 
-            let txo_subaddress_spk: RistrettoPublic = recover_public_subaddress_spend_key(
-                view_private_key,
-                &tx_out_target_key,
-                &tx_public_key,
-            );
+    fn create_for_account_at_index(
+        account_id_hex: &str,
+        subaddress_index: u64,
+        comment: &str,
+        ledger_db: &LedgerDB,
+        conn: &Conn,
+    ) -> Result<(String, i64), WalletDbError> {
+        use crate::db::schema::accounts::dsl::{
+            account_id_hex as dsl_account_id_hex, accounts, next_subaddress_index,
+        };
 
-            if txo_subaddress_spk == *subaddress.spend_public_key() {
-                let onetime_private_key = recover_onetime_private_key(
-                    &tx_public_key,
-                    account_key.view_private_key(),
-                    &account_key.subaddress_spend_private(subaddress_index as u64),
-                );
-
-                let key_image = KeyImage::from(&onetime_private_key);
-
-                if ledger_db.contains_key_image(&key_image)? {
-                    let txo_index = ledger_db.get_tx_out_index_by_public_key(&txo_public_key)?;
-                    let block_index = ledger_db.get_block_index_by_tx_out_index(txo_index)?;
-                    diesel::update(orphaned_txo)
-                        .set(
-                            crate::db::schema::txos::spent_block_index.eq(Some(block_index as i64)),
-                        )
-                        .execute(conn)?;
-                }
-
-                let key_image_bytes = mc_util_serial::encode(&key_image);
-
-                // Update the account status mapping.
-                diesel::update(orphaned_txo)
-                    .set((
-                        crate::db::schema::txos::subaddress_index.eq(subaddress_index),
-                        crate::db::schema::txos::key_image.eq(key_image_bytes),
-                    ))
-                    .execute(conn)?;
+        let account = Account::get(&AccountID(account_id_hex.to_string()), conn)?;
 
-                diesel::update(
-                    transaction_logs
-                        .filter(tx_log_transaction_id_hex.eq(&orphaned_txo.txo_id_hex))
-                        .filter(tx_log_account_id_hex.eq(account_id_hex)),
-                )
-                .set(
-                    (crate::db::schema::transaction_logs::assigned_subaddress_b58
-                        .eq(&subaddress_b58),),
-                )
-                .execute(conn)?;
+        if account.fog_enabled {
+            return Err(WalletDbError::SubaddressesNotSupportedForFOGEnabledAccounts);
+        }
+
+        let account_key: AccountKey = mc_util_serial::decode(&account.account_key)?;
+        let subaddress_b58 =
+            AssignedSubaddress::create(&account_key, None, subaddress_index, comment, conn)?;
+
+        diesel::update(
+            accounts
+                .filter(dsl_account_id_hex.eq(account_id_hex))
+                .filter(next_subaddress_index.le(subaddress_index as i64)),
+        )
+        .set(next_subaddress_index.eq(subaddress_index as i64 + 1))
+        .execute(conn)?;
+
+        repair_orphaned_txos(
+            &account_key,
+            account_id_hex,
+            subaddress_index,
+            &subaddress_b58,
+            ledger_db,
+            conn,
+        )?;
+
+        Ok((subaddress_b58, subaddress_index as i64))
+    }
+
+    fn create_sparse_for_account_at_index(
+        account_id_hex: &str,
+        subaddress_index: u64,
+        comment: &str,
+        ledger_db: &LedgerDB,
+        conn: &Conn,
+    ) -> Result<(String, i64), WalletDbError> {
+        // Indices are stored as i64, which also keeps integrators clear of the
+        // reserved change subaddress near the top of the range.
+        if subaddress_index > i64::MAX as u64 {
+            return Err(WalletDbError::SubaddressIndexOutOfRange(subaddress_index));
+        }
+
+        let account = Account::get(&AccountID(account_id_hex.to_string()), conn)?;
+
+        if account.fog_enabled {
+            return Err(WalletDbError::SubaddressesNotSupportedForFOGEnabledAccounts);
+        }
+
+        if is_index_assigned(account_id_hex, subaddress_index as i64, conn)? {
+            return Err(WalletDbError::SubaddressAlreadyAssigned(subaddress_index));
+        }
+
+        let account_key: AccountKey = mc_util_serial::decode(&account.account_key)?;
+        let subaddress_b58 =
+            AssignedSubaddress::create(&account_key, None, subaddress_index, comment, conn)?;
+
+        repair_orphaned_txos(
+            &account_key,
+            account_id_hex,
+            subaddress_index,
+            &subaddress_b58,
+            ledger_db,
+            conn,
+        )?;
+
+        Ok((subaddress_b58, subaddress_index as i64))
+    }
+
+    fn recover_orphaned_for_account(
+        account_id_hex: &str,
+        gap_limit: u64,
+        ledger_db: &LedgerDB,
+        conn: &Conn,
+    ) -> Result<Vec<u64>, WalletDbError> {
+        let account = Account::get(&AccountID(account_id_hex.to_string()), conn)?;
+        if account.fog_enabled || gap_limit == 0 {
+            return Ok(Vec::new());
+        }
+        let account_key: AccountKey = mc_util_serial::decode(&account.account_key)?;
+
+        // The spend public keys of the subaddresses the orphaned txos were
+        // sent to, which are compared with each index's in turn.
+        let mut orphaned_spend_keys: HashSet<RistrettoPublic> =
+            Txo::list_orphaned(account_id_hex, None, conn)?
+                .iter()
+                .map(|orphaned_txo| {
+                    let tx_out_target_key: RistrettoPublic =
+                        mc_util_serial::decode(&orphaned_txo.target_key)?;
+                    let tx_public_key: RistrettoPublic =
+                        mc_util_serial::decode(&orphaned_txo.public_key)?;
+                    Ok(recover_public_subaddress_spend_key(
+                        account_key.view_private_key(),
+                        &tx_out_target_key,
+                        &tx_public_key,
+                    ))
+                })
+                .collect::<Result<_, WalletDbError>>()?;
+
+        let mut recovered = Vec::new();
+        let mut subaddress_index = account.next_subaddress_index as u64;
+        let mut gap = 0;
+        while gap < gap_limit && !orphaned_spend_keys.is_empty() {
+            let subaddress = account_key.subaddress(subaddress_index);
+            if orphaned_spend_keys.remove(subaddress.spend_public_key()) {
+                AssignedSubaddress::create_for_account_at_index(
+                    account_id_hex,
+                    subaddress_index,
+                    "",
+                    ledger_db,
+                    conn,
+                )?;
+                recovered.push(subaddress_index);
+                gap = 0;
+            } else if is_index_assigned(account_id_hex, subaddress_index as i64, conn)? {
+                gap = 0;
+            } else {
+                gap += 1;
             }
+            subaddress_index += 1;
         }
+        Ok(recovered)
+    }
 
-        Ok((subaddress_b58, subaddress_index))
+    fn list_orphaned_txos_at_index(
+        account_id_hex: &str,
+        subaddress_index: u64,
+        conn: &Conn,
+    ) -> Result<Vec<Txo>, WalletDbError> {
+        let account = Account::get(&AccountID(account_id_hex.to_string()), conn)?;
+        let account_key: AccountKey = mc_util_serial::decode(&account.account_key)?;
+        orphaned_txos_at_index(&account_key, account_id_hex, subaddress_index, conn)
     }
 
     fn get(public_address_b58: &str, conn: &Conn) -> Result<AssignedSubaddress, WalletDbError> {
@@ -318,6 +484,17 @@ impl AssignedSubaddressModel for AssignedSubaddress {
         Ok(addresses)
     }
 
+    fn update_comment(&self, comment: &str, conn: &Conn) -> Result<(), WalletDbError> {
+        use crate::db::schema::assigned_subaddresses;
+
+        diesel::update(assigned_subaddresses::table.filter(
+            assigned_subaddresses::assigned_subaddress_b58.eq(&self.assigned_subaddress_b58),
+        ))
+        .set(assigned_subaddresses::comment.eq(comment))
+        .execute(conn)?;
+        Ok(())
+    }
+
     fn delete_all(account_id_hex: &str, conn: &Conn) -> Result<(), WalletDbError> {
         use crate::db::schema::assigned_subaddresses::dsl::{
             account_id_hex as schema_account_id_hex, assigned_subaddresses,
@@ -328,3 +505,106 @@ impl AssignedSubaddressModel for AssignedSubaddress {
         Ok(())
     }
 }
+
+/// Whether the account has assigned the subaddress at the given index.
+fn is_index_assigned(
+    account_id_hex: &str,
+    subaddress_index: i64,
+    conn: &Conn,
+) -> Result<bool, WalletDbError> {
+    use crate::db::schema::assigned_subaddresses;
+
+    let count: i64 = assigned_subaddresses::table
+        .filter(assigned_subaddresses::account_id_hex.eq(account_id_hex))
+        .filter(assigned_subaddresses::subaddress_index.eq(subaddress_index))
+        .count()
+        .get_result(conn)?;
+    Ok(count > 0)
+}
+
+/// The account's orphaned txos whose target key was derived from the
+/// subaddress at the given index.
+fn orphaned_txos_at_index(
+    account_key: &AccountKey,
+    account_id_hex: &str,
+    subaddress_index: u64,
+    conn: &Conn,
+) -> Result<Vec<Txo>, WalletDbError> {
+    let subaddress = account_key.subaddress(subaddress_index);
+    Ok(Txo::list_orphaned(account_id_hex, None, conn)?
+        .into_iter()
+        .filter(|orphaned_txo| {
+            let tx_out_target_key: RistrettoPublic =
+                mc_util_serial::decode(&orphaned_txo.target_key).unwrap();
+            let tx_public_key: RistrettoPublic =
+                mc_util_serial::decode(&orphaned_txo.public_key).unwrap();
+            let txo_subaddress_spk: RistrettoPublic = recover_public_subaddress_spend_key(
+                account_key.view_private_key(),
+                &tx_out_target_key,
+                &tx_public_key,
+            );
+            txo_subaddress_spk == *subaddress.spend_public_key()
+        })
+        .collect())
+}
+
+/// Give the orphaned txos sent to a newly assigned subaddress their
+/// subaddress index and key image, marking any which have been spent.
+fn repair_orphaned_txos(
+    account_key: &AccountKey,
+    account_id_hex: &str,
+    subaddress_index: u64,
+    subaddress_b58: &str,
+    ledger_db: &LedgerDB,
+    conn: &Conn,
+) -> Result<(), WalletDbError> {
+    use crate::db::schema::transaction_logs::dsl::{
+        account_id_hex as tx_log_account_id_hex, transaction_id_hex as tx_log_transaction_id_hex,
+        transaction_logs,
+    };
+
+    let orphaned_txos =
+        orphaned_txos_at_index(account_key, account_id_hex, subaddress_index, conn)?;
+
+    for orphaned_txo in orphaned_txos.iter() {
+        let tx_public_key: RistrettoPublic =
+            mc_util_serial::decode(&orphaned_txo.public_key).unwrap();
+        let txo_public_key = CompressedRistrettoPublic::from(tx_public_key);
+
+        let onetime_private_key = recover_onetime_private_key(
+            &tx_public_key,
+            account_key.view_private_key(),
+            &account_key.subaddress_spend_private(subaddress_index),
+        );
+
+        let key_image = KeyImage::from(&onetime_private_key);
+
+        if ledger_db.contains_key_image(&key_image)? {
+            let txo_index = ledger_db.get_tx_out_index_by_public_key(&txo_public_key)?;
+            let block_index = ledger_db.get_block_index_by_tx_out_index(txo_index)?;
+            diesel::update(orphaned_txo)
+                .set(crate::db::schema::txos::spent_block_index.eq(Some(block_index as i64)))
+                .execute(conn)?;
+        }
+
+        let key_image_bytes = mc_util_serial::encode(&key_image);
+
+        // Update the account status mapping.
+        diesel::update(orphaned_txo)
+            .set((
+                crate::db::schema::txos::subaddress_index.eq(subaddress_index as i64),
+                crate::db::schema::txos::key_image.eq(key_image_bytes),
+            ))
+            .execute(conn)?;
+
+        diesel::update(
+            transaction_logs
+                .filter(tx_log_transaction_id_hex.eq(&orphaned_txo.txo_id_hex))
+                .filter(tx_log_account_id_hex.eq(account_id_hex)),
+        )
+        .set((crate::db::schema::transaction_logs::assigned_subaddress_b58.eq(subaddress_b58),))
+        .execute(conn)?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,110 @@
+// Copyright (c) 2020-2022 MobileCoin Inc.
+
+//! The Continuity Check-In Model.
+//!
+//! Each time the operator checks in, a row is added. When the latest check-in
+//! is older than the configured period, a continuity export is written and
+//! recorded against it, so that only one export is written until the operator
+//! checks in again.
+
+use crate::db::{
+    models::{ContinuityCheckIn, NewContinuityCheckIn},
+    Conn, WalletDbError,
+};
+use diesel::prelude::*;
+
+pub trait ContinuityCheckInModel {
+    /// Record a check-in by the operator.
+    fn create(now: i64, conn: &Conn) -> Result<ContinuityCheckIn, WalletDbError>;
+
+    /// Get the most recent check-in, if there has been one.
+    fn get_latest(conn: &Conn) -> Result<Option<ContinuityCheckIn>, WalletDbError>;
+
+    /// Record that a continuity export was written for this check-in.
+    fn record_export(
+        &self,
+        export_path: &str,
+        now: i64,
+        conn: &Conn,
+    ) -> Result<ContinuityCheckIn, WalletDbError>;
+}
+
+impl ContinuityCheckInModel for ContinuityCheckIn {
+    fn create(now: i64, conn: &Conn) -> Result<ContinuityCheckIn, WalletDbError> {
+        use crate::db::schema::continuity_check_ins;
+
+        diesel::insert_into(continuity_check_ins::table)
+            .values(&NewContinuityCheckIn { created_time: now })
+            .execute(conn)?;
+
+        Ok(continuity_check_ins::table
+            .order(continuity_check_ins::id.desc())
+            .first::<ContinuityCheckIn>(conn)?)
+    }
+
+    fn get_latest(conn: &Conn) -> Result<Option<ContinuityCheckIn>, WalletDbError> {
+        use crate::db::schema::continuity_check_ins;
+
+        Ok(continuity_check_ins::table
+            .order(continuity_check_ins::id.desc())
+            .first::<ContinuityCheckIn>(conn)
+            .optional()?)
+    }
+
+    fn record_export(
+        &self,
+        export_path: &str,
+        now: i64,
+        conn: &Conn,
+    ) -> Result<ContinuityCheckIn, WalletDbError> {
+        use crate::db::schema::continuity_check_ins;
+
+        diesel::update(continuity_check_ins::table.filter(continuity_check_ins::id.eq(self.id)))
+            .set((
+                continuity_check_ins::exported_time.eq(Some(now)),
+                continuity_check_ins::export_path.eq(Some(export_path)),
+            ))
+            .execute(conn)?;
+
+        Ok(continuity_check_ins::table
+            .filter(continuity_check_ins::id.eq(self.id))
+            .get_result::<ContinuityCheckIn>(conn)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::WalletDbTestContext;
+    use mc_common::logger::{test_with_logger, Logger};
+
+    #[test_with_logger]
+    fn test_continuity_check_ins(logger: Logger) {
+        let db_test_context = WalletDbTestContext::default();
+        let wallet_db = db_test_context.get_db_instance(logger);
+        let conn = wallet_db.get_conn().unwrap();
+
+        assert!(ContinuityCheckIn::get_latest(&conn).unwrap().is_none());
+
+        ContinuityCheckIn::create(100, &conn).unwrap();
+        let second = ContinuityCheckIn::create(200, &conn).unwrap();
+        assert_eq!(
+            ContinuityCheckIn::get_latest(&conn).unwrap(),
+            Some(second.clone())
+        );
+        assert_eq!(second.exported_time, None);
+
+        let exported = second.record_export("/exports/a.bin", 300, &conn).unwrap();
+        assert_eq!(exported.exported_time, Some(300));
+        assert_eq!(exported.export_path, Some("/exports/a.bin".to_string()));
+        assert_eq!(
+            ContinuityCheckIn::get_latest(&conn).unwrap(),
+            Some(exported)
+        );
+
+        // A new check-in starts without an export.
+        let third = ContinuityCheckIn::create(400, &conn).unwrap();
+        assert_eq!(third.exported_time, None);
+        assert_eq!(ContinuityCheckIn::get_latest(&conn).unwrap(), Some(third));
+    }
+}
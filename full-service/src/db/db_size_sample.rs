@@ -0,0 +1,97 @@
+// Copyright (c) 2020-2022 MobileCoin Inc.
+
+//! The DB Size Sample Model.
+//!
+//! The sizes of the wallet and ledger databases are sampled periodically, so
+//! that their growth over the last day or week can be reported. Samples older
+//! than DB_SIZE_SAMPLE_RETENTION_SECONDS are dropped as new ones are taken.
+
+use crate::db::{
+    models::{DbSizeSample, NewDbSizeSample},
+    Conn, WalletDbError,
+};
+use diesel::prelude::*;
+
+/// How long samples are kept, in seconds.
+pub const DB_SIZE_SAMPLE_RETENTION_SECONDS: i64 = 7 * 24 * 60 * 60;
+
+pub trait DbSizeSampleModel {
+    /// Record the sizes of the databases, in bytes.
+    fn create(
+        wallet_db_bytes: Option<u64>,
+        ledger_db_bytes: Option<u64>,
+        now: i64,
+        conn: &Conn,
+    ) -> Result<(), WalletDbError>;
+
+    /// List the samples taken since the given time, oldest first.
+    fn list_since(created_since: i64, conn: &Conn) -> Result<Vec<DbSizeSample>, WalletDbError>;
+}
+
+impl DbSizeSampleModel for DbSizeSample {
+    fn create(
+        wallet_db_bytes: Option<u64>,
+        ledger_db_bytes: Option<u64>,
+        now: i64,
+        conn: &Conn,
+    ) -> Result<(), WalletDbError> {
+        use crate::db::schema::db_size_samples;
+
+        diesel::insert_into(db_size_samples::table)
+            .values(&NewDbSizeSample {
+                wallet_db_bytes: wallet_db_bytes.map(|bytes| bytes as i64),
+                ledger_db_bytes: ledger_db_bytes.map(|bytes| bytes as i64),
+                created_time: now,
+            })
+            .execute(conn)?;
+        diesel::delete(
+            db_size_samples::table
+                .filter(db_size_samples::created_time.lt(now - DB_SIZE_SAMPLE_RETENTION_SECONDS)),
+        )
+        .execute(conn)?;
+        Ok(())
+    }
+
+    fn list_since(created_since: i64, conn: &Conn) -> Result<Vec<DbSizeSample>, WalletDbError> {
+        use crate::db::schema::db_size_samples;
+
+        Ok(db_size_samples::table
+            .filter(db_size_samples::created_time.ge(created_since))
+            .order(db_size_samples::id.asc())
+            .load(conn)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::WalletDbTestContext;
+    use mc_common::logger::{test_with_logger, Logger};
+
+    #[test_with_logger]
+    fn test_db_size_samples(logger: Logger) {
+        let db_test_context = WalletDbTestContext::default();
+        let wallet_db = db_test_context.get_db_instance(logger);
+        let conn = wallet_db.get_conn().unwrap();
+
+        DbSizeSample::create(Some(100), Some(1000), 10, &conn).unwrap();
+        DbSizeSample::create(Some(150), None, 20, &conn).unwrap();
+        let samples = DbSizeSample::list_since(0, &conn).unwrap();
+        assert_eq!(samples.len(), 2);
+        assert_eq!(samples[0].wallet_db_bytes, Some(100));
+        assert_eq!(samples[1].ledger_db_bytes, None);
+        assert_eq!(DbSizeSample::list_since(11, &conn).unwrap().len(), 1);
+
+        // Samples are dropped once they pass the retention period.
+        DbSizeSample::create(
+            Some(200),
+            None,
+            15 + DB_SIZE_SAMPLE_RETENTION_SECONDS,
+            &conn,
+        )
+        .unwrap();
+        let samples = DbSizeSample::list_since(0, &conn).unwrap();
+        assert_eq!(samples.len(), 2);
+        assert_eq!(samples[0].created_time, 20);
+    }
+}
@@ -9,13 +9,19 @@ use crate::{
     },
     service::gift_code::EncodedGiftCode,
 };
-use diesel::prelude::*;
+use diesel::{
+    prelude::*,
+    result::{DatabaseErrorKind, Error as DieselError},
+};
 use displaydoc::Display;
 
 #[derive(Display, Debug)]
 pub enum GiftCodeDbError {
     /// Could not get gift code: {0}
     GiftCodeNotFound(String),
+
+    /// Gift code has already been saved: {0}
+    GiftCodeAlreadyExists(String),
 }
 
 pub trait GiftCodeModel {
@@ -37,6 +43,8 @@ pub trait GiftCodeModel {
     fn create(
         gift_code_b58: &EncodedGiftCode,
         value: i64,
+        funding_account_id_hex: Option<&str>,
+        expiry_time: Option<i64>,
         conn: &Conn,
     ) -> Result<GiftCode, WalletDbError>;
 
@@ -46,6 +54,26 @@ pub trait GiftCodeModel {
     /// Get all Gift Codes in this wallet.
     fn list_all(conn: &Conn) -> Result<Vec<GiftCode>, WalletDbError>;
 
+    /// List the gift codes funded by this wallet which expired by `now`
+    /// without being claimed by it, and whose expiry hasn't been dealt with.
+    fn list_expired(now: i64, conn: &Conn) -> Result<Vec<GiftCode>, WalletDbError>;
+
+    /// Record that the gift code's expiry has been dealt with.
+    fn mark_expired(&self, conn: &Conn) -> Result<(), WalletDbError>;
+
+    /// Record that an account in this wallet claimed the gift code, and the
+    /// log under which it records the claimed funds.
+    ///
+    /// Only the first claim is recorded, as any later claim of the same gift
+    /// code is rejected by the network.
+    fn record_claim(
+        &self,
+        claimer_account_id_hex: &str,
+        claim_transaction_log_id: &str,
+        claimed_time: i64,
+        conn: &Conn,
+    ) -> Result<(), WalletDbError>;
+
     /// Delete a gift code.
     fn delete(self, conn: &Conn) -> Result<(), WalletDbError>;
 }
@@ -54,6 +82,8 @@ impl GiftCodeModel for GiftCode {
     fn create(
         gift_code_b58: &EncodedGiftCode,
         value: i64,
+        funding_account_id_hex: Option<&str>,
+        expiry_time: Option<i64>,
         conn: &Conn,
     ) -> Result<GiftCode, WalletDbError> {
         use crate::db::schema::gift_codes;
@@ -62,10 +92,23 @@ impl GiftCodeModel for GiftCode {
         let new_gift_code = NewGiftCode {
             gift_code_b58: &gift_code_b58.to_string(),
             value,
+            funding_account_id_hex,
+            expiry_time,
         };
-        diesel::insert_into(gift_codes::table)
+        // Gift codes are unique, so that the same gift code can't be
+        // submitted twice, even by two processes sharing the database.
+        match diesel::insert_into(gift_codes::table)
             .values(&new_gift_code)
-            .execute(conn)?;
+            .execute(conn)
+        {
+            Ok(_) => {}
+            Err(DieselError::DatabaseError(DatabaseErrorKind::UniqueViolation, _)) => {
+                return Err(
+                    GiftCodeDbError::GiftCodeAlreadyExists(gift_code_b58.to_string()).into(),
+                )
+            }
+            Err(e) => return Err(e.into()),
+        }
 
         let gift_code = GiftCode::get(gift_code_b58, conn)?;
         Ok(gift_code)
@@ -95,6 +138,50 @@ impl GiftCodeModel for GiftCode {
             .load::<GiftCode>(conn)?)
     }
 
+    fn list_expired(now: i64, conn: &Conn) -> Result<Vec<GiftCode>, WalletDbError> {
+        use crate::db::schema::gift_codes;
+
+        Ok(gift_codes::table
+            .filter(gift_codes::expiry_time.le(now))
+            .filter(gift_codes::expired.eq(false))
+            .filter(gift_codes::funding_account_id_hex.is_not_null())
+            .filter(gift_codes::claimer_account_id_hex.is_null())
+            .order(gift_codes::id.asc())
+            .load::<GiftCode>(conn)?)
+    }
+
+    fn mark_expired(&self, conn: &Conn) -> Result<(), WalletDbError> {
+        use crate::db::schema::gift_codes;
+
+        diesel::update(gift_codes::table.filter(gift_codes::id.eq(self.id)))
+            .set(gift_codes::expired.eq(true))
+            .execute(conn)?;
+        Ok(())
+    }
+
+    fn record_claim(
+        &self,
+        claimer_account_id_hex: &str,
+        claim_transaction_log_id: &str,
+        claimed_time: i64,
+        conn: &Conn,
+    ) -> Result<(), WalletDbError> {
+        use crate::db::schema::gift_codes;
+
+        diesel::update(
+            gift_codes::table
+                .filter(gift_codes::id.eq(self.id))
+                .filter(gift_codes::claimer_account_id_hex.is_null()),
+        )
+        .set((
+            gift_codes::claimer_account_id_hex.eq(claimer_account_id_hex),
+            gift_codes::claim_transaction_log_id.eq(claim_transaction_log_id),
+            gift_codes::claimed_time.eq(claimed_time),
+        ))
+        .execute(conn)?;
+        Ok(())
+    }
+
     fn delete(self, conn: &Conn) -> Result<(), WalletDbError> {
         use crate::db::schema::gift_codes::dsl::{gift_code_b58, gift_codes};
 
@@ -142,6 +229,8 @@ mod tests {
         let gift_code = GiftCode::create(
             &EncodedGiftCode("gk7CcXuK5RKNW13LvrWY156ZLjaoHaXxLedqACZsw3w6FfF6TR4TVzaAQkH5EHxaw54DnGWRJPA31PpcmvGLoArZbDRj1kBhcTusE8AVW4Mj7QT5".to_string()),
             value as i64,
+            Some("abcd"),
+            None,
             &wallet_db.get_conn().unwrap(),
         )
         .unwrap();
@@ -156,11 +245,71 @@ mod tests {
             id: 1,
             gift_code_b58: gotten.gift_code_b58.clone(),
             value: value as i64,
+            funding_account_id_hex: Some("abcd".to_string()),
+            claimer_account_id_hex: None,
+            claim_transaction_log_id: None,
+            claimed_time: None,
+            expiry_time: None,
+            expired: false,
         };
         assert_eq!(gotten, expected_gift_code);
 
         let all_gift_codes = GiftCode::list_all(&wallet_db.get_conn().unwrap()).unwrap();
         assert_eq!(all_gift_codes.len(), 1);
         assert_eq!(all_gift_codes[0], expected_gift_code);
+
+        gotten
+            .record_claim("ef01", "2345", 1000, &wallet_db.get_conn().unwrap())
+            .unwrap();
+        let claimed = GiftCode::get(
+            &EncodedGiftCode(gotten.gift_code_b58),
+            &wallet_db.get_conn().unwrap(),
+        )
+        .unwrap();
+        assert_eq!(claimed.claimer_account_id_hex, Some("ef01".to_string()));
+        assert_eq!(claimed.claim_transaction_log_id, Some("2345".to_string()));
+        assert_eq!(claimed.claimed_time, Some(1000));
+
+        // A later claim doesn't replace the first.
+        claimed
+            .record_claim("ef02", "6789", 2000, &wallet_db.get_conn().unwrap())
+            .unwrap();
+        let reclaimed = GiftCode::get(
+            &EncodedGiftCode(claimed.gift_code_b58.clone()),
+            &wallet_db.get_conn().unwrap(),
+        )
+        .unwrap();
+        assert_eq!(reclaimed, claimed);
+
+        // The same gift code can't be saved twice.
+        match GiftCode::create(
+            &EncodedGiftCode(claimed.gift_code_b58),
+            value as i64,
+            Some("abcd"),
+            None,
+            &wallet_db.get_conn().unwrap(),
+        ) {
+            Err(WalletDbError::GiftCode(GiftCodeDbError::GiftCodeAlreadyExists(_))) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+
+        // A gift code with an expiry is listed once it has expired, until its
+        // expiry has been dealt with.
+        let conn = wallet_db.get_conn().unwrap();
+        let expiring = GiftCode::create(
+            &EncodedGiftCode("expiring".to_string()),
+            value as i64,
+            Some("abcd"),
+            Some(1000),
+            &conn,
+        )
+        .unwrap();
+        assert!(GiftCode::list_expired(999, &conn).unwrap().is_empty());
+        assert_eq!(
+            GiftCode::list_expired(1000, &conn).unwrap(),
+            vec![expiring.clone()]
+        );
+        expiring.mark_expired(&conn).unwrap();
+        assert!(GiftCode::list_expired(2000, &conn).unwrap().is_empty());
     }
 }
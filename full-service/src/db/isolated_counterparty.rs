@@ -0,0 +1,157 @@
+// Copyright (c) 2020-2022 MobileCoin Inc.
+
+//! The Isolated Counterparty Model.
+//!
+//! Txos received with a sender memo from an isolated counterparty are kept out
+//! of the account's general pool when selecting inputs, and are only spent
+//! together with other txos from the same counterparty.
+
+use crate::db::{
+    models::{IsolatedCounterparty, NewIsolatedCounterparty},
+    Conn, WalletDbError,
+};
+use diesel::prelude::*;
+
+pub trait IsolatedCounterpartyModel {
+    /// Isolate a counterparty's payments to an account. Isolating a
+    /// counterparty twice has no further effect.
+    fn create(
+        account_id_hex: &str,
+        address_hash: &str,
+        now: i64,
+        conn: &Conn,
+    ) -> Result<IsolatedCounterparty, WalletDbError>;
+
+    /// List the counterparties isolated for an account, by address hash.
+    fn list_for_account(
+        account_id_hex: &str,
+        conn: &Conn,
+    ) -> Result<Vec<IsolatedCounterparty>, WalletDbError>;
+
+    /// Stop isolating a counterparty's payments to an account, if they were.
+    fn delete(account_id_hex: &str, address_hash: &str, conn: &Conn) -> Result<(), WalletDbError>;
+
+    /// Remove every isolated counterparty of an account.
+    fn delete_for_account(account_id_hex: &str, conn: &Conn) -> Result<(), WalletDbError>;
+}
+
+impl IsolatedCounterpartyModel for IsolatedCounterparty {
+    fn create(
+        account_id_hex: &str,
+        address_hash: &str,
+        now: i64,
+        conn: &Conn,
+    ) -> Result<IsolatedCounterparty, WalletDbError> {
+        use crate::db::schema::isolated_counterparties;
+
+        let existing = isolated_counterparties::table
+            .filter(isolated_counterparties::account_id_hex.eq(account_id_hex))
+            .filter(isolated_counterparties::address_hash.eq(address_hash))
+            .first::<IsolatedCounterparty>(conn)
+            .optional()?;
+        if let Some(existing) = existing {
+            return Ok(existing);
+        }
+
+        let new_counterparty = NewIsolatedCounterparty {
+            account_id_hex,
+            address_hash,
+            created_time: now,
+        };
+        diesel::insert_into(isolated_counterparties::table)
+            .values(&new_counterparty)
+            .execute(conn)?;
+
+        Ok(isolated_counterparties::table
+            .filter(isolated_counterparties::account_id_hex.eq(account_id_hex))
+            .filter(isolated_counterparties::address_hash.eq(address_hash))
+            .get_result(conn)?)
+    }
+
+    fn list_for_account(
+        account_id_hex: &str,
+        conn: &Conn,
+    ) -> Result<Vec<IsolatedCounterparty>, WalletDbError> {
+        use crate::db::schema::isolated_counterparties;
+
+        Ok(isolated_counterparties::table
+            .filter(isolated_counterparties::account_id_hex.eq(account_id_hex))
+            .order(isolated_counterparties::address_hash.asc())
+            .load(conn)?)
+    }
+
+    fn delete(account_id_hex: &str, address_hash: &str, conn: &Conn) -> Result<(), WalletDbError> {
+        use crate::db::schema::isolated_counterparties;
+
+        diesel::delete(
+            isolated_counterparties::table
+                .filter(isolated_counterparties::account_id_hex.eq(account_id_hex))
+                .filter(isolated_counterparties::address_hash.eq(address_hash)),
+        )
+        .execute(conn)?;
+        Ok(())
+    }
+
+    fn delete_for_account(account_id_hex: &str, conn: &Conn) -> Result<(), WalletDbError> {
+        use crate::db::schema::isolated_counterparties;
+
+        diesel::delete(
+            isolated_counterparties::table
+                .filter(isolated_counterparties::account_id_hex.eq(account_id_hex)),
+        )
+        .execute(conn)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::WalletDbTestContext;
+    use mc_common::logger::{test_with_logger, Logger};
+
+    #[test_with_logger]
+    fn test_isolated_counterparty_crud(logger: Logger) {
+        let db_test_context = WalletDbTestContext::default();
+        let wallet_db = db_test_context.get_db_instance(logger);
+        let conn = wallet_db.get_conn().unwrap();
+
+        let first = IsolatedCounterparty::create("abcd", "22", 1000, &conn).unwrap();
+        IsolatedCounterparty::create("abcd", "11", 1000, &conn).unwrap();
+        IsolatedCounterparty::create("efgh", "22", 1000, &conn).unwrap();
+
+        // Isolating again returns the existing record.
+        assert_eq!(
+            IsolatedCounterparty::create("abcd", "22", 2000, &conn).unwrap(),
+            first
+        );
+
+        let listed = IsolatedCounterparty::list_for_account("abcd", &conn).unwrap();
+        assert_eq!(
+            listed
+                .iter()
+                .map(|c| c.address_hash.as_str())
+                .collect::<Vec<_>>(),
+            vec!["11", "22"]
+        );
+
+        IsolatedCounterparty::delete("abcd", "22", &conn).unwrap();
+        assert_eq!(
+            IsolatedCounterparty::list_for_account("abcd", &conn)
+                .unwrap()
+                .len(),
+            1
+        );
+        assert_eq!(
+            IsolatedCounterparty::list_for_account("efgh", &conn)
+                .unwrap()
+                .len(),
+            1
+        );
+
+        IsolatedCounterparty::delete_for_account("abcd", &conn).unwrap();
+        assert!(IsolatedCounterparty::list_for_account("abcd", &conn)
+            .unwrap()
+            .is_empty());
+    }
+}
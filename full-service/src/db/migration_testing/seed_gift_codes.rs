@@ -1,5 +1,5 @@
 use crate::{
-    db::{account::AccountID, models::Account, WalletDb},
+    db::{account::AccountID, models::Account, Conn, WalletDb},
     service::{
         gift_code::{EncodedGiftCode, GiftCodeService, GiftCodeStatus},
         WalletService,
@@ -9,10 +9,6 @@ use crate::{
         manually_sync_account, MOB,
     },
 };
-use diesel::{
-    r2d2::{ConnectionManager, PooledConnection},
-    SqliteConnection,
-};
 use mc_account_keys::AccountKey;
 use mc_common::logger::Logger;
 use mc_connection_test_utils::MockBlockchainConnection;
@@ -28,7 +24,7 @@ pub struct SeedGiftCodesResult {
     claimed: EncodedGiftCode,
 }
 pub fn seed_gift_codes(
-    _conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+    _conn: &Conn,
     ledger_db: &mut LedgerDB,
     wallet_db: &WalletDb,
     service: &WalletService<MockBlockchainConnection<LedgerDB>, MockFogPubkeyResolver>,
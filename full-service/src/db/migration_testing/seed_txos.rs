@@ -4,17 +4,13 @@ use crate::{
         models::{Account, TransactionLog, Txo},
         transaction_log::TransactionLogModel,
         txo::TxoModel,
-        WalletDb,
+        Conn, WalletDb,
     },
     test_utils::{
         add_block_with_db_txos, add_block_with_tx_outs, create_test_minted_and_change_txos,
         create_test_txo_for_recipient, manually_sync_account, MOB,
     },
 };
-use diesel::{
-    r2d2::{ConnectionManager, PooledConnection},
-    SqliteConnection,
-};
 use mc_common::logger::Logger;
 use mc_crypto_rand::RngCore;
 use mc_ledger_db::LedgerDB;
@@ -23,7 +19,7 @@ use rand::{rngs::StdRng, SeedableRng};
 
 // create 1 spent, 1 change (minted), and 1 orphaned txo
 pub fn seed_txos(
-    _conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+    _conn: &Conn,
     ledger_db: &mut LedgerDB,
     wallet_db: &WalletDb,
     logger: &Logger,
@@ -78,10 +74,7 @@ pub fn seed_txos(
     );
 }
 
-pub fn test_txos(
-    account_id: AccountID,
-    conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
-) {
+pub fn test_txos(account_id: AccountID, conn: &Conn) {
     // validate expected txo states
     let txos = Txo::list_for_account(&account_id.to_string(), None, None, Some(0), &conn).unwrap();
     assert_eq!(txos.len(), 3);
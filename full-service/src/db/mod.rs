@@ -4,19 +4,37 @@
 //! is stored in LMDB).
 
 pub mod account;
+pub mod account_rotation;
+pub mod account_snapshot;
+pub mod account_sync_record;
 pub mod assigned_subaddress;
+pub mod continuity_check_in;
+pub mod db_size_sample;
 pub mod gift_code;
+pub mod isolated_counterparty;
 pub mod models;
+pub mod payment_request;
+pub mod quarantined_block;
+pub mod reporting_key;
+pub mod rpc_usage;
+pub mod scheduled_payment;
 pub mod schema;
+pub mod sweep_policy;
+pub mod synced_block;
 pub mod transaction_log;
+pub mod transaction_watch;
 pub mod txo;
 pub mod view_only_account;
 pub mod view_only_subaddress;
+pub mod view_only_sync_record;
 pub mod view_only_txo;
 mod wallet_db;
 mod wallet_db_error;
+pub mod wallet_event;
+pub mod wallet_settings;
+pub mod webhook;
 
-pub use wallet_db::{transaction, Conn, WalletDb};
+pub use wallet_db::{transaction, Conn, DbConnection, WalletDb, LATEST_MIGRATION_VERSION};
 pub use wallet_db_error::WalletDbError;
 
 #[cfg(any(test))]
@@ -3,8 +3,12 @@
 //! DB Models
 
 use super::schema::{
-    accounts, assigned_subaddresses, gift_codes, transaction_logs, transaction_txo_types, txos,
-    view_only_accounts, view_only_subaddresses, view_only_txos,
+    account_rotations, account_snapshots, account_sync_records, accounts, assigned_subaddresses,
+    continuity_check_ins, db_size_samples, gift_codes, isolated_counterparties, payment_requests,
+    quarantined_blocks, reporting_keys, rpc_usage, scheduled_payments, sweep_policies,
+    synced_blocks, transaction_logs, transaction_txo_types, transaction_watches, txos,
+    view_only_accounts, view_only_subaddresses, view_only_sync_records, view_only_txos,
+    wallet_events, wallet_settings, webhooks,
 };
 
 use serde::Serialize;
@@ -65,6 +69,170 @@ pub const TXO_USED_AS_OUTPUT: &str = "txo_used_as_output";
 /// A transaction output used as a change output of a new transaction.
 pub const TXO_USED_AS_CHANGE: &str = "txo_used_as_change";
 
+/// A sync request handed to the offline signer for a view-only account.
+pub const SYNC_RECORD_TYPE_REQUEST: &str = "sync_record_type_request";
+
+/// The offline signer's response to a view-only account sync request.
+pub const SYNC_RECORD_TYPE_RESPONSE: &str = "sync_record_type_response";
+
+/// An account rotation that is still moving funds to the new account.
+pub const ROTATION_STATUS_SWEEPING: &str = "rotation_status_sweeping";
+
+/// An account rotation that has moved all funds and archived the old account.
+pub const ROTATION_STATUS_COMPLETED: &str = "rotation_status_completed";
+
+/// An account rotation that stopped because a sweep transaction could not be
+/// built or submitted.
+pub const ROTATION_STATUS_FAILED: &str = "rotation_status_failed";
+
+/// A scheduled payment which is still to be made, or is recurring.
+pub const SCHEDULED_PAYMENT_STATUS_ACTIVE: &str = "scheduled_payment_status_active";
+
+/// A one-off scheduled payment which has been submitted.
+pub const SCHEDULED_PAYMENT_STATUS_COMPLETED: &str = "scheduled_payment_status_completed";
+
+/// A one-off scheduled payment whose transaction could not be built or
+/// submitted.
+pub const SCHEDULED_PAYMENT_STATUS_FAILED: &str = "scheduled_payment_status_failed";
+
+/// A scheduled payment which was cancelled before it completed.
+pub const SCHEDULED_PAYMENT_STATUS_CANCELLED: &str = "scheduled_payment_status_cancelled";
+
+/// A payment request which has not been paid.
+pub const PAYMENT_REQUEST_STATUS_PENDING: &str = "payment_request_status_pending";
+
+/// A payment request which was paid by its expiry block, or has no expiry.
+pub const PAYMENT_REQUEST_STATUS_PAID: &str = "payment_request_status_paid";
+
+/// A payment request which was paid after its expiry block.
+pub const PAYMENT_REQUEST_STATUS_LATE: &str = "payment_request_status_late";
+
+/// A watched transaction whose outputs have not yet all appeared in the ledger.
+pub const WATCH_STATUS_PENDING: &str = "watch_status_pending";
+
+/// A watched transaction whose outputs have all appeared in the ledger.
+pub const WATCH_STATUS_LANDED: &str = "watch_status_landed";
+
+/// A watched transaction that reached its tombstone block before landing.
+pub const WATCH_STATUS_EXPIRED: &str = "watch_status_expired";
+
+/// A txo which is not in the ledger, as far as the wallet can tell.
+pub const TXO_CONFIRMATION_STATUS_UNCONFIRMED: &str = "txo_confirmation_status_unconfirmed";
+
+/// A txo in a block which is not yet buried under the confirmation depth.
+pub const TXO_CONFIRMATION_STATUS_CONFIRMING: &str = "txo_confirmation_status_confirming";
+
+/// A txo in a block buried at least as deep as the confirmation depth.
+pub const TXO_CONFIRMATION_STATUS_CONFIRMED: &str = "txo_confirmation_status_confirmed";
+
+/// Event emitted when a watched transaction lands in the ledger.
+pub const EVENT_TYPE_TRANSACTION_LANDED: &str = "transaction_landed";
+
+/// Event emitted when a watched transaction reaches its tombstone block before
+/// landing.
+pub const EVENT_TYPE_TRANSACTION_EXPIRED: &str = "transaction_expired";
+
+/// Event emitted as an imported account scans the blocks which were in the
+/// ledger when it was imported.
+pub const EVENT_TYPE_ACCOUNT_IMPORT_PROGRESS: &str = "account_import_progress";
+
+/// Event emitted when an imported account has caught up with the ledger as it
+/// was at import.
+pub const EVENT_TYPE_ACCOUNT_IMPORT_COMPLETED: &str = "account_import_completed";
+
+/// Event emitted when an import is cancelled and the account removed.
+pub const EVENT_TYPE_ACCOUNT_IMPORT_CANCELLED: &str = "account_import_cancelled";
+
+/// Version of the wallet event payload schema. Bump this whenever the payload
+/// of an existing event type changes in a way consumers would notice, so that
+/// they can tell old events from new when replaying the log.
+pub const WALLET_EVENT_SCHEMA_VERSION: i32 = 1;
+
+/// Event emitted when spending is disabled wallet-wide.
+pub const EVENT_TYPE_SPENDING_DISABLED: &str = "spending_disabled";
+
+/// Event emitted when spending is re-enabled with the admin key.
+pub const EVENT_TYPE_SPENDING_ENABLED: &str = "spending_enabled";
+
+/// Event emitted when a received txo's sender memo appears to impersonate an
+/// address the account has sent to.
+pub const EVENT_TYPE_SUSPECTED_ADDRESS_POISONING: &str = "suspected_address_poisoning";
+
+/// Event emitted when a sweep policy submits a transaction moving an
+/// account's excess balance to its destination address.
+pub const EVENT_TYPE_AUTO_SWEEP_SUBMITTED: &str = "auto_sweep_submitted";
+
+/// Event emitted when a sweep policy was due but its transaction could not be
+/// built or submitted.
+pub const EVENT_TYPE_AUTO_SWEEP_FAILED: &str = "auto_sweep_failed";
+
+/// Event emitted when a scheduled payment submits a transaction.
+pub const EVENT_TYPE_SCHEDULED_PAYMENT_SUBMITTED: &str = "scheduled_payment_submitted";
+
+/// Event emitted when a scheduled payment was due but its transaction could
+/// not be built or submitted.
+pub const EVENT_TYPE_SCHEDULED_PAYMENT_FAILED: &str = "scheduled_payment_failed";
+
+/// Event emitted when a received txo's memo pays one of the account's payment
+/// requests, whether on time or late.
+pub const EVENT_TYPE_PAYMENT_REQUEST_PAID: &str = "payment_request_paid";
+
+/// Event emitted when wallet-wide settings are changed.
+pub const EVENT_TYPE_SETTINGS_UPDATED: &str = "settings_updated";
+
+/// Event emitted when the sync first finds a block it can't process, and stops
+/// scanning accounts at that block.
+pub const EVENT_TYPE_BLOCK_QUARANTINED: &str = "block_quarantined";
+
+/// Event emitted when a quarantined block can be processed again, for example
+/// after an upgrade, and the sync carries on past it.
+pub const EVENT_TYPE_BLOCK_QUARANTINE_RELEASED: &str = "block_quarantine_released";
+
+/// Event emitted when a quarantined block is added to the skip list, so that
+/// the sync passes over it.
+pub const EVENT_TYPE_BLOCK_SKIPPED: &str = "block_skipped";
+
+/// Event emitted when the network's advertised block version or minimum fees
+/// change how ready this build is for a network upgrade.
+pub const EVENT_TYPE_UPGRADE_READINESS_CHANGED: &str = "upgrade_readiness_changed";
+
+/// Event emitted when a transaction sent from this wallet lands in the ledger
+/// and its transaction log is marked succeeded.
+pub const EVENT_TYPE_TRANSACTION_SUCCEEDED: &str = "transaction_succeeded";
+
+/// Event emitted when a transaction sent from this wallet passes its tombstone
+/// block without landing, and its transaction log is marked failed.
+pub const EVENT_TYPE_TRANSACTION_FAILED: &str = "transaction_failed";
+
+/// Event emitted when a sent transaction which expired without landing is
+/// rebuilt with a fresh tombstone block and submitted again.
+pub const EVENT_TYPE_TRANSACTION_RESUBMITTED: &str = "transaction_resubmitted";
+
+/// Event emitted when the sync credits a newly received txo to an account.
+pub const EVENT_TYPE_TXO_RECEIVED: &str = "txo_received";
+
+/// Event emitted when the wallet database is encrypted, or its password is
+/// changed.
+pub const EVENT_TYPE_DATABASE_PASSWORD_CHANGED: &str = "database_password_changed";
+
+/// Event emitted when a consensus peer attests with a different enclave
+/// measurement than it last attested with.
+pub const EVENT_TYPE_PEER_MEASUREMENT_CHANGED: &str = "peer_measurement_changed";
+
+/// Event emitted when a consensus peer fails attestation, having passed it
+/// before.
+pub const EVENT_TYPE_PEER_ATTESTATION_FAILED: &str = "peer_attestation_failed";
+
+/// Event emitted when the ledger no longer has blocks the sync has scanned,
+/// and accounts are rolled back to rescan from where it diverged.
+pub const EVENT_TYPE_LEDGER_REORG_DETECTED: &str = "ledger_reorg_detected";
+
+/// Event emitted when the wallet enters maintenance mode.
+pub const EVENT_TYPE_MAINTENANCE_STARTED: &str = "maintenance_started";
+
+/// Event emitted when the wallet leaves maintenance mode.
+pub const EVENT_TYPE_MAINTENANCE_ENDED: &str = "maintenance_ended";
+
 /// An Account entity.
 ///
 /// Contains the account private keys, subaddress configuration, and ...
@@ -103,6 +271,13 @@ pub struct Account {
     pub name: String, /* empty string for nullable */
     /// Fog enabled address
     pub fog_enabled: bool,
+    /// The token that transactions and balances use when none is specified.
+    pub preferred_token_id: i64,
+    /// Whether the account has been retired, e.g. after rotating its funds
+    /// to a new account.
+    pub archived: bool,
+    /// Whether the sync thread skips this account until it is resumed.
+    pub sync_paused: bool,
 }
 
 /// A View Only Account entity.
@@ -135,6 +310,9 @@ pub struct ViewOnlyAccount {
     pub import_block_index: i64,
     /// Name of this account.
     pub name: String, /* empty string for nullable */
+    /// Whether the account's spend key is on a hardware wallet, which signs
+    /// its transactions through the configured transaction signer.
+    pub hardware_backed: bool,
 }
 
 /// A structure that can be inserted to create a new entity in the
@@ -209,6 +387,22 @@ pub struct Txo {
     pub recipient_public_address_b58: String,
     pub minted_account_id_hex: Option<String>,
     pub received_account_id_hex: Option<String>,
+    /// Index of the TxOut in the ledger, cached once it has been looked up.
+    pub ledger_txo_index: Option<i64>,
+    /// The address of a contact which this Txo's sender memo appears to
+    /// impersonate, if it was flagged as a possible address poisoning attempt.
+    pub mimicked_address_b58: Option<String>,
+    /// The hex-encoded address hash of the sender, from this Txo's
+    /// Recoverable Transaction History sender memo.
+    pub sender_address_hash: Option<String>,
+    /// The hex-encoded address hash of the recipient of the transaction which
+    /// minted this Txo, from the destination memo written to its change.
+    pub destination_address_hash: Option<String>,
+    /// Whether the Txo is kept out of transactions until it is unfrozen.
+    pub frozen: bool,
+    /// The label the wallet's txo classifier gave this Txo when it was
+    /// received, such as "payroll", if it gave one.
+    pub classification: Option<String>,
 }
 
 /// A structure that can be inserted to create a new entity in the `txos` table.
@@ -373,6 +567,8 @@ pub struct TransactionLog {
     // Directions: sent, received
     pub direction: String,
     pub tx: Option<Vec<u8>>,
+    /// The transaction submitted in place of this one after it expired.
+    pub resubmitted_transaction_id_hex: Option<String>,
 }
 
 /// A structure that can be inserted to create a new TransactionLog entity.
@@ -422,6 +618,21 @@ pub struct GiftCode {
     pub id: i32,
     pub gift_code_b58: String,
     pub value: i64,
+    /// The account which funded the gift code, if it was submitted by this
+    /// wallet.
+    pub funding_account_id_hex: Option<String>,
+    /// The account which claimed the gift code, if it was claimed by this
+    /// wallet.
+    pub claimer_account_id_hex: Option<String>,
+    /// The log under which the claimer's account records the claimed funds.
+    pub claim_transaction_log_id: Option<String>,
+    pub claimed_time: Option<i64>,
+    /// The time after which an unclaimed gift code is swept back to the
+    /// account which funded it, if it was made as a claim link.
+    pub expiry_time: Option<i64>,
+    /// Whether the gift code's expiry has been dealt with, either by sweeping
+    /// it back or by finding it already claimed.
+    pub expired: bool,
 }
 
 #[derive(Insertable)]
@@ -429,4 +640,478 @@ pub struct GiftCode {
 pub struct NewGiftCode<'a> {
     pub gift_code_b58: &'a str,
     pub value: i64,
+    pub funding_account_id_hex: Option<&'a str>,
+    pub expiry_time: Option<i64>,
+}
+
+/// A transaction not built by this wallet, whose confirmation is being
+/// tracked.
+#[derive(Clone, Serialize, Identifiable, Queryable, PartialEq, Debug)]
+#[table_name = "transaction_watches"]
+#[primary_key(id)]
+pub struct TransactionWatch {
+    pub id: i32,
+    pub watch_id_hex: String,
+    /// Serialized public keys of the transaction's outputs.
+    pub tx_public_keys: Vec<u8>,
+    pub tombstone_block_index: Option<i64>,
+    // Statuses: pending, landed, expired
+    pub status: String,
+    /// The block in which the transaction landed, or at which it expired.
+    pub finalized_block_index: Option<i64>,
+    pub comment: String,
+}
+
+#[derive(Insertable)]
+#[table_name = "transaction_watches"]
+pub struct NewTransactionWatch<'a> {
+    pub watch_id_hex: &'a str,
+    pub tx_public_keys: &'a [u8],
+    pub tombstone_block_index: Option<i64>,
+    pub status: &'a str,
+    pub comment: &'a str,
+}
+
+/// A notable change in wallet state, recorded so that clients can follow
+/// along.
+#[derive(Clone, Serialize, Identifiable, Queryable, PartialEq, Debug)]
+#[table_name = "wallet_events"]
+#[primary_key(id)]
+pub struct WalletEvent {
+    pub id: i32,
+    pub event_type: String,
+    /// JSON payload describing the event.
+    pub payload: String,
+    /// Version of the payload schema the event was recorded with.
+    pub schema_version: i32,
+}
+
+#[derive(Insertable)]
+#[table_name = "wallet_events"]
+pub struct NewWalletEvent<'a> {
+    pub event_type: &'a str,
+    pub payload: &'a str,
+    pub schema_version: i32,
+}
+
+/// The wallet's reporting key, used only to sign exports so that their
+/// recipients can check they weren't modified.
+#[derive(Clone, Identifiable, Queryable, PartialEq, Debug)]
+#[table_name = "reporting_keys"]
+#[primary_key(id)]
+pub struct ReportingKey {
+    pub id: i32,
+    /// The Ed25519 private key.
+    pub private_key: Vec<u8>,
+}
+
+#[derive(Insertable)]
+#[table_name = "reporting_keys"]
+pub struct NewReportingKey<'a> {
+    pub private_key: &'a [u8],
+}
+
+/// A record of a sync request handed to the offline signer for a view-only
+/// account, or of the signer's response, kept as an audit trail.
+#[derive(Clone, Serialize, Identifiable, Queryable, PartialEq, Debug)]
+#[table_name = "view_only_sync_records"]
+#[primary_key(id)]
+pub struct ViewOnlySyncRecord {
+    pub id: i32,
+    pub view_only_account_id_hex: String,
+    // Types: sync_record_type_request, sync_record_type_response
+    pub record_type: String,
+    /// Hex-encoded digest of the txos in a request, or of the txo ids and key
+    /// images in a response.
+    pub payload_hash: String,
+    pub txo_count: i32,
+    /// For a response, the request it answered, if one was outstanding.
+    pub request_record_id: Option<i32>,
+    pub created_time: i64,
+}
+
+#[derive(Insertable)]
+#[table_name = "view_only_sync_records"]
+pub struct NewViewOnlySyncRecord<'a> {
+    pub view_only_account_id_hex: &'a str,
+    pub record_type: &'a str,
+    pub payload_hash: &'a str,
+    pub txo_count: i32,
+    pub request_record_id: Option<i32>,
+    pub created_time: i64,
+}
+
+/// The progress of moving an account's funds to a new account.
+#[derive(Clone, Serialize, Identifiable, Queryable, PartialEq, Debug)]
+#[table_name = "account_rotations"]
+#[primary_key(id)]
+pub struct AccountRotation {
+    pub id: i32,
+    /// The account being retired. An account can only be rotated once.
+    pub old_account_id_hex: String,
+    /// The account receiving the funds.
+    pub new_account_id_hex: String,
+    // Statuses: sweeping, completed, failed
+    pub status: String,
+    /// JSON array of the ids of the sweep transactions submitted so far.
+    pub sweep_transaction_log_ids: String,
+    pub failure_message: Option<String>,
+}
+
+#[derive(Insertable)]
+#[table_name = "account_rotations"]
+pub struct NewAccountRotation<'a> {
+    pub old_account_id_hex: &'a str,
+    pub new_account_id_hex: &'a str,
+    pub status: &'a str,
+}
+
+/// A policy which periodically sweeps an account's balance above a threshold
+/// to another address, such as a cold wallet.
+#[derive(Clone, Serialize, Identifiable, Queryable, PartialEq, Debug)]
+#[table_name = "sweep_policies"]
+#[primary_key(id)]
+pub struct SweepPolicy {
+    pub id: i32,
+    /// The account being swept. An account has at most one policy.
+    pub account_id_hex: String,
+    /// The b58-encoded address receiving the swept funds.
+    pub destination_address: String,
+    pub token_id: i64,
+    /// The balance left in the account after a sweep.
+    pub threshold: i64,
+    pub interval_seconds: i64,
+    /// The time at which the policy is next due to run.
+    pub next_run_time: i64,
+    pub last_run_time: Option<i64>,
+    /// The transaction submitted by the most recent sweep, if any.
+    pub last_transaction_log_id: Option<String>,
+    /// Why the most recent run failed, cleared when a run succeeds.
+    pub last_failure_message: Option<String>,
+}
+
+#[derive(Insertable)]
+#[table_name = "sweep_policies"]
+pub struct NewSweepPolicy<'a> {
+    pub account_id_hex: &'a str,
+    pub destination_address: &'a str,
+    pub token_id: i64,
+    pub threshold: i64,
+    pub interval_seconds: i64,
+    pub next_run_time: i64,
+}
+
+/// A wallet-wide setting, stored as a string and parsed by the settings model.
+#[derive(Clone, Serialize, Identifiable, Queryable, PartialEq, Debug)]
+#[table_name = "wallet_settings"]
+#[primary_key(name)]
+pub struct WalletSetting {
+    pub name: String,
+    pub value: String,
+}
+
+#[derive(Insertable)]
+#[table_name = "wallet_settings"]
+pub struct NewWalletSetting<'a> {
+    pub name: &'a str,
+    pub value: &'a str,
+}
+
+/// A ledger block which the sync could not process.
+#[derive(Clone, Serialize, Identifiable, Queryable, PartialEq, Debug)]
+#[table_name = "quarantined_blocks"]
+#[primary_key(id)]
+pub struct QuarantinedBlock {
+    pub id: i32,
+    pub block_index: i64,
+    /// Why the block could not be processed, when it was first found.
+    pub reason: String,
+    pub quarantined_time: i64,
+    /// Whether the block is on the skip list, and is passed over by the sync.
+    pub skipped: bool,
+}
+
+#[derive(Insertable)]
+#[table_name = "quarantined_blocks"]
+pub struct NewQuarantinedBlock<'a> {
+    pub block_index: i64,
+    pub reason: &'a str,
+    pub quarantined_time: i64,
+}
+
+/// A URL which is sent a POST request for each transaction outcome and
+/// received txo.
+#[derive(Clone, Serialize, Identifiable, Queryable, PartialEq, Debug)]
+#[table_name = "webhooks"]
+#[primary_key(id)]
+pub struct Webhook {
+    pub id: i32,
+    pub url: String,
+    /// The account whose events are delivered, or all accounts if not set.
+    pub account_id_hex: Option<String>,
+    /// The wallet event up to which delivery has been attempted.
+    pub last_event_id: i32,
+    /// How many times delivery of the next event has failed.
+    pub failed_attempts: i32,
+    pub last_failure_message: Option<String>,
+    pub created_time: i64,
+}
+
+#[derive(Insertable)]
+#[table_name = "webhooks"]
+pub struct NewWebhook<'a> {
+    pub url: &'a str,
+    pub account_id_hex: Option<&'a str>,
+    pub last_event_id: i32,
+    pub created_time: i64,
+}
+
+/// A chunk of blocks scanned for an account by the sync thread, and how long
+/// it took.
+#[derive(Clone, Serialize, Identifiable, Queryable, PartialEq, Debug)]
+#[table_name = "account_sync_records"]
+#[primary_key(id)]
+pub struct AccountSyncRecord {
+    pub id: i32,
+    pub account_id_hex: String,
+    pub start_block_index: i64,
+    /// The last block scanned.
+    pub end_block_index: i64,
+    pub txos_received: i32,
+    pub txos_spent: i32,
+    pub duration_ms: i64,
+    pub created_time: i64,
+}
+
+#[derive(Insertable)]
+#[table_name = "account_sync_records"]
+pub struct NewAccountSyncRecord<'a> {
+    pub account_id_hex: &'a str,
+    pub start_block_index: i64,
+    pub end_block_index: i64,
+    pub txos_received: i32,
+    pub txos_spent: i32,
+    pub duration_ms: i64,
+    pub created_time: i64,
+}
+
+/// The calls made to an API method within an hour.
+#[derive(Clone, Serialize, Identifiable, Queryable, PartialEq, Debug)]
+#[table_name = "rpc_usage"]
+#[primary_key(id)]
+pub struct RpcUsage {
+    pub id: i32,
+    /// The start of the hour, in seconds since the epoch.
+    pub hour_start: i64,
+    pub method: String,
+    pub calls: i64,
+    /// The calls which returned an error.
+    pub errors: i64,
+    /// The time taken to handle all of the calls.
+    pub total_duration_ms: i64,
+}
+
+#[derive(Insertable)]
+#[table_name = "rpc_usage"]
+pub struct NewRpcUsage<'a> {
+    pub hour_start: i64,
+    pub method: &'a str,
+    pub calls: i64,
+    pub errors: i64,
+    pub total_duration_ms: i64,
+}
+
+/// The sizes of the wallet and ledger databases at a point in time.
+#[derive(Clone, Serialize, Identifiable, Queryable, PartialEq, Debug)]
+#[table_name = "db_size_samples"]
+#[primary_key(id)]
+pub struct DbSizeSample {
+    pub id: i32,
+    /// None if the wallet database is not on disk.
+    pub wallet_db_bytes: Option<i64>,
+    /// None if the ledger database is not on disk.
+    pub ledger_db_bytes: Option<i64>,
+    pub created_time: i64,
+}
+
+#[derive(Insertable)]
+#[table_name = "db_size_samples"]
+pub struct NewDbSizeSample {
+    pub wallet_db_bytes: Option<i64>,
+    pub ledger_db_bytes: Option<i64>,
+    pub created_time: i64,
+}
+
+/// A time the operator confirmed they still have access to the wallet.
+#[derive(Clone, Serialize, Identifiable, Queryable, PartialEq, Debug)]
+#[table_name = "continuity_check_ins"]
+#[primary_key(id)]
+pub struct ContinuityCheckIn {
+    pub id: i32,
+    pub created_time: i64,
+    /// When a continuity export was written because no check-in followed
+    /// this one in time.
+    pub exported_time: Option<i64>,
+    /// Where that export was written.
+    pub export_path: Option<String>,
+}
+
+#[derive(Insertable)]
+#[table_name = "continuity_check_ins"]
+pub struct NewContinuityCheckIn {
+    pub created_time: i64,
+}
+
+/// A payment to be made once the ledger reaches a block height, or every
+/// interval.
+#[derive(Clone, Serialize, Identifiable, Queryable, PartialEq, Debug)]
+#[table_name = "scheduled_payments"]
+#[primary_key(id)]
+pub struct ScheduledPayment {
+    pub id: i32,
+    /// The account making the payment.
+    pub account_id_hex: String,
+    /// The b58-encoded address being paid.
+    pub recipient_address: String,
+    pub value: i64,
+    pub token_id: i64,
+    /// How often a recurring payment is made.
+    pub interval_seconds: Option<i64>,
+    /// The number of blocks the ledger must have before a one-off payment is
+    /// made.
+    pub block_height: Option<i64>,
+    /// The time at which the payment is next due, once any block height is
+    /// reached.
+    pub next_run_time: i64,
+    // Statuses: active, completed, failed, cancelled
+    pub status: String,
+    /// The comment recorded on the payments' transaction logs.
+    pub comment: String,
+    pub payments_submitted: i32,
+    pub last_run_time: Option<i64>,
+    /// The transaction submitted by the most recent payment, if any.
+    pub last_transaction_log_id: Option<String>,
+    /// Why the most recent run failed, cleared when a run succeeds.
+    pub last_failure_message: Option<String>,
+    pub created_time: i64,
+}
+
+#[derive(Insertable)]
+#[table_name = "scheduled_payments"]
+pub struct NewScheduledPayment<'a> {
+    pub account_id_hex: &'a str,
+    pub recipient_address: &'a str,
+    pub value: i64,
+    pub token_id: i64,
+    pub interval_seconds: Option<i64>,
+    pub block_height: Option<i64>,
+    pub next_run_time: i64,
+    pub status: &'a str,
+    pub comment: &'a str,
+    pub created_time: i64,
+}
+
+/// A request for payment to one of an account's subaddresses, which is matched
+/// against the payment request ids in received txos' memos.
+#[derive(Clone, Serialize, Identifiable, Queryable, PartialEq, Debug)]
+#[table_name = "payment_requests"]
+#[primary_key(id)]
+pub struct PaymentRequest {
+    /// The payment request id senders put in their memos.
+    pub id: i32,
+    /// The account being paid.
+    pub account_id_hex: String,
+    pub subaddress_index: i64,
+    pub value: i64,
+    pub token_id: i64,
+    pub memo: String,
+    /// The last block in which a payment is on time.
+    pub expiry_block_index: Option<i64>,
+    // Statuses: pending, paid, late
+    pub status: String,
+    /// The received txo which paid the request.
+    pub paid_txo_id_hex: Option<String>,
+    pub paid_value: Option<i64>,
+    pub paid_block_index: Option<i64>,
+    pub created_time: i64,
+}
+
+#[derive(Insertable)]
+#[table_name = "payment_requests"]
+pub struct NewPaymentRequest<'a> {
+    pub account_id_hex: &'a str,
+    pub subaddress_index: i64,
+    pub value: i64,
+    pub token_id: i64,
+    pub memo: &'a str,
+    pub expiry_block_index: Option<i64>,
+    pub status: &'a str,
+    pub created_time: i64,
+}
+
+/// A counterparty whose payments to an account are only spent with each other,
+/// so that transactions don't link them to the account's other funds.
+#[derive(Clone, Serialize, Identifiable, Queryable, PartialEq, Debug)]
+#[table_name = "isolated_counterparties"]
+#[primary_key(id)]
+pub struct IsolatedCounterparty {
+    pub id: i32,
+    pub account_id_hex: String,
+    /// The hex-encoded short address hash the counterparty writes to the
+    /// sender memos of its payments.
+    pub address_hash: String,
+    pub created_time: i64,
+}
+
+#[derive(Insertable)]
+#[table_name = "isolated_counterparties"]
+pub struct NewIsolatedCounterparty<'a> {
+    pub account_id_hex: &'a str,
+    pub address_hash: &'a str,
+    pub created_time: i64,
+}
+
+/// The id of a ledger block the sync has seen, so that it can tell when the
+/// ledger no longer has that block.
+#[derive(Clone, Serialize, Identifiable, Queryable, PartialEq, Debug)]
+#[table_name = "synced_blocks"]
+#[primary_key(id)]
+pub struct SyncedBlock {
+    pub id: i32,
+    pub block_index: i64,
+    pub block_id_hex: String,
+}
+
+#[derive(Insertable)]
+#[table_name = "synced_blocks"]
+pub struct NewSyncedBlock<'a> {
+    pub block_index: i64,
+    pub block_id_hex: &'a str,
+}
+
+/// An account's balance of a token as of a block, with how many of its txos
+/// of the token were unspent and spent.
+#[derive(Clone, Serialize, Identifiable, Queryable, PartialEq, Debug)]
+#[table_name = "account_snapshots"]
+#[primary_key(id)]
+pub struct AccountSnapshot {
+    pub id: i32,
+    pub account_id_hex: String,
+    pub block_index: i64,
+    pub token_id: i64,
+    /// The sum of the unspent txos' values, which may not fit in a BigInt.
+    pub unspent_value: String,
+    pub unspent_txo_count: i64,
+    pub spent_txo_count: i64,
+}
+
+#[derive(Insertable)]
+#[table_name = "account_snapshots"]
+pub struct NewAccountSnapshot<'a> {
+    pub account_id_hex: &'a str,
+    pub block_index: i64,
+    pub token_id: i64,
+    pub unspent_value: &'a str,
+    pub unspent_txo_count: i64,
+    pub spent_txo_count: i64,
 }
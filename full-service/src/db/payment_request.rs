@@ -0,0 +1,239 @@
+// Copyright (c) 2020-2022 MobileCoin Inc.
+
+//! The Payment Request Model.
+//!
+//! A payment request is paid by the first received txo whose memo carries its
+//! id. Payments which land after the request's expiry block are still
+//! recorded, but marked late, so that the account's owner can decide whether
+//! to accept or refund them.
+
+use crate::db::{
+    models::{
+        NewPaymentRequest, PaymentRequest, PAYMENT_REQUEST_STATUS_LATE,
+        PAYMENT_REQUEST_STATUS_PAID, PAYMENT_REQUEST_STATUS_PENDING,
+    },
+    Conn, WalletDbError,
+};
+use diesel::prelude::*;
+
+pub trait PaymentRequestModel {
+    /// Create a payment request for one of an account's subaddresses.
+    #[allow(clippy::too_many_arguments)]
+    fn create(
+        account_id_hex: &str,
+        subaddress_index: u64,
+        value: u64,
+        token_id: u64,
+        memo: &str,
+        expiry_block_index: Option<u64>,
+        now: i64,
+        conn: &Conn,
+    ) -> Result<PaymentRequest, WalletDbError>;
+
+    /// Get a payment request by its id.
+    fn get(id: i32, conn: &Conn) -> Result<PaymentRequest, WalletDbError>;
+
+    /// List the payment requests of an account, in the order they were
+    /// created.
+    fn list(account_id_hex: &str, conn: &Conn) -> Result<Vec<PaymentRequest>, WalletDbError>;
+
+    /// Get an account's pending payment request with the given id, if it has
+    /// one.
+    fn get_pending(
+        account_id_hex: &str,
+        id: u64,
+        conn: &Conn,
+    ) -> Result<Option<PaymentRequest>, WalletDbError>;
+
+    /// Record the txo which paid the request, marking the request late if it
+    /// landed after the expiry block.
+    ///
+    /// Returns the request's new status.
+    fn record_payment(
+        &self,
+        txo_id_hex: &str,
+        value: u64,
+        block_index: u64,
+        conn: &Conn,
+    ) -> Result<&'static str, WalletDbError>;
+
+    /// Remove the payment requests of an account.
+    fn delete_for_account(account_id_hex: &str, conn: &Conn) -> Result<(), WalletDbError>;
+}
+
+impl PaymentRequestModel for PaymentRequest {
+    fn create(
+        account_id_hex: &str,
+        subaddress_index: u64,
+        value: u64,
+        token_id: u64,
+        memo: &str,
+        expiry_block_index: Option<u64>,
+        now: i64,
+        conn: &Conn,
+    ) -> Result<PaymentRequest, WalletDbError> {
+        use crate::db::schema::payment_requests;
+
+        let new_request = NewPaymentRequest {
+            account_id_hex,
+            subaddress_index: subaddress_index as i64,
+            value: value as i64,
+            token_id: token_id as i64,
+            memo,
+            expiry_block_index: expiry_block_index.map(|index| index as i64),
+            status: PAYMENT_REQUEST_STATUS_PENDING,
+            created_time: now,
+        };
+        diesel::insert_into(payment_requests::table)
+            .values(&new_request)
+            .execute(conn)?;
+
+        Ok(payment_requests::table
+            .order(payment_requests::id.desc())
+            .first::<PaymentRequest>(conn)?)
+    }
+
+    fn get(id: i32, conn: &Conn) -> Result<PaymentRequest, WalletDbError> {
+        use crate::db::schema::payment_requests;
+
+        match payment_requests::table
+            .filter(payment_requests::id.eq(id))
+            .get_result::<PaymentRequest>(conn)
+        {
+            Ok(request) => Ok(request),
+            // Match on NotFound to get a more informative NotFound Error
+            Err(diesel::result::Error::NotFound) => Err(WalletDbError::PaymentRequestNotFound(id)),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn list(account_id_hex: &str, conn: &Conn) -> Result<Vec<PaymentRequest>, WalletDbError> {
+        use crate::db::schema::payment_requests;
+
+        Ok(payment_requests::table
+            .filter(payment_requests::account_id_hex.eq(account_id_hex))
+            .order(payment_requests::id.asc())
+            .load(conn)?)
+    }
+
+    fn get_pending(
+        account_id_hex: &str,
+        id: u64,
+        conn: &Conn,
+    ) -> Result<Option<PaymentRequest>, WalletDbError> {
+        use crate::db::schema::payment_requests;
+
+        // Memos carry a u64, which can't be one of ours if it doesn't fit.
+        if id > i32::MAX as u64 {
+            return Ok(None);
+        }
+        Ok(payment_requests::table
+            .filter(payment_requests::id.eq(id as i32))
+            .filter(payment_requests::account_id_hex.eq(account_id_hex))
+            .filter(payment_requests::status.eq(PAYMENT_REQUEST_STATUS_PENDING))
+            .first::<PaymentRequest>(conn)
+            .optional()?)
+    }
+
+    fn record_payment(
+        &self,
+        txo_id_hex: &str,
+        value: u64,
+        block_index: u64,
+        conn: &Conn,
+    ) -> Result<&'static str, WalletDbError> {
+        use crate::db::schema::payment_requests;
+
+        let status = match self.expiry_block_index {
+            Some(expiry_block_index) if block_index as i64 > expiry_block_index => {
+                PAYMENT_REQUEST_STATUS_LATE
+            }
+            _ => PAYMENT_REQUEST_STATUS_PAID,
+        };
+        diesel::update(payment_requests::table.filter(payment_requests::id.eq(self.id)))
+            .set((
+                payment_requests::status.eq(status),
+                payment_requests::paid_txo_id_hex.eq(Some(txo_id_hex)),
+                payment_requests::paid_value.eq(Some(value as i64)),
+                payment_requests::paid_block_index.eq(Some(block_index as i64)),
+            ))
+            .execute(conn)?;
+        Ok(status)
+    }
+
+    fn delete_for_account(account_id_hex: &str, conn: &Conn) -> Result<(), WalletDbError> {
+        use crate::db::schema::payment_requests;
+
+        diesel::delete(
+            payment_requests::table.filter(payment_requests::account_id_hex.eq(account_id_hex)),
+        )
+        .execute(conn)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::WalletDbTestContext;
+    use mc_common::logger::{test_with_logger, Logger};
+
+    #[test_with_logger]
+    fn test_payment_request_crud(logger: Logger) {
+        let db_test_context = WalletDbTestContext::default();
+        let wallet_db = db_test_context.get_db_instance(logger);
+        let conn = wallet_db.get_conn().unwrap();
+
+        let on_time = PaymentRequest::create("abcd", 0, 100, 0, "", Some(20), 1000, &conn).unwrap();
+        let late = PaymentRequest::create("abcd", 1, 50, 0, "inv", Some(20), 1000, &conn).unwrap();
+        let no_expiry = PaymentRequest::create("abcd", 0, 10, 0, "", None, 1000, &conn).unwrap();
+        PaymentRequest::create("ef01", 0, 1, 0, "", None, 1000, &conn).unwrap();
+        assert_eq!(on_time.status, PAYMENT_REQUEST_STATUS_PENDING);
+        assert_eq!(
+            PaymentRequest::list("abcd", &conn).unwrap(),
+            vec![on_time.clone(), late.clone(), no_expiry.clone()]
+        );
+
+        // Requests are only matched for the account which made them.
+        assert!(
+            PaymentRequest::get_pending("ef01", on_time.id as u64, &conn)
+                .unwrap()
+                .is_none()
+        );
+        assert!(PaymentRequest::get_pending("abcd", u64::MAX, &conn)
+            .unwrap()
+            .is_none());
+
+        // A payment in the expiry block is on time, and one after it is late.
+        assert_eq!(
+            on_time.record_payment("aaaa", 100, 20, &conn).unwrap(),
+            PAYMENT_REQUEST_STATUS_PAID
+        );
+        assert_eq!(
+            late.record_payment("bbbb", 50, 21, &conn).unwrap(),
+            PAYMENT_REQUEST_STATUS_LATE
+        );
+        assert_eq!(
+            no_expiry.record_payment("cccc", 10, 1000, &conn).unwrap(),
+            PAYMENT_REQUEST_STATUS_PAID
+        );
+        let late = PaymentRequest::get(late.id, &conn).unwrap();
+        assert_eq!(late.status, PAYMENT_REQUEST_STATUS_LATE);
+        assert_eq!(late.paid_txo_id_hex, Some("bbbb".to_string()));
+        assert_eq!(late.paid_value, Some(50));
+        assert_eq!(late.paid_block_index, Some(21));
+
+        // Paid requests aren't matched again.
+        assert!(
+            PaymentRequest::get_pending("abcd", on_time.id as u64, &conn)
+                .unwrap()
+                .is_none()
+        );
+
+        PaymentRequest::delete_for_account("abcd", &conn).unwrap();
+        match PaymentRequest::get(on_time.id, &conn) {
+            Err(WalletDbError::PaymentRequestNotFound(_)) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,195 @@
+// Copyright (c) 2020-2022 MobileCoin Inc.
+
+//! The Quarantined Block Model.
+//!
+//! A block which the sync can't process, for example one with a block version
+//! newer than this build supports, is quarantined. Accounts are scanned up to
+//! the block and then wait, rather than the whole sync failing, until either
+//! the block can be processed or an operator adds it to the skip list.
+
+use crate::db::{
+    models::{
+        NewQuarantinedBlock, QuarantinedBlock, WalletEvent, EVENT_TYPE_BLOCK_QUARANTINED,
+        EVENT_TYPE_BLOCK_QUARANTINE_RELEASED, EVENT_TYPE_BLOCK_SKIPPED,
+    },
+    wallet_event::WalletEventModel,
+    Conn, WalletDbError,
+};
+use chrono::Utc;
+use diesel::prelude::*;
+use serde_json::json;
+
+pub trait QuarantinedBlockModel {
+    /// Quarantine a block, recording an event, unless it already is.
+    ///
+    /// Returns the quarantined block, and whether it was newly quarantined.
+    fn quarantine(
+        block_index: u64,
+        reason: &str,
+        conn: &Conn,
+    ) -> Result<(QuarantinedBlock, bool), WalletDbError>;
+
+    /// Get a quarantined block by its index.
+    fn get(block_index: u64, conn: &Conn) -> Result<QuarantinedBlock, WalletDbError>;
+
+    /// List all quarantined blocks, including those on the skip list.
+    fn list_all(conn: &Conn) -> Result<Vec<QuarantinedBlock>, WalletDbError>;
+
+    /// Add the block to the skip list, recording an event.
+    fn skip(&self, conn: &Conn) -> Result<QuarantinedBlock, WalletDbError>;
+
+    /// Release the block from quarantine, recording an event.
+    fn release(&self, conn: &Conn) -> Result<(), WalletDbError>;
+}
+
+impl QuarantinedBlockModel for QuarantinedBlock {
+    fn quarantine(
+        block_index: u64,
+        reason: &str,
+        conn: &Conn,
+    ) -> Result<(QuarantinedBlock, bool), WalletDbError> {
+        use crate::db::schema::quarantined_blocks;
+
+        if let Ok(existing) = QuarantinedBlock::get(block_index, conn) {
+            return Ok((existing, false));
+        }
+
+        let new_block = NewQuarantinedBlock {
+            block_index: block_index as i64,
+            reason,
+            quarantined_time: Utc::now().timestamp(),
+        };
+        diesel::insert_into(quarantined_blocks::table)
+            .values(&new_block)
+            .execute(conn)?;
+
+        WalletEvent::create(
+            EVENT_TYPE_BLOCK_QUARANTINED,
+            &json!({
+                "block_index": block_index.to_string(),
+                "reason": reason,
+            }),
+            conn,
+        )?;
+
+        Ok((QuarantinedBlock::get(block_index, conn)?, true))
+    }
+
+    fn get(block_index: u64, conn: &Conn) -> Result<QuarantinedBlock, WalletDbError> {
+        use crate::db::schema::quarantined_blocks;
+
+        match quarantined_blocks::table
+            .filter(quarantined_blocks::block_index.eq(block_index as i64))
+            .get_result::<QuarantinedBlock>(conn)
+        {
+            Ok(block) => Ok(block),
+            // Match on NotFound to get a more informative NotFound Error
+            Err(diesel::result::Error::NotFound) => {
+                Err(WalletDbError::QuarantinedBlockNotFound(block_index))
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn list_all(conn: &Conn) -> Result<Vec<QuarantinedBlock>, WalletDbError> {
+        use crate::db::schema::quarantined_blocks;
+
+        Ok(quarantined_blocks::table
+            .order(quarantined_blocks::block_index.asc())
+            .load(conn)?)
+    }
+
+    fn skip(&self, conn: &Conn) -> Result<QuarantinedBlock, WalletDbError> {
+        use crate::db::schema::quarantined_blocks;
+
+        if !self.skipped {
+            diesel::update(quarantined_blocks::table.filter(quarantined_blocks::id.eq(self.id)))
+                .set(quarantined_blocks::skipped.eq(true))
+                .execute(conn)?;
+
+            WalletEvent::create(
+                EVENT_TYPE_BLOCK_SKIPPED,
+                &json!({
+                    "block_index": self.block_index.to_string(),
+                    "reason": self.reason,
+                }),
+                conn,
+            )?;
+        }
+
+        QuarantinedBlock::get(self.block_index as u64, conn)
+    }
+
+    fn release(&self, conn: &Conn) -> Result<(), WalletDbError> {
+        use crate::db::schema::quarantined_blocks;
+
+        diesel::delete(quarantined_blocks::table.filter(quarantined_blocks::id.eq(self.id)))
+            .execute(conn)?;
+
+        WalletEvent::create(
+            EVENT_TYPE_BLOCK_QUARANTINE_RELEASED,
+            &json!({
+                "block_index": self.block_index.to_string(),
+            }),
+            conn,
+        )?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::WalletDbTestContext;
+    use mc_common::logger::{test_with_logger, Logger};
+
+    #[test_with_logger]
+    fn test_quarantined_block_lifecycle(logger: Logger) {
+        let db_test_context = WalletDbTestContext::default();
+        let wallet_db = db_test_context.get_db_instance(logger);
+        let conn = wallet_db.get_conn().unwrap();
+
+        let (block, created) =
+            QuarantinedBlock::quarantine(12, "Unsupported block version 4", &conn).unwrap();
+        assert!(created);
+        assert_eq!(block.block_index, 12);
+        assert!(!block.skipped);
+
+        // Finding the same block again keeps the original record.
+        let (again, created) = QuarantinedBlock::quarantine(12, "other", &conn).unwrap();
+        assert!(!created);
+        assert_eq!(again, block);
+
+        QuarantinedBlock::quarantine(9, "Unsupported block version 4", &conn).unwrap();
+        let blocks = QuarantinedBlock::list_all(&conn).unwrap();
+        assert_eq!(
+            blocks.iter().map(|b| b.block_index).collect::<Vec<_>>(),
+            vec![9, 12]
+        );
+
+        let skipped = block.skip(&conn).unwrap();
+        assert!(skipped.skipped);
+        assert_eq!(skipped.skip(&conn).unwrap(), skipped);
+
+        blocks[0].release(&conn).unwrap();
+        match QuarantinedBlock::get(9, &conn) {
+            Err(WalletDbError::QuarantinedBlockNotFound(9)) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+
+        let events = WalletEvent::list(None, None, &conn).unwrap();
+        assert_eq!(
+            events
+                .iter()
+                .map(|e| e.event_type.as_str())
+                .collect::<Vec<_>>(),
+            vec![
+                EVENT_TYPE_BLOCK_QUARANTINED,
+                EVENT_TYPE_BLOCK_QUARANTINED,
+                EVENT_TYPE_BLOCK_SKIPPED,
+                EVENT_TYPE_BLOCK_QUARANTINE_RELEASED,
+            ]
+        );
+    }
+}
@@ -0,0 +1,40 @@
+// Copyright (c) 2020-2022 MobileCoin Inc.
+
+//! The Reporting Key Model.
+
+use crate::db::{
+    models::{NewReportingKey, ReportingKey},
+    Conn, WalletDbError,
+};
+use diesel::prelude::*;
+
+pub trait ReportingKeyModel {
+    /// Store the wallet's reporting key.
+    fn create(private_key: &[u8], conn: &Conn) -> Result<ReportingKey, WalletDbError>;
+
+    /// Get the wallet's reporting key, if one has been created.
+    fn get(conn: &Conn) -> Result<Option<ReportingKey>, WalletDbError>;
+}
+
+impl ReportingKeyModel for ReportingKey {
+    fn create(private_key: &[u8], conn: &Conn) -> Result<ReportingKey, WalletDbError> {
+        use crate::db::schema::reporting_keys;
+
+        diesel::insert_into(reporting_keys::table)
+            .values(&NewReportingKey { private_key })
+            .execute(conn)?;
+
+        Ok(reporting_keys::table
+            .order(reporting_keys::id.desc())
+            .first::<ReportingKey>(conn)?)
+    }
+
+    fn get(conn: &Conn) -> Result<Option<ReportingKey>, WalletDbError> {
+        use crate::db::schema::reporting_keys;
+
+        Ok(reporting_keys::table
+            .order(reporting_keys::id.asc())
+            .first::<ReportingKey>(conn)
+            .optional()?)
+    }
+}
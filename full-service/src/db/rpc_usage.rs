@@ -0,0 +1,143 @@
+// Copyright (c) 2020-2022 MobileCoin Inc.
+
+//! The RPC Usage Model.
+//!
+//! Calls to each API method are counted in hourly buckets, so that operators
+//! without a metrics stack can still see how the wallet is being used. Buckets
+//! older than RPC_USAGE_RETENTION_SECONDS are dropped as new ones are made.
+//! Calls are counted in memory as they are made, and added to the buckets in
+//! batches, rather than writing to the database on every call.
+
+use crate::db::{
+    models::{NewRpcUsage, RpcUsage},
+    Conn, WalletDbError,
+};
+use diesel::prelude::*;
+
+/// The length of each bucket, in seconds.
+pub const RPC_USAGE_BUCKET_SECONDS: i64 = 60 * 60;
+
+/// How long buckets are kept, in seconds.
+pub const RPC_USAGE_RETENTION_SECONDS: i64 = 7 * 24 * 60 * 60;
+
+/// The start of the bucket containing `now`.
+pub fn rpc_usage_hour_start(now: i64) -> i64 {
+    now - now.rem_euclid(RPC_USAGE_BUCKET_SECONDS)
+}
+
+pub trait RpcUsageModel {
+    /// Add calls to a method to the bucket for the hour containing `now`.
+    fn record(
+        method: &str,
+        calls: i64,
+        errors: i64,
+        total_duration_ms: i64,
+        now: i64,
+        conn: &Conn,
+    ) -> Result<(), WalletDbError>;
+
+    /// List the buckets of every method starting at or after the given time.
+    fn list_since(hour_start: i64, conn: &Conn) -> Result<Vec<RpcUsage>, WalletDbError>;
+}
+
+impl RpcUsageModel for RpcUsage {
+    fn record(
+        method: &str,
+        calls: i64,
+        errors: i64,
+        total_duration_ms: i64,
+        now: i64,
+        conn: &Conn,
+    ) -> Result<(), WalletDbError> {
+        use crate::db::schema::rpc_usage;
+
+        let hour_start = rpc_usage_hour_start(now);
+
+        let updated = diesel::update(
+            rpc_usage::table
+                .filter(rpc_usage::hour_start.eq(hour_start))
+                .filter(rpc_usage::method.eq(method)),
+        )
+        .set((
+            rpc_usage::calls.eq(rpc_usage::calls + calls),
+            rpc_usage::errors.eq(rpc_usage::errors + errors),
+            rpc_usage::total_duration_ms.eq(rpc_usage::total_duration_ms + total_duration_ms),
+        ))
+        .execute(conn)?;
+        if updated > 0 {
+            return Ok(());
+        }
+
+        // The first call in a new bucket also drops the expired ones.
+        diesel::insert_into(rpc_usage::table)
+            .values(&NewRpcUsage {
+                hour_start,
+                method,
+                calls,
+                errors,
+                total_duration_ms,
+            })
+            .execute(conn)?;
+        diesel::delete(
+            rpc_usage::table.filter(rpc_usage::hour_start.lt(now - RPC_USAGE_RETENTION_SECONDS)),
+        )
+        .execute(conn)?;
+        Ok(())
+    }
+
+    fn list_since(hour_start: i64, conn: &Conn) -> Result<Vec<RpcUsage>, WalletDbError> {
+        use crate::db::schema::rpc_usage;
+
+        Ok(rpc_usage::table
+            .filter(rpc_usage::hour_start.ge(hour_start))
+            .order((rpc_usage::hour_start.asc(), rpc_usage::method.asc()))
+            .load(conn)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::WalletDbTestContext;
+    use mc_common::logger::{test_with_logger, Logger};
+
+    #[test_with_logger]
+    fn test_rpc_usage_buckets(logger: Logger) {
+        let db_test_context = WalletDbTestContext::default();
+        let wallet_db = db_test_context.get_db_instance(logger);
+        let conn = wallet_db.get_conn().unwrap();
+
+        let hour = RPC_USAGE_BUCKET_SECONDS;
+        let start = 1000 * hour;
+        RpcUsage::record("version", 1, 0, 5, start, &conn).unwrap();
+        RpcUsage::record("version", 1, 1, 7, start + 10, &conn).unwrap();
+        RpcUsage::record("get_txo", 1, 0, 1, start + 20, &conn).unwrap();
+        RpcUsage::record("version", 1, 0, 2, start + hour, &conn).unwrap();
+        assert_eq!(rpc_usage_hour_start(start + hour + 1), start + hour);
+
+        let buckets = RpcUsage::list_since(0, &conn).unwrap();
+        assert_eq!(buckets.len(), 3);
+        assert_eq!(buckets[0].method, "get_txo");
+        assert_eq!(buckets[1].method, "version");
+        assert_eq!(buckets[1].hour_start, start);
+        assert_eq!(buckets[1].calls, 2);
+        assert_eq!(buckets[1].errors, 1);
+        assert_eq!(buckets[1].total_duration_ms, 12);
+        assert_eq!(buckets[2].hour_start, start + hour);
+        assert_eq!(RpcUsage::list_since(start + 1, &conn).unwrap().len(), 1);
+
+        // Buckets are dropped once they pass the retention period.
+        RpcUsage::record(
+            "version",
+            1,
+            0,
+            0,
+            start + hour + RPC_USAGE_RETENTION_SECONDS,
+            &conn,
+        )
+        .unwrap();
+        let buckets = RpcUsage::list_since(0, &conn).unwrap();
+        assert_eq!(buckets.len(), 2);
+        assert_eq!(buckets[0].hour_start, start + hour);
+    }
+}
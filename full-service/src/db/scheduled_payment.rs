@@ -0,0 +1,320 @@
+// Copyright (c) 2020-2022 MobileCoin Inc.
+
+//! The Scheduled Payment Model.
+//!
+//! A scheduled payment is either made once, when the ledger reaches a block
+//! height, or every interval until it is cancelled. Payments are kept after
+//! they complete, fail or are cancelled, so that their last transaction can
+//! still be looked up.
+
+use crate::db::{
+    models::{
+        NewScheduledPayment, ScheduledPayment, SCHEDULED_PAYMENT_STATUS_ACTIVE,
+        SCHEDULED_PAYMENT_STATUS_CANCELLED, SCHEDULED_PAYMENT_STATUS_COMPLETED,
+        SCHEDULED_PAYMENT_STATUS_FAILED,
+    },
+    Conn, WalletDbError,
+};
+use diesel::prelude::*;
+
+pub trait ScheduledPaymentModel {
+    /// Schedule a payment from an account, due at the given time, or once the
+    /// ledger has the given number of blocks.
+    #[allow(clippy::too_many_arguments)]
+    fn create(
+        account_id_hex: &str,
+        recipient_address: &str,
+        value: u64,
+        token_id: u64,
+        interval_seconds: Option<u64>,
+        block_height: Option<u64>,
+        comment: &str,
+        now: i64,
+        conn: &Conn,
+    ) -> Result<ScheduledPayment, WalletDbError>;
+
+    /// Get a scheduled payment by its id.
+    fn get(id: i32, conn: &Conn) -> Result<ScheduledPayment, WalletDbError>;
+
+    /// List the scheduled payments of an account, or of all accounts, in the
+    /// order they were created.
+    fn list(
+        account_id_hex: Option<&str>,
+        conn: &Conn,
+    ) -> Result<Vec<ScheduledPayment>, WalletDbError>;
+
+    /// List the active payments due at the given time, with the ledger at the
+    /// given number of blocks.
+    fn list_due(
+        now: i64,
+        num_blocks: u64,
+        conn: &Conn,
+    ) -> Result<Vec<ScheduledPayment>, WalletDbError>;
+
+    /// Record a run of the payment.
+    ///
+    /// A recurring payment is scheduled again after its interval, whether or
+    /// not the run succeeded. A one-off payment is completed, or failed.
+    fn record_run(
+        &self,
+        transaction_log_id: Option<&str>,
+        failure_message: Option<&str>,
+        now: i64,
+        conn: &Conn,
+    ) -> Result<(), WalletDbError>;
+
+    /// Record a run of the payment which failed for a reason which may pass,
+    /// such as the node being unreachable, leaving the payment due so that it
+    /// is tried again on the next run.
+    fn record_retry(
+        &self,
+        failure_message: &str,
+        now: i64,
+        conn: &Conn,
+    ) -> Result<(), WalletDbError>;
+
+    /// Stop the payment from being made again.
+    fn cancel(&self, conn: &Conn) -> Result<(), WalletDbError>;
+
+    /// Remove the scheduled payments of an account.
+    fn delete_for_account(account_id_hex: &str, conn: &Conn) -> Result<(), WalletDbError>;
+}
+
+impl ScheduledPaymentModel for ScheduledPayment {
+    fn create(
+        account_id_hex: &str,
+        recipient_address: &str,
+        value: u64,
+        token_id: u64,
+        interval_seconds: Option<u64>,
+        block_height: Option<u64>,
+        comment: &str,
+        now: i64,
+        conn: &Conn,
+    ) -> Result<ScheduledPayment, WalletDbError> {
+        use crate::db::schema::scheduled_payments;
+
+        let new_payment = NewScheduledPayment {
+            account_id_hex,
+            recipient_address,
+            value: value as i64,
+            token_id: token_id as i64,
+            interval_seconds: interval_seconds.map(|interval| interval as i64),
+            block_height: block_height.map(|height| height as i64),
+            next_run_time: now,
+            status: SCHEDULED_PAYMENT_STATUS_ACTIVE,
+            comment,
+            created_time: now,
+        };
+        diesel::insert_into(scheduled_payments::table)
+            .values(&new_payment)
+            .execute(conn)?;
+
+        Ok(scheduled_payments::table
+            .order(scheduled_payments::id.desc())
+            .first::<ScheduledPayment>(conn)?)
+    }
+
+    fn get(id: i32, conn: &Conn) -> Result<ScheduledPayment, WalletDbError> {
+        use crate::db::schema::scheduled_payments;
+
+        match scheduled_payments::table
+            .filter(scheduled_payments::id.eq(id))
+            .get_result::<ScheduledPayment>(conn)
+        {
+            Ok(payment) => Ok(payment),
+            // Match on NotFound to get a more informative NotFound Error
+            Err(diesel::result::Error::NotFound) => {
+                Err(WalletDbError::ScheduledPaymentNotFound(id))
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn list(
+        account_id_hex: Option<&str>,
+        conn: &Conn,
+    ) -> Result<Vec<ScheduledPayment>, WalletDbError> {
+        use crate::db::schema::scheduled_payments;
+
+        let mut query = scheduled_payments::table
+            .order(scheduled_payments::id.asc())
+            .into_boxed();
+        if let Some(account_id_hex) = account_id_hex {
+            query = query.filter(scheduled_payments::account_id_hex.eq(account_id_hex));
+        }
+
+        Ok(query.load(conn)?)
+    }
+
+    fn list_due(
+        now: i64,
+        num_blocks: u64,
+        conn: &Conn,
+    ) -> Result<Vec<ScheduledPayment>, WalletDbError> {
+        use crate::db::schema::scheduled_payments;
+
+        Ok(scheduled_payments::table
+            .filter(scheduled_payments::status.eq(SCHEDULED_PAYMENT_STATUS_ACTIVE))
+            .filter(scheduled_payments::next_run_time.le(now))
+            .filter(
+                scheduled_payments::block_height
+                    .is_null()
+                    .or(scheduled_payments::block_height.le(num_blocks as i64)),
+            )
+            .order(scheduled_payments::next_run_time.asc())
+            .load(conn)?)
+    }
+
+    fn record_run(
+        &self,
+        transaction_log_id: Option<&str>,
+        failure_message: Option<&str>,
+        now: i64,
+        conn: &Conn,
+    ) -> Result<(), WalletDbError> {
+        use crate::db::schema::scheduled_payments;
+
+        let (next_run_time, status) = match self.interval_seconds {
+            Some(interval_seconds) => (now + interval_seconds, SCHEDULED_PAYMENT_STATUS_ACTIVE),
+            None if failure_message.is_some() => {
+                (self.next_run_time, SCHEDULED_PAYMENT_STATUS_FAILED)
+            }
+            None => (self.next_run_time, SCHEDULED_PAYMENT_STATUS_COMPLETED),
+        };
+        let payments_submitted = self.payments_submitted + transaction_log_id.is_some() as i32;
+        let last_transaction_log_id = transaction_log_id
+            .map(|id| id.to_string())
+            .or_else(|| self.last_transaction_log_id.clone());
+        diesel::update(scheduled_payments::table.filter(scheduled_payments::id.eq(self.id)))
+            .set((
+                scheduled_payments::next_run_time.eq(next_run_time),
+                scheduled_payments::status.eq(status),
+                scheduled_payments::payments_submitted.eq(payments_submitted),
+                scheduled_payments::last_run_time.eq(Some(now)),
+                scheduled_payments::last_transaction_log_id.eq(last_transaction_log_id),
+                scheduled_payments::last_failure_message.eq(failure_message),
+            ))
+            .execute(conn)?;
+        Ok(())
+    }
+
+    fn record_retry(
+        &self,
+        failure_message: &str,
+        now: i64,
+        conn: &Conn,
+    ) -> Result<(), WalletDbError> {
+        use crate::db::schema::scheduled_payments;
+
+        diesel::update(scheduled_payments::table.filter(scheduled_payments::id.eq(self.id)))
+            .set((
+                scheduled_payments::last_run_time.eq(Some(now)),
+                scheduled_payments::last_failure_message.eq(Some(failure_message)),
+            ))
+            .execute(conn)?;
+        Ok(())
+    }
+
+    fn cancel(&self, conn: &Conn) -> Result<(), WalletDbError> {
+        use crate::db::schema::scheduled_payments;
+
+        diesel::update(scheduled_payments::table.filter(scheduled_payments::id.eq(self.id)))
+            .set(scheduled_payments::status.eq(SCHEDULED_PAYMENT_STATUS_CANCELLED))
+            .execute(conn)?;
+        Ok(())
+    }
+
+    fn delete_for_account(account_id_hex: &str, conn: &Conn) -> Result<(), WalletDbError> {
+        use crate::db::schema::scheduled_payments;
+
+        diesel::delete(
+            scheduled_payments::table.filter(scheduled_payments::account_id_hex.eq(account_id_hex)),
+        )
+        .execute(conn)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::WalletDbTestContext;
+    use mc_common::logger::{test_with_logger, Logger};
+
+    #[test_with_logger]
+    fn test_scheduled_payment_crud(logger: Logger) {
+        let db_test_context = WalletDbTestContext::default();
+        let wallet_db = db_test_context.get_db_instance(logger);
+        let conn = wallet_db.get_conn().unwrap();
+
+        let recurring =
+            ScheduledPayment::create("abcd", "rent", 100, 0, Some(60), None, "", 1000, &conn)
+                .unwrap();
+        let one_off =
+            ScheduledPayment::create("abcd", "bonus", 50, 0, None, Some(20), "", 1000, &conn)
+                .unwrap();
+        ScheduledPayment::create("ef01", "other", 1, 0, Some(60), None, "", 1000, &conn).unwrap();
+        assert_eq!(recurring.status, SCHEDULED_PAYMENT_STATUS_ACTIVE);
+        assert_eq!(
+            ScheduledPayment::list(Some("abcd"), &conn).unwrap(),
+            vec![recurring.clone(), one_off.clone()]
+        );
+        assert_eq!(ScheduledPayment::list(None, &conn).unwrap().len(), 3);
+
+        // The one-off payment waits for its block height.
+        assert!(ScheduledPayment::list_due(999, 20, &conn)
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            ScheduledPayment::list_due(1000, 19, &conn).unwrap().len(),
+            2
+        );
+        assert_eq!(
+            ScheduledPayment::list_due(1000, 20, &conn).unwrap().len(),
+            3
+        );
+
+        // A recurring payment is due again after its interval, even if the run
+        // failed.
+        recurring
+            .record_run(Some("aaaa"), None, 1010, &conn)
+            .unwrap();
+        let recurring = ScheduledPayment::get(recurring.id, &conn).unwrap();
+        assert_eq!(recurring.next_run_time, 1070);
+        assert_eq!(recurring.payments_submitted, 1);
+        recurring
+            .record_run(None, Some("no peers"), 1070, &conn)
+            .unwrap();
+        let recurring = ScheduledPayment::get(recurring.id, &conn).unwrap();
+        assert_eq!(recurring.status, SCHEDULED_PAYMENT_STATUS_ACTIVE);
+        assert_eq!(recurring.next_run_time, 1130);
+        assert_eq!(recurring.payments_submitted, 1);
+        assert_eq!(recurring.last_transaction_log_id, Some("aaaa".to_string()));
+        assert_eq!(recurring.last_failure_message, Some("no peers".to_string()));
+
+        // A one-off payment only runs once.
+        one_off.record_run(Some("bbbb"), None, 1010, &conn).unwrap();
+        let one_off = ScheduledPayment::get(one_off.id, &conn).unwrap();
+        assert_eq!(one_off.status, SCHEDULED_PAYMENT_STATUS_COMPLETED);
+        assert!(ScheduledPayment::list_due(2000, 100, &conn)
+            .unwrap()
+            .iter()
+            .all(|payment| payment.id != one_off.id));
+
+        recurring.cancel(&conn).unwrap();
+        let recurring = ScheduledPayment::get(recurring.id, &conn).unwrap();
+        assert_eq!(recurring.status, SCHEDULED_PAYMENT_STATUS_CANCELLED);
+        assert_eq!(
+            ScheduledPayment::list_due(2000, 100, &conn).unwrap().len(),
+            1
+        );
+
+        ScheduledPayment::delete_for_account("abcd", &conn).unwrap();
+        match ScheduledPayment::get(recurring.id, &conn) {
+            Err(WalletDbError::ScheduledPaymentNotFound(_)) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+        assert_eq!(ScheduledPayment::list(None, &conn).unwrap().len(), 1);
+    }
+}
@@ -13,6 +13,9 @@ table! {
         import_block_index -> Nullable<BigInt>,
         name -> Text,
         fog_enabled -> Bool,
+        preferred_token_id -> BigInt,
+        archived -> Bool,
+        sync_paused -> Bool,
     }
 }
 
@@ -28,6 +31,7 @@ table! {
         next_subaddress_index -> BigInt,
         import_block_index -> BigInt,
         name -> Text,
+        hardware_backed -> Bool,
     }
 }
 
@@ -73,11 +77,28 @@ table! {
     }
 }
 
+table! {
+    account_rotations (id) {
+        id -> Integer,
+        old_account_id_hex -> Text,
+        new_account_id_hex -> Text,
+        status -> Text,
+        sweep_transaction_log_ids -> Text,
+        failure_message -> Nullable<Text>,
+    }
+}
+
 table! {
     gift_codes (id) {
         id -> Integer,
         gift_code_b58 -> Text,
         value -> BigInt,
+        funding_account_id_hex -> Nullable<Text>,
+        claimer_account_id_hex -> Nullable<Text>,
+        claim_transaction_log_id -> Nullable<Text>,
+        claimed_time -> Nullable<BigInt>,
+        expiry_time -> Nullable<BigInt>,
+        expired -> Bool,
     }
 }
 
@@ -96,6 +117,7 @@ table! {
         comment -> Text,
         direction -> Text,
         tx -> Nullable<Binary>,
+        resubmitted_transaction_id_hex -> Nullable<Text>,
     }
 }
 
@@ -107,6 +129,18 @@ table! {
     }
 }
 
+table! {
+    transaction_watches (id) {
+        id -> Integer,
+        watch_id_hex -> Text,
+        tx_public_keys -> Binary,
+        tombstone_block_index -> Nullable<BigInt>,
+        status -> Text,
+        finalized_block_index -> Nullable<BigInt>,
+        comment -> Text,
+    }
+}
+
 table! {
     txos (id) {
         id -> Integer,
@@ -126,6 +160,192 @@ table! {
         recipient_public_address_b58 -> Text,
         minted_account_id_hex -> Nullable<Text>,
         received_account_id_hex -> Nullable<Text>,
+        ledger_txo_index -> Nullable<BigInt>,
+        mimicked_address_b58 -> Nullable<Text>,
+        sender_address_hash -> Nullable<Text>,
+        destination_address_hash -> Nullable<Text>,
+        frozen -> Bool,
+        classification -> Nullable<Text>,
+    }
+}
+
+table! {
+    reporting_keys (id) {
+        id -> Integer,
+        private_key -> Binary,
+    }
+}
+
+table! {
+    view_only_sync_records (id) {
+        id -> Integer,
+        view_only_account_id_hex -> Text,
+        record_type -> Text,
+        payload_hash -> Text,
+        txo_count -> Integer,
+        request_record_id -> Nullable<Integer>,
+        created_time -> BigInt,
+    }
+}
+
+table! {
+    wallet_events (id) {
+        id -> Integer,
+        event_type -> Text,
+        payload -> Text,
+        schema_version -> Integer,
+    }
+}
+
+table! {
+    sweep_policies (id) {
+        id -> Integer,
+        account_id_hex -> Text,
+        destination_address -> Text,
+        token_id -> BigInt,
+        threshold -> BigInt,
+        interval_seconds -> BigInt,
+        next_run_time -> BigInt,
+        last_run_time -> Nullable<BigInt>,
+        last_transaction_log_id -> Nullable<Text>,
+        last_failure_message -> Nullable<Text>,
+    }
+}
+
+table! {
+    quarantined_blocks (id) {
+        id -> Integer,
+        block_index -> BigInt,
+        reason -> Text,
+        quarantined_time -> BigInt,
+        skipped -> Bool,
+    }
+}
+
+table! {
+    wallet_settings (name) {
+        name -> Text,
+        value -> Text,
+    }
+}
+
+table! {
+    webhooks (id) {
+        id -> Integer,
+        url -> Text,
+        account_id_hex -> Nullable<Text>,
+        last_event_id -> Integer,
+        failed_attempts -> Integer,
+        last_failure_message -> Nullable<Text>,
+        created_time -> BigInt,
+    }
+}
+
+table! {
+    account_snapshots (id) {
+        id -> Integer,
+        account_id_hex -> Text,
+        block_index -> BigInt,
+        token_id -> BigInt,
+        unspent_value -> Text,
+        unspent_txo_count -> BigInt,
+        spent_txo_count -> BigInt,
+    }
+}
+
+table! {
+    account_sync_records (id) {
+        id -> Integer,
+        account_id_hex -> Text,
+        start_block_index -> BigInt,
+        end_block_index -> BigInt,
+        txos_received -> Integer,
+        txos_spent -> Integer,
+        duration_ms -> BigInt,
+        created_time -> BigInt,
+    }
+}
+
+table! {
+    scheduled_payments (id) {
+        id -> Integer,
+        account_id_hex -> Text,
+        recipient_address -> Text,
+        value -> BigInt,
+        token_id -> BigInt,
+        interval_seconds -> Nullable<BigInt>,
+        block_height -> Nullable<BigInt>,
+        next_run_time -> BigInt,
+        status -> Text,
+        comment -> Text,
+        payments_submitted -> Integer,
+        last_run_time -> Nullable<BigInt>,
+        last_transaction_log_id -> Nullable<Text>,
+        last_failure_message -> Nullable<Text>,
+        created_time -> BigInt,
+    }
+}
+
+table! {
+    payment_requests (id) {
+        id -> Integer,
+        account_id_hex -> Text,
+        subaddress_index -> BigInt,
+        value -> BigInt,
+        token_id -> BigInt,
+        memo -> Text,
+        expiry_block_index -> Nullable<BigInt>,
+        status -> Text,
+        paid_txo_id_hex -> Nullable<Text>,
+        paid_value -> Nullable<BigInt>,
+        paid_block_index -> Nullable<BigInt>,
+        created_time -> BigInt,
+    }
+}
+
+table! {
+    rpc_usage (id) {
+        id -> Integer,
+        hour_start -> BigInt,
+        method -> Text,
+        calls -> BigInt,
+        errors -> BigInt,
+        total_duration_ms -> BigInt,
+    }
+}
+
+table! {
+    db_size_samples (id) {
+        id -> Integer,
+        wallet_db_bytes -> Nullable<BigInt>,
+        ledger_db_bytes -> Nullable<BigInt>,
+        created_time -> BigInt,
+    }
+}
+
+table! {
+    continuity_check_ins (id) {
+        id -> Integer,
+        created_time -> BigInt,
+        exported_time -> Nullable<BigInt>,
+        export_path -> Nullable<Text>,
+    }
+}
+
+table! {
+    isolated_counterparties (id) {
+        id -> Integer,
+        account_id_hex -> Text,
+        address_hash -> Text,
+        created_time -> BigInt,
+    }
+}
+
+table! {
+    synced_blocks (id) {
+        id -> Integer,
+        block_index -> BigInt,
+        block_id_hex -> Text,
     }
 }
 
@@ -0,0 +1,185 @@
+// Copyright (c) 2020-2022 MobileCoin Inc.
+
+//! The Sweep Policy Model.
+//!
+//! A sweep policy moves an account's balance above a threshold to another
+//! address every so often, so that only a working balance is kept in the hot
+//! wallet.
+
+use crate::db::{
+    models::{NewSweepPolicy, SweepPolicy},
+    Conn, WalletDbError,
+};
+use diesel::prelude::*;
+
+pub trait SweepPolicyModel {
+    /// Set the sweep policy of an account, replacing any existing one.
+    ///
+    /// The new policy is due to run immediately.
+    fn set(
+        account_id_hex: &str,
+        destination_address: &str,
+        token_id: u64,
+        threshold: u64,
+        interval_seconds: u64,
+        now: i64,
+        conn: &Conn,
+    ) -> Result<SweepPolicy, WalletDbError>;
+
+    /// Get the sweep policy of an account.
+    fn get(account_id_hex: &str, conn: &Conn) -> Result<SweepPolicy, WalletDbError>;
+
+    /// List the policies due to run at the given time.
+    fn list_due(now: i64, conn: &Conn) -> Result<Vec<SweepPolicy>, WalletDbError>;
+
+    /// Record a run of the policy, and schedule the next one.
+    ///
+    /// The last transaction is only replaced if the run submitted one.
+    fn record_run(
+        &self,
+        transaction_log_id: Option<&str>,
+        failure_message: Option<&str>,
+        now: i64,
+        conn: &Conn,
+    ) -> Result<(), WalletDbError>;
+
+    /// Remove the sweep policy of an account, if it has one.
+    fn delete_for_account(account_id_hex: &str, conn: &Conn) -> Result<(), WalletDbError>;
+}
+
+impl SweepPolicyModel for SweepPolicy {
+    fn set(
+        account_id_hex: &str,
+        destination_address: &str,
+        token_id: u64,
+        threshold: u64,
+        interval_seconds: u64,
+        now: i64,
+        conn: &Conn,
+    ) -> Result<SweepPolicy, WalletDbError> {
+        use crate::db::schema::sweep_policies;
+
+        SweepPolicy::delete_for_account(account_id_hex, conn)?;
+
+        let new_policy = NewSweepPolicy {
+            account_id_hex,
+            destination_address,
+            token_id: token_id as i64,
+            threshold: threshold as i64,
+            interval_seconds: interval_seconds as i64,
+            next_run_time: now,
+        };
+        diesel::insert_into(sweep_policies::table)
+            .values(&new_policy)
+            .execute(conn)?;
+
+        SweepPolicy::get(account_id_hex, conn)
+    }
+
+    fn get(account_id_hex: &str, conn: &Conn) -> Result<SweepPolicy, WalletDbError> {
+        use crate::db::schema::sweep_policies;
+
+        match sweep_policies::table
+            .filter(sweep_policies::account_id_hex.eq(account_id_hex))
+            .get_result::<SweepPolicy>(conn)
+        {
+            Ok(policy) => Ok(policy),
+            // Match on NotFound to get a more informative NotFound Error
+            Err(diesel::result::Error::NotFound) => Err(WalletDbError::SweepPolicyNotFound(
+                account_id_hex.to_string(),
+            )),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn list_due(now: i64, conn: &Conn) -> Result<Vec<SweepPolicy>, WalletDbError> {
+        use crate::db::schema::sweep_policies;
+
+        Ok(sweep_policies::table
+            .filter(sweep_policies::next_run_time.le(now))
+            .order(sweep_policies::next_run_time.asc())
+            .load(conn)?)
+    }
+
+    fn record_run(
+        &self,
+        transaction_log_id: Option<&str>,
+        failure_message: Option<&str>,
+        now: i64,
+        conn: &Conn,
+    ) -> Result<(), WalletDbError> {
+        use crate::db::schema::sweep_policies;
+
+        let last_transaction_log_id = transaction_log_id
+            .map(|id| id.to_string())
+            .or_else(|| self.last_transaction_log_id.clone());
+        diesel::update(sweep_policies::table.filter(sweep_policies::id.eq(self.id)))
+            .set((
+                sweep_policies::last_run_time.eq(Some(now)),
+                sweep_policies::next_run_time.eq(now + self.interval_seconds),
+                sweep_policies::last_transaction_log_id.eq(last_transaction_log_id),
+                sweep_policies::last_failure_message.eq(failure_message),
+            ))
+            .execute(conn)?;
+        Ok(())
+    }
+
+    fn delete_for_account(account_id_hex: &str, conn: &Conn) -> Result<(), WalletDbError> {
+        use crate::db::schema::sweep_policies;
+
+        diesel::delete(
+            sweep_policies::table.filter(sweep_policies::account_id_hex.eq(account_id_hex)),
+        )
+        .execute(conn)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::WalletDbTestContext;
+    use mc_common::logger::{test_with_logger, Logger};
+
+    #[test_with_logger]
+    fn test_sweep_policy_crud(logger: Logger) {
+        let db_test_context = WalletDbTestContext::default();
+        let wallet_db = db_test_context.get_db_instance(logger);
+        let conn = wallet_db.get_conn().unwrap();
+
+        let policy = SweepPolicy::set("abcd", "cold", 0, 100, 60, 1000, &conn).unwrap();
+        assert_eq!(policy.threshold, 100);
+        assert_eq!(policy.next_run_time, 1000);
+        assert_eq!(policy.last_run_time, None);
+        assert_eq!(
+            SweepPolicy::list_due(1000, &conn).unwrap(),
+            vec![policy.clone()]
+        );
+        assert!(SweepPolicy::list_due(999, &conn).unwrap().is_empty());
+
+        policy.record_run(Some("aaaa"), None, 1010, &conn).unwrap();
+        let policy = SweepPolicy::get("abcd", &conn).unwrap();
+        assert_eq!(policy.last_run_time, Some(1010));
+        assert_eq!(policy.next_run_time, 1070);
+        assert!(SweepPolicy::list_due(1069, &conn).unwrap().is_empty());
+
+        // A failed run keeps the last transaction.
+        policy
+            .record_run(None, Some("no peers"), 1070, &conn)
+            .unwrap();
+        let policy = SweepPolicy::get("abcd", &conn).unwrap();
+        assert_eq!(policy.last_transaction_log_id, Some("aaaa".to_string()));
+        assert_eq!(policy.last_failure_message, Some("no peers".to_string()));
+
+        // Setting a policy again replaces it.
+        let policy = SweepPolicy::set("abcd", "colder", 0, 200, 60, 2000, &conn).unwrap();
+        assert_eq!(policy.destination_address, "colder");
+        assert_eq!(policy.last_transaction_log_id, None);
+
+        SweepPolicy::delete_for_account("abcd", &conn).unwrap();
+        match SweepPolicy::get("abcd", &conn) {
+            Err(WalletDbError::SweepPolicyNotFound(_)) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,116 @@
+// Copyright (c) 2020-2022 MobileCoin Inc.
+
+//! The Synced Block Model.
+//!
+//! The sync records the ids of the most recent ledger blocks it has seen. If
+//! the ledger is rolled back or reorganized, the recorded ids no longer match
+//! the ledger's, and the sync rolls accounts back to where the two diverged.
+
+use crate::db::{
+    models::{NewSyncedBlock, SyncedBlock},
+    Conn, WalletDbError,
+};
+use diesel::prelude::*;
+
+pub trait SyncedBlockModel {
+    /// Record the id of a block, replacing any id recorded for its index.
+    fn record(block_index: u64, block_id_hex: &str, conn: &Conn) -> Result<(), WalletDbError>;
+
+    /// List the recorded blocks with the highest indices, highest first.
+    fn list_latest(limit: u64, conn: &Conn) -> Result<Vec<SyncedBlock>, WalletDbError>;
+
+    /// Remove the recorded blocks at or after the block index.
+    fn delete_from(block_index: u64, conn: &Conn) -> Result<(), WalletDbError>;
+
+    /// Remove the recorded blocks before the block index.
+    fn delete_before(block_index: u64, conn: &Conn) -> Result<(), WalletDbError>;
+}
+
+impl SyncedBlockModel for SyncedBlock {
+    fn record(block_index: u64, block_id_hex: &str, conn: &Conn) -> Result<(), WalletDbError> {
+        use crate::db::schema::synced_blocks;
+
+        // Replace any block recorded at this index. diesel only supports
+        // REPLACE on SQLite, so this is a delete and an insert.
+        diesel::delete(
+            synced_blocks::table.filter(synced_blocks::block_index.eq(block_index as i64)),
+        )
+        .execute(conn)?;
+        diesel::insert_into(synced_blocks::table)
+            .values(&NewSyncedBlock {
+                block_index: block_index as i64,
+                block_id_hex,
+            })
+            .execute(conn)?;
+        Ok(())
+    }
+
+    fn list_latest(limit: u64, conn: &Conn) -> Result<Vec<SyncedBlock>, WalletDbError> {
+        use crate::db::schema::synced_blocks;
+
+        Ok(synced_blocks::table
+            .order(synced_blocks::block_index.desc())
+            .limit(limit as i64)
+            .load(conn)?)
+    }
+
+    fn delete_from(block_index: u64, conn: &Conn) -> Result<(), WalletDbError> {
+        use crate::db::schema::synced_blocks;
+
+        diesel::delete(
+            synced_blocks::table.filter(synced_blocks::block_index.ge(block_index as i64)),
+        )
+        .execute(conn)?;
+        Ok(())
+    }
+
+    fn delete_before(block_index: u64, conn: &Conn) -> Result<(), WalletDbError> {
+        use crate::db::schema::synced_blocks;
+
+        diesel::delete(
+            synced_blocks::table.filter(synced_blocks::block_index.lt(block_index as i64)),
+        )
+        .execute(conn)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::WalletDbTestContext;
+    use mc_common::logger::{test_with_logger, Logger};
+
+    #[test_with_logger]
+    fn test_synced_blocks(logger: Logger) {
+        let db_test_context = WalletDbTestContext::default();
+        let wallet_db = db_test_context.get_db_instance(logger);
+        let conn = wallet_db.get_conn().unwrap();
+
+        for block_index in 0..5 {
+            SyncedBlock::record(block_index, &format!("{:02x}", block_index), &conn).unwrap();
+        }
+        // Recording a block again replaces its id.
+        SyncedBlock::record(4, "ff", &conn).unwrap();
+
+        let latest = SyncedBlock::list_latest(2, &conn).unwrap();
+        assert_eq!(
+            latest
+                .iter()
+                .map(|block| (block.block_index, block.block_id_hex.as_str()))
+                .collect::<Vec<_>>(),
+            vec![(4, "ff"), (3, "03")]
+        );
+
+        SyncedBlock::delete_from(3, &conn).unwrap();
+        SyncedBlock::delete_before(1, &conn).unwrap();
+        let remaining = SyncedBlock::list_latest(10, &conn).unwrap();
+        assert_eq!(
+            remaining
+                .iter()
+                .map(|block| block.block_index)
+                .collect::<Vec<_>>(),
+            vec![2, 1]
+        );
+    }
+}
@@ -7,18 +7,21 @@ use diesel::prelude::*;
 use mc_common::HashMap;
 use mc_crypto_digestible::{Digestible, MerlinTranscript};
 use mc_mobilecoind::payments::TxProposal;
-use mc_transaction_core::{tx::Tx, Amount};
+use mc_transaction_core::{constants::MAX_OUTPUTS, tx::Tx, Amount};
+use serde_json::json;
 use std::fmt;
 
 use crate::db::{
     account::{AccountID, AccountModel},
     models::{
         Account, NewTransactionLog, NewTransactionTxoType, TransactionLog, TransactionTxoType, Txo,
+        WalletEvent, EVENT_TYPE_TRANSACTION_FAILED, EVENT_TYPE_TRANSACTION_SUCCEEDED,
         TXO_USED_AS_CHANGE, TXO_USED_AS_INPUT, TXO_USED_AS_OUTPUT, TX_DIRECTION_RECEIVED,
         TX_DIRECTION_SENT, TX_STATUS_BUILT, TX_STATUS_FAILED, TX_STATUS_PENDING,
         TX_STATUS_SUCCEEDED,
     },
     txo::{TxoID, TxoModel},
+    wallet_event::WalletEventModel,
     Conn, WalletDbError,
 };
 
@@ -57,9 +60,13 @@ pub trait TransactionLogModel {
     /// Get a transaction log from the TransactionId.
     fn get(transaction_id_hex: &str, conn: &Conn) -> Result<TransactionLog, WalletDbError>;
 
-    /// Get all transaction logs for the given block index.
-    fn get_all_for_block_index(
-        block_index: u64,
+    /// Get the transaction logs finalized in the given range of blocks,
+    /// inclusive, ordered by block and then by when they were logged.
+    fn get_all_for_block_range(
+        min_block_index: u64,
+        max_block_index: u64,
+        offset: u64,
+        limit: u64,
         conn: &Conn,
     ) -> Result<Vec<TransactionLog>, WalletDbError>;
 
@@ -76,6 +83,13 @@ pub trait TransactionLogModel {
     /// Select the TransactionLogs associated with a given TxoId.
     fn select_for_txo(txo_id_hex: &str, conn: &Conn) -> Result<Vec<TransactionLog>, WalletDbError>;
 
+    /// Select the logs of the transactions which spend a given Txo, oldest
+    /// first. There may be several if earlier attempts failed.
+    fn select_spending_txo(
+        txo_id_hex: &str,
+        conn: &Conn,
+    ) -> Result<Vec<TransactionLog>, WalletDbError>;
+
     /// List all TransactionLogs and their associated Txos for a given account.
     ///
     /// Returns:
@@ -89,6 +103,22 @@ pub trait TransactionLogModel {
         conn: &Conn,
     ) -> Result<Vec<(TransactionLog, AssociatedTxos)>, WalletDbError>;
 
+    /// Get a page of the TransactionLogs and their associated Txos for a given
+    /// account, in insertion order.
+    ///
+    /// Returns:
+    /// * Up to `limit` logs with a primary key greater than `after_id`, after
+    ///   skipping `offset` of them.
+    fn list_page(
+        account_id_hex: &str,
+        after_id: Option<i32>,
+        offset: Option<u64>,
+        limit: u64,
+        min_block_index: Option<u64>,
+        max_block_index: Option<u64>,
+        conn: &Conn,
+    ) -> Result<Vec<(TransactionLog, AssociatedTxos)>, WalletDbError>;
+
     /// Log a received transaction.
     fn log_received(
         account_id_hex: &str,
@@ -117,9 +147,56 @@ pub trait TransactionLogModel {
         conn: &Conn,
     ) -> Result<TransactionLog, WalletDbError>;
 
+    /// Log a transaction which has been built, but not submitted.
+    ///
+    /// The inputs are marked pending as they are for a submitted transaction,
+    /// so that no other transaction is built with them before the tombstone
+    /// block.
+    fn log_built(
+        tx_proposal: TxProposal,
+        comment: String,
+        account_id_hex: &str,
+        conn: &Conn,
+    ) -> Result<TransactionLog, WalletDbError>;
+
+    /// Record that a built transaction has been submitted.
+    fn update_to_submitted(
+        &self,
+        block_index: u64,
+        comment: String,
+        conn: &Conn,
+    ) -> Result<TransactionLog, WalletDbError>;
+
     /// Remove all logs for an account
     fn delete_all_for_account(account_id_hex: &str, conn: &Conn) -> Result<(), WalletDbError>;
 
+    /// Remove the sent logs in any of the given statuses, and logged before
+    /// the given time if one is given.
+    ///
+    /// Returns how many logs were removed.
+    fn delete_sent_with_status(
+        statuses: &[&str],
+        sent_before: Option<i64>,
+        conn: &Conn,
+    ) -> Result<usize, WalletDbError>;
+
+    /// Remove the logs of built transactions which were never submitted, and
+    /// which spend any of the given txos. Called once the txos' tombstone
+    /// block has passed, after which the transactions can't be submitted.
+    ///
+    /// Returns how many logs were removed.
+    fn delete_built_associated_with_txos(txos: &[Txo], conn: &Conn)
+        -> Result<usize, WalletDbError>;
+
+    /// Undo what the account's logs recorded of the blocks at or after the
+    /// block index: received logs are removed, and sent logs which succeeded
+    /// are pending again.
+    fn roll_back_from_block_index(
+        account_id_hex: &str,
+        block_index: u64,
+        conn: &Conn,
+    ) -> Result<(), WalletDbError>;
+
     fn update_tx_logs_associated_with_txo_to_succeeded(
         txo_id_hex: &str,
         finalized_block_index: u64,
@@ -130,6 +207,33 @@ pub trait TransactionLogModel {
         txos: &[Txo],
         conn: &Conn,
     ) -> Result<(), WalletDbError>;
+
+    /// List the sent transactions in the given status, sent at or after the
+    /// given time if one is given, oldest first.
+    fn list_sent_with_status(
+        status: &str,
+        sent_since: Option<i64>,
+        conn: &Conn,
+    ) -> Result<Vec<TransactionLog>, WalletDbError>;
+
+    /// Mark a pending transaction failed once its tombstone block has passed,
+    /// releasing the inputs it held so that they can be spent again.
+    fn update_expired_to_failed(
+        &self,
+        tombstone_block: u64,
+        conn: &Conn,
+    ) -> Result<(), WalletDbError>;
+
+    /// Record the transaction submitted in place of this expired one.
+    fn record_resubmission(
+        &self,
+        resubmitted_transaction_id_hex: &str,
+        conn: &Conn,
+    ) -> Result<(), WalletDbError>;
+
+    /// Whether this transaction was itself submitted in place of an expired
+    /// one.
+    fn is_resubmission(&self, conn: &Conn) -> Result<bool, WalletDbError>;
 }
 
 impl TransactionLogModel for TransactionLog {
@@ -151,17 +255,24 @@ impl TransactionLogModel for TransactionLog {
         }
     }
 
-    fn get_all_for_block_index(
-        block_index: u64,
+    fn get_all_for_block_range(
+        min_block_index: u64,
+        max_block_index: u64,
+        offset: u64,
+        limit: u64,
         conn: &Conn,
     ) -> Result<Vec<TransactionLog>, WalletDbError> {
         use crate::db::schema::transaction_logs::{
-            all_columns, dsl::transaction_logs, finalized_block_index,
+            all_columns, dsl::transaction_logs, finalized_block_index, id,
         };
 
         let matches: Vec<TransactionLog> = transaction_logs
             .select(all_columns)
-            .filter(finalized_block_index.eq(block_index as i64))
+            .filter(finalized_block_index.ge(min_block_index as i64))
+            .filter(finalized_block_index.le(max_block_index as i64))
+            .order((finalized_block_index.asc(), id.asc()))
+            .offset(offset as i64)
+            .limit(limit as i64)
             .load::<TransactionLog>(conn)?;
 
         Ok(matches)
@@ -227,6 +338,23 @@ impl TransactionLogModel for TransactionLog {
             .load(conn)?)
     }
 
+    fn select_spending_txo(
+        txo_id_hex: &str,
+        conn: &Conn,
+    ) -> Result<Vec<TransactionLog>, WalletDbError> {
+        use crate::db::schema::{transaction_logs, transaction_txo_types};
+
+        Ok(transaction_logs::table
+            .inner_join(transaction_txo_types::table.on(
+                transaction_logs::transaction_id_hex.eq(transaction_txo_types::transaction_id_hex),
+            ))
+            .filter(transaction_txo_types::txo_id_hex.eq(txo_id_hex))
+            .filter(transaction_txo_types::transaction_txo_type.eq(TXO_USED_AS_INPUT))
+            .order(transaction_logs::id.asc())
+            .select(transaction_logs::all_columns)
+            .load(conn)?)
+    }
+
     fn list_all(
         account_id_hex: &str,
         offset: Option<u64>,
@@ -271,63 +399,66 @@ impl TransactionLogModel for TransactionLog {
 
         let transactions: Vec<(TransactionLog, TransactionTxoType, Txo)> =
             transactions_query.load(conn)?;
+        group_associated_txos(transactions)
+    }
 
-        #[derive(Clone)]
-        struct TransactionContents {
-            transaction_log: TransactionLog,
-            inputs: Vec<Txo>,
-            outputs: Vec<Txo>,
-            change: Vec<Txo>,
+    fn list_page(
+        account_id_hex: &str,
+        after_id: Option<i32>,
+        offset: Option<u64>,
+        limit: u64,
+        min_block_index: Option<u64>,
+        max_block_index: Option<u64>,
+        conn: &Conn,
+    ) -> Result<Vec<(TransactionLog, AssociatedTxos)>, WalletDbError> {
+        use crate::db::schema::{transaction_logs, transaction_txo_types, txos};
+
+        // Page over the logs themselves, rather than the rows joining them to
+        // their txos, so that a page holds `limit` whole logs.
+        let mut ids_query = transaction_logs::table
+            .inner_join(transaction_txo_types::table.on(
+                transaction_logs::transaction_id_hex.eq(transaction_txo_types::transaction_id_hex),
+            ))
+            .filter(transaction_logs::account_id_hex.eq(account_id_hex))
+            .select(transaction_logs::id)
+            .distinct()
+            .order(transaction_logs::id)
+            .limit(limit as i64)
+            .into_boxed();
+
+        if let Some(after_id) = after_id {
+            ids_query = ids_query.filter(transaction_logs::id.gt(after_id));
         }
-        let mut results: HashMap<String, TransactionContents> = HashMap::default();
-        for (transaction, transaction_txo_type, txo) in transactions {
-            if results.get(&transaction.transaction_id_hex).is_none() {
-                results.insert(
-                    transaction.transaction_id_hex.clone(),
-                    TransactionContents {
-                        transaction_log: transaction.clone(),
-                        inputs: Vec::new(),
-                        outputs: Vec::new(),
-                        change: Vec::new(),
-                    },
-                );
-            };
 
-            let entry = results.get_mut(&transaction.transaction_id_hex).unwrap();
+        if let Some(offset) = offset {
+            ids_query = ids_query.offset(offset as i64);
+        }
 
-            if entry.transaction_log != transaction {
-                return Err(WalletDbError::TransactionMismatch);
-            }
+        if let Some(min_block_index) = min_block_index {
+            ids_query = ids_query
+                .filter(transaction_logs::finalized_block_index.ge(min_block_index as i64));
+        }
 
-            match transaction_txo_type.transaction_txo_type.as_str() {
-                TXO_USED_AS_INPUT => entry.inputs.push(txo),
-                TXO_USED_AS_OUTPUT => entry.outputs.push(txo),
-                TXO_USED_AS_CHANGE => entry.change.push(txo),
-                _ => {
-                    return Err(WalletDbError::UnexpectedTransactionTxoType(
-                        transaction_txo_type.transaction_txo_type,
-                    ));
-                }
-            }
+        if let Some(max_block_index) = max_block_index {
+            ids_query = ids_query
+                .filter(transaction_logs::finalized_block_index.le(max_block_index as i64));
         }
 
-        let mut results: Vec<(TransactionLog, AssociatedTxos)> = results
-            .values()
-            .cloned()
-            .map(|t| {
-                (
-                    t.transaction_log,
-                    AssociatedTxos {
-                        inputs: t.inputs,
-                        outputs: t.outputs,
-                        change: t.change,
-                    },
-                )
-            })
-            .collect();
+        let ids: Vec<i32> = ids_query.load(conn)?;
 
-        results.sort_by_key(|r| r.0.id);
-        Ok(results)
+        let transactions: Vec<(TransactionLog, TransactionTxoType, Txo)> = transaction_logs::table
+            .inner_join(transaction_txo_types::table.on(
+                transaction_logs::transaction_id_hex.eq(transaction_txo_types::transaction_id_hex),
+            ))
+            .inner_join(txos::table.on(transaction_txo_types::txo_id_hex.eq(txos::txo_id_hex)))
+            .filter(transaction_logs::id.eq_any(ids))
+            .select((
+                transaction_logs::all_columns,
+                transaction_txo_types::all_columns,
+                txos::all_columns,
+            ))
+            .load(conn)?;
+        group_associated_txos(transactions)
     }
 
     fn log_received(
@@ -338,7 +469,17 @@ impl TransactionLogModel for TransactionLog {
         block_index: u64,
         conn: &Conn,
     ) -> Result<(), WalletDbError> {
-        use crate::db::schema::transaction_txo_types;
+        use crate::db::schema::{transaction_logs, transaction_txo_types};
+
+        // Rescanning blocks that the account has already synced will find the
+        // same txos again, which have already been logged.
+        let existing = transaction_logs::table
+            .filter(transaction_logs::transaction_id_hex.eq(txo_id_hex))
+            .count()
+            .get_result::<i64>(conn)?;
+        if existing > 0 {
+            return Ok(());
+        }
 
         let new_transaction_log = NewTransactionLog {
             transaction_id_hex: txo_id_hex,
@@ -380,82 +521,51 @@ impl TransactionLogModel for TransactionLog {
         account_id_hex: &str,
         conn: &Conn,
     ) -> Result<TransactionLog, WalletDbError> {
-        // Verify that the account exists.
-        Account::get(&AccountID(account_id_hex.to_string()), conn)?;
-
-        // Store the txo_id_hex -> transaction_txo_type
-        let mut txo_ids: Vec<(String, String)> = Vec::new();
-
-        // Verify that the TxProposal is well-formed according to our assumptions about
-        // how to store the sent data in our wallet (num_output_TXOs = num_outlays +
-        // change_TXO).
-        if tx_proposal.tx.prefix.outputs.len() - tx_proposal.outlays.len() > 1 {
-            return Err(WalletDbError::UnexpectedNumberOfChangeOutputs);
-        }
-
-        // First update all inputs to "pending." They will remain pending until their
-        // key_image hits the ledger or their tombstone block is exceeded.
-        for utxo in tx_proposal.utxos.iter() {
-            let txo_id = TxoID::from(&utxo.tx_out);
-            let txo = Txo::get(&txo_id.to_string(), conn)?;
-            txo.update_to_pending(tx_proposal.tx.prefix.tombstone_block, conn)?;
-            txo_ids.push((txo_id.to_string(), TXO_USED_AS_INPUT.to_string()));
-        }
-
-        // Next, add all of our minted outputs to the Txo Table
-        for (i, output) in tx_proposal.tx.prefix.outputs.iter().enumerate() {
-            let processed_output =
-                Txo::create_minted(account_id_hex, output, &tx_proposal, i, conn)?;
-            txo_ids.push((
-                processed_output.txo_id_hex,
-                processed_output.txo_type.to_string(),
-            ));
-        }
-
-        // Enforce maximum value.
-        let transaction_value = tx_proposal
-            .outlays
-            .iter()
-            .map(|o| o.value as u128)
-            .sum::<u128>();
-        if transaction_value > u64::MAX as u128 {
-            return Err(WalletDbError::TransactionValueExceedsMax);
-        }
+        log_sent(
+            tx_proposal,
+            Some(block_index),
+            TX_STATUS_PENDING,
+            comment,
+            account_id_hex,
+            conn,
+        )
+    }
 
-        let transaction_id = TransactionID::from(&tx_proposal.tx);
-        let tx = mc_util_serial::encode(&tx_proposal.tx);
+    fn log_built(
+        tx_proposal: TxProposal,
+        comment: String,
+        account_id_hex: &str,
+        conn: &Conn,
+    ) -> Result<TransactionLog, WalletDbError> {
+        log_sent(
+            tx_proposal,
+            None,
+            TX_STATUS_BUILT,
+            comment,
+            account_id_hex,
+            conn,
+        )
+    }
 
-        // Create a TransactionLogs entry
-        let new_transaction_log = NewTransactionLog {
-            transaction_id_hex: &transaction_id.to_string(),
-            account_id_hex, // Can be null if submitting an "unowned" proposal.
-            assigned_subaddress_b58: None, // NULL for sent
-            value: transaction_value as i64,
-            fee: Some(tx_proposal.tx.prefix.fee as i64),
-            status: TX_STATUS_PENDING,
-            sent_time: Some(Utc::now().timestamp()),
-            submitted_block_index: Some(block_index as i64),
-            finalized_block_index: None,
-            comment: &comment,
-            direction: TX_DIRECTION_SENT,
-            tx: Some(&tx),
-        };
-        diesel::insert_into(crate::db::schema::transaction_logs::table)
-            .values(&new_transaction_log)
-            .execute(conn)?;
+    fn update_to_submitted(
+        &self,
+        block_index: u64,
+        comment: String,
+        conn: &Conn,
+    ) -> Result<TransactionLog, WalletDbError> {
+        use crate::db::schema::transaction_logs;
 
-        // Create an entry per TXO for the TransactionTxoTypes
-        for (txo_id_hex, transaction_txo_type) in txo_ids {
-            let new_transaction_txo = NewTransactionTxoType {
-                transaction_id_hex: &transaction_id.to_string(),
-                txo_id_hex: &txo_id_hex,
-                transaction_txo_type: &transaction_txo_type,
-            };
-            diesel::insert_into(crate::db::schema::transaction_txo_types::table)
-                .values(&new_transaction_txo)
-                .execute(conn)?;
-        }
-        TransactionLog::get(&transaction_id.to_string(), conn)
+        diesel::update(
+            transaction_logs::table
+                .filter(transaction_logs::transaction_id_hex.eq(&self.transaction_id_hex)),
+        )
+        .set((
+            transaction_logs::status.eq(TX_STATUS_PENDING),
+            transaction_logs::submitted_block_index.eq(Some(block_index as i64)),
+            transaction_logs::comment.eq(comment),
+        ))
+        .execute(conn)?;
+        TransactionLog::get(&self.transaction_id_hex, conn)
     }
 
     fn delete_all_for_account(account_id_hex: &str, conn: &Conn) -> Result<(), WalletDbError> {
@@ -482,6 +592,78 @@ impl TransactionLogModel for TransactionLog {
         Ok(())
     }
 
+    fn delete_sent_with_status(
+        statuses: &[&str],
+        sent_before: Option<i64>,
+        conn: &Conn,
+    ) -> Result<usize, WalletDbError> {
+        use crate::db::schema::transaction_logs;
+
+        let mut query = transaction_logs::table
+            .filter(transaction_logs::direction.eq(TX_DIRECTION_SENT))
+            .filter(transaction_logs::status.eq_any(statuses))
+            .select(transaction_logs::transaction_id_hex)
+            .into_boxed();
+        if let Some(sent_before) = sent_before {
+            query = query.filter(transaction_logs::sent_time.lt(sent_before));
+        }
+        let transaction_ids: Vec<String> = query.load(conn)?;
+
+        delete_logs(&transaction_ids, conn)
+    }
+
+    fn roll_back_from_block_index(
+        account_id_hex: &str,
+        block_index: u64,
+        conn: &Conn,
+    ) -> Result<(), WalletDbError> {
+        use crate::db::schema::transaction_logs;
+
+        let received_ids: Vec<String> = transaction_logs::table
+            .filter(transaction_logs::account_id_hex.eq(account_id_hex))
+            .filter(transaction_logs::direction.eq(TX_DIRECTION_RECEIVED))
+            .filter(transaction_logs::finalized_block_index.ge(block_index as i64))
+            .select(transaction_logs::transaction_id_hex)
+            .load(conn)?;
+        delete_logs(&received_ids, conn)?;
+
+        diesel::update(
+            transaction_logs::table
+                .filter(transaction_logs::account_id_hex.eq(account_id_hex))
+                .filter(transaction_logs::direction.eq(TX_DIRECTION_SENT))
+                .filter(transaction_logs::status.eq(TX_STATUS_SUCCEEDED))
+                .filter(transaction_logs::finalized_block_index.ge(block_index as i64)),
+        )
+        .set((
+            transaction_logs::status.eq(TX_STATUS_PENDING),
+            transaction_logs::finalized_block_index.eq::<Option<i64>>(None),
+        ))
+        .execute(conn)?;
+        Ok(())
+    }
+
+    fn delete_built_associated_with_txos(
+        txos: &[Txo],
+        conn: &Conn,
+    ) -> Result<usize, WalletDbError> {
+        use crate::db::schema::{transaction_logs, transaction_txo_types};
+
+        let txo_ids: Vec<String> = txos.iter().map(|txo| txo.txo_id_hex.clone()).collect();
+
+        let transaction_ids: Vec<String> = transaction_logs::table
+            .inner_join(transaction_txo_types::table.on(
+                transaction_logs::transaction_id_hex.eq(transaction_txo_types::transaction_id_hex),
+            ))
+            .filter(transaction_txo_types::txo_id_hex.eq_any(txo_ids))
+            .filter(transaction_txo_types::transaction_txo_type.eq(TXO_USED_AS_INPUT))
+            .filter(transaction_logs::status.eq(TX_STATUS_BUILT))
+            .select(transaction_logs::transaction_id_hex)
+            .distinct()
+            .load(conn)?;
+
+        delete_logs(&transaction_ids, conn)
+    }
+
     fn update_tx_logs_associated_with_txo_to_succeeded(
         txo_id_hex: &str,
         finalized_block_index: u64,
@@ -492,14 +674,19 @@ impl TransactionLogModel for TransactionLog {
         // Find all transaction_logs that are BUILT or PENDING that are associated
         // with the txo id when it is used as an input.
         // Update the status to SUCCEEDED and update the finalized_block_index.
-        let transaction_log_ids: Vec<String> = transaction_logs::table
+        let updated_logs: Vec<(String, String)> = transaction_logs::table
             .inner_join(transaction_txo_types::table.on(
                 transaction_logs::transaction_id_hex.eq(transaction_txo_types::transaction_id_hex),
             ))
             .filter(transaction_txo_types::txo_id_hex.eq(txo_id_hex))
             .filter(transaction_logs::status.eq_any(vec![TX_STATUS_BUILT, TX_STATUS_PENDING]))
-            .select(transaction_logs::transaction_id_hex)
+            .select((
+                transaction_logs::transaction_id_hex,
+                transaction_logs::account_id_hex,
+            ))
+            .distinct()
             .load(conn)?;
+        let transaction_log_ids: Vec<&String> = updated_logs.iter().map(|(id, _)| id).collect();
 
         diesel::update(
             transaction_logs::table
@@ -511,6 +698,18 @@ impl TransactionLogModel for TransactionLog {
         ))
         .execute(conn)?;
 
+        for (transaction_log_id, account_id_hex) in &updated_logs {
+            WalletEvent::create(
+                EVENT_TYPE_TRANSACTION_SUCCEEDED,
+                &json!({
+                    "transaction_log_id": transaction_log_id,
+                    "account_id": account_id_hex,
+                    "finalized_block_index": finalized_block_index.to_string(),
+                }),
+                conn,
+            )?;
+        }
+
         Ok(())
     }
 
@@ -525,14 +724,19 @@ impl TransactionLogModel for TransactionLog {
         // Find all transaction_logs that are BUILT or PENDING that are associated
         // with the txo id when it is used as an input.
         // Update the status to FAILED
-        let transaction_log_ids: Vec<String> = transaction_logs::table
+        let updated_logs: Vec<(String, String)> = transaction_logs::table
             .inner_join(transaction_txo_types::table.on(
                 transaction_logs::transaction_id_hex.eq(transaction_txo_types::transaction_id_hex),
             ))
             .filter(transaction_txo_types::txo_id_hex.eq_any(txo_ids))
             .filter(transaction_logs::status.eq_any(vec![TX_STATUS_BUILT, TX_STATUS_PENDING]))
-            .select(transaction_logs::transaction_id_hex)
+            .select((
+                transaction_logs::transaction_id_hex,
+                transaction_logs::account_id_hex,
+            ))
+            .distinct()
             .load(conn)?;
+        let transaction_log_ids: Vec<&String> = updated_logs.iter().map(|(id, _)| id).collect();
 
         diesel::update(
             transaction_logs::table
@@ -541,8 +745,270 @@ impl TransactionLogModel for TransactionLog {
         .set((transaction_logs::status.eq(TX_STATUS_FAILED),))
         .execute(conn)?;
 
+        for (transaction_log_id, account_id_hex) in &updated_logs {
+            WalletEvent::create(
+                EVENT_TYPE_TRANSACTION_FAILED,
+                &json!({
+                    "transaction_log_id": transaction_log_id,
+                    "account_id": account_id_hex,
+                }),
+                conn,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    fn list_sent_with_status(
+        status: &str,
+        sent_since: Option<i64>,
+        conn: &Conn,
+    ) -> Result<Vec<TransactionLog>, WalletDbError> {
+        use crate::db::schema::transaction_logs;
+
+        let mut query = transaction_logs::table
+            .filter(transaction_logs::direction.eq(TX_DIRECTION_SENT))
+            .filter(transaction_logs::status.eq(status))
+            .into_boxed();
+        if let Some(sent_since) = sent_since {
+            query = query.filter(transaction_logs::sent_time.ge(sent_since));
+        }
+        Ok(query.order(transaction_logs::id.asc()).load(conn)?)
+    }
+
+    fn update_expired_to_failed(
+        &self,
+        tombstone_block: u64,
+        conn: &Conn,
+    ) -> Result<(), WalletDbError> {
+        use crate::db::schema::{transaction_logs, transaction_txo_types, txos};
+
+        diesel::update(
+            transaction_logs::table
+                .filter(transaction_logs::transaction_id_hex.eq(&self.transaction_id_hex)),
+        )
+        .set(transaction_logs::status.eq(TX_STATUS_FAILED))
+        .execute(conn)?;
+
+        // Only release inputs this transaction still holds, in case they have
+        // since been released and used by another.
+        let input_txo_ids: Vec<String> = transaction_txo_types::table
+            .filter(transaction_txo_types::transaction_id_hex.eq(&self.transaction_id_hex))
+            .filter(transaction_txo_types::transaction_txo_type.eq(TXO_USED_AS_INPUT))
+            .select(transaction_txo_types::txo_id_hex)
+            .load(conn)?;
+        diesel::update(
+            txos::table
+                .filter(txos::txo_id_hex.eq_any(input_txo_ids))
+                .filter(txos::spent_block_index.is_null())
+                .filter(txos::pending_tombstone_block_index.le(tombstone_block as i64)),
+        )
+        .set(txos::pending_tombstone_block_index.eq::<Option<i64>>(None))
+        .execute(conn)?;
+
+        WalletEvent::create(
+            EVENT_TYPE_TRANSACTION_FAILED,
+            &json!({
+                "transaction_log_id": self.transaction_id_hex,
+                "account_id": self.account_id_hex,
+            }),
+            conn,
+        )?;
+
+        Ok(())
+    }
+
+    fn record_resubmission(
+        &self,
+        resubmitted_transaction_id_hex: &str,
+        conn: &Conn,
+    ) -> Result<(), WalletDbError> {
+        use crate::db::schema::transaction_logs;
+
+        diesel::update(
+            transaction_logs::table
+                .filter(transaction_logs::transaction_id_hex.eq(&self.transaction_id_hex)),
+        )
+        .set(transaction_logs::resubmitted_transaction_id_hex.eq(resubmitted_transaction_id_hex))
+        .execute(conn)?;
         Ok(())
     }
+
+    fn is_resubmission(&self, conn: &Conn) -> Result<bool, WalletDbError> {
+        use crate::db::schema::transaction_logs;
+
+        let count: i64 = transaction_logs::table
+            .filter(transaction_logs::resubmitted_transaction_id_hex.eq(&self.transaction_id_hex))
+            .count()
+            .get_result(conn)?;
+        Ok(count > 0)
+    }
+}
+
+/// Remove the given logs, and the records of which txos they involved.
+fn delete_logs(transaction_ids: &[String], conn: &Conn) -> Result<usize, WalletDbError> {
+    use crate::db::schema::{transaction_logs, transaction_txo_types};
+
+    diesel::delete(
+        transaction_txo_types::table
+            .filter(transaction_txo_types::transaction_id_hex.eq_any(transaction_ids)),
+    )
+    .execute(conn)?;
+    Ok(diesel::delete(
+        transaction_logs::table
+            .filter(transaction_logs::transaction_id_hex.eq_any(transaction_ids)),
+    )
+    .execute(conn)?)
+}
+
+/// Log a transaction sent from this wallet, which has been submitted if a
+/// submitted block index is given.
+fn log_sent(
+    tx_proposal: TxProposal,
+    submitted_block_index: Option<u64>,
+    status: &str,
+    comment: String,
+    account_id_hex: &str,
+    conn: &Conn,
+) -> Result<TransactionLog, WalletDbError> {
+    // Verify that the account exists.
+    Account::get(&AccountID(account_id_hex.to_string()), conn)?;
+
+    // Store the txo_id_hex -> transaction_txo_type
+    let mut txo_ids: Vec<(String, String)> = Vec::new();
+
+    // Verify that the TxProposal is well-formed according to our assumptions about
+    // how to store the sent data in our wallet (num_output_TXOs = num_outlays +
+    // change_TXOs, within the protocol's limit on outputs).
+    if tx_proposal.tx.prefix.outputs.len() > MAX_OUTPUTS as usize {
+        return Err(WalletDbError::UnexpectedNumberOfChangeOutputs);
+    }
+
+    // First update all inputs to "pending." They will remain pending until their
+    // key_image hits the ledger or their tombstone block is exceeded.
+    for utxo in tx_proposal.utxos.iter() {
+        let txo_id = TxoID::from(&utxo.tx_out);
+        let txo = Txo::get(&txo_id.to_string(), conn)?;
+        txo.update_to_pending(tx_proposal.tx.prefix.tombstone_block, conn)?;
+        txo_ids.push((txo_id.to_string(), TXO_USED_AS_INPUT.to_string()));
+    }
+
+    // Next, add all of our minted outputs to the Txo Table
+    for (i, output) in tx_proposal.tx.prefix.outputs.iter().enumerate() {
+        let processed_output = Txo::create_minted(account_id_hex, output, &tx_proposal, i, conn)?;
+        txo_ids.push((
+            processed_output.txo_id_hex,
+            processed_output.txo_type.to_string(),
+        ));
+    }
+
+    // Enforce maximum value.
+    let transaction_value = tx_proposal
+        .outlays
+        .iter()
+        .map(|o| o.value as u128)
+        .sum::<u128>();
+    if transaction_value > u64::MAX as u128 {
+        return Err(WalletDbError::TransactionValueExceedsMax);
+    }
+
+    let transaction_id = TransactionID::from(&tx_proposal.tx);
+    let tx = mc_util_serial::encode(&tx_proposal.tx);
+
+    // Create a TransactionLogs entry
+    let new_transaction_log = NewTransactionLog {
+        transaction_id_hex: &transaction_id.to_string(),
+        account_id_hex, // Can be null if submitting an "unowned" proposal.
+        assigned_subaddress_b58: None, // NULL for sent
+        value: transaction_value as i64,
+        fee: Some(tx_proposal.tx.prefix.fee as i64),
+        status,
+        sent_time: Some(Utc::now().timestamp()),
+        submitted_block_index: submitted_block_index.map(|index| index as i64),
+        finalized_block_index: None,
+        comment: &comment,
+        direction: TX_DIRECTION_SENT,
+        tx: Some(&tx),
+    };
+    diesel::insert_into(crate::db::schema::transaction_logs::table)
+        .values(&new_transaction_log)
+        .execute(conn)?;
+
+    // Create an entry per TXO for the TransactionTxoTypes
+    for (txo_id_hex, transaction_txo_type) in txo_ids {
+        let new_transaction_txo = NewTransactionTxoType {
+            transaction_id_hex: &transaction_id.to_string(),
+            txo_id_hex: &txo_id_hex,
+            transaction_txo_type: &transaction_txo_type,
+        };
+        diesel::insert_into(crate::db::schema::transaction_txo_types::table)
+            .values(&new_transaction_txo)
+            .execute(conn)?;
+    }
+    TransactionLog::get(&transaction_id.to_string(), conn)
+}
+
+/// Group the rows joining transaction logs to their txos by log, in log
+/// order.
+fn group_associated_txos(
+    transactions: Vec<(TransactionLog, TransactionTxoType, Txo)>,
+) -> Result<Vec<(TransactionLog, AssociatedTxos)>, WalletDbError> {
+    #[derive(Clone)]
+    struct TransactionContents {
+        transaction_log: TransactionLog,
+        inputs: Vec<Txo>,
+        outputs: Vec<Txo>,
+        change: Vec<Txo>,
+    }
+    let mut results: HashMap<String, TransactionContents> = HashMap::default();
+    for (transaction, transaction_txo_type, txo) in transactions {
+        if results.get(&transaction.transaction_id_hex).is_none() {
+            results.insert(
+                transaction.transaction_id_hex.clone(),
+                TransactionContents {
+                    transaction_log: transaction.clone(),
+                    inputs: Vec::new(),
+                    outputs: Vec::new(),
+                    change: Vec::new(),
+                },
+            );
+        };
+
+        let entry = results.get_mut(&transaction.transaction_id_hex).unwrap();
+
+        if entry.transaction_log != transaction {
+            return Err(WalletDbError::TransactionMismatch);
+        }
+
+        match transaction_txo_type.transaction_txo_type.as_str() {
+            TXO_USED_AS_INPUT => entry.inputs.push(txo),
+            TXO_USED_AS_OUTPUT => entry.outputs.push(txo),
+            TXO_USED_AS_CHANGE => entry.change.push(txo),
+            _ => {
+                return Err(WalletDbError::UnexpectedTransactionTxoType(
+                    transaction_txo_type.transaction_txo_type,
+                ));
+            }
+        }
+    }
+
+    let mut results: Vec<(TransactionLog, AssociatedTxos)> = results
+        .values()
+        .cloned()
+        .map(|t| {
+            (
+                t.transaction_log,
+                AssociatedTxos {
+                    inputs: t.inputs,
+                    outputs: t.outputs,
+                    change: t.change,
+                },
+            )
+        })
+        .collect();
+
+    results.sort_by_key(|r| r.0.id);
+    Ok(results)
 }
 
 #[cfg(test)]
@@ -886,6 +1352,165 @@ mod tests {
         assert_eq!(associated.change.len(), 1);
     }
 
+    #[test_with_logger]
+    fn test_log_built_and_cleanup(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let db_test_context = WalletDbTestContext::default();
+        let wallet_db = db_test_context.get_db_instance(logger.clone());
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let mut ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        let account_key = random_account_with_seed_values(
+            &wallet_db,
+            &mut ledger_db,
+            &vec![100 * MOB, 200 * MOB],
+            &mut rng,
+            &logger,
+        );
+        let account_id_hex = AccountID::from(&account_key).to_string();
+
+        let conn = wallet_db.get_conn().unwrap();
+        let (recipient, mut builder) =
+            builder_for_random_recipient(&account_key, &ledger_db, &mut rng, &logger);
+        builder.add_recipient(recipient, 50 * MOB).unwrap();
+        builder.set_tombstone(0).unwrap();
+        builder.select_txos(&conn, None, false).unwrap();
+        let tx_proposal = builder.build(&conn).unwrap();
+
+        // A built transaction has no submitted block, until it is submitted.
+        let tx_log =
+            TransactionLog::log_built(tx_proposal, "".to_string(), &account_id_hex, &conn).unwrap();
+        assert_eq!(tx_log.status, TX_STATUS_BUILT);
+        assert_eq!(tx_log.submitted_block_index, None);
+        assert!(tx_log.sent_time.unwrap() > 0);
+
+        // Only the given statuses, and logs sent before the given time, are
+        // removed.
+        assert_eq!(
+            TransactionLog::delete_sent_with_status(&[TX_STATUS_FAILED], None, &conn).unwrap(),
+            0
+        );
+        assert_eq!(
+            TransactionLog::delete_sent_with_status(&[TX_STATUS_BUILT], Some(0), &conn).unwrap(),
+            0
+        );
+
+        let submitted = tx_log
+            .update_to_submitted(12, "submitted".to_string(), &conn)
+            .unwrap();
+        assert_eq!(submitted.status, TX_STATUS_PENDING);
+        assert_eq!(submitted.submitted_block_index, Some(12));
+        assert_eq!(submitted.comment, "submitted");
+
+        // Mark the transaction failed, as sync does once its inputs pass their
+        // tombstone block.
+        let inputs = submitted.get_associated_txos(&conn).unwrap().inputs;
+        assert_eq!(
+            TransactionLog::delete_built_associated_with_txos(&inputs, &conn).unwrap(),
+            0
+        );
+        TransactionLog::update_tx_logs_associated_with_txos_to_failed(&inputs, &conn).unwrap();
+
+        assert_eq!(
+            TransactionLog::delete_sent_with_status(
+                &[TX_STATUS_BUILT, TX_STATUS_FAILED],
+                None,
+                &conn
+            )
+            .unwrap(),
+            1
+        );
+        match TransactionLog::get(&submitted.transaction_id_hex, &conn) {
+            Err(WalletDbError::TransactionLogNotFound(_)) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test_with_logger]
+    fn test_expire_and_resubmit(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let db_test_context = WalletDbTestContext::default();
+        let wallet_db = db_test_context.get_db_instance(logger.clone());
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let mut ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        let account_key = random_account_with_seed_values(
+            &wallet_db,
+            &mut ledger_db,
+            &vec![100 * MOB],
+            &mut rng,
+            &logger,
+        );
+        let account_id_hex = AccountID::from(&account_key).to_string();
+
+        let conn = wallet_db.get_conn().unwrap();
+        let submit = |rng: &mut StdRng| {
+            let (recipient, mut builder) =
+                builder_for_random_recipient(&account_key, &ledger_db, rng, &logger);
+            builder.add_recipient(recipient, 50 * MOB).unwrap();
+            builder.set_tombstone(0).unwrap();
+            builder.select_txos(&conn, None, false).unwrap();
+            let tx_proposal = builder.build(&conn).unwrap();
+            TransactionLog::log_submitted(tx_proposal, 12, "".to_string(), &account_id_hex, &conn)
+                .unwrap()
+        };
+
+        let expired = submit(&mut rng);
+        let inputs = expired.get_associated_txos(&conn).unwrap().inputs;
+        let tombstone_block = inputs[0].pending_tombstone_block_index.unwrap() as u64;
+        assert_eq!(
+            TransactionLog::list_sent_with_status(TX_STATUS_PENDING, None, &conn).unwrap(),
+            vec![expired.clone()]
+        );
+
+        // Failing the transaction releases its inputs to be spent again.
+        expired
+            .update_expired_to_failed(tombstone_block, &conn)
+            .unwrap();
+        let expired = TransactionLog::get(&expired.transaction_id_hex, &conn).unwrap();
+        assert_eq!(expired.status, TX_STATUS_FAILED);
+        assert_eq!(
+            Txo::get(&inputs[0].txo_id_hex, &conn)
+                .unwrap()
+                .pending_tombstone_block_index,
+            None
+        );
+        assert!(
+            TransactionLog::list_sent_with_status(TX_STATUS_PENDING, None, &conn)
+                .unwrap()
+                .is_empty()
+        );
+        let sent_time = expired.sent_time.unwrap();
+        assert_eq!(
+            TransactionLog::list_sent_with_status(TX_STATUS_FAILED, Some(sent_time), &conn)
+                .unwrap()
+                .len(),
+            1
+        );
+        assert!(TransactionLog::list_sent_with_status(
+            TX_STATUS_FAILED,
+            Some(sent_time + 1),
+            &conn
+        )
+        .unwrap()
+        .is_empty());
+
+        let resubmitted = submit(&mut rng);
+        expired
+            .record_resubmission(&resubmitted.transaction_id_hex, &conn)
+            .unwrap();
+        assert_eq!(
+            TransactionLog::get(&expired.transaction_id_hex, &conn)
+                .unwrap()
+                .resubmitted_transaction_id_hex,
+            Some(resubmitted.transaction_id_hex.clone())
+        );
+        assert!(resubmitted.is_resubmission(&conn).unwrap());
+        assert!(!expired.is_resubmission(&conn).unwrap());
+    }
+
     #[test_with_logger]
     fn test_delete_transaction_logs_for_account(logger: Logger) {
         use crate::db::schema::{transaction_logs, transaction_txo_types};
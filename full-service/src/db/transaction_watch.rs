@@ -0,0 +1,341 @@
+// Copyright (c) 2020-2022 MobileCoin Inc.
+
+//! The Transaction Watch Model.
+//!
+//! A transaction watch tracks the confirmation of a transaction that this
+//! wallet did not build, for example one submitted by another instance or by
+//! an offline signer, by looking for its outputs in the ledger.
+
+use crate::db::{
+    models::{
+        NewTransactionWatch, TransactionWatch, WalletEvent, WalletSetting,
+        EVENT_TYPE_TRANSACTION_EXPIRED, EVENT_TYPE_TRANSACTION_LANDED, WATCH_STATUS_EXPIRED,
+        WATCH_STATUS_LANDED, WATCH_STATUS_PENDING,
+    },
+    wallet_event::WalletEventModel,
+    wallet_settings::WalletSettingModel,
+    Conn, WalletDbError,
+};
+use diesel::prelude::*;
+use mc_crypto_keys::CompressedRistrettoPublic;
+use mc_ledger_db::{Ledger, LedgerDB};
+use serde_json::json;
+use std::convert::TryFrom;
+
+pub trait TransactionWatchModel {
+    /// Start watching for a transaction with the given output public keys.
+    ///
+    /// The watch is identified by its first output public key, hex encoded in
+    /// the same way as a txo public key, so watching the same transaction
+    /// twice returns the existing watch.
+    fn create(
+        tx_public_keys: &[CompressedRistrettoPublic],
+        tombstone_block_index: Option<u64>,
+        comment: &str,
+        conn: &Conn,
+    ) -> Result<TransactionWatch, WalletDbError>;
+
+    /// Get a transaction watch by its id.
+    fn get(watch_id_hex: &str, conn: &Conn) -> Result<TransactionWatch, WalletDbError>;
+
+    /// List all transaction watches.
+    fn list_all(
+        offset: Option<u64>,
+        limit: Option<u64>,
+        conn: &Conn,
+    ) -> Result<Vec<TransactionWatch>, WalletDbError>;
+
+    /// List the transaction watches which have neither landed nor expired.
+    fn list_pending(conn: &Conn) -> Result<Vec<TransactionWatch>, WalletDbError>;
+
+    /// The public keys of the watched transaction's outputs.
+    fn public_keys(&self) -> Result<Vec<CompressedRistrettoPublic>, WalletDbError>;
+
+    /// Check the ledger for the watched transaction, and mark the watch as
+    /// landed or expired, recording an event, if it has reached either state.
+    ///
+    /// A transaction is only considered landed once it is buried under the
+    /// wallet's confirmation depth.
+    ///
+    /// Returns the watch as it is after the check.
+    fn update_from_ledger(
+        &self,
+        ledger_db: &LedgerDB,
+        conn: &Conn,
+    ) -> Result<TransactionWatch, WalletDbError>;
+}
+
+impl TransactionWatchModel for TransactionWatch {
+    fn create(
+        tx_public_keys: &[CompressedRistrettoPublic],
+        tombstone_block_index: Option<u64>,
+        comment: &str,
+        conn: &Conn,
+    ) -> Result<TransactionWatch, WalletDbError> {
+        use crate::db::schema::transaction_watches;
+
+        let watch_id_hex = match tx_public_keys.first() {
+            Some(public_key) => hex::encode(mc_util_serial::encode(public_key)),
+            None => return Err(WalletDbError::TransactionWatchLacksOutputs),
+        };
+        if let Ok(existing) = TransactionWatch::get(&watch_id_hex, conn) {
+            return Ok(existing);
+        }
+
+        let encoded_public_keys: Vec<u8> = tx_public_keys
+            .iter()
+            .flat_map(|public_key| public_key.as_bytes().to_vec())
+            .collect();
+        let new_watch = NewTransactionWatch {
+            watch_id_hex: &watch_id_hex,
+            tx_public_keys: &encoded_public_keys,
+            tombstone_block_index: tombstone_block_index.map(|i| i as i64),
+            status: WATCH_STATUS_PENDING,
+            comment,
+        };
+        diesel::insert_into(transaction_watches::table)
+            .values(&new_watch)
+            .execute(conn)?;
+
+        TransactionWatch::get(&watch_id_hex, conn)
+    }
+
+    fn get(watch_id_hex: &str, conn: &Conn) -> Result<TransactionWatch, WalletDbError> {
+        use crate::db::schema::transaction_watches;
+
+        match transaction_watches::table
+            .filter(transaction_watches::watch_id_hex.eq(watch_id_hex))
+            .get_result::<TransactionWatch>(conn)
+        {
+            Ok(watch) => Ok(watch),
+            // Match on NotFound to get a more informative NotFound Error
+            Err(diesel::result::Error::NotFound) => Err(WalletDbError::TransactionWatchNotFound(
+                watch_id_hex.to_string(),
+            )),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn list_all(
+        offset: Option<u64>,
+        limit: Option<u64>,
+        conn: &Conn,
+    ) -> Result<Vec<TransactionWatch>, WalletDbError> {
+        use crate::db::schema::transaction_watches;
+
+        let query = transaction_watches::table.order(transaction_watches::id.asc());
+
+        Ok(match (offset, limit) {
+            (Some(o), Some(l)) => query
+                .offset(o as i64)
+                .limit(l as i64)
+                .load::<TransactionWatch>(conn)?,
+            _ => query.load::<TransactionWatch>(conn)?,
+        })
+    }
+
+    fn list_pending(conn: &Conn) -> Result<Vec<TransactionWatch>, WalletDbError> {
+        use crate::db::schema::transaction_watches;
+
+        Ok(transaction_watches::table
+            .filter(transaction_watches::status.eq(WATCH_STATUS_PENDING))
+            .order(transaction_watches::id.asc())
+            .load::<TransactionWatch>(conn)?)
+    }
+
+    fn public_keys(&self) -> Result<Vec<CompressedRistrettoPublic>, WalletDbError> {
+        Ok(self
+            .tx_public_keys
+            .chunks(32)
+            .map(CompressedRistrettoPublic::try_from)
+            .collect::<Result<Vec<_>, _>>()?)
+    }
+
+    fn update_from_ledger(
+        &self,
+        ledger_db: &LedgerDB,
+        conn: &Conn,
+    ) -> Result<TransactionWatch, WalletDbError> {
+        use crate::db::schema::transaction_watches;
+
+        if self.status != WATCH_STATUS_PENDING {
+            return Ok(self.clone());
+        }
+
+        // The transaction has landed once all of its outputs are in the
+        // ledger, in the block containing the last of them.
+        let mut landed_block_index = Some(0);
+        for public_key in self.public_keys()? {
+            match ledger_db.get_tx_out_index_by_public_key(&public_key) {
+                Ok(tx_out_index) => {
+                    let block_index = ledger_db.get_block_index_by_tx_out_index(tx_out_index)?;
+                    landed_block_index = landed_block_index.map(|i: u64| i.max(block_index));
+                }
+                Err(mc_ledger_db::Error::NotFound) => {
+                    landed_block_index = None;
+                    break;
+                }
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        // A transaction can only land in a block before its tombstone block.
+        let num_blocks = ledger_db.num_blocks()?;
+        let confirmation_depth = WalletSetting::get_settings(conn)?.confirmation_depth;
+        let (status, event_type, finalized_block_index) =
+            match (landed_block_index, self.tombstone_block_index) {
+                (Some(block_index), _) if num_blocks - block_index >= confirmation_depth => (
+                    WATCH_STATUS_LANDED,
+                    EVENT_TYPE_TRANSACTION_LANDED,
+                    block_index,
+                ),
+                // Landed, but not yet buried deep enough.
+                (Some(_), _) => return Ok(self.clone()),
+                (None, Some(tombstone)) if num_blocks >= tombstone as u64 => (
+                    WATCH_STATUS_EXPIRED,
+                    EVENT_TYPE_TRANSACTION_EXPIRED,
+                    num_blocks - 1,
+                ),
+                _ => return Ok(self.clone()),
+            };
+
+        diesel::update(transaction_watches::table.filter(transaction_watches::id.eq(self.id)))
+            .set((
+                transaction_watches::status.eq(status),
+                transaction_watches::finalized_block_index.eq(Some(finalized_block_index as i64)),
+            ))
+            .execute(conn)?;
+
+        WalletEvent::create(
+            event_type,
+            &json!({
+                "watch_id": self.watch_id_hex,
+                "status": status,
+                "block_index": finalized_block_index.to_string(),
+                "comment": self.comment,
+            }),
+            conn,
+        )?;
+
+        TransactionWatch::get(&self.watch_id_hex, conn)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        db::wallet_settings::SETTING_CONFIRMATION_DEPTH,
+        test_utils::{get_test_ledger, WalletDbTestContext},
+    };
+    use mc_common::logger::{test_with_logger, Logger};
+    use mc_crypto_keys::RistrettoPublic;
+    use mc_util_from_random::FromRandom;
+    use rand::{rngs::StdRng, SeedableRng};
+    use std::collections::BTreeMap;
+
+    #[test_with_logger]
+    fn test_transaction_watch_update_from_ledger(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let db_test_context = WalletDbTestContext::default();
+        let wallet_db = db_test_context.get_db_instance(logger);
+        let conn = wallet_db.get_conn().unwrap();
+
+        let known_recipients = vec![];
+        let ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+        let num_blocks = ledger_db.num_blocks().unwrap();
+
+        // An output which is already in the ledger.
+        let block_contents = ledger_db.get_block_contents(num_blocks - 1).unwrap();
+        let landed_public_key = block_contents.outputs[0].public_key;
+
+        let watch = TransactionWatch::create(&[landed_public_key], None, "landed", &conn).unwrap();
+        assert_eq!(watch.status, WATCH_STATUS_PENDING);
+        assert_eq!(watch.public_keys().unwrap(), vec![landed_public_key]);
+
+        // Watching the same transaction again returns the same watch.
+        let again = TransactionWatch::create(&[landed_public_key], None, "", &conn).unwrap();
+        assert_eq!(again, watch);
+
+        let watch = watch.update_from_ledger(&ledger_db, &conn).unwrap();
+        assert_eq!(watch.status, WATCH_STATUS_LANDED);
+        assert_eq!(watch.finalized_block_index, Some((num_blocks - 1) as i64));
+
+        // Outputs which are not in the ledger stay pending until the
+        // tombstone block.
+        let missing_public_key =
+            CompressedRistrettoPublic::from(&RistrettoPublic::from_random(&mut rng));
+        let pending = TransactionWatch::create(
+            &[missing_public_key],
+            Some(num_blocks + 1),
+            "pending",
+            &conn,
+        )
+        .unwrap()
+        .update_from_ledger(&ledger_db, &conn)
+        .unwrap();
+        assert_eq!(pending.status, WATCH_STATUS_PENDING);
+
+        let missing_public_key =
+            CompressedRistrettoPublic::from(&RistrettoPublic::from_random(&mut rng));
+        let expired =
+            TransactionWatch::create(&[missing_public_key], Some(num_blocks), "expired", &conn)
+                .unwrap()
+                .update_from_ledger(&ledger_db, &conn)
+                .unwrap();
+        assert_eq!(expired.status, WATCH_STATUS_EXPIRED);
+
+        assert_eq!(
+            TransactionWatch::list_pending(&conn).unwrap(),
+            vec![pending]
+        );
+
+        let events = WalletEvent::list(None, None, &conn).unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].event_type, EVENT_TYPE_TRANSACTION_LANDED);
+        assert_eq!(events[1].event_type, EVENT_TYPE_TRANSACTION_EXPIRED);
+    }
+
+    #[test_with_logger]
+    fn test_transaction_watch_confirmation_depth(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let db_test_context = WalletDbTestContext::default();
+        let wallet_db = db_test_context.get_db_instance(logger);
+        let conn = wallet_db.get_conn().unwrap();
+
+        let known_recipients = vec![];
+        let ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+        let num_blocks = ledger_db.num_blocks().unwrap();
+
+        let mut settings = BTreeMap::new();
+        settings.insert(SETTING_CONFIRMATION_DEPTH.to_string(), "2".to_string());
+        WalletSetting::update_settings(&settings, &conn).unwrap();
+
+        // An output in the latest block is not yet deep enough, even once the
+        // tombstone block has passed.
+        let latest_public_key = ledger_db
+            .get_block_contents(num_blocks - 1)
+            .unwrap()
+            .outputs[0]
+            .public_key;
+        let watch = TransactionWatch::create(&[latest_public_key], Some(num_blocks), "", &conn)
+            .unwrap()
+            .update_from_ledger(&ledger_db, &conn)
+            .unwrap();
+        assert_eq!(watch.status, WATCH_STATUS_PENDING);
+
+        let buried_public_key = ledger_db
+            .get_block_contents(num_blocks - 2)
+            .unwrap()
+            .outputs[0]
+            .public_key;
+        let watch = TransactionWatch::create(&[buried_public_key], None, "", &conn)
+            .unwrap()
+            .update_from_ledger(&ledger_db, &conn)
+            .unwrap();
+        assert_eq!(watch.status, WATCH_STATUS_LANDED);
+        assert_eq!(watch.finalized_block_index, Some((num_blocks - 2) as i64));
+    }
+}
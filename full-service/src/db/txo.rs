@@ -15,17 +15,24 @@ use mc_transaction_core::{
     tx::{TxOut, TxOutConfirmationNumber},
     Amount, Token,
 };
-use std::fmt;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+};
 
 use crate::{
     db::{
         account::{AccountID, AccountModel},
         assigned_subaddress::AssignedSubaddressModel,
+        isolated_counterparty::IsolatedCounterpartyModel,
         models::{
-            Account, AssignedSubaddress, NewTxo, Txo, TXO_USED_AS_CHANGE, TXO_USED_AS_OUTPUT,
+            Account, AssignedSubaddress, IsolatedCounterparty, NewTxo, Txo, TXO_STATUS_ORPHANED,
+            TXO_STATUS_PENDING, TXO_STATUS_SECRETED, TXO_STATUS_SPENT, TXO_STATUS_UNSPENT,
+            TXO_USED_AS_CHANGE, TXO_USED_AS_OUTPUT,
         },
         Conn, WalletDbError,
     },
+    service::{sync::decode_amount, transaction_builder::CoinSelectionStrategy},
     util::b58::b58_encode_public_address,
 };
 
@@ -60,6 +67,143 @@ pub struct SpendableTxosResult {
     pub max_spendable_in_wallet: u128,
 }
 
+/// What an account is short of when it can't spend a value in one
+/// transaction, and whether combining its txos would let it.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct InsufficientFundsDetails {
+    /// The value asked for, including the network fee.
+    pub target_value: u64,
+
+    /// The total value of the account's spendable txos.
+    pub total_unspent_value: u128,
+
+    /// The most the account can spend in one transaction, after the fee.
+    pub max_spendable_value: u128,
+
+    /// How much more the account would need to receive for its txos to sum to
+    /// the target value.
+    pub shortfall: u128,
+
+    /// How many of the account's largest txos sum to the target value, which
+    /// is more than MAX_INPUTS when they must be combined first. None when all
+    /// of them don't.
+    pub txos_needed: Option<usize>,
+
+    /// Whether combining the account's txos, paying the fee to do so, would
+    /// let it spend the target value.
+    pub consolidation_would_help: bool,
+}
+
+impl InsufficientFundsDetails {
+    /// The details for spending a target value from txos of the given values,
+    /// largest first, where combining them costs the given fee.
+    pub fn new(txo_values: &[u64], target_value: u64, fee: u64) -> Self {
+        let total_unspent_value: u128 = txo_values.iter().map(|value| *value as u128).sum();
+        let max_spendable_value = txo_values
+            .iter()
+            .take(MAX_INPUTS as usize)
+            .map(|value| *value as u128)
+            .sum::<u128>()
+            .saturating_sub(fee as u128);
+
+        let mut running_total: u128 = 0;
+        let txos_needed = txo_values.iter().position(|value| {
+            running_total += *value as u128;
+            running_total >= target_value as u128
+        });
+
+        Self {
+            target_value,
+            total_unspent_value,
+            max_spendable_value,
+            shortfall: (target_value as u128).saturating_sub(total_unspent_value),
+            txos_needed: txos_needed.map(|index| index + 1),
+            consolidation_would_help: total_unspent_value >= target_value as u128 + fee as u128,
+        }
+    }
+}
+
+impl fmt::Display for InsufficientFundsDetails {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Max spendable value in wallet: {:?}, but target value: {:?}",
+            self.max_spendable_value, self.target_value
+        )
+    }
+}
+
+/// The total value of an account's txos in each status.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TxoStatusTotals {
+    pub unspent: u128,
+    pub pending: u128,
+    pub spent: u128,
+    pub secreted: u128,
+    pub orphaned: u128,
+    /// The part of the unspent total which is frozen.
+    pub frozen: u128,
+}
+
+/// Criteria for selecting txos to export. Unset fields match all txos.
+#[derive(Clone, Debug, Default)]
+pub struct TxoExportFilter {
+    /// Only export txos received or minted by this account.
+    pub account_id_hex: Option<String>,
+
+    /// Only export txos with this status, e.g. TXO_STATUS_UNSPENT. Statuses
+    /// are relative to the account, or to any account if none is given.
+    pub status: Option<String>,
+
+    /// Only export txos of this token.
+    pub token_id: Option<u64>,
+
+    /// Only export txos received at or after this block index.
+    pub min_received_block_index: Option<u64>,
+
+    /// Only export txos received at or before this block index.
+    pub max_received_block_index: Option<u64>,
+
+    /// Only export txos the txo classifier gave this label.
+    pub classification: Option<String>,
+}
+
+/// Txo values are u64s stored in signed BigInt columns, so large values wrap to
+/// negative numbers and a plain SUM could overflow. Summing the low and high 32
+/// bits separately keeps every partial sum positive and within range. The sums
+/// are cast back to BIGINT, as Postgres widens the SUM of a BIGINT to NUMERIC.
+const SUM_VALUE_HALVES: &str = "CAST(SUM(value & 4294967295) AS BIGINT), \
+    CAST(SUM((value >> 32) & 4294967295) AS BIGINT)";
+
+/// As SUM_VALUE_HALVES, but only summing the txos which match the condition,
+/// so that several totals can be computed in one pass over the table.
+pub(crate) fn sum_value_halves_where(condition: &str) -> String {
+    format!(
+        "CAST(COALESCE(SUM(CASE WHEN {0} THEN value & 4294967295 END), 0) AS BIGINT), \
+        CAST(COALESCE(SUM(CASE WHEN {0} THEN (value >> 32) & 4294967295 END), 0) AS BIGINT)",
+        condition
+    )
+}
+
+pub(crate) fn combine_value_halves(low: i64, high: i64) -> u128 {
+    ((high as u128) << 32) + low as u128
+}
+
+/// The SQL condition restricting a query to the txos of an assigned
+/// subaddress, if one is given.
+fn subaddress_filter(
+    assigned_subaddress_b58: Option<&str>,
+    conn: &Conn,
+) -> Result<String, WalletDbError> {
+    Ok(match assigned_subaddress_b58 {
+        Some(subaddress_b58) => format!(
+            " AND subaddress_index = {}",
+            AssignedSubaddress::get(subaddress_b58, conn)?.subaddress_index
+        ),
+        None => String::new(),
+    })
+}
+
 pub trait TxoModel {
     /// Upserts a received Txo.
     ///
@@ -71,7 +215,7 @@ pub trait TxoModel {
     /// * `received_block_index` - the block at which the Txo was received.
     /// * `account_id_hex` - the account ID for the account which received this
     ///   Txo.
-    /// * `conn` - Sqlite database connection.
+    /// * `conn` - Wallet database connection.
     ///
     /// The subaddress_index may be None, and the Txo is said to be "orphaned",
     /// if the subaddress is not yet being tracked by the wallet.
@@ -118,6 +262,13 @@ pub trait TxoModel {
         conn: &Conn,
     ) -> Result<(), WalletDbError>;
 
+    /// Cache the index of a Txo's TxOut in the ledger.
+    fn update_ledger_txo_index(
+        &self,
+        ledger_txo_index: u64,
+        conn: &Conn,
+    ) -> Result<(), WalletDbError>;
+
     /// Update a Txo's status to pending
     fn update_to_pending(
         &self,
@@ -132,6 +283,50 @@ pub trait TxoModel {
         conn: &Conn,
     ) -> Result<(), WalletDbError>;
 
+    /// Flag a Txo as possibly impersonating the given address.
+    fn update_mimicked_address(
+        txo_id_hex: &str,
+        mimicked_address_b58: &str,
+        conn: &Conn,
+    ) -> Result<(), WalletDbError>;
+
+    /// Record the address hashes decoded from a Txo's memo.
+    fn update_memo_address_hashes(
+        txo_id_hex: &str,
+        sender_address_hash: Option<&str>,
+        destination_address_hash: Option<&str>,
+        conn: &Conn,
+    ) -> Result<(), WalletDbError>;
+
+    /// Record the label the txo classifier gave a received Txo, or clear it.
+    fn update_classification(
+        txo_id_hex: &str,
+        classification: Option<&str>,
+        conn: &Conn,
+    ) -> Result<(), WalletDbError>;
+
+    /// Freeze or unfreeze a Txo. Frozen Txos are left out of transactions.
+    fn update_frozen(&self, frozen: bool, conn: &Conn) -> Result<(), WalletDbError>;
+
+    /// Mark the account's txos which were spent at or after the block index as
+    /// pending until the block they were spent in, returning how many there
+    /// were. Rescanning those blocks marks them spent again when their key
+    /// images are found, and unspent if they never are.
+    fn update_spent_from_block_index_to_pending(
+        account_id_hex: &str,
+        block_index: u64,
+        conn: &Conn,
+    ) -> Result<usize, WalletDbError>;
+
+    /// Forget that the account received the txos it received at or after the
+    /// block index, returning how many there were. Txos the wallet didn't mint
+    /// are no longer referenced, and are removed by delete_unreferenced.
+    fn roll_back_received_from_block_index(
+        account_id_hex: &str,
+        block_index: u64,
+        conn: &Conn,
+    ) -> Result<usize, WalletDbError>;
+
     /// Update all Txo's that are pending with a pending_tombstone_block_index
     /// less than the target block index to unspent
     fn update_txos_exceeding_pending_tombstone_block_index_to_unspent(
@@ -148,6 +343,21 @@ pub trait TxoModel {
         conn: &Conn,
     ) -> Result<Vec<Txo>, WalletDbError>;
 
+    /// Get a page of the Txos associated with a given account, in insertion
+    /// order.
+    ///
+    /// Returns:
+    /// * Up to `limit` txos with a primary key greater than `after_id`, after
+    ///   skipping `offset` of them.
+    fn list_page_for_account(
+        account_id_hex: &str,
+        after_id: Option<i32>,
+        offset: Option<u64>,
+        limit: u64,
+        token_id: Option<u64>,
+        conn: &Conn,
+    ) -> Result<Vec<Txo>, WalletDbError>;
+
     fn list_for_address(
         assigned_subaddress_b58: &str,
         token_id: Option<u64>,
@@ -168,6 +378,16 @@ pub trait TxoModel {
         conn: &Conn,
     ) -> Result<HashMap<KeyImage, String>, WalletDbError>;
 
+    /// Get a page of the received txos whose key images are known, of an
+    /// account or of every account, in insertion order, starting after the
+    /// txo with the given id.
+    fn list_key_images_page(
+        account_id_hex: Option<&str>,
+        after_id: Option<i32>,
+        limit: u64,
+        conn: &Conn,
+    ) -> Result<Vec<Txo>, WalletDbError>;
+
     fn list_spent(
         account_id_hex: &str,
         assigned_subaddress_b58: Option<&str>,
@@ -208,6 +428,13 @@ pub trait TxoModel {
         conn: &Conn,
     ) -> Result<Vec<Txo>, WalletDbError>;
 
+    /// Get the distinct addresses, other than its own, that an account has
+    /// sent to.
+    fn list_recipient_addresses(
+        account_id_hex: &str,
+        conn: &Conn,
+    ) -> Result<Vec<String>, WalletDbError>;
+
     fn list_pending_exceeding_block_index(
         account_id_hex: &str,
         block_index: u64,
@@ -215,6 +442,70 @@ pub trait TxoModel {
         conn: &Conn,
     ) -> Result<Vec<Txo>, WalletDbError>;
 
+    /// Get a page of txos matching a filter, in insertion order.
+    ///
+    /// Returns:
+    /// * Up to `limit` txos with a primary key greater than `after_id`.
+    fn list_for_export(
+        filter: &TxoExportFilter,
+        after_id: Option<i32>,
+        limit: u64,
+        conn: &Conn,
+    ) -> Result<Vec<Txo>, WalletDbError>;
+
+    /// Get the total value of each account's txos in each status, using
+    /// aggregate queries rather than loading the txos.
+    ///
+    /// Returns:
+    /// * Map from account_id_hex to the totals for that account. Accounts with
+    ///   no txos are omitted.
+    fn sum_values_by_account_and_status(
+        token_id: u64,
+        conn: &Conn,
+    ) -> Result<HashMap<String, TxoStatusTotals>, WalletDbError>;
+
+    /// Get the total value of an account's txos in each status, using
+    /// aggregate queries rather than loading the txos.
+    ///
+    /// If an assigned subaddress is given, only its txos are included, and
+    /// the secreted and orphaned totals are zero.
+    fn sum_values_for_account(
+        account_id_hex: &str,
+        assigned_subaddress_b58: Option<&str>,
+        token_id: u64,
+        conn: &Conn,
+    ) -> Result<TxoStatusTotals, WalletDbError>;
+
+    /// Sum the values of every txo the account has received of a token, spent
+    /// or not, leaving out change and txos the account sent to itself.
+    fn sum_received_for_account(
+        account_id_hex: &str,
+        token_id: u64,
+        conn: &Conn,
+    ) -> Result<u128, WalletDbError>;
+
+    /// List the txos the account has received from others in a range of
+    /// blocks, leaving out change and txos the account sent to itself, ordered
+    /// by the block they were received in.
+    fn list_received_from_others(
+        account_id_hex: &str,
+        min_block_index: Option<u64>,
+        max_block_index: Option<u64>,
+        conn: &Conn,
+    ) -> Result<Vec<Txo>, WalletDbError>;
+
+    /// List the tokens of all txos in the wallet, in token ID order.
+    fn list_token_ids(conn: &Conn) -> Result<Vec<u64>, WalletDbError>;
+
+    /// Get the largest value which can be spent in one transaction, as in
+    /// list_spendable, without loading the txos.
+    fn max_spendable_in_wallet(
+        account_id_hex: &str,
+        assigned_subaddress_b58: Option<&str>,
+        token_id: u64,
+        conn: &Conn,
+    ) -> Result<u128, WalletDbError>;
+
     /// Get the details for a specific Txo.
     ///
     /// Returns:
@@ -250,6 +541,7 @@ pub trait TxoModel {
         max_spendable_value: Option<u64>,
         pending_tombstone_block_index: Option<u64>,
         token_id: Option<u64>,
+        strategy: CoinSelectionStrategy,
         conn: &Conn,
     ) -> Result<Vec<Txo>, WalletDbError>;
 
@@ -372,11 +664,15 @@ impl TxoModel for Txo {
                 Some(outlay.receiver.clone()),
             )
         } else {
-            // This is the change output. Note: there should only be one change output
-            // per transaction, based on how we construct transactions. If we change
-            // how we construct transactions, these assumptions will change, and should be
-            // reflected in the TxProposal.
-            (change_value, None, None)
+            // This is a change output. A transaction may split its change over
+            // several outputs, so read this one's value from the output, which
+            // was sent to the account, falling back to all of the change.
+            let value = Account::get(&AccountID(account_id_hex.to_string()), conn)
+                .ok()
+                .and_then(|account| mc_util_serial::decode::<AccountKey>(&account.account_key).ok())
+                .and_then(|account_key| decode_amount(output, account_key.view_private_key()))
+                .map_or(change_value, |amount| amount.value);
+            (value, None, None)
         };
 
         // Update receiver, transaction_value, and transaction_txo_type, if outlay was
@@ -444,13 +740,21 @@ impl TxoModel for Txo {
 
         let encoded_key_image = received_key_image.map(|k| mc_util_serial::encode(&k));
 
+        // A txo found again by a rescan stays pending, as it may be the input
+        // of a transaction which has yet to land.
+        let pending_tombstone_block_index = if self.received_block_index.is_some() {
+            self.pending_tombstone_block_index
+        } else {
+            None
+        };
+
         diesel::update(self)
             .set((
                 txos::received_account_id_hex.eq(Some(received_account_id_hex)),
                 txos::received_block_index.eq(Some(block_index as i64)),
                 txos::subaddress_index.eq(received_subaddress_index.map(|i| i as i64)),
                 txos::key_image.eq(encoded_key_image),
-                txos::pending_tombstone_block_index.eq::<Option<i64>>(None),
+                txos::pending_tombstone_block_index.eq(pending_tombstone_block_index),
             ))
             .execute(conn)?;
         Ok(())
@@ -469,6 +773,19 @@ impl TxoModel for Txo {
         Ok(())
     }
 
+    fn update_ledger_txo_index(
+        &self,
+        ledger_txo_index: u64,
+        conn: &Conn,
+    ) -> Result<(), WalletDbError> {
+        use crate::db::schema::txos;
+
+        diesel::update(self)
+            .set(txos::ledger_txo_index.eq(Some(ledger_txo_index as i64)))
+            .execute(conn)?;
+        Ok(())
+    }
+
     fn update_to_pending(
         &self,
         pending_tombstone_block_index: u64,
@@ -498,6 +815,105 @@ impl TxoModel for Txo {
         Ok(())
     }
 
+    fn update_mimicked_address(
+        txo_id_hex: &str,
+        mimicked_address_b58: &str,
+        conn: &Conn,
+    ) -> Result<(), WalletDbError> {
+        use crate::db::schema::txos;
+
+        diesel::update(txos::table.filter(txos::txo_id_hex.eq(txo_id_hex)))
+            .set(txos::mimicked_address_b58.eq(Some(mimicked_address_b58)))
+            .execute(conn)?;
+        Ok(())
+    }
+
+    fn update_memo_address_hashes(
+        txo_id_hex: &str,
+        sender_address_hash: Option<&str>,
+        destination_address_hash: Option<&str>,
+        conn: &Conn,
+    ) -> Result<(), WalletDbError> {
+        use crate::db::schema::txos;
+
+        diesel::update(txos::table.filter(txos::txo_id_hex.eq(txo_id_hex)))
+            .set((
+                txos::sender_address_hash.eq(sender_address_hash),
+                txos::destination_address_hash.eq(destination_address_hash),
+            ))
+            .execute(conn)?;
+        Ok(())
+    }
+
+    fn update_classification(
+        txo_id_hex: &str,
+        classification: Option<&str>,
+        conn: &Conn,
+    ) -> Result<(), WalletDbError> {
+        use crate::db::schema::txos;
+
+        diesel::update(txos::table.filter(txos::txo_id_hex.eq(txo_id_hex)))
+            .set(txos::classification.eq(classification))
+            .execute(conn)?;
+        Ok(())
+    }
+
+    fn update_frozen(&self, frozen: bool, conn: &Conn) -> Result<(), WalletDbError> {
+        use crate::db::schema::txos;
+
+        diesel::update(self)
+            .set(txos::frozen.eq(frozen))
+            .execute(conn)?;
+        Ok(())
+    }
+
+    fn update_spent_from_block_index_to_pending(
+        account_id_hex: &str,
+        block_index: u64,
+        conn: &Conn,
+    ) -> Result<usize, WalletDbError> {
+        use crate::db::schema::txos;
+
+        let spent: Vec<Txo> = txos::table
+            .filter(txos::received_account_id_hex.eq(account_id_hex))
+            .filter(txos::spent_block_index.ge(block_index as i64))
+            .load(conn)?;
+        for txo in &spent {
+            // Syncing matches key images before expiring pending txos, so a
+            // txo whose key image is in its spent block is marked spent again.
+            diesel::update(txo)
+                .set((
+                    txos::pending_tombstone_block_index.eq(txo.spent_block_index),
+                    txos::spent_block_index.eq::<Option<i64>>(None),
+                ))
+                .execute(conn)?;
+        }
+        Ok(spent.len())
+    }
+
+    fn roll_back_received_from_block_index(
+        account_id_hex: &str,
+        block_index: u64,
+        conn: &Conn,
+    ) -> Result<usize, WalletDbError> {
+        use crate::db::schema::txos;
+
+        Ok(diesel::update(
+            txos::table
+                .filter(txos::received_account_id_hex.eq(account_id_hex))
+                .filter(txos::received_block_index.ge(block_index as i64)),
+        )
+        .set((
+            txos::received_account_id_hex.eq::<Option<String>>(None),
+            txos::received_block_index.eq::<Option<i64>>(None),
+            txos::subaddress_index.eq::<Option<i64>>(None),
+            txos::key_image.eq::<Option<Vec<u8>>>(None),
+            txos::spent_block_index.eq::<Option<i64>>(None),
+            txos::pending_tombstone_block_index.eq::<Option<i64>>(None),
+        ))
+        .execute(conn)?)
+    }
+
     fn update_txos_exceeding_pending_tombstone_block_index_to_unspent(
         block_index: u64,
         conn: &Conn,
@@ -541,6 +957,41 @@ impl TxoModel for Txo {
         Ok(query.load(conn)?)
     }
 
+    fn list_page_for_account(
+        account_id_hex: &str,
+        after_id: Option<i32>,
+        offset: Option<u64>,
+        limit: u64,
+        token_id: Option<u64>,
+        conn: &Conn,
+    ) -> Result<Vec<Txo>, WalletDbError> {
+        use crate::db::schema::txos;
+
+        let mut query = txos::table
+            .into_boxed()
+            .filter(
+                txos::received_account_id_hex
+                    .eq(account_id_hex)
+                    .or(txos::minted_account_id_hex.eq(account_id_hex)),
+            )
+            .order(txos::id)
+            .limit(limit as i64);
+
+        if let Some(after_id) = after_id {
+            query = query.filter(txos::id.gt(after_id));
+        }
+
+        if let Some(offset) = offset {
+            query = query.offset(offset as i64);
+        }
+
+        if let Some(token_id) = token_id {
+            query = query.filter(txos::token_id.eq(token_id as i64));
+        }
+
+        Ok(query.load(conn)?)
+    }
+
     fn list_for_address(
         assigned_subaddress_b58: &str,
         token_id: Option<u64>,
@@ -627,6 +1078,32 @@ impl TxoModel for Txo {
             .collect())
     }
 
+    fn list_key_images_page(
+        account_id_hex: Option<&str>,
+        after_id: Option<i32>,
+        limit: u64,
+        conn: &Conn,
+    ) -> Result<Vec<Txo>, WalletDbError> {
+        use crate::db::schema::txos;
+
+        let mut query = txos::table
+            .into_boxed()
+            .filter(txos::key_image.is_not_null())
+            .filter(txos::received_account_id_hex.is_not_null())
+            .order(txos::id)
+            .limit(limit as i64);
+
+        if let Some(account_id_hex) = account_id_hex {
+            query = query.filter(txos::received_account_id_hex.eq(account_id_hex));
+        }
+
+        if let Some(after_id) = after_id {
+            query = query.filter(txos::id.gt(after_id));
+        }
+
+        Ok(query.load(conn)?)
+    }
+
     fn list_spent(
         account_id_hex: &str,
         assigned_subaddress_b58: Option<&str>,
@@ -759,6 +1236,352 @@ impl TxoModel for Txo {
         Ok(txos)
     }
 
+    fn list_for_export(
+        filter: &TxoExportFilter,
+        after_id: Option<i32>,
+        limit: u64,
+        conn: &Conn,
+    ) -> Result<Vec<Txo>, WalletDbError> {
+        use crate::db::schema::txos;
+
+        let mut query = txos::table.into_boxed();
+
+        if let Some(after_id) = after_id {
+            query = query.filter(txos::id.gt(after_id));
+        }
+
+        if let Some(token_id) = filter.token_id {
+            query = query.filter(txos::token_id.eq(token_id as i64));
+        }
+
+        if let Some(block_index) = filter.min_received_block_index {
+            query = query.filter(txos::received_block_index.ge(block_index as i64));
+        }
+
+        if let Some(block_index) = filter.max_received_block_index {
+            query = query.filter(txos::received_block_index.le(block_index as i64));
+        }
+
+        if let Some(classification) = &filter.classification {
+            query = query.filter(txos::classification.eq(classification.as_str()));
+        }
+
+        let account_id_hex = filter.account_id_hex.as_deref();
+        query = match filter.status.as_deref() {
+            // Secreted txos were minted by the account, but not received by it.
+            Some(TXO_STATUS_SECRETED) => match account_id_hex {
+                Some(account_id_hex) => query
+                    .filter(txos::minted_account_id_hex.eq(account_id_hex))
+                    .filter(
+                        txos::received_account_id_hex
+                            .ne(account_id_hex)
+                            .or(txos::received_account_id_hex.is_null()),
+                    ),
+                None => query
+                    .filter(txos::minted_account_id_hex.is_not_null())
+                    .filter(
+                        txos::received_account_id_hex
+                            .ne(txos::minted_account_id_hex)
+                            .or(txos::received_account_id_hex.is_null()),
+                    ),
+            },
+            Some(status) => {
+                query = match account_id_hex {
+                    Some(account_id_hex) => {
+                        query.filter(txos::received_account_id_hex.eq(account_id_hex))
+                    }
+                    None => query.filter(txos::received_account_id_hex.is_not_null()),
+                };
+                match status {
+                    TXO_STATUS_UNSPENT => query
+                        .filter(txos::subaddress_index.is_not_null())
+                        .filter(txos::pending_tombstone_block_index.is_null())
+                        .filter(txos::spent_block_index.is_null()),
+                    TXO_STATUS_PENDING => query
+                        .filter(txos::subaddress_index.is_not_null())
+                        .filter(txos::pending_tombstone_block_index.is_not_null())
+                        .filter(txos::spent_block_index.is_null()),
+                    TXO_STATUS_SPENT => query.filter(txos::spent_block_index.is_not_null()),
+                    TXO_STATUS_ORPHANED => query.filter(txos::subaddress_index.is_null()),
+                    _ => {
+                        return Err(WalletDbError::UnexpectedAccountTxoStatus(
+                            status.to_string(),
+                        ))
+                    }
+                }
+            }
+            None => match account_id_hex {
+                Some(account_id_hex) => query.filter(
+                    txos::received_account_id_hex
+                        .eq(account_id_hex)
+                        .or(txos::minted_account_id_hex.eq(account_id_hex)),
+                ),
+                None => query,
+            },
+        };
+
+        Ok(query.order(txos::id.asc()).limit(limit as i64).load(conn)?)
+    }
+
+    fn sum_values_by_account_and_status(
+        token_id: u64,
+        conn: &Conn,
+    ) -> Result<HashMap<String, TxoStatusTotals>, WalletDbError> {
+        use diesel::sql_types::{BigInt, Text};
+
+        let mut totals: HashMap<String, TxoStatusTotals> = HashMap::default();
+
+        // Received txos are in exactly one of these statuses, matching the
+        // filters used by list_orphaned, list_spent, list_pending and
+        // list_unspent. Frozen txos are counted as unspent as well.
+        let received = diesel::dsl::sql::<(Text, Text, BigInt, BigInt)>(&format!(
+            "SELECT received_account_id_hex,
+                CASE
+                    WHEN subaddress_index IS NULL THEN 'orphaned'
+                    WHEN spent_block_index IS NOT NULL THEN 'spent'
+                    WHEN pending_tombstone_block_index IS NOT NULL THEN 'pending'
+                    WHEN frozen THEN 'frozen'
+                    ELSE 'unspent'
+                END AS status,
+                {}
+            FROM txos
+            WHERE received_account_id_hex IS NOT NULL AND token_id = ",
+            SUM_VALUE_HALVES
+        ))
+        .bind::<BigInt, _>(token_id as i64)
+        .sql(" GROUP BY received_account_id_hex, status")
+        .load::<(String, String, i64, i64)>(conn)?;
+
+        for (account_id_hex, status, low, high) in received {
+            let value = combine_value_halves(low, high);
+            let account_totals = totals.entry(account_id_hex).or_default();
+            match status.as_str() {
+                "orphaned" => account_totals.orphaned += value,
+                "spent" => account_totals.spent += value,
+                "pending" => account_totals.pending += value,
+                "frozen" => {
+                    account_totals.unspent += value;
+                    account_totals.frozen += value;
+                }
+                _ => account_totals.unspent += value,
+            }
+        }
+
+        // Secreted txos were minted by an account, but not received by it, as
+        // in list_secreted.
+        let secreted = diesel::dsl::sql::<(Text, BigInt, BigInt)>(&format!(
+            "SELECT minted_account_id_hex, {}
+            FROM txos
+            WHERE minted_account_id_hex IS NOT NULL
+                AND (received_account_id_hex IS NULL
+                    OR received_account_id_hex != minted_account_id_hex)
+                AND token_id = ",
+            SUM_VALUE_HALVES
+        ))
+        .bind::<BigInt, _>(token_id as i64)
+        .sql(" GROUP BY minted_account_id_hex")
+        .load::<(String, i64, i64)>(conn)?;
+
+        for (account_id_hex, low, high) in secreted {
+            totals.entry(account_id_hex).or_default().secreted += combine_value_halves(low, high);
+        }
+
+        Ok(totals)
+    }
+
+    fn sum_values_for_account(
+        account_id_hex: &str,
+        assigned_subaddress_b58: Option<&str>,
+        token_id: u64,
+        conn: &Conn,
+    ) -> Result<TxoStatusTotals, WalletDbError> {
+        use diesel::sql_types::{BigInt, Text};
+
+        let subaddress_filter = subaddress_filter(assigned_subaddress_b58, conn)?;
+
+        // Each total matches the filters used by the corresponding list query.
+        let sums = diesel::dsl::sql::<(
+            BigInt,
+            BigInt,
+            BigInt,
+            BigInt,
+            BigInt,
+            BigInt,
+            BigInt,
+            BigInt,
+            BigInt,
+            BigInt,
+        )>(&format!(
+            "SELECT {}, {}, {}, {}, {}
+            FROM txos
+            WHERE received_account_id_hex = ",
+            sum_value_halves_where(
+                "subaddress_index IS NOT NULL
+                    AND pending_tombstone_block_index IS NULL
+                    AND spent_block_index IS NULL"
+            ),
+            sum_value_halves_where(
+                "subaddress_index IS NOT NULL
+                    AND pending_tombstone_block_index IS NOT NULL
+                    AND spent_block_index IS NULL"
+            ),
+            sum_value_halves_where("spent_block_index IS NOT NULL"),
+            sum_value_halves_where("subaddress_index IS NULL"),
+            sum_value_halves_where(
+                "subaddress_index IS NOT NULL
+                    AND pending_tombstone_block_index IS NULL
+                    AND spent_block_index IS NULL
+                    AND frozen"
+            ),
+        ))
+        .bind::<Text, _>(account_id_hex)
+        .sql(" AND token_id = ")
+        .bind::<BigInt, _>(token_id as i64)
+        .sql(&subaddress_filter)
+        .get_result::<(i64, i64, i64, i64, i64, i64, i64, i64, i64, i64)>(conn)?;
+
+        let secreted = if assigned_subaddress_b58.is_some() {
+            0
+        } else {
+            let (low, high) = diesel::dsl::sql::<(BigInt, BigInt)>(&format!(
+                "SELECT {}
+                FROM txos
+                WHERE minted_account_id_hex = ",
+                sum_value_halves_where(
+                    "received_account_id_hex IS NULL
+                        OR received_account_id_hex != minted_account_id_hex"
+                ),
+            ))
+            .bind::<Text, _>(account_id_hex)
+            .sql(" AND token_id = ")
+            .bind::<BigInt, _>(token_id as i64)
+            .get_result::<(i64, i64)>(conn)?;
+            combine_value_halves(low, high)
+        };
+
+        Ok(TxoStatusTotals {
+            unspent: combine_value_halves(sums.0, sums.1),
+            pending: combine_value_halves(sums.2, sums.3),
+            spent: combine_value_halves(sums.4, sums.5),
+            secreted,
+            orphaned: combine_value_halves(sums.6, sums.7),
+            frozen: combine_value_halves(sums.8, sums.9),
+        })
+    }
+
+    fn sum_received_for_account(
+        account_id_hex: &str,
+        token_id: u64,
+        conn: &Conn,
+    ) -> Result<u128, WalletDbError> {
+        use diesel::sql_types::{BigInt, Nullable, Text};
+
+        let (low, high) = diesel::dsl::sql::<(Nullable<BigInt>, Nullable<BigInt>)>(&format!(
+            "SELECT {}
+            FROM txos
+            WHERE (minted_account_id_hex IS NULL
+                    OR minted_account_id_hex != received_account_id_hex)
+                AND received_account_id_hex = ",
+            SUM_VALUE_HALVES
+        ))
+        .bind::<Text, _>(account_id_hex)
+        .sql(" AND token_id = ")
+        .bind::<BigInt, _>(token_id as i64)
+        .sql(" AND (subaddress_index IS NULL OR subaddress_index != ")
+        .bind::<BigInt, _>(CHANGE_SUBADDRESS_INDEX as i64)
+        .sql(")")
+        .get_result::<(Option<i64>, Option<i64>)>(conn)?;
+
+        Ok(combine_value_halves(low.unwrap_or(0), high.unwrap_or(0)))
+    }
+
+    fn list_received_from_others(
+        account_id_hex: &str,
+        min_block_index: Option<u64>,
+        max_block_index: Option<u64>,
+        conn: &Conn,
+    ) -> Result<Vec<Txo>, WalletDbError> {
+        use crate::db::schema::txos;
+
+        let mut query = txos::table
+            .filter(txos::received_account_id_hex.eq(account_id_hex))
+            .filter(txos::received_block_index.is_not_null())
+            .filter(
+                txos::minted_account_id_hex
+                    .is_null()
+                    .or(txos::minted_account_id_hex.ne(account_id_hex)),
+            )
+            .filter(
+                txos::subaddress_index
+                    .is_null()
+                    .or(txos::subaddress_index.ne(CHANGE_SUBADDRESS_INDEX as i64)),
+            )
+            .into_boxed();
+
+        if let Some(block_index) = min_block_index {
+            query = query.filter(txos::received_block_index.ge(block_index as i64));
+        }
+
+        if let Some(block_index) = max_block_index {
+            query = query.filter(txos::received_block_index.le(block_index as i64));
+        }
+
+        Ok(query
+            .order((txos::received_block_index.asc(), txos::id.asc()))
+            .load(conn)?)
+    }
+
+    fn list_token_ids(conn: &Conn) -> Result<Vec<u64>, WalletDbError> {
+        use crate::db::schema::txos;
+
+        Ok(txos::table
+            .select(txos::token_id)
+            .distinct()
+            .order(txos::token_id.asc())
+            .load::<i64>(conn)?
+            .into_iter()
+            .map(|token_id| token_id as u64)
+            .collect())
+    }
+
+    fn max_spendable_in_wallet(
+        account_id_hex: &str,
+        assigned_subaddress_b58: Option<&str>,
+        token_id: u64,
+        conn: &Conn,
+    ) -> Result<u128, WalletDbError> {
+        use diesel::sql_types::{BigInt, Nullable, Text};
+
+        let subaddress_filter = subaddress_filter(assigned_subaddress_b58, conn)?;
+
+        // The largest MAX_INPUTS spendable txos, in the same order as
+        // list_spendable.
+        let (low, high) = diesel::dsl::sql::<(Nullable<BigInt>, Nullable<BigInt>)>(&format!(
+            "SELECT {}
+            FROM (
+                SELECT value
+                FROM txos
+                WHERE spent_block_index IS NULL
+                    AND pending_tombstone_block_index IS NULL
+                    AND subaddress_index IS NOT NULL
+                    AND key_image IS NOT NULL
+                    AND NOT frozen
+                    AND received_account_id_hex = ",
+            SUM_VALUE_HALVES
+        ))
+        .bind::<Text, _>(account_id_hex)
+        .sql(" AND token_id = ")
+        .bind::<BigInt, _>(token_id as i64)
+        .sql(&subaddress_filter)
+        .sql(" ORDER BY value DESC LIMIT ")
+        .bind::<BigInt, _>(MAX_INPUTS as i64)
+        .sql(") AS spendable_txos")
+        .get_result::<(Option<i64>, Option<i64>)>(conn)?;
+
+        let max_spendable_in_wallet = combine_value_halves(low.unwrap_or(0), high.unwrap_or(0));
+        Ok(max_spendable_in_wallet.saturating_sub(Mob::MINIMUM_FEE as u128))
+    }
+
     fn list_minted(
         account_id_hex: &str,
         token_id: Option<u64>,
@@ -777,6 +1600,26 @@ impl TxoModel for Txo {
         Ok(query.load(conn)?)
     }
 
+    fn list_recipient_addresses(
+        account_id_hex: &str,
+        conn: &Conn,
+    ) -> Result<Vec<String>, WalletDbError> {
+        use crate::db::schema::txos;
+
+        // Change outputs are received back by the minting account, so skip them.
+        Ok(txos::table
+            .filter(txos::minted_account_id_hex.eq(account_id_hex))
+            .filter(
+                txos::received_account_id_hex
+                    .is_null()
+                    .or(txos::received_account_id_hex.ne(account_id_hex)),
+            )
+            .filter(txos::recipient_public_address_b58.ne(""))
+            .select(txos::recipient_public_address_b58)
+            .distinct()
+            .load(conn)?)
+    }
+
     fn get(txo_id_hex: &str, conn: &Conn) -> Result<Txo, WalletDbError> {
         use crate::db::schema::txos;
 
@@ -849,6 +1692,7 @@ impl TxoModel for Txo {
             .filter(txos::pending_tombstone_block_index.is_null())
             .filter(txos::subaddress_index.is_not_null())
             .filter(txos::key_image.is_not_null())
+            .filter(txos::frozen.eq(false))
             .filter(txos::received_account_id_hex.eq(account_id_hex));
 
         if let Some(token_id) = token_id {
@@ -905,10 +1749,11 @@ impl TxoModel for Txo {
         max_spendable_value: Option<u64>,
         pending_tombstone_block_index: Option<u64>,
         token_id: Option<u64>,
+        strategy: CoinSelectionStrategy,
         conn: &Conn,
     ) -> Result<Vec<Txo>, WalletDbError> {
         let SpendableTxosResult {
-            mut spendable_txos,
+            spendable_txos,
             max_spendable_in_wallet,
         } = Txo::list_spendable(account_id_hex, max_spendable_value, None, token_id, conn)?;
 
@@ -920,57 +1765,55 @@ impl TxoModel for Txo {
         // defrag
         if target_value as u128 > max_spendable_in_wallet + Mob::MINIMUM_FEE as u128 {
             // See if we merged the UTXOs we would be able to spend this amount.
-            let total_unspent_value_in_wallet: u128 = spendable_txos
+            let txo_values: Vec<u64> = spendable_txos
                 .iter()
-                .map(|utxo| (utxo.value as u64) as u128)
-                .sum();
+                .map(|utxo| utxo.value as u64)
+                .collect();
+            let details =
+                InsufficientFundsDetails::new(&txo_values, target_value, Mob::MINIMUM_FEE);
 
-            if total_unspent_value_in_wallet >= (target_value + Mob::MINIMUM_FEE) as u128 {
-                return Err(WalletDbError::InsufficientFundsFragmentedTxos);
+            if details.consolidation_would_help {
+                return Err(WalletDbError::InsufficientFundsFragmentedTxos(details));
             } else {
-                return Err(WalletDbError::InsufficientFundsUnderMaxSpendable(format!(
-                    "Max spendable value in wallet: {:?}, but target value: {:?}",
-                    max_spendable_in_wallet, target_value
-                )));
+                return Err(WalletDbError::InsufficientFundsUnderMaxSpendable(details));
             }
         }
 
-        // Select the actual Txos to spend. We want to opportunistically fill up the
-        // input slots with dust, from any subaddress, so we take from the back
-        // of the Txo vec. This is a knapsack problem, and the selection could
-        // be improved. For now, we simply move the window of MAX_INPUTS up from
-        // the back of the sorted vector until we have a window with
-        // a large enough sum.
-        let mut selected_utxos: Vec<Txo> = Vec::new();
-        let mut total: u64 = 0;
-        loop {
-            if total >= target_value {
-                break;
-            }
+        // Txos received from isolated counterparties are only spent with others
+        // from the same counterparty, so the general pool is tried first and
+        // then each counterparty's txos on their own.
+        let isolated_address_hashes: BTreeSet<String> =
+            IsolatedCounterparty::list_for_account(account_id_hex, conn)?
+                .into_iter()
+                .map(|counterparty| counterparty.address_hash)
+                .collect();
+        let mut pools: BTreeMap<Option<String>, Vec<Txo>> = BTreeMap::new();
+        for txo in spendable_txos {
+            let pool = txo
+                .sender_address_hash
+                .clone()
+                .filter(|address_hash| isolated_address_hashes.contains(address_hash));
+            pools.entry(pool).or_default().push(txo);
+        }
 
-            // Grab the next (smallest) utxo, in order to opportunistically sweep up dust
-            let next_utxo = spendable_txos.pop().ok_or_else(|| {
-                WalletDbError::InsufficientFunds(format!(
-                    "Not enough Txos to sum to target value: {:?}",
-                    target_value
-                ))
+        let selected_utxos = pools
+            .into_iter()
+            .find_map(|(_pool, txos)| strategy.select(txos, target_value))
+            .ok_or_else(|| {
+                if isolated_address_hashes.is_empty() {
+                    WalletDbError::InsufficientFunds(format!(
+                        "Not enough Txos to sum to target value: {:?}",
+                        target_value
+                    ))
+                } else {
+                    WalletDbError::InsufficientFunds(format!(
+                        "Not enough Txos to sum to target value {:?} without spending \
+                         isolated counterparties' Txos together with other funds",
+                        target_value
+                    ))
+                }
             })?;
-            selected_utxos.push(next_utxo.clone());
-            total += next_utxo.value as u64;
-
-            // Cap at maximum allowed inputs.
-            if selected_utxos.len() > MAX_INPUTS as usize {
-                // Remove the lowest utxo.
-                let removed = selected_utxos.remove(0);
-                total -= removed.value as u64;
-            }
-        }
 
-        if selected_utxos.is_empty() || selected_utxos.len() > MAX_INPUTS as usize {
-            return Err(WalletDbError::InsufficientFunds(
-                "Logic error. Could not select Txos despite having sufficient funds".to_string(),
-            ));
-        }
         if let Some(pending_tombstone_block_index) = pending_tombstone_block_index {
             for txo in &selected_utxos {
                 txo.update_to_pending(pending_tombstone_block_index, conn)?;
@@ -1167,6 +2010,12 @@ mod tests {
             recipient_public_address_b58: "".to_string(),
             minted_account_id_hex: None,
             received_account_id_hex: Some(alice_account_id.to_string()),
+            ledger_txo_index: None,
+            mimicked_address_b58: None,
+            sender_address_hash: None,
+            destination_address_hash: None,
+            frozen: false,
+            classification: None,
         };
 
         assert_eq!(expected_txo, txos[0]);
@@ -1448,6 +2297,7 @@ mod tests {
             None,
             None,
             Some(0),
+            CoinSelectionStrategy::default(),
             &wallet_db.get_conn().unwrap(),
         )
         .unwrap();
@@ -1461,6 +2311,7 @@ mod tests {
             None,
             None,
             Some(0),
+            CoinSelectionStrategy::default(),
             &wallet_db.get_conn().unwrap(),
         )
         .unwrap();
@@ -1477,6 +2328,7 @@ mod tests {
             Some(200 * MOB),
             None,
             Some(0),
+            CoinSelectionStrategy::default(),
             &wallet_db.get_conn().unwrap(),
         );
 
@@ -1494,6 +2346,7 @@ mod tests {
             None,
             None,
             Some(0),
+            CoinSelectionStrategy::default(),
             &wallet_db.get_conn().unwrap(),
         )
         .unwrap();
@@ -1564,6 +2417,7 @@ mod tests {
             None,
             Some(100),
             Some(0),
+            CoinSelectionStrategy::default(),
             &wallet_db.get_conn().unwrap(),
         )
         .unwrap();
@@ -1574,6 +2428,7 @@ mod tests {
             None,
             Some(100),
             Some(0),
+            CoinSelectionStrategy::default(),
             &wallet_db.get_conn().unwrap(),
         );
 
@@ -1625,10 +2480,20 @@ mod tests {
             None,
             None,
             Some(0),
+            CoinSelectionStrategy::default(),
             &wallet_db.get_conn().unwrap(),
         );
         match res {
-            Err(WalletDbError::InsufficientFundsFragmentedTxos) => {}
+            Err(WalletDbError::InsufficientFundsFragmentedTxos(details)) => {
+                assert_eq!(details.total_unspent_value, 1900 * MOB as u128);
+                assert_eq!(
+                    details.max_spendable_value,
+                    (1600 * MOB - Mob::MINIMUM_FEE) as u128
+                );
+                assert_eq!(details.shortfall, 0);
+                assert_eq!(details.txos_needed, Some(18));
+                assert!(details.consolidation_would_help);
+            }
             Ok(_) => panic!("Should error with InsufficientFundsFragmentedTxos"),
             Err(e) => panic!(
                 "Should error with InsufficientFundsFragmentedTxos but got {:?}",
@@ -2028,6 +2893,165 @@ mod tests {
         );
     }
 
+    #[test_with_logger]
+    fn test_sum_values_by_account_and_status(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let db_test_context = WalletDbTestContext::default();
+        let wallet_db = db_test_context.get_db_instance(logger);
+        let conn = wallet_db.get_conn().unwrap();
+
+        let root_id = RootIdentity::from_random(&mut rng);
+        let account_key = AccountKey::from(&root_id);
+        let (account_id, _address) = Account::create_from_root_entropy(
+            &root_id.root_entropy,
+            Some(0),
+            None,
+            None,
+            "",
+            "".to_string(),
+            "".to_string(),
+            "".to_string(),
+            &conn,
+        )
+        .unwrap();
+
+        // A value which doesn't fit in a signed BigInt column.
+        let large_value = u64::MAX - 10;
+        let (_txo_id, _txo, _key_image) = create_test_received_txo(
+            &account_key,
+            0,
+            Amount::new(large_value, Mob::ID),
+            1,
+            &mut rng,
+            &wallet_db,
+        );
+        let (pending_txo_id, _txo, _key_image) = create_test_received_txo(
+            &account_key,
+            0,
+            Amount::new(20 * MOB, Mob::ID),
+            2,
+            &mut rng,
+            &wallet_db,
+        );
+        let (spent_txo_id, _txo, _key_image) = create_test_received_txo(
+            &account_key,
+            0,
+            Amount::new(30 * MOB, Mob::ID),
+            3,
+            &mut rng,
+            &wallet_db,
+        );
+        Txo::get(&pending_txo_id, &conn)
+            .unwrap()
+            .update_to_pending(10, &conn)
+            .unwrap();
+        Txo::update_to_spent(&spent_txo_id, 4, &conn).unwrap();
+
+        let totals = Txo::sum_values_by_account_and_status(0, &conn).unwrap();
+        let account_totals = totals.get(&account_id.to_string()).unwrap();
+
+        // The totals match those computed from the individual txos.
+        let sum = |txos: Vec<Txo>| {
+            txos.iter()
+                .map(|txo| (txo.value as u64) as u128)
+                .sum::<u128>()
+        };
+        let account_id_hex = account_id.to_string();
+        assert_eq!(
+            account_totals.unspent,
+            sum(Txo::list_unspent(&account_id_hex, None, Some(0), &conn).unwrap())
+        );
+        assert_eq!(account_totals.unspent, large_value as u128);
+        assert_eq!(account_totals.pending, (20 * MOB) as u128);
+        assert_eq!(account_totals.spent, (30 * MOB) as u128);
+        assert_eq!(account_totals.secreted, 0);
+        assert_eq!(account_totals.orphaned, 0);
+
+        // Other tokens are not included.
+        assert!(Txo::sum_values_by_account_and_status(1, &conn)
+            .unwrap()
+            .is_empty());
+
+        // The totals for a single account match.
+        assert_eq!(
+            &Txo::sum_values_for_account(&account_id_hex, None, 0, &conn).unwrap(),
+            account_totals
+        );
+        assert_eq!(
+            Txo::sum_values_for_account(&account_id_hex, None, 1, &conn).unwrap(),
+            TxoStatusTotals::default()
+        );
+        assert_eq!(
+            Txo::max_spendable_in_wallet(&account_id_hex, None, 0, &conn).unwrap(),
+            Txo::list_spendable(&account_id_hex, None, None, Some(0), &conn)
+                .unwrap()
+                .max_spendable_in_wallet
+        );
+
+        // As do the totals for a subaddress.
+        let subaddress_b58 = b58_encode_public_address(&account_key.subaddress(0)).unwrap();
+        let subaddress_totals =
+            Txo::sum_values_for_account(&account_id_hex, Some(&subaddress_b58), 0, &conn).unwrap();
+        assert_eq!(
+            subaddress_totals.unspent,
+            sum(Txo::list_unspent(&account_id_hex, Some(&subaddress_b58), Some(0), &conn).unwrap())
+        );
+        assert_eq!(subaddress_totals.pending, (20 * MOB) as u128);
+        assert_eq!(subaddress_totals.spent, (30 * MOB) as u128);
+        let change_b58 =
+            b58_encode_public_address(&account_key.subaddress(CHANGE_SUBADDRESS_INDEX)).unwrap();
+        assert_eq!(
+            Txo::sum_values_for_account(&account_id_hex, Some(&change_b58), 0, &conn).unwrap(),
+            TxoStatusTotals::default()
+        );
+        assert_eq!(
+            Txo::max_spendable_in_wallet(&account_id_hex, Some(&change_b58), 0, &conn).unwrap(),
+            0
+        );
+
+        // The received total counts spent txos, but not change.
+        create_test_received_txo(
+            &account_key,
+            CHANGE_SUBADDRESS_INDEX,
+            Amount::new(5 * MOB, Mob::ID),
+            5,
+            &mut rng,
+            &wallet_db,
+        );
+        assert_eq!(
+            Txo::sum_received_for_account(&account_id_hex, 0, &conn).unwrap(),
+            large_value as u128 + (50 * MOB) as u128
+        );
+        assert_eq!(
+            Txo::sum_received_for_account(&account_id_hex, 1, &conn).unwrap(),
+            0
+        );
+
+        // Each token is listed once.
+        assert_eq!(Txo::list_token_ids(&conn).unwrap(), vec![0]);
+        create_test_received_txo(
+            &account_key,
+            0,
+            Amount::new(7, TokenId::from(1)),
+            6,
+            &mut rng,
+            &wallet_db,
+        );
+        assert_eq!(Txo::list_token_ids(&conn).unwrap(), vec![0, 1]);
+
+        // Txos received from others are listed in block order, without change.
+        let received_block_indices = |min_block_index, max_block_index| {
+            Txo::list_received_from_others(&account_id_hex, min_block_index, max_block_index, &conn)
+                .unwrap()
+                .iter()
+                .map(|txo| txo.received_block_index.unwrap())
+                .collect::<Vec<i64>>()
+        };
+        assert_eq!(received_block_indices(None, None), vec![1, 2, 3, 6]);
+        assert_eq!(received_block_indices(Some(2), Some(5)), vec![2, 3]);
+    }
+
     #[test_with_logger]
     fn test_list_spendable_less_than_min_fee(logger: Logger) {
         let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
@@ -2261,6 +3285,7 @@ mod tests {
             None,
             None,
             Some(0),
+            CoinSelectionStrategy::default(),
             &wallet_db.get_conn().unwrap(),
         )
         .unwrap();
@@ -2279,6 +3304,7 @@ mod tests {
             None,
             None,
             Some(0),
+            CoinSelectionStrategy::default(),
             &wallet_db.get_conn().unwrap(),
         );
 
@@ -2297,6 +3323,7 @@ mod tests {
             None,
             None,
             Some(0),
+            CoinSelectionStrategy::default(),
             &wallet_db.get_conn().unwrap(),
         )
         .unwrap();
@@ -2313,6 +3340,7 @@ mod tests {
             None,
             None,
             Some(0),
+            CoinSelectionStrategy::default(),
             &wallet_db.get_conn().unwrap(),
         );
         assert!(result.is_err());
@@ -2330,6 +3358,7 @@ mod tests {
             None,
             None,
             Some(0),
+            CoinSelectionStrategy::default(),
             &wallet_db.get_conn().unwrap(),
         )
         .unwrap();
@@ -48,6 +48,13 @@ pub trait ViewOnlyAccountModel {
     /// adding a new account, and deleting the existing if desired.
     fn update_name(&self, new_name: &str, conn: &Conn) -> Result<(), WalletDbError>;
 
+    /// Mark whether the account's spend key is on a hardware wallet.
+    fn update_hardware_backed(
+        &self,
+        hardware_backed: bool,
+        conn: &Conn,
+    ) -> Result<(), WalletDbError>;
+
     /// Update the next block index this account will need to sync.
     fn update_next_block_index(
         &self,
@@ -145,6 +152,22 @@ impl ViewOnlyAccountModel for ViewOnlyAccount {
         Ok(())
     }
 
+    fn update_hardware_backed(
+        &self,
+        hardware_backed: bool,
+        conn: &Conn,
+    ) -> Result<(), WalletDbError> {
+        use schema::view_only_accounts;
+
+        diesel::update(
+            view_only_accounts::table
+                .filter(view_only_accounts::account_id_hex.eq(&self.account_id_hex)),
+        )
+        .set(view_only_accounts::hardware_backed.eq(hardware_backed))
+        .execute(conn)?;
+        Ok(())
+    }
+
     fn update_next_block_index(
         &self,
         next_block_index: u64,
@@ -242,6 +265,7 @@ mod tests {
             main_subaddress_index: DEFAULT_SUBADDRESS_INDEX as i64,
             change_subaddress_index: CHANGE_SUBADDRESS_INDEX as i64,
             next_subaddress_index: 2,
+            hardware_backed: false,
         };
 
         let created = ViewOnlyAccount::create(
@@ -264,6 +288,10 @@ mod tests {
 
         created.update_name(&new_name, &conn).unwrap();
 
+        // test marking the account hardware backed
+
+        created.update_hardware_backed(true, &conn).unwrap();
+
         // test updating next block index
 
         let new_next_block = 100;
@@ -277,6 +305,7 @@ mod tests {
         let updated: ViewOnlyAccount = ViewOnlyAccount::get(&account_id_hex, &conn).unwrap();
 
         assert_eq!(&updated.name, &new_name);
+        assert!(updated.hardware_backed);
         assert_eq!(updated.next_block_index as u64, new_next_block);
 
         // test getting all accounts
@@ -0,0 +1,196 @@
+// Copyright (c) 2020-2022 MobileCoin Inc.
+
+//! The View-Only Sync Record Model.
+
+use crate::db::{
+    models::{
+        NewViewOnlySyncRecord, ViewOnlySyncRecord, SYNC_RECORD_TYPE_REQUEST,
+        SYNC_RECORD_TYPE_RESPONSE,
+    },
+    schema, Conn, WalletDbError,
+};
+use chrono::Utc;
+use diesel::prelude::*;
+
+pub trait ViewOnlySyncRecordModel {
+    /// Record a sync request handed to the offline signer.
+    fn create_request(
+        account_id_hex: &str,
+        payload_hash: &str,
+        txo_count: usize,
+        conn: &Conn,
+    ) -> Result<ViewOnlySyncRecord, WalletDbError>;
+
+    /// Record the offline signer's response to a sync request.
+    ///
+    /// The response is linked to the account's latest request, unless that
+    /// request has already been answered.
+    fn create_response(
+        account_id_hex: &str,
+        payload_hash: &str,
+        txo_count: usize,
+        conn: &Conn,
+    ) -> Result<ViewOnlySyncRecord, WalletDbError>;
+
+    /// List the sync records for an account, oldest first.
+    fn list_for_account(
+        account_id_hex: &str,
+        offset: Option<u64>,
+        limit: Option<u64>,
+        conn: &Conn,
+    ) -> Result<Vec<ViewOnlySyncRecord>, WalletDbError>;
+}
+
+impl ViewOnlySyncRecordModel for ViewOnlySyncRecord {
+    fn create_request(
+        account_id_hex: &str,
+        payload_hash: &str,
+        txo_count: usize,
+        conn: &Conn,
+    ) -> Result<ViewOnlySyncRecord, WalletDbError> {
+        insert(
+            account_id_hex,
+            SYNC_RECORD_TYPE_REQUEST,
+            payload_hash,
+            txo_count,
+            None,
+            conn,
+        )
+    }
+
+    fn create_response(
+        account_id_hex: &str,
+        payload_hash: &str,
+        txo_count: usize,
+        conn: &Conn,
+    ) -> Result<ViewOnlySyncRecord, WalletDbError> {
+        use schema::view_only_sync_records;
+
+        let latest_request = view_only_sync_records::table
+            .filter(view_only_sync_records::view_only_account_id_hex.eq(account_id_hex))
+            .filter(view_only_sync_records::record_type.eq(SYNC_RECORD_TYPE_REQUEST))
+            .order(view_only_sync_records::id.desc())
+            .first::<ViewOnlySyncRecord>(conn)
+            .optional()?;
+
+        let request_record_id = match latest_request {
+            Some(request) => {
+                let answered = view_only_sync_records::table
+                    .filter(view_only_sync_records::request_record_id.eq(request.id))
+                    .count()
+                    .get_result::<i64>(conn)?
+                    > 0;
+                if answered {
+                    None
+                } else {
+                    Some(request.id)
+                }
+            }
+            None => None,
+        };
+
+        insert(
+            account_id_hex,
+            SYNC_RECORD_TYPE_RESPONSE,
+            payload_hash,
+            txo_count,
+            request_record_id,
+            conn,
+        )
+    }
+
+    fn list_for_account(
+        account_id_hex: &str,
+        offset: Option<u64>,
+        limit: Option<u64>,
+        conn: &Conn,
+    ) -> Result<Vec<ViewOnlySyncRecord>, WalletDbError> {
+        use schema::view_only_sync_records;
+
+        let mut query = view_only_sync_records::table
+            .filter(view_only_sync_records::view_only_account_id_hex.eq(account_id_hex))
+            .order(view_only_sync_records::id.asc())
+            .into_boxed();
+
+        if let (Some(o), Some(l)) = (offset, limit) {
+            query = query.offset(o as i64).limit(l as i64);
+        }
+
+        Ok(query.load(conn)?)
+    }
+}
+
+fn insert(
+    account_id_hex: &str,
+    record_type: &str,
+    payload_hash: &str,
+    txo_count: usize,
+    request_record_id: Option<i32>,
+    conn: &Conn,
+) -> Result<ViewOnlySyncRecord, WalletDbError> {
+    use schema::view_only_sync_records;
+
+    let new_record = NewViewOnlySyncRecord {
+        view_only_account_id_hex: account_id_hex,
+        record_type,
+        payload_hash,
+        txo_count: txo_count as i32,
+        request_record_id,
+        created_time: Utc::now().timestamp(),
+    };
+    diesel::insert_into(view_only_sync_records::table)
+        .values(&new_record)
+        .execute(conn)?;
+
+    Ok(view_only_sync_records::table
+        .order(view_only_sync_records::id.desc())
+        .first::<ViewOnlySyncRecord>(conn)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::WalletDbTestContext;
+    use mc_common::logger::{test_with_logger, Logger};
+
+    #[test_with_logger]
+    fn test_view_only_sync_record_crud(logger: Logger) {
+        let db_test_context = WalletDbTestContext::default();
+        let wallet_db = db_test_context.get_db_instance(logger);
+        let conn = wallet_db.get_conn().unwrap();
+
+        // A response to the latest request is linked to it.
+        let request = ViewOnlySyncRecord::create_request("abcd", "aa", 3, &conn).unwrap();
+        let response = ViewOnlySyncRecord::create_response("abcd", "bb", 2, &conn).unwrap();
+        assert_eq!(request.record_type, SYNC_RECORD_TYPE_REQUEST);
+        assert_eq!(request.txo_count, 3);
+        assert_eq!(response.record_type, SYNC_RECORD_TYPE_RESPONSE);
+        assert_eq!(response.request_record_id, Some(request.id));
+
+        // A second response to the same request is not.
+        let unmatched = ViewOnlySyncRecord::create_response("abcd", "cc", 0, &conn).unwrap();
+        assert_eq!(unmatched.request_record_id, None);
+
+        // A request that was never answered stays unlinked.
+        let missed = ViewOnlySyncRecord::create_request("abcd", "dd", 1, &conn).unwrap();
+        let latest = ViewOnlySyncRecord::create_request("abcd", "ee", 1, &conn).unwrap();
+        let response = ViewOnlySyncRecord::create_response("abcd", "ff", 1, &conn).unwrap();
+        assert_eq!(response.request_record_id, Some(latest.id));
+
+        // Records for other accounts are kept apart.
+        ViewOnlySyncRecord::create_request("ef01", "00", 0, &conn).unwrap();
+
+        let records = ViewOnlySyncRecord::list_for_account("abcd", None, None, &conn).unwrap();
+        assert_eq!(records.len(), 6);
+        assert_eq!(records[0], request);
+        assert_eq!(records[3], missed);
+        assert!(!records
+            .iter()
+            .any(|r| r.request_record_id == Some(missed.id)));
+
+        let records =
+            ViewOnlySyncRecord::list_for_account("abcd", Some(4), Some(10), &conn).unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0], latest);
+    }
+}
@@ -2,13 +2,19 @@
 
 //! DB impl for the view-only Txo model.
 
-use crate::db::{
-    models::{NewViewOnlyTxo, ViewOnlyAccount, ViewOnlySubaddress, ViewOnlyTxo},
-    schema,
-    txo::TxoID,
-    view_only_account::ViewOnlyAccountModel,
-    view_only_subaddress::ViewOnlySubaddressModel,
-    Conn, WalletDbError,
+use crate::{
+    db::{
+        models::{NewViewOnlyTxo, ViewOnlyAccount, ViewOnlySubaddress, ViewOnlyTxo},
+        schema,
+        txo::{
+            combine_value_halves, sum_value_halves_where, InsufficientFundsDetails, TxoID,
+            TxoStatusTotals,
+        },
+        view_only_account::ViewOnlyAccountModel,
+        view_only_subaddress::ViewOnlySubaddressModel,
+        Conn, WalletDbError,
+    },
+    service::transaction_builder::CoinSelectionStrategy,
 };
 use diesel::prelude::*;
 use mc_common::HashMap;
@@ -43,16 +49,50 @@ pub trait ViewOnlyTxoModel {
         conn: &Conn,
     ) -> Result<Vec<ViewOnlyTxo>, WalletDbError>;
 
+    /// list a page of view only txos for a view only account, in insertion
+    /// order
+    ///
+    /// Returns:
+    /// * Up to `limit` txos with a primary key greater than `after_id`, after
+    ///   skipping `offset` of them.
+    fn list_page_for_account(
+        account_id_hex: &str,
+        after_id: Option<i32>,
+        offset: Option<u64>,
+        limit: u64,
+        token_id: Option<u64>,
+        conn: &Conn,
+    ) -> Result<Vec<ViewOnlyTxo>, WalletDbError>;
+
     /// list view only txos for a view only address
     ///
     /// Returns:
     /// * Vec<ViewOnlyTxo>
+    /// Get a page of the txos whose key images are known, of a view only
+    /// account or of every view only account, in insertion order, starting
+    /// after the txo with the given id.
+    fn list_key_images_page(
+        account_id_hex: Option<&str>,
+        after_id: Option<i32>,
+        limit: u64,
+        conn: &Conn,
+    ) -> Result<Vec<ViewOnlyTxo>, WalletDbError>;
+
     fn list_for_address(
         assigned_subaddress_b58: &str,
         token_id: Option<u64>,
         conn: &Conn,
     ) -> Result<Vec<ViewOnlyTxo>, WalletDbError>;
 
+    /// list the ids of the view only txos an account received in a range of
+    /// blocks, inclusive of both ends
+    fn list_ids_received_in_block_range(
+        account_id_hex: &str,
+        first_block_index: u64,
+        last_block_index: u64,
+        conn: &Conn,
+    ) -> Result<Vec<String>, WalletDbError>;
+
     /// list view only txos that are unspent with key images for an account
     fn list_unspent_with_key_images(
         account_id_hex: &str,
@@ -93,14 +133,29 @@ pub trait ViewOnlyTxoModel {
         conn: &Conn,
     ) -> Result<Vec<ViewOnlyTxo>, WalletDbError>;
 
-    /// Select a set of unspent view only Txos to reach a given value.
+    /// Get the total value of a view only account's txos in each status,
+    /// using aggregate queries rather than loading the txos.
+    ///
+    /// If an assigned subaddress is given, the unspent, pending and spent
+    /// totals only include its txos. View only accounts have no secreted txos.
+    fn sum_values_for_account(
+        account_id_hex: &str,
+        assigned_subaddress_b58: Option<&str>,
+        token_id: u64,
+        conn: &Conn,
+    ) -> Result<TxoStatusTotals, WalletDbError>;
+
+    /// Select a set of unspent view only Txos to reach a given value, leaving
+    /// out any worth more than the max spendable value.
     ///
     /// Returns:
     /// * Vec<ViewOnlyTxo>
     fn select_unspent_view_only_txos_for_value(
         account_id_hex: &str,
         target_value: u64,
+        max_spendable_value: Option<u64>,
         token_id: Option<u64>,
+        strategy: CoinSelectionStrategy,
         conn: &Conn,
     ) -> Result<Vec<ViewOnlyTxo>, WalletDbError>;
 
@@ -153,6 +208,14 @@ pub trait ViewOnlyTxoModel {
 
     /// delete all view only txos for a view-only account
     fn delete_all_for_account(account_id_hex: &str, conn: &Conn) -> Result<(), WalletDbError>;
+
+    /// Undo what the account found in the blocks at or after the block index:
+    /// txos received in them are removed, and txos spent in them are unspent.
+    fn roll_back_from_block_index(
+        account_id_hex: &str,
+        block_index: u64,
+        conn: &Conn,
+    ) -> Result<(), WalletDbError>;
 }
 
 impl ViewOnlyTxoModel for ViewOnlyTxo {
@@ -237,6 +300,54 @@ impl ViewOnlyTxoModel for ViewOnlyTxo {
         Ok(query.load(conn)?)
     }
 
+    fn list_page_for_account(
+        account_id_hex: &str,
+        after_id: Option<i32>,
+        offset: Option<u64>,
+        limit: u64,
+        token_id: Option<u64>,
+        conn: &Conn,
+    ) -> Result<Vec<ViewOnlyTxo>, WalletDbError> {
+        use schema::view_only_txos;
+
+        let mut query = view_only_txos::table
+            .into_boxed()
+            .filter(view_only_txos::view_only_account_id_hex.eq(account_id_hex))
+            .order(view_only_txos::id)
+            .limit(limit as i64);
+
+        if let Some(after_id) = after_id {
+            query = query.filter(view_only_txos::id.gt(after_id));
+        }
+
+        if let Some(offset) = offset {
+            query = query.offset(offset as i64);
+        }
+
+        if let Some(token_id) = token_id {
+            query = query.filter(view_only_txos::token_id.eq(token_id as i64));
+        }
+
+        Ok(query.load(conn)?)
+    }
+
+    fn list_ids_received_in_block_range(
+        account_id_hex: &str,
+        first_block_index: u64,
+        last_block_index: u64,
+        conn: &Conn,
+    ) -> Result<Vec<String>, WalletDbError> {
+        use schema::view_only_txos;
+
+        Ok(view_only_txos::table
+            .filter(view_only_txos::view_only_account_id_hex.eq(account_id_hex))
+            .filter(view_only_txos::received_block_index.ge(first_block_index as i64))
+            .filter(view_only_txos::received_block_index.le(last_block_index as i64))
+            .select(view_only_txos::txo_id_hex)
+            .order(view_only_txos::txo_id_hex.asc())
+            .load(conn)?)
+    }
+
     fn list_for_address(
         assigned_subaddress_b58: &str,
         token_id: Option<u64>,
@@ -296,6 +407,31 @@ impl ViewOnlyTxoModel for ViewOnlyTxo {
             .collect())
     }
 
+    fn list_key_images_page(
+        account_id_hex: Option<&str>,
+        after_id: Option<i32>,
+        limit: u64,
+        conn: &Conn,
+    ) -> Result<Vec<ViewOnlyTxo>, WalletDbError> {
+        use schema::view_only_txos;
+
+        let mut query = view_only_txos::table
+            .into_boxed()
+            .filter(view_only_txos::key_image.is_not_null())
+            .order(view_only_txos::id)
+            .limit(limit as i64);
+
+        if let Some(account_id_hex) = account_id_hex {
+            query = query.filter(view_only_txos::view_only_account_id_hex.eq(account_id_hex));
+        }
+
+        if let Some(after_id) = after_id {
+            query = query.filter(view_only_txos::id.gt(after_id));
+        }
+
+        Ok(query.load(conn)?)
+    }
+
     fn list_orphaned_with_key_images(
         account_id_hex: &str,
         token_id: Option<u64>,
@@ -421,13 +557,77 @@ impl ViewOnlyTxoModel for ViewOnlyTxo {
         Ok(query.load(conn)?)
     }
 
+    fn sum_values_for_account(
+        account_id_hex: &str,
+        assigned_subaddress_b58: Option<&str>,
+        token_id: u64,
+        conn: &Conn,
+    ) -> Result<TxoStatusTotals, WalletDbError> {
+        use diesel::sql_types::{BigInt, Text};
+
+        let subaddress_filter = match assigned_subaddress_b58 {
+            Some(subaddress_b58) => format!(
+                " AND subaddress_index = {}",
+                ViewOnlySubaddress::get(subaddress_b58, conn)?.subaddress_index
+            ),
+            None => String::new(),
+        };
+
+        // Each total matches the filters used by the corresponding list query.
+        let sums = diesel::dsl::sql::<(
+            BigInt,
+            BigInt,
+            BigInt,
+            BigInt,
+            BigInt,
+            BigInt,
+            BigInt,
+            BigInt,
+        )>(&format!(
+            "SELECT {}, {}, {}, {}
+            FROM view_only_txos
+            WHERE view_only_account_id_hex = ",
+            sum_value_halves_where(&format!(
+                "received_block_index IS NOT NULL
+                    AND pending_tombstone_block_index IS NULL
+                    AND spent_block_index IS NULL{}",
+                subaddress_filter
+            )),
+            sum_value_halves_where(&format!(
+                "pending_tombstone_block_index IS NOT NULL
+                    AND spent_block_index IS NULL{}",
+                subaddress_filter
+            )),
+            sum_value_halves_where(&format!(
+                "spent_block_index IS NOT NULL{}",
+                subaddress_filter
+            )),
+            sum_value_halves_where("key_image IS NULL AND subaddress_index IS NULL"),
+        ))
+        .bind::<Text, _>(account_id_hex)
+        .sql(" AND token_id = ")
+        .bind::<BigInt, _>(token_id as i64)
+        .get_result::<(i64, i64, i64, i64, i64, i64, i64, i64)>(conn)?;
+
+        Ok(TxoStatusTotals {
+            unspent: combine_value_halves(sums.0, sums.1),
+            pending: combine_value_halves(sums.2, sums.3),
+            spent: combine_value_halves(sums.4, sums.5),
+            secreted: 0,
+            orphaned: combine_value_halves(sums.6, sums.7),
+            frozen: 0,
+        })
+    }
+
     // This is a direct port of txo selection and
     // the whole things needs a nice big refactor
     // to make it happy.
     fn select_unspent_view_only_txos_for_value(
         account_id_hex: &str,
         target_value: u64,
+        max_spendable_value: Option<u64>,
         token_id: Option<u64>,
+        strategy: CoinSelectionStrategy,
         conn: &Conn,
     ) -> Result<Vec<ViewOnlyTxo>, WalletDbError> {
         use schema::view_only_txos;
@@ -445,7 +645,11 @@ impl ViewOnlyTxoModel for ViewOnlyTxo {
             query = query.filter(view_only_txos::token_id.eq(token_id as i64));
         }
 
-        let mut spendable_txos: Vec<ViewOnlyTxo> =
+        if let Some(max_spendable_value) = max_spendable_value {
+            query = query.filter(view_only_txos::value.le(max_spendable_value as i64));
+        }
+
+        let spendable_txos: Vec<ViewOnlyTxo> =
             query.order_by(view_only_txos::value.desc()).load(conn)?;
 
         if spendable_txos.is_empty() {
@@ -460,52 +664,26 @@ impl ViewOnlyTxoModel for ViewOnlyTxo {
 
         if target_value as u128 > max_spendable_in_wallet {
             // See if we merged the UTXOs we would be able to spend this amount.
-            let total_unspent_value_in_wallet: u128 = spendable_txos
+            let txo_values: Vec<u64> = spendable_txos
                 .iter()
-                .map(|utxo| (utxo.value as u64) as u128)
-                .sum();
-            if total_unspent_value_in_wallet >= target_value as u128 {
-                return Err(WalletDbError::InsufficientFundsFragmentedTxos);
+                .map(|utxo| utxo.value as u64)
+                .collect();
+            let details = InsufficientFundsDetails::new(&txo_values, target_value, 0);
+            if details.consolidation_would_help {
+                return Err(WalletDbError::InsufficientFundsFragmentedTxos(details));
             } else {
-                return Err(WalletDbError::InsufficientFundsUnderMaxSpendable(format!(
-                    "Max spendable value in wallet: {:?}, but target value: {:?}",
-                    max_spendable_in_wallet, target_value
-                )));
+                return Err(WalletDbError::InsufficientFundsUnderMaxSpendable(details));
             }
         }
 
-        let mut selected_utxos: Vec<ViewOnlyTxo> = Vec::new();
-        let mut total: u64 = 0;
-        loop {
-            if total >= target_value {
-                break;
-            }
-
-            // Grab the next (smallest) utxo, in order to opportunistically sweep up dust
-            let next_utxo = spendable_txos.pop().ok_or_else(|| {
+        strategy
+            .select(spendable_txos, target_value)
+            .ok_or_else(|| {
                 WalletDbError::InsufficientFunds(format!(
                     "Not enough Txos to sum to target value: {:?}",
                     target_value
                 ))
-            })?;
-            selected_utxos.push(next_utxo.clone());
-            total += next_utxo.value as u64;
-
-            // Cap at maximum allowed inputs.
-            if selected_utxos.len() > MAX_INPUTS as usize {
-                // Remove the lowest utxo.
-                let removed = selected_utxos.remove(0);
-                total -= removed.value as u64;
-            }
-        }
-
-        if selected_utxos.is_empty() || selected_utxos.len() > MAX_INPUTS as usize {
-            return Err(WalletDbError::InsufficientFunds(
-                "Logic error. Could not select Txos despite having sufficient funds".to_string(),
-            ));
-        }
-
-        Ok(selected_utxos)
+            })
     }
 
     fn update_key_image(
@@ -643,6 +821,29 @@ impl ViewOnlyTxoModel for ViewOnlyTxo {
         diesel::delete(view_only_txos.filter(dsl_account_id.eq(account_id_hex))).execute(conn)?;
         Ok(())
     }
+
+    fn roll_back_from_block_index(
+        account_id_hex: &str,
+        block_index: u64,
+        conn: &Conn,
+    ) -> Result<(), WalletDbError> {
+        use schema::view_only_txos;
+
+        diesel::delete(
+            view_only_txos::table
+                .filter(view_only_txos::view_only_account_id_hex.eq(account_id_hex))
+                .filter(view_only_txos::received_block_index.ge(block_index as i64)),
+        )
+        .execute(conn)?;
+        diesel::update(
+            view_only_txos::table
+                .filter(view_only_txos::view_only_account_id_hex.eq(account_id_hex))
+                .filter(view_only_txos::spent_block_index.ge(block_index as i64)),
+        )
+        .set(view_only_txos::spent_block_index.eq::<Option<i64>>(None))
+        .execute(conn)?;
+        Ok(())
+    }
 }
 
 #[cfg(test)]
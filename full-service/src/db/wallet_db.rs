@@ -3,32 +3,92 @@ use diesel::{
     connection::SimpleConnection,
     prelude::*,
     r2d2::{ConnectionManager, Pool, PooledConnection},
-    sql_types, SqliteConnection,
+    sql_types,
 };
 use diesel_migrations::embed_migrations;
+#[cfg(not(feature = "postgres"))]
 use mc_common::logger::global_log;
-use std::{env, thread::sleep, time::Duration};
+use std::{
+    env,
+    path::PathBuf,
+    sync::{Arc, RwLock},
+    thread::sleep,
+    time::Duration,
+};
+#[cfg(not(feature = "postgres"))]
+use std::{fs, time::Instant};
+
+/// The connection to the wallet database, which is SQLite unless full-service
+/// is built with the postgres feature.
+#[cfg(not(feature = "postgres"))]
+pub type DbConnection = diesel::SqliteConnection;
+
+/// The connection to the wallet database, which is SQLite unless full-service
+/// is built with the postgres feature.
+#[cfg(feature = "postgres")]
+pub type DbConnection = diesel::PgConnection;
 
+#[cfg(not(feature = "postgres"))]
 embed_migrations!("migrations/");
 
-pub type Conn = PooledConnection<ConnectionManager<SqliteConnection>>;
+// Postgres databases start from the current schema, so have their own
+// migrations.
+#[cfg(feature = "postgres")]
+embed_migrations!("postgres-migrations/");
+
+/// The version of the newest migration this release applies to the wallet
+/// database.
+#[cfg(not(feature = "postgres"))]
+pub const LATEST_MIGRATION_VERSION: &str = env!("MC_LATEST_MIGRATION_VERSION");
+
+/// The version of the newest migration this release applies to the wallet
+/// database.
+#[cfg(feature = "postgres")]
+pub const LATEST_MIGRATION_VERSION: &str = env!("MC_LATEST_POSTGRES_MIGRATION_VERSION");
 
-#[derive(Debug)]
+pub type Conn = PooledConnection<ConnectionManager<DbConnection>>;
+
+/// How long to wait for connections to be returned to the pool before the
+/// database password is changed.
+#[cfg(not(feature = "postgres"))]
+const PASSWORD_CHANGE_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[cfg(not(feature = "postgres"))]
 pub struct ConnectionOptions {
     pub enable_wal: bool,
     pub enable_foreign_keys: bool,
     pub busy_timeout: Option<Duration>,
+    /// The SQLCipher key, shared with the WalletDb so that connections opened
+    /// after the password is changed use the new one.
+    pub encryption_key: Arc<RwLock<String>>,
 }
 
-impl diesel::r2d2::CustomizeConnection<SqliteConnection, diesel::r2d2::Error>
-    for ConnectionOptions
-{
-    fn on_acquire(&self, conn: &mut SqliteConnection) -> Result<(), diesel::r2d2::Error> {
+// The encryption key is left out, so that it isn't logged.
+#[cfg(not(feature = "postgres"))]
+impl std::fmt::Debug for ConnectionOptions {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("ConnectionOptions")
+            .field("enable_wal", &self.enable_wal)
+            .field("enable_foreign_keys", &self.enable_foreign_keys)
+            .field("busy_timeout", &self.busy_timeout)
+            .finish()
+    }
+}
+
+#[cfg(not(feature = "postgres"))]
+impl diesel::r2d2::CustomizeConnection<DbConnection, diesel::r2d2::Error> for ConnectionOptions {
+    fn on_acquire(&self, conn: &mut DbConnection) -> Result<(), diesel::r2d2::Error> {
         (|| {
             if let Some(d) = self.busy_timeout {
                 conn.batch_execute(&format!("PRAGMA busy_timeout = {};", d.as_millis()))?;
             }
-            WalletDb::set_db_encryption_key_from_env(conn);
+            let encryption_key = self.encryption_key.read().expect("lock poisoned");
+            if !encryption_key.is_empty() {
+                conn.batch_execute(&format!(
+                    "PRAGMA key = {};",
+                    sql_escape_string(&encryption_key)
+                ))?;
+            }
             if self.enable_wal {
                 conn.batch_execute("
                     PRAGMA journal_mode = WAL;          -- better write-concurrency
@@ -51,33 +111,211 @@ impl diesel::r2d2::CustomizeConnection<SqliteConnection, diesel::r2d2::Error>
 
 #[derive(Clone)]
 pub struct WalletDb {
-    pool: Pool<ConnectionManager<SqliteConnection>>,
+    /// The pool is replaced when the password changes, so that no connection
+    /// is left using the old one. It is only None if reopening the database
+    /// failed.
+    pool: Arc<RwLock<Option<Pool<ConnectionManager<DbConnection>>>>>,
+    database_url: String,
+    db_connections: u32,
+    encryption_key: Arc<RwLock<String>>,
 }
 
 impl WalletDb {
-    pub fn new(pool: Pool<ConnectionManager<SqliteConnection>>) -> Self {
-        Self { pool }
+    /// Open a pool of connections to the database, using the password in the
+    /// MC_PASSWORD environment variable, if any.
+    pub fn new_from_url(database_url: &str, db_connections: u32) -> Result<Self, WalletDbError> {
+        let encryption_key = Arc::new(RwLock::new(
+            env::var("MC_PASSWORD").unwrap_or_else(|_| "".to_string()),
+        ));
+        let pool = Self::build_pool(database_url, db_connections, encryption_key.clone())?;
+        Ok(Self {
+            pool: Arc::new(RwLock::new(Some(pool))),
+            database_url: database_url.to_string(),
+            db_connections,
+            encryption_key,
+        })
     }
 
-    pub fn new_from_url(database_url: &str, db_connections: u32) -> Result<Self, WalletDbError> {
-        let manager = ConnectionManager::<SqliteConnection>::new(database_url);
-        let pool = Pool::builder()
+    fn build_pool(
+        database_url: &str,
+        db_connections: u32,
+        encryption_key: Arc<RwLock<String>>,
+    ) -> Result<Pool<ConnectionManager<DbConnection>>, WalletDbError> {
+        let manager = ConnectionManager::<DbConnection>::new(database_url);
+        let builder = Pool::builder()
             .max_size(db_connections)
-            .connection_customizer(Box::new(ConnectionOptions {
-                enable_wal: true,
-                enable_foreign_keys: true,
-                busy_timeout: Some(Duration::from_secs(30)),
-            }))
-            .test_on_check_out(true)
-            .build(manager)?;
-        Ok(Self::new(pool))
+            .test_on_check_out(true);
+
+        #[cfg(not(feature = "postgres"))]
+        let builder = builder.connection_customizer(Box::new(ConnectionOptions {
+            enable_wal: true,
+            enable_foreign_keys: true,
+            busy_timeout: Some(Duration::from_secs(30)),
+            encryption_key,
+        }));
+
+        // Postgres databases are not encrypted by full-service.
+        #[cfg(feature = "postgres")]
+        let _ = encryption_key;
+
+        Ok(builder.build(manager)?)
     }
 
     pub fn get_conn(&self) -> Result<Conn, WalletDbError> {
-        Ok(self.pool.get()?)
+        let pool = self
+            .pool
+            .read()
+            .expect("lock poisoned")
+            .clone()
+            .ok_or(WalletDbError::DatabaseUnavailable)?;
+        Ok(pool.get()?)
     }
 
-    pub fn set_db_encryption_key_from_env(conn: &SqliteConnection) {
+    /// Whether the database is encrypted with a password.
+    pub fn is_encrypted(&self) -> bool {
+        !self
+            .encryption_key
+            .read()
+            .expect("lock poisoned")
+            .is_empty()
+    }
+
+    /// Encrypt an unencrypted database with the given password.
+    ///
+    /// SQLCipher can only encrypt a database by exporting it to a new file, so
+    /// the encrypted copy replaces the database file once every connection to
+    /// it has been closed.
+    #[cfg(not(feature = "postgres"))]
+    pub fn set_password(&self, password: &str) -> Result<(), WalletDbError> {
+        if password.is_empty() {
+            return Err(WalletDbError::EmptyDatabasePassword);
+        }
+        if self.is_encrypted() {
+            return Err(WalletDbError::DatabaseAlreadyEncrypted);
+        }
+
+        let database_path = PathBuf::from(&self.database_url);
+        let mut encrypted_path = database_path.clone().into_os_string();
+        encrypted_path.push(".encrypted");
+        let encrypted_path = PathBuf::from(encrypted_path);
+
+        self.with_pool_closed(|| {
+            if encrypted_path.exists() {
+                fs::remove_file(&encrypted_path)?;
+            }
+            {
+                let conn = DbConnection::establish(&self.database_url)?;
+                conn.batch_execute(&format!(
+                    "PRAGMA wal_checkpoint(TRUNCATE);
+                    ATTACH DATABASE {} AS encrypted KEY {};
+                    SELECT sqlcipher_export('encrypted');
+                    DETACH DATABASE encrypted;",
+                    sql_escape_string(&encrypted_path.to_string_lossy()),
+                    sql_escape_string(password)
+                ))?;
+            }
+            fs::rename(&encrypted_path, &database_path)?;
+            *self.encryption_key.write().expect("lock poisoned") = password.to_string();
+            Ok(())
+        })?;
+
+        global_log::info!("Encrypted database with new password.");
+        Ok(())
+    }
+
+    /// Re-encrypt the database with a new password.
+    #[cfg(not(feature = "postgres"))]
+    pub fn change_password(
+        &self,
+        old_password: &str,
+        new_password: &str,
+    ) -> Result<(), WalletDbError> {
+        if new_password.is_empty() {
+            return Err(WalletDbError::EmptyDatabasePassword);
+        }
+        if !self.is_encrypted() {
+            return Err(WalletDbError::DatabaseNotEncrypted);
+        }
+        if *self.encryption_key.read().expect("lock poisoned") != old_password {
+            return Err(WalletDbError::IncorrectDatabasePassword);
+        }
+
+        self.with_pool_closed(|| {
+            let conn = DbConnection::establish(&self.database_url)?;
+            conn.batch_execute(&format!(
+                "PRAGMA key = {};",
+                sql_escape_string(old_password)
+            ))?;
+            conn.batch_execute(&format!(
+                "PRAGMA rekey = {};",
+                sql_escape_string(new_password)
+            ))?;
+            *self.encryption_key.write().expect("lock poisoned") = new_password.to_string();
+            Ok(())
+        })?;
+
+        global_log::info!("Re-encrypted database with new password.");
+        Ok(())
+    }
+
+    /// Postgres databases are not encrypted by full-service, so have no
+    /// password to set.
+    #[cfg(feature = "postgres")]
+    pub fn set_password(&self, _password: &str) -> Result<(), WalletDbError> {
+        Err(WalletDbError::DatabaseEncryptionUnsupported)
+    }
+
+    /// Postgres databases are not encrypted by full-service, so have no
+    /// password to change.
+    #[cfg(feature = "postgres")]
+    pub fn change_password(
+        &self,
+        _old_password: &str,
+        _new_password: &str,
+    ) -> Result<(), WalletDbError> {
+        Err(WalletDbError::DatabaseEncryptionUnsupported)
+    }
+
+    /// Close every connection in the pool, run f, then open a new pool with
+    /// the current password.
+    ///
+    /// New connections wait until the pool is reopened. The pool is reopened
+    /// even if f fails, so that the wallet can carry on with the old password.
+    #[cfg(not(feature = "postgres"))]
+    fn with_pool_closed<F>(&self, f: F) -> Result<(), WalletDbError>
+    where
+        F: FnOnce() -> Result<(), WalletDbError>,
+    {
+        let mut pool = self.pool.write().expect("lock poisoned");
+
+        // Connections in use by other threads must be returned before the
+        // database file changes underneath them.
+        if let Some(open_pool) = pool.as_ref() {
+            let start = Instant::now();
+            loop {
+                let state = open_pool.state();
+                if state.idle_connections == state.connections {
+                    break;
+                }
+                if start.elapsed() > PASSWORD_CHANGE_TIMEOUT {
+                    return Err(WalletDbError::DatabaseBusy);
+                }
+                sleep(Duration::from_millis(50));
+            }
+        }
+        *pool = None;
+
+        let result = f();
+        *pool = Some(Self::build_pool(
+            &self.database_url,
+            self.db_connections,
+            self.encryption_key.clone(),
+        )?);
+        result
+    }
+
+    #[cfg(not(feature = "postgres"))]
+    pub fn set_db_encryption_key_from_env(conn: &DbConnection) {
         // Send the encryption key to SQLCipher, if it is not the empty string.
         let encryption_key = env::var("MC_PASSWORD").unwrap_or_else(|_| "".to_string());
         if !encryption_key.is_empty() {
@@ -91,7 +329,8 @@ impl WalletDb {
         }
     }
 
-    pub fn try_change_db_encryption_key_from_env(conn: &SqliteConnection) {
+    #[cfg(not(feature = "postgres"))]
+    pub fn try_change_db_encryption_key_from_env(conn: &DbConnection) {
         // Change the encryption key if specified by the environment variable.
         let encryption_key = env::var("MC_PASSWORD").unwrap_or_else(|_| "".to_string());
         let changed_encryption_key =
@@ -114,12 +353,19 @@ impl WalletDb {
         }
     }
 
-    pub fn check_database_connectivity(conn: &SqliteConnection) -> bool {
+    #[cfg(not(feature = "postgres"))]
+    pub fn check_database_connectivity(conn: &DbConnection) -> bool {
         conn.batch_execute("SELECT count(*) FROM sqlite_master;")
             .is_ok()
     }
 
-    pub fn validate_foreign_keys(conn: &SqliteConnection) {
+    #[cfg(feature = "postgres")]
+    pub fn check_database_connectivity(conn: &DbConnection) -> bool {
+        conn.batch_execute("SELECT 1;").is_ok()
+    }
+
+    #[cfg(not(feature = "postgres"))]
+    pub fn validate_foreign_keys(conn: &DbConnection) {
         let invalid_foreign_keys = diesel::dsl::sql::<(
             sql_types::Text,
             sql_types::Int8,
@@ -137,7 +383,36 @@ impl WalletDb {
         }
     }
 
-    pub fn run_migrations(conn: &SqliteConnection) {
+    /// The version of the most recent migration applied to the database.
+    pub fn schema_version(conn: &DbConnection) -> Result<Option<String>, WalletDbError> {
+        Ok(diesel::dsl::sql::<sql_types::Nullable<sql_types::Text>>(
+            "SELECT MAX(version) FROM __diesel_schema_migrations;",
+        )
+        .get_result::<Option<String>>(conn)?)
+    }
+
+    /// The file backing the main database of this connection, if any.
+    #[cfg(not(feature = "postgres"))]
+    pub fn database_path(conn: &DbConnection) -> Result<Option<PathBuf>, WalletDbError> {
+        let databases = diesel::dsl::sql::<(sql_types::Integer, sql_types::Text, sql_types::Text)>(
+            "PRAGMA database_list;",
+        )
+        .get_results::<(i32, String, String)>(conn)?;
+
+        Ok(databases
+            .into_iter()
+            .find(|(_seq, name, file)| name == "main" && !file.is_empty())
+            .map(|(_seq, _name, file)| PathBuf::from(file)))
+    }
+
+    /// A Postgres database is not backed by a local file.
+    #[cfg(feature = "postgres")]
+    pub fn database_path(_conn: &DbConnection) -> Result<Option<PathBuf>, WalletDbError> {
+        Ok(None)
+    }
+
+    #[cfg(not(feature = "postgres"))]
+    pub fn run_migrations(conn: &DbConnection) {
         // Our migrations sometimes violate foreign keys, so disable foreign key checks
         // while we apply them.
         // This has to happen outside the scope of a transaction. Quoting
@@ -155,9 +430,21 @@ impl WalletDb {
         conn.batch_execute("PRAGMA foreign_keys = ON;")
             .expect("failed enabling foreign keys");
     }
+
+    /// Postgres applies each migration in a transaction, checking foreign
+    /// keys as it goes.
+    #[cfg(feature = "postgres")]
+    pub fn run_migrations(conn: &DbConnection) {
+        embedded_migrations::run_with_output(conn, &mut std::io::stdout())
+            .expect("failed running migrations");
+    }
 }
 
 /// Create an immediate SQLite transaction with retry.
+///
+/// On Postgres the transaction is serializable instead, so that several
+/// full-service instances sharing the database can't interleave writes, and
+/// is retried when it conflicts with another.
 /// Note: This function does not support nested transactions.
 pub fn transaction<T, E, F>(conn: &Conn, f: F) -> Result<T, E>
 where
@@ -165,7 +452,13 @@ where
     E: From<diesel::result::Error>,
 {
     for i in 0..NUM_RETRIES {
+        #[cfg(not(feature = "postgres"))]
         let r = conn.exclusive_transaction::<T, E, F>(f.clone());
+        #[cfg(feature = "postgres")]
+        let r = conn
+            .build_transaction()
+            .serializable()
+            .run::<T, E, F>(f.clone());
         if r.is_ok() || i == (NUM_RETRIES - 1) {
             return r;
         }
@@ -179,6 +472,28 @@ const NUM_RETRIES: u32 = 5;
 /// Escape a string for consumption by SQLite.
 /// This function doubles all single quote characters within the string, then
 /// wraps the string in single quotes on the front and back.
+#[cfg(not(feature = "postgres"))]
 fn sql_escape_string(s: &str) -> String {
     format!("'{}'", s.replace('\'', "''"))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::WalletDbTestContext;
+    use mc_common::logger::{test_with_logger, Logger};
+
+    #[test_with_logger]
+    fn test_schema_version_is_latest_migration(logger: Logger) {
+        // Each backend has its own migrations, so a freshly migrated database
+        // must be at the newest migration of the backend it was built for.
+        let db_test_context = WalletDbTestContext::default();
+        let wallet_db = db_test_context.get_db_instance(logger);
+        let conn = wallet_db.get_conn().unwrap();
+
+        assert_eq!(
+            WalletDb::schema_version(&conn).unwrap().as_deref(),
+            Some(LATEST_MIGRATION_VERSION)
+        );
+    }
+}
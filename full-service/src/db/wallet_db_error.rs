@@ -1,6 +1,9 @@
 // Copyright (c) 2020-2021 MobileCoin Inc.
 
-use crate::{db::gift_code::GiftCodeDbError, util::b58::B58Error};
+use crate::{
+    db::{gift_code::GiftCodeDbError, txo::InsufficientFundsDetails},
+    util::b58::B58Error,
+};
 
 use displaydoc::Display;
 
@@ -18,6 +21,33 @@ pub enum WalletDbError {
     /// Error with rocket databases: {0}
     RocketDB(rocket_contrib::databases::r2d2::Error),
 
+    /// Error connecting to the database: {0}
+    Connection(diesel::ConnectionError),
+
+    /// Error replacing the database file: {0}
+    Io(std::io::Error),
+
+    /// The database is unavailable, because it could not be reopened
+    DatabaseUnavailable,
+
+    /// Timed out waiting for database connections to be returned
+    DatabaseBusy,
+
+    /// The database is already encrypted, use change_db_password
+    DatabaseAlreadyEncrypted,
+
+    /// The database is not encrypted, use set_db_password
+    DatabaseNotEncrypted,
+
+    /// Incorrect password for the database
+    IncorrectDatabasePassword,
+
+    /// The database password cannot be empty
+    EmptyDatabasePassword,
+
+    /// Database passwords are only supported for SQLite wallet databases
+    DatabaseEncryptionUnsupported,
+
     /// Duplicate entries with the same ID: {0}
     DuplicateEntries(String),
 
@@ -41,7 +71,7 @@ pub enum WalletDbError {
     /// Error decoding prost: {0}
     ProstDecode(mc_util_serial::DecodeError),
 
-    /// We expect one change output per TxProposal
+    /// We expect at most MAX_OUTPUTS outlays and change outputs per TxProposal
     UnexpectedNumberOfChangeOutputs,
 
     /// Key Image missing when recovering orphaned Txo
@@ -56,13 +86,13 @@ pub enum WalletDbError {
     /** Txos are too fragmented to construct a transaction with MAX_INPUTS.
      * Please combine txos.
      */
-    InsufficientFundsFragmentedTxos,
+    InsufficientFundsFragmentedTxos(InsufficientFundsDetails),
 
     /// Insufficient Funds: {0}
     InsufficientFunds(String),
 
     /// Insufficient funds from Txos under max_spendable_value: {0}
-    InsufficientFundsUnderMaxSpendable(String),
+    InsufficientFundsUnderMaxSpendable(InsufficientFundsDetails),
 
     /// Multiple AccountTxoStatus entries for Txo
     MultipleStatusesForTxo,
@@ -85,12 +115,48 @@ pub enum WalletDbError {
     /// AssignedSubaddress Not Found: {0}
     AssignedSubaddressNotFound(String),
 
+    /// The subaddress at index {0} is already assigned
+    SubaddressAlreadyAssigned(u64),
+
+    /// Subaddress index {0} is out of range
+    SubaddressIndexOutOfRange(u64),
+
     /// Txo Not Found: {0}
     TxoNotFound(String),
 
     /// TransactionLog Not Found: {0}
     TransactionLogNotFound(String),
 
+    /// TransactionWatch Not Found: {0}
+    TransactionWatchNotFound(String),
+
+    /// Cannot watch a transaction without any output public keys
+    TransactionWatchLacksOutputs,
+
+    /// AccountRotation Not Found: {0}
+    AccountRotationNotFound(String),
+
+    /// SweepPolicy Not Found: {0}
+    SweepPolicyNotFound(String),
+
+    /// QuarantinedBlock Not Found: {0}
+    QuarantinedBlockNotFound(u64),
+
+    /// Webhook Not Found: {0}
+    WebhookNotFound(i32),
+
+    /// ScheduledPayment Not Found: {0}
+    ScheduledPaymentNotFound(i32),
+
+    /// PaymentRequest Not Found: {0}
+    PaymentRequestNotFound(i32),
+
+    /// Unknown wallet setting: {0}
+    UnknownSetting(String),
+
+    /// Invalid value for wallet setting {0}: {1}
+    InvalidSettingValue(String, String),
+
     /// AccountTxoStatus not found: {0}
     AccountTxoStatusNotFound(String),
 
@@ -151,6 +217,18 @@ impl From<rocket_contrib::databases::r2d2::Error> for WalletDbError {
     }
 }
 
+impl From<diesel::ConnectionError> for WalletDbError {
+    fn from(src: diesel::ConnectionError) -> Self {
+        Self::Connection(src)
+    }
+}
+
+impl From<std::io::Error> for WalletDbError {
+    fn from(src: std::io::Error) -> Self {
+        Self::Io(src)
+    }
+}
+
 impl From<mc_api::ConversionError> for WalletDbError {
     fn from(src: mc_api::ConversionError) -> Self {
         Self::ProtoConversion(src)
@@ -0,0 +1,154 @@
+// Copyright (c) 2020-2022 MobileCoin Inc.
+
+//! The Wallet Event Model.
+
+use crate::db::{
+    models::{NewWalletEvent, WalletEvent, WALLET_EVENT_SCHEMA_VERSION},
+    Conn, WalletDbError,
+};
+use diesel::prelude::*;
+
+pub trait WalletEventModel {
+    /// Record an event.
+    fn create(
+        event_type: &str,
+        payload: &serde_json::Value,
+        conn: &Conn,
+    ) -> Result<WalletEvent, WalletDbError>;
+
+    /// List events in the order they were recorded, optionally starting after
+    /// the event with the given id.
+    fn list(
+        after_id: Option<i32>,
+        limit: Option<u64>,
+        conn: &Conn,
+    ) -> Result<Vec<WalletEvent>, WalletDbError>;
+
+    /// List the events with ids from `from_id` through `to_id` inclusive, or
+    /// through the latest event if `to_id` is not given.
+    fn list_range(
+        from_id: i32,
+        to_id: Option<i32>,
+        conn: &Conn,
+    ) -> Result<Vec<WalletEvent>, WalletDbError>;
+
+    /// Get the most recently recorded event of the given type, if any.
+    fn get_latest(event_type: &str, conn: &Conn) -> Result<Option<WalletEvent>, WalletDbError>;
+}
+
+impl WalletEventModel for WalletEvent {
+    fn create(
+        event_type: &str,
+        payload: &serde_json::Value,
+        conn: &Conn,
+    ) -> Result<WalletEvent, WalletDbError> {
+        use crate::db::schema::wallet_events;
+
+        let payload = payload.to_string();
+        let new_event = NewWalletEvent {
+            event_type,
+            payload: &payload,
+            schema_version: WALLET_EVENT_SCHEMA_VERSION,
+        };
+        diesel::insert_into(wallet_events::table)
+            .values(&new_event)
+            .execute(conn)?;
+
+        Ok(wallet_events::table
+            .order(wallet_events::id.desc())
+            .first::<WalletEvent>(conn)?)
+    }
+
+    fn list(
+        after_id: Option<i32>,
+        limit: Option<u64>,
+        conn: &Conn,
+    ) -> Result<Vec<WalletEvent>, WalletDbError> {
+        use crate::db::schema::wallet_events;
+
+        let query = wallet_events::table
+            .filter(wallet_events::id.gt(after_id.unwrap_or(0)))
+            .order(wallet_events::id.asc());
+
+        Ok(match limit {
+            Some(limit) => query.limit(limit as i64).load::<WalletEvent>(conn)?,
+            None => query.load::<WalletEvent>(conn)?,
+        })
+    }
+
+    fn list_range(
+        from_id: i32,
+        to_id: Option<i32>,
+        conn: &Conn,
+    ) -> Result<Vec<WalletEvent>, WalletDbError> {
+        use crate::db::schema::wallet_events;
+
+        let mut query = wallet_events::table
+            .filter(wallet_events::id.ge(from_id))
+            .into_boxed();
+
+        if let Some(to_id) = to_id {
+            query = query.filter(wallet_events::id.le(to_id));
+        }
+
+        Ok(query
+            .order(wallet_events::id.asc())
+            .load::<WalletEvent>(conn)?)
+    }
+
+    fn get_latest(event_type: &str, conn: &Conn) -> Result<Option<WalletEvent>, WalletDbError> {
+        use crate::db::schema::wallet_events;
+
+        Ok(wallet_events::table
+            .filter(wallet_events::event_type.eq(event_type))
+            .order(wallet_events::id.desc())
+            .first::<WalletEvent>(conn)
+            .optional()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::WalletDbTestContext;
+    use mc_common::logger::{test_with_logger, Logger};
+    use serde_json::json;
+
+    #[test_with_logger]
+    fn test_wallet_event_crud(logger: Logger) {
+        let db_test_context = WalletDbTestContext::default();
+        let wallet_db = db_test_context.get_db_instance(logger);
+        let conn = wallet_db.get_conn().unwrap();
+
+        let first = WalletEvent::create("first", &json!({"value": 1}), &conn).unwrap();
+        let second = WalletEvent::create("second", &json!({"value": 2}), &conn).unwrap();
+        assert_eq!(first.event_type, "first");
+        assert_eq!(second.payload, "{\"value\":2}");
+        assert_eq!(second.schema_version, WALLET_EVENT_SCHEMA_VERSION);
+        assert!(second.id > first.id);
+
+        let events = WalletEvent::list(None, None, &conn).unwrap();
+        assert_eq!(events, vec![first.clone(), second.clone()]);
+
+        let events = WalletEvent::list(Some(first.id), None, &conn).unwrap();
+        assert_eq!(events, vec![second]);
+
+        let events = WalletEvent::list(None, Some(1), &conn).unwrap();
+        assert_eq!(events, vec![first.clone()]);
+
+        let third = WalletEvent::create("third", &json!({"value": 3}), &conn).unwrap();
+
+        let events = WalletEvent::list_range(first.id, Some(second.id), &conn).unwrap();
+        assert_eq!(events, vec![first, second.clone()]);
+
+        let events = WalletEvent::list_range(second.id, None, &conn).unwrap();
+        assert_eq!(events, vec![second.clone(), third]);
+
+        let latest = WalletEvent::create("second", &json!({"value": 4}), &conn).unwrap();
+        assert_eq!(
+            WalletEvent::get_latest("second", &conn).unwrap(),
+            Some(latest)
+        );
+        assert_eq!(WalletEvent::get_latest("fourth", &conn).unwrap(), None);
+    }
+}
@@ -0,0 +1,257 @@
+// Copyright (c) 2020-2022 MobileCoin Inc.
+
+//! The Wallet Settings Model.
+//!
+//! Wallet-wide preferences which can be changed while the wallet is running.
+//! Each setting is stored as a name and a string value, and any setting which
+//! has never been set takes its default.
+
+use crate::db::{
+    models::{NewWalletSetting, WalletSetting},
+    Conn, WalletDbError,
+};
+use diesel::prelude::*;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// The multiple of the network minimum fee paid by transactions built without
+/// an explicit fee.
+pub const SETTING_DEFAULT_FEE_MULTIPLIER: &str = "default_fee_multiplier";
+
+/// The number of blocks, counting the one it landed in, that a watched
+/// transaction must be buried under before it is considered landed.
+pub const SETTING_CONFIRMATION_DEPTH: &str = "confirmation_depth";
+
+/// The value at or below which scheduled sweeps are skipped.
+pub const SETTING_DUST_THRESHOLD: &str = "dust_threshold";
+
+/// How long to wait for a webhook endpoint to respond.
+pub const SETTING_WEBHOOK_TIMEOUT_SECONDS: &str = "webhook_timeout_seconds";
+
+/// How many times to try to deliver a webhook before giving up.
+pub const SETTING_WEBHOOK_MAX_ATTEMPTS: &str = "webhook_max_attempts";
+
+/// How many unfunded subaddress indices in a row the sync looks past for
+/// subaddresses which orphaned txos were sent to, or 0 not to look.
+pub const SETTING_SUBADDRESS_GAP_LIMIT: &str = "subaddress_gap_limit";
+
+/// Whether spending was disabled with disable_spending, and has not been
+/// re-enabled with the admin key since. It is stored alongside the settings,
+/// but is not one, so that update_wallet_settings can't change it.
+pub const SPENDING_DISABLED: &str = "spending_disabled";
+
+/// The wallet's settings, with defaults for any which are unset.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct WalletSettings {
+    pub default_fee_multiplier: u64,
+    pub confirmation_depth: u64,
+    pub dust_threshold: u64,
+    pub webhook_timeout_seconds: u64,
+    pub webhook_max_attempts: u64,
+    pub subaddress_gap_limit: u64,
+}
+
+impl Default for WalletSettings {
+    fn default() -> Self {
+        Self {
+            default_fee_multiplier: 1,
+            confirmation_depth: 1,
+            dust_threshold: 0,
+            webhook_timeout_seconds: 10,
+            webhook_max_attempts: 3,
+            subaddress_gap_limit: 0,
+        }
+    }
+}
+
+impl WalletSettings {
+    /// Parse and apply a setting by name.
+    fn apply(&mut self, name: &str, value: &str) -> Result<(), WalletDbError> {
+        let (setting, minimum) = match name {
+            SETTING_DEFAULT_FEE_MULTIPLIER => (&mut self.default_fee_multiplier, 1),
+            SETTING_CONFIRMATION_DEPTH => (&mut self.confirmation_depth, 1),
+            SETTING_DUST_THRESHOLD => (&mut self.dust_threshold, 0),
+            SETTING_WEBHOOK_TIMEOUT_SECONDS => (&mut self.webhook_timeout_seconds, 1),
+            SETTING_WEBHOOK_MAX_ATTEMPTS => (&mut self.webhook_max_attempts, 1),
+            SETTING_SUBADDRESS_GAP_LIMIT => (&mut self.subaddress_gap_limit, 0),
+            _ => return Err(WalletDbError::UnknownSetting(name.to_string())),
+        };
+
+        let value = value
+            .parse::<u64>()
+            .map_err(|err| WalletDbError::InvalidSettingValue(name.to_string(), err.to_string()))?;
+        if value < minimum {
+            return Err(WalletDbError::InvalidSettingValue(
+                name.to_string(),
+                format!("must be at least {}", minimum),
+            ));
+        }
+        *setting = value;
+        Ok(())
+    }
+}
+
+pub trait WalletSettingModel {
+    /// Get the wallet's settings.
+    fn get_settings(conn: &Conn) -> Result<WalletSettings, WalletDbError>;
+
+    /// Update the named settings, leaving the rest as they are.
+    ///
+    /// Every setting is validated before any are saved.
+    fn update_settings(
+        settings: &BTreeMap<String, String>,
+        conn: &Conn,
+    ) -> Result<WalletSettings, WalletDbError>;
+
+    /// Whether spending was disabled, so that it stays disabled across a
+    /// restart.
+    fn is_spending_disabled(conn: &Conn) -> Result<bool, WalletDbError>;
+
+    /// Record whether spending is disabled.
+    fn set_spending_disabled(disabled: bool, conn: &Conn) -> Result<(), WalletDbError>;
+}
+
+impl WalletSettingModel for WalletSetting {
+    fn get_settings(conn: &Conn) -> Result<WalletSettings, WalletDbError> {
+        use crate::db::schema::wallet_settings;
+
+        let mut settings = WalletSettings::default();
+        for setting in wallet_settings::table.load::<WalletSetting>(conn)? {
+            // Settings were validated when they were saved, so the only ones
+            // which fail to apply are those a newer version of the wallet
+            // knows about, and are left for it.
+            let _ = settings.apply(&setting.name, &setting.value);
+        }
+        Ok(settings)
+    }
+
+    fn update_settings(
+        settings: &BTreeMap<String, String>,
+        conn: &Conn,
+    ) -> Result<WalletSettings, WalletDbError> {
+        use crate::db::schema::wallet_settings;
+
+        let mut updated = WalletSetting::get_settings(conn)?;
+        for (name, value) in settings {
+            updated.apply(name, value)?;
+        }
+
+        for (name, value) in settings {
+            diesel::delete(wallet_settings::table.filter(wallet_settings::name.eq(name)))
+                .execute(conn)?;
+            diesel::insert_into(wallet_settings::table)
+                .values(&NewWalletSetting { name, value })
+                .execute(conn)?;
+        }
+
+        Ok(updated)
+    }
+
+    fn is_spending_disabled(conn: &Conn) -> Result<bool, WalletDbError> {
+        use crate::db::schema::wallet_settings;
+
+        Ok(wallet_settings::table
+            .filter(wallet_settings::name.eq(SPENDING_DISABLED))
+            .select(wallet_settings::value)
+            .first::<String>(conn)
+            .optional()?
+            .map_or(false, |value| value == "true"))
+    }
+
+    fn set_spending_disabled(disabled: bool, conn: &Conn) -> Result<(), WalletDbError> {
+        use crate::db::schema::wallet_settings;
+
+        diesel::delete(wallet_settings::table.filter(wallet_settings::name.eq(SPENDING_DISABLED)))
+            .execute(conn)?;
+        if disabled {
+            diesel::insert_into(wallet_settings::table)
+                .values(&NewWalletSetting {
+                    name: SPENDING_DISABLED,
+                    value: "true",
+                })
+                .execute(conn)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::WalletDbTestContext;
+    use mc_common::logger::{test_with_logger, Logger};
+
+    #[test_with_logger]
+    fn test_wallet_settings(logger: Logger) {
+        let db_test_context = WalletDbTestContext::default();
+        let wallet_db = db_test_context.get_db_instance(logger);
+        let conn = wallet_db.get_conn().unwrap();
+
+        assert_eq!(
+            WalletSetting::get_settings(&conn).unwrap(),
+            WalletSettings::default()
+        );
+
+        let mut updates = BTreeMap::new();
+        updates.insert(SETTING_CONFIRMATION_DEPTH.to_string(), "6".to_string());
+        updates.insert(SETTING_DUST_THRESHOLD.to_string(), "1000".to_string());
+        let updated = WalletSetting::update_settings(&updates, &conn).unwrap();
+        assert_eq!(updated.confirmation_depth, 6);
+        assert_eq!(updated.dust_threshold, 1000);
+        assert_eq!(updated.default_fee_multiplier, 1);
+        assert_eq!(WalletSetting::get_settings(&conn).unwrap(), updated);
+
+        // Setting a value again replaces it.
+        let mut updates = BTreeMap::new();
+        updates.insert(SETTING_CONFIRMATION_DEPTH.to_string(), "3".to_string());
+        let updated = WalletSetting::update_settings(&updates, &conn).unwrap();
+        assert_eq!(updated.confirmation_depth, 3);
+        assert_eq!(updated.dust_threshold, 1000);
+
+        // An invalid setting rejects the whole update.
+        let mut updates = BTreeMap::new();
+        updates.insert(SETTING_DUST_THRESHOLD.to_string(), "5".to_string());
+        updates.insert(SETTING_WEBHOOK_MAX_ATTEMPTS.to_string(), "0".to_string());
+        match WalletSetting::update_settings(&updates, &conn) {
+            Err(WalletDbError::InvalidSettingValue(name, _)) => {
+                assert_eq!(name, SETTING_WEBHOOK_MAX_ATTEMPTS)
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+        let mut updates = BTreeMap::new();
+        updates.insert("fee_pmob".to_string(), "5".to_string());
+        match WalletSetting::update_settings(&updates, &conn) {
+            Err(WalletDbError::UnknownSetting(name)) => assert_eq!(name, "fee_pmob"),
+            other => panic!("unexpected result {:?}", other),
+        }
+        assert_eq!(WalletSetting::get_settings(&conn).unwrap(), updated);
+    }
+
+    #[test_with_logger]
+    fn test_spending_disabled(logger: Logger) {
+        let db_test_context = WalletDbTestContext::default();
+        let wallet_db = db_test_context.get_db_instance(logger);
+        let conn = wallet_db.get_conn().unwrap();
+
+        assert!(!WalletSetting::is_spending_disabled(&conn).unwrap());
+        WalletSetting::set_spending_disabled(true, &conn).unwrap();
+        assert!(WalletSetting::is_spending_disabled(&conn).unwrap());
+
+        // It is not a setting, so it doesn't show up in, and can't be changed
+        // with, the settings.
+        assert_eq!(
+            WalletSetting::get_settings(&conn).unwrap(),
+            WalletSettings::default()
+        );
+        let mut updates = BTreeMap::new();
+        updates.insert(SPENDING_DISABLED.to_string(), "false".to_string());
+        assert!(matches!(
+            WalletSetting::update_settings(&updates, &conn),
+            Err(WalletDbError::UnknownSetting(_))
+        ));
+        assert!(WalletSetting::is_spending_disabled(&conn).unwrap());
+
+        WalletSetting::set_spending_disabled(false, &conn).unwrap();
+        assert!(!WalletSetting::is_spending_disabled(&conn).unwrap());
+    }
+}
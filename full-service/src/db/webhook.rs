@@ -0,0 +1,182 @@
+// Copyright (c) 2020-2022 MobileCoin Inc.
+
+//! The Webhook Model.
+//!
+//! A webhook follows the wallet event log, keeping track of the last event
+//! delivered to it, so that events recorded while the endpoint is unreachable
+//! or the wallet is stopped are delivered once it is back.
+
+use crate::db::{
+    models::{NewWebhook, Webhook},
+    Conn, WalletDbError,
+};
+use diesel::prelude::*;
+
+pub trait WebhookModel {
+    /// Register a webhook. It is sent the events recorded from now on.
+    fn create(
+        url: &str,
+        account_id_hex: Option<&str>,
+        now: i64,
+        conn: &Conn,
+    ) -> Result<Webhook, WalletDbError>;
+
+    /// Get a webhook by its id.
+    fn get(id: i32, conn: &Conn) -> Result<Webhook, WalletDbError>;
+
+    /// List all webhooks, in the order they were registered.
+    fn list_all(conn: &Conn) -> Result<Vec<Webhook>, WalletDbError>;
+
+    /// Move the webhook past the given event, whether it was delivered, given
+    /// up on, or not for this webhook, and reset its failed attempts.
+    fn record_delivered(&self, event_id: i32, conn: &Conn) -> Result<(), WalletDbError>;
+
+    /// Record a failed attempt to deliver the webhook's next event.
+    fn record_failed_attempt(
+        &self,
+        failure_message: &str,
+        conn: &Conn,
+    ) -> Result<(), WalletDbError>;
+
+    /// Remove the webhook.
+    fn delete(&self, conn: &Conn) -> Result<(), WalletDbError>;
+}
+
+impl WebhookModel for Webhook {
+    fn create(
+        url: &str,
+        account_id_hex: Option<&str>,
+        now: i64,
+        conn: &Conn,
+    ) -> Result<Webhook, WalletDbError> {
+        use crate::db::schema::{wallet_events, webhooks};
+
+        let last_event_id = wallet_events::table
+            .select(diesel::dsl::max(wallet_events::id))
+            .first::<Option<i32>>(conn)?
+            .unwrap_or(0);
+
+        let new_webhook = NewWebhook {
+            url,
+            account_id_hex,
+            last_event_id,
+            created_time: now,
+        };
+        diesel::insert_into(webhooks::table)
+            .values(&new_webhook)
+            .execute(conn)?;
+
+        Ok(webhooks::table
+            .order(webhooks::id.desc())
+            .first::<Webhook>(conn)?)
+    }
+
+    fn get(id: i32, conn: &Conn) -> Result<Webhook, WalletDbError> {
+        use crate::db::schema::webhooks;
+
+        match webhooks::table
+            .filter(webhooks::id.eq(id))
+            .get_result::<Webhook>(conn)
+        {
+            Ok(webhook) => Ok(webhook),
+            // Match on NotFound to get a more informative NotFound Error
+            Err(diesel::result::Error::NotFound) => Err(WalletDbError::WebhookNotFound(id)),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn list_all(conn: &Conn) -> Result<Vec<Webhook>, WalletDbError> {
+        use crate::db::schema::webhooks;
+
+        Ok(webhooks::table.order(webhooks::id.asc()).load(conn)?)
+    }
+
+    fn record_delivered(&self, event_id: i32, conn: &Conn) -> Result<(), WalletDbError> {
+        use crate::db::schema::webhooks;
+
+        diesel::update(webhooks::table.filter(webhooks::id.eq(self.id)))
+            .set((
+                webhooks::last_event_id.eq(event_id),
+                webhooks::failed_attempts.eq(0),
+            ))
+            .execute(conn)?;
+        Ok(())
+    }
+
+    fn record_failed_attempt(
+        &self,
+        failure_message: &str,
+        conn: &Conn,
+    ) -> Result<(), WalletDbError> {
+        use crate::db::schema::webhooks;
+
+        diesel::update(webhooks::table.filter(webhooks::id.eq(self.id)))
+            .set((
+                webhooks::failed_attempts.eq(webhooks::failed_attempts + 1),
+                webhooks::last_failure_message.eq(failure_message),
+            ))
+            .execute(conn)?;
+        Ok(())
+    }
+
+    fn delete(&self, conn: &Conn) -> Result<(), WalletDbError> {
+        use crate::db::schema::webhooks;
+
+        diesel::delete(webhooks::table.filter(webhooks::id.eq(self.id))).execute(conn)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        db::{models::WalletEvent, wallet_event::WalletEventModel},
+        test_utils::WalletDbTestContext,
+    };
+    use mc_common::logger::{test_with_logger, Logger};
+    use serde_json::json;
+
+    #[test_with_logger]
+    fn test_webhook_crud(logger: Logger) {
+        let db_test_context = WalletDbTestContext::default();
+        let wallet_db = db_test_context.get_db_instance(logger);
+        let conn = wallet_db.get_conn().unwrap();
+
+        let first = Webhook::create("http://localhost:9091/all", None, 1000, &conn).unwrap();
+        assert_eq!(first.last_event_id, 0);
+        assert_eq!(first.account_id_hex, None);
+
+        // A webhook starts from the latest event, rather than being sent the
+        // whole log.
+        let event = WalletEvent::create("test_event", &json!({}), &conn).unwrap();
+        let second =
+            Webhook::create("http://localhost:9091/one", Some("abcd"), 1001, &conn).unwrap();
+        assert_eq!(second.last_event_id, event.id);
+        assert_eq!(
+            Webhook::list_all(&conn).unwrap(),
+            vec![first.clone(), second.clone()]
+        );
+
+        first.record_failed_attempt("timed out", &conn).unwrap();
+        first.record_failed_attempt("timed out", &conn).unwrap();
+        let first = Webhook::get(first.id, &conn).unwrap();
+        assert_eq!(first.failed_attempts, 2);
+        assert_eq!(first.last_failure_message, Some("timed out".to_string()));
+
+        // Moving past the event resets the attempts, but keeps the reason the
+        // last one failed.
+        first.record_delivered(event.id, &conn).unwrap();
+        let first = Webhook::get(first.id, &conn).unwrap();
+        assert_eq!(first.last_event_id, event.id);
+        assert_eq!(first.failed_attempts, 0);
+        assert_eq!(first.last_failure_message, Some("timed out".to_string()));
+
+        first.delete(&conn).unwrap();
+        match Webhook::get(first.id, &conn) {
+            Err(WalletDbError::WebhookNotFound(_)) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+        assert_eq!(Webhook::list_all(&conn).unwrap(), vec![second]);
+    }
+}
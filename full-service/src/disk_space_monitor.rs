@@ -0,0 +1,495 @@
+// Copyright (c) 2020-2022 MobileCoin Inc.
+
+//! Monitoring of free space on the volumes holding the ledger and wallet
+//! databases.
+//!
+//! LMDB does not cope well with running out of space part way through a write,
+//! so when free space becomes critically low we stop downloading blocks until
+//! space is freed, rather than risk corrupting the ledger.
+//!
+//! LMDB also fails writes once the database outgrows the map size it was
+//! opened with, so the ledger database is given a maximum size no larger than
+//! that, and counts as critically low on space as it nears it.
+
+use crate::{
+    metrics,
+    util::disk_space::{database_size, disk_space, DiskSpace, DiskSpaceLevel, DiskSpaceThresholds},
+};
+use mc_common::logger::{log, Logger};
+use mc_ledger_sync::LedgerSyncServiceThread;
+use std::{
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, RwLock,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+/// The most recent measurement of a monitored volume.
+#[derive(Clone, Debug)]
+pub struct VolumeStatus {
+    /// Name of the volume, e.g. "ledger_db".
+    pub name: String,
+
+    /// Path of the database on the volume.
+    pub path: PathBuf,
+
+    /// Space usage, or None if it could not be measured.
+    pub space: Option<DiskSpace>,
+
+    /// Size of the database, or None if it could not be measured.
+    pub size_bytes: Option<u64>,
+
+    /// The size the database may grow to, if it is limited.
+    pub max_size_bytes: Option<u64>,
+
+    /// Free space relative to the thresholds.
+    pub level: DiskSpaceLevel,
+}
+
+impl VolumeStatus {
+    /// The space the database has left to grow into, which is the free space
+    /// on the volume, or the space left before its maximum size if that is
+    /// less.
+    pub fn headroom(&self) -> Option<DiskSpace> {
+        let space = self.space?;
+        match (self.max_size_bytes, self.size_bytes) {
+            (Some(max_size_bytes), Some(size_bytes)) => Some(DiskSpace {
+                available_bytes: space
+                    .available_bytes
+                    .min(max_size_bytes.saturating_sub(size_bytes)),
+                total_bytes: space.total_bytes,
+            }),
+            _ => Some(space),
+        }
+    }
+
+    /// Whether the database's maximum size, rather than the free space on the
+    /// volume, limits how much it can grow.
+    pub fn is_limited_by_max_size(&self) -> bool {
+        match (self.headroom(), self.space) {
+            (Some(headroom), Some(space)) => headroom.available_bytes < space.available_bytes,
+            _ => false,
+        }
+    }
+}
+
+/// A shared handle to the latest disk space measurements.
+#[derive(Clone, Debug, Default)]
+pub struct DiskSpaceState {
+    volumes: Arc<RwLock<Vec<VolumeStatus>>>,
+    thresholds: DiskSpaceThresholds,
+    ledger_sync_holds: Arc<AtomicUsize>,
+    ledger_sync_running: Arc<AtomicBool>,
+}
+
+impl DiskSpaceState {
+    /// The latest measurement of each monitored volume.
+    pub fn volumes(&self) -> Vec<VolumeStatus> {
+        self.volumes.read().expect("lock poisoned").clone()
+    }
+
+    /// The latest measurement of the named volume.
+    pub fn volume(&self, name: &str) -> Option<VolumeStatus> {
+        self.volumes
+            .read()
+            .expect("lock poisoned")
+            .iter()
+            .find(|volume| volume.name == name)
+            .cloned()
+    }
+
+    /// The worst level across all monitored volumes.
+    pub fn level(&self) -> DiskSpaceLevel {
+        self.volumes
+            .read()
+            .expect("lock poisoned")
+            .iter()
+            .map(|volume| volume.level)
+            .max()
+            .unwrap_or_default()
+    }
+
+    /// Whether any volume is critically low, in which case the ledger should
+    /// not be written to.
+    pub fn is_critical(&self) -> bool {
+        self.level() == DiskSpaceLevel::Critical
+    }
+
+    /// The thresholds the volumes are measured against.
+    pub fn thresholds(&self) -> DiskSpaceThresholds {
+        self.thresholds
+    }
+
+    /// Limit the size the named database may grow to, returning false if no
+    /// such volume is monitored. The limit takes effect at the next check.
+    pub fn set_max_size(&self, name: &str, max_size_bytes: Option<u64>) -> bool {
+        let mut volumes = self.volumes.write().expect("lock poisoned");
+        match volumes.iter_mut().find(|volume| volume.name == name) {
+            Some(volume) => {
+                volume.max_size_bytes = max_size_bytes;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Pause ledger sync until the returned hold is dropped, so that the
+    /// ledger database is not written to meanwhile.
+    ///
+    /// The sync stops at its next check, which can be waited for with
+    /// wait_for_ledger_sync_to_stop.
+    pub fn hold_ledger_sync(&self) -> LedgerSyncHold {
+        self.ledger_sync_holds.fetch_add(1, Ordering::SeqCst);
+        LedgerSyncHold {
+            ledger_sync_holds: self.ledger_sync_holds.clone(),
+        }
+    }
+
+    /// Whether ledger sync should be paused, because a volume is critically
+    /// low on space or the sync is held.
+    pub fn should_pause_ledger_sync(&self) -> bool {
+        self.ledger_sync_holds.load(Ordering::SeqCst) > 0 || self.is_critical()
+    }
+
+    /// Whether ledger sync may be writing to the ledger database.
+    pub fn is_ledger_sync_running(&self) -> bool {
+        self.ledger_sync_running.load(Ordering::SeqCst)
+    }
+
+    /// Record whether ledger sync may be writing to the ledger database.
+    ///
+    /// Whatever runs the sync sets this before checking
+    /// should_pause_ledger_sync, so that either it sees a new hold, or the
+    /// holder sees that the sync is running and waits for it to stop.
+    pub fn set_ledger_sync_running(&self, running: bool) {
+        self.ledger_sync_running.store(running, Ordering::SeqCst);
+    }
+
+    /// Wait until ledger sync has stopped, returning false if it is still
+    /// running after the timeout.
+    pub fn wait_for_ledger_sync_to_stop(&self, timeout: Duration) -> bool {
+        let start = Instant::now();
+        while self.is_ledger_sync_running() {
+            if start.elapsed() > timeout {
+                return false;
+            }
+            thread::sleep(Duration::from_millis(100));
+        }
+        true
+    }
+
+    /// Measure all volumes, returning the previous and new level of any volume
+    /// whose level changed.
+    fn update(&self) -> Vec<(VolumeStatus, DiskSpaceLevel)> {
+        let mut volumes = self.volumes.write().expect("lock poisoned");
+        let mut changed = Vec::new();
+        for volume in volumes.iter_mut() {
+            let previous_level = volume.level;
+            volume.space = disk_space(&volume.path).ok();
+            volume.size_bytes = database_size(&volume.path).ok();
+            // If we can't measure the volume, keep the previous level rather
+            // than flapping the ledger sync on a transient error.
+            if let Some(headroom) = volume.headroom() {
+                volume.level = self.thresholds.level(&headroom);
+            }
+            metrics::set_disk_space(
+                &volume.name,
+                volume.headroom().map(|headroom| headroom.available_bytes),
+                volume.level,
+            );
+            if volume.level != previous_level {
+                changed.push((volume.clone(), previous_level));
+            }
+        }
+        changed
+    }
+}
+
+/// Keeps ledger sync paused while it is alive.
+pub struct LedgerSyncHold {
+    ledger_sync_holds: Arc<AtomicUsize>,
+}
+
+impl Drop for LedgerSyncHold {
+    fn drop(&mut self) {
+        self.ledger_sync_holds.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Factory for the ledger sync thread, so that it can be stopped while disk
+/// space is critically low and restarted once space is freed.
+pub type LedgerSyncFactory = Box<dyn FnMut() -> LedgerSyncServiceThread + Send>;
+
+pub struct DiskSpaceMonitor {
+    join_handle: Option<thread::JoinHandle<()>>,
+    stop_requested: Arc<AtomicBool>,
+    state: DiskSpaceState,
+}
+
+impl DiskSpaceMonitor {
+    /// Start monitoring the given (name, path, max size) volumes.
+    ///
+    /// The volumes are measured once before returning, so that the state is
+    /// populated from the start. If a ledger sync factory is given, the
+    /// monitor owns the ledger sync thread, and only runs it while no volume
+    /// is critically low.
+    pub fn new(
+        volumes: Vec<(String, PathBuf, Option<u64>)>,
+        thresholds: DiskSpaceThresholds,
+        poll_interval: Duration,
+        mut ledger_sync_factory: Option<LedgerSyncFactory>,
+        logger: Logger,
+    ) -> Self {
+        let state = DiskSpaceState {
+            volumes: Arc::new(RwLock::new(
+                volumes
+                    .into_iter()
+                    .map(|(name, path, max_size_bytes)| VolumeStatus {
+                        name,
+                        path,
+                        space: None,
+                        size_bytes: None,
+                        max_size_bytes,
+                        level: DiskSpaceLevel::Ok,
+                    })
+                    .collect(),
+            )),
+            thresholds,
+            ledger_sync_holds: Arc::new(AtomicUsize::new(0)),
+            ledger_sync_running: Arc::new(AtomicBool::new(false)),
+        };
+
+        let mut ledger_sync_thread = None;
+        Self::check(
+            &state,
+            &mut ledger_sync_factory,
+            &mut ledger_sync_thread,
+            &logger,
+        );
+
+        let stop_requested = Arc::new(AtomicBool::new(false));
+        let thread_stop_requested = stop_requested.clone();
+        let thread_state = state.clone();
+        let join_handle = Some(
+            thread::Builder::new()
+                .name("DiskSpaceMonitor".into())
+                .spawn(move || {
+                    log::info!(logger, "DiskSpaceMonitor thread started");
+                    loop {
+                        if thread_stop_requested.load(Ordering::SeqCst) {
+                            log::debug!(logger, "DiskSpaceMonitor stop requested.");
+                            break;
+                        }
+                        Self::check(
+                            &thread_state,
+                            &mut ledger_sync_factory,
+                            &mut ledger_sync_thread,
+                            &logger,
+                        );
+                        thread::sleep(poll_interval);
+                    }
+                })
+                .expect("Failed spawning DiskSpaceMonitor thread"),
+        );
+
+        Self {
+            join_handle,
+            stop_requested,
+            state,
+        }
+    }
+
+    /// A handle to the latest measurements.
+    pub fn state(&self) -> DiskSpaceState {
+        self.state.clone()
+    }
+
+    pub fn stop(&mut self) {
+        self.stop_requested.store(true, Ordering::SeqCst);
+        if let Some(thread) = self.join_handle.take() {
+            thread.join().expect("thread join failed");
+        }
+    }
+
+    fn check(
+        state: &DiskSpaceState,
+        ledger_sync_factory: &mut Option<LedgerSyncFactory>,
+        ledger_sync_thread: &mut Option<LedgerSyncServiceThread>,
+        logger: &Logger,
+    ) {
+        for (volume, previous_level) in state.update() {
+            let available_mb = volume
+                .headroom()
+                .map(|space| space.available_bytes / (1024 * 1024))
+                .unwrap_or_default();
+            match volume.level {
+                DiskSpaceLevel::Critical if volume.is_limited_by_max_size() => log::error!(
+                    logger,
+                    "The {} at {:?} is within {} MB of its maximum size of {} MB, ledger sync is paused. Raise --ledger-db-max-size-mb, or relocate the ledger with relocate_ledger_db",
+                    volume.name,
+                    volume.path,
+                    available_mb,
+                    volume.max_size_bytes.unwrap_or_default() / (1024 * 1024)
+                ),
+                DiskSpaceLevel::Critical => log::error!(
+                    logger,
+                    "Disk space for {} at {:?} is critically low ({} MB free), ledger sync is paused",
+                    volume.name,
+                    volume.path,
+                    available_mb
+                ),
+                DiskSpaceLevel::Low => log::warn!(
+                    logger,
+                    "Disk space for {} at {:?} is low ({} MB free)",
+                    volume.name,
+                    volume.path,
+                    available_mb
+                ),
+                DiskSpaceLevel::Ok => log::info!(
+                    logger,
+                    "Disk space for {} at {:?} recovered from {} ({} MB free)",
+                    volume.name,
+                    volume.path,
+                    previous_level,
+                    available_mb
+                ),
+            }
+        }
+
+        if let Some(factory) = ledger_sync_factory.as_mut() {
+            state.set_ledger_sync_running(true);
+            if state.should_pause_ledger_sync() {
+                if let Some(mut thread) = ledger_sync_thread.take() {
+                    if state.is_critical() {
+                        log::warn!(logger, "Stopping ledger sync due to low disk space");
+                    } else {
+                        log::info!(logger, "Pausing ledger sync while the ledger is held");
+                    }
+                    thread.stop();
+                }
+            } else if ledger_sync_thread.is_none() {
+                log::info!(logger, "Starting ledger sync");
+                *ledger_sync_thread = Some(factory());
+            }
+            state.set_ledger_sync_running(ledger_sync_thread.is_some());
+        }
+    }
+}
+
+impl Drop for DiskSpaceMonitor {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mc_common::logger::{test_with_logger, Logger};
+    use tempdir::TempDir;
+
+    #[test_with_logger]
+    fn test_disk_space_monitor(logger: Logger) {
+        let dir = TempDir::new("disk_space_monitor").unwrap();
+        let volumes = vec![("ledger_db".to_string(), dir.path().to_path_buf(), None)];
+
+        let monitor = DiskSpaceMonitor::new(
+            volumes.clone(),
+            DiskSpaceThresholds {
+                warning_bytes: 0,
+                critical_bytes: 0,
+            },
+            Duration::from_millis(10),
+            None,
+            logger.clone(),
+        );
+        let state = monitor.state();
+        assert!(state.volume("ledger_db").unwrap().space.is_some());
+        assert_eq!(state.level(), DiskSpaceLevel::Ok);
+        assert!(!state.is_critical());
+
+        // No volume can have more free space than this.
+        let monitor = DiskSpaceMonitor::new(
+            volumes,
+            DiskSpaceThresholds {
+                warning_bytes: u64::MAX,
+                critical_bytes: u64::MAX,
+            },
+            Duration::from_millis(10),
+            None,
+            logger,
+        );
+        assert!(monitor.state().is_critical());
+    }
+
+    #[test_with_logger]
+    fn test_disk_space_monitor_max_size(logger: Logger) {
+        let dir = TempDir::new("disk_space_monitor").unwrap();
+        std::fs::write(dir.path().join("data.mdb"), vec![0; 1024]).unwrap();
+        let thresholds = DiskSpaceThresholds {
+            warning_bytes: 200,
+            critical_bytes: 100,
+        };
+
+        // A database nearing its maximum size is critical, however much space
+        // the volume has free.
+        let monitor = DiskSpaceMonitor::new(
+            vec![(
+                "ledger_db".to_string(),
+                dir.path().to_path_buf(),
+                Some(1100),
+            )],
+            thresholds,
+            Duration::from_millis(10),
+            None,
+            logger.clone(),
+        );
+        let state = monitor.state();
+        let volume = state.volume("ledger_db").unwrap();
+        assert_eq!(volume.size_bytes, Some(1024));
+        assert_eq!(volume.headroom().unwrap().available_bytes, 76);
+        assert!(volume.is_limited_by_max_size());
+        assert!(state.is_critical());
+
+        // Raising the limit recovers at the next check.
+        assert!(state.set_max_size("ledger_db", Some(1 << 40)));
+        assert!(!state.set_max_size("wallet_db", None));
+        let start = Instant::now();
+        while state.is_critical() {
+            assert!(start.elapsed() < Duration::from_secs(10));
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test_with_logger]
+    fn test_hold_ledger_sync(logger: Logger) {
+        let dir = TempDir::new("disk_space_monitor").unwrap();
+        let monitor = DiskSpaceMonitor::new(
+            vec![("ledger_db".to_string(), dir.path().to_path_buf(), None)],
+            DiskSpaceThresholds {
+                warning_bytes: 0,
+                critical_bytes: 0,
+            },
+            Duration::from_millis(10),
+            None,
+            logger,
+        );
+        let state = monitor.state();
+        assert!(!state.should_pause_ledger_sync());
+
+        let hold = state.hold_ledger_sync();
+        let second_hold = state.hold_ledger_sync();
+        assert!(state.should_pause_ledger_sync());
+        drop(hold);
+        assert!(state.should_pause_ledger_sync());
+        drop(second_hold);
+        assert!(!state.should_pause_ledger_sync());
+
+        // Without a ledger sync factory there is no sync to wait for.
+        assert!(!state.is_ledger_sync_running());
+        assert!(state.wait_for_ledger_sync_to_stop(Duration::from_secs(1)));
+    }
+}
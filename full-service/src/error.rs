@@ -274,6 +274,9 @@ pub enum WalletTransactionBuilderError {
     /// Attempting to build a transaction from a TXO without a subaddress: {0}
     NullSubaddress(String),
 
+    /// Txo is frozen, and must be unfrozen before it is spent: {0}
+    TxoFrozen(String),
+
     /// Error executing diesel transaction: {0}
     Diesel(diesel::result::Error),
 
@@ -292,6 +295,10 @@ pub enum WalletTransactionBuilderError {
     /// Fee must be at least MINIMUM_FEE: {0}
     InsufficientFee(String),
 
+    /// Block version {2} can't pay the fee in token {0} for outlays in token
+    /// {1}
+    MixedTokenFee(u64, u64, u32),
+
     /// Error parsing URI {0}
     UriParse(mc_util_uri::UriParseError),
 
@@ -306,6 +313,15 @@ pub enum WalletTransactionBuilderError {
 
     /// Error passed up from KeyError
     KeyError(mc_crypto_keys::KeyError),
+
+    /// Unsupported offline signing format version {0}, expected version {1}
+    UnsupportedOfflineSigningVersion(u32, u32),
+
+    /// Invalid offline signing request: {0}
+    InvalidSigningRequest(String),
+
+    /// Invalid signed transaction: {0}
+    InvalidSignedTransaction(String),
 }
 
 impl From<mc_transaction_core::AmountError> for WalletTransactionBuilderError {
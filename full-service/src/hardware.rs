@@ -0,0 +1,83 @@
+// Copyright (c) 2020-2022 MobileCoin Inc.
+
+//! Signing for accounts whose spend key is on a hardware wallet.
+//!
+//! A hardware backed account is imported as a view only account, so the
+//! wallet never holds its spend key. Its transactions are built unsigned, and
+//! sent to an external signer, such as a bridge to a Ledger device, which asks
+//! the device to sign them and returns the signed transaction. The request is
+//! the same as the transaction-signer's sign-transaction input, and the
+//! response the params of the submit_transaction request it writes, so that a
+//! signer can reuse either.
+
+use crate::{
+    fog_resolver::FullServiceFogResolver, json_rpc, service::transaction::TransactionSigner,
+    unsigned_tx::UnsignedTx,
+};
+use mc_mobilecoind::payments::TxProposal;
+use reqwest::{
+    blocking::Client,
+    header::{HeaderValue, CONTENT_TYPE},
+};
+use serde_json::json;
+use std::{convert::TryFrom, time::Duration};
+
+/// How long to wait for the signer, which may be waiting for the user to
+/// confirm the transaction on the device.
+const EXTERNAL_SIGNER_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// A signer reached over HTTP.
+pub struct ExternalSigner {
+    url: String,
+    client: Client,
+}
+
+impl ExternalSigner {
+    pub fn new(url: &str) -> Result<Self, String> {
+        let client = Client::builder()
+            .use_rustls_tls()
+            .timeout(EXTERNAL_SIGNER_TIMEOUT)
+            .build()
+            .map_err(|err| err.to_string())?;
+        Ok(Self {
+            url: url.to_string(),
+            client,
+        })
+    }
+}
+
+impl TransactionSigner for ExternalSigner {
+    fn sign_transaction(
+        &self,
+        account_id_hex: &str,
+        unsigned_tx: UnsignedTx,
+        fog_resolver: FullServiceFogResolver,
+    ) -> Result<TxProposal, String> {
+        let body = json!({
+            "account_id": account_id_hex,
+            "unsigned_tx": unsigned_tx,
+            "fog_resolver": fog_resolver,
+        });
+
+        let response = self
+            .client
+            .post(&self.url)
+            .header(CONTENT_TYPE, HeaderValue::from_static("application/json"))
+            .body(body.to_string())
+            .send()
+            .and_then(|response| response.error_for_status())
+            .and_then(|response| response.text())
+            .map_err(|err| err.to_string())?;
+        let response: serde_json::Value =
+            serde_json::from_str(&response).map_err(|err| err.to_string())?;
+
+        let tx_proposal: json_rpc::tx_proposal::TxProposal = serde_json::from_value(
+            response
+                .get("tx_proposal")
+                .cloned()
+                .ok_or_else(|| "The signer's response has no tx_proposal".to_string())?,
+        )
+        .map_err(|err| err.to_string())?;
+        TxProposal::try_from(&tx_proposal)
+    }
+}
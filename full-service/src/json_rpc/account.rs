@@ -52,6 +52,17 @@ pub struct Account {
     /// the default change subaddress (index 1). It also generates
     /// PublicAddressB58's with fog credentials.
     pub fog_enabled: bool,
+
+    /// The token this account's transactions and balances use when none is
+    /// specified.
+    pub preferred_token_id: String,
+
+    /// Whether the account has been archived, e.g. after its funds were
+    /// rotated to a new account.
+    pub archived: bool,
+
+    /// Whether the sync thread skips this account until it is resumed.
+    pub sync_paused: bool,
 }
 
 impl TryFrom<&db::models::Account> for Account {
@@ -75,6 +86,9 @@ impl TryFrom<&db::models::Account> for Account {
             next_block_index: (src.next_block_index as u64).to_string(),
             recovery_mode: false,
             fog_enabled: src.fog_enabled,
+            preferred_token_id: (src.preferred_token_id as u64).to_string(),
+            archived: src.archived,
+            sync_paused: src.sync_paused,
         })
     }
 }
@@ -0,0 +1,105 @@
+// Copyright (c) 2020-2022 MobileCoin Inc.
+
+//! API definitions for batch account imports.
+
+use crate::service::account::{self, AccountImportSecret};
+use serde_derive::{Deserialize, Serialize};
+use std::convert::TryFrom;
+
+/// A single account to import, from either a mnemonic or legacy root
+/// entropy.
+#[derive(Deserialize, Serialize, Default, Debug, Clone)]
+pub struct AccountImport {
+    /// A BIP39 mnemonic. Exactly one of mnemonic and entropy must be set.
+    pub mnemonic: Option<String>,
+
+    /// The key derivation version of the mnemonic.
+    pub key_derivation_version: Option<String>,
+
+    /// Hex encoded legacy root entropy.
+    pub entropy: Option<String>,
+
+    pub name: Option<String>,
+
+    pub first_block_index: Option<String>,
+
+    pub next_subaddress_index: Option<String>,
+
+    pub fog_report_url: Option<String>,
+
+    pub fog_report_id: Option<String>,
+
+    pub fog_authority_spki: Option<String>,
+}
+
+impl TryFrom<&AccountImport> for account::AccountImport {
+    type Error = String;
+
+    fn try_from(src: &AccountImport) -> Result<account::AccountImport, String> {
+        let secret = match (&src.mnemonic, &src.entropy) {
+            (Some(mnemonic), None) => AccountImportSecret::Mnemonic {
+                mnemonic_phrase: mnemonic.clone(),
+                key_derivation_version: src
+                    .key_derivation_version
+                    .as_ref()
+                    .ok_or("key_derivation_version is required with a mnemonic")?
+                    .parse::<u8>()
+                    .map_err(|err| format!("Could not parse key_derivation_version: {}", err))?,
+            },
+            (None, Some(entropy)) => AccountImportSecret::LegacyRootEntropy(entropy.clone()),
+            _ => return Err("Exactly one of mnemonic and entropy must be provided".to_string()),
+        };
+
+        Ok(account::AccountImport {
+            secret,
+            name: src.name.clone(),
+            first_block_index: src
+                .first_block_index
+                .as_ref()
+                .map(|i| i.parse::<u64>())
+                .transpose()
+                .map_err(|err| format!("Could not parse first_block_index: {}", err))?,
+            next_subaddress_index: src
+                .next_subaddress_index
+                .as_ref()
+                .map(|i| i.parse::<u64>())
+                .transpose()
+                .map_err(|err| format!("Could not parse next_subaddress_index: {}", err))?,
+            fog_report_url: src.fog_report_url.clone().unwrap_or_default(),
+            fog_report_id: src.fog_report_id.clone().unwrap_or_default(),
+            fog_authority_spki: src.fog_authority_spki.clone().unwrap_or_default(),
+        })
+    }
+}
+
+/// The blocks the wallet will scan to catch up a batch of imported accounts.
+#[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+pub struct AccountImportSyncPlan {
+    /// String representing the object's type. Objects of the same type share
+    /// the same value.
+    pub object: String,
+
+    /// The earliest first block of any account in the batch.
+    pub start_block_index: String,
+
+    /// The last block in the ledger at the time of import.
+    pub import_block_index: String,
+
+    /// The number of blocks to scan before the batch is caught up.
+    pub num_blocks: String,
+
+    /// The number of accounts which need to scan any blocks to catch up.
+    pub num_accounts: String,
+}
+
+impl From<&account::AccountImportSyncPlan> for AccountImportSyncPlan {
+    fn from(src: &account::AccountImportSyncPlan) -> AccountImportSyncPlan {
+        AccountImportSyncPlan {
+            object: "account_import_sync_plan".to_string(),
+            start_block_index: src.start_block_index.to_string(),
+            import_block_index: src.import_block_index.to_string(),
+            num_blocks: src.num_blocks.to_string(),
+            num_accounts: src.num_accounts.to_string(),
+        }
+    }
+}
@@ -0,0 +1,47 @@
+// Copyright (c) 2020-2022 MobileCoin Inc.
+
+//! API definition for the Recovery Candidate object.
+
+use crate::service;
+use serde_derive::{Deserialize, Serialize};
+
+/// An account a mnemonic could stand for, and what probing the ledger found
+/// of it.
+#[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+pub struct RecoveryCandidate {
+    /// String representing the object's type. Objects of the same type share
+    /// the same value.
+    pub object: String,
+
+    /// The key derivation version to import the account with.
+    pub key_derivation_version: String,
+
+    /// The id the account would have in the wallet.
+    pub account_id: String,
+
+    /// The account's main address.
+    pub main_address: String,
+
+    /// Whether the account is already in the wallet.
+    pub already_imported: bool,
+
+    /// The first probed block in which the account received a txo, if any.
+    pub first_activity_block_index: Option<String>,
+
+    /// The block the account would start scanning from if imported.
+    pub first_block_index: String,
+}
+
+impl From<&service::account_recovery::RecoveryCandidate> for RecoveryCandidate {
+    fn from(src: &service::account_recovery::RecoveryCandidate) -> RecoveryCandidate {
+        RecoveryCandidate {
+            object: "recovery_candidate".to_string(),
+            key_derivation_version: src.key_derivation_version.to_string(),
+            account_id: src.account_id.clone(),
+            main_address: src.main_address.clone(),
+            already_imported: src.already_imported,
+            first_activity_block_index: src.first_activity_block_index.map(|i| i.to_string()),
+            first_block_index: src.first_block_index.to_string(),
+        }
+    }
+}
@@ -0,0 +1,52 @@
+// Copyright (c) 2020-2022 MobileCoin Inc.
+
+//! API definition for the Account Rotation object.
+
+use crate::db::{
+    self,
+    account_rotation::AccountRotationModel,
+    models::{ROTATION_STATUS_COMPLETED, ROTATION_STATUS_FAILED},
+};
+use serde_derive::{Deserialize, Serialize};
+
+/// The progress of moving an account's funds to a new account.
+#[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+pub struct AccountRotation {
+    /// String representing the object's type. Objects of the same type share
+    /// the same value.
+    pub object: String,
+
+    /// The account being rotated, which is archived once the rotation
+    /// completes.
+    pub account_id: String,
+
+    /// The account receiving the funds.
+    pub new_account_id: String,
+
+    /// One of "sweeping", "completed" or "failed".
+    pub status: String,
+
+    /// The ids of the sweep transactions submitted so far, oldest first.
+    pub sweep_transaction_log_ids: Vec<String>,
+
+    /// Why the last sweep failed, if the rotation is failed.
+    pub failure_message: Option<String>,
+}
+
+impl From<&db::models::AccountRotation> for AccountRotation {
+    fn from(src: &db::models::AccountRotation) -> AccountRotation {
+        let status = match src.status.as_str() {
+            ROTATION_STATUS_COMPLETED => "completed",
+            ROTATION_STATUS_FAILED => "failed",
+            _ => "sweeping",
+        };
+        AccountRotation {
+            object: "account_rotation".to_string(),
+            account_id: src.old_account_id_hex.clone(),
+            new_account_id: src.new_account_id_hex.clone(),
+            status: status.to_string(),
+            sweep_transaction_log_ids: src.sweep_transaction_log_ids(),
+            failure_message: src.failure_message.clone(),
+        }
+    }
+}
@@ -0,0 +1,47 @@
+// Copyright (c) 2020-2022 MobileCoin Inc.
+
+//! API definition for the Account Snapshot object.
+
+use crate::db;
+use serde_derive::{Deserialize, Serialize};
+
+/// An account's balance of a token, and its txo counts, as of a block.
+#[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+pub struct AccountSnapshot {
+    /// String representing the object's type. Objects of the same type share
+    /// the same value.
+    pub object: String,
+
+    /// The account that was snapshotted.
+    pub account_id: String,
+
+    /// The block the snapshot was taken as of.
+    pub block_index: String,
+
+    /// The token the snapshot is of.
+    pub token_id: String,
+
+    /// The value of the account's unspent txos of the token, as of the block.
+    pub unspent_value: String,
+
+    /// The number of the account's unspent txos of the token, as of the block.
+    pub unspent_txo_count: String,
+
+    /// The number of the account's txos of the token which had been spent, as
+    /// of the block.
+    pub spent_txo_count: String,
+}
+
+impl From<&db::models::AccountSnapshot> for AccountSnapshot {
+    fn from(src: &db::models::AccountSnapshot) -> AccountSnapshot {
+        AccountSnapshot {
+            object: "account_snapshot".to_string(),
+            account_id: src.account_id_hex.clone(),
+            block_index: (src.block_index as u64).to_string(),
+            token_id: (src.token_id as u64).to_string(),
+            unspent_value: src.unspent_value.clone(),
+            unspent_txo_count: src.unspent_txo_count.to_string(),
+            spent_txo_count: src.spent_txo_count.to_string(),
+        }
+    }
+}
@@ -0,0 +1,64 @@
+// Copyright (c) 2020-2022 MobileCoin Inc.
+
+//! API definition for the Account Sync Record object.
+
+use crate::db::{self, account_sync_record::AccountSyncRecordModel};
+use chrono::{offset::TimeZone, Utc};
+use serde_derive::{Deserialize, Serialize};
+
+/// A chunk of blocks the sync thread scanned for an account.
+#[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+pub struct AccountSyncRecord {
+    /// String representing the object's type. Objects of the same type share
+    /// the same value.
+    pub object: String,
+
+    /// Unique identifier for the record. Records are numbered in the order
+    /// they were made.
+    pub sync_record_id: String,
+
+    /// The account that was synced.
+    pub account_id: String,
+
+    /// The first block scanned.
+    pub start_block_index: String,
+
+    /// The last block scanned.
+    pub end_block_index: String,
+
+    /// The number of blocks scanned.
+    pub num_blocks: String,
+
+    /// The number of txos the account received in the blocks.
+    pub txos_received: String,
+
+    /// The number of the account's txos spent in the blocks.
+    pub txos_spent: String,
+
+    /// How long scanning the blocks took, in milliseconds.
+    pub duration_ms: String,
+
+    /// The number of blocks scanned per second.
+    pub blocks_per_second: String,
+
+    /// The time at which the blocks finished scanning.
+    pub created_time: String,
+}
+
+impl From<&db::models::AccountSyncRecord> for AccountSyncRecord {
+    fn from(src: &db::models::AccountSyncRecord) -> AccountSyncRecord {
+        AccountSyncRecord {
+            object: "account_sync_record".to_string(),
+            sync_record_id: src.id.to_string(),
+            account_id: src.account_id_hex.clone(),
+            start_block_index: (src.start_block_index as u64).to_string(),
+            end_block_index: (src.end_block_index as u64).to_string(),
+            num_blocks: src.num_blocks().to_string(),
+            txos_received: src.txos_received.to_string(),
+            txos_spent: src.txos_spent.to_string(),
+            duration_ms: src.duration_ms.to_string(),
+            blocks_per_second: format!("{:.1}", src.blocks_per_second()),
+            created_time: Utc.timestamp(src.created_time, 0).to_string(),
+        }
+    }
+}
@@ -2,7 +2,10 @@
 
 //! API definition for the Address object.
 
-use crate::db::models::{AssignedSubaddress, ViewOnlySubaddress};
+use crate::{
+    db::models::{AssignedSubaddress, ViewOnlySubaddress},
+    service::address::DerivedAddress,
+};
 use serde_derive::{Deserialize, Serialize};
 
 /// An address for an account in the wallet.
@@ -56,3 +59,18 @@ impl From<&ViewOnlySubaddress> for Address {
         }
     }
 }
+
+impl From<&DerivedAddress> for Address {
+    fn from(src: &DerivedAddress) -> Address {
+        match &src.assigned_subaddress {
+            Some(assigned_subaddress) => Address::from(assigned_subaddress),
+            None => Address {
+                object: "address".to_string(),
+                public_address: src.public_address_b58.clone(),
+                account_id: src.account_id.to_string(),
+                metadata: "".to_string(),
+                subaddress_index: src.subaddress_index.to_string(),
+            },
+        }
+    }
+}
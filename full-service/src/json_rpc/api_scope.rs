@@ -0,0 +1,407 @@
+// Copyright (c) 2020-2022 MobileCoin Inc.
+
+//! Scopes limiting which API methods a key may call.
+//!
+//! The wallet's main API key grants access to every method. Scoped keys grant
+//! access to a subset, so that companions such as the offline transaction
+//! signer can be given only the rights they need.
+//!
+//! A scope can also be chosen as the wallet's preset at startup, which limits
+//! every key, the main one included, to the scope's methods. The receive-only
+//! preset leaves exchange deposit boxes only the methods deposit processing
+//! needs, so that nothing else has to be reviewed.
+//!
+//! Operators who need a tighter or different surface than any preset can
+//! configure an explicit allowlist of methods instead. Every method missing
+//! from it is disabled, whichever API key is used.
+//!
+//! While the wallet is in maintenance, only methods which read from it are
+//! served, along with those which manage the maintenance itself.
+
+use displaydoc::Display;
+use std::{collections::BTreeSet, fmt};
+
+/// Methods for managing view-only accounts.
+const VIEW_ONLY_METHODS: &[&str] = &[
+    "create_new_subaddresses_request",
+    "create_view_only_account_sync_proof",
+    "create_view_only_account_sync_request",
+    "export_view_only_account_secrets",
+    "get_address_for_view_only_account",
+    "get_addresses_for_view_only_account",
+    "get_all_view_only_accounts",
+    "get_balance_for_view_only_account",
+    "get_balance_for_view_only_address",
+    "get_txos_for_view_only_account",
+    "get_view_only_account",
+    "get_view_only_account_sync_records",
+    "import_subaddresses_to_view_only_account",
+    "import_view_only_account",
+    "remove_view_only_account",
+    "sync_view_only_account",
+    "update_view_only_account_name",
+];
+
+/// Methods the offline signer calls to return its results to the wallet.
+const SIGNER_METHODS: &[&str] = &[
+    "import_subaddresses_to_view_only_account",
+    "sync_view_only_account",
+];
+
+/// Methods for processing deposits: assigning addresses, and following the
+/// balances, txos and events of the accounts they are paid into.
+const RECEIVE_ONLY_METHODS: &[&str] = &[
+    "assign_address_for_account",
+    "assign_address_for_account_at_index",
+    "assign_addresses_for_account",
+    "check_receiver_receipt_status",
+    "export_txos",
+    "get_account",
+    "get_account_snapshots",
+    "get_account_status",
+    "get_address_for_account",
+    "get_addresses_for_account",
+    "get_all_accounts",
+    "get_all_txos_for_address",
+    "get_balance_for_account",
+    "get_balance_for_address",
+    "get_deposits",
+    "get_network_status",
+    "get_payment_request",
+    "get_sync_history",
+    "get_sync_status",
+    "get_transaction_log",
+    "get_transaction_logs_for_account",
+    "get_txo",
+    "get_txos_for_account",
+    "get_wallet_events",
+    "get_wallet_status",
+    "list_payment_requests",
+    "validate_confirmation",
+    "verify_address",
+    "version",
+];
+
+/// Methods which only read from the wallet. Methods which look like reads
+/// but may write, such as get_continuity_status recording the first check-in,
+/// are left out.
+const READ_METHODS: &[&str] = &[
+    "check_b58_type",
+    "check_gift_code_status",
+    "check_receiver_receipt_status",
+    "compare_log_digest",
+    "convert_amount",
+    "export_address_labels",
+    "export_spent_txo_ids",
+    "get_account",
+    "get_account_snapshots",
+    "get_account_status",
+    "get_account_sweep_policy",
+    "get_address_for_account",
+    "get_address_for_view_only_account",
+    "get_addresses_for_account",
+    "get_addresses_for_view_only_account",
+    "get_all_accounts",
+    "get_all_gift_codes",
+    "get_all_transaction_logs_for_block_range",
+    "get_all_transaction_logs_ordered_by_block",
+    "get_all_txos_for_address",
+    "get_all_view_only_accounts",
+    "get_balance_for_account",
+    "get_balance_for_address",
+    "get_balance_for_view_only_account",
+    "get_balance_for_view_only_address",
+    "get_block",
+    "get_confirmations",
+    "get_deposits",
+    "get_gift_code",
+    "get_isolated_counterparties",
+    "get_ledger_db_size",
+    "get_log_digest",
+    "get_maintenance_status",
+    "get_mc_protocol_transaction",
+    "get_mc_protocol_txo",
+    "get_network_status",
+    "get_payment_request",
+    "get_pending_outflows",
+    "get_quarantined_blocks",
+    "get_settings",
+    "get_sync_history",
+    "get_sync_status",
+    "get_tokens",
+    "get_transaction_log",
+    "get_transaction_logs_for_account",
+    "get_transaction_watch",
+    "get_transaction_watches",
+    "get_txo",
+    "get_txos_for_account",
+    "get_txos_for_view_only_account",
+    "get_usage_stats",
+    "get_view_only_account",
+    "get_view_only_account_sync_records",
+    "get_wallet_events",
+    "get_wallet_status",
+    "get_webhooks",
+    "list_payment_requests",
+    "list_scheduled_payments",
+    "replay_events",
+    "run_diagnostics",
+    "validate_confirmation",
+    "verify_address",
+    "version",
+];
+
+/// Methods for starting and ending maintenance.
+const MAINTENANCE_METHODS: &[&str] = &["end_maintenance", "start_maintenance"];
+
+/// Whether the wallet serves the given method while it is in maintenance.
+pub fn allowed_during_maintenance(method: &str) -> bool {
+    READ_METHODS.contains(&method) || MAINTENANCE_METHODS.contains(&method)
+}
+
+/// The set of methods an API key may call.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ApiScope {
+    /// Every method.
+    Full,
+
+    /// Methods for managing view-only accounts.
+    ViewOnly,
+
+    /// Only the methods the offline signer needs.
+    Signer,
+
+    /// Only the methods needed to process deposits.
+    ReceiveOnly,
+}
+
+/// Errors for API scopes.
+#[derive(Display, Debug)]
+pub enum ApiScopeError {
+    /// The API key's {0} scope does not permit calling {1}
+    MethodNotPermitted(ApiScope, String),
+
+    /// The wallet's {0} preset does not permit calling {1}
+    MethodDisabledByPreset(ApiScope, String),
+
+    /// {0} is not in the wallet's allowlist of methods
+    MethodNotAllowlisted(String),
+}
+
+/// The only methods the wallet serves, as configured by the operator.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MethodAllowlist(pub BTreeSet<String>);
+
+impl MethodAllowlist {
+    /// Parse an allowlist with one method per line. Blank lines and comments
+    /// starting with # are ignored.
+    pub fn parse(src: &str) -> Result<Self, String> {
+        let methods: BTreeSet<String> = src
+            .lines()
+            .map(|line| line.split('#').next().unwrap_or_default().trim())
+            .filter(|method| !method.is_empty())
+            .map(|method| method.to_string())
+            .collect();
+        // An empty list would disable the whole API, which is more likely to
+        // be a mistake than what was meant.
+        if methods.is_empty() {
+            return Err("The method allowlist does not list any methods".to_string());
+        }
+        Ok(Self(methods))
+    }
+
+    /// Whether the wallet serves the given method.
+    pub fn allows_method(&self, method: &str) -> bool {
+        self.0.contains(method)
+    }
+}
+
+impl ApiScope {
+    /// Whether a key with this scope may call the given method.
+    pub fn allows_method(&self, method: &str) -> bool {
+        match self {
+            ApiScope::Full => true,
+            ApiScope::ViewOnly => VIEW_ONLY_METHODS.contains(&method),
+            ApiScope::Signer => SIGNER_METHODS.contains(&method),
+            ApiScope::ReceiveOnly => RECEIVE_ONLY_METHODS.contains(&method),
+        }
+    }
+
+    /// Check that a key with this scope may call the given method.
+    pub fn check_method(&self, method: &str) -> Result<(), ApiScopeError> {
+        if self.allows_method(method) {
+            Ok(())
+        } else {
+            Err(ApiScopeError::MethodNotPermitted(*self, method.to_string()))
+        }
+    }
+}
+
+/// Check that a key with the given scope may call the method, on a wallet
+/// started with the given preset.
+pub fn check_method_with_preset(
+    preset: ApiScope,
+    scope: ApiScope,
+    method: &str,
+) -> Result<(), ApiScopeError> {
+    if !preset.allows_method(method) {
+        return Err(ApiScopeError::MethodDisabledByPreset(
+            preset,
+            method.to_string(),
+        ));
+    }
+    scope.check_method(method)
+}
+
+/// Check that a key with the given scope may call the method, on a wallet
+/// started with the given preset and, if one was configured, allowlist.
+pub fn check_method_with_allowlist(
+    allowlist: Option<&MethodAllowlist>,
+    preset: ApiScope,
+    scope: ApiScope,
+    method: &str,
+) -> Result<(), ApiScopeError> {
+    if let Some(allowlist) = allowlist {
+        if !allowlist.allows_method(method) {
+            return Err(ApiScopeError::MethodNotAllowlisted(method.to_string()));
+        }
+    }
+    check_method_with_preset(preset, scope, method)
+}
+
+/// Parse the name of a preset given on the command line.
+pub fn parse_api_preset(src: &str) -> Result<ApiScope, String> {
+    match src {
+        "receive-only" => Ok(ApiScope::ReceiveOnly),
+        _ => Err(format!("Unknown preset {}, expected receive-only", src)),
+    }
+}
+
+impl fmt::Display for ApiScope {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ApiScope::Full => write!(f, "full"),
+            ApiScope::ViewOnly => write!(f, "view_only"),
+            ApiScope::Signer => write!(f, "signer"),
+            ApiScope::ReceiveOnly => write!(f, "receive_only"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_allows_method() {
+        assert!(ApiScope::Full.allows_method("build_and_submit_transaction"));
+
+        assert!(ApiScope::ViewOnly.allows_method("import_view_only_account"));
+        assert!(ApiScope::ViewOnly.allows_method("sync_view_only_account"));
+        assert!(!ApiScope::ViewOnly.allows_method("export_account_secrets"));
+
+        assert!(ApiScope::Signer.allows_method("sync_view_only_account"));
+        assert!(ApiScope::Signer.allows_method("import_subaddresses_to_view_only_account"));
+        assert!(!ApiScope::Signer.allows_method("remove_view_only_account"));
+        assert!(!ApiScope::Signer.allows_method("build_and_submit_transaction"));
+
+        assert!(ApiScope::ReceiveOnly.allows_method("assign_address_for_account"));
+        assert!(ApiScope::ReceiveOnly.allows_method("get_wallet_events"));
+        assert!(!ApiScope::ReceiveOnly.allows_method("build_and_submit_transaction"));
+        assert!(!ApiScope::ReceiveOnly.allows_method("export_account_secrets"));
+
+        assert_eq!(
+            ApiScope::Signer
+                .check_method("create_account")
+                .unwrap_err()
+                .to_string(),
+            "The API key's signer scope does not permit calling create_account"
+        );
+    }
+
+    #[test]
+    fn test_check_method_with_preset() {
+        assert!(check_method_with_preset(ApiScope::Full, ApiScope::Full, "create_account").is_ok());
+
+        // The preset limits even the main key.
+        assert_eq!(
+            check_method_with_preset(ApiScope::ReceiveOnly, ApiScope::Full, "create_account")
+                .unwrap_err()
+                .to_string(),
+            "The wallet's receive_only preset does not permit calling create_account"
+        );
+        assert!(check_method_with_preset(
+            ApiScope::ReceiveOnly,
+            ApiScope::Full,
+            "get_balance_for_account"
+        )
+        .is_ok());
+
+        // And a scoped key is limited to methods in both.
+        assert!(check_method_with_preset(
+            ApiScope::ReceiveOnly,
+            ApiScope::Signer,
+            "get_balance_for_account"
+        )
+        .is_err());
+
+        assert_eq!(parse_api_preset("receive-only"), Ok(ApiScope::ReceiveOnly));
+        assert!(parse_api_preset("everything").is_err());
+    }
+
+    #[test]
+    fn test_check_method_with_allowlist() {
+        let allowlist = MethodAllowlist::parse(
+            "# Deposit processing\nget_balance_for_account\n\n  assign_address_for_account  # per customer\ncreate_account\n",
+        )
+        .unwrap();
+        assert_eq!(allowlist.0.len(), 3);
+        assert!(MethodAllowlist::parse("# nothing\n\n").is_err());
+
+        assert!(check_method_with_allowlist(
+            Some(&allowlist),
+            ApiScope::Full,
+            ApiScope::Full,
+            "assign_address_for_account"
+        )
+        .is_ok());
+        assert_eq!(
+            check_method_with_allowlist(
+                Some(&allowlist),
+                ApiScope::Full,
+                ApiScope::Full,
+                "build_and_submit_transaction"
+            )
+            .unwrap_err()
+            .to_string(),
+            "build_and_submit_transaction is not in the wallet's allowlist of methods"
+        );
+        assert!(check_method_with_allowlist(
+            None,
+            ApiScope::Full,
+            ApiScope::Full,
+            "build_and_submit_transaction"
+        )
+        .is_ok());
+
+        // The preset and the key's scope still apply to allowlisted methods.
+        assert!(check_method_with_allowlist(
+            Some(&allowlist),
+            ApiScope::ReceiveOnly,
+            ApiScope::Full,
+            "create_account"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_allowed_during_maintenance() {
+        assert!(allowed_during_maintenance("get_balance_for_account"));
+        assert!(allowed_during_maintenance("get_maintenance_status"));
+        assert!(allowed_during_maintenance("end_maintenance"));
+        assert!(!allowed_during_maintenance("build_and_submit_transaction"));
+        assert!(!allowed_during_maintenance("create_account"));
+        // Reads which may write are not served.
+        assert!(!allowed_during_maintenance("get_continuity_status"));
+    }
+}
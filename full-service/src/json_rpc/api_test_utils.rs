@@ -2,16 +2,21 @@
 
 use crate::{
     json_rpc::{
+        api_scope::{ApiScope, MethodAllowlist},
         json_rpc_request::{JsonCommandRequest, JsonRPCRequest},
-        json_rpc_response::JsonRPCResponse,
+        json_rpc_response::{format_error, JsonRPCResponse},
+        txo_export::{export_txos, ExportTxosRequest},
         wallet::wallet_api_inner,
     },
-    service::WalletService,
+    service::{maintenance::MaintenanceMode, WalletService, WalletServiceOptions},
     test_utils::{
         get_resolver_factory, get_test_ledger, setup_peer_manager_and_network_state,
         WalletDbTestContext,
     },
-    wallet::{APIKeyState, ApiKeyGuard},
+    wallet::{
+        render_json, unauthorized, APIKeyState, ApiKeyGuard, ApiPresetState, JsonOutputMode,
+        MethodAllowlistState, ScopedAPIKeysState,
+    },
 };
 use mc_account_keys::PublicAddress;
 use mc_common::logger::{log, Logger};
@@ -21,9 +26,12 @@ use mc_ledger_db::{Ledger, LedgerDB};
 use mc_ledger_sync::PollingNetworkState;
 use rand::rngs::StdRng;
 use rocket::{
+    catchers,
     http::{ContentType, Header, Status},
     local::Client,
-    post, routes,
+    post,
+    response::{status::Custom, Content, Response},
+    routes,
 };
 use rocket_contrib::json::{Json, JsonValue};
 use std::{
@@ -48,10 +56,11 @@ pub struct TestWalletState {
 // TestWalletState, which handles Mock objects.
 #[post("/wallet", format = "json", data = "<command>")]
 fn test_wallet_api(
-    _guard: ApiKeyGuard,
+    guard: ApiKeyGuard,
+    output_mode: JsonOutputMode,
     state: rocket::State<TestWalletState>,
     command: Json<JsonRPCRequest>,
-) -> Result<Json<JsonRPCResponse>, String> {
+) -> Result<Content<String>, String> {
     let req: JsonRPCRequest = command.0.clone();
 
     let mut response = JsonRPCResponse {
@@ -62,6 +71,11 @@ fn test_wallet_api(
         id: command.0.id,
     };
 
+    if let Err(error) = guard.check_method(&req.method) {
+        response.error = Some(format_error(error));
+        return render_json(&response, &output_mode);
+    }
+
     match wallet_api_inner(
         &state.service,
         JsonCommandRequest::try_from(&req).map_err(|e| e)?,
@@ -74,12 +88,26 @@ fn test_wallet_api(
         }
     };
 
-    Ok(Json(response))
+    render_json(&response, &output_mode)
+}
+
+#[post("/wallet/export_txos", format = "json", data = "<request>")]
+fn test_export_txos(
+    guard: ApiKeyGuard,
+    output_mode: JsonOutputMode,
+    state: rocket::State<TestWalletState>,
+    request: Json<ExportTxosRequest>,
+) -> Result<Response<'static>, Custom<String>> {
+    guard
+        .check_method("export_txos")
+        .map_err(|err| Custom(Status::Forbidden, err.to_string()))?;
+    export_txos(&state.service, request.into_inner(), output_mode.canonical)
 }
 
 pub fn test_rocket(rocket_config: rocket::Config, state: TestWalletState) -> rocket::Rocket {
     rocket::custom(rocket_config)
-        .mount("/", routes![test_wallet_api])
+        .mount("/", routes![test_wallet_api, test_export_txos])
+        .register(catchers![unauthorized])
         .manage(state)
 }
 
@@ -88,6 +116,7 @@ pub const BASE_TEST_BLOCK_HEIGHT: usize = 12;
 pub fn create_test_setup(
     mut rng: &mut StdRng,
     logger: Logger,
+    maintenance_mode: MaintenanceMode,
 ) -> (
     rocket::Rocket,
     LedgerDB,
@@ -107,7 +136,10 @@ pub fn create_test_setup(
         peer_manager,
         network_state.clone(),
         get_resolver_factory(&mut rng).unwrap(),
-        false,
+        WalletServiceOptions {
+            maintenance_mode,
+            ..Default::default()
+        },
         logger,
     );
 
@@ -121,6 +153,26 @@ pub fn create_test_setup(
     (rocket_instance, ledger_db, db_test_context, network_state)
 }
 
+/// How the test API and its wallet are configured. The default is an open
+/// API, with no keys, preset or allowlist, and no maintenance admin key.
+#[derive(Default)]
+pub struct TestSetupOptions {
+    /// The full API key, or empty for none.
+    pub api_key: String,
+
+    /// Additional keys, each limited to its scope.
+    pub scoped_api_keys: Vec<(String, ApiScope)>,
+
+    /// The preset limiting every key, if any.
+    pub preset: Option<ApiScope>,
+
+    /// The methods the API is limited to, if any.
+    pub method_allowlist: Option<MethodAllowlist>,
+
+    /// The wallet's maintenance mode.
+    pub maintenance_mode: MaintenanceMode,
+}
+
 pub fn setup(
     rng: &mut StdRng,
     logger: Logger,
@@ -130,22 +182,13 @@ pub fn setup(
     WalletDbTestContext,
     Arc<RwLock<PollingNetworkState<MockBlockchainConnection<LedgerDB>>>>,
 ) {
-    let (rocket_instance, ledger_db, db_test_context, network_state) =
-        create_test_setup(rng, logger);
-
-    let rocket = rocket_instance.manage(APIKeyState("".to_string()));
-    (
-        Client::new(rocket).expect("valid rocket instance"),
-        ledger_db,
-        db_test_context,
-        network_state,
-    )
+    setup_with(rng, logger, TestSetupOptions::default())
 }
 
-pub fn setup_with_api_key(
+pub fn setup_with(
     rng: &mut StdRng,
     logger: Logger,
-    api_key: String,
+    options: TestSetupOptions,
 ) -> (
     Client,
     LedgerDB,
@@ -153,9 +196,15 @@ pub fn setup_with_api_key(
     Arc<RwLock<PollingNetworkState<MockBlockchainConnection<LedgerDB>>>>,
 ) {
     let (rocket_instance, ledger_db, db_test_context, network_state) =
-        create_test_setup(rng, logger);
-
-    let rocket = rocket_instance.manage(APIKeyState(api_key));
+        create_test_setup(rng, logger, options.maintenance_mode);
+
+    let mut rocket = rocket_instance
+        .manage(APIKeyState(options.api_key))
+        .manage(ScopedAPIKeysState(options.scoped_api_keys))
+        .manage(MethodAllowlistState(options.method_allowlist.map(Arc::new)));
+    if let Some(preset) = options.preset {
+        rocket = rocket.manage(ApiPresetState(preset));
+    }
 
     (
         Client::new(rocket).expect("valid rocket instance"),
@@ -14,6 +14,10 @@ pub struct Balance {
     /// the same value.
     pub object: String,
 
+    /// The token the balance is in. Values are in the token's smallest unit,
+    /// which for MOB is pico MOB.
+    pub token_id: String,
+
     /// The block count of MobileCoin's distributed ledger.
     pub network_block_height: String,
 
@@ -57,12 +61,26 @@ pub struct Balance {
     /// view-key matched, but which can not be spent until their subaddress
     /// index is recovered.
     pub orphaned_pmob: String,
+
+    /// Frozen pico MOB. This is the part of the unspent value held in frozen
+    /// Txos, which are left out of transactions until they are unfrozen.
+    pub frozen_pmob: String,
+
+    /// The fee a single transaction needs, in the balance's token. Fees are
+    /// paid in the token being sent, so the account must hold at least this
+    /// much of the token to send any of it.
+    pub required_fee_reserve: String,
+
+    /// Problems which keep the balance from being sent, such as not holding
+    /// enough of the token to pay its fee.
+    pub warnings: Vec<String>,
 }
 
 impl From<&service::balance::Balance> for Balance {
     fn from(src: &service::balance::Balance) -> Balance {
         Balance {
             object: "balance".to_string(),
+            token_id: src.token_id.to_string(),
             network_block_height: src.network_block_height.to_string(),
             local_block_height: src.local_block_height.to_string(),
             account_block_height: src.synced_blocks.to_string(),
@@ -73,6 +91,9 @@ impl From<&service::balance::Balance> for Balance {
             spent_pmob: src.spent.to_string(),
             secreted_pmob: src.secreted.to_string(),
             orphaned_pmob: src.orphaned.to_string(),
+            frozen_pmob: src.frozen.to_string(),
+            required_fee_reserve: src.required_fee_reserve.to_string(),
+            warnings: src.warnings.clone(),
         }
     }
 }
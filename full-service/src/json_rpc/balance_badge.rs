@@ -0,0 +1,263 @@
+// Copyright (c) 2020-2022 MobileCoin Inc.
+
+//! Public, unauthenticated badge showing the total an account has received.
+//!
+//! Donation pages and transparency dashboards want to show how much an address
+//! has been sent, without exposing the JSON-RPC API. The badge serves only
+//! the received total of the one account chosen at startup, and is disabled
+//! unless one is. Each client is rate limited, and the total is cached, so that
+//! the endpoint can't be used to load the wallet.
+
+use crate::{
+    db::account::AccountID,
+    json_rpc::wallet::{render_json, JsonOutputMode},
+    service::balance::BalanceService,
+    WalletService,
+};
+use mc_common::{logger::log, HashMap};
+use mc_connection::{BlockchainConnection, UserTxConnection};
+use mc_fog_report_validation::FogPubkeyResolver;
+use mc_transaction_core::{tokens::Mob, Token};
+use rocket::{
+    http::Status,
+    outcome::Outcome,
+    request::{FromRequest, State},
+    response::{status::Custom, Content},
+    Request,
+};
+use serde_derive::{Deserialize, Serialize};
+use std::{
+    net::{IpAddr, Ipv4Addr},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// The window over which each client's requests are counted.
+const RATE_LIMIT_WINDOW: Duration = Duration::from_secs(60);
+
+/// How long a received total is served before it is computed again.
+const BALANCE_BADGE_CACHE_DURATION: Duration = Duration::from_secs(60);
+
+/// How many clients to track. Once this many clients are within their window,
+/// requests from other clients are refused until some windows pass.
+const MAX_TRACKED_CLIENTS: usize = 1024;
+
+/// The body of a balance badge response.
+#[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+pub struct BalanceBadge {
+    /// String representing the object's type. Objects of the same type share
+    /// the same value.
+    pub object: String,
+
+    /// The token the total is in.
+    pub token_id: String,
+
+    /// The total the account has received, not counting change, in the
+    /// token's smallest unit.
+    pub received_total: String,
+}
+
+/// Limits how many requests each client may make per window.
+pub struct ClientRateLimiter {
+    limit: u32,
+    window: Duration,
+    clients: Mutex<HashMap<IpAddr, (Instant, u32)>>,
+}
+
+impl ClientRateLimiter {
+    pub fn new(limit: u32, window: Duration) -> Self {
+        Self {
+            limit,
+            window,
+            clients: Mutex::new(HashMap::default()),
+        }
+    }
+
+    /// Count a request from the client, returning whether it is allowed.
+    pub fn allow(&self, client: IpAddr, now: Instant) -> bool {
+        let mut clients = self.clients.lock().expect("mutex poisoned");
+        if clients.len() >= MAX_TRACKED_CLIENTS && !clients.contains_key(&client) {
+            let window = self.window;
+            clients.retain(|_, (start, _)| now.duration_since(*start) < window);
+            // Refuse rather than evict, so that a flood of new clients can't
+            // reset the limits of those already tracked.
+            if clients.len() >= MAX_TRACKED_CLIENTS {
+                return false;
+            }
+        }
+
+        let (start, count) = clients.entry(client).or_insert((now, 0));
+        if now.duration_since(*start) >= self.window {
+            *start = now;
+            *count = 0;
+        }
+        if *count >= self.limit {
+            return false;
+        }
+        *count += 1;
+        true
+    }
+}
+
+/// Configuration and state of the balance badge, managed by rocket.
+pub struct BalanceBadgeState {
+    /// The account whose received total is served, if the badge is enabled.
+    pub account_id: Option<AccountID>,
+
+    /// Whether clients are identified by the X-Real-IP header, which is only
+    /// trustworthy when set by a reverse proxy.
+    pub trust_proxy: bool,
+    rate_limiter: ClientRateLimiter,
+    cache: Mutex<HashMap<u64, (Instant, u128)>>,
+}
+
+impl BalanceBadgeState {
+    pub fn new(account_id: Option<AccountID>, requests_per_minute: u32, trust_proxy: bool) -> Self {
+        Self {
+            account_id,
+            trust_proxy,
+            rate_limiter: ClientRateLimiter::new(requests_per_minute, RATE_LIMIT_WINDOW),
+            cache: Mutex::new(HashMap::default()),
+        }
+    }
+}
+
+/// The address of the client making a request. It is only taken from the
+/// X-Real-IP header, which any client can set, if the badge is configured to
+/// trust a proxy.
+pub struct ClientIp(pub IpAddr);
+
+impl<'a, 'r> FromRequest<'a, 'r> for ClientIp {
+    type Error = ();
+
+    fn from_request(req: &'a Request<'r>) -> Outcome<Self, (Status, ()), ()> {
+        let trust_proxy = req
+            .guard::<State<BalanceBadgeState>>()
+            .succeeded()
+            .map_or(false, |state| state.trust_proxy);
+        let client_ip = if trust_proxy {
+            req.client_ip()
+        } else {
+            req.remote().map(|address| address.ip())
+        };
+        // Clients whose address is unknown share a limit.
+        Outcome::Success(ClientIp(
+            client_ip.unwrap_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED)),
+        ))
+    }
+}
+
+/// Serve the balance badge, if it is enabled and the client is within its
+/// rate limit.
+pub fn balance_badge<T, FPR>(
+    service: &WalletService<T, FPR>,
+    state: &BalanceBadgeState,
+    client_ip: ClientIp,
+    token_id: Option<String>,
+    output_mode: &JsonOutputMode,
+) -> Result<Content<String>, Custom<String>>
+where
+    T: BlockchainConnection + UserTxConnection + 'static,
+    FPR: FogPubkeyResolver + Send + Sync + 'static,
+{
+    let account_id = match &state.account_id {
+        Some(account_id) => account_id,
+        None => return Err(Custom(Status::NotFound, "Not found".to_string())),
+    };
+
+    let now = Instant::now();
+    if !state.rate_limiter.allow(client_ip.0, now) {
+        return Err(Custom(
+            Status::TooManyRequests,
+            "Too many requests".to_string(),
+        ));
+    }
+
+    let token_id = match token_id {
+        Some(token_id) => token_id
+            .parse::<u64>()
+            .map_err(|err| Custom(Status::BadRequest, err.to_string()))?,
+        None => *Mob::ID,
+    };
+
+    let mut cache = state.cache.lock().expect("mutex poisoned");
+    let received_total = match cache.get(&token_id) {
+        Some((cached_at, total))
+            if now.duration_since(*cached_at) < BALANCE_BADGE_CACHE_DURATION =>
+        {
+            *total
+        }
+        _ => {
+            let total = service
+                .get_received_total(account_id, token_id)
+                .map_err(|err| {
+                    // The badge is public, so the details are only logged.
+                    log::error!(
+                        service.logger,
+                        "Error computing balance badge for {}: {}",
+                        account_id,
+                        err
+                    );
+                    Custom(
+                        Status::ServiceUnavailable,
+                        "Balance unavailable".to_string(),
+                    )
+                })?;
+            cache.insert(token_id, (now, total));
+            total
+        }
+    };
+
+    let badge = BalanceBadge {
+        object: "balance_badge".to_string(),
+        token_id: token_id.to_string(),
+        received_total: received_total.to_string(),
+    };
+    render_json(&badge, output_mode).map_err(|err| Custom(Status::InternalServerError, err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_client_rate_limiter() {
+        let limiter = ClientRateLimiter::new(2, Duration::from_secs(60));
+        let alice = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+        let bob = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2));
+        let start = Instant::now();
+
+        assert!(limiter.allow(alice, start));
+        assert!(limiter.allow(alice, start + Duration::from_secs(1)));
+        assert!(!limiter.allow(alice, start + Duration::from_secs(2)));
+
+        // Each client has its own limit.
+        assert!(limiter.allow(bob, start + Duration::from_secs(2)));
+
+        // The limit resets once the window has passed.
+        assert!(limiter.allow(alice, start + Duration::from_secs(60)));
+    }
+
+    #[test]
+    fn test_client_rate_limiter_is_bounded() {
+        let limiter = ClientRateLimiter::new(2, Duration::from_secs(60));
+        let start = Instant::now();
+        let client = |i: usize| IpAddr::V4(Ipv4Addr::from(0x0a00_0000 + i as u32));
+
+        for i in 0..MAX_TRACKED_CLIENTS {
+            assert!(limiter.allow(client(i), start));
+        }
+
+        // Once full, new clients are refused, while those already tracked keep
+        // their limits.
+        assert!(!limiter.allow(client(MAX_TRACKED_CLIENTS), start));
+        assert!(limiter.allow(client(0), start));
+        assert!(!limiter.allow(client(0), start));
+        assert_eq!(limiter.clients.lock().unwrap().len(), MAX_TRACKED_CLIENTS);
+
+        // New clients are tracked again once the windows have passed.
+        let later = start + Duration::from_secs(60);
+        assert!(limiter.allow(client(MAX_TRACKED_CLIENTS), later));
+        assert_eq!(limiter.clients.lock().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,315 @@
+// Copyright (c) 2020-2022 MobileCoin Inc.
+
+//! Capture of API requests and responses, for replaying against a test
+//! instance.
+//!
+//! When a capture file is configured, each request to the wallet API is
+//! written to it with its response and how long it took, one JSON object per
+//! line. Secrets, such as mnemonics, keys, gift codes and passwords, are
+//! replaced before anything is written, so a capture can be shared by an
+//! integrator reporting a bug. Requests which had secrets replaced can't be
+//! replayed as they were made, and are marked as redacted.
+
+use chrono::Utc;
+use mc_common::logger::global_log;
+use serde_derive::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{
+    fs::{self, File, OpenOptions},
+    io::Write,
+    path::Path,
+    sync::Mutex,
+};
+
+/// What secret values are replaced with.
+pub const REDACTED: &str = "[redacted]";
+
+/// Fields of requests and responses which hold secrets.
+const SECRET_FIELDS: &[&str] = &[
+    "account_key",
+    "admin_key",
+    "entropy",
+    "gift_code_b58",
+    "mnemonic",
+    "new_password",
+    "old_password",
+    "password",
+    "spend_private_key",
+    "view_private_key",
+];
+
+/// A request to the wallet API and its response.
+#[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+pub struct CapturedExchange {
+    /// When the request was received, in milliseconds since the epoch.
+    pub received_at_ms: i64,
+
+    /// How long the request took, in milliseconds.
+    pub duration_ms: u64,
+
+    /// The method called.
+    pub method: String,
+
+    /// Whether secrets were replaced in the request, so that it can't be
+    /// replayed as it was made.
+    pub redacted: bool,
+
+    /// The JSON-RPC request.
+    pub request: Value,
+
+    /// The JSON-RPC response.
+    pub response: Value,
+}
+
+impl CapturedExchange {
+    /// Capture a request and its response, replacing their secrets.
+    pub fn new(
+        received_at_ms: i64,
+        duration_ms: u64,
+        method: &str,
+        mut request: Value,
+        mut response: Value,
+    ) -> Self {
+        let redacted = redact_secrets(&mut request);
+        redact_secrets(&mut response);
+        Self {
+            received_at_ms,
+            duration_ms,
+            method: method.to_string(),
+            redacted,
+            request,
+            response,
+        }
+    }
+}
+
+/// A file requests are captured to, managed by rocket when capture is
+/// enabled.
+pub struct RequestCapture {
+    file: Mutex<File>,
+}
+
+impl RequestCapture {
+    /// Append captured requests to the file at the given path, creating it if
+    /// needed.
+    pub fn create(path: &Path) -> Result<Self, String> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|err| format!("Failed opening capture file {:?}: {}", path, err))?;
+        Ok(Self {
+            file: Mutex::new(file),
+        })
+    }
+
+    /// Write an exchange to the capture file. Failing to capture a request
+    /// is logged, rather than failing the request.
+    pub fn record(&self, exchange: &CapturedExchange) {
+        let result = serde_json::to_string(exchange)
+            .map_err(|err| err.to_string())
+            .and_then(|line| {
+                let mut file = self.file.lock().expect("mutex poisoned");
+                writeln!(file, "{}", line).map_err(|err| err.to_string())
+            });
+        if let Err(err) = result {
+            global_log::warn!("Failed capturing {} request: {}", exchange.method, err);
+        }
+    }
+}
+
+/// The current time, in milliseconds since the epoch.
+pub fn now_ms() -> i64 {
+    Utc::now().timestamp_millis()
+}
+
+/// Read the exchanges from a capture file, in the order they were captured.
+pub fn read_capture_file(path: &Path) -> Result<Vec<CapturedExchange>, String> {
+    let contents = fs::read_to_string(path)
+        .map_err(|err| format!("Failed reading capture file {:?}: {}", path, err))?;
+    contents
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .enumerate()
+        .map(|(i, line)| {
+            serde_json::from_str(line).map_err(|err| format!("Invalid line {}: {}", i + 1, err))
+        })
+        .collect()
+}
+
+/// Replace the secrets in a JSON value, returning whether there were any.
+pub fn redact_secrets(value: &mut Value) -> bool {
+    match value {
+        Value::Object(map) => {
+            let mut redacted = false;
+            for (key, field) in map.iter_mut() {
+                if SECRET_FIELDS.contains(&key.as_str()) {
+                    if !field.is_null() {
+                        *field = Value::String(REDACTED.to_string());
+                        redacted = true;
+                    }
+                } else {
+                    redacted |= redact_secrets(field);
+                }
+            }
+            redacted
+        }
+        Value::Array(values) => values
+            .iter_mut()
+            .fold(false, |redacted, value| redact_secrets(value) | redacted),
+        _ => false,
+    }
+}
+
+/// List the paths at which a replayed response differs from the recorded
+/// one, such as "result.account.account_id".
+pub fn diff_responses(recorded: &Value, replayed: &Value) -> Vec<String> {
+    let mut differences = Vec::new();
+    diff_values("", recorded, replayed, &mut differences);
+    differences
+}
+
+fn diff_values(path: &str, recorded: &Value, replayed: &Value, differences: &mut Vec<String>) {
+    let child_path = |key: &str| {
+        if path.is_empty() {
+            key.to_string()
+        } else {
+            format!("{}.{}", path, key)
+        }
+    };
+    match (recorded, replayed) {
+        (Value::Object(recorded), Value::Object(replayed)) => {
+            for (key, recorded_value) in recorded {
+                let replayed_value = replayed.get(key).unwrap_or(&Value::Null);
+                diff_values(
+                    &child_path(key),
+                    recorded_value,
+                    replayed_value,
+                    differences,
+                );
+            }
+            for key in replayed.keys().filter(|key| !recorded.contains_key(*key)) {
+                differences.push(child_path(key));
+            }
+        }
+        (Value::Array(recorded), Value::Array(replayed)) if recorded.len() == replayed.len() => {
+            for (i, (recorded_value, replayed_value)) in recorded.iter().zip(replayed).enumerate() {
+                diff_values(
+                    &child_path(&i.to_string()),
+                    recorded_value,
+                    replayed_value,
+                    differences,
+                );
+            }
+        }
+        // Secrets can't be compared.
+        (Value::String(recorded), _) if recorded == REDACTED => {}
+        _ => {
+            if recorded != replayed {
+                differences.push(path.to_string());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tempdir::TempDir;
+
+    #[test]
+    fn test_redact_secrets() {
+        let mut request = json!({
+            "method": "import_account",
+            "params": {
+                "mnemonic": "sheriff odor square",
+                "name": "Alice",
+                "fog_report_url": null,
+            },
+        });
+        assert!(redact_secrets(&mut request));
+        assert_eq!(request["params"]["mnemonic"], REDACTED);
+        assert_eq!(request["params"]["name"], "Alice");
+
+        let mut response = json!({
+            "result": {
+                "account_secrets": {
+                    "entropy": "c0b2",
+                    "account_key": {
+                        "view_private_key": "0a20",
+                        "spend_private_key": "0a20",
+                    },
+                },
+                "accounts": [{"account_id": "abcd", "mnemonic": "sheriff"}],
+            },
+        });
+        assert!(redact_secrets(&mut response));
+        assert_eq!(
+            response["result"]["account_secrets"]["account_key"],
+            REDACTED
+        );
+        assert_eq!(response["result"]["accounts"][0]["mnemonic"], REDACTED);
+        assert_eq!(response["result"]["accounts"][0]["account_id"], "abcd");
+
+        // Missing secrets aren't redacted.
+        let mut request = json!({"params": {"gift_code_b58": null, "account_id": "abcd"}});
+        assert!(!redact_secrets(&mut request));
+    }
+
+    #[test]
+    fn test_diff_responses() {
+        let recorded = json!({
+            "result": {
+                "account": {"account_id": "abcd", "name": "Alice", "mnemonic": REDACTED},
+                "txo_ids": ["01", "02"],
+            },
+        });
+        let replayed = json!({
+            "result": {
+                "account": {"account_id": "ef01", "name": "Alice", "mnemonic": "sheriff"},
+                "txo_ids": ["01", "03"],
+                "next_cursor": null,
+            },
+        });
+        assert_eq!(
+            diff_responses(&recorded, &replayed),
+            vec![
+                "result.account.account_id".to_string(),
+                "result.txo_ids.1".to_string(),
+                "result.next_cursor".to_string(),
+            ]
+        );
+        assert!(diff_responses(&recorded, &recorded).is_empty());
+    }
+
+    #[test]
+    fn test_capture_file() {
+        let dir = TempDir::new("capture").unwrap();
+        let path = dir.path().join("capture.jsonl");
+
+        let capture = RequestCapture::create(&path).unwrap();
+        let first = CapturedExchange::new(
+            1000,
+            12,
+            "import_account",
+            json!({"method": "import_account", "params": {"mnemonic": "sheriff"}}),
+            json!({"result": {"account": {"account_id": "abcd"}}}),
+        );
+        let second = CapturedExchange::new(
+            1500,
+            3,
+            "get_account",
+            json!({"method": "get_account", "params": {"account_id": "abcd"}}),
+            json!({"result": {"account": {"account_id": "abcd"}}}),
+        );
+        capture.record(&first);
+        capture.record(&second);
+
+        let exchanges = read_capture_file(&path).unwrap();
+        assert_eq!(exchanges, vec![first, second]);
+        assert!(exchanges[0].redacted);
+        assert!(!exchanges[1].redacted);
+        assert_eq!(exchanges[0].request["params"]["mnemonic"], REDACTED);
+    }
+}
@@ -0,0 +1,43 @@
+// Copyright (c) 2020-2022 MobileCoin Inc.
+
+//! API definition for the Continuity Status object.
+
+use crate::service;
+use serde_derive::{Deserialize, Serialize};
+
+/// When the operator last checked in, and whether a continuity export has
+/// been written since.
+#[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+pub struct ContinuityStatus {
+    /// String representing the object's type. Objects of the same type share
+    /// the same value.
+    pub object: String,
+
+    /// How long the operator may go without checking in, in seconds.
+    pub check_in_period_seconds: String,
+
+    /// When the operator last checked in, in seconds since the epoch.
+    pub last_check_in_time: String,
+
+    /// When an export will be written unless the operator checks in first.
+    pub export_due_time: String,
+
+    /// When an export was written for the last check-in, if one has been.
+    pub exported_time: Option<String>,
+
+    /// Where that export was written.
+    pub export_path: Option<String>,
+}
+
+impl From<&service::continuity::ContinuityStatus> for ContinuityStatus {
+    fn from(src: &service::continuity::ContinuityStatus) -> ContinuityStatus {
+        ContinuityStatus {
+            object: "continuity_status".to_string(),
+            check_in_period_seconds: src.check_in_period_seconds.to_string(),
+            last_check_in_time: src.last_check_in_time.to_string(),
+            export_due_time: src.export_due_time.to_string(),
+            exported_time: src.exported_time.map(|time| time.to_string()),
+            export_path: src.export_path.clone(),
+        }
+    }
+}
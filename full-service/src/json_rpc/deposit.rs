@@ -0,0 +1,48 @@
+// Copyright (c) 2020-2022 MobileCoin Inc.
+
+//! API definition for the Deposit object.
+
+use crate::service;
+use serde_derive::{Deserialize, Serialize};
+
+/// The txos of one token received from others in one block at one
+/// subaddress, which together are typically a single inbound transaction.
+#[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+pub struct Deposit {
+    /// String representing the object's type. Objects of the same type share
+    /// the same value.
+    pub object: String,
+
+    /// The account which received the deposit.
+    pub account_id: String,
+
+    /// The block the txos were received in.
+    pub block_index: String,
+
+    /// The subaddress the txos were received at, or null while they are
+    /// orphaned.
+    pub subaddress_index: Option<String>,
+
+    /// The token of the txos.
+    pub token_id: String,
+
+    /// The combined value of the txos, in the token's smallest unit.
+    pub value: String,
+
+    /// The txos making up the deposit.
+    pub txo_ids: Vec<String>,
+}
+
+impl From<&service::deposit::Deposit> for Deposit {
+    fn from(src: &service::deposit::Deposit) -> Deposit {
+        Deposit {
+            object: "deposit".to_string(),
+            account_id: src.account_id_hex.clone(),
+            block_index: src.block_index.to_string(),
+            subaddress_index: src.subaddress_index.map(|index| index.to_string()),
+            token_id: src.token_id.to_string(),
+            value: src.value.to_string(),
+            txo_ids: src.txo_ids.clone(),
+        }
+    }
+}
@@ -0,0 +1,35 @@
+// Copyright (c) 2020-2022 MobileCoin Inc.
+
+//! API definition for the Diagnostic Check object.
+
+use crate::service::diagnostics::{self, DiagnosticStatus};
+
+use serde_derive::{Deserialize, Serialize};
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct DiagnosticCheck {
+    /// String representing the object's type. Objects of the same type share
+    /// the same value.
+    pub object: String,
+
+    /// Name of the check.
+    pub name: String,
+
+    /// Outcome of the check, one of "passed", "warning", "failed" or
+    /// "skipped".
+    pub status: DiagnosticStatus,
+
+    /// Description of the outcome, including how to resolve any failure.
+    pub message: String,
+}
+
+impl From<&diagnostics::DiagnosticCheck> for DiagnosticCheck {
+    fn from(src: &diagnostics::DiagnosticCheck) -> DiagnosticCheck {
+        DiagnosticCheck {
+            object: "diagnostic_check".to_string(),
+            name: src.name.clone(),
+            status: src.status,
+            message: src.message.clone(),
+        }
+    }
+}
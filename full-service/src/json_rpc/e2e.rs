@@ -7,28 +7,40 @@ mod e2e {
     use crate::{
         db::{
             account::AccountID,
-            models::{TXO_STATUS_UNSPENT, TXO_TYPE_RECEIVED},
+            models::{QuarantinedBlock, Txo, TXO_STATUS_UNSPENT, TXO_TYPE_RECEIVED},
+            quarantined_block::QuarantinedBlockModel,
+            txo::TxoModel,
         },
         json_rpc,
-        json_rpc::api_test_utils::{
-            dispatch, dispatch_expect_error, dispatch_with_header,
-            dispatch_with_header_expect_error, setup, setup_with_api_key,
+        json_rpc::{
+            api_scope::{ApiScope, MethodAllowlist},
+            api_test_utils::{
+                dispatch, dispatch_expect_error, dispatch_with_header,
+                dispatch_with_header_expect_error, setup, setup_with, TestSetupOptions,
+            },
+            txo_export::{EXPORT_SIGNATURE_HEADER, EXPORT_SIGNING_KEY_HEADER},
         },
+        service::maintenance::MaintenanceMode,
         test_utils::{
             add_block_to_ledger_db, add_block_with_tx_proposal, manually_sync_account,
             manually_sync_view_only_account, MOB,
         },
-        util::b58::b58_decode_public_address,
+        unsigned_tx::OFFLINE_SIGNING_FORMAT_VERSION,
+        util::{
+            b58::{b58_decode_public_address, b58_encode_public_address},
+            pagination::{encode_page_cursor, TXO_CURSOR},
+        },
     };
     use bip39::{Language, Mnemonic};
     use mc_account_keys::{AccountKey, RootEntropy, RootIdentity};
     use mc_account_keys_slip10::Slip10Key;
     use mc_common::logger::{test_with_logger, Logger};
+    use mc_crypto_keys::{Ed25519Public, Ed25519Signature, Verifier};
     use mc_crypto_rand::rand_core::RngCore;
     use mc_ledger_db::Ledger;
     use mc_transaction_core::{ring_signature::KeyImage, tokens::Mob, Token};
     use rand::{rngs::StdRng, SeedableRng};
-    use rocket::http::{Header, Status};
+    use rocket::http::{ContentType, Header, Status};
     use std::convert::TryFrom;
 
     #[test_with_logger]
@@ -235,11 +247,13 @@ mod e2e {
             json!({
                 "method": "import_account",
                 "error": json!({
-                    "code": -32603,
-                    "message": "InternalError",
+                    "code": 1201,
+                    "message": "InvalidAccountSecrets",
                     "data": json!({
                         "server_error": "UnknownKeyDerivation(3)",
                         "details": "Unknown key version version: 3",
+                        "category": "wallet_db",
+                        "retryable": false,
                     })
                 }),
                 "jsonrpc": "2.0",
@@ -576,6 +590,21 @@ mod e2e {
                 .to_string(),
             (42 * MOB - Mob::MINIMUM_FEE).to_string()
         );
+        assert_eq!(
+            balance
+                .get("required_fee_reserve")
+                .unwrap()
+                .as_str()
+                .unwrap()
+                .to_string(),
+            Mob::MINIMUM_FEE.to_string()
+        );
+        assert!(balance
+            .get("warnings")
+            .unwrap()
+            .as_array()
+            .unwrap()
+            .is_empty());
     }
 
     #[test_with_logger]
@@ -591,7 +620,8 @@ mod e2e {
                 "name": "Alice Main Account",
             }
         });
-        let _result = dispatch(&client, body, &logger).get("result").unwrap();
+        let res = dispatch(&client, body, &logger);
+        let account_id = res["result"]["account"]["account_id"].as_str().unwrap();
 
         let body = json!({
             "jsonrpc": "2.0",
@@ -624,6 +654,99 @@ mod e2e {
                 .len(),
             1
         );
+        assert_eq!(
+            status
+                .get("quarantined_block_indices")
+                .unwrap()
+                .as_array()
+                .unwrap()
+                .len(),
+            0
+        );
+
+        // MOB is broken out even before the wallet holds any.
+        let zero_totals = json!({
+            "unspent": "0",
+            "pending": "0",
+            "spent": "0",
+            "secreted": "0",
+            "orphaned": "0",
+        });
+        assert_eq!(status["balance_per_token"], json!({ "0": zero_totals }));
+        assert_eq!(status["unknown_token_balances"], json!({}));
+        assert_eq!(
+            status["account_balance_per_token"],
+            json!({ account_id: { "0": zero_totals } })
+        );
+    }
+
+    #[test_with_logger]
+    fn test_quarantined_blocks(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+        let (client, _ledger_db, db_ctx, _network_state) = setup(&mut rng, logger.clone());
+
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "get_quarantined_blocks",
+        });
+        let res = dispatch(&client, body.clone(), &logger);
+        assert_eq!(
+            res["result"]["quarantined_blocks"]
+                .as_array()
+                .unwrap()
+                .len(),
+            0
+        );
+
+        // Only quarantined blocks can be skipped.
+        let body_skip = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "skip_quarantined_block",
+            "params": {
+                "block_index": "40",
+            },
+        });
+        let res = dispatch(&client, body_skip.clone(), &logger);
+        assert!(res.get("result").is_none());
+
+        // Blocks past the end of the ledger stay quarantined.
+        let wallet_db = db_ctx.get_db_instance(logger.clone());
+        QuarantinedBlock::quarantine(
+            40,
+            "Unsupported block version 4",
+            &wallet_db.get_conn().unwrap(),
+        )
+        .unwrap();
+
+        let body_status = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "get_wallet_status",
+        });
+        let res = dispatch(&client, body_status.clone(), &logger);
+        assert_eq!(
+            res["result"]["wallet_status"]["quarantined_block_indices"],
+            json!(["40"])
+        );
+
+        let res = dispatch(&client, body_skip, &logger);
+        let quarantined_block = &res["result"]["quarantined_block"];
+        assert_eq!(quarantined_block["block_index"], "40");
+        assert_eq!(quarantined_block["reason"], "Unsupported block version 4");
+        assert_eq!(quarantined_block["skipped"], true);
+
+        let res = dispatch(&client, body, &logger);
+        assert_eq!(
+            res["result"]["quarantined_blocks"],
+            json!([quarantined_block])
+        );
+        let res = dispatch(&client, body_status, &logger);
+        assert_eq!(
+            res["result"]["wallet_status"]["quarantined_block_indices"],
+            json!([])
+        );
     }
 
     #[test_with_logger]
@@ -683,6 +806,33 @@ mod e2e {
             (42 * MOB).to_string()
         );
         let _account = result.get("account").unwrap();
+
+        // The scan which found the txo is in the account's sync history.
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "get_sync_history",
+            "params": {
+                "account_id": account_id,
+            }
+        });
+        let res = dispatch(&client, body, &logger);
+        let sync_history = res["result"]["sync_history"].as_array().unwrap();
+        let last_record = sync_history.last().unwrap();
+        assert_eq!(last_record["object"], "account_sync_record");
+        assert_eq!(last_record["account_id"], account_id);
+        assert_eq!(last_record["end_block_index"], "12");
+        let txos_received: u64 = sync_history
+            .iter()
+            .map(|record| {
+                record["txos_received"]
+                    .as_str()
+                    .unwrap()
+                    .parse::<u64>()
+                    .unwrap()
+            })
+            .sum();
+        assert_eq!(txos_received, 1);
     }
 
     #[test_with_logger]
@@ -1073,11 +1223,22 @@ mod e2e {
             json!({
                 "method": "build_transaction",
                 "error": json!({
-                    "code": -32603,
-                    "message": "InternalError",
+                    "code": 2001,
+                    "message": "InsufficientFunds",
                     "data": json!({
-                        "server_error": format!("TransactionBuilder(WalletDb(InsufficientFundsUnderMaxSpendable(\"Max spendable value in wallet: 0, but target value: {}\")))", 42 + Mob::MINIMUM_FEE),
+                        "server_error": format!("TransactionBuilder(WalletDb(InsufficientFundsUnderMaxSpendable(InsufficientFundsDetails {{ target_value: {}, total_unspent_value: 100, max_spendable_value: 0, shortfall: {}, txos_needed: None, consolidation_would_help: false }})))", 42 + Mob::MINIMUM_FEE, 42 + Mob::MINIMUM_FEE - 100),
                         "details": format!("Error building transaction: Wallet DB Error: Insufficient funds from Txos under max_spendable_value: Max spendable value in wallet: 0, but target value: {}", 42 + Mob::MINIMUM_FEE),
+                        "insufficient_funds": {
+                            "object": "insufficient_funds",
+                            "target_value": (42 + Mob::MINIMUM_FEE).to_string(),
+                            "total_unspent_value": "100",
+                            "max_spendable_value": "0",
+                            "shortfall": (42 + Mob::MINIMUM_FEE - 100).to_string(),
+                            "txos_needed": null,
+                            "consolidation_would_help": false,
+                        },
+                        "category": "transaction",
+                        "retryable": false,
                     })
                 }),
                 "jsonrpc": "2.0",
@@ -2026,6 +2187,86 @@ mod e2e {
         assert_eq!(tx_logs_page.len(), 6);
         assert_eq!(tx_logs_all[3..9].len(), 6);
         assert_eq!(tx_logs_page[..], tx_logs_all[3..9]);
+
+        // Page through the txos with a cursor.
+        let txos_page = |cursor: &serde_json::Value| {
+            let body = json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": "get_txos_for_account",
+                "params": {
+                    "account_id": account_id,
+                    "limit": "4",
+                    "cursor": cursor,
+                }
+            });
+            dispatch(&client, body, &logger)["result"].clone()
+        };
+        let first_page = txos_page(&serde_json::Value::Null);
+        assert_eq!(
+            first_page["txo_ids"].as_array().unwrap()[..],
+            txos_all[0..4]
+        );
+        assert_eq!(first_page["has_more"], true);
+
+        // Txos received while paging land on a later page, rather than
+        // shifting the txos already paged through.
+        for _ in 0..2 {
+            add_block_to_ledger_db(
+                &mut ledger_db,
+                &vec![public_address.clone()],
+                100,
+                &vec![KeyImage::from(rng.next_u64())],
+                &mut rng,
+            );
+        }
+        manually_sync_account(
+            &ledger_db,
+            &db_ctx.get_db_instance(logger.clone()),
+            &AccountID(account_id.to_string()),
+            &logger,
+        );
+
+        let second_page = txos_page(&first_page["next_cursor"]);
+        assert_eq!(
+            second_page["txo_ids"].as_array().unwrap()[..],
+            txos_all[4..8]
+        );
+        assert_eq!(second_page["has_more"], true);
+
+        let last_page = txos_page(&second_page["next_cursor"]);
+        let last_page_ids = last_page["txo_ids"].as_array().unwrap();
+        assert_eq!(last_page_ids.len(), 4);
+        assert_eq!(last_page_ids[..2], txos_all[8..10]);
+        assert_eq!(last_page["has_more"], false);
+        assert_eq!(last_page["next_cursor"], serde_json::Value::Null);
+
+        // A cursor only pages through the list it was returned for, and can't
+        // be combined with an offset.
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "get_transaction_logs_for_account",
+            "params": {
+                "account_id": account_id,
+                "cursor": first_page["next_cursor"],
+            }
+        });
+        let res = dispatch(&client, body, &logger);
+        assert_eq!(res["error"]["code"], -32602);
+
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "get_txos_for_account",
+            "params": {
+                "account_id": account_id,
+                "offset": "2",
+                "cursor": first_page["next_cursor"],
+            }
+        });
+        let res = dispatch(&client, body, &logger);
+        assert_eq!(res["error"]["code"], -32602);
     }
 
     #[test_with_logger]
@@ -2669,7 +2910,9 @@ mod e2e {
         let res = dispatch(&client, body, &logger);
         let error = res.get("error").unwrap();
         let code = error.get("code").unwrap();
-        assert_eq!(code, -32603);
+        assert_eq!(code, 1102);
+        assert_eq!(error["message"], "AddressNotFound");
+        assert_eq!(error["data"]["category"], "wallet_db");
 
         // Create a subaddress
         let body = json!({
@@ -2698,6 +2941,49 @@ mod e2e {
         let subaddress_index = address.get("subaddress_index").unwrap().as_str().unwrap();
 
         assert_eq!(subaddress_index, "2");
+        assert!(address.get("address_status").is_none());
+
+        // Any index can be derived, and reports whether it is in use.
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "get_address_for_account",
+            "params": {
+                "account_id": account_id,
+                "index": 7,
+                "derive": true,
+            }
+        });
+        let res = dispatch(&client, body, &logger);
+        let result = res.get("result").unwrap();
+        assert_eq!(result["address"]["subaddress_index"], "7");
+        assert_eq!(result["address_status"], "untouched");
+        assert_eq!(result["orphaned_txo_ids"], json!([]));
+
+        // And can be assigned in the same call.
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "get_address_for_account",
+            "params": {
+                "account_id": account_id,
+                "index": 7,
+                "assign": true,
+            }
+        });
+        let res = dispatch(&client, body, &logger);
+        assert_eq!(res["result"]["address_status"], "untouched");
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "get_address_for_account",
+            "params": {
+                "account_id": account_id,
+                "index": 7,
+            }
+        });
+        let res = dispatch(&client, body, &logger);
+        assert_eq!(res["result"]["address"]["subaddress_index"], "7");
     }
 
     #[test_with_logger]
@@ -2744,125 +3030,283 @@ mod e2e {
     }
 
     #[test_with_logger]
-    fn test_balance_for_address(logger: Logger) {
+    fn test_convert_amount(logger: Logger) {
         let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
-        let (client, mut ledger_db, db_ctx, _network_state) = setup(&mut rng, logger.clone());
+        let (client, _ledger_db, _db_ctx, _network_state) = setup(&mut rng, logger.clone());
 
-        // Add an account
         let body = json!({
             "jsonrpc": "2.0",
-            "api_version": "2",
             "id": 1,
-            "method": "create_account",
+            "method": "convert_amount",
             "params": {
-                "name": "Alice Main Account",
+                "value": "1.25",
+                "from": "mob",
+                "to": "pmob",
             }
         });
         let res = dispatch(&client, body, &logger);
-        let account_id = res["result"]["account"]["account_id"].as_str().unwrap();
-        let b58_public_address = res["result"]["account"]["main_address"].as_str().unwrap();
-
-        let alice_public_address = b58_decode_public_address(&b58_public_address)
-            .expect("Could not b58_decode public address");
-        add_block_to_ledger_db(
-            &mut ledger_db,
-            &vec![alice_public_address],
-            42 * MOB,
-            &vec![KeyImage::from(rng.next_u64())],
-            &mut rng,
-        );
-        //
-        manually_sync_account(
-            &ledger_db,
-            &db_ctx.get_db_instance(logger.clone()),
-            &AccountID(account_id.to_string()),
-            &logger,
-        );
+        let result = res.get("result").unwrap();
+        assert_eq!(result["value"].as_str().unwrap(), "1250000000000");
+        assert_eq!(result["unit"].as_str().unwrap(), "pmob");
 
+        // Sub-picoMOB precision is rejected unless a rounding mode is given.
         let body = json!({
             "jsonrpc": "2.0",
-            "api_version": "2",
             "id": 1,
-            "method": "get_balance_for_address",
+            "method": "convert_amount",
             "params": {
-                "address": b58_public_address,
+                "value": "0.0000000000019",
+                "from": "mob",
+                "to": "pmob",
             }
         });
         let res = dispatch(&client, body, &logger);
-        let balance = res["result"]["balance"].clone();
-        assert_eq!(
-            balance["unspent_pmob"]
-                .as_str()
-                .unwrap()
-                .parse::<u64>()
-                .expect("Could not parse u64"),
-            42 * MOB
-        );
-        assert_eq!(
-            balance["pending_pmob"]
-                .as_str()
-                .unwrap()
-                .parse::<u64>()
-                .expect("Could not parse u64"),
-            0
-        );
-        assert_eq!(
-            balance["spent_pmob"]
-                .as_str()
-                .unwrap()
-                .parse::<u64>()
-                .expect("Could not parse u64"),
-            0
-        );
-        assert_eq!(
-            balance["secreted_pmob"]
-                .as_str()
-                .unwrap()
-                .parse::<u64>()
-                .expect("Could not parse u64"),
-            0
-        );
-        assert_eq!(
-            balance["orphaned_pmob"]
-                .as_str()
-                .unwrap()
-                .parse::<u64>()
-                .expect("Could not parse u64"),
-            0
-        );
+        assert!(res.get("error").is_some());
 
-        // Create a subaddress
         let body = json!({
             "jsonrpc": "2.0",
-            "api_version": "2",
             "id": 1,
-            "method": "assign_address_for_account",
+            "method": "convert_amount",
             "params": {
-                "account_id": account_id,
-                "comment": "For Bob",
+                "value": "0.0000000000019",
+                "from": "mob",
+                "to": "pmob",
+                "rounding": "down",
             }
         });
         let res = dispatch(&client, body, &logger);
+        assert_eq!(res["result"]["value"].as_str().unwrap(), "1");
+    }
+
+    #[test_with_logger]
+    fn test_run_diagnostics(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+        let (client, _ledger_db, _db_ctx, _network_state) = setup(&mut rng, logger.clone());
+
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "run_diagnostics",
+        });
+        let res = dispatch(&client, body, &logger);
         let result = res.get("result").unwrap();
-        let from_bob_b58_public_address = result
-            .get("address")
-            .unwrap()
-            .get("public_address")
-            .unwrap()
-            .as_str()
+        let checks = result["checks"].as_array().unwrap();
+        let schema = checks
+            .iter()
+            .find(|check| check["name"] == "wallet_db_schema")
             .unwrap();
-        let from_bob_public_address =
-            b58_decode_public_address(from_bob_b58_public_address).unwrap();
+        assert_eq!(schema["object"], "diagnostic_check");
+        assert_eq!(schema["status"], "passed");
+        assert!(result["passed"].as_bool().is_some());
+    }
 
-        // Add a block to the ledger with a transaction "From Bob"
-        add_block_to_ledger_db(
+    #[test_with_logger]
+    fn test_ledger_db_size(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+        let (client, _ledger_db, _db_ctx, _network_state) = setup(&mut rng, logger.clone());
+
+        // The max size can't be larger than LedgerDB's map.
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "set_ledger_db_max_size",
+            "params": {
+                "max_size_bytes": u64::MAX.to_string(),
+            },
+        });
+        let res = dispatch(&client, body, &logger);
+        assert_eq!(res["error"]["code"], -32602);
+        assert!(res["error"]["data"]["details"]
+            .as_str()
+            .unwrap()
+            .contains("larger than LedgerDB's map size"));
+
+        // The test ledger is not given a path, so has no files to measure.
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "get_ledger_db_size",
+        });
+        let res = dispatch(&client, body, &logger);
+        assert!(res.get("result").is_none());
+        assert_eq!(res["error"]["code"], -32603);
+    }
+
+    #[test_with_logger]
+    fn test_export_txos(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+        let (client, mut ledger_db, db_ctx, _network_state) = setup(&mut rng, logger.clone());
+
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "create_account",
+            "params": {
+                "name": "Alice Main Account",
+            }
+        });
+        let res = dispatch(&client, body, &logger);
+        let account_id = res["result"]["account"]["account_id"].as_str().unwrap();
+        let b58_public_address = res["result"]["account"]["main_address"].as_str().unwrap();
+        let alice_public_address = b58_decode_public_address(&b58_public_address).unwrap();
+
+        for value in [1, 2, 3] {
+            add_block_to_ledger_db(
+                &mut ledger_db,
+                &vec![alice_public_address.clone()],
+                value * MOB,
+                &vec![KeyImage::from(rng.next_u64())],
+                &mut rng,
+            );
+        }
+        manually_sync_account(
+            &ledger_db,
+            &db_ctx.get_db_instance(logger.clone()),
+            &AccountID(account_id.to_string()),
+            &logger,
+        );
+
+        let export = |body: serde_json::Value| {
+            let mut res = client
+                .post("/wallet/export_txos")
+                .header(ContentType::JSON)
+                .body(body.to_string())
+                .dispatch();
+            (res.status(), res.body_string().unwrap_or_default())
+        };
+
+        // Export the first two txos as NDJSON, then resume from the cursor.
+        let (status, body) = export(json!({
+            "account_id": account_id,
+            "status": TXO_STATUS_UNSPENT,
+            "limit": "2",
+        }));
+        assert_eq!(status, Status::Ok);
+        let rows: Vec<serde_json::Value> = body
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0]["value"], MOB.to_string());
+        assert_eq!(rows[0]["received_status"], TXO_STATUS_UNSPENT);
+
+        let (status, body) = export(json!({
+            "account_id": account_id,
+            "cursor": rows[1]["cursor"],
+        }));
+        assert_eq!(status, Status::Ok);
+        let rows: Vec<serde_json::Value> = body
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0]["value"], (3 * MOB).to_string());
+
+        // CSV has a header row, then one row per txo.
+        let (status, body) = export(json!({
+            "account_id": account_id,
+            "format": "csv",
+        }));
+        assert_eq!(status, Status::Ok);
+        let lines: Vec<&str> = body.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert!(lines[0].starts_with("cursor,txo_id_hex,value,token_id,"));
+
+        // Txos can be exported by the label the txo classifier gave them.
+        let conn = db_ctx.get_db_instance(logger.clone()).get_conn().unwrap();
+        let txo_id_hex = rows[0]["txo_id_hex"].as_str().unwrap();
+        Txo::update_classification(txo_id_hex, Some("payroll"), &conn).unwrap();
+        let (status, body) = export(json!({
+            "account_id": account_id,
+            "classification": "payroll",
+        }));
+        assert_eq!(status, Status::Ok);
+        let rows: Vec<serde_json::Value> = body
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0]["txo_id_hex"], txo_id_hex);
+        assert_eq!(rows[0]["classification"], "payroll");
+
+        let (status, _body) = export(json!({
+            "status": "not_a_status",
+        }));
+        assert_eq!(status, Status::BadRequest);
+
+        // A signed export carries a detached signature from the reporting key.
+        let mut res = client
+            .post("/wallet/export_txos")
+            .header(ContentType::JSON)
+            .body(json!({"account_id": account_id, "signed": true}).to_string())
+            .dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        let signature = res
+            .headers()
+            .get_one(EXPORT_SIGNATURE_HEADER)
+            .unwrap()
+            .to_string();
+        let signing_key = res
+            .headers()
+            .get_one(EXPORT_SIGNING_KEY_HEADER)
+            .unwrap()
+            .to_string();
+        let signed_body = res.body_string().unwrap();
+        assert_eq!(signed_body.lines().count(), 3);
+
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "get_reporting_public_key",
+        });
+        let res = dispatch(&client, body, &logger);
+        assert_eq!(res["result"]["reporting_public_key"], signing_key);
+
+        let public_key =
+            Ed25519Public::try_from(hex::decode(&signing_key).unwrap().as_slice()).unwrap();
+        let signature =
+            Ed25519Signature::try_from(hex::decode(&signature).unwrap().as_slice()).unwrap();
+        assert!(public_key
+            .verify(signed_body.as_bytes(), &signature)
+            .is_ok());
+    }
+
+    #[test_with_logger]
+    fn test_canonical_json_output(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+        let (client, mut ledger_db, db_ctx, _network_state) = setup(&mut rng, logger.clone());
+
+        fn keys_are_sorted(value: &serde_json::Value) -> bool {
+            match value {
+                serde_json::Value::Object(map) => {
+                    map.keys().zip(map.keys().skip(1)).all(|(a, b)| a < b)
+                        && map.values().all(keys_are_sorted)
+                }
+                serde_json::Value::Array(values) => values.iter().all(keys_are_sorted),
+                _ => true,
+            }
+        }
+
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "create_account",
+            "params": {
+                "name": "Alice Main Account",
+            }
+        });
+        let res = dispatch(&client, body, &logger);
+        let account_id = res["result"]["account"]["account_id"].as_str().unwrap();
+        let b58_public_address = res["result"]["account"]["main_address"].as_str().unwrap();
+        let alice_public_address = b58_decode_public_address(&b58_public_address).unwrap();
+
+        add_block_to_ledger_db(
             &mut ledger_db,
-            &vec![from_bob_public_address],
-            64 * MOB,
+            &vec![alice_public_address.clone()],
+            100 * MOB,
             &vec![KeyImage::from(rng.next_u64())],
             &mut rng,
         );
-        //
         manually_sync_account(
             &ledger_db,
             &db_ctx.get_db_instance(logger.clone()),
@@ -2870,84 +3314,76 @@ mod e2e {
             &logger,
         );
 
+        // Responses keep declaration order unless canonical output is asked for.
         let body = json!({
             "jsonrpc": "2.0",
-            "api_version": "2",
             "id": 1,
-            "method": "get_balance_for_address",
+            "method": "get_account_status",
             "params": {
-                "address": from_bob_b58_public_address,
+                "account_id": account_id,
             }
         });
-        let res = dispatch(&client, body, &logger);
-        let balance = res["result"]["balance"].clone();
-        assert_eq!(
-            balance["unspent_pmob"]
-                .as_str()
-                .unwrap()
-                .parse::<u64>()
-                .expect("Could not parse u64"),
-            64 * MOB
-        );
+        let res = dispatch(&client, body.clone(), &logger);
+        assert!(!keys_are_sorted(&res));
+
+        let header = Header::new("X-Canonical-JSON", "true");
+        let canonical = dispatch_with_header(&client, body, header, &logger);
+        assert!(keys_are_sorted(&canonical));
+        assert_eq!(canonical, res);
+
+        // Exported rows are canonical too.
+        let mut res = client
+            .post("/wallet/export_txos")
+            .header(ContentType::JSON)
+            .header(Header::new("X-Canonical-JSON", "true"))
+            .body(json!({ "account_id": account_id }).to_string())
+            .dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        let body = res.body_string().unwrap();
+        let rows: Vec<serde_json::Value> = body
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(rows.len(), 1);
+        assert!(keys_are_sorted(&rows[0]));
     }
 
-    /// This test is intended to make sure that when a subaddress is assigned
-    /// that it correctly generates and checks the key image against the ledger
-    /// db to see if the previously orphaned txo has been spent or not
     #[test_with_logger]
-    fn test_mark_orphaned_txo_as_spent(logger: Logger) {
+    fn test_submit_payout_file(logger: Logger) {
         let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
         let (client, mut ledger_db, db_ctx, _network_state) = setup(&mut rng, logger.clone());
 
-        // Add an account
         let body = json!({
             "jsonrpc": "2.0",
             "id": 1,
-            "method": "import_account",
+            "method": "create_account",
             "params": {
-                "mnemonic": "sheriff odor square mistake huge skate mouse shoot purity weapon proof stuff correct concert blanket neck own shift clay mistake air viable stick group",
-                "key_derivation_version": "2",
                 "name": "Alice Main Account",
             }
         });
         let res = dispatch(&client, body, &logger);
-        let result = res.get("result").unwrap();
-        let account_obj = result.get("account").unwrap();
-        let account_id = account_obj.get("account_id").unwrap().as_str().unwrap();
+        let account_id = res["result"]["account"]["account_id"].as_str().unwrap();
+        let b58_public_address = res["result"]["account"]["main_address"].as_str().unwrap();
+        let alice_public_address = b58_decode_public_address(&b58_public_address).unwrap();
 
-        // Assign next subaddress for account.
         let body = json!({
             "jsonrpc": "2.0",
             "id": 1,
-            "method": "assign_address_for_account",
+            "method": "create_account",
             "params": {
-                "account_id": account_id,
-                "metadata": "subaddress_index_2",
+                "name": "Bob Main Account",
             }
         });
         let res = dispatch(&client, body, &logger);
-        let result = res.get("result").unwrap();
-        let address = result.get("address").unwrap();
-        let b58_public_address = address.get("public_address").unwrap().as_str().unwrap();
-        let public_address = b58_decode_public_address(b58_public_address).unwrap();
-
-        // Add a block to fund account at the new subaddress.
-        add_block_to_ledger_db(
-            &mut ledger_db,
-            &vec![public_address.clone()],
-            100000000000000, // 100.0 MOB
-            &vec![KeyImage::from(rng.next_u64())],
-            &mut rng,
-        );
+        let bob_address = res["result"]["account"]["main_address"].as_str().unwrap();
 
         add_block_to_ledger_db(
             &mut ledger_db,
-            &vec![public_address.clone()],
-            500000000000000, // 500.0 MOB
+            &vec![alice_public_address.clone()],
+            100 * MOB,
             &vec![KeyImage::from(rng.next_u64())],
             &mut rng,
         );
-
         manually_sync_account(
             &ledger_db,
             &db_ctx.get_db_instance(logger.clone()),
@@ -2955,35 +3391,86 @@ mod e2e {
             &logger,
         );
 
-        // Remove the account.
+        let contents = json!([
+            {"address": bob_address, "amount": "1.5", "external_id": "payout-1"},
+            {"address": bob_address, "amount": "2", "token": "mob", "external_id": "payout-2"},
+            {"address": "not_an_address", "amount": "1", "external_id": "payout-3"},
+            {"address": bob_address, "amount": "1", "external_id": "payout-1"},
+        ]);
         let body = json!({
             "jsonrpc": "2.0",
-            "id": 2,
-            "method": "remove_account",
+            "id": 1,
+            "method": "submit_payout_file",
             "params": {
-                "account_id": *account_id,
+                "account_id": account_id,
+                "contents": contents.to_string(),
+                "format": "json",
+                "amount_unit": "mob",
             }
         });
         let res = dispatch(&client, body, &logger);
         let result = res.get("result").unwrap();
-        assert_eq!(result["removed"].as_bool().unwrap(), true,);
+        assert_eq!(result["accepted_count"], "2");
+        assert_eq!(result["rejected_count"], "2");
+
+        let results = result["results"].as_array().unwrap();
+        assert_eq!(results.len(), 4);
+        assert_eq!(results[0]["object"], "payout_row_result");
+        assert_eq!(results[0]["row"], "1");
+        assert_eq!(results[0]["accepted"], true);
+        assert_eq!(results[1]["accepted"], true);
+        assert_eq!(
+            results[0]["transaction_log_id"],
+            results[1]["transaction_log_id"]
+        );
+        assert_eq!(results[2]["accepted"], false);
+        assert!(results[2]["error"].is_string());
+        assert_eq!(results[3]["accepted"], false);
+        assert!(results[3]["transaction_log_id"].is_null());
 
-        // Add the same account back.
+        // An unknown format fails the whole request.
         let body = json!({
             "jsonrpc": "2.0",
             "id": 1,
-            "method": "import_account",
+            "method": "submit_payout_file",
+            "params": {
+                "account_id": account_id,
+                "contents": "",
+                "format": "xlsx",
+            }
+        });
+        let res = dispatch(&client, body, &logger);
+        assert_eq!(
+            res["error"]["data"]["details"],
+            "Unknown payout file format: xlsx"
+        );
+    }
+
+    #[test_with_logger]
+    fn test_disable_spending(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+        let (client, mut ledger_db, db_ctx, _network_state) = setup(&mut rng, logger.clone());
+
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "create_account",
             "params": {
-                "mnemonic": "sheriff odor square mistake huge skate mouse shoot purity weapon proof stuff correct concert blanket neck own shift clay mistake air viable stick group",
-                "key_derivation_version": "2",
                 "name": "Alice Main Account",
             }
         });
         let res = dispatch(&client, body, &logger);
-        let result = res.get("result").unwrap();
-        let account_obj = result.get("account").unwrap();
-        let account_id = account_obj.get("account_id").unwrap().as_str().unwrap();
+        let account_id = res["result"]["account"]["account_id"].as_str().unwrap();
+        let b58_public_address = res["result"]["account"]["main_address"].as_str().unwrap();
+        let alice_public_address = b58_decode_public_address(&b58_public_address).unwrap();
 
+        add_block_to_ledger_db(
+            &mut ledger_db,
+            &vec![alice_public_address.clone()],
+            100 * MOB,
+            &vec![KeyImage::from(rng.next_u64())],
+            &mut rng,
+        );
         manually_sync_account(
             &ledger_db,
             &db_ctx.get_db_instance(logger.clone()),
@@ -2994,142 +3481,296 @@ mod e2e {
         let body = json!({
             "jsonrpc": "2.0",
             "id": 1,
-            "method": "get_balance_for_account",
+            "method": "disable_spending",
+        });
+        let res = dispatch(&client, body, &logger);
+        assert_eq!(res["result"]["spending_disabled"], true);
+
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "build_and_submit_transaction",
             "params": {
-                "account_id": *account_id,
+                "account_id": account_id,
+                "recipient_public_address": b58_public_address,
+                "value_pmob": "42000000000000",
             }
         });
         let res = dispatch(&client, body, &logger);
-        let result = res.get("result").unwrap();
-        let balance = result.get("balance").unwrap();
-        assert_eq!(balance.get("unspent_pmob").unwrap(), "0");
-        assert_eq!(balance.get("spent_pmob").unwrap(), "0");
-        assert_eq!(balance.get("orphaned_pmob").unwrap(), "600000000000000");
+        assert_eq!(
+            res["error"]["data"]["details"],
+            "Spending has been disabled on this wallet"
+        );
 
-        // Add back next subaddress. Txos are detected as unspent.
+        // Read access is unaffected.
         let body = json!({
             "jsonrpc": "2.0",
             "id": 1,
-            "method": "assign_address_for_account",
+            "method": "get_balance_for_account",
             "params": {
                 "account_id": account_id,
-                "metadata": "subaddress_index_2",
             }
         });
-        dispatch(&client, body, &logger);
+        let res = dispatch(&client, body, &logger);
+        assert_eq!(res["result"]["balance"]["unspent_pmob"], "100000000000000");
 
+        // Without an admin key configured, spending stays disabled.
         let body = json!({
             "jsonrpc": "2.0",
             "id": 1,
-            "method": "get_balance_for_account",
+            "method": "enable_spending",
             "params": {
-                "account_id": *account_id,
+                "admin_key": "",
             }
         });
         let res = dispatch(&client, body, &logger);
-        let result = res.get("result").unwrap();
-        let balance = result.get("balance").unwrap();
-        assert_eq!(balance.get("unspent_pmob").unwrap(), "600000000000000");
-        assert_eq!(balance.get("spent_pmob").unwrap(), "0");
-        assert_eq!(balance.get("orphaned_pmob").unwrap(), "0");
+        assert_eq!(
+            res["error"]["data"]["details"],
+            "No admin key is configured to re-enable spending"
+        );
+    }
+
+    #[test_with_logger]
+    fn test_maintenance_mode(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+        let (client, _ledger_db, _db_ctx, _network_state) = setup_with(
+            &mut rng,
+            logger.clone(),
+            TestSetupOptions {
+                maintenance_mode: MaintenanceMode::new(Some("admin".to_string())),
+                ..Default::default()
+            },
+        );
 
-        // Create a second account.
         let body = json!({
             "jsonrpc": "2.0",
             "id": 1,
             "method": "create_account",
             "params": {
-                "name": "account 2",
+                "name": "Alice Main Account",
             }
         });
         let res = dispatch(&client, body, &logger);
-        let result = res.get("result").unwrap();
-        let account_obj = result.get("account").unwrap();
-        let account_id_2 = account_obj.get("account_id").unwrap().as_str().unwrap();
-        let b58_public_address_2 = account_obj.get("main_address").unwrap().as_str().unwrap();
+        let account_id = res["result"]["account"]["account_id"].as_str().unwrap();
 
-        // Remove the second Account
         let body = json!({
             "jsonrpc": "2.0",
-            "id": 2,
-            "method": "remove_account",
+            "id": 1,
+            "method": "start_maintenance",
             "params": {
-                "account_id": *account_id_2,
+                "admin_key": "admin",
+                "eta_seconds": "600",
+                "reason": "Database backup",
             }
         });
         let res = dispatch(&client, body, &logger);
-        let result = res.get("result").unwrap();
-        assert_eq!(result["removed"].as_bool().unwrap(), true,);
+        let maintenance_status = &res["result"]["maintenance_status"];
+        assert_eq!(maintenance_status["object"], "maintenance_status");
+        assert_eq!(maintenance_status["in_maintenance"], true);
+        assert_eq!(maintenance_status["reason"], "Database backup");
+        let eta_time = maintenance_status["eta_time"].as_str().unwrap().to_string();
 
-        // Send some coins to the removed second account.
+        // Writes are rejected with a retryable error carrying the estimate.
         let body = json!({
             "jsonrpc": "2.0",
             "id": 1,
-            "method": "build_transaction",
+            "method": "update_account_name",
             "params": {
                 "account_id": account_id,
-                "recipient_public_address": b58_public_address_2,
-                "value_pmob": "50000000000000", // 50.0 MOB
+                "name": "Alice",
             }
         });
         let res = dispatch(&client, body, &logger);
-        let result = res.get("result").unwrap();
-        let tx_proposal = result.get("tx_proposal").unwrap();
+        assert_eq!(res["error"]["code"], 1004);
+        assert_eq!(res["error"]["data"]["retryable"], true);
+        assert_eq!(res["error"]["data"]["eta_time"], eta_time);
+        assert_eq!(res["error"]["data"]["reason"], "Database backup");
+        assert!(res["error"]["data"]["retry_after_seconds"].is_string());
 
+        // Reads are still served.
         let body = json!({
             "jsonrpc": "2.0",
             "id": 1,
-            "method": "submit_transaction",
+            "method": "get_account",
             "params": {
-                "tx_proposal": tx_proposal
+                "account_id": account_id,
             }
         });
         let res = dispatch(&client, body, &logger);
-        let result = res.get("result");
-        assert!(result.is_some());
+        assert_eq!(res["result"]["account"]["name"], "Alice Main Account");
 
-        let json_tx_proposal: json_rpc::tx_proposal::TxProposal =
-            serde_json::from_value(tx_proposal.clone()).unwrap();
-        let payments_tx_proposal =
-            mc_mobilecoind::payments::TxProposal::try_from(&json_tx_proposal).unwrap();
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "end_maintenance",
+            "params": {
+                "admin_key": "admin",
+            }
+        });
+        let res = dispatch(&client, body, &logger);
+        assert_eq!(res["result"]["maintenance_status"]["in_maintenance"], false);
 
-        add_block_with_tx_proposal(&mut ledger_db, payments_tx_proposal);
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "update_account_name",
+            "params": {
+                "account_id": account_id,
+                "name": "Alice",
+            }
+        });
+        let res = dispatch(&client, body, &logger);
+        assert_eq!(res["result"]["account"]["name"], "Alice");
+    }
 
-        manually_sync_account(
-            &ledger_db,
-            &db_ctx.get_db_instance(logger.clone()),
-            &AccountID(account_id.to_string()),
-            &logger,
-        );
+    #[test_with_logger]
+    fn test_get_tokens(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+        let (client, _ledger_db, _db_ctx, _network_state) = setup(&mut rng, logger.clone());
 
-        // The first account shows the coins are spent.
         let body = json!({
             "jsonrpc": "2.0",
             "id": 1,
-            "method": "get_balance_for_account",
+            "method": "get_tokens",
+        });
+        let res = dispatch(&client, body, &logger);
+        let tokens = res["result"]["tokens"].as_array().unwrap();
+        assert_eq!(tokens[0]["object"], "token");
+        assert_eq!(tokens[0]["token_id"], "0");
+        assert_eq!(tokens[0]["symbol"], "MOB");
+        assert_eq!(tokens[0]["decimals"], "12");
+        assert!(tokens[0]["minimum_fee"].is_string());
+        assert_eq!(tokens[1]["token_id"], "1");
+        assert_eq!(tokens[1]["symbol"], "eUSD");
+        assert_eq!(tokens[1]["decimals"], "6");
+    }
+
+    #[test_with_logger]
+    fn test_cancel_account_import(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+        let (client, _ledger_db, _db_ctx, _network_state) = setup(&mut rng, logger.clone());
+
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "import_account",
             "params": {
-                "account_id": *account_id,
+                "mnemonic": "sheriff odor square mistake huge skate mouse shoot purity weapon proof stuff correct concert blanket neck own shift clay mistake air viable stick group",
+                "key_derivation_version": "2",
+                "name": "Alice Main Account",
             }
         });
         let res = dispatch(&client, body, &logger);
-        let result = res.get("result").unwrap();
-        let balance = result.get("balance").unwrap();
-        assert_eq!(balance.get("unspent_pmob").unwrap(), "549999600000000");
-        assert_eq!(balance.get("spent_pmob").unwrap(), "100000000000000");
-        assert_eq!(balance.get("orphaned_pmob").unwrap(), "0");
+        let account_id = res["result"]["account"]["account_id"].as_str().unwrap();
 
-        // Remove the first account and add it back again.
         let body = json!({
             "jsonrpc": "2.0",
-            "id": 2,
-            "method": "remove_account",
+            "id": 1,
+            "method": "cancel_account_import",
             "params": {
-                "account_id": *account_id,
+                "account_id": account_id,
+            }
+        });
+        let res = dispatch(&client, body, &logger);
+        assert_eq!(res["result"]["removed"], true);
+
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "get_all_accounts",
+        });
+        let res = dispatch(&client, body, &logger);
+        assert_eq!(res["result"]["account_ids"].as_array().unwrap().len(), 0);
+
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "get_wallet_events",
+            "params": {}
+        });
+        let res = dispatch(&client, body, &logger);
+        let events = res["result"]["events"].as_array().unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0]["event_type"], "account_import_cancelled");
+        assert_eq!(events[0]["payload"]["account_id"], account_id);
+    }
+
+    #[test_with_logger]
+    fn test_import_accounts(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+        let (client, ledger_db, _db_ctx, _network_state) = setup(&mut rng, logger.clone());
+
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "import_accounts",
+            "params": {
+                "accounts": [
+                    {
+                        "mnemonic": "sheriff odor square mistake huge skate mouse shoot purity weapon proof stuff correct concert blanket neck own shift clay mistake air viable stick group",
+                        "key_derivation_version": "2",
+                        "name": "Alice Main Account",
+                        "first_block_index": "3",
+                    },
+                    {
+                        "entropy": "c593274dc6f6eb94242e34ae5f0ab16bc3085d45d49d9e18b8a8c6f057e6b56b",
+                        "name": "Bob Main Account",
+                        "first_block_index": "5",
+                    },
+                ],
             }
         });
         let res = dispatch(&client, body, &logger);
         let result = res.get("result").unwrap();
-        assert_eq!(result["removed"].as_bool().unwrap(), true,);
+        let accounts = result["accounts"].as_array().unwrap();
+        assert_eq!(accounts.len(), 2);
+        assert_eq!(accounts[0]["name"], "Alice Main Account");
+        assert_eq!(accounts[1]["name"], "Bob Main Account");
+
+        let import_block_index = ledger_db.num_blocks().unwrap() - 1;
+        let sync_plan = &result["sync_plan"];
+        assert_eq!(sync_plan["object"], "account_import_sync_plan");
+        assert_eq!(sync_plan["start_block_index"], "3");
+        assert_eq!(
+            sync_plan["import_block_index"],
+            import_block_index.to_string()
+        );
+        assert_eq!(
+            sync_plan["num_blocks"],
+            (import_block_index - 2).to_string()
+        );
+        assert_eq!(sync_plan["num_accounts"], "2");
+
+        // Importing an account which already exists fails the whole batch.
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "import_accounts",
+            "params": {
+                "accounts": [
+                    {
+                        "mnemonic": "sheriff odor square mistake huge skate mouse shoot purity weapon proof stuff correct concert blanket neck own shift clay mistake air viable stick group",
+                        "key_derivation_version": "2",
+                    },
+                ],
+            }
+        });
+        let res = dispatch(&client, body, &logger);
+        assert!(res.get("error").is_some());
+
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "get_all_accounts",
+        });
+        let res = dispatch(&client, body, &logger);
+        assert_eq!(res["result"]["account_ids"].as_array().unwrap().len(), 2);
+    }
+
+    #[test_with_logger]
+    fn test_set_account_first_block(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+        let (client, _ledger_db, _db_ctx, _network_state) = setup(&mut rng, logger.clone());
 
         let body = json!({
             "jsonrpc": "2.0",
@@ -3139,44 +3780,34 @@ mod e2e {
                 "mnemonic": "sheriff odor square mistake huge skate mouse shoot purity weapon proof stuff correct concert blanket neck own shift clay mistake air viable stick group",
                 "key_derivation_version": "2",
                 "name": "Alice Main Account",
+                "first_block_index": "200",
             }
         });
         let res = dispatch(&client, body, &logger);
-        let result = res.get("result").unwrap();
-        let account_obj = result.get("account").unwrap();
-        let account_id = account_obj.get("account_id").unwrap().as_str().unwrap();
-
-        manually_sync_account(
-            &ledger_db,
-            &db_ctx.get_db_instance(logger.clone()),
-            &AccountID(account_id.to_string()),
-            &logger,
-        );
+        let account_id = res["result"]["account"]["account_id"].as_str().unwrap();
+        assert_eq!(res["result"]["account"]["first_block_index"], "200");
 
-        // The unspent pmob shows what wasn't sent to the second account.
-        // The orphaned pmob are because we haven't added back the next subaddress.
         let body = json!({
             "jsonrpc": "2.0",
             "id": 1,
-            "method": "get_balance_for_account",
+            "method": "set_account_first_block",
             "params": {
-                "account_id": *account_id,
+                "account_id": account_id,
+                "block_index": "3",
             }
         });
         let res = dispatch(&client, body, &logger);
-        let result = res.get("result").unwrap();
-        let balance = result.get("balance").unwrap();
-        assert_eq!(balance.get("unspent_pmob").unwrap(), "49999600000000");
-        assert_eq!(balance.get("spent_pmob").unwrap(), "0");
-        assert_eq!(balance.get("orphaned_pmob").unwrap(), "600000000000000");
+        let account = &res["result"]["account"];
+        assert_eq!(account["first_block_index"], "3");
+        assert_eq!(account["next_block_index"], "3");
+        assert_eq!(account["name"], "Alice Main Account");
     }
 
     #[test_with_logger]
-    fn test_get_txos(logger: Logger) {
+    fn test_update_account_preferred_token_id(logger: Logger) {
         let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
-        let (client, mut ledger_db, db_ctx, _network_state) = setup(&mut rng, logger.clone());
+        let (client, _ledger_db, _db_ctx, _network_state) = setup(&mut rng, logger.clone());
 
-        // Add an account
         let body = json!({
             "jsonrpc": "2.0",
             "id": 1,
@@ -3186,65 +3817,21 @@ mod e2e {
             }
         });
         let res = dispatch(&client, body, &logger);
-        let result = res.get("result").unwrap();
-        let account_obj = result.get("account").unwrap();
-        let account_id = account_obj.get("account_id").unwrap().as_str().unwrap();
-        let b58_public_address = account_obj.get("main_address").unwrap().as_str().unwrap();
-        let public_address = b58_decode_public_address(b58_public_address).unwrap();
-
-        // Add a block with a txo for this address
-        add_block_to_ledger_db(
-            &mut ledger_db,
-            &vec![public_address],
-            100,
-            &vec![KeyImage::from(rng.next_u64())],
-            &mut rng,
-        );
-
-        manually_sync_account(
-            &ledger_db,
-            &db_ctx.get_db_instance(logger.clone()),
-            &AccountID(account_id.to_string()),
-            &logger,
-        );
+        let account_id = res["result"]["account"]["account_id"].as_str().unwrap();
+        assert_eq!(res["result"]["account"]["preferred_token_id"], "0");
 
         let body = json!({
             "jsonrpc": "2.0",
             "id": 1,
-            "method": "get_txos_for_account",
+            "method": "update_account_preferred_token_id",
             "params": {
                 "account_id": account_id,
+                "preferred_token_id": "1",
             }
         });
         let res = dispatch(&client, body, &logger);
-        let result = res.get("result").unwrap();
-        let txos = result.get("txo_ids").unwrap().as_array().unwrap();
-        assert_eq!(txos.len(), 1);
-        let txo_map = result.get("txo_map").unwrap().as_object().unwrap();
-        let txo = txo_map.get(txos[0].as_str().unwrap()).unwrap();
-        let account_status_map = txo
-            .get("account_status_map")
-            .unwrap()
-            .as_object()
-            .unwrap()
-            .get(account_id)
-            .unwrap();
-        let txo_status = account_status_map
-            .get("txo_status")
-            .unwrap()
-            .as_str()
-            .unwrap();
-        assert_eq!(txo_status, TXO_STATUS_UNSPENT);
-        let txo_type = account_status_map
-            .get("txo_type")
-            .unwrap()
-            .as_str()
-            .unwrap();
-        assert_eq!(txo_type, TXO_TYPE_RECEIVED);
-        let value = txo.get("value_pmob").unwrap().as_str().unwrap();
-        assert_eq!(value, "100");
+        assert_eq!(res["result"]["account"]["preferred_token_id"], "1");
 
-        // Check the overall balance for the account
         let body = json!({
             "jsonrpc": "2.0",
             "id": 1,
@@ -3254,18 +3841,16 @@ mod e2e {
             }
         });
         let res = dispatch(&client, body, &logger);
-        let result = res.get("result").unwrap();
-        let balance_status = result.get("balance").unwrap();
-        let unspent = balance_status["unspent_pmob"].as_str().unwrap();
-        assert_eq!(unspent, "100");
+        let balance = &res["result"]["balance"];
+        assert_eq!(balance["token_id"], "1");
+        assert_eq!(balance["unspent_pmob"], "0");
     }
 
     #[test_with_logger]
-    fn test_split_txo(logger: Logger) {
+    fn test_watch_transaction(logger: Logger) {
         let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
         let (client, mut ledger_db, db_ctx, _network_state) = setup(&mut rng, logger.clone());
 
-        // Add an account
         let body = json!({
             "jsonrpc": "2.0",
             "id": 1,
@@ -3275,21 +3860,17 @@ mod e2e {
             }
         });
         let res = dispatch(&client, body, &logger);
-        let result = res.get("result").unwrap();
-        let account_obj = result.get("account").unwrap();
-        let account_id = account_obj.get("account_id").unwrap().as_str().unwrap();
-        let b58_public_address = account_obj.get("main_address").unwrap().as_str().unwrap();
-        let public_address = b58_decode_public_address(b58_public_address).unwrap();
+        let account_id = res["result"]["account"]["account_id"].as_str().unwrap();
+        let b58_public_address = res["result"]["account"]["main_address"].as_str().unwrap();
+        let alice_public_address = b58_decode_public_address(&b58_public_address).unwrap();
 
-        // Add a block with a txo for this address
         add_block_to_ledger_db(
             &mut ledger_db,
-            &vec![public_address],
-            250000000000,
+            &vec![alice_public_address.clone()],
+            100 * MOB,
             &vec![KeyImage::from(rng.next_u64())],
             &mut rng,
         );
-
         manually_sync_account(
             &ledger_db,
             &db_ctx.get_db_instance(logger.clone()),
@@ -3306,444 +3887,1769 @@ mod e2e {
             }
         });
         let res = dispatch(&client, body, &logger);
-        let result = res.get("result").unwrap();
-        let txos = result.get("txo_ids").unwrap().as_array().unwrap();
-        assert_eq!(txos.len(), 1);
-        let txo_map = result.get("txo_map").unwrap().as_object().unwrap();
-        let txo = txo_map.get(txos[0].as_str().unwrap()).unwrap();
-        let account_status_map = txo
-            .get("account_status_map")
-            .unwrap()
-            .as_object()
-            .unwrap()
-            .get(account_id)
-            .unwrap();
-        let txo_status = account_status_map
-            .get("txo_status")
-            .unwrap()
+        let txo_id = res["result"]["txo_ids"][0].as_str().unwrap();
+        let public_key = res["result"]["txo_map"][txo_id]["public_key"]
             .as_str()
             .unwrap();
-        assert_eq!(txo_status, TXO_STATUS_UNSPENT);
-        let txo_type = account_status_map
-            .get("txo_type")
-            .unwrap()
-            .as_str()
-            .unwrap();
-        assert_eq!(txo_type, TXO_TYPE_RECEIVED);
-        let value = txo.get("value_pmob").unwrap().as_str().unwrap();
-        assert_eq!(value, "250000000000");
-        let txo_id = &txos[0];
 
-        // Check the overall balance for the account
+        // Watch for a transaction which has already landed.
         let body = json!({
             "jsonrpc": "2.0",
             "id": 1,
-            "method": "get_balance_for_account",
+            "method": "watch_transaction",
             "params": {
-                "account_id": account_id,
+                "tx_public_keys": [public_key],
+                "comment": "external",
             }
         });
         let res = dispatch(&client, body, &logger);
-        let result = res.get("result").unwrap();
-        let balance_status = result.get("balance").unwrap();
-        let unspent = balance_status["unspent_pmob"].as_str().unwrap();
-        assert_eq!(unspent, "250000000000");
+        let transaction_watch = &res["result"]["transaction_watch"];
+        assert_eq!(transaction_watch["object"], "transaction_watch");
+        assert_eq!(transaction_watch["watch_id"], public_key);
+        assert_eq!(transaction_watch["tx_public_keys"][0], public_key);
+        assert_eq!(transaction_watch["status"], "watch_status_landed");
+        assert_eq!(transaction_watch["comment"], "external");
+        let finalized_block_index = transaction_watch["finalized_block_index"].clone();
+        assert_eq!(
+            finalized_block_index,
+            (ledger_db.num_blocks().unwrap() - 1).to_string()
+        );
 
         let body = json!({
             "jsonrpc": "2.0",
             "id": 1,
-            "method": "build_split_txo_transaction",
+            "method": "get_transaction_watch",
             "params": {
-                "txo_id": txo_id,
-                "output_values": ["20000000000", "80000000000", "30000000000", "70000000000", "40000000000"],
-                "fee": "10000000000"
+                "watch_id": public_key,
             }
         });
         let res = dispatch(&client, body, &logger);
-        let result = res.get("result").unwrap();
-        let tx_proposal = result.get("tx_proposal").unwrap();
+        assert_eq!(
+            res["result"]["transaction_watch"]["status"],
+            "watch_status_landed"
+        );
 
         let body = json!({
             "jsonrpc": "2.0",
             "id": 1,
-            "method": "submit_transaction",
+            "method": "get_wallet_events",
+            "params": {}
+        });
+        let res = dispatch(&client, body, &logger);
+        let events = res["result"]["events"].as_array().unwrap();
+        assert_eq!(events.len(), 2);
+        // The sync recorded the txo Alice received before the watch.
+        assert_eq!(events[0]["event_type"], "txo_received");
+        assert_eq!(events[0]["payload"]["account_id"], account_id);
+        assert_eq!(events[0]["payload"]["txo_id"], txo_id);
+        assert_eq!(events[1]["object"], "wallet_event");
+        assert_eq!(events[1]["event_type"], "transaction_landed");
+        assert_eq!(events[1]["schema_version"], "1");
+        assert_eq!(events[1]["payload"]["watch_id"], public_key);
+        assert_eq!(events[1]["payload"]["block_index"], finalized_block_index);
+
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "get_wallet_events",
             "params": {
-                "tx_proposal": tx_proposal,
-                "account_id": account_id,
+                "after_event_id": events[1]["event_id"],
             }
         });
         let res = dispatch(&client, body, &logger);
-        let result = res.get("result");
-        assert!(result.is_some());
+        assert_eq!(res["result"]["events"].as_array().unwrap().len(), 0);
 
-        let json_tx_proposal: json_rpc::tx_proposal::TxProposal =
-            serde_json::from_value(tx_proposal.clone()).unwrap();
-        let payments_tx_proposal =
-            mc_mobilecoind::payments::TxProposal::try_from(&json_tx_proposal).unwrap();
-
-        add_block_with_tx_proposal(&mut ledger_db, payments_tx_proposal);
-
-        manually_sync_account(
-            &ledger_db,
-            &db_ctx.get_db_instance(logger.clone()),
-            &AccountID(account_id.to_string()),
-            &logger,
-        );
-
-        // Check the overall balance for the account
+        // Replaying from the first event's cursor returns them again,
+        // unchanged.
         let body = json!({
             "jsonrpc": "2.0",
             "id": 1,
-            "method": "get_balance_for_account",
+            "method": "replay_events",
             "params": {
-                "account_id": account_id,
+                "from_cursor": events[0]["event_id"],
+                "to_cursor": events[1]["event_id"],
             }
         });
         let res = dispatch(&client, body, &logger);
-        let result = res.get("result").unwrap();
-        let balance_status = result.get("balance").unwrap();
-        let unspent = balance_status["unspent_pmob"].as_str().unwrap();
-        assert_eq!(unspent, "240000000000");
+        assert_eq!(res["result"]["events"].as_array().unwrap(), events);
+
+        // A watch needs at least one output.
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "watch_transaction",
+            "params": {}
+        });
+        let res = dispatch(&client, body, &logger);
+        assert_eq!(
+            res["error"]["data"]["details"],
+            "At least one output public key is required to watch a transaction"
+        );
     }
 
     #[test_with_logger]
-    fn test_receipts(logger: Logger) {
+    fn test_webhooks(logger: Logger) {
         let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
-        let (client, mut ledger_db, db_ctx, _network_state) = setup(&mut rng, logger.clone());
+        let (client, _ledger_db, _db_ctx, _network_state) = setup(&mut rng, logger.clone());
 
-        // Add an account
         let body = json!({
             "jsonrpc": "2.0",
             "id": 1,
-            "method": "create_account",
+            "method": "register_webhook",
             "params": {
-                "name": "Alice Main Account",
+                "url": "not a url",
             }
         });
         let res = dispatch(&client, body, &logger);
-        let result = res.get("result").unwrap();
-        let account_obj = result.get("account").unwrap();
-        let alice_account_id = account_obj.get("account_id").unwrap().as_str().unwrap();
-        let alice_b58_public_address = account_obj.get("main_address").unwrap().as_str().unwrap();
-        let alice_public_address = b58_decode_public_address(alice_b58_public_address).unwrap();
-
-        // Add a block with a txo for this address
-        add_block_to_ledger_db(
-            &mut ledger_db,
-            &vec![alice_public_address],
-            100 * MOB,
-            &vec![KeyImage::from(rng.next_u64())],
-            &mut rng,
-        );
-
-        manually_sync_account(
-            &ledger_db,
-            &db_ctx.get_db_instance(logger.clone()),
-            &AccountID(alice_account_id.to_string()),
-            &logger,
-        );
+        assert!(res["error"]["data"]["details"]
+            .as_str()
+            .unwrap()
+            .contains("Invalid webhook URL"));
 
-        // Add Bob's account to our wallet
         let body = json!({
             "jsonrpc": "2.0",
             "id": 1,
-            "method": "create_account",
+            "method": "register_webhook",
             "params": {
-                "name": "Bob Main Account",
+                "url": "https://example.com/hook",
             }
         });
         let res = dispatch(&client, body, &logger);
-        let result = res.get("result").unwrap();
-        let bob_account_obj = result.get("account").unwrap();
-        let bob_account_id = bob_account_obj.get("account_id").unwrap().as_str().unwrap();
-        let bob_b58_public_address = bob_account_obj
-            .get("main_address")
-            .unwrap()
-            .as_str()
-            .unwrap();
+        let webhook = res["result"]["webhook"].clone();
+        assert_eq!(webhook["object"], "webhook");
+        assert_eq!(webhook["url"], "https://example.com/hook");
+        assert_eq!(webhook["account_id"], serde_json::Value::Null);
+        assert_eq!(webhook["failed_attempts"], "0");
+        let webhook_id = webhook["webhook_id"].as_str().unwrap();
 
-        // Construct a transaction proposal from Alice to Bob
         let body = json!({
             "jsonrpc": "2.0",
             "id": 1,
-            "method": "build_transaction",
-            "params": {
-                "account_id": alice_account_id,
-                "recipient_public_address": bob_b58_public_address,
-                "value_pmob": "42000000000000", // 42 MOB
-            }
+            "method": "get_webhooks",
         });
         let res = dispatch(&client, body, &logger);
-        let result = res.get("result").unwrap();
-        let tx_proposal = result.get("tx_proposal").unwrap();
+        assert_eq!(res["result"]["webhooks"], json!([webhook]));
 
-        // Get the receipts from the tx_proposal
         let body = json!({
             "jsonrpc": "2.0",
             "id": 1,
-            "method": "create_receiver_receipts",
+            "method": "remove_webhook",
             "params": {
-                "tx_proposal": tx_proposal
+                "webhook_id": webhook_id,
             }
         });
         let res = dispatch(&client, body, &logger);
-        let result = res.get("result").unwrap();
-        let receipts = result["receiver_receipts"].as_array().unwrap();
-        assert_eq!(receipts.len(), 1);
-        let receipt = &receipts[0];
+        assert_eq!(res["result"]["removed"], true);
 
-        // Bob checks status (should be pending before the block is added to the ledger)
         let body = json!({
             "jsonrpc": "2.0",
             "id": 1,
-            "method": "check_receiver_receipt_status",
-            "params": {
-                "address": bob_b58_public_address,
-                "receiver_receipt": receipt,
-            }
+            "method": "get_webhooks",
         });
         let res = dispatch(&client, body, &logger);
-        let result = res.get("result").unwrap();
-        let status = result["receipt_transaction_status"].as_str().unwrap();
-        assert_eq!(status, "TransactionPending");
+        assert_eq!(res["result"]["webhooks"].as_array().unwrap().len(), 0);
+    }
 
-        // Add the block to the ledger with the tx proposal
-        let json_tx_proposal: json_rpc::tx_proposal::TxProposal =
-            serde_json::from_value(tx_proposal.clone()).unwrap();
-        let payments_tx_proposal =
-            mc_mobilecoind::payments::TxProposal::try_from(&json_tx_proposal).unwrap();
+    #[test_with_logger]
+    fn test_balance_for_address(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+        let (client, mut ledger_db, db_ctx, _network_state) = setup(&mut rng, logger.clone());
 
-        // The MockBlockchainConnection does not write to the ledger_db
-        add_block_with_tx_proposal(&mut ledger_db, payments_tx_proposal);
+        // Add an account
+        let body = json!({
+            "jsonrpc": "2.0",
+            "api_version": "2",
+            "id": 1,
+            "method": "create_account",
+            "params": {
+                "name": "Alice Main Account",
+            }
+        });
+        let res = dispatch(&client, body, &logger);
+        let account_id = res["result"]["account"]["account_id"].as_str().unwrap();
+        let b58_public_address = res["result"]["account"]["main_address"].as_str().unwrap();
 
-        manually_sync_account(
-            &ledger_db,
-            &db_ctx.get_db_instance(logger.clone()),
-            &AccountID(alice_account_id.to_string()),
-            &logger,
+        let alice_public_address = b58_decode_public_address(&b58_public_address)
+            .expect("Could not b58_decode public address");
+        add_block_to_ledger_db(
+            &mut ledger_db,
+            &vec![alice_public_address],
+            42 * MOB,
+            &vec![KeyImage::from(rng.next_u64())],
+            &mut rng,
         );
+        //
         manually_sync_account(
             &ledger_db,
             &db_ctx.get_db_instance(logger.clone()),
-            &AccountID(bob_account_id.to_string()),
+            &AccountID(account_id.to_string()),
             &logger,
         );
 
-        // Bob checks status (should be successful after added to the ledger)
         let body = json!({
             "jsonrpc": "2.0",
+            "api_version": "2",
             "id": 1,
-            "method": "check_receiver_receipt_status",
+            "method": "get_balance_for_address",
             "params": {
-                "address": bob_b58_public_address,
-                "receiver_receipt": receipt,
+                "address": b58_public_address,
             }
         });
         let res = dispatch(&client, body, &logger);
-        let result = res.get("result").unwrap();
-        let status = result["receipt_transaction_status"].as_str().unwrap();
-        assert_eq!(status, "TransactionSuccess");
-    }
-
-    #[test_with_logger]
-    fn test_gift_codes(logger: Logger) {
-        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
-        let (client, mut ledger_db, db_ctx, _network_state) = setup(&mut rng, logger.clone());
+        let balance = res["result"]["balance"].clone();
+        assert_eq!(
+            balance["unspent_pmob"]
+                .as_str()
+                .unwrap()
+                .parse::<u64>()
+                .expect("Could not parse u64"),
+            42 * MOB
+        );
+        assert_eq!(
+            balance["pending_pmob"]
+                .as_str()
+                .unwrap()
+                .parse::<u64>()
+                .expect("Could not parse u64"),
+            0
+        );
+        assert_eq!(
+            balance["spent_pmob"]
+                .as_str()
+                .unwrap()
+                .parse::<u64>()
+                .expect("Could not parse u64"),
+            0
+        );
+        assert_eq!(
+            balance["secreted_pmob"]
+                .as_str()
+                .unwrap()
+                .parse::<u64>()
+                .expect("Could not parse u64"),
+            0
+        );
+        assert_eq!(
+            balance["orphaned_pmob"]
+                .as_str()
+                .unwrap()
+                .parse::<u64>()
+                .expect("Could not parse u64"),
+            0
+        );
 
-        // Add an account
+        // Create a subaddress
         let body = json!({
             "jsonrpc": "2.0",
+            "api_version": "2",
             "id": 1,
-            "method": "create_account",
+            "method": "assign_address_for_account",
             "params": {
-                "name": "Alice Main Account",
+                "account_id": account_id,
+                "comment": "For Bob",
             }
         });
         let res = dispatch(&client, body, &logger);
         let result = res.get("result").unwrap();
-        let account_obj = result.get("account").unwrap();
-        let alice_account_id = account_obj.get("account_id").unwrap().as_str().unwrap();
-        let alice_b58_public_address = account_obj.get("main_address").unwrap().as_str().unwrap();
-        let alice_public_address = b58_decode_public_address(alice_b58_public_address).unwrap();
+        let from_bob_b58_public_address = result
+            .get("address")
+            .unwrap()
+            .get("public_address")
+            .unwrap()
+            .as_str()
+            .unwrap();
+        let from_bob_public_address =
+            b58_decode_public_address(from_bob_b58_public_address).unwrap();
 
-        // Add a block with a txo for this address
+        // Add a block to the ledger with a transaction "From Bob"
         add_block_to_ledger_db(
             &mut ledger_db,
-            &vec![alice_public_address],
-            100 * MOB,
+            &vec![from_bob_public_address],
+            64 * MOB,
             &vec![KeyImage::from(rng.next_u64())],
             &mut rng,
         );
-
+        //
         manually_sync_account(
             &ledger_db,
             &db_ctx.get_db_instance(logger.clone()),
-            &AccountID(alice_account_id.to_string()),
+            &AccountID(account_id.to_string()),
             &logger,
         );
-        // Create a gift code
+
         let body = json!({
             "jsonrpc": "2.0",
+            "api_version": "2",
             "id": 1,
-            "method": "build_gift_code",
+            "method": "get_balance_for_address",
             "params": {
-                "account_id": alice_account_id,
-                "value_pmob": "42000000000000",
-                "memo": "Happy Birthday!",
+                "address": from_bob_b58_public_address,
             }
         });
         let res = dispatch(&client, body, &logger);
-        let result = res["result"].clone();
-        let gift_code_b58 = result["gift_code_b58"].as_str().unwrap();
-        let tx_proposal = result["tx_proposal"].clone();
+        let balance = res["result"]["balance"].clone();
+        assert_eq!(
+            balance["unspent_pmob"]
+                .as_str()
+                .unwrap()
+                .parse::<u64>()
+                .expect("Could not parse u64"),
+            64 * MOB
+        );
+    }
+
+    /// This test is intended to make sure that when a subaddress is assigned
+    /// that it correctly generates and checks the key image against the ledger
+    /// db to see if the previously orphaned txo has been spent or not
+    #[test_with_logger]
+    fn test_mark_orphaned_txo_as_spent(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+        let (client, mut ledger_db, db_ctx, _network_state) = setup(&mut rng, logger.clone());
+
+        // Add an account
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "import_account",
+            "params": {
+                "mnemonic": "sheriff odor square mistake huge skate mouse shoot purity weapon proof stuff correct concert blanket neck own shift clay mistake air viable stick group",
+                "key_derivation_version": "2",
+                "name": "Alice Main Account",
+            }
+        });
+        let res = dispatch(&client, body, &logger);
+        let result = res.get("result").unwrap();
+        let account_obj = result.get("account").unwrap();
+        let account_id = account_obj.get("account_id").unwrap().as_str().unwrap();
+
+        // Assign next subaddress for account.
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "assign_address_for_account",
+            "params": {
+                "account_id": account_id,
+                "metadata": "subaddress_index_2",
+            }
+        });
+        let res = dispatch(&client, body, &logger);
+        let result = res.get("result").unwrap();
+        let address = result.get("address").unwrap();
+        let b58_public_address = address.get("public_address").unwrap().as_str().unwrap();
+        let public_address = b58_decode_public_address(b58_public_address).unwrap();
+
+        // Add a block to fund account at the new subaddress.
+        add_block_to_ledger_db(
+            &mut ledger_db,
+            &vec![public_address.clone()],
+            100000000000000, // 100.0 MOB
+            &vec![KeyImage::from(rng.next_u64())],
+            &mut rng,
+        );
+
+        add_block_to_ledger_db(
+            &mut ledger_db,
+            &vec![public_address.clone()],
+            500000000000000, // 500.0 MOB
+            &vec![KeyImage::from(rng.next_u64())],
+            &mut rng,
+        );
+
+        manually_sync_account(
+            &ledger_db,
+            &db_ctx.get_db_instance(logger.clone()),
+            &AccountID(account_id.to_string()),
+            &logger,
+        );
+
+        // Remove the account.
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 2,
+            "method": "remove_account",
+            "params": {
+                "account_id": *account_id,
+            }
+        });
+        let res = dispatch(&client, body, &logger);
+        let result = res.get("result").unwrap();
+        assert_eq!(result["removed"].as_bool().unwrap(), true,);
+
+        // Add the same account back.
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "import_account",
+            "params": {
+                "mnemonic": "sheriff odor square mistake huge skate mouse shoot purity weapon proof stuff correct concert blanket neck own shift clay mistake air viable stick group",
+                "key_derivation_version": "2",
+                "name": "Alice Main Account",
+            }
+        });
+        let res = dispatch(&client, body, &logger);
+        let result = res.get("result").unwrap();
+        let account_obj = result.get("account").unwrap();
+        let account_id = account_obj.get("account_id").unwrap().as_str().unwrap();
+
+        manually_sync_account(
+            &ledger_db,
+            &db_ctx.get_db_instance(logger.clone()),
+            &AccountID(account_id.to_string()),
+            &logger,
+        );
+
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "get_balance_for_account",
+            "params": {
+                "account_id": *account_id,
+            }
+        });
+        let res = dispatch(&client, body, &logger);
+        let result = res.get("result").unwrap();
+        let balance = result.get("balance").unwrap();
+        assert_eq!(balance.get("unspent_pmob").unwrap(), "0");
+        assert_eq!(balance.get("spent_pmob").unwrap(), "0");
+        assert_eq!(balance.get("orphaned_pmob").unwrap(), "600000000000000");
+
+        // Add back next subaddress. Txos are detected as unspent.
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "assign_address_for_account",
+            "params": {
+                "account_id": account_id,
+                "metadata": "subaddress_index_2",
+            }
+        });
+        dispatch(&client, body, &logger);
+
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "get_balance_for_account",
+            "params": {
+                "account_id": *account_id,
+            }
+        });
+        let res = dispatch(&client, body, &logger);
+        let result = res.get("result").unwrap();
+        let balance = result.get("balance").unwrap();
+        assert_eq!(balance.get("unspent_pmob").unwrap(), "600000000000000");
+        assert_eq!(balance.get("spent_pmob").unwrap(), "0");
+        assert_eq!(balance.get("orphaned_pmob").unwrap(), "0");
+
+        // Create a second account.
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "create_account",
+            "params": {
+                "name": "account 2",
+            }
+        });
+        let res = dispatch(&client, body, &logger);
+        let result = res.get("result").unwrap();
+        let account_obj = result.get("account").unwrap();
+        let account_id_2 = account_obj.get("account_id").unwrap().as_str().unwrap();
+        let b58_public_address_2 = account_obj.get("main_address").unwrap().as_str().unwrap();
+
+        // Remove the second Account
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 2,
+            "method": "remove_account",
+            "params": {
+                "account_id": *account_id_2,
+            }
+        });
+        let res = dispatch(&client, body, &logger);
+        let result = res.get("result").unwrap();
+        assert_eq!(result["removed"].as_bool().unwrap(), true,);
+
+        // Send some coins to the removed second account.
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "build_transaction",
+            "params": {
+                "account_id": account_id,
+                "recipient_public_address": b58_public_address_2,
+                "value_pmob": "50000000000000", // 50.0 MOB
+            }
+        });
+        let res = dispatch(&client, body, &logger);
+        let result = res.get("result").unwrap();
+        let tx_proposal = result.get("tx_proposal").unwrap();
+
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "submit_transaction",
+            "params": {
+                "tx_proposal": tx_proposal
+            }
+        });
+        let res = dispatch(&client, body, &logger);
+        let result = res.get("result");
+        assert!(result.is_some());
+
+        let json_tx_proposal: json_rpc::tx_proposal::TxProposal =
+            serde_json::from_value(tx_proposal.clone()).unwrap();
+        let payments_tx_proposal =
+            mc_mobilecoind::payments::TxProposal::try_from(&json_tx_proposal).unwrap();
+
+        add_block_with_tx_proposal(&mut ledger_db, payments_tx_proposal);
+
+        manually_sync_account(
+            &ledger_db,
+            &db_ctx.get_db_instance(logger.clone()),
+            &AccountID(account_id.to_string()),
+            &logger,
+        );
+
+        // The first account shows the coins are spent.
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "get_balance_for_account",
+            "params": {
+                "account_id": *account_id,
+            }
+        });
+        let res = dispatch(&client, body, &logger);
+        let result = res.get("result").unwrap();
+        let balance = result.get("balance").unwrap();
+        assert_eq!(balance.get("unspent_pmob").unwrap(), "549999600000000");
+        assert_eq!(balance.get("spent_pmob").unwrap(), "100000000000000");
+        assert_eq!(balance.get("orphaned_pmob").unwrap(), "0");
+
+        // Remove the first account and add it back again.
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 2,
+            "method": "remove_account",
+            "params": {
+                "account_id": *account_id,
+            }
+        });
+        let res = dispatch(&client, body, &logger);
+        let result = res.get("result").unwrap();
+        assert_eq!(result["removed"].as_bool().unwrap(), true,);
+
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "import_account",
+            "params": {
+                "mnemonic": "sheriff odor square mistake huge skate mouse shoot purity weapon proof stuff correct concert blanket neck own shift clay mistake air viable stick group",
+                "key_derivation_version": "2",
+                "name": "Alice Main Account",
+            }
+        });
+        let res = dispatch(&client, body, &logger);
+        let result = res.get("result").unwrap();
+        let account_obj = result.get("account").unwrap();
+        let account_id = account_obj.get("account_id").unwrap().as_str().unwrap();
+
+        manually_sync_account(
+            &ledger_db,
+            &db_ctx.get_db_instance(logger.clone()),
+            &AccountID(account_id.to_string()),
+            &logger,
+        );
+
+        // The unspent pmob shows what wasn't sent to the second account.
+        // The orphaned pmob are because we haven't added back the next subaddress.
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "get_balance_for_account",
+            "params": {
+                "account_id": *account_id,
+            }
+        });
+        let res = dispatch(&client, body, &logger);
+        let result = res.get("result").unwrap();
+        let balance = result.get("balance").unwrap();
+        assert_eq!(balance.get("unspent_pmob").unwrap(), "49999600000000");
+        assert_eq!(balance.get("spent_pmob").unwrap(), "0");
+        assert_eq!(balance.get("orphaned_pmob").unwrap(), "600000000000000");
+    }
+
+    #[test_with_logger]
+    fn test_get_txos(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+        let (client, mut ledger_db, db_ctx, _network_state) = setup(&mut rng, logger.clone());
+
+        // Add an account
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "create_account",
+            "params": {
+                "name": "Alice Main Account",
+            }
+        });
+        let res = dispatch(&client, body, &logger);
+        let result = res.get("result").unwrap();
+        let account_obj = result.get("account").unwrap();
+        let account_id = account_obj.get("account_id").unwrap().as_str().unwrap();
+        let b58_public_address = account_obj.get("main_address").unwrap().as_str().unwrap();
+        let public_address = b58_decode_public_address(b58_public_address).unwrap();
+
+        // Add a block with a txo for this address
+        add_block_to_ledger_db(
+            &mut ledger_db,
+            &vec![public_address],
+            100,
+            &vec![KeyImage::from(rng.next_u64())],
+            &mut rng,
+        );
+
+        manually_sync_account(
+            &ledger_db,
+            &db_ctx.get_db_instance(logger.clone()),
+            &AccountID(account_id.to_string()),
+            &logger,
+        );
+
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "get_txos_for_account",
+            "params": {
+                "account_id": account_id,
+            }
+        });
+        let res = dispatch(&client, body, &logger);
+        let result = res.get("result").unwrap();
+        let txos = result.get("txo_ids").unwrap().as_array().unwrap();
+        assert_eq!(txos.len(), 1);
+        let txo_map = result.get("txo_map").unwrap().as_object().unwrap();
+        let txo = txo_map.get(txos[0].as_str().unwrap()).unwrap();
+        let account_status_map = txo
+            .get("account_status_map")
+            .unwrap()
+            .as_object()
+            .unwrap()
+            .get(account_id)
+            .unwrap();
+        let txo_status = account_status_map
+            .get("txo_status")
+            .unwrap()
+            .as_str()
+            .unwrap();
+        assert_eq!(txo_status, TXO_STATUS_UNSPENT);
+        let txo_type = account_status_map
+            .get("txo_type")
+            .unwrap()
+            .as_str()
+            .unwrap();
+        assert_eq!(txo_type, TXO_TYPE_RECEIVED);
+        let value = txo.get("value_pmob").unwrap().as_str().unwrap();
+        assert_eq!(value, "100");
+
+        // Check the overall balance for the account
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "get_balance_for_account",
+            "params": {
+                "account_id": account_id,
+            }
+        });
+        let res = dispatch(&client, body, &logger);
+        let result = res.get("result").unwrap();
+        let balance_status = result.get("balance").unwrap();
+        let unspent = balance_status["unspent_pmob"].as_str().unwrap();
+        assert_eq!(unspent, "100");
+    }
+
+    #[test_with_logger]
+    fn test_export_key_images(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+        let (client, mut ledger_db, db_ctx, _network_state) = setup(&mut rng, logger.clone());
+
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "create_account",
+            "params": {
+                "name": "Alice Main Account",
+            }
+        });
+        let res = dispatch(&client, body, &logger);
+        let result = res.get("result").unwrap();
+        let account_obj = result.get("account").unwrap();
+        let account_id = account_obj.get("account_id").unwrap().as_str().unwrap();
+        let b58_public_address = account_obj.get("main_address").unwrap().as_str().unwrap();
+        let public_address = b58_decode_public_address(b58_public_address).unwrap();
+
+        for _ in 0..2 {
+            add_block_to_ledger_db(
+                &mut ledger_db,
+                &vec![public_address.clone()],
+                100,
+                &vec![KeyImage::from(rng.next_u64())],
+                &mut rng,
+            );
+        }
+        manually_sync_account(
+            &ledger_db,
+            &db_ctx.get_db_instance(logger.clone()),
+            &AccountID(account_id.to_string()),
+            &logger,
+        );
+
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "export_key_images",
+            "params": {
+                "account_id": account_id,
+                "limit": "1",
+            }
+        });
+        let res = dispatch(&client, body, &logger);
+        let result = res.get("result").unwrap();
+        let key_images = result.get("key_images").unwrap().as_array().unwrap();
+        assert_eq!(key_images.len(), 1);
+        assert_eq!(result.get("has_more").unwrap().as_bool().unwrap(), true);
+        let key_image = &key_images[0];
+        assert_eq!(key_image["account_id"].as_str().unwrap(), account_id);
+        assert_eq!(key_image["value"].as_str().unwrap(), "100");
+        // The key image is the 32 bytes recorded in the ledger.
+        assert_eq!(key_image["key_image"].as_str().unwrap().len(), 64);
+        let first_txo_id = key_image["txo_id"].as_str().unwrap().to_string();
+        let next_cursor = result.get("next_cursor").unwrap().as_str().unwrap();
+
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "export_key_images",
+            "params": {
+                "cursor": next_cursor,
+            }
+        });
+        let res = dispatch(&client, body, &logger);
+        let result = res.get("result").unwrap();
+        let key_images = result.get("key_images").unwrap().as_array().unwrap();
+        assert_eq!(key_images.len(), 1);
+        assert_ne!(key_images[0]["txo_id"].as_str().unwrap(), first_txo_id);
+        assert_eq!(result.get("has_more").unwrap().as_bool().unwrap(), false);
+
+        // A txo cursor can't be used to page key images.
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "export_key_images",
+            "params": {
+                "cursor": encode_page_cursor(TXO_CURSOR, 1),
+            }
+        });
+        let res = dispatch(&client, body, &logger);
+        assert!(res.get("error").is_some());
+    }
+
+    #[test_with_logger]
+    fn test_split_txo(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+        let (client, mut ledger_db, db_ctx, _network_state) = setup(&mut rng, logger.clone());
+
+        // Add an account
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "create_account",
+            "params": {
+                "name": "Alice Main Account",
+            }
+        });
+        let res = dispatch(&client, body, &logger);
+        let result = res.get("result").unwrap();
+        let account_obj = result.get("account").unwrap();
+        let account_id = account_obj.get("account_id").unwrap().as_str().unwrap();
+        let b58_public_address = account_obj.get("main_address").unwrap().as_str().unwrap();
+        let public_address = b58_decode_public_address(b58_public_address).unwrap();
+
+        // Add a block with a txo for this address
+        add_block_to_ledger_db(
+            &mut ledger_db,
+            &vec![public_address],
+            250000000000,
+            &vec![KeyImage::from(rng.next_u64())],
+            &mut rng,
+        );
+
+        manually_sync_account(
+            &ledger_db,
+            &db_ctx.get_db_instance(logger.clone()),
+            &AccountID(account_id.to_string()),
+            &logger,
+        );
+
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "get_txos_for_account",
+            "params": {
+                "account_id": account_id,
+            }
+        });
+        let res = dispatch(&client, body, &logger);
+        let result = res.get("result").unwrap();
+        let txos = result.get("txo_ids").unwrap().as_array().unwrap();
+        assert_eq!(txos.len(), 1);
+        let txo_map = result.get("txo_map").unwrap().as_object().unwrap();
+        let txo = txo_map.get(txos[0].as_str().unwrap()).unwrap();
+        let account_status_map = txo
+            .get("account_status_map")
+            .unwrap()
+            .as_object()
+            .unwrap()
+            .get(account_id)
+            .unwrap();
+        let txo_status = account_status_map
+            .get("txo_status")
+            .unwrap()
+            .as_str()
+            .unwrap();
+        assert_eq!(txo_status, TXO_STATUS_UNSPENT);
+        let txo_type = account_status_map
+            .get("txo_type")
+            .unwrap()
+            .as_str()
+            .unwrap();
+        assert_eq!(txo_type, TXO_TYPE_RECEIVED);
+        let value = txo.get("value_pmob").unwrap().as_str().unwrap();
+        assert_eq!(value, "250000000000");
+        let txo_id = &txos[0];
+
+        // Check the overall balance for the account
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "get_balance_for_account",
+            "params": {
+                "account_id": account_id,
+            }
+        });
+        let res = dispatch(&client, body, &logger);
+        let result = res.get("result").unwrap();
+        let balance_status = result.get("balance").unwrap();
+        let unspent = balance_status["unspent_pmob"].as_str().unwrap();
+        assert_eq!(unspent, "250000000000");
+
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "build_split_txo_transaction",
+            "params": {
+                "txo_id": txo_id,
+                "output_values": ["20000000000", "80000000000", "30000000000", "70000000000", "40000000000"],
+                "fee": "10000000000"
+            }
+        });
+        let res = dispatch(&client, body, &logger);
+        let result = res.get("result").unwrap();
+        let tx_proposal = result.get("tx_proposal").unwrap();
+
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "submit_transaction",
+            "params": {
+                "tx_proposal": tx_proposal,
+                "account_id": account_id,
+            }
+        });
+        let res = dispatch(&client, body, &logger);
+        let result = res.get("result");
+        assert!(result.is_some());
+
+        let json_tx_proposal: json_rpc::tx_proposal::TxProposal =
+            serde_json::from_value(tx_proposal.clone()).unwrap();
+        let payments_tx_proposal =
+            mc_mobilecoind::payments::TxProposal::try_from(&json_tx_proposal).unwrap();
+
+        add_block_with_tx_proposal(&mut ledger_db, payments_tx_proposal);
+
+        manually_sync_account(
+            &ledger_db,
+            &db_ctx.get_db_instance(logger.clone()),
+            &AccountID(account_id.to_string()),
+            &logger,
+        );
+
+        // Check the overall balance for the account
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "get_balance_for_account",
+            "params": {
+                "account_id": account_id,
+            }
+        });
+        let res = dispatch(&client, body, &logger);
+        let result = res.get("result").unwrap();
+        let balance_status = result.get("balance").unwrap();
+        let unspent = balance_status["unspent_pmob"].as_str().unwrap();
+        assert_eq!(unspent, "240000000000");
+    }
+
+    #[test_with_logger]
+    fn test_receipts(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+        let (client, mut ledger_db, db_ctx, _network_state) = setup(&mut rng, logger.clone());
+
+        // Add an account
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "create_account",
+            "params": {
+                "name": "Alice Main Account",
+            }
+        });
+        let res = dispatch(&client, body, &logger);
+        let result = res.get("result").unwrap();
+        let account_obj = result.get("account").unwrap();
+        let alice_account_id = account_obj.get("account_id").unwrap().as_str().unwrap();
+        let alice_b58_public_address = account_obj.get("main_address").unwrap().as_str().unwrap();
+        let alice_public_address = b58_decode_public_address(alice_b58_public_address).unwrap();
+
+        // Add a block with a txo for this address
+        add_block_to_ledger_db(
+            &mut ledger_db,
+            &vec![alice_public_address],
+            100 * MOB,
+            &vec![KeyImage::from(rng.next_u64())],
+            &mut rng,
+        );
+
+        manually_sync_account(
+            &ledger_db,
+            &db_ctx.get_db_instance(logger.clone()),
+            &AccountID(alice_account_id.to_string()),
+            &logger,
+        );
+
+        // Add Bob's account to our wallet
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "create_account",
+            "params": {
+                "name": "Bob Main Account",
+            }
+        });
+        let res = dispatch(&client, body, &logger);
+        let result = res.get("result").unwrap();
+        let bob_account_obj = result.get("account").unwrap();
+        let bob_account_id = bob_account_obj.get("account_id").unwrap().as_str().unwrap();
+        let bob_b58_public_address = bob_account_obj
+            .get("main_address")
+            .unwrap()
+            .as_str()
+            .unwrap();
+
+        // Construct a transaction proposal from Alice to Bob
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "build_transaction",
+            "params": {
+                "account_id": alice_account_id,
+                "recipient_public_address": bob_b58_public_address,
+                "value_pmob": "42000000000000", // 42 MOB
+            }
+        });
+        let res = dispatch(&client, body, &logger);
+        let result = res.get("result").unwrap();
+        let tx_proposal = result.get("tx_proposal").unwrap();
+
+        // Get the receipts from the tx_proposal
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "create_receiver_receipts",
+            "params": {
+                "tx_proposal": tx_proposal
+            }
+        });
+        let res = dispatch(&client, body, &logger);
+        let result = res.get("result").unwrap();
+        let receipts = result["receiver_receipts"].as_array().unwrap();
+        assert_eq!(receipts.len(), 1);
+        let receipt = &receipts[0];
+
+        // Bob checks status (should be pending before the block is added to the ledger)
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "check_receiver_receipt_status",
+            "params": {
+                "address": bob_b58_public_address,
+                "receiver_receipt": receipt,
+            }
+        });
+        let res = dispatch(&client, body, &logger);
+        let result = res.get("result").unwrap();
+        let status = result["receipt_transaction_status"].as_str().unwrap();
+        assert_eq!(status, "TransactionPending");
+
+        // Add the block to the ledger with the tx proposal
+        let json_tx_proposal: json_rpc::tx_proposal::TxProposal =
+            serde_json::from_value(tx_proposal.clone()).unwrap();
+        let payments_tx_proposal =
+            mc_mobilecoind::payments::TxProposal::try_from(&json_tx_proposal).unwrap();
+
+        // The MockBlockchainConnection does not write to the ledger_db
+        add_block_with_tx_proposal(&mut ledger_db, payments_tx_proposal);
+
+        manually_sync_account(
+            &ledger_db,
+            &db_ctx.get_db_instance(logger.clone()),
+            &AccountID(alice_account_id.to_string()),
+            &logger,
+        );
+        manually_sync_account(
+            &ledger_db,
+            &db_ctx.get_db_instance(logger.clone()),
+            &AccountID(bob_account_id.to_string()),
+            &logger,
+        );
+
+        // Bob checks status (should be successful after added to the ledger)
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "check_receiver_receipt_status",
+            "params": {
+                "address": bob_b58_public_address,
+                "receiver_receipt": receipt,
+            }
+        });
+        let res = dispatch(&client, body, &logger);
+        let result = res.get("result").unwrap();
+        let status = result["receipt_transaction_status"].as_str().unwrap();
+        assert_eq!(status, "TransactionSuccess");
+    }
+
+    #[test_with_logger]
+    fn test_gift_codes(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+        let (client, mut ledger_db, db_ctx, _network_state) = setup(&mut rng, logger.clone());
+
+        // Add an account
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "create_account",
+            "params": {
+                "name": "Alice Main Account",
+            }
+        });
+        let res = dispatch(&client, body, &logger);
+        let result = res.get("result").unwrap();
+        let account_obj = result.get("account").unwrap();
+        let alice_account_id = account_obj.get("account_id").unwrap().as_str().unwrap();
+        let alice_b58_public_address = account_obj.get("main_address").unwrap().as_str().unwrap();
+        let alice_public_address = b58_decode_public_address(alice_b58_public_address).unwrap();
+
+        // Add a block with a txo for this address
+        add_block_to_ledger_db(
+            &mut ledger_db,
+            &vec![alice_public_address],
+            100 * MOB,
+            &vec![KeyImage::from(rng.next_u64())],
+            &mut rng,
+        );
+
+        manually_sync_account(
+            &ledger_db,
+            &db_ctx.get_db_instance(logger.clone()),
+            &AccountID(alice_account_id.to_string()),
+            &logger,
+        );
+        // Create a gift code
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "build_gift_code",
+            "params": {
+                "account_id": alice_account_id,
+                "value_pmob": "42000000000000",
+                "memo": "Happy Birthday!",
+            }
+        });
+        let res = dispatch(&client, body, &logger);
+        let result = res["result"].clone();
+        let gift_code_b58 = result["gift_code_b58"].as_str().unwrap();
+        let tx_proposal = result["tx_proposal"].clone();
+
+        // Check the status of the gift code
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "check_gift_code_status",
+            "params": {
+                "gift_code_b58": gift_code_b58,
+            }
+        });
+        let res = dispatch(&client, body, &logger);
+        let status = res["result"]["gift_code_status"].as_str().unwrap();
+        assert_eq!(status, "GiftCodeSubmittedPending");
+        let memo = res["result"]["gift_code_memo"].as_str().unwrap();
+        assert_eq!(memo, "Happy Birthday!");
+
+        // Submit the gift code and tx proposal
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "submit_gift_code",
+            "params": {
+                "from_account_id": alice_account_id,
+                "gift_code_b58": gift_code_b58,
+                "tx_proposal": tx_proposal,
+            }
+        });
+        dispatch(&client, body, &logger);
+
+        // Add the TxProposal for the gift code
+        let json_tx_proposal: json_rpc::tx_proposal::TxProposal =
+            serde_json::from_value(tx_proposal.clone()).unwrap();
+        let payments_tx_proposal =
+            mc_mobilecoind::payments::TxProposal::try_from(&json_tx_proposal).unwrap();
+
+        // The MockBlockchainConnection does not write to the ledger_db
+        add_block_with_tx_proposal(&mut ledger_db, payments_tx_proposal);
+
+        manually_sync_account(
+            &ledger_db,
+            &db_ctx.get_db_instance(logger.clone()),
+            &AccountID(alice_account_id.to_string()),
+            &logger,
+        );
+
+        // Check the status of the gift code
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "check_gift_code_status",
+            "params": {
+                "gift_code_b58": gift_code_b58,
+            }
+        });
+        let res = dispatch(&client, body, &logger);
+        let status = res["result"]["gift_code_status"].as_str().unwrap();
+        assert_eq!(status, "GiftCodeAvailable");
+        let memo = res["result"]["gift_code_memo"].as_str().unwrap();
+        assert_eq!(memo, "Happy Birthday!");
+
+        // Add Bob's account to our wallet
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "create_account",
+            "params": {
+                "name": "Bob Main Account",
+            }
+        });
+        let res = dispatch(&client, body, &logger);
+        let result = res.get("result").unwrap();
+        let bob_account_obj = result.get("account").unwrap();
+        let bob_account_id = bob_account_obj.get("account_id").unwrap().as_str().unwrap();
+
+        manually_sync_account(
+            &ledger_db,
+            &db_ctx.get_db_instance(logger.clone()),
+            &AccountID(bob_account_id.to_string()),
+            &logger,
+        );
+
+        // Get all the gift codes in the wallet
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "get_all_gift_codes",
+        });
+        let res = dispatch(&client, body, &logger);
+        let result = res["result"]["gift_codes"].as_array().unwrap();
+        assert_eq!(result.len(), 1);
+
+        // Get the specific gift code
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "get_gift_code",
+            "params": {
+                "gift_code_b58": gift_code_b58,
+            }
+        });
+        dispatch(&client, body, &logger);
+
+        // Claim the gift code for bob
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "claim_gift_code",
+            "params": {
+                "account_id": bob_account_id,
+                "gift_code_b58": gift_code_b58,
+            }
+        });
+        let res = dispatch(&client, body, &logger);
+        let txo_id_hex = res["result"]["txo_id"].as_str().unwrap();
+        assert_eq!(txo_id_hex.len(), 64);
+
+        // Now remove that gift code
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "remove_gift_code",
+            "params": {
+                "gift_code_b58": gift_code_b58,
+            }
+        });
+        let res = dispatch(&client, body, &logger);
+        let result = res["result"]["removed"].as_bool().unwrap();
+        assert!(result);
+
+        // Get all the gift codes in the wallet again, should be 0 now
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "get_all_gift_codes",
+        });
+        let res = dispatch(&client, body, &logger);
+        let result = res["result"]["gift_codes"].as_array().unwrap();
+        assert_eq!(result.len(), 0);
+    }
+
+    #[test_with_logger]
+    fn test_request_with_correct_api_key(logger: Logger) {
+        let api_key = "mobilecats";
+
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+        let (client, _ledger_db, _db_ctx, _network_state) = setup_with(
+            &mut rng,
+            logger.clone(),
+            TestSetupOptions {
+                api_key: api_key.to_string(),
+                ..Default::default()
+            },
+        );
+
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "create_account",
+            "params": {
+                "name": "Alice Main Account",
+            },
+        });
+
+        let header = Header::new("X-API-KEY", api_key);
+
+        dispatch_with_header(&client, body, header, &logger);
+    }
+
+    #[test_with_logger]
+    fn test_request_with_bad_api_key(logger: Logger) {
+        let api_key = "mobilecats";
+
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+        let (client, _ledger_db, _db_ctx, _network_state) = setup_with(
+            &mut rng,
+            logger.clone(),
+            TestSetupOptions {
+                api_key: api_key.to_string(),
+                ..Default::default()
+            },
+        );
+
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "create_account",
+            "params": {
+                "name": "Alice Main Account",
+            },
+        });
+
+        let header = Header::new("X-API-KEY", "wrong-header");
+
+        dispatch_with_header_expect_error(
+            &client,
+            body.clone(),
+            header,
+            &logger,
+            Status::Unauthorized,
+        );
+
+        // A missing key is rejected too, with an error JSON-RPC clients can
+        // handle.
+        let mut res = client
+            .post("/wallet")
+            .header(ContentType::JSON)
+            .body(body.to_string())
+            .dispatch();
+        assert_eq!(res.status(), Status::Unauthorized);
+        let res: serde_json::Value =
+            serde_json::from_str(&res.body().unwrap().into_string().unwrap()).unwrap();
+        assert_eq!(res["jsonrpc"], "2.0");
+        assert_eq!(res["error"]["code"], -32001);
+        assert_eq!(res["error"]["message"], "Unauthorized");
+        assert!(res.get("result").is_none());
+    }
+
+    #[test_with_logger]
+    fn test_rotate_empty_account(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+        let (client, _ledger_db, _db_ctx, _network_state) = setup(&mut rng, logger.clone());
+
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "create_account",
+            "params": {
+                "name": "Alice Main Account",
+            },
+        });
+        let res = dispatch(&client, body, &logger);
+        let account_id = res["result"]["account"]["account_id"].as_str().unwrap();
+        assert_eq!(res["result"]["account"]["archived"], false);
+
+        // With nothing to sweep, the rotation completes straight away.
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "rotate_account",
+            "params": {
+                "account_id": account_id,
+                "new_account_name": "Alice Rotated Account",
+            },
+        });
+        let res = dispatch(&client, body, &logger);
+        let rotation = &res["result"]["account_rotation"];
+        assert_eq!(rotation["account_id"], account_id);
+        assert_eq!(rotation["status"], "completed");
+        assert_eq!(rotation["sweep_transaction_log_ids"], json!([]));
+        let new_account_id = rotation["new_account_id"].as_str().unwrap();
+
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "get_account_rotation_status",
+            "params": {
+                "account_id": account_id,
+            },
+        });
+        let res = dispatch(&client, body, &logger);
+        assert_eq!(res["result"]["account_rotation"]["status"], "completed");
+
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "get_account",
+            "params": {
+                "account_id": account_id,
+            },
+        });
+        let res = dispatch(&client, body, &logger);
+        assert_eq!(res["result"]["account"]["archived"], true);
+
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "get_account",
+            "params": {
+                "account_id": new_account_id,
+            },
+        });
+        let res = dispatch(&client, body, &logger);
+        assert_eq!(res["result"]["account"]["name"], "Alice Rotated Account");
+        assert_eq!(res["result"]["account"]["archived"], false);
+    }
+
+    #[test_with_logger]
+    fn test_account_sweep_policy(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+        let (client, _ledger_db, _db_ctx, _network_state) = setup(&mut rng, logger.clone());
+
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "create_account",
+            "params": {
+                "name": "Alice Main Account",
+            },
+        });
+        let res = dispatch(&client, body, &logger);
+        let account_id = res["result"]["account"]["account_id"].as_str().unwrap();
+
+        let cold_address =
+            b58_encode_public_address(&AccountKey::random(&mut rng).default_subaddress()).unwrap();
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "set_account_sweep_policy",
+            "params": {
+                "account_id": account_id,
+                "destination_address": cold_address,
+                "threshold": "1000000000000",
+                "interval_seconds": "86400",
+            },
+        });
+        let res = dispatch(&client, body, &logger);
+        let sweep_policy = &res["result"]["sweep_policy"];
+        assert_eq!(sweep_policy["account_id"], account_id);
+        assert_eq!(sweep_policy["destination_address"], cold_address);
+        assert_eq!(sweep_policy["token_id"], "0");
+        assert_eq!(sweep_policy["threshold"], "1000000000000");
+        assert_eq!(sweep_policy["interval_seconds"], "86400");
+        assert_eq!(sweep_policy["last_run_time"], serde_json::Value::Null);
+
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "get_account_sweep_policy",
+            "params": {
+                "account_id": account_id,
+            },
+        });
+        let res = dispatch(&client, body.clone(), &logger);
+        assert_eq!(res["result"]["sweep_policy"], *sweep_policy);
+
+        // The destination must be a valid address.
+        let body_invalid = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "set_account_sweep_policy",
+            "params": {
+                "account_id": account_id,
+                "destination_address": "not an address",
+                "threshold": "0",
+                "interval_seconds": "86400",
+            },
+        });
+        let res = dispatch(&client, body_invalid, &logger);
+        assert!(res.get("result").is_none());
+
+        let body_remove = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "remove_account_sweep_policy",
+            "params": {
+                "account_id": account_id,
+            },
+        });
+        let res = dispatch(&client, body_remove, &logger);
+        assert_eq!(res["result"]["removed"], true);
+
+        let res = dispatch(&client, body, &logger);
+        assert!(res.get("result").is_none());
+    }
+
+    #[test_with_logger]
+    fn test_scheduled_payments(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+        let (client, _ledger_db, _db_ctx, _network_state) = setup(&mut rng, logger.clone());
+
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "create_account",
+            "params": {
+                "name": "Alice Main Account",
+            },
+        });
+        let res = dispatch(&client, body, &logger);
+        let account_id = res["result"]["account"]["account_id"].as_str().unwrap();
+
+        let bob_address =
+            b58_encode_public_address(&AccountKey::random(&mut rng).default_subaddress()).unwrap();
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "create_scheduled_payment",
+            "params": {
+                "account_id": account_id,
+                "recipient_public_address": bob_address,
+                "value": "1000000000000",
+                "block_height": "1000",
+                "comment": "Bonus",
+            },
+        });
+        let res = dispatch(&client, body, &logger);
+        let scheduled_payment = &res["result"]["scheduled_payment"];
+        assert_eq!(scheduled_payment["account_id"], account_id);
+        assert_eq!(scheduled_payment["recipient_public_address"], bob_address);
+        assert_eq!(scheduled_payment["token_id"], "0");
+        assert_eq!(scheduled_payment["block_height"], "1000");
+        assert_eq!(
+            scheduled_payment["interval_seconds"],
+            serde_json::Value::Null
+        );
+        assert_eq!(scheduled_payment["status"], "active");
+        assert_eq!(scheduled_payment["payments_submitted"], "0");
+        let scheduled_payment_id = scheduled_payment["scheduled_payment_id"].as_str().unwrap();
+
+        // A payment needs either an interval or a block height.
+        let body_invalid = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "create_scheduled_payment",
+            "params": {
+                "account_id": account_id,
+                "recipient_public_address": bob_address,
+                "value": "1000000000000",
+            },
+        });
+        let res = dispatch(&client, body_invalid, &logger);
+        assert!(res.get("result").is_none());
+
+        let body_list = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "list_scheduled_payments",
+            "params": {
+                "account_id": account_id,
+            },
+        });
+        let res = dispatch(&client, body_list.clone(), &logger);
+        let scheduled_payments = res["result"]["scheduled_payments"].as_array().unwrap();
+        assert_eq!(scheduled_payments.len(), 1);
+        assert_eq!(
+            scheduled_payments[0]["scheduled_payment_id"],
+            scheduled_payment_id
+        );
+
+        let body_cancel = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "cancel_scheduled_payment",
+            "params": {
+                "scheduled_payment_id": scheduled_payment_id,
+            },
+        });
+        let res = dispatch(&client, body_cancel.clone(), &logger);
+        assert_eq!(res["result"]["scheduled_payment"]["status"], "cancelled");
+
+        // Cancelled payments are still listed, but can't be cancelled again.
+        let res = dispatch(&client, body_list, &logger);
+        assert_eq!(
+            res["result"]["scheduled_payments"][0]["status"],
+            "cancelled"
+        );
+        let res = dispatch(&client, body_cancel, &logger);
+        assert!(res.get("result").is_none());
+    }
+
+    #[test_with_logger]
+    fn test_wallet_settings(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+        let (client, _ledger_db, _db_ctx, _network_state) = setup(&mut rng, logger.clone());
 
-        // Check the status of the gift code
         let body = json!({
             "jsonrpc": "2.0",
             "id": 1,
-            "method": "check_gift_code_status",
-            "params": {
-                "gift_code_b58": gift_code_b58,
-            }
+            "method": "get_settings",
         });
-        let res = dispatch(&client, body, &logger);
-        let status = res["result"]["gift_code_status"].as_str().unwrap();
-        assert_eq!(status, "GiftCodeSubmittedPending");
-        let memo = res["result"]["gift_code_memo"].as_str().unwrap();
-        assert_eq!(memo, "Happy Birthday!");
+        let res = dispatch(&client, body.clone(), &logger);
+        let settings = &res["result"]["settings"];
+        assert_eq!(settings["object"], "wallet_settings");
+        assert_eq!(settings["default_fee_multiplier"], "1");
+        assert_eq!(settings["confirmation_depth"], "1");
+        assert_eq!(settings["dust_threshold"], "0");
 
-        // Submit the gift code and tx proposal
-        let body = json!({
+        let body_update = json!({
             "jsonrpc": "2.0",
             "id": 1,
-            "method": "submit_gift_code",
+            "method": "update_settings",
             "params": {
-                "from_account_id": alice_account_id,
-                "gift_code_b58": gift_code_b58,
-                "tx_proposal": tx_proposal,
-            }
+                "settings": {
+                    "confirmation_depth": "6",
+                    "dust_threshold": "1000000",
+                },
+            },
         });
-        dispatch(&client, body, &logger);
-
-        // Add the TxProposal for the gift code
-        let json_tx_proposal: json_rpc::tx_proposal::TxProposal =
-            serde_json::from_value(tx_proposal.clone()).unwrap();
-        let payments_tx_proposal =
-            mc_mobilecoind::payments::TxProposal::try_from(&json_tx_proposal).unwrap();
-
-        // The MockBlockchainConnection does not write to the ledger_db
-        add_block_with_tx_proposal(&mut ledger_db, payments_tx_proposal);
+        let res = dispatch(&client, body_update, &logger);
+        let settings = res["result"]["settings"].clone();
+        assert_eq!(settings["confirmation_depth"], "6");
+        assert_eq!(settings["dust_threshold"], "1000000");
+        assert_eq!(settings["default_fee_multiplier"], "1");
 
-        manually_sync_account(
-            &ledger_db,
-            &db_ctx.get_db_instance(logger.clone()),
-            &AccountID(alice_account_id.to_string()),
-            &logger,
-        );
+        let res = dispatch(&client, body.clone(), &logger);
+        assert_eq!(res["result"]["settings"], settings);
 
-        // Check the status of the gift code
-        let body = json!({
+        // Unknown settings are rejected, and nothing is changed.
+        let body_invalid = json!({
             "jsonrpc": "2.0",
             "id": 1,
-            "method": "check_gift_code_status",
+            "method": "update_settings",
             "params": {
-                "gift_code_b58": gift_code_b58,
-            }
+                "settings": {
+                    "confirmation_depth": "2",
+                    "fee_pmob": "400000000",
+                },
+            },
         });
+        let res = dispatch(&client, body_invalid, &logger);
+        let error = res["error"]["data"]["details"].as_str().unwrap();
+        assert!(error.contains("Unknown wallet setting: fee_pmob"));
+
         let res = dispatch(&client, body, &logger);
-        let status = res["result"]["gift_code_status"].as_str().unwrap();
-        assert_eq!(status, "GiftCodeAvailable");
-        let memo = res["result"]["gift_code_memo"].as_str().unwrap();
-        assert_eq!(memo, "Happy Birthday!");
+        assert_eq!(res["result"]["settings"], settings);
+    }
 
-        // Add Bob's account to our wallet
+    #[test_with_logger]
+    fn test_request_with_scoped_api_keys(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+        let (client, _ledger_db, _db_ctx, _network_state) = setup_with(
+            &mut rng,
+            logger.clone(),
+            TestSetupOptions {
+                api_key: "mobilecats".to_string(),
+                scoped_api_keys: vec![
+                    ("viewer".to_string(), ApiScope::ViewOnly),
+                    ("signer".to_string(), ApiScope::Signer),
+                ],
+                ..Default::default()
+            },
+        );
+
+        // The signer key may not create accounts.
         let body = json!({
             "jsonrpc": "2.0",
             "id": 1,
             "method": "create_account",
             "params": {
-                "name": "Bob Main Account",
-            }
+                "name": "Alice Main Account",
+            },
         });
-        let res = dispatch(&client, body, &logger);
-        let result = res.get("result").unwrap();
-        let bob_account_obj = result.get("account").unwrap();
-        let bob_account_id = bob_account_obj.get("account_id").unwrap().as_str().unwrap();
-
-        manually_sync_account(
-            &ledger_db,
-            &db_ctx.get_db_instance(logger.clone()),
-            &AccountID(bob_account_id.to_string()),
-            &logger,
-        );
+        let res = dispatch_with_header(&client, body, Header::new("X-API-KEY", "signer"), &logger);
+        assert!(res.get("result").is_none());
+        let error = res["error"]["data"]["details"].as_str().unwrap();
+        assert!(error.contains("does not permit calling create_account"));
 
-        // Get all the gift codes in the wallet
+        // The signer key reaches the methods in its scope, which fail here only
+        // because the account does not exist.
         let body = json!({
             "jsonrpc": "2.0",
             "id": 1,
-            "method": "get_all_gift_codes",
+            "method": "sync_view_only_account",
+            "params": {
+                "account_id": "deadbeef",
+                "completed_txos": [],
+                "subaddresses": [],
+            },
         });
-        let res = dispatch(&client, body, &logger);
-        let result = res["result"]["gift_codes"].as_array().unwrap();
-        assert_eq!(result.len(), 1);
+        let res = dispatch_with_header(&client, body, Header::new("X-API-KEY", "signer"), &logger);
+        let error = res["error"].to_string();
+        assert!(!error.contains("does not permit calling"));
 
-        // Get the specific gift code
+        // The view-only key may list view-only accounts.
         let body = json!({
             "jsonrpc": "2.0",
             "id": 1,
-            "method": "get_gift_code",
-            "params": {
-                "gift_code_b58": gift_code_b58,
-            }
+            "method": "get_all_view_only_accounts",
         });
-        dispatch(&client, body, &logger);
+        let res = dispatch_with_header(&client, body, Header::new("X-API-KEY", "viewer"), &logger);
+        assert!(res.get("result").is_some());
 
-        // Claim the gift code for bob
+        // Exports are outside both scopes.
+        let res = client
+            .post("/wallet/export_txos")
+            .header(ContentType::JSON)
+            .header(Header::new("X-API-KEY", "signer"))
+            .body(json!({"account_id": "deadbeef"}).to_string())
+            .dispatch();
+        assert_eq!(res.status(), Status::Forbidden);
+
+        // The full key still reaches every method.
         let body = json!({
             "jsonrpc": "2.0",
             "id": 1,
-            "method": "claim_gift_code",
+            "method": "create_account",
             "params": {
-                "account_id": bob_account_id,
-                "gift_code_b58": gift_code_b58,
-            }
+                "name": "Alice Main Account",
+            },
         });
-        let res = dispatch(&client, body, &logger);
-        let txo_id_hex = res["result"]["txo_id"].as_str().unwrap();
-        assert_eq!(txo_id_hex.len(), 64);
+        let res = dispatch_with_header(
+            &client,
+            body,
+            Header::new("X-API-KEY", "mobilecats"),
+            &logger,
+        );
+        assert!(res.get("result").is_some());
+    }
+
+    #[test_with_logger]
+    fn test_request_without_api_key_header_when_only_scoped_keys_set(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+        let (client, _ledger_db, _db_ctx, _network_state) = setup_with(
+            &mut rng,
+            logger.clone(),
+            TestSetupOptions {
+                scoped_api_keys: vec![("viewer".to_string(), ApiScope::ViewOnly)],
+                ..Default::default()
+            },
+        );
 
-        // Now remove that gift code
         let body = json!({
             "jsonrpc": "2.0",
             "id": 1,
-            "method": "remove_gift_code",
+            "method": "create_account",
             "params": {
-                "gift_code_b58": gift_code_b58,
-            }
+                "name": "Alice Main Account",
+            },
         });
-        let res = dispatch(&client, body, &logger);
-        let result = res["result"]["removed"].as_bool().unwrap();
-        assert!(result);
 
-        // Get all the gift codes in the wallet again, should be 0 now
+        // A missing key must not match the unset full key.
+        let res = client
+            .post("/wallet")
+            .header(ContentType::JSON)
+            .body(body.to_string())
+            .dispatch();
+        assert_eq!(res.status(), Status::Unauthorized);
+
+        // Nor may an empty one.
+        dispatch_with_header_expect_error(
+            &client,
+            body.clone(),
+            Header::new("X-API-KEY", ""),
+            &logger,
+            Status::Unauthorized,
+        );
+
+        // The scoped key still works within its scope.
         let body = json!({
             "jsonrpc": "2.0",
             "id": 1,
-            "method": "get_all_gift_codes",
+            "method": "get_all_view_only_accounts",
         });
-        let res = dispatch(&client, body, &logger);
-        let result = res["result"]["gift_codes"].as_array().unwrap();
-        assert_eq!(result.len(), 0);
+        let res = dispatch_with_header(&client, body, Header::new("X-API-KEY", "viewer"), &logger);
+        assert!(res.get("result").is_some());
     }
 
     #[test_with_logger]
-    fn test_request_with_correct_api_key(logger: Logger) {
-        let api_key = "mobilecats";
-
+    fn test_request_with_receive_only_preset(logger: Logger) {
         let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
-        let (client, _ledger_db, _db_ctx, _network_state) =
-            setup_with_api_key(&mut rng, logger.clone(), api_key.to_string());
+        let (client, _ledger_db, _db_ctx, _network_state) = setup_with(
+            &mut rng,
+            logger.clone(),
+            TestSetupOptions {
+                api_key: "mobilecats".to_string(),
+                preset: Some(ApiScope::ReceiveOnly),
+                ..Default::default()
+            },
+        );
 
+        // Even the full key may not create accounts.
         let body = json!({
             "jsonrpc": "2.0",
             "id": 1,
@@ -3752,19 +5658,43 @@ mod e2e {
                 "name": "Alice Main Account",
             },
         });
+        let res = dispatch_with_header(
+            &client,
+            body,
+            Header::new("X-API-KEY", "mobilecats"),
+            &logger,
+        );
+        assert!(res.get("result").is_none());
+        let error = res["error"]["data"]["details"].as_str().unwrap();
+        assert!(error.contains("receive_only preset does not permit calling create_account"));
 
-        let header = Header::new("X-API-KEY", api_key);
-
-        dispatch_with_header(&client, body, header, &logger);
+        // Methods needed to process deposits are still allowed.
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "get_all_accounts",
+        });
+        let res = dispatch_with_header(
+            &client,
+            body,
+            Header::new("X-API-KEY", "mobilecats"),
+            &logger,
+        );
+        assert!(res.get("result").is_some());
     }
 
     #[test_with_logger]
-    fn test_request_with_bad_api_key(logger: Logger) {
-        let api_key = "mobilecats";
-
+    fn test_request_with_method_allowlist(logger: Logger) {
         let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
-        let (client, _ledger_db, _db_ctx, _network_state) =
-            setup_with_api_key(&mut rng, logger.clone(), api_key.to_string());
+        let allowlist = MethodAllowlist::parse("get_all_accounts\nversion\n").unwrap();
+        let (client, _ledger_db, _db_ctx, _network_state) = setup_with(
+            &mut rng,
+            logger.clone(),
+            TestSetupOptions {
+                method_allowlist: Some(allowlist),
+                ..Default::default()
+            },
+        );
 
         let body = json!({
             "jsonrpc": "2.0",
@@ -3774,10 +5704,20 @@ mod e2e {
                 "name": "Alice Main Account",
             },
         });
+        let res = dispatch(&client, body, &logger);
+        assert!(res.get("result").is_none());
+        assert_eq!(res["error"]["code"], -32002);
+        assert_eq!(res["error"]["message"], "MethodDisabled");
+        let error = res["error"]["data"]["details"].as_str().unwrap();
+        assert!(error.contains("create_account is not in the wallet's allowlist"));
 
-        let header = Header::new("X-API-KEY", "wrong-header");
-
-        dispatch_with_header_expect_error(&client, body, header, &logger, Status::Unauthorized);
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "get_all_accounts",
+        });
+        let res = dispatch(&client, body, &logger);
+        assert!(res.get("result").is_some());
     }
 
     #[test_with_logger]
@@ -3927,6 +5867,84 @@ mod e2e {
         let account_name = account.get("name").unwrap();
         assert_eq!(name, account_name);
 
+        // test marking the account as hardware backed
+        assert_eq!(account["hardware_backed"], false);
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 2,
+            "method": "update_view_only_account_hardware_backed",
+            "params": {
+                "account_id": account_id,
+                "hardware_backed": true,
+            }
+        });
+        let res = dispatch(&client, body, &logger);
+        assert_eq!(res["result"]["view_only_account"]["hardware_backed"], true);
+
+        // no transaction signer is configured, so nothing can be signed
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 2,
+            "method": "build_sign_and_submit_transaction",
+            "params": {
+                "account_id": account_id,
+                "recipient_public_address": main_address,
+                "value_pmob": "50000000000000",
+            }
+        });
+        let res = dispatch(&client, body, &logger);
+        assert!(res["error"]["data"]["details"]
+            .as_str()
+            .unwrap()
+            .contains("No transaction signer is configured"));
+
+        // a sync round is kept in the account's audit trail
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 2,
+            "method": "create_view_only_account_sync_request",
+            "params": {
+                "account_id": account_id,
+            }
+        });
+        let res = dispatch(&client, body, &logger);
+        let incomplete_txos = res["result"]["incomplete_txos_encoded"]
+            .as_array()
+            .unwrap()
+            .len();
+
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 2,
+            "method": "sync_view_only_account",
+            "params": {
+                "account_id": account_id,
+                "completed_txos": [],
+                "subaddresses": [],
+            }
+        });
+        let res = dispatch(&client, body, &logger);
+        assert!(res.get("error").is_none());
+
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 2,
+            "method": "get_view_only_account_sync_records",
+            "params": {
+                "account_id": account_id,
+            }
+        });
+        let res = dispatch(&client, body, &logger);
+        let sync_records = res["result"]["sync_records"].as_array().unwrap();
+        assert_eq!(sync_records.len(), 2);
+        assert_eq!(sync_records[0]["record_type"], "request");
+        assert_eq!(sync_records[0]["txo_count"], incomplete_txos.to_string());
+        assert_eq!(sync_records[1]["record_type"], "response");
+        assert_eq!(
+            sync_records[1]["request_record_id"],
+            sync_records[0]["sync_record_id"]
+        );
+
         // create new subaddress request
         let body = json!({
             "jsonrpc": "2.0",
@@ -3961,6 +5979,67 @@ mod e2e {
         let result = res.get("result").unwrap();
         let _tx = result.get("unsigned_tx").unwrap();
 
+        // unsigned transactions take the same options as build_transaction
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 2,
+            "method": "build_unsigned_transaction",
+            "params": {
+                "account_id": account_id,
+                "addresses_and_values": [[main_address, "50000000000000"]],
+                "token_id": "0",
+                "payment_request_id": "17",
+                "coin_selection_strategy": "largest-first",
+                "change_values": ["10000000000000"],
+            }
+        });
+        let res = dispatch(&client, body, &logger);
+        let unsigned_tx = &res["result"]["unsigned_tx"];
+        assert_eq!(unsigned_tx["outlays"].as_array().unwrap().len(), 1);
+        assert_eq!(unsigned_tx["token_id"], 0);
+        assert_eq!(unsigned_tx["payment_request_id"], 17);
+        assert_eq!(
+            unsigned_tx["change_outputs"],
+            json!({"Fixed": [10000000000000u64]})
+        );
+
+        // the fixed change can't be more than the inputs hold
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 2,
+            "method": "build_unsigned_transaction",
+            "params": {
+                "account_id": account_id,
+                "addresses_and_values": [[main_address, "50000000000000"]],
+                "change_values": ["60000000000000"],
+            }
+        });
+        let res = dispatch(&client, body, &logger);
+        assert!(res.get("error").is_some());
+
+        // test exporting a signing request
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 2,
+            "method": "export_signing_request",
+            "params": {
+                "account_id": account_id,
+                "recipient_public_address": main_address,
+                "value_pmob": "50000000000000",
+            }
+        });
+        let res = dispatch(&client, body, &logger);
+        let signing_request = &res["result"]["signing_request"];
+        assert_eq!(signing_request["object"], "offline_signing_request");
+        assert_eq!(signing_request["version"], OFFLINE_SIGNING_FORMAT_VERSION);
+        assert_eq!(&signing_request["account_id"], account_id);
+        assert!(
+            !signing_request["unsigned_tx"]["inputs_and_real_indices_and_subaddress_indices"]
+                .as_array()
+                .unwrap()
+                .is_empty()
+        );
+
         // test remove
         let body = json!({
             "jsonrpc": "2.0",
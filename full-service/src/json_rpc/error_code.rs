@@ -0,0 +1,792 @@
+// Copyright (c) 2020-2022 MobileCoin Inc.
+
+//! The codes the wallet's errors are returned with.
+//!
+//! Each error is returned with a numeric code, from the ranges documented on
+//! JsonRPCErrorCodes, so that clients can branch on the kind of error rather
+//! than on its message. Errors which wrap another error take the code of the
+//! one they wrap, so a missing account is reported as AccountNotFound by every
+//! method. Errors without a more specific code are returned as InternalError.
+
+use crate::{
+    db::{gift_code::GiftCodeDbError, txo::InsufficientFundsDetails, WalletDbError},
+    error::{WalletServiceError, WalletTransactionBuilderError},
+    json_rpc::{api_scope::ApiScopeError, json_rpc_response::JsonRPCErrorCodes},
+    service::{
+        account::AccountServiceError, account_recovery::AccountRecoveryServiceError,
+        account_rotation::AccountRotationServiceError,
+        account_snapshot::AccountSnapshotServiceError, address::AddressServiceError,
+        address_label::AddressLabelServiceError, balance::BalanceServiceError,
+        confirmation_number::ConfirmationServiceError, continuity::ContinuityServiceError,
+        database_password::DatabasePasswordServiceError, deposit::DepositServiceError,
+        gift_code::GiftCodeServiceError, isolated_counterparty::IsolatedCounterpartyServiceError,
+        ledger::LedgerServiceError, ledger_db::LedgerDbServiceError,
+        log_digest::LogDigestServiceError, maintenance::MaintenanceServiceError,
+        payment_request::PaymentRequestServiceError, payout::PayoutServiceError,
+        receipt::ReceiptServiceError, reporting::ReportingServiceError,
+        scheduled_payment::ScheduledPaymentServiceError, spending_lock::SpendingLockServiceError,
+        sweep_policy::SweepPolicyServiceError, sync_status::SyncStatusServiceError,
+        transaction::TransactionServiceError, transaction_log::TransactionLogServiceError,
+        transaction_watch::TransactionWatchServiceError, txo::TxoServiceError,
+        upgrade_readiness::UpgradeReadinessServiceError, usage_stats::UsageStatsServiceError,
+        wallet_settings::WalletSettingsServiceError, webhook::WebhookServiceError,
+    },
+    sync_proof::SyncProofError,
+    util::{
+        amount_conversion::AmountConversionError, b58::errors::B58Error,
+        pagination::PageCursorError,
+    },
+};
+use std::any::Any;
+
+/// An error which is returned to clients with a code.
+pub trait JsonRPCErrorCode {
+    /// The code the error is returned with.
+    fn error_code(&self) -> JsonRPCErrorCodes;
+
+    /// What the account was short of, if the error is for insufficient funds.
+    fn insufficient_funds(&self) -> Option<&InsufficientFundsDetails> {
+        None
+    }
+}
+
+/// The code for an error passed to format_error, which may be of any type.
+pub fn error_code_of(e: &dyn Any) -> JsonRPCErrorCodes {
+    if let Some(e) = as_json_rpc_error(e) {
+        return e.error_code();
+    }
+    if e.is::<hex::FromHexError>() {
+        return JsonRPCErrorCodes::InvalidParams;
+    }
+    JsonRPCErrorCodes::InternalError
+}
+
+/// What the account was short of, for an error passed to format_error.
+pub fn insufficient_funds_of(e: &dyn Any) -> Option<&InsufficientFundsDetails> {
+    as_json_rpc_error(e).and_then(|e| e.insufficient_funds())
+}
+
+/// The error passed to format_error, if it is one of the wallet's.
+fn as_json_rpc_error(e: &dyn Any) -> Option<&dyn JsonRPCErrorCode> {
+    macro_rules! code_if_type {
+        ($($error_type:ty),* $(,)?) => {
+            $(
+                if let Some(e) = e.downcast_ref::<$error_type>() {
+                    return Some(e);
+                }
+            )*
+        };
+    }
+
+    code_if_type!(
+        WalletServiceError,
+        WalletDbError,
+        WalletTransactionBuilderError,
+        GiftCodeDbError,
+        B58Error,
+        AmountConversionError,
+        SyncProofError,
+        PageCursorError,
+        ApiScopeError,
+        AccountServiceError,
+        AccountRecoveryServiceError,
+        AccountRotationServiceError,
+        AccountSnapshotServiceError,
+        AddressServiceError,
+        AddressLabelServiceError,
+        BalanceServiceError,
+        ConfirmationServiceError,
+        ContinuityServiceError,
+        DatabasePasswordServiceError,
+        DepositServiceError,
+        GiftCodeServiceError,
+        IsolatedCounterpartyServiceError,
+        LedgerServiceError,
+        LedgerDbServiceError,
+        LogDigestServiceError,
+        MaintenanceServiceError,
+        PaymentRequestServiceError,
+        PayoutServiceError,
+        ReceiptServiceError,
+        ReportingServiceError,
+        ScheduledPaymentServiceError,
+        SpendingLockServiceError,
+        SweepPolicyServiceError,
+        SyncStatusServiceError,
+        TransactionServiceError,
+        TransactionLogServiceError,
+        TransactionWatchServiceError,
+        TxoServiceError,
+        UpgradeReadinessServiceError,
+        UsageStatsServiceError,
+        WalletSettingsServiceError,
+        WebhookServiceError,
+    );
+    None
+}
+
+impl JsonRPCErrorCode for ApiScopeError {
+    fn error_code(&self) -> JsonRPCErrorCodes {
+        match self {
+            Self::MethodNotAllowlisted(_) => JsonRPCErrorCodes::MethodDisabled,
+            Self::MethodNotPermitted(_, _) | Self::MethodDisabledByPreset(_, _) => {
+                JsonRPCErrorCodes::InvalidRequest
+            }
+        }
+    }
+}
+
+impl JsonRPCErrorCode for WalletDbError {
+    fn error_code(&self) -> JsonRPCErrorCodes {
+        match self {
+            Self::AccountAlreadyExists(_) | Self::ViewOnlyAccountAlreadyExists(_) => {
+                JsonRPCErrorCodes::AccountAlreadyExists
+            }
+            Self::DatabaseUnavailable | Self::DatabaseBusy => JsonRPCErrorCodes::DatabaseBusy,
+            Self::DatabaseAlreadyEncrypted
+            | Self::DatabaseNotEncrypted
+            | Self::IncorrectDatabasePassword
+            | Self::EmptyDatabasePassword
+            | Self::DatabaseEncryptionUnsupported => JsonRPCErrorCodes::DatabasePasswordError,
+            Self::DuplicateEntries(_) | Self::SubaddressAlreadyAssigned(_) => {
+                JsonRPCErrorCodes::RecordAlreadyExists
+            }
+            Self::AccountNotFound(_) => JsonRPCErrorCodes::AccountNotFound,
+            Self::AssignedSubaddressNotFound(_) => JsonRPCErrorCodes::AddressNotFound,
+            Self::TxoNotFound(_) => JsonRPCErrorCodes::TxoNotFound,
+            Self::TransactionLogNotFound(_) => JsonRPCErrorCodes::TransactionLogNotFound,
+            Self::TransactionWatchNotFound(_)
+            | Self::AccountRotationNotFound(_)
+            | Self::SweepPolicyNotFound(_)
+            | Self::QuarantinedBlockNotFound(_)
+            | Self::WebhookNotFound(_)
+            | Self::ScheduledPaymentNotFound(_)
+            | Self::PaymentRequestNotFound(_)
+            | Self::AccountTxoStatusNotFound(_) => JsonRPCErrorCodes::RecordNotFound,
+            Self::UnknownSetting(_) | Self::InvalidSettingValue(_, _) => {
+                JsonRPCErrorCodes::InvalidSetting
+            }
+            Self::NoSpendableTxos
+            | Self::InsufficientFunds(_)
+            | Self::InsufficientFundsUnderMaxSpendable(_) => JsonRPCErrorCodes::InsufficientFunds,
+            Self::InsufficientFundsFragmentedTxos(_) => JsonRPCErrorCodes::FragmentedTxos,
+            Self::TransactionLacksRecipient | Self::TransactionValueExceedsMax => {
+                JsonRPCErrorCodes::InvalidTransaction
+            }
+            Self::AccountSecretsDoNotMatch
+            | Self::InsufficientSecretsToCreateAccount
+            | Self::Slip10Key(_) => JsonRPCErrorCodes::InvalidAccountSecrets,
+            Self::SubaddressesNotSupportedForFOGEnabledAccounts => {
+                JsonRPCErrorCodes::InvalidAccountState
+            }
+            Self::B58Decode
+            | Self::Base64Decode(_)
+            | Self::TransactionWatchLacksOutputs
+            | Self::SubaddressIndexOutOfRange(_) => JsonRPCErrorCodes::InvalidParams,
+            Self::B58(e) => e.error_code(),
+            Self::GiftCode(e) => e.error_code(),
+            Self::LedgerDB(_) => JsonRPCErrorCodes::LedgerDbError,
+            _ => JsonRPCErrorCodes::DatabaseError,
+        }
+    }
+
+    fn insufficient_funds(&self) -> Option<&InsufficientFundsDetails> {
+        match self {
+            Self::InsufficientFundsFragmentedTxos(details)
+            | Self::InsufficientFundsUnderMaxSpendable(details) => Some(details),
+            _ => None,
+        }
+    }
+}
+
+impl JsonRPCErrorCode for GiftCodeDbError {
+    fn error_code(&self) -> JsonRPCErrorCodes {
+        match self {
+            Self::GiftCodeNotFound(_) => JsonRPCErrorCodes::GiftCodeNotFound,
+            Self::GiftCodeAlreadyExists(_) => JsonRPCErrorCodes::RecordAlreadyExists,
+        }
+    }
+}
+
+impl JsonRPCErrorCode for B58Error {
+    fn error_code(&self) -> JsonRPCErrorCodes {
+        JsonRPCErrorCodes::InvalidParams
+    }
+}
+
+impl JsonRPCErrorCode for AmountConversionError {
+    fn error_code(&self) -> JsonRPCErrorCodes {
+        JsonRPCErrorCodes::InvalidParams
+    }
+}
+
+impl JsonRPCErrorCode for SyncProofError {
+    fn error_code(&self) -> JsonRPCErrorCodes {
+        JsonRPCErrorCodes::InvalidParams
+    }
+}
+
+impl JsonRPCErrorCode for PageCursorError {
+    fn error_code(&self) -> JsonRPCErrorCodes {
+        JsonRPCErrorCodes::InvalidParams
+    }
+}
+
+impl JsonRPCErrorCode for WalletTransactionBuilderError {
+    fn error_code(&self) -> JsonRPCErrorCodes {
+        match self {
+            Self::InsufficientFunds(_)
+            | Self::InsufficientInputFunds(_)
+            | Self::InsufficientTxOuts => JsonRPCErrorCodes::InsufficientFunds,
+            Self::NoRecipient
+            | Self::InvalidArgument(_)
+            | Self::NoInputs
+            | Self::OutboundValueTooLarge
+            | Self::TombstoneNotSet
+            | Self::TxoFrozen(_)
+            | Self::MixedTokenFee(_, _, _) => JsonRPCErrorCodes::InvalidTransaction,
+            Self::InsufficientFee(_) => JsonRPCErrorCodes::InsufficientFee,
+            Self::FogError(_) | Self::FogPubkeyResolver(_) | Self::UriParse(_) => {
+                JsonRPCErrorCodes::FogError
+            }
+            Self::UnsupportedOfflineSigningVersion(_, _)
+            | Self::InvalidSigningRequest(_)
+            | Self::InvalidSignedTransaction(_) => JsonRPCErrorCodes::InvalidOfflineSigning,
+            Self::WalletDb(e) => e.error_code(),
+            Self::B58(e) => e.error_code(),
+            Self::LedgerDB(_) => JsonRPCErrorCodes::LedgerDbError,
+            Self::Diesel(_) => JsonRPCErrorCodes::DatabaseError,
+            _ => JsonRPCErrorCodes::TransactionBuilderError,
+        }
+    }
+
+    fn insufficient_funds(&self) -> Option<&InsufficientFundsDetails> {
+        match self {
+            Self::WalletDb(e) => e.insufficient_funds(),
+            _ => None,
+        }
+    }
+}
+
+impl JsonRPCErrorCode for WalletServiceError {
+    fn error_code(&self) -> JsonRPCErrorCodes {
+        match self {
+            Self::Database(e) => e.error_code(),
+            Self::HexDecode(_) | Self::U64Parse => JsonRPCErrorCodes::InvalidParams,
+            Self::LedgerDB(_) => JsonRPCErrorCodes::LedgerDbError,
+            Self::Diesel(_) => JsonRPCErrorCodes::DatabaseError,
+            Self::TransactionService(e) => e.error_code(),
+            Self::BalanceService(e) => e.error_code(),
+            Self::LedgerService(e) => e.error_code(),
+            Self::TxoService(e) => e.error_code(),
+            Self::ConfirmationService(e) => e.error_code(),
+            Self::TransactionLogService(e) => e.error_code(),
+            Self::GiftCodeService(e) => e.error_code(),
+            Self::AccountService(e) => e.error_code(),
+            Self::PaymentRequestService(e) => e.error_code(),
+            _ => JsonRPCErrorCodes::InternalError,
+        }
+    }
+
+    fn insufficient_funds(&self) -> Option<&InsufficientFundsDetails> {
+        match self {
+            Self::Database(e) => e.insufficient_funds(),
+            Self::TransactionService(e) => e.insufficient_funds(),
+            Self::TxoService(e) => e.insufficient_funds(),
+            Self::GiftCodeService(e) => e.insufficient_funds(),
+            _ => None,
+        }
+    }
+}
+
+impl JsonRPCErrorCode for AccountServiceError {
+    fn error_code(&self) -> JsonRPCErrorCodes {
+        match self {
+            Self::Database(e) => e.error_code(),
+            Self::LedgerService(e) => e.error_code(),
+            Self::LedgerDB(_) => JsonRPCErrorCodes::LedgerDbError,
+            Self::Diesel(_) => JsonRPCErrorCodes::DatabaseError,
+            Self::UnknownKeyDerivation(_)
+            | Self::InvalidMnemonic(_)
+            | Self::Base64DecodeError(_)
+            | Self::DecodePrivateKeyError(_)
+            | Self::NoMnemonic(_) => JsonRPCErrorCodes::InvalidAccountSecrets,
+            Self::AccountNotImporting(_) => JsonRPCErrorCodes::InvalidAccountState,
+            Self::HexDecode(_)
+            | Self::BatchImport(_, _)
+            | Self::InvalidSubaddresses(_)
+            | Self::RescanFromUnsyncedBlock(_, _) => JsonRPCErrorCodes::InvalidParams,
+        }
+    }
+}
+
+impl JsonRPCErrorCode for AccountRecoveryServiceError {
+    fn error_code(&self) -> JsonRPCErrorCodes {
+        match self {
+            Self::Database(e) => e.error_code(),
+            Self::AccountService(e) => e.error_code(),
+            Self::B58(e) => e.error_code(),
+            Self::LedgerDB(_) => JsonRPCErrorCodes::LedgerDbError,
+            Self::InvalidMnemonic | Self::KeyDerivation(_) => {
+                JsonRPCErrorCodes::InvalidAccountSecrets
+            }
+            Self::UnknownCandidate(_) => JsonRPCErrorCodes::InvalidParams,
+            Self::AlreadyImported(_) => JsonRPCErrorCodes::AccountAlreadyExists,
+        }
+    }
+}
+
+impl JsonRPCErrorCode for AccountRotationServiceError {
+    fn error_code(&self) -> JsonRPCErrorCodes {
+        match self {
+            Self::Database(e) => e.error_code(),
+            Self::AccountService(e) => e.error_code(),
+            Self::B58(e) => e.error_code(),
+            Self::LedgerDB(_) => JsonRPCErrorCodes::LedgerDbError,
+            Self::Diesel(_) => JsonRPCErrorCodes::DatabaseError,
+            Self::AlreadyRotated(_) | Self::AccountArchived(_) => {
+                JsonRPCErrorCodes::InvalidAccountState
+            }
+            Self::Decode(_) => JsonRPCErrorCodes::InternalError,
+        }
+    }
+}
+
+impl JsonRPCErrorCode for AccountSnapshotServiceError {
+    fn error_code(&self) -> JsonRPCErrorCodes {
+        match self {
+            Self::Database(e) => e.error_code(),
+            Self::Diesel(_) => JsonRPCErrorCodes::DatabaseError,
+        }
+    }
+}
+
+impl JsonRPCErrorCode for AddressServiceError {
+    fn error_code(&self) -> JsonRPCErrorCodes {
+        match self {
+            Self::Database(e) => e.error_code(),
+            Self::Diesel(_) => JsonRPCErrorCodes::DatabaseError,
+            Self::InvalidAddressCount(_, _) => JsonRPCErrorCodes::InvalidParams,
+        }
+    }
+}
+
+impl JsonRPCErrorCode for AddressLabelServiceError {
+    fn error_code(&self) -> JsonRPCErrorCodes {
+        match self {
+            Self::Database(e) => e.error_code(),
+            Self::Diesel(_) => JsonRPCErrorCodes::DatabaseError,
+            Self::Json(_) => JsonRPCErrorCodes::InternalError,
+            Self::UnknownFormat(_)
+            | Self::InvalidFile(_)
+            | Self::EmptyFile
+            | Self::TooManyRows(_, _)
+            | Self::InvalidRow(_, _) => JsonRPCErrorCodes::InvalidParams,
+        }
+    }
+}
+
+impl JsonRPCErrorCode for BalanceServiceError {
+    fn error_code(&self) -> JsonRPCErrorCodes {
+        match self {
+            Self::Database(e) => e.error_code(),
+            Self::NetworkBlockHeight(e) => e.error_code(),
+            Self::LedgerDB(_) => JsonRPCErrorCodes::LedgerDbError,
+            Self::Diesel(_) | Self::UnexpectedAccountTxoStatus(_) => {
+                JsonRPCErrorCodes::DatabaseError
+            }
+        }
+    }
+}
+
+impl JsonRPCErrorCode for ConfirmationServiceError {
+    fn error_code(&self) -> JsonRPCErrorCodes {
+        match self {
+            Self::Database(e) => e.error_code(),
+            Self::TxoService(e) => e.error_code(),
+            Self::TransactionLogService(e) => e.error_code(),
+            Self::LedgerDB(_) => JsonRPCErrorCodes::LedgerDbError,
+            Self::Diesel(_) => JsonRPCErrorCodes::DatabaseError,
+            Self::ProstDecode(_) | Self::HexDecode(_) => JsonRPCErrorCodes::InvalidParams,
+            Self::MissingConfirmation(_) => JsonRPCErrorCodes::RecordNotFound,
+        }
+    }
+}
+
+impl JsonRPCErrorCode for ContinuityServiceError {
+    fn error_code(&self) -> JsonRPCErrorCodes {
+        match self {
+            Self::Database(e) => e.error_code(),
+            Self::AccountService(e) => e.error_code(),
+            Self::NotConfigured => JsonRPCErrorCodes::InvalidRequest,
+            Self::Export(_) | Self::Json(_) | Self::Encryption(_) | Self::Io(_) => {
+                JsonRPCErrorCodes::InternalError
+            }
+        }
+    }
+}
+
+impl JsonRPCErrorCode for DatabasePasswordServiceError {
+    fn error_code(&self) -> JsonRPCErrorCodes {
+        match self {
+            Self::Database(e) => e.error_code(),
+        }
+    }
+}
+
+impl JsonRPCErrorCode for DepositServiceError {
+    fn error_code(&self) -> JsonRPCErrorCodes {
+        match self {
+            Self::Database(e) => e.error_code(),
+        }
+    }
+}
+
+impl JsonRPCErrorCode for GiftCodeServiceError {
+    fn error_code(&self) -> JsonRPCErrorCodes {
+        match self {
+            Self::Database(e) => e.error_code(),
+            Self::TransactionService(e) => e.error_code(),
+            Self::AccountService(e) => e.error_code(),
+            Self::AddressService(e) => e.error_code(),
+            Self::B58(e) => e.error_code(),
+            Self::LedgerDB(_) => JsonRPCErrorCodes::LedgerDbError,
+            Self::Diesel(_) => JsonRPCErrorCodes::DatabaseError,
+            Self::HexDecode(_)
+            | Self::ProstDecode(_)
+            | Self::PrintableWrapper(_)
+            | Self::InvalidExpiry => JsonRPCErrorCodes::InvalidParams,
+            Self::AccountNotFound => JsonRPCErrorCodes::AccountNotFound,
+            Self::InsufficientValueForFee(_) => JsonRPCErrorCodes::InsufficientFee,
+            Self::TxoNotConsumable
+            | Self::GiftCodeTxoNotInLedger(_)
+            | Self::GiftCodeClaimed
+            | Self::GiftCodeNotYetAvailable
+            | Self::GiftCodeRemoved => JsonRPCErrorCodes::GiftCodeNotClaimable,
+            Self::BuildGiftCodeFailed | Self::TxBuilder(_) => {
+                JsonRPCErrorCodes::TransactionBuilderError
+            }
+            Self::UriParse(_) | Self::FogPubkeyResolver(_) | Self::InvalidFogUri(_) => {
+                JsonRPCErrorCodes::FogError
+            }
+            Self::NodeNotFound => JsonRPCErrorCodes::NoPeers,
+            Self::Connection(_) => JsonRPCErrorCodes::ConnectionError,
+            Self::SpendingDisabled => JsonRPCErrorCodes::SpendingDisabled,
+            _ => JsonRPCErrorCodes::InternalError,
+        }
+    }
+
+    fn insufficient_funds(&self) -> Option<&InsufficientFundsDetails> {
+        match self {
+            Self::Database(e) => e.insufficient_funds(),
+            Self::TransactionService(e) => e.insufficient_funds(),
+            _ => None,
+        }
+    }
+}
+
+impl JsonRPCErrorCode for IsolatedCounterpartyServiceError {
+    fn error_code(&self) -> JsonRPCErrorCodes {
+        match self {
+            Self::Database(e) => e.error_code(),
+            Self::Diesel(_) => JsonRPCErrorCodes::DatabaseError,
+            Self::B58(e) => e.error_code(),
+        }
+    }
+}
+
+impl JsonRPCErrorCode for LedgerServiceError {
+    fn error_code(&self) -> JsonRPCErrorCodes {
+        match self {
+            Self::Database(e) => e.error_code(),
+            Self::LedgerDB(_) => JsonRPCErrorCodes::LedgerDbError,
+            _ => JsonRPCErrorCodes::InternalError,
+        }
+    }
+}
+
+impl JsonRPCErrorCode for LedgerDbServiceError {
+    fn error_code(&self) -> JsonRPCErrorCodes {
+        match self {
+            Self::Io(_) => JsonRPCErrorCodes::LedgerDbError,
+            Self::MaxSizeTooLarge(_, _)
+            | Self::AlreadyExists(_)
+            | Self::InsufficientSpace(_, _, _) => JsonRPCErrorCodes::InvalidParams,
+            Self::LedgerSyncStillRunning => JsonRPCErrorCodes::DatabaseBusy,
+            Self::NotOnDisk | Self::NotMonitored => JsonRPCErrorCodes::InternalError,
+        }
+    }
+}
+
+impl JsonRPCErrorCode for LogDigestServiceError {
+    fn error_code(&self) -> JsonRPCErrorCodes {
+        match self {
+            Self::Database(e) => e.error_code(),
+        }
+    }
+}
+
+impl JsonRPCErrorCode for MaintenanceServiceError {
+    fn error_code(&self) -> JsonRPCErrorCodes {
+        match self {
+            Self::Database(e) => e.error_code(),
+            Self::NoAdminKey | Self::InvalidAdminKey => JsonRPCErrorCodes::InvalidParams,
+        }
+    }
+}
+
+impl JsonRPCErrorCode for PaymentRequestServiceError {
+    fn error_code(&self) -> JsonRPCErrorCodes {
+        match self {
+            Self::Database(e) => e.error_code(),
+            Self::LedgerService(e) => e.error_code(),
+            Self::B58(e) => e.error_code(),
+            Self::LedgerDB(_) => JsonRPCErrorCodes::LedgerDbError,
+            Self::Diesel(_) => JsonRPCErrorCodes::DatabaseError,
+            Self::HexDecode(_) | Self::ExpiryInPast(..) => JsonRPCErrorCodes::InvalidParams,
+            Self::UnknownKeyDerivation(_) | Self::InvalidMnemonic(_) => {
+                JsonRPCErrorCodes::InvalidAccountSecrets
+            }
+        }
+    }
+}
+
+impl JsonRPCErrorCode for PayoutServiceError {
+    fn error_code(&self) -> JsonRPCErrorCodes {
+        match self {
+            Self::Database(e) => e.error_code(),
+            _ => JsonRPCErrorCodes::InvalidParams,
+        }
+    }
+}
+
+impl JsonRPCErrorCode for ReceiptServiceError {
+    fn error_code(&self) -> JsonRPCErrorCodes {
+        match self {
+            Self::Database(e) => e.error_code(),
+            Self::LedgerDB(_) => JsonRPCErrorCodes::LedgerDbError,
+            Self::Diesel(_) => JsonRPCErrorCodes::DatabaseError,
+            Self::ProtoConversionInfallible => JsonRPCErrorCodes::InternalError,
+            _ => JsonRPCErrorCodes::InvalidParams,
+        }
+    }
+}
+
+impl JsonRPCErrorCode for ReportingServiceError {
+    fn error_code(&self) -> JsonRPCErrorCodes {
+        match self {
+            Self::Database(e) => e.error_code(),
+            Self::Diesel(_) => JsonRPCErrorCodes::DatabaseError,
+            _ => JsonRPCErrorCodes::InternalError,
+        }
+    }
+}
+
+impl JsonRPCErrorCode for ScheduledPaymentServiceError {
+    fn error_code(&self) -> JsonRPCErrorCodes {
+        match self {
+            Self::Database(e) => e.error_code(),
+            Self::B58(e) => e.error_code(),
+            Self::LedgerDB(_) => JsonRPCErrorCodes::LedgerDbError,
+            Self::Diesel(_) => JsonRPCErrorCodes::DatabaseError,
+            Self::AccountArchived(_) | Self::NotActive(_) => JsonRPCErrorCodes::InvalidAccountState,
+            Self::InvalidSchedule | Self::InvalidValue => JsonRPCErrorCodes::InvalidParams,
+        }
+    }
+}
+
+impl JsonRPCErrorCode for SpendingLockServiceError {
+    fn error_code(&self) -> JsonRPCErrorCodes {
+        match self {
+            Self::Database(e) => e.error_code(),
+            Self::NoAdminKey => JsonRPCErrorCodes::SpendingDisabled,
+            Self::InvalidAdminKey => JsonRPCErrorCodes::InvalidParams,
+        }
+    }
+}
+
+impl JsonRPCErrorCode for SweepPolicyServiceError {
+    fn error_code(&self) -> JsonRPCErrorCodes {
+        match self {
+            Self::Database(e) => e.error_code(),
+            Self::B58(e) => e.error_code(),
+            Self::LedgerDB(_) => JsonRPCErrorCodes::LedgerDbError,
+            Self::Diesel(_) => JsonRPCErrorCodes::DatabaseError,
+            Self::AccountArchived(_) => JsonRPCErrorCodes::InvalidAccountState,
+            Self::InvalidInterval => JsonRPCErrorCodes::InvalidParams,
+        }
+    }
+}
+
+impl JsonRPCErrorCode for SyncStatusServiceError {
+    fn error_code(&self) -> JsonRPCErrorCodes {
+        match self {
+            Self::Database(e) => e.error_code(),
+            Self::LedgerDB(_) => JsonRPCErrorCodes::LedgerDbError,
+            Self::NetworkBlockHeight(e) => e.error_code(),
+        }
+    }
+}
+
+impl JsonRPCErrorCode for TransactionServiceError {
+    fn error_code(&self) -> JsonRPCErrorCodes {
+        match self {
+            Self::Database(e) => e.error_code(),
+            Self::TransactionBuilder(e) => e.error_code(),
+            Self::AddressService(e) => e.error_code(),
+            Self::B58(e) => e.error_code(),
+            Self::LedgerDB(_) => JsonRPCErrorCodes::LedgerDbError,
+            Self::Diesel(_) => JsonRPCErrorCodes::DatabaseError,
+            Self::U64Parse | Self::MissingAccountOnSubmit | Self::InvalidPublicAddress(_) => {
+                JsonRPCErrorCodes::InvalidParams
+            }
+            Self::UnknownMinimumFee(_) => JsonRPCErrorCodes::InvalidTransaction,
+            Self::NodeNotFound | Self::NoPeersConfigured => JsonRPCErrorCodes::NoPeers,
+            Self::Offline => JsonRPCErrorCodes::Offline,
+            Self::Connection(_) => JsonRPCErrorCodes::ConnectionError,
+            Self::SpendingDisabled => JsonRPCErrorCodes::SpendingDisabled,
+            Self::LargeSendNotConfirmed(_) => JsonRPCErrorCodes::LargeSendNotConfirmed,
+            Self::NoTransactionSigner | Self::NotHardwareBacked(_) | Self::Signer(_) => {
+                JsonRPCErrorCodes::SignerError
+            }
+            _ => JsonRPCErrorCodes::InternalError,
+        }
+    }
+
+    fn insufficient_funds(&self) -> Option<&InsufficientFundsDetails> {
+        match self {
+            Self::Database(e) => e.insufficient_funds(),
+            Self::TransactionBuilder(e) => e.insufficient_funds(),
+            _ => None,
+        }
+    }
+}
+
+impl JsonRPCErrorCode for TransactionLogServiceError {
+    fn error_code(&self) -> JsonRPCErrorCodes {
+        match self {
+            Self::Database(e) => e.error_code(),
+            Self::Diesel(_) => JsonRPCErrorCodes::DatabaseError,
+            Self::Decode(_) => JsonRPCErrorCodes::InternalError,
+            _ => JsonRPCErrorCodes::InvalidParams,
+        }
+    }
+}
+
+impl JsonRPCErrorCode for TransactionWatchServiceError {
+    fn error_code(&self) -> JsonRPCErrorCodes {
+        match self {
+            Self::Database(e) => e.error_code(),
+            Self::Diesel(_) => JsonRPCErrorCodes::DatabaseError,
+            Self::NoPublicKeys => JsonRPCErrorCodes::InvalidParams,
+        }
+    }
+}
+
+impl JsonRPCErrorCode for TxoServiceError {
+    fn error_code(&self) -> JsonRPCErrorCodes {
+        match self {
+            Self::Database(e) => e.error_code(),
+            Self::TransactionService(e) => e.error_code(),
+            Self::LedgerDB(_) => JsonRPCErrorCodes::LedgerDbError,
+            Self::Diesel(_) => JsonRPCErrorCodes::DatabaseError,
+            Self::MissingConfirmation(_) => JsonRPCErrorCodes::RecordNotFound,
+            Self::TxoNotSpendableByAnyAccount(_) | Self::TxoNotSpendable(_) => {
+                JsonRPCErrorCodes::InvalidTransaction
+            }
+            Self::BlockNotScanned(_) => JsonRPCErrorCodes::InvalidParams,
+            Self::NothingToConsolidate(_)
+            | Self::ConsolidationBelowFee(_)
+            | Self::SweepBelowFee(_) => JsonRPCErrorCodes::InsufficientFunds,
+            Self::TooManyTxosToSweep(_) => JsonRPCErrorCodes::FragmentedTxos,
+        }
+    }
+
+    fn insufficient_funds(&self) -> Option<&InsufficientFundsDetails> {
+        match self {
+            Self::Database(e) => e.insufficient_funds(),
+            Self::TransactionService(e) => e.insufficient_funds(),
+            _ => None,
+        }
+    }
+}
+
+impl JsonRPCErrorCode for UpgradeReadinessServiceError {
+    fn error_code(&self) -> JsonRPCErrorCodes {
+        match self {
+            Self::Database(e) => e.error_code(),
+            Self::Serialization(_) => JsonRPCErrorCodes::InternalError,
+        }
+    }
+}
+
+impl JsonRPCErrorCode for UsageStatsServiceError {
+    fn error_code(&self) -> JsonRPCErrorCodes {
+        match self {
+            Self::Database(e) => e.error_code(),
+        }
+    }
+}
+
+impl JsonRPCErrorCode for WalletSettingsServiceError {
+    fn error_code(&self) -> JsonRPCErrorCodes {
+        match self {
+            Self::Database(e) => e.error_code(),
+            Self::Diesel(_) => JsonRPCErrorCodes::DatabaseError,
+        }
+    }
+}
+
+impl JsonRPCErrorCode for WebhookServiceError {
+    fn error_code(&self) -> JsonRPCErrorCodes {
+        match self {
+            Self::Database(e) => e.error_code(),
+            Self::Reqwest(_) => JsonRPCErrorCodes::ConnectionError,
+            Self::InvalidUrl(_) => JsonRPCErrorCodes::InvalidParams,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_code_of() {
+        // Wrapped errors take the code of the error they wrap.
+        let error = WalletServiceError::BalanceService(BalanceServiceError::Database(
+            WalletDbError::AccountNotFound("abcd".to_string()),
+        ));
+        assert_eq!(error_code_of(&error), JsonRPCErrorCodes::AccountNotFound);
+
+        let error =
+            TransactionServiceError::TransactionBuilder(WalletTransactionBuilderError::WalletDb(
+                WalletDbError::InsufficientFundsUnderMaxSpendable(InsufficientFundsDetails::new(
+                    &[100, 20],
+                    150,
+                    10,
+                )),
+            ));
+        assert_eq!(error_code_of(&error), JsonRPCErrorCodes::InsufficientFunds);
+        assert_eq!(error.error_code().category(), "transaction");
+        let details = insufficient_funds_of(&error).unwrap();
+        assert_eq!(details.max_spendable_value, 110);
+        assert_eq!(details.shortfall, 30);
+        assert_eq!(details.txos_needed, None);
+        assert!(!details.consolidation_would_help);
+        assert_eq!(insufficient_funds_of(&WalletDbError::DatabaseBusy), None);
+
+        assert_eq!(
+            error_code_of(&WalletDbError::DatabaseBusy),
+            JsonRPCErrorCodes::DatabaseBusy
+        );
+        assert!(JsonRPCErrorCodes::DatabaseBusy.is_retryable());
+
+        // Errors which aren't the wallet's are internal errors.
+        assert_eq!(
+            error_code_of(&"Could not get account".to_string()),
+            JsonRPCErrorCodes::InternalError
+        );
+        assert_eq!(JsonRPCErrorCodes::InternalError.category(), "request");
+    }
+}
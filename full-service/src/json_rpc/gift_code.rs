@@ -4,6 +4,7 @@
 
 use crate::service::gift_code::DecodedGiftCode;
 
+use chrono::{offset::TimeZone, Utc};
 use serde::{Deserialize, Serialize};
 
 /// An gift code created by this wallet to share.
@@ -29,6 +30,29 @@ pub struct GiftCode {
 
     /// A memo associated with this gift code.
     pub memo: String,
+
+    /// The account which funded this gift code, if it was submitted by this
+    /// wallet.
+    pub funding_account_id: Option<String>,
+
+    /// The account which claimed this gift code, if it was claimed by this
+    /// wallet.
+    pub claimer_account_id: Option<String>,
+
+    /// The id of the transaction log under which the claiming account records
+    /// the claimed funds.
+    pub claim_transaction_log_id: Option<String>,
+
+    /// The time at which this gift code was claimed.
+    pub claimed_time: Option<String>,
+
+    /// The time after which this gift code is swept back to the funding
+    /// account if it hasn't been claimed, if it was created as a claim link.
+    pub expiry_time: Option<String>,
+
+    /// Whether this gift code's expiry has passed and been dealt with, either
+    /// by sweeping it back or by finding it already claimed.
+    pub expired: bool,
 }
 
 impl From<&DecodedGiftCode> for GiftCode {
@@ -48,6 +72,16 @@ impl From<&DecodedGiftCode> for GiftCode {
                 .unwrap_or_default(),
             value_pmob: src.value.to_string(),
             memo: src.memo.clone(),
+            funding_account_id: src.funding_account_id.clone(),
+            claimer_account_id: src.claimer_account_id.clone(),
+            claim_transaction_log_id: src.claim_transaction_log_id.clone(),
+            claimed_time: src
+                .claimed_time
+                .map(|time| Utc.timestamp(time, 0).to_string()),
+            expiry_time: src
+                .expiry_time
+                .map(|time| Utc.timestamp(time, 0).to_string()),
+            expired: src.expired,
         }
     }
 }
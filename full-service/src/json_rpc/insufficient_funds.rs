@@ -0,0 +1,54 @@
+// Copyright (c) 2020-2022 MobileCoin Inc.
+
+//! API definition for the details of an insufficient funds error.
+//!
+//! Returned in the data of InsufficientFunds and FragmentedTxos errors, so
+//! that clients can tell users how much more they need, or that they should
+//! consolidate their txos, rather than only that their funds are insufficient.
+
+use crate::db::txo::InsufficientFundsDetails;
+use serde_derive::{Deserialize, Serialize};
+
+/// What an account is short of to build a transaction.
+#[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+pub struct InsufficientFunds {
+    /// String representing the object's type. Objects of the same type share
+    /// the same value.
+    pub object: String,
+
+    /// The value the transaction needed, including the network fee.
+    pub target_value: String,
+
+    /// The total value of the account's spendable txos.
+    pub total_unspent_value: String,
+
+    /// The most the account can spend in one transaction, after the fee.
+    pub max_spendable_value: String,
+
+    /// How much more the account needs to receive before its txos sum to the
+    /// target value.
+    pub shortfall: String,
+
+    /// How many of the account's largest txos sum to the target value. More
+    /// than the maximum inputs of a transaction when the txos must be
+    /// consolidated first, and null when they don't sum to it at all.
+    pub txos_needed: Option<String>,
+
+    /// Whether consolidating the account's txos, for example with
+    /// build_consolidation_transaction, would let it send the target value.
+    pub consolidation_would_help: bool,
+}
+
+impl From<&InsufficientFundsDetails> for InsufficientFunds {
+    fn from(src: &InsufficientFundsDetails) -> InsufficientFunds {
+        InsufficientFunds {
+            object: "insufficient_funds".to_string(),
+            target_value: src.target_value.to_string(),
+            total_unspent_value: src.total_unspent_value.to_string(),
+            max_spendable_value: src.max_spendable_value.to_string(),
+            shortfall: src.shortfall.to_string(),
+            txos_needed: src.txos_needed.map(|count| count.to_string()),
+            consolidation_would_help: src.consolidation_would_help,
+        }
+    }
+}
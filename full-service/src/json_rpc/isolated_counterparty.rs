@@ -0,0 +1,37 @@
+// Copyright (c) 2020-2022 MobileCoin Inc.
+
+//! API definition for the Isolated Counterparty object.
+
+use crate::db;
+use chrono::{offset::TimeZone, Utc};
+use serde_derive::{Deserialize, Serialize};
+
+/// A counterparty whose payments to an account are not spent together with
+/// the account's other funds.
+#[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+pub struct IsolatedCounterparty {
+    /// String representing the object's type. Objects of the same type share
+    /// the same value.
+    pub object: String,
+
+    /// The account the counterparty pays.
+    pub account_id: String,
+
+    /// The hex-encoded address hash the counterparty writes to the sender
+    /// memos of its payments.
+    pub address_hash: String,
+
+    /// The time at which the counterparty was isolated.
+    pub created_time: String,
+}
+
+impl From<&db::models::IsolatedCounterparty> for IsolatedCounterparty {
+    fn from(src: &db::models::IsolatedCounterparty) -> IsolatedCounterparty {
+        IsolatedCounterparty {
+            object: "isolated_counterparty".to_string(),
+            account_id: src.account_id_hex.clone(),
+            address_hash: src.address_hash.clone(),
+            created_time: Utc.timestamp(src.created_time, 0).to_string(),
+        }
+    }
+}
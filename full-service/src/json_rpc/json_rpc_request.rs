@@ -3,14 +3,16 @@
 //! The JSON RPC 2.0 Requests to the Wallet API for Full Service.
 
 use crate::json_rpc::{
+    account_import::AccountImport,
+    log_digest::LogDigest,
     tx_proposal::TxProposal,
     view_only_account::{ViewOnlyAccountJSON, ViewOnlyAccountSecretsJSON},
     view_only_subaddress::ViewOnlySubaddressesJSON,
 };
 
-use crate::json_rpc::receiver_receipt::ReceiverReceipt;
+use crate::{json_rpc::receiver_receipt::ReceiverReceipt, unsigned_tx::OfflineSignedTransaction};
 use serde::{Deserialize, Serialize};
-use std::convert::TryFrom;
+use std::{collections::BTreeMap, convert::TryFrom};
 use strum::IntoEnumIterator;
 use strum_macros::EnumIter;
 
@@ -68,17 +70,42 @@ pub enum JsonCommandRequest {
         account_id: String,
         metadata: Option<String>,
     },
+    assign_address_for_account_at_index {
+        account_id: String,
+        subaddress_index: String,
+        metadata: Option<String>,
+    },
+    assign_addresses_for_account {
+        account_id: String,
+        count: String,
+        metadata_prefix: Option<String>,
+    },
     build_and_submit_transaction {
         account_id: String,
         addresses_and_values: Option<Vec<(String, String)>>,
         recipient_public_address: Option<String>,
         value_pmob: Option<String>,
         input_txo_ids: Option<Vec<String>>,
+        token_id: Option<String>,
         fee: Option<String>,
+        fee_token_id: Option<String>,
         tombstone_block: Option<String>,
         max_spendable_value: Option<String>,
+        confirm_large_send: Option<bool>,
+        payment_request_id: Option<String>,
+        coin_selection_strategy: Option<String>,
+        num_change_outputs: Option<String>,
+        change_values: Option<Vec<String>>,
         comment: Option<String>,
     },
+    build_consolidation_transaction {
+        account_id: String,
+        threshold: String,
+        token_id: Option<String>,
+        destination_subaddress_index: Option<String>,
+        fee: Option<String>,
+        tombstone_block: Option<String>,
+    },
     build_gift_code {
         account_id: String,
         value_pmob: String,
@@ -88,6 +115,13 @@ pub enum JsonCommandRequest {
         tombstone_block: Option<String>,
         max_spendable_value: Option<String>,
     },
+    build_sign_and_submit_transaction {
+        account_id: String,
+        recipient_public_address: String,
+        value_pmob: String,
+        fee: Option<String>,
+        tombstone_block: Option<String>,
+    },
     build_split_txo_transaction {
         txo_id: String,
         output_values: Vec<String>,
@@ -95,23 +129,59 @@ pub enum JsonCommandRequest {
         fee: Option<String>,
         tombstone_block: Option<String>,
     },
+    build_sweep_transaction {
+        account_id: String,
+        recipient_public_address: String,
+        token_id: Option<String>,
+        subaddress_index: Option<String>,
+        allow_multiple_transactions: Option<bool>,
+        fee: Option<String>,
+        tombstone_block: Option<String>,
+        confirm_large_send: Option<bool>,
+    },
     build_transaction {
         account_id: String,
         addresses_and_values: Option<Vec<(String, String)>>,
         recipient_public_address: Option<String>,
         value_pmob: Option<String>,
         input_txo_ids: Option<Vec<String>>,
+        token_id: Option<String>,
         fee: Option<String>,
+        fee_token_id: Option<String>,
         tombstone_block: Option<String>,
         max_spendable_value: Option<String>,
+        confirm_large_send: Option<bool>,
+        payment_request_id: Option<String>,
+        coin_selection_strategy: Option<String>,
+        num_change_outputs: Option<String>,
+        change_values: Option<Vec<String>>,
         log_tx_proposal: Option<bool>,
     },
     build_unsigned_transaction {
         account_id: String,
+        addresses_and_values: Option<Vec<(String, String)>>,
         recipient_public_address: Option<String>,
         value_pmob: Option<String>,
+        input_txo_ids: Option<Vec<String>>,
+        token_id: Option<String>,
         fee: Option<String>,
+        fee_token_id: Option<String>,
         tombstone_block: Option<String>,
+        max_spendable_value: Option<String>,
+        payment_request_id: Option<String>,
+        coin_selection_strategy: Option<String>,
+        num_change_outputs: Option<String>,
+        change_values: Option<Vec<String>>,
+    },
+    cancel_account_import {
+        account_id: String,
+    },
+    cancel_scheduled_payment {
+        scheduled_payment_id: String,
+    },
+    change_db_password {
+        old_password: String,
+        new_password: String,
     },
     check_b58_type {
         b58_code: String,
@@ -128,12 +198,33 @@ pub enum JsonCommandRequest {
         account_id: String,
         address: Option<String>,
     },
+    cleanup_transaction_logs {
+        statuses: Vec<String>,
+        older_than: Option<String>,
+    },
+    compare_log_digest {
+        log_digest: LogDigest,
+    },
+    continuity_check_in,
+    convert_amount {
+        value: String,
+        from: String,
+        to: String,
+        rounding: Option<String>,
+    },
     create_account {
         name: Option<String>,
         fog_report_url: Option<String>,
         fog_report_id: Option<String>,
         fog_authority_spki: Option<String>,
     },
+    create_claim_link {
+        account_id: String,
+        value_pmob: String,
+        memo: Option<String>,
+        expiry_seconds: String,
+        fee: Option<String>,
+    },
     create_new_subaddresses_request {
         account_id: String,
         num_subaddresses_to_generate: String,
@@ -143,16 +234,64 @@ pub enum JsonCommandRequest {
         subaddress_index: Option<i64>,
         amount_pmob: u64,
         memo: Option<String>,
+        expiry_block_index: Option<String>,
     },
     create_receiver_receipts {
         tx_proposal: TxProposal,
     },
+    create_scheduled_payment {
+        account_id: String,
+        recipient_public_address: String,
+        value: String,
+        token_id: Option<String>,
+        interval_seconds: Option<String>,
+        block_height: Option<String>,
+        comment: Option<String>,
+    },
+    create_view_only_account_sync_proof {
+        account_id: String,
+        first_block_index: Option<String>,
+        block_count: Option<String>,
+    },
     create_view_only_account_sync_request {
         account_id: String,
     },
+    disable_spending,
+    enable_spending {
+        admin_key: String,
+    },
+    end_maintenance {
+        admin_key: String,
+    },
     export_account_secrets {
         account_id: String,
     },
+    export_address_labels {
+        account_id: String,
+        format: String,
+    },
+    export_key_images {
+        account_id: Option<String>,
+        view_only: Option<bool>,
+        limit: Option<String>,
+        cursor: Option<String>,
+    },
+    export_signing_request {
+        account_id: String,
+        recipient_public_address: Option<String>,
+        value_pmob: Option<String>,
+        addresses_and_values: Option<Vec<(String, String)>>,
+        input_txo_ids: Option<Vec<String>>,
+        token_id: Option<String>,
+        fee: Option<String>,
+        fee_token_id: Option<String>,
+        tombstone_block: Option<String>,
+        max_spendable_value: Option<String>,
+        payment_request_id: Option<String>,
+        coin_selection_strategy: Option<String>,
+        num_change_outputs: Option<String>,
+        change_values: Option<Vec<String>>,
+    },
     export_spent_txo_ids {
         account_id: String,
     },
@@ -162,15 +301,32 @@ pub enum JsonCommandRequest {
     export_view_only_account_secrets {
         account_id: String,
     },
+    freeze_txos {
+        txo_ids: Vec<String>,
+    },
     get_account {
         account_id: String,
     },
+    get_account_rotation_status {
+        account_id: String,
+    },
+    get_account_snapshots {
+        account_id: String,
+        token_id: Option<String>,
+        from_block_index: Option<String>,
+        to_block_index: Option<String>,
+    },
     get_account_status {
         account_id: String,
     },
+    get_account_sweep_policy {
+        account_id: String,
+    },
     get_address_for_account {
         account_id: String,
         index: i64,
+        derive: Option<bool>,
+        assign: Option<bool>,
     },
     get_address_for_view_only_account {
         account_id: String,
@@ -188,8 +344,11 @@ pub enum JsonCommandRequest {
     },
     get_all_accounts,
     get_all_gift_codes,
-    get_all_transaction_logs_for_block {
-        block_index: String,
+    get_all_transaction_logs_for_block_range {
+        min_block_index: String,
+        max_block_index: String,
+        offset: Option<String>,
+        limit: Option<String>,
     },
     get_all_transaction_logs_ordered_by_block,
     get_all_txos_for_address {
@@ -198,6 +357,7 @@ pub enum JsonCommandRequest {
     get_all_view_only_accounts,
     get_balance_for_account {
         account_id: String,
+        force_refresh: Option<bool>,
     },
     get_balance_for_address {
         address: String,
@@ -214,9 +374,23 @@ pub enum JsonCommandRequest {
     get_confirmations {
         transaction_log_id: String,
     },
+    get_continuity_status,
+    get_deposits {
+        account_id: String,
+        min_block_index: Option<String>,
+        max_block_index: Option<String>,
+    },
     get_gift_code {
         gift_code_b58: String,
     },
+    get_isolated_counterparties {
+        account_id: String,
+    },
+    get_ledger_db_size,
+    get_log_digest {
+        account_id: String,
+    },
+    get_maintenance_status,
     get_mc_protocol_transaction {
         transaction_log_id: String,
     },
@@ -224,6 +398,20 @@ pub enum JsonCommandRequest {
         txo_id: String,
     },
     get_network_status,
+    get_payment_request {
+        payment_request_id: String,
+    },
+    get_pending_outflows,
+    get_quarantined_blocks,
+    get_reporting_public_key,
+    get_settings,
+    get_sync_history {
+        account_id: String,
+        offset: Option<String>,
+        limit: Option<String>,
+    },
+    get_sync_status,
+    get_tokens,
     get_transaction_log {
         transaction_log_id: String,
     },
@@ -233,6 +421,14 @@ pub enum JsonCommandRequest {
         limit: Option<String>,
         min_block_index: Option<String>,
         max_block_index: Option<String>,
+        cursor: Option<String>,
+    },
+    get_transaction_watch {
+        watch_id: String,
+    },
+    get_transaction_watches {
+        offset: Option<String>,
+        limit: Option<String>,
     },
     get_txo {
         txo_id: String,
@@ -241,16 +437,29 @@ pub enum JsonCommandRequest {
         account_id: String,
         offset: Option<String>,
         limit: Option<String>,
+        cursor: Option<String>,
     },
     get_txos_for_view_only_account {
         account_id: String,
         offset: Option<String>,
         limit: Option<String>,
+        cursor: Option<String>,
     },
+    get_usage_stats,
     get_view_only_account {
         account_id: String,
     },
+    get_view_only_account_sync_records {
+        account_id: String,
+        offset: Option<String>,
+        limit: Option<String>,
+    },
+    get_wallet_events {
+        after_event_id: Option<String>,
+        limit: Option<String>,
+    },
     get_wallet_status,
+    get_webhooks,
     import_account {
         mnemonic: String,
         key_derivation_version: String,
@@ -270,6 +479,18 @@ pub enum JsonCommandRequest {
         fog_report_id: Option<String>,
         fog_authority_spki: Option<String>,
     },
+    import_accounts {
+        accounts: Vec<AccountImport>,
+    },
+    import_address_labels {
+        account_id: String,
+        contents: String,
+        format: String,
+    },
+    import_signed_transaction {
+        signed_transaction: OfflineSignedTransaction,
+        comment: Option<String>,
+    },
     import_subaddresses_to_view_only_account {
         account_id: String,
         subaddresses: ViewOnlySubaddressesJSON,
@@ -279,20 +500,110 @@ pub enum JsonCommandRequest {
         secrets: ViewOnlyAccountSecretsJSON,
         subaddresses: ViewOnlySubaddressesJSON,
     },
+    isolate_counterparty {
+        account_id: String,
+        counterparty: String,
+    },
+    list_payment_requests {
+        account_id: String,
+    },
+    list_scheduled_payments {
+        account_id: Option<String>,
+    },
+    pause_account_sync {
+        account_id: String,
+    },
+    recover_account {
+        mnemonic: String,
+        start_block_index: Option<String>,
+        max_blocks: Option<String>,
+        import_key_derivation_version: Option<String>,
+        name: Option<String>,
+        fog_report_url: Option<String>,
+        fog_report_id: Option<String>,
+        fog_authority_spki: Option<String>,
+    },
+    register_webhook {
+        url: String,
+        account_id: Option<String>,
+    },
+    relocate_ledger_db {
+        path: String,
+    },
     remove_account {
         account_id: String,
     },
+    remove_account_sweep_policy {
+        account_id: String,
+    },
     remove_gift_code {
         gift_code_b58: String,
     },
+    remove_isolated_counterparty {
+        account_id: String,
+        counterparty: String,
+    },
     remove_view_only_account {
         account_id: String,
     },
+    remove_webhook {
+        webhook_id: String,
+    },
+    replay_events {
+        from_cursor: String,
+        to_cursor: Option<String>,
+    },
+    rescan_account {
+        account_id: String,
+        from_block_index: String,
+    },
+    resume_account_sync {
+        account_id: String,
+    },
+    rotate_account {
+        account_id: String,
+        new_account_name: Option<String>,
+    },
+    run_diagnostics,
+    set_account_first_block {
+        account_id: String,
+        block_index: String,
+    },
+    set_account_sweep_policy {
+        account_id: String,
+        destination_address: String,
+        token_id: Option<String>,
+        threshold: String,
+        interval_seconds: String,
+    },
+    set_db_password {
+        password: String,
+    },
+    set_ledger_db_max_size {
+        max_size_bytes: String,
+    },
+    skip_quarantined_block {
+        block_index: String,
+    },
+    start_maintenance {
+        admin_key: String,
+        eta_seconds: Option<String>,
+        reason: Option<String>,
+    },
     submit_gift_code {
         from_account_id: String,
         gift_code_b58: String,
         tx_proposal: TxProposal,
     },
+    submit_payout_file {
+        account_id: String,
+        contents: String,
+        format: String,
+        amount_unit: Option<String>,
+        fee: Option<String>,
+        tombstone_block: Option<String>,
+        comment: Option<String>,
+    },
     submit_transaction {
         tx_proposal: TxProposal,
         comment: Option<String>,
@@ -303,10 +614,24 @@ pub enum JsonCommandRequest {
         completed_txos: Vec<(String, String)>,
         subaddresses: ViewOnlySubaddressesJSON,
     },
+    unfreeze_txos {
+        txo_ids: Vec<String>,
+    },
     update_account_name {
         account_id: String,
         name: String,
     },
+    update_account_preferred_token_id {
+        account_id: String,
+        preferred_token_id: String,
+    },
+    update_settings {
+        settings: BTreeMap<String, String>,
+    },
+    update_view_only_account_hardware_backed {
+        account_id: String,
+        hardware_backed: bool,
+    },
     update_view_only_account_name {
         account_id: String,
         name: String,
@@ -316,10 +641,20 @@ pub enum JsonCommandRequest {
         txo_id: String,
         confirmation: String,
     },
+    verify_account_backup {
+        account_id: String,
+        mnemonic: String,
+    },
     verify_address {
         address: String,
     },
     version,
+    watch_transaction {
+        tx_public_keys: Option<Vec<String>>,
+        receiver_receipt: Option<ReceiverReceipt>,
+        tombstone_block: Option<String>,
+        comment: Option<String>,
+    },
 }
 
 fn method_alias(m: &str) -> &str {
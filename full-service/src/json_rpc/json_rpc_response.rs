@@ -7,23 +7,56 @@
 use crate::{
     json_rpc::{
         account::Account,
+        account_import::AccountImportSyncPlan,
+        account_recovery::RecoveryCandidate,
+        account_rotation::AccountRotation,
         account_secrets::AccountSecrets,
+        account_snapshot::AccountSnapshot,
+        account_sync_record::AccountSyncRecord,
         address::Address,
         balance::Balance,
         block::{Block, BlockContents},
         confirmation_number::Confirmation,
+        continuity_status::ContinuityStatus,
+        deposit::Deposit,
+        diagnostic_check::DiagnosticCheck,
+        error_code::{error_code_of, insufficient_funds_of},
         gift_code::GiftCode,
+        insufficient_funds::InsufficientFunds,
+        isolated_counterparty::IsolatedCounterparty,
         json_rpc_request::JsonRPCRequest,
+        key_image_export::ExportedKeyImage,
+        ledger_db_size::LedgerDbSize,
+        log_digest::{LogDigest, LogDigestDiff},
+        maintenance_status::MaintenanceStatus,
         network_status::NetworkStatus,
+        payment_request::PaymentRequest,
+        payout_row_result::PayoutRowResult,
+        pending_outflows::PendingOutflows,
+        quarantined_block::QuarantinedBlock,
         receiver_receipt::ReceiverReceipt,
+        scheduled_payment::ScheduledPayment,
+        sweep_policy::SweepPolicy,
+        sync_status::SyncStatus,
+        token::Token,
         transaction_log::TransactionLog,
+        transaction_watch::TransactionWatch,
         tx_proposal::TxProposal,
         txo::Txo,
+        txo_context::TxoContext,
+        usage_stats::UsageStats,
         view_only_account::{ViewOnlyAccountJSON, ViewOnlyAccountSecretsJSON},
         view_only_subaddress::ViewOnlySubaddressJSON,
+        view_only_sync_record::ViewOnlySyncRecordJSON,
+        wallet_event::WalletEvent,
+        wallet_settings::WalletSettings,
         wallet_status::WalletStatus,
+        webhook::Webhook,
+    },
+    service::{
+        gift_code::GiftCodeStatus, maintenance::MaintenanceWindow,
+        receipt::ReceiptTransactionStatus,
     },
-    service::{gift_code::GiftCodeStatus, receipt::ReceiptTransactionStatus},
     util::b58::PrintableWrapperType,
 };
 use mc_mobilecoind_json::data_types::{JsonTx, JsonTxOut};
@@ -32,7 +65,11 @@ use serde_json::Map;
 use std::collections::HashMap;
 use strum::Display;
 
-use crate::{fog_resolver::FullServiceFogResolver, unsigned_tx::UnsignedTx};
+use crate::{
+    fog_resolver::FullServiceFogResolver,
+    sync_proof::ViewOnlySyncProof,
+    unsigned_tx::{OfflineSigningRequest, UnsignedTx},
+};
 
 /// A JSON RPC 2.0 Response.
 #[derive(Deserialize, Serialize, Debug)]
@@ -81,7 +118,13 @@ pub enum JsonRPCError {
 }
 
 /// JSON RPC Error codes.
-#[derive(Deserialize, Serialize, Debug, Display)]
+///
+/// Codes from -32768 to -32000 are for errors with the request itself, 1000
+/// to 1999 for errors with the wallet database, 2000 to 2999 for errors
+/// building and submitting transactions, and 3000 to 3999 for errors reaching
+/// the network. Once assigned, a code keeps its meaning, so that clients can
+/// branch on it.
+#[derive(Deserialize, Serialize, Debug, Display, Clone, Copy, PartialEq, Eq)]
 pub enum JsonRPCErrorCodes {
     /// Parse error.
     ParseError = -32700,
@@ -97,31 +140,212 @@ pub enum JsonRPCErrorCodes {
 
     /// Internal Error.
     InternalError = -32603,
-    /* Server error.
-     * ServerError(i32), // FIXME: WalletServiceError -> i32 between 32000 and 32099 */
+
+    /// Unauthorized, because the API key was missing or wrong.
+    Unauthorized = -32001,
+
+    /// The method is disabled on this wallet by its operator.
+    MethodDisabled = -32002,
+
+    /// Error reading or writing the wallet database.
+    DatabaseError = 1000,
+
+    /// The wallet database is busy or unavailable, and the request may be
+    /// retried.
+    DatabaseBusy = 1001,
+
+    /// Error with the wallet database's password.
+    DatabasePasswordError = 1002,
+
+    /// Error reading the ledger database.
+    LedgerDbError = 1003,
+
+    /// The wallet is in maintenance and only serving reads, and the request
+    /// should be retried once maintenance ends.
+    RetryLater = 1004,
+
+    /// The account is not in the wallet.
+    AccountNotFound = 1100,
+
+    /// The account is already in the wallet.
+    AccountAlreadyExists = 1101,
+
+    /// The address is not assigned to any account in the wallet.
+    AddressNotFound = 1102,
+
+    /// The txo is not in the wallet.
+    TxoNotFound = 1103,
+
+    /// The transaction log is not in the wallet.
+    TransactionLogNotFound = 1104,
+
+    /// The gift code is not in the wallet.
+    GiftCodeNotFound = 1105,
+
+    /// Some other record is not in the wallet.
+    RecordNotFound = 1106,
+
+    /// The record is already in the wallet.
+    RecordAlreadyExists = 1107,
+
+    /// Unknown wallet setting, or invalid value for one.
+    InvalidSetting = 1200,
+
+    /// The mnemonic, entropy or keys given for an account are invalid.
+    InvalidAccountSecrets = 1201,
+
+    /// The account can't be used for this request, for example because it is
+    /// archived or already rotated.
+    InvalidAccountState = 1202,
+
+    /// Error building the transaction.
+    TransactionBuilderError = 2000,
+
+    /// The account does not have enough funds for the transaction.
+    InsufficientFunds = 2001,
+
+    /// The account's funds are spread over too many txos for one transaction,
+    /// and must be combined first.
+    FragmentedTxos = 2002,
+
+    /// The fee is too low.
+    InsufficientFee = 2003,
+
+    /// The transaction asked for is invalid.
+    InvalidTransaction = 2004,
+
+    /// Error with fog, for a recipient with a fog enabled address.
+    FogError = 2005,
+
+    /// The offline signing request or signed transaction is invalid.
+    InvalidOfflineSigning = 2006,
+
+    /// Spending is disabled on this wallet.
+    SpendingDisabled = 2100,
+
+    /// The send is over the large send threshold, and must be confirmed.
+    LargeSendNotConfirmed = 2101,
+
+    /// Error signing the transaction with an external signer.
+    SignerError = 2102,
+
+    /// The gift code can't be claimed.
+    GiftCodeNotClaimable = 2103,
+
+    /// Error reaching the network, and the request may be retried.
+    ConnectionError = 3000,
+
+    /// No consensus node is configured to reach.
+    NoPeers = 3001,
+
+    /// The wallet is running offline.
+    Offline = 3002,
+}
+
+impl JsonRPCErrorCodes {
+    /// The group of errors the code is in.
+    pub fn category(&self) -> &'static str {
+        match *self as i32 {
+            -32768..=-32000 => "request",
+            1000..=1999 => "wallet_db",
+            2000..=2999 => "transaction",
+            3000..=3999 => "connection",
+            _ => "other",
+        }
+    }
+
+    /// Whether the same request may succeed if it is made again later.
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            JsonRPCErrorCodes::DatabaseBusy
+                | JsonRPCErrorCodes::RetryLater
+                | JsonRPCErrorCodes::ConnectionError
+        )
+    }
 }
 
-/// Helper method to format displaydoc errors in JSON RPC 2.0 format.
-pub fn format_error<T: std::fmt::Display + std::fmt::Debug>(e: T) -> JsonRPCError {
-    let data: serde_json::Value =
-        json!({"server_error": format!("{:?}", e), "details": e.to_string()}).into();
+/// The error object for the given code.
+fn error_with_code(
+    code: JsonRPCErrorCodes,
+    mut data: Map<String, serde_json::Value>,
+) -> JsonRPCError {
+    data.insert("category".to_string(), code.category().into());
+    data.insert("retryable".to_string(), code.is_retryable().into());
     JsonRPCError::error {
-        code: JsonRPCErrorCodes::InternalError as i32,
-        message: JsonRPCErrorCodes::InternalError.to_string(),
-        data,
+        code: code as i32,
+        message: code.to_string(),
+        data: data.into(),
+    }
+}
+
+/// Helper method to format displaydoc errors in JSON RPC 2.0 format, with the
+/// code for the error.
+pub fn format_error<T: std::fmt::Display + std::fmt::Debug + 'static>(e: T) -> JsonRPCError {
+    let mut data = Map::new();
+    data.insert("server_error".to_string(), format!("{:?}", e).into());
+    data.insert("details".to_string(), e.to_string().into());
+    if let Some(details) = insufficient_funds_of(&e) {
+        data.insert(
+            "insufficient_funds".to_string(),
+            json!(InsufficientFunds::from(details)),
+        );
     }
+    error_with_code(error_code_of(&e), data)
+}
+
+/// Helper method to format displaydoc errors in JSON RPC 2.0 format, along
+/// with the errors for each entry of the request which was rejected.
+pub fn format_entry_errors<T: std::fmt::Display + std::fmt::Debug + 'static, E: Serialize>(
+    e: &T,
+    entry_errors: &[E],
+) -> JsonRPCError {
+    let mut data = Map::new();
+    data.insert("server_error".to_string(), format!("{:?}", e).into());
+    data.insert("details".to_string(), e.to_string().into());
+    data.insert("entry_errors".to_string(), json!(entry_errors));
+    error_with_code(error_code_of(e), data)
 }
 
 /// Helper method to format displaydoc invalid request errors in JSON RPC 2.0
 /// format.
 pub fn format_invalid_request_error<T: std::fmt::Display + std::fmt::Debug>(e: T) -> JsonRPCError {
-    let data: serde_json::Value =
-        json!({"server_error": format!("{:?}", e), "details": e.to_string()}).into();
-    JsonRPCError::error {
-        code: JsonRPCErrorCodes::InvalidRequest as i32,
-        message: JsonRPCErrorCodes::InvalidRequest.to_string(),
-        data,
-    }
+    let mut data = Map::new();
+    data.insert("server_error".to_string(), format!("{:?}", e).into());
+    data.insert("details".to_string(), e.to_string().into());
+    error_with_code(JsonRPCErrorCodes::InvalidRequest, data)
+}
+
+/// The error returned when a request's API key is missing or wrong.
+pub fn format_unauthorized_error() -> JsonRPCError {
+    let mut data = Map::new();
+    data.insert(
+        "details".to_string(),
+        "The X-API-KEY header is missing or does not match the wallet's API key.".into(),
+    );
+    error_with_code(JsonRPCErrorCodes::Unauthorized, data)
+}
+
+/// The error returned for a request which would write to the wallet while it
+/// is in maintenance, with when maintenance is expected to end.
+pub fn format_maintenance_error(window: &MaintenanceWindow, now: i64) -> JsonRPCError {
+    let mut data = Map::new();
+    data.insert(
+        "details".to_string(),
+        "The wallet is in maintenance and only serving reads.".into(),
+    );
+    data.insert(
+        "eta_time".to_string(),
+        json!(window.eta_time.map(|eta_time| eta_time.to_string())),
+    );
+    data.insert(
+        "retry_after_seconds".to_string(),
+        json!(window
+            .retry_after_seconds(now)
+            .map(|seconds| seconds.to_string())),
+    );
+    data.insert("reason".to_string(), json!(window.reason));
+    error_with_code(JsonRPCErrorCodes::RetryLater, data)
 }
 
 /// Responses from the Full Service Wallet.
@@ -133,18 +357,37 @@ pub enum JsonCommandResponse {
     assign_address_for_account {
         address: Address,
     },
+    assign_address_for_account_at_index {
+        address: Address,
+    },
+    assign_addresses_for_account {
+        public_addresses: Vec<String>,
+        address_map: Map<String, serde_json::Value>,
+    },
     build_and_submit_transaction {
         transaction_log: TransactionLog,
         tx_proposal: TxProposal,
     },
+    build_consolidation_transaction {
+        tx_proposal: TxProposal,
+        transaction_log_id: String,
+    },
     build_gift_code {
         tx_proposal: TxProposal,
         gift_code_b58: String,
     },
+    build_sign_and_submit_transaction {
+        tx_proposal: TxProposal,
+    },
     build_split_txo_transaction {
         tx_proposal: TxProposal,
         transaction_log_id: String,
     },
+    build_sweep_transaction {
+        tx_proposals: Vec<TxProposal>,
+        transaction_log_ids: Vec<String>,
+        complete: bool,
+    },
     build_transaction {
         tx_proposal: TxProposal,
         transaction_log_id: String,
@@ -154,6 +397,15 @@ pub enum JsonCommandResponse {
         unsigned_tx: UnsignedTx,
         fog_resolver: FullServiceFogResolver,
     },
+    cancel_account_import {
+        removed: bool,
+    },
+    cancel_scheduled_payment {
+        scheduled_payment: ScheduledPayment,
+    },
+    change_db_password {
+        changed: bool,
+    },
     check_b58_type {
         b58_type: PrintableWrapperType,
         data: HashMap<String, String>,
@@ -170,11 +422,28 @@ pub enum JsonCommandResponse {
     claim_gift_code {
         txo_id: String,
     },
+    cleanup_transaction_logs {
+        deleted_count: String,
+    },
+    compare_log_digest {
+        log_digest_diff: LogDigestDiff,
+    },
+    continuity_check_in {
+        continuity_status: ContinuityStatus,
+    },
+    convert_amount {
+        value: String,
+        unit: String,
+    },
     create_account {
         account: Account,
     },
+    create_claim_link {
+        gift_code: GiftCode,
+    },
     create_payment_request {
         payment_request_b58: String,
+        payment_request: PaymentRequest,
     },
     create_new_subaddresses_request {
         account_id: String,
@@ -184,13 +453,39 @@ pub enum JsonCommandResponse {
     create_receiver_receipts {
         receiver_receipts: Vec<ReceiverReceipt>,
     },
+    create_scheduled_payment {
+        scheduled_payment: ScheduledPayment,
+    },
+    create_view_only_account_sync_proof {
+        sync_proof: ViewOnlySyncProof,
+    },
     create_view_only_account_sync_request {
         account_id: String,
         incomplete_txos_encoded: Vec<String>,
     },
+    disable_spending {
+        spending_disabled: bool,
+    },
+    enable_spending {
+        spending_disabled: bool,
+    },
+    end_maintenance {
+        maintenance_status: MaintenanceStatus,
+    },
     export_account_secrets {
         account_secrets: AccountSecrets,
     },
+    export_address_labels {
+        contents: String,
+    },
+    export_key_images {
+        key_images: Vec<ExportedKeyImage>,
+        next_cursor: Option<String>,
+        has_more: bool,
+    },
+    export_signing_request {
+        signing_request: OfflineSigningRequest,
+    },
     export_spent_txo_ids {
         spent_txo_ids: Vec<String>,
     },
@@ -200,15 +495,32 @@ pub enum JsonCommandResponse {
     export_view_only_account_secrets {
         view_only_account_secrets: ViewOnlyAccountSecretsJSON,
     },
+    freeze_txos {
+        txo_ids: Vec<String>,
+        txo_map: Map<String, serde_json::Value>,
+    },
     get_account {
         account: Account,
     },
+    get_account_rotation_status {
+        account_rotation: AccountRotation,
+    },
+    get_account_snapshots {
+        account_snapshots: Vec<AccountSnapshot>,
+    },
     get_account_status {
         account: Account,
         balance: Balance,
     },
+    get_account_sweep_policy {
+        sweep_policy: SweepPolicy,
+    },
     get_address_for_account {
         address: Address,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        address_status: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        orphaned_txo_ids: Option<Vec<String>>,
     },
     get_address_for_view_only_account {
         address: ViewOnlySubaddressJSON,
@@ -228,7 +540,7 @@ pub enum JsonCommandResponse {
     get_all_gift_codes {
         gift_codes: Vec<GiftCode>,
     },
-    get_all_transaction_logs_for_block {
+    get_all_transaction_logs_for_block_range {
         transaction_log_ids: Vec<String>,
         transaction_log_map: Map<String, serde_json::Value>,
     },
@@ -262,9 +574,27 @@ pub enum JsonCommandResponse {
     get_confirmations {
         confirmations: Vec<Confirmation>,
     },
+    get_continuity_status {
+        continuity_status: ContinuityStatus,
+    },
+    get_deposits {
+        deposits: Vec<Deposit>,
+    },
     get_gift_code {
         gift_code: GiftCode,
     },
+    get_isolated_counterparties {
+        isolated_counterparties: Vec<IsolatedCounterparty>,
+    },
+    get_ledger_db_size {
+        ledger_db_size: LedgerDbSize,
+    },
+    get_log_digest {
+        log_digest: LogDigest,
+    },
+    get_maintenance_status {
+        maintenance_status: MaintenanceStatus,
+    },
     get_mc_protocol_transaction {
         transaction: JsonTx,
     },
@@ -274,67 +604,214 @@ pub enum JsonCommandResponse {
     get_network_status {
         network_status: NetworkStatus,
     },
+    get_payment_request {
+        payment_request: PaymentRequest,
+    },
+    get_pending_outflows {
+        pending_outflows: PendingOutflows,
+    },
+    get_quarantined_blocks {
+        quarantined_blocks: Vec<QuarantinedBlock>,
+    },
+    get_reporting_public_key {
+        reporting_public_key: String,
+    },
+    get_settings {
+        settings: WalletSettings,
+    },
+    get_sync_history {
+        sync_history: Vec<AccountSyncRecord>,
+    },
+    get_sync_status {
+        sync_status: SyncStatus,
+    },
+    get_tokens {
+        tokens: Vec<Token>,
+    },
     get_transaction_log {
         transaction_log: TransactionLog,
     },
     get_transaction_logs_for_account {
         transaction_log_ids: Vec<String>,
         transaction_log_map: Map<String, serde_json::Value>,
+        next_cursor: Option<String>,
+        has_more: bool,
+    },
+    get_transaction_watch {
+        transaction_watch: TransactionWatch,
+    },
+    get_transaction_watches {
+        transaction_watches: Vec<TransactionWatch>,
     },
     get_txo {
         txo: Txo,
+        context: TxoContext,
     },
     get_txos_for_account {
         txo_ids: Vec<String>,
         txo_map: Map<String, serde_json::Value>,
+        next_cursor: Option<String>,
+        has_more: bool,
     },
     get_txos_for_view_only_account {
         txo_ids: Vec<String>,
         txo_map: Map<String, serde_json::Value>,
     },
+    get_usage_stats {
+        usage_stats: UsageStats,
+    },
     get_view_only_account {
         view_only_account: ViewOnlyAccountJSON,
     },
+    get_view_only_account_sync_records {
+        sync_records: Vec<ViewOnlySyncRecordJSON>,
+    },
+    get_wallet_events {
+        events: Vec<WalletEvent>,
+    },
     get_wallet_status {
         wallet_status: WalletStatus,
     },
+    get_webhooks {
+        webhooks: Vec<Webhook>,
+    },
     import_account {
         account: Account,
     },
     import_account_from_legacy_root_entropy {
         account: Account,
     },
+    import_accounts {
+        accounts: Vec<Account>,
+        sync_plan: AccountImportSyncPlan,
+    },
+    import_address_labels {
+        assigned_count: String,
+        updated_count: String,
+        unchanged_count: String,
+    },
+    import_signed_transaction {
+        transaction_log: Option<TransactionLog>,
+    },
     import_subaddresses_to_view_only_account {
         public_address_b58s: Vec<String>,
     },
     import_view_only_account {
         view_only_account: ViewOnlyAccountJSON,
     },
+    isolate_counterparty {
+        isolated_counterparty: IsolatedCounterparty,
+    },
+    list_payment_requests {
+        payment_requests: Vec<PaymentRequest>,
+    },
+    list_scheduled_payments {
+        scheduled_payments: Vec<ScheduledPayment>,
+    },
+    pause_account_sync {
+        account: Account,
+    },
+    recover_account {
+        start_block_index: String,
+        end_block_index: String,
+        candidates: Vec<RecoveryCandidate>,
+        account: Option<Account>,
+    },
+    register_webhook {
+        webhook: Webhook,
+    },
+    relocate_ledger_db {
+        ledger_db_size: LedgerDbSize,
+    },
     remove_account {
         removed: bool,
     },
+    remove_account_sweep_policy {
+        removed: bool,
+    },
     remove_gift_code {
         removed: bool,
     },
+    remove_isolated_counterparty {
+        removed: bool,
+    },
     remove_view_only_account {
         removed: bool,
     },
+    remove_webhook {
+        removed: bool,
+    },
+    replay_events {
+        events: Vec<WalletEvent>,
+    },
+    rescan_account {
+        account: Account,
+    },
+    resume_account_sync {
+        account: Account,
+    },
+    rotate_account {
+        account_rotation: AccountRotation,
+    },
+    run_diagnostics {
+        checks: Vec<DiagnosticCheck>,
+        passed: bool,
+    },
+    set_account_first_block {
+        account: Account,
+    },
+    set_account_sweep_policy {
+        sweep_policy: SweepPolicy,
+    },
+    set_db_password {
+        encrypted: bool,
+    },
+    set_ledger_db_max_size {
+        ledger_db_size: LedgerDbSize,
+    },
+    skip_quarantined_block {
+        quarantined_block: QuarantinedBlock,
+    },
+    start_maintenance {
+        maintenance_status: MaintenanceStatus,
+    },
     submit_gift_code {
         gift_code: GiftCode,
     },
+    submit_payout_file {
+        results: Vec<PayoutRowResult>,
+        accepted_count: String,
+        rejected_count: String,
+    },
     submit_transaction {
         transaction_log: Option<TransactionLog>,
     },
     sync_view_only_account,
+    unfreeze_txos {
+        txo_ids: Vec<String>,
+        txo_map: Map<String, serde_json::Value>,
+    },
     update_account_name {
         account: Account,
     },
+    update_account_preferred_token_id {
+        account: Account,
+    },
+    update_settings {
+        settings: WalletSettings,
+    },
+    update_view_only_account_hardware_backed {
+        view_only_account: ViewOnlyAccountJSON,
+    },
     update_view_only_account_name {
         view_only_account: ViewOnlyAccountJSON,
     },
     validate_confirmation {
         validated: bool,
     },
+    verify_account_backup {
+        verified: bool,
+    },
     verify_address {
         verified: bool,
     },
@@ -343,4 +820,7 @@ pub enum JsonCommandResponse {
         number: (String, String, String, String),
         commit: String,
     },
+    watch_transaction {
+        transaction_watch: TransactionWatch,
+    },
 }
@@ -0,0 +1,85 @@
+// Copyright (c) 2020-2022 MobileCoin Inc.
+
+//! API definition for exported key images.
+//!
+//! Key images are exported as they appear in the ledger, rather than in the
+//! serialized form the wallet stores them in, so that external systems can
+//! match them against the key images of each block directly.
+
+use crate::db::models::{Txo, ViewOnlyTxo};
+use mc_transaction_core::ring_signature::KeyImage;
+use serde_derive::{Deserialize, Serialize};
+use std::convert::TryFrom;
+
+/// The key image of a txo received by an account, which the network records
+/// when the txo is spent.
+#[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+pub struct ExportedKeyImage {
+    /// String representing the object's type. Objects of the same type share
+    /// the same value.
+    pub object: String,
+
+    /// The hex-encoded key image.
+    pub key_image: String,
+
+    /// The account which received the txo.
+    pub account_id: String,
+
+    /// The txo the key image belongs to.
+    pub txo_id: String,
+
+    /// The subaddress the txo was received at, if known.
+    pub subaddress_index: Option<String>,
+
+    pub value: String,
+
+    pub token_id: String,
+
+    /// The block the txo was received in, if known.
+    pub received_block_index: Option<String>,
+
+    /// The block the key image appeared in, if the wallet has seen the txo
+    /// spent.
+    pub spent_block_index: Option<String>,
+}
+
+fn encode_key_image(key_image: &[u8]) -> Result<String, String> {
+    let key_image: KeyImage = mc_util_serial::decode(key_image).map_err(|e| e.to_string())?;
+    Ok(hex::encode(key_image))
+}
+
+impl TryFrom<&Txo> for ExportedKeyImage {
+    type Error = String;
+
+    fn try_from(txo: &Txo) -> Result<ExportedKeyImage, String> {
+        Ok(ExportedKeyImage {
+            object: "key_image".to_string(),
+            key_image: encode_key_image(txo.key_image.as_deref().ok_or("Missing key image")?)?,
+            account_id: txo.received_account_id_hex.clone().unwrap_or_default(),
+            txo_id: txo.txo_id_hex.clone(),
+            subaddress_index: txo.subaddress_index.map(|i| (i as u64).to_string()),
+            value: (txo.value as u64).to_string(),
+            token_id: (txo.token_id as u64).to_string(),
+            received_block_index: txo.received_block_index.map(|i| (i as u64).to_string()),
+            spent_block_index: txo.spent_block_index.map(|i| (i as u64).to_string()),
+        })
+    }
+}
+
+impl TryFrom<&ViewOnlyTxo> for ExportedKeyImage {
+    type Error = String;
+
+    fn try_from(txo: &ViewOnlyTxo) -> Result<ExportedKeyImage, String> {
+        Ok(ExportedKeyImage {
+            object: "key_image".to_string(),
+            key_image: encode_key_image(txo.key_image.as_deref().ok_or("Missing key image")?)?,
+            account_id: txo.view_only_account_id_hex.clone(),
+            txo_id: txo.txo_id_hex.clone(),
+            subaddress_index: txo.subaddress_index.map(|i| (i as u64).to_string()),
+            value: (txo.value as u64).to_string(),
+            token_id: (txo.token_id as u64).to_string(),
+            received_block_index: txo.received_block_index.map(|i| (i as u64).to_string()),
+            spent_block_index: txo.spent_block_index.map(|i| (i as u64).to_string()),
+        })
+    }
+}
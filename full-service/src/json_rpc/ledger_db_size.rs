@@ -0,0 +1,45 @@
+// Copyright (c) 2020-2022 MobileCoin Inc.
+
+//! API definition for the Ledger DB Size object.
+
+use crate::service;
+
+use serde_derive::{Deserialize, Serialize};
+
+/// The size of the ledger database, and how much more it can grow.
+#[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+pub struct LedgerDbSize {
+    /// String representing the object's type. Objects of the same type share
+    /// the same value.
+    pub object: String,
+
+    /// Path of the ledger database directory.
+    pub path: String,
+
+    /// Size of the ledger database files, in bytes.
+    pub size_bytes: String,
+
+    /// The size, in bytes, the ledger database may grow to. Ledger sync
+    /// pauses as the ledger nears it.
+    pub max_size_bytes: String,
+
+    /// Free space, in bytes, on the volume holding the ledger database.
+    pub free_bytes: Option<String>,
+
+    /// Space the ledger database has left to grow into, one of "ok", "low"
+    /// or "critical". Ledger sync is paused while this is "critical".
+    pub disk_space_status: String,
+}
+
+impl From<&service::ledger_db::LedgerDbSize> for LedgerDbSize {
+    fn from(src: &service::ledger_db::LedgerDbSize) -> LedgerDbSize {
+        LedgerDbSize {
+            object: "ledger_db_size".to_string(),
+            path: src.path.display().to_string(),
+            size_bytes: src.size_bytes.to_string(),
+            max_size_bytes: src.max_size_bytes.to_string(),
+            free_bytes: src.space.map(|space| space.available_bytes.to_string()),
+            disk_space_status: src.level.to_string(),
+        }
+    }
+}
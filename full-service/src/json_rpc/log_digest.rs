@@ -0,0 +1,112 @@
+// Copyright (c) 2020-2022 MobileCoin Inc.
+
+//! API definitions for the Log Digest and Log Digest Diff objects.
+
+use crate::service;
+use serde_derive::{Deserialize, Serialize};
+use std::{collections::BTreeMap, convert::TryFrom};
+
+/// The ids and statuses of an account's transaction logs and txos, for
+/// comparing with another instance.
+#[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+pub struct LogDigest {
+    /// String representing the object's type. Objects of the same type share
+    /// the same value.
+    pub object: String,
+
+    /// The account the digest was made of.
+    pub account_id: String,
+
+    /// The next block the account would scan when the digest was made.
+    pub next_block_index: String,
+
+    /// The status of each of the account's transaction logs, by id.
+    pub transaction_logs: BTreeMap<String, String>,
+
+    /// The account's status for each of its txos, by id.
+    pub txos: BTreeMap<String, String>,
+}
+
+impl From<&service::log_digest::LogDigest> for LogDigest {
+    fn from(src: &service::log_digest::LogDigest) -> LogDigest {
+        LogDigest {
+            object: "log_digest".to_string(),
+            account_id: src.account_id.clone(),
+            next_block_index: src.next_block_index.to_string(),
+            transaction_logs: src.transaction_logs.clone(),
+            txos: src.txos.clone(),
+        }
+    }
+}
+
+impl TryFrom<&LogDigest> for service::log_digest::LogDigest {
+    type Error = String;
+
+    fn try_from(src: &LogDigest) -> Result<service::log_digest::LogDigest, String> {
+        Ok(service::log_digest::LogDigest {
+            account_id: src.account_id.clone(),
+            next_block_index: src
+                .next_block_index
+                .parse::<u64>()
+                .map_err(|err| format!("Could not parse next_block_index: {}", err))?,
+            transaction_logs: src.transaction_logs.clone(),
+            txos: src.txos.clone(),
+        })
+    }
+}
+
+/// The records which differ between this instance and another instance's
+/// digest of the same account.
+#[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+pub struct LogDigestDiff {
+    /// String representing the object's type. Objects of the same type share
+    /// the same value.
+    pub object: String,
+
+    pub account_id: String,
+
+    /// The next block this instance will scan for the account.
+    pub local_next_block_index: String,
+
+    /// The next block the other instance would scan, when it made the digest.
+    pub remote_next_block_index: String,
+
+    /// Whether both instances hold the same records.
+    pub is_reconciled: bool,
+
+    /// Transaction logs in the digest which this instance doesn't have.
+    pub missing_transaction_log_ids: Vec<String>,
+
+    /// Transaction logs this instance has which aren't in the digest.
+    pub extra_transaction_log_ids: Vec<String>,
+
+    /// Transaction logs which both instances have, with different statuses.
+    pub mismatched_transaction_log_ids: Vec<String>,
+
+    /// Txos in the digest which this instance doesn't have.
+    pub missing_txo_ids: Vec<String>,
+
+    /// Txos this instance has which aren't in the digest.
+    pub extra_txo_ids: Vec<String>,
+
+    /// Txos which both instances have, with different statuses.
+    pub mismatched_txo_ids: Vec<String>,
+}
+
+impl From<&service::log_digest::LogDigestDiff> for LogDigestDiff {
+    fn from(src: &service::log_digest::LogDigestDiff) -> LogDigestDiff {
+        LogDigestDiff {
+            object: "log_digest_diff".to_string(),
+            account_id: src.account_id.clone(),
+            local_next_block_index: src.local_next_block_index.to_string(),
+            remote_next_block_index: src.remote_next_block_index.to_string(),
+            is_reconciled: src.is_empty(),
+            missing_transaction_log_ids: src.missing_transaction_log_ids.clone(),
+            extra_transaction_log_ids: src.extra_transaction_log_ids.clone(),
+            mismatched_transaction_log_ids: src.mismatched_transaction_log_ids.clone(),
+            missing_txo_ids: src.missing_txo_ids.clone(),
+            extra_txo_ids: src.extra_txo_ids.clone(),
+            mismatched_txo_ids: src.mismatched_txo_ids.clone(),
+        }
+    }
+}
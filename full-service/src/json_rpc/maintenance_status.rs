@@ -0,0 +1,49 @@
+// Copyright (c) 2020-2022 MobileCoin Inc.
+
+//! API definition for the Maintenance Status object.
+
+use crate::service;
+use serde_derive::{Deserialize, Serialize};
+
+/// Whether the wallet is in maintenance, and whether its sync has paused.
+#[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+pub struct MaintenanceStatus {
+    /// String representing the object's type. Objects of the same type share
+    /// the same value.
+    pub object: String,
+
+    /// Whether the wallet is in maintenance and only serving reads.
+    pub in_maintenance: bool,
+
+    /// When maintenance started, in seconds since the epoch.
+    pub started_time: Option<String>,
+
+    /// When maintenance is expected to end, if the operator gave an estimate.
+    pub eta_time: Option<String>,
+
+    /// Why the wallet is in maintenance, if the operator said.
+    pub reason: Option<String>,
+
+    /// Whether the sync has stopped writing to the database.
+    pub sync_paused: bool,
+}
+
+impl From<&service::maintenance::MaintenanceStatus> for MaintenanceStatus {
+    fn from(src: &service::maintenance::MaintenanceStatus) -> MaintenanceStatus {
+        MaintenanceStatus {
+            object: "maintenance_status".to_string(),
+            in_maintenance: src.window.is_some(),
+            started_time: src
+                .window
+                .as_ref()
+                .map(|window| window.started_time.to_string()),
+            eta_time: src
+                .window
+                .as_ref()
+                .and_then(|window| window.eta_time)
+                .map(|time| time.to_string()),
+            reason: src.window.as_ref().and_then(|window| window.reason.clone()),
+            sync_paused: src.sync_paused,
+        }
+    }
+}
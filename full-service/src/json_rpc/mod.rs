@@ -3,27 +3,61 @@
 //! JSON RPC 2.0 API specification for the Full Service wallet.
 
 pub mod account;
+pub mod account_import;
 pub mod account_key;
+mod account_recovery;
+mod account_rotation;
 pub mod account_secrets;
+mod account_snapshot;
+mod account_sync_record;
 mod address;
 mod amount;
+pub mod api_scope;
 mod balance;
+pub mod balance_badge;
 mod block;
+pub mod capture;
 mod confirmation_number;
+mod continuity_status;
+mod deposit;
+mod diagnostic_check;
+mod error_code;
 mod gift_code;
+mod insufficient_funds;
+mod isolated_counterparty;
 pub mod json_rpc_request;
 pub mod json_rpc_response;
+mod key_image_export;
+mod ledger_db_size;
+mod log_digest;
+mod maintenance_status;
 mod network_status;
+mod payment_request;
+mod payout_row_result;
+mod pending_outflows;
+mod quarantined_block;
 mod receiver_receipt;
+mod scheduled_payment;
+mod sweep_policy;
+mod sync_status;
+mod token;
 mod transaction_log;
+mod transaction_watch;
 pub mod tx_proposal;
 mod txo;
+mod txo_context;
+pub mod txo_export;
 mod unspent_tx_out;
+mod usage_stats;
 pub mod view_only_account;
 pub mod view_only_subaddress;
+pub mod view_only_sync_record;
 pub mod view_only_txo;
 pub mod wallet;
+mod wallet_event;
+mod wallet_settings;
 mod wallet_status;
+mod webhook;
 
 #[cfg(any(test, feature = "test_utils"))]
 pub mod api_test_utils;
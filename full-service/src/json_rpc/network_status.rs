@@ -5,7 +5,7 @@
 use crate::service;
 
 use serde_derive::{Deserialize, Serialize};
-use std::convert::TryFrom;
+use std::{collections::BTreeMap, convert::TryFrom};
 
 #[derive(Deserialize, Serialize, Default, Debug, Clone)]
 pub struct NetworkStatus {
@@ -13,9 +13,16 @@ pub struct NetworkStatus {
     /// the same value.
     pub object: String,
 
-    /// The block count of MobileCoin's distributed ledger.
+    /// The block count of MobileCoin's distributed ledger, as reported by a
+    /// majority of the peers.
     pub network_block_height: String,
 
+    /// The highest block count reported by any peer.
+    pub network_block_height_max: String,
+
+    /// The block count reported by each peer, by responder ID.
+    pub peer_block_heights: BTreeMap<String, String>,
+
     /// The local block count downloaded from the ledger. The local database
     /// is synced when the local_block_height reaches the network_block_height.
     pub local_block_height: String,
@@ -23,20 +30,91 @@ pub struct NetworkStatus {
     /// The current network fee per transaction, in pmob.
     pub fee_pmob: String,
 
+    /// The minimum fee per transaction for each token the network accepts
+    /// fees in, by token id, in the smallest unit of that token.
+    pub minimum_fees: BTreeMap<String, String>,
+
     /// The current block version
     pub block_version: String,
+
+    /// The lowest block version advertised by any peer, if any could be
+    /// reached.
+    pub network_block_version_min: Option<String>,
+
+    /// The highest block version advertised by any peer.
+    pub network_block_version_max: Option<String>,
+
+    /// Free space on the ledger and wallet database volumes, one of "ok",
+    /// "low" or "critical". Ledger sync is paused while this is "critical".
+    pub disk_space_status: Option<String>,
+
+    /// Free space, in bytes, on the volume holding the ledger database.
+    pub ledger_db_free_bytes: Option<String>,
+
+    /// Free space, in bytes, on the volume holding the wallet database.
+    pub wallet_db_free_bytes: Option<String>,
+
+    /// The newest block version this build of full-service can process.
+    pub max_supported_block_version: String,
+
+    /// Whether this build is ready for the block version the network
+    /// advertises, one of "unknown", "ready", "upgrade_soon" or
+    /// "unsupported".
+    pub upgrade_status: String,
+
+    /// Problems found comparing the network's block version and minimum fees
+    /// against what this build supports.
+    pub upgrade_warnings: Vec<String>,
 }
 
 impl TryFrom<&service::balance::NetworkStatus> for NetworkStatus {
     type Error = String;
 
     fn try_from(src: &service::balance::NetworkStatus) -> Result<NetworkStatus, String> {
+        let free_bytes = |name: &str| {
+            src.disk_space
+                .as_ref()
+                .and_then(|disk_space| disk_space.volume(name))
+                .and_then(|volume| volume.space)
+                .map(|space| space.available_bytes.to_string())
+        };
         Ok(NetworkStatus {
             object: "network_status".to_string(),
             network_block_height: src.network_block_height.to_string(),
+            network_block_height_max: src.network_block_height_max.to_string(),
+            peer_block_heights: src
+                .peer_block_heights
+                .iter()
+                .map(|(peer, height)| (peer.clone(), height.to_string()))
+                .collect(),
             local_block_height: src.local_block_height.to_string(),
             fee_pmob: src.fee_pmob.to_string(),
+            minimum_fees: src
+                .minimum_fees
+                .iter()
+                .map(|(token_id, fee)| (token_id.to_string(), fee.to_string()))
+                .collect(),
             block_version: src.block_version.to_string(),
+            network_block_version_min: src
+                .upgrade_readiness
+                .lowest_network_block_version
+                .map(|version| version.to_string()),
+            network_block_version_max: src
+                .upgrade_readiness
+                .highest_network_block_version
+                .map(|version| version.to_string()),
+            disk_space_status: src
+                .disk_space
+                .as_ref()
+                .map(|disk_space| disk_space.level().to_string()),
+            ledger_db_free_bytes: free_bytes("ledger_db"),
+            wallet_db_free_bytes: free_bytes("wallet_db"),
+            max_supported_block_version: src
+                .upgrade_readiness
+                .max_supported_block_version
+                .to_string(),
+            upgrade_status: src.upgrade_readiness.status.to_string(),
+            upgrade_warnings: src.upgrade_readiness.warnings.clone(),
         })
     }
 }
@@ -0,0 +1,80 @@
+// Copyright (c) 2020-2022 MobileCoin Inc.
+
+//! API definition for the Payment Request object.
+
+use crate::db::{
+    self,
+    models::{PAYMENT_REQUEST_STATUS_LATE, PAYMENT_REQUEST_STATUS_PAID},
+};
+use chrono::{offset::TimeZone, Utc};
+use serde_derive::{Deserialize, Serialize};
+
+/// A request for payment to one of an account's subaddresses.
+#[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+pub struct PaymentRequest {
+    /// String representing the object's type. Objects of the same type share
+    /// the same value.
+    pub object: String,
+
+    /// Unique identifier for the payment request, which senders put in their
+    /// memos to pay it.
+    pub payment_request_id: String,
+
+    /// The account being paid.
+    pub account_id: String,
+
+    /// The subaddress being paid.
+    pub subaddress_index: String,
+
+    /// The value requested, in the token's smallest unit.
+    pub value: String,
+
+    /// The token requested.
+    pub token_id: String,
+
+    /// The memo shown to the payer.
+    pub memo: String,
+
+    /// The last block in which a payment is on time, if the request expires.
+    pub expiry_block_index: Option<String>,
+
+    /// One of "pending", "paid" or "late".
+    pub status: String,
+
+    /// The received txo which paid the request, once it is paid.
+    pub paid_txo_id: Option<String>,
+
+    /// The value of the txo which paid the request.
+    pub paid_value: Option<String>,
+
+    /// The block in which the request was paid.
+    pub paid_block_index: Option<String>,
+
+    /// The time at which the request was created.
+    pub created_time: String,
+}
+
+impl From<&db::models::PaymentRequest> for PaymentRequest {
+    fn from(src: &db::models::PaymentRequest) -> PaymentRequest {
+        let status = match src.status.as_str() {
+            PAYMENT_REQUEST_STATUS_PAID => "paid",
+            PAYMENT_REQUEST_STATUS_LATE => "late",
+            _ => "pending",
+        };
+        PaymentRequest {
+            object: "payment_request".to_string(),
+            payment_request_id: src.id.to_string(),
+            account_id: src.account_id_hex.clone(),
+            subaddress_index: src.subaddress_index.to_string(),
+            value: (src.value as u64).to_string(),
+            token_id: (src.token_id as u64).to_string(),
+            memo: src.memo.clone(),
+            expiry_block_index: src.expiry_block_index.map(|index| index.to_string()),
+            status: status.to_string(),
+            paid_txo_id: src.paid_txo_id_hex.clone(),
+            paid_value: src.paid_value.map(|value| (value as u64).to_string()),
+            paid_block_index: src.paid_block_index.map(|index| index.to_string()),
+            created_time: Utc.timestamp(src.created_time, 0).to_string(),
+        }
+    }
+}
@@ -0,0 +1,43 @@
+// Copyright (c) 2020-2022 MobileCoin Inc.
+
+//! API definition for the Payout Row Result object.
+
+use crate::service::payout;
+
+use serde_derive::{Deserialize, Serialize};
+
+#[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+pub struct PayoutRowResult {
+    /// String representing the object's type. Objects of the same type share
+    /// the same value.
+    pub object: String,
+
+    /// The 1-based index of the row in the payout file, excluding any header.
+    pub row: String,
+
+    /// The caller-supplied identifier of the payout.
+    pub external_id: String,
+
+    /// Whether the payout was submitted.
+    pub accepted: bool,
+
+    /// Why the payout was rejected.
+    pub error: Option<String>,
+
+    /// The transaction log of the transaction containing the payout, if it
+    /// was accepted.
+    pub transaction_log_id: Option<String>,
+}
+
+impl From<&payout::PayoutRowResult> for PayoutRowResult {
+    fn from(src: &payout::PayoutRowResult) -> PayoutRowResult {
+        PayoutRowResult {
+            object: "payout_row_result".to_string(),
+            row: src.row.to_string(),
+            external_id: src.external_id.clone(),
+            accepted: src.accepted,
+            error: src.error.clone(),
+            transaction_log_id: src.transaction_log_id.clone(),
+        }
+    }
+}
@@ -0,0 +1,52 @@
+// Copyright (c) 2020-2022 MobileCoin Inc.
+
+//! API definition for the Pending Outflows object.
+
+use crate::service;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// The value committed by the wallet's pending transactions, which have been
+/// submitted but have not yet landed or expired. Values are in each token's
+/// smallest unit, which for MOB is pico MOB.
+#[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+pub struct PendingOutflows {
+    /// String representing the object's type. Objects of the same type share
+    /// the same value.
+    pub object: String,
+
+    /// The number of pending transactions, across all accounts.
+    pub num_transactions: String,
+
+    /// The value sent to recipients, by token id. Change is not included.
+    pub value_per_token: BTreeMap<String, String>,
+
+    /// The network fees paid, by fee token id.
+    pub fee_per_token: BTreeMap<String, String>,
+
+    /// The number of distinct addresses the transactions pay.
+    pub num_recipients: String,
+
+    /// The lowest tombstone block of the transactions, by which the first of
+    /// them will have either landed or expired.
+    pub earliest_tombstone_block: Option<String>,
+}
+
+impl From<&service::transaction_log::PendingOutflows> for PendingOutflows {
+    fn from(src: &service::transaction_log::PendingOutflows) -> PendingOutflows {
+        let per_token = |values: &BTreeMap<u64, u128>| {
+            values
+                .iter()
+                .map(|(token_id, value)| (token_id.to_string(), value.to_string()))
+                .collect()
+        };
+        PendingOutflows {
+            object: "pending_outflows".to_string(),
+            num_transactions: src.num_transactions.to_string(),
+            value_per_token: per_token(&src.values),
+            fee_per_token: per_token(&src.fees),
+            num_recipients: src.num_recipients.to_string(),
+            earliest_tombstone_block: src.earliest_tombstone_block.map(|i| i.to_string()),
+        }
+    }
+}
@@ -0,0 +1,39 @@
+// Copyright (c) 2020-2022 MobileCoin Inc.
+
+//! API definition for the Quarantined Block object.
+
+use crate::db;
+use chrono::{offset::TimeZone, Utc};
+use serde_derive::{Deserialize, Serialize};
+
+/// A ledger block which the sync could not process.
+#[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+pub struct QuarantinedBlock {
+    /// String representing the object's type. Objects of the same type share
+    /// the same value.
+    pub object: String,
+
+    /// The index of the block.
+    pub block_index: String,
+
+    /// Why the block could not be processed, when it was first found.
+    pub reason: String,
+
+    /// The time at which the block was quarantined.
+    pub quarantined_time: String,
+
+    /// Whether the block is on the skip list, and is passed over by the sync.
+    pub skipped: bool,
+}
+
+impl From<&db::models::QuarantinedBlock> for QuarantinedBlock {
+    fn from(src: &db::models::QuarantinedBlock) -> QuarantinedBlock {
+        QuarantinedBlock {
+            object: "quarantined_block".to_string(),
+            block_index: (src.block_index as u64).to_string(),
+            reason: src.reason.clone(),
+            quarantined_time: Utc.timestamp(src.quarantined_time, 0).to_string(),
+            skipped: src.skipped,
+        }
+    }
+}
@@ -0,0 +1,100 @@
+// Copyright (c) 2020-2022 MobileCoin Inc.
+
+//! API definition for the Scheduled Payment object.
+
+use crate::db::{
+    self,
+    models::{
+        SCHEDULED_PAYMENT_STATUS_CANCELLED, SCHEDULED_PAYMENT_STATUS_COMPLETED,
+        SCHEDULED_PAYMENT_STATUS_FAILED,
+    },
+};
+use chrono::{offset::TimeZone, Utc};
+use serde_derive::{Deserialize, Serialize};
+
+/// A payment to be made once the ledger reaches a block height, or every
+/// interval.
+#[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+pub struct ScheduledPayment {
+    /// String representing the object's type. Objects of the same type share
+    /// the same value.
+    pub object: String,
+
+    /// Unique identifier for the scheduled payment.
+    pub scheduled_payment_id: String,
+
+    /// The account making the payment.
+    pub account_id: String,
+
+    /// The b58-encoded address being paid.
+    pub recipient_public_address: String,
+
+    /// The value of each payment, in the token's smallest unit.
+    pub value: String,
+
+    /// The token being paid.
+    pub token_id: String,
+
+    /// How often a recurring payment is made.
+    pub interval_seconds: Option<String>,
+
+    /// The number of blocks the ledger must have before a one-off payment is
+    /// made.
+    pub block_height: Option<String>,
+
+    /// The time at which the payment is next due, once any block height is
+    /// reached.
+    pub next_run_time: String,
+
+    /// One of "active", "completed", "failed" or "cancelled".
+    pub status: String,
+
+    /// The comment recorded on the payments' transaction logs.
+    pub comment: String,
+
+    /// How many payments have been submitted.
+    pub payments_submitted: String,
+
+    /// The time at which the payment was last due, if it has been.
+    pub last_run_time: Option<String>,
+
+    /// The transaction submitted by the most recent payment, if any.
+    pub last_transaction_log_id: Option<String>,
+
+    /// Why the most recent payment failed, if it did.
+    pub last_failure_message: Option<String>,
+
+    /// The time at which the payment was scheduled.
+    pub created_time: String,
+}
+
+impl From<&db::models::ScheduledPayment> for ScheduledPayment {
+    fn from(src: &db::models::ScheduledPayment) -> ScheduledPayment {
+        let status = match src.status.as_str() {
+            SCHEDULED_PAYMENT_STATUS_COMPLETED => "completed",
+            SCHEDULED_PAYMENT_STATUS_FAILED => "failed",
+            SCHEDULED_PAYMENT_STATUS_CANCELLED => "cancelled",
+            _ => "active",
+        };
+        ScheduledPayment {
+            object: "scheduled_payment".to_string(),
+            scheduled_payment_id: src.id.to_string(),
+            account_id: src.account_id_hex.clone(),
+            recipient_public_address: src.recipient_address.clone(),
+            value: (src.value as u64).to_string(),
+            token_id: (src.token_id as u64).to_string(),
+            interval_seconds: src.interval_seconds.map(|interval| interval.to_string()),
+            block_height: src.block_height.map(|height| height.to_string()),
+            next_run_time: Utc.timestamp(src.next_run_time, 0).to_string(),
+            status: status.to_string(),
+            comment: src.comment.clone(),
+            payments_submitted: src.payments_submitted.to_string(),
+            last_run_time: src
+                .last_run_time
+                .map(|time| Utc.timestamp(time, 0).to_string()),
+            last_transaction_log_id: src.last_transaction_log_id.clone(),
+            last_failure_message: src.last_failure_message.clone(),
+            created_time: Utc.timestamp(src.created_time, 0).to_string(),
+        }
+    }
+}
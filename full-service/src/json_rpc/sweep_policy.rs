@@ -0,0 +1,63 @@
+// Copyright (c) 2020-2022 MobileCoin Inc.
+
+//! API definition for the Sweep Policy object.
+
+use crate::db;
+use chrono::{offset::TimeZone, Utc};
+use serde_derive::{Deserialize, Serialize};
+
+/// A policy which periodically sweeps an account's balance above a threshold
+/// to another address, such as a cold wallet.
+#[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+pub struct SweepPolicy {
+    /// String representing the object's type. Objects of the same type share
+    /// the same value.
+    pub object: String,
+
+    /// The account being swept.
+    pub account_id: String,
+
+    /// The b58-encoded address receiving the swept funds.
+    pub destination_address: String,
+
+    /// The token being swept.
+    pub token_id: String,
+
+    /// The balance left in the account after each sweep, in the token's
+    /// smallest unit.
+    pub threshold: String,
+
+    /// How often the policy runs.
+    pub interval_seconds: String,
+
+    /// The time at which the policy is next due to run.
+    pub next_run_time: String,
+
+    /// The time at which the policy last ran, if it has.
+    pub last_run_time: Option<String>,
+
+    /// The transaction submitted by the most recent sweep, if any.
+    pub last_transaction_log_id: Option<String>,
+
+    /// Why the most recent run failed, if it did.
+    pub last_failure_message: Option<String>,
+}
+
+impl From<&db::models::SweepPolicy> for SweepPolicy {
+    fn from(src: &db::models::SweepPolicy) -> SweepPolicy {
+        SweepPolicy {
+            object: "sweep_policy".to_string(),
+            account_id: src.account_id_hex.clone(),
+            destination_address: src.destination_address.clone(),
+            token_id: (src.token_id as u64).to_string(),
+            threshold: (src.threshold as u64).to_string(),
+            interval_seconds: src.interval_seconds.to_string(),
+            next_run_time: Utc.timestamp(src.next_run_time, 0).to_string(),
+            last_run_time: src
+                .last_run_time
+                .map(|time| Utc.timestamp(time, 0).to_string()),
+            last_transaction_log_id: src.last_transaction_log_id.clone(),
+            last_failure_message: src.last_failure_message.clone(),
+        }
+    }
+}
@@ -0,0 +1,89 @@
+// Copyright (c) 2020-2022 MobileCoin Inc.
+
+//! API definition for the Sync Status object.
+
+use crate::service;
+use serde_derive::{Deserialize, Serialize};
+
+/// How far the sync has got with an account.
+#[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+pub struct AccountSyncStatus {
+    /// String representing the object's type. Objects of the same type share
+    /// the same value.
+    pub object: String,
+
+    pub account_id: String,
+
+    /// The next block the account will scan.
+    pub next_block_index: String,
+
+    /// The blocks in the local ledger which the account has yet to scan.
+    pub blocks_remaining: String,
+
+    /// One of "synced", "syncing", "waiting" or "paused".
+    pub status: String,
+
+    /// The error the account's last sync failed with, if it did.
+    pub last_error: Option<String>,
+
+    /// How many blocks a second the account was scanned at over the last few
+    /// minutes, or None if it scanned none.
+    pub blocks_per_second: Option<String>,
+
+    /// How many seconds until the account reaches the network's block
+    /// height, at its scan rate. None if it is paused or has no scan rate.
+    pub eta_seconds: Option<String>,
+}
+
+impl From<&service::sync_status::AccountSyncStatus> for AccountSyncStatus {
+    fn from(src: &service::sync_status::AccountSyncStatus) -> AccountSyncStatus {
+        AccountSyncStatus {
+            object: "account_sync_status".to_string(),
+            account_id: src.account_id_hex.clone(),
+            next_block_index: src.next_block_index.to_string(),
+            blocks_remaining: src.blocks_remaining.to_string(),
+            status: src.status.to_string(),
+            last_error: src.last_error.clone(),
+            blocks_per_second: src
+                .blocks_per_second
+                .map(|blocks_per_second| format!("{:.1}", blocks_per_second)),
+            eta_seconds: src.eta_seconds.map(|eta_seconds| eta_seconds.to_string()),
+        }
+    }
+}
+
+/// How far the sync has got with every account in the wallet.
+#[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+pub struct SyncStatus {
+    /// String representing the object's type. Objects of the same type share
+    /// the same value.
+    pub object: String,
+
+    /// The number of blocks in the local ledger.
+    pub local_block_height: String,
+
+    /// The number of blocks on the network, or in the local ledger if it is
+    /// ahead of the network's peers.
+    pub network_block_height: String,
+
+    /// The number of accounts which can be synced at once.
+    pub sync_threads: String,
+
+    /// Whether the sync thread has made progress in the last few minutes.
+    pub sync_thread_healthy: bool,
+
+    pub accounts: Vec<AccountSyncStatus>,
+}
+
+impl From<&service::sync_status::SyncStatus> for SyncStatus {
+    fn from(src: &service::sync_status::SyncStatus) -> SyncStatus {
+        SyncStatus {
+            object: "sync_status".to_string(),
+            local_block_height: src.local_block_height.to_string(),
+            network_block_height: src.network_block_height.to_string(),
+            sync_threads: src.sync_threads.to_string(),
+            sync_thread_healthy: src.sync_thread_healthy,
+            accounts: src.accounts.iter().map(AccountSyncStatus::from).collect(),
+        }
+    }
+}
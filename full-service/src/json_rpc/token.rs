@@ -0,0 +1,41 @@
+// Copyright (c) 2020-2022 MobileCoin Inc.
+
+//! API definition for the Token object.
+
+use crate::service;
+
+use serde_derive::{Deserialize, Serialize};
+
+/// A token which amounts in the wallet can be denominated in.
+#[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+pub struct Token {
+    /// String representing the object's type. Objects of the same type share
+    /// the same value.
+    pub object: String,
+
+    /// The id of the token, as it appears in amounts and fees.
+    pub token_id: String,
+
+    /// The token's symbol, e.g. "MOB", if known.
+    pub symbol: Option<String>,
+
+    /// The number of decimal places between the token's smallest unit and its
+    /// display unit, if known. For MOB this is 12, as amounts are in pmob.
+    pub decimals: Option<String>,
+
+    /// The minimum fee for a transaction paying its fee in this token, in the
+    /// token's smallest unit, if known.
+    pub minimum_fee: Option<String>,
+}
+
+impl From<&service::token::TokenMetadata> for Token {
+    fn from(src: &service::token::TokenMetadata) -> Token {
+        Token {
+            object: "token".to_string(),
+            token_id: src.token_id.to_string(),
+            symbol: src.symbol.clone(),
+            decimals: src.decimals.map(|d| d.to_string()),
+            minimum_fee: src.minimum_fee.map(|f| f.to_string()),
+        }
+    }
+}
@@ -5,7 +5,10 @@
 use chrono::{offset::TimeZone, Utc};
 use serde::{Deserialize, Serialize};
 
-use crate::{db, db::transaction_log::AssociatedTxos};
+use crate::{
+    db,
+    db::{models::TX_DIRECTION_RECEIVED, transaction_log::AssociatedTxos},
+};
 
 /// A log of a transaction that occurred on the MobileCoin network, constructed
 /// and/or submitted from an account in this wallet.
@@ -80,6 +83,14 @@ pub struct TransactionLog {
 
     /// Human parsable explanation of "failed" status.
     pub failure_message: Option<String>,
+
+    /// The address hash of the sender, from the sender memo of a received
+    /// Txo. Only available if direction is "received".
+    pub sender_address_hash: Option<String>,
+
+    /// The transaction submitted in place of this one after it passed its
+    /// tombstone block without landing, if it was resubmitted.
+    pub resubmitted_transaction_log_id: Option<String>,
 }
 
 impl TransactionLog {
@@ -88,6 +99,14 @@ impl TransactionLog {
         associated_txos: &AssociatedTxos,
     ) -> Self {
         let assigned_address_id = transaction_log.assigned_subaddress_b58.clone();
+        let sender_address_hash = if transaction_log.direction == TX_DIRECTION_RECEIVED {
+            associated_txos
+                .outputs
+                .iter()
+                .find_map(|txo| txo.sender_address_hash.clone())
+        } else {
+            None
+        };
         Self {
             object: "transaction_log".to_string(),
             transaction_log_id: transaction_log.transaction_id_hex.clone(),
@@ -113,6 +132,8 @@ impl TransactionLog {
             comment: transaction_log.comment.clone(),
             failure_code: None,    // FIXME: WS-17 Failiure code
             failure_message: None, // FIXME: WS-17 Failure message
+            sender_address_hash,
+            resubmitted_transaction_log_id: transaction_log.resubmitted_transaction_id_hex.clone(),
         }
     }
 }
@@ -128,6 +149,9 @@ pub struct TxoAbbrev {
     /// Available pico MOB for this Txo.
     /// If the account is syncing, this value may change.
     pub value_pmob: String,
+
+    /// The address hash of the sender, from the Txo's sender memo.
+    pub sender_address_hash: Option<String>,
 }
 
 impl TxoAbbrev {
@@ -136,6 +160,7 @@ impl TxoAbbrev {
             txo_id_hex: txo.txo_id_hex.clone(),
             recipient_address_id: txo.recipient_public_address_b58.clone(),
             value_pmob: (txo.value as u64).to_string(),
+            sender_address_hash: txo.sender_address_hash.clone(),
         }
     }
 }
@@ -0,0 +1,60 @@
+// Copyright (c) 2020-2022 MobileCoin Inc.
+
+//! API definition for the Transaction Watch object.
+
+use crate::db::{self, transaction_watch::TransactionWatchModel};
+
+use serde_derive::{Deserialize, Serialize};
+use std::convert::TryFrom;
+
+/// A transaction built outside of this wallet, whose confirmation is being
+/// tracked by the public keys of its outputs.
+#[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+pub struct TransactionWatch {
+    /// String representing the object's type. Objects of the same type share
+    /// the same value.
+    pub object: String,
+
+    /// Unique identifier for the watch. This is the public key of the first
+    /// output of the transaction.
+    pub watch_id: String,
+
+    /// The public keys of the outputs of the transaction.
+    pub tx_public_keys: Vec<String>,
+
+    /// The block at which the transaction expires, if known.
+    pub tombstone_block_index: Option<String>,
+
+    /// Current status of the watch, one of "watch_status_pending",
+    /// "watch_status_landed" or "watch_status_expired".
+    pub status: String,
+
+    /// The block in which the transaction landed, or at which it expired.
+    pub finalized_block_index: Option<String>,
+
+    /// An arbitrary string attached to the watch.
+    pub comment: String,
+}
+
+impl TryFrom<&db::models::TransactionWatch> for TransactionWatch {
+    type Error = String;
+
+    fn try_from(src: &db::models::TransactionWatch) -> Result<TransactionWatch, String> {
+        let tx_public_keys = src
+            .public_keys()
+            .map_err(|err| format!("Could not decode tx public keys: {:?}", err))?;
+
+        Ok(TransactionWatch {
+            object: "transaction_watch".to_string(),
+            watch_id: src.watch_id_hex.clone(),
+            tx_public_keys: tx_public_keys
+                .iter()
+                .map(|public_key| hex::encode(mc_util_serial::encode(public_key)))
+                .collect(),
+            tombstone_block_index: src.tombstone_block_index.map(|i| (i as u64).to_string()),
+            status: src.status.clone(),
+            finalized_block_index: src.finalized_block_index.map(|i| (i as u64).to_string()),
+            comment: src.comment.clone(),
+        })
+    }
+}
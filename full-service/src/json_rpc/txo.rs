@@ -30,9 +30,14 @@ pub struct Txo {
     pub txo_id_hex: String,
 
     /// Available pico MOB for this account at the current account_block_height.
-    /// If the account is syncing, this value may change.
+    /// If the account is syncing, this value may change. For Txos of other
+    /// tokens, this is the value in the token's smallest unit.
     pub value_pmob: String,
 
+    /// The token the Txo's value is in, which may be one the wallet has no
+    /// metadata for.
+    pub token_id: String,
+
     /// Unique identifier for the recipient associated account. Only available
     /// if direction is "sent".
     pub recipient_address_id: Option<String>,
@@ -100,6 +105,27 @@ pub struct Txo {
     /// A confirmation number that the sender of the Txo can provide to verify
     /// that they participated in the construction of this Txo.
     pub confirmation: Option<String>,
+
+    /// The address of a contact which this Txo's sender memo impersonates, if
+    /// it was flagged as a possible address poisoning attempt.
+    pub mimicked_address: Option<String>,
+
+    /// The address hash of the sender, from the Txo's sender memo. The memo is
+    /// only authenticated against addresses the account has sent to, so a
+    /// hash which matches none of them may be made up.
+    pub sender_address_hash: Option<String>,
+
+    /// For change, the address hash of the recipient of the transaction which
+    /// minted the Txo, from its destination memo.
+    pub destination_address_hash: Option<String>,
+
+    /// Whether the Txo is frozen, which keeps it out of transactions until it
+    /// is unfrozen.
+    pub frozen: bool,
+
+    /// The label the wallet's txo classifier gave the Txo when it was
+    /// received, if it gave one.
+    pub classification: Option<String>,
 }
 
 impl From<&db::models::Txo> for Txo {
@@ -146,6 +172,7 @@ impl From<&db::models::Txo> for Txo {
             object: "txo".to_string(),
             txo_id_hex: txo.txo_id_hex.clone(),
             value_pmob: (txo.value as u64).to_string(),
+            token_id: (txo.token_id as u64).to_string(),
             recipient_address_id: None,
             received_block_index: txo.received_block_index.map(|x| (x as u64).to_string()),
             spent_block_index: txo.spent_block_index.map(|x| (x as u64).to_string()),
@@ -159,6 +186,11 @@ impl From<&db::models::Txo> for Txo {
             assigned_address: None,
             key_image: txo.key_image.as_ref().map(|k| hex::encode(&k)),
             confirmation: txo.confirmation.as_ref().map(hex::encode),
+            mimicked_address: txo.mimicked_address_b58.clone(),
+            sender_address_hash: txo.sender_address_hash.clone(),
+            destination_address_hash: txo.destination_address_hash.clone(),
+            frozen: txo.frozen,
+            classification: txo.classification.clone(),
             account_status_map,
         }
     }
@@ -0,0 +1,72 @@
+// Copyright (c) 2020-2022 MobileCoin Inc.
+
+//! API definition for the Txo Context object.
+
+use crate::service;
+use serde_derive::{Deserialize, Serialize};
+
+/// How a Txo was received and spent, and how deep it is in the ledger.
+#[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+pub struct TxoContext {
+    /// String representing the object's type. Objects of the same type share
+    /// the same value.
+    pub object: String,
+
+    /// The subaddress index the Txo was received at, if it is assigned.
+    pub received_subaddress_index: Option<String>,
+
+    /// The address the Txo was received at, if it is assigned.
+    pub received_address: Option<String>,
+
+    /// The label of the address the Txo was received at.
+    pub received_address_label: Option<String>,
+
+    /// The id of the transaction log of the transaction which spends the Txo,
+    /// if it was sent from this wallet.
+    pub spending_transaction_log_id: Option<String>,
+
+    /// The status of the transaction which spends the Txo.
+    pub spending_transaction_log_status: Option<String>,
+
+    /// The block the Txo is in, if it is in the ledger.
+    pub block_index: Option<String>,
+
+    /// The number of blocks from the Txo's block to the end of the ledger,
+    /// counting its own.
+    pub confirmations: String,
+
+    /// Whether the Txo is in the ledger, and buried under the wallet's
+    /// confirmation depth.
+    pub confirmation_status: String,
+}
+
+impl From<&service::txo::TxoContext> for TxoContext {
+    fn from(src: &service::txo::TxoContext) -> TxoContext {
+        TxoContext {
+            object: "txo_context".to_string(),
+            received_subaddress_index: src
+                .received_subaddress
+                .as_ref()
+                .map(|subaddress| (subaddress.subaddress_index as u64).to_string()),
+            received_address: src
+                .received_subaddress
+                .as_ref()
+                .map(|subaddress| subaddress.assigned_subaddress_b58.clone()),
+            received_address_label: src
+                .received_subaddress
+                .as_ref()
+                .map(|subaddress| subaddress.comment.clone()),
+            spending_transaction_log_id: src
+                .spending_transaction_log
+                .as_ref()
+                .map(|log| log.transaction_id_hex.clone()),
+            spending_transaction_log_status: src
+                .spending_transaction_log
+                .as_ref()
+                .map(|log| log.status.clone()),
+            block_index: src.block_index.map(|i| i.to_string()),
+            confirmations: src.confirmations.to_string(),
+            confirmation_status: src.confirmation_status.to_string(),
+        }
+    }
+}
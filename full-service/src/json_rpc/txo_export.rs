@@ -0,0 +1,366 @@
+// Copyright (c) 2020-2022 MobileCoin Inc.
+
+//! Streaming export of txos as newline-delimited JSON or CSV.
+//!
+//! This is served outside of the JSON-RPC endpoint, so that large wallets can
+//! be exported in a single response rather than paging through get_txos.
+//!
+//! A signed export is buffered rather than streamed, since the signature over
+//! the whole body is returned in a header ahead of it.
+
+use crate::{
+    db::{
+        models::{
+            Txo, TXO_STATUS_ORPHANED, TXO_STATUS_PENDING, TXO_STATUS_SECRETED, TXO_STATUS_SPENT,
+            TXO_STATUS_UNSPENT,
+        },
+        txo::{TxoExportFilter, TxoModel},
+    },
+    service::{
+        reporting::ReportingService,
+        txo::{TxoExportPages, TxoService},
+    },
+    util::{canonical_json::canonicalize, csv::csv_escape},
+    WalletService,
+};
+use mc_connection::{BlockchainConnection, UserTxConnection};
+use mc_fog_report_validation::FogPubkeyResolver;
+use rocket::{
+    http::{ContentType, Status},
+    response::{status::Custom, Response},
+};
+use serde_derive::{Deserialize, Serialize};
+use std::{
+    io::{self, Cursor, Read},
+    str::FromStr,
+};
+
+/// Header carrying the hex encoded Ed25519 signature of a signed export.
+pub const EXPORT_SIGNATURE_HEADER: &str = "X-Export-Signature";
+
+/// Header carrying the hex encoded reporting public key which signed an export.
+pub const EXPORT_SIGNING_KEY_HEADER: &str = "X-Export-Signing-Key";
+
+/// The format to export txos in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// One JSON object per line.
+    Ndjson,
+
+    /// Comma separated values, with a header row.
+    Csv,
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ndjson" => Ok(ExportFormat::Ndjson),
+            "csv" => Ok(ExportFormat::Csv),
+            _ => Err(format!("Unknown export format: {}", s)),
+        }
+    }
+}
+
+/// The body of a request to export txos. All fields are optional.
+#[derive(Deserialize, Serialize, Default, Debug, Clone)]
+pub struct ExportTxosRequest {
+    /// Only export txos received or minted by this account.
+    pub account_id: Option<String>,
+
+    /// Only export txos with this status, e.g. "txo_status_unspent".
+    pub status: Option<String>,
+
+    /// Only export txos of this token.
+    pub token_id: Option<String>,
+
+    /// Only export txos received at or after this block index.
+    pub min_received_block_index: Option<String>,
+
+    /// Only export txos received at or before this block index.
+    pub max_received_block_index: Option<String>,
+
+    /// Only export txos the txo classifier gave this label.
+    pub classification: Option<String>,
+
+    /// "ndjson" (the default) or "csv".
+    pub format: Option<String>,
+
+    /// Resume the export after the row with this cursor.
+    pub cursor: Option<String>,
+
+    /// The maximum number of rows to export.
+    pub limit: Option<String>,
+
+    /// Sign the export with the wallet's reporting key. The signature is
+    /// returned in the X-Export-Signature header.
+    pub signed: Option<bool>,
+}
+
+/// A single exported txo.
+#[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+pub struct ExportedTxo {
+    /// Pass as the cursor of a subsequent export to resume after this row.
+    pub cursor: String,
+
+    pub txo_id_hex: String,
+
+    pub value: String,
+
+    pub token_id: String,
+
+    pub received_account_id: Option<String>,
+
+    /// Status of the txo with respect to the received account.
+    pub received_status: Option<String>,
+
+    pub minted_account_id: Option<String>,
+
+    /// Status of the txo with respect to the minted account.
+    pub minted_status: Option<String>,
+
+    pub subaddress_index: Option<String>,
+
+    pub received_block_index: Option<String>,
+
+    pub pending_tombstone_block_index: Option<String>,
+
+    pub spent_block_index: Option<String>,
+
+    pub public_key: String,
+
+    pub key_image: Option<String>,
+
+    /// The label the txo classifier gave the txo, if it gave one.
+    pub classification: Option<String>,
+}
+
+impl From<&Txo> for ExportedTxo {
+    fn from(txo: &Txo) -> ExportedTxo {
+        let received_status = if txo.is_spent() {
+            TXO_STATUS_SPENT
+        } else if txo.is_pending() {
+            TXO_STATUS_PENDING
+        } else if txo.is_orphaned() {
+            TXO_STATUS_ORPHANED
+        } else {
+            TXO_STATUS_UNSPENT
+        };
+
+        let minted_status = if txo.minted_account_id_hex != txo.received_account_id_hex {
+            TXO_STATUS_SECRETED
+        } else {
+            received_status
+        };
+
+        ExportedTxo {
+            cursor: txo.id.to_string(),
+            txo_id_hex: txo.txo_id_hex.clone(),
+            value: (txo.value as u64).to_string(),
+            token_id: (txo.token_id as u64).to_string(),
+            received_account_id: txo.received_account_id_hex.clone(),
+            received_status: txo
+                .received_account_id_hex
+                .as_ref()
+                .map(|_| received_status.to_string()),
+            minted_account_id: txo.minted_account_id_hex.clone(),
+            minted_status: txo
+                .minted_account_id_hex
+                .as_ref()
+                .map(|_| minted_status.to_string()),
+            subaddress_index: txo.subaddress_index.map(|i| (i as u64).to_string()),
+            received_block_index: txo.received_block_index.map(|i| (i as u64).to_string()),
+            pending_tombstone_block_index: txo
+                .pending_tombstone_block_index
+                .map(|i| (i as u64).to_string()),
+            spent_block_index: txo.spent_block_index.map(|i| (i as u64).to_string()),
+            public_key: hex::encode(&txo.public_key),
+            key_image: txo.key_image.as_ref().map(hex::encode),
+            classification: txo.classification.clone(),
+        }
+    }
+}
+
+/// Serializes pages of txos into the export format as they are read.
+pub struct TxoExportReader {
+    pages: TxoExportPages,
+    format: ExportFormat,
+    canonical_json: bool,
+    buffer: Vec<u8>,
+    position: usize,
+}
+
+impl TxoExportReader {
+    /// Create a reader for the given pages. NDJSON rows are written as
+    /// canonical JSON if `canonical_json` is set.
+    pub fn new(pages: TxoExportPages, format: ExportFormat, canonical_json: bool) -> Self {
+        let mut buffer = Vec::new();
+        if format == ExportFormat::Csv {
+            let columns =
+                serde_json::to_value(ExportedTxo::default()).expect("ExportedTxo is serializable");
+            let header: Vec<String> = columns
+                .as_object()
+                .expect("ExportedTxo is an object")
+                .keys()
+                .map(String::as_str)
+                .map(csv_escape)
+                .collect();
+            buffer.extend_from_slice(header.join(",").as_bytes());
+            buffer.push(b'\n');
+        }
+        Self {
+            pages,
+            format,
+            canonical_json,
+            buffer,
+            position: 0,
+        }
+    }
+
+    /// Refill the buffer from the next page. Returns false when the export is
+    /// complete.
+    fn fill_buffer(&mut self) -> io::Result<bool> {
+        let txos = match self.pages.next() {
+            Some(page) => {
+                page.map_err(|err| io::Error::new(io::ErrorKind::Other, err.to_string()))?
+            }
+            None => return Ok(false),
+        };
+
+        self.buffer.clear();
+        self.position = 0;
+        for txo in txos.iter() {
+            let row = serde_json::to_value(ExportedTxo::from(txo))
+                .map_err(|err| io::Error::new(io::ErrorKind::Other, err))?;
+            match self.format {
+                ExportFormat::Ndjson => {
+                    let row = if self.canonical_json {
+                        canonicalize(row)
+                    } else {
+                        row
+                    };
+                    serde_json::to_writer(&mut self.buffer, &row)
+                        .map_err(|err| io::Error::new(io::ErrorKind::Other, err))?;
+                    self.buffer.push(b'\n');
+                }
+                ExportFormat::Csv => {
+                    let values: Vec<String> = row
+                        .as_object()
+                        .expect("ExportedTxo is an object")
+                        .values()
+                        .map(|value| csv_escape(value.as_str().unwrap_or_default()))
+                        .collect();
+                    self.buffer.extend_from_slice(values.join(",").as_bytes());
+                    self.buffer.push(b'\n');
+                }
+            }
+        }
+        Ok(true)
+    }
+}
+
+impl Read for TxoExportReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.position >= self.buffer.len() {
+            if !self.fill_buffer()? {
+                return Ok(0);
+            }
+        }
+        let count = std::cmp::min(buf.len(), self.buffer.len() - self.position);
+        buf[..count].copy_from_slice(&self.buffer[self.position..self.position + count]);
+        self.position += count;
+        Ok(count)
+    }
+}
+
+fn parse_optional<F: FromStr>(name: &str, value: Option<String>) -> Result<Option<F>, String>
+where
+    F::Err: std::fmt::Display,
+{
+    value
+        .map(|v| {
+            v.parse::<F>()
+                .map_err(|err| format!("Invalid {} '{}': {}", name, v, err))
+        })
+        .transpose()
+}
+
+/// Handle a request to export txos, shared by the routes for each kind of
+/// wallet state.
+pub fn export_txos<T, FPR>(
+    service: &WalletService<T, FPR>,
+    request: ExportTxosRequest,
+    canonical_json: bool,
+) -> Result<Response<'static>, Custom<String>>
+where
+    T: BlockchainConnection + UserTxConnection + 'static,
+    FPR: FogPubkeyResolver + Send + Sync + 'static,
+{
+    let bad_request = |err: String| Custom(Status::BadRequest, err);
+    let internal_error = |err: String| Custom(Status::InternalServerError, err);
+
+    let format = parse_optional::<ExportFormat>("format", request.format)
+        .map_err(bad_request)?
+        .unwrap_or(ExportFormat::Ndjson);
+    let filter = TxoExportFilter {
+        account_id_hex: request.account_id,
+        status: request.status,
+        token_id: parse_optional("token_id", request.token_id).map_err(bad_request)?,
+        min_received_block_index: parse_optional(
+            "min_received_block_index",
+            request.min_received_block_index,
+        )
+        .map_err(bad_request)?,
+        max_received_block_index: parse_optional(
+            "max_received_block_index",
+            request.max_received_block_index,
+        )
+        .map_err(bad_request)?,
+        classification: request.classification,
+    };
+    let cursor = parse_optional("cursor", request.cursor).map_err(bad_request)?;
+    let limit = parse_optional("limit", request.limit).map_err(bad_request)?;
+
+    // Validate the status up front, so that a bad request gets an error status
+    // rather than an empty stream.
+    if let Some(status) = filter.status.as_deref() {
+        if ![
+            TXO_STATUS_UNSPENT,
+            TXO_STATUS_PENDING,
+            TXO_STATUS_SPENT,
+            TXO_STATUS_SECRETED,
+            TXO_STATUS_ORPHANED,
+        ]
+        .contains(&status)
+        {
+            return Err(bad_request(format!("Unknown txo status: {}", status)));
+        }
+    }
+    let pages = service.export_txos(filter, cursor, limit);
+
+    let content_type = match format {
+        ExportFormat::Ndjson => ContentType::new("application", "x-ndjson"),
+        ExportFormat::Csv => ContentType::CSV,
+    };
+    let mut reader = TxoExportReader::new(pages, format, canonical_json);
+
+    let mut response = Response::build();
+    response.header(content_type);
+    if request.signed.unwrap_or(false) {
+        let mut contents = Vec::new();
+        reader
+            .read_to_end(&mut contents)
+            .map_err(|err| internal_error(err.to_string()))?;
+        let (signature, public_key) = service
+            .sign_report(&contents)
+            .map_err(|err| internal_error(err.to_string()))?;
+        response
+            .raw_header(EXPORT_SIGNATURE_HEADER, hex::encode(signature))
+            .raw_header(EXPORT_SIGNING_KEY_HEADER, hex::encode(public_key))
+            .sized_body(Cursor::new(contents));
+    } else {
+        response.streamed_body(reader);
+    }
+    Ok(response.finalize())
+}
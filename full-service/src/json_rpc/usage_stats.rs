@@ -0,0 +1,101 @@
+// Copyright (c) 2020-2022 MobileCoin Inc.
+
+//! API definition for the Usage Stats object.
+
+use crate::service;
+use serde_derive::{Deserialize, Serialize};
+
+/// The calls made to an API method within a window.
+#[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+pub struct MethodUsage {
+    /// The API method called.
+    pub method: String,
+
+    /// How many times the method was called.
+    pub calls: String,
+
+    /// How many of the calls returned an error.
+    pub errors: String,
+
+    /// The average time taken to handle a call, in milliseconds.
+    pub average_latency_ms: String,
+}
+
+impl From<&service::usage_stats::MethodUsage> for MethodUsage {
+    fn from(src: &service::usage_stats::MethodUsage) -> MethodUsage {
+        MethodUsage {
+            method: src.method.clone(),
+            calls: src.calls.to_string(),
+            errors: src.errors.to_string(),
+            average_latency_ms: format!("{:.1}", src.average_latency_ms()),
+        }
+    }
+}
+
+/// How the wallet was used within a window ending now.
+#[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+pub struct UsageWindow {
+    /// String representing the object's type. Objects of the same type share
+    /// the same value.
+    pub object: String,
+
+    /// The length of the window, in seconds.
+    pub period_seconds: String,
+
+    /// The calls made to each method, busiest first.
+    pub methods: Vec<MethodUsage>,
+
+    /// The blocks the sync thread scanned, summed over every account.
+    pub blocks_synced: String,
+
+    /// The blocks scanned per second the sync thread was scanning, if it
+    /// scanned any.
+    pub sync_blocks_per_second: Option<String>,
+
+    /// How much the wallet database grew, in bytes, if its size was sampled
+    /// at least twice in the window.
+    pub wallet_db_growth_bytes: Option<String>,
+
+    /// How much the ledger database grew, in bytes.
+    pub ledger_db_growth_bytes: Option<String>,
+}
+
+impl From<&service::usage_stats::UsageWindow> for UsageWindow {
+    fn from(src: &service::usage_stats::UsageWindow) -> UsageWindow {
+        UsageWindow {
+            object: "usage_window".to_string(),
+            period_seconds: src.period_seconds.to_string(),
+            methods: src.methods.iter().map(MethodUsage::from).collect(),
+            blocks_synced: src.blocks_synced.to_string(),
+            sync_blocks_per_second: src
+                .sync_blocks_per_second()
+                .map(|rate| format!("{:.1}", rate)),
+            wallet_db_growth_bytes: src.wallet_db_growth_bytes.map(|bytes| bytes.to_string()),
+            ledger_db_growth_bytes: src.ledger_db_growth_bytes.map(|bytes| bytes.to_string()),
+        }
+    }
+}
+
+/// How the wallet was used over the last day and week.
+#[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+pub struct UsageStats {
+    /// String representing the object's type. Objects of the same type share
+    /// the same value.
+    pub object: String,
+
+    /// Usage over the last 24 hours.
+    pub last_24h: UsageWindow,
+
+    /// Usage over the last 7 days.
+    pub last_7d: UsageWindow,
+}
+
+impl From<&service::usage_stats::UsageStats> for UsageStats {
+    fn from(src: &service::usage_stats::UsageStats) -> UsageStats {
+        UsageStats {
+            object: "usage_stats".to_string(),
+            last_24h: UsageWindow::from(&src.last_24h),
+            last_7d: UsageWindow::from(&src.last_7d),
+        }
+    }
+}
@@ -40,6 +40,11 @@ pub struct ViewOnlyAccountJSON {
     pub change_subaddress_index: String,
 
     pub next_subaddress_index: String,
+
+    /// Whether the account's spend key is on a hardware wallet, which signs
+    /// its transactions through the transaction signer.
+    #[serde(default)]
+    pub hardware_backed: bool,
 }
 
 impl From<&db::models::ViewOnlyAccount> for ViewOnlyAccountJSON {
@@ -53,6 +58,7 @@ impl From<&db::models::ViewOnlyAccount> for ViewOnlyAccountJSON {
             main_subaddress_index: (src.main_subaddress_index as u64).to_string(),
             change_subaddress_index: (src.change_subaddress_index as u64).to_string(),
             next_subaddress_index: (src.next_subaddress_index as u64).to_string(),
+            hardware_backed: src.hardware_backed,
         }
     }
 }
@@ -68,6 +74,7 @@ impl From<&db::models::Account> for ViewOnlyAccountJSON {
             main_subaddress_index: (src.main_subaddress_index as u64).to_string(),
             change_subaddress_index: (src.change_subaddress_index as u64).to_string(),
             next_subaddress_index: (src.next_subaddress_index as u64).to_string(),
+            hardware_backed: false,
         }
     }
 }
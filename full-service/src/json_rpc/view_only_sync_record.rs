@@ -0,0 +1,60 @@
+// Copyright (c) 2020-2022 MobileCoin Inc.
+
+//! API definition for the View-Only Sync Record object.
+
+use crate::db::{self, models::SYNC_RECORD_TYPE_REQUEST};
+use chrono::{offset::TimeZone, Utc};
+use serde_derive::{Deserialize, Serialize};
+
+/// A sync request handed to the offline signer for a view-only account, or
+/// the signer's response to one.
+#[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+pub struct ViewOnlySyncRecordJSON {
+    /// String representing the object's type. Objects of the same type share
+    /// the same value.
+    pub object: String,
+
+    /// Unique identifier for the record. Records are numbered in the order
+    /// they were made.
+    pub sync_record_id: String,
+
+    /// The view-only account that was synced.
+    pub account_id: String,
+
+    /// Either "request" or "response".
+    pub record_type: String,
+
+    /// Hex-encoded digest of the txos in a request, or of the txo ids and key
+    /// images in a response.
+    pub payload_hash: String,
+
+    /// The number of txos in the request or response.
+    pub txo_count: String,
+
+    /// For a response, the id of the request it answered. A request that no
+    /// response refers to was never answered.
+    pub request_record_id: Option<String>,
+
+    /// The time at which the record was made.
+    pub created_time: String,
+}
+
+impl From<&db::models::ViewOnlySyncRecord> for ViewOnlySyncRecordJSON {
+    fn from(src: &db::models::ViewOnlySyncRecord) -> ViewOnlySyncRecordJSON {
+        let record_type = if src.record_type == SYNC_RECORD_TYPE_REQUEST {
+            "request"
+        } else {
+            "response"
+        };
+        ViewOnlySyncRecordJSON {
+            object: "view_only_sync_record".to_string(),
+            sync_record_id: src.id.to_string(),
+            account_id: src.view_only_account_id_hex.clone(),
+            record_type: record_type.to_string(),
+            payload_hash: src.payload_hash.clone(),
+            txo_count: src.txo_count.to_string(),
+            request_record_id: src.request_record_id.map(|id| id.to_string()),
+            created_time: Utc.timestamp(src.created_time, 0).to_string(),
+        }
+    }
+}
@@ -6,62 +6,140 @@ use crate::{
     db::{self, account::AccountID, transaction_log::TransactionID, txo::TxoID},
     json_rpc,
     json_rpc::{
+        account_import::AccountImportSyncPlan,
+        account_recovery::RecoveryCandidate,
+        account_rotation::AccountRotation,
         account_secrets::AccountSecrets,
+        account_snapshot::AccountSnapshot,
+        account_sync_record::AccountSyncRecord,
         address::Address,
+        api_scope::{
+            allowed_during_maintenance, check_method_with_allowlist, ApiScope, ApiScopeError,
+            MethodAllowlist,
+        },
         balance::Balance,
+        balance_badge::{balance_badge, BalanceBadgeState, ClientIp},
         block::{Block, BlockContents},
+        capture::{self, CapturedExchange, RequestCapture},
         confirmation_number::Confirmation,
+        continuity_status::ContinuityStatus,
+        deposit::Deposit,
+        diagnostic_check::DiagnosticCheck,
         gift_code::GiftCode,
+        isolated_counterparty::IsolatedCounterparty,
         json_rpc_request::{help_str, JsonCommandRequest, JsonRPCRequest},
         json_rpc_response::{
-            format_error, format_invalid_request_error, JsonCommandResponse, JsonRPCError,
+            format_entry_errors, format_error, format_invalid_request_error,
+            format_maintenance_error, format_unauthorized_error, JsonCommandResponse, JsonRPCError,
             JsonRPCResponse,
         },
+        ledger_db_size::LedgerDbSize,
+        log_digest::{LogDigest, LogDigestDiff},
+        maintenance_status::MaintenanceStatus,
         network_status::NetworkStatus,
+        payment_request::PaymentRequest,
+        payout_row_result::PayoutRowResult,
+        pending_outflows::PendingOutflows,
+        quarantined_block::QuarantinedBlock,
         receiver_receipt::ReceiverReceipt,
+        scheduled_payment::ScheduledPayment,
+        sweep_policy::SweepPolicy,
+        sync_status::SyncStatus,
+        token::Token,
+        transaction_watch::TransactionWatch,
         tx_proposal::TxProposal,
         txo::Txo,
+        txo_context::TxoContext,
+        txo_export::{export_txos, ExportTxosRequest},
+        usage_stats::UsageStats,
         view_only_subaddress::ViewOnlySubaddressJSON,
+        view_only_sync_record::ViewOnlySyncRecordJSON,
         view_only_txo::ViewOnlyTxo,
+        wallet_event::WalletEvent,
+        wallet_settings::WalletSettings,
         wallet_status::WalletStatus,
+        webhook::Webhook,
     },
+    metrics::encode_metrics,
     service,
     service::{
-        account::AccountService,
+        account::{AccountService, AccountServiceError},
+        account_recovery::AccountRecoveryService,
+        account_rotation::AccountRotationService,
+        account_snapshot::AccountSnapshotService,
         address::AddressService,
+        address_label::{AddressLabelFormat, AddressLabelService},
         balance::BalanceService,
         confirmation_number::ConfirmationService,
+        continuity::ContinuityService,
+        database_password::DatabasePasswordService,
+        deposit::DepositService,
+        diagnostics::{DiagnosticStatus, DiagnosticsService},
         gift_code::{EncodedGiftCode, GiftCodeService},
+        isolated_counterparty::IsolatedCounterpartyService,
         ledger::LedgerService,
+        ledger_db::LedgerDbService,
+        log_digest::LogDigestService,
+        maintenance::MaintenanceService,
         payment_request::PaymentRequestService,
+        payout::{PayoutFileFormat, PayoutService},
         receipt::ReceiptService,
+        reporting::ReportingService,
+        scheduled_payment::ScheduledPaymentService,
+        spending_lock::SpendingLockService,
+        sweep_policy::SweepPolicyService,
+        sync_status::SyncStatusService,
+        token::TokenService,
         transaction::TransactionService,
+        transaction_builder::ChangeOutputs,
         transaction_log::TransactionLogService,
+        transaction_watch::TransactionWatchService,
         txo::TxoService,
+        usage_stats::UsageStatsService,
         view_only_account::ViewOnlyAccountService,
         view_only_txo::ViewOnlyTxoService,
+        wallet_event::WalletEventService,
+        wallet_settings::WalletSettingsService,
+        webhook::WebhookService,
         WalletService,
     },
-    util::b58::{
-        b58_decode_payment_request, b58_encode_public_address, b58_printable_wrapper_type,
-        PrintableWrapperType,
+    util::{
+        amount_conversion::{convert_amount, AmountUnit, RoundingMode},
+        b58::{
+            b58_decode_payment_request, b58_encode_public_address, b58_printable_wrapper_type,
+            PrintableWrapperType,
+        },
+        canonical_json::to_canonical_string,
+        pagination::{
+            decode_page_cursor, encode_page_cursor, PageCursorError, KEY_IMAGE_CURSOR,
+            TRANSACTION_LOG_CURSOR, TXO_CURSOR, VIEW_ONLY_KEY_IMAGE_CURSOR, VIEW_ONLY_TXO_CURSOR,
+        },
+        secret::key_matches,
     },
 };
+use chrono::Utc;
 use mc_common::logger::global_log;
 use mc_connection::{
     BlockchainConnection, HardcodedCredentialsProvider, ThickClient, UserTxConnection,
 };
-use mc_crypto_keys::{RistrettoPrivate, RistrettoPublic};
+use mc_crypto_keys::{CompressedRistrettoPublic, RistrettoPrivate, RistrettoPublic};
 use mc_fog_report_validation::{FogPubkeyResolver, FogResolver};
 use mc_mobilecoind_json::data_types::{JsonTx, JsonTxOut};
-use mc_transaction_core::ring_signature::KeyImage;
+use mc_transaction_core::{ring_signature::KeyImage, tokens::Mob, Token as _};
 use mc_validator_connection::ValidatorConnection;
 use rocket::{
-    self, get, http::Status, outcome::Outcome, post, request::FromRequest, routes, Request, State,
+    self, catch, catchers, get,
+    http::{ContentType, Status},
+    outcome::Outcome,
+    post,
+    request::FromRequest,
+    response::{status::Custom, Content, Response},
+    routes, Request, State,
 };
 use rocket_contrib::json::Json;
+use serde::Serialize;
 use serde_json::Map;
-use std::{collections::HashMap, convert::TryFrom, iter::FromIterator};
+use std::{collections::HashMap, convert::TryFrom, iter::FromIterator, sync::Arc, time::Instant};
 
 /// State managed by rocket.
 pub struct WalletState<
@@ -76,9 +154,82 @@ pub const API_KEY_HEADER: &str = "X-API-KEY";
 
 pub struct APIKeyState(pub String);
 
+/// Additional API keys, each limited to the methods permitted by its scope.
+pub struct ScopedAPIKeysState(pub Vec<(String, ApiScope)>);
+
+/// Whether Prometheus metrics are served at /metrics.
+pub struct MetricsState(pub bool);
+
+/// The preset the wallet was started with, which limits every API key to the
+/// methods in its scope.
+pub struct ApiPresetState(pub ApiScope);
+
+/// The allowlist the wallet was started with, if any, outside of which every
+/// method is disabled.
+pub struct MethodAllowlistState(pub Option<Arc<MethodAllowlist>>);
+
+/// Header a client can set to "true" or "false" to choose whether responses
+/// are written as canonical JSON, overriding the wallet's default.
+pub const CANONICAL_JSON_HEADER: &str = "X-Canonical-JSON";
+
+/// Whether responses are written as canonical JSON by default.
+pub struct CanonicalJsonState(pub bool);
+
+/// How JSON output should be written for a request.
+pub struct JsonOutputMode {
+    /// Sort object keys and normalize numbers, so that the output is stable
+    /// across instances.
+    pub canonical: bool,
+}
+
+impl<'a, 'r> FromRequest<'a, 'r> for JsonOutputMode {
+    type Error = ();
+
+    fn from_request(req: &'a Request<'r>) -> Outcome<Self, (rocket::http::Status, ()), ()> {
+        let canonical = match req.headers().get_one(CANONICAL_JSON_HEADER) {
+            Some(value) => value.eq_ignore_ascii_case("true"),
+            None => req
+                .guard::<State<CanonicalJsonState>>()
+                .succeeded()
+                .map_or(false, |state| state.0),
+        };
+        Outcome::Success(JsonOutputMode { canonical })
+    }
+}
+
+/// Serialize a response body as JSON in the requested mode.
+pub fn render_json<T: Serialize>(
+    value: &T,
+    output_mode: &JsonOutputMode,
+) -> Result<Content<String>, String> {
+    let body = if output_mode.canonical {
+        to_canonical_string(value)
+    } else {
+        serde_json::to_string(value)
+    }
+    .map_err(|err| err.to_string())?;
+    Ok(Content(ContentType::JSON, body))
+}
+
 /// Ensures check for a pre-shared symmetric API key for the JsonRPC loop on the
 /// Mobilecoin wallet.
-pub struct ApiKeyGuard {}
+pub struct ApiKeyGuard {
+    /// The methods the client's key may call.
+    pub scope: ApiScope,
+
+    /// The methods the wallet's preset permits any key to call.
+    pub preset: ApiScope,
+
+    /// The only methods the wallet serves, if an allowlist was configured.
+    pub allowlist: Option<Arc<MethodAllowlist>>,
+}
+
+impl ApiKeyGuard {
+    /// Check that the client may call the given method.
+    pub fn check_method(&self, method: &str) -> Result<(), ApiScopeError> {
+        check_method_with_allowlist(self.allowlist.as_deref(), self.preset, self.scope, method)
+    }
+}
 
 #[derive(Debug)]
 pub enum ApiKeyError {
@@ -91,47 +242,159 @@ impl<'a, 'r> FromRequest<'a, 'r> for ApiKeyGuard {
     fn from_request(
         req: &'a Request<'r>,
     ) -> Outcome<Self, (rocket::http::Status, Self::Error), ()> {
+        let preset = req
+            .guard::<State<ApiPresetState>>()
+            .succeeded()
+            .map_or(ApiScope::Full, |state| state.0);
+        let allowlist = req
+            .guard::<State<MethodAllowlistState>>()
+            .succeeded()
+            .and_then(|state| state.0.clone());
         let client_key = req.headers().get_one(API_KEY_HEADER).unwrap_or_default();
         let local_key = &req
             .guard::<State<APIKeyState>>()
             .expect("api key state config is bad. see main.rs")
             .0;
-        if local_key == client_key {
-            Outcome::Success(ApiKeyGuard {})
-        } else {
-            Outcome::Failure((Status::Unauthorized, ApiKeyError::Invalid))
+        let scoped_keys = req
+            .guard::<State<ScopedAPIKeysState>>()
+            .succeeded()
+            .map(|state| state.inner().0.as_slice())
+            .unwrap_or_default();
+
+        // Without any key configured the API is open. Once any key is
+        // configured, a missing or empty key must never match an unset one.
+        if local_key.is_empty() && scoped_keys.is_empty() {
+            return Outcome::Success(ApiKeyGuard {
+                scope: ApiScope::Full,
+                preset,
+                allowlist,
+            });
+        }
+        if client_key.is_empty() {
+            return Outcome::Failure((Status::Unauthorized, ApiKeyError::Invalid));
+        }
+        if key_matches(local_key, client_key) {
+            return Outcome::Success(ApiKeyGuard {
+                scope: ApiScope::Full,
+                preset,
+                allowlist,
+            });
+        }
+
+        // Check every scoped key, so that the time taken doesn't reveal which
+        // of them matched.
+        let scoped_key = scoped_keys.iter().fold(None, |matched, (key, scope)| {
+            if key_matches(key, client_key) {
+                matched.or(Some(*scope))
+            } else {
+                matched
+            }
+        });
+        match scoped_key {
+            Some(scope) => Outcome::Success(ApiKeyGuard {
+                scope,
+                preset,
+                allowlist,
+            }),
+            None => Outcome::Failure((Status::Unauthorized, ApiKeyError::Invalid)),
         }
     }
 }
 
+/// Answers requests rejected by the ApiKeyGuard with a JSON-RPC error, so
+/// that clients can handle a bad API key like any other error.
+#[catch(401)]
+pub fn unauthorized(req: &Request) -> Result<Content<String>, String> {
+    let response = JsonRPCResponse {
+        method: None,
+        result: None,
+        error: Some(format_unauthorized_error()),
+        jsonrpc: "2.0".to_string(),
+        id: serde_json::Value::Null,
+    };
+    let output_mode = req
+        .guard::<JsonOutputMode>()
+        .succeeded()
+        .unwrap_or(JsonOutputMode { canonical: false });
+    render_json(&response, &output_mode)
+}
+
 fn generic_wallet_api<T, FPR>(
-    _api_key_guard: ApiKeyGuard,
+    api_key_guard: ApiKeyGuard,
+    output_mode: JsonOutputMode,
     state: rocket::State<WalletState<T, FPR>>,
+    request_capture: Option<rocket::State<RequestCapture>>,
     command: Json<JsonRPCRequest>,
-) -> Result<Json<JsonRPCResponse>, String>
+) -> Result<Content<String>, String>
 where
     T: BlockchainConnection + UserTxConnection + 'static,
     FPR: FogPubkeyResolver + Send + Sync + 'static,
 {
-    let req: JsonRPCRequest = command.0.clone();
+    let received_at_ms = capture::now_ms();
+    let start = Instant::now();
+    let response = wallet_api_response(&api_key_guard, &state.service, command.0.clone());
+
+    // Calls are counted in memory, and only occasionally written to the
+    // database.
+    if let Err(e) =
+        state
+            .service
+            .record_rpc_call(&command.0.method, response.error.is_none(), start.elapsed())
+    {
+        global_log::debug!("Could not record call to {}: {}", command.0.method, e);
+    }
+
+    if let Some(request_capture) = request_capture {
+        request_capture.record(&CapturedExchange::new(
+            received_at_ms,
+            start.elapsed().as_millis() as u64,
+            &command.0.method,
+            serde_json::to_value(&command.0).unwrap_or_default(),
+            serde_json::to_value(&response).unwrap_or_default(),
+        ));
+    }
+
+    render_json(&response, &output_mode)
+}
 
+fn wallet_api_response<T, FPR>(
+    api_key_guard: &ApiKeyGuard,
+    service: &WalletService<T, FPR>,
+    req: JsonRPCRequest,
+) -> JsonRPCResponse
+where
+    T: BlockchainConnection + UserTxConnection + 'static,
+    FPR: FogPubkeyResolver + Send + Sync + 'static,
+{
     let mut response = JsonRPCResponse {
-        method: Some(command.0.method),
+        method: Some(req.method.clone()),
         result: None,
         error: None,
         jsonrpc: "2.0".to_string(),
-        id: command.0.id,
+        id: req.id.clone(),
     };
 
+    if let Err(error) = api_key_guard.check_method(&req.method) {
+        response.error = Some(format_error(error));
+        return response;
+    }
+
+    if let Some(window) = service.maintenance_mode.current() {
+        if !allowed_during_maintenance(&req.method) {
+            response.error = Some(format_maintenance_error(&window, Utc::now().timestamp()));
+            return response;
+        }
+    }
+
     let request = match JsonCommandRequest::try_from(&req) {
         Ok(request) => request,
         Err(error) => {
             response.error = Some(format_invalid_request_error(error));
-            return Ok(Json(response));
+            return response;
         }
     };
 
-    match wallet_api_inner(&state.service, request) {
+    match wallet_api_inner(service, request) {
         Ok(command_response) => {
             response.result = Some(command_response);
         }
@@ -140,26 +403,92 @@ where
         }
     };
 
-    Ok(Json(response))
+    response
 }
 
 /// The route for the Full Service Wallet API.
 #[post("/wallet", format = "json", data = "<command>")]
 pub fn consensus_backed_wallet_api(
-    _api_key_guard: ApiKeyGuard,
+    api_key_guard: ApiKeyGuard,
+    output_mode: JsonOutputMode,
     state: rocket::State<WalletState<ThickClient<HardcodedCredentialsProvider>, FogResolver>>,
+    capture: Option<State<RequestCapture>>,
     command: Json<JsonRPCRequest>,
-) -> Result<Json<JsonRPCResponse>, String> {
-    generic_wallet_api(_api_key_guard, state, command)
+) -> Result<Content<String>, String> {
+    generic_wallet_api(api_key_guard, output_mode, state, capture, command)
 }
 
 #[post("/wallet", format = "json", data = "<command>")]
 pub fn validator_backed_wallet_api(
-    _api_key_guard: ApiKeyGuard,
+    api_key_guard: ApiKeyGuard,
+    output_mode: JsonOutputMode,
     state: rocket::State<WalletState<ValidatorConnection, FogResolver>>,
+    capture: Option<State<RequestCapture>>,
     command: Json<JsonRPCRequest>,
-) -> Result<Json<JsonRPCResponse>, String> {
-    generic_wallet_api(_api_key_guard, state, command)
+) -> Result<Content<String>, String> {
+    generic_wallet_api(api_key_guard, output_mode, state, capture, command)
+}
+
+/// The route for streaming an export of txos.
+#[post("/wallet/export_txos", format = "json", data = "<request>")]
+pub fn consensus_backed_export_txos(
+    api_key_guard: ApiKeyGuard,
+    output_mode: JsonOutputMode,
+    state: rocket::State<WalletState<ThickClient<HardcodedCredentialsProvider>, FogResolver>>,
+    request: Json<ExportTxosRequest>,
+) -> Result<Response<'static>, Custom<String>> {
+    api_key_guard
+        .check_method("export_txos")
+        .map_err(|err| Custom(Status::Forbidden, err.to_string()))?;
+    export_txos(&state.service, request.into_inner(), output_mode.canonical)
+}
+
+#[post("/wallet/export_txos", format = "json", data = "<request>")]
+pub fn validator_backed_export_txos(
+    api_key_guard: ApiKeyGuard,
+    output_mode: JsonOutputMode,
+    state: rocket::State<WalletState<ValidatorConnection, FogResolver>>,
+    request: Json<ExportTxosRequest>,
+) -> Result<Response<'static>, Custom<String>> {
+    api_key_guard
+        .check_method("export_txos")
+        .map_err(|err| Custom(Status::Forbidden, err.to_string()))?;
+    export_txos(&state.service, request.into_inner(), output_mode.canonical)
+}
+
+/// The route for the public balance badge, which needs no API key.
+#[get("/balance_badge?<token_id>")]
+pub fn consensus_backed_balance_badge(
+    client_ip: ClientIp,
+    output_mode: JsonOutputMode,
+    state: rocket::State<WalletState<ThickClient<HardcodedCredentialsProvider>, FogResolver>>,
+    badge_state: rocket::State<BalanceBadgeState>,
+    token_id: Option<String>,
+) -> Result<Content<String>, Custom<String>> {
+    balance_badge(
+        &state.service,
+        &badge_state,
+        client_ip,
+        token_id,
+        &output_mode,
+    )
+}
+
+#[get("/balance_badge?<token_id>")]
+pub fn validator_backed_balance_badge(
+    client_ip: ClientIp,
+    output_mode: JsonOutputMode,
+    state: rocket::State<WalletState<ValidatorConnection, FogResolver>>,
+    badge_state: rocket::State<BalanceBadgeState>,
+    token_id: Option<String>,
+) -> Result<Content<String>, Custom<String>> {
+    balance_badge(
+        &state.service,
+        &badge_state,
+        client_ip,
+        token_id,
+        &output_mode,
+    )
 }
 
 /// The Wallet API inner method, which handles switching on the method enum.
@@ -189,15 +518,74 @@ where
                     .map_err(format_error)?,
             ),
         },
+        JsonCommandRequest::assign_address_for_account_at_index {
+            account_id,
+            subaddress_index,
+            metadata,
+        } => {
+            let subaddress_index = subaddress_index.parse::<u64>().map_err(format_error)?;
+            JsonCommandResponse::assign_address_for_account_at_index {
+                address: Address::from(
+                    &service
+                        .assign_address_for_account_at_index(
+                            &AccountID(account_id),
+                            subaddress_index,
+                            metadata.as_deref(),
+                        )
+                        .map_err(format_error)?,
+                ),
+            }
+        }
+        JsonCommandRequest::assign_addresses_for_account {
+            account_id,
+            count,
+            metadata_prefix,
+        } => {
+            let count = count.parse::<u64>().map_err(format_error)?;
+            let addresses = service
+                .assign_addresses_for_account(
+                    &AccountID(account_id),
+                    count,
+                    metadata_prefix.as_deref(),
+                )
+                .map_err(format_error)?;
+            let address_map: Map<String, serde_json::Value> = Map::from_iter(
+                addresses
+                    .iter()
+                    .map(|a| {
+                        (
+                            a.assigned_subaddress_b58.clone(),
+                            serde_json::to_value(&(Address::from(a)))
+                                .expect("Could not get json value"),
+                        )
+                    })
+                    .collect::<Vec<(String, serde_json::Value)>>(),
+            );
+
+            JsonCommandResponse::assign_addresses_for_account {
+                public_addresses: addresses
+                    .iter()
+                    .map(|a| a.assigned_subaddress_b58.clone())
+                    .collect(),
+                address_map,
+            }
+        }
         JsonCommandRequest::build_and_submit_transaction {
             account_id,
             addresses_and_values,
             recipient_public_address,
             value_pmob,
             input_txo_ids,
+            token_id,
             fee,
+            fee_token_id,
             tombstone_block,
             max_spendable_value,
+            confirm_large_send,
+            payment_request_id,
+            coin_selection_strategy,
+            num_change_outputs,
+            change_values,
             comment,
         } => {
             // The user can specify either a single address and a single value, or a list of
@@ -211,9 +599,16 @@ where
                     &account_id,
                     &addresses_and_values,
                     input_txo_ids.as_ref(),
+                    token_id,
                     fee,
+                    fee_token_id,
                     tombstone_block,
                     max_spendable_value,
+                    confirm_large_send,
+                    payment_request_id,
+                    coin_selection_strategy,
+                    ChangeOutputs::from_params(num_change_outputs, change_values)
+                        .map_err(format_error)?,
                     comment,
                 )
                 .map_err(format_error)?;
@@ -225,6 +620,35 @@ where
                 tx_proposal: TxProposal::try_from(&tx_proposal).map_err(format_error)?,
             }
         }
+        JsonCommandRequest::build_consolidation_transaction {
+            account_id,
+            threshold,
+            token_id,
+            destination_subaddress_index,
+            fee,
+            tombstone_block,
+        } => {
+            let tx_proposal = service
+                .build_consolidation_transaction(
+                    &AccountID(account_id),
+                    threshold.parse::<u64>().map_err(format_error)?,
+                    token_id
+                        .map(|t| t.parse::<u64>())
+                        .transpose()
+                        .map_err(format_error)?,
+                    destination_subaddress_index
+                        .map(|i| i.parse::<i64>())
+                        .transpose()
+                        .map_err(format_error)?,
+                    fee,
+                    tombstone_block,
+                )
+                .map_err(format_error)?;
+            JsonCommandResponse::build_consolidation_transaction {
+                tx_proposal: TxProposal::try_from(&tx_proposal).map_err(format_error)?,
+                transaction_log_id: TransactionID::from(&tx_proposal.tx).to_string(),
+            }
+        }
         JsonCommandRequest::build_gift_code {
             account_id,
             value_pmob,
@@ -258,6 +682,25 @@ where
                 gift_code_b58: gift_code_b58.to_string(),
             }
         }
+        JsonCommandRequest::build_sign_and_submit_transaction {
+            account_id,
+            recipient_public_address,
+            value_pmob,
+            fee,
+            tombstone_block,
+        } => {
+            let tx_proposal = service
+                .build_sign_and_submit_transaction(
+                    &account_id,
+                    &[(recipient_public_address, value_pmob)],
+                    fee,
+                    tombstone_block,
+                )
+                .map_err(format_error)?;
+            JsonCommandResponse::build_sign_and_submit_transaction {
+                tx_proposal: TxProposal::try_from(&tx_proposal).map_err(format_error)?,
+            }
+        }
         JsonCommandRequest::build_split_txo_transaction {
             txo_id,
             output_values,
@@ -282,15 +725,65 @@ where
                 transaction_log_id: TransactionID::from(&tx_proposal.tx).to_string(),
             }
         }
+        JsonCommandRequest::build_sweep_transaction {
+            account_id,
+            recipient_public_address,
+            token_id,
+            subaddress_index,
+            allow_multiple_transactions,
+            fee,
+            tombstone_block,
+            confirm_large_send,
+        } => {
+            let plan = service
+                .build_sweep_transaction(
+                    &AccountID(account_id),
+                    &recipient_public_address,
+                    token_id
+                        .map(|t| t.parse::<u64>())
+                        .transpose()
+                        .map_err(format_error)?,
+                    subaddress_index
+                        .map(|i| i.parse::<i64>())
+                        .transpose()
+                        .map_err(format_error)?,
+                    allow_multiple_transactions.unwrap_or(false),
+                    fee,
+                    tombstone_block,
+                    confirm_large_send,
+                )
+                .map_err(format_error)?;
+            JsonCommandResponse::build_sweep_transaction {
+                tx_proposals: plan
+                    .tx_proposals
+                    .iter()
+                    .map(TxProposal::try_from)
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(format_error)?,
+                transaction_log_ids: plan
+                    .tx_proposals
+                    .iter()
+                    .map(|tx_proposal| TransactionID::from(&tx_proposal.tx).to_string())
+                    .collect(),
+                complete: plan.complete,
+            }
+        }
         JsonCommandRequest::build_transaction {
             account_id,
             addresses_and_values,
             recipient_public_address,
             value_pmob,
             input_txo_ids,
+            token_id,
             fee,
+            fee_token_id,
             tombstone_block,
             max_spendable_value,
+            confirm_large_send,
+            payment_request_id,
+            coin_selection_strategy,
+            num_change_outputs,
+            change_values,
             log_tx_proposal,
         } => {
             // The user can specify a list of addresses and values,
@@ -304,9 +797,16 @@ where
                     &account_id,
                     &addresses_and_values,
                     input_txo_ids.as_ref(),
+                    token_id,
                     fee,
+                    fee_token_id,
                     tombstone_block,
                     max_spendable_value,
+                    confirm_large_send,
+                    payment_request_id,
+                    coin_selection_strategy,
+                    ChangeOutputs::from_params(num_change_outputs, change_values)
+                        .map_err(format_error)?,
                     log_tx_proposal,
                 )
                 .map_err(format_error)?;
@@ -317,12 +817,23 @@ where
         }
         JsonCommandRequest::build_unsigned_transaction {
             account_id,
+            addresses_and_values,
             recipient_public_address,
             value_pmob,
+            input_txo_ids,
+            token_id,
             fee,
+            fee_token_id,
             tombstone_block,
+            max_spendable_value,
+            payment_request_id,
+            coin_selection_strategy,
+            num_change_outputs,
+            change_values,
         } => {
-            let mut addresses_and_values: Vec<(String, String)> = Vec::new();
+            // The user can specify a list of addresses and values,
+            // or a single address and a single value (deprecated).
+            let mut addresses_and_values = addresses_and_values.unwrap_or_default();
             if let (Some(a), Some(v)) = (recipient_public_address, value_pmob) {
                 addresses_and_values.push((a, v));
             }
@@ -330,8 +841,16 @@ where
                 .build_unsigned_transaction(
                     &account_id,
                     &addresses_and_values,
+                    input_txo_ids.as_ref(),
+                    token_id,
                     fee,
+                    fee_token_id,
                     tombstone_block,
+                    max_spendable_value,
+                    payment_request_id,
+                    coin_selection_strategy,
+                    ChangeOutputs::from_params(num_change_outputs, change_values)
+                        .map_err(format_error)?,
                 )
                 .map_err(format_error)?;
             JsonCommandResponse::build_unsigned_transaction {
@@ -340,6 +859,33 @@ where
                 fog_resolver,
             }
         }
+        JsonCommandRequest::cancel_account_import { account_id } => {
+            JsonCommandResponse::cancel_account_import {
+                removed: service
+                    .cancel_account_import(&AccountID(account_id))
+                    .map_err(format_error)?,
+            }
+        }
+        JsonCommandRequest::cancel_scheduled_payment {
+            scheduled_payment_id,
+        } => {
+            let scheduled_payment_id = scheduled_payment_id.parse::<i32>().map_err(format_error)?;
+            JsonCommandResponse::cancel_scheduled_payment {
+                scheduled_payment: ScheduledPayment::from(
+                    &service
+                        .cancel_scheduled_payment(scheduled_payment_id)
+                        .map_err(format_error)?,
+                ),
+            }
+        }
+        JsonCommandRequest::change_db_password {
+            old_password,
+            new_password,
+        } => JsonCommandResponse::change_db_password {
+            changed: service
+                .change_db_password(&old_password, &new_password)
+                .map_err(format_error)?,
+        },
         JsonCommandRequest::check_b58_type { b58_code } => {
             let b58_type = b58_printable_wrapper_type(b58_code.clone()).map_err(format_error)?;
             let mut b58_data = HashMap::new();
@@ -404,6 +950,55 @@ where
                 txo_id: TxoID::from(&tx.prefix.outputs[0]).to_string(),
             }
         }
+        JsonCommandRequest::cleanup_transaction_logs {
+            statuses,
+            older_than,
+        } => {
+            let older_than = older_than
+                .map(|o| o.parse::<u64>())
+                .transpose()
+                .map_err(format_error)?;
+            let deleted_count = service
+                .cleanup_transaction_logs(&statuses, older_than)
+                .map_err(format_error)?;
+            JsonCommandResponse::cleanup_transaction_logs {
+                deleted_count: deleted_count.to_string(),
+            }
+        }
+        JsonCommandRequest::compare_log_digest { log_digest } => {
+            let log_digest =
+                service::log_digest::LogDigest::try_from(&log_digest).map_err(format_error)?;
+            JsonCommandResponse::compare_log_digest {
+                log_digest_diff: LogDigestDiff::from(
+                    &service
+                        .compare_log_digest(&log_digest)
+                        .map_err(format_error)?,
+                ),
+            }
+        }
+        JsonCommandRequest::continuity_check_in => JsonCommandResponse::continuity_check_in {
+            continuity_status: ContinuityStatus::from(
+                &service.continuity_check_in().map_err(format_error)?,
+            ),
+        },
+        JsonCommandRequest::convert_amount {
+            value,
+            from,
+            to,
+            rounding,
+        } => {
+            let from = from.parse::<AmountUnit>().map_err(format_error)?;
+            let to = to.parse::<AmountUnit>().map_err(format_error)?;
+            let rounding = rounding
+                .map(|r| r.parse::<RoundingMode>())
+                .transpose()
+                .map_err(format_error)?
+                .unwrap_or_default();
+            JsonCommandResponse::convert_amount {
+                value: convert_amount(&value, from, to, rounding).map_err(format_error)?,
+                unit: to.to_string(),
+            }
+        }
         JsonCommandRequest::create_account {
             name,
             fog_report_url,
@@ -425,6 +1020,28 @@ where
                 })?,
             }
         }
+        JsonCommandRequest::create_claim_link {
+            account_id,
+            value_pmob,
+            memo,
+            expiry_seconds,
+            fee,
+        } => {
+            let (gift_code, _tx_proposal) = service
+                .create_claim_link(
+                    &AccountID(account_id),
+                    value_pmob.parse::<u64>().map_err(format_error)?,
+                    memo,
+                    expiry_seconds.parse::<u64>().map_err(format_error)?,
+                    fee.map(|f| f.parse::<u64>())
+                        .transpose()
+                        .map_err(format_error)?,
+                )
+                .map_err(format_error)?;
+            JsonCommandResponse::create_claim_link {
+                gift_code: GiftCode::from(&gift_code),
+            }
+        }
         JsonCommandRequest::create_new_subaddresses_request {
             account_id,
             num_subaddresses_to_generate,
@@ -444,11 +1061,26 @@ where
             subaddress_index,
             amount_pmob,
             memo,
-        } => JsonCommandResponse::create_payment_request {
-            payment_request_b58: service
-                .create_payment_request(account_id, subaddress_index, amount_pmob, memo)
-                .map_err(format_error)?,
-        },
+            expiry_block_index,
+        } => {
+            let expiry_block_index = expiry_block_index
+                .map(|index| index.parse::<u64>())
+                .transpose()
+                .map_err(format_error)?;
+            let (payment_request, payment_request_b58) = service
+                .create_payment_request(
+                    account_id,
+                    subaddress_index,
+                    amount_pmob,
+                    memo,
+                    expiry_block_index,
+                )
+                .map_err(format_error)?;
+            JsonCommandResponse::create_payment_request {
+                payment_request_b58,
+                payment_request: PaymentRequest::from(&payment_request),
+            }
+        }
         JsonCommandRequest::create_receiver_receipts { tx_proposal } => {
             let receipts = service
                 .create_receiver_receipts(
@@ -465,9 +1097,67 @@ where
                 receiver_receipts: json_receipts,
             }
         }
+        JsonCommandRequest::create_scheduled_payment {
+            account_id,
+            recipient_public_address,
+            value,
+            token_id,
+            interval_seconds,
+            block_height,
+            comment,
+        } => {
+            let value = value.parse::<u64>().map_err(format_error)?;
+            let token_id = token_id
+                .map(|token_id| token_id.parse::<u64>())
+                .transpose()
+                .map_err(format_error)?
+                .unwrap_or(*Mob::ID);
+            let interval_seconds = interval_seconds
+                .map(|interval_seconds| interval_seconds.parse::<u64>())
+                .transpose()
+                .map_err(format_error)?;
+            let block_height = block_height
+                .map(|block_height| block_height.parse::<u64>())
+                .transpose()
+                .map_err(format_error)?;
+            JsonCommandResponse::create_scheduled_payment {
+                scheduled_payment: ScheduledPayment::from(
+                    &service
+                        .create_scheduled_payment(
+                            &AccountID(account_id),
+                            &recipient_public_address,
+                            value,
+                            token_id,
+                            interval_seconds,
+                            block_height,
+                            comment,
+                        )
+                        .map_err(format_error)?,
+                ),
+            }
+        }
+        JsonCommandRequest::create_view_only_account_sync_proof {
+            account_id,
+            first_block_index,
+            block_count,
+        } => JsonCommandResponse::create_view_only_account_sync_proof {
+            sync_proof: service
+                .create_view_only_account_sync_proof(
+                    &account_id,
+                    first_block_index
+                        .map(|i| i.parse::<u64>())
+                        .transpose()
+                        .map_err(format_error)?,
+                    block_count
+                        .map(|c| c.parse::<u64>())
+                        .transpose()
+                        .map_err(format_error)?,
+                )
+                .map_err(format_error)?,
+        },
         JsonCommandRequest::create_view_only_account_sync_request { account_id } => {
             let incomplete_txos = service
-                .list_incomplete_view_only_txos(&account_id)
+                .create_view_only_account_sync_request(&account_id)
                 .map_err(format_error)?;
 
             let incomplete_txos_encoded: Vec<String> = incomplete_txos
@@ -480,6 +1170,17 @@ where
                 incomplete_txos_encoded,
             }
         }
+        JsonCommandRequest::disable_spending => JsonCommandResponse::disable_spending {
+            spending_disabled: service.disable_spending().map_err(format_error)?,
+        },
+        JsonCommandRequest::enable_spending { admin_key } => JsonCommandResponse::enable_spending {
+            spending_disabled: service.enable_spending(&admin_key).map_err(format_error)?,
+        },
+        JsonCommandRequest::end_maintenance { admin_key } => JsonCommandResponse::end_maintenance {
+            maintenance_status: MaintenanceStatus::from(
+                &service.end_maintenance(&admin_key).map_err(format_error)?,
+            ),
+        },
         JsonCommandRequest::export_account_secrets { account_id } => {
             let account = service
                 .get_account(&AccountID(account_id))
@@ -488,7 +1189,98 @@ where
                 account_secrets: AccountSecrets::try_from(&account).map_err(format_error)?,
             }
         }
-        JsonCommandRequest::export_spent_txo_ids { account_id } => {
+        JsonCommandRequest::export_address_labels { account_id, format } => {
+            let format = format.parse::<AddressLabelFormat>().map_err(format_error)?;
+            JsonCommandResponse::export_address_labels {
+                contents: service
+                    .export_address_labels(&AccountID(account_id), format)
+                    .map_err(format_error)?,
+            }
+        }
+        JsonCommandRequest::export_key_images {
+            account_id,
+            view_only,
+            limit,
+            cursor,
+        } => {
+            let (key_images, next_cursor) = if view_only.unwrap_or(false) {
+                let (cursor, _, limit) =
+                    cursor_page_helper(VIEW_ONLY_KEY_IMAGE_CURSOR, cursor, None, limit)?;
+                let page = service
+                    .export_view_only_key_images(account_id.as_deref(), cursor, limit)
+                    .map_err(format_error)?;
+                (
+                    page.items
+                        .iter()
+                        .map(ExportedKeyImage::try_from)
+                        .collect::<Result<Vec<_>, String>>()
+                        .map_err(format_error)?,
+                    page.next_cursor
+                        .map(|id| encode_page_cursor(VIEW_ONLY_KEY_IMAGE_CURSOR, id)),
+                )
+            } else {
+                let (cursor, _, limit) = cursor_page_helper(KEY_IMAGE_CURSOR, cursor, None, limit)?;
+                let page = service
+                    .export_key_images(account_id.map(AccountID).as_ref(), cursor, limit)
+                    .map_err(format_error)?;
+                (
+                    page.items
+                        .iter()
+                        .map(ExportedKeyImage::try_from)
+                        .collect::<Result<Vec<_>, String>>()
+                        .map_err(format_error)?,
+                    page.next_cursor
+                        .map(|id| encode_page_cursor(KEY_IMAGE_CURSOR, id)),
+                )
+            };
+            JsonCommandResponse::export_key_images {
+                key_images,
+                has_more: next_cursor.is_some(),
+                next_cursor,
+            }
+        }
+        JsonCommandRequest::export_signing_request {
+            account_id,
+            recipient_public_address,
+            value_pmob,
+            addresses_and_values,
+            input_txo_ids,
+            token_id,
+            fee,
+            fee_token_id,
+            tombstone_block,
+            max_spendable_value,
+            payment_request_id,
+            coin_selection_strategy,
+            num_change_outputs,
+            change_values,
+        } => {
+            // The user can specify either a single address and a single value, or a list of
+            // addresses and values.
+            let mut addresses_and_values = addresses_and_values.unwrap_or_default();
+            if let (Some(a), Some(v)) = (recipient_public_address, value_pmob) {
+                addresses_and_values.push((a, v));
+            }
+            JsonCommandResponse::export_signing_request {
+                signing_request: service
+                    .build_signing_request(
+                        &account_id,
+                        &addresses_and_values,
+                        input_txo_ids.as_ref(),
+                        token_id,
+                        fee,
+                        fee_token_id,
+                        tombstone_block,
+                        max_spendable_value,
+                        payment_request_id,
+                        coin_selection_strategy,
+                        ChangeOutputs::from_params(num_change_outputs, change_values)
+                            .map_err(format_error)?,
+                    )
+                    .map_err(format_error)?,
+            }
+        }
+        JsonCommandRequest::export_spent_txo_ids { account_id } => {
             let txos = service
                 .list_spent_txos(&AccountID(account_id))
                 .map_err(format_error)?;
@@ -518,6 +1310,24 @@ where
                         .map_err(format_error)?,
             }
         }
+        JsonCommandRequest::freeze_txos { txo_ids } => {
+            let txos = service.freeze_txos(&txo_ids).map_err(format_error)?;
+            let txo_map: Map<String, serde_json::Value> = Map::from_iter(
+                txos.iter()
+                    .map(|t| {
+                        (
+                            t.txo_id_hex.clone(),
+                            serde_json::to_value(Txo::from(t)).expect("Could not get json value"),
+                        )
+                    })
+                    .collect::<Vec<(String, serde_json::Value)>>(),
+            );
+
+            JsonCommandResponse::freeze_txos {
+                txo_ids: txos.iter().map(|t| t.txo_id_hex.clone()).collect(),
+                txo_map,
+            }
+        }
         JsonCommandRequest::get_account { account_id } => JsonCommandResponse::get_account {
             account: json_rpc::account::Account::try_from(
                 &service
@@ -526,6 +1336,48 @@ where
             )
             .map_err(format_error)?,
         },
+        JsonCommandRequest::get_account_rotation_status { account_id } => {
+            JsonCommandResponse::get_account_rotation_status {
+                account_rotation: AccountRotation::from(
+                    &service
+                        .advance_account_rotation(&AccountID(account_id))
+                        .map_err(format_error)?,
+                ),
+            }
+        }
+        JsonCommandRequest::get_account_snapshots {
+            account_id,
+            token_id,
+            from_block_index,
+            to_block_index,
+        } => {
+            let token_id = token_id
+                .map(|s| s.parse::<u64>())
+                .transpose()
+                .map_err(format_error)?;
+            let from_block_index = from_block_index
+                .map(|s| s.parse::<u64>())
+                .transpose()
+                .map_err(format_error)?;
+            let to_block_index = to_block_index
+                .map(|s| s.parse::<u64>())
+                .transpose()
+                .map_err(format_error)?;
+            let account_snapshots = service
+                .get_account_snapshots(
+                    &AccountID(account_id),
+                    token_id,
+                    from_block_index,
+                    to_block_index,
+                )
+                .map_err(format_error)?;
+            JsonCommandResponse::get_account_snapshots {
+                account_snapshots: account_snapshots
+                    .iter()
+                    .map(AccountSnapshot::from)
+                    .collect(),
+            }
+        }
         JsonCommandRequest::get_account_status { account_id } => {
             let account = json_rpc::account::Account::try_from(
                 &service
@@ -535,17 +1387,46 @@ where
             .map_err(format_error)?;
             let balance = Balance::from(
                 &service
-                    .get_balance_for_account(&AccountID(account_id))
+                    .get_balance_for_account(&AccountID(account_id), false)
                     .map_err(format_error)?,
             );
             JsonCommandResponse::get_account_status { account, balance }
         }
-        JsonCommandRequest::get_address_for_account { account_id, index } => {
-            let assigned_subaddress = service
-                .get_address_for_account(&AccountID(account_id), index)
-                .map_err(format_error)?;
-            JsonCommandResponse::get_address_for_account {
-                address: Address::from(&assigned_subaddress),
+        JsonCommandRequest::get_account_sweep_policy { account_id } => {
+            JsonCommandResponse::get_account_sweep_policy {
+                sweep_policy: SweepPolicy::from(
+                    &service
+                        .get_sweep_policy(&AccountID(account_id))
+                        .map_err(format_error)?,
+                ),
+            }
+        }
+        JsonCommandRequest::get_address_for_account {
+            account_id,
+            index,
+            derive,
+            assign,
+        } => {
+            let assign = assign.unwrap_or(false);
+            if derive.unwrap_or(false) || assign {
+                let index = u64::try_from(index).map_err(format_error)?;
+                let derived = service
+                    .derive_address_for_account(&AccountID(account_id), index, assign)
+                    .map_err(format_error)?;
+                JsonCommandResponse::get_address_for_account {
+                    address: Address::from(&derived),
+                    address_status: Some(derived.status.to_string()),
+                    orphaned_txo_ids: Some(derived.orphaned_txo_ids),
+                }
+            } else {
+                let assigned_subaddress = service
+                    .get_address_for_account(&AccountID(account_id), index)
+                    .map_err(format_error)?;
+                JsonCommandResponse::get_address_for_account {
+                    address: Address::from(&assigned_subaddress),
+                    address_status: None,
+                    orphaned_txo_ids: None,
+                }
             }
         }
         JsonCommandRequest::get_address_for_view_only_account { account_id, index } => {
@@ -644,10 +1525,20 @@ where
                 .map(GiftCode::from)
                 .collect(),
         },
-        JsonCommandRequest::get_all_transaction_logs_for_block { block_index } => {
+        JsonCommandRequest::get_all_transaction_logs_for_block_range {
+            min_block_index,
+            max_block_index,
+            offset,
+            limit,
+        } => {
+            let (o, l) = page_helper(offset, limit)?;
+
             let transaction_logs_and_txos = service
-                .get_all_transaction_logs_for_block(
-                    block_index.parse::<u64>().map_err(format_error)?,
+                .get_all_transaction_logs_for_block_range(
+                    min_block_index.parse::<u64>().map_err(format_error)?,
+                    max_block_index.parse::<u64>().map_err(format_error)?,
+                    Some(o),
+                    Some(l),
                 )
                 .map_err(format_error)?;
             let transaction_log_map: Map<String, serde_json::Value> = Map::from_iter(
@@ -662,7 +1553,7 @@ where
                     .collect::<Vec<(String, serde_json::Value)>>(),
             );
 
-            JsonCommandResponse::get_all_transaction_logs_for_block {
+            JsonCommandResponse::get_all_transaction_logs_for_block_range {
                 transaction_log_ids: transaction_logs_and_txos
                     .iter()
                     .map(|(t, _a)| t.transaction_id_hex.to_string())
@@ -730,15 +1621,19 @@ where
                 account_map,
             }
         }
-        JsonCommandRequest::get_balance_for_account { account_id } => {
-            JsonCommandResponse::get_balance_for_account {
-                balance: Balance::from(
-                    &service
-                        .get_balance_for_account(&AccountID(account_id))
-                        .map_err(format_error)?,
-                ),
-            }
-        }
+        JsonCommandRequest::get_balance_for_account {
+            account_id,
+            force_refresh,
+        } => JsonCommandResponse::get_balance_for_account {
+            balance: Balance::from(
+                &service
+                    .get_balance_for_account(
+                        &AccountID(account_id),
+                        force_refresh.unwrap_or_default(),
+                    )
+                    .map_err(format_error)?,
+            ),
+        },
         JsonCommandRequest::get_balance_for_address { address } => {
             JsonCommandResponse::get_balance_for_address {
                 balance: Balance::from(
@@ -785,6 +1680,35 @@ where
                     .collect(),
             }
         }
+        JsonCommandRequest::get_continuity_status => JsonCommandResponse::get_continuity_status {
+            continuity_status: ContinuityStatus::from(
+                &service.get_continuity_status().map_err(format_error)?,
+            ),
+        },
+        JsonCommandRequest::get_deposits {
+            account_id,
+            min_block_index,
+            max_block_index,
+        } => {
+            let min_block_index = min_block_index
+                .map(|i| i.parse::<u64>())
+                .transpose()
+                .map_err(format_error)?;
+
+            let max_block_index = max_block_index
+                .map(|i| i.parse::<u64>())
+                .transpose()
+                .map_err(format_error)?;
+
+            JsonCommandResponse::get_deposits {
+                deposits: service
+                    .get_deposits(&AccountID(account_id), min_block_index, max_block_index)
+                    .map_err(format_error)?
+                    .iter()
+                    .map(Deposit::from)
+                    .collect(),
+            }
+        }
         JsonCommandRequest::get_gift_code { gift_code_b58 } => JsonCommandResponse::get_gift_code {
             gift_code: GiftCode::from(
                 &service
@@ -792,6 +1716,31 @@ where
                     .map_err(format_error)?,
             ),
         },
+        JsonCommandRequest::get_isolated_counterparties { account_id } => {
+            JsonCommandResponse::get_isolated_counterparties {
+                isolated_counterparties: service
+                    .list_isolated_counterparties(&AccountID(account_id))
+                    .map_err(format_error)?
+                    .iter()
+                    .map(IsolatedCounterparty::from)
+                    .collect(),
+            }
+        }
+        JsonCommandRequest::get_ledger_db_size => JsonCommandResponse::get_ledger_db_size {
+            ledger_db_size: LedgerDbSize::from(
+                &service.get_ledger_db_size().map_err(format_error)?,
+            ),
+        },
+        JsonCommandRequest::get_log_digest { account_id } => JsonCommandResponse::get_log_digest {
+            log_digest: LogDigest::from(
+                &service
+                    .get_log_digest(&AccountID(account_id))
+                    .map_err(format_error)?,
+            ),
+        },
+        JsonCommandRequest::get_maintenance_status => JsonCommandResponse::get_maintenance_status {
+            maintenance_status: MaintenanceStatus::from(&service.get_maintenance_status()),
+        },
         JsonCommandRequest::get_mc_protocol_transaction { transaction_log_id } => {
             let tx = service
                 .get_transaction_object(&transaction_log_id)
@@ -814,6 +1763,58 @@ where
             )
             .map_err(format_error)?,
         },
+        JsonCommandRequest::get_payment_request { payment_request_id } => {
+            let payment_request_id = payment_request_id.parse::<i32>().map_err(format_error)?;
+            JsonCommandResponse::get_payment_request {
+                payment_request: PaymentRequest::from(
+                    &service
+                        .get_payment_request(payment_request_id)
+                        .map_err(format_error)?,
+                ),
+            }
+        }
+        JsonCommandRequest::get_pending_outflows => JsonCommandResponse::get_pending_outflows {
+            pending_outflows: PendingOutflows::from(
+                &service.get_pending_outflows().map_err(format_error)?,
+            ),
+        },
+        JsonCommandRequest::get_quarantined_blocks => JsonCommandResponse::get_quarantined_blocks {
+            quarantined_blocks: service
+                .list_quarantined_blocks()
+                .map_err(format_error)?
+                .iter()
+                .map(QuarantinedBlock::from)
+                .collect(),
+        },
+        JsonCommandRequest::get_reporting_public_key => {
+            JsonCommandResponse::get_reporting_public_key {
+                reporting_public_key: hex::encode(
+                    service.get_reporting_public_key().map_err(format_error)?,
+                ),
+            }
+        }
+        JsonCommandRequest::get_settings => JsonCommandResponse::get_settings {
+            settings: WalletSettings::from(&service.get_settings().map_err(format_error)?),
+        },
+        JsonCommandRequest::get_sync_history {
+            account_id,
+            offset,
+            limit,
+        } => {
+            let (o, l) = page_helper(offset, limit)?;
+            let sync_history = service
+                .get_sync_history(&AccountID(account_id), Some(o), Some(l))
+                .map_err(format_error)?;
+            JsonCommandResponse::get_sync_history {
+                sync_history: sync_history.iter().map(AccountSyncRecord::from).collect(),
+            }
+        }
+        JsonCommandRequest::get_sync_status => JsonCommandResponse::get_sync_status {
+            sync_status: SyncStatus::from(&service.get_sync_status().map_err(format_error)?),
+        },
+        JsonCommandRequest::get_tokens => JsonCommandResponse::get_tokens {
+            tokens: service.get_tokens().iter().map(Token::from).collect(),
+        },
         JsonCommandRequest::get_transaction_log { transaction_log_id } => {
             let (transaction_log, associated_txos) = service
                 .get_transaction_log(&transaction_log_id)
@@ -831,8 +1832,10 @@ where
             limit,
             min_block_index,
             max_block_index,
+            cursor,
         } => {
-            let (o, l) = page_helper(offset, limit)?;
+            let (cursor, offset, limit) =
+                cursor_page_helper(TRANSACTION_LOG_CURSOR, cursor, offset, limit)?;
 
             let min_block_index = min_block_index
                 .map(|i| i.parse::<u64>())
@@ -844,17 +1847,18 @@ where
                 .transpose()
                 .map_err(format_error)?;
 
-            let transaction_logs_and_txos = service
-                .list_transaction_logs(
+            let page = service
+                .list_transaction_logs_page(
                     &AccountID(account_id),
-                    Some(o),
-                    Some(l),
+                    cursor,
+                    offset,
+                    limit,
                     min_block_index,
                     max_block_index,
                 )
                 .map_err(format_error)?;
             let transaction_log_map: Map<String, serde_json::Value> = Map::from_iter(
-                transaction_logs_and_txos
+                page.items
                     .iter()
                     .map(|(t, a)| {
                         (
@@ -866,30 +1870,61 @@ where
             );
 
             JsonCommandResponse::get_transaction_logs_for_account {
-                transaction_log_ids: transaction_logs_and_txos
+                transaction_log_ids: page
+                    .items
                     .iter()
                     .map(|(t, _a)| t.transaction_id_hex.to_string())
                     .collect(),
                 transaction_log_map,
+                next_cursor: page
+                    .next_cursor
+                    .map(|id| encode_page_cursor(TRANSACTION_LOG_CURSOR, id)),
+                has_more: page.has_more(),
+            }
+        }
+        JsonCommandRequest::get_transaction_watch { watch_id } => {
+            let transaction_watch = service
+                .get_transaction_watch(&watch_id)
+                .map_err(format_error)?;
+            JsonCommandResponse::get_transaction_watch {
+                transaction_watch: TransactionWatch::try_from(&transaction_watch)
+                    .map_err(format_error)?,
+            }
+        }
+        JsonCommandRequest::get_transaction_watches { offset, limit } => {
+            let (o, l) = page_helper(offset, limit)?;
+            let transaction_watches = service
+                .list_transaction_watches(Some(o), Some(l))
+                .map_err(format_error)?;
+            JsonCommandResponse::get_transaction_watches {
+                transaction_watches: transaction_watches
+                    .iter()
+                    .map(TransactionWatch::try_from)
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(format_error)?,
             }
         }
         JsonCommandRequest::get_txo { txo_id } => {
             let result = service.get_txo(&TxoID(txo_id)).map_err(format_error)?;
+            let context = service.get_txo_context(&result).map_err(format_error)?;
             JsonCommandResponse::get_txo {
                 txo: Txo::from(&result),
+                context: TxoContext::from(&context),
             }
         }
         JsonCommandRequest::get_txos_for_account {
             account_id,
             offset,
             limit,
+            cursor,
         } => {
-            let (o, l) = page_helper(offset, limit)?;
-            let txos = service
-                .list_txos(&AccountID(account_id), Some(o), Some(l))
+            let (cursor, offset, limit) = cursor_page_helper(TXO_CURSOR, cursor, offset, limit)?;
+            let page = service
+                .list_txos_page(&AccountID(account_id), cursor, offset, limit)
                 .map_err(format_error)?;
             let txo_map: Map<String, serde_json::Value> = Map::from_iter(
-                txos.iter()
+                page.items
+                    .iter()
                     .map(|t| {
                         (
                             t.txo_id_hex.clone(),
@@ -900,21 +1935,28 @@ where
             );
 
             JsonCommandResponse::get_txos_for_account {
-                txo_ids: txos.iter().map(|t| t.txo_id_hex.clone()).collect(),
+                txo_ids: page.items.iter().map(|t| t.txo_id_hex.clone()).collect(),
                 txo_map,
+                next_cursor: page
+                    .next_cursor
+                    .map(|id| encode_page_cursor(TXO_CURSOR, id)),
+                has_more: page.has_more(),
             }
         }
         JsonCommandRequest::get_txos_for_view_only_account {
             account_id,
             offset,
             limit,
+            cursor,
         } => {
-            let (o, l) = page_helper(offset, limit)?;
-            let txos = service
-                .list_view_only_txos(&account_id, Some(o), Some(l))
+            let (cursor, offset, limit) =
+                cursor_page_helper(VIEW_ONLY_TXO_CURSOR, cursor, offset, limit)?;
+            let page = service
+                .list_view_only_txos_page(&account_id, cursor, offset, limit)
                 .map_err(format_error)?;
             let txo_map: Map<String, serde_json::Value> = Map::from_iter(
-                txos.iter()
+                page.items
+                    .iter()
                     .map(|t| {
                         (
                             t.txo_id_hex.clone(),
@@ -926,16 +1968,50 @@ where
             );
 
             JsonCommandResponse::get_txos_for_account {
-                txo_ids: txos.iter().map(|t| t.txo_id_hex.clone()).collect(),
+                txo_ids: page.items.iter().map(|t| t.txo_id_hex.clone()).collect(),
                 txo_map,
+                next_cursor: page
+                    .next_cursor
+                    .map(|id| encode_page_cursor(VIEW_ONLY_TXO_CURSOR, id)),
+                has_more: page.has_more(),
+            }
+        }
+        JsonCommandRequest::get_wallet_events {
+            after_event_id,
+            limit,
+        } => {
+            let after_event_id = after_event_id
+                .map(|i| i.parse::<i32>())
+                .transpose()
+                .map_err(format_error)?;
+            let limit = limit
+                .map(|l| l.parse::<u64>())
+                .transpose()
+                .map_err(format_error)?;
+            let events = service
+                .get_wallet_events(after_event_id, limit)
+                .map_err(format_error)?;
+            JsonCommandResponse::get_wallet_events {
+                events: events.iter().map(WalletEvent::from).collect(),
             }
         }
         JsonCommandRequest::get_wallet_status => JsonCommandResponse::get_wallet_status {
             wallet_status: WalletStatus::try_from(
-                &service.get_wallet_status().map_err(format_error)?,
+                &service.get_wallet_status(false).map_err(format_error)?,
             )
             .map_err(format_error)?,
         },
+        JsonCommandRequest::get_webhooks => JsonCommandResponse::get_webhooks {
+            webhooks: service
+                .get_webhooks()
+                .map_err(format_error)?
+                .iter()
+                .map(Webhook::from)
+                .collect(),
+        },
+        JsonCommandRequest::get_usage_stats => JsonCommandResponse::get_usage_stats {
+            usage_stats: UsageStats::from(&service.get_usage_stats().map_err(format_error)?),
+        },
         JsonCommandRequest::get_view_only_account { account_id } => {
             JsonCommandResponse::get_view_only_account {
                 view_only_account: json_rpc::view_only_account::ViewOnlyAccountJSON::try_from(
@@ -946,6 +2022,25 @@ where
                 .map_err(format_error)?,
             }
         }
+        JsonCommandRequest::get_view_only_account_sync_records {
+            account_id,
+            offset,
+            limit,
+        } => {
+            service
+                .get_view_only_account(&account_id)
+                .map_err(format_error)?;
+            let (o, l) = page_helper(offset, limit)?;
+            let sync_records = service
+                .list_view_only_account_sync_records(&account_id, Some(o), Some(l))
+                .map_err(format_error)?;
+            JsonCommandResponse::get_view_only_account_sync_records {
+                sync_records: sync_records
+                    .iter()
+                    .map(ViewOnlySyncRecordJSON::from)
+                    .collect(),
+            }
+        }
         JsonCommandRequest::import_account {
             mnemonic,
             key_derivation_version,
@@ -1019,6 +2114,54 @@ where
                 .map_err(format_error)?,
             }
         }
+        JsonCommandRequest::import_accounts { accounts } => {
+            let imports = accounts
+                .iter()
+                .map(service::account::AccountImport::try_from)
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(format_error)?;
+            let (accounts, sync_plan) = service.import_accounts(imports).map_err(format_error)?;
+            JsonCommandResponse::import_accounts {
+                accounts: accounts
+                    .iter()
+                    .map(json_rpc::account::Account::try_from)
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(format_error)?,
+                sync_plan: AccountImportSyncPlan::from(&sync_plan),
+            }
+        }
+        JsonCommandRequest::import_address_labels {
+            account_id,
+            contents,
+            format,
+        } => {
+            let format = format.parse::<AddressLabelFormat>().map_err(format_error)?;
+            let import = service
+                .import_address_labels(&AccountID(account_id), &contents, format)
+                .map_err(format_error)?;
+            JsonCommandResponse::import_address_labels {
+                assigned_count: import.assigned.to_string(),
+                updated_count: import.updated.to_string(),
+                unchanged_count: import.unchanged.to_string(),
+            }
+        }
+        JsonCommandRequest::import_signed_transaction {
+            signed_transaction,
+            comment,
+        } => {
+            let result: Option<json_rpc::transaction_log::TransactionLog> = service
+                .submit_signed_transaction(signed_transaction, comment)
+                .map_err(format_error)?
+                .map(|(transaction_log, associated_txos)| {
+                    json_rpc::transaction_log::TransactionLog::new(
+                        &transaction_log,
+                        &associated_txos,
+                    )
+                });
+            JsonCommandResponse::import_signed_transaction {
+                transaction_log: result,
+            }
+        }
         JsonCommandRequest::import_subaddresses_to_view_only_account {
             account_id,
             subaddresses,
@@ -1043,7 +2186,7 @@ where
 
             let public_address_b58s = service
                 .import_subaddresses(&account_id, subaddresses_decoded)
-                .map_err(format_error)?;
+                .map_err(format_account_service_error)?;
 
             JsonCommandResponse::import_subaddresses_to_view_only_account {
                 public_address_b58s,
@@ -1094,7 +2237,7 @@ where
                     &account.name,
                     subaddresses_decoded,
                 )
-                .map_err(format_error)?;
+                .map_err(format_account_service_error)?;
 
             let view_only_account_json =
                 json_rpc::view_only_account::ViewOnlyAccountJSON::from(view_only_account);
@@ -1103,11 +2246,121 @@ where
                 view_only_account: view_only_account_json,
             }
         }
+        JsonCommandRequest::isolate_counterparty {
+            account_id,
+            counterparty,
+        } => JsonCommandResponse::isolate_counterparty {
+            isolated_counterparty: IsolatedCounterparty::from(
+                &service
+                    .isolate_counterparty(&AccountID(account_id), &counterparty)
+                    .map_err(format_error)?,
+            ),
+        },
+        JsonCommandRequest::list_payment_requests { account_id } => {
+            JsonCommandResponse::list_payment_requests {
+                payment_requests: service
+                    .list_payment_requests(&AccountID(account_id))
+                    .map_err(format_error)?
+                    .iter()
+                    .map(PaymentRequest::from)
+                    .collect(),
+            }
+        }
+        JsonCommandRequest::list_scheduled_payments { account_id } => {
+            JsonCommandResponse::list_scheduled_payments {
+                scheduled_payments: service
+                    .list_scheduled_payments(account_id.map(AccountID).as_ref())
+                    .map_err(format_error)?
+                    .iter()
+                    .map(ScheduledPayment::from)
+                    .collect(),
+            }
+        }
+        JsonCommandRequest::pause_account_sync { account_id } => {
+            JsonCommandResponse::pause_account_sync {
+                account: json_rpc::account::Account::try_from(
+                    &service
+                        .pause_account_sync(&AccountID(account_id))
+                        .map_err(format_error)?,
+                )
+                .map_err(format_error)?,
+            }
+        }
+        JsonCommandRequest::recover_account {
+            mnemonic,
+            start_block_index,
+            max_blocks,
+            import_key_derivation_version,
+            name,
+            fog_report_url,
+            fog_report_id,
+            fog_authority_spki,
+        } => {
+            let recovery = service
+                .recover_account(
+                    &mnemonic,
+                    start_block_index
+                        .map(|i| i.parse::<u64>())
+                        .transpose()
+                        .map_err(format_error)?,
+                    max_blocks
+                        .map(|n| n.parse::<u64>())
+                        .transpose()
+                        .map_err(format_error)?,
+                    import_key_derivation_version
+                        .map(|v| v.parse::<u8>())
+                        .transpose()
+                        .map_err(format_error)?,
+                    name,
+                    fog_report_url.unwrap_or_default(),
+                    fog_report_id.unwrap_or_default(),
+                    fog_authority_spki.unwrap_or_default(),
+                )
+                .map_err(format_error)?;
+            JsonCommandResponse::recover_account {
+                start_block_index: recovery.start_block_index.to_string(),
+                end_block_index: recovery.end_block_index.to_string(),
+                candidates: recovery
+                    .candidates
+                    .iter()
+                    .map(RecoveryCandidate::from)
+                    .collect(),
+                account: recovery
+                    .account
+                    .as_ref()
+                    .map(json_rpc::account::Account::try_from)
+                    .transpose()
+                    .map_err(format_error)?,
+            }
+        }
+        JsonCommandRequest::register_webhook { url, account_id } => {
+            JsonCommandResponse::register_webhook {
+                webhook: Webhook::from(
+                    &service
+                        .register_webhook(&url, account_id.map(AccountID).as_ref())
+                        .map_err(format_error)?,
+                ),
+            }
+        }
+        JsonCommandRequest::relocate_ledger_db { path } => {
+            JsonCommandResponse::relocate_ledger_db {
+                ledger_db_size: LedgerDbSize::from(
+                    &service.relocate_ledger_db(&path).map_err(format_error)?,
+                ),
+            }
+        }
         JsonCommandRequest::remove_account { account_id } => JsonCommandResponse::remove_account {
             removed: service
                 .remove_account(&AccountID(account_id))
                 .map_err(format_error)?,
         },
+        JsonCommandRequest::remove_account_sweep_policy { account_id } => {
+            JsonCommandResponse::remove_account_sweep_policy {
+                removed: service
+                    .remove_sweep_policy(&AccountID(account_id))
+                    .map_err(format_error)?,
+            }
+        }
         JsonCommandRequest::remove_gift_code { gift_code_b58 } => {
             JsonCommandResponse::remove_gift_code {
                 removed: service
@@ -1115,6 +2368,14 @@ where
                     .map_err(format_error)?,
             }
         }
+        JsonCommandRequest::remove_isolated_counterparty {
+            account_id,
+            counterparty,
+        } => JsonCommandResponse::remove_isolated_counterparty {
+            removed: service
+                .remove_isolated_counterparty(&AccountID(account_id), &counterparty)
+                .map_err(format_error)?,
+        },
         JsonCommandRequest::remove_view_only_account { account_id } => {
             JsonCommandResponse::remove_view_only_account {
                 removed: service
@@ -1122,6 +2383,155 @@ where
                     .map_err(format_error)?,
             }
         }
+        JsonCommandRequest::remove_webhook { webhook_id } => {
+            let webhook_id = webhook_id.parse::<i32>().map_err(format_error)?;
+            JsonCommandResponse::remove_webhook {
+                removed: service.remove_webhook(webhook_id).map_err(format_error)?,
+            }
+        }
+        JsonCommandRequest::replay_events {
+            from_cursor,
+            to_cursor,
+        } => {
+            let from_event_id = from_cursor.parse::<i32>().map_err(format_error)?;
+            let to_event_id = to_cursor
+                .map(|i| i.parse::<i32>())
+                .transpose()
+                .map_err(format_error)?;
+            let events = service
+                .replay_wallet_events(from_event_id, to_event_id)
+                .map_err(format_error)?;
+            JsonCommandResponse::replay_events {
+                events: events.iter().map(WalletEvent::from).collect(),
+            }
+        }
+        JsonCommandRequest::rescan_account {
+            account_id,
+            from_block_index,
+        } => {
+            let from_block_index = from_block_index.parse::<u64>().map_err(format_error)?;
+            JsonCommandResponse::rescan_account {
+                account: json_rpc::account::Account::try_from(
+                    &service
+                        .rescan_account(&AccountID(account_id), from_block_index)
+                        .map_err(format_error)?,
+                )
+                .map_err(format_error)?,
+            }
+        }
+        JsonCommandRequest::resume_account_sync { account_id } => {
+            JsonCommandResponse::resume_account_sync {
+                account: json_rpc::account::Account::try_from(
+                    &service
+                        .resume_account_sync(&AccountID(account_id))
+                        .map_err(format_error)?,
+                )
+                .map_err(format_error)?,
+            }
+        }
+        JsonCommandRequest::rotate_account {
+            account_id,
+            new_account_name,
+        } => JsonCommandResponse::rotate_account {
+            account_rotation: AccountRotation::from(
+                &service
+                    .rotate_account(&AccountID(account_id), new_account_name)
+                    .map_err(format_error)?,
+            ),
+        },
+        JsonCommandRequest::run_diagnostics => {
+            let checks = service.run_diagnostics();
+            let passed = checks
+                .iter()
+                .all(|check| check.status != DiagnosticStatus::Failed);
+            JsonCommandResponse::run_diagnostics {
+                checks: checks.iter().map(DiagnosticCheck::from).collect(),
+                passed,
+            }
+        }
+        JsonCommandRequest::set_account_first_block {
+            account_id,
+            block_index,
+        } => {
+            let block_index = block_index.parse::<u64>().map_err(format_error)?;
+            JsonCommandResponse::set_account_first_block {
+                account: json_rpc::account::Account::try_from(
+                    &service
+                        .set_account_first_block(&AccountID(account_id), block_index)
+                        .map_err(format_error)?,
+                )
+                .map_err(format_error)?,
+            }
+        }
+        JsonCommandRequest::set_account_sweep_policy {
+            account_id,
+            destination_address,
+            token_id,
+            threshold,
+            interval_seconds,
+        } => {
+            let token_id = token_id
+                .map(|token_id| token_id.parse::<u64>())
+                .transpose()
+                .map_err(format_error)?
+                .unwrap_or(*Mob::ID);
+            let threshold = threshold.parse::<u64>().map_err(format_error)?;
+            let interval_seconds = interval_seconds.parse::<u64>().map_err(format_error)?;
+            JsonCommandResponse::set_account_sweep_policy {
+                sweep_policy: SweepPolicy::from(
+                    &service
+                        .set_sweep_policy(
+                            &AccountID(account_id),
+                            &destination_address,
+                            token_id,
+                            threshold,
+                            interval_seconds,
+                        )
+                        .map_err(format_error)?,
+                ),
+            }
+        }
+        JsonCommandRequest::set_db_password { password } => JsonCommandResponse::set_db_password {
+            encrypted: service.set_db_password(&password).map_err(format_error)?,
+        },
+        JsonCommandRequest::set_ledger_db_max_size { max_size_bytes } => {
+            let max_size_bytes = max_size_bytes.parse::<u64>().map_err(format_error)?;
+            JsonCommandResponse::set_ledger_db_max_size {
+                ledger_db_size: LedgerDbSize::from(
+                    &service
+                        .set_ledger_db_max_size(max_size_bytes)
+                        .map_err(format_error)?,
+                ),
+            }
+        }
+        JsonCommandRequest::skip_quarantined_block { block_index } => {
+            let block_index = block_index.parse::<u64>().map_err(format_error)?;
+            JsonCommandResponse::skip_quarantined_block {
+                quarantined_block: QuarantinedBlock::from(
+                    &service
+                        .skip_quarantined_block(block_index)
+                        .map_err(format_error)?,
+                ),
+            }
+        }
+        JsonCommandRequest::start_maintenance {
+            admin_key,
+            eta_seconds,
+            reason,
+        } => JsonCommandResponse::start_maintenance {
+            maintenance_status: MaintenanceStatus::from(
+                &service
+                    .start_maintenance(
+                        &admin_key,
+                        eta_seconds
+                            .map(|s| s.parse::<u64>())
+                            .transpose()
+                            .map_err(format_error)?,
+                        reason,
+                    )
+                    .map_err(format_error)?,
+            ),
+        },
         JsonCommandRequest::submit_gift_code {
             from_account_id,
             gift_code_b58,
@@ -1139,6 +2549,39 @@ where
                 gift_code: GiftCode::from(&gift_code),
             }
         }
+        JsonCommandRequest::submit_payout_file {
+            account_id,
+            contents,
+            format,
+            amount_unit,
+            fee,
+            tombstone_block,
+            comment,
+        } => {
+            let format = format.parse::<PayoutFileFormat>().map_err(format_error)?;
+            let amount_unit = amount_unit
+                .map(|unit| unit.parse::<AmountUnit>())
+                .transpose()
+                .map_err(format_error)?
+                .unwrap_or(AmountUnit::Pmob);
+            let results = service
+                .submit_payout_file(
+                    &AccountID(account_id),
+                    &contents,
+                    format,
+                    amount_unit,
+                    fee,
+                    tombstone_block,
+                    comment,
+                )
+                .map_err(format_error)?;
+            let accepted_count = results.iter().filter(|result| result.accepted).count();
+            JsonCommandResponse::submit_payout_file {
+                results: results.iter().map(PayoutRowResult::from).collect(),
+                accepted_count: accepted_count.to_string(),
+                rejected_count: (results.len() - accepted_count).to_string(),
+            }
+        }
         JsonCommandRequest::submit_transaction {
             tx_proposal,
             comment,
@@ -1178,7 +2621,7 @@ where
                 .collect::<Result<Vec<_>, _>>()?;
 
             service
-                .set_view_only_txos_key_images(txo_ids_and_key_images)
+                .set_view_only_txos_key_images(txo_ids_and_key_images.clone())
                 .map_err(format_error)?;
 
             let subaddresses_decoded = subaddresses
@@ -1201,10 +2644,32 @@ where
 
             service
                 .import_subaddresses(&account_id, subaddresses_decoded)
+                .map_err(format_account_service_error)?;
+
+            service
+                .record_view_only_account_sync_response(&account_id, &txo_ids_and_key_images)
                 .map_err(format_error)?;
 
             JsonCommandResponse::sync_view_only_account
         }
+        JsonCommandRequest::unfreeze_txos { txo_ids } => {
+            let txos = service.unfreeze_txos(&txo_ids).map_err(format_error)?;
+            let txo_map: Map<String, serde_json::Value> = Map::from_iter(
+                txos.iter()
+                    .map(|t| {
+                        (
+                            t.txo_id_hex.clone(),
+                            serde_json::to_value(Txo::from(t)).expect("Could not get json value"),
+                        )
+                    })
+                    .collect::<Vec<(String, serde_json::Value)>>(),
+            );
+
+            JsonCommandResponse::unfreeze_txos {
+                txo_ids: txos.iter().map(|t| t.txo_id_hex.clone()).collect(),
+                txo_map,
+            }
+        }
         JsonCommandRequest::update_account_name { account_id, name } => {
             JsonCommandResponse::update_account_name {
                 account: json_rpc::account::Account::try_from(
@@ -1215,6 +2680,39 @@ where
                 .map_err(format_error)?,
             }
         }
+        JsonCommandRequest::update_account_preferred_token_id {
+            account_id,
+            preferred_token_id,
+        } => {
+            let preferred_token_id = preferred_token_id.parse::<u64>().map_err(format_error)?;
+            JsonCommandResponse::update_account_preferred_token_id {
+                account: json_rpc::account::Account::try_from(
+                    &service
+                        .update_account_preferred_token_id(
+                            &AccountID(account_id),
+                            preferred_token_id,
+                        )
+                        .map_err(format_error)?,
+                )
+                .map_err(format_error)?,
+            }
+        }
+        JsonCommandRequest::update_settings { settings } => JsonCommandResponse::update_settings {
+            settings: WalletSettings::from(
+                &service.update_settings(&settings).map_err(format_error)?,
+            ),
+        },
+        JsonCommandRequest::update_view_only_account_hardware_backed {
+            account_id,
+            hardware_backed,
+        } => JsonCommandResponse::update_view_only_account_hardware_backed {
+            view_only_account: json_rpc::view_only_account::ViewOnlyAccountJSON::try_from(
+                &service
+                    .update_view_only_account_hardware_backed(&account_id, hardware_backed)
+                    .map_err(format_error)?,
+            )
+            .map_err(format_error)?,
+        },
         JsonCommandRequest::update_view_only_account_name { account_id, name } => {
             JsonCommandResponse::update_view_only_account_name {
                 view_only_account: json_rpc::view_only_account::ViewOnlyAccountJSON::try_from(
@@ -1235,6 +2733,14 @@ where
                 .map_err(format_error)?;
             JsonCommandResponse::validate_confirmation { validated: result }
         }
+        JsonCommandRequest::verify_account_backup {
+            account_id,
+            mnemonic,
+        } => JsonCommandResponse::verify_account_backup {
+            verified: service
+                .verify_account_backup(&AccountID(account_id), &mnemonic)
+                .map_err(format_error)?,
+        },
         JsonCommandRequest::verify_address { address } => JsonCommandResponse::verify_address {
             verified: service.verify_address(&address).map_err(format_error)?,
         },
@@ -1248,6 +2754,42 @@ where
             ),
             commit: env!("VERGEN_GIT_SHA").to_string(),
         },
+        JsonCommandRequest::watch_transaction {
+            tx_public_keys,
+            receiver_receipt,
+            tombstone_block,
+            comment,
+        } => {
+            let mut public_keys: Vec<CompressedRistrettoPublic> = tx_public_keys
+                .unwrap_or_default()
+                .iter()
+                .map(|public_key| {
+                    let public_key_bytes = hex::decode(public_key).map_err(format_error)?;
+                    mc_util_serial::decode(&public_key_bytes).map_err(format_error)
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            let mut tombstone_block = tombstone_block
+                .map(|t| t.parse::<u64>())
+                .transpose()
+                .map_err(format_error)?;
+
+            // The receipt identifies an output, and carries the tombstone block
+            // of the transaction it was sent in.
+            if let Some(receiver_receipt) = receiver_receipt {
+                let receipt = service::receipt::ReceiverReceipt::try_from(&receiver_receipt)
+                    .map_err(format_error)?;
+                public_keys.push(receipt.public_key);
+                tombstone_block.get_or_insert(receipt.tombstone_block);
+            }
+
+            let transaction_watch = service
+                .watch_transaction(&public_keys, tombstone_block, comment)
+                .map_err(format_error)?;
+            JsonCommandResponse::watch_transaction {
+                transaction_watch: TransactionWatch::try_from(&transaction_watch)
+                    .map_err(format_error)?,
+            }
+        }
     };
 
     Ok(response)
@@ -1263,6 +2805,26 @@ fn health() -> Result<(), ()> {
     Ok(())
 }
 
+/// The route for Prometheus metrics, which needs no API key, so that it can be
+/// scraped. Not found unless metrics are enabled.
+#[get("/metrics")]
+fn prometheus_metrics(state: State<MetricsState>) -> Result<Content<String>, Custom<String>> {
+    if !state.0 {
+        return Err(Custom(Status::NotFound, "Not found".to_string()));
+    }
+    let metrics = encode_metrics().map_err(|err| Custom(Status::InternalServerError, err))?;
+    Ok(Content(ContentType::Plain, metrics))
+}
+
+/// Format an account service error, listing each rejected subaddress when an
+/// import of subaddresses is rejected.
+fn format_account_service_error(e: AccountServiceError) -> JsonRPCError {
+    match &e {
+        AccountServiceError::InvalidSubaddresses(errors) => format_entry_errors(&e, &errors.0),
+        _ => format_error(e),
+    }
+}
+
 fn page_helper(offset: Option<String>, limit: Option<String>) -> Result<(u64, u64), JsonRPCError> {
     let offset = match offset {
         Some(o) => o.parse::<u64>().map_err(format_error)?,
@@ -1275,6 +2837,29 @@ fn page_helper(offset: Option<String>, limit: Option<String>) -> Result<(u64, u6
     Ok((offset, limit))
 }
 
+/// Parse the params of a list which can be paged through with a cursor, or
+/// with an offset, returning the cursor, offset and page size.
+fn cursor_page_helper(
+    kind: &str,
+    cursor: Option<String>,
+    offset: Option<String>,
+    limit: Option<String>,
+) -> Result<(Option<i32>, Option<u64>, u64), JsonRPCError> {
+    if cursor.is_some() && offset.is_some() {
+        return Err(format_error(PageCursorError::CursorWithOffset));
+    }
+    let cursor = cursor
+        .map(|c| decode_page_cursor(kind, &c))
+        .transpose()
+        .map_err(format_error)?;
+    let offset = offset
+        .map(|o| o.parse::<u64>())
+        .transpose()
+        .map_err(format_error)?;
+    let (_, limit) = page_helper(None, limit)?;
+    Ok((cursor, offset, limit))
+}
+
 /// Returns an instance of a Rocket server.
 pub fn consensus_backed_rocket(
     rocket_config: rocket::Config,
@@ -1283,8 +2868,16 @@ pub fn consensus_backed_rocket(
     rocket::custom(rocket_config)
         .mount(
             "/",
-            routes![consensus_backed_wallet_api, wallet_help, health],
+            routes![
+                consensus_backed_wallet_api,
+                consensus_backed_export_txos,
+                consensus_backed_balance_badge,
+                wallet_help,
+                health,
+                prometheus_metrics
+            ],
         )
+        .register(catchers![unauthorized])
         .manage(state)
 }
 
@@ -1295,7 +2888,15 @@ pub fn validator_backed_rocket(
     rocket::custom(rocket_config)
         .mount(
             "/",
-            routes![validator_backed_wallet_api, wallet_help, health],
+            routes![
+                validator_backed_wallet_api,
+                validator_backed_export_txos,
+                validator_backed_balance_badge,
+                wallet_help,
+                health,
+                prometheus_metrics
+            ],
         )
+        .register(catchers![unauthorized])
         .manage(state)
 }
@@ -0,0 +1,40 @@
+// Copyright (c) 2020-2022 MobileCoin Inc.
+
+//! API definition for the Wallet Event object.
+
+use crate::db;
+
+use serde_derive::{Deserialize, Serialize};
+
+/// A notable change in wallet state.
+#[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+pub struct WalletEvent {
+    /// String representing the object's type. Objects of the same type share
+    /// the same value.
+    pub object: String,
+
+    /// Unique identifier for the event. Events are numbered in the order they
+    /// were recorded.
+    pub event_id: String,
+
+    /// The kind of event, e.g. "transaction_landed".
+    pub event_type: String,
+
+    /// Details of the event, which depend on its type.
+    pub payload: serde_json::Value,
+
+    /// Version of the payload schema the event was recorded with.
+    pub schema_version: String,
+}
+
+impl From<&db::models::WalletEvent> for WalletEvent {
+    fn from(src: &db::models::WalletEvent) -> WalletEvent {
+        WalletEvent {
+            object: "wallet_event".to_string(),
+            event_id: src.id.to_string(),
+            event_type: src.event_type.clone(),
+            payload: serde_json::from_str(&src.payload).unwrap_or_default(),
+            schema_version: src.schema_version.to_string(),
+        }
+    }
+}
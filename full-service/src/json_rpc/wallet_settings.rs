@@ -0,0 +1,50 @@
+// Copyright (c) 2020-2022 MobileCoin Inc.
+
+//! API definition for the Wallet Settings object.
+
+use crate::db;
+use serde_derive::{Deserialize, Serialize};
+
+/// Wallet-wide settings, which can be changed while the wallet is running.
+#[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+pub struct WalletSettings {
+    /// String representing the object's type. Objects of the same type share
+    /// the same value.
+    pub object: String,
+
+    /// Transactions built without an explicit fee pay the network minimum fee
+    /// times this multiplier.
+    pub default_fee_multiplier: String,
+
+    /// The number of blocks, counting the one it landed in, that a watched
+    /// transaction must be buried under before it is considered landed.
+    pub confirmation_depth: String,
+
+    /// Scheduled sweeps which would move no more than this value, in the
+    /// smallest unit of the token being swept, are skipped.
+    pub dust_threshold: String,
+
+    /// How long to wait for a webhook endpoint to respond.
+    pub webhook_timeout_seconds: String,
+
+    /// How many times to try to deliver a webhook before giving up.
+    pub webhook_max_attempts: String,
+
+    /// How many unfunded subaddress indices in a row the sync looks past for
+    /// subaddresses which orphaned txos were sent to, or 0 not to look.
+    pub subaddress_gap_limit: String,
+}
+
+impl From<&db::wallet_settings::WalletSettings> for WalletSettings {
+    fn from(src: &db::wallet_settings::WalletSettings) -> WalletSettings {
+        WalletSettings {
+            object: "wallet_settings".to_string(),
+            default_fee_multiplier: src.default_fee_multiplier.to_string(),
+            confirmation_depth: src.confirmation_depth.to_string(),
+            dust_threshold: src.dust_threshold.to_string(),
+            webhook_timeout_seconds: src.webhook_timeout_seconds.to_string(),
+            webhook_max_attempts: src.webhook_max_attempts.to_string(),
+            subaddress_gap_limit: src.subaddress_gap_limit.to_string(),
+        }
+    }
+}
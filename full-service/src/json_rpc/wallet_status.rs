@@ -2,11 +2,50 @@
 
 //! API definition for the Wallet Status object.
 
-use crate::{json_rpc, service};
+use crate::{db::txo::TxoStatusTotals, json_rpc, service};
 
 use serde_derive::{Deserialize, Serialize};
 use serde_json::Map;
-use std::{convert::TryFrom, iter::FromIterator};
+use std::{collections::BTreeMap, convert::TryFrom, iter::FromIterator};
+
+/// The total value of txos of one token in each status. Values are in the
+/// token's smallest unit, which for MOB is pico MOB.
+#[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+pub struct TokenTotals {
+    /// Unspent value, which may change while accounts are syncing.
+    pub unspent: String,
+
+    /// Pending, out-going value, which clears once the ledger processes the
+    /// outgoing txos.
+    pub pending: String,
+
+    /// Value of the txos which have been spent.
+    pub spent: String,
+
+    /// Value of the txos created for outgoing transactions.
+    pub secreted: String,
+
+    /// Value of the txos which were view-key matched, but which can not be
+    /// spent until their subaddress index is recovered.
+    pub orphaned: String,
+
+    /// The part of the unspent value which is frozen, and left out of
+    /// transactions until it is unfrozen.
+    pub frozen: String,
+}
+
+impl From<&TxoStatusTotals> for TokenTotals {
+    fn from(src: &TxoStatusTotals) -> TokenTotals {
+        TokenTotals {
+            unspent: src.unspent.to_string(),
+            pending: src.pending.to_string(),
+            spent: src.spent.to_string(),
+            secreted: src.secreted.to_string(),
+            orphaned: src.orphaned.to_string(),
+            frozen: src.frozen.to_string(),
+        }
+    }
+}
 
 /// The status of the wallet, including the sum of the balances for all
 /// accounts.
@@ -66,6 +105,23 @@ pub struct WalletStatus {
     /// A normalized hash mapping view only account_id to view only account
     /// objects.
     pub view_only_account_map: Map<String, serde_json::Value>,
+
+    /// Blocks the sync could not process, which no account can be synced past
+    /// until they can be processed or are skipped.
+    pub quarantined_block_indices: Vec<String>,
+
+    /// The wallet's totals of each token held by any account, by token ID.
+    /// The total_*_pmob fields above are the totals for MOB.
+    pub balance_per_token: BTreeMap<String, TokenTotals>,
+
+    /// The wallet's totals of each token held which this version of the
+    /// wallet has no metadata for, by token ID. They are not included in
+    /// balance_per_token.
+    pub unknown_token_balances: BTreeMap<String, TokenTotals>,
+
+    /// Each account's totals of each token it holds, by account ID and then
+    /// token ID. MOB is always included.
+    pub account_balance_per_token: BTreeMap<String, BTreeMap<String, TokenTotals>>,
 }
 
 impl TryFrom<&service::balance::WalletStatus> for WalletStatus {
@@ -115,6 +171,24 @@ impl TryFrom<&service::balance::WalletStatus> for WalletStatus {
                 .map(|a| a.to_string())
                 .collect(),
             view_only_account_map: Map::from_iter(view_only_account_mapped),
+            quarantined_block_indices: src
+                .quarantined_block_indices
+                .iter()
+                .map(|i| i.to_string())
+                .collect(),
+            balance_per_token: token_totals_json(&src.token_totals),
+            unknown_token_balances: token_totals_json(&src.unknown_token_totals),
+            account_balance_per_token: src
+                .account_token_totals
+                .iter()
+                .map(|(account_id, totals)| (account_id.to_string(), token_totals_json(totals)))
+                .collect(),
         })
     }
 }
+
+fn token_totals_json(src: &BTreeMap<u64, TxoStatusTotals>) -> BTreeMap<String, TokenTotals> {
+    src.iter()
+        .map(|(token_id, totals)| (token_id.to_string(), TokenTotals::from(totals)))
+        .collect()
+}
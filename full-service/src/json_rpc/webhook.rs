@@ -0,0 +1,52 @@
+// Copyright (c) 2020-2022 MobileCoin Inc.
+
+//! API definition for the Webhook object.
+
+use crate::db;
+use chrono::{offset::TimeZone, Utc};
+use serde_derive::{Deserialize, Serialize};
+
+/// A URL which is sent transaction outcomes and received txos as they are
+/// recorded.
+#[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq)]
+pub struct Webhook {
+    /// String representing the object's type. Objects of the same type share
+    /// the same value.
+    pub object: String,
+
+    /// Unique identifier for the webhook.
+    pub webhook_id: String,
+
+    /// The URL events are POSTed to.
+    pub url: String,
+
+    /// The account whose events are delivered, or null for all accounts.
+    pub account_id: Option<String>,
+
+    /// The last wallet event delivered to, or given up on for, the webhook.
+    pub last_event_id: String,
+
+    /// How many times delivery of the next event has failed.
+    pub failed_attempts: String,
+
+    /// Why the most recent failed delivery failed, if any has.
+    pub last_failure_message: Option<String>,
+
+    /// The time at which the webhook was registered.
+    pub created_time: String,
+}
+
+impl From<&db::models::Webhook> for Webhook {
+    fn from(src: &db::models::Webhook) -> Webhook {
+        Webhook {
+            object: "webhook".to_string(),
+            webhook_id: src.id.to_string(),
+            url: src.url.clone(),
+            account_id: src.account_id_hex.clone(),
+            last_event_id: src.last_event_id.to_string(),
+            failed_attempts: src.failed_attempts.to_string(),
+            last_failure_message: src.last_failure_message.clone(),
+            created_time: Utc.timestamp(src.created_time, 0).to_string(),
+        }
+    }
+}
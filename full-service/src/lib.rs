@@ -6,18 +6,26 @@
 
 pub mod check_host;
 pub mod config;
+pub mod consensus_pool;
 pub mod db;
+pub mod disk_space_monitor;
 mod error;
 pub mod fog_resolver;
+pub mod hardware;
 pub mod json_rpc;
+pub mod metrics;
 pub mod service;
+pub mod sync_proof;
+pub mod tls_reload;
+pub mod txo_classifier;
 pub mod unsigned_tx;
 pub mod util;
 mod validator_ledger_sync;
 
 pub use db::WalletDb;
+pub use disk_space_monitor::{DiskSpaceMonitor, DiskSpaceState};
 pub use json_rpc::wallet;
-pub use service::WalletService;
+pub use service::{WalletService, WalletServiceOptions};
 pub use validator_ledger_sync::ValidatorLedgerSyncThread;
 
 extern crate alloc;
@@ -0,0 +1,254 @@
+// Copyright (c) 2020-2022 MobileCoin Inc.
+
+//! Prometheus counters of the funds each account receives and sends, of the
+//! wallet's connections to consensus validators, and of the space left for the
+//! databases.
+//!
+//! Operational dashboards want to show how funds flow through the wallet
+//! without polling the API. Received txos are counted when an account's sync
+//! commits them, and sent transactions when they are submitted. Accounts are
+//! labelled with a hash of their ID rather than the ID, so that the metrics
+//! don't identify accounts to whoever can scrape them. The counters start
+//! from zero each time full-service starts.
+
+use crate::util::disk_space::DiskSpaceLevel;
+use lazy_static::lazy_static;
+use mc_crypto_digestible::{Digestible, MerlinTranscript};
+use prometheus::{
+    register_histogram_vec, register_int_counter_vec, register_int_gauge_vec, Encoder,
+    HistogramVec, IntCounterVec, IntGaugeVec, TextEncoder,
+};
+use std::time::Duration;
+
+lazy_static! {
+    static ref RECEIVED_VALUE: IntCounterVec = register_int_counter_vec!(
+        "full_service_received_value_total",
+        "Value received by the account, not counting change, in the token's smallest unit",
+        &["account", "token_id"]
+    )
+    .expect("Failed registering metric");
+    static ref RECEIVED_TXOS: IntCounterVec = register_int_counter_vec!(
+        "full_service_received_txos_total",
+        "Txos received by the account, not counting change",
+        &["account", "token_id"]
+    )
+    .expect("Failed registering metric");
+    static ref SENT_VALUE: IntCounterVec = register_int_counter_vec!(
+        "full_service_sent_value_total",
+        "Value sent by the account, not counting change or fees, in the token's smallest unit",
+        &["account", "token_id"]
+    )
+    .expect("Failed registering metric");
+    static ref SENT_TRANSACTIONS: IntCounterVec = register_int_counter_vec!(
+        "full_service_sent_transactions_total",
+        "Transactions submitted by the account",
+        &["account", "token_id"]
+    )
+    .expect("Failed registering metric");
+    static ref SUBMIT_SECONDS: HistogramVec = register_histogram_vec!(
+        "full_service_consensus_submit_seconds",
+        "Time taken to submit a transaction to the consensus peer",
+        &["peer", "result"]
+    )
+    .expect("Failed registering metric");
+    static ref ATTESTATION_SECONDS: HistogramVec = register_histogram_vec!(
+        "full_service_consensus_attestation_seconds",
+        "Time taken to attest a pooled connection to the consensus peer",
+        &["peer", "result"]
+    )
+    .expect("Failed registering metric");
+    static ref WARM_CONNECTIONS: IntGaugeVec = register_int_gauge_vec!(
+        "full_service_consensus_warm_connections",
+        "Pooled connections to the consensus peer which are attested",
+        &["peer"]
+    )
+    .expect("Failed registering metric");
+    static ref ATTESTATION_VERIFIED: IntGaugeVec = register_int_gauge_vec!(
+        "full_service_consensus_attestation_verified",
+        "Whether the latest attestation to the consensus peer verified",
+        &["peer"]
+    )
+    .expect("Failed registering metric");
+    static ref MEASUREMENT_CHANGES: IntCounterVec = register_int_counter_vec!(
+        "full_service_consensus_measurement_changes_total",
+        "Times the consensus peer attested with a different enclave measurement than before",
+        &["peer"]
+    )
+    .expect("Failed registering metric");
+    static ref DISK_AVAILABLE_BYTES: IntGaugeVec = register_int_gauge_vec!(
+        "full_service_disk_available_bytes",
+        "Space the database on the volume has left to grow into",
+        &["volume"]
+    )
+    .expect("Failed registering metric");
+    static ref DISK_SPACE_LEVEL: IntGaugeVec = register_int_gauge_vec!(
+        "full_service_disk_space_level",
+        "Free space on the volume against the thresholds: 0 ok, 1 low, 2 critical",
+        &["volume"]
+    )
+    .expect("Failed registering metric");
+}
+
+fn result_label(ok: bool) -> &'static str {
+    if ok {
+        "ok"
+    } else {
+        "error"
+    }
+}
+
+/// The label an account's metrics are given, which is the start of a hash of
+/// its ID.
+pub fn account_label(account_id_hex: &str) -> String {
+    let digest: [u8; 32] = account_id_hex
+        .to_string()
+        .digest32::<MerlinTranscript>(b"metrics_account_label");
+    hex::encode(&digest[..8])
+}
+
+/// Count a txo received by the account.
+pub fn record_received(account_id_hex: &str, token_id: u64, value: u64) {
+    let labels = [account_label(account_id_hex), token_id.to_string()];
+    let labels = [labels[0].as_str(), labels[1].as_str()];
+    RECEIVED_VALUE.with_label_values(&labels).inc_by(value);
+    RECEIVED_TXOS.with_label_values(&labels).inc();
+}
+
+/// Count a transaction submitted by the account.
+pub fn record_sent(account_id_hex: &str, token_id: u64, value: u64) {
+    let labels = [account_label(account_id_hex), token_id.to_string()];
+    let labels = [labels[0].as_str(), labels[1].as_str()];
+    SENT_VALUE.with_label_values(&labels).inc_by(value);
+    SENT_TRANSACTIONS.with_label_values(&labels).inc();
+}
+
+/// Time a transaction submission to a consensus peer.
+pub fn record_submit(peer: &str, duration: Duration, ok: bool) {
+    SUBMIT_SECONDS
+        .with_label_values(&[peer, result_label(ok)])
+        .observe(duration.as_secs_f64());
+}
+
+/// Time an attestation of a pooled connection to a consensus peer.
+pub fn record_attestation(peer: &str, duration: Duration, ok: bool) {
+    ATTESTATION_SECONDS
+        .with_label_values(&[peer, result_label(ok)])
+        .observe(duration.as_secs_f64());
+}
+
+/// Set the number of pooled connections to a consensus peer which are
+/// attested.
+pub fn set_warm_connections(peer: &str, warm: usize) {
+    WARM_CONNECTIONS.with_label_values(&[peer]).set(warm as i64);
+}
+
+/// Set whether the latest attestation to a consensus peer verified.
+pub fn set_attestation_verified(peer: &str, verified: bool) {
+    ATTESTATION_VERIFIED
+        .with_label_values(&[peer])
+        .set(verified as i64);
+}
+
+/// Count a consensus peer attesting with a different enclave measurement.
+pub fn record_measurement_change(peer: &str) {
+    MEASUREMENT_CHANGES.with_label_values(&[peer]).inc();
+}
+
+/// Set the space the database on a volume has left, or clear it if the volume
+/// could not be measured, and its level against the thresholds.
+pub fn set_disk_space(volume: &str, available_bytes: Option<u64>, level: DiskSpaceLevel) {
+    match available_bytes {
+        Some(available_bytes) => DISK_AVAILABLE_BYTES
+            .with_label_values(&[volume])
+            .set(available_bytes.min(i64::MAX as u64) as i64),
+        None => {
+            let _ = DISK_AVAILABLE_BYTES.remove_label_values(&[volume]);
+        }
+    }
+    let level = match level {
+        DiskSpaceLevel::Ok => 0,
+        DiskSpaceLevel::Low => 1,
+        DiskSpaceLevel::Critical => 2,
+    };
+    DISK_SPACE_LEVEL.with_label_values(&[volume]).set(level);
+}
+
+/// The metrics of full-service, in the Prometheus text format.
+pub fn encode_metrics() -> Result<String, String> {
+    let mut buffer = Vec::new();
+    TextEncoder::new()
+        .encode(&prometheus::gather(), &mut buffer)
+        .map_err(|err| err.to_string())?;
+    String::from_utf8(buffer).map_err(|err| err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_metrics() {
+        let account_id_hex = "a8c9c7acb96cf4ad9154eec9384c09f2c75a340b441924847fe5f60a41805bde";
+        let label = account_label(account_id_hex);
+        assert_eq!(label.len(), 16);
+        assert_eq!(label, account_label(account_id_hex));
+        assert_ne!(label, account_label("deadbeef"));
+
+        record_received(account_id_hex, 0, 100);
+        record_received(account_id_hex, 0, 50);
+        record_sent(account_id_hex, 0, 30);
+
+        assert_eq!(RECEIVED_VALUE.with_label_values(&[&label, "0"]).get(), 150);
+        assert_eq!(RECEIVED_TXOS.with_label_values(&[&label, "0"]).get(), 2);
+        assert_eq!(SENT_VALUE.with_label_values(&[&label, "0"]).get(), 30);
+        assert_eq!(SENT_TRANSACTIONS.with_label_values(&[&label, "0"]).get(), 1);
+
+        // The account's ID is never exposed, only its label.
+        let metrics = encode_metrics().unwrap();
+        assert!(metrics.contains(&label));
+        assert!(!metrics.contains(account_id_hex));
+    }
+
+    #[test]
+    fn test_record_consensus_metrics() {
+        let peer = "node1.test.mobilecoin.com:443";
+        record_submit(peer, Duration::from_millis(120), true);
+        record_attestation(peer, Duration::from_millis(400), false);
+        set_warm_connections(peer, 2);
+        set_attestation_verified(peer, true);
+        record_measurement_change(peer);
+
+        assert_eq!(
+            SUBMIT_SECONDS
+                .with_label_values(&[peer, "ok"])
+                .get_sample_count(),
+            1
+        );
+        assert_eq!(
+            ATTESTATION_SECONDS
+                .with_label_values(&[peer, "error"])
+                .get_sample_count(),
+            1
+        );
+        assert_eq!(WARM_CONNECTIONS.with_label_values(&[peer]).get(), 2);
+        assert_eq!(ATTESTATION_VERIFIED.with_label_values(&[peer]).get(), 1);
+        assert_eq!(MEASUREMENT_CHANGES.with_label_values(&[peer]).get(), 1);
+    }
+
+    #[test]
+    fn test_set_disk_space() {
+        set_disk_space("test_db", Some(u64::MAX), DiskSpaceLevel::Low);
+        assert_eq!(
+            DISK_AVAILABLE_BYTES.with_label_values(&["test_db"]).get(),
+            i64::MAX
+        );
+        assert_eq!(DISK_SPACE_LEVEL.with_label_values(&["test_db"]).get(), 1);
+
+        // An unmeasured volume has no free space reported, rather than a stale
+        // one.
+        set_disk_space("test_db", None, DiskSpaceLevel::Low);
+        assert!(!encode_metrics()
+            .unwrap()
+            .contains("full_service_disk_available_bytes{volume=\"test_db\"}"));
+    }
+}
@@ -5,12 +5,21 @@
 use crate::{
     db::{
         account::{AccountID, AccountModel, ViewOnlyAccountImportPackage},
+        account_snapshot::AccountSnapshotModel,
+        account_sync_record::AccountSyncRecordModel,
         assigned_subaddress::AssignedSubaddressModel,
-        models::{Account, AssignedSubaddress},
-        transaction, WalletDbError,
+        models::{
+            Account, AccountSnapshot, AccountSyncRecord, AssignedSubaddress, Txo, WalletEvent,
+            EVENT_TYPE_ACCOUNT_IMPORT_CANCELLED,
+        },
+        transaction,
+        txo::TxoModel,
+        wallet_event::WalletEventModel,
+        WalletDbError,
     },
     service::{
         ledger::{LedgerService, LedgerServiceError},
+        view_only_account::SubaddressImportErrors,
         WalletService,
     },
     util::constants::MNEMONIC_KEY_DERIVATION_VERSION,
@@ -18,12 +27,13 @@ use crate::{
 use base64;
 use bip39::{Language, Mnemonic, MnemonicType};
 use displaydoc::Display;
-use mc_account_keys::RootEntropy;
-use mc_account_keys_slip10;
+use mc_account_keys::{AccountKey, RootEntropy};
+use mc_account_keys_slip10::Slip10Key;
 use mc_common::logger::log;
 use mc_connection::{BlockchainConnection, UserTxConnection};
 use mc_fog_report_validation::FogPubkeyResolver;
 use mc_ledger_db::Ledger;
+use serde_json::json;
 
 #[derive(Display, Debug)]
 pub enum AccountServiceError {
@@ -53,6 +63,23 @@ pub enum AccountServiceError {
 
     /// Error decoding private view key: {0}
     DecodePrivateKeyError(String),
+
+    /// Account has finished importing: {0}
+    AccountNotImporting(String),
+
+    /// Could not import account {0} of the batch: {1}
+    BatchImport(usize, String),
+
+    /// Could not import subaddresses: {0}
+    InvalidSubaddresses(SubaddressImportErrors),
+
+    /// Account {0} was imported from legacy root entropy, so has no mnemonic to
+    /// verify
+    NoMnemonic(String),
+
+    /// Cannot rescan from block {0}, as the account has only synced up to
+    /// block {1}
+    RescanFromUnsyncedBlock(u64, u64),
 }
 
 impl From<WalletDbError> for AccountServiceError {
@@ -103,6 +130,49 @@ impl From<mc_util_serial::DecodeError> for AccountServiceError {
     }
 }
 
+/// The secret an account is imported from.
+#[derive(Clone, Debug)]
+pub enum AccountImportSecret {
+    /// A BIP39 mnemonic, with its key derivation version.
+    Mnemonic {
+        mnemonic_phrase: String,
+        key_derivation_version: u8,
+    },
+
+    /// Hex encoded legacy root entropy.
+    LegacyRootEntropy(String),
+}
+
+/// A single account in a batch import.
+#[derive(Clone, Debug)]
+pub struct AccountImport {
+    pub secret: AccountImportSecret,
+    pub name: Option<String>,
+    pub first_block_index: Option<u64>,
+    pub next_subaddress_index: Option<u64>,
+    pub fog_report_url: String,
+    pub fog_report_id: String,
+    pub fog_authority_spki: String,
+}
+
+/// The blocks the sync thread will scan to catch up the accounts of a batch
+/// import. The accounts share an import block, so the batch is caught up once
+/// the blocks from the earliest first block to the import block are scanned.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AccountImportSyncPlan {
+    /// The earliest first block of any account in the batch.
+    pub start_block_index: u64,
+
+    /// The last block in the ledger at the time of import.
+    pub import_block_index: u64,
+
+    /// The number of blocks to scan before the batch is caught up.
+    pub num_blocks: u64,
+
+    /// The number of accounts which need to scan any blocks to catch up.
+    pub num_accounts: u64,
+}
+
 /// Trait defining the ways in which the wallet can interact with and manage
 /// accounts.
 pub trait AccountService {
@@ -142,6 +212,13 @@ pub trait AccountService {
         fog_authority_spki: String,
     ) -> Result<Account, AccountServiceError>;
 
+    /// Import several accounts at once. Either all of the accounts are
+    /// imported, or, if any of them can't be, none are.
+    fn import_accounts(
+        &self,
+        imports: Vec<AccountImport>,
+    ) -> Result<(Vec<Account>, AccountImportSyncPlan), AccountServiceError>;
+
     /// List accounts in the wallet.
     fn list_accounts(&self) -> Result<Vec<Account>, AccountServiceError>;
 
@@ -155,13 +232,81 @@ pub trait AccountService {
         name: String,
     ) -> Result<Account, AccountServiceError>;
 
+    /// Set the token an account's transactions and balances use when none is
+    /// specified.
+    fn update_account_preferred_token_id(
+        &self,
+        account_id: &AccountID,
+        preferred_token_id: u64,
+    ) -> Result<Account, AccountServiceError>;
+
     fn get_view_only_import_package(
         &self,
         account_id: &AccountID,
     ) -> Result<ViewOnlyAccountImportPackage, AccountServiceError>;
 
+    /// Change the block index an account starts scanning from, e.g. because it
+    /// was imported with the wrong first_block_index.
+    ///
+    /// Moving the first block earlier rescans the account from the new first
+    /// block. Moving it later skips any blocks not yet scanned, but keeps
+    /// anything already found before the new first block.
+    fn set_account_first_block(
+        &self,
+        account_id: &AccountID,
+        first_block_index: u64,
+    ) -> Result<Account, AccountServiceError>;
+
+    /// Rescan an account from a block it has already synced, e.g. because the
+    /// wallet db got out of sync with the ledger, or the account was imported
+    /// with a first_block_index after its first txo.
+    ///
+    /// Txos found since the block are kept along with their metadata, and are
+    /// found again. Txos spent since the block are pending until the rescan
+    /// reaches the block they were spent in, so that they are not spent again
+    /// in the meantime. A block before the first block also moves the first
+    /// block back to it.
+    fn rescan_account(
+        &self,
+        account_id: &AccountID,
+        from_block_index: u64,
+    ) -> Result<Account, AccountServiceError>;
+
+    /// Stop the sync thread scanning new blocks for an account, e.g. to keep a
+    /// large cold account from slowing the sync of the others. The account
+    /// keeps what it has already found, and can still be used to build
+    /// transactions, but its balance falls behind the ledger.
+    fn pause_account_sync(&self, account_id: &AccountID) -> Result<Account, AccountServiceError>;
+
+    /// Let the sync thread scan new blocks for a paused account again, from
+    /// where it left off.
+    fn resume_account_sync(&self, account_id: &AccountID) -> Result<Account, AccountServiceError>;
+
+    /// List the chunks of blocks the sync thread has scanned for an account,
+    /// oldest first, with how long each took.
+    fn get_sync_history(
+        &self,
+        account_id: &AccountID,
+        offset: Option<u64>,
+        limit: Option<u64>,
+    ) -> Result<Vec<AccountSyncRecord>, AccountServiceError>;
+
     /// Remove an account from the wallet.
     fn remove_account(&self, account_id: &AccountID) -> Result<bool, AccountServiceError>;
+
+    /// Stop importing an account, removing it along with anything found so
+    /// far. Fails if the account has already caught up with the ledger as it
+    /// was at import, in which case it should be removed with remove_account.
+    fn cancel_account_import(&self, account_id: &AccountID) -> Result<bool, AccountServiceError>;
+
+    /// Check whether a mnemonic, such as one the user has written down as a
+    /// backup, reproduces the account's keys. The account's secrets are never
+    /// returned, and a phrase which isn't a valid mnemonic doesn't verify.
+    fn verify_account_backup(
+        &self,
+        account_id: &AccountID,
+        mnemonic_phrase: &str,
+    ) -> Result<bool, AccountServiceError>;
 }
 
 impl<T, FPR> AccountService for WalletService<T, FPR>
@@ -312,6 +457,112 @@ where
         })
     }
 
+    fn import_accounts(
+        &self,
+        imports: Vec<AccountImport>,
+    ) -> Result<(Vec<Account>, AccountImportSyncPlan), AccountServiceError> {
+        log::info!(self.logger, "Importing {} accounts", imports.len());
+
+        // Decode every secret before touching the database, so that a bad
+        // entry is reported without starting the import.
+        enum DecodedSecret {
+            Mnemonic(Mnemonic),
+            LegacyRootEntropy(RootEntropy),
+        }
+        let decoded: Vec<DecodedSecret> = imports
+            .iter()
+            .enumerate()
+            .map(|(index, import)| match &import.secret {
+                AccountImportSecret::Mnemonic {
+                    mnemonic_phrase,
+                    key_derivation_version,
+                } => {
+                    if *key_derivation_version != MNEMONIC_KEY_DERIVATION_VERSION {
+                        return Err(AccountServiceError::BatchImport(
+                            index,
+                            AccountServiceError::UnknownKeyDerivation(*key_derivation_version)
+                                .to_string(),
+                        ));
+                    }
+                    Mnemonic::from_phrase(mnemonic_phrase, Language::English)
+                        .map(DecodedSecret::Mnemonic)
+                        .map_err(|_| {
+                            AccountServiceError::BatchImport(
+                                index,
+                                "Invalid BIP39 english mnemonic".to_string(),
+                            )
+                        })
+                }
+                AccountImportSecret::LegacyRootEntropy(entropy) => {
+                    let mut entropy_bytes = [0u8; 32];
+                    hex::decode_to_slice(entropy, &mut entropy_bytes)
+                        .map_err(|err| AccountServiceError::BatchImport(index, err.to_string()))?;
+                    Ok(DecodedSecret::LegacyRootEntropy(RootEntropy::from(
+                        &entropy_bytes,
+                    )))
+                }
+            })
+            .collect::<Result<_, _>>()?;
+
+        // We record the local highest block index because that is the earliest we could
+        // start scanning.
+        let import_block = self.ledger_db.num_blocks()? - 1;
+
+        let conn = self.wallet_db.get_conn()?;
+        let accounts = transaction(&conn, || {
+            imports
+                .iter()
+                .zip(decoded.iter())
+                .enumerate()
+                .map(|(index, (import, secret))| {
+                    match secret {
+                        DecodedSecret::Mnemonic(mnemonic) => Account::import(
+                            mnemonic,
+                            import.name.clone(),
+                            import_block,
+                            import.first_block_index,
+                            import.next_subaddress_index,
+                            import.fog_report_url.clone(),
+                            import.fog_report_id.clone(),
+                            import.fog_authority_spki.clone(),
+                            &conn,
+                        ),
+                        DecodedSecret::LegacyRootEntropy(entropy) => Account::import_legacy(
+                            entropy,
+                            import.name.clone(),
+                            import_block,
+                            import.first_block_index,
+                            import.next_subaddress_index,
+                            import.fog_report_url.clone(),
+                            import.fog_report_id.clone(),
+                            import.fog_authority_spki.clone(),
+                            &conn,
+                        ),
+                    }
+                    .map_err(|err| AccountServiceError::BatchImport(index, err.to_string()))
+                })
+                .collect::<Result<Vec<_>, AccountServiceError>>()
+        })?;
+
+        let catching_up: Vec<&Account> = accounts
+            .iter()
+            .filter(|account| account.next_block_index as u64 <= import_block)
+            .collect();
+        let start_block_index = catching_up
+            .iter()
+            .map(|account| account.next_block_index as u64)
+            .min()
+            .unwrap_or(import_block + 1);
+        let sync_plan = AccountImportSyncPlan {
+            start_block_index,
+            import_block_index: import_block,
+            num_blocks: import_block + 1 - start_block_index,
+            num_accounts: catching_up.len() as u64,
+        };
+
+        Ok((accounts, sync_plan))
+    }
+
     fn list_accounts(&self) -> Result<Vec<Account>, AccountServiceError> {
         let conn = self.wallet_db.get_conn()?;
         Ok(Account::list_all(&conn)?)
@@ -332,6 +583,16 @@ where
         Ok(Account::get(account_id, &conn)?)
     }
 
+    fn update_account_preferred_token_id(
+        &self,
+        account_id: &AccountID,
+        preferred_token_id: u64,
+    ) -> Result<Account, AccountServiceError> {
+        let conn = self.wallet_db.get_conn()?;
+        Account::get(account_id, &conn)?.update_preferred_token_id(preferred_token_id, &conn)?;
+        Ok(Account::get(account_id, &conn)?)
+    }
+
     fn get_view_only_import_package(
         &self,
         account_id: &AccountID,
@@ -350,30 +611,213 @@ where
         Ok(view_only_account_import_package)
     }
 
+    fn set_account_first_block(
+        &self,
+        account_id: &AccountID,
+        first_block_index: u64,
+    ) -> Result<Account, AccountServiceError> {
+        log::info!(
+            self.logger,
+            "Setting first block of account {} to {}",
+            account_id,
+            first_block_index,
+        );
+
+        let conn = self.wallet_db.get_conn()?;
+        transaction(&conn, || {
+            let account = Account::get(account_id, &conn)?;
+            let next_block_index = account.next_block_index as u64;
+
+            account.update_first_block_index(first_block_index, &conn)?;
+            // The snapshots were taken from a different range of blocks, so
+            // are taken again as the account syncs.
+            AccountSnapshot::delete_for_account(&account_id.0, &conn)?;
+            if first_block_index < account.first_block_index as u64 {
+                // Rescan from the new first block. Txos and transaction logs
+                // that were already found are kept, along with their metadata.
+                account.update_next_block_index(first_block_index, &conn)?;
+            } else if next_block_index < first_block_index {
+                account.update_next_block_index(first_block_index, &conn)?;
+            }
+
+            Ok(Account::get(account_id, &conn)?)
+        })
+    }
+
+    fn rescan_account(
+        &self,
+        account_id: &AccountID,
+        from_block_index: u64,
+    ) -> Result<Account, AccountServiceError> {
+        log::info!(
+            self.logger,
+            "Rescanning account {} from block {}",
+            account_id,
+            from_block_index,
+        );
+
+        let conn = self.wallet_db.get_conn()?;
+        let account = transaction(&conn, || {
+            let account = Account::get(account_id, &conn)?;
+            let next_block_index = account.next_block_index as u64;
+            if from_block_index > next_block_index {
+                return Err(AccountServiceError::RescanFromUnsyncedBlock(
+                    from_block_index,
+                    next_block_index,
+                ));
+            }
+
+            let num_pending = Txo::update_spent_from_block_index_to_pending(
+                &account_id.0,
+                from_block_index,
+                &conn,
+            )?;
+            if num_pending > 0 {
+                log::info!(
+                    self.logger,
+                    "Marked {} txos of account {} spent since block {} as pending",
+                    num_pending,
+                    account_id,
+                    from_block_index,
+                );
+            }
+            if from_block_index < account.first_block_index as u64 {
+                account.update_first_block_index(from_block_index, &conn)?;
+            }
+            account.update_next_block_index(from_block_index, &conn)?;
+            AccountSnapshot::delete_from(&account_id.0, from_block_index, &conn)?;
+
+            Ok(Account::get(account_id, &conn)?)
+        })?;
+        self.balance_cache.invalidate(&account_id.0);
+        Ok(account)
+    }
+
+    fn pause_account_sync(&self, account_id: &AccountID) -> Result<Account, AccountServiceError> {
+        log::info!(self.logger, "Pausing sync of account {}", account_id);
+
+        let conn = self.wallet_db.get_conn()?;
+        transaction(&conn, || {
+            let account = Account::get(account_id, &conn)?;
+            account.update_sync_paused(true, &conn)?;
+            Ok(Account::get(account_id, &conn)?)
+        })
+    }
+
+    fn resume_account_sync(&self, account_id: &AccountID) -> Result<Account, AccountServiceError> {
+        log::info!(self.logger, "Resuming sync of account {}", account_id);
+
+        let conn = self.wallet_db.get_conn()?;
+        transaction(&conn, || {
+            let account = Account::get(account_id, &conn)?;
+            account.update_sync_paused(false, &conn)?;
+            Ok(Account::get(account_id, &conn)?)
+        })
+    }
+
+    fn get_sync_history(
+        &self,
+        account_id: &AccountID,
+        offset: Option<u64>,
+        limit: Option<u64>,
+    ) -> Result<Vec<AccountSyncRecord>, AccountServiceError> {
+        let conn = self.wallet_db.get_conn()?;
+        Account::get(account_id, &conn)?;
+        Ok(AccountSyncRecord::list_for_account(
+            &account_id.0,
+            offset,
+            limit,
+            &conn,
+        )?)
+    }
+
     fn remove_account(&self, account_id: &AccountID) -> Result<bool, AccountServiceError> {
         log::info!(self.logger, "Deleting account {}", account_id,);
         let conn = self.wallet_db.get_conn()?;
         transaction(&conn, || {
             let account = Account::get(account_id, &conn)?;
             account.delete(&conn)?;
-            Ok(true)
-        })
+            Ok::<(), AccountServiceError>(())
+        })?;
+        self.balance_cache.invalidate(&account_id.0);
+        Ok(true)
+    }
+
+    fn cancel_account_import(&self, account_id: &AccountID) -> Result<bool, AccountServiceError> {
+        log::info!(self.logger, "Cancelling import of account {}", account_id,);
+        let conn = self.wallet_db.get_conn()?;
+        // The sync thread scans each chunk of blocks in its own exclusive
+        // transaction, so the account is removed between chunks rather than
+        // part way through one.
+        transaction(&conn, || {
+            let account = Account::get(account_id, &conn)?;
+            if !account.is_importing() {
+                return Err(AccountServiceError::AccountNotImporting(
+                    account_id.to_string(),
+                ));
+            }
+            let next_block_index = account.next_block_index;
+            account.delete(&conn)?;
+            WalletEvent::create(
+                EVENT_TYPE_ACCOUNT_IMPORT_CANCELLED,
+                &json!({
+                    "account_id": account_id.to_string(),
+                    "next_block_index": (next_block_index as u64).to_string(),
+                }),
+                &conn,
+            )?;
+            Ok(())
+        })?;
+        self.balance_cache.invalidate(&account_id.0);
+        Ok(true)
+    }
+
+    fn verify_account_backup(
+        &self,
+        account_id: &AccountID,
+        mnemonic_phrase: &str,
+    ) -> Result<bool, AccountServiceError> {
+        let conn = self.wallet_db.get_conn()?;
+        let account = Account::get(account_id, &conn)?;
+        if account.key_derivation_version != MNEMONIC_KEY_DERIVATION_VERSION as i32 {
+            return Err(AccountServiceError::NoMnemonic(account_id.to_string()));
+        }
+
+        // Words are often written down with different spacing or capitals. The
+        // phrase is never logged or returned in an error, as it is a secret.
+        let mnemonic_phrase = mnemonic_phrase
+            .split_whitespace()
+            .map(|word| word.to_lowercase())
+            .collect::<Vec<String>>()
+            .join(" ");
+        let mnemonic = match Mnemonic::from_phrase(&mnemonic_phrase, Language::English) {
+            Ok(m) => m,
+            Err(_) => return Ok(false),
+        };
+
+        // The account id covers the fog details too, so derive the keys with
+        // the account's own.
+        let account_key: AccountKey = mc_util_serial::decode(&account.account_key)?;
+        let backup_account_key = Slip10Key::from(mnemonic).try_into_account_key(
+            account_key.fog_report_url().unwrap_or(""),
+            account_key.fog_report_id().unwrap_or(""),
+            account_key.fog_authority_spki().unwrap_or(&[]),
+        )?;
+
+        Ok(AccountID::from(&backup_account_key) == *account_id)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{
-        db::{models::Txo, txo::TxoModel},
-        test_utils::{
-            create_test_received_txo, get_empty_test_ledger, get_test_ledger,
-            manually_sync_account, setup_wallet_service, setup_wallet_service_offline, MOB,
-        },
+    use crate::test_utils::{
+        add_block_to_ledger_db, create_test_received_txo, get_empty_test_ledger, get_test_ledger,
+        manually_sync_account, setup_wallet_service, setup_wallet_service_offline, MOB,
     };
     use mc_account_keys::{AccountKey, PublicAddress};
     use mc_common::logger::{test_with_logger, Logger};
-    use mc_transaction_core::{tokens::Mob, Amount, Token};
+    use mc_transaction_core::{ring_signature::KeyImage, tokens::Mob, Amount, Token};
     use rand::{rngs::StdRng, SeedableRng};
 
     #[test_with_logger]
@@ -499,4 +943,396 @@ mod tests {
         assert_eq!(account.next_block_index, 0);
         assert_eq!(account.import_block_index, Some(0));
     }
+
+    #[test_with_logger]
+    fn test_cancel_account_import(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+        let service = setup_wallet_service(ledger_db.clone(), logger.clone());
+
+        let import = |name: &str| {
+            let mnemonic = Mnemonic::new(MnemonicType::Words24, Language::English);
+            service
+                .import_account(
+                    mnemonic.phrase().to_string(),
+                    MNEMONIC_KEY_DERIVATION_VERSION,
+                    Some(name.to_string()),
+                    None,
+                    None,
+                    "".to_string(),
+                    "".to_string(),
+                    "".to_string(),
+                )
+                .unwrap()
+        };
+
+        // An account which has caught up can't have its import cancelled.
+        let account = import("A");
+        assert!(account.is_importing());
+        let account_id = AccountID(account.account_id_hex);
+        manually_sync_account(&ledger_db, &service.wallet_db, &account_id, &logger);
+        assert!(!service.get_account(&account_id).unwrap().is_importing());
+        assert!(matches!(
+            service.cancel_account_import(&account_id),
+            Err(AccountServiceError::AccountNotImporting(_))
+        ));
+
+        let conn = service.wallet_db.get_conn().unwrap();
+        let events = WalletEvent::list(None, None, &conn).unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(
+            events[0].event_type,
+            crate::db::models::EVENT_TYPE_ACCOUNT_IMPORT_COMPLETED
+        );
+
+        // An account which is still importing is removed.
+        let account = import("B");
+        let account_id = AccountID(account.account_id_hex);
+        assert!(service.cancel_account_import(&account_id).unwrap());
+        assert!(service.get_account(&account_id).is_err());
+
+        let events = WalletEvent::list(Some(events[0].id), None, &conn).unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].event_type, EVENT_TYPE_ACCOUNT_IMPORT_CANCELLED);
+    }
+
+    #[test_with_logger]
+    fn test_sync_history(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+        let service = setup_wallet_service(ledger_db.clone(), logger.clone());
+
+        let account = service
+            .import_account(
+                Mnemonic::new(MnemonicType::Words24, Language::English)
+                    .phrase()
+                    .to_string(),
+                MNEMONIC_KEY_DERIVATION_VERSION,
+                None,
+                None,
+                None,
+                "".to_string(),
+                "".to_string(),
+                "".to_string(),
+            )
+            .unwrap();
+        let account_id = AccountID(account.account_id_hex);
+        assert!(service
+            .get_sync_history(&account_id, None, None)
+            .unwrap()
+            .is_empty());
+
+        // Each chunk scanned is recorded.
+        manually_sync_account(&ledger_db, &service.wallet_db, &account_id, &logger);
+        let history = service.get_sync_history(&account_id, None, None).unwrap();
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].start_block_index, 0);
+        assert_eq!(history[0].end_block_index, 11);
+        assert_eq!(history[0].num_blocks(), 12);
+
+        // The history goes with the account.
+        service.remove_account(&account_id).unwrap();
+        assert!(service.get_sync_history(&account_id, None, None).is_err());
+        let conn = service.wallet_db.get_conn().unwrap();
+        assert!(
+            AccountSyncRecord::list_for_account(&account_id.0, None, None, &conn)
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test_with_logger]
+    fn test_set_account_first_block(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let mnemonic = Mnemonic::new(MnemonicType::Words24, Language::English);
+        let account_key = mc_account_keys_slip10::Slip10Key::from(mnemonic.clone())
+            .try_into_account_key("", "", &[])
+            .unwrap();
+        let ledger_db = get_test_ledger(5, &[account_key.default_subaddress()], 12, &mut rng);
+        let service = setup_wallet_service(ledger_db.clone(), logger.clone());
+
+        // Import with a first block after all of the account's txos.
+        let account = service
+            .import_account(
+                mnemonic.phrase().to_string(),
+                MNEMONIC_KEY_DERIVATION_VERSION,
+                None,
+                Some(12),
+                None,
+                "".to_string(),
+                "".to_string(),
+                "".to_string(),
+            )
+            .unwrap();
+        let account_id = AccountID(account.account_id_hex);
+        manually_sync_account(&ledger_db, &service.wallet_db, &account_id, &logger);
+        let list_txos = || {
+            Txo::list_for_account(
+                &account_id.to_string(),
+                None,
+                None,
+                None,
+                &service.wallet_db.get_conn().unwrap(),
+            )
+            .unwrap()
+        };
+        assert_eq!(list_txos().len(), 0);
+
+        // Moving the first block earlier rescans from there.
+        let account = service.set_account_first_block(&account_id, 6).unwrap();
+        assert_eq!(account.first_block_index, 6);
+        assert_eq!(account.next_block_index, 6);
+        manually_sync_account(&ledger_db, &service.wallet_db, &account_id, &logger);
+        assert_eq!(list_txos().len(), 6);
+
+        // Rescanning blocks which were already synced keeps what was found.
+        let account = service.set_account_first_block(&account_id, 0).unwrap();
+        assert_eq!(account.next_block_index, 0);
+        manually_sync_account(&ledger_db, &service.wallet_db, &account_id, &logger);
+        assert_eq!(list_txos().len(), 12);
+        let account = service.get_account(&account_id).unwrap();
+        assert_eq!(account.next_block_index, 12);
+
+        // Moving the first block later doesn't rescan.
+        let account = service.set_account_first_block(&account_id, 10).unwrap();
+        assert_eq!(account.first_block_index, 10);
+        assert_eq!(account.next_block_index, 12);
+        assert_eq!(list_txos().len(), 12);
+    }
+
+    #[test_with_logger]
+    fn test_rescan_account(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let mnemonic = Mnemonic::new(MnemonicType::Words24, Language::English);
+        let account_key = mc_account_keys_slip10::Slip10Key::from(mnemonic.clone())
+            .try_into_account_key("", "", &[])
+            .unwrap();
+        let mut ledger_db = get_test_ledger(5, &[account_key.default_subaddress()], 12, &mut rng);
+        let service = setup_wallet_service(ledger_db.clone(), logger.clone());
+
+        let account = service
+            .import_account(
+                mnemonic.phrase().to_string(),
+                MNEMONIC_KEY_DERIVATION_VERSION,
+                None,
+                Some(6),
+                None,
+                "".to_string(),
+                "".to_string(),
+                "".to_string(),
+            )
+            .unwrap();
+        let account_id = AccountID(account.account_id_hex);
+        manually_sync_account(&ledger_db, &service.wallet_db, &account_id, &logger);
+        let list_txos = || {
+            Txo::list_for_account(
+                &account_id.to_string(),
+                None,
+                None,
+                None,
+                &service.wallet_db.get_conn().unwrap(),
+            )
+            .unwrap()
+        };
+        let txos = list_txos();
+        assert_eq!(txos.len(), 6);
+
+        // Spend one txo on the ledger, and record another as spent in the same
+        // block although it never was.
+        let spent_txo = &txos[0];
+        let key_image: KeyImage =
+            mc_util_serial::decode(spent_txo.key_image.as_ref().unwrap()).unwrap();
+        let spent_block_index = add_block_to_ledger_db(
+            &mut ledger_db,
+            &[AccountKey::random(&mut rng).default_subaddress()],
+            100 * MOB,
+            &[key_image],
+            &mut rng,
+        );
+        manually_sync_account(&ledger_db, &service.wallet_db, &account_id, &logger);
+        let wrongly_spent_txo = &txos[1];
+        Txo::update_to_spent(
+            &wrongly_spent_txo.txo_id_hex,
+            spent_block_index,
+            &service.wallet_db.get_conn().unwrap(),
+        )
+        .unwrap();
+
+        // Blocks which haven't been synced can't be rescanned from.
+        assert!(matches!(
+            service.rescan_account(&account_id, 14),
+            Err(AccountServiceError::RescanFromUnsyncedBlock(14, 13))
+        ));
+
+        // Rescanning from before the first block moves it back, and leaves the
+        // txos spent since pending until their spent block is rescanned.
+        let account = service.rescan_account(&account_id, 0).unwrap();
+        assert_eq!(account.first_block_index, 0);
+        assert_eq!(account.next_block_index, 0);
+        let get_txo = |txo_id_hex: &str| {
+            Txo::get(txo_id_hex, &service.wallet_db.get_conn().unwrap()).unwrap()
+        };
+        for txo_id_hex in &[&spent_txo.txo_id_hex, &wrongly_spent_txo.txo_id_hex] {
+            let txo = get_txo(txo_id_hex);
+            assert_eq!(txo.spent_block_index, None);
+            assert_eq!(
+                txo.pending_tombstone_block_index,
+                Some(spent_block_index as i64)
+            );
+        }
+        assert_eq!(list_txos().len(), 6);
+
+        // The rescan finds the txos before the old first block, and matches
+        // only the key image which is on the ledger.
+        manually_sync_account(&ledger_db, &service.wallet_db, &account_id, &logger);
+        assert_eq!(list_txos().len(), 12);
+        let txo = get_txo(&spent_txo.txo_id_hex);
+        assert_eq!(txo.spent_block_index, Some(spent_block_index as i64));
+        assert_eq!(txo.pending_tombstone_block_index, None);
+        let txo = get_txo(&wrongly_spent_txo.txo_id_hex);
+        assert_eq!(txo.spent_block_index, None);
+        assert_eq!(txo.pending_tombstone_block_index, None);
+    }
+
+    #[test_with_logger]
+    fn test_import_accounts(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+        let service = setup_wallet_service(ledger_db.clone(), logger.clone());
+
+        let import = |secret: AccountImportSecret, first_block_index: Option<u64>| AccountImport {
+            secret,
+            name: Some("imported".to_string()),
+            first_block_index,
+            next_subaddress_index: None,
+            fog_report_url: "".to_string(),
+            fog_report_id: "".to_string(),
+            fog_authority_spki: "".to_string(),
+        };
+        let mnemonic = |phrase: &str| AccountImportSecret::Mnemonic {
+            mnemonic_phrase: phrase.to_string(),
+            key_derivation_version: MNEMONIC_KEY_DERIVATION_VERSION,
+        };
+        let phrase = Mnemonic::new(MnemonicType::Words24, Language::English)
+            .phrase()
+            .to_string();
+        let entropy = hex::encode([7u8; 32]);
+
+        // A bad entry fails the whole batch.
+        let result = service.import_accounts(vec![
+            import(mnemonic(&phrase), Some(4)),
+            import(mnemonic("not a mnemonic"), None),
+        ]);
+        assert!(matches!(
+            result,
+            Err(AccountServiceError::BatchImport(1, _))
+        ));
+        assert_eq!(service.list_accounts().unwrap().len(), 0);
+
+        // As does a duplicate account, which is only found once the import has
+        // started.
+        let result = service.import_accounts(vec![
+            import(mnemonic(&phrase), Some(4)),
+            import(mnemonic(&phrase), Some(4)),
+        ]);
+        assert!(matches!(
+            result,
+            Err(AccountServiceError::BatchImport(1, _))
+        ));
+        assert_eq!(service.list_accounts().unwrap().len(), 0);
+
+        let (accounts, sync_plan) = service
+            .import_accounts(vec![
+                import(mnemonic(&phrase), Some(4)),
+                import(AccountImportSecret::LegacyRootEntropy(entropy), Some(8)),
+                import(
+                    mnemonic(
+                        &Mnemonic::new(MnemonicType::Words24, Language::English)
+                            .phrase()
+                            .to_string(),
+                    ),
+                    Some(20),
+                ),
+            ])
+            .unwrap();
+        assert_eq!(accounts.len(), 3);
+        assert_eq!(service.list_accounts().unwrap().len(), 3);
+        assert_eq!(
+            sync_plan,
+            AccountImportSyncPlan {
+                start_block_index: 4,
+                import_block_index: 11,
+                num_blocks: 8,
+                num_accounts: 2,
+            }
+        );
+    }
+
+    #[test_with_logger]
+    fn test_verify_account_backup(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+        let service = setup_wallet_service(ledger_db.clone(), logger.clone());
+
+        let phrase = Mnemonic::new(MnemonicType::Words24, Language::English)
+            .phrase()
+            .to_string();
+        let account = service
+            .import_account(
+                phrase.clone(),
+                MNEMONIC_KEY_DERIVATION_VERSION,
+                None,
+                None,
+                None,
+                "".to_string(),
+                "".to_string(),
+                "".to_string(),
+            )
+            .unwrap();
+        let account_id = AccountID(account.account_id_hex.clone());
+
+        assert!(service.verify_account_backup(&account_id, &phrase).unwrap());
+
+        // Spacing and capitals don't matter.
+        let written_down = format!("  {}\n", phrase.to_uppercase().replace(' ', "   "));
+        assert!(service
+            .verify_account_backup(&account_id, &written_down)
+            .unwrap());
+
+        // Neither a different mnemonic, nor a phrase with a word missing,
+        // verifies.
+        let other_phrase = Mnemonic::new(MnemonicType::Words24, Language::English)
+            .phrase()
+            .to_string();
+        assert!(!service
+            .verify_account_backup(&account_id, &other_phrase)
+            .unwrap());
+        let missing_word = phrase.split(' ').skip(1).collect::<Vec<&str>>().join(" ");
+        assert!(!service
+            .verify_account_backup(&account_id, &missing_word)
+            .unwrap());
+
+        // Accounts imported from root entropy have no mnemonic.
+        let legacy = service
+            .import_account_from_legacy_root_entropy(
+                hex::encode([7u8; 32]),
+                None,
+                None,
+                None,
+                "".to_string(),
+                "".to_string(),
+                "".to_string(),
+            )
+            .unwrap();
+        assert!(matches!(
+            service.verify_account_backup(&AccountID(legacy.account_id_hex), &phrase),
+            Err(AccountServiceError::NoMnemonic(_))
+        ));
+    }
 }
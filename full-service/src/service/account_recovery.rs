@@ -0,0 +1,474 @@
+// Copyright (c) 2020-2022 MobileCoin Inc.
+
+//! Service for recovering an account from its mnemonic.
+//!
+//! A mnemonic may have been used with either key derivation, and the account
+//! may have first received funds anywhere in the ledger, so recovering one has
+//! meant importing it, waiting for the rescan, and trying again when a guess
+//! was wrong. Recovery instead probes the ledger for each account the mnemonic
+//! could stand for, reporting the first block each received a txo in, and
+//! imports the one the caller picks from that block.
+
+use crate::{
+    db::{
+        account::{AccountID, AccountModel},
+        models::Account,
+        WalletDbError,
+    },
+    service::{
+        account::{AccountService, AccountServiceError},
+        WalletService,
+    },
+    util::{
+        b58::{b58_encode_public_address, B58Error},
+        constants::{MNEMONIC_KEY_DERIVATION_VERSION, ROOT_ENTROPY_KEY_DERIVATION_VERSION},
+    },
+};
+use bip39::{Language, Mnemonic};
+use displaydoc::Display;
+use mc_account_keys::{AccountKey, RootEntropy, RootIdentity};
+use mc_account_keys_slip10::Slip10Key;
+use mc_common::logger::log;
+use mc_connection::{BlockchainConnection, UserTxConnection};
+use mc_crypto_keys::RistrettoPublic;
+use mc_fog_report_validation::FogPubkeyResolver;
+use mc_ledger_db::{Ledger, LedgerDB};
+use mc_transaction_core::onetime_keys::recover_public_subaddress_spend_key;
+use std::{collections::HashSet, convert::TryFrom};
+
+/// The number of blocks probed when no limit is given.
+pub const DEFAULT_RECOVERY_SCAN_BLOCKS: u64 = 100_000;
+
+/// The number of subaddresses, from index 0, whose txos count as activity.
+/// These cover the main and change subaddresses of any account, and the first
+/// addresses it assigned.
+pub const RECOVERY_PROBE_SUBADDRESSES: u64 = 10;
+
+/// Errors for the Account Recovery Service.
+#[derive(Display, Debug)]
+#[allow(clippy::large_enum_variant)]
+pub enum AccountRecoveryServiceError {
+    /// Error interacting with the database: {0}
+    Database(WalletDbError),
+
+    /// Error with LedgerDB: {0}
+    LedgerDB(mc_ledger_db::Error),
+
+    /// Error importing the account: {0}
+    AccountService(AccountServiceError),
+
+    /// Error encoding an address: {0}
+    B58(B58Error),
+
+    /// Invalid BIP39 english mnemonic
+    InvalidMnemonic,
+
+    /// Could not derive the account key: {0}
+    KeyDerivation(String),
+
+    /// The mnemonic has no candidate account with key derivation version {0}
+    UnknownCandidate(u8),
+
+    /// Account {0} is already in the wallet
+    AlreadyImported(String),
+}
+
+impl From<WalletDbError> for AccountRecoveryServiceError {
+    fn from(src: WalletDbError) -> Self {
+        Self::Database(src)
+    }
+}
+
+impl From<mc_ledger_db::Error> for AccountRecoveryServiceError {
+    fn from(src: mc_ledger_db::Error) -> Self {
+        Self::LedgerDB(src)
+    }
+}
+
+impl From<AccountServiceError> for AccountRecoveryServiceError {
+    fn from(src: AccountServiceError) -> Self {
+        Self::AccountService(src)
+    }
+}
+
+impl From<B58Error> for AccountRecoveryServiceError {
+    fn from(src: B58Error) -> Self {
+        Self::B58(src)
+    }
+}
+
+impl From<base64::DecodeError> for AccountRecoveryServiceError {
+    fn from(src: base64::DecodeError) -> Self {
+        Self::KeyDerivation(src.to_string())
+    }
+}
+
+impl From<mc_account_keys_slip10::Error> for AccountRecoveryServiceError {
+    fn from(src: mc_account_keys_slip10::Error) -> Self {
+        Self::KeyDerivation(src.to_string())
+    }
+}
+
+/// An account a mnemonic could stand for, and what probing the ledger found
+/// of it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RecoveryCandidate {
+    pub key_derivation_version: u8,
+    pub account_id: String,
+    /// The b58-encoded main address.
+    pub main_address: String,
+    /// Whether the account is already in the wallet.
+    pub already_imported: bool,
+    /// The first probed block in which the account received a txo.
+    pub first_activity_block_index: Option<u64>,
+    /// The block the account starts scanning from if it is imported. This is
+    /// its first activity, or the end of the probe if none was found.
+    pub first_block_index: u64,
+}
+
+/// The outcome of probing the ledger for the accounts a mnemonic could stand
+/// for.
+#[derive(Clone, Debug)]
+pub struct AccountRecovery {
+    /// The first block probed.
+    pub start_block_index: u64,
+    /// The block after the last one probed.
+    pub end_block_index: u64,
+    pub candidates: Vec<RecoveryCandidate>,
+    /// The account imported, if one of the candidates was picked.
+    pub account: Option<Account>,
+}
+
+/// Trait defining the ways in which the wallet can recover accounts.
+pub trait AccountRecoveryService {
+    /// Probe the ledger for each account the mnemonic could stand for, from
+    /// the start block for at most max_blocks blocks, and import the one with
+    /// the given key derivation version, if any.
+    ///
+    /// The imported account starts scanning from its first activity, so if
+    /// the probe started after block 0, anything it received before the start
+    /// block is skipped.
+    #[allow(clippy::too_many_arguments)]
+    fn recover_account(
+        &self,
+        mnemonic_phrase: &str,
+        start_block_index: Option<u64>,
+        max_blocks: Option<u64>,
+        import_key_derivation_version: Option<u8>,
+        name: Option<String>,
+        fog_report_url: String,
+        fog_report_id: String,
+        fog_authority_spki: String,
+    ) -> Result<AccountRecovery, AccountRecoveryServiceError>;
+}
+
+impl<T, FPR> AccountRecoveryService for WalletService<T, FPR>
+where
+    T: BlockchainConnection + UserTxConnection + 'static,
+    FPR: FogPubkeyResolver + Send + Sync + 'static,
+{
+    fn recover_account(
+        &self,
+        mnemonic_phrase: &str,
+        start_block_index: Option<u64>,
+        max_blocks: Option<u64>,
+        import_key_derivation_version: Option<u8>,
+        name: Option<String>,
+        fog_report_url: String,
+        fog_report_id: String,
+        fog_authority_spki: String,
+    ) -> Result<AccountRecovery, AccountRecoveryServiceError> {
+        let mnemonic = Mnemonic::from_phrase(mnemonic_phrase, Language::English)
+            .map_err(|_| AccountRecoveryServiceError::InvalidMnemonic)?;
+        let account_keys = candidate_account_keys(
+            &mnemonic,
+            &fog_report_url,
+            &fog_report_id,
+            &fog_authority_spki,
+        )?;
+        if let Some(key_derivation_version) = import_key_derivation_version {
+            if !account_keys
+                .iter()
+                .any(|(version, _)| *version == key_derivation_version)
+            {
+                return Err(AccountRecoveryServiceError::UnknownCandidate(
+                    key_derivation_version,
+                ));
+            }
+        }
+
+        let num_blocks = self.ledger_db.num_blocks()?;
+        let start_block_index = start_block_index.unwrap_or(0).min(num_blocks);
+        let end_block_index = start_block_index
+            .saturating_add(max_blocks.unwrap_or(DEFAULT_RECOVERY_SCAN_BLOCKS))
+            .min(num_blocks);
+        log::info!(
+            self.logger,
+            "Probing blocks {} to {} for {} candidate accounts",
+            start_block_index,
+            end_block_index,
+            account_keys.len()
+        );
+
+        let keys: Vec<AccountKey> = account_keys.iter().map(|(_, key)| key.clone()).collect();
+        let first_activity = first_activity_block_indices(
+            &self.ledger_db,
+            &keys,
+            start_block_index,
+            end_block_index,
+        )?;
+
+        let mut candidates = Vec::new();
+        {
+            let conn = self.wallet_db.get_conn()?;
+            for ((key_derivation_version, account_key), first_activity_block_index) in
+                account_keys.iter().zip(first_activity)
+            {
+                let account_id = AccountID::from(account_key);
+                let already_imported = match Account::get(&account_id, &conn) {
+                    Ok(_) => true,
+                    Err(WalletDbError::AccountNotFound(_)) => false,
+                    Err(err) => return Err(err.into()),
+                };
+                candidates.push(RecoveryCandidate {
+                    key_derivation_version: *key_derivation_version,
+                    account_id: account_id.to_string(),
+                    main_address: b58_encode_public_address(&account_key.default_subaddress())?,
+                    already_imported,
+                    first_activity_block_index,
+                    first_block_index: first_activity_block_index.unwrap_or(end_block_index),
+                });
+            }
+        }
+
+        let account = match import_key_derivation_version {
+            None => None,
+            Some(key_derivation_version) => {
+                let candidate = candidates
+                    .iter()
+                    .find(|candidate| candidate.key_derivation_version == key_derivation_version)
+                    .ok_or(AccountRecoveryServiceError::UnknownCandidate(
+                        key_derivation_version,
+                    ))?;
+                if candidate.already_imported {
+                    return Err(AccountRecoveryServiceError::AlreadyImported(
+                        candidate.account_id.clone(),
+                    ));
+                }
+                let account = if key_derivation_version == MNEMONIC_KEY_DERIVATION_VERSION {
+                    self.import_account(
+                        mnemonic_phrase.to_string(),
+                        key_derivation_version,
+                        name,
+                        Some(candidate.first_block_index),
+                        None,
+                        fog_report_url,
+                        fog_report_id,
+                        fog_authority_spki,
+                    )?
+                } else {
+                    self.import_account_from_legacy_root_entropy(
+                        hex::encode(mnemonic.entropy()),
+                        name,
+                        Some(candidate.first_block_index),
+                        None,
+                        fog_report_url,
+                        fog_report_id,
+                        fog_authority_spki,
+                    )?
+                };
+                Some(account)
+            }
+        };
+
+        Ok(AccountRecovery {
+            start_block_index,
+            end_block_index,
+            candidates,
+            account,
+        })
+    }
+}
+
+/// The accounts a mnemonic could stand for, by key derivation version: the
+/// account derived from the mnemonic, and the account whose legacy root
+/// entropy is the mnemonic's entropy, as some wallets used it.
+fn candidate_account_keys(
+    mnemonic: &Mnemonic,
+    fog_report_url: &str,
+    fog_report_id: &str,
+    fog_authority_spki: &str,
+) -> Result<Vec<(u8, AccountKey)>, AccountRecoveryServiceError> {
+    let fog_authority_spki = base64::decode(fog_authority_spki)?;
+
+    let mut account_keys = vec![(
+        MNEMONIC_KEY_DERIVATION_VERSION,
+        Slip10Key::from(mnemonic.clone()).try_into_account_key(
+            fog_report_url,
+            fog_report_id,
+            &fog_authority_spki,
+        )?,
+    )];
+
+    // Only 24 word mnemonics have as much entropy as a root entropy.
+    if let Ok(entropy) = <[u8; 32]>::try_from(mnemonic.entropy()) {
+        let root_id = RootIdentity {
+            root_entropy: RootEntropy::from(&entropy),
+            fog_report_url: fog_report_url.to_string(),
+            fog_report_id: fog_report_id.to_string(),
+            fog_authority_spki,
+        };
+        account_keys.push((
+            ROOT_ENTROPY_KEY_DERIVATION_VERSION,
+            AccountKey::from(&root_id),
+        ));
+    }
+
+    Ok(account_keys)
+}
+
+/// The first block in the range in which each account received a txo at one
+/// of its first RECOVERY_PROBE_SUBADDRESSES subaddresses. The blocks are
+/// scanned in order, stopping once every account has been found.
+fn first_activity_block_indices(
+    ledger_db: &LedgerDB,
+    account_keys: &[AccountKey],
+    start_block_index: u64,
+    end_block_index: u64,
+) -> Result<Vec<Option<u64>>, mc_ledger_db::Error> {
+    let spend_keys: Vec<HashSet<RistrettoPublic>> = account_keys
+        .iter()
+        .map(|account_key| {
+            (0..RECOVERY_PROBE_SUBADDRESSES)
+                .map(|index| *account_key.subaddress(index).spend_public_key())
+                .collect()
+        })
+        .collect();
+
+    let mut first_activity = vec![None; account_keys.len()];
+    for block_index in start_block_index..end_block_index {
+        if first_activity.iter().all(Option::is_some) {
+            break;
+        }
+        let block_contents = ledger_db.get_block_contents(block_index)?;
+        for tx_out in &block_contents.outputs {
+            let (target_key, public_key) = match (
+                RistrettoPublic::try_from(&tx_out.target_key),
+                RistrettoPublic::try_from(&tx_out.public_key),
+            ) {
+                (Ok(target_key), Ok(public_key)) => (target_key, public_key),
+                _ => continue,
+            };
+            for (i, account_key) in account_keys.iter().enumerate() {
+                if first_activity[i].is_some() {
+                    continue;
+                }
+                let spend_key = recover_public_subaddress_spend_key(
+                    account_key.view_private_key(),
+                    &target_key,
+                    &public_key,
+                );
+                if spend_keys[i].contains(&spend_key) {
+                    first_activity[i] = Some(block_index);
+                }
+            }
+        }
+    }
+    Ok(first_activity)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{add_block_to_ledger_db, get_test_ledger, setup_wallet_service, MOB};
+    use bip39::MnemonicType;
+    use mc_common::logger::{test_with_logger, Logger};
+    use mc_transaction_core::ring_signature::KeyImage;
+    use rand::{rngs::StdRng, RngCore, SeedableRng};
+
+    #[test_with_logger]
+    fn test_recover_account(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+        let mut ledger_db = get_test_ledger(5, &[], 12, &mut rng);
+        let service = setup_wallet_service(ledger_db.clone(), logger.clone());
+
+        let mnemonic = Mnemonic::new(MnemonicType::Words24, Language::English);
+        let account_key = Slip10Key::from(mnemonic.clone())
+            .try_into_account_key("", "", &[])
+            .unwrap();
+        let funded_block = add_block_to_ledger_db(
+            &mut ledger_db,
+            &[account_key.subaddress(2)],
+            100 * MOB,
+            &[KeyImage::from(rng.next_u64())],
+            &mut rng,
+        );
+        add_block_to_ledger_db(
+            &mut ledger_db,
+            &[AccountKey::random(&mut rng).default_subaddress()],
+            100 * MOB,
+            &[KeyImage::from(rng.next_u64())],
+            &mut rng,
+        );
+        let recover = |max_blocks: Option<u64>, import: Option<u8>| {
+            service.recover_account(
+                mnemonic.phrase(),
+                None,
+                max_blocks,
+                import,
+                Some("Recovered".to_string()),
+                "".to_string(),
+                "".to_string(),
+                "".to_string(),
+            )
+        };
+
+        let recovery = recover(None, None).unwrap();
+        assert_eq!(recovery.start_block_index, 0);
+        assert_eq!(recovery.end_block_index, ledger_db.num_blocks().unwrap());
+        assert!(recovery.account.is_none());
+        assert_eq!(recovery.candidates.len(), 2);
+        let candidate = &recovery.candidates[0];
+        assert_eq!(
+            candidate.key_derivation_version,
+            MNEMONIC_KEY_DERIVATION_VERSION
+        );
+        assert_eq!(
+            candidate.account_id,
+            AccountID::from(&account_key).to_string()
+        );
+        assert_eq!(candidate.first_activity_block_index, Some(funded_block));
+        assert_eq!(candidate.first_block_index, funded_block);
+        assert!(!candidate.already_imported);
+        let legacy = &recovery.candidates[1];
+        assert_eq!(
+            legacy.key_derivation_version,
+            ROOT_ENTROPY_KEY_DERIVATION_VERSION
+        );
+        assert_eq!(legacy.first_activity_block_index, None);
+        assert_eq!(legacy.first_block_index, recovery.end_block_index);
+
+        // A probe which stops short of the activity would start scanning
+        // where it stopped.
+        let short = recover(Some(funded_block), None).unwrap();
+        assert_eq!(short.candidates[0].first_activity_block_index, None);
+        assert_eq!(short.candidates[0].first_block_index, funded_block);
+
+        // Picking a candidate imports it from its first activity.
+        let recovery = recover(None, Some(MNEMONIC_KEY_DERIVATION_VERSION)).unwrap();
+        let account = recovery.account.unwrap();
+        assert_eq!(account.account_id_hex, candidate.account_id);
+        assert_eq!(account.first_block_index as u64, funded_block);
+
+        let recovery = recover(None, None).unwrap();
+        assert!(recovery.candidates[0].already_imported);
+        assert!(matches!(
+            recover(None, Some(MNEMONIC_KEY_DERIVATION_VERSION)),
+            Err(AccountRecoveryServiceError::AlreadyImported(_))
+        ));
+        assert!(matches!(
+            recover(None, Some(3)),
+            Err(AccountRecoveryServiceError::UnknownCandidate(3))
+        ));
+    }
+}
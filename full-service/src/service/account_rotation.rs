@@ -0,0 +1,404 @@
+// Copyright (c) 2020-2022 MobileCoin Inc.
+
+//! Service for rotating an account's funds to a new account.
+//!
+//! A rotation creates a new account, then moves the old account's funds to it
+//! with a series of sweep transactions, each spending as many txos as a
+//! transaction allows. Only one sweep is in flight at a time, so each step
+//! waits for the previous sweep to land or expire. Once nothing sweepable is
+//! left, the old account is archived.
+
+use crate::{
+    db::{
+        account::{AccountID, AccountModel},
+        account_rotation::AccountRotationModel,
+        models::{
+            Account, AccountRotation, Txo, ROTATION_STATUS_COMPLETED, ROTATION_STATUS_FAILED,
+            ROTATION_STATUS_SWEEPING,
+        },
+        transaction,
+        txo::TxoModel,
+        WalletDbError,
+    },
+    service::{
+        account::{AccountService, AccountServiceError},
+        ledger::LedgerService,
+        transaction::TransactionService,
+        WalletService,
+    },
+    util::b58::{b58_encode_public_address, B58Error},
+};
+use displaydoc::Display;
+use mc_account_keys::AccountKey;
+use mc_common::logger::log;
+use mc_connection::{BlockchainConnection, UserTxConnection};
+use mc_fog_report_validation::FogPubkeyResolver;
+use mc_ledger_db::Ledger;
+use mc_transaction_core::{constants::MAX_INPUTS, TokenId};
+use std::collections::BTreeMap;
+
+#[derive(Display, Debug)]
+pub enum AccountRotationServiceError {
+    /// Error interacting with the database: {0}
+    Database(WalletDbError),
+
+    /// Diesel error: {0}
+    Diesel(diesel::result::Error),
+
+    /// Error with LedgerDB: {0}
+    LedgerDB(mc_ledger_db::Error),
+
+    /// Error with the account service: {0}
+    AccountService(AccountServiceError),
+
+    /// Error decoding account key: {0}
+    Decode(mc_util_serial::DecodeError),
+
+    /// Error encoding the new account's address: {0}
+    B58(B58Error),
+
+    /// Account has already been rotated: {0}
+    AlreadyRotated(String),
+
+    /// Account has been archived: {0}
+    AccountArchived(String),
+}
+
+impl From<WalletDbError> for AccountRotationServiceError {
+    fn from(src: WalletDbError) -> Self {
+        Self::Database(src)
+    }
+}
+
+impl From<diesel::result::Error> for AccountRotationServiceError {
+    fn from(src: diesel::result::Error) -> Self {
+        Self::Diesel(src)
+    }
+}
+
+impl From<mc_ledger_db::Error> for AccountRotationServiceError {
+    fn from(src: mc_ledger_db::Error) -> Self {
+        Self::LedgerDB(src)
+    }
+}
+
+impl From<AccountServiceError> for AccountRotationServiceError {
+    fn from(src: AccountServiceError) -> Self {
+        Self::AccountService(src)
+    }
+}
+
+impl From<mc_util_serial::DecodeError> for AccountRotationServiceError {
+    fn from(src: mc_util_serial::DecodeError) -> Self {
+        Self::Decode(src)
+    }
+}
+
+impl From<B58Error> for AccountRotationServiceError {
+    fn from(src: B58Error) -> Self {
+        Self::B58(src)
+    }
+}
+
+/// Trait defining the ways in which the wallet can rotate accounts.
+pub trait AccountRotationService {
+    /// Create a new account with the same fog settings as the given one, and
+    /// start moving the given account's funds to it.
+    ///
+    /// The new account takes the old account's name unless another is given.
+    fn rotate_account(
+        &self,
+        account_id: &AccountID,
+        new_account_name: Option<String>,
+    ) -> Result<AccountRotation, AccountRotationServiceError>;
+
+    /// Move the rotation on if it can make progress, then return it.
+    ///
+    /// Submits the next sweep transaction once the old account has caught up
+    /// with the ledger and the previous sweep has landed or expired, and
+    /// archives the old account once nothing sweepable is left. A sweep that
+    /// fails marks the rotation failed, and is retried on the next call.
+    fn advance_account_rotation(
+        &self,
+        account_id: &AccountID,
+    ) -> Result<AccountRotation, AccountRotationServiceError>;
+}
+
+impl<T, FPR> AccountRotationService for WalletService<T, FPR>
+where
+    T: BlockchainConnection + UserTxConnection + 'static,
+    FPR: FogPubkeyResolver + Send + Sync + 'static,
+{
+    fn rotate_account(
+        &self,
+        account_id: &AccountID,
+        new_account_name: Option<String>,
+    ) -> Result<AccountRotation, AccountRotationServiceError> {
+        let conn = self.wallet_db.get_conn()?;
+
+        let old_account = Account::get(account_id, &conn)?;
+        if old_account.archived {
+            return Err(AccountRotationServiceError::AccountArchived(
+                account_id.to_string(),
+            ));
+        }
+        if AccountRotation::get(&account_id.to_string(), &conn).is_ok() {
+            return Err(AccountRotationServiceError::AlreadyRotated(
+                account_id.to_string(),
+            ));
+        }
+
+        let old_account_key: AccountKey = mc_util_serial::decode(&old_account.account_key)?;
+        let new_account = self.create_account(
+            Some(new_account_name.unwrap_or_else(|| old_account.name.clone())),
+            old_account_key.fog_report_url().unwrap_or("").to_string(),
+            old_account_key.fog_report_id().unwrap_or("").to_string(),
+            base64::encode(old_account_key.fog_authority_spki().unwrap_or(&[])),
+        )?;
+
+        transaction(&conn, || {
+            new_account.update_preferred_token_id(old_account.preferred_token_id as u64, &conn)?;
+            AccountRotation::create(&account_id.to_string(), &new_account.account_id_hex, &conn)
+        })?;
+
+        log::info!(
+            self.logger,
+            "Rotating account {} to new account {}",
+            account_id,
+            new_account.account_id_hex,
+        );
+
+        self.advance_account_rotation(account_id)
+    }
+
+    fn advance_account_rotation(
+        &self,
+        account_id: &AccountID,
+    ) -> Result<AccountRotation, AccountRotationServiceError> {
+        let conn = self.wallet_db.get_conn()?;
+
+        let rotation = AccountRotation::get(&account_id.to_string(), &conn)?;
+        if rotation.status == ROTATION_STATUS_COMPLETED {
+            return Ok(rotation);
+        }
+
+        // Only sweep once the old account has seen every block and has no
+        // sweep in flight, so that its unspent txos are up to date.
+        let old_account = Account::get(account_id, &conn)?;
+        if (old_account.next_block_index as u64) < self.ledger_db.num_blocks()?
+            || !Txo::list_pending(&account_id.to_string(), None, None, &conn)?.is_empty()
+        {
+            return Ok(rotation);
+        }
+
+        // Frozen txos stay behind in the old account until they are unfrozen.
+        let unspent_txos: Vec<Txo> = Txo::list_unspent(&account_id.to_string(), None, None, &conn)?
+            .into_iter()
+            .filter(|txo| !txo.frozen)
+            .collect();
+        match next_sweep(&unspent_txos, &self.get_network_minimum_fees()) {
+            Some(sweep) => {
+                let new_account =
+                    Account::get(&AccountID(rotation.new_account_id_hex.clone()), &conn)?;
+                let new_account_key: AccountKey = mc_util_serial::decode(&new_account.account_key)?;
+                let new_main_address = b58_encode_public_address(
+                    &new_account_key.subaddress(new_account.main_subaddress_index as u64),
+                )?;
+
+                match self.build_and_submit(
+                    &account_id.to_string(),
+                    &[(new_main_address, sweep.value.to_string())],
+                    Some(&sweep.input_txo_ids),
+                    Some(sweep.token_id.to_string()),
+                    Some(sweep.fee.to_string()),
+                    None,
+                    None,
+                    None,
+                    Some(true),
+                    None,
+                    None,
+                    None,
+                    Some(format!(
+                        "Rotation of account {} to {}",
+                        account_id, rotation.new_account_id_hex
+                    )),
+                ) {
+                    Ok((transaction_log, _, _)) => {
+                        rotation.add_sweep(&transaction_log.transaction_id_hex, &conn)?;
+                        rotation.update_status(ROTATION_STATUS_SWEEPING, None, &conn)?;
+                    }
+                    Err(err) => {
+                        log::warn!(
+                            self.logger,
+                            "Sweep for rotation of account {} failed: {}",
+                            account_id,
+                            err,
+                        );
+                        rotation.update_status(
+                            ROTATION_STATUS_FAILED,
+                            Some(&err.to_string()),
+                            &conn,
+                        )?;
+                    }
+                }
+            }
+            None => {
+                transaction(&conn, || {
+                    old_account.archive(&conn)?;
+                    rotation.update_status(ROTATION_STATUS_COMPLETED, None, &conn)?;
+                    Ok::<(), AccountRotationServiceError>(())
+                })?;
+                log::info!(
+                    self.logger,
+                    "Rotation of account {} completed; account archived",
+                    account_id,
+                );
+            }
+        }
+
+        Ok(AccountRotation::get(&account_id.to_string(), &conn)?)
+    }
+}
+
+/// A single sweep transaction.
+#[derive(Debug, PartialEq)]
+struct Sweep {
+    token_id: u64,
+    fee: u64,
+    input_txo_ids: Vec<String>,
+    /// The value sent to the new account, which is the inputs less the fee.
+    value: u64,
+}
+
+/// Pick the next sweep: the largest txos of the first token whose txos are
+/// worth more than the fee, up to the most inputs a transaction allows.
+///
+/// Returns None once every token left is dust, or has no known fee.
+fn next_sweep(unspent_txos: &[Txo], minimum_fees: &BTreeMap<TokenId, u64>) -> Option<Sweep> {
+    let mut txos_by_token: BTreeMap<u64, Vec<&Txo>> = BTreeMap::new();
+    for txo in unspent_txos {
+        txos_by_token
+            .entry(txo.token_id as u64)
+            .or_default()
+            .push(txo);
+    }
+
+    for (token_id, mut txos) in txos_by_token {
+        let fee = match minimum_fees.get(&TokenId::from(token_id)) {
+            Some(fee) => *fee,
+            None => continue,
+        };
+
+        txos.sort_by_key(|txo| std::cmp::Reverse(txo.value as u64));
+        let mut input_txo_ids = Vec::new();
+        let mut total: u64 = 0;
+        for txo in txos.into_iter().take(MAX_INPUTS as usize) {
+            match total.checked_add(txo.value as u64) {
+                Some(sum) => total = sum,
+                None => break,
+            }
+            input_txo_ids.push(txo.txo_id_hex.clone());
+        }
+
+        if total > fee {
+            return Some(Sweep {
+                token_id,
+                fee,
+                input_txo_ids,
+                value: total - fee,
+            });
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        db::{models::TransactionLog, transaction_log::TransactionLogModel},
+        test_utils::{
+            add_block_from_transaction_log, add_block_to_ledger_db, get_test_ledger,
+            manually_sync_account, setup_wallet_service, MOB,
+        },
+    };
+    use mc_common::logger::{test_with_logger, Logger};
+    use mc_crypto_rand::rand_core::RngCore;
+    use mc_transaction_core::{ring_signature::KeyImage, tokens::Mob, Token};
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test_with_logger]
+    fn test_rotate_account(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let mut ledger_db = get_test_ledger(5, &vec![], 12, &mut rng);
+        let service = setup_wallet_service(ledger_db.clone(), logger.clone());
+        let conn = service.wallet_db.get_conn().unwrap();
+
+        let alice = service
+            .create_account(
+                Some("Alice's Main Account".to_string()),
+                "".to_string(),
+                "".to_string(),
+                "".to_string(),
+            )
+            .unwrap();
+        let alice_id = AccountID(alice.account_id_hex.clone());
+        let alice_account_key: AccountKey = mc_util_serial::decode(&alice.account_key).unwrap();
+
+        // More txos than fit in one transaction, so the sweep takes two rounds.
+        let num_txos = MAX_INPUTS as usize + 4;
+        add_block_to_ledger_db(
+            &mut ledger_db,
+            &vec![alice_account_key.subaddress(alice.main_subaddress_index as u64); num_txos],
+            10 * MOB,
+            &vec![KeyImage::from(rng.next_u64())],
+            &mut rng,
+        );
+        manually_sync_account(&ledger_db, &service.wallet_db, &alice_id, &logger);
+
+        let rotation = service.rotate_account(&alice_id, None).unwrap();
+        assert_eq!(rotation.status, ROTATION_STATUS_SWEEPING);
+        assert_eq!(rotation.sweep_transaction_log_ids().len(), 1);
+        let new_id = AccountID(rotation.new_account_id_hex.clone());
+        let new_account = Account::get(&new_id, &conn).unwrap();
+        assert_eq!(new_account.name, alice.name);
+
+        // An account can only be rotated once.
+        assert!(service.rotate_account(&alice_id, None).is_err());
+
+        // Nothing more happens until the first sweep lands.
+        let mut rotation = service.advance_account_rotation(&alice_id).unwrap();
+        assert_eq!(rotation.sweep_transaction_log_ids().len(), 1);
+
+        let fee = service.get_network_minimum_fees()[&Mob::ID];
+        for round in 1..=2 {
+            let transaction_log_id = &rotation.sweep_transaction_log_ids()[round - 1];
+            let transaction_log = TransactionLog::get(transaction_log_id, &conn).unwrap();
+            add_block_from_transaction_log(&mut ledger_db, &conn, &transaction_log);
+            manually_sync_account(&ledger_db, &service.wallet_db, &alice_id, &logger);
+            manually_sync_account(&ledger_db, &service.wallet_db, &new_id, &logger);
+
+            rotation = service.advance_account_rotation(&alice_id).unwrap();
+            if round == 1 {
+                assert_eq!(rotation.status, ROTATION_STATUS_SWEEPING);
+                assert_eq!(rotation.sweep_transaction_log_ids().len(), 2);
+            } else {
+                assert_eq!(rotation.status, ROTATION_STATUS_COMPLETED);
+            }
+        }
+
+        assert!(Account::get(&alice_id, &conn).unwrap().archived);
+        assert!(!Account::get(&new_id, &conn).unwrap().archived);
+        let new_balance: u64 = Txo::list_unspent(&new_id.to_string(), None, None, &conn)
+            .unwrap()
+            .iter()
+            .map(|txo| txo.value as u64)
+            .sum();
+        assert_eq!(new_balance, num_txos as u64 * 10 * MOB - 2 * fee);
+
+        // Archived accounts can't be rotated again.
+        assert!(service.rotate_account(&alice_id, None).is_err());
+    }
+}
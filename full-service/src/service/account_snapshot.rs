@@ -0,0 +1,208 @@
+// Copyright (c) 2020-2022 MobileCoin Inc.
+
+//! Service for recording and listing snapshots of account state.
+//!
+//! The scheduler snapshots each account as of every so many blocks, once the
+//! account has synced past them, so that UIs can chart an account's balance
+//! over time from a handful of rows rather than from its whole txo history.
+
+use crate::{
+    db::{
+        account::{AccountID, AccountModel},
+        account_snapshot::AccountSnapshotModel,
+        models::{Account, AccountSnapshot},
+        transaction, WalletDbError,
+    },
+    WalletService,
+};
+use displaydoc::Display;
+use mc_common::logger::log;
+use mc_connection::{BlockchainConnection, UserTxConnection};
+use mc_fog_report_validation::FogPubkeyResolver;
+
+/// Errors for the Account Snapshot Service.
+#[derive(Display, Debug)]
+pub enum AccountSnapshotServiceError {
+    /// Error interacting with the database: {0}
+    Database(WalletDbError),
+
+    /// Error with Diesel: {0}
+    Diesel(diesel::result::Error),
+}
+
+impl From<WalletDbError> for AccountSnapshotServiceError {
+    fn from(src: WalletDbError) -> Self {
+        Self::Database(src)
+    }
+}
+
+impl From<diesel::result::Error> for AccountSnapshotServiceError {
+    fn from(src: diesel::result::Error) -> Self {
+        Self::Diesel(src)
+    }
+}
+
+/// Trait defining the ways in which the wallet can snapshot account state.
+pub trait AccountSnapshotService {
+    /// Snapshot each account as of every interval_blocks blocks which it has
+    /// synced past since its latest snapshot, starting from its first block.
+    ///
+    /// Returns the number of blocks snapshotted, across all accounts.
+    fn record_account_snapshots(
+        &self,
+        interval_blocks: u64,
+    ) -> Result<usize, AccountSnapshotServiceError>;
+
+    /// List an account's snapshots from one block up to and including
+    /// another, oldest first, optionally only those of a token.
+    fn get_account_snapshots(
+        &self,
+        account_id: &AccountID,
+        token_id: Option<u64>,
+        from_block_index: Option<u64>,
+        to_block_index: Option<u64>,
+    ) -> Result<Vec<AccountSnapshot>, AccountSnapshotServiceError>;
+}
+
+impl<T, FPR> AccountSnapshotService for WalletService<T, FPR>
+where
+    T: BlockchainConnection + UserTxConnection + 'static,
+    FPR: FogPubkeyResolver + Send + Sync + 'static,
+{
+    fn record_account_snapshots(
+        &self,
+        interval_blocks: u64,
+    ) -> Result<usize, AccountSnapshotServiceError> {
+        let conn = self.wallet_db.get_conn()?;
+
+        let mut num_snapshotted = 0;
+        for account in Account::list_all(&conn)? {
+            let num_blocks = transaction(&conn, || {
+                // The account may have been rolled back since it was listed.
+                let account = Account::get(&AccountID(account.account_id_hex.clone()), &conn)?;
+                let start_block_index =
+                    match AccountSnapshot::latest_block_index(&account.account_id_hex, &conn)? {
+                        Some(latest) => latest + interval_blocks,
+                        None => {
+                            let first_block_index = account.first_block_index as u64;
+                            (first_block_index + interval_blocks - 1) / interval_blocks
+                                * interval_blocks
+                        }
+                    };
+                let block_indices: Vec<u64> = (start_block_index..account.next_block_index as u64)
+                    .step_by(interval_blocks as usize)
+                    .collect();
+                AccountSnapshot::create_for_blocks(&account.account_id_hex, &block_indices, &conn)?;
+                Ok::<usize, AccountSnapshotServiceError>(block_indices.len())
+            })?;
+            if num_blocks > 0 {
+                log::debug!(
+                    self.logger,
+                    "Snapshotted account {} as of {} blocks",
+                    account.account_id_hex,
+                    num_blocks
+                );
+            }
+            num_snapshotted += num_blocks;
+        }
+        Ok(num_snapshotted)
+    }
+
+    fn get_account_snapshots(
+        &self,
+        account_id: &AccountID,
+        token_id: Option<u64>,
+        from_block_index: Option<u64>,
+        to_block_index: Option<u64>,
+    ) -> Result<Vec<AccountSnapshot>, AccountSnapshotServiceError> {
+        let conn = self.wallet_db.get_conn()?;
+        // Fail for an account which isn't in the wallet.
+        Account::get(account_id, &conn)?;
+        Ok(AccountSnapshot::list_for_account(
+            &account_id.0,
+            token_id,
+            from_block_index,
+            to_block_index,
+            &conn,
+        )?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        service::account::AccountService,
+        test_utils::{
+            add_block_to_ledger_db, get_test_ledger, manually_sync_account, setup_wallet_service,
+            MOB,
+        },
+        util::constants::MNEMONIC_KEY_DERIVATION_VERSION,
+    };
+    use bip39::{Language, Mnemonic, MnemonicType};
+    use mc_account_keys::PublicAddress;
+    use mc_common::logger::{test_with_logger, Logger};
+    use mc_crypto_rand::RngCore;
+    use mc_transaction_core::ring_signature::KeyImage;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test_with_logger]
+    fn test_record_account_snapshots(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let mut ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+        let service = setup_wallet_service(ledger_db.clone(), logger.clone());
+
+        let mnemonic = Mnemonic::new(MnemonicType::Words24, Language::English);
+        let account_key = mc_account_keys_slip10::Slip10Key::from(mnemonic.clone())
+            .try_into_account_key("", "", &[])
+            .unwrap();
+        let account = service
+            .import_account(
+                mnemonic.phrase().to_string(),
+                MNEMONIC_KEY_DERIVATION_VERSION,
+                None,
+                None,
+                None,
+                "".to_string(),
+                "".to_string(),
+                "".to_string(),
+            )
+            .unwrap();
+        let account_id = AccountID(account.account_id_hex);
+
+        // Blocks 12 and 13 each pay the account 100 MOB.
+        for _ in 0..2 {
+            add_block_to_ledger_db(
+                &mut ledger_db,
+                &vec![account_key.default_subaddress()],
+                100 * MOB,
+                &vec![KeyImage::from(rng.next_u64())],
+                &mut rng,
+            );
+        }
+        manually_sync_account(&ledger_db, &service.wallet_db, &account_id, &logger);
+
+        // The account has synced blocks 0 to 13, so is snapshotted as of
+        // blocks 0, 5 and 10, and then 12.
+        assert_eq!(service.record_account_snapshots(5).unwrap(), 3);
+        assert_eq!(service.record_account_snapshots(2).unwrap(), 1);
+        assert_eq!(service.record_account_snapshots(2).unwrap(), 0);
+
+        let snapshots = service
+            .get_account_snapshots(&account_id, None, Some(11), None)
+            .unwrap();
+        assert_eq!(snapshots.len(), 1);
+        assert_eq!(snapshots[0].block_index, 12);
+        assert_eq!(snapshots[0].unspent_value, (100 * MOB).to_string());
+        assert_eq!(snapshots[0].unspent_txo_count, 1);
+
+        // Rescanning removes the snapshots of the blocks rescanned.
+        service.rescan_account(&account_id, 5).unwrap();
+        let snapshots = service
+            .get_account_snapshots(&account_id, None, None, None)
+            .unwrap();
+        assert!(snapshots.iter().all(|snapshot| snapshot.block_index < 5));
+    }
+}
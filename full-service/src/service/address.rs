@@ -4,22 +4,26 @@
 
 use crate::{
     db::{
-        account::AccountID,
+        account::{AccountID, AccountModel},
         assigned_subaddress::AssignedSubaddressModel,
-        models::{AssignedSubaddress, ViewOnlySubaddress},
+        models::{Account, AssignedSubaddress, Txo, ViewOnlySubaddress},
         transaction,
         view_only_subaddress::ViewOnlySubaddressModel,
         WalletDbError,
     },
     service::WalletService,
-    util::b58::b58_decode_public_address,
+    util::b58::{b58_decode_public_address, b58_encode_public_address},
 };
+use mc_account_keys::AccountKey;
 use mc_common::logger::log;
 use mc_connection::{BlockchainConnection, UserTxConnection};
 use mc_fog_report_validation::FogPubkeyResolver;
 
 use displaydoc::Display;
 
+/// The most addresses which can be assigned in one call.
+pub const MAX_ADDRESSES_PER_ASSIGNMENT: u64 = 10_000;
+
 /// Errors for the Address Service.
 #[derive(Display, Debug)]
 #[allow(clippy::large_enum_variant)]
@@ -29,6 +33,9 @@ pub enum AddressServiceError {
 
     /// Diesel Error: {0}
     Diesel(diesel::result::Error),
+
+    /// Cannot assign {0} addresses at once, the count must be from 1 to {1}
+    InvalidAddressCount(u64, u64),
 }
 
 impl From<WalletDbError> for AddressServiceError {
@@ -43,6 +50,51 @@ impl From<diesel::result::Error> for AddressServiceError {
     }
 }
 
+/// Whether an account's subaddress is in use.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AddressStatus {
+    /// The subaddress has been assigned, so txos sent to it are recognized.
+    Assigned,
+
+    /// The subaddress has not been assigned, but orphaned txos were sent to
+    /// it. Assigning it recovers them.
+    OrphanFunded,
+
+    /// The subaddress has not been assigned, and nothing has been sent to it.
+    Untouched,
+}
+
+impl std::fmt::Display for AddressStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let status = match self {
+            Self::Assigned => "assigned",
+            Self::OrphanFunded => "orphan_funded",
+            Self::Untouched => "untouched",
+        };
+        write!(f, "{}", status)
+    }
+}
+
+/// An account's address at a subaddress index, whether or not it has been
+/// assigned.
+#[derive(Clone, Debug)]
+pub struct DerivedAddress {
+    pub account_id: AccountID,
+    pub public_address_b58: String,
+    pub subaddress_index: u64,
+
+    /// The status of the subaddress when it was looked up, before any
+    /// assignment.
+    pub status: AddressStatus,
+
+    /// The assigned subaddress, if it is assigned now.
+    pub assigned_subaddress: Option<AssignedSubaddress>,
+
+    /// The orphaned txos which were sent to the subaddress when it was looked
+    /// up.
+    pub orphaned_txo_ids: Vec<String>,
+}
+
 /// Trait defining the ways in which the wallet can interact with and manage
 /// addresses.
 pub trait AddressService {
@@ -54,12 +106,46 @@ pub trait AddressService {
         // FIXME: FS-32 - add "sync from block"
     ) -> Result<AssignedSubaddress, AddressServiceError>;
 
+    /// Assigns the account's next `count` addresses in a single database
+    /// transaction, so that either all of them are assigned or none are.
+    ///
+    /// With a metadata prefix, each address's metadata is the prefix followed
+    /// by its subaddress index.
+    fn assign_addresses_for_account(
+        &self,
+        account_id: &AccountID,
+        count: u64,
+        metadata_prefix: Option<&str>,
+    ) -> Result<Vec<AssignedSubaddress>, AddressServiceError>;
+
+    /// Assigns the address at a chosen subaddress index, such as one mapped
+    /// from a customer ID, without moving the account's next subaddress
+    /// index. Addresses assigned in sequence later skip over it.
+    fn assign_address_for_account_at_index(
+        &self,
+        account_id: &AccountID,
+        subaddress_index: u64,
+        metadata: Option<&str>,
+    ) -> Result<AssignedSubaddress, AddressServiceError>;
+
     fn get_address_for_account(
         &self,
         account_id: &AccountID,
         index: i64,
     ) -> Result<AssignedSubaddress, AddressServiceError>;
 
+    /// Derive the address at any subaddress index of an account, reporting
+    /// whether it is assigned, unassigned with orphaned txos sent to it, or
+    /// untouched, and optionally assigning it.
+    ///
+    /// Assigning an address which orphaned txos were sent to recovers them.
+    fn derive_address_for_account(
+        &self,
+        account_id: &AccountID,
+        index: u64,
+        assign: bool,
+    ) -> Result<DerivedAddress, AddressServiceError>;
+
     /// Gets all the addresses for the given account.
     fn get_addresses_for_account(
         &self,
@@ -96,7 +182,7 @@ where
         metadata: Option<&str>,
     ) -> Result<AssignedSubaddress, AddressServiceError> {
         let conn = self.wallet_db.get_conn()?;
-        transaction(&conn, || {
+        let address = transaction(&conn, || {
             let (public_address_b58, _subaddress_index) =
                 AssignedSubaddress::create_next_for_account(
                     &account_id.to_string(),
@@ -104,8 +190,79 @@ where
                     &self.ledger_db,
                     &conn,
                 )?;
-            Ok(AssignedSubaddress::get(&public_address_b58, &conn)?)
-        })
+            Ok::<AssignedSubaddress, AddressServiceError>(AssignedSubaddress::get(
+                &public_address_b58,
+                &conn,
+            )?)
+        })?;
+        // Orphaned txos sent to the new address are now unspent.
+        self.balance_cache.invalidate(&account_id.0);
+        Ok(address)
+    }
+
+    fn assign_addresses_for_account(
+        &self,
+        account_id: &AccountID,
+        count: u64,
+        metadata_prefix: Option<&str>,
+    ) -> Result<Vec<AssignedSubaddress>, AddressServiceError> {
+        if count == 0 || count > MAX_ADDRESSES_PER_ASSIGNMENT {
+            return Err(AddressServiceError::InvalidAddressCount(
+                count,
+                MAX_ADDRESSES_PER_ASSIGNMENT,
+            ));
+        }
+
+        let conn = self.wallet_db.get_conn()?;
+        let addresses = transaction(&conn, || {
+            let mut addresses = Vec::new();
+            for _ in 0..count {
+                let (public_address_b58, subaddress_index) =
+                    AssignedSubaddress::create_next_for_account(
+                        &account_id.to_string(),
+                        "",
+                        &self.ledger_db,
+                        &conn,
+                    )?;
+                let mut address = AssignedSubaddress::get(&public_address_b58, &conn)?;
+                // The index is only known once it has been claimed.
+                if let Some(prefix) = metadata_prefix {
+                    let comment = format!("{}{}", prefix, subaddress_index);
+                    address.update_comment(&comment, &conn)?;
+                    address.comment = comment;
+                }
+                addresses.push(address);
+            }
+            Ok::<Vec<AssignedSubaddress>, AddressServiceError>(addresses)
+        })?;
+        // Orphaned txos sent to the new addresses are now unspent.
+        self.balance_cache.invalidate(&account_id.0);
+        Ok(addresses)
+    }
+
+    fn assign_address_for_account_at_index(
+        &self,
+        account_id: &AccountID,
+        subaddress_index: u64,
+        metadata: Option<&str>,
+    ) -> Result<AssignedSubaddress, AddressServiceError> {
+        let conn = self.wallet_db.get_conn()?;
+        let address = transaction(&conn, || {
+            let (public_address_b58, _subaddress_index) =
+                AssignedSubaddress::create_sparse_for_account_at_index(
+                    &account_id.to_string(),
+                    subaddress_index,
+                    metadata.unwrap_or(""),
+                    &self.ledger_db,
+                    &conn,
+                )?;
+            Ok::<AssignedSubaddress, AddressServiceError>(AssignedSubaddress::get(
+                &public_address_b58,
+                &conn,
+            )?)
+        })?;
+        self.balance_cache.invalidate(&account_id.0);
+        Ok(address)
     }
 
     fn get_address_for_account(
@@ -121,6 +278,69 @@ where
         )?)
     }
 
+    fn derive_address_for_account(
+        &self,
+        account_id: &AccountID,
+        index: u64,
+        assign: bool,
+    ) -> Result<DerivedAddress, AddressServiceError> {
+        let conn = self.wallet_db.get_conn()?;
+        let derived = transaction(&conn, || {
+            let account = Account::get(account_id, &conn)?;
+            let account_key: AccountKey =
+                mc_util_serial::decode(&account.account_key).map_err(WalletDbError::from)?;
+            let public_address_b58 = b58_encode_public_address(&account_key.subaddress(index))
+                .map_err(WalletDbError::from)?;
+
+            let mut assigned_subaddress = match AssignedSubaddress::get(&public_address_b58, &conn)
+            {
+                Ok(assigned_subaddress) => Some(assigned_subaddress),
+                Err(WalletDbError::AssignedSubaddressNotFound(_)) => None,
+                Err(err) => return Err(err.into()),
+            };
+            let orphaned_txo_ids: Vec<String> = if assigned_subaddress.is_some() {
+                Vec::new()
+            } else {
+                AssignedSubaddress::list_orphaned_txos_at_index(&account_id.0, index, &conn)?
+                    .into_iter()
+                    .map(|txo: Txo| txo.txo_id_hex)
+                    .collect()
+            };
+            let status = if assigned_subaddress.is_some() {
+                AddressStatus::Assigned
+            } else if !orphaned_txo_ids.is_empty() {
+                AddressStatus::OrphanFunded
+            } else {
+                AddressStatus::Untouched
+            };
+
+            if assign && assigned_subaddress.is_none() {
+                AssignedSubaddress::create_for_account_at_index(
+                    &account_id.0,
+                    index,
+                    "",
+                    &self.ledger_db,
+                    &conn,
+                )?;
+                assigned_subaddress = Some(AssignedSubaddress::get(&public_address_b58, &conn)?);
+            }
+
+            Ok::<DerivedAddress, AddressServiceError>(DerivedAddress {
+                account_id: account_id.clone(),
+                public_address_b58,
+                subaddress_index: index,
+                status,
+                assigned_subaddress,
+                orphaned_txo_ids,
+            })
+        })?;
+        if assign && derived.status == AddressStatus::OrphanFunded {
+            // The orphaned txos are now unspent.
+            self.balance_cache.invalidate(&account_id.0);
+        }
+        Ok(derived)
+    }
+
     fn get_addresses_for_account(
         &self,
         account_id: &AccountID,
@@ -195,13 +415,284 @@ where
 mod tests {
     use super::*;
     use crate::{
-        test_utils::{get_test_ledger, setup_wallet_service},
+        db::txo::TxoModel,
+        service::account::AccountService,
+        test_utils::{get_test_ledger, manually_sync_account, setup_wallet_service},
         util::b58::b58_encode_public_address,
     };
-    use mc_account_keys::{AccountKey, PublicAddress};
+    use mc_account_keys::{AccountKey, PublicAddress, RootEntropy, RootIdentity};
     use mc_common::logger::{test_with_logger, Logger};
     use mc_crypto_rand::rand_core::RngCore;
+    use mc_util_from_random::FromRandom;
     use rand::{rngs::StdRng, SeedableRng};
+    use std::{sync::Arc, thread};
+
+    // Addresses assigned in parallel should each get a distinct subaddress
+    // index, with none skipped.
+    #[test_with_logger]
+    fn test_assign_address_concurrently(logger: Logger) {
+        const NUM_WORKERS: usize = 8;
+        const ADDRESSES_PER_WORKER: usize = 10;
+
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        let service = Arc::new(setup_wallet_service(ledger_db.clone(), logger.clone()));
+
+        let account = service
+            .create_account(
+                Some("A".to_string()),
+                "".to_string(),
+                "".to_string(),
+                "".to_string(),
+            )
+            .unwrap();
+        let account_id = AccountID(account.account_id_hex.clone());
+
+        let workers: Vec<_> = (0..NUM_WORKERS)
+            .map(|_| {
+                let service = service.clone();
+                let account_id = account_id.clone();
+                thread::spawn(move || {
+                    (0..ADDRESSES_PER_WORKER)
+                        .map(|_| {
+                            service
+                                .assign_address_for_account(&account_id, None)
+                                .expect("Could not assign address")
+                                .subaddress_index
+                        })
+                        .collect::<Vec<i64>>()
+                })
+            })
+            .collect();
+
+        let mut subaddress_indices: Vec<i64> = workers
+            .into_iter()
+            .flat_map(|worker| worker.join().unwrap())
+            .collect();
+        subaddress_indices.sort_unstable();
+
+        let num_assigned = (NUM_WORKERS * ADDRESSES_PER_WORKER) as i64;
+        let first_index = account.next_subaddress_index;
+        assert_eq!(
+            subaddress_indices,
+            (first_index..first_index + num_assigned).collect::<Vec<i64>>()
+        );
+
+        let account = service.get_account(&account_id).unwrap();
+        assert_eq!(account.next_subaddress_index, first_index + num_assigned);
+    }
+
+    #[test_with_logger]
+    fn test_assign_addresses_for_account(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+        let service = setup_wallet_service(ledger_db, logger);
+        let account = service
+            .create_account(
+                Some("A".to_string()),
+                "".to_string(),
+                "".to_string(),
+                "".to_string(),
+            )
+            .unwrap();
+        let account_id = AccountID(account.account_id_hex.clone());
+        let first_index = account.next_subaddress_index;
+
+        let addresses = service
+            .assign_addresses_for_account(&account_id, 5, Some("user-"))
+            .unwrap();
+        assert_eq!(
+            addresses
+                .iter()
+                .map(|address| address.subaddress_index)
+                .collect::<Vec<i64>>(),
+            (first_index..first_index + 5).collect::<Vec<i64>>()
+        );
+        for address in &addresses {
+            assert_eq!(
+                address.comment,
+                format!("user-{}", address.subaddress_index)
+            );
+            let conn = service.wallet_db.get_conn().unwrap();
+            assert_eq!(
+                AssignedSubaddress::get(&address.assigned_subaddress_b58, &conn)
+                    .unwrap()
+                    .comment,
+                address.comment
+            );
+        }
+        assert_eq!(
+            service
+                .get_account(&account_id)
+                .unwrap()
+                .next_subaddress_index,
+            first_index + 5
+        );
+
+        // Nothing is assigned if the count is out of range.
+        assert!(service
+            .assign_addresses_for_account(&account_id, 0, None)
+            .is_err());
+        assert!(service
+            .assign_addresses_for_account(&account_id, MAX_ADDRESSES_PER_ASSIGNMENT + 1, None)
+            .is_err());
+        assert_eq!(
+            service
+                .get_account(&account_id)
+                .unwrap()
+                .next_subaddress_index,
+            first_index + 5
+        );
+    }
+
+    #[test_with_logger]
+    fn test_derive_address_for_account(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        // Fund a subaddress beyond those the account has assigned.
+        let entropy = RootEntropy::from_random(&mut rng);
+        let account_key = AccountKey::from(&RootIdentity::from(&entropy));
+        let ledger_db = get_test_ledger(5, &[account_key.subaddress(10)], 12, &mut rng);
+
+        let service = setup_wallet_service(ledger_db.clone(), logger.clone());
+        let account = service
+            .import_account_from_legacy_root_entropy(
+                hex::encode(&entropy.bytes),
+                None,
+                None,
+                None,
+                "".to_string(),
+                "".to_string(),
+                "".to_string(),
+            )
+            .unwrap();
+        let account_id = AccountID(account.account_id_hex);
+        manually_sync_account(&ledger_db, &service.wallet_db, &account_id, &logger);
+
+        let derived = service
+            .derive_address_for_account(&account_id, 0, false)
+            .unwrap();
+        assert_eq!(derived.status, AddressStatus::Assigned);
+        assert!(derived.assigned_subaddress.is_some());
+
+        let derived = service
+            .derive_address_for_account(&account_id, 11, false)
+            .unwrap();
+        assert_eq!(derived.status, AddressStatus::Untouched);
+        assert!(derived.assigned_subaddress.is_none());
+        assert_eq!(
+            derived.public_address_b58,
+            b58_encode_public_address(&account_key.subaddress(11)).unwrap()
+        );
+
+        let derived = service
+            .derive_address_for_account(&account_id, 10, false)
+            .unwrap();
+        assert_eq!(derived.status, AddressStatus::OrphanFunded);
+        assert_eq!(derived.orphaned_txo_ids.len(), 12);
+        assert!(service.get_address_for_account(&account_id, 10).is_err());
+
+        // Assigning the subaddress recovers the orphaned txos, and later
+        // assignments carry on after it.
+        let derived = service
+            .derive_address_for_account(&account_id, 10, true)
+            .unwrap();
+        assert_eq!(derived.status, AddressStatus::OrphanFunded);
+        assert_eq!(derived.assigned_subaddress.unwrap().subaddress_index, 10);
+        let conn = service.wallet_db.get_conn().unwrap();
+        assert!(Txo::list_orphaned(&account_id.0, None, &conn)
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            service
+                .derive_address_for_account(&account_id, 10, false)
+                .unwrap()
+                .status,
+            AddressStatus::Assigned
+        );
+        assert_eq!(
+            service
+                .assign_address_for_account(&account_id, None)
+                .unwrap()
+                .subaddress_index,
+            11
+        );
+    }
+
+    #[test_with_logger]
+    fn test_assign_address_for_account_at_index(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        // Fund a customer's subaddress before it has been assigned.
+        let entropy = RootEntropy::from_random(&mut rng);
+        let account_key = AccountKey::from(&RootIdentity::from(&entropy));
+        let ledger_db = get_test_ledger(5, &[account_key.subaddress(1_000_000)], 12, &mut rng);
+
+        let service = setup_wallet_service(ledger_db.clone(), logger.clone());
+        let account = service
+            .import_account_from_legacy_root_entropy(
+                hex::encode(&entropy.bytes),
+                None,
+                None,
+                None,
+                "".to_string(),
+                "".to_string(),
+                "".to_string(),
+            )
+            .unwrap();
+        let account_id = AccountID(account.account_id_hex);
+        manually_sync_account(&ledger_db, &service.wallet_db, &account_id, &logger);
+        let next_subaddress_index = service
+            .get_account(&account_id)
+            .unwrap()
+            .next_subaddress_index;
+
+        let assigned = service
+            .assign_address_for_account_at_index(&account_id, 1_000_000, Some("customer"))
+            .unwrap();
+        assert_eq!(assigned.subaddress_index, 1_000_000);
+        assert_eq!(assigned.comment, "customer");
+        assert_eq!(
+            assigned.assigned_subaddress_b58,
+            b58_encode_public_address(&account_key.subaddress(1_000_000)).unwrap()
+        );
+        let conn = service.wallet_db.get_conn().unwrap();
+        assert!(Txo::list_orphaned(&account_id.0, None, &conn)
+            .unwrap()
+            .is_empty());
+
+        // The sequence of assigned addresses is left where it was.
+        assert_eq!(
+            service
+                .get_account(&account_id)
+                .unwrap()
+                .next_subaddress_index,
+            next_subaddress_index
+        );
+        assert!(service
+            .assign_address_for_account_at_index(&account_id, 1_000_000, None)
+            .is_err());
+        assert!(service
+            .assign_address_for_account_at_index(&account_id, u64::MAX - 1, None)
+            .is_err());
+
+        // And passes over indices which were assigned out of sequence.
+        service
+            .assign_address_for_account_at_index(&account_id, next_subaddress_index as u64, None)
+            .unwrap();
+        assert_eq!(
+            service
+                .assign_address_for_account(&account_id, None)
+                .unwrap()
+                .subaddress_index,
+            next_subaddress_index + 1
+        );
+    }
 
     // A properly encoded address should verify.
     #[test_with_logger]
@@ -0,0 +1,440 @@
+// Copyright (c) 2020-2022 MobileCoin Inc.
+
+//! Service for importing and exporting the metadata of an account's
+//! addresses in bulk.
+//!
+//! Merchants migrating from another system typically hold a mapping of their
+//! customer ids to deposit addresses. Exporting an account's labels, or
+//! importing them from the same CSV or JSON layout, carries that mapping over
+//! in one call. Importing a label for an address which isn't assigned yet
+//! assigns it, recovering any txos already sent to it.
+
+use crate::{
+    db::{
+        account::{AccountID, AccountModel},
+        assigned_subaddress::AssignedSubaddressModel,
+        models::{Account, AssignedSubaddress},
+        transaction, WalletDbError,
+    },
+    service::WalletService,
+    util::{
+        b58::b58_encode_public_address,
+        csv::{csv_escape, parse_csv},
+    },
+};
+use displaydoc::Display;
+use mc_account_keys::AccountKey;
+use mc_connection::{BlockchainConnection, UserTxConnection};
+use mc_fog_report_validation::FogPubkeyResolver;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashSet, str::FromStr};
+
+/// The most labels which can be imported at once.
+pub const MAX_ADDRESS_LABELS_PER_IMPORT: usize = 10_000;
+
+/// Errors for the Address Label Service.
+#[derive(Display, Debug)]
+#[allow(clippy::large_enum_variant)]
+pub enum AddressLabelServiceError {
+    /// Error interacting with the database: {0}
+    Database(WalletDbError),
+
+    /// Diesel Error: {0}
+    Diesel(diesel::result::Error),
+
+    /// Error serializing the labels: {0}
+    Json(serde_json::Error),
+
+    /// Unknown address label format: {0}
+    UnknownFormat(String),
+
+    /// Invalid address label file: {0}
+    InvalidFile(String),
+
+    /// The address label file contains no rows
+    EmptyFile,
+
+    /// The file has {0} rows, but at most {1} labels can be imported at once
+    TooManyRows(usize, usize),
+
+    /// Row {0}: {1}
+    InvalidRow(usize, String),
+}
+
+impl From<WalletDbError> for AddressLabelServiceError {
+    fn from(src: WalletDbError) -> Self {
+        Self::Database(src)
+    }
+}
+
+impl From<diesel::result::Error> for AddressLabelServiceError {
+    fn from(src: diesel::result::Error) -> Self {
+        Self::Diesel(src)
+    }
+}
+
+impl From<serde_json::Error> for AddressLabelServiceError {
+    fn from(src: serde_json::Error) -> Self {
+        Self::Json(src)
+    }
+}
+
+/// The layout of an address label file.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AddressLabelFormat {
+    /// CSV with a header row naming the subaddress_index, address and
+    /// metadata columns.
+    Csv,
+
+    /// A JSON array of objects with subaddress_index, address and metadata
+    /// fields.
+    Json,
+}
+
+impl FromStr for AddressLabelFormat {
+    type Err = AddressLabelServiceError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "csv" => Ok(AddressLabelFormat::Csv),
+            "json" => Ok(AddressLabelFormat::Json),
+            _ => Err(AddressLabelServiceError::UnknownFormat(s.to_string())),
+        }
+    }
+}
+
+/// A single row of an address label file.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct AddressLabel {
+    /// The subaddress index of the address. May be left empty on import if
+    /// the address is given and already assigned.
+    #[serde(default)]
+    pub subaddress_index: String,
+
+    /// The b58 address. Optional on import, where it is checked against the
+    /// account's address at the subaddress index.
+    #[serde(default)]
+    pub address: String,
+
+    /// The label to store with the address, such as a customer id.
+    #[serde(default)]
+    pub metadata: String,
+}
+
+/// What an import changed.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AddressLabelImport {
+    /// Addresses which weren't assigned before, and now are.
+    pub assigned: u64,
+    /// Assigned addresses whose metadata was replaced.
+    pub updated: u64,
+    /// Assigned addresses which already had the given metadata.
+    pub unchanged: u64,
+}
+
+/// Trait defining the ways in which the wallet can import and export address
+/// labels.
+pub trait AddressLabelService {
+    /// Export the subaddress index, address and metadata of each of the
+    /// account's assigned addresses, in order of subaddress index.
+    fn export_address_labels(
+        &self,
+        account_id: &AccountID,
+        format: AddressLabelFormat,
+    ) -> Result<String, AddressLabelServiceError>;
+
+    /// Set the metadata of the account's addresses from a file, assigning any
+    /// which aren't assigned yet.
+    ///
+    /// Every row is checked before any is applied, and the import is applied
+    /// all at once, so that a file with a bad row changes nothing.
+    fn import_address_labels(
+        &self,
+        account_id: &AccountID,
+        contents: &str,
+        format: AddressLabelFormat,
+    ) -> Result<AddressLabelImport, AddressLabelServiceError>;
+}
+
+impl<T, FPR> AddressLabelService for WalletService<T, FPR>
+where
+    T: BlockchainConnection + UserTxConnection + 'static,
+    FPR: FogPubkeyResolver + Send + Sync + 'static,
+{
+    fn export_address_labels(
+        &self,
+        account_id: &AccountID,
+        format: AddressLabelFormat,
+    ) -> Result<String, AddressLabelServiceError> {
+        let conn = self.wallet_db.get_conn()?;
+        Account::get(account_id, &conn)?;
+        let mut addresses = AssignedSubaddress::list_all(&account_id.0, None, None, &conn)?;
+        addresses.sort_by_key(|address| address.subaddress_index);
+
+        let labels: Vec<AddressLabel> = addresses
+            .into_iter()
+            .map(|address| AddressLabel {
+                subaddress_index: address.subaddress_index.to_string(),
+                address: address.assigned_subaddress_b58,
+                metadata: address.comment,
+            })
+            .collect();
+
+        Ok(match format {
+            AddressLabelFormat::Csv => {
+                let mut contents = "subaddress_index,address,metadata\n".to_string();
+                for label in &labels {
+                    contents.push_str(&format!(
+                        "{},{},{}\n",
+                        label.subaddress_index,
+                        label.address,
+                        csv_escape(&label.metadata),
+                    ));
+                }
+                contents
+            }
+            AddressLabelFormat::Json => serde_json::to_string(&labels)?,
+        })
+    }
+
+    fn import_address_labels(
+        &self,
+        account_id: &AccountID,
+        contents: &str,
+        format: AddressLabelFormat,
+    ) -> Result<AddressLabelImport, AddressLabelServiceError> {
+        let labels = match format {
+            AddressLabelFormat::Csv => parse_csv_labels(contents)?,
+            AddressLabelFormat::Json => serde_json::from_str::<Vec<AddressLabel>>(contents)
+                .map_err(|err| AddressLabelServiceError::InvalidFile(err.to_string()))?,
+        };
+        if labels.is_empty() {
+            return Err(AddressLabelServiceError::EmptyFile);
+        }
+        if labels.len() > MAX_ADDRESS_LABELS_PER_IMPORT {
+            return Err(AddressLabelServiceError::TooManyRows(
+                labels.len(),
+                MAX_ADDRESS_LABELS_PER_IMPORT,
+            ));
+        }
+
+        let conn = self.wallet_db.get_conn()?;
+        let import = transaction(&conn, || {
+            let account = Account::get(account_id, &conn)?;
+            let account_key: AccountKey =
+                mc_util_serial::decode(&account.account_key).map_err(WalletDbError::from)?;
+
+            // Resolve every row to a subaddress index before changing anything.
+            let mut resolved = Vec::new();
+            let mut seen = HashSet::new();
+            for (i, label) in labels.iter().enumerate() {
+                let row = i + 1;
+                let invalid = |message: String| AddressLabelServiceError::InvalidRow(row, message);
+                let address = label.address.trim();
+                let subaddress_index = if label.subaddress_index.trim().is_empty() {
+                    if address.is_empty() {
+                        return Err(invalid(
+                            "One of subaddress_index and address is required".to_string(),
+                        ));
+                    }
+                    match AssignedSubaddress::get(address, &conn) {
+                        Ok(assigned) if assigned.account_id_hex == account_id.0 => {
+                            assigned.subaddress_index as u64
+                        }
+                        Ok(_) | Err(WalletDbError::AssignedSubaddressNotFound(_)) => {
+                            return Err(invalid(format!(
+                                "{} is not assigned to the account, give its subaddress_index",
+                                address
+                            )));
+                        }
+                        Err(err) => return Err(err.into()),
+                    }
+                } else {
+                    label
+                        .subaddress_index
+                        .trim()
+                        .parse::<u64>()
+                        .map_err(|err| invalid(format!("Invalid subaddress_index: {}", err)))?
+                };
+
+                let public_address_b58 =
+                    b58_encode_public_address(&account_key.subaddress(subaddress_index))
+                        .map_err(WalletDbError::from)?;
+                if !address.is_empty() && address != public_address_b58 {
+                    return Err(invalid(format!(
+                        "{} is not the account's address at subaddress index {}",
+                        address, subaddress_index
+                    )));
+                }
+                if !seen.insert(subaddress_index) {
+                    return Err(invalid(format!(
+                        "Subaddress index {} appears more than once",
+                        subaddress_index
+                    )));
+                }
+                resolved.push((
+                    public_address_b58,
+                    subaddress_index,
+                    label.metadata.as_str(),
+                ));
+            }
+
+            let mut import = AddressLabelImport::default();
+            for (public_address_b58, subaddress_index, metadata) in resolved {
+                match AssignedSubaddress::get(&public_address_b58, &conn) {
+                    Ok(assigned) if assigned.comment == metadata => import.unchanged += 1,
+                    Ok(assigned) => {
+                        assigned.update_comment(metadata, &conn)?;
+                        import.updated += 1;
+                    }
+                    Err(WalletDbError::AssignedSubaddressNotFound(_)) => {
+                        AssignedSubaddress::create_for_account_at_index(
+                            &account_id.0,
+                            subaddress_index,
+                            metadata,
+                            &self.ledger_db,
+                            &conn,
+                        )?;
+                        import.assigned += 1;
+                    }
+                    Err(err) => return Err(err.into()),
+                }
+            }
+            Ok::<AddressLabelImport, AddressLabelServiceError>(import)
+        })?;
+
+        if import.assigned > 0 {
+            // Orphaned txos sent to the new addresses are now unspent.
+            self.balance_cache.invalidate(&account_id.0);
+        }
+        Ok(import)
+    }
+}
+
+/// Parse a CSV address label file. The first row is a header naming the
+/// columns, which may be in any order; either the subaddress_index or the
+/// address column may be left out.
+fn parse_csv_labels(contents: &str) -> Result<Vec<AddressLabel>, AddressLabelServiceError> {
+    let mut records = parse_csv(contents)
+        .map_err(AddressLabelServiceError::InvalidFile)?
+        .into_iter();
+    let header = records.next().ok_or(AddressLabelServiceError::EmptyFile)?;
+
+    let column = |name: &str| {
+        header
+            .iter()
+            .position(|field| field.trim().eq_ignore_ascii_case(name))
+    };
+    let subaddress_index = column("subaddress_index");
+    let address = column("address");
+    if subaddress_index.is_none() && address.is_none() {
+        return Err(AddressLabelServiceError::InvalidFile(
+            "Missing subaddress_index and address columns".to_string(),
+        ));
+    }
+    let metadata = column("metadata").ok_or_else(|| {
+        AddressLabelServiceError::InvalidFile("Missing metadata column".to_string())
+    })?;
+
+    // Metadata is kept as given, as surrounding spaces may be significant.
+    let field = |record: &[String], index: Option<usize>| {
+        index
+            .and_then(|i| record.get(i))
+            .cloned()
+            .unwrap_or_default()
+    };
+    Ok(records
+        .map(|record| AddressLabel {
+            subaddress_index: field(&record, subaddress_index),
+            address: field(&record, address),
+            metadata: field(&record, Some(metadata)),
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        service::{account::AccountService, address::AddressService},
+        test_utils::{get_test_ledger, setup_wallet_service},
+    };
+    use mc_account_keys::PublicAddress;
+    use mc_common::logger::{test_with_logger, Logger};
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test_with_logger]
+    fn test_import_and_export_address_labels(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+        let service = setup_wallet_service(ledger_db, logger);
+        let account = service
+            .create_account(
+                Some("merchant".to_string()),
+                "".to_string(),
+                "".to_string(),
+                "".to_string(),
+            )
+            .unwrap();
+        let account_id = AccountID(account.account_id_hex.clone());
+        let assigned = service
+            .assign_address_for_account(&account_id, Some("customer-1"))
+            .unwrap();
+        let account_key: AccountKey = mc_util_serial::decode(&account.account_key).unwrap();
+        let address_5 = b58_encode_public_address(&account_key.subaddress(5)).unwrap();
+
+        // Relabel an assigned address by its address alone, and assign a new
+        // one by index.
+        let csv = format!(
+            "address,metadata,subaddress_index\n{},\"customer-1, renamed\",\n{},customer-5,5\n",
+            assigned.assigned_subaddress_b58, address_5
+        );
+        let import = service
+            .import_address_labels(&account_id, &csv, AddressLabelFormat::Csv)
+            .unwrap();
+        assert_eq!(
+            import,
+            AddressLabelImport {
+                assigned: 1,
+                updated: 1,
+                unchanged: 0,
+            }
+        );
+        let exported = service
+            .export_address_labels(&account_id, AddressLabelFormat::Json)
+            .unwrap();
+        let labels: Vec<AddressLabel> = serde_json::from_str(&exported).unwrap();
+        let last = labels.last().unwrap();
+        assert_eq!(last.subaddress_index, "5");
+        assert_eq!(last.address, address_5);
+        assert_eq!(last.metadata, "customer-5");
+        assert!(labels
+            .iter()
+            .any(|label| label.metadata == "customer-1, renamed"));
+
+        // Importing an export changes nothing.
+        let exported_csv = service
+            .export_address_labels(&account_id, AddressLabelFormat::Csv)
+            .unwrap();
+        let import = service
+            .import_address_labels(&account_id, &exported_csv, AddressLabelFormat::Csv)
+            .unwrap();
+        assert_eq!(import.unchanged, labels.len() as u64);
+        assert_eq!(import.assigned + import.updated, 0);
+
+        // A bad row rejects the whole file.
+        let json = format!(
+            r#"[{{"subaddress_index": "7", "metadata": "customer-7"}},
+                {{"subaddress_index": "8", "address": "{}", "metadata": "customer-8"}}]"#,
+            address_5
+        );
+        match service.import_address_labels(&account_id, &json, AddressLabelFormat::Json) {
+            Err(AddressLabelServiceError::InvalidRow(2, _)) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+        let conn = service.wallet_db.get_conn().unwrap();
+        assert!(AssignedSubaddress::get_for_account_by_index(&account_id.0, 7, &conn).is_err());
+    }
+}
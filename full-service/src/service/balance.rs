@@ -7,16 +7,22 @@ use crate::{
         account::{AccountID, AccountModel},
         assigned_subaddress::AssignedSubaddressModel,
         models::{
-            Account, AssignedSubaddress, Txo, ViewOnlyAccount, ViewOnlySubaddress, ViewOnlyTxo,
+            Account, AssignedSubaddress, QuarantinedBlock, Txo, ViewOnlyAccount,
+            ViewOnlySubaddress, ViewOnlyTxo,
         },
-        txo::TxoModel,
+        quarantined_block::QuarantinedBlockModel,
+        txo::{TxoModel, TxoStatusTotals},
         view_only_account::ViewOnlyAccountModel,
         view_only_subaddress::ViewOnlySubaddressModel,
         view_only_txo::ViewOnlyTxoModel,
         Conn, WalletDbError,
     },
+    disk_space_monitor::DiskSpaceState,
     service::{
+        balance_cache::BalanceSnapshot,
         ledger::{LedgerService, LedgerServiceError},
+        token::TokenService,
+        upgrade_readiness::{UpgradeReadiness, UpgradeReadinessService},
         WalletService,
     },
 };
@@ -25,6 +31,8 @@ use mc_common::HashMap;
 use mc_connection::{BlockchainConnection, UserTxConnection};
 use mc_fog_report_validation::FogPubkeyResolver;
 use mc_ledger_db::Ledger;
+use mc_transaction_core::{tokens::Mob, Token, TokenId};
+use std::collections::BTreeMap;
 
 /// Errors for the Address Service.
 #[derive(Display, Debug)]
@@ -75,24 +83,63 @@ impl From<LedgerServiceError> for BalanceServiceError {
 /// This must be a service object because there is no "Balance" table in our
 /// data model.
 pub struct Balance {
+    /// The token the values are in.
+    pub token_id: u64,
     pub unspent: u128,
     pub pending: u128,
     pub spent: u128,
     pub secreted: u128,
     pub orphaned: u128,
+    /// The part of the unspent balance which is frozen.
+    pub frozen: u128,
     pub network_block_height: u64,
     pub local_block_height: u64,
     pub synced_blocks: u64,
     pub max_spendable: u128,
+    /// The fee a single transaction needs. Fees are paid in the token being
+    /// sent, so this is in the same token as the balance.
+    pub required_fee_reserve: u64,
+    /// Problems which keep the balance from being sent.
+    pub warnings: Vec<String>,
+}
+
+/// Warn when an account holds a token, but not enough of it to pay the fee
+/// for sending any.
+///
+/// Transactions pay their fee in the token they send, so holding MOB does not
+/// help an account send another token. It needs enough of the token itself.
+pub fn assess_fee_reserve(
+    token_id: u64,
+    unspent: u128,
+    max_spendable: u128,
+    required_fee_reserve: u64,
+) -> Vec<String> {
+    let mut warnings = Vec::new();
+    if unspent > 0 && max_spendable == 0 {
+        warnings.push(format!(
+            "The account holds {} of token {}, which is not enough to pay the fee of {} for a single transaction. Fees are paid in the token being sent, so receive more of token {} before sending it.",
+            unspent, token_id, required_fee_reserve, token_id
+        ));
+    }
+    warnings
 }
 
 /// The Network Status object.
 /// This holds the number of blocks in the ledger, on the network and locally.
 pub struct NetworkStatus {
+    /// The block count reached by a majority of the peers.
     pub network_block_height: u64,
+    /// The highest block count reported by any peer.
+    pub network_block_height_max: u64,
+    /// The block count reported by each peer.
+    pub peer_block_heights: BTreeMap<String, u64>,
     pub local_block_height: u64,
     pub fee_pmob: u64,
+    /// The minimum fee for each token the network accepts fees in.
+    pub minimum_fees: BTreeMap<TokenId, u64>,
     pub block_version: u32,
+    pub disk_space: Option<DiskSpaceState>,
+    pub upgrade_readiness: UpgradeReadiness,
 }
 
 /// The Wallet Status object returned by balance services.
@@ -115,6 +162,20 @@ pub struct WalletStatus {
     pub account_map: HashMap<AccountID, Account>,
     pub view_only_account_ids: Vec<String>,
     pub view_only_account_map: HashMap<String, ViewOnlyAccount>,
+    /// Blocks which accounts can't be synced past, until they can be processed
+    /// or are skipped.
+    pub quarantined_block_indices: Vec<u64>,
+    /// The wallet's totals of each known token held by any account, by token
+    /// ID. The totals above are for MOB.
+    pub token_totals: BTreeMap<u64, TxoStatusTotals>,
+    /// The wallet's totals of each token held which this build has no
+    /// metadata for, such as tokens launched since it was released, by token
+    /// ID. These are kept apart so that they are never mistaken for MOB or
+    /// another known token.
+    pub unknown_token_totals: BTreeMap<u64, TxoStatusTotals>,
+    /// Each account's totals of each token it holds, by token ID. MOB is
+    /// always included.
+    pub account_token_totals: HashMap<AccountID, BTreeMap<u64, TxoStatusTotals>>,
 }
 
 /// Trait defining the ways in which the wallet can interact with and manage
@@ -122,10 +183,13 @@ pub struct WalletStatus {
 pub trait BalanceService {
     /// Gets the balance for a given account.
     ///
-    /// Balance consists of the sums of the various txo states in our wallet
+    /// Balance consists of the sums of the various txo states in our wallet.
+    /// It is read from the account's balance snapshot unless force_refresh is
+    /// set, or the account has synced past the snapshot.
     fn get_balance_for_account(
         &self,
         account_id: &AccountID,
+        force_refresh: bool,
     ) -> Result<Balance, BalanceServiceError>;
 
     fn get_balance_for_view_only_account(
@@ -140,9 +204,19 @@ pub trait BalanceService {
         address: &str,
     ) -> Result<Balance, BalanceServiceError>;
 
+    /// Gets the total the account has received of a token, not counting
+    /// change.
+    fn get_received_total(
+        &self,
+        account_id: &AccountID,
+        token_id: u64,
+    ) -> Result<u128, BalanceServiceError>;
+
     fn get_network_status(&self) -> Result<NetworkStatus, BalanceServiceError>;
 
-    fn get_wallet_status(&self) -> Result<WalletStatus, BalanceServiceError>;
+    /// Gets an overview of the wallet, totalling the accounts' MOB balances
+    /// from their balance snapshots unless force_refresh is set.
+    fn get_wallet_status(&self, force_refresh: bool) -> Result<WalletStatus, BalanceServiceError>;
 }
 
 impl<T, FPR> BalanceService for WalletService<T, FPR>
@@ -153,27 +227,38 @@ where
     fn get_balance_for_account(
         &self,
         account_id: &AccountID,
+        force_refresh: bool,
     ) -> Result<Balance, BalanceServiceError> {
-        let account_id_hex = &account_id.to_string();
-
         let conn = self.wallet_db.get_conn()?;
-        let (unspent, max_spendable, pending, spent, secreted, orphaned) =
-            Self::get_balance_inner(account_id_hex, None, &conn)?;
+        let account = Account::get(account_id, &conn)?;
+        let token_id = account.preferred_token_id as u64;
+        let BalanceSnapshot {
+            totals,
+            max_spendable,
+            ..
+        } = self
+            .balance_cache
+            .get(&account, token_id, force_refresh, &conn)?;
+        let unspent = totals.unspent;
 
         let network_block_height = self.get_network_block_height()?;
         let local_block_height = self.ledger_db.num_blocks()?;
-        let account = Account::get(account_id, &conn)?;
+        let required_fee_reserve = self.get_required_fee_reserve(token_id);
 
         Ok(Balance {
+            token_id,
             unspent,
             max_spendable,
-            pending,
-            spent,
-            secreted,
-            orphaned,
+            pending: totals.pending,
+            spent: totals.spent,
+            secreted: totals.secreted,
+            orphaned: totals.orphaned,
+            frozen: totals.frozen,
             network_block_height,
             local_block_height,
             synced_blocks: account.next_block_index as u64,
+            required_fee_reserve,
+            warnings: assess_fee_reserve(token_id, unspent, max_spendable, required_fee_reserve),
         })
     }
 
@@ -191,15 +276,20 @@ where
         let account = ViewOnlyAccount::get(account_id, &conn)?;
 
         Ok(Balance {
+            token_id: 0,
             unspent,
             pending,
             spent,
             secreted,
             orphaned,
+            frozen: 0,
             network_block_height,
             local_block_height,
             synced_blocks: account.next_block_index as u64,
             max_spendable,
+            // View-only accounts can't send, so there is nothing to warn of.
+            required_fee_reserve: self.get_required_fee_reserve(0),
+            warnings: Vec::new(),
         })
     }
 
@@ -209,22 +299,27 @@ where
 
         let conn = self.wallet_db.get_conn()?;
         let assigned_address = AssignedSubaddress::get(address, &conn)?;
-
-        let (unspent, max_spendable, pending, spent, secreted, orphaned) =
-            Self::get_balance_inner(&assigned_address.account_id_hex, Some(address), &conn)?;
-
         let account = Account::get(&AccountID(assigned_address.account_id_hex), &conn)?;
+        let token_id = account.preferred_token_id as u64;
+
+        let (unspent, max_spendable, pending, spent, secreted, orphaned, frozen) =
+            Self::get_balance_inner(&account.account_id_hex, Some(address), token_id, &conn)?;
+        let required_fee_reserve = self.get_required_fee_reserve(token_id);
 
         Ok(Balance {
+            token_id,
             unspent,
             max_spendable,
             pending,
             spent,
             secreted,
             orphaned,
+            frozen,
             network_block_height,
             local_block_height,
             synced_blocks: account.next_block_index as u64,
+            required_fee_reserve,
+            warnings: assess_fee_reserve(token_id, unspent, max_spendable, required_fee_reserve),
         })
     }
 
@@ -246,28 +341,52 @@ where
         let account = ViewOnlyAccount::get(&view_only_subaddress.view_only_account_id_hex, &conn)?;
 
         Ok(Balance {
+            token_id: 0,
             unspent,
             max_spendable,
             pending,
             spent,
             secreted,
             orphaned,
+            frozen: 0,
             network_block_height,
             local_block_height,
             synced_blocks: account.next_block_index as u64,
+            required_fee_reserve: self.get_required_fee_reserve(0),
+            warnings: Vec::new(),
         })
     }
+
+    fn get_received_total(
+        &self,
+        account_id: &AccountID,
+        token_id: u64,
+    ) -> Result<u128, BalanceServiceError> {
+        let conn = self.wallet_db.get_conn()?;
+        Account::get(account_id, &conn)?;
+        Ok(Txo::sum_received_for_account(
+            &account_id.to_string(),
+            token_id,
+            &conn,
+        )?)
+    }
+
     fn get_network_status(&self) -> Result<NetworkStatus, BalanceServiceError> {
         Ok(NetworkStatus {
             network_block_height: self.get_network_block_height()?,
+            network_block_height_max: self.get_network_block_height_max(),
+            peer_block_heights: self.get_peer_block_heights(),
             local_block_height: self.ledger_db.num_blocks()?,
             fee_pmob: self.get_network_fee(),
+            minimum_fees: self.get_network_minimum_fees(),
             block_version: *self.get_network_block_version(),
+            disk_space: self.disk_space.clone(),
+            upgrade_readiness: self.get_upgrade_readiness(),
         })
     }
 
     // Wallet Status is an overview of the wallet's status
-    fn get_wallet_status(&self) -> Result<WalletStatus, BalanceServiceError> {
+    fn get_wallet_status(&self, force_refresh: bool) -> Result<WalletStatus, BalanceServiceError> {
         let network_block_height = self.get_network_block_height()?;
 
         let conn = self.wallet_db.get_conn()?;
@@ -276,24 +395,65 @@ where
         let view_only_accounts = ViewOnlyAccount::list_all(&conn)?;
         let mut view_only_account_map = HashMap::default();
 
-        let mut unspent: u128 = 0;
-        let mut pending: u128 = 0;
-        let mut spent: u128 = 0;
-        let mut secreted: u128 = 0;
-        let mut orphaned: u128 = 0;
+        let mut token_ids = Txo::list_token_ids(&conn)?;
+        if !token_ids.contains(&*Mob::ID) {
+            token_ids.insert(0, *Mob::ID);
+        }
+
+        // A forced refresh aggregates in the database rather than loading
+        // every txo for every account, or summing each account separately.
+        let totals = if force_refresh {
+            Some(
+                token_ids
+                    .iter()
+                    .map(|token_id| {
+                        Ok((
+                            *token_id,
+                            Txo::sum_values_by_account_and_status(*token_id, &conn)?,
+                        ))
+                    })
+                    .collect::<Result<HashMap<_, _>, WalletDbError>>()?,
+            )
+        } else {
+            None
+        };
 
+        let mut token_totals: BTreeMap<u64, TxoStatusTotals> = token_ids
+            .iter()
+            .map(|token_id| (*token_id, TxoStatusTotals::default()))
+            .collect();
+        let mut account_token_totals = HashMap::default();
         let mut min_synced_block_index = network_block_height - 1;
         let mut account_ids = Vec::new();
 
         for account in accounts {
             let account_id = AccountID(account.account_id_hex.clone());
-            let balance = Self::get_balance_inner(&account_id.to_string(), None, &conn)?;
+            let mut account_totals_by_token = BTreeMap::new();
+            for token_id in &token_ids {
+                let account_totals = match &totals {
+                    Some(totals) => totals[token_id]
+                        .get(&account.account_id_hex)
+                        .cloned()
+                        .unwrap_or_default(),
+                    None => {
+                        self.balance_cache
+                            .get(&account, *token_id, false, &conn)?
+                            .totals
+                    }
+                };
+                let wallet_totals = token_totals.entry(*token_id).or_default();
+                wallet_totals.unspent += account_totals.unspent;
+                wallet_totals.pending += account_totals.pending;
+                wallet_totals.spent += account_totals.spent;
+                wallet_totals.secreted += account_totals.secreted;
+                wallet_totals.orphaned += account_totals.orphaned;
+                wallet_totals.frozen += account_totals.frozen;
+                if *token_id == *Mob::ID || account_totals != TxoStatusTotals::default() {
+                    account_totals_by_token.insert(*token_id, account_totals);
+                }
+            }
+            account_token_totals.insert(account_id.clone(), account_totals_by_token);
             account_map.insert(account_id.clone(), account.clone());
-            unspent += balance.0;
-            pending += balance.2;
-            spent += balance.3;
-            secreted += balance.4;
-            orphaned += balance.5;
 
             // account.next_block_index is an index in range [0..ledger_db.num_blocks()]
             min_synced_block_index = std::cmp::min(
@@ -302,6 +462,17 @@ where
             );
             account_ids.push(account_id);
         }
+        let mob_totals = token_totals.get(&*Mob::ID).cloned().unwrap_or_default();
+        // Tokens launched since this build are synced like any other, but
+        // are totalled apart from those it has metadata for.
+        let known_token_ids: Vec<u64> = self
+            .get_tokens()
+            .iter()
+            .map(|token| *token.token_id)
+            .collect();
+        let (token_totals, unknown_token_totals) = token_totals
+            .into_iter()
+            .partition(|(token_id, _)| known_token_ids.contains(token_id));
 
         let mut view_only_account_ids = Vec::new();
         for account in view_only_accounts {
@@ -311,11 +482,11 @@ where
         }
 
         Ok(WalletStatus {
-            unspent,
-            pending,
-            spent,
-            secreted,
-            orphaned,
+            unspent: mob_totals.unspent,
+            pending: mob_totals.pending,
+            spent: mob_totals.spent,
+            secreted: mob_totals.secreted,
+            orphaned: mob_totals.orphaned,
             network_block_height,
             local_block_height: self.ledger_db.num_blocks()?,
             min_synced_block_index: min_synced_block_index as u64,
@@ -323,6 +494,14 @@ where
             account_map,
             view_only_account_ids,
             view_only_account_map,
+            quarantined_block_indices: QuarantinedBlock::list_all(&conn)?
+                .iter()
+                .filter(|block| !block.skipped)
+                .map(|block| block.block_index as u64)
+                .collect(),
+            token_totals,
+            unknown_token_totals,
+            account_token_totals,
         })
     }
 }
@@ -332,46 +511,36 @@ where
     T: BlockchainConnection + UserTxConnection + 'static,
     FPR: FogPubkeyResolver + Send + Sync + 'static,
 {
+    /// The fee a transaction sending the token needs, which is the network's
+    /// minimum fee for it, or MOB's minimum fee if the network doesn't report
+    /// one, as the transaction builder defaults to.
+    fn get_required_fee_reserve(&self, token_id: u64) -> u64 {
+        self.get_network_minimum_fees()
+            .get(&TokenId::from(token_id))
+            .cloned()
+            .unwrap_or(Mob::MINIMUM_FEE)
+    }
+
     fn get_balance_inner(
         account_id_hex: &str,
         assigned_subaddress_b58: Option<&str>,
+        token_id: u64,
         conn: &Conn,
-    ) -> Result<(u128, u128, u128, u128, u128, u128), BalanceServiceError> {
+    ) -> Result<(u128, u128, u128, u128, u128, u128, u128), BalanceServiceError> {
         let max_spendable =
-            Txo::list_spendable(account_id_hex, None, assigned_subaddress_b58, Some(0), conn)?
-                .max_spendable_in_wallet;
-        let unspent = Txo::list_unspent(account_id_hex, assigned_subaddress_b58, Some(0), conn)?
-            .iter()
-            .map(|t| (t.value as u64) as u128)
-            .sum::<u128>();
-        let spent = Txo::list_spent(account_id_hex, assigned_subaddress_b58, Some(0), conn)?
-            .iter()
-            .map(|t| (t.value as u64) as u128)
-            .sum::<u128>();
-        let pending = Txo::list_pending(account_id_hex, assigned_subaddress_b58, Some(0), conn)?
-            .iter()
-            .map(|t| (t.value as u64) as u128)
-            .sum::<u128>();
+            Txo::max_spendable_in_wallet(account_id_hex, assigned_subaddress_b58, token_id, conn)?;
+        let totals =
+            Txo::sum_values_for_account(account_id_hex, assigned_subaddress_b58, token_id, conn)?;
 
-        let secreted = if assigned_subaddress_b58.is_some() {
-            0
-        } else {
-            Txo::list_secreted(account_id_hex, Some(0), conn)?
-                .iter()
-                .map(|t| t.value as u128)
-                .sum::<u128>()
-        };
-
-        let orphaned = if assigned_subaddress_b58.is_some() {
-            0
-        } else {
-            Txo::list_orphaned(account_id_hex, Some(0), conn)?
-                .iter()
-                .map(|t| t.value as u128)
-                .sum::<u128>()
-        };
-
-        let result = (unspent, max_spendable, pending, spent, secreted, orphaned);
+        let result = (
+            totals.unspent,
+            max_spendable,
+            totals.pending,
+            totals.spent,
+            totals.secreted,
+            totals.orphaned,
+            totals.frozen,
+        );
         Ok(result)
     }
 
@@ -380,27 +549,17 @@ where
         assigned_subaddress_b58: Option<&str>,
         conn: &Conn,
     ) -> Result<(u128, u128, u128, u128, u128, u128), BalanceServiceError> {
-        let unspent =
-            ViewOnlyTxo::list_unspent(account_id_hex, assigned_subaddress_b58, Some(0), conn)?
-                .iter()
-                .map(|t| (t.value as u64) as u128)
-                .sum::<u128>();
-        let spent =
-            ViewOnlyTxo::list_spent(account_id_hex, assigned_subaddress_b58, Some(0), conn)?
-                .iter()
-                .map(|t| (t.value as u64) as u128)
-                .sum::<u128>();
-        let orphaned = ViewOnlyTxo::list_orphaned(account_id_hex, Some(0), conn)?
-            .iter()
-            .map(|t| (t.value as u64) as u128)
-            .sum::<u128>();
-        let pending =
-            ViewOnlyTxo::list_pending(account_id_hex, assigned_subaddress_b58, Some(0), conn)?
-                .iter()
-                .map(|t| (t.value as u64) as u128)
-                .sum::<u128>();
-
-        let result = (unspent, 0, pending, spent, 0, orphaned);
+        let totals =
+            ViewOnlyTxo::sum_values_for_account(account_id_hex, assigned_subaddress_b58, 0, conn)?;
+
+        let result = (
+            totals.unspent,
+            0,
+            totals.pending,
+            totals.spent,
+            0,
+            totals.orphaned,
+        );
         Ok(result)
     }
 }
@@ -413,7 +572,10 @@ mod tests {
             account::AccountService, address::AddressService,
             view_only_account::ViewOnlyAccountService,
         },
-        test_utils::{get_test_ledger, manually_sync_account, setup_wallet_service, MOB},
+        test_utils::{
+            create_test_received_txo, get_test_ledger, manually_sync_account, setup_wallet_service,
+            MOB,
+        },
         util::b58::b58_encode_public_address,
     };
     use mc_account_keys::{
@@ -422,9 +584,7 @@ mod tests {
     };
     use mc_common::logger::{test_with_logger, Logger};
     use mc_crypto_keys::{RistrettoPrivate, RistrettoPublic};
-    use mc_transaction_core::{
-        encrypted_fog_hint::EncryptedFogHint, tokens::Mob, tx::TxOut, Amount, Token,
-    };
+    use mc_transaction_core::{encrypted_fog_hint::EncryptedFogHint, tx::TxOut, Amount};
     use mc_util_from_random::FromRandom;
     use rand::{rngs::StdRng, SeedableRng};
 
@@ -477,7 +637,7 @@ mod tests {
         );
 
         let account_balance = service
-            .get_balance_for_account(&AccountID(account.account_id_hex))
+            .get_balance_for_account(&AccountID(account.account_id_hex.clone()), false)
             .expect("Could not get balance for account");
 
         // 3 accounts * 5_000 MOB * 12 blocks
@@ -488,6 +648,8 @@ mod tests {
         assert_eq!(account_balance.spent, 0);
         assert_eq!(account_balance.secreted, 0);
         assert_eq!(account_balance.orphaned, 60_000 * MOB as u128); // Public address 3
+        assert_eq!(account_balance.required_fee_reserve, Mob::MINIMUM_FEE);
+        assert!(account_balance.warnings.is_empty());
 
         let db_account_key: AccountKey =
             mc_util_serial::decode(&account.account_key).expect("Could not decode account key");
@@ -525,6 +687,79 @@ mod tests {
             Err(BalanceServiceError::Database(WalletDbError::AssignedSubaddressNotFound(_))) => {}
             Err(e) => panic!("Unexpected error {:?}", e),
         }
+
+        // Balances are in the account's preferred token, which the account
+        // holds none of.
+        service
+            .update_account_preferred_token_id(&AccountID(account.account_id_hex.clone()), 1)
+            .expect("Could not update preferred token");
+        let account_balance = service
+            .get_balance_for_account(&AccountID(account.account_id_hex.clone()), false)
+            .expect("Could not get balance for account");
+        assert_eq!(account_balance.token_id, 1);
+        assert_eq!(account_balance.unspent, 0);
+        assert_eq!(account_balance.orphaned, 0);
+        let address_balance = service
+            .get_balance_for_address(&b58_pub_address)
+            .expect("Could not get balance for address");
+        assert_eq!(address_balance.token_id, 1);
+        assert_eq!(address_balance.unspent, 0);
+    }
+
+    // Tokens the wallet has no metadata for are totalled apart from known ones.
+    #[test_with_logger]
+    fn test_wallet_status_unknown_tokens(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+        let service = setup_wallet_service(ledger_db, logger);
+
+        let entropy = RootEntropy::from_random(&mut rng);
+        let account_key = AccountKey::from(&RootIdentity::from(&entropy));
+        service
+            .import_account_from_legacy_root_entropy(
+                hex::encode(&entropy.bytes),
+                None,
+                None,
+                None,
+                "".to_string(),
+                "".to_string(),
+                "".to_string(),
+            )
+            .expect("Could not import account entropy");
+
+        for (value, token_id) in [(MOB, 0), (250, 1), (70, 8192), (30, 8192)] {
+            create_test_received_txo(
+                &account_key,
+                DEFAULT_SUBADDRESS_INDEX,
+                Amount::new(value as u64, TokenId::from(token_id)),
+                4,
+                &mut rng,
+                &service.wallet_db,
+            );
+        }
+
+        for force_refresh in [false, true] {
+            let status = service
+                .get_wallet_status(force_refresh)
+                .expect("Could not get wallet status");
+            assert_eq!(status.unspent, MOB as u128);
+            assert_eq!(
+                status.token_totals.keys().cloned().collect::<Vec<_>>(),
+                vec![0, 1]
+            );
+            assert_eq!(status.token_totals[&1].unspent, 250);
+            assert_eq!(
+                status
+                    .unknown_token_totals
+                    .keys()
+                    .cloned()
+                    .collect::<Vec<_>>(),
+                vec![8192]
+            );
+            assert_eq!(status.unknown_token_totals[&8192].unspent, 100);
+        }
     }
 
     // The balance for an address should be accurate.
@@ -653,4 +888,19 @@ mod tests {
         assert_eq!(balance.secreted, 0);
         assert_eq!(balance.orphaned, 0);
     }
+
+    #[test]
+    fn test_assess_fee_reserve() {
+        let fee = Mob::MINIMUM_FEE;
+
+        // An empty account has nothing it can't send.
+        assert!(assess_fee_reserve(1, 0, 0, fee).is_empty());
+
+        assert!(assess_fee_reserve(1, fee as u128 * 2, fee as u128, fee).is_empty());
+
+        // Holding less of the token than the fee leaves it stuck.
+        let warnings = assess_fee_reserve(1, fee as u128 / 2, 0, fee);
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("token 1"));
+    }
 }
@@ -0,0 +1,206 @@
+// Copyright (c) 2020-2022 MobileCoin Inc.
+
+//! Cache of account balances, kept up to date by the sync thread.
+//!
+//! Summing an account's txos for each balance request is slow for wallets
+//! with many accounts, so each account's totals are snapshotted in memory.
+//! The sync thread refreshes the snapshots of the accounts it syncs, and the
+//! services which change txo statuses outside of sync invalidate them.
+//!
+//! A snapshot records how far its account had synced when it was taken, and
+//! is only used while the account has not synced further, so a snapshot is
+//! never read after sync has changed the account's txos, even when the
+//! account is synced other than by the sync thread.
+
+use crate::db::{
+    account::{AccountID, AccountModel},
+    models::{Account, Txo},
+    txo::{TxoModel, TxoStatusTotals},
+    Conn, WalletDbError,
+};
+use mc_common::HashMap;
+use mc_transaction_core::{tokens::Mob, Token};
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc, RwLock,
+};
+
+/// An account's balance of a token, as of a point in its sync.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BalanceSnapshot {
+    /// The total value of the account's txos in each status.
+    pub totals: TxoStatusTotals,
+
+    /// The largest value which can be spent in one transaction.
+    pub max_spendable: u128,
+
+    /// The account's next block index when the snapshot was taken.
+    pub next_block_index: u64,
+}
+
+/// Snapshots of account balances, by account ID and token ID.
+#[derive(Clone, Default)]
+pub struct BalanceCache {
+    snapshots: Arc<RwLock<HashMap<String, HashMap<u64, BalanceSnapshot>>>>,
+
+    /// Count of invalidations, so that a snapshot read from the database
+    /// before an invalidation is not stored after it.
+    generation: Arc<AtomicU64>,
+}
+
+impl BalanceCache {
+    /// Get the account's balance of a token, from its snapshot if the account
+    /// has not synced past it, or from the database otherwise.
+    pub fn get(
+        &self,
+        account: &Account,
+        token_id: u64,
+        force_refresh: bool,
+        conn: &Conn,
+    ) -> Result<BalanceSnapshot, WalletDbError> {
+        if !force_refresh {
+            let snapshot = self
+                .snapshots
+                .read()
+                .expect("lock poisoned")
+                .get(&account.account_id_hex)
+                .and_then(|snapshots| snapshots.get(&token_id))
+                .cloned();
+            if let Some(snapshot) = snapshot {
+                if snapshot.next_block_index == account.next_block_index as u64 {
+                    return Ok(snapshot);
+                }
+            }
+        }
+        self.refresh(account, token_id, conn)
+    }
+
+    /// Snapshot the account's balance of a token from the database.
+    pub fn refresh(
+        &self,
+        account: &Account,
+        token_id: u64,
+        conn: &Conn,
+    ) -> Result<BalanceSnapshot, WalletDbError> {
+        let account_id_hex = &account.account_id_hex;
+        let generation = self.generation.load(Ordering::SeqCst);
+        let snapshot = BalanceSnapshot {
+            totals: Txo::sum_values_for_account(account_id_hex, None, token_id, conn)?,
+            max_spendable: Txo::max_spendable_in_wallet(account_id_hex, None, token_id, conn)?,
+            next_block_index: account.next_block_index as u64,
+        };
+        let mut snapshots = self.snapshots.write().expect("lock poisoned");
+        if self.generation.load(Ordering::SeqCst) == generation {
+            snapshots
+                .entry(account_id_hex.to_string())
+                .or_default()
+                .insert(token_id, snapshot.clone());
+        }
+        Ok(snapshot)
+    }
+
+    /// Snapshot the account's balance of MOB, and of each other token which
+    /// has been snapshotted for it, after its txos have changed.
+    pub fn refresh_account(&self, account_id_hex: &str, conn: &Conn) -> Result<(), WalletDbError> {
+        let account = match Account::get(&AccountID(account_id_hex.to_string()), conn) {
+            Ok(account) => account,
+            Err(WalletDbError::AccountNotFound(_)) => {
+                self.invalidate(account_id_hex);
+                return Ok(());
+            }
+            Err(err) => return Err(err),
+        };
+
+        let mut token_ids: Vec<u64> = self
+            .snapshots
+            .read()
+            .expect("lock poisoned")
+            .get(account_id_hex)
+            .map(|snapshots| snapshots.keys().cloned().collect())
+            .unwrap_or_default();
+        if !token_ids.contains(&*Mob::ID) {
+            token_ids.push(*Mob::ID);
+        }
+        for token_id in token_ids {
+            self.refresh(&account, token_id, conn)?;
+        }
+        Ok(())
+    }
+
+    /// Drop the account's snapshots, after its txos have changed other than by
+    /// sync.
+    pub fn invalidate(&self, account_id_hex: &str) {
+        let mut snapshots = self.snapshots.write().expect("lock poisoned");
+        snapshots.remove(account_id_hex);
+        self.generation.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        service::account::AccountService,
+        test_utils::{
+            add_block_to_ledger_db, get_test_ledger, manually_sync_account, setup_wallet_service,
+            MOB,
+        },
+    };
+    use mc_account_keys::{AccountKey, RootEntropy, RootIdentity};
+    use mc_common::logger::{test_with_logger, Logger};
+    use mc_transaction_core::ring_signature::KeyImage;
+    use mc_util_from_random::FromRandom;
+    use rand::{rngs::StdRng, RngCore, SeedableRng};
+
+    #[test_with_logger]
+    fn test_balance_cache(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let entropy = RootEntropy::from_random(&mut rng);
+        let account_key = AccountKey::from(&RootIdentity::from(&entropy));
+        let public_address = account_key.subaddress(0);
+        let mut ledger_db = get_test_ledger(5, &[public_address.clone()], 12, &mut rng);
+
+        let service = setup_wallet_service(ledger_db.clone(), logger.clone());
+        let account = service
+            .import_account_from_legacy_root_entropy(
+                hex::encode(&entropy.bytes),
+                None,
+                None,
+                None,
+                "".to_string(),
+                "".to_string(),
+                "".to_string(),
+            )
+            .unwrap();
+        let account_id = AccountID(account.account_id_hex);
+        let wallet_db = &service.wallet_db;
+        let account = manually_sync_account(&ledger_db, wallet_db, &account_id, &logger);
+
+        let cache = BalanceCache::default();
+        let conn = wallet_db.get_conn().unwrap();
+        let snapshot = cache.get(&account, 0, false, &conn).unwrap();
+        assert_eq!(snapshot.totals.unspent, 60_000 * MOB as u128);
+        assert_eq!(snapshot.next_block_index, account.next_block_index as u64);
+
+        // Once the account syncs further, its snapshot is no longer used.
+        add_block_to_ledger_db(
+            &mut ledger_db,
+            &[public_address],
+            100 * MOB,
+            &[KeyImage::from(rng.next_u64())],
+            &mut rng,
+        );
+        let account = manually_sync_account(&ledger_db, wallet_db, &account_id, &logger);
+        let snapshot = cache.get(&account, 0, false, &conn).unwrap();
+        assert_eq!(snapshot.totals.unspent, 60_100 * MOB as u128);
+
+        // Snapshots of other tokens are refreshed along with MOB's.
+        cache.get(&account, 1, false, &conn).unwrap();
+        cache.refresh_account(&account_id.0, &conn).unwrap();
+        assert_eq!(cache.snapshots.read().unwrap()[&account_id.0].len(), 2);
+
+        cache.invalidate(&account_id.0);
+        assert!(cache.snapshots.read().unwrap().is_empty());
+    }
+}
@@ -6,12 +6,13 @@ use crate::{
     db::{
         account::AccountID,
         models::Txo,
+        transaction,
         txo::{TxoID, TxoModel},
         WalletDbError,
     },
     service::{
         transaction_log::{TransactionLogService, TransactionLogServiceError},
-        txo::{TxoService, TxoServiceError},
+        txo::TxoServiceError,
     },
     WalletService,
 };
@@ -21,6 +22,7 @@ use mc_crypto_keys::CompressedRistrettoPublic;
 use mc_fog_report_validation::FogPubkeyResolver;
 use mc_ledger_db::Ledger;
 use mc_transaction_core::tx::TxOutConfirmationNumber;
+use std::collections::HashMap;
 
 /// Errors for the Txo Service.
 #[derive(Display, Debug)]
@@ -104,6 +106,8 @@ pub struct Confirmation {
 /// tonfirmation numbers.
 pub trait ConfirmationService {
     /// Get the confirmations from the outputs in a transaction log.
+    ///
+    /// The ledger index of each output is cached the first time it is found.
     fn get_confirmations(
         &self,
         transaction_log_id: &str,
@@ -129,24 +133,56 @@ where
     ) -> Result<Vec<Confirmation>, ConfirmationServiceError> {
         let (_transaction_log, associated_txos) = self.get_transaction_log(transaction_log_id)?;
 
+        // Confirmation numbers are stored with the outputs when the transaction
+        // is built, so load all of the outputs at once, and only go to the
+        // ledger for the index of outputs which have not been looked up yet.
+        let conn = self.wallet_db.get_conn()?;
+        let txo_ids: Vec<String> = associated_txos
+            .outputs
+            .iter()
+            .map(|txo| txo.txo_id_hex.clone())
+            .collect();
+        let txos: HashMap<String, Txo> = Txo::select_by_id(&txo_ids, None, &conn)?
+            .into_iter()
+            .map(|txo| (txo.txo_id_hex.clone(), txo))
+            .collect();
+
         let mut results = Vec::new();
-        for associated_txo in associated_txos.outputs {
-            let txo = self.get_txo(&TxoID(associated_txo.txo_id_hex.clone()))?;
-            if let Some(confirmation) = txo.confirmation {
-                let confirmation: TxOutConfirmationNumber = mc_util_serial::decode(&confirmation)?;
-                let pubkey: CompressedRistrettoPublic = mc_util_serial::decode(&txo.public_key)?;
-                let txo_index = self.ledger_db.get_tx_out_index_by_public_key(&pubkey)?;
-                results.push(Confirmation {
-                    txo_id: TxoID(txo.txo_id_hex),
-                    txo_index,
-                    confirmation,
-                });
-            } else {
-                return Err(ConfirmationServiceError::MissingConfirmation(
-                    associated_txo.txo_id_hex,
-                ));
-            }
+        let mut uncached = Vec::new();
+        for txo_id_hex in txo_ids {
+            let txo = txos
+                .get(&txo_id_hex)
+                .ok_or_else(|| WalletDbError::TxoNotFound(txo_id_hex.clone()))?;
+            let confirmation: TxOutConfirmationNumber = match &txo.confirmation {
+                Some(confirmation) => mc_util_serial::decode(confirmation)?,
+                None => return Err(ConfirmationServiceError::MissingConfirmation(txo_id_hex)),
+            };
+            let txo_index = match txo.ledger_txo_index {
+                Some(txo_index) => txo_index as u64,
+                None => {
+                    let pubkey: CompressedRistrettoPublic =
+                        mc_util_serial::decode(&txo.public_key)?;
+                    let txo_index = self.ledger_db.get_tx_out_index_by_public_key(&pubkey)?;
+                    uncached.push((txo, txo_index));
+                    txo_index
+                }
+            };
+            results.push(Confirmation {
+                txo_id: TxoID(txo_id_hex),
+                txo_index,
+                confirmation,
+            });
         }
+
+        if !uncached.is_empty() {
+            transaction(&conn, || {
+                for (txo, txo_index) in &uncached {
+                    txo.update_ledger_txo_index(*txo_index, &conn)?;
+                }
+                Ok::<(), WalletDbError>(())
+            })?;
+        }
+
         Ok(results)
     }
 
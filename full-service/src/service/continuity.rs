@@ -0,0 +1,301 @@
+// Copyright (c) 2020-2022 MobileCoin Inc.
+
+//! Service for continuity exports, for estate and continuity planning.
+//!
+//! When configured, the operator is expected to check in at least once per
+//! check-in period. If they don't, the scheduler writes the view only
+//! materials of every account to the export directory, encrypted to a
+//! beneficiary's key, so that someone else can see the wallet's funds without
+//! being able to spend them. One export is written per missed check-in.
+//!
+//! The export is JSON, holding an import_view_only_account request for each
+//! account, encrypted with mc-crypto-box to the beneficiary's Ristretto public
+//! key. It never contains spend keys or mnemonics.
+
+use crate::{
+    db::{
+        account::AccountID, continuity_check_in::ContinuityCheckInModel, models::ContinuityCheckIn,
+        Conn, WalletDbError,
+    },
+    json_rpc::json_rpc_request::JsonRPCRequest,
+    service::account::{AccountService, AccountServiceError},
+    WalletService,
+};
+use chrono::Utc;
+use displaydoc::Display;
+use mc_common::logger::log;
+use mc_connection::{BlockchainConnection, UserTxConnection};
+use mc_crypto_box::{CryptoBox, VersionedCryptoBox};
+use mc_crypto_keys::RistrettoPublic;
+use mc_fog_report_validation::FogPubkeyResolver;
+use std::{convert::TryFrom, fs, path::PathBuf, time::Duration};
+
+/// Errors for the Continuity Service.
+#[derive(Display, Debug)]
+#[allow(clippy::large_enum_variant)]
+pub enum ContinuityServiceError {
+    /// Error interacting with the database: {0}
+    Database(WalletDbError),
+
+    /// Error with the account service: {0}
+    AccountService(AccountServiceError),
+
+    /// Continuity exports are not configured
+    NotConfigured,
+
+    /// Error building the export: {0}
+    Export(String),
+
+    /// Error serializing the export: {0}
+    Json(serde_json::Error),
+
+    /// Error encrypting the export: {0}
+    Encryption(String),
+
+    /// Error writing the export: {0}
+    Io(std::io::Error),
+}
+
+impl From<WalletDbError> for ContinuityServiceError {
+    fn from(src: WalletDbError) -> Self {
+        Self::Database(src)
+    }
+}
+
+impl From<AccountServiceError> for ContinuityServiceError {
+    fn from(src: AccountServiceError) -> Self {
+        Self::AccountService(src)
+    }
+}
+
+impl From<serde_json::Error> for ContinuityServiceError {
+    fn from(src: serde_json::Error) -> Self {
+        Self::Json(src)
+    }
+}
+
+impl From<std::io::Error> for ContinuityServiceError {
+    fn from(src: std::io::Error) -> Self {
+        Self::Io(src)
+    }
+}
+
+/// Where continuity exports are written, who they are encrypted to, and how
+/// often the operator must check in to hold them off.
+#[derive(Clone, Debug)]
+pub struct ContinuityExportConfig {
+    /// The directory exports are written to.
+    pub dir: PathBuf,
+
+    /// The beneficiary's key, which exports are encrypted to.
+    pub public_key: RistrettoPublic,
+
+    /// How long the operator may go without checking in.
+    pub check_in_period: Duration,
+}
+
+/// When the operator last checked in, and whether an export has been written
+/// since.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ContinuityStatus {
+    pub check_in_period_seconds: i64,
+    pub last_check_in_time: i64,
+    /// The time after which an export is written, unless the operator checks
+    /// in first.
+    pub export_due_time: i64,
+    /// When an export was written for the last check-in, if one has been.
+    pub exported_time: Option<i64>,
+    pub export_path: Option<String>,
+}
+
+impl ContinuityStatus {
+    fn new(check_in: ContinuityCheckIn, config: &ContinuityExportConfig) -> Self {
+        let check_in_period_seconds = config.check_in_period.as_secs() as i64;
+        Self {
+            check_in_period_seconds,
+            last_check_in_time: check_in.created_time,
+            export_due_time: check_in.created_time + check_in_period_seconds,
+            exported_time: check_in.exported_time,
+            export_path: check_in.export_path,
+        }
+    }
+}
+
+/// Trait defining the ways in which the wallet can interact with continuity
+/// exports.
+pub trait ContinuityService {
+    /// Record that the operator still has access to the wallet, holding off
+    /// the next export for another check-in period.
+    fn continuity_check_in(&self) -> Result<ContinuityStatus, ContinuityServiceError>;
+
+    /// Get when the operator last checked in, and when an export is due.
+    fn get_continuity_status(&self) -> Result<ContinuityStatus, ContinuityServiceError>;
+
+    /// Write an export if the operator has missed a check-in and none has
+    /// been written since, returning its path.
+    fn run_due_continuity_export(&self) -> Result<Option<PathBuf>, ContinuityServiceError>;
+}
+
+impl<T, FPR> ContinuityService for WalletService<T, FPR>
+where
+    T: BlockchainConnection + UserTxConnection + 'static,
+    FPR: FogPubkeyResolver + Send + Sync + 'static,
+{
+    fn continuity_check_in(&self) -> Result<ContinuityStatus, ContinuityServiceError> {
+        let config = self
+            .continuity_export
+            .as_ref()
+            .ok_or(ContinuityServiceError::NotConfigured)?;
+        let conn = self.wallet_db.get_conn()?;
+        let check_in = ContinuityCheckIn::create(Utc::now().timestamp(), &conn)?;
+        log::info!(self.logger, "Continuity check-in recorded");
+        Ok(ContinuityStatus::new(check_in, config))
+    }
+
+    fn get_continuity_status(&self) -> Result<ContinuityStatus, ContinuityServiceError> {
+        let config = self
+            .continuity_export
+            .as_ref()
+            .ok_or(ContinuityServiceError::NotConfigured)?;
+        let conn = self.wallet_db.get_conn()?;
+        let check_in = latest_check_in(Utc::now().timestamp(), &conn)?;
+        Ok(ContinuityStatus::new(check_in, config))
+    }
+
+    fn run_due_continuity_export(&self) -> Result<Option<PathBuf>, ContinuityServiceError> {
+        let config = match &self.continuity_export {
+            Some(config) => config,
+            None => return Ok(None),
+        };
+        let conn = self.wallet_db.get_conn()?;
+        let now = Utc::now().timestamp();
+        let check_in = latest_check_in(now, &conn)?;
+        let status = ContinuityStatus::new(check_in.clone(), config);
+        if status.exported_time.is_some() || now < status.export_due_time {
+            return Ok(None);
+        }
+
+        let mut import_requests = Vec::new();
+        for account in self.list_accounts()? {
+            let package = self.get_view_only_import_package(&AccountID(account.account_id_hex))?;
+            import_requests
+                .push(JsonRPCRequest::try_from(&package).map_err(ContinuityServiceError::Export)?);
+        }
+        let export = serde_json::json!({
+            "object": "continuity_export",
+            "created_time": now,
+            "last_check_in_time": status.last_check_in_time,
+            "import_requests": import_requests,
+        });
+        let ciphertext = VersionedCryptoBox::default()
+            .encrypt(
+                &mut rand::thread_rng(),
+                &config.public_key,
+                &serde_json::to_vec(&export)?,
+            )
+            .map_err(|err| ContinuityServiceError::Encryption(format!("{:?}", err)))?;
+
+        fs::create_dir_all(&config.dir)?;
+        let path = config.dir.join(format!("continuity-export-{}.bin", now));
+        fs::write(&path, ciphertext)?;
+        check_in.record_export(&path.to_string_lossy(), now, &conn)?;
+
+        log::warn!(
+            self.logger,
+            "No continuity check-in since {}, wrote view only export of {} accounts to {:?}",
+            status.last_check_in_time,
+            import_requests.len(),
+            path,
+        );
+        Ok(Some(path))
+    }
+}
+
+/// Get the latest check-in, starting the clock now if the operator has never
+/// checked in, so that enabling exports doesn't write one straight away.
+fn latest_check_in(now: i64, conn: &Conn) -> Result<ContinuityCheckIn, WalletDbError> {
+    match ContinuityCheckIn::get_latest(conn)? {
+        Some(check_in) => Ok(check_in),
+        None => ContinuityCheckIn::create(now, conn),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{get_test_ledger, setup_wallet_service};
+    use mc_account_keys::PublicAddress;
+    use mc_common::logger::{test_with_logger, Logger};
+    use mc_crypto_keys::RistrettoPrivate;
+    use mc_util_from_random::FromRandom;
+    use rand::{rngs::StdRng, SeedableRng};
+    use tempdir::TempDir;
+
+    #[test_with_logger]
+    fn test_continuity_export(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+        let mut service = setup_wallet_service(ledger_db, logger);
+        let account = service
+            .create_account(
+                Some("treasury".to_string()),
+                "".to_string(),
+                "".to_string(),
+                "".to_string(),
+            )
+            .unwrap();
+
+        // Without a configuration, nothing is exported.
+        match service.continuity_check_in() {
+            Err(ContinuityServiceError::NotConfigured) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+        assert_eq!(service.run_due_continuity_export().unwrap(), None);
+
+        let dir = TempDir::new("continuity").unwrap();
+        let private_key = RistrettoPrivate::from_random(&mut rng);
+        service.continuity_export = Some(ContinuityExportConfig {
+            dir: dir.path().to_path_buf(),
+            public_key: RistrettoPublic::from(&private_key),
+            check_in_period: Duration::from_secs(3600),
+        });
+
+        // The clock starts when exports are first enabled.
+        let status = service.get_continuity_status().unwrap();
+        assert_eq!(status.export_due_time, status.last_check_in_time + 3600);
+        assert_eq!(service.run_due_continuity_export().unwrap(), None);
+
+        // A missed check-in writes one export.
+        let conn = service.wallet_db.get_conn().unwrap();
+        ContinuityCheckIn::create(Utc::now().timestamp() - 3601, &conn).unwrap();
+        let path = service.run_due_continuity_export().unwrap().unwrap();
+        assert_eq!(service.run_due_continuity_export().unwrap(), None);
+        let status = service.get_continuity_status().unwrap();
+        assert_eq!(status.export_path, Some(path.to_string_lossy().to_string()));
+
+        // The beneficiary can decrypt it, and it holds no spend keys.
+        let ciphertext = fs::read(&path).unwrap();
+        let (success, plaintext) = VersionedCryptoBox::default()
+            .decrypt(&private_key, &ciphertext)
+            .unwrap();
+        assert!(success);
+        let export: serde_json::Value = serde_json::from_slice(&plaintext).unwrap();
+        let import_requests = export["import_requests"].as_array().unwrap();
+        assert_eq!(import_requests.len(), 1);
+        assert_eq!(import_requests[0]["method"], "import_view_only_account");
+        assert_eq!(
+            import_requests[0]["params"]["account"]["account_id"],
+            account.account_id_hex.as_str()
+        );
+        let plaintext = String::from_utf8(plaintext).unwrap();
+        assert!(!plaintext.contains("spend_private_key"));
+        assert!(!plaintext.contains("mnemonic"));
+
+        // Checking in holds off the next export.
+        let status = service.continuity_check_in().unwrap();
+        assert_eq!(status.exported_time, None);
+        assert_eq!(service.run_due_continuity_export().unwrap(), None);
+    }
+}
@@ -0,0 +1,147 @@
+// Copyright (c) 2020-2022 MobileCoin Inc.
+
+//! Service for encrypting the wallet database with a password.
+//!
+//! The wallet database holds each account's entropy, so anyone who can read
+//! the file can spend from the accounts unless it is encrypted. SQLCipher
+//! encrypts every page of the database with a key derived from the password.
+
+use crate::{
+    db::{
+        models::{WalletEvent, EVENT_TYPE_DATABASE_PASSWORD_CHANGED},
+        wallet_event::WalletEventModel,
+        WalletDbError,
+    },
+    WalletService,
+};
+use displaydoc::Display;
+use mc_connection::{BlockchainConnection, UserTxConnection};
+use mc_fog_report_validation::FogPubkeyResolver;
+use serde_json::json;
+
+/// Errors for the Database Password Service.
+#[derive(Display, Debug)]
+#[allow(clippy::large_enum_variant)]
+pub enum DatabasePasswordServiceError {
+    /// Error interacting with the database: {0}
+    Database(WalletDbError),
+}
+
+impl From<WalletDbError> for DatabasePasswordServiceError {
+    fn from(src: WalletDbError) -> Self {
+        Self::Database(src)
+    }
+}
+
+/// Trait defining the ways in which the wallet can encrypt its database.
+pub trait DatabasePasswordService {
+    /// Encrypt an unencrypted database with a password.
+    ///
+    /// The password must then be given with --wallet-db-password or
+    /// MC_PASSWORD each time the wallet is started.
+    fn set_db_password(&self, password: &str) -> Result<bool, DatabasePasswordServiceError>;
+
+    /// Re-encrypt the database with a new password.
+    fn change_db_password(
+        &self,
+        old_password: &str,
+        new_password: &str,
+    ) -> Result<bool, DatabasePasswordServiceError>;
+}
+
+impl<T, FPR> DatabasePasswordService for WalletService<T, FPR>
+where
+    T: BlockchainConnection + UserTxConnection + 'static,
+    FPR: FogPubkeyResolver + Send + Sync + 'static,
+{
+    fn set_db_password(&self, password: &str) -> Result<bool, DatabasePasswordServiceError> {
+        self.wallet_db.set_password(password)?;
+
+        let conn = self.wallet_db.get_conn()?;
+        WalletEvent::create(
+            EVENT_TYPE_DATABASE_PASSWORD_CHANGED,
+            &json!({ "operation": "set_db_password" }),
+            &conn,
+        )?;
+        Ok(true)
+    }
+
+    fn change_db_password(
+        &self,
+        old_password: &str,
+        new_password: &str,
+    ) -> Result<bool, DatabasePasswordServiceError> {
+        self.wallet_db.change_password(old_password, new_password)?;
+
+        let conn = self.wallet_db.get_conn()?;
+        WalletEvent::create(
+            EVENT_TYPE_DATABASE_PASSWORD_CHANGED,
+            &json!({ "operation": "change_db_password" }),
+            &conn,
+        )?;
+        Ok(true)
+    }
+}
+
+// Postgres databases are not encrypted by full-service.
+#[cfg(all(test, not(feature = "postgres")))]
+mod tests {
+    use super::*;
+    use crate::{
+        db::account::AccountID,
+        service::account::AccountService,
+        test_utils::{get_test_ledger, setup_wallet_service},
+    };
+    use mc_common::logger::{test_with_logger, Logger};
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test_with_logger]
+    fn test_set_and_change_db_password(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+        let ledger_db = get_test_ledger(5, &vec![], 12, &mut rng);
+        let service = setup_wallet_service(ledger_db.clone(), logger.clone());
+
+        let account = service
+            .create_account(
+                Some("Encrypted".to_string()),
+                "".to_string(),
+                "".to_string(),
+                "".to_string(),
+            )
+            .unwrap();
+        let account_id = AccountID(account.id.clone());
+
+        assert!(matches!(
+            service.change_db_password("", "hunter2"),
+            Err(DatabasePasswordServiceError::Database(
+                WalletDbError::DatabaseNotEncrypted
+            ))
+        ));
+        assert!(matches!(
+            service.set_db_password(""),
+            Err(DatabasePasswordServiceError::Database(
+                WalletDbError::EmptyDatabasePassword
+            ))
+        ));
+
+        // The accounts are still there once the database is encrypted.
+        assert!(service.set_db_password("hunter2").unwrap());
+        assert!(service.wallet_db.is_encrypted());
+        assert_eq!(service.get_account(&account_id).unwrap(), account);
+        assert!(matches!(
+            service.set_db_password("hunter3"),
+            Err(DatabasePasswordServiceError::Database(
+                WalletDbError::DatabaseAlreadyEncrypted
+            ))
+        ));
+
+        assert!(matches!(
+            service.change_db_password("wrong", "hunter3"),
+            Err(DatabasePasswordServiceError::Database(
+                WalletDbError::IncorrectDatabasePassword
+            ))
+        ));
+        assert!(service.change_db_password("hunter2", "hunter3").unwrap());
+        assert_eq!(service.get_account(&account_id).unwrap(), account);
+    }
+}
@@ -0,0 +1,197 @@
+// Copyright (c) 2020-2022 MobileCoin Inc.
+
+//! Service for listing the deposits received by an account.
+//!
+//! A single inbound transaction often pays a subaddress with several txos,
+//! so counting txos overcounts deposits. Instead, the txos received from
+//! others in one block at one subaddress are grouped into a deposit, with one
+//! deposit for each token they are in.
+
+use crate::{
+    db::{
+        account::{AccountID, AccountModel},
+        models::{Account, Txo},
+        txo::TxoModel,
+        WalletDbError,
+    },
+    WalletService,
+};
+use displaydoc::Display;
+use mc_connection::{BlockchainConnection, UserTxConnection};
+use mc_fog_report_validation::FogPubkeyResolver;
+use std::collections::BTreeMap;
+
+/// Errors for the Deposit Service.
+#[derive(Display, Debug)]
+#[allow(clippy::large_enum_variant)]
+pub enum DepositServiceError {
+    /// Error interacting with the database: {0}
+    Database(WalletDbError),
+}
+
+impl From<WalletDbError> for DepositServiceError {
+    fn from(src: WalletDbError) -> Self {
+        Self::Database(src)
+    }
+}
+
+/// The txos of one token received from others in one block at one
+/// subaddress.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Deposit {
+    pub account_id_hex: String,
+    pub block_index: u64,
+    /// The subaddress the txos were received at, or None while they are
+    /// orphaned.
+    pub subaddress_index: Option<u64>,
+    pub token_id: u64,
+    /// The combined value of the txos.
+    pub value: u128,
+    pub txo_ids: Vec<String>,
+}
+
+/// Trait defining the ways in which the wallet can list deposits.
+pub trait DepositService {
+    /// List the deposits received by an account in a range of blocks, in block
+    /// order, and then by subaddress and token.
+    fn get_deposits(
+        &self,
+        account_id: &AccountID,
+        min_block_index: Option<u64>,
+        max_block_index: Option<u64>,
+    ) -> Result<Vec<Deposit>, DepositServiceError>;
+}
+
+impl<T, FPR> DepositService for WalletService<T, FPR>
+where
+    T: BlockchainConnection + UserTxConnection + 'static,
+    FPR: FogPubkeyResolver + Send + Sync + 'static,
+{
+    fn get_deposits(
+        &self,
+        account_id: &AccountID,
+        min_block_index: Option<u64>,
+        max_block_index: Option<u64>,
+    ) -> Result<Vec<Deposit>, DepositServiceError> {
+        let conn = self.wallet_db.get_conn()?;
+        Account::get(account_id, &conn)?;
+        let txos = Txo::list_received_from_others(
+            &account_id.to_string(),
+            min_block_index,
+            max_block_index,
+            &conn,
+        )?;
+        Ok(group_deposits(&account_id.to_string(), &txos))
+    }
+}
+
+/// Group received txos into deposits, by the block they were received in, the
+/// subaddress they were received at, and their token.
+pub fn group_deposits(account_id_hex: &str, txos: &[Txo]) -> Vec<Deposit> {
+    let mut deposits: BTreeMap<(u64, Option<u64>, u64), Deposit> = BTreeMap::new();
+    for txo in txos {
+        let block_index = match txo.received_block_index {
+            Some(block_index) => block_index as u64,
+            None => continue,
+        };
+        let subaddress_index = txo.subaddress_index.map(|index| index as u64);
+        let token_id = txo.token_id as u64;
+        let deposit = deposits
+            .entry((block_index, subaddress_index, token_id))
+            .or_insert_with(|| Deposit {
+                account_id_hex: account_id_hex.to_string(),
+                block_index,
+                subaddress_index,
+                token_id,
+                value: 0,
+                txo_ids: Vec::new(),
+            });
+        deposit.value += (txo.value as u64) as u128;
+        deposit.txo_ids.push(txo.txo_id_hex.clone());
+    }
+    deposits.into_values().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        service::account::AccountService,
+        test_utils::{create_test_received_txo, get_test_ledger, setup_wallet_service, MOB},
+    };
+    use mc_account_keys::{AccountKey, PublicAddress, RootEntropy, RootIdentity};
+    use mc_common::logger::{test_with_logger, Logger};
+    use mc_transaction_core::{Amount, TokenId};
+    use mc_util_from_random::FromRandom;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test_with_logger]
+    fn test_get_deposits(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+        let service = setup_wallet_service(ledger_db, logger);
+
+        let entropy = RootEntropy::from_random(&mut rng);
+        let account_key = AccountKey::from(&RootIdentity::from(&entropy));
+        let account = service
+            .import_account_from_legacy_root_entropy(
+                hex::encode(&entropy.bytes),
+                None,
+                None,
+                None,
+                "".to_string(),
+                "".to_string(),
+                "".to_string(),
+            )
+            .unwrap();
+        let account_id = AccountID(account.account_id_hex.clone());
+
+        // Two txos of one transaction, another token in the same block, and
+        // payments to another subaddress and in a later block.
+        let mut receive = |subaddress_index, value: u64, token_id: u64, block_index| {
+            create_test_received_txo(
+                &account_key,
+                subaddress_index,
+                Amount::new(value, TokenId::from(token_id)),
+                block_index,
+                &mut rng,
+                &service.wallet_db,
+            )
+            .0
+        };
+        let first = receive(0, 3 * MOB, 0, 4);
+        let second = receive(0, 2 * MOB, 0, 4);
+        receive(0, 250, 1, 4);
+        receive(2, MOB, 0, 4);
+        receive(0, MOB, 0, 6);
+
+        let deposits = service.get_deposits(&account_id, None, None).unwrap();
+        assert_eq!(deposits.len(), 4);
+        assert_eq!(
+            deposits[0],
+            Deposit {
+                account_id_hex: account.account_id_hex.clone(),
+                block_index: 4,
+                subaddress_index: Some(0),
+                token_id: 0,
+                value: 5 * MOB as u128,
+                txo_ids: vec![first, second],
+            }
+        );
+        assert_eq!(deposits[1].token_id, 1);
+        assert_eq!(deposits[1].value, 250);
+        assert_eq!(deposits[2].subaddress_index, Some(2));
+        assert_eq!(deposits[3].block_index, 6);
+
+        let deposits = service.get_deposits(&account_id, Some(5), None).unwrap();
+        assert_eq!(deposits.len(), 1);
+        assert_eq!(deposits[0].block_index, 6);
+
+        match service.get_deposits(&AccountID("abcd".to_string()), None, None) {
+            Err(DepositServiceError::Database(WalletDbError::AccountNotFound(_))) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,527 @@
+// Copyright (c) 2020-2022 MobileCoin Inc.
+
+//! Service for running preflight diagnostics against the wallet's
+//! configuration and environment.
+
+use crate::{
+    db::{account::AccountModel, models::Account, WalletDb, LATEST_MIGRATION_VERSION},
+    util::disk_space::{disk_space, DiskSpaceLevel, DiskSpaceThresholds},
+    WalletService,
+};
+use mc_account_keys::AccountKey;
+use mc_connection::{BlockchainConnection, RetryableBlockchainConnection, UserTxConnection};
+use mc_fog_report_validation::FogPubkeyResolver;
+use mc_ledger_db::Ledger;
+use mc_util_uri::FogUri;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeSet,
+    fs::OpenOptions,
+    iter::empty,
+    path::Path,
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// The outcome of a single diagnostic check.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DiagnosticStatus {
+    Passed,
+    Warning,
+    Failed,
+    Skipped,
+}
+
+/// A single diagnostic check and its outcome.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DiagnosticCheck {
+    /// Name of the check, e.g. "wallet_db_schema".
+    pub name: String,
+
+    /// Whether the check passed.
+    pub status: DiagnosticStatus,
+
+    /// Human readable description of the outcome, including how to resolve a
+    /// failure.
+    pub message: String,
+}
+
+impl DiagnosticCheck {
+    fn new(name: &str, status: DiagnosticStatus, message: impl Into<String>) -> Self {
+        Self {
+            name: name.to_string(),
+            status,
+            message: message.into(),
+        }
+    }
+}
+
+/// Trait defining the ways in which the wallet can check its own health.
+pub trait DiagnosticsService {
+    /// Run all diagnostic checks, returning the outcome of each.
+    fn run_diagnostics(&self) -> Vec<DiagnosticCheck>;
+}
+
+impl<T, FPR> DiagnosticsService for WalletService<T, FPR>
+where
+    T: BlockchainConnection + UserTxConnection + 'static,
+    FPR: FogPubkeyResolver + Send + Sync + 'static,
+{
+    fn run_diagnostics(&self) -> Vec<DiagnosticCheck> {
+        let mut checks = Vec::new();
+        checks.extend(check_wallet_db(self));
+        checks.push(check_ledger_db(self));
+        checks.extend(check_disk_space(self));
+        checks.push(check_system_clock(self));
+        checks.extend(check_peers(self));
+        checks.extend(check_fog(self));
+        checks
+    }
+}
+
+fn check_wallet_db<T, FPR>(service: &WalletService<T, FPR>) -> Vec<DiagnosticCheck>
+where
+    T: BlockchainConnection + UserTxConnection + 'static,
+    FPR: FogPubkeyResolver + Send + Sync + 'static,
+{
+    let conn = match service.wallet_db.get_conn() {
+        Ok(conn) => conn,
+        Err(err) => {
+            return vec![DiagnosticCheck::new(
+                "wallet_db_connection",
+                DiagnosticStatus::Failed,
+                format!(
+                    "Could not connect to the wallet database, check the --wallet-db path and MC_PASSWORD: {}",
+                    err
+                ),
+            )]
+        }
+    };
+
+    let writable = match WalletDb::database_path(&conn) {
+        Ok(Some(path)) => match OpenOptions::new().write(true).open(&path) {
+            Ok(_) => DiagnosticCheck::new(
+                "wallet_db_writable",
+                DiagnosticStatus::Passed,
+                format!("{} is writable", path.display()),
+            ),
+            Err(err) => DiagnosticCheck::new(
+                "wallet_db_writable",
+                DiagnosticStatus::Failed,
+                format!(
+                    "{} is not writable, check the file permissions: {}",
+                    path.display(),
+                    err
+                ),
+            ),
+        },
+        #[cfg(not(feature = "postgres"))]
+        Ok(None) => DiagnosticCheck::new(
+            "wallet_db_writable",
+            DiagnosticStatus::Warning,
+            "The wallet database is not backed by a file, and will not persist",
+        ),
+        #[cfg(feature = "postgres")]
+        Ok(None) => DiagnosticCheck::new(
+            "wallet_db_writable",
+            DiagnosticStatus::Passed,
+            "The wallet database is stored in Postgres",
+        ),
+        Err(err) => DiagnosticCheck::new(
+            "wallet_db_writable",
+            DiagnosticStatus::Failed,
+            format!("Could not determine the wallet database path: {}", err),
+        ),
+    };
+
+    let expected_version = LATEST_MIGRATION_VERSION;
+    let schema = match WalletDb::schema_version(&conn) {
+        Ok(Some(version)) if version == expected_version => DiagnosticCheck::new(
+            "wallet_db_schema",
+            DiagnosticStatus::Passed,
+            format!("Schema is at version {}", version),
+        ),
+        Ok(Some(version)) if version.as_str() > expected_version => DiagnosticCheck::new(
+            "wallet_db_schema",
+            DiagnosticStatus::Failed,
+            format!(
+                "Schema version {} is newer than this release supports ({}). The database was created by a newer version of full-service, please upgrade.",
+                version, expected_version
+            ),
+        ),
+        Ok(Some(version)) => DiagnosticCheck::new(
+            "wallet_db_schema",
+            DiagnosticStatus::Failed,
+            format!(
+                "Schema version {} has pending migrations up to {}. Restart full-service to apply them.",
+                version, expected_version
+            ),
+        ),
+        Ok(None) => DiagnosticCheck::new(
+            "wallet_db_schema",
+            DiagnosticStatus::Failed,
+            "No migrations have been applied to the wallet database",
+        ),
+        Err(err) => DiagnosticCheck::new(
+            "wallet_db_schema",
+            DiagnosticStatus::Failed,
+            format!("Could not read the schema version: {}", err),
+        ),
+    };
+
+    vec![writable, schema]
+}
+
+fn check_ledger_db<T, FPR>(service: &WalletService<T, FPR>) -> DiagnosticCheck
+where
+    T: BlockchainConnection + UserTxConnection + 'static,
+    FPR: FogPubkeyResolver + Send + Sync + 'static,
+{
+    if let Some(path) = service.ledger_db_path.as_ref() {
+        if !path.join("data.mdb").exists() {
+            return DiagnosticCheck::new(
+                "ledger_db",
+                DiagnosticStatus::Failed,
+                format!(
+                    "No ledger found at {}, check the --ledger-db path",
+                    path.display()
+                ),
+            );
+        }
+    }
+
+    match service.ledger_db.num_blocks() {
+        Ok(0) => DiagnosticCheck::new(
+            "ledger_db",
+            DiagnosticStatus::Failed,
+            "The ledger is empty, and has not been bootstrapped with the origin block",
+        ),
+        Ok(num_blocks) => DiagnosticCheck::new(
+            "ledger_db",
+            DiagnosticStatus::Passed,
+            format!("The ledger contains {} blocks", num_blocks),
+        ),
+        Err(err) => DiagnosticCheck::new(
+            "ledger_db",
+            DiagnosticStatus::Failed,
+            format!("Could not read the ledger: {}", err),
+        ),
+    }
+}
+
+fn check_disk_space<T, FPR>(service: &WalletService<T, FPR>) -> Vec<DiagnosticCheck>
+where
+    T: BlockchainConnection + UserTxConnection + 'static,
+    FPR: FogPubkeyResolver + Send + Sync + 'static,
+{
+    let wallet_db_path = service
+        .wallet_db
+        .get_conn()
+        .ok()
+        .and_then(|conn| WalletDb::database_path(&conn).ok().flatten());
+
+    let thresholds = service
+        .disk_space
+        .as_ref()
+        .map(|disk_space| disk_space.thresholds())
+        .unwrap_or_default();
+
+    [
+        ("disk_space_wallet_db", wallet_db_path),
+        ("disk_space_ledger_db", service.ledger_db_path.clone()),
+    ]
+    .iter()
+    .filter_map(|(name, path)| {
+        path.as_ref()
+            .map(|path| check_volume(name, path, &thresholds))
+    })
+    .collect()
+}
+
+fn check_volume(name: &str, path: &Path, thresholds: &DiskSpaceThresholds) -> DiagnosticCheck {
+    match disk_space(path) {
+        Ok(space) if thresholds.level(&space) == DiskSpaceLevel::Critical => DiagnosticCheck::new(
+            name,
+            DiagnosticStatus::Failed,
+            format!(
+                "Only {} MB free on the volume holding {}, free up space to avoid database corruption",
+                space.available_bytes / (1024 * 1024),
+                path.display()
+            ),
+        ),
+        Ok(space) if thresholds.level(&space) == DiskSpaceLevel::Low => DiagnosticCheck::new(
+            name,
+            DiagnosticStatus::Warning,
+            format!(
+                "Only {} MB free on the volume holding {}",
+                space.available_bytes / (1024 * 1024),
+                path.display()
+            ),
+        ),
+        Ok(space) => DiagnosticCheck::new(
+            name,
+            DiagnosticStatus::Passed,
+            format!(
+                "{} MB free on the volume holding {}",
+                space.available_bytes / (1024 * 1024),
+                path.display()
+            ),
+        ),
+        Err(err) => DiagnosticCheck::new(
+            name,
+            DiagnosticStatus::Failed,
+            format!("Could not check free space for {}: {}", path.display(), err),
+        ),
+    }
+}
+
+/// How far, in seconds, the system clock may lag behind the time at which the
+/// latest block was signed before we consider it wrong.
+const MAX_CLOCK_SKEW_SECS: u64 = 5 * 60;
+
+fn check_system_clock<T, FPR>(service: &WalletService<T, FPR>) -> DiagnosticCheck
+where
+    T: BlockchainConnection + UserTxConnection + 'static,
+    FPR: FogPubkeyResolver + Send + Sync + 'static,
+{
+    let latest_block_index = match service.ledger_db.num_blocks() {
+        Ok(num_blocks) if num_blocks > 0 => num_blocks - 1,
+        _ => {
+            return DiagnosticCheck::new(
+                "system_clock",
+                DiagnosticStatus::Skipped,
+                "The ledger does not contain any blocks to compare the system clock against",
+            )
+        }
+    };
+
+    // Consensus signs each block with the time at which it was externalized,
+    // so a system clock which is earlier than that is definitely wrong.
+    let signed_at = match service.ledger_db.get_block_signature(latest_block_index) {
+        Ok(signature) => signature.signed_at(),
+        Err(mc_ledger_db::Error::NotFound) => {
+            return DiagnosticCheck::new(
+                "system_clock",
+                DiagnosticStatus::Skipped,
+                format!(
+                    "Block {} is not signed, so the system clock cannot be checked against it",
+                    latest_block_index
+                ),
+            )
+        }
+        Err(err) => {
+            return DiagnosticCheck::new(
+                "system_clock",
+                DiagnosticStatus::Failed,
+                format!("Could not read the latest block signature: {}", err),
+            )
+        }
+    };
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|now| now.as_secs())
+        .unwrap_or(0);
+    compare_system_clock(now, signed_at)
+}
+
+fn compare_system_clock(now: u64, latest_block_signed_at: u64) -> DiagnosticCheck {
+    if now.saturating_add(MAX_CLOCK_SKEW_SECS) < latest_block_signed_at {
+        DiagnosticCheck::new(
+            "system_clock",
+            DiagnosticStatus::Failed,
+            format!(
+                "The system clock is {} seconds behind the time at which the latest block was signed. Attestation with consensus will fail until the clock is corrected.",
+                latest_block_signed_at - now
+            ),
+        )
+    } else {
+        DiagnosticCheck::new(
+            "system_clock",
+            DiagnosticStatus::Passed,
+            "The system clock is not behind the latest block in the ledger",
+        )
+    }
+}
+
+fn check_peers<T, FPR>(service: &WalletService<T, FPR>) -> Vec<DiagnosticCheck>
+where
+    T: BlockchainConnection + UserTxConnection + 'static,
+    FPR: FogPubkeyResolver + Send + Sync + 'static,
+{
+    if service.offline {
+        return vec![DiagnosticCheck::new(
+            "peers",
+            DiagnosticStatus::Skipped,
+            "Running in offline mode",
+        )];
+    }
+
+    let responder_ids = service.peer_manager.responder_ids();
+    if responder_ids.is_empty() {
+        return vec![DiagnosticCheck::new(
+            "peers",
+            DiagnosticStatus::Failed,
+            "No peers are configured, use --peer or --validator",
+        )];
+    }
+
+    responder_ids
+        .iter()
+        .map(|responder_id| {
+            let name = format!("peer:{}", responder_id);
+            match service.peer_manager.conn(responder_id) {
+                Some(conn) => match conn.fetch_block_info(empty()) {
+                    Ok(block_info) => DiagnosticCheck::new(
+                        &name,
+                        DiagnosticStatus::Passed,
+                        format!("Reachable, at block index {}", block_info.block_index),
+                    ),
+                    Err(err) => DiagnosticCheck::new(
+                        &name,
+                        DiagnosticStatus::Failed,
+                        format!(
+                            "Could not reach or attest the peer, check the peer URI, network access and system clock: {}",
+                            err
+                        ),
+                    ),
+                },
+                None => DiagnosticCheck::new(
+                    &name,
+                    DiagnosticStatus::Failed,
+                    "No connection to the peer",
+                ),
+            }
+        })
+        .collect()
+}
+
+fn check_fog<T, FPR>(service: &WalletService<T, FPR>) -> Vec<DiagnosticCheck>
+where
+    T: BlockchainConnection + UserTxConnection + 'static,
+    FPR: FogPubkeyResolver + Send + Sync + 'static,
+{
+    let accounts = match service
+        .wallet_db
+        .get_conn()
+        .and_then(|conn| Account::list_all(&conn))
+    {
+        Ok(accounts) => accounts,
+        Err(err) => {
+            return vec![DiagnosticCheck::new(
+                "fog",
+                DiagnosticStatus::Failed,
+                format!("Could not list accounts: {}", err),
+            )]
+        }
+    };
+
+    let fog_report_urls: BTreeSet<String> = accounts
+        .iter()
+        .filter(|account| account.fog_enabled)
+        .filter_map(|account| mc_util_serial::decode::<AccountKey>(&account.account_key).ok())
+        .filter_map(|account_key| account_key.fog_report_url().map(|url| url.to_string()))
+        .collect();
+
+    fog_report_urls
+        .iter()
+        .map(|url| {
+            let name = format!("fog:{}", url);
+            let fog_uri = match FogUri::from_str(url) {
+                Ok(fog_uri) => fog_uri,
+                Err(err) => {
+                    return DiagnosticCheck::new(
+                        &name,
+                        DiagnosticStatus::Failed,
+                        format!("Invalid fog report url: {}", err),
+                    )
+                }
+            };
+            if service.offline {
+                return DiagnosticCheck::new(
+                    &name,
+                    DiagnosticStatus::Skipped,
+                    "Running in offline mode",
+                );
+            }
+            match (service.fog_resolver_factory)(&[fog_uri]) {
+                Ok(_) => DiagnosticCheck::new(
+                    &name,
+                    DiagnosticStatus::Passed,
+                    "Fetched and verified the fog report",
+                ),
+                Err(err) => DiagnosticCheck::new(
+                    &name,
+                    DiagnosticStatus::Failed,
+                    format!(
+                        "Could not fetch or verify the fog report, check --fog-ingest-enclave-css: {}",
+                        err
+                    ),
+                ),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{setup_wallet_service, setup_wallet_service_offline};
+    use mc_account_keys::PublicAddress;
+    use mc_common::logger::{test_with_logger, Logger};
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test_with_logger]
+    fn test_run_diagnostics(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let ledger_db = crate::test_utils::get_test_ledger(5, &known_recipients, 12, &mut rng);
+        let service = setup_wallet_service(ledger_db, logger);
+
+        let checks = service.run_diagnostics();
+        let status_of = |name: &str| {
+            checks
+                .iter()
+                .find(|c| c.name == name)
+                .unwrap_or_else(|| panic!("missing check {}", name))
+                .status
+        };
+        assert_eq!(status_of("wallet_db_writable"), DiagnosticStatus::Passed);
+        assert_eq!(status_of("wallet_db_schema"), DiagnosticStatus::Passed);
+        assert_eq!(status_of("ledger_db"), DiagnosticStatus::Passed);
+        // The test ledger's blocks are not signed.
+        assert_eq!(status_of("system_clock"), DiagnosticStatus::Skipped);
+    }
+
+    #[test]
+    fn test_compare_system_clock() {
+        let signed_at = 1_650_000_000;
+        assert_eq!(
+            compare_system_clock(signed_at + 3600, signed_at).status,
+            DiagnosticStatus::Passed
+        );
+        assert_eq!(
+            compare_system_clock(signed_at - 60, signed_at).status,
+            DiagnosticStatus::Passed
+        );
+
+        let check = compare_system_clock(signed_at - 3600, signed_at);
+        assert_eq!(check.status, DiagnosticStatus::Failed);
+        assert!(check.message.contains("3600 seconds behind"));
+    }
+
+    #[test_with_logger]
+    fn test_run_diagnostics_offline(logger: Logger) {
+        let ledger_db = crate::test_utils::get_empty_test_ledger();
+        let service = setup_wallet_service_offline(ledger_db, logger);
+
+        let checks = service.run_diagnostics();
+        let peers = checks.iter().find(|c| c.name == "peers").unwrap();
+        assert_eq!(peers.status, DiagnosticStatus::Skipped);
+
+        // The empty ledger has not been bootstrapped.
+        let ledger = checks.iter().find(|c| c.name == "ledger_db").unwrap();
+        assert_eq!(ledger.status, DiagnosticStatus::Failed);
+    }
+}
@@ -0,0 +1,339 @@
+// Copyright (c) 2020-2022 MobileCoin Inc.
+
+//! Service for resolving sent transactions which expired without landing.
+//!
+//! The sync thread marks a transaction failed when its inputs pass their
+//! tombstone block, but a transaction whose inputs were released some other
+//! way can be left pending forever. The scheduler checks the pending
+//! transactions against their tombstone blocks, failing those which can no
+//! longer land and releasing their inputs. If resubmission is enabled, each
+//! expired transaction is then rebuilt with a fresh tombstone block and
+//! submitted again, once.
+
+use crate::{
+    db::{
+        account::{AccountID, AccountModel},
+        models::{
+            Account, TransactionLog, WalletEvent, EVENT_TYPE_TRANSACTION_RESUBMITTED,
+            TX_STATUS_FAILED, TX_STATUS_PENDING,
+        },
+        transaction,
+        transaction_log::TransactionLogModel,
+        wallet_event::WalletEventModel,
+        Conn, WalletDbError,
+    },
+    service::{transaction::TransactionService, WalletService},
+};
+use chrono::Utc;
+use displaydoc::Display;
+use mc_common::logger::log;
+use mc_connection::{BlockchainConnection, UserTxConnection};
+use mc_fog_report_validation::FogPubkeyResolver;
+use mc_ledger_db::Ledger;
+use mc_transaction_core::tx::Tx;
+use serde_json::json;
+
+/// How long after it was sent an expired transaction may be resubmitted, so
+/// that enabling resubmission doesn't resend old payments which may since
+/// have been made some other way.
+pub const RESUBMIT_WINDOW_SECONDS: i64 = 24 * 60 * 60;
+
+/// Errors for the Expired Transaction Service.
+#[derive(Display, Debug)]
+#[allow(clippy::large_enum_variant)]
+pub enum ExpiredTransactionServiceError {
+    /// Error interacting with the database: {0}
+    Database(WalletDbError),
+
+    /// Error with LedgerDB: {0}
+    LedgerDB(mc_ledger_db::Error),
+
+    /// Error decoding the logged transaction: {0}
+    Decode(mc_util_serial::DecodeError),
+}
+
+impl From<WalletDbError> for ExpiredTransactionServiceError {
+    fn from(src: WalletDbError) -> Self {
+        Self::Database(src)
+    }
+}
+
+impl From<mc_ledger_db::Error> for ExpiredTransactionServiceError {
+    fn from(src: mc_ledger_db::Error) -> Self {
+        Self::LedgerDB(src)
+    }
+}
+
+impl From<mc_util_serial::DecodeError> for ExpiredTransactionServiceError {
+    fn from(src: mc_util_serial::DecodeError) -> Self {
+        Self::Decode(src)
+    }
+}
+
+/// Trait defining the ways in which the wallet can resolve expired
+/// transactions.
+pub trait ExpiredTransactionService {
+    /// Fail the pending transactions which can no longer land, and resubmit
+    /// the recently expired ones if `resubmit` is set.
+    fn resolve_expired_transactions(
+        &self,
+        resubmit: bool,
+    ) -> Result<(), ExpiredTransactionServiceError>;
+}
+
+impl<T, FPR> ExpiredTransactionService for WalletService<T, FPR>
+where
+    T: BlockchainConnection + UserTxConnection + 'static,
+    FPR: FogPubkeyResolver + Send + Sync + 'static,
+{
+    fn resolve_expired_transactions(
+        &self,
+        resubmit: bool,
+    ) -> Result<(), ExpiredTransactionServiceError> {
+        let conn = self.wallet_db.get_conn()?;
+
+        for transaction_log in
+            TransactionLog::list_sent_with_status(TX_STATUS_PENDING, None, &conn)?
+        {
+            if let Some(tombstone_block) = self.expired_tombstone_block(&transaction_log, &conn)? {
+                transaction(&conn, || {
+                    transaction_log.update_expired_to_failed(tombstone_block, &conn)
+                })?;
+                log::warn!(
+                    self.logger,
+                    "Transaction {} passed its tombstone block {} without landing",
+                    transaction_log.transaction_id_hex,
+                    tombstone_block,
+                );
+            }
+        }
+
+        if !resubmit {
+            return Ok(());
+        }
+
+        let sent_since = Utc::now().timestamp() - RESUBMIT_WINDOW_SECONDS;
+        for transaction_log in
+            TransactionLog::list_sent_with_status(TX_STATUS_FAILED, Some(sent_since), &conn)?
+        {
+            if transaction_log.resubmitted_transaction_id_hex.is_some()
+                || transaction_log.is_resubmission(&conn)?
+                || self
+                    .expired_tombstone_block(&transaction_log, &conn)?
+                    .is_none()
+            {
+                continue;
+            }
+
+            let outputs = transaction_log.get_associated_txos(&conn)?.outputs;
+            let token_id = match outputs.first() {
+                Some(output) => output.token_id as u64,
+                None => continue,
+            };
+            let addresses_and_values: Vec<(String, String)> = outputs
+                .iter()
+                .map(|txo| {
+                    (
+                        txo.recipient_public_address_b58.clone(),
+                        (txo.value as u64).to_string(),
+                    )
+                })
+                .collect();
+
+            // The original send was already confirmed if it was large.
+            let result = self.build_and_submit(
+                &transaction_log.account_id_hex,
+                &addresses_and_values,
+                None,
+                Some(token_id.to_string()),
+                None,
+                None,
+                None,
+                None,
+                Some(true),
+                None,
+                None,
+                None,
+                Some(transaction_log.comment.clone()),
+            );
+            match result {
+                Ok((resubmitted, _, _)) => {
+                    transaction(&conn, || {
+                        transaction_log
+                            .record_resubmission(&resubmitted.transaction_id_hex, &conn)?;
+                        WalletEvent::create(
+                            EVENT_TYPE_TRANSACTION_RESUBMITTED,
+                            &json!({
+                                "account_id": transaction_log.account_id_hex,
+                                "transaction_log_id": transaction_log.transaction_id_hex,
+                                "resubmitted_transaction_log_id": resubmitted.transaction_id_hex,
+                            }),
+                            &conn,
+                        )
+                    })?;
+                    log::info!(
+                        self.logger,
+                        "Resubmitted expired transaction {} as {}",
+                        transaction_log.transaction_id_hex,
+                        resubmitted.transaction_id_hex,
+                    );
+                }
+                // Tried again on the next check, until the window closes.
+                Err(err) => log::warn!(
+                    self.logger,
+                    "Could not resubmit expired transaction {}: {}",
+                    transaction_log.transaction_id_hex,
+                    err,
+                ),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<T, FPR> WalletService<T, FPR>
+where
+    T: BlockchainConnection + UserTxConnection + 'static,
+    FPR: FogPubkeyResolver + Send + Sync + 'static,
+{
+    /// The tombstone block of a logged transaction, if it has passed without
+    /// the transaction landing.
+    ///
+    /// The account must have scanned every block the transaction could have
+    /// landed in, so that one which landed is never taken for expired.
+    fn expired_tombstone_block(
+        &self,
+        transaction_log: &TransactionLog,
+        conn: &Conn,
+    ) -> Result<Option<u64>, ExpiredTransactionServiceError> {
+        let tx: Tx = match &transaction_log.tx {
+            Some(tx) => mc_util_serial::decode(tx)?,
+            None => return Ok(None),
+        };
+        let tombstone_block = tx.prefix.tombstone_block;
+        let account = Account::get(&AccountID(transaction_log.account_id_hex.clone()), conn)?;
+        if (account.next_block_index as u64) < tombstone_block {
+            return Ok(None);
+        }
+        for key_image in tx.key_images() {
+            if self.ledger_db.contains_key_image(&key_image)? {
+                return Ok(None);
+            }
+        }
+        Ok(Some(tombstone_block))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        db::{models::Txo, txo::TxoModel},
+        test_utils::{
+            add_block_with_tx_outs, get_test_ledger, manually_sync_account,
+            random_account_with_seed_values, setup_wallet_service, MOB,
+        },
+        util::b58::b58_encode_public_address,
+    };
+    use mc_account_keys::{AccountKey, PublicAddress};
+    use mc_common::logger::{test_with_logger, Logger};
+    use mc_crypto_rand::rand_core::RngCore;
+    use mc_transaction_core::ring_signature::KeyImage;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test_with_logger]
+    fn test_resolve_expired_transactions(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let mut ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+        let service = setup_wallet_service(ledger_db.clone(), logger.clone());
+        let account_key = random_account_with_seed_values(
+            &service.wallet_db,
+            &mut ledger_db,
+            &vec![100 * MOB],
+            &mut rng,
+            &logger,
+        );
+        let account_id = AccountID::from(&account_key);
+        let recipient = AccountKey::random(&mut rng).subaddress(0);
+        let recipient_b58 = b58_encode_public_address(&recipient).unwrap();
+
+        let conn = service.wallet_db.get_conn().unwrap();
+        let tx_proposal = service
+            .build_transaction(
+                &account_id.to_string(),
+                &[(recipient_b58.clone(), (10 * MOB).to_string())],
+                None,
+                None,
+                None,
+                None,
+                Some("15".to_string()),
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+            )
+            .unwrap();
+        let expired = TransactionLog::log_submitted(
+            tx_proposal,
+            13,
+            "rent".to_string(),
+            &account_id.to_string(),
+            &conn,
+        )
+        .unwrap();
+
+        // Nothing expires until the account has scanned past the tombstone.
+        service.resolve_expired_transactions(true).unwrap();
+        let pending = TransactionLog::get(&expired.transaction_id_hex, &conn).unwrap();
+        assert_eq!(pending.status, TX_STATUS_PENDING);
+
+        // Pass the tombstone block, then put the log and its inputs back the way
+        // they are left when another account's sync releases the inputs first.
+        for _ in 0..3 {
+            add_block_with_tx_outs(&mut ledger_db, &[], &[KeyImage::from(rng.next_u64())]);
+        }
+        manually_sync_account(&ledger_db, &service.wallet_db, &account_id, &logger);
+        let inputs = expired.get_associated_txos(&conn).unwrap().inputs;
+        for input in &inputs {
+            input.update_to_pending(15, &conn).unwrap();
+        }
+        pending
+            .update_to_submitted(13, "rent".to_string(), &conn)
+            .unwrap();
+
+        service.resolve_expired_transactions(false).unwrap();
+        let failed = TransactionLog::get(&expired.transaction_id_hex, &conn).unwrap();
+        assert_eq!(failed.status, TX_STATUS_FAILED);
+        assert_eq!(failed.resubmitted_transaction_id_hex, None);
+        for input in &inputs {
+            let input = Txo::get(&input.txo_id_hex, &conn).unwrap();
+            assert_eq!(input.pending_tombstone_block_index, None);
+        }
+
+        // The same payment is resubmitted with a fresh tombstone, once.
+        service.resolve_expired_transactions(true).unwrap();
+        let failed = TransactionLog::get(&expired.transaction_id_hex, &conn).unwrap();
+        let resubmitted_id = failed.resubmitted_transaction_id_hex.unwrap();
+        let resubmitted = TransactionLog::get(&resubmitted_id, &conn).unwrap();
+        assert_eq!(resubmitted.status, TX_STATUS_PENDING);
+        assert_eq!(resubmitted.comment, "rent");
+        assert!(resubmitted.is_resubmission(&conn).unwrap());
+        let outputs = resubmitted.get_associated_txos(&conn).unwrap().outputs;
+        assert_eq!(outputs.len(), 1);
+        assert_eq!(outputs[0].recipient_public_address_b58, recipient_b58);
+        assert_eq!(outputs[0].value as u64, 10 * MOB);
+
+        service.resolve_expired_transactions(true).unwrap();
+        assert_eq!(
+            TransactionLog::list_sent_with_status(TX_STATUS_PENDING, None, &conn)
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+}
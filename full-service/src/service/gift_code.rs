@@ -10,9 +10,11 @@
 use crate::{
     db::{
         account::{AccountID, AccountModel},
-        gift_code::GiftCodeModel,
+        gift_code::{GiftCodeDbError, GiftCodeModel},
         models::{Account, GiftCode},
-        transaction, WalletDbError,
+        transaction,
+        txo::TxoID,
+        WalletDbError,
     },
     service::{
         account::AccountServiceError,
@@ -27,6 +29,7 @@ use crate::{
     },
 };
 use bip39::{Language, Mnemonic, MnemonicType};
+use chrono::Utc;
 use displaydoc::Display;
 use mc_account_keys::{AccountKey, DEFAULT_SUBADDRESS_INDEX};
 use mc_account_keys_slip10::Slip10KeyGenerator;
@@ -150,6 +153,12 @@ pub enum GiftCodeServiceError {
 
     /// Invalid Fog Uri: {0}
     InvalidFogUri(String),
+
+    /// Spending has been disabled on this wallet
+    SpendingDisabled,
+
+    /// A claim link must expire at least one second after it is created
+    InvalidExpiry,
 }
 
 impl From<WalletDbError> for GiftCodeServiceError {
@@ -259,6 +268,12 @@ pub struct DecodedGiftCode {
     pub txo_public_key: Vec<u8>,
     pub value: u64,
     pub memo: String,
+    pub funding_account_id: Option<String>,
+    pub claimer_account_id: Option<String>,
+    pub claim_transaction_log_id: Option<String>,
+    pub claimed_time: Option<i64>,
+    pub expiry_time: Option<i64>,
+    pub expired: bool,
 }
 
 impl TryFrom<GiftCode> for DecodedGiftCode {
@@ -275,6 +290,12 @@ impl TryFrom<GiftCode> for DecodedGiftCode {
             txo_public_key: mc_util_serial::encode(&transfer_payload.txo_public_key),
             value: src.value as u64,
             memo: transfer_payload.memo,
+            funding_account_id: src.funding_account_id_hex,
+            claimer_account_id: src.claimer_account_id_hex,
+            claim_transaction_log_id: src.claim_transaction_log_id,
+            claimed_time: src.claimed_time,
+            expiry_time: src.expiry_time,
+            expired: src.expired,
         })
     }
 }
@@ -327,6 +348,31 @@ pub trait GiftCodeService {
         tx_proposal: &TxProposal,
     ) -> Result<DecodedGiftCode, GiftCodeServiceError>;
 
+    /// Builds and submits a gift code to be shared as a single-use claim
+    /// link, for paying someone whose address isn't known.
+    ///
+    /// If the link hasn't been claimed `expiry_seconds` from now, the
+    /// scheduler sweeps its funds back to the funding account.
+    ///
+    /// Returns:
+    /// * The gift code, as saved to the wallet
+    /// * The submitted transaction funding the gift code
+    fn create_claim_link(
+        &self,
+        from_account_id: &AccountID,
+        value: u64,
+        memo: Option<String>,
+        expiry_seconds: u64,
+        fee: Option<u64>,
+    ) -> Result<(DecodedGiftCode, TxProposal), GiftCodeServiceError>;
+
+    /// Sweep every claim link which expired unclaimed back to the account
+    /// which funded it.
+    ///
+    /// A link whose funding transaction has yet to land is left to a later
+    /// call.
+    fn reclaim_expired_gift_codes(&self) -> Result<(), GiftCodeServiceError>;
+
     /// Get the details for a specific gift code.
     fn get_gift_code(
         &self,
@@ -406,10 +452,16 @@ where
             &from_account.account_id_hex,
             &[(gift_code_account_main_subaddress_b58, value.to_string())],
             input_txo_ids,
+            Some(Mob::ID.to_string()),
             fee.map(|f| f.to_string()),
+            None,
             tombstone_block.map(|t| t.to_string()),
             max_spendable_value.map(|f| f.to_string()),
             None,
+            None,
+            None,
+            None,
+            None,
         )?;
 
         if tx_proposal.outlay_index_to_tx_out_index.len() != 1 {
@@ -448,7 +500,9 @@ where
 
         // Save the gift code to the database before attempting to send it out.
         let conn = self.wallet_db.get_conn()?;
-        let gift_code = transaction(&conn, || GiftCode::create(gift_code_b58, value, &conn))?;
+        let gift_code = transaction(&conn, || {
+            GiftCode::create(gift_code_b58, value, Some(&from_account_id.0), None, &conn)
+        })?;
 
         self.submit_transaction(
             tx_proposal.clone(),
@@ -463,9 +517,126 @@ where
             txo_public_key: mc_util_serial::encode(&transfer_payload.txo_public_key),
             value: tx_proposal.outlays[0].value,
             memo: transfer_payload.memo,
+            funding_account_id: gift_code.funding_account_id_hex,
+            claimer_account_id: None,
+            claim_transaction_log_id: None,
+            claimed_time: None,
+            expiry_time: None,
+            expired: false,
         })
     }
 
+    fn create_claim_link(
+        &self,
+        from_account_id: &AccountID,
+        value: u64,
+        memo: Option<String>,
+        expiry_seconds: u64,
+        fee: Option<u64>,
+    ) -> Result<(DecodedGiftCode, TxProposal), GiftCodeServiceError> {
+        let expiry_time = match i64::try_from(expiry_seconds) {
+            Ok(expiry_seconds) if expiry_seconds > 0 => {
+                Utc::now().timestamp().saturating_add(expiry_seconds)
+            }
+            _ => return Err(GiftCodeServiceError::InvalidExpiry),
+        };
+
+        let (tx_proposal, gift_code_b58) =
+            self.build_gift_code(from_account_id, value, memo, None, fee, None, None)?;
+        let transfer_payload = decode_transfer_payload(&gift_code_b58)?;
+
+        // Save the claim link to the database before attempting to send it out,
+        // so that it is swept back even if the wallet stops in between.
+        let conn = self.wallet_db.get_conn()?;
+        let gift_code = transaction(&conn, || {
+            GiftCode::create(
+                &gift_code_b58,
+                tx_proposal.outlays[0].value as i64,
+                Some(&from_account_id.0),
+                Some(expiry_time),
+                &conn,
+            )
+        })?;
+
+        self.submit_transaction(
+            tx_proposal.clone(),
+            Some(json!({"gift_code_memo": transfer_payload.memo}).to_string()),
+            Some(from_account_id.to_string()),
+        )?;
+
+        log::info!(
+            self.logger,
+            "Created claim link for {} from account {}, expiring at {}",
+            tx_proposal.outlays[0].value,
+            from_account_id,
+            expiry_time,
+        );
+
+        Ok((DecodedGiftCode::try_from(gift_code)?, tx_proposal))
+    }
+
+    fn reclaim_expired_gift_codes(&self) -> Result<(), GiftCodeServiceError> {
+        if self.spending_lock.is_disabled() {
+            return Ok(());
+        }
+
+        let conn = self.wallet_db.get_conn()?;
+        for gift_code in GiftCode::list_expired(Utc::now().timestamp(), &conn)? {
+            let gift_code_b58 = EncodedGiftCode(gift_code.gift_code_b58.clone());
+            let funding_account_id =
+                AccountID(gift_code.funding_account_id_hex.clone().unwrap_or_default());
+
+            match Account::get(&funding_account_id, &conn) {
+                Ok(_) => {}
+                Err(WalletDbError::AccountNotFound(_)) => {
+                    log::warn!(
+                        self.logger,
+                        "Not sweeping back expired claim link, as its funding account {} has been removed",
+                        funding_account_id,
+                    );
+                    transaction(&conn, || gift_code.mark_expired(&conn))?;
+                    continue;
+                }
+                Err(e) => return Err(e.into()),
+            }
+
+            let (status, _value, _memo) = self.check_gift_code_status(&gift_code_b58)?;
+            match status {
+                GiftCodeStatus::GiftCodeSubmittedPending => continue,
+                GiftCodeStatus::GiftCodeClaimed => {
+                    log::info!(
+                        self.logger,
+                        "Claim link funded by account {} was claimed before it expired",
+                        funding_account_id,
+                    );
+                    transaction(&conn, || gift_code.mark_expired(&conn))?;
+                }
+                GiftCodeStatus::GiftCodeAvailable => {
+                    // A failed sweep is tried again on the next call.
+                    match self.claim_gift_code(&gift_code_b58, &funding_account_id, None) {
+                        Ok(_) => {
+                            log::info!(
+                                self.logger,
+                                "Swept expired claim link for {} back to account {}",
+                                gift_code.value,
+                                funding_account_id,
+                            );
+                            transaction(&conn, || gift_code.mark_expired(&conn))?;
+                        }
+                        Err(e) => log::warn!(
+                            self.logger,
+                            "Error sweeping expired claim link back to account {}: {}",
+                            funding_account_id,
+                            e,
+                        ),
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
     fn get_gift_code(
         &self,
         gift_code_b58: &EncodedGiftCode,
@@ -555,6 +726,10 @@ where
         account_id: &AccountID,
         assigned_subaddress_b58: Option<String>,
     ) -> Result<Tx, GiftCodeServiceError> {
+        if self.spending_lock.is_disabled() {
+            return Err(GiftCodeServiceError::SpendingDisabled);
+        }
+
         let (status, gift_value, _memo) = self.check_gift_code_status(gift_code_b58)?;
 
         match status {
@@ -650,7 +825,6 @@ where
         )?;
 
         // Create transaction builder.
-        // TODO: After servers that support memos are deployed, use RTHMemoBuilder here
         let mut memo_builder = RTHMemoBuilder::default();
         memo_builder.set_sender_credential(SenderMemoCredential::from(&gift_account_key));
         memo_builder.enable_destination_memo();
@@ -691,6 +865,21 @@ where
             block_index
         );
 
+        // If this wallet made the gift code, attribute the claim to the
+        // claiming account. The claimed txo's id becomes the id of the
+        // transaction log the account records on receiving it.
+        let conn = self.wallet_db.get_conn()?;
+        transaction(&conn, || match GiftCode::get(gift_code_b58, &conn) {
+            Ok(gift_code) => gift_code.record_claim(
+                &account_id.to_string(),
+                &TxoID::from(&tx.prefix.outputs[0]).to_string(),
+                Utc::now().timestamp(),
+                &conn,
+            ),
+            Err(WalletDbError::GiftCode(GiftCodeDbError::GiftCodeNotFound(_))) => Ok(()),
+            Err(e) => Err(e),
+        })?;
+
         Ok(tx)
     }
 
@@ -715,6 +904,7 @@ pub fn decode_transfer_payload(
 mod tests {
     use super::*;
     use crate::{
+        db::{models::TransactionLog, transaction_log::TransactionLogModel},
         service::{account::AccountService, balance::BalanceService},
         test_utils::{
             add_block_to_ledger_db, add_block_with_tx, add_block_with_tx_proposal, get_test_ledger,
@@ -763,7 +953,7 @@ mod tests {
 
         // Verify balance for Alice
         let balance = service
-            .get_balance_for_account(&AccountID(alice.account_id_hex.clone()))
+            .get_balance_for_account(&AccountID(alice.account_id_hex.clone()), false)
             .unwrap();
         assert_eq!(balance.unspent, 100 * MOB as u128);
 
@@ -824,7 +1014,7 @@ mod tests {
 
         // Verify balance for Alice = original balance - fee - gift_code_value
         let balance = service
-            .get_balance_for_account(&AccountID(alice.account_id_hex.clone()))
+            .get_balance_for_account(&AccountID(alice.account_id_hex.clone()), false)
             .unwrap();
         assert_eq!(balance.unspent, (98 * MOB - Mob::MINIMUM_FEE) as u128);
 
@@ -891,9 +1081,32 @@ mod tests {
 
         // Bob's balance should be = gift code value - fee (10000000000)
         let bob_balance = service
-            .get_balance_for_account(&AccountID(bob.account_id_hex))
+            .get_balance_for_account(&AccountID(bob.account_id_hex.clone()), false)
             .unwrap();
-        assert_eq!(bob_balance.unspent, (2 * MOB - Mob::MINIMUM_FEE) as u128)
+        assert_eq!(bob_balance.unspent, (2 * MOB - Mob::MINIMUM_FEE) as u128);
+
+        // The gift code records who funded it and who claimed it, and links to
+        // the log of the claimed funds in Bob's account.
+        let claimed_gift_code = service.get_gift_code(&gift_code_b58).unwrap();
+        assert_eq!(
+            claimed_gift_code.funding_account_id,
+            Some(alice.account_id_hex.clone())
+        );
+        assert_eq!(
+            claimed_gift_code.claimer_account_id,
+            Some(bob.account_id_hex.clone())
+        );
+        assert!(claimed_gift_code.claimed_time.is_some());
+        let claim_transaction_log = TransactionLog::get(
+            &claimed_gift_code.claim_transaction_log_id.unwrap(),
+            &service.wallet_db.get_conn().unwrap(),
+        )
+        .unwrap();
+        assert_eq!(claim_transaction_log.account_id_hex, bob.account_id_hex);
+        assert_eq!(
+            claim_transaction_log.value as u64,
+            2 * MOB - Mob::MINIMUM_FEE
+        );
     }
 
     #[test_with_logger]
@@ -932,7 +1145,7 @@ mod tests {
 
         // Verify balance for Alice
         let balance = service
-            .get_balance_for_account(&AccountID(alice.account_id_hex.clone()))
+            .get_balance_for_account(&AccountID(alice.account_id_hex.clone()), false)
             .unwrap();
         assert_eq!(balance.unspent, 100 * MOB as u128);
 
@@ -991,4 +1204,84 @@ mod tests {
             .expect("Could not list gift codes");
         assert_eq!(gift_codes.len(), 0);
     }
+
+    #[test_with_logger]
+    fn test_reclaim_expired_claim_link(logger: Logger) {
+        use crate::db::schema::gift_codes;
+        use diesel::prelude::*;
+
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let mut ledger_db = get_test_ledger(5, &vec![], 12, &mut rng);
+        let service = setup_wallet_service(ledger_db.clone(), logger.clone());
+        let conn = service.wallet_db.get_conn().unwrap();
+
+        let alice = service
+            .create_account(
+                Some("Alice's Main Account".to_string()),
+                "".to_string(),
+                "".to_string(),
+                "".to_string(),
+            )
+            .unwrap();
+        let alice_account_key: AccountKey = mc_util_serial::decode(&alice.account_key).unwrap();
+        let alice_account_id = AccountID(alice.account_id_hex.clone());
+
+        add_block_to_ledger_db(
+            &mut ledger_db,
+            &vec![alice_account_key.subaddress(alice.main_subaddress_index as u64)],
+            100 * MOB as u64,
+            &vec![KeyImage::from(rng.next_u64())],
+            &mut rng,
+        );
+        manually_sync_account(&ledger_db, &service.wallet_db, &alice_account_id, &logger);
+
+        match service.create_claim_link(&alice_account_id, 2 * MOB as u64, None, 0, None) {
+            Err(GiftCodeServiceError::InvalidExpiry) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+
+        let (claim_link, tx_proposal) = service
+            .create_claim_link(
+                &alice_account_id,
+                2 * MOB as u64,
+                Some("For whoever gets here first".to_string()),
+                3600,
+                None,
+            )
+            .unwrap();
+        assert!(claim_link.expiry_time.unwrap() > Utc::now().timestamp());
+        assert!(!claim_link.expired);
+        assert_eq!(
+            claim_link.funding_account_id,
+            Some(alice.account_id_hex.clone())
+        );
+        let gift_code_b58 = EncodedGiftCode(claim_link.gift_code_b58.clone());
+
+        // A link is left alone until it expires.
+        service.reclaim_expired_gift_codes().unwrap();
+        assert_eq!(service.get_gift_code(&gift_code_b58).unwrap(), claim_link);
+
+        diesel::update(gift_codes::table)
+            .set(gift_codes::expiry_time.eq(Utc::now().timestamp() - 1))
+            .execute(&conn)
+            .unwrap();
+
+        // An expired link isn't swept back until its funding transaction has
+        // landed.
+        service.reclaim_expired_gift_codes().unwrap();
+        assert!(!service.get_gift_code(&gift_code_b58).unwrap().expired);
+
+        add_block_with_tx_proposal(&mut ledger_db, tx_proposal);
+        manually_sync_account(&ledger_db, &service.wallet_db, &alice_account_id, &logger);
+
+        service.reclaim_expired_gift_codes().unwrap();
+        let reclaimed = service.get_gift_code(&gift_code_b58).unwrap();
+        assert!(reclaimed.expired);
+        assert_eq!(
+            reclaimed.claimer_account_id,
+            Some(alice.account_id_hex.clone())
+        );
+        assert!(reclaimed.claimed_time.is_some());
+    }
 }
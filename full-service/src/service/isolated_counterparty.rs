@@ -0,0 +1,271 @@
+// Copyright (c) 2020-2022 MobileCoin Inc.
+
+//! Service for isolating the funds received from particular counterparties.
+//!
+//! Spending txos together links them on chain, so paying one business
+//! relationship with funds received from another can reveal that both deal
+//! with the same wallet. Payments received with a sender memo from an isolated
+//! counterparty are kept out of the account's general pool when inputs are
+//! selected, and are only ever spent together with other payments from the
+//! same counterparty. Listing the inputs of a transaction explicitly spends
+//! them however they were received.
+
+use crate::{
+    db::{
+        account::{AccountID, AccountModel},
+        isolated_counterparty::IsolatedCounterpartyModel,
+        models::{Account, IsolatedCounterparty},
+        transaction, WalletDbError,
+    },
+    service::WalletService,
+    util::b58::{b58_decode_public_address, B58Error},
+};
+use chrono::Utc;
+use displaydoc::Display;
+use mc_account_keys::ShortAddressHash;
+use mc_connection::{BlockchainConnection, UserTxConnection};
+use mc_fog_report_validation::FogPubkeyResolver;
+
+/// Errors for the Isolated Counterparty Service.
+#[derive(Display, Debug)]
+#[allow(clippy::large_enum_variant)]
+pub enum IsolatedCounterpartyServiceError {
+    /// Error interacting with the database: {0}
+    Database(WalletDbError),
+
+    /// Diesel error: {0}
+    Diesel(diesel::result::Error),
+
+    /// Error decoding the counterparty's address: {0}
+    B58(B58Error),
+}
+
+impl From<WalletDbError> for IsolatedCounterpartyServiceError {
+    fn from(src: WalletDbError) -> Self {
+        Self::Database(src)
+    }
+}
+
+impl From<diesel::result::Error> for IsolatedCounterpartyServiceError {
+    fn from(src: diesel::result::Error) -> Self {
+        Self::Diesel(src)
+    }
+}
+
+impl From<B58Error> for IsolatedCounterpartyServiceError {
+    fn from(src: B58Error) -> Self {
+        Self::B58(src)
+    }
+}
+
+/// Trait defining the ways in which the wallet can isolate counterparties'
+/// funds.
+pub trait IsolatedCounterpartyService {
+    /// Stop spending the counterparty's payments to the account together with
+    /// its other funds.
+    ///
+    /// The counterparty is either the hex-encoded address hash from its sender
+    /// memos, or its b58-encoded public address.
+    fn isolate_counterparty(
+        &self,
+        account_id: &AccountID,
+        counterparty: &str,
+    ) -> Result<IsolatedCounterparty, IsolatedCounterpartyServiceError>;
+
+    /// Return the counterparty's payments to the account's general pool.
+    fn remove_isolated_counterparty(
+        &self,
+        account_id: &AccountID,
+        counterparty: &str,
+    ) -> Result<bool, IsolatedCounterpartyServiceError>;
+
+    /// List the counterparties isolated for the account.
+    fn list_isolated_counterparties(
+        &self,
+        account_id: &AccountID,
+    ) -> Result<Vec<IsolatedCounterparty>, IsolatedCounterpartyServiceError>;
+}
+
+impl<T, FPR> IsolatedCounterpartyService for WalletService<T, FPR>
+where
+    T: BlockchainConnection + UserTxConnection + 'static,
+    FPR: FogPubkeyResolver + Send + Sync + 'static,
+{
+    fn isolate_counterparty(
+        &self,
+        account_id: &AccountID,
+        counterparty: &str,
+    ) -> Result<IsolatedCounterparty, IsolatedCounterpartyServiceError> {
+        let address_hash = counterparty_address_hash(counterparty)?;
+        let conn = self.wallet_db.get_conn()?;
+        transaction(&conn, || {
+            Account::get(account_id, &conn)?;
+            Ok(IsolatedCounterparty::create(
+                &account_id.to_string(),
+                &address_hash,
+                Utc::now().timestamp(),
+                &conn,
+            )?)
+        })
+    }
+
+    fn remove_isolated_counterparty(
+        &self,
+        account_id: &AccountID,
+        counterparty: &str,
+    ) -> Result<bool, IsolatedCounterpartyServiceError> {
+        let address_hash = counterparty_address_hash(counterparty)?;
+        let conn = self.wallet_db.get_conn()?;
+        transaction(&conn, || {
+            Account::get(account_id, &conn)?;
+            IsolatedCounterparty::delete(&account_id.to_string(), &address_hash, &conn)?;
+            Ok(true)
+        })
+    }
+
+    fn list_isolated_counterparties(
+        &self,
+        account_id: &AccountID,
+    ) -> Result<Vec<IsolatedCounterparty>, IsolatedCounterpartyServiceError> {
+        let conn = self.wallet_db.get_conn()?;
+        Account::get(account_id, &conn)?;
+        Ok(IsolatedCounterparty::list_for_account(
+            &account_id.to_string(),
+            &conn,
+        )?)
+    }
+}
+
+/// The hex-encoded address hash a counterparty writes to its sender memos,
+/// given either the hash itself or the counterparty's b58-encoded address.
+pub fn counterparty_address_hash(counterparty: &str) -> Result<String, B58Error> {
+    if counterparty.len() == 32 {
+        if let Ok(address_hash) = hex::decode(counterparty) {
+            return Ok(hex::encode(address_hash));
+        }
+    }
+    let address = b58_decode_public_address(counterparty)?;
+    let address_hash: [u8; 16] = ShortAddressHash::from(&address).into();
+    Ok(hex::encode(address_hash))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        db::{
+            models::Txo,
+            txo::{TxoID, TxoModel},
+        },
+        service::transaction::TransactionService,
+        test_utils::{get_test_ledger, random_account_with_seed_values, setup_wallet_service, MOB},
+        util::b58::b58_encode_public_address,
+    };
+    use mc_account_keys::{AccountKey, PublicAddress};
+    use mc_common::logger::{test_with_logger, Logger};
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test_with_logger]
+    fn test_isolated_counterparty_txos_are_not_co_spent(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let mut ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+        let service = setup_wallet_service(ledger_db.clone(), logger.clone());
+        let account_key = random_account_with_seed_values(
+            &service.wallet_db,
+            &mut ledger_db,
+            &vec![70 * MOB, 50 * MOB, 40 * MOB],
+            &mut rng,
+            &logger,
+        );
+        let account_id = AccountID::from(&account_key);
+
+        // Attribute the two largest txos to a counterparty, as if they had been
+        // received with its sender memos.
+        let counterparty = AccountKey::random(&mut rng).default_subaddress();
+        let counterparty_b58 = b58_encode_public_address(&counterparty).unwrap();
+        let address_hash = counterparty_address_hash(&counterparty_b58).unwrap();
+        assert_eq!(
+            counterparty_address_hash(&address_hash.to_uppercase()).unwrap(),
+            address_hash
+        );
+        let conn = service.wallet_db.get_conn().unwrap();
+        let txos =
+            Txo::list_for_account(&account_id.to_string(), None, None, Some(0), &conn).unwrap();
+        let isolated_txo_ids: Vec<String> = txos
+            .iter()
+            .filter(|txo| txo.value as u64 >= 50 * MOB)
+            .map(|txo| txo.txo_id_hex.clone())
+            .collect();
+        assert_eq!(isolated_txo_ids.len(), 2);
+        for txo_id in &isolated_txo_ids {
+            Txo::update_memo_address_hashes(txo_id, Some(&address_hash), None, &conn).unwrap();
+        }
+
+        service
+            .isolate_counterparty(&account_id, &counterparty_b58)
+            .unwrap();
+        service
+            .isolate_counterparty(&account_id, &address_hash)
+            .unwrap();
+        let isolated = service.list_isolated_counterparties(&account_id).unwrap();
+        assert_eq!(isolated.len(), 1);
+        assert_eq!(isolated[0].address_hash, address_hash);
+
+        let recipient = AccountKey::random(&mut rng).subaddress(0);
+        let recipient_b58 = b58_encode_public_address(&recipient).unwrap();
+        let build = |value: u64| {
+            service.build_transaction(
+                &account_id.to_string(),
+                &[(recipient_b58.clone(), value.to_string())],
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+            )
+        };
+        let input_ids = |value: u64| -> Vec<String> {
+            let tx_proposal = build(value).unwrap();
+            let mut ids: Vec<String> = tx_proposal
+                .utxos
+                .iter()
+                .map(|utxo| TxoID::from(&utxo.tx_out).to_string())
+                .collect();
+            ids.sort();
+            ids
+        };
+
+        // Small payments come from the general pool, even though the isolated
+        // txos are larger.
+        let general = input_ids(10 * MOB);
+        assert_eq!(general.len(), 1);
+        assert!(!isolated_txo_ids.contains(&general[0]));
+
+        // Larger payments are made from the counterparty's txos alone, rather
+        // than mixing them with the general pool.
+        let mut expected = isolated_txo_ids.clone();
+        expected.sort();
+        assert_eq!(input_ids(100 * MOB), expected);
+
+        // Only mixing the two could pay this much.
+        assert!(build(150 * MOB).is_err());
+
+        // Once the counterparty is no longer isolated, its funds are pooled.
+        assert!(service
+            .remove_isolated_counterparty(&account_id, &counterparty_b58)
+            .unwrap());
+        assert!(service
+            .list_isolated_counterparties(&account_id)
+            .unwrap()
+            .is_empty());
+        assert_eq!(input_ids(150 * MOB).len(), 3);
+    }
+}
@@ -0,0 +1,131 @@
+// Copyright (c) 2020-2022 MobileCoin Inc.
+
+//! Guardrails against sending unusually large amounts.
+//!
+//! A unit conversion bug in an integration can turn a send of 5 MOB into one
+//! of 5 million. Sends above the warn threshold are logged, and sends above
+//! the deny threshold are rejected unless the caller confirms them with
+//! `confirm_large_send`. Each threshold is either an absolute value, in the
+//! smallest unit of the token being sent, or a percentage of the account's
+//! unspent balance in that token.
+
+use std::str::FromStr;
+
+/// The value above which a send is considered large.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LargeSendThreshold {
+    /// An absolute value, in the smallest unit of the token being sent.
+    Value(u64),
+
+    /// A whole number percentage of the account's unspent balance.
+    PercentOfBalance(u64),
+}
+
+impl LargeSendThreshold {
+    /// Whether sending the given value, from an account with the given unspent
+    /// balance, exceeds this threshold.
+    pub fn is_exceeded_by(&self, value: u128, balance: u128) -> bool {
+        match self {
+            Self::Value(threshold) => value > *threshold as u128,
+            Self::PercentOfBalance(percent) => {
+                value.saturating_mul(100) > balance.saturating_mul(*percent as u128)
+            }
+        }
+    }
+}
+
+/// Parses either a value, e.g. "5000000000000", or a percentage of the
+/// balance, e.g. "50%".
+impl FromStr for LargeSendThreshold {
+    type Err = String;
+
+    fn from_str(src: &str) -> Result<Self, String> {
+        if let Some(percent) = src.strip_suffix('%') {
+            let percent = percent
+                .trim()
+                .parse()
+                .map_err(|err| format!("Invalid large send percentage '{}': {}", src, err))?;
+            Ok(Self::PercentOfBalance(percent))
+        } else {
+            let value = src
+                .parse()
+                .map_err(|err| format!("Invalid large send value '{}': {}", src, err))?;
+            Ok(Self::Value(value))
+        }
+    }
+}
+
+/// The configured thresholds for large sends. Both are off by default.
+#[derive(Clone, Debug, Default)]
+pub struct LargeSendGuard {
+    /// Sends above this threshold are logged.
+    pub warn_threshold: Option<LargeSendThreshold>,
+
+    /// Sends above this threshold must be confirmed.
+    pub deny_threshold: Option<LargeSendThreshold>,
+}
+
+impl LargeSendGuard {
+    pub fn new(
+        warn_threshold: Option<LargeSendThreshold>,
+        deny_threshold: Option<LargeSendThreshold>,
+    ) -> Self {
+        Self {
+            warn_threshold,
+            deny_threshold,
+        }
+    }
+
+    /// Whether any threshold is configured.
+    pub fn is_enabled(&self) -> bool {
+        self.warn_threshold.is_some() || self.deny_threshold.is_some()
+    }
+
+    /// Whether the send should be logged as large.
+    pub fn warns(&self, value: u128, balance: u128) -> bool {
+        self.warn_threshold
+            .map_or(false, |threshold| threshold.is_exceeded_by(value, balance))
+    }
+
+    /// Whether the send must be confirmed.
+    pub fn denies(&self, value: u128, balance: u128) -> bool {
+        self.deny_threshold
+            .map_or(false, |threshold| threshold.is_exceeded_by(value, balance))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_threshold() {
+        assert_eq!(
+            "5000".parse::<LargeSendThreshold>().unwrap(),
+            LargeSendThreshold::Value(5000)
+        );
+        assert_eq!(
+            "50%".parse::<LargeSendThreshold>().unwrap(),
+            LargeSendThreshold::PercentOfBalance(50)
+        );
+        assert!("5.5%".parse::<LargeSendThreshold>().is_err());
+        assert!("lots".parse::<LargeSendThreshold>().is_err());
+    }
+
+    #[test]
+    fn test_thresholds() {
+        let guard = LargeSendGuard::new(
+            Some(LargeSendThreshold::PercentOfBalance(50)),
+            Some(LargeSendThreshold::Value(1000)),
+        );
+        assert!(guard.is_enabled());
+
+        assert!(!guard.warns(500, 1000));
+        assert!(guard.warns(501, 1000));
+        assert!(!guard.denies(1000, 1_000_000));
+        assert!(guard.denies(1001, 1_000_000));
+
+        assert!(!LargeSendGuard::default().is_enabled());
+        assert!(!LargeSendGuard::default().denies(u128::MAX, 0));
+    }
+}
@@ -4,12 +4,15 @@
 
 use crate::{
     db::{
-        models::{TransactionLog, Txo},
+        models::{QuarantinedBlock, TransactionLog, Txo},
+        quarantined_block::QuarantinedBlockModel,
+        transaction,
         transaction_log::TransactionLogModel,
         txo::TxoModel,
     },
     WalletService,
 };
+use mc_common::logger::log;
 use mc_connection::{BlockchainConnection, RetryableBlockchainConnection, UserTxConnection};
 use mc_fog_report_validation::FogPubkeyResolver;
 use mc_ledger_db::Ledger;
@@ -18,13 +21,13 @@ use mc_transaction_core::{
     ring_signature::KeyImage,
     tokens::Mob,
     tx::{Tx, TxOut},
-    Block, BlockContents, BlockVersion, Token,
+    Block, BlockContents, BlockVersion, Token, TokenId,
 };
 
 use crate::db::WalletDbError;
 use displaydoc::Display;
 use rayon::prelude::*; // For par_iter
-use std::{convert::TryFrom, iter::empty};
+use std::{collections::BTreeMap, convert::TryFrom, iter::empty};
 
 /// Errors for the Address Service.
 #[derive(Display, Debug)]
@@ -63,12 +66,30 @@ impl From<WalletDbError> for LedgerServiceError {
     }
 }
 
+/// The highest block count which a majority of the peers have reached.
+///
+/// A single lagging peer would make the network look behind if its height
+/// were taken, and a single peer ahead of the rest would make the wallet look
+/// behind a network which has not agreed on those blocks yet.
+pub fn quorum_block_height(peer_block_heights: &[u64]) -> u64 {
+    let mut heights = peer_block_heights.to_vec();
+    heights.sort_unstable_by(|a, b| b.cmp(a));
+    heights.get(heights.len() / 2).cloned().unwrap_or(0)
+}
+
 /// Trait defining the ways in which the wallet can interact with and manage
 /// ledger objects and interfaces.
 pub trait LedgerService {
-    /// Get the total number of blocks on the ledger.
+    /// Get the total number of blocks on the ledger, as reported by a
+    /// majority of the peers which have answered.
     fn get_network_block_height(&self) -> Result<u64, LedgerServiceError>;
 
+    /// Get the highest number of blocks reported by any peer.
+    fn get_network_block_height_max(&self) -> u64;
+
+    /// Get the number of blocks reported by each peer which has answered.
+    fn get_peer_block_heights(&self) -> BTreeMap<String, u64>;
+
     fn get_transaction_object(&self, transaction_id_hex: &str) -> Result<Tx, LedgerServiceError>;
 
     fn get_txo_object(&self, txo_id_hex: &str) -> Result<TxOut, LedgerServiceError>;
@@ -82,7 +103,24 @@ pub trait LedgerService {
 
     fn get_network_fee(&self) -> u64;
 
+    /// Get the minimum fee for each token the network accepts fees in.
+    fn get_network_minimum_fees(&self) -> BTreeMap<TokenId, u64>;
+
     fn get_network_block_version(&self) -> BlockVersion;
+
+    /// List the blocks the sync could not process, including those on the
+    /// skip list.
+    fn list_quarantined_blocks(&self) -> Result<Vec<QuarantinedBlock>, LedgerServiceError>;
+
+    /// Add a quarantined block to the skip list, so that the sync passes over
+    /// it.
+    ///
+    /// Any txos received or spent in the block are missed by accounts which
+    /// sync past it while it is skipped.
+    fn skip_quarantined_block(
+        &self,
+        block_index: u64,
+    ) -> Result<QuarantinedBlock, LedgerServiceError>;
 }
 
 impl<T, FPR> LedgerService for WalletService<T, FPR>
@@ -91,13 +129,29 @@ where
     FPR: FogPubkeyResolver + Send + Sync + 'static,
 {
     fn get_network_block_height(&self) -> Result<u64, LedgerServiceError> {
+        let peer_block_heights = self.get_peer_block_heights();
+        Ok(quorum_block_height(
+            &peer_block_heights.values().cloned().collect::<Vec<_>>(),
+        ))
+    }
+
+    fn get_network_block_height_max(&self) -> u64 {
         let network_state = self.network_state.read().expect("lock poisoned");
         match network_state.highest_block_index_on_network() {
-            Some(index) => Ok(index + 1),
-            None => Ok(0),
+            Some(index) => index + 1,
+            None => 0,
         }
     }
 
+    fn get_peer_block_heights(&self) -> BTreeMap<String, u64> {
+        let network_state = self.network_state.read().expect("lock poisoned");
+        network_state
+            .peer_to_current_block_index()
+            .iter()
+            .map(|(responder_id, index)| (responder_id.to_string(), index + 1))
+            .collect()
+    }
+
     fn get_transaction_object(&self, transaction_id_hex: &str) -> Result<Tx, LedgerServiceError> {
         let conn = self.wallet_db.get_conn()?;
         let transaction = TransactionLog::get(transaction_id_hex, &conn)?;
@@ -155,6 +209,32 @@ where
         }
     }
 
+    fn get_network_minimum_fees(&self) -> BTreeMap<TokenId, u64> {
+        // Take the highest fee any node reports for each token, ignoring the
+        // protobuf default of zero. If no node reports a fee for MOB, use the
+        // hard-coded minimum.
+        let mut minimum_fees = BTreeMap::new();
+        if !self.peer_manager.is_empty() {
+            let block_infos: Vec<_> = self
+                .peer_manager
+                .conns()
+                .par_iter()
+                .filter_map(|conn| conn.fetch_block_info(empty()).ok())
+                .collect();
+            for block_info in block_infos {
+                for (token_id, fee) in block_info.minimum_fees.iter() {
+                    if *fee == 0 {
+                        continue;
+                    }
+                    let minimum_fee = minimum_fees.entry(*token_id).or_insert(*fee);
+                    *minimum_fee = (*minimum_fee).max(*fee);
+                }
+            }
+        }
+        minimum_fees.entry(Mob::ID).or_insert(Mob::MINIMUM_FEE);
+        minimum_fees
+    }
+
     fn get_network_block_version(&self) -> BlockVersion {
         if self.peer_manager.is_empty() {
             BlockVersion::MAX
@@ -171,4 +251,47 @@ where
             BlockVersion::try_from(block_version).unwrap_or(BlockVersion::MAX)
         }
     }
+
+    fn list_quarantined_blocks(&self) -> Result<Vec<QuarantinedBlock>, LedgerServiceError> {
+        let conn = self.wallet_db.get_conn()?;
+        Ok(QuarantinedBlock::list_all(&conn)?)
+    }
+
+    fn skip_quarantined_block(
+        &self,
+        block_index: u64,
+    ) -> Result<QuarantinedBlock, LedgerServiceError> {
+        let conn = self.wallet_db.get_conn()?;
+        let skipped = transaction(&conn, || {
+            QuarantinedBlock::get(block_index, &conn)?.skip(&conn)
+        })?;
+
+        log::warn!(
+            self.logger,
+            "Skipping quarantined block {}, which can't be processed: {}",
+            block_index,
+            skipped.reason
+        );
+        Ok(skipped)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quorum_block_height() {
+        assert_eq!(quorum_block_height(&[]), 0);
+        assert_eq!(quorum_block_height(&[12]), 12);
+
+        // A lagging peer, or one which is ahead, is outvoted.
+        assert_eq!(quorum_block_height(&[12, 3, 12]), 12);
+        assert_eq!(quorum_block_height(&[12, 15, 12]), 12);
+
+        // Without a majority at any one height, take the highest which a
+        // majority have reached.
+        assert_eq!(quorum_block_height(&[10, 11, 12, 13]), 11);
+        assert_eq!(quorum_block_height(&[10, 11, 12, 13, 14]), 12);
+    }
 }
@@ -0,0 +1,330 @@
+// Copyright (c) 2020-2022 MobileCoin Inc.
+
+//! Service for managing the size and location of the ledger database.
+//!
+//! LedgerDB opens LMDB with a fixed map size, and LMDB fails every write once
+//! the database outgrows it. The ledger is given a maximum size no larger than
+//! the map size, and the disk space monitor pauses ledger sync as the ledger
+//! nears it, so that the wallet keeps running with a clear error instead.
+//!
+//! When the ledger's volume fills up, the ledger can be copied to another
+//! volume while the wallet runs. Ledger sync is paused during the copy, so
+//! that the copy is consistent, and the wallet uses the new location once it
+//! is restarted with --ledger-db set to it.
+
+use crate::{
+    disk_space_monitor::VolumeStatus,
+    util::disk_space::{database_size, disk_space, DiskSpace, DiskSpaceLevel},
+    WalletService,
+};
+use displaydoc::Display;
+use mc_common::logger::log;
+use mc_connection::{BlockchainConnection, UserTxConnection};
+use mc_fog_report_validation::FogPubkeyResolver;
+use mc_ledger_db::MAX_LMDB_FILE_SIZE;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+/// The map size LedgerDB opens LMDB with, which is the largest size the
+/// ledger can grow to.
+pub const LEDGER_DB_MAP_SIZE_BYTES: u64 = MAX_LMDB_FILE_SIZE as u64;
+
+/// The LMDB file holding the ledger. Its lock file is recreated by LMDB, so is
+/// not copied.
+const LEDGER_DB_DATA_FILE: &str = "data.mdb";
+
+/// How long to wait for ledger sync to pause before copying the ledger.
+const LEDGER_SYNC_PAUSE_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Errors for the Ledger DB Service.
+#[derive(Display, Debug)]
+pub enum LedgerDbServiceError {
+    /// The ledger database is not stored on disk
+    NotOnDisk,
+
+    /// Error reading or writing the ledger database files: {0}
+    Io(std::io::Error),
+
+    /// Max size of {0} bytes is larger than LedgerDB's map size of {1} bytes
+    MaxSizeTooLarge(u64, u64),
+
+    /// The ledger database's size is not monitored, so its max size can't be
+    /// set
+    NotMonitored,
+
+    /// A ledger database already exists at {0}
+    AlreadyExists(String),
+
+    /// Not enough space at {0} for the ledger database: {1} bytes are needed,
+    /// but {2} are free
+    InsufficientSpace(String, u64, u64),
+
+    /// Timed out waiting for ledger sync to pause
+    LedgerSyncStillRunning,
+}
+
+impl From<std::io::Error> for LedgerDbServiceError {
+    fn from(src: std::io::Error) -> Self {
+        Self::Io(src)
+    }
+}
+
+/// The size of the ledger database, and how much more it can grow.
+#[derive(Clone, Debug)]
+pub struct LedgerDbSize {
+    /// Path of the ledger database directory.
+    pub path: PathBuf,
+
+    /// Size of the ledger database files.
+    pub size_bytes: u64,
+
+    /// The size the ledger database may grow to.
+    pub max_size_bytes: u64,
+
+    /// Space usage of the volume holding the ledger database, or None if it
+    /// could not be measured.
+    pub space: Option<DiskSpace>,
+
+    /// Space the ledger database has left to grow into, relative to the disk
+    /// space thresholds.
+    pub level: DiskSpaceLevel,
+}
+
+/// Check that a maximum size for the ledger database fits in LedgerDB's map.
+pub fn validate_ledger_db_max_size(max_size_bytes: u64) -> Result<u64, LedgerDbServiceError> {
+    if max_size_bytes > LEDGER_DB_MAP_SIZE_BYTES {
+        return Err(LedgerDbServiceError::MaxSizeTooLarge(
+            max_size_bytes,
+            LEDGER_DB_MAP_SIZE_BYTES,
+        ));
+    }
+    Ok(max_size_bytes)
+}
+
+/// Trait defining the ways in which the wallet can manage the ledger
+/// database's files.
+pub trait LedgerDbService {
+    /// Get the size of the ledger database, and how much more it can grow.
+    fn get_ledger_db_size(&self) -> Result<LedgerDbSize, LedgerDbServiceError>;
+
+    /// Set the size the ledger database may grow to, until the wallet is
+    /// restarted.
+    ///
+    /// Ledger sync pauses as the ledger nears this size. It can be no larger
+    /// than the map size LedgerDB opens LMDB with.
+    fn set_ledger_db_max_size(
+        &self,
+        max_size_bytes: u64,
+    ) -> Result<LedgerDbSize, LedgerDbServiceError>;
+
+    /// Copy the ledger database to a new directory, returning the size of the
+    /// copy.
+    ///
+    /// Ledger sync is paused while the ledger is copied. The wallet keeps
+    /// using the ledger at its current path until it is restarted with
+    /// --ledger-db set to the new one, after which the old copy can be
+    /// deleted.
+    fn relocate_ledger_db(&self, path: &str) -> Result<LedgerDbSize, LedgerDbServiceError>;
+}
+
+impl<T, FPR> WalletService<T, FPR>
+where
+    T: BlockchainConnection + UserTxConnection + 'static,
+    FPR: FogPubkeyResolver + Send + Sync + 'static,
+{
+    fn ledger_db_max_size(&self) -> u64 {
+        self.disk_space
+            .as_ref()
+            .and_then(|disk_space| disk_space.volume("ledger_db"))
+            .and_then(|volume| volume.max_size_bytes)
+            .unwrap_or(LEDGER_DB_MAP_SIZE_BYTES)
+    }
+
+    fn ledger_db_size_at(&self, path: &Path) -> Result<LedgerDbSize, LedgerDbServiceError> {
+        let thresholds = self
+            .disk_space
+            .as_ref()
+            .map(|disk_space| disk_space.thresholds())
+            .unwrap_or_default();
+        let volume = VolumeStatus {
+            name: "ledger_db".to_string(),
+            path: path.to_path_buf(),
+            space: disk_space(path).ok(),
+            size_bytes: Some(database_size(path)?),
+            max_size_bytes: Some(self.ledger_db_max_size()),
+            level: DiskSpaceLevel::Ok,
+        };
+        let level = volume
+            .headroom()
+            .map(|headroom| thresholds.level(&headroom))
+            .unwrap_or_default();
+
+        Ok(LedgerDbSize {
+            path: volume.path,
+            size_bytes: volume.size_bytes.unwrap_or_default(),
+            max_size_bytes: volume.max_size_bytes.unwrap_or_default(),
+            space: volume.space,
+            level,
+        })
+    }
+}
+
+impl<T, FPR> LedgerDbService for WalletService<T, FPR>
+where
+    T: BlockchainConnection + UserTxConnection + 'static,
+    FPR: FogPubkeyResolver + Send + Sync + 'static,
+{
+    fn get_ledger_db_size(&self) -> Result<LedgerDbSize, LedgerDbServiceError> {
+        let path = self
+            .ledger_db_path
+            .as_ref()
+            .ok_or(LedgerDbServiceError::NotOnDisk)?;
+        self.ledger_db_size_at(path)
+    }
+
+    fn set_ledger_db_max_size(
+        &self,
+        max_size_bytes: u64,
+    ) -> Result<LedgerDbSize, LedgerDbServiceError> {
+        let max_size_bytes = validate_ledger_db_max_size(max_size_bytes)?;
+        let disk_space = self
+            .disk_space
+            .as_ref()
+            .ok_or(LedgerDbServiceError::NotMonitored)?;
+        if !disk_space.set_max_size("ledger_db", Some(max_size_bytes)) {
+            return Err(LedgerDbServiceError::NotMonitored);
+        }
+
+        log::info!(
+            self.logger,
+            "Ledger DB max size set to {} MB",
+            max_size_bytes / (1024 * 1024)
+        );
+        self.get_ledger_db_size()
+    }
+
+    fn relocate_ledger_db(&self, path: &str) -> Result<LedgerDbSize, LedgerDbServiceError> {
+        let current_path = self
+            .ledger_db_path
+            .as_ref()
+            .ok_or(LedgerDbServiceError::NotOnDisk)?;
+        let new_path = PathBuf::from(path);
+        let new_data_file = new_path.join(LEDGER_DB_DATA_FILE);
+        if new_data_file.exists() {
+            return Err(LedgerDbServiceError::AlreadyExists(path.to_string()));
+        }
+
+        let size_bytes = fs::metadata(current_path.join(LEDGER_DB_DATA_FILE))?.len();
+        let available_bytes = disk_space(&new_path)?.available_bytes;
+        if available_bytes < size_bytes {
+            return Err(LedgerDbServiceError::InsufficientSpace(
+                path.to_string(),
+                size_bytes,
+                available_bytes,
+            ));
+        }
+
+        fs::create_dir_all(&new_path)?;
+
+        // LMDB only writes to the data file in write transactions, which only
+        // ledger sync makes, so the file is consistent while it is paused.
+        let _hold = match self.disk_space.as_ref() {
+            Some(disk_space) => {
+                let hold = disk_space.hold_ledger_sync();
+                if !disk_space.wait_for_ledger_sync_to_stop(LEDGER_SYNC_PAUSE_TIMEOUT) {
+                    return Err(LedgerDbServiceError::LedgerSyncStillRunning);
+                }
+                Some(hold)
+            }
+            None => None,
+        };
+
+        log::info!(
+            self.logger,
+            "Copying ledger DB from {:?} to {:?}",
+            current_path,
+            new_path
+        );
+        // Copy under a temporary name, so that an interrupted copy is never
+        // mistaken for a ledger.
+        let partial_data_file = new_path.join(format!("{}.partial", LEDGER_DB_DATA_FILE));
+        fs::copy(current_path.join(LEDGER_DB_DATA_FILE), &partial_data_file)?;
+        fs::rename(&partial_data_file, &new_data_file)?;
+        log::info!(
+            self.logger,
+            "Copied ledger DB to {:?}, restart with --ledger-db {:?} to use it",
+            new_path,
+            new_path
+        );
+
+        self.ledger_db_size_at(&new_path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{get_test_ledger, setup_wallet_service};
+    use mc_account_keys::PublicAddress;
+    use mc_common::logger::{test_with_logger, Logger};
+    use rand::{rngs::StdRng, SeedableRng};
+    use tempdir::TempDir;
+
+    #[test_with_logger]
+    fn test_relocate_ledger_db(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+        let mut service = setup_wallet_service(ledger_db, logger);
+
+        // The test ledger is not given a path.
+        assert!(matches!(
+            service.get_ledger_db_size(),
+            Err(LedgerDbServiceError::NotOnDisk)
+        ));
+
+        let dir = TempDir::new("ledger_db").unwrap();
+        let current_path = dir.path().join("current");
+        fs::create_dir_all(&current_path).unwrap();
+        fs::write(current_path.join(LEDGER_DB_DATA_FILE), vec![7; 4096]).unwrap();
+        service.ledger_db_path = Some(current_path.clone());
+
+        let size = service.get_ledger_db_size().unwrap();
+        assert_eq!(size.size_bytes, 4096);
+        assert_eq!(size.max_size_bytes, LEDGER_DB_MAP_SIZE_BYTES);
+
+        // Without a disk space monitor there is no max size to set.
+        assert!(matches!(
+            service.set_ledger_db_max_size(LEDGER_DB_MAP_SIZE_BYTES + 1),
+            Err(LedgerDbServiceError::MaxSizeTooLarge(_, _))
+        ));
+        assert!(matches!(
+            service.set_ledger_db_max_size(1024 * 1024),
+            Err(LedgerDbServiceError::NotMonitored)
+        ));
+
+        let new_path = dir.path().join("new");
+        let copy = service
+            .relocate_ledger_db(new_path.to_str().unwrap())
+            .unwrap();
+        assert_eq!(copy.path, new_path);
+        assert_eq!(copy.size_bytes, 4096);
+        assert_eq!(
+            fs::read(new_path.join(LEDGER_DB_DATA_FILE)).unwrap(),
+            vec![7; 4096]
+        );
+
+        // An existing ledger, including the current one, is never overwritten.
+        assert!(matches!(
+            service.relocate_ledger_db(new_path.to_str().unwrap()),
+            Err(LedgerDbServiceError::AlreadyExists(_))
+        ));
+        assert!(matches!(
+            service.relocate_ledger_db(current_path.to_str().unwrap()),
+            Err(LedgerDbServiceError::AlreadyExists(_))
+        ));
+    }
+}
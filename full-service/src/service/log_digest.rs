@@ -0,0 +1,261 @@
+// Copyright (c) 2020-2022 MobileCoin Inc.
+
+//! Service for reconciling an account's records between two instances.
+//!
+//! A log digest lists the ids and statuses of an account's transaction logs
+//! and txos, without their contents, so that a replica or a migrated wallet
+//! can be checked against the original without dumping either database. The
+//! digest is exported from one instance and compared on the other, which
+//! reports the records each is missing and those whose statuses disagree.
+
+use crate::{
+    db::{
+        account::{AccountID, AccountModel},
+        models::{
+            Account, TransactionLog, Txo, TXO_STATUS_ORPHANED, TXO_STATUS_PENDING,
+            TXO_STATUS_SECRETED, TXO_STATUS_SPENT, TXO_STATUS_UNSPENT,
+        },
+        transaction_log::TransactionLogModel,
+        txo::TxoModel,
+        WalletDbError,
+    },
+    WalletService,
+};
+use displaydoc::Display;
+use mc_connection::{BlockchainConnection, UserTxConnection};
+use mc_fog_report_validation::FogPubkeyResolver;
+use std::collections::BTreeMap;
+
+/// Errors for the Log Digest Service.
+#[derive(Display, Debug)]
+#[allow(clippy::large_enum_variant)]
+pub enum LogDigestServiceError {
+    /// Error interacting with the database: {0}
+    Database(WalletDbError),
+}
+
+impl From<WalletDbError> for LogDigestServiceError {
+    fn from(src: WalletDbError) -> Self {
+        Self::Database(src)
+    }
+}
+
+/// The ids and statuses of an account's transaction logs and txos.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LogDigest {
+    pub account_id: String,
+    /// The next block the account will scan, so that differences due to one
+    /// instance being further behind can be told apart.
+    pub next_block_index: u64,
+    /// The status of each transaction log, by id.
+    pub transaction_logs: BTreeMap<String, String>,
+    /// The account's status for each txo, by id.
+    pub txos: BTreeMap<String, String>,
+}
+
+/// The differences between this instance's records and another instance's
+/// digest of the same account.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LogDigestDiff {
+    pub account_id: String,
+    pub local_next_block_index: u64,
+    pub remote_next_block_index: u64,
+    /// Transaction logs in the digest which this instance doesn't have.
+    pub missing_transaction_log_ids: Vec<String>,
+    /// Transaction logs this instance has which aren't in the digest.
+    pub extra_transaction_log_ids: Vec<String>,
+    /// Transaction logs which both have, with different statuses.
+    pub mismatched_transaction_log_ids: Vec<String>,
+    pub missing_txo_ids: Vec<String>,
+    pub extra_txo_ids: Vec<String>,
+    pub mismatched_txo_ids: Vec<String>,
+}
+
+impl LogDigestDiff {
+    /// Whether both instances hold the same records.
+    pub fn is_empty(&self) -> bool {
+        self.missing_transaction_log_ids.is_empty()
+            && self.extra_transaction_log_ids.is_empty()
+            && self.mismatched_transaction_log_ids.is_empty()
+            && self.missing_txo_ids.is_empty()
+            && self.extra_txo_ids.is_empty()
+            && self.mismatched_txo_ids.is_empty()
+    }
+}
+
+/// Trait defining the ways in which the wallet can reconcile its records with
+/// another instance.
+pub trait LogDigestService {
+    /// Get a digest of the account's transaction logs and txos.
+    fn get_log_digest(&self, account_id: &AccountID) -> Result<LogDigest, LogDigestServiceError>;
+
+    /// Compare another instance's digest with this instance's records of the
+    /// same account. If this wallet doesn't have the account, every record in
+    /// the digest is reported missing.
+    fn compare_log_digest(
+        &self,
+        remote: &LogDigest,
+    ) -> Result<LogDigestDiff, LogDigestServiceError>;
+}
+
+impl<T, FPR> LogDigestService for WalletService<T, FPR>
+where
+    T: BlockchainConnection + UserTxConnection + 'static,
+    FPR: FogPubkeyResolver + Send + Sync + 'static,
+{
+    fn get_log_digest(&self, account_id: &AccountID) -> Result<LogDigest, LogDigestServiceError> {
+        let conn = self.wallet_db.get_conn()?;
+        let account = Account::get(account_id, &conn)?;
+
+        let transaction_logs =
+            TransactionLog::list_all(&account.account_id_hex, None, None, None, None, &conn)?
+                .into_iter()
+                .map(|(transaction_log, _)| {
+                    (transaction_log.transaction_id_hex, transaction_log.status)
+                })
+                .collect();
+        let txos = Txo::list_for_account(&account.account_id_hex, None, None, None, &conn)?
+            .iter()
+            .map(|txo| {
+                (
+                    txo.txo_id_hex.clone(),
+                    txo_status(txo, &account.account_id_hex).to_string(),
+                )
+            })
+            .collect();
+
+        Ok(LogDigest {
+            account_id: account.account_id_hex,
+            next_block_index: account.next_block_index as u64,
+            transaction_logs,
+            txos,
+        })
+    }
+
+    fn compare_log_digest(
+        &self,
+        remote: &LogDigest,
+    ) -> Result<LogDigestDiff, LogDigestServiceError> {
+        let local = match self.get_log_digest(&AccountID(remote.account_id.clone())) {
+            Ok(local) => local,
+            Err(LogDigestServiceError::Database(WalletDbError::AccountNotFound(_))) => LogDigest {
+                account_id: remote.account_id.clone(),
+                ..Default::default()
+            },
+            Err(err) => return Err(err),
+        };
+        Ok(diff_log_digests(&local, remote))
+    }
+}
+
+/// The account's status for a txo, as reported by the txo API.
+fn txo_status(txo: &Txo, account_id_hex: &str) -> &'static str {
+    if txo.received_account_id_hex.as_deref() != Some(account_id_hex) {
+        TXO_STATUS_SECRETED
+    } else if txo.is_spent() {
+        TXO_STATUS_SPENT
+    } else if txo.is_pending() {
+        TXO_STATUS_PENDING
+    } else if txo.is_orphaned() {
+        TXO_STATUS_ORPHANED
+    } else {
+        TXO_STATUS_UNSPENT
+    }
+}
+
+/// Compare two digests of the same account, reporting what the local one is
+/// missing from the remote one and what it has in addition.
+pub fn diff_log_digests(local: &LogDigest, remote: &LogDigest) -> LogDigestDiff {
+    let (missing_transaction_log_ids, extra_transaction_log_ids, mismatched_transaction_log_ids) =
+        diff_statuses(&local.transaction_logs, &remote.transaction_logs);
+    let (missing_txo_ids, extra_txo_ids, mismatched_txo_ids) =
+        diff_statuses(&local.txos, &remote.txos);
+
+    LogDigestDiff {
+        account_id: local.account_id.clone(),
+        local_next_block_index: local.next_block_index,
+        remote_next_block_index: remote.next_block_index,
+        missing_transaction_log_ids,
+        extra_transaction_log_ids,
+        mismatched_transaction_log_ids,
+        missing_txo_ids,
+        extra_txo_ids,
+        mismatched_txo_ids,
+    }
+}
+
+/// The ids only in the remote map, those only in the local map, and those in
+/// both with different statuses, each in order.
+fn diff_statuses(
+    local: &BTreeMap<String, String>,
+    remote: &BTreeMap<String, String>,
+) -> (Vec<String>, Vec<String>, Vec<String>) {
+    let missing = remote
+        .keys()
+        .filter(|id| !local.contains_key(*id))
+        .cloned()
+        .collect();
+    let mut extra = Vec::new();
+    let mut mismatched = Vec::new();
+    for (id, status) in local {
+        match remote.get(id) {
+            None => extra.push(id.clone()),
+            Some(remote_status) if remote_status != status => mismatched.push(id.clone()),
+            Some(_) => {}
+        }
+    }
+    (missing, extra, mismatched)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::models::{TX_STATUS_PENDING, TX_STATUS_SUCCEEDED};
+
+    fn digest(transaction_logs: &[(&str, &str)], txos: &[(&str, &str)]) -> LogDigest {
+        let to_map = |entries: &[(&str, &str)]| {
+            entries
+                .iter()
+                .map(|(id, status)| (id.to_string(), status.to_string()))
+                .collect()
+        };
+        LogDigest {
+            account_id: "abcd".to_string(),
+            next_block_index: 10,
+            transaction_logs: to_map(transaction_logs),
+            txos: to_map(txos),
+        }
+    }
+
+    #[test]
+    fn test_diff_log_digests() {
+        let local = digest(
+            &[("a1", TX_STATUS_SUCCEEDED), ("a2", TX_STATUS_PENDING)],
+            &[("t1", TXO_STATUS_SPENT), ("t2", TXO_STATUS_UNSPENT)],
+        );
+        assert!(diff_log_digests(&local, &local).is_empty());
+
+        let remote = digest(
+            &[
+                ("a1", TX_STATUS_SUCCEEDED),
+                ("a2", TX_STATUS_SUCCEEDED),
+                ("a3", TX_STATUS_SUCCEEDED),
+            ],
+            &[("t1", TXO_STATUS_SPENT), ("t3", TXO_STATUS_UNSPENT)],
+        );
+        let diff = diff_log_digests(&local, &remote);
+        assert!(!diff.is_empty());
+        assert_eq!(diff.missing_transaction_log_ids, vec!["a3".to_string()]);
+        assert!(diff.extra_transaction_log_ids.is_empty());
+        assert_eq!(diff.mismatched_transaction_log_ids, vec!["a2".to_string()]);
+        assert_eq!(diff.missing_txo_ids, vec!["t3".to_string()]);
+        assert_eq!(diff.extra_txo_ids, vec!["t2".to_string()]);
+        assert!(diff.mismatched_txo_ids.is_empty());
+
+        // Comparing the other way round swaps what is missing and extra.
+        let reverse = diff_log_digests(&remote, &local);
+        assert_eq!(reverse.extra_transaction_log_ids, vec!["a3".to_string()]);
+        assert_eq!(reverse.missing_txo_ids, vec!["t2".to_string()]);
+        assert_eq!(reverse.extra_txo_ids, vec!["t3".to_string()]);
+    }
+}
@@ -0,0 +1,324 @@
+// Copyright (c) 2020-2022 MobileCoin Inc.
+
+//! Service for putting the wallet into maintenance mode.
+//!
+//! While the wallet database is being migrated or backed up, an operator can
+//! start maintenance with the maintenance admin key, read from the
+//! MC_MAINTENANCE_ADMIN_KEY environment variable at startup, instead of taking
+//! the wallet down.
+//! Methods which only read keep working, while methods which would write are
+//! rejected with a retryable error carrying the time the maintenance is
+//! expected to end, so that integrators can hold their writes until then. The
+//! sync pauses once the chunk it is on is committed, and the scheduler runs
+//! no tasks, so that nothing else writes to the database either.
+//!
+//! Maintenance mode is held in memory, and a restart leaves it.
+
+use crate::{
+    db::{
+        models::{WalletEvent, EVENT_TYPE_MAINTENANCE_ENDED, EVENT_TYPE_MAINTENANCE_STARTED},
+        wallet_event::WalletEventModel,
+        WalletDbError,
+    },
+    util::secret::{AdminKey, AdminKeyError},
+    WalletService,
+};
+use chrono::Utc;
+use displaydoc::Display;
+use mc_common::logger::log;
+use mc_connection::{BlockchainConnection, UserTxConnection};
+use mc_fog_report_validation::FogPubkeyResolver;
+use serde_json::json;
+use std::sync::{Arc, RwLock};
+
+/// Errors for the Maintenance Service.
+#[derive(Display, Debug)]
+pub enum MaintenanceServiceError {
+    /// Error interacting with the database: {0}
+    Database(WalletDbError),
+
+    /// No admin key is configured to start or end maintenance
+    NoAdminKey,
+
+    /// The admin key is incorrect
+    InvalidAdminKey,
+}
+
+impl From<WalletDbError> for MaintenanceServiceError {
+    fn from(src: WalletDbError) -> Self {
+        Self::Database(src)
+    }
+}
+
+impl From<AdminKeyError> for MaintenanceServiceError {
+    fn from(src: AdminKeyError) -> Self {
+        match src {
+            AdminKeyError::NotConfigured => Self::NoAdminKey,
+            AdminKeyError::Invalid => Self::InvalidAdminKey,
+        }
+    }
+}
+
+/// A maintenance window in progress.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MaintenanceWindow {
+    /// When maintenance started, in seconds since the epoch.
+    pub started_time: i64,
+
+    /// When maintenance is expected to end, if the operator gave an estimate.
+    pub eta_time: Option<i64>,
+
+    /// Why the wallet is in maintenance, if the operator said.
+    pub reason: Option<String>,
+}
+
+impl MaintenanceWindow {
+    /// How long until maintenance is expected to end, as of `now`, or None if
+    /// there is no estimate. Zero once the estimate has passed.
+    pub fn retry_after_seconds(&self, now: i64) -> Option<u64> {
+        self.eta_time
+            .map(|eta_time| eta_time.saturating_sub(now).max(0) as u64)
+    }
+}
+
+/// Whether the wallet is in maintenance, and whether its sync has paused.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MaintenanceStatus {
+    /// The maintenance window in progress, if there is one.
+    pub window: Option<MaintenanceWindow>,
+
+    /// Whether the sync has stopped writing to the database.
+    pub sync_paused: bool,
+}
+
+/// A shared switch which, while engaged, limits the wallet to reads.
+#[derive(Clone, Default)]
+pub struct MaintenanceMode {
+    window: Arc<RwLock<Option<MaintenanceWindow>>>,
+    admin_key: AdminKey,
+}
+
+impl MaintenanceMode {
+    pub fn new(admin_key: Option<String>) -> Self {
+        Self {
+            window: Default::default(),
+            admin_key: AdminKey::new(admin_key),
+        }
+    }
+
+    /// The maintenance window in progress, if there is one.
+    pub fn current(&self) -> Option<MaintenanceWindow> {
+        self.window.read().expect("lock poisoned").clone()
+    }
+
+    /// Whether the wallet is in maintenance.
+    pub fn is_active(&self) -> bool {
+        self.window.read().expect("lock poisoned").is_some()
+    }
+
+    fn check_admin_key(&self, admin_key: &str) -> Result<(), MaintenanceServiceError> {
+        Ok(self.admin_key.check(admin_key)?)
+    }
+}
+
+/// Trait defining the ways in which the wallet can enter and leave
+/// maintenance mode.
+pub trait MaintenanceService {
+    /// Limit the wallet to reads and pause the sync, given the admin key.
+    ///
+    /// If the wallet is already in maintenance, its estimate and reason are
+    /// replaced, and it keeps the time maintenance started.
+    fn start_maintenance(
+        &self,
+        admin_key: &str,
+        eta_seconds: Option<u64>,
+        reason: Option<String>,
+    ) -> Result<MaintenanceStatus, MaintenanceServiceError>;
+
+    /// Let the wallet write again and resume the sync, given the admin key.
+    fn end_maintenance(
+        &self,
+        admin_key: &str,
+    ) -> Result<MaintenanceStatus, MaintenanceServiceError>;
+
+    /// Whether the wallet is in maintenance, and whether its sync has paused.
+    fn get_maintenance_status(&self) -> MaintenanceStatus;
+}
+
+impl<T, FPR> MaintenanceService for WalletService<T, FPR>
+where
+    T: BlockchainConnection + UserTxConnection + 'static,
+    FPR: FogPubkeyResolver + Send + Sync + 'static,
+{
+    fn start_maintenance(
+        &self,
+        admin_key: &str,
+        eta_seconds: Option<u64>,
+        reason: Option<String>,
+    ) -> Result<MaintenanceStatus, MaintenanceServiceError> {
+        self.maintenance_mode.check_admin_key(admin_key)?;
+
+        let now = Utc::now().timestamp();
+        let eta_time = eta_seconds.map(|eta_seconds| now.saturating_add(eta_seconds as i64));
+
+        // The event is recorded before the wallet stops writing.
+        let conn = self.wallet_db.get_conn()?;
+        WalletEvent::create(
+            EVENT_TYPE_MAINTENANCE_STARTED,
+            &json!({
+                "eta_time": eta_time.map(|eta_time| eta_time.to_string()),
+                "reason": reason,
+            }),
+            &conn,
+        )?;
+
+        {
+            let mut window = self.maintenance_mode.window.write().expect("lock poisoned");
+            let started_time = window.as_ref().map_or(now, |window| window.started_time);
+            *window = Some(MaintenanceWindow {
+                started_time,
+                eta_time,
+                reason,
+            });
+        }
+        self.sync_activity.pause();
+        log::warn!(self.logger, "Maintenance started, the wallet is read-only");
+
+        Ok(self.get_maintenance_status())
+    }
+
+    fn end_maintenance(
+        &self,
+        admin_key: &str,
+    ) -> Result<MaintenanceStatus, MaintenanceServiceError> {
+        self.maintenance_mode.check_admin_key(admin_key)?;
+
+        let ended = self
+            .maintenance_mode
+            .window
+            .write()
+            .expect("lock poisoned")
+            .take();
+        self.sync_activity.resume();
+
+        if let Some(window) = ended {
+            log::warn!(
+                self.logger,
+                "Maintenance ended, the wallet is writable again"
+            );
+            let conn = self.wallet_db.get_conn()?;
+            WalletEvent::create(
+                EVENT_TYPE_MAINTENANCE_ENDED,
+                &json!({
+                    "started_time": window.started_time.to_string(),
+                }),
+                &conn,
+            )?;
+        }
+
+        Ok(self.get_maintenance_status())
+    }
+
+    fn get_maintenance_status(&self) -> MaintenanceStatus {
+        MaintenanceStatus {
+            window: self.maintenance_mode.current(),
+            sync_paused: self.sync_activity.is_paused(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        service::WalletServiceOptions,
+        test_utils::{get_test_ledger, setup_wallet_service_with},
+    };
+    use mc_account_keys::PublicAddress;
+    use mc_common::logger::{test_with_logger, Logger};
+    use rand::{rngs::StdRng, SeedableRng};
+    use std::{thread, time::Duration};
+
+    #[test_with_logger]
+    fn test_maintenance_pauses_sync(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+        let service = setup_wallet_service_with(
+            ledger_db,
+            WalletServiceOptions {
+                maintenance_mode: MaintenanceMode::new(Some("admin".to_string())),
+                ..Default::default()
+            },
+            logger,
+        );
+
+        // The admin key is required.
+        match service.start_maintenance("wrong", None, None) {
+            Err(MaintenanceServiceError::InvalidAdminKey) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+        assert!(service.get_maintenance_status().window.is_none());
+
+        let status = service
+            .start_maintenance("admin", Some(600), Some("backup".to_string()))
+            .unwrap();
+        let window = status.window.unwrap();
+        assert_eq!(window.eta_time, Some(window.started_time + 600));
+        assert_eq!(window.reason, Some("backup".to_string()));
+        assert_eq!(
+            window.retry_after_seconds(window.started_time + 100),
+            Some(500)
+        );
+        assert_eq!(
+            window.retry_after_seconds(window.started_time + 700),
+            Some(0)
+        );
+
+        // The sync stops once its current pass is done.
+        let mut sync_paused = false;
+        for _ in 0..100 {
+            if service.get_maintenance_status().sync_paused {
+                sync_paused = true;
+                break;
+            }
+            thread::sleep(Duration::from_millis(100));
+        }
+        assert!(sync_paused);
+
+        // Starting again updates the estimate, but not the start time.
+        let restarted = service
+            .start_maintenance("admin", None, None)
+            .unwrap()
+            .window
+            .unwrap();
+        assert_eq!(restarted.started_time, window.started_time);
+        assert_eq!(restarted.eta_time, None);
+
+        let status = service.end_maintenance("admin").unwrap();
+        assert!(status.window.is_none());
+        assert!(!status.sync_paused);
+    }
+
+    #[test_with_logger]
+    fn test_maintenance_without_admin_key(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+        let service = setup_wallet_service_with(
+            ledger_db,
+            WalletServiceOptions {
+                maintenance_mode: MaintenanceMode::new(None),
+                ..Default::default()
+            },
+            logger,
+        );
+
+        match service.start_maintenance("", None, None) {
+            Err(MaintenanceServiceError::NoAdminKey) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+}
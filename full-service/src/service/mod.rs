@@ -3,20 +3,50 @@
 //! Implementations of services.
 
 pub mod account;
+pub mod account_recovery;
+pub mod account_rotation;
+pub mod account_snapshot;
 pub mod address;
+pub mod address_label;
 pub mod balance;
+pub mod balance_cache;
 pub mod confirmation_number;
+pub mod continuity;
+pub mod database_password;
+pub mod deposit;
+pub mod diagnostics;
+pub mod expired_transaction;
 pub mod gift_code;
+pub mod isolated_counterparty;
+pub mod large_send;
 pub mod ledger;
+pub mod ledger_db;
+pub mod log_digest;
+pub mod maintenance;
 pub mod payment_request;
+pub mod payout;
+pub mod peer_attestation;
 pub mod receipt;
+pub mod reporting;
+pub mod scheduled_payment;
+pub mod scheduler;
+pub mod spending_lock;
+pub mod sweep_policy;
 pub mod sync;
+pub mod sync_status;
+pub mod token;
 pub mod transaction;
 pub mod transaction_builder;
 pub mod transaction_log;
+pub mod transaction_watch;
 pub mod txo;
+pub mod upgrade_readiness;
+pub mod usage_stats;
 pub mod view_only_account;
 pub mod view_only_txo;
+pub mod wallet_event;
 mod wallet_service;
+pub mod wallet_settings;
+pub mod webhook;
 
-pub use wallet_service::WalletService;
+pub use wallet_service::{WalletService, WalletServiceOptions};
@@ -1,14 +1,23 @@
 // Copyright (c) 2020-2021 MobileCoin Inc.
 
-//! Service for managing accounts.
+//! Service for managing payment requests.
 
 use crate::{
-    db::{assigned_subaddress::AssignedSubaddressModel, models::AssignedSubaddress, WalletDbError},
+    db::{
+        account::AccountID,
+        assigned_subaddress::AssignedSubaddressModel,
+        models::{AssignedSubaddress, PaymentRequest},
+        payment_request::PaymentRequestModel,
+        transaction, WalletDbError,
+    },
     service::WalletService,
     util::b58::{b58_decode_public_address, b58_encode_payment_request, B58Error},
 };
+use chrono::Utc;
 use mc_connection::{BlockchainConnection, UserTxConnection};
 use mc_fog_report_validation::FogPubkeyResolver;
+use mc_ledger_db::Ledger;
+use mc_transaction_core::{tokens::Mob, Token};
 
 use crate::service::ledger::LedgerServiceError;
 use displaydoc::Display;
@@ -38,6 +47,9 @@ pub enum PaymentRequestServiceError {
 
     /// Invalid BIP39 english mnemonic: {0}
     InvalidMnemonic(String),
+
+    /// Expiry block {0} has already passed, the ledger has {1} blocks
+    ExpiryInPast(u64, u64),
 }
 
 impl From<WalletDbError> for PaymentRequestServiceError {
@@ -77,14 +89,30 @@ impl From<LedgerServiceError> for PaymentRequestServiceError {
 }
 
 pub trait PaymentRequestService {
-    /// Creates a new payment request b58.
+    /// Creates a new payment request, returning it with its b58.
+    ///
+    /// Payments whose memos carry the request's id are matched to it as they
+    /// are received, and marked late if they land after the expiry block.
     fn create_payment_request(
         &self,
         account_id: String,
         subaddress_index: Option<i64>,
         amount_pmob: u64,
         memo: Option<String>,
-    ) -> Result<String, PaymentRequestServiceError>;
+        expiry_block_index: Option<u64>,
+    ) -> Result<(PaymentRequest, String), PaymentRequestServiceError>;
+
+    /// Get a payment request by its id.
+    fn get_payment_request(
+        &self,
+        payment_request_id: i32,
+    ) -> Result<PaymentRequest, PaymentRequestServiceError>;
+
+    /// List the payment requests of an account.
+    fn list_payment_requests(
+        &self,
+        account_id: &AccountID,
+    ) -> Result<Vec<PaymentRequest>, PaymentRequestServiceError>;
 }
 
 impl<T, FPR> PaymentRequestService for WalletService<T, FPR>
@@ -98,7 +126,18 @@ where
         subaddress_index: Option<i64>,
         amount_pmob: u64,
         memo: Option<String>,
-    ) -> Result<String, PaymentRequestServiceError> {
+        expiry_block_index: Option<u64>,
+    ) -> Result<(PaymentRequest, String), PaymentRequestServiceError> {
+        if let Some(expiry_block_index) = expiry_block_index {
+            let num_blocks = self.ledger_db.num_blocks()?;
+            if expiry_block_index < num_blocks {
+                return Err(PaymentRequestServiceError::ExpiryInPast(
+                    expiry_block_index,
+                    num_blocks,
+                ));
+            }
+        }
+
         let conn = self.wallet_db.get_conn()?;
 
         let assigned_subaddress = AssignedSubaddress::get_for_account_by_index(
@@ -110,12 +149,39 @@ where
         let public_address =
             b58_decode_public_address(&assigned_subaddress.assigned_subaddress_b58)?;
 
-        let payment_request_b58 = b58_encode_payment_request(
-            &public_address,
-            amount_pmob,
-            memo.unwrap_or_else(|| "".to_string()),
-        )?;
+        let memo = memo.unwrap_or_else(|| "".to_string());
+        let payment_request_b58 =
+            b58_encode_payment_request(&public_address, amount_pmob, memo.clone())?;
+
+        let payment_request = transaction(&conn, || {
+            PaymentRequest::create(
+                &account_id,
+                assigned_subaddress.subaddress_index as u64,
+                amount_pmob,
+                *Mob::ID,
+                &memo,
+                expiry_block_index,
+                Utc::now().timestamp(),
+                &conn,
+            )
+        })?;
+
+        Ok((payment_request, payment_request_b58))
+    }
 
-        Ok(payment_request_b58)
+    fn get_payment_request(
+        &self,
+        payment_request_id: i32,
+    ) -> Result<PaymentRequest, PaymentRequestServiceError> {
+        let conn = self.wallet_db.get_conn()?;
+        Ok(PaymentRequest::get(payment_request_id, &conn)?)
+    }
+
+    fn list_payment_requests(
+        &self,
+        account_id: &AccountID,
+    ) -> Result<Vec<PaymentRequest>, PaymentRequestServiceError> {
+        let conn = self.wallet_db.get_conn()?;
+        Ok(PaymentRequest::list(&account_id.to_string(), &conn)?)
     }
 }
@@ -0,0 +1,427 @@
+// Copyright (c) 2020-2022 MobileCoin Inc.
+
+//! Service for submitting bulk payouts from an uploaded file.
+
+use crate::{
+    db::{
+        account::{AccountID, AccountModel},
+        models::Account,
+        WalletDbError,
+    },
+    service::{
+        address::AddressService,
+        transaction::{TransactionService, TransactionServiceError},
+        WalletService,
+    },
+    util::{
+        amount_conversion::{parse_decimal, AmountUnit, RoundingMode},
+        csv::parse_csv,
+    },
+};
+use displaydoc::Display;
+use mc_connection::{BlockchainConnection, UserTxConnection};
+use mc_fog_report_validation::FogPubkeyResolver;
+use mc_transaction_core::constants::MAX_OUTPUTS;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashSet, str::FromStr};
+
+/// The maximum number of payouts in a single transaction. One output is
+/// reserved for change.
+pub const MAX_PAYOUTS_PER_TRANSACTION: usize = (MAX_OUTPUTS - 1) as usize;
+
+#[derive(Display, Debug)]
+#[allow(clippy::large_enum_variant)]
+pub enum PayoutServiceError {
+    /// Error interacting with the database: {0}
+    Database(WalletDbError),
+
+    /// Unknown payout file format: {0}
+    UnknownFormat(String),
+
+    /// Invalid payout file: {0}
+    InvalidFile(String),
+
+    /// The payout file contains no rows
+    EmptyFile,
+}
+
+impl From<WalletDbError> for PayoutServiceError {
+    fn from(src: WalletDbError) -> Self {
+        Self::Database(src)
+    }
+}
+
+/// The format of an uploaded payout file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PayoutFileFormat {
+    /// CSV with a header row naming the address, amount, token and
+    /// external_id columns.
+    Csv,
+
+    /// A JSON array of objects with address, amount, token and external_id
+    /// fields.
+    Json,
+}
+
+impl FromStr for PayoutFileFormat {
+    type Err = PayoutServiceError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "csv" => Ok(PayoutFileFormat::Csv),
+            "json" => Ok(PayoutFileFormat::Json),
+            _ => Err(PayoutServiceError::UnknownFormat(s.to_string())),
+        }
+    }
+}
+
+/// A single row of a payout file, before validation.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct PayoutRow {
+    #[serde(default)]
+    pub address: String,
+
+    #[serde(default)]
+    pub amount: String,
+
+    /// The token to pay out. Only MOB, given as "0" or "mob", is currently
+    /// supported. Defaults to MOB when empty.
+    #[serde(default)]
+    pub token: String,
+
+    /// A caller-assigned identifier for the payout, which must be unique
+    /// within the file.
+    #[serde(default)]
+    pub external_id: String,
+}
+
+/// The outcome of a single row of a payout file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PayoutRowResult {
+    /// The 1-based index of the row in the file, excluding any header.
+    pub row: usize,
+
+    pub external_id: String,
+
+    /// Whether the payout was submitted.
+    pub accepted: bool,
+
+    /// Why the payout was rejected.
+    pub error: Option<String>,
+
+    /// The transaction containing the payout, if it was accepted.
+    pub transaction_log_id: Option<String>,
+}
+
+/// Trait defining the ways in which the wallet can submit bulk payouts.
+pub trait PayoutService {
+    /// Validate every row of a payout file, then submit the valid rows from
+    /// the given account, batched into as few transactions as possible.
+    ///
+    /// Returns the outcome of each row, in file order.
+    #[allow(clippy::too_many_arguments)]
+    fn submit_payout_file(
+        &self,
+        account_id: &AccountID,
+        contents: &str,
+        format: PayoutFileFormat,
+        amount_unit: AmountUnit,
+        fee: Option<String>,
+        tombstone_block: Option<String>,
+        comment: Option<String>,
+    ) -> Result<Vec<PayoutRowResult>, PayoutServiceError>;
+}
+
+impl<T, FPR> PayoutService for WalletService<T, FPR>
+where
+    T: BlockchainConnection + UserTxConnection + 'static,
+    FPR: FogPubkeyResolver + Send + Sync + 'static,
+{
+    fn submit_payout_file(
+        &self,
+        account_id: &AccountID,
+        contents: &str,
+        format: PayoutFileFormat,
+        amount_unit: AmountUnit,
+        fee: Option<String>,
+        tombstone_block: Option<String>,
+        comment: Option<String>,
+    ) -> Result<Vec<PayoutRowResult>, PayoutServiceError> {
+        // Fail early, rather than rejecting every row, if the account is
+        // unknown.
+        let conn = self.wallet_db.get_conn()?;
+        Account::get(account_id, &conn)?;
+
+        let rows = match format {
+            PayoutFileFormat::Csv => parse_csv_rows(contents)?,
+            PayoutFileFormat::Json => serde_json::from_str::<Vec<PayoutRow>>(contents)
+                .map_err(|err| PayoutServiceError::InvalidFile(err.to_string()))?,
+        };
+        if rows.is_empty() {
+            return Err(PayoutServiceError::EmptyFile);
+        }
+
+        let mut results = Vec::with_capacity(rows.len());
+        let mut valid = Vec::new();
+        let mut external_ids = HashSet::new();
+        for (index, row) in rows.into_iter().enumerate() {
+            let mut result = PayoutRowResult {
+                row: index + 1,
+                external_id: row.external_id.clone(),
+                accepted: false,
+                error: None,
+                transaction_log_id: None,
+            };
+            match self.validate_payout_row(&row, amount_unit, &mut external_ids) {
+                Ok(value) => valid.push((results.len(), row.address, value.to_string())),
+                Err(err) => result.error = Some(err),
+            }
+            results.push(result);
+        }
+
+        for batch in valid.chunks(MAX_PAYOUTS_PER_TRANSACTION) {
+            let addresses_and_values: Vec<(String, String)> = batch
+                .iter()
+                .map(|(_, address, value)| (address.clone(), value.clone()))
+                .collect();
+            let outcome: Result<String, TransactionServiceError> = self
+                .build_and_submit(
+                    &account_id.to_string(),
+                    &addresses_and_values,
+                    None,
+                    None,
+                    fee.clone(),
+                    None,
+                    tombstone_block.clone(),
+                    None,
+                    None,
+                    None,
+                    None,
+                    None,
+                    comment.clone(),
+                )
+                .map(|(transaction_log, _, _)| transaction_log.transaction_id_hex);
+
+            for (result_index, _, _) in batch {
+                let result = &mut results[*result_index];
+                match &outcome {
+                    Ok(transaction_log_id) => {
+                        result.accepted = true;
+                        result.transaction_log_id = Some(transaction_log_id.clone());
+                    }
+                    Err(err) => result.error = Some(format!("Submitting payout failed: {}", err)),
+                }
+            }
+        }
+
+        Ok(results)
+    }
+}
+
+impl<T, FPR> WalletService<T, FPR>
+where
+    T: BlockchainConnection + UserTxConnection + 'static,
+    FPR: FogPubkeyResolver + Send + Sync + 'static,
+{
+    /// Check a payout row, returning its value in picoMOB.
+    fn validate_payout_row(
+        &self,
+        row: &PayoutRow,
+        amount_unit: AmountUnit,
+        external_ids: &mut HashSet<String>,
+    ) -> Result<u64, String> {
+        if row.external_id.is_empty() {
+            return Err("Missing external_id".to_string());
+        }
+        if !external_ids.insert(row.external_id.clone()) {
+            return Err(format!("Duplicate external_id: {}", row.external_id));
+        }
+
+        match row.token.to_lowercase().as_str() {
+            "" | "0" | "mob" => {}
+            token => return Err(format!("Unsupported token: {}", token)),
+        }
+
+        if !matches!(self.verify_address(&row.address), Ok(true)) {
+            return Err(format!("Invalid address: {}", row.address));
+        }
+
+        let value = parse_decimal(&row.amount, amount_unit.decimals(), RoundingMode::Exact)
+            .map_err(|err| format!("Invalid amount: {}", err))?;
+        if value == 0 {
+            return Err("Amount must be greater than zero".to_string());
+        }
+
+        Ok(value)
+    }
+}
+
+/// Parse a CSV payout file. The first row is a header naming the columns,
+/// which may be in any order; a token column is optional.
+fn parse_csv_rows(contents: &str) -> Result<Vec<PayoutRow>, PayoutServiceError> {
+    let mut records = parse_csv(contents)
+        .map_err(PayoutServiceError::InvalidFile)?
+        .into_iter();
+    let header = records.next().ok_or(PayoutServiceError::EmptyFile)?;
+
+    let column = |name: &str| {
+        header
+            .iter()
+            .position(|field| field.trim().eq_ignore_ascii_case(name))
+    };
+    let missing = |name: &str| PayoutServiceError::InvalidFile(format!("Missing {} column", name));
+    let address = column("address").ok_or_else(|| missing("address"))?;
+    let amount = column("amount").ok_or_else(|| missing("amount"))?;
+    let external_id = column("external_id").ok_or_else(|| missing("external_id"))?;
+    let token = column("token");
+
+    let field = |record: &[String], index: usize| {
+        record
+            .get(index)
+            .map(|value| value.trim().to_string())
+            .unwrap_or_default()
+    };
+    Ok(records
+        .map(|record| PayoutRow {
+            address: field(&record, address),
+            amount: field(&record, amount),
+            token: token.map(|i| field(&record, i)).unwrap_or_default(),
+            external_id: field(&record, external_id),
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        db::{models::Txo, txo::TxoModel},
+        service::account::AccountService,
+        test_utils::{
+            add_block_to_ledger_db, get_test_ledger, manually_sync_account, setup_wallet_service,
+            MOB,
+        },
+        util::b58::b58_encode_public_address,
+    };
+    use mc_account_keys::{AccountKey, PublicAddress};
+    use mc_common::logger::{test_with_logger, Logger};
+    use mc_crypto_rand::rand_core::RngCore;
+    use mc_transaction_core::ring_signature::KeyImage;
+    use mc_util_from_random::FromRandom;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn test_parse_csv() {
+        let records = parse_csv("a,b,c\r\n\"x,1\",\"say \"\"hi\"\"\",\n\n1,2,3").unwrap();
+        assert_eq!(
+            records,
+            vec![
+                vec!["a", "b", "c"],
+                vec!["x,1", "say \"hi\"", ""],
+                vec!["1", "2", "3"],
+            ]
+        );
+        assert!(parse_csv("a,\"b").is_err());
+
+        let rows = parse_csv_rows("external_id,amount,address\nid-1,5,addr\n").unwrap();
+        assert_eq!(
+            rows,
+            vec![PayoutRow {
+                address: "addr".to_string(),
+                amount: "5".to_string(),
+                token: "".to_string(),
+                external_id: "id-1".to_string(),
+            }]
+        );
+        assert!(parse_csv_rows("address,amount\naddr,5\n").is_err());
+    }
+
+    #[test_with_logger]
+    fn test_submit_payout_file(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let mut ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        let service = setup_wallet_service(ledger_db.clone(), logger.clone());
+        let alice = service
+            .create_account(
+                Some("Alice's Main Account".to_string()),
+                "".to_string(),
+                "".to_string(),
+                "".to_string(),
+            )
+            .unwrap();
+        let alice_account_key: AccountKey = mc_util_serial::decode(&alice.account_key).unwrap();
+        add_block_to_ledger_db(
+            &mut ledger_db,
+            &vec![alice_account_key.subaddress(alice.main_subaddress_index as u64)],
+            100 * MOB,
+            &vec![KeyImage::from(rng.next_u64())],
+            &mut rng,
+        );
+        let alice_account_id = AccountID(alice.account_id_hex.to_string());
+        manually_sync_account(&ledger_db, &service.wallet_db, &alice_account_id, &logger);
+
+        let bob =
+            b58_encode_public_address(&AccountKey::random(&mut rng).default_subaddress()).unwrap();
+        let contents = format!(
+            "address,amount,token,external_id\n\
+             {bob},1.5,mob,payout-1\n\
+             {bob},2,0,payout-2\n\
+             {bob},2,eusd,payout-3\n\
+             not-an-address,1,mob,payout-4\n\
+             {bob},1,mob,payout-1\n\
+             {bob},0,mob,payout-6\n",
+            bob = bob
+        );
+
+        let results = service
+            .submit_payout_file(
+                &alice_account_id,
+                &contents,
+                PayoutFileFormat::Csv,
+                AmountUnit::Mob,
+                None,
+                None,
+                None,
+            )
+            .unwrap();
+
+        let accepted: Vec<usize> = results
+            .iter()
+            .filter(|result| result.accepted)
+            .map(|result| result.row)
+            .collect();
+        assert_eq!(accepted, vec![1, 2]);
+        // Both valid rows are paid in a single transaction.
+        assert!(results[0].transaction_log_id.is_some());
+        assert_eq!(results[0].transaction_log_id, results[1].transaction_log_id);
+        assert!(results[2]
+            .error
+            .as_ref()
+            .unwrap()
+            .contains("Unsupported token"));
+        assert!(results[3]
+            .error
+            .as_ref()
+            .unwrap()
+            .contains("Invalid address"));
+        assert!(results[4]
+            .error
+            .as_ref()
+            .unwrap()
+            .contains("Duplicate external_id"));
+        assert!(results[5]
+            .error
+            .as_ref()
+            .unwrap()
+            .contains("greater than zero"));
+
+        // The transaction spends the received txo.
+        let conn = service.wallet_db.get_conn().unwrap();
+        let pending =
+            Txo::list_pending(&alice_account_id.to_string(), None, Some(0), &conn).unwrap();
+        assert_eq!(pending.len(), 1);
+    }
+}
@@ -0,0 +1,103 @@
+// Copyright (c) 2020-2022 MobileCoin Inc.
+
+//! Service for reporting changes in the consensus peers' attestations.
+//!
+//! The consensus pool keeper re-attests each peer in the background, and
+//! notes when a peer attests with a different enclave measurement than
+//! before, or stops passing attestation. Those changes are recorded here as
+//! wallet events, so that operators learn of an unexpected enclave change as
+//! it happens, rather than from the next failed submission.
+
+use crate::{
+    consensus_pool::{PeerAttestation, PeerAttestationChange},
+    db::{
+        models::{
+            WalletEvent, EVENT_TYPE_PEER_ATTESTATION_FAILED, EVENT_TYPE_PEER_MEASUREMENT_CHANGED,
+        },
+        wallet_event::WalletEventModel,
+        WalletDbError,
+    },
+    WalletService,
+};
+use displaydoc::Display;
+use mc_common::ResponderId;
+use mc_connection::{BlockchainConnection, UserTxConnection};
+use mc_fog_report_validation::FogPubkeyResolver;
+use serde_json::json;
+
+/// Errors for the Peer Attestation Service.
+#[derive(Display, Debug)]
+#[allow(clippy::large_enum_variant)]
+pub enum PeerAttestationServiceError {
+    /// Error interacting with the database: {0}
+    Database(WalletDbError),
+}
+
+impl From<WalletDbError> for PeerAttestationServiceError {
+    fn from(src: WalletDbError) -> Self {
+        Self::Database(src)
+    }
+}
+
+/// Trait defining the ways in which the wallet can report on the consensus
+/// peers' attestations.
+pub trait PeerAttestationService {
+    /// What the pool keeper last learned from each peer's attestations. Empty
+    /// when the wallet doesn't pool its connections to the peers.
+    fn get_peer_attestations(&self) -> Vec<(ResponderId, PeerAttestation)>;
+
+    /// Record an event for each change in the peers' attestations since they
+    /// were last recorded, returning how many were recorded.
+    fn record_peer_attestation_changes(&self) -> Result<usize, PeerAttestationServiceError>;
+}
+
+impl<T, FPR> PeerAttestationService for WalletService<T, FPR>
+where
+    T: BlockchainConnection + UserTxConnection + 'static,
+    FPR: FogPubkeyResolver + Send + Sync + 'static,
+{
+    fn get_peer_attestations(&self) -> Vec<(ResponderId, PeerAttestation)> {
+        match &self.consensus_pool {
+            Some(pool) => pool.peer_attestations(),
+            None => Vec::new(),
+        }
+    }
+
+    fn record_peer_attestation_changes(&self) -> Result<usize, PeerAttestationServiceError> {
+        let changes = match &self.consensus_pool {
+            Some(pool) => pool.take_attestation_changes(),
+            None => return Ok(0),
+        };
+        if changes.is_empty() {
+            return Ok(0);
+        }
+
+        let conn = self.wallet_db.get_conn()?;
+        for (responder_id, change) in &changes {
+            match change {
+                PeerAttestationChange::MeasurementChanged { previous, current } => {
+                    WalletEvent::create(
+                        EVENT_TYPE_PEER_MEASUREMENT_CHANGED,
+                        &json!({
+                            "peer": responder_id.to_string(),
+                            "previous_measurement": previous,
+                            "measurement": current,
+                        }),
+                        &conn,
+                    )?;
+                }
+                PeerAttestationChange::VerificationFailed { error } => {
+                    WalletEvent::create(
+                        EVENT_TYPE_PEER_ATTESTATION_FAILED,
+                        &json!({
+                            "peer": responder_id.to_string(),
+                            "error": error,
+                        }),
+                        &conn,
+                    )?;
+                }
+            }
+        }
+        Ok(changes.len())
+    }
+}
@@ -380,6 +380,12 @@ mod tests {
                 None,
                 None,
                 None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
             )
             .expect("Could not build transaction");
 
@@ -444,6 +450,24 @@ mod tests {
             .expect("Could not get confirmations");
         assert_eq!(confirmations.len(), 1);
 
+        // The ledger index is cached on the txo, and served from there.
+        let confirmed_txo = service.get_txo(&confirmations[0].txo_id).unwrap();
+        assert_eq!(
+            confirmed_txo.ledger_txo_index,
+            Some(confirmations[0].txo_index as i64)
+        );
+        let cached_confirmations = service
+            .get_confirmations(&sent_transaction_log.transaction_id_hex)
+            .expect("Could not get confirmations");
+        assert_eq!(
+            cached_confirmations[0].txo_index,
+            confirmations[0].txo_index
+        );
+        assert_eq!(
+            cached_confirmations[0].confirmation,
+            confirmations[0].confirmation
+        );
+
         let txo_pubkey =
             mc_util_serial::decode(&txos[0].public_key).expect("Could not decode pubkey");
         assert_eq!(receipt.public_key, txo_pubkey);
@@ -512,6 +536,12 @@ mod tests {
                 None,
                 None,
                 None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
             )
             .expect("Could not build transaction");
 
@@ -634,6 +664,12 @@ mod tests {
                 None,
                 None,
                 None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
             )
             .expect("Could not build transaction");
 
@@ -763,6 +799,12 @@ mod tests {
                 None,
                 None,
                 None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
             )
             .expect("Could not build transaction");
 
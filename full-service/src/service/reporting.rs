@@ -0,0 +1,144 @@
+// Copyright (c) 2020-2022 MobileCoin Inc.
+
+//! Service for signing wallet exports.
+//!
+//! Exports can be signed with the wallet's reporting key, an Ed25519 key which
+//! is generated the first time it is needed and used for nothing else, so that
+//! it can be shared with auditors and partners without exposing any account
+//! keys. The signature is detached from the export, so the export itself is
+//! unchanged, and recipients verify it against the reporting public key.
+
+use crate::{
+    db::{models::ReportingKey, reporting_key::ReportingKeyModel, transaction, WalletDbError},
+    WalletService,
+};
+use displaydoc::Display;
+use mc_connection::{BlockchainConnection, UserTxConnection};
+use mc_crypto_keys::{Ed25519Pair, Ed25519Private, Ed25519Public, Ed25519Signature, Signer};
+use mc_fog_report_validation::FogPubkeyResolver;
+use mc_util_from_random::FromRandom;
+use std::convert::TryFrom;
+
+/// Errors for the Reporting Service.
+#[derive(Display, Debug)]
+pub enum ReportingServiceError {
+    /// Error interacting with the database: {0}
+    Database(WalletDbError),
+
+    /// Diesel Error: {0}
+    Diesel(diesel::result::Error),
+
+    /// The stored reporting key is invalid: {0}
+    Key(mc_crypto_keys::KeyError),
+
+    /// Error signing the report: {0}
+    Signature(mc_crypto_keys::SignatureError),
+}
+
+impl From<WalletDbError> for ReportingServiceError {
+    fn from(src: WalletDbError) -> Self {
+        Self::Database(src)
+    }
+}
+
+impl From<diesel::result::Error> for ReportingServiceError {
+    fn from(src: diesel::result::Error) -> Self {
+        Self::Diesel(src)
+    }
+}
+
+impl From<mc_crypto_keys::KeyError> for ReportingServiceError {
+    fn from(src: mc_crypto_keys::KeyError) -> Self {
+        Self::Key(src)
+    }
+}
+
+impl From<mc_crypto_keys::SignatureError> for ReportingServiceError {
+    fn from(src: mc_crypto_keys::SignatureError) -> Self {
+        Self::Signature(src)
+    }
+}
+
+/// Trait defining the ways in which the wallet can sign its exports.
+pub trait ReportingService {
+    /// Get the public key which export signatures can be verified against.
+    fn get_reporting_public_key(&self) -> Result<Ed25519Public, ReportingServiceError>;
+
+    /// Sign the contents of an export with the reporting key, returning the
+    /// detached signature and the public key to verify it with.
+    fn sign_report(
+        &self,
+        contents: &[u8],
+    ) -> Result<(Ed25519Signature, Ed25519Public), ReportingServiceError>;
+}
+
+impl<T, FPR> ReportingService for WalletService<T, FPR>
+where
+    T: BlockchainConnection + UserTxConnection + 'static,
+    FPR: FogPubkeyResolver + Send + Sync + 'static,
+{
+    fn get_reporting_public_key(&self) -> Result<Ed25519Public, ReportingServiceError> {
+        Ok(get_or_create_reporting_key(self)?.public_key())
+    }
+
+    fn sign_report(
+        &self,
+        contents: &[u8],
+    ) -> Result<(Ed25519Signature, Ed25519Public), ReportingServiceError> {
+        let reporting_key = get_or_create_reporting_key(self)?;
+        let signature = reporting_key.try_sign(contents)?;
+        Ok((signature, reporting_key.public_key()))
+    }
+}
+
+/// Load the reporting key, generating it if this is the first time it has
+/// been needed.
+fn get_or_create_reporting_key<T, FPR>(
+    service: &WalletService<T, FPR>,
+) -> Result<Ed25519Pair, ReportingServiceError>
+where
+    T: BlockchainConnection + UserTxConnection + 'static,
+    FPR: FogPubkeyResolver + Send + Sync + 'static,
+{
+    let conn = service.wallet_db.get_conn()?;
+    let reporting_key = transaction(&conn, || {
+        if let Some(reporting_key) = ReportingKey::get(&conn)? {
+            return Ok::<ReportingKey, ReportingServiceError>(reporting_key);
+        }
+        let private_key = Ed25519Private::from_random(&mut rand::thread_rng());
+        Ok(ReportingKey::create(private_key.as_ref(), &conn)?)
+    })?;
+    let private_key = Ed25519Private::try_from(reporting_key.private_key.as_slice())?;
+    Ok(Ed25519Pair::from(private_key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{get_test_ledger, setup_wallet_service};
+    use mc_common::logger::{test_with_logger, Logger};
+    use mc_crypto_keys::Verifier;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test_with_logger]
+    fn test_sign_report(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let ledger_db = get_test_ledger(5, &[], 12, &mut rng);
+        let service = setup_wallet_service(ledger_db, logger);
+
+        let contents = b"cursor,txo_id_hex,value\n1,abcd,5\n";
+        let (signature, public_key) = service.sign_report(contents).unwrap();
+
+        // The key is generated once and then reused.
+        assert_eq!(service.get_reporting_public_key().unwrap(), public_key);
+        let (second_signature, second_public_key) = service.sign_report(contents).unwrap();
+        assert_eq!(second_public_key, public_key);
+        assert!(public_key.verify(contents, &second_signature).is_ok());
+
+        assert!(public_key.verify(contents, &signature).is_ok());
+        assert!(public_key
+            .verify(b"cursor,txo_id_hex,value\n1,abcd,6\n", &signature)
+            .is_err());
+    }
+}
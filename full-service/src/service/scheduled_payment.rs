@@ -0,0 +1,609 @@
+// Copyright (c) 2020-2022 MobileCoin Inc.
+
+//! Service for making payments on a schedule.
+//!
+//! A scheduled payment is made once the ledger reaches a block height, or
+//! every interval until it is cancelled. The scheduler runs the payments which
+//! are due, building and submitting each one's transaction as if it had been
+//! requested through the API.
+
+use crate::{
+    db::{
+        account::{AccountID, AccountModel},
+        models::{
+            Account, ScheduledPayment, Txo, WalletEvent, EVENT_TYPE_SCHEDULED_PAYMENT_FAILED,
+            EVENT_TYPE_SCHEDULED_PAYMENT_SUBMITTED, SCHEDULED_PAYMENT_STATUS_ACTIVE,
+        },
+        scheduled_payment::ScheduledPaymentModel,
+        transaction,
+        txo::TxoModel,
+        wallet_event::WalletEventModel,
+        Conn, WalletDbError,
+    },
+    error::WalletTransactionBuilderError,
+    service::{
+        transaction::{TransactionService, TransactionServiceError},
+        WalletService,
+    },
+    util::b58::{b58_decode_public_address, B58Error},
+};
+use chrono::Utc;
+use displaydoc::Display;
+use mc_common::logger::log;
+use mc_connection::{BlockchainConnection, UserTxConnection};
+use mc_fog_report_validation::FogPubkeyResolver;
+use mc_ledger_db::Ledger;
+use serde_json::json;
+
+#[derive(Display, Debug)]
+pub enum ScheduledPaymentServiceError {
+    /// Error interacting with the database: {0}
+    Database(WalletDbError),
+
+    /// Diesel error: {0}
+    Diesel(diesel::result::Error),
+
+    /// Error with LedgerDB: {0}
+    LedgerDB(mc_ledger_db::Error),
+
+    /// Error decoding the recipient address: {0}
+    B58(B58Error),
+
+    /// Account has been archived: {0}
+    AccountArchived(String),
+
+    /// A payment needs either an interval of at least one second, or a block
+    /// height, but not both
+    InvalidSchedule,
+
+    /// The payment value must be greater than zero
+    InvalidValue,
+
+    /// Scheduled payment {0} is no longer active
+    NotActive(i32),
+}
+
+impl From<WalletDbError> for ScheduledPaymentServiceError {
+    fn from(src: WalletDbError) -> Self {
+        Self::Database(src)
+    }
+}
+
+impl From<diesel::result::Error> for ScheduledPaymentServiceError {
+    fn from(src: diesel::result::Error) -> Self {
+        Self::Diesel(src)
+    }
+}
+
+impl From<mc_ledger_db::Error> for ScheduledPaymentServiceError {
+    fn from(src: mc_ledger_db::Error) -> Self {
+        Self::LedgerDB(src)
+    }
+}
+
+impl From<B58Error> for ScheduledPaymentServiceError {
+    fn from(src: B58Error) -> Self {
+        Self::B58(src)
+    }
+}
+
+/// Trait defining the ways in which the wallet can make scheduled payments.
+pub trait ScheduledPaymentService {
+    /// Pay the recipient from the account once the ledger has `block_height`
+    /// blocks, or every `interval_seconds` starting with the scheduler's next
+    /// run.
+    #[allow(clippy::too_many_arguments)]
+    fn create_scheduled_payment(
+        &self,
+        account_id: &AccountID,
+        recipient_address: &str,
+        value: u64,
+        token_id: u64,
+        interval_seconds: Option<u64>,
+        block_height: Option<u64>,
+        comment: Option<String>,
+    ) -> Result<ScheduledPayment, ScheduledPaymentServiceError>;
+
+    /// List the scheduled payments of an account, or of every account.
+    fn list_scheduled_payments(
+        &self,
+        account_id: Option<&AccountID>,
+    ) -> Result<Vec<ScheduledPayment>, ScheduledPaymentServiceError>;
+
+    /// Stop an active payment from being made again.
+    fn cancel_scheduled_payment(
+        &self,
+        scheduled_payment_id: i32,
+    ) -> Result<ScheduledPayment, ScheduledPaymentServiceError>;
+
+    /// Make every payment which is due.
+    ///
+    /// A payment whose account has not caught up with the ledger, or still has
+    /// a transaction in flight, is left due and tried again on the next call,
+    /// as is one which fails for a reason which may pass, such as spending
+    /// being disabled or the node being unreachable. Only a payment which
+    /// can't be made as it stands, such as one from an archived account, is
+    /// recorded as failed.
+    fn run_due_scheduled_payments(&self) -> Result<(), ScheduledPaymentServiceError>;
+}
+
+impl<T, FPR> ScheduledPaymentService for WalletService<T, FPR>
+where
+    T: BlockchainConnection + UserTxConnection + 'static,
+    FPR: FogPubkeyResolver + Send + Sync + 'static,
+{
+    fn create_scheduled_payment(
+        &self,
+        account_id: &AccountID,
+        recipient_address: &str,
+        value: u64,
+        token_id: u64,
+        interval_seconds: Option<u64>,
+        block_height: Option<u64>,
+        comment: Option<String>,
+    ) -> Result<ScheduledPayment, ScheduledPaymentServiceError> {
+        match (interval_seconds, block_height) {
+            (Some(interval_seconds), None) if interval_seconds > 0 => {}
+            (None, Some(_)) => {}
+            _ => return Err(ScheduledPaymentServiceError::InvalidSchedule),
+        }
+        if value == 0 {
+            return Err(ScheduledPaymentServiceError::InvalidValue);
+        }
+        b58_decode_public_address(recipient_address)?;
+
+        let conn = self.wallet_db.get_conn()?;
+        transaction(&conn, || {
+            let account = Account::get(account_id, &conn)?;
+            if account.archived {
+                return Err(ScheduledPaymentServiceError::AccountArchived(
+                    account_id.to_string(),
+                ));
+            }
+            let payment = ScheduledPayment::create(
+                &account_id.to_string(),
+                recipient_address,
+                value,
+                token_id,
+                interval_seconds,
+                block_height,
+                comment.as_deref().unwrap_or(""),
+                Utc::now().timestamp(),
+                &conn,
+            )?;
+            log::info!(
+                self.logger,
+                "Scheduled payment {} of {} of token {} from account {} to {}",
+                payment.id,
+                value,
+                token_id,
+                account_id,
+                recipient_address,
+            );
+            Ok(payment)
+        })
+    }
+
+    fn list_scheduled_payments(
+        &self,
+        account_id: Option<&AccountID>,
+    ) -> Result<Vec<ScheduledPayment>, ScheduledPaymentServiceError> {
+        let conn = self.wallet_db.get_conn()?;
+        let account_id_hex = account_id.map(|account_id| account_id.to_string());
+        Ok(ScheduledPayment::list(account_id_hex.as_deref(), &conn)?)
+    }
+
+    fn cancel_scheduled_payment(
+        &self,
+        scheduled_payment_id: i32,
+    ) -> Result<ScheduledPayment, ScheduledPaymentServiceError> {
+        let conn = self.wallet_db.get_conn()?;
+        transaction(&conn, || {
+            let payment = ScheduledPayment::get(scheduled_payment_id, &conn)?;
+            if payment.status != SCHEDULED_PAYMENT_STATUS_ACTIVE {
+                return Err(ScheduledPaymentServiceError::NotActive(
+                    scheduled_payment_id,
+                ));
+            }
+            payment.cancel(&conn)?;
+            log::info!(
+                self.logger,
+                "Cancelled scheduled payment {}",
+                scheduled_payment_id
+            );
+            Ok(ScheduledPayment::get(scheduled_payment_id, &conn)?)
+        })
+    }
+
+    fn run_due_scheduled_payments(&self) -> Result<(), ScheduledPaymentServiceError> {
+        let conn = self.wallet_db.get_conn()?;
+        let now = Utc::now().timestamp();
+        let num_blocks = self.ledger_db.num_blocks()?;
+
+        for payment in ScheduledPayment::list_due(now, num_blocks, &conn)? {
+            let account_id = AccountID(payment.account_id_hex.clone());
+            let account = Account::get(&account_id, &conn)?;
+
+            // Only pay once the account has seen every block and has nothing in
+            // flight, so that its balance is up to date.
+            if (account.next_block_index as u64) < num_blocks
+                || !Txo::list_pending(&payment.account_id_hex, None, None, &conn)?.is_empty()
+            {
+                continue;
+            }
+
+            let token_id = payment.token_id as u64;
+            let value = payment.value as u64;
+            // Errors are paired with whether the payment should be retried.
+            let result = if account.archived {
+                Err(("Account has been archived".to_string(), false))
+            } else {
+                let comment = if payment.comment.is_empty() {
+                    format!("Scheduled payment {}", payment.id)
+                } else {
+                    payment.comment.clone()
+                };
+                self.build_and_submit(
+                    &payment.account_id_hex,
+                    &[(payment.recipient_address.clone(), value.to_string())],
+                    None,
+                    Some(token_id.to_string()),
+                    None,
+                    None,
+                    None,
+                    None,
+                    Some(true),
+                    None,
+                    None,
+                    None,
+                    Some(comment),
+                )
+                .map(|(transaction_log, _, _)| transaction_log)
+                .map_err(|err| {
+                    let retry = is_transient(&err, &payment.account_id_hex, &conn);
+                    (err.to_string(), retry)
+                })
+            };
+
+            transaction(&conn, || {
+                match &result {
+                    Ok(transaction_log) => {
+                        payment.record_run(
+                            Some(&transaction_log.transaction_id_hex),
+                            None,
+                            now,
+                            &conn,
+                        )?;
+                        WalletEvent::create(
+                            EVENT_TYPE_SCHEDULED_PAYMENT_SUBMITTED,
+                            &json!({
+                                "account_id": payment.account_id_hex,
+                                "scheduled_payment_id": payment.id.to_string(),
+                                "transaction_log_id": transaction_log.transaction_id_hex,
+                                "recipient_address": payment.recipient_address,
+                                "token_id": token_id.to_string(),
+                                "value": value.to_string(),
+                            }),
+                            &conn,
+                        )?;
+                    }
+                    Err((err, true)) => payment.record_retry(err, now, &conn)?,
+                    Err((err, false)) => {
+                        payment.record_run(None, Some(err.as_str()), now, &conn)?;
+                        WalletEvent::create(
+                            EVENT_TYPE_SCHEDULED_PAYMENT_FAILED,
+                            &json!({
+                                "account_id": payment.account_id_hex,
+                                "scheduled_payment_id": payment.id.to_string(),
+                                "error": err,
+                            }),
+                            &conn,
+                        )?;
+                    }
+                }
+                Ok::<(), ScheduledPaymentServiceError>(())
+            })?;
+
+            match result {
+                Ok(transaction_log) => log::info!(
+                    self.logger,
+                    "Made scheduled payment {} of {} of token {} to {} in transaction {}",
+                    payment.id,
+                    value,
+                    token_id,
+                    payment.recipient_address,
+                    transaction_log.transaction_id_hex,
+                ),
+                Err((err, true)) => log::warn!(
+                    self.logger,
+                    "Scheduled payment {} will be retried: {}",
+                    payment.id,
+                    err,
+                ),
+                Err((err, false)) => log::warn!(
+                    self.logger,
+                    "Scheduled payment {} failed: {}",
+                    payment.id,
+                    err,
+                ),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Whether a payment which failed to build or submit may succeed if it is
+/// tried again: the wallet or the network could not send it for now, rather
+/// than the payment being one which can't be made.
+fn is_transient(err: &TransactionServiceError, account_id_hex: &str, conn: &Conn) -> bool {
+    match err {
+        TransactionServiceError::SpendingDisabled
+        | TransactionServiceError::Offline
+        | TransactionServiceError::NodeNotFound
+        | TransactionServiceError::NoPeersConfigured
+        | TransactionServiceError::Connection(_)
+        | TransactionServiceError::Diesel(_)
+        | TransactionServiceError::LedgerDB(_)
+        | TransactionServiceError::Database(WalletDbError::Diesel(_))
+        | TransactionServiceError::TransactionBuilder(
+            WalletTransactionBuilderError::FogPubkeyResolver(_),
+        ) => true,
+        // Funds spent by a transaction which is still in flight come back as
+        // change once it lands.
+        TransactionServiceError::Database(WalletDbError::NoSpendableTxos)
+        | TransactionServiceError::Database(WalletDbError::InsufficientFunds(_))
+        | TransactionServiceError::TransactionBuilder(WalletTransactionBuilderError::WalletDb(
+            WalletDbError::NoSpendableTxos,
+        ))
+        | TransactionServiceError::TransactionBuilder(WalletTransactionBuilderError::WalletDb(
+            WalletDbError::InsufficientFunds(_),
+        )) => Txo::list_pending(account_id_hex, None, None, conn)
+            .map(|pending| !pending.is_empty())
+            .unwrap_or(true),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        db::{
+            models::{
+                TransactionLog, SCHEDULED_PAYMENT_STATUS_CANCELLED,
+                SCHEDULED_PAYMENT_STATUS_COMPLETED,
+            },
+            transaction_log::TransactionLogModel,
+        },
+        service::{
+            account::AccountService,
+            spending_lock::{SpendingLock, SpendingLockService},
+            WalletServiceOptions,
+        },
+        test_utils::{
+            add_block_from_transaction_log, add_block_to_ledger_db, get_test_ledger,
+            manually_sync_account, setup_wallet_service, setup_wallet_service_with, MOB,
+        },
+        util::b58::b58_encode_public_address,
+    };
+    use mc_account_keys::AccountKey;
+    use mc_common::logger::{test_with_logger, Logger};
+    use mc_crypto_rand::rand_core::RngCore;
+    use mc_transaction_core::{ring_signature::KeyImage, tokens::Mob, Token};
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test_with_logger]
+    fn test_run_due_scheduled_payments(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let mut ledger_db = get_test_ledger(5, &vec![], 12, &mut rng);
+        let service = setup_wallet_service(ledger_db.clone(), logger.clone());
+        let conn = service.wallet_db.get_conn().unwrap();
+
+        let alice = service
+            .create_account(
+                Some("Alice's Main Account".to_string()),
+                "".to_string(),
+                "".to_string(),
+                "".to_string(),
+            )
+            .unwrap();
+        let alice_id = AccountID(alice.account_id_hex.clone());
+        let alice_account_key: AccountKey = mc_util_serial::decode(&alice.account_key).unwrap();
+        let bob_address =
+            b58_encode_public_address(&AccountKey::random(&mut rng).default_subaddress()).unwrap();
+
+        add_block_to_ledger_db(
+            &mut ledger_db,
+            &vec![alice_account_key.subaddress(alice.main_subaddress_index as u64)],
+            100 * MOB,
+            &vec![KeyImage::from(rng.next_u64())],
+            &mut rng,
+        );
+        manually_sync_account(&ledger_db, &service.wallet_db, &alice_id, &logger);
+
+        assert!(service
+            .create_scheduled_payment(&alice_id, &bob_address, MOB, *Mob::ID, None, None, None)
+            .is_err());
+        assert!(service
+            .create_scheduled_payment(
+                &alice_id,
+                &bob_address,
+                MOB,
+                *Mob::ID,
+                Some(3600),
+                Some(20),
+                None
+            )
+            .is_err());
+        assert!(service
+            .create_scheduled_payment(&alice_id, &bob_address, 0, *Mob::ID, Some(3600), None, None)
+            .is_err());
+        assert!(service
+            .create_scheduled_payment(
+                &alice_id,
+                "not an address",
+                MOB,
+                *Mob::ID,
+                Some(3600),
+                None,
+                None
+            )
+            .is_err());
+
+        // A recurring payment is made straight away.
+        let recurring = service
+            .create_scheduled_payment(
+                &alice_id,
+                &bob_address,
+                10 * MOB,
+                *Mob::ID,
+                Some(3600),
+                None,
+                Some("Rent".to_string()),
+            )
+            .unwrap();
+        service.run_due_scheduled_payments().unwrap();
+        let recurring = ScheduledPayment::get(recurring.id, &conn).unwrap();
+        assert_eq!(recurring.payments_submitted, 1);
+        assert_eq!(recurring.status, SCHEDULED_PAYMENT_STATUS_ACTIVE);
+        assert_eq!(recurring.last_failure_message, None);
+        let transaction_log =
+            TransactionLog::get(recurring.last_transaction_log_id.as_ref().unwrap(), &conn)
+                .unwrap();
+        assert_eq!(transaction_log.comment, "Rent");
+
+        let events = WalletEvent::list(None, None, &conn).unwrap();
+        let event = events
+            .iter()
+            .find(|event| event.event_type == EVENT_TYPE_SCHEDULED_PAYMENT_SUBMITTED)
+            .unwrap();
+        let payload: serde_json::Value = serde_json::from_str(&event.payload).unwrap();
+        assert_eq!(payload["scheduled_payment_id"], recurring.id.to_string());
+        assert_eq!(payload["value"], (10 * MOB).to_string());
+
+        add_block_from_transaction_log(&mut ledger_db, &conn, &transaction_log);
+        manually_sync_account(&ledger_db, &service.wallet_db, &alice_id, &logger);
+
+        // A one-off payment waits for its block height.
+        let block_height = ledger_db.num_blocks().unwrap() + 1;
+        let one_off = service
+            .create_scheduled_payment(
+                &alice_id,
+                &bob_address,
+                5 * MOB,
+                *Mob::ID,
+                None,
+                Some(block_height),
+                None,
+            )
+            .unwrap();
+        service.run_due_scheduled_payments().unwrap();
+        let one_off = ScheduledPayment::get(one_off.id, &conn).unwrap();
+        assert_eq!(one_off.payments_submitted, 0);
+
+        add_block_to_ledger_db(
+            &mut ledger_db,
+            &vec![alice_account_key.subaddress(alice.main_subaddress_index as u64)],
+            MOB,
+            &vec![KeyImage::from(rng.next_u64())],
+            &mut rng,
+        );
+        manually_sync_account(&ledger_db, &service.wallet_db, &alice_id, &logger);
+        service.run_due_scheduled_payments().unwrap();
+        let one_off = ScheduledPayment::get(one_off.id, &conn).unwrap();
+        assert_eq!(one_off.payments_submitted, 1);
+        assert_eq!(one_off.status, SCHEDULED_PAYMENT_STATUS_COMPLETED);
+
+        // The recurring payment is not due again until its interval has passed.
+        assert_eq!(
+            ScheduledPayment::get(recurring.id, &conn).unwrap(),
+            recurring
+        );
+
+        assert_eq!(
+            service
+                .list_scheduled_payments(Some(&alice_id))
+                .unwrap()
+                .len(),
+            2
+        );
+        let cancelled = service.cancel_scheduled_payment(recurring.id).unwrap();
+        assert_eq!(cancelled.status, SCHEDULED_PAYMENT_STATUS_CANCELLED);
+        assert!(service.cancel_scheduled_payment(recurring.id).is_err());
+        assert!(service.cancel_scheduled_payment(one_off.id).is_err());
+    }
+
+    #[test_with_logger]
+    fn test_scheduled_payment_retried_while_spending_disabled(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let mut ledger_db = get_test_ledger(5, &vec![], 12, &mut rng);
+        let service = setup_wallet_service_with(
+            ledger_db.clone(),
+            WalletServiceOptions {
+                spending_lock: SpendingLock::new(false, Some("admin".to_string())),
+                ..Default::default()
+            },
+            logger.clone(),
+        );
+        let conn = service.wallet_db.get_conn().unwrap();
+
+        let alice = service
+            .create_account(
+                Some("Alice's Main Account".to_string()),
+                "".to_string(),
+                "".to_string(),
+                "".to_string(),
+            )
+            .unwrap();
+        let alice_id = AccountID(alice.account_id_hex.clone());
+        let alice_account_key: AccountKey = mc_util_serial::decode(&alice.account_key).unwrap();
+        let bob_address =
+            b58_encode_public_address(&AccountKey::random(&mut rng).default_subaddress()).unwrap();
+
+        add_block_to_ledger_db(
+            &mut ledger_db,
+            &vec![alice_account_key.subaddress(alice.main_subaddress_index as u64)],
+            100 * MOB,
+            &vec![KeyImage::from(rng.next_u64())],
+            &mut rng,
+        );
+        manually_sync_account(&ledger_db, &service.wallet_db, &alice_id, &logger);
+
+        let payment = service
+            .create_scheduled_payment(
+                &alice_id,
+                &bob_address,
+                10 * MOB,
+                *Mob::ID,
+                Some(3600),
+                None,
+                None,
+            )
+            .unwrap();
+
+        // While spending is disabled the payment is left due, rather than
+        // skipped until its next interval.
+        service.disable_spending().unwrap();
+        service.run_due_scheduled_payments().unwrap();
+        let retried = ScheduledPayment::get(payment.id, &conn).unwrap();
+        assert_eq!(retried.next_run_time, payment.next_run_time);
+        assert_eq!(retried.status, SCHEDULED_PAYMENT_STATUS_ACTIVE);
+        assert_eq!(retried.payments_submitted, 0);
+        assert!(retried.last_failure_message.is_some());
+        assert!(WalletEvent::list(None, None, &conn)
+            .unwrap()
+            .iter()
+            .all(|event| event.event_type != EVENT_TYPE_SCHEDULED_PAYMENT_FAILED));
+
+        // Once spending is enabled again it is made on the next run.
+        service.enable_spending("admin").unwrap();
+        service.run_due_scheduled_payments().unwrap();
+        let made = ScheduledPayment::get(payment.id, &conn).unwrap();
+        assert_eq!(made.payments_submitted, 1);
+        assert!(made.next_run_time > payment.next_run_time);
+        assert!(made.last_transaction_log_id.is_some());
+    }
+}
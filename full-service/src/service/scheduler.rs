@@ -0,0 +1,221 @@
+// Copyright (c) 2020-2022 MobileCoin Inc.
+
+//! Scheduler for the wallet's periodic tasks.
+//!
+//! The scheduler thread runs alongside the API with its own handle to the
+//! wallet service, so that it can build and submit transactions. Each task
+//! keeps track of when it is next due in the wallet database, so that the
+//! schedule survives restarts. Checks which only report on the wallet's
+//! surroundings are instead run on a fixed interval from when the scheduler
+//! starts, as is the delivery of webhooks, which keep their own place in the
+//! event log. No tasks are run while the wallet is in maintenance.
+
+use crate::service::{
+    account_snapshot::AccountSnapshotService, continuity::ContinuityService,
+    expired_transaction::ExpiredTransactionService, gift_code::GiftCodeService,
+    peer_attestation::PeerAttestationService, scheduled_payment::ScheduledPaymentService,
+    sweep_policy::SweepPolicyService, upgrade_readiness::UpgradeReadinessService,
+    usage_stats::UsageStatsService, webhook::WebhookService, WalletService,
+};
+use mc_common::logger::{log, Logger};
+use mc_connection::{BlockchainConnection, UserTxConnection};
+use mc_fog_report_validation::FogPubkeyResolver;
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+/// How often the scheduler checks for due tasks.
+const SCHEDULER_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How often the scheduler checks that this build is ready for the network's
+/// next upgrade.
+const UPGRADE_READINESS_CHECK_INTERVAL: Duration = Duration::from_secs(600);
+
+/// How often the scheduler samples the sizes of the wallet and ledger
+/// databases, for reporting their growth.
+const DB_SIZE_SAMPLE_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// How often the scheduler checks whether the operator has missed a
+/// continuity check-in.
+const CONTINUITY_EXPORT_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// How often the scheduler checks for sent transactions which passed their
+/// tombstone block without landing.
+const EXPIRED_TRANSACTION_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// How often the scheduler checks for claim links which expired unclaimed, to
+/// sweep them back to their funding accounts.
+const GIFT_CODE_EXPIRY_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// How often the scheduler records the changes in the consensus peers'
+/// attestations which the pool keeper has seen.
+const PEER_ATTESTATION_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// How often the scheduler snapshots the accounts which have synced past
+/// their next snapshot block.
+const ACCOUNT_SNAPSHOT_INTERVAL: Duration = Duration::from_secs(60);
+
+/// How often the scheduler delivers pending events to webhooks, and so how
+/// long a failed delivery waits before it is retried.
+const WEBHOOK_DELIVERY_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Scheduler thread - holds objects needed to cleanly terminate the thread.
+pub struct SchedulerThread {
+    /// The scheduler thread handle.
+    join_handle: Option<thread::JoinHandle<()>>,
+
+    /// Stop trigger, used to signal the thread to terminate.
+    stop_requested: Arc<AtomicBool>,
+}
+
+impl SchedulerThread {
+    pub fn start<T, FPR>(
+        service: WalletService<T, FPR>,
+        enable_webhooks: bool,
+        resubmit_expired_transactions: bool,
+        account_snapshot_interval_blocks: u64,
+        logger: Logger,
+    ) -> Self
+    where
+        T: BlockchainConnection + UserTxConnection + 'static,
+        FPR: FogPubkeyResolver + Send + Sync + 'static,
+    {
+        let stop_requested = Arc::new(AtomicBool::new(false));
+        let thread_stop_requested = stop_requested.clone();
+
+        let join_handle = Some(
+            thread::Builder::new()
+                .name("scheduler".to_string())
+                .spawn(move || {
+                    log::debug!(logger, "Scheduler thread started.");
+                    let mut last_upgrade_readiness_check: Option<Instant> = None;
+                    let mut last_webhook_delivery: Option<Instant> = None;
+                    let mut last_db_size_sample: Option<Instant> = None;
+                    let mut last_continuity_export_check: Option<Instant> = None;
+                    let mut last_expired_transaction_check: Option<Instant> = None;
+                    let mut last_peer_attestation_check: Option<Instant> = None;
+                    let mut last_gift_code_expiry_check: Option<Instant> = None;
+                    let mut last_account_snapshot: Option<Instant> = None;
+
+                    loop {
+                        if thread_stop_requested.load(Ordering::SeqCst) {
+                            log::debug!(logger, "SchedulerThread stop requested.");
+                            break;
+                        }
+                        // Tasks which came due during maintenance run once it
+                        // ends.
+                        if service.maintenance_mode.is_active() {
+                            thread::sleep(SCHEDULER_POLL_INTERVAL);
+                            continue;
+                        }
+                        if let Err(e) = service.run_due_sweeps() {
+                            log::error!(&logger, "Error running sweep policies: {}", e);
+                        }
+                        if let Err(e) = service.run_due_scheduled_payments() {
+                            log::error!(&logger, "Error running scheduled payments: {}", e);
+                        }
+                        if last_upgrade_readiness_check.map_or(true, |last| {
+                            last.elapsed() >= UPGRADE_READINESS_CHECK_INTERVAL
+                        }) {
+                            if let Err(e) = service.check_upgrade_readiness() {
+                                log::error!(&logger, "Error checking upgrade readiness: {}", e);
+                            }
+                            last_upgrade_readiness_check = Some(Instant::now());
+                        }
+                        if last_db_size_sample
+                            .map_or(true, |last| last.elapsed() >= DB_SIZE_SAMPLE_INTERVAL)
+                        {
+                            if let Err(e) = service.sample_db_sizes() {
+                                log::error!(&logger, "Error sampling database sizes: {}", e);
+                            }
+                            last_db_size_sample = Some(Instant::now());
+                        }
+                        if last_continuity_export_check.map_or(true, |last| {
+                            last.elapsed() >= CONTINUITY_EXPORT_CHECK_INTERVAL
+                        }) {
+                            if let Err(e) = service.run_due_continuity_export() {
+                                log::error!(&logger, "Error writing continuity export: {}", e);
+                            }
+                            last_continuity_export_check = Some(Instant::now());
+                        }
+                        if last_expired_transaction_check.map_or(true, |last| {
+                            last.elapsed() >= EXPIRED_TRANSACTION_CHECK_INTERVAL
+                        }) {
+                            if let Err(e) =
+                                service.resolve_expired_transactions(resubmit_expired_transactions)
+                            {
+                                log::error!(&logger, "Error resolving expired transactions: {}", e);
+                            }
+                            last_expired_transaction_check = Some(Instant::now());
+                        }
+                        if last_gift_code_expiry_check.map_or(true, |last| {
+                            last.elapsed() >= GIFT_CODE_EXPIRY_CHECK_INTERVAL
+                        }) {
+                            if let Err(e) = service.reclaim_expired_gift_codes() {
+                                log::error!(&logger, "Error reclaiming expired gift codes: {}", e);
+                            }
+                            last_gift_code_expiry_check = Some(Instant::now());
+                        }
+                        if last_account_snapshot
+                            .map_or(true, |last| last.elapsed() >= ACCOUNT_SNAPSHOT_INTERVAL)
+                        {
+                            if let Err(e) =
+                                service.record_account_snapshots(account_snapshot_interval_blocks)
+                            {
+                                log::error!(&logger, "Error snapshotting accounts: {}", e);
+                            }
+                            last_account_snapshot = Some(Instant::now());
+                        }
+                        if last_peer_attestation_check.map_or(true, |last| {
+                            last.elapsed() >= PEER_ATTESTATION_CHECK_INTERVAL
+                        }) {
+                            if let Err(e) = service.record_peer_attestation_changes() {
+                                log::error!(
+                                    &logger,
+                                    "Error recording peer attestation changes: {}",
+                                    e
+                                );
+                            }
+                            last_peer_attestation_check = Some(Instant::now());
+                        }
+                        if enable_webhooks
+                            && last_webhook_delivery
+                                .map_or(true, |last| last.elapsed() >= WEBHOOK_DELIVERY_INTERVAL)
+                        {
+                            if let Err(e) = service.deliver_webhooks() {
+                                log::error!(&logger, "Error delivering webhooks: {}", e);
+                            }
+                            last_webhook_delivery = Some(Instant::now());
+                        }
+
+                        thread::sleep(SCHEDULER_POLL_INTERVAL);
+                    }
+                    log::debug!(logger, "SchedulerThread stopped.");
+                })
+                .expect("failed starting scheduler thread"),
+        );
+
+        Self {
+            join_handle,
+            stop_requested,
+        }
+    }
+
+    pub fn stop(&mut self) {
+        self.stop_requested.store(true, Ordering::SeqCst);
+        if let Some(join_handle) = self.join_handle.take() {
+            join_handle.join().expect("SchedulerThread join failed");
+        }
+    }
+}
+
+impl Drop for SchedulerThread {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
@@ -0,0 +1,347 @@
+// Copyright (c) 2020-2022 MobileCoin Inc.
+
+//! Service for disabling spending wallet-wide.
+//!
+//! An operator responding to a suspected compromise can disable spending,
+//! which blocks every path that submits a transaction to the network while
+//! leaving read access untouched. Anyone with access to the API can disable
+//! spending, but re-enabling it requires the admin key, which is read from the
+//! MC_ADMIN_API_KEY environment variable at startup.
+//!
+//! Disabling spending is recorded in the wallet database, so spending stays
+//! disabled across a restart until it is re-enabled with the admin key. A
+//! wallet started with `--disable-spending` is locked until it is restarted
+//! without it.
+
+use crate::{
+    db::{
+        models::{
+            WalletEvent, WalletSetting, EVENT_TYPE_SPENDING_DISABLED, EVENT_TYPE_SPENDING_ENABLED,
+        },
+        wallet_event::WalletEventModel,
+        wallet_settings::WalletSettingModel,
+        Conn, WalletDbError,
+    },
+    util::secret::{AdminKey, AdminKeyError},
+    WalletService,
+};
+use displaydoc::Display;
+use mc_common::logger::log;
+use mc_connection::{BlockchainConnection, UserTxConnection};
+use mc_fog_report_validation::FogPubkeyResolver;
+use serde_json::json;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+/// Errors for the Spending Lock Service.
+#[derive(Display, Debug)]
+pub enum SpendingLockServiceError {
+    /// Error interacting with the database: {0}
+    Database(WalletDbError),
+
+    /// No admin key is configured to re-enable spending
+    NoAdminKey,
+
+    /// The admin key is incorrect
+    InvalidAdminKey,
+}
+
+impl From<WalletDbError> for SpendingLockServiceError {
+    fn from(src: WalletDbError) -> Self {
+        Self::Database(src)
+    }
+}
+
+impl From<AdminKeyError> for SpendingLockServiceError {
+    fn from(src: AdminKeyError) -> Self {
+        match src {
+            AdminKeyError::NotConfigured => Self::NoAdminKey,
+            AdminKeyError::Invalid => Self::InvalidAdminKey,
+        }
+    }
+}
+
+/// A shared switch which, while engaged, blocks transaction submission.
+#[derive(Clone, Default)]
+pub struct SpendingLock {
+    disabled: Arc<AtomicBool>,
+    admin_key: AdminKey,
+}
+
+impl SpendingLock {
+    pub fn new(disabled: bool, admin_key: Option<String>) -> Self {
+        Self {
+            disabled: Arc::new(AtomicBool::new(disabled)),
+            admin_key: AdminKey::new(admin_key),
+        }
+    }
+
+    /// Whether spending is currently disabled.
+    pub fn is_disabled(&self) -> bool {
+        self.disabled.load(Ordering::SeqCst)
+    }
+
+    /// Disable spending, without recording it in the database.
+    pub fn disable(&self) {
+        self.disabled.store(true, Ordering::SeqCst);
+    }
+
+    /// Disable spending if it was disabled before the wallet was restarted.
+    pub fn restore(&self, conn: &Conn) -> Result<(), WalletDbError> {
+        if WalletSetting::is_spending_disabled(conn)? {
+            self.disabled.store(true, Ordering::SeqCst);
+        }
+        Ok(())
+    }
+}
+
+/// Trait defining the ways in which the wallet can disable and re-enable
+/// spending.
+pub trait SpendingLockService {
+    /// Block all transaction submission until spending is re-enabled.
+    ///
+    /// Returns whether spending is disabled, which is always true.
+    fn disable_spending(&self) -> Result<bool, SpendingLockServiceError>;
+
+    /// Allow transaction submission again, given the admin key.
+    ///
+    /// Returns whether spending is disabled, which is always false.
+    fn enable_spending(&self, admin_key: &str) -> Result<bool, SpendingLockServiceError>;
+
+    /// Whether spending is currently disabled.
+    fn is_spending_disabled(&self) -> bool;
+}
+
+impl<T, FPR> SpendingLockService for WalletService<T, FPR>
+where
+    T: BlockchainConnection + UserTxConnection + 'static,
+    FPR: FogPubkeyResolver + Send + Sync + 'static,
+{
+    fn disable_spending(&self) -> Result<bool, SpendingLockServiceError> {
+        // Disable spending before recording it, so that it is disabled even if
+        // the database can't be written.
+        let was_disabled = self.spending_lock.disabled.swap(true, Ordering::SeqCst);
+        let conn = self.wallet_db.get_conn()?;
+        WalletSetting::set_spending_disabled(true, &conn)?;
+        if !was_disabled {
+            log::warn!(self.logger, "Spending has been disabled");
+            WalletEvent::create(EVENT_TYPE_SPENDING_DISABLED, &json!({}), &conn)?;
+        }
+        Ok(true)
+    }
+
+    fn enable_spending(&self, admin_key: &str) -> Result<bool, SpendingLockServiceError> {
+        self.spending_lock.admin_key.check(admin_key)?;
+
+        // Clear the record first, so that spending is never enabled in memory
+        // while it would still be disabled after a restart.
+        let conn = self.wallet_db.get_conn()?;
+        WalletSetting::set_spending_disabled(false, &conn)?;
+        if self.spending_lock.disabled.swap(false, Ordering::SeqCst) {
+            log::warn!(self.logger, "Spending has been re-enabled");
+            WalletEvent::create(EVENT_TYPE_SPENDING_ENABLED, &json!({}), &conn)?;
+        }
+        Ok(false)
+    }
+
+    fn is_spending_disabled(&self) -> bool {
+        self.spending_lock.is_disabled()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        db::account::AccountID,
+        service::{
+            account::AccountService,
+            address::AddressService,
+            transaction::{TransactionService, TransactionServiceError},
+            WalletServiceOptions,
+        },
+        test_utils::{
+            add_block_to_ledger_db, get_test_ledger, manually_sync_account,
+            setup_wallet_service_with, MOB,
+        },
+    };
+    use mc_account_keys::{AccountKey, PublicAddress};
+    use mc_common::logger::{test_with_logger, Logger};
+    use mc_crypto_rand::RngCore;
+    use mc_transaction_core::ring_signature::KeyImage;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test_with_logger]
+    fn test_disable_spending_blocks_submit(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let mut ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        let service = setup_wallet_service_with(
+            ledger_db.clone(),
+            WalletServiceOptions {
+                spending_lock: SpendingLock::new(false, Some("admin".to_string())),
+                ..Default::default()
+            },
+            logger.clone(),
+        );
+        let alice = service
+            .create_account(
+                Some("Alice's Main Account".to_string()),
+                "".to_string(),
+                "".to_string(),
+                "".to_string(),
+            )
+            .unwrap();
+        let alice_account_key: AccountKey = mc_util_serial::decode(&alice.account_key).unwrap();
+        let alice_public_address = alice_account_key.subaddress(alice.main_subaddress_index as u64);
+        add_block_to_ledger_db(
+            &mut ledger_db,
+            &vec![alice_public_address],
+            100 * MOB,
+            &vec![KeyImage::from(rng.next_u64())],
+            &mut rng,
+        );
+        manually_sync_account(
+            &ledger_db,
+            &service.wallet_db,
+            &AccountID(alice.account_id_hex.to_string()),
+            &logger,
+        );
+        let alice_address = service
+            .get_addresses_for_account(&AccountID(alice.account_id_hex.clone()), None, None)
+            .unwrap()[0]
+            .assigned_subaddress_b58
+            .clone();
+
+        assert!(!service.is_spending_disabled());
+        assert!(service.disable_spending().unwrap());
+        assert!(service.is_spending_disabled());
+
+        assert!(matches!(
+            service.build_and_submit(
+                &alice.account_id_hex,
+                &vec![(alice_address.clone(), (42 * MOB).to_string())],
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+            ),
+            Err(TransactionServiceError::SpendingDisabled)
+        ));
+
+        // Building a transaction is still allowed, but submitting it is not.
+        let tx_proposal = service
+            .build_transaction(
+                &alice.account_id_hex,
+                &vec![(alice_address, (42 * MOB).to_string())],
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+            )
+            .unwrap();
+        assert!(matches!(
+            service.submit_transaction(
+                tx_proposal.clone(),
+                None,
+                Some(alice.account_id_hex.clone())
+            ),
+            Err(TransactionServiceError::SpendingDisabled)
+        ));
+
+        // Re-enabling requires the admin key.
+        assert!(matches!(
+            service.enable_spending("not admin"),
+            Err(SpendingLockServiceError::InvalidAdminKey)
+        ));
+        assert!(service.is_spending_disabled());
+        assert!(!service.enable_spending("admin").unwrap());
+        assert!(!service.is_spending_disabled());
+        service
+            .submit_transaction(tx_proposal, None, Some(alice.account_id_hex.clone()))
+            .unwrap();
+
+        let events = WalletEvent::list(None, None, &service.wallet_db.get_conn().unwrap()).unwrap();
+        let event_types: Vec<&str> = events
+            .iter()
+            .map(|event| event.event_type.as_str())
+            .filter(|event_type| event_type.starts_with("spending_"))
+            .collect();
+        assert_eq!(
+            event_types,
+            vec![EVENT_TYPE_SPENDING_DISABLED, EVENT_TYPE_SPENDING_ENABLED]
+        );
+    }
+
+    #[test_with_logger]
+    fn test_disabled_spending_survives_restart(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        let service = setup_wallet_service_with(
+            ledger_db,
+            WalletServiceOptions {
+                spending_lock: SpendingLock::new(false, Some("admin".to_string())),
+                ..Default::default()
+            },
+            logger,
+        );
+        let conn = service.wallet_db.get_conn().unwrap();
+        assert!(service.disable_spending().unwrap());
+
+        // A restarted wallet's lock starts out enabled, and is restored from
+        // the database.
+        let restarted = SpendingLock::new(false, Some("admin".to_string()));
+        restarted.restore(&conn).unwrap();
+        assert!(restarted.is_disabled());
+
+        // Once re-enabled, a restart leaves spending enabled.
+        assert!(!service.enable_spending("admin").unwrap());
+        let restarted = SpendingLock::new(false, Some("admin".to_string()));
+        restarted.restore(&conn).unwrap();
+        assert!(!restarted.is_disabled());
+    }
+
+    #[test_with_logger]
+    fn test_enable_spending_without_admin_key(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        let service = setup_wallet_service_with(
+            ledger_db,
+            WalletServiceOptions {
+                spending_lock: SpendingLock::new(true, Some("".to_string())),
+                ..Default::default()
+            },
+            logger,
+        );
+        assert!(service.is_spending_disabled());
+        assert!(matches!(
+            service.enable_spending(""),
+            Err(SpendingLockServiceError::NoAdminKey)
+        ));
+        assert!(service.is_spending_disabled());
+    }
+}
@@ -0,0 +1,399 @@
+// Copyright (c) 2020-2022 MobileCoin Inc.
+
+//! Service for sweeping accounts' excess balances to cold storage.
+//!
+//! Each account may have a sweep policy, which the scheduler runs every so
+//! often. A run sends whatever the account holds of the policy's token above
+//! the threshold to the destination address, less the fee, so that the
+//! account is left holding the threshold. Sweeps which would move no more than
+//! the wallet's dust threshold are skipped.
+
+use crate::{
+    db::{
+        account::{AccountID, AccountModel},
+        models::{
+            Account, SweepPolicy, Txo, WalletEvent, WalletSetting, EVENT_TYPE_AUTO_SWEEP_FAILED,
+            EVENT_TYPE_AUTO_SWEEP_SUBMITTED,
+        },
+        sweep_policy::SweepPolicyModel,
+        transaction,
+        txo::TxoModel,
+        wallet_event::WalletEventModel,
+        wallet_settings::WalletSettingModel,
+        WalletDbError,
+    },
+    service::{ledger::LedgerService, transaction::TransactionService, WalletService},
+    util::b58::{b58_decode_public_address, B58Error},
+};
+use chrono::Utc;
+use displaydoc::Display;
+use mc_common::logger::log;
+use mc_connection::{BlockchainConnection, UserTxConnection};
+use mc_fog_report_validation::FogPubkeyResolver;
+use mc_ledger_db::Ledger;
+use mc_transaction_core::TokenId;
+use serde_json::json;
+
+#[derive(Display, Debug)]
+pub enum SweepPolicyServiceError {
+    /// Error interacting with the database: {0}
+    Database(WalletDbError),
+
+    /// Diesel error: {0}
+    Diesel(diesel::result::Error),
+
+    /// Error with LedgerDB: {0}
+    LedgerDB(mc_ledger_db::Error),
+
+    /// Error decoding the destination address: {0}
+    B58(B58Error),
+
+    /// Account has been archived: {0}
+    AccountArchived(String),
+
+    /// The sweep interval must be at least one second
+    InvalidInterval,
+}
+
+impl From<WalletDbError> for SweepPolicyServiceError {
+    fn from(src: WalletDbError) -> Self {
+        Self::Database(src)
+    }
+}
+
+impl From<diesel::result::Error> for SweepPolicyServiceError {
+    fn from(src: diesel::result::Error) -> Self {
+        Self::Diesel(src)
+    }
+}
+
+impl From<mc_ledger_db::Error> for SweepPolicyServiceError {
+    fn from(src: mc_ledger_db::Error) -> Self {
+        Self::LedgerDB(src)
+    }
+}
+
+impl From<B58Error> for SweepPolicyServiceError {
+    fn from(src: B58Error) -> Self {
+        Self::B58(src)
+    }
+}
+
+/// Trait defining the ways in which the wallet can sweep accounts.
+pub trait SweepPolicyService {
+    /// Sweep the account's balance of a token above the threshold to the
+    /// destination address every interval, starting with the scheduler's next
+    /// run. Replaces the account's existing policy, if any.
+    fn set_sweep_policy(
+        &self,
+        account_id: &AccountID,
+        destination_address: &str,
+        token_id: u64,
+        threshold: u64,
+        interval_seconds: u64,
+    ) -> Result<SweepPolicy, SweepPolicyServiceError>;
+
+    /// Get the account's sweep policy.
+    fn get_sweep_policy(
+        &self,
+        account_id: &AccountID,
+    ) -> Result<SweepPolicy, SweepPolicyServiceError>;
+
+    /// Stop sweeping the account.
+    fn remove_sweep_policy(&self, account_id: &AccountID) -> Result<bool, SweepPolicyServiceError>;
+
+    /// Run every policy which is due.
+    ///
+    /// A policy whose account has not caught up with the ledger, or still has
+    /// a transaction in flight, is left due and tried again on the next call.
+    fn run_due_sweeps(&self) -> Result<(), SweepPolicyServiceError>;
+}
+
+impl<T, FPR> SweepPolicyService for WalletService<T, FPR>
+where
+    T: BlockchainConnection + UserTxConnection + 'static,
+    FPR: FogPubkeyResolver + Send + Sync + 'static,
+{
+    fn set_sweep_policy(
+        &self,
+        account_id: &AccountID,
+        destination_address: &str,
+        token_id: u64,
+        threshold: u64,
+        interval_seconds: u64,
+    ) -> Result<SweepPolicy, SweepPolicyServiceError> {
+        if interval_seconds == 0 {
+            return Err(SweepPolicyServiceError::InvalidInterval);
+        }
+        b58_decode_public_address(destination_address)?;
+
+        let conn = self.wallet_db.get_conn()?;
+        transaction(&conn, || {
+            let account = Account::get(account_id, &conn)?;
+            if account.archived {
+                return Err(SweepPolicyServiceError::AccountArchived(
+                    account_id.to_string(),
+                ));
+            }
+            let policy = SweepPolicy::set(
+                &account_id.to_string(),
+                destination_address,
+                token_id,
+                threshold,
+                interval_seconds,
+                Utc::now().timestamp(),
+                &conn,
+            )?;
+            log::info!(
+                self.logger,
+                "Sweeping account {} above {} of token {} to {} every {} seconds",
+                account_id,
+                threshold,
+                token_id,
+                destination_address,
+                interval_seconds,
+            );
+            Ok(policy)
+        })
+    }
+
+    fn get_sweep_policy(
+        &self,
+        account_id: &AccountID,
+    ) -> Result<SweepPolicy, SweepPolicyServiceError> {
+        let conn = self.wallet_db.get_conn()?;
+        Ok(SweepPolicy::get(&account_id.to_string(), &conn)?)
+    }
+
+    fn remove_sweep_policy(&self, account_id: &AccountID) -> Result<bool, SweepPolicyServiceError> {
+        let conn = self.wallet_db.get_conn()?;
+        transaction(&conn, || {
+            SweepPolicy::get(&account_id.to_string(), &conn)?;
+            SweepPolicy::delete_for_account(&account_id.to_string(), &conn)?;
+            Ok(true)
+        })
+    }
+
+    fn run_due_sweeps(&self) -> Result<(), SweepPolicyServiceError> {
+        let conn = self.wallet_db.get_conn()?;
+        let now = Utc::now().timestamp();
+        let num_blocks = self.ledger_db.num_blocks()?;
+        let minimum_fees = self.get_network_minimum_fees();
+        let dust_threshold = WalletSetting::get_settings(&conn)?.dust_threshold as u128;
+
+        for policy in SweepPolicy::list_due(now, &conn)? {
+            let account_id = AccountID(policy.account_id_hex.clone());
+            let account = Account::get(&account_id, &conn)?;
+
+            // Only sweep once the account has seen every block and has nothing
+            // in flight, so that its balance is up to date.
+            if (account.next_block_index as u64) < num_blocks
+                || !Txo::list_pending(&policy.account_id_hex, None, None, &conn)?.is_empty()
+            {
+                continue;
+            }
+
+            // Frozen txos are left where they are.
+            let token_id = policy.token_id as u64;
+            let balance: u128 = Txo::list_unspent(&policy.account_id_hex, None, None, &conn)?
+                .iter()
+                .filter(|txo| txo.token_id as u64 == token_id && !txo.frozen)
+                .map(|txo| txo.value as u64 as u128)
+                .sum();
+
+            let result = if account.archived {
+                Err("Account has been archived".to_string())
+            } else {
+                match minimum_fees.get(&TokenId::from(token_id)) {
+                    None => Err(format!("No known fee for token {}", token_id)),
+                    Some(fee) => {
+                        let excess = balance.saturating_sub(policy.threshold as u64 as u128);
+                        if excess <= *fee as u128 + dust_threshold {
+                            Ok(None)
+                        } else {
+                            let value = (excess - *fee as u128).min(u64::MAX as u128) as u64;
+                            self.build_and_submit(
+                                &policy.account_id_hex,
+                                &[(policy.destination_address.clone(), value.to_string())],
+                                None,
+                                Some(token_id.to_string()),
+                                Some(fee.to_string()),
+                                None,
+                                None,
+                                None,
+                                Some(true),
+                                None,
+                                None,
+                                None,
+                                Some(format!("Automatic sweep to {}", policy.destination_address)),
+                            )
+                            .map(|(transaction_log, _, _)| Some((transaction_log, value)))
+                            .map_err(|err| err.to_string())
+                        }
+                    }
+                }
+            };
+
+            transaction(&conn, || {
+                match &result {
+                    Ok(Some((transaction_log, value))) => {
+                        policy.record_run(
+                            Some(&transaction_log.transaction_id_hex),
+                            None,
+                            now,
+                            &conn,
+                        )?;
+                        WalletEvent::create(
+                            EVENT_TYPE_AUTO_SWEEP_SUBMITTED,
+                            &json!({
+                                "account_id": policy.account_id_hex,
+                                "transaction_log_id": transaction_log.transaction_id_hex,
+                                "destination_address": policy.destination_address,
+                                "token_id": token_id.to_string(),
+                                "value": value.to_string(),
+                            }),
+                            &conn,
+                        )?;
+                    }
+                    Ok(None) => policy.record_run(None, None, now, &conn)?,
+                    Err(err) => {
+                        policy.record_run(None, Some(err.as_str()), now, &conn)?;
+                        WalletEvent::create(
+                            EVENT_TYPE_AUTO_SWEEP_FAILED,
+                            &json!({
+                                "account_id": policy.account_id_hex,
+                                "token_id": token_id.to_string(),
+                                "error": err,
+                            }),
+                            &conn,
+                        )?;
+                    }
+                }
+                Ok::<(), SweepPolicyServiceError>(())
+            })?;
+
+            match result {
+                Ok(Some((transaction_log, value))) => log::info!(
+                    self.logger,
+                    "Swept {} of token {} from account {} to {} in transaction {}",
+                    value,
+                    token_id,
+                    policy.account_id_hex,
+                    policy.destination_address,
+                    transaction_log.transaction_id_hex,
+                ),
+                Ok(None) => log::debug!(
+                    self.logger,
+                    "Nothing to sweep from account {}",
+                    policy.account_id_hex,
+                ),
+                Err(err) => log::warn!(
+                    self.logger,
+                    "Sweep of account {} failed: {}",
+                    policy.account_id_hex,
+                    err,
+                ),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        db::{models::TransactionLog, transaction_log::TransactionLogModel},
+        service::account::AccountService,
+        test_utils::{
+            add_block_from_transaction_log, add_block_to_ledger_db, get_test_ledger,
+            manually_sync_account, setup_wallet_service, MOB,
+        },
+        util::b58::b58_encode_public_address,
+    };
+    use mc_account_keys::AccountKey;
+    use mc_common::logger::{test_with_logger, Logger};
+    use mc_crypto_rand::rand_core::RngCore;
+    use mc_transaction_core::{ring_signature::KeyImage, tokens::Mob, Token};
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test_with_logger]
+    fn test_run_due_sweeps(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let mut ledger_db = get_test_ledger(5, &vec![], 12, &mut rng);
+        let service = setup_wallet_service(ledger_db.clone(), logger.clone());
+        let conn = service.wallet_db.get_conn().unwrap();
+
+        let alice = service
+            .create_account(
+                Some("Alice's Main Account".to_string()),
+                "".to_string(),
+                "".to_string(),
+                "".to_string(),
+            )
+            .unwrap();
+        let alice_id = AccountID(alice.account_id_hex.clone());
+        let alice_account_key: AccountKey = mc_util_serial::decode(&alice.account_key).unwrap();
+        let cold_address =
+            b58_encode_public_address(&AccountKey::random(&mut rng).default_subaddress()).unwrap();
+
+        add_block_to_ledger_db(
+            &mut ledger_db,
+            &vec![alice_account_key.subaddress(alice.main_subaddress_index as u64)],
+            100 * MOB,
+            &vec![KeyImage::from(rng.next_u64())],
+            &mut rng,
+        );
+        manually_sync_account(&ledger_db, &service.wallet_db, &alice_id, &logger);
+
+        assert!(service
+            .set_sweep_policy(&alice_id, &cold_address, *Mob::ID, 30 * MOB, 0)
+            .is_err());
+        assert!(service
+            .set_sweep_policy(&alice_id, "not an address", *Mob::ID, 30 * MOB, 3600)
+            .is_err());
+
+        let policy = service
+            .set_sweep_policy(&alice_id, &cold_address, *Mob::ID, 30 * MOB, 3600)
+            .unwrap();
+        assert_eq!(policy.last_run_time, None);
+
+        // The new policy is due straight away, and sweeps all but the threshold.
+        service.run_due_sweeps().unwrap();
+        let policy = service.get_sweep_policy(&alice_id).unwrap();
+        assert!(policy.last_run_time.is_some());
+        assert_eq!(policy.last_failure_message, None);
+        let transaction_log_id = policy.last_transaction_log_id.clone().unwrap();
+        let transaction_log = TransactionLog::get(&transaction_log_id, &conn).unwrap();
+
+        let fee = service.get_network_minimum_fees()[&Mob::ID];
+        let events = WalletEvent::list(None, None, &conn).unwrap();
+        let event = events
+            .iter()
+            .find(|event| event.event_type == EVENT_TYPE_AUTO_SWEEP_SUBMITTED)
+            .unwrap();
+        let payload: serde_json::Value = serde_json::from_str(&event.payload).unwrap();
+        assert_eq!(payload["transaction_log_id"], transaction_log_id);
+        assert_eq!(payload["value"], (70 * MOB - fee).to_string());
+
+        add_block_from_transaction_log(&mut ledger_db, &conn, &transaction_log);
+        manually_sync_account(&ledger_db, &service.wallet_db, &alice_id, &logger);
+
+        let balance: u64 = Txo::list_unspent(&alice_id.to_string(), None, None, &conn)
+            .unwrap()
+            .iter()
+            .map(|txo| txo.value as u64)
+            .sum();
+        assert_eq!(balance, 30 * MOB);
+
+        // Nothing more happens until the interval has passed.
+        service.run_due_sweeps().unwrap();
+        assert_eq!(service.get_sweep_policy(&alice_id).unwrap(), policy);
+
+        assert!(service.remove_sweep_policy(&alice_id).unwrap());
+        assert!(service.get_sweep_policy(&alice_id).is_err());
+        assert!(service.remove_sweep_policy(&alice_id).is_err());
+    }
+}
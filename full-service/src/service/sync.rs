@@ -5,26 +5,41 @@
 use crate::{
     db::{
         account::{AccountID, AccountModel},
+        account_snapshot::AccountSnapshotModel,
+        account_sync_record::AccountSyncRecordModel,
         assigned_subaddress::AssignedSubaddressModel,
         models::{
-            Account, AssignedSubaddress, TransactionLog, Txo, ViewOnlyAccount, ViewOnlySubaddress,
-            ViewOnlyTxo,
+            Account, AccountSnapshot, AccountSyncRecord, AssignedSubaddress, PaymentRequest,
+            QuarantinedBlock, SyncedBlock, TransactionLog, TransactionWatch, Txo, ViewOnlyAccount,
+            ViewOnlySubaddress, ViewOnlyTxo, WalletEvent, WalletSetting,
+            EVENT_TYPE_ACCOUNT_IMPORT_COMPLETED, EVENT_TYPE_ACCOUNT_IMPORT_PROGRESS,
+            EVENT_TYPE_LEDGER_REORG_DETECTED, EVENT_TYPE_PAYMENT_REQUEST_PAID,
+            EVENT_TYPE_SUSPECTED_ADDRESS_POISONING, EVENT_TYPE_TXO_RECEIVED,
+            PAYMENT_REQUEST_STATUS_LATE,
         },
+        payment_request::PaymentRequestModel,
+        quarantined_block::QuarantinedBlockModel,
+        synced_block::SyncedBlockModel,
         transaction,
         transaction_log::TransactionLogModel,
+        transaction_watch::TransactionWatchModel,
         txo::TxoModel,
         view_only_account::ViewOnlyAccountModel,
         view_only_subaddress::ViewOnlySubaddressModel,
         view_only_txo::ViewOnlyTxoModel,
-        Conn, WalletDb,
+        wallet_event::WalletEventModel,
+        wallet_settings::WalletSettingModel,
+        Conn, WalletDb, WalletDbError,
     },
     error::SyncError,
-    util::b58::b58_encode_public_address,
+    metrics,
+    service::balance_cache::BalanceCache,
+    util::b58::{b58_decode_public_address, b58_encode_public_address},
 };
-use mc_account_keys::AccountKey;
+use mc_account_keys::{AccountKey, PublicAddress, ShortAddressHash};
 use mc_common::{
     logger::{log, Logger},
-    HashMap,
+    HashMap, HashSet,
 };
 use mc_crypto_keys::{RistrettoPrivate, RistrettoPublic};
 use mc_ledger_db::{Ledger, LedgerDB};
@@ -34,21 +49,178 @@ use mc_transaction_core::{
     ring_signature::KeyImage,
     tokens::Mob,
     tx::TxOut,
-    Amount, Token,
+    Amount, BlockContents, BlockVersion, Token,
 };
+use mc_transaction_std::MemoType;
 use rayon::prelude::*;
+use serde_json::json;
 
 use std::{
     convert::TryFrom,
     sync::{
         atomic::{AtomicBool, Ordering},
-        Arc,
+        Arc, Mutex, RwLock,
     },
     thread,
-    time::Instant,
+    time::{Duration, Instant},
 };
 
-const BLOCKS_CHUNK_SIZE: u64 = 1_000;
+/// Number of blocks an account is synced by at a time, unless configured
+/// otherwise. Each chunk's blocks are held in memory while they are scanned,
+/// and the account's progress is committed once the chunk is done.
+pub const DEFAULT_SYNC_CHUNK_SIZE: u64 = 1_000;
+
+/// Number of the most recent ledger blocks whose ids are kept, to tell when
+/// the ledger is rolled back or reorganized. A divergence deeper than this is
+/// rolled back to the oldest block kept.
+const SYNCED_BLOCK_HISTORY_LEN: u64 = 1_000;
+
+/// Number of leading bytes a sender memo's address hash must share with a
+/// contact's for the sender to be treated as a lookalike of that contact.
+const LOOKALIKE_ADDRESS_HASH_PREFIX_LEN: usize = 4;
+
+/// The longest label a txo classifier may give a txo.
+pub const MAX_TXO_CLASSIFICATION_LEN: usize = 64;
+
+/// A txo the sync has just credited to an account, as given to the txo
+/// classifier.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReceivedTxoDetails {
+    pub account_id_hex: String,
+    pub txo_id_hex: String,
+    pub value: u64,
+    pub token_id: u64,
+
+    /// The subaddress the txo was sent to, or None if it is orphaned.
+    pub subaddress_index: Option<u64>,
+
+    pub block_index: u64,
+
+    /// The address hash of the sender, from the txo's sender memo.
+    pub sender_address_hash: Option<String>,
+}
+
+/// Labels the txos the sync credits to accounts, such as "payroll" or
+/// "customer-deposit", so that a deployment can tell its txos apart in
+/// listings, exports and events. The classifier is asked while the txo's
+/// chunk is being written, so it should answer quickly.
+pub trait TxoClassifier: Send + Sync {
+    /// The label for the txo, None to leave it unlabelled, or why it couldn't
+    /// be classified.
+    fn classify(&self, txo: &ReceivedTxoDetails) -> Result<Option<String>, String>;
+}
+
+/// What the sync workers are doing with each account, shared with the wallet
+/// service so that it can report the sync's progress.
+#[derive(Clone, Default)]
+pub struct SyncActivity {
+    accounts: Arc<RwLock<HashMap<String, AccountSyncActivity>>>,
+
+    /// Whether the sync has been asked to pause.
+    pause_requested: Arc<AtomicBool>,
+
+    /// Whether the sync thread is partway through a pass over the accounts.
+    in_pass: Arc<AtomicBool>,
+
+    /// The accounts queued for or being synced by a worker, which are not
+    /// queued again until the worker is done with them.
+    in_flight: Arc<Mutex<HashSet<String>>>,
+
+    /// When the sync thread last started a pass or committed a chunk.
+    last_progress: Arc<RwLock<Option<Instant>>>,
+}
+
+/// What the sync workers are doing with an account.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AccountSyncActivity {
+    /// Whether a worker is scanning blocks for the account right now.
+    pub syncing: bool,
+
+    /// The error the account's last sync failed with, cleared once it syncs.
+    pub last_error: Option<String>,
+}
+
+impl SyncActivity {
+    /// What the sync workers are doing with the account.
+    pub fn get(&self, account_id_hex: &str) -> AccountSyncActivity {
+        self.accounts
+            .read()
+            .expect("lock poisoned")
+            .get(account_id_hex)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Ask the sync to pause. Each account being synced stops once its
+    /// current chunk is committed, and no further passes are started until
+    /// the sync is resumed.
+    pub fn pause(&self) {
+        self.pause_requested.store(true, Ordering::SeqCst);
+    }
+
+    /// Let the sync carry on from where it paused.
+    pub fn resume(&self) {
+        self.pause_requested.store(false, Ordering::SeqCst);
+    }
+
+    /// Whether the sync has been asked to pause.
+    pub fn is_pause_requested(&self) -> bool {
+        self.pause_requested.load(Ordering::SeqCst)
+    }
+
+    /// Whether the sync has been asked to pause, and has stopped writing to
+    /// the database.
+    pub fn is_paused(&self) -> bool {
+        self.is_pause_requested()
+            && !self.in_pass.load(Ordering::SeqCst)
+            && self.in_flight.lock().expect("lock poisoned").is_empty()
+    }
+
+    /// When the sync thread last started a pass over the accounts or
+    /// committed a chunk of an account's blocks, if it has yet.
+    pub fn last_progress(&self) -> Option<Instant> {
+        *self.last_progress.read().expect("lock poisoned")
+    }
+
+    fn record_progress(&self) {
+        *self.last_progress.write().expect("lock poisoned") = Some(Instant::now());
+    }
+
+    /// Mark the account as in flight, unless it already is. Returns whether
+    /// it was marked.
+    fn queue(&self, account_id_hex: &str) -> bool {
+        self.in_flight
+            .lock()
+            .expect("lock poisoned")
+            .insert(account_id_hex.to_string())
+    }
+
+    fn dequeue(&self, account_id_hex: &str) {
+        self.in_flight
+            .lock()
+            .expect("lock poisoned")
+            .remove(account_id_hex);
+    }
+
+    fn start(&self, account_id_hex: &str) {
+        self.accounts
+            .write()
+            .expect("lock poisoned")
+            .entry(account_id_hex.to_string())
+            .or_default()
+            .syncing = true;
+    }
+
+    fn finish(&self, account_id_hex: &str, error: Option<String>) {
+        self.accounts.write().expect("lock poisoned").insert(
+            account_id_hex.to_string(),
+            AccountSyncActivity {
+                syncing: false,
+                last_error: error,
+            },
+        );
+    }
+}
 
 /// Sync thread - holds objects needed to cleanly terminate the sync thread.
 pub struct SyncThread {
@@ -61,6 +233,44 @@ pub struct SyncThread {
 
 impl SyncThread {
     pub fn start(ledger_db: LedgerDB, wallet_db: WalletDb, logger: Logger) -> Self {
+        Self::start_with_balance_cache(ledger_db, wallet_db, BalanceCache::default(), logger)
+    }
+
+    /// Start the sync thread, refreshing the balance snapshots of the accounts
+    /// it syncs.
+    pub fn start_with_balance_cache(
+        ledger_db: LedgerDB,
+        wallet_db: WalletDb,
+        balance_cache: BalanceCache,
+        logger: Logger,
+    ) -> Self {
+        Self::start_with_workers(
+            ledger_db,
+            wallet_db,
+            balance_cache,
+            SyncActivity::default(),
+            None,
+            1,
+            DEFAULT_SYNC_CHUNK_SIZE,
+            logger,
+        )
+    }
+
+    /// Start the sync thread, syncing up to sync_threads accounts at once by
+    /// sync_chunk_size blocks at a time, recording what it is doing with each
+    /// account in sync_activity, and labelling the txos it finds with the
+    /// txo_classifier, if there is one.
+    #[allow(clippy::too_many_arguments)]
+    pub fn start_with_workers(
+        ledger_db: LedgerDB,
+        wallet_db: WalletDb,
+        balance_cache: BalanceCache,
+        sync_activity: SyncActivity,
+        txo_classifier: Option<Arc<dyn TxoClassifier>>,
+        sync_threads: usize,
+        sync_chunk_size: u64,
+        logger: Logger,
+    ) -> Self {
         // Start the sync thread.
 
         let stop_requested = Arc::new(AtomicBool::new(false));
@@ -72,18 +282,63 @@ impl SyncThread {
                 .spawn(move || {
                     log::debug!(logger, "Sync thread started.");
 
+                    // The workers outlive each pass, so that an account still
+                    // being synced doesn't hold up the next pass, and the
+                    // accounts which are done are queued again as new blocks
+                    // arrive.
+                    let workers = SyncWorkers::start(
+                        sync_activity.clone(),
+                        sync_threads,
+                        thread_stop_requested.clone(),
+                        account_syncer(
+                            ledger_db.clone(),
+                            wallet_db.clone(),
+                            balance_cache.clone(),
+                            txo_classifier,
+                            sync_chunk_size,
+                            logger.clone(),
+                        ),
+                        logger.clone(),
+                    );
+
                     loop {
                         if thread_stop_requested.load(Ordering::SeqCst) {
                             log::debug!(logger, "SyncThread stop requested.");
                             break;
                         }
-                        match sync_all_accounts(&ledger_db, &wallet_db, &logger) {
-                            Ok(()) => (),
-                            Err(e) => log::error!(&logger, "Error during account sync:\n{:?}", e),
+                        // The pass is marked as started before checking for a
+                        // pause, so that a pause requested meanwhile is not
+                        // reported as done until the pass has finished.
+                        sync_activity.in_pass.store(true, Ordering::SeqCst);
+                        sync_activity.record_progress();
+                        if !sync_activity.is_pause_requested() {
+                            match queue_accounts(
+                                &ledger_db,
+                                &wallet_db,
+                                &balance_cache,
+                                &workers,
+                                &logger,
+                            )
+                            .and_then(|()| {
+                                sync_view_only_accounts(
+                                    &ledger_db,
+                                    &wallet_db,
+                                    &sync_activity,
+                                    sync_chunk_size,
+                                    &logger,
+                                )
+                            }) {
+                                Ok(()) => (),
+                                Err(e) => {
+                                    log::error!(&logger, "Error during account sync:\n{:?}", e)
+                                }
+                            }
                         }
+                        sync_activity.in_pass.store(false, Ordering::SeqCst);
 
                         thread::sleep(std::time::Duration::from_secs(1));
                     }
+                    workers.join();
                     log::debug!(logger, "SyncThread stopped.");
                 })
                 .expect("failed starting main sync thread"),
@@ -109,11 +364,193 @@ impl Drop for SyncThread {
     }
 }
 
+/// Syncs an account's blocks, stopping early once the given function returns
+/// true.
+pub type SyncAccountFn = dyn Fn(&str, &dyn Fn() -> bool) -> Result<(), SyncError> + Send + Sync;
+
+/// Sync an account by sync_chunk_size blocks at a time, labelling the txos it
+/// finds with the txo_classifier, and then refresh its balance snapshot.
+fn account_syncer(
+    ledger_db: LedgerDB,
+    wallet_db: WalletDb,
+    balance_cache: BalanceCache,
+    txo_classifier: Option<Arc<dyn TxoClassifier>>,
+    sync_chunk_size: u64,
+    logger: Logger,
+) -> Arc<SyncAccountFn> {
+    Arc::new(move |account_id_hex: &str, stop: &dyn Fn() -> bool| {
+        sync_account_chunks_until(
+            &ledger_db,
+            &wallet_db,
+            account_id_hex,
+            sync_chunk_size,
+            txo_classifier.as_deref(),
+            stop,
+            &logger,
+        )?;
+        let conn = wallet_db.get_conn()?;
+        Ok(balance_cache.refresh_account(account_id_hex, &conn)?)
+    })
+}
+
+/// Workers which sync the accounts queued for them. Each worker takes the next
+/// queued account as soon as it is done with the last, and an account is only
+/// queued while it isn't already queued or being synced, so that an account
+/// with many blocks to scan only holds up its own worker.
+pub struct SyncWorkers {
+    /// Where accounts are queued for the workers. It is closed to stop them.
+    sender: Option<crossbeam_channel::Sender<String>>,
+
+    /// What the workers are doing with each account.
+    sync_activity: SyncActivity,
+
+    /// The worker thread handles.
+    join_handles: Vec<thread::JoinHandle<()>>,
+}
+
+impl SyncWorkers {
+    /// Start sync_threads workers, which sync each account with sync_account.
+    /// They skip the accounts queued for them while the sync is paused or
+    /// stop_requested is set.
+    pub fn start(
+        sync_activity: SyncActivity,
+        sync_threads: usize,
+        stop_requested: Arc<AtomicBool>,
+        sync_account: Arc<SyncAccountFn>,
+        logger: Logger,
+    ) -> Self {
+        let (sender, receiver) = crossbeam_channel::unbounded::<String>();
+        let join_handles = (0..sync_threads.max(1))
+            .map(|index| {
+                let receiver = receiver.clone();
+                let sync_activity = sync_activity.clone();
+                let stop_requested = stop_requested.clone();
+                let sync_account = sync_account.clone();
+                let logger = logger.clone();
+                thread::Builder::new()
+                    .name(format!("sync-worker-{}", index))
+                    .spawn(move || {
+                        // Each chunk committed counts as progress, so that an
+                        // account with many blocks to scan does not look like
+                        // a stalled sync.
+                        let stop = || {
+                            sync_activity.record_progress();
+                            sync_activity.is_pause_requested()
+                                || stop_requested.load(Ordering::SeqCst)
+                        };
+                        for account_id_hex in receiver {
+                            if !sync_activity.is_pause_requested()
+                                && !stop_requested.load(Ordering::SeqCst)
+                            {
+                                sync_activity.start(&account_id_hex);
+                                // A failure syncing one account is logged, and
+                                // the rest carry on.
+                                let synced = sync_account(&account_id_hex, &stop);
+                                if let Err(e) = &synced {
+                                    log::error!(
+                                        logger,
+                                        "Error syncing account {}: {:?}",
+                                        account_id_hex,
+                                        e
+                                    );
+                                }
+                                sync_activity
+                                    .finish(&account_id_hex, synced.err().map(|e| e.to_string()));
+                            }
+                            sync_activity.dequeue(&account_id_hex);
+                        }
+                    })
+                    .expect("failed starting sync worker")
+            })
+            .collect();
+
+        Self {
+            sender: Some(sender),
+            sync_activity,
+            join_handles,
+        }
+    }
+
+    /// Queue the account to be synced, unless it is already queued or being
+    /// synced. Returns whether it was queued.
+    pub fn queue(&self, account_id_hex: &str) -> bool {
+        if !self.sync_activity.queue(account_id_hex) {
+            return false;
+        }
+        self.sender
+            .as_ref()
+            .expect("sync workers stopped")
+            .send(account_id_hex.to_string())
+            .expect("sync worker channel closed");
+        true
+    }
+
+    /// Wait for the workers to finish with the queued accounts, and stop them.
+    pub fn join(mut self) {
+        self.stop();
+    }
+
+    fn stop(&mut self) {
+        self.sender.take();
+        for join_handle in self.join_handles.drain(..) {
+            join_handle.join().expect("sync worker panicked");
+        }
+    }
+}
+
+impl Drop for SyncWorkers {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Sync every account which has blocks left to scan, up to sync_threads
+/// accounts at once, by sync_chunk_size blocks at a time, and wait for them to
+/// finish. The sync thread instead keeps its SyncWorkers between passes.
+#[allow(clippy::too_many_arguments)]
 pub fn sync_all_accounts(
     ledger_db: &LedgerDB,
     wallet_db: &WalletDb,
+    balance_cache: &BalanceCache,
+    sync_activity: &SyncActivity,
+    txo_classifier: Option<Arc<dyn TxoClassifier>>,
+    sync_threads: usize,
+    sync_chunk_size: u64,
+    logger: &Logger,
+) -> Result<(), SyncError> {
+    let workers = SyncWorkers::start(
+        sync_activity.clone(),
+        sync_threads,
+        Arc::new(AtomicBool::new(false)),
+        account_syncer(
+            ledger_db.clone(),
+            wallet_db.clone(),
+            balance_cache.clone(),
+            txo_classifier,
+            sync_chunk_size,
+            logger.clone(),
+        ),
+        logger.clone(),
+    );
+    queue_accounts(ledger_db, wallet_db, balance_cache, &workers, logger)?;
+    workers.join();
+
+    sync_view_only_accounts(ledger_db, wallet_db, sync_activity, sync_chunk_size, logger)
+}
+
+/// Queue every account which has blocks left to scan to the workers, unless
+/// its sync has been paused, or it is already queued or being synced.
+pub fn queue_accounts(
+    ledger_db: &LedgerDB,
+    wallet_db: &WalletDb,
+    balance_cache: &BalanceCache,
+    workers: &SyncWorkers,
     logger: &Logger,
 ) -> Result<(), SyncError> {
+    // Roll accounts back if the ledger has diverged from the blocks they were
+    // synced with, before syncing them any further.
+    check_ledger_continuity(ledger_db, wallet_db, balance_cache, logger)?;
+
     // Get the current number of blocks in ledger.
     let num_blocks = ledger_db
         .num_blocks()
@@ -127,6 +564,31 @@ pub fn sync_all_accounts(
         Account::list_all(conn).expect("Failed getting accounts from database")
     };
 
+    // If there are no new blocks for an account, or its sync has been paused,
+    // don't do anything.
+    for account in accounts {
+        if account.next_block_index as u64 <= num_blocks - 1 && !account.sync_paused {
+            workers.queue(&account.account_id_hex);
+        }
+    }
+
+    Ok(())
+}
+
+/// Sync every view only account which has blocks left to scan, and then update
+/// the transaction watches.
+fn sync_view_only_accounts(
+    ledger_db: &LedgerDB,
+    wallet_db: &WalletDb,
+    sync_activity: &SyncActivity,
+    sync_chunk_size: u64,
+    logger: &Logger,
+) -> Result<(), SyncError> {
+    // Get the current number of blocks in ledger.
+    let num_blocks = ledger_db
+        .num_blocks()
+        .expect("failed getting number of blocks");
+
     let view_only_accounts: Vec<ViewOnlyAccount> = {
         let conn = &wallet_db
             .get_conn()
@@ -134,20 +596,177 @@ pub fn sync_all_accounts(
         ViewOnlyAccount::list_all(conn).expect("Failed getting view only accounts from database")
     };
 
-    for account in accounts {
+    for account in view_only_accounts {
+        if sync_activity.is_pause_requested() {
+            return Ok(());
+        }
         // If there are no new blocks for this account, don't do anything.
         if account.next_block_index as u64 > num_blocks - 1 {
             continue;
         }
-        sync_account(ledger_db, wallet_db, &account.account_id_hex, logger)?;
+        if let Err(e) = sync_view_only_account_in_chunks(
+            ledger_db,
+            wallet_db,
+            &account.account_id_hex,
+            sync_chunk_size,
+            logger,
+        ) {
+            log::error!(
+                logger,
+                "Error syncing view only account {}: {:?}",
+                account.account_id_hex,
+                e
+            );
+        }
     }
 
-    for account in view_only_accounts {
-        // If there are no new blocks for this account, don't do anything.
-        if account.next_block_index as u64 > num_blocks - 1 {
+    update_transaction_watches(ledger_db, wallet_db, logger)?;
+
+    Ok(())
+}
+
+/// Compare the ids of the blocks the sync has seen with the ledger's, rolling
+/// accounts back to rescan from the first block where they differ, and then
+/// record the ids of any new blocks.
+///
+/// Returns the block the accounts were rolled back to, if they were.
+pub fn check_ledger_continuity(
+    ledger_db: &LedgerDB,
+    wallet_db: &WalletDb,
+    balance_cache: &BalanceCache,
+    logger: &Logger,
+) -> Result<Option<u64>, SyncError> {
+    let conn = wallet_db.get_conn()?;
+
+    // Walk back from the latest block seen until one matches the ledger.
+    let synced_blocks = SyncedBlock::list_latest(SYNCED_BLOCK_HISTORY_LEN, &conn)?;
+    let mut fork_block_index = None;
+    for synced_block in &synced_blocks {
+        let block_index = synced_block.block_index as u64;
+        if ledger_block_id_hex(ledger_db, block_index)?.as_ref() == Some(&synced_block.block_id_hex)
+        {
+            break;
+        }
+        fork_block_index = Some(block_index);
+    }
+
+    if let Some(fork_block_index) = fork_block_index {
+        log::warn!(
+            logger,
+            "Ledger diverged from the synced blocks at block {}, rolling back accounts",
+            fork_block_index
+        );
+        let account_ids = transaction(&conn, || roll_back_accounts(fork_block_index, &conn))?;
+        for account_id in &account_ids {
+            balance_cache.invalidate(account_id);
+        }
+    }
+
+    // Record the blocks added since the last check.
+    let num_blocks = ledger_db.num_blocks()?;
+    let history_start = num_blocks.saturating_sub(SYNCED_BLOCK_HISTORY_LEN);
+    let next_block_index = match fork_block_index {
+        Some(fork_block_index) => fork_block_index,
+        None => synced_blocks
+            .first()
+            .map_or(0, |block| block.block_index as u64 + 1),
+    };
+    transaction(&conn, || {
+        for block_index in next_block_index.max(history_start)..num_blocks {
+            if let Some(block_id_hex) = ledger_block_id_hex(ledger_db, block_index)? {
+                SyncedBlock::record(block_index, &block_id_hex, &conn)?;
+            }
+        }
+        SyncedBlock::delete_before(history_start, &conn)?;
+        Ok::<(), SyncError>(())
+    })?;
+
+    Ok(fork_block_index)
+}
+
+/// The hex-encoded id of a block in the ledger, or None if it isn't there.
+fn ledger_block_id_hex(
+    ledger_db: &LedgerDB,
+    block_index: u64,
+) -> Result<Option<String>, SyncError> {
+    match ledger_db.get_block(block_index) {
+        Ok(block) => Ok(Some(hex::encode(block.id))),
+        Err(mc_ledger_db::Error::NotFound) => Ok(None),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Undo what each account found in the blocks at or after the fork block, so
+/// that they are rescanned from it, recording an event. Returns the ids of
+/// the accounts rolled back.
+///
+/// Txos spent since the fork are pending until the rescan reaches the block
+/// they were spent in, so that they are not spent again before it is known
+/// whether the spend is still in the ledger.
+fn roll_back_accounts(fork_block_index: u64, conn: &Conn) -> Result<Vec<String>, SyncError> {
+    let mut account_ids = Vec::new();
+    for account in Account::list_all(conn)? {
+        if account.next_block_index as u64 <= fork_block_index {
+            continue;
+        }
+        let account_id_hex = &account.account_id_hex;
+        Txo::update_spent_from_block_index_to_pending(account_id_hex, fork_block_index, conn)?;
+        Txo::roll_back_received_from_block_index(account_id_hex, fork_block_index, conn)?;
+        TransactionLog::roll_back_from_block_index(account_id_hex, fork_block_index, conn)?;
+        AccountSnapshot::delete_from(account_id_hex, fork_block_index, conn)?;
+        account.update_next_block_index(
+            fork_block_index.max(account.first_block_index as u64),
+            conn,
+        )?;
+        account_ids.push(account_id_hex.clone());
+    }
+    Txo::delete_unreferenced(conn)?;
+
+    for account in ViewOnlyAccount::list_all(conn)? {
+        if account.next_block_index as u64 <= fork_block_index {
             continue;
         }
-        sync_view_only_account(ledger_db, wallet_db, &account.account_id_hex, logger)?;
+        ViewOnlyTxo::roll_back_from_block_index(&account.account_id_hex, fork_block_index, conn)?;
+        account.update_next_block_index(
+            fork_block_index.max(account.first_block_index as u64),
+            conn,
+        )?;
+        account_ids.push(account.account_id_hex.clone());
+    }
+
+    SyncedBlock::delete_from(fork_block_index, conn)?;
+    WalletEvent::create(
+        EVENT_TYPE_LEDGER_REORG_DETECTED,
+        &json!({
+            "fork_block_index": fork_block_index.to_string(),
+            "account_ids": account_ids,
+        }),
+        conn,
+    )?;
+
+    Ok(account_ids)
+}
+
+/// Check the ledger for any watched transactions that have not yet landed or
+/// expired.
+pub fn update_transaction_watches(
+    ledger_db: &LedgerDB,
+    wallet_db: &WalletDb,
+    logger: &Logger,
+) -> Result<(), SyncError> {
+    let conn = wallet_db.get_conn()?;
+
+    for watch in TransactionWatch::list_pending(&conn)? {
+        let updated = transaction(&conn, || watch.update_from_ledger(ledger_db, &conn))?;
+        if updated.status != watch.status {
+            log::info!(
+                logger,
+                "Watched transaction {} is now {} at block {:?}",
+                updated.watch_id_hex,
+                updated.status,
+                updated.finalized_block_index,
+            );
+        }
     }
 
     Ok(())
@@ -159,29 +778,169 @@ enum SyncStatus {
     NoMoreBlocks,
 }
 
+/// A block loaded for scanning.
+enum LoadedBlock {
+    /// The block's contents.
+    Contents(BlockContents),
+
+    /// A quarantined block on the skip list, which is passed over.
+    Skipped,
+
+    /// The block is not in the ledger yet, or is quarantined, so the chunk
+    /// ends before it.
+    End,
+}
+
+fn list_quarantined_blocks(conn: &Conn) -> Result<HashMap<u64, QuarantinedBlock>, SyncError> {
+    Ok(QuarantinedBlock::list_all(conn)?
+        .into_iter()
+        .map(|block| (block.block_index as u64, block))
+        .collect())
+}
+
+/// Load a block for scanning.
+///
+/// A block which can't be processed is quarantined, unless it is on the skip
+/// list, and a quarantined block which can be processed again is released.
+fn load_block(
+    ledger_db: &LedgerDB,
+    block_index: u64,
+    quarantined_blocks: &HashMap<u64, QuarantinedBlock>,
+    logger: &Logger,
+    conn: &Conn,
+) -> Result<LoadedBlock, SyncError> {
+    let reason = match read_block_contents(ledger_db, block_index) {
+        Ok(Some(block_contents)) => {
+            if let Some(block) = quarantined_blocks.get(&block_index) {
+                if !block.skipped {
+                    log::info!(logger, "Released block {} from quarantine", block_index);
+                    block.release(conn)?;
+                }
+            }
+            return Ok(LoadedBlock::Contents(block_contents));
+        }
+        Ok(None) => return Ok(LoadedBlock::End),
+        Err(reason) => reason,
+    };
+
+    match quarantined_blocks.get(&block_index) {
+        Some(block) if block.skipped => Ok(LoadedBlock::Skipped),
+        _ => {
+            let (_, created) = QuarantinedBlock::quarantine(block_index, &reason, conn)?;
+            if created {
+                log::error!(
+                    logger,
+                    "Quarantined block {}, which can't be processed: {}",
+                    block_index,
+                    reason
+                );
+            }
+            Ok(LoadedBlock::End)
+        }
+    }
+}
+
+/// Read a block's contents from the ledger, or None if it isn't there yet.
+///
+/// Returns why the block can't be processed if it can't be read, or if its
+/// block version is newer than this build supports.
+fn read_block_contents(
+    ledger_db: &LedgerDB,
+    block_index: u64,
+) -> Result<Option<BlockContents>, String> {
+    match ledger_db.get_block(block_index) {
+        Ok(block) if block.version > *BlockVersion::MAX => {
+            return Err(format!("Unsupported block version {}", block.version))
+        }
+        Ok(_) => {}
+        Err(mc_ledger_db::Error::NotFound) => return Ok(None),
+        Err(err) => return Err(err.to_string()),
+    }
+
+    match ledger_db.get_block_contents(block_index) {
+        Ok(block_contents) => Ok(Some(block_contents)),
+        Err(mc_ledger_db::Error::NotFound) => Ok(None),
+        Err(err) => Err(err.to_string()),
+    }
+}
+
 /// Sync a single view only account.
 pub fn sync_view_only_account(
     ledger_db: &LedgerDB,
     wallet_db: &WalletDb,
     account_id_hex: &str,
     logger: &Logger,
+) -> Result<(), SyncError> {
+    sync_view_only_account_in_chunks(
+        ledger_db,
+        wallet_db,
+        account_id_hex,
+        DEFAULT_SYNC_CHUNK_SIZE,
+        logger,
+    )
+}
+
+/// Sync a single view only account, by chunk_size blocks at a time.
+pub fn sync_view_only_account_in_chunks(
+    ledger_db: &LedgerDB,
+    wallet_db: &WalletDb,
+    account_id_hex: &str,
+    chunk_size: u64,
+    logger: &Logger,
 ) -> Result<(), SyncError> {
     let conn = wallet_db.get_conn()?;
 
     while let SyncStatus::ChunkFinished =
-        sync_view_only_account_next_chunk(ledger_db, &conn, logger, account_id_hex)?
-    {}
+        sync_view_only_account_next_chunk(ledger_db, &conn, logger, account_id_hex, chunk_size)?
+    {
+    }
+
+    sync_view_only_key_images(ledger_db, &conn, logger, account_id_hex)?;
 
     Ok(())
 }
 
+/// Mark spent any view only txos whose key images are already in the ledger.
+///
+/// Key images reach the wallet from the offline signer, usually after the
+/// blocks spending their txos have been scanned, so matching key images
+/// block-by-block during the scan misses them. Checking every known key image
+/// against the ledger keeps spent status current however the key image
+/// arrived.
+fn sync_view_only_key_images(
+    ledger_db: &LedgerDB,
+    conn: &Conn,
+    logger: &Logger,
+    account_id_hex: &str,
+) -> Result<(), SyncError> {
+    transaction(conn, || {
+        let unspent_key_images: HashMap<KeyImage, String> =
+            ViewOnlyTxo::list_unspent_with_key_images(account_id_hex, None, conn)?;
+
+        for (key_image, txo_id_hex) in unspent_key_images {
+            if let Some(block_index) = ledger_db.check_key_image(&key_image)? {
+                ViewOnlyTxo::update_spent_block_index(&txo_id_hex, block_index, conn)?;
+                log::debug!(
+                    logger,
+                    "Marked view only txo {} spent at block {} from its key image",
+                    txo_id_hex.chars().take(6).collect::<String>(),
+                    block_index,
+                );
+            }
+        }
+
+        Ok(())
+    })
+}
+
 fn sync_view_only_account_next_chunk(
     ledger_db: &LedgerDB,
     conn: &Conn,
     logger: &Logger,
     account_id_hex: &str,
+    chunk_size: u64,
 ) -> Result<SyncStatus, SyncError> {
-    transaction(conn, || {
+    let (sync_status, received_amounts) = transaction(conn, || {
         // Get the account data. If it is no longer available, the account has been
         // removed and we can simply return.
         let view_only_account = ViewOnlyAccount::get(account_id_hex, conn)?;
@@ -198,26 +957,27 @@ fn sync_view_only_account_next_chunk(
 
         let start_time = Instant::now();
         let start_block_index = view_only_account.next_block_index as u64;
-        let mut end_block_index = view_only_account.next_block_index as u64;
+        let mut end_block_index: Option<u64> = None;
 
         // Load transaction outputs and key_images for this chunk.
         let mut tx_outs: Vec<(u64, TxOut)> = Vec::new();
         let mut key_images: Vec<(u64, KeyImage)> = Vec::new();
 
+        let quarantined_blocks = list_quarantined_blocks(conn)?;
         let start = view_only_account.next_block_index as u64;
-        let end = start + BLOCKS_CHUNK_SIZE;
+        let end = start + chunk_size;
         for block_index in start..end {
             let block_index = block_index as u64;
-            let block_contents = match ledger_db.get_block_contents(block_index as u64) {
-                Ok(block_contents) => block_contents,
-                Err(mc_ledger_db::Error::NotFound) => {
-                    break;
-                }
-                Err(err) => {
-                    return Err(err.into());
-                }
-            };
-            end_block_index = block_index;
+            let block_contents =
+                match load_block(ledger_db, block_index, &quarantined_blocks, logger, conn)? {
+                    LoadedBlock::Contents(block_contents) => block_contents,
+                    LoadedBlock::Skipped => {
+                        end_block_index = Some(block_index);
+                        continue;
+                    }
+                    LoadedBlock::End => break,
+                };
+            end_block_index = Some(block_index);
 
             for tx_out in block_contents.outputs {
                 tx_outs.push((block_index, tx_out));
@@ -228,6 +988,12 @@ fn sync_view_only_account_next_chunk(
             }
         }
 
+        // If no blocks were found, exit.
+        let end_block_index = match end_block_index {
+            Some(end_block_index) => end_block_index,
+            None => return Ok((SyncStatus::NoMoreBlocks, Vec::new())),
+        };
+
         // Attempt to decode each transaction as received by this account.
         let received_txos: Vec<_> = tx_outs
             .into_par_iter()
@@ -245,7 +1011,12 @@ fn sync_view_only_account_next_chunk(
         let num_received_txos = received_txos.len();
 
         // Write received txos to db
+        let mut received_amounts = Vec::new();
         for (block_index, tx_out, amount, subaddress_index) in received_txos {
+            if subaddress_index != Some(view_only_account.change_subaddress_index as u64) {
+                received_amounts.push(amount);
+            }
+
             ViewOnlyTxo::create(
                 tx_out.clone(),
                 amount,
@@ -295,12 +1066,19 @@ fn sync_view_only_account_next_chunk(
             num_received_txos,
         );
 
-        if num_blocks_synced < BLOCKS_CHUNK_SIZE {
-            Ok(SyncStatus::NoMoreBlocks)
+        if num_blocks_synced < chunk_size {
+            Ok((SyncStatus::NoMoreBlocks, received_amounts))
         } else {
-            Ok(SyncStatus::ChunkFinished)
+            Ok((SyncStatus::ChunkFinished, received_amounts))
         }
-    })
+    })?;
+
+    // Counted once the chunk is committed, since the transaction may be retried.
+    for amount in received_amounts {
+        metrics::record_received(account_id_hex, *amount.token_id, amount.value);
+    }
+
+    Ok(sync_status)
 }
 
 /// Sync a single account.
@@ -309,61 +1087,252 @@ pub fn sync_account(
     wallet_db: &WalletDb,
     account_id_hex: &str,
     logger: &Logger,
+) -> Result<(), SyncError> {
+    sync_account_in_chunks(
+        ledger_db,
+        wallet_db,
+        account_id_hex,
+        DEFAULT_SYNC_CHUNK_SIZE,
+        logger,
+    )
+}
+
+/// Sync a single account, by chunk_size blocks at a time.
+///
+/// Only one chunk's blocks are held in memory at once, and each chunk is
+/// committed as it is done, so an account syncing from far behind the ledger
+/// picks up from its last chunk if the sync is interrupted.
+pub fn sync_account_in_chunks(
+    ledger_db: &LedgerDB,
+    wallet_db: &WalletDb,
+    account_id_hex: &str,
+    chunk_size: u64,
+    logger: &Logger,
+) -> Result<(), SyncError> {
+    sync_account_chunks_until(
+        ledger_db,
+        wallet_db,
+        account_id_hex,
+        chunk_size,
+        None,
+        || false,
+        logger,
+    )
+}
+
+/// Sync a single account by chunk_size blocks at a time, stopping early once
+/// `stop` returns true after a chunk is committed.
+fn sync_account_chunks_until(
+    ledger_db: &LedgerDB,
+    wallet_db: &WalletDb,
+    account_id_hex: &str,
+    chunk_size: u64,
+    txo_classifier: Option<&dyn TxoClassifier>,
+    stop: impl Fn() -> bool,
+    logger: &Logger,
 ) -> Result<(), SyncError> {
     let conn = wallet_db.get_conn()?;
 
-    while let SyncStatus::ChunkFinished =
-        sync_account_next_chunk(ledger_db, &conn, logger, account_id_hex)?
-    {}
+    // Each chunk is scanned for the account's txos before the database is
+    // locked to write what was found, so that accounts being synced at the
+    // same time don't wait on each other to scan.
+    while !stop() {
+        let scanned_chunk = scan_next_chunk(ledger_db, &conn, account_id_hex, chunk_size)?;
+        if let SyncStatus::NoMoreBlocks = sync_account_next_chunk(
+            ledger_db,
+            &conn,
+            logger,
+            account_id_hex,
+            chunk_size,
+            txo_classifier,
+            scanned_chunk,
+        )? {
+            break;
+        }
+    }
 
     Ok(())
 }
 
-fn sync_account_next_chunk(
+/// A txo received in a chunk of blocks, with the block it is in, its amount,
+/// and its subaddress index and key image if they could be found.
+type ReceivedTxo = (u64, TxOut, Amount, Option<u64>, Option<KeyImage>);
+
+/// The txos an account received in a chunk of blocks, found without locking
+/// the database.
+struct ScannedChunk {
+    /// The blocks whose contents were scanned.
+    block_indices: Vec<u64>,
+
+    /// The account's subaddresses the txos were matched against.
+    subaddress_keys: HashMap<RistrettoPublic, u64>,
+
+    /// The txos the account received in those blocks.
+    received_txos: Vec<ReceivedTxo>,
+
+    /// How long the scan took.
+    duration: Duration,
+}
+
+/// Scan the account's next chunk of blocks for the txos it received, leaving
+/// any quarantining to the sync of the chunk.
+fn scan_next_chunk(
     ledger_db: &LedgerDB,
     conn: &Conn,
-    logger: &Logger,
     account_id_hex: &str,
-) -> Result<SyncStatus, SyncError> {
-    transaction(conn, || {
+    chunk_size: u64,
+) -> Result<Option<ScannedChunk>, SyncError> {
+    let start_time = Instant::now();
+    let account = match Account::get(&AccountID(account_id_hex.to_string()), conn) {
+        Ok(account) => account,
+        Err(WalletDbError::AccountNotFound(_)) => return Ok(None),
+        Err(err) => return Err(err.into()),
+    };
+    let account_key: AccountKey = mc_util_serial::decode(&account.account_key)?;
+    let subaddress_keys = load_subaddress_keys(account_id_hex, conn)?;
+
+    let quarantined_blocks = list_quarantined_blocks(conn)?;
+    let mut block_indices = Vec::new();
+    let mut tx_outs: Vec<(u64, TxOut)> = Vec::new();
+    let start = account.next_block_index as u64;
+    for block_index in start..start + chunk_size {
+        match read_block_contents(ledger_db, block_index) {
+            Ok(Some(block_contents)) => {
+                block_indices.push(block_index);
+                for tx_out in block_contents.outputs {
+                    tx_outs.push((block_index, tx_out));
+                }
+            }
+            Err(_)
+                if quarantined_blocks
+                    .get(&block_index)
+                    .map_or(false, |block| block.skipped) =>
+            {
+                continue
+            }
+            _ => break,
+        }
+    }
+    if block_indices.is_empty() {
+        return Ok(None);
+    }
+
+    let received_txos = decode_received_txos(tx_outs, &account_key, &subaddress_keys);
+    Ok(Some(ScannedChunk {
+        block_indices,
+        subaddress_keys,
+        received_txos,
+        duration: start_time.elapsed(),
+    }))
+}
+
+/// Read the outputs of the given blocks from the ledger.
+fn read_block_outputs(
+    ledger_db: &LedgerDB,
+    block_indices: &[u64],
+) -> Result<Vec<(u64, TxOut)>, SyncError> {
+    let mut tx_outs = Vec::new();
+    for block_index in block_indices {
+        for tx_out in ledger_db.get_block_contents(*block_index)?.outputs {
+            tx_outs.push((*block_index, tx_out));
+        }
+    }
+    Ok(tx_outs)
+}
+
+/// Load the account's subaddress spend keys, mapped to their indices.
+fn load_subaddress_keys(
+    account_id_hex: &str,
+    conn: &Conn,
+) -> Result<HashMap<RistrettoPublic, u64>, SyncError> {
+    let mut subaddress_keys: HashMap<RistrettoPublic, u64> = HashMap::default();
+    let subaddresses: Vec<_> = AssignedSubaddress::list_all(account_id_hex, None, None, conn)?;
+    for s in subaddresses {
+        let subaddress_key = mc_util_serial::decode(s.subaddress_spend_key.as_slice())?;
+        subaddress_keys.insert(subaddress_key, s.subaddress_index as u64);
+    }
+    Ok(subaddress_keys)
+}
+
+/// Attempt to decode each transaction output as received by the account.
+fn decode_received_txos(
+    tx_outs: Vec<(u64, TxOut)>,
+    account_key: &AccountKey,
+    subaddress_keys: &HashMap<RistrettoPublic, u64>,
+) -> Vec<ReceivedTxo> {
+    tx_outs
+        .into_par_iter()
+        .filter_map(|(block_index, tx_out)| {
+            let amount = match decode_amount(&tx_out, account_key.view_private_key()) {
+                None => return None,
+                Some(a) => a,
+            };
+            let (subaddress_index, key_image) =
+                decode_subaddress_and_key_image(&tx_out, account_key, subaddress_keys);
+            Some((block_index, tx_out, amount, subaddress_index, key_image))
+        })
+        .collect()
+}
+
+fn sync_account_next_chunk(
+    ledger_db: &LedgerDB,
+    conn: &Conn,
+    logger: &Logger,
+    account_id_hex: &str,
+    chunk_size: u64,
+    txo_classifier: Option<&dyn TxoClassifier>,
+    scanned_chunk: Option<ScannedChunk>,
+) -> Result<SyncStatus, SyncError> {
+    let (sync_status, received_amounts) = transaction(conn, || {
         // Get the account data. If it is no longer available, the account has been
         // removed and we can simply return.
-        let account = Account::get(&AccountID(account_id_hex.to_string()), conn)?;
+        let account = match Account::get(&AccountID(account_id_hex.to_string()), conn) {
+            Ok(account) => account,
+            Err(WalletDbError::AccountNotFound(_)) => {
+                return Ok((SyncStatus::NoMoreBlocks, Vec::new()))
+            }
+            Err(err) => return Err(err.into()),
+        };
         let account_key: AccountKey = mc_util_serial::decode(&account.account_key)?;
 
         // Load subaddresses for this account into a hash map.
-        let mut subaddress_keys: HashMap<RistrettoPublic, u64> = HashMap::default();
-        let subaddresses: Vec<_> = AssignedSubaddress::list_all(account_id_hex, None, None, conn)?;
-        for s in subaddresses {
-            let subaddress_key = mc_util_serial::decode(s.subaddress_spend_key.as_slice())?;
-            subaddress_keys.insert(subaddress_key, s.subaddress_index as u64);
-        }
+        let subaddress_keys = load_subaddress_keys(account_id_hex, conn)?;
 
         let start_time = Instant::now();
         let start_block_index = account.next_block_index as u64;
         let mut end_block_index: Option<u64> = None;
 
-        // Load transaction outputs and key images for this chunk.
+        // Load transaction outputs and key images for this chunk. The outputs
+        // are only kept if the scan before the database was locked can't be
+        // used, so that the chunk's outputs aren't held in memory twice.
+        let scan_usable = scanned_chunk.as_ref().map_or(false, |scanned_chunk| {
+            scanned_chunk.subaddress_keys == subaddress_keys
+        });
         let mut tx_outs: Vec<(u64, TxOut)> = Vec::new();
         let mut key_images: Vec<(u64, KeyImage)> = Vec::new();
+        let mut block_indices: Vec<u64> = Vec::new();
 
+        let quarantined_blocks = list_quarantined_blocks(conn)?;
         let start = account.next_block_index as u64;
-        let end = start + BLOCKS_CHUNK_SIZE;
+        let end = start + chunk_size;
         for block_index in start..end {
             let block_index = block_index as u64;
-            let block_contents = match ledger_db.get_block_contents(block_index as u64) {
-                Ok(block_contents) => block_contents,
-                Err(mc_ledger_db::Error::NotFound) => {
-                    break;
-                }
-                Err(err) => {
-                    return Err(err.into());
-                }
-            };
+            let block_contents =
+                match load_block(ledger_db, block_index, &quarantined_blocks, logger, conn)? {
+                    LoadedBlock::Contents(block_contents) => block_contents,
+                    LoadedBlock::Skipped => {
+                        end_block_index = Some(block_index);
+                        continue;
+                    }
+                    LoadedBlock::End => break,
+                };
             end_block_index = Some(block_index);
+            block_indices.push(block_index);
 
-            for tx_out in block_contents.outputs {
-                tx_outs.push((block_index, tx_out));
+            if !scan_usable {
+                for tx_out in block_contents.outputs {
+                    tx_outs.push((block_index, tx_out));
+                }
             }
 
             for key_image in block_contents.key_images {
@@ -373,27 +1342,55 @@ fn sync_account_next_chunk(
 
         // If no blocks were found, exit.
         if end_block_index.is_none() {
-            return Ok(SyncStatus::NoMoreBlocks);
+            return Ok((SyncStatus::NoMoreBlocks, Vec::new()));
         }
         let end_block_index = end_block_index.unwrap();
 
-        // Attempt to decode each transaction as received by this account.
-        let received_txos: Vec<_> = tx_outs
-            .into_par_iter()
-            .filter_map(|(block_index, tx_out)| {
-                let amount = match decode_amount(&tx_out, account_key.view_private_key()) {
-                    None => return None,
-                    Some(a) => a,
-                };
-                let (subaddress_index, key_image) =
-                    decode_subaddress_and_key_image(&tx_out, &account_key, &subaddress_keys);
-                Some((block_index, tx_out, amount, subaddress_index, key_image))
-            })
-            .collect();
+        // Use the txos found by the scan before the database was locked, unless
+        // the account or the blocks have changed since, in which case the
+        // chunk is scanned again.
+        let (received_txos, scan_duration) = match &scanned_chunk {
+            Some(scanned_chunk) if scan_usable && scanned_chunk.block_indices == block_indices => {
+                (scanned_chunk.received_txos.clone(), scanned_chunk.duration)
+            }
+            _ => {
+                if scan_usable {
+                    tx_outs = read_block_outputs(ledger_db, &block_indices)?;
+                }
+                (
+                    decode_received_txos(tx_outs, &account_key, &subaddress_keys),
+                    Duration::default(),
+                )
+            }
+        };
         let num_received_txos = received_txos.len();
+        let num_orphaned_txos = received_txos
+            .iter()
+            .filter(|(_, _, _, subaddress_index, _)| subaddress_index.is_none())
+            .count();
+
+        // Load the addresses this account has sent to, so that received txos
+        // whose sender memos impersonate them can be flagged.
+        let contacts: Vec<(String, PublicAddress)> = if received_txos.is_empty() {
+            Vec::new()
+        } else {
+            Txo::list_recipient_addresses(account_id_hex, conn)?
+                .into_iter()
+                .filter_map(|address_b58| {
+                    b58_decode_public_address(&address_b58)
+                        .ok()
+                        .map(|address| (address_b58, address))
+                })
+                .collect()
+        };
 
         // Write received transactions to the database.
+        let mut received_amounts = Vec::new();
         for (block_index, tx_out, amount, subaddress_index, key_image) in received_txos {
+            if subaddress_index != Some(account.change_subaddress_index as u64) {
+                received_amounts.push(amount);
+            }
+
             let txo_id = Txo::create_received(
                 tx_out.clone(),
                 subaddress_index,
@@ -404,6 +1401,105 @@ fn sync_account_next_chunk(
                 conn,
             )?;
 
+            let (sender_address_hash, destination_address_hash) =
+                decode_memo_address_hashes(&tx_out, &account_key);
+            if sender_address_hash.is_some() || destination_address_hash.is_some() {
+                Txo::update_memo_address_hashes(
+                    &txo_id,
+                    sender_address_hash.as_deref(),
+                    destination_address_hash.as_deref(),
+                    conn,
+                )?;
+            }
+
+            // A txo found again by a rescan is classified again, in case the
+            // classifier has changed its mind.
+            let classification = txo_classifier.and_then(|txo_classifier| {
+                classify_received_txo(
+                    txo_classifier,
+                    &ReceivedTxoDetails {
+                        account_id_hex: account_id_hex.to_string(),
+                        txo_id_hex: txo_id.clone(),
+                        value: amount.value,
+                        token_id: *amount.token_id,
+                        subaddress_index,
+                        block_index,
+                        sender_address_hash: sender_address_hash.clone(),
+                    },
+                    logger,
+                )
+            });
+            if txo_classifier.is_some() {
+                Txo::update_classification(&txo_id, classification.as_deref(), conn)?;
+            }
+
+            WalletEvent::create(
+                EVENT_TYPE_TXO_RECEIVED,
+                &json!({
+                    "account_id": account_id_hex,
+                    "txo_id": txo_id,
+                    "value": amount.value.to_string(),
+                    "token_id": (*amount.token_id).to_string(),
+                    "subaddress_index": subaddress_index.map(|index| index.to_string()),
+                    "block_index": block_index.to_string(),
+                    "classification": classification,
+                }),
+                conn,
+            )?;
+
+            if let Some(mimicked_address_b58) =
+                detect_address_poisoning(&tx_out, &account_key, subaddress_index, &contacts)
+            {
+                log::warn!(
+                    logger,
+                    "Txo {} received by account {} has a sender memo impersonating {}",
+                    txo_id,
+                    account_id_hex,
+                    mimicked_address_b58,
+                );
+                Txo::update_mimicked_address(&txo_id, &mimicked_address_b58, conn)?;
+                WalletEvent::create(
+                    EVENT_TYPE_SUSPECTED_ADDRESS_POISONING,
+                    &json!({
+                        "account_id": account_id_hex,
+                        "txo_id": txo_id,
+                        "mimicked_address": mimicked_address_b58,
+                    }),
+                    conn,
+                )?;
+            }
+
+            // Match the txo to the payment request its memo names, marking
+            // the request late if the payment landed after its expiry.
+            let payment_request = match decode_payment_request_id(&tx_out, &account_key) {
+                Some(payment_request_id) => {
+                    PaymentRequest::get_pending(account_id_hex, payment_request_id, conn)?
+                }
+                None => None,
+            };
+            if let Some(payment_request) =
+                payment_request.filter(|request| request.token_id as u64 == *amount.token_id)
+            {
+                let status =
+                    payment_request.record_payment(&txo_id, amount.value, block_index, conn)?;
+                WalletEvent::create(
+                    EVENT_TYPE_PAYMENT_REQUEST_PAID,
+                    &json!({
+                        "account_id": account_id_hex,
+                        "payment_request_id": payment_request.id.to_string(),
+                        "txo_id": txo_id,
+                        "value": amount.value.to_string(),
+                        "block_index": block_index.to_string(),
+                        "status": if status == PAYMENT_REQUEST_STATUS_LATE {
+                            "late"
+                        } else {
+                            "paid"
+                        },
+                    }),
+                    conn,
+                )?;
+            }
+
             // TODO: What's the best way to get the assigned_subaddress_b58?
             // Do we even care about saving this in the database at all? We
             // should be able to look up any relevant information about the
@@ -448,6 +1544,27 @@ fn sync_account_next_chunk(
             }
         }
 
+        // An account imported without knowing how many addresses it had
+        // assigned sees payments to the others as orphaned. Look for them
+        // before matching key images, so that the recovered txos are matched.
+        if num_orphaned_txos > 0 {
+            let gap_limit = WalletSetting::get_settings(conn)?.subaddress_gap_limit;
+            let recovered = AssignedSubaddress::recover_orphaned_for_account(
+                account_id_hex,
+                gap_limit,
+                ledger_db,
+                conn,
+            )?;
+            if !recovered.is_empty() {
+                log::info!(
+                    logger,
+                    "Assigned subaddresses {:?} of account {}, which orphaned txos were sent to",
+                    recovered,
+                    account_id_hex
+                );
+            }
+        }
+
         // Match key images to mark existing unspent transactions as spent.
         let unspent_key_images: HashMap<KeyImage, String> =
             Txo::list_unspent_or_pending_key_images(account_id_hex, None, conn)?;
@@ -475,6 +1592,21 @@ fn sync_account_next_chunk(
             None,
             conn,
         )?;
+        // Transactions which were built but never submitted can't be submitted
+        // once their tombstone block has passed, so their logs are removed
+        // rather than recorded as failed.
+        let num_expired_built_logs = TransactionLog::delete_built_associated_with_txos(
+            &txos_exceeding_pending_block_index,
+            conn,
+        )?;
+        if num_expired_built_logs > 0 {
+            log::info!(
+                logger,
+                "Removed {} expired logs of transactions which were never submitted for account {}",
+                num_expired_built_logs,
+                account_id_hex
+            );
+        }
         TransactionLog::update_tx_logs_associated_with_txos_to_failed(
             &txos_exceeding_pending_block_index,
             conn,
@@ -488,9 +1620,22 @@ fn sync_account_next_chunk(
         // Done syncing this chunk. Mark these blocks as synced for this account.
         account.update_next_block_index(end_block_index + 1, conn)?;
 
+        if account.is_importing() {
+            record_import_progress(&account, end_block_index + 1, logger, conn)?;
+        }
+
         let num_blocks_synced = end_block_index - start_block_index + 1;
 
-        let duration = start_time.elapsed();
+        let duration = scan_duration + start_time.elapsed();
+        AccountSyncRecord::create(
+            account_id_hex,
+            start_block_index,
+            end_block_index,
+            num_received_txos,
+            num_spent_txos,
+            duration,
+            conn,
+        )?;
 
         log::debug!(
             logger,
@@ -505,12 +1650,67 @@ fn sync_account_next_chunk(
             unspent_key_images.len(),
         );
 
-        if num_blocks_synced < BLOCKS_CHUNK_SIZE {
-            Ok(SyncStatus::NoMoreBlocks)
+        if num_blocks_synced < chunk_size {
+            Ok((SyncStatus::NoMoreBlocks, received_amounts))
         } else {
-            Ok(SyncStatus::ChunkFinished)
+            Ok((SyncStatus::ChunkFinished, received_amounts))
         }
-    })
+    })?;
+
+    // Counted once the chunk is committed, since the transaction may be retried.
+    for amount in received_amounts {
+        metrics::record_received(account_id_hex, *amount.token_id, amount.value);
+    }
+
+    Ok(sync_status)
+}
+
+/// Record how far an imported account has got through scanning the blocks which
+/// were in the ledger when it was imported.
+fn record_import_progress(
+    account: &Account,
+    next_block_index: u64,
+    logger: &Logger,
+    conn: &Conn,
+) -> Result<(), SyncError> {
+    let first_block_index = account.first_block_index as u64;
+    let import_block_index = account.import_block_index.unwrap_or_default() as u64;
+    let blocks_total = import_block_index + 1 - first_block_index;
+    let blocks_scanned =
+        std::cmp::min(next_block_index, import_block_index + 1) - first_block_index;
+
+    let event_type = if next_block_index > import_block_index {
+        log::info!(
+            logger,
+            "Finished importing account {}",
+            account.account_id_hex
+        );
+        EVENT_TYPE_ACCOUNT_IMPORT_COMPLETED
+    } else {
+        log::info!(
+            logger,
+            "Importing account {}: scanned {}/{} blocks",
+            account.account_id_hex,
+            blocks_scanned,
+            blocks_total,
+        );
+        EVENT_TYPE_ACCOUNT_IMPORT_PROGRESS
+    };
+
+    WalletEvent::create(
+        event_type,
+        &json!({
+            "account_id": account.account_id_hex,
+            "first_block_index": first_block_index.to_string(),
+            "import_block_index": import_block_index.to_string(),
+            "next_block_index": next_block_index.to_string(),
+            "blocks_scanned": blocks_scanned.to_string(),
+            "blocks_total": blocks_total.to_string(),
+        }),
+        conn,
+    )?;
+
+    Ok(())
 }
 
 /// Attempt to decode the transaction amount. If we can't, then this transaction
@@ -576,18 +1776,176 @@ pub fn decode_subaddress_and_key_image(
     (subaddress_index, key_image)
 }
 
+/// Check whether a received TxOut's sender memo impersonates one of the given
+/// contacts, returning the b58 address of the contact it mimics.
+///
+/// A memo impersonates a contact if it claims to be from the contact but fails
+/// authentication, or if its address hash is a lookalike of the contact's,
+/// sharing the leading bytes but differing after them.
+pub fn detect_address_poisoning(
+    tx_out: &TxOut,
+    account_key: &AccountKey,
+    subaddress_index: Option<u64>,
+    contacts: &[(String, PublicAddress)],
+) -> Option<String> {
+    if contacts.is_empty() {
+        return None;
+    }
+
+    let memo_type = decrypt_memo(tx_out, account_key)?;
+
+    let sender_address_hash = match &memo_type {
+        MemoType::AuthenticatedSender(memo) => *memo.sender_address_hash(),
+        MemoType::AuthenticatedSenderWithPaymentRequestId(memo) => *memo.sender_address_hash(),
+        _ => return None,
+    };
+    let sender_address_hash: [u8; 16] = sender_address_hash.into();
+
+    let is_authentic = |address: &PublicAddress| match (subaddress_index, &memo_type) {
+        (Some(index), MemoType::AuthenticatedSender(memo)) => bool::from(memo.validate(
+            address,
+            &account_key.subaddress_view_private(index),
+            &tx_out.public_key,
+        )),
+        (Some(index), MemoType::AuthenticatedSenderWithPaymentRequestId(memo)) => {
+            bool::from(memo.validate(
+                address,
+                &account_key.subaddress_view_private(index),
+                &tx_out.public_key,
+            ))
+        }
+        // Orphaned txos can't be authenticated until their subaddress is known.
+        _ => true,
+    };
+
+    let mut lookalike = None;
+    for (address_b58, address) in contacts {
+        let contact_address_hash: [u8; 16] = ShortAddressHash::from(address).into();
+        if contact_address_hash == sender_address_hash {
+            return if is_authentic(address) {
+                None
+            } else {
+                Some(address_b58.clone())
+            };
+        }
+        if lookalike.is_none()
+            && contact_address_hash[..LOOKALIKE_ADDRESS_HASH_PREFIX_LEN]
+                == sender_address_hash[..LOOKALIKE_ADDRESS_HASH_PREFIX_LEN]
+        {
+            lookalike = Some(address_b58.clone());
+        }
+    }
+    lookalike
+}
+
+/// Ask the classifier for a received txo's label, trimmed of whitespace.
+///
+/// A classifier which fails, or gives a blank label or one longer than
+/// MAX_TXO_CLASSIFICATION_LEN, leaves the txo unlabelled rather than holding
+/// up the sync.
+pub fn classify_received_txo(
+    txo_classifier: &dyn TxoClassifier,
+    txo: &ReceivedTxoDetails,
+    logger: &Logger,
+) -> Option<String> {
+    match txo_classifier.classify(txo) {
+        Ok(Some(classification)) => {
+            let classification = classification.trim();
+            if classification.is_empty() || classification.len() > MAX_TXO_CLASSIFICATION_LEN {
+                log::warn!(
+                    logger,
+                    "Ignoring the classification of txo {}, which must be 1 to {} characters: {:?}",
+                    txo.txo_id_hex,
+                    MAX_TXO_CLASSIFICATION_LEN,
+                    classification
+                );
+                return None;
+            }
+            Some(classification.to_string())
+        }
+        Ok(None) => None,
+        Err(e) => {
+            log::warn!(
+                logger,
+                "Could not classify txo {} received by account {}: {}",
+                txo.txo_id_hex,
+                txo.account_id_hex,
+                e
+            );
+            None
+        }
+    }
+}
+
+/// Get the payment request id from a received TxOut's memo, if it has one.
+pub fn decode_payment_request_id(tx_out: &TxOut, account_key: &AccountKey) -> Option<u64> {
+    match decrypt_memo(tx_out, account_key)? {
+        MemoType::AuthenticatedSenderWithPaymentRequestId(memo) => Some(memo.payment_request_id()),
+        _ => None,
+    }
+}
+
+/// Get the hex-encoded address hashes from a received TxOut's Recoverable
+/// Transaction History memo.
+///
+/// Returns the sender's address hash from a sender memo, and the recipient's
+/// from the destination memo the sending wallet writes to its change.
+pub fn decode_memo_address_hashes(
+    tx_out: &TxOut,
+    account_key: &AccountKey,
+) -> (Option<String>, Option<String>) {
+    let encode = |address_hash: &ShortAddressHash| {
+        let address_hash: [u8; 16] = (*address_hash).into();
+        hex::encode(address_hash)
+    };
+    match decrypt_memo(tx_out, account_key) {
+        Some(MemoType::AuthenticatedSender(memo)) => {
+            (Some(encode(memo.sender_address_hash())), None)
+        }
+        Some(MemoType::AuthenticatedSenderWithPaymentRequestId(memo)) => {
+            (Some(encode(memo.sender_address_hash())), None)
+        }
+        Some(MemoType::Destination(memo)) => (None, Some(encode(memo.get_address_hash()))),
+        _ => (None, None),
+    }
+}
+
+/// Decrypt a received TxOut's memo, if it is of a type the wallet understands.
+fn decrypt_memo(tx_out: &TxOut, account_key: &AccountKey) -> Option<MemoType> {
+    let tx_public_key = RistrettoPublic::try_from(&tx_out.public_key).ok()?;
+    let shared_secret = get_tx_out_shared_secret(account_key.view_private_key(), &tx_public_key);
+    let memo_payload = tx_out.decrypt_memo(&shared_secret);
+    MemoType::try_from(&memo_payload).ok()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::{
-        service::{account::AccountService, balance::BalanceService, txo::TxoService},
+        db::models::EVENT_TYPE_BLOCK_QUARANTINE_RELEASED,
+        service::{
+            account::AccountService,
+            balance::BalanceService,
+            ledger::{LedgerService, LedgerServiceError},
+            txo::TxoService,
+            view_only_account::ViewOnlyAccountService,
+        },
         test_utils::{
-            add_block_to_ledger_db, get_test_ledger, manually_sync_account, setup_wallet_service,
-            MOB,
+            add_block_to_ledger_db, get_test_ledger, manually_sync_account,
+            manually_sync_view_only_account, setup_wallet_service, WalletDbTestContext, MOB,
         },
     };
-    use mc_account_keys::{AccountKey, RootEntropy, RootIdentity};
+    use mc_account_keys::{
+        AccountKey, RootEntropy, RootIdentity, CHANGE_SUBADDRESS_INDEX, DEFAULT_SUBADDRESS_INDEX,
+    };
     use mc_common::logger::{test_with_logger, Logger};
+    use mc_crypto_keys::CompressedRistrettoPublic;
+    use mc_crypto_rand::RngCore;
+    use mc_transaction_core::encrypted_fog_hint::EncryptedFogHint;
+    use mc_transaction_std::{
+        AuthenticatedSenderMemo, AuthenticatedSenderWithPaymentRequestIdMemo, DestinationMemo,
+        SenderMemoCredential,
+    };
     use mc_util_from_random::FromRandom;
     use rand::{rngs::StdRng, SeedableRng};
 
@@ -664,11 +2022,786 @@ mod tests {
 
         // Now verify that the service gets the balance with the correct value
         let balance = service
-            .get_balance_for_account(&AccountID::from(&account_key))
+            .get_balance_for_account(&AccountID::from(&account_key), false)
             .expect("Could not get balance");
         assert_eq!(balance.unspent, 250_000_000 * MOB as u128);
     }
 
+    fn sender_memo_tx_out(
+        sender: &SenderMemoCredential,
+        recipient: &PublicAddress,
+        rng: &mut StdRng,
+    ) -> TxOut {
+        TxOut::new_with_memo(
+            Amount::new(MOB, Mob::ID),
+            recipient,
+            &RistrettoPrivate::from_random(rng),
+            EncryptedFogHint::fake_onetime_hint(rng),
+            |memo_context| {
+                Ok(AuthenticatedSenderMemo::new(
+                    sender,
+                    recipient.view_public_key(),
+                    &CompressedRistrettoPublic::from(memo_context.tx_public_key),
+                )
+                .into())
+            },
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_detect_address_poisoning() {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let alice = AccountKey::random(&mut rng);
+        let bob = AccountKey::random(&mut rng);
+        let carol = AccountKey::random(&mut rng);
+        let alice_address = alice.default_subaddress();
+        let bob_address = bob.default_subaddress();
+        let bob_b58 = b58_encode_public_address(&bob_address).unwrap();
+        let contacts = vec![(bob_b58.clone(), bob_address)];
+
+        // A memo genuinely from Bob is not flagged.
+        let from_bob =
+            sender_memo_tx_out(&SenderMemoCredential::from(&bob), &alice_address, &mut rng);
+        assert_eq!(
+            detect_address_poisoning(&from_bob, &alice, Some(DEFAULT_SUBADDRESS_INDEX), &contacts),
+            None
+        );
+
+        // Nor is a memo from Carol, who doesn't claim to be anyone Alice has
+        // sent to.
+        let from_carol = sender_memo_tx_out(
+            &SenderMemoCredential::from(&carol),
+            &alice_address,
+            &mut rng,
+        );
+        assert_eq!(
+            detect_address_poisoning(
+                &from_carol,
+                &alice,
+                Some(DEFAULT_SUBADDRESS_INDEX),
+                &contacts
+            ),
+            None
+        );
+
+        // A memo naming Bob which fails authentication is flagged. Checking it
+        // against the wrong subaddress stands in for a forged HMAC.
+        assert_eq!(
+            detect_address_poisoning(&from_bob, &alice, Some(1), &contacts),
+            Some(bob_b58)
+        );
+
+        // Without any contacts there is nothing to impersonate.
+        assert_eq!(
+            detect_address_poisoning(&from_bob, &alice, Some(1), &[]),
+            None
+        );
+    }
+
+    #[test]
+    fn test_decode_payment_request_id() {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let alice = AccountKey::random(&mut rng);
+        let bob = AccountKey::random(&mut rng);
+        let alice_address = alice.default_subaddress();
+        let bob_credential = SenderMemoCredential::from(&bob);
+
+        let paying = TxOut::new_with_memo(
+            Amount::new(MOB, Mob::ID),
+            &alice_address,
+            &RistrettoPrivate::from_random(&mut rng),
+            EncryptedFogHint::fake_onetime_hint(&mut rng),
+            |memo_context| {
+                Ok(AuthenticatedSenderWithPaymentRequestIdMemo::new(
+                    &bob_credential,
+                    alice_address.view_public_key(),
+                    &CompressedRistrettoPublic::from(memo_context.tx_public_key),
+                    42,
+                )
+                .into())
+            },
+        )
+        .unwrap();
+        assert_eq!(decode_payment_request_id(&paying, &alice), Some(42));
+
+        // A sender memo without a payment request id doesn't pay anything.
+        let from_bob = sender_memo_tx_out(&bob_credential, &alice_address, &mut rng);
+        assert_eq!(decode_payment_request_id(&from_bob, &alice), None);
+    }
+
+    #[test]
+    fn test_decode_memo_address_hashes() {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let alice = AccountKey::random(&mut rng);
+        let bob = AccountKey::random(&mut rng);
+        let alice_address = alice.default_subaddress();
+        let bob_address = bob.default_subaddress();
+        let hash_of = |address: &PublicAddress| {
+            let address_hash: [u8; 16] = ShortAddressHash::from(address).into();
+            hex::encode(address_hash)
+        };
+
+        // A sender memo names the sender.
+        let from_bob =
+            sender_memo_tx_out(&SenderMemoCredential::from(&bob), &alice_address, &mut rng);
+        assert_eq!(
+            decode_memo_address_hashes(&from_bob, &alice),
+            (Some(hash_of(&bob_address)), None)
+        );
+
+        // The destination memo on Alice's change names who she paid.
+        let change = TxOut::new_with_memo(
+            Amount::new(MOB, Mob::ID),
+            &alice.change_subaddress(),
+            &RistrettoPrivate::from_random(&mut rng),
+            EncryptedFogHint::fake_onetime_hint(&mut rng),
+            |_memo_context| {
+                Ok(DestinationMemo::new(
+                    ShortAddressHash::from(&bob_address),
+                    MOB,
+                    Mob::MINIMUM_FEE,
+                )
+                .unwrap()
+                .into())
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            decode_memo_address_hashes(&change, &alice),
+            (None, Some(hash_of(&bob_address)))
+        );
+
+        // Without a memo there is nothing to decode.
+        let unmemoed = TxOut::new(
+            Amount::new(MOB, Mob::ID),
+            &alice_address,
+            &RistrettoPrivate::from_random(&mut rng),
+            EncryptedFogHint::fake_onetime_hint(&mut rng),
+        )
+        .unwrap();
+        assert_eq!(decode_memo_address_hashes(&unmemoed, &alice), (None, None));
+    }
+
+    #[test_with_logger]
+    fn test_sync_view_only_key_images(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let view_private_key = RistrettoPrivate::from_random(&mut rng);
+        let spend_private_key = RistrettoPrivate::from_random(&mut rng);
+        let account_key = AccountKey::new(&spend_private_key, &view_private_key);
+        let account_id = AccountID::from(&account_key);
+        let main_public_address = account_key.default_subaddress();
+
+        let mut ledger_db = get_test_ledger(5, &vec![], 12, &mut rng);
+        let service = setup_wallet_service(ledger_db.clone(), logger.clone());
+        let wallet_db = &service.wallet_db;
+
+        service
+            .import_view_only_account(
+                &account_id.to_string(),
+                &view_private_key,
+                DEFAULT_SUBADDRESS_INDEX,
+                CHANGE_SUBADDRESS_INDEX,
+                2,
+                "view only",
+                vec![(
+                    b58_encode_public_address(&main_public_address).unwrap(),
+                    DEFAULT_SUBADDRESS_INDEX,
+                    "Main".to_string(),
+                    *main_public_address.spend_public_key(),
+                )],
+            )
+            .unwrap();
+
+        add_block_to_ledger_db(
+            &mut ledger_db,
+            &vec![main_public_address.clone()],
+            42 * MOB,
+            &vec![],
+            &mut rng,
+        );
+        manually_sync_view_only_account(&ledger_db, wallet_db, &account_id.to_string(), &logger);
+
+        // Spend the txo before the wallet learns its key image, so the scan
+        // can't match it.
+        let key_image = KeyImage::from(rng.next_u64());
+        let spent_block_index = add_block_to_ledger_db(
+            &mut ledger_db,
+            &vec![main_public_address],
+            MOB,
+            &vec![key_image],
+            &mut rng,
+        );
+        manually_sync_view_only_account(&ledger_db, wallet_db, &account_id.to_string(), &logger);
+
+        let conn = wallet_db.get_conn().unwrap();
+        let txos = ViewOnlyTxo::list_for_account(&account_id.to_string(), None, None, None, &conn)
+            .unwrap();
+        let txo = txos.iter().find(|t| t.value == (42 * MOB) as i64).unwrap();
+        assert_eq!(txo.spent_block_index, None);
+
+        // Once the key image is known, the next sync finds it in the ledger.
+        ViewOnlyTxo::update_key_image(&txo.txo_id_hex, &key_image, &conn).unwrap();
+        manually_sync_view_only_account(&ledger_db, wallet_db, &account_id.to_string(), &logger);
+
+        let txo = ViewOnlyTxo::get(&txo.txo_id_hex, &conn).unwrap();
+        assert_eq!(txo.spent_block_index, Some(spent_block_index as i64));
+    }
+
+    #[test_with_logger]
+    fn test_recover_orphaned_subaddresses(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        // Fund subaddresses with gaps between them, the last beyond the gap
+        // limit.
+        let entropy = RootEntropy::from_random(&mut rng);
+        let account_key = AccountKey::from(&RootIdentity::from(&entropy));
+        let recipients = vec![
+            account_key.subaddress(3),
+            account_key.subaddress(6),
+            account_key.subaddress(30),
+        ];
+        let ledger_db = get_test_ledger(5, &recipients, 12, &mut rng);
+        let service = setup_wallet_service(ledger_db.clone(), logger.clone());
+        let conn = service.wallet_db.get_conn().unwrap();
+
+        let mut settings = std::collections::BTreeMap::new();
+        settings.insert(
+            crate::db::wallet_settings::SETTING_SUBADDRESS_GAP_LIMIT.to_string(),
+            "5".to_string(),
+        );
+        WalletSetting::update_settings(&settings, &conn).unwrap();
+
+        let account = service
+            .import_account_from_legacy_root_entropy(
+                hex::encode(&entropy.bytes),
+                None,
+                None,
+                None,
+                "".to_string(),
+                "".to_string(),
+                "".to_string(),
+            )
+            .unwrap();
+        let account_id = AccountID(account.account_id_hex);
+        let account = manually_sync_account(&ledger_db, &service.wallet_db, &account_id, &logger);
+
+        // The funded subaddresses within the gap limit are assigned, and their
+        // txos recovered.
+        assert!(AssignedSubaddress::get_for_account_by_index(&account_id.0, 3, &conn).is_ok());
+        assert!(AssignedSubaddress::get_for_account_by_index(&account_id.0, 6, &conn).is_ok());
+        assert!(AssignedSubaddress::get_for_account_by_index(&account_id.0, 30, &conn).is_err());
+        assert_eq!(account.next_subaddress_index, 7);
+
+        let orphaned = Txo::list_orphaned(&account_id.0, None, &conn).unwrap();
+        assert!(!orphaned.is_empty());
+        let subaddress_30 = account_key.subaddress(30);
+        for txo in orphaned {
+            let target_key: RistrettoPublic = mc_util_serial::decode(&txo.target_key).unwrap();
+            let public_key: RistrettoPublic = mc_util_serial::decode(&txo.public_key).unwrap();
+            assert_eq!(
+                recover_public_subaddress_spend_key(
+                    account_key.view_private_key(),
+                    &target_key,
+                    &public_key
+                ),
+                *subaddress_30.spend_public_key()
+            );
+        }
+    }
+
+    #[test_with_logger]
+    fn test_quarantined_blocks(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let ledger_db = get_test_ledger(5, &vec![], 12, &mut rng);
+        let service = setup_wallet_service(ledger_db.clone(), logger.clone());
+        let conn = service.wallet_db.get_conn().unwrap();
+
+        // A block which was quarantined, but which can now be processed, is
+        // released as an account syncs past it.
+        QuarantinedBlock::quarantine(3, "Unsupported block version 4", &conn).unwrap();
+        // A block which is not in the ledger yet.
+        QuarantinedBlock::quarantine(20, "Unsupported block version 4", &conn).unwrap();
+        assert_eq!(
+            service
+                .get_wallet_status(false)
+                .unwrap()
+                .quarantined_block_indices,
+            vec![3, 20]
+        );
+
+        let entropy = RootEntropy::from_random(&mut rng);
+        let account = service
+            .import_account_from_legacy_root_entropy(
+                hex::encode(&entropy.bytes),
+                None,
+                None,
+                None,
+                "".to_string(),
+                "".to_string(),
+                "".to_string(),
+            )
+            .unwrap();
+        manually_sync_account(
+            &ledger_db,
+            &service.wallet_db,
+            &AccountID(account.account_id_hex),
+            &logger,
+        );
+
+        assert_eq!(
+            service
+                .get_wallet_status(false)
+                .unwrap()
+                .quarantined_block_indices,
+            vec![20]
+        );
+        let events = WalletEvent::list(None, None, &conn).unwrap();
+        let released: Vec<_> = events
+            .iter()
+            .filter(|event| event.event_type == EVENT_TYPE_BLOCK_QUARANTINE_RELEASED)
+            .collect();
+        assert_eq!(released.len(), 1);
+        let payload: serde_json::Value = serde_json::from_str(&released[0].payload).unwrap();
+        assert_eq!(payload["block_index"], "3");
+
+        // Skipped blocks are no longer reported as holding up the sync.
+        let skipped = service.skip_quarantined_block(20).unwrap();
+        assert!(skipped.skipped);
+        assert!(service
+            .get_wallet_status(false)
+            .unwrap()
+            .quarantined_block_indices
+            .is_empty());
+        assert_eq!(service.list_quarantined_blocks().unwrap(), vec![skipped]);
+
+        assert!(matches!(
+            service.skip_quarantined_block(3),
+            Err(LedgerServiceError::Database(
+                WalletDbError::QuarantinedBlockNotFound(3)
+            ))
+        ));
+    }
+
+    #[test_with_logger]
+    fn test_ledger_reorg_rolls_back_accounts(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let mut ledger_db = get_test_ledger(5, &vec![], 12, &mut rng);
+        let service = setup_wallet_service(ledger_db.clone(), logger.clone());
+        let conn = service.wallet_db.get_conn().unwrap();
+        let balance_cache = BalanceCache::default();
+
+        let entropy = RootEntropy::from_random(&mut rng);
+        let account_key = AccountKey::from(&RootIdentity::from(&entropy));
+        let account = service
+            .import_account_from_legacy_root_entropy(
+                hex::encode(&entropy.bytes),
+                None,
+                None,
+                None,
+                "".to_string(),
+                "".to_string(),
+                "".to_string(),
+            )
+            .unwrap();
+        let account_id_hex = account.account_id_hex;
+
+        // Receive a txo in each of blocks 12 to 14, spending the first in 14.
+        for _ in 0..2 {
+            add_block_to_ledger_db(
+                &mut ledger_db,
+                &[account_key.subaddress(0)],
+                10 * MOB,
+                &[KeyImage::from(rng.next_u64())],
+                &mut rng,
+            );
+        }
+        sync_all_accounts(
+            &ledger_db,
+            &service.wallet_db,
+            &balance_cache,
+            &SyncActivity::default(),
+            None,
+            1,
+            DEFAULT_SYNC_CHUNK_SIZE,
+            &logger,
+        )
+        .unwrap();
+        let list_txos = || Txo::list_for_account(&account_id_hex, None, None, None, &conn).unwrap();
+        let spent_txo = list_txos()
+            .into_iter()
+            .find(|txo| txo.received_block_index == Some(12))
+            .unwrap();
+        let key_image: KeyImage =
+            mc_util_serial::decode(spent_txo.key_image.as_ref().unwrap()).unwrap();
+        add_block_to_ledger_db(
+            &mut ledger_db,
+            &[account_key.subaddress(0)],
+            10 * MOB,
+            &[key_image],
+            &mut rng,
+        );
+        sync_all_accounts(
+            &ledger_db,
+            &service.wallet_db,
+            &balance_cache,
+            &SyncActivity::default(),
+            None,
+            1,
+            DEFAULT_SYNC_CHUNK_SIZE,
+            &logger,
+        )
+        .unwrap();
+        assert_eq!(list_txos().len(), 3);
+        assert_eq!(
+            Txo::get(&spent_txo.txo_id_hex, &conn)
+                .unwrap()
+                .spent_block_index,
+            Some(14)
+        );
+
+        // While the ids the sync saw match the ledger, nothing is rolled back.
+        assert_eq!(
+            check_ledger_continuity(&ledger_db, &service.wallet_db, &balance_cache, &logger)
+                .unwrap(),
+            None
+        );
+
+        // Blocks 13 and 14 were seen with other ids, as if the ledger had
+        // since been replaced from block 13.
+        for block_index in 13..15 {
+            SyncedBlock::record(block_index, &hex::encode([block_index as u8; 32]), &conn).unwrap();
+        }
+        assert_eq!(
+            check_ledger_continuity(&ledger_db, &service.wallet_db, &balance_cache, &logger)
+                .unwrap(),
+            Some(13)
+        );
+        let account = Account::get(&AccountID(account_id_hex.clone()), &conn).unwrap();
+        assert_eq!(account.next_block_index, 13);
+        let txos = list_txos();
+        assert_eq!(txos.len(), 1);
+        assert_eq!(txos[0].spent_block_index, None);
+        assert_eq!(txos[0].pending_tombstone_block_index, Some(14));
+
+        let events = WalletEvent::list(None, None, &conn).unwrap();
+        let reorgs: Vec<_> = events
+            .iter()
+            .filter(|event| event.event_type == EVENT_TYPE_LEDGER_REORG_DETECTED)
+            .collect();
+        assert_eq!(reorgs.len(), 1);
+        let payload: serde_json::Value = serde_json::from_str(&reorgs[0].payload).unwrap();
+        assert_eq!(payload["fork_block_index"], "13");
+        assert_eq!(payload["account_ids"], json!([account_id_hex]));
+
+        // The ledger's ids were recorded again, and the rescan finds what is
+        // in the ledger now.
+        assert_eq!(
+            check_ledger_continuity(&ledger_db, &service.wallet_db, &balance_cache, &logger)
+                .unwrap(),
+            None
+        );
+        sync_all_accounts(
+            &ledger_db,
+            &service.wallet_db,
+            &balance_cache,
+            &SyncActivity::default(),
+            None,
+            1,
+            DEFAULT_SYNC_CHUNK_SIZE,
+            &logger,
+        )
+        .unwrap();
+        assert_eq!(list_txos().len(), 3);
+        assert_eq!(
+            Txo::get(&spent_txo.txo_id_hex, &conn)
+                .unwrap()
+                .spent_block_index,
+            Some(14)
+        );
+    }
+
+    #[test_with_logger]
+    fn test_small_account_syncs_while_large_account_scans(logger: Logger) {
+        let sync_activity = SyncActivity::default();
+        let timeout = Duration::from_secs(10);
+
+        // The large account's sync is held until it is released, and each
+        // account's sync is reported once it is done.
+        let (release_sender, release_receiver) = crossbeam_channel::unbounded::<()>();
+        let (synced_sender, synced_receiver) = crossbeam_channel::unbounded::<String>();
+        let sync_account: Arc<SyncAccountFn> =
+            Arc::new(move |account_id_hex: &str, _stop: &dyn Fn() -> bool| {
+                if account_id_hex == "large" {
+                    release_receiver.recv().expect("release channel closed");
+                }
+                synced_sender.send(account_id_hex.to_string()).unwrap();
+                Ok(())
+            });
+        let workers = SyncWorkers::start(
+            sync_activity.clone(),
+            2,
+            Arc::new(AtomicBool::new(false)),
+            sync_account,
+            logger,
+        );
+
+        assert!(workers.queue("large"));
+        assert!(workers.queue("small"));
+        assert_eq!(synced_receiver.recv_timeout(timeout).unwrap(), "small");
+
+        // As new blocks arrive, the small account is queued and synced again
+        // once its worker is done with it, while the large account is still
+        // being synced and isn't queued twice.
+        for _ in 0..3 {
+            assert!(!workers.queue("large"));
+            let deadline = Instant::now() + timeout;
+            while !workers.queue("small") {
+                assert!(Instant::now() < deadline, "small account was not requeued");
+                thread::sleep(Duration::from_millis(10));
+            }
+            assert_eq!(synced_receiver.recv_timeout(timeout).unwrap(), "small");
+            assert!(sync_activity.get("large").syncing);
+        }
+
+        release_sender.send(()).unwrap();
+        assert_eq!(synced_receiver.recv_timeout(timeout).unwrap(), "large");
+        workers.join();
+        assert!(!sync_activity.get("large").syncing);
+        assert!(sync_activity.in_flight.lock().unwrap().is_empty());
+    }
+
+    #[test_with_logger]
+    fn test_paused_account_is_not_synced(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let mut ledger_db = get_test_ledger(5, &vec![], 12, &mut rng);
+        let service = setup_wallet_service(ledger_db.clone(), logger.clone());
+        let conn = service.wallet_db.get_conn().unwrap();
+        let balance_cache = BalanceCache::default();
+
+        let mut import_account = || {
+            let entropy = RootEntropy::from_random(&mut rng);
+            let account = service
+                .import_account_from_legacy_root_entropy(
+                    hex::encode(&entropy.bytes),
+                    None,
+                    None,
+                    None,
+                    "".to_string(),
+                    "".to_string(),
+                    "".to_string(),
+                )
+                .unwrap();
+            (
+                AccountID(account.account_id_hex),
+                AccountKey::from(&RootIdentity::from(&entropy)),
+            )
+        };
+        let (cold_account_id, cold_account_key) = import_account();
+        let (hot_account_id, hot_account_key) = import_account();
+
+        let account = service.pause_account_sync(&cold_account_id).unwrap();
+        assert!(account.sync_paused);
+
+        add_block_to_ledger_db(
+            &mut ledger_db,
+            &[
+                cold_account_key.subaddress(0),
+                hot_account_key.subaddress(0),
+            ],
+            10 * MOB,
+            &[KeyImage::from(rng.next_u64())],
+            &mut rng,
+        );
+        sync_all_accounts(
+            &ledger_db,
+            &service.wallet_db,
+            &balance_cache,
+            &SyncActivity::default(),
+            None,
+            1,
+            DEFAULT_SYNC_CHUNK_SIZE,
+            &logger,
+        )
+        .unwrap();
+        let next_block_index =
+            |account_id: &AccountID| Account::get(account_id, &conn).unwrap().next_block_index;
+        assert_eq!(next_block_index(&cold_account_id), 0);
+        assert_eq!(next_block_index(&hot_account_id), 13);
+
+        // Once resumed, the account picks up where it left off.
+        let account = service.resume_account_sync(&cold_account_id).unwrap();
+        assert!(!account.sync_paused);
+        sync_all_accounts(
+            &ledger_db,
+            &service.wallet_db,
+            &balance_cache,
+            &SyncActivity::default(),
+            None,
+            1,
+            DEFAULT_SYNC_CHUNK_SIZE,
+            &logger,
+        )
+        .unwrap();
+        assert_eq!(next_block_index(&cold_account_id), 13);
+        assert_eq!(
+            Txo::list_for_account(&cold_account_id.0, None, None, None, &conn)
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    /// Labels txos by their value, and fails for the largest.
+    struct ValueClassifier;
+
+    impl TxoClassifier for ValueClassifier {
+        fn classify(&self, txo: &ReceivedTxoDetails) -> Result<Option<String>, String> {
+            match txo.value / MOB {
+                10 => Ok(None),
+                20 => Ok(Some(" payroll ".to_string())),
+                30 => Ok(Some("x".repeat(MAX_TXO_CLASSIFICATION_LEN + 1))),
+                _ => Err("classifier unavailable".to_string()),
+            }
+        }
+    }
+
+    #[test_with_logger]
+    fn test_txo_classifier(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        // The wallet is synced without a service, whose own sync thread would
+        // race this one without the classifier.
+        let mut ledger_db = get_test_ledger(5, &vec![], 12, &mut rng);
+        let db_test_context = WalletDbTestContext::default();
+        let wallet_db = db_test_context.get_db_instance(logger.clone());
+        let conn = wallet_db.get_conn().unwrap();
+
+        let root_id = RootIdentity::from_random(&mut rng);
+        let account_key = AccountKey::from(&root_id);
+        let (account_id, _) = Account::create_from_root_entropy(
+            &root_id.root_entropy,
+            Some(0),
+            None,
+            None,
+            "",
+            "".to_string(),
+            "".to_string(),
+            "".to_string(),
+            &conn,
+        )
+        .unwrap();
+
+        for value in [10, 20, 30, 40] {
+            add_block_to_ledger_db(
+                &mut ledger_db,
+                &[account_key.subaddress(0)],
+                value * MOB,
+                &[KeyImage::from(rng.next_u64())],
+                &mut rng,
+            );
+        }
+        let txo_classifier: Arc<dyn TxoClassifier> = Arc::new(ValueClassifier);
+        sync_all_accounts(
+            &ledger_db,
+            &wallet_db,
+            &BalanceCache::default(),
+            &SyncActivity::default(),
+            Some(txo_classifier),
+            1,
+            DEFAULT_SYNC_CHUNK_SIZE,
+            &logger,
+        )
+        .unwrap();
+
+        // Only the label which was given and valid is kept, and a classifier
+        // which fails doesn't hold up the sync.
+        let mut txos = Txo::list_for_account(&account_id.0, None, None, None, &conn).unwrap();
+        txos.sort_by_key(|txo| txo.value);
+        assert_eq!(
+            txos.iter()
+                .map(|txo| txo.classification.as_deref())
+                .collect::<Vec<_>>(),
+            vec![None, Some("payroll"), None, None]
+        );
+        assert_eq!(
+            Account::get(&account_id, &conn).unwrap().next_block_index,
+            16
+        );
+
+        // The label is carried by the txo's event.
+        let payroll_events: Vec<_> = WalletEvent::list(None, None, &conn)
+            .unwrap()
+            .into_iter()
+            .filter(|event| event.event_type == EVENT_TYPE_TXO_RECEIVED)
+            .map(|event| serde_json::from_str::<serde_json::Value>(&event.payload).unwrap())
+            .filter(|payload| payload["classification"] == "payroll")
+            .collect();
+        assert_eq!(payroll_events.len(), 1);
+        assert_eq!(payroll_events[0]["txo_id"], txos[1].txo_id_hex);
+    }
+
+    #[test_with_logger]
+    fn test_sync_account_in_chunks(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let root_id = RootIdentity::from_random(&mut rng);
+        let account_key = AccountKey::from(&root_id);
+        let ledger_db = get_test_ledger(5, &vec![account_key.subaddress(0)], 12, &mut rng);
+
+        let db_test_context = WalletDbTestContext::default();
+        let wallet_db = db_test_context.get_db_instance(logger.clone());
+        let conn = wallet_db.get_conn().unwrap();
+        let (account_id, _) = Account::create_from_root_entropy(
+            &root_id.root_entropy,
+            Some(0),
+            None,
+            None,
+            "",
+            "".to_string(),
+            "".to_string(),
+            "".to_string(),
+            &conn,
+        )
+        .unwrap();
+
+        sync_account_in_chunks(&ledger_db, &wallet_db, &account_id.0, 5, &logger).unwrap();
+
+        // Each chunk is committed with its own sync record.
+        let records =
+            AccountSyncRecord::list_for_account(&account_id.0, None, None, &conn).unwrap();
+        assert_eq!(
+            records
+                .iter()
+                .map(|record| (record.start_block_index, record.end_block_index))
+                .collect::<Vec<_>>(),
+            vec![(0, 4), (5, 9), (10, 11)]
+        );
+        assert_eq!(
+            Account::get(&account_id, &conn).unwrap().next_block_index,
+            12
+        );
+
+        let num_txos = Txo::list_for_account(&account_id.0, None, None, None, &conn)
+            .unwrap()
+            .len();
+        assert!(num_txos > 0);
+        assert_eq!(
+            records
+                .iter()
+                .map(|record| record.txos_received as usize)
+                .sum::<usize>(),
+            num_txos
+        );
+    }
+
     // #[test_with_logger]
     // fn test_sync_view_only_account(logger: Logger) {
     //     let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
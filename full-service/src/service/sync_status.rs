@@ -0,0 +1,339 @@
+// Copyright (c) 2020-2022 MobileCoin Inc.
+
+//! Service for reporting how far the sync has got with each account.
+//!
+//! The sync thread hands accounts to a pool of workers, which sync as many
+//! accounts at once as there are workers. An account which is behind the
+//! ledger is either being synced by a worker, or waiting for one to be free.
+//!
+//! How fast each account is being scanned is worked out from the chunks
+//! recorded for it over the last few minutes, so that integrators can see
+//! how long an account has left to sync without polling its block index.
+
+use crate::{
+    db::{
+        account::AccountModel,
+        account_sync_record::AccountSyncRecordModel,
+        models::{Account, AccountSyncRecord},
+        WalletDbError,
+    },
+    service::ledger::{LedgerService, LedgerServiceError},
+    WalletService,
+};
+use chrono::Utc;
+use displaydoc::Display;
+use mc_connection::{BlockchainConnection, UserTxConnection};
+use mc_fog_report_validation::FogPubkeyResolver;
+use mc_ledger_db::Ledger;
+use std::{collections::HashMap, time::Duration};
+
+/// The account has scanned every block in the local ledger.
+pub const ACCOUNT_SYNC_STATUS_SYNCED: &str = "synced";
+
+/// A sync worker is scanning blocks for the account.
+pub const ACCOUNT_SYNC_STATUS_SYNCING: &str = "syncing";
+
+/// The account is behind the ledger, and waiting for a sync worker.
+pub const ACCOUNT_SYNC_STATUS_WAITING: &str = "waiting";
+
+/// The account's sync has been paused with pause_account_sync.
+pub const ACCOUNT_SYNC_STATUS_PAUSED: &str = "paused";
+
+/// How far back the chunks an account scanned are counted towards its scan
+/// rate.
+const SYNC_RATE_WINDOW: Duration = Duration::from_secs(10 * 60);
+
+/// How long the sync thread may go without starting a pass or committing a
+/// chunk before it is reported as unhealthy.
+const SYNC_STALL_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+/// Errors for the Sync Status Service.
+#[derive(Display, Debug)]
+#[allow(clippy::large_enum_variant)]
+pub enum SyncStatusServiceError {
+    /// Error interacting with the database: {0}
+    Database(WalletDbError),
+
+    /// Error with LedgerDB: {0}
+    LedgerDB(mc_ledger_db::Error),
+
+    /// Error getting network block height: {0}
+    NetworkBlockHeight(LedgerServiceError),
+}
+
+impl From<WalletDbError> for SyncStatusServiceError {
+    fn from(src: WalletDbError) -> Self {
+        Self::Database(src)
+    }
+}
+
+impl From<mc_ledger_db::Error> for SyncStatusServiceError {
+    fn from(src: mc_ledger_db::Error) -> Self {
+        Self::LedgerDB(src)
+    }
+}
+
+impl From<LedgerServiceError> for SyncStatusServiceError {
+    fn from(src: LedgerServiceError) -> Self {
+        Self::NetworkBlockHeight(src)
+    }
+}
+
+/// How far the sync has got with an account.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AccountSyncStatus {
+    pub account_id_hex: String,
+
+    /// The next block the account will scan.
+    pub next_block_index: u64,
+
+    /// The blocks in the local ledger which the account has yet to scan.
+    pub blocks_remaining: u64,
+
+    /// One of the ACCOUNT_SYNC_STATUS constants.
+    pub status: &'static str,
+
+    /// The error the account's last sync failed with, if it did.
+    pub last_error: Option<String>,
+
+    /// How many blocks a second the account was scanned at over the last
+    /// SYNC_RATE_WINDOW, or None if it scanned none.
+    pub blocks_per_second: Option<f64>,
+
+    /// How long until the account reaches the network's block height, at its
+    /// scan rate. Zero once it has, and None if it is paused or has no scan
+    /// rate.
+    pub eta_seconds: Option<u64>,
+}
+
+/// How far the sync has got with every account in the wallet.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SyncStatus {
+    /// The number of blocks in the local ledger.
+    pub local_block_height: u64,
+
+    /// The number of blocks on the network, or in the local ledger if it is
+    /// ahead of the network's peers.
+    pub network_block_height: u64,
+
+    /// The number of accounts which can be synced at once.
+    pub sync_threads: usize,
+
+    /// Whether the sync thread has started a pass or committed a chunk within
+    /// SYNC_STALL_TIMEOUT.
+    pub sync_thread_healthy: bool,
+
+    pub accounts: Vec<AccountSyncStatus>,
+}
+
+/// Trait defining the ways in which the wallet can report the sync's
+/// progress.
+pub trait SyncStatusService {
+    /// Report how far the sync has got with each account, and whether a sync
+    /// worker is busy with it.
+    fn get_sync_status(&self) -> Result<SyncStatus, SyncStatusServiceError>;
+}
+
+impl<T, FPR> SyncStatusService for WalletService<T, FPR>
+where
+    T: BlockchainConnection + UserTxConnection + 'static,
+    FPR: FogPubkeyResolver + Send + Sync + 'static,
+{
+    fn get_sync_status(&self) -> Result<SyncStatus, SyncStatusServiceError> {
+        let local_block_height = self.ledger_db.num_blocks()?;
+        let network_block_height = self.get_network_block_height()?.max(local_block_height);
+        let conn = self.wallet_db.get_conn()?;
+
+        let window_start = Utc::now().timestamp() - SYNC_RATE_WINDOW.as_secs() as i64;
+        let mut recent_records: HashMap<String, Vec<AccountSyncRecord>> = HashMap::new();
+        for record in AccountSyncRecord::list_since(window_start, &conn)? {
+            recent_records
+                .entry(record.account_id_hex.clone())
+                .or_default()
+                .push(record);
+        }
+
+        let accounts = Account::list_all(&conn)?
+            .into_iter()
+            .map(|account| {
+                let next_block_index = account.next_block_index as u64;
+                let blocks_remaining = local_block_height.saturating_sub(next_block_index);
+                let activity = self.sync_activity.get(&account.account_id_hex);
+                let status =
+                    account_sync_status(activity.syncing, account.sync_paused, blocks_remaining);
+                let blocks_per_second = recent_records
+                    .get(&account.account_id_hex)
+                    .and_then(|records| scan_rate(records));
+                let eta_seconds = if account.sync_paused {
+                    None
+                } else {
+                    eta_seconds(
+                        network_block_height.saturating_sub(next_block_index),
+                        blocks_per_second,
+                    )
+                };
+                AccountSyncStatus {
+                    account_id_hex: account.account_id_hex,
+                    next_block_index,
+                    blocks_remaining,
+                    status,
+                    last_error: activity.last_error,
+                    blocks_per_second,
+                    eta_seconds,
+                }
+            })
+            .collect();
+
+        let sync_thread_healthy = self
+            .sync_activity
+            .last_progress()
+            .map_or(false, |last| last.elapsed() < SYNC_STALL_TIMEOUT);
+
+        Ok(SyncStatus {
+            local_block_height,
+            network_block_height,
+            sync_threads: self.sync_threads,
+            sync_thread_healthy,
+            accounts,
+        })
+    }
+}
+
+/// An account's sync status. A worker may still be finishing with an account
+/// which was paused while it was being synced.
+fn account_sync_status(syncing: bool, sync_paused: bool, blocks_remaining: u64) -> &'static str {
+    if syncing {
+        ACCOUNT_SYNC_STATUS_SYNCING
+    } else if sync_paused {
+        ACCOUNT_SYNC_STATUS_PAUSED
+    } else if blocks_remaining == 0 {
+        ACCOUNT_SYNC_STATUS_SYNCED
+    } else {
+        ACCOUNT_SYNC_STATUS_WAITING
+    }
+}
+
+/// The blocks a second scanned over the chunks, or None if they scanned no
+/// blocks.
+fn scan_rate(records: &[AccountSyncRecord]) -> Option<f64> {
+    let num_blocks: u64 = records.iter().map(|record| record.num_blocks()).sum();
+    if num_blocks == 0 {
+        return None;
+    }
+    // Chunks of a block or two can finish within a millisecond.
+    let duration_ms: i64 = records.iter().map(|record| record.duration_ms).sum();
+    Some(num_blocks as f64 * 1000.0 / duration_ms.max(1) as f64)
+}
+
+/// How many seconds scanning the blocks remaining takes at the scan rate,
+/// rounded up.
+fn eta_seconds(blocks_remaining: u64, blocks_per_second: Option<f64>) -> Option<u64> {
+    if blocks_remaining == 0 {
+        return Some(0);
+    }
+    blocks_per_second
+        .filter(|rate| *rate > 0.0)
+        .map(|rate| (blocks_remaining as f64 / rate).ceil() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        db::account::AccountID,
+        service::account::AccountService,
+        test_utils::{get_test_ledger, manually_sync_account, setup_wallet_service},
+    };
+    use mc_account_keys::PublicAddress;
+    use mc_common::logger::{test_with_logger, Logger};
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test_with_logger]
+    fn test_get_sync_status(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+        let service = setup_wallet_service(ledger_db.clone(), logger.clone());
+
+        let create_account = |name: &str| {
+            AccountID(
+                service
+                    .create_account(
+                        Some(name.to_string()),
+                        "".to_string(),
+                        "".to_string(),
+                        "".to_string(),
+                    )
+                    .unwrap()
+                    .account_id_hex,
+            )
+        };
+        let synced_account_id = create_account("Synced");
+        let paused_account_id = create_account("Paused");
+        manually_sync_account(&ledger_db, &service.wallet_db, &synced_account_id, &logger);
+        service.pause_account_sync(&paused_account_id).unwrap();
+
+        let sync_status = service.get_sync_status().unwrap();
+        assert_eq!(sync_status.local_block_height, 12);
+        // The test wallet has no peers, so the network is as far as the ledger.
+        assert_eq!(sync_status.network_block_height, 12);
+        assert_eq!(sync_status.sync_threads, 1);
+        let status_of = |account_id: &AccountID| {
+            sync_status
+                .accounts
+                .iter()
+                .find(|account| account.account_id_hex == account_id.0)
+                .unwrap()
+                .clone()
+        };
+
+        let synced = status_of(&synced_account_id);
+        assert_eq!(synced.status, ACCOUNT_SYNC_STATUS_SYNCED);
+        assert_eq!(synced.next_block_index, 12);
+        assert_eq!(synced.blocks_remaining, 0);
+        assert_eq!(synced.last_error, None);
+        assert!(synced.blocks_per_second.unwrap() > 0.0);
+        assert_eq!(synced.eta_seconds, Some(0));
+
+        let paused = status_of(&paused_account_id);
+        assert_eq!(paused.status, ACCOUNT_SYNC_STATUS_PAUSED);
+        assert_eq!(paused.eta_seconds, None);
+    }
+
+    #[test]
+    fn test_eta_seconds() {
+        assert_eq!(eta_seconds(0, None), Some(0));
+        assert_eq!(eta_seconds(100, None), None);
+        assert_eq!(eta_seconds(100, Some(0.0)), None);
+        assert_eq!(eta_seconds(100, Some(40.0)), Some(3));
+        assert_eq!(eta_seconds(100, Some(50.0)), Some(2));
+    }
+
+    #[test]
+    fn test_account_sync_status() {
+        assert_eq!(
+            account_sync_status(true, false, 10),
+            ACCOUNT_SYNC_STATUS_SYNCING
+        );
+        assert_eq!(
+            account_sync_status(true, true, 10),
+            ACCOUNT_SYNC_STATUS_SYNCING
+        );
+        assert_eq!(
+            account_sync_status(false, true, 10),
+            ACCOUNT_SYNC_STATUS_PAUSED
+        );
+        assert_eq!(
+            account_sync_status(false, true, 0),
+            ACCOUNT_SYNC_STATUS_PAUSED
+        );
+        assert_eq!(
+            account_sync_status(false, false, 0),
+            ACCOUNT_SYNC_STATUS_SYNCED
+        );
+        assert_eq!(
+            account_sync_status(false, false, 10),
+            ACCOUNT_SYNC_STATUS_WAITING
+        );
+    }
+}
@@ -0,0 +1,169 @@
+// Copyright (c) 2020-2022 MobileCoin Inc.
+
+//! Service for describing the tokens the wallet knows about.
+//!
+//! Clients need a token's symbol and number of decimals to display amounts in
+//! it. These come from a built-in table of well-known tokens, which can be
+//! extended or corrected with a local override file, while minimum fees come
+//! from the network, unless overridden.
+
+use crate::{service::ledger::LedgerService, WalletService};
+use mc_connection::{BlockchainConnection, UserTxConnection};
+use mc_fog_report_validation::FogPubkeyResolver;
+use mc_transaction_core::TokenId;
+use serde_derive::Deserialize;
+use std::collections::BTreeMap;
+
+/// Tokens whose symbol and decimals are known without any configuration, as
+/// (token_id, symbol, decimals).
+const KNOWN_TOKENS: &[(u64, &str, u32)] = &[(0, "MOB", 12), (1, "eUSD", 6)];
+
+/// A locally configured override for a token's metadata.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+pub struct TokenMetadataOverride {
+    pub token_id: u64,
+    pub symbol: Option<String>,
+    pub decimals: Option<u32>,
+    pub minimum_fee: Option<u64>,
+}
+
+/// Metadata for a token.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TokenMetadata {
+    pub token_id: TokenId,
+
+    /// The token's symbol, if known.
+    pub symbol: Option<String>,
+
+    /// The number of decimal places between the token's smallest unit and its
+    /// display unit, if known.
+    pub decimals: Option<u32>,
+
+    /// The minimum fee for a transaction paying its fee in this token, in the
+    /// token's smallest unit, if known.
+    pub minimum_fee: Option<u64>,
+}
+
+/// Trait defining the ways in which the wallet can describe tokens.
+pub trait TokenService {
+    /// List the tokens known from the built-in table, the network's fee
+    /// configuration, and the local overrides, ordered by token id.
+    fn get_tokens(&self) -> Vec<TokenMetadata>;
+}
+
+impl<T, FPR> TokenService for WalletService<T, FPR>
+where
+    T: BlockchainConnection + UserTxConnection + 'static,
+    FPR: FogPubkeyResolver + Send + Sync + 'static,
+{
+    fn get_tokens(&self) -> Vec<TokenMetadata> {
+        let mut tokens: BTreeMap<TokenId, TokenMetadata> = BTreeMap::new();
+
+        for (token_id, symbol, decimals) in KNOWN_TOKENS {
+            let token_id = TokenId::from(*token_id);
+            tokens.insert(
+                token_id,
+                TokenMetadata {
+                    token_id,
+                    symbol: Some(symbol.to_string()),
+                    decimals: Some(*decimals),
+                    minimum_fee: None,
+                },
+            );
+        }
+
+        for (token_id, minimum_fee) in self.get_network_minimum_fees() {
+            tokens
+                .entry(token_id)
+                .or_insert_with(|| unknown_token(token_id))
+                .minimum_fee = Some(minimum_fee);
+        }
+
+        for token_override in &self.token_metadata_overrides {
+            let token_id = TokenId::from(token_override.token_id);
+            let token = tokens
+                .entry(token_id)
+                .or_insert_with(|| unknown_token(token_id));
+            if let Some(symbol) = &token_override.symbol {
+                token.symbol = Some(symbol.clone());
+            }
+            if let Some(decimals) = token_override.decimals {
+                token.decimals = Some(decimals);
+            }
+            if let Some(minimum_fee) = token_override.minimum_fee {
+                token.minimum_fee = Some(minimum_fee);
+            }
+        }
+
+        tokens.into_values().collect()
+    }
+}
+
+fn unknown_token(token_id: TokenId) -> TokenMetadata {
+    TokenMetadata {
+        token_id,
+        symbol: None,
+        decimals: None,
+        minimum_fee: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{get_test_ledger, setup_wallet_service};
+    use mc_common::logger::{test_with_logger, Logger};
+    use mc_transaction_core::{tokens::Mob, Token};
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test_with_logger]
+    fn test_get_tokens(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let known_recipients = vec![];
+        let ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+        let mut service = setup_wallet_service(ledger_db, logger);
+
+        let tokens = service.get_tokens();
+        assert_eq!(tokens[0].token_id, Mob::ID);
+        assert_eq!(tokens[0].symbol, Some("MOB".to_string()));
+        assert_eq!(tokens[0].decimals, Some(12));
+        assert!(tokens[0].minimum_fee.is_some());
+        assert_eq!(tokens[1].token_id, TokenId::from(1));
+        assert_eq!(tokens[1].symbol, Some("eUSD".to_string()));
+
+        // Overrides correct known tokens and add unknown ones.
+        service.token_metadata_overrides = vec![
+            TokenMetadataOverride {
+                token_id: 1,
+                minimum_fee: Some(2560),
+                ..Default::default()
+            },
+            TokenMetadataOverride {
+                token_id: 8192,
+                symbol: Some("TEST".to_string()),
+                decimals: Some(2),
+                minimum_fee: None,
+            },
+        ];
+        let tokens = service.get_tokens();
+        assert_eq!(
+            tokens[1],
+            TokenMetadata {
+                token_id: TokenId::from(1),
+                symbol: Some("eUSD".to_string()),
+                decimals: Some(6),
+                minimum_fee: Some(2560),
+            }
+        );
+        assert_eq!(
+            tokens.last().unwrap(),
+            &TokenMetadata {
+                token_id: TokenId::from(8192),
+                symbol: Some("TEST".to_string()),
+                decimals: Some(2),
+                minimum_fee: None,
+            }
+        );
+    }
+}
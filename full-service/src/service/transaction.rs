@@ -5,17 +5,24 @@
 use crate::{
     db::{
         account::{AccountID, AccountModel},
-        models::{Account, TransactionLog, ViewOnlyAccount, ViewOnlyTxo},
+        models::{
+            Account, TransactionLog, Txo, ViewOnlyAccount, ViewOnlyTxo, WalletSetting,
+            TX_STATUS_BUILT,
+        },
         transaction,
-        transaction_log::{AssociatedTxos, TransactionLogModel},
-        txo::TxoID,
+        transaction_log::{AssociatedTxos, TransactionID, TransactionLogModel},
+        txo::{TxoID, TxoModel},
         view_only_account::ViewOnlyAccountModel,
         view_only_txo::ViewOnlyTxoModel,
+        wallet_settings::WalletSettingModel,
         WalletDbError,
     },
     error::WalletTransactionBuilderError,
+    metrics,
     service::{
-        ledger::LedgerService, transaction_builder::WalletTransactionBuilder, WalletService,
+        ledger::LedgerService,
+        transaction_builder::{ChangeOutputs, WalletTransactionBuilder},
+        WalletService,
     },
     util::b58::{b58_decode_public_address, B58Error},
 };
@@ -24,15 +31,19 @@ use mc_connection::{BlockchainConnection, RetryableUserTxConnection, UserTxConne
 use mc_fog_report_validation::FogPubkeyResolver;
 use mc_ledger_db::Ledger;
 use mc_mobilecoind::payments::TxProposal;
-use mc_transaction_core::constants::{MAX_INPUTS, MAX_OUTPUTS};
+use mc_transaction_core::{
+    constants::{MAX_INPUTS, MAX_OUTPUTS},
+    tokens::Mob,
+    Token, TokenId,
+};
 
 use crate::{
     fog_resolver::FullServiceFogResolver,
     service::address::{AddressService, AddressServiceError},
-    unsigned_tx::UnsignedTx,
+    unsigned_tx::{OfflineSignedTransaction, OfflineSigningRequest, UnsignedTx},
 };
 use displaydoc::Display;
-use std::{convert::TryFrom, iter::empty, sync::atomic::Ordering};
+use std::{convert::TryFrom, iter::empty, sync::atomic::Ordering, time::Instant};
 
 /// Errors for the Transaction Service.
 #[derive(Display, Debug)]
@@ -70,6 +81,9 @@ pub enum TransactionServiceError {
     /// Cannot complete this action in offline mode.
     Offline,
 
+    /// Spending has been disabled on this wallet
+    SpendingDisabled,
+
     /// Connection Error
     Connection(retry::Error<mc_connection::Error>),
 
@@ -84,6 +98,21 @@ pub enum TransactionServiceError {
 
     /// Ledger DB Error: {0}
     LedgerDB(mc_ledger_db::Error),
+
+    /// No minimum fee is known for token {0}
+    UnknownMinimumFee(u64),
+
+    /// Sending {0} exceeds the large send threshold, and was not confirmed
+    LargeSendNotConfirmed(u128),
+
+    /// No transaction signer is configured for hardware backed accounts
+    NoTransactionSigner,
+
+    /// View only account {0} is not hardware backed
+    NotHardwareBacked(String),
+
+    /// The transaction signer did not sign the transaction: {0}
+    Signer(String),
 }
 
 impl From<WalletDbError> for TransactionServiceError {
@@ -140,27 +169,95 @@ impl From<mc_ledger_db::Error> for TransactionServiceError {
     }
 }
 
+/// Signs transactions for accounts whose spend key the wallet does not hold,
+/// such as accounts on a hardware wallet. The wallet only sees the signed
+/// transaction, never the spend key.
+pub trait TransactionSigner: Send + Sync {
+    /// Sign a transaction built for the view only account, returning why it
+    /// was not signed if it wasn't.
+    fn sign_transaction(
+        &self,
+        account_id_hex: &str,
+        unsigned_tx: UnsignedTx,
+        fog_resolver: FullServiceFogResolver,
+    ) -> Result<TxProposal, String>;
+}
+
 /// Trait defining the ways in which the wallet can interact with and manage
 /// transactions.
 pub trait TransactionService {
+    /// Builds a transaction from a view only account to the specified
+    /// recipients, to be signed by the holder of the account's spend key.
+    ///
+    /// The inputs, token, fee, change and memo are chosen as for
+    /// build_transaction, except that the token defaults to MOB.
+    #[allow(clippy::too_many_arguments)]
     fn build_unsigned_transaction(
         &self,
         account_id_hex: &str,
         addresses_and_values: &[(String, String)],
+        input_txo_ids: Option<&Vec<String>>,
+        token_id: Option<String>,
         fee: Option<String>,
+        fee_token_id: Option<String>,
         tombstone_block: Option<String>,
+        max_spendable_value: Option<String>,
+        payment_request_id: Option<String>,
+        coin_selection_strategy: Option<String>,
+        change_outputs: Option<ChangeOutputs>,
     ) -> Result<(UnsignedTx, FullServiceFogResolver), TransactionServiceError>;
 
+    /// Builds an unsigned transaction for a view only account, packaged with
+    /// everything needed to sign it on an offline machine.
+    #[allow(clippy::too_many_arguments)]
+    fn build_signing_request(
+        &self,
+        account_id_hex: &str,
+        addresses_and_values: &[(String, String)],
+        input_txo_ids: Option<&Vec<String>>,
+        token_id: Option<String>,
+        fee: Option<String>,
+        fee_token_id: Option<String>,
+        tombstone_block: Option<String>,
+        max_spendable_value: Option<String>,
+        payment_request_id: Option<String>,
+        coin_selection_strategy: Option<String>,
+        change_outputs: Option<ChangeOutputs>,
+    ) -> Result<OfflineSigningRequest, TransactionServiceError>;
+
+    /// Submits a transaction signed offline from a signing request, logging it
+    /// for the account it spends from.
+    fn submit_signed_transaction(
+        &self,
+        signed_transaction: OfflineSignedTransaction,
+        comment: Option<String>,
+    ) -> Result<Option<(TransactionLog, AssociatedTxos)>, TransactionServiceError>;
+
     /// Builds a transaction from the given account to the specified recipients.
+    ///
+    /// The transaction sends the account's preferred token unless another
+    /// token is given, and the fee is paid in the same token unless another
+    /// fee token is given. Sends above the configured large send threshold
+    /// must be confirmed with confirm_large_send.
+    ///
+    /// A payment request id is carried to the recipients in the memo of each
+    /// output. Outputs may have a value of zero, so that a transaction can
+    /// carry only the memo, though the fee is still paid in full.
     #[allow(clippy::too_many_arguments)]
     fn build_transaction(
         &self,
         account_id_hex: &str,
         addresses_and_values: &[(String, String)],
         input_txo_ids: Option<&Vec<String>>,
+        token_id: Option<String>,
         fee: Option<String>,
+        fee_token_id: Option<String>,
         tombstone_block: Option<String>,
         max_spendable_value: Option<String>,
+        confirm_large_send: Option<bool>,
+        payment_request_id: Option<String>,
+        coin_selection_strategy: Option<String>,
+        change_outputs: Option<ChangeOutputs>,
         log_tx_proposal: Option<bool>,
     ) -> Result<TxProposal, TransactionServiceError>;
 
@@ -179,11 +276,27 @@ pub trait TransactionService {
         account_id_hex: &str,
         addresses_and_values: &[(String, String)],
         input_txo_ids: Option<&Vec<String>>,
+        token_id: Option<String>,
         fee: Option<String>,
+        fee_token_id: Option<String>,
         tombstone_block: Option<String>,
         max_spendable_value: Option<String>,
+        confirm_large_send: Option<bool>,
+        payment_request_id: Option<String>,
+        coin_selection_strategy: Option<String>,
+        change_outputs: Option<ChangeOutputs>,
         comment: Option<String>,
     ) -> Result<(TransactionLog, AssociatedTxos, TxProposal), TransactionServiceError>;
+
+    /// Builds a transaction from a hardware backed view only account, has the
+    /// transaction signer sign it, and submits it.
+    fn build_sign_and_submit_transaction(
+        &self,
+        account_id_hex: &str,
+        addresses_and_values: &[(String, String)],
+        fee: Option<String>,
+        tombstone_block: Option<String>,
+    ) -> Result<TxProposal, TransactionServiceError>;
 }
 
 impl<T, FPR> TransactionService for WalletService<T, FPR>
@@ -195,10 +308,23 @@ where
         &self,
         account_id_hex: &str,
         addresses_and_values: &[(String, String)],
+        input_txo_ids: Option<&Vec<String>>,
+        token_id: Option<String>,
         fee: Option<String>,
+        fee_token_id: Option<String>,
         tombstone_block: Option<String>,
+        max_spendable_value: Option<String>,
+        payment_request_id: Option<String>,
+        coin_selection_strategy: Option<String>,
+        change_outputs: Option<ChangeOutputs>,
     ) -> Result<(UnsignedTx, FullServiceFogResolver), TransactionServiceError> {
-        validate_number_outputs(addresses_and_values.len() as u64)?;
+        validate_number_inputs(input_txo_ids.unwrap_or(&Vec::new()).len() as u64)?;
+        validate_number_outputs(
+            addresses_and_values.len() as u64,
+            change_outputs
+                .as_ref()
+                .map_or(1, ChangeOutputs::num_outputs) as u64,
+        )?;
 
         let conn = self.wallet_db.get_conn()?;
         transaction(&conn, || {
@@ -225,32 +351,139 @@ where
                 builder.set_tombstone(0)?;
             }
 
+            let token_id = match token_id {
+                Some(t) => TokenId::from(t.parse::<u64>()?),
+                None => Mob::ID,
+            };
+            builder.set_token_id(token_id);
+
+            // Whether the fee can be paid in another token depends on the
+            // block version, so set it first.
+            builder.set_block_version(self.get_network_block_version());
+
+            let minimum_fees = self.get_network_minimum_fees();
+            let fee_token_id = match fee_token_id {
+                Some(fee_token_id) => {
+                    let fee_token_id = TokenId::from(fee_token_id.parse::<u64>()?);
+                    if !minimum_fees.contains_key(&fee_token_id) {
+                        return Err(TransactionServiceError::UnknownMinimumFee(*fee_token_id));
+                    }
+                    builder.set_fee_token_id(fee_token_id)?;
+                    fee_token_id
+                }
+                None => token_id,
+            };
+
+            // Without an explicit fee, pay the configured multiple of the
+            // network minimum for the fee token.
             builder.set_fee(match fee {
                 Some(f) => f.parse()?,
-                None => self.get_network_fee(),
+                None => minimum_fees
+                    .get(&fee_token_id)
+                    .ok_or(TransactionServiceError::UnknownMinimumFee(*fee_token_id))?
+                    .saturating_mul(WalletSetting::get_settings(&conn)?.default_fee_multiplier),
             })?;
 
-            let unsigned_tx = builder.build_unsigned(&conn)?;
+            if let Some(payment_request_id) = payment_request_id {
+                builder.set_payment_request_id(payment_request_id.parse::<u64>()?);
+            }
+
+            if let Some(coin_selection_strategy) = coin_selection_strategy {
+                builder.set_coin_selection_strategy(coin_selection_strategy.parse()?);
+            }
+
+            if let Some(change_outputs) = change_outputs {
+                builder.set_change_outputs(change_outputs)?;
+            }
+
+            if let Some(inputs) = input_txo_ids {
+                builder.set_view_only_txos(&conn, inputs)?;
+            } else {
+                let max_spendable = if let Some(msv) = max_spendable_value {
+                    Some(msv.parse::<u64>()?)
+                } else {
+                    None
+                };
+                builder.select_view_only_txos(&conn, max_spendable)?;
+            }
+
+            let unsigned_tx = builder.build_unsigned()?;
             let fog_resolver = builder.get_fs_fog_resolver(&conn)?;
 
             Ok((unsigned_tx, fog_resolver))
         })
     }
+
+    fn build_signing_request(
+        &self,
+        account_id_hex: &str,
+        addresses_and_values: &[(String, String)],
+        input_txo_ids: Option<&Vec<String>>,
+        token_id: Option<String>,
+        fee: Option<String>,
+        fee_token_id: Option<String>,
+        tombstone_block: Option<String>,
+        max_spendable_value: Option<String>,
+        payment_request_id: Option<String>,
+        coin_selection_strategy: Option<String>,
+        change_outputs: Option<ChangeOutputs>,
+    ) -> Result<OfflineSigningRequest, TransactionServiceError> {
+        let (unsigned_tx, fog_resolver) = self.build_unsigned_transaction(
+            account_id_hex,
+            addresses_and_values,
+            input_txo_ids,
+            token_id,
+            fee,
+            fee_token_id,
+            tombstone_block,
+            max_spendable_value,
+            payment_request_id,
+            coin_selection_strategy,
+            change_outputs,
+        )?;
+        let signing_request = OfflineSigningRequest::new(account_id_hex, unsigned_tx, fog_resolver);
+        signing_request.validate()?;
+        Ok(signing_request)
+    }
+
+    fn submit_signed_transaction(
+        &self,
+        signed_transaction: OfflineSignedTransaction,
+        comment: Option<String>,
+    ) -> Result<Option<(TransactionLog, AssociatedTxos)>, TransactionServiceError> {
+        let tx_proposal = signed_transaction.tx_proposal()?;
+        {
+            let conn = self.wallet_db.get_conn()?;
+            ViewOnlyAccount::get(&signed_transaction.account_id, &conn)?;
+        }
+        self.submit_transaction(tx_proposal, comment, Some(signed_transaction.account_id))
+    }
     fn build_transaction(
         &self,
         account_id_hex: &str,
         addresses_and_values: &[(String, String)],
         input_txo_ids: Option<&Vec<String>>,
+        token_id: Option<String>,
         fee: Option<String>,
+        fee_token_id: Option<String>,
         tombstone_block: Option<String>,
         max_spendable_value: Option<String>,
+        confirm_large_send: Option<bool>,
+        payment_request_id: Option<String>,
+        coin_selection_strategy: Option<String>,
+        change_outputs: Option<ChangeOutputs>,
         log_tx_proposal: Option<bool>,
     ) -> Result<TxProposal, TransactionServiceError> {
         validate_number_inputs(input_txo_ids.unwrap_or(&Vec::new()).len() as u64)?;
-        validate_number_outputs(addresses_and_values.len() as u64)?;
+        validate_number_outputs(
+            addresses_and_values.len() as u64,
+            change_outputs
+                .as_ref()
+                .map_or(1, ChangeOutputs::num_outputs) as u64,
+        )?;
 
         let conn = self.wallet_db.get_conn()?;
-        transaction(&conn, || {
+        let tx_proposal = transaction(&conn, || {
             let mut builder = WalletTransactionBuilder::new(
                 account_id_hex.to_string(),
                 self.ledger_db.clone(),
@@ -274,12 +507,83 @@ where
                 builder.set_tombstone(0)?;
             }
 
+            let token_id = match token_id {
+                Some(t) => TokenId::from(t.parse::<u64>()?),
+                None => TokenId::from(
+                    Account::get(&AccountID(account_id_hex.to_string()), &conn)?.preferred_token_id
+                        as u64,
+                ),
+            };
+            builder.set_token_id(token_id);
+
+            if self.large_send_guard.is_enabled() {
+                let value = addresses_and_values
+                    .iter()
+                    .map(|(_, value)| value.parse::<u64>().map(|v| v as u128))
+                    .sum::<Result<u128, _>>()?;
+                let balance = Txo::list_unspent(account_id_hex, None, Some(*token_id), &conn)?
+                    .iter()
+                    .map(|txo| (txo.value as u64) as u128)
+                    .sum::<u128>();
+                if self.large_send_guard.denies(value, balance)
+                    && !confirm_large_send.unwrap_or_default()
+                {
+                    return Err(TransactionServiceError::LargeSendNotConfirmed(value));
+                }
+                if self.large_send_guard.warns(value, balance) {
+                    log::warn!(
+                        self.logger,
+                        "Large send of {} in token {} from account {} with balance {}",
+                        value,
+                        *token_id,
+                        account_id_hex,
+                        balance
+                    );
+                }
+            }
+
+            // Whether the fee can be paid in another token depends on the
+            // block version, so set it first.
+            builder.set_block_version(self.get_network_block_version());
+
+            // The fee is paid in the token sent unless another is given, which
+            // must be one the network reports a minimum fee for, as consensus
+            // rejects fees in any other token.
+
+            let minimum_fees = self.get_network_minimum_fees();
+            let fee_token_id = match fee_token_id {
+                Some(fee_token_id) => {
+                    let fee_token_id = TokenId::from(fee_token_id.parse::<u64>()?);
+                    if !minimum_fees.contains_key(&fee_token_id) {
+                        return Err(TransactionServiceError::UnknownMinimumFee(*fee_token_id));
+                    }
+                    builder.set_fee_token_id(fee_token_id)?;
+                    fee_token_id
+                }
+                None => token_id,
+            };
+
+            // Without an explicit fee, pay the configured multiple of the
+            // network minimum for the fee token.
             builder.set_fee(match fee {
                 Some(f) => f.parse()?,
-                None => self.get_network_fee(),
+                None => minimum_fees
+                    .get(&fee_token_id)
+                    .ok_or(TransactionServiceError::UnknownMinimumFee(*fee_token_id))?
+                    .saturating_mul(WalletSetting::get_settings(&conn)?.default_fee_multiplier),
             })?;
 
-            builder.set_block_version(self.get_network_block_version());
+            if let Some(payment_request_id) = payment_request_id {
+                builder.set_payment_request_id(payment_request_id.parse::<u64>()?);
+            }
+
+            if let Some(coin_selection_strategy) = coin_selection_strategy {
+                builder.set_coin_selection_strategy(coin_selection_strategy.parse()?);
+            }
+
+            if let Some(change_outputs) = change_outputs {
+                builder.set_change_outputs(change_outputs)?;
+            }
 
             if let Some(inputs) = input_txo_ids {
                 builder.set_txos(&conn, inputs, log_tx_proposal.unwrap_or_default())?;
@@ -295,10 +599,8 @@ where
             let tx_proposal = builder.build(&conn)?;
 
             if log_tx_proposal.unwrap_or_default() {
-                let block_index = self.ledger_db.num_blocks()? - 1;
-                let _transaction_log = TransactionLog::log_submitted(
+                let _transaction_log = TransactionLog::log_built(
                     tx_proposal.clone(),
-                    block_index,
                     "".to_string(),
                     account_id_hex,
                     &conn,
@@ -306,7 +608,14 @@ where
             }
 
             Ok(tx_proposal)
-        })
+        })?;
+
+        // Logging the proposal marked its inputs pending.
+        if log_tx_proposal.unwrap_or_default() {
+            self.balance_cache.invalidate(account_id_hex);
+        }
+
+        Ok(tx_proposal)
     }
 
     fn submit_transaction(
@@ -318,6 +627,9 @@ where
         if self.offline {
             return Err(TransactionServiceError::Offline);
         }
+        if self.spending_lock.is_disabled() {
+            return Err(TransactionServiceError::SpendingDisabled);
+        }
 
         // Pick a peer to submit to.
         let responder_ids = self.peer_manager.responder_ids();
@@ -336,12 +648,22 @@ where
         let tx = mc_transaction_core::tx::Tx::try_from(tx_proposal_proto.get_tx())
             .map_err(|_| TransactionServiceError::ProtoConversionInfallible)?;
 
-        let block_index = self
-            .peer_manager
-            .conn(responder_id)
-            .ok_or(TransactionServiceError::NodeNotFound)?
-            .propose_tx(&tx, empty())
-            .map_err(TransactionServiceError::from)?;
+        // Prefer a warm connection from the pool, which has already attested.
+        let conn = match self
+            .consensus_pool
+            .as_ref()
+            .and_then(|pool| pool.conn(responder_id))
+        {
+            Some(conn) => conn,
+            None => self
+                .peer_manager
+                .conn(responder_id)
+                .ok_or(TransactionServiceError::NodeNotFound)?,
+        };
+        let start = Instant::now();
+        let proposed = conn.propose_tx(&tx, empty());
+        metrics::record_submit(&responder_id.to_string(), start.elapsed(), proposed.is_ok());
+        let block_index = proposed.map_err(TransactionServiceError::from)?;
 
         log::trace!(
             self.logger,
@@ -353,16 +675,31 @@ where
         if let Some(account_id_hex) = account_id_hex {
             let conn = self.wallet_db.get_conn()?;
             let account_id = AccountID(account_id_hex.to_string());
+            let sent_token_id = tx_proposal.tx.prefix.fee_token_id;
+            let sent_value = tx_proposal
+                .outlays
+                .iter()
+                .fold(0u64, |total, outlay| total.saturating_add(outlay.value));
 
-            transaction(&conn, || {
+            let result = transaction(&conn, || {
                 if Account::get(&account_id, &conn).is_ok() {
-                    let transaction_log = TransactionLog::log_submitted(
-                        tx_proposal,
-                        block_index,
-                        comment.unwrap_or_else(|| "".to_string()),
-                        &account_id_hex,
-                        &conn,
-                    )?;
+                    // A transaction logged when it was built already has a log,
+                    // which only needs to record that it was submitted.
+                    let transaction_id = TransactionID::from(&tx_proposal.tx);
+                    let comment = comment.unwrap_or_else(|| "".to_string());
+                    let transaction_log =
+                        match TransactionLog::get(&transaction_id.to_string(), &conn) {
+                            Ok(built) if built.status == TX_STATUS_BUILT => {
+                                built.update_to_submitted(block_index, comment, &conn)?
+                            }
+                            _ => TransactionLog::log_submitted(
+                                tx_proposal,
+                                block_index,
+                                comment,
+                                &account_id_hex,
+                                &conn,
+                            )?,
+                        };
 
                     let associated_txos = transaction_log.get_associated_txos(&conn)?;
 
@@ -385,7 +722,11 @@ where
                         WalletDbError::AccountNotFound(account_id_hex),
                     ))
                 }
-            })
+            })?;
+
+            self.balance_cache.invalidate(&account_id.0);
+            metrics::record_sent(&account_id.0, sent_token_id, sent_value);
+            Ok(result)
         } else {
             Ok(None)
         }
@@ -396,18 +737,35 @@ where
         account_id_hex: &str,
         addresses_and_values: &[(String, String)],
         input_txo_ids: Option<&Vec<String>>,
+        token_id: Option<String>,
         fee: Option<String>,
+        fee_token_id: Option<String>,
         tombstone_block: Option<String>,
         max_spendable_value: Option<String>,
+        confirm_large_send: Option<bool>,
+        payment_request_id: Option<String>,
+        coin_selection_strategy: Option<String>,
+        change_outputs: Option<ChangeOutputs>,
         comment: Option<String>,
     ) -> Result<(TransactionLog, AssociatedTxos, TxProposal), TransactionServiceError> {
+        // Check before building, so that the inputs are not left pending.
+        if self.spending_lock.is_disabled() {
+            return Err(TransactionServiceError::SpendingDisabled);
+        }
+
         let tx_proposal = self.build_transaction(
             account_id_hex,
             addresses_and_values,
             input_txo_ids,
+            token_id,
             fee,
+            fee_token_id,
             tombstone_block,
             max_spendable_value,
+            confirm_large_send,
+            payment_request_id,
+            coin_selection_strategy,
+            change_outputs,
             None,
         )?;
         if let Some(transaction_log_and_associated_txos) = self.submit_transaction(
@@ -424,6 +782,55 @@ where
             Err(TransactionServiceError::MissingAccountOnSubmit)
         }
     }
+
+    fn build_sign_and_submit_transaction(
+        &self,
+        account_id_hex: &str,
+        addresses_and_values: &[(String, String)],
+        fee: Option<String>,
+        tombstone_block: Option<String>,
+    ) -> Result<TxProposal, TransactionServiceError> {
+        // Check before building, so that the device isn't asked to sign a
+        // transaction which can't be submitted.
+        if self.spending_lock.is_disabled() {
+            return Err(TransactionServiceError::SpendingDisabled);
+        }
+        let signer = self
+            .transaction_signer
+            .as_ref()
+            .ok_or(TransactionServiceError::NoTransactionSigner)?;
+        {
+            let conn = self.wallet_db.get_conn()?;
+            if !ViewOnlyAccount::get(account_id_hex, &conn)?.hardware_backed {
+                return Err(TransactionServiceError::NotHardwareBacked(
+                    account_id_hex.to_string(),
+                ));
+            }
+        }
+
+        let (unsigned_tx, fog_resolver) = self.build_unsigned_transaction(
+            account_id_hex,
+            addresses_and_values,
+            None,
+            None,
+            fee,
+            None,
+            tombstone_block,
+            None,
+            None,
+            None,
+            None,
+        )?;
+        let tx_proposal = signer
+            .sign_transaction(account_id_hex, unsigned_tx.clone(), fog_resolver)
+            .map_err(TransactionServiceError::Signer)?;
+        // The signer is outside the wallet, so check what it signed matches
+        // what it was asked to before submitting. This can't check where the
+        // outputs are sent, so the signer must still be trusted.
+        unsigned_tx.verify_signed(&tx_proposal)?;
+        self.submit_transaction(tx_proposal.clone(), None, Some(account_id_hex.to_string()))?;
+        Ok(tx_proposal)
+    }
 }
 
 fn validate_number_inputs(num_inputs: u64) -> Result<(), TransactionServiceError> {
@@ -435,9 +842,12 @@ fn validate_number_inputs(num_inputs: u64) -> Result<(), TransactionServiceError
     Ok(())
 }
 
-fn validate_number_outputs(num_outputs: u64) -> Result<(), TransactionServiceError> {
-    // maximum number of outputs is 16 but we reserve 1 for change
-    let max_outputs = MAX_OUTPUTS - 1;
+fn validate_number_outputs(
+    num_outputs: u64,
+    num_change_outputs: u64,
+) -> Result<(), TransactionServiceError> {
+    // maximum number of outputs is 16 but we reserve some for change
+    let max_outputs = MAX_OUTPUTS.saturating_sub(num_change_outputs);
     if num_outputs > max_outputs {
         return Err(TransactionServiceError::TransactionBuilder(WalletTransactionBuilderError::InvalidArgument(
             format!("Invalid number of recipiants. {:?} recipiants provided but maximum allowed number of outputs is {:?}", num_outputs, max_outputs)
@@ -452,20 +862,34 @@ mod tests {
     use crate::{
         db::{account::AccountID, models::Txo, txo::TxoModel},
         service::{
-            account::AccountService, address::AddressService, balance::BalanceService,
+            account::AccountService,
+            address::AddressService,
+            balance::BalanceService,
+            large_send::{LargeSendGuard, LargeSendThreshold},
+            spending_lock::SpendingLock,
+            transaction_builder::MIXED_TRANSACTIONS_BLOCK_VERSION,
             transaction_log::TransactionLogService,
+            view_only_account::ViewOnlyAccountService,
+            WalletServiceOptions,
         },
         test_utils::{
             add_block_from_transaction_log, add_block_to_ledger_db, get_test_ledger,
-            manually_sync_account, setup_wallet_service, MOB,
+            manually_sync_account, manually_sync_view_only_account, setup_wallet_service,
+            setup_wallet_service_with, MOB,
         },
         util::b58::b58_encode_public_address,
     };
-    use mc_account_keys::{AccountKey, PublicAddress};
+    use mc_account_keys::{
+        AccountKey, PublicAddress, CHANGE_SUBADDRESS_INDEX, DEFAULT_SUBADDRESS_INDEX,
+    };
     use mc_common::logger::{test_with_logger, Logger};
+    use mc_connection_test_utils::MockBlockchainConnection;
     use mc_crypto_rand::rand_core::RngCore;
+    use mc_fog_report_validation::MockFogPubkeyResolver;
+    use mc_ledger_db::LedgerDB;
     use mc_transaction_core::{ring_signature::KeyImage, tokens::Mob, Token};
     use rand::{rngs::StdRng, SeedableRng};
+    use std::sync::{atomic::AtomicBool, Arc};
 
     #[test_with_logger]
     fn test_build_transaction_and_log(logger: Logger) {
@@ -515,7 +939,7 @@ mod tests {
 
         // Verify balance for Alice
         let balance = service
-            .get_balance_for_account(&AccountID(alice.account_id_hex.clone()))
+            .get_balance_for_account(&AccountID(alice.account_id_hex.clone()), false)
             .unwrap();
         assert_eq!(balance.unspent, 100 * MOB as u128);
 
@@ -549,6 +973,12 @@ mod tests {
                 None,
                 None,
                 None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
             )
             .unwrap();
         log::info!(logger, "Built transaction from Alice");
@@ -575,6 +1005,12 @@ mod tests {
                 None,
                 None,
                 None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
                 Some(false),
             )
             .unwrap();
@@ -602,6 +1038,12 @@ mod tests {
                 None,
                 None,
                 None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
                 Some(true),
             )
             .unwrap();
@@ -650,7 +1092,7 @@ mod tests {
 
         // Verify balance for Alice
         let balance = service
-            .get_balance_for_account(&AccountID(alice.account_id_hex.clone()))
+            .get_balance_for_account(&AccountID(alice.account_id_hex.clone()), false)
             .unwrap();
         assert_eq!(balance.unspent, 100 * MOB as u128);
 
@@ -685,6 +1127,12 @@ mod tests {
                 None,
                 None,
                 None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
             )
             .unwrap();
         log::info!(logger, "Built and submitted transaction from Alice");
@@ -731,13 +1179,13 @@ mod tests {
 
         // Verify balance for Alice = original balance - fee - txo_value
         let balance = service
-            .get_balance_for_account(&AccountID(alice.account_id_hex.clone()))
+            .get_balance_for_account(&AccountID(alice.account_id_hex.clone()), false)
             .unwrap();
         assert_eq!(balance.unspent, (58 * MOB - Mob::MINIMUM_FEE) as u128);
 
         // Bob's balance should be = output_txo_value
         let bob_balance = service
-            .get_balance_for_account(&AccountID(bob.account_id_hex.clone()))
+            .get_balance_for_account(&AccountID(bob.account_id_hex.clone()), false)
             .unwrap();
         assert_eq!(bob_balance.unspent, 42000000000000);
 
@@ -754,6 +1202,12 @@ mod tests {
                 None,
                 None,
                 None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
             )
             .unwrap();
 
@@ -771,13 +1225,13 @@ mod tests {
         manually_sync_account(&ledger_db, &service.wallet_db, &bob_account_id, &logger);
 
         let alice_balance = service
-            .get_balance_for_account(&AccountID(alice.account_id_hex))
+            .get_balance_for_account(&AccountID(alice.account_id_hex), false)
             .unwrap();
         assert_eq!(alice_balance.unspent, (66 * MOB - Mob::MINIMUM_FEE) as u128);
 
         // Bob's balance should be = output_txo_value
         let bob_balance = service
-            .get_balance_for_account(&AccountID(bob.account_id_hex))
+            .get_balance_for_account(&AccountID(bob.account_id_hex), false)
             .unwrap();
         assert_eq!(bob_balance.unspent, (34 * MOB - Mob::MINIMUM_FEE) as u128);
     }
@@ -824,6 +1278,12 @@ mod tests {
             None,
             None,
             None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
         ) {
             Ok(_) => {
                 panic!("Should not be able to build transaction to invalid b58 public address")
@@ -882,6 +1342,12 @@ mod tests {
             None,
             None,
             None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
         ) {
             Ok(_) => {
                 panic!("Should not be able to build transaction with too many ouputs")
@@ -912,6 +1378,12 @@ mod tests {
             None,
             None,
             None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
         ) {
             Ok(_) => {
                 panic!("Should not be able to build transaction with too many inputs")
@@ -923,6 +1395,588 @@ mod tests {
         };
     }
 
+    #[test_with_logger]
+    fn test_build_transaction_preferred_token(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let mut ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        let service = setup_wallet_service(ledger_db.clone(), logger.clone());
+
+        let alice = service
+            .create_account(
+                Some("Alice's Main Account".to_string()),
+                "".to_string(),
+                "".to_string(),
+                "".to_string(),
+            )
+            .unwrap();
+        let alice_account_key: AccountKey = mc_util_serial::decode(&alice.account_key).unwrap();
+        let alice_account_id = AccountID::from(&alice_account_key);
+        let alice_public_address = alice_account_key.subaddress(alice.main_subaddress_index as u64);
+        let alice_address = b58_encode_public_address(&alice_public_address).unwrap();
+
+        add_block_to_ledger_db(
+            &mut ledger_db,
+            &vec![alice_public_address],
+            100 * MOB,
+            &vec![KeyImage::from(rng.next_u64())],
+            &mut rng,
+        );
+        manually_sync_account(&ledger_db, &service.wallet_db, &alice_account_id, &logger);
+
+        let alice = service
+            .update_account_preferred_token_id(&alice_account_id, 1)
+            .unwrap();
+        assert_eq!(alice.preferred_token_id, 1);
+
+        // Alice only holds MOB, so a transaction in her preferred token has no
+        // inputs to spend.
+        match service.build_transaction(
+            &alice.account_id_hex,
+            &vec![(alice_address.clone(), (42 * MOB).to_string())],
+            None,
+            None,
+            Some("2560".to_string()),
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+        ) {
+            Ok(_) => panic!("Should not be able to build transaction without inputs"),
+            Err(TransactionServiceError::TransactionBuilder(
+                WalletTransactionBuilderError::WalletDb(WalletDbError::NoSpendableTxos),
+            )) => {}
+            Err(e) => panic!("Unexpected error {:?}", e),
+        };
+
+        // Giving the token explicitly overrides the preference.
+        let tx_proposal = service
+            .build_transaction(
+                &alice.account_id_hex,
+                &vec![(alice_address.clone(), (42 * MOB).to_string())],
+                None,
+                Some(Mob::ID.to_string()),
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+            )
+            .unwrap();
+        assert_eq!(tx_proposal.utxos[0].token_id, *Mob::ID);
+
+        // The fee can only be paid in a token the network reports a minimum
+        // fee for.
+        match service.build_transaction(
+            &alice.account_id_hex,
+            &vec![(alice_address.clone(), (42 * MOB).to_string())],
+            None,
+            Some(Mob::ID.to_string()),
+            None,
+            Some("1".to_string()),
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+        ) {
+            Ok(_) => panic!("Should not be able to pay the fee in a token without a minimum fee"),
+            Err(TransactionServiceError::UnknownMinimumFee(1)) => {}
+            Err(e) => panic!("Unexpected error {:?}", e),
+        };
+
+        let tx_proposal = service
+            .build_transaction(
+                &alice.account_id_hex,
+                &vec![(alice_address, (42 * MOB).to_string())],
+                None,
+                Some(Mob::ID.to_string()),
+                None,
+                Some(Mob::ID.to_string()),
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+            )
+            .unwrap();
+        assert_eq!(tx_proposal.tx.prefix.fee_token_id, *Mob::ID);
+        assert_eq!(tx_proposal.tx.prefix.fee, Mob::MINIMUM_FEE);
+    }
+
+    #[test_with_logger]
+    fn test_build_transaction_mixed_token_fee(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let mut ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        let service = setup_wallet_service(ledger_db.clone(), logger.clone());
+
+        let alice = service
+            .create_account(
+                Some("Alice's Main Account".to_string()),
+                "".to_string(),
+                "".to_string(),
+                "".to_string(),
+            )
+            .unwrap();
+        let alice_account_key: AccountKey = mc_util_serial::decode(&alice.account_key).unwrap();
+        let alice_account_id = AccountID::from(&alice_account_key);
+        let alice_public_address = alice_account_key.subaddress(alice.main_subaddress_index as u64);
+        let alice_address = b58_encode_public_address(&alice_public_address).unwrap();
+
+        add_block_to_ledger_db(
+            &mut ledger_db,
+            &vec![alice_public_address],
+            100 * MOB,
+            &vec![KeyImage::from(rng.next_u64())],
+            &mut rng,
+        );
+        manually_sync_account(&ledger_db, &service.wallet_db, &alice_account_id, &logger);
+
+        // Sending token 1 while paying the fee in MOB is a mixed transaction,
+        // which is refused before any inputs are selected while the network's
+        // block version does not support it.
+        let network_block_version = service.get_network_block_version();
+        assert!(*network_block_version < MIXED_TRANSACTIONS_BLOCK_VERSION);
+        match service.build_transaction(
+            &alice.account_id_hex,
+            &vec![(alice_address, (42 * MOB).to_string())],
+            None,
+            Some("1".to_string()),
+            None,
+            Some(Mob::ID.to_string()),
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+        ) {
+            Ok(_) => panic!("Should not be able to pay the fee in another token"),
+            Err(TransactionServiceError::TransactionBuilder(
+                WalletTransactionBuilderError::MixedTokenFee(0, 1, block_version),
+            )) => assert_eq!(block_version, *network_block_version),
+            Err(e) => panic!("Unexpected error {:?}", e),
+        };
+    }
+
+    #[test_with_logger]
+    fn test_build_memo_only_transaction(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let mut ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        let service = setup_wallet_service(ledger_db.clone(), logger.clone());
+
+        let alice = service
+            .create_account(
+                Some("Alice's Main Account".to_string()),
+                "".to_string(),
+                "".to_string(),
+                "".to_string(),
+            )
+            .unwrap();
+        let alice_account_key: AccountKey = mc_util_serial::decode(&alice.account_key).unwrap();
+        let alice_account_id = AccountID::from(&alice_account_key);
+        let alice_public_address = alice_account_key.subaddress(alice.main_subaddress_index as u64);
+
+        let bob = service
+            .create_account(
+                Some("Bob's Main Account".to_string()),
+                "".to_string(),
+                "".to_string(),
+                "".to_string(),
+            )
+            .unwrap();
+        let bob_account_key: AccountKey = mc_util_serial::decode(&bob.account_key).unwrap();
+        let bob_address = b58_encode_public_address(
+            &bob_account_key.subaddress(bob.main_subaddress_index as u64),
+        )
+        .unwrap();
+
+        add_block_to_ledger_db(
+            &mut ledger_db,
+            &vec![alice_public_address],
+            100 * MOB,
+            &vec![KeyImage::from(rng.next_u64())],
+            &mut rng,
+        );
+        manually_sync_account(&ledger_db, &service.wallet_db, &alice_account_id, &logger);
+
+        match service.build_transaction(
+            &alice.account_id_hex,
+            &vec![(bob_address.clone(), "0".to_string())],
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            Some("not a number".to_string()),
+            None,
+            None,
+            None,
+        ) {
+            Ok(_) => panic!("Should not be able to build transaction with an invalid id"),
+            Err(TransactionServiceError::U64Parse) => {}
+            Err(e) => panic!("Unexpected error {:?}", e),
+        };
+
+        // The only value moved is the fee, and the rest of the input comes
+        // back as change.
+        let tx_proposal = service
+            .build_transaction(
+                &alice.account_id_hex,
+                &vec![(bob_address, "0".to_string())],
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                Some("42".to_string()),
+                None,
+                None,
+                None,
+            )
+            .unwrap();
+        assert_eq!(tx_proposal.outlays.len(), 1);
+        assert_eq!(tx_proposal.outlays[0].value, 0);
+        assert_eq!(tx_proposal.tx.prefix.outputs.len(), 2);
+        assert!(tx_proposal.tx.prefix.fee > 0);
+        assert_eq!(
+            tx_proposal.utxos.iter().map(|utxo| utxo.value).sum::<u64>(),
+            100 * MOB
+        );
+    }
+
+    #[test_with_logger]
+    fn test_build_transaction_large_send(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let mut ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        let mut service = setup_wallet_service(ledger_db.clone(), logger.clone());
+        service.large_send_guard = LargeSendGuard::new(
+            Some(LargeSendThreshold::Value(10 * MOB)),
+            Some(LargeSendThreshold::PercentOfBalance(50)),
+        );
+
+        let alice = service
+            .create_account(
+                Some("Alice's Main Account".to_string()),
+                "".to_string(),
+                "".to_string(),
+                "".to_string(),
+            )
+            .unwrap();
+        let alice_account_key: AccountKey = mc_util_serial::decode(&alice.account_key).unwrap();
+        let alice_account_id = AccountID::from(&alice_account_key);
+        let alice_public_address = alice_account_key.subaddress(alice.main_subaddress_index as u64);
+        let alice_address = b58_encode_public_address(&alice_public_address).unwrap();
+
+        add_block_to_ledger_db(
+            &mut ledger_db,
+            &vec![alice_public_address],
+            100 * MOB,
+            &vec![KeyImage::from(rng.next_u64())],
+            &mut rng,
+        );
+        manually_sync_account(&ledger_db, &service.wallet_db, &alice_account_id, &logger);
+
+        // Sends above the warn threshold, but below the deny threshold, go
+        // through.
+        service
+            .build_transaction(
+                &alice.account_id_hex,
+                &vec![(alice_address.clone(), (42 * MOB).to_string())],
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+            )
+            .unwrap();
+
+        // Sending more than half the balance must be confirmed.
+        match service.build_transaction(
+            &alice.account_id_hex,
+            &vec![(alice_address.clone(), (60 * MOB).to_string())],
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+        ) {
+            Ok(_) => panic!("Should not be able to send a large amount without confirming"),
+            Err(TransactionServiceError::LargeSendNotConfirmed(value)) => {
+                assert_eq!(value, 60 * MOB as u128)
+            }
+            Err(e) => panic!("Unexpected error {:?}", e),
+        };
+
+        service
+            .build_transaction(
+                &alice.account_id_hex,
+                &vec![(alice_address, (60 * MOB).to_string())],
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                Some(true),
+                None,
+                None,
+                None,
+                None,
+            )
+            .unwrap();
+    }
+
+    /// Signs transactions with the given function, standing in for a hardware
+    /// wallet.
+    struct MockTransactionSigner<F>(F);
+
+    impl<F> TransactionSigner for MockTransactionSigner<F>
+    where
+        F: Fn(UnsignedTx, FullServiceFogResolver) -> Result<TxProposal, String> + Send + Sync,
+    {
+        fn sign_transaction(
+            &self,
+            _account_id_hex: &str,
+            unsigned_tx: UnsignedTx,
+            fog_resolver: FullServiceFogResolver,
+        ) -> Result<TxProposal, String> {
+            (self.0)(unsigned_tx, fog_resolver)
+        }
+    }
+
+    fn mock_signer<F>(sign: F) -> Arc<dyn TransactionSigner>
+    where
+        F: Fn(UnsignedTx, FullServiceFogResolver) -> Result<TxProposal, String>
+            + Send
+            + Sync
+            + 'static,
+    {
+        Arc::new(MockTransactionSigner(sign))
+    }
+
+    /// Import a view only account for a new account key, funded with 42 MOB,
+    /// returning the account key and the view only account's id.
+    fn setup_view_only_account(
+        service: &WalletService<MockBlockchainConnection<LedgerDB>, MockFogPubkeyResolver>,
+        ledger_db: &mut LedgerDB,
+        hardware_backed: bool,
+        rng: &mut StdRng,
+        logger: &Logger,
+    ) -> (AccountKey, String) {
+        let account_key = AccountKey::random(rng);
+        let account_id = AccountID::from(&account_key).to_string();
+        let main_public_address = account_key.default_subaddress();
+
+        service
+            .import_view_only_account(
+                &account_id,
+                account_key.view_private_key(),
+                DEFAULT_SUBADDRESS_INDEX,
+                CHANGE_SUBADDRESS_INDEX,
+                2,
+                "hardware wallet",
+                vec![(
+                    b58_encode_public_address(&main_public_address).unwrap(),
+                    DEFAULT_SUBADDRESS_INDEX,
+                    "Main".to_string(),
+                    *main_public_address.spend_public_key(),
+                )],
+            )
+            .unwrap();
+        if hardware_backed {
+            service
+                .update_view_only_account_hardware_backed(&account_id, true)
+                .unwrap();
+        }
+
+        add_block_to_ledger_db(
+            ledger_db,
+            &vec![main_public_address],
+            42 * MOB,
+            &vec![KeyImage::from(rng.next_u64())],
+            rng,
+        );
+        manually_sync_view_only_account(ledger_db, &service.wallet_db, &account_id, logger);
+
+        (account_key, account_id)
+    }
+
+    fn recipient(rng: &mut StdRng) -> Vec<(String, String)> {
+        let address = AccountKey::random(rng).default_subaddress();
+        vec![(
+            b58_encode_public_address(&address).unwrap(),
+            (10 * MOB).to_string(),
+        )]
+    }
+
+    #[test_with_logger]
+    fn test_build_sign_and_submit_transaction(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+        let mut ledger_db = get_test_ledger(5, &vec![], 12, &mut rng);
+        let mut service = setup_wallet_service(ledger_db.clone(), logger.clone());
+        let (account_key, account_id) =
+            setup_view_only_account(&service, &mut ledger_db, true, &mut rng, &logger);
+
+        service.transaction_signer = Some(mock_signer(move |unsigned_tx, fog_resolver| {
+            unsigned_tx
+                .sign(&account_key, fog_resolver)
+                .map_err(|err| err.to_string())
+        }));
+
+        let recipients = recipient(&mut rng);
+        let tx_proposal = service
+            .build_sign_and_submit_transaction(&account_id, &recipients, None, None)
+            .unwrap();
+        assert_eq!(tx_proposal.outlays.len(), 1);
+        assert_eq!(tx_proposal.outlays[0].value, 10 * MOB);
+        assert_eq!(tx_proposal.tx.prefix.fee, Mob::MINIMUM_FEE);
+    }
+
+    #[test_with_logger]
+    fn test_build_sign_and_submit_transaction_not_hardware_backed(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+        let mut ledger_db = get_test_ledger(5, &vec![], 12, &mut rng);
+        let mut service = setup_wallet_service(ledger_db.clone(), logger.clone());
+        let (_account_key, account_id) =
+            setup_view_only_account(&service, &mut ledger_db, false, &mut rng, &logger);
+
+        let signed = Arc::new(AtomicBool::new(false));
+        let signed_clone = signed.clone();
+        service.transaction_signer = Some(mock_signer(move |_unsigned_tx, _fog_resolver| {
+            signed_clone.store(true, Ordering::SeqCst);
+            Err("should not be asked to sign".to_string())
+        }));
+
+        let recipients = recipient(&mut rng);
+        match service.build_sign_and_submit_transaction(&account_id, &recipients, None, None) {
+            Err(TransactionServiceError::NotHardwareBacked(id)) => assert_eq!(id, account_id),
+            other => panic!("Unexpected result {:?}", other.map(|_| ())),
+        }
+        assert!(!signed.load(Ordering::SeqCst));
+    }
+
+    #[test_with_logger]
+    fn test_build_sign_and_submit_transaction_spending_disabled(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+        let mut ledger_db = get_test_ledger(5, &vec![], 12, &mut rng);
+        let mut service = setup_wallet_service_with(
+            ledger_db.clone(),
+            WalletServiceOptions {
+                spending_lock: SpendingLock::new(true, None),
+                ..Default::default()
+            },
+            logger.clone(),
+        );
+        let (_account_key, account_id) =
+            setup_view_only_account(&service, &mut ledger_db, true, &mut rng, &logger);
+
+        let signed = Arc::new(AtomicBool::new(false));
+        let signed_clone = signed.clone();
+        service.transaction_signer = Some(mock_signer(move |_unsigned_tx, _fog_resolver| {
+            signed_clone.store(true, Ordering::SeqCst);
+            Err("should not be asked to sign".to_string())
+        }));
+
+        let recipients = recipient(&mut rng);
+        assert!(matches!(
+            service.build_sign_and_submit_transaction(&account_id, &recipients, None, None),
+            Err(TransactionServiceError::SpendingDisabled)
+        ));
+        assert!(!signed.load(Ordering::SeqCst));
+    }
+
+    #[test_with_logger]
+    fn test_build_sign_and_submit_transaction_signer_error(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+        let mut ledger_db = get_test_ledger(5, &vec![], 12, &mut rng);
+        let mut service = setup_wallet_service(ledger_db.clone(), logger.clone());
+        let (_account_key, account_id) =
+            setup_view_only_account(&service, &mut ledger_db, true, &mut rng, &logger);
+
+        service.transaction_signer = Some(mock_signer(|_unsigned_tx, _fog_resolver| {
+            Err("rejected on the device".to_string())
+        }));
+
+        let recipients = recipient(&mut rng);
+        match service.build_sign_and_submit_transaction(&account_id, &recipients, None, None) {
+            Err(TransactionServiceError::Signer(err)) => assert_eq!(err, "rejected on the device"),
+            other => panic!("Unexpected result {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test_with_logger]
+    fn test_build_sign_and_submit_transaction_mismatched_signature(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+        let mut ledger_db = get_test_ledger(5, &vec![], 12, &mut rng);
+        let mut service = setup_wallet_service(ledger_db.clone(), logger.clone());
+        let (account_key, account_id) =
+            setup_view_only_account(&service, &mut ledger_db, true, &mut rng, &logger);
+
+        // The signer pays the recipient less than it was asked to, keeping the
+        // difference as change.
+        service.transaction_signer = Some(mock_signer(move |mut unsigned_tx, fog_resolver| {
+            unsigned_tx.outlays[0].1 -= MOB;
+            unsigned_tx
+                .sign(&account_key, fog_resolver)
+                .map_err(|err| err.to_string())
+        }));
+
+        let recipients = recipient(&mut rng);
+        assert!(matches!(
+            service.build_sign_and_submit_transaction(&account_id, &recipients, None, None),
+            Err(TransactionServiceError::TransactionBuilder(
+                WalletTransactionBuilderError::InvalidSignedTransaction(_)
+            ))
+        ));
+
+        // Nothing was submitted, so no transaction was logged for the account.
+        assert!(service
+            .list_transaction_logs(&AccountID(account_id), None, None, None, None)
+            .unwrap()
+            .is_empty());
+    }
+
     // FIXME: Test with 0 change transactions
     // FIXME: Test with balance > u64::max
     // FIXME: sending a transaction with value > u64::max
@@ -35,12 +35,12 @@ use mc_mobilecoind::{
     UnspentTxOut,
 };
 use mc_transaction_core::{
-    constants::RING_SIZE,
+    constants::{MAX_INPUTS, MAX_OUTPUTS, RING_SIZE},
     onetime_keys::recover_onetime_private_key,
     ring_signature::KeyImage,
     tokens::Mob,
     tx::{TxIn, TxOut, TxOutMembershipProof},
-    Amount, BlockVersion, Token,
+    Amount, BlockVersion, Token, TokenId,
 };
 use mc_transaction_std::{
     ChangeDestination, InputCredentials, RTHMemoBuilder, SenderMemoCredential, TransactionBuilder,
@@ -48,6 +48,7 @@ use mc_transaction_std::{
 use mc_util_uri::FogUri;
 
 use rand::Rng;
+use serde::{Deserialize, Serialize};
 use std::{convert::TryFrom, str::FromStr, sync::Arc};
 
 /// Default number of blocks used for calculating transaction tombstone block
@@ -55,6 +56,296 @@ use std::{convert::TryFrom, str::FromStr, sync::Arc};
 // TODO support for making this configurable
 pub const DEFAULT_NEW_TX_BLOCK_ATTEMPTS: u64 = 10;
 
+/// The first block version whose transactions may mix tokens, and so pay the
+/// fee in a token other than the outlays'.
+pub const MIXED_TRANSACTIONS_BLOCK_VERSION: u32 = 3;
+
+/// Check that a transaction at the given block version can pay its fee in
+/// fee_token_id when its outlays are in token_id.
+pub fn check_fee_token_id(
+    block_version: BlockVersion,
+    token_id: TokenId,
+    fee_token_id: TokenId,
+) -> Result<(), WalletTransactionBuilderError> {
+    if fee_token_id != token_id && *block_version < MIXED_TRANSACTIONS_BLOCK_VERSION {
+        return Err(WalletTransactionBuilderError::MixedTokenFee(
+            *fee_token_id,
+            *token_id,
+            *block_version,
+        ));
+    }
+    Ok(())
+}
+
+/// How inputs are chosen from an account's unspent txos when they are not
+/// given explicitly.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CoinSelectionStrategy {
+    /// Take the largest txos first.
+    LargestFirst,
+
+    /// Take the smallest txos first, dropping the smallest from the selection
+    /// when the input limit is reached. This sweeps up dust opportunistically,
+    /// and is the default.
+    SmallestFirst,
+
+    /// Select as for smallest-first, then fill the remaining input slots with
+    /// the smallest txos left, merging as much dust as one transaction can.
+    ConsolidateDust,
+
+    /// Use as few inputs as possible, preferring the smallest txo which can
+    /// stand in for the last one, to keep the change small.
+    MinimizeInputs,
+}
+
+impl Default for CoinSelectionStrategy {
+    fn default() -> Self {
+        CoinSelectionStrategy::SmallestFirst
+    }
+}
+
+impl FromStr for CoinSelectionStrategy {
+    type Err = WalletTransactionBuilderError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "largest-first" => Ok(CoinSelectionStrategy::LargestFirst),
+            "smallest-first" => Ok(CoinSelectionStrategy::SmallestFirst),
+            "consolidate-dust" => Ok(CoinSelectionStrategy::ConsolidateDust),
+            "minimize-inputs" => Ok(CoinSelectionStrategy::MinimizeInputs),
+            _ => Err(WalletTransactionBuilderError::InvalidArgument(format!(
+                "Unknown coin selection strategy {}, expected one of largest-first, \
+                 smallest-first, consolidate-dust or minimize-inputs",
+                s
+            ))),
+        }
+    }
+}
+
+/// A txo which can be selected as an input, by its value.
+pub trait SelectableTxo {
+    fn selection_value(&self) -> u64;
+}
+
+impl SelectableTxo for Txo {
+    fn selection_value(&self) -> u64 {
+        self.value as u64
+    }
+}
+
+impl SelectableTxo for ViewOnlyTxo {
+    fn selection_value(&self) -> u64 {
+        self.value as u64
+    }
+}
+
+impl CoinSelectionStrategy {
+    /// Select txos summing to at least the target value, from txos sorted by
+    /// decreasing value, using at most MAX_INPUTS of them.
+    ///
+    /// Returns None if no such selection is found.
+    pub fn select<T: SelectableTxo>(&self, mut txos: Vec<T>, target_value: u64) -> Option<Vec<T>> {
+        let total = |selected: &[T]| -> u128 {
+            selected
+                .iter()
+                .map(|txo| txo.selection_value() as u128)
+                .sum()
+        };
+        let target_value = target_value as u128;
+
+        let selected = match self {
+            CoinSelectionStrategy::LargestFirst => {
+                let mut selected = Vec::new();
+                for txo in txos {
+                    if total(&selected) >= target_value {
+                        break;
+                    }
+                    selected.push(txo);
+                }
+                selected
+            }
+            CoinSelectionStrategy::SmallestFirst => select_smallest_first(&mut txos, target_value)?,
+            CoinSelectionStrategy::ConsolidateDust => {
+                let mut selected = select_smallest_first(&mut txos, target_value)?;
+                while selected.len() < MAX_INPUTS as usize {
+                    match txos.pop() {
+                        Some(txo) => selected.push(txo),
+                        None => break,
+                    }
+                }
+                selected
+            }
+            CoinSelectionStrategy::MinimizeInputs => {
+                // The largest txos reach the target in the fewest inputs.
+                let num_inputs = txos
+                    .iter()
+                    .scan(0u128, |sum, txo| {
+                        let reached = *sum >= target_value;
+                        *sum += txo.selection_value() as u128;
+                        Some(reached)
+                    })
+                    .position(|reached| reached)
+                    .unwrap_or_else(|| txos.len());
+                if num_inputs == 0 {
+                    return None;
+                }
+                let mut rest = txos.split_off(num_inputs - 1);
+                let remaining = target_value.saturating_sub(total(&txos));
+                // The smallest of the rest which still covers what is left.
+                let last = rest
+                    .iter()
+                    .rposition(|txo| txo.selection_value() as u128 >= remaining)?;
+                txos.push(rest.swap_remove(last));
+                txos
+            }
+        };
+
+        if selected.is_empty()
+            || selected.len() > MAX_INPUTS as usize
+            || total(&selected) < target_value
+        {
+            return None;
+        }
+        Some(selected)
+    }
+}
+
+/// Take the smallest txos until the target value is reached, moving the
+/// window of MAX_INPUTS txos up from the back of the vector, and leaving the
+/// txos which were not selected in it.
+fn select_smallest_first<T: SelectableTxo>(
+    txos: &mut Vec<T>,
+    target_value: u128,
+) -> Option<Vec<T>> {
+    let mut selected: Vec<T> = Vec::new();
+    let mut dropped: Vec<T> = Vec::new();
+    let mut total: u128 = 0;
+    while total < target_value {
+        // Grab the next (smallest) utxo, in order to opportunistically sweep up dust
+        let next_utxo = txos.pop()?;
+        total += next_utxo.selection_value() as u128;
+        selected.push(next_utxo);
+
+        // Cap at maximum allowed inputs.
+        if selected.len() > MAX_INPUTS as usize {
+            // Remove the lowest utxo.
+            let removed = selected.remove(0);
+            total -= removed.selection_value() as u128;
+            dropped.push(removed);
+        }
+    }
+    // Keep the txos left over sorted by decreasing value.
+    txos.extend(dropped.into_iter().rev());
+    Some(selected)
+}
+
+/// How the change of a transaction is split into outputs, all sent to the
+/// account's change subaddress.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum ChangeOutputs {
+    /// Split the change evenly over this many outputs, the last of which takes
+    /// what doesn't divide evenly. One output is the default.
+    Split(usize),
+
+    /// Change outputs of these values, and one more for the rest of the
+    /// change, which may be zero. The inputs are selected to cover them.
+    Fixed(Vec<u64>),
+}
+
+impl Default for ChangeOutputs {
+    fn default() -> Self {
+        ChangeOutputs::Split(1)
+    }
+}
+
+impl ChangeOutputs {
+    /// The change outputs asked for by their number, or by their values, of
+    /// which at most one may be given.
+    pub fn from_params(
+        num_change_outputs: Option<String>,
+        change_values: Option<Vec<String>>,
+    ) -> Result<Option<Self>, WalletTransactionBuilderError> {
+        let parse = |value: &str| {
+            value.parse::<u64>().map_err(|_| {
+                WalletTransactionBuilderError::InvalidArgument(format!(
+                    "Invalid change output {}",
+                    value
+                ))
+            })
+        };
+        let change_outputs = match (num_change_outputs, change_values) {
+            (Some(_), Some(_)) => {
+                return Err(WalletTransactionBuilderError::InvalidArgument(
+                    "Only one of num_change_outputs and change_values may be given".to_string(),
+                ))
+            }
+            (Some(num), None) => Some(ChangeOutputs::Split(parse(&num)? as usize)),
+            (None, Some(values)) => Some(ChangeOutputs::Fixed(
+                values
+                    .iter()
+                    .map(|value| parse(value))
+                    .collect::<Result<_, _>>()?,
+            )),
+            (None, None) => None,
+        };
+        Ok(change_outputs)
+    }
+
+    /// The number of outputs the change is sent in.
+    pub fn num_outputs(&self) -> usize {
+        match self {
+            ChangeOutputs::Split(num_outputs) => *num_outputs,
+            ChangeOutputs::Fixed(values) => values.len() + 1,
+        }
+    }
+
+    /// The value the inputs must cover for the change, beyond the outlays and
+    /// the fee.
+    fn fixed_value(&self) -> u128 {
+        match self {
+            ChangeOutputs::Split(_) => 0,
+            ChangeOutputs::Fixed(values) => values.iter().map(|value| *value as u128).sum(),
+        }
+    }
+
+    /// The values of the change outputs, for the given change, or None if it
+    /// does not cover the fixed values.
+    pub(crate) fn values(&self, change: u64) -> Option<Vec<u64>> {
+        match self {
+            ChangeOutputs::Split(num_outputs) => {
+                let num_outputs = *num_outputs as u64;
+                let share = change / num_outputs;
+                let mut values = vec![share; num_outputs as usize - 1];
+                values.push(change - share * (num_outputs - 1));
+                Some(values)
+            }
+            ChangeOutputs::Fixed(fixed_values) => {
+                let rest = change.checked_sub(fixed_values.iter().sum())?;
+                let mut values = fixed_values.clone();
+                values.push(rest);
+                Some(values)
+            }
+        }
+    }
+
+    fn validate(&self) -> Result<(), WalletTransactionBuilderError> {
+        match self {
+            ChangeOutputs::Split(0) => Err(WalletTransactionBuilderError::InvalidArgument(
+                "At least one change output is needed".to_string(),
+            )),
+            ChangeOutputs::Fixed(values) if values.contains(&0) => {
+                Err(WalletTransactionBuilderError::InvalidArgument(
+                    "Change output values must be above zero".to_string(),
+                ))
+            }
+            ChangeOutputs::Fixed(_) if self.fixed_value() > u64::MAX as u128 => {
+                Err(WalletTransactionBuilderError::OutboundValueTooLarge)
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
 /// A builder of transactions constructed from this wallet.
 pub struct WalletTransactionBuilder<FPR: FogPubkeyResolver + 'static> {
     /// Account ID (hex-encoded) from which to construct a transaction.
@@ -66,6 +357,9 @@ pub struct WalletTransactionBuilder<FPR: FogPubkeyResolver + 'static> {
     /// Optional inputs specified to use to construct the transaction.
     inputs: Vec<Txo>,
 
+    /// The inputs of an unsigned transaction, from a view only account.
+    view_only_inputs: Vec<ViewOnlyTxo>,
+
     /// Vector of (PublicAddress, Amounts) for the recipients of this
     /// transaction.
     outlays: Vec<(PublicAddress, u64)>,
@@ -73,12 +367,27 @@ pub struct WalletTransactionBuilder<FPR: FogPubkeyResolver + 'static> {
     /// The block after which this transaction is invalid.
     tombstone: u64,
 
+    /// The token the outlays are sent in, and the inputs are selected from.
+    token_id: TokenId,
+
     /// The fee for the transaction.
     fee: Option<u64>,
 
+    /// The token the fee is paid in.
+    fee_token_id: TokenId,
+
     /// The block version for the transaction
     block_version: Option<BlockVersion>,
 
+    /// The payment request id written to the memo of each outlay, if any.
+    payment_request_id: Option<u64>,
+
+    /// How inputs are selected, if they are not set explicitly.
+    coin_selection_strategy: CoinSelectionStrategy,
+
+    /// How the change is split into outputs.
+    change_outputs: ChangeOutputs,
+
     /// Fog resolver maker, used when constructing outputs to fog recipients.
     /// This is abstracted because in tests, we don't want to form grpc
     /// connections to fog.
@@ -99,10 +408,16 @@ impl<FPR: FogPubkeyResolver + 'static> WalletTransactionBuilder<FPR> {
             account_id_hex,
             ledger_db,
             inputs: vec![],
+            view_only_inputs: vec![],
             outlays: vec![],
             tombstone: 0,
+            token_id: Mob::ID,
             fee: None,
+            fee_token_id: Mob::ID,
             block_version: None,
+            payment_request_id: None,
+            coin_selection_strategy: CoinSelectionStrategy::default(),
+            change_outputs: ChangeOutputs::default(),
             fog_resolver_factory,
             logger,
         }
@@ -124,6 +439,13 @@ impl<FPR: FogPubkeyResolver + 'static> WalletTransactionBuilder<FPR> {
 
         let txos = Txo::select_by_id(input_txo_ids, pending_tombstone_block_index, conn)?;
 
+        // Frozen txos are held back even when they are asked for by id.
+        if let Some(frozen) = txos.iter().find(|txo| txo.frozen) {
+            return Err(WalletTransactionBuilderError::TxoFrozen(
+                frozen.txo_id_hex.clone(),
+            ));
+        }
+
         let unspent: Vec<Txo> = txos
             .iter()
             .filter(|txo| {
@@ -148,7 +470,9 @@ impl<FPR: FogPubkeyResolver + 'static> WalletTransactionBuilder<FPR> {
         max_spendable_value: Option<u64>,
         update_to_pending: bool,
     ) -> Result<(), WalletTransactionBuilderError> {
-        let outlay_value_sum = self.outlays.iter().map(|(_r, v)| *v as u128).sum::<u128>();
+        // Fixed change outputs are paid for like outlays.
+        let outlay_value_sum = self.outlays.iter().map(|(_r, v)| *v as u128).sum::<u128>()
+            + self.change_outputs.fixed_value();
 
         let fee = self.fee.unwrap_or(Mob::MINIMUM_FEE);
         if outlay_value_sum > u64::MAX as u128 || outlay_value_sum > u64::MAX as u128 - fee as u128
@@ -174,19 +498,55 @@ impl<FPR: FogPubkeyResolver + 'static> WalletTransactionBuilder<FPR> {
             total_value,
             max_spendable_value,
             pending_tombstone_block_index,
-            Some(0),
+            Some(*self.token_id),
+            self.coin_selection_strategy,
             conn,
         )?;
 
         Ok(())
     }
 
-    /// Selects View Only Txos from the account.
-    fn select_view_only_txos(
-        &self,
+    /// Sets the inputs of an unsigned transaction to the view only txos with
+    /// the given ids, which must belong to the view only account. Only unspent
+    /// txos are included.
+    pub fn set_view_only_txos(
+        &mut self,
+        conn: &Conn,
+        input_txo_ids: &[String],
+    ) -> Result<(), WalletTransactionBuilderError> {
+        let mut unspent = Vec::new();
+        for txo_id in input_txo_ids {
+            let txo = ViewOnlyTxo::get(txo_id, conn)?;
+            if txo.view_only_account_id_hex != self.account_id_hex {
+                return Err(WalletTransactionBuilderError::InvalidArgument(format!(
+                    "Txo {} does not belong to account {}",
+                    txo_id, self.account_id_hex
+                )));
+            }
+            if txo.pending_tombstone_block_index == None && txo.spent_block_index == None {
+                unspent.push(txo);
+            }
+        }
+
+        if unspent.iter().map(|t| t.value as u128).sum::<u128>() > u64::MAX as u128 {
+            return Err(WalletTransactionBuilderError::OutboundValueTooLarge);
+        }
+
+        self.view_only_inputs = unspent;
+
+        Ok(())
+    }
+
+    /// Selects View Only Txos from the account, as the inputs of an unsigned
+    /// transaction.
+    pub fn select_view_only_txos(
+        &mut self,
         conn: &Conn,
-    ) -> Result<Vec<ViewOnlyTxo>, WalletTransactionBuilderError> {
-        let outlay_value_sum = self.outlays.iter().map(|(_r, v)| *v as u128).sum::<u128>();
+        max_spendable_value: Option<u64>,
+    ) -> Result<(), WalletTransactionBuilderError> {
+        // Fixed change outputs are paid for like outlays.
+        let outlay_value_sum = self.outlays.iter().map(|(_r, v)| *v as u128).sum::<u128>()
+            + self.change_outputs.fixed_value();
 
         let fee = self.fee.unwrap_or(Mob::MINIMUM_FEE);
         if outlay_value_sum > u64::MAX as u128 || outlay_value_sum > u64::MAX as u128 - fee as u128
@@ -201,12 +561,16 @@ impl<FPR: FogPubkeyResolver + 'static> WalletTransactionBuilder<FPR> {
         );
         let total_value = outlay_value_sum as u64 + fee;
 
-        Ok(ViewOnlyTxo::select_unspent_view_only_txos_for_value(
+        self.view_only_inputs = ViewOnlyTxo::select_unspent_view_only_txos_for_value(
             &self.account_id_hex,
             total_value,
-            Some(0),
+            max_spendable_value,
+            Some(*self.token_id),
+            self.coin_selection_strategy,
             conn,
-        )?)
+        )?;
+
+        Ok(())
     }
 
     pub fn add_recipient(
@@ -234,10 +598,59 @@ impl<FPR: FogPubkeyResolver + 'static> WalletTransactionBuilder<FPR> {
         Ok(())
     }
 
+    /// Sets the token the outlays are sent in, which is also the token the fee
+    /// is paid in.
+    pub fn set_token_id(&mut self, token_id: TokenId) {
+        self.token_id = token_id;
+        self.fee_token_id = token_id;
+    }
+
+    /// Sets the token the fee is paid in.
+    ///
+    /// Paying the fee in a token other than the outlays' needs a mixed token
+    /// transaction, so it is only allowed from
+    /// MIXED_TRANSACTIONS_BLOCK_VERSION, and is checked against the block
+    /// version set with set_block_version, which should be set first.
+    pub fn set_fee_token_id(
+        &mut self,
+        fee_token_id: TokenId,
+    ) -> Result<(), WalletTransactionBuilderError> {
+        check_fee_token_id(
+            self.block_version.unwrap_or(BlockVersion::MAX),
+            self.token_id,
+            fee_token_id,
+        )?;
+        self.fee_token_id = fee_token_id;
+        Ok(())
+    }
+
     pub fn set_block_version(&mut self, block_version: BlockVersion) {
         self.block_version = Some(block_version);
     }
 
+    /// Sets the payment request id sent to the recipients in their memos, so
+    /// that the transaction can signal which request it answers. This is what
+    /// a zero-value outlay carries.
+    pub fn set_payment_request_id(&mut self, payment_request_id: u64) {
+        self.payment_request_id = Some(payment_request_id);
+    }
+
+    /// Sets how inputs are chosen by select_txos.
+    pub fn set_coin_selection_strategy(&mut self, coin_selection_strategy: CoinSelectionStrategy) {
+        self.coin_selection_strategy = coin_selection_strategy;
+    }
+
+    /// Sets how the change is split into outputs. Fixed change values must be
+    /// set before selecting txos, so that the selection covers them.
+    pub fn set_change_outputs(
+        &mut self,
+        change_outputs: ChangeOutputs,
+    ) -> Result<(), WalletTransactionBuilderError> {
+        change_outputs.validate()?;
+        self.change_outputs = change_outputs;
+        Ok(())
+    }
+
     pub fn set_tombstone(&mut self, tombstone: u64) -> Result<(), WalletTransactionBuilderError> {
         let tombstone_block = if tombstone > 0 {
             tombstone
@@ -285,16 +698,54 @@ impl<FPR: FogPubkeyResolver + 'static> WalletTransactionBuilder<FPR> {
         Ok(FullServiceFogResolver(fully_validated_fog_pubkeys))
     }
 
-    pub fn build_unsigned(&self, conn: &Conn) -> Result<UnsignedTx, WalletTransactionBuilderError> {
+    pub fn build_unsigned(&self) -> Result<UnsignedTx, WalletTransactionBuilderError> {
+        if self.view_only_inputs.is_empty() {
+            return Err(WalletTransactionBuilderError::NoInputs);
+        }
+
         if self.tombstone == 0 {
             return Err(WalletTransactionBuilderError::TombstoneNotSet);
         }
 
-        // select inputs here
-        let view_only_inputs = self.select_view_only_txos(conn)?;
+        if self.outlays.is_empty() {
+            return Err(WalletTransactionBuilderError::NoRecipient);
+        }
+
+        if self.outlays.len() + self.change_outputs.num_outputs() > MAX_OUTPUTS as usize {
+            return Err(WalletTransactionBuilderError::InvalidArgument(format!(
+                "{} outlays and {} change outputs are more than the maximum of {} outputs",
+                self.outlays.len(),
+                self.change_outputs.num_outputs(),
+                MAX_OUTPUTS
+            )));
+        }
+
+        let block_version = self.block_version.unwrap_or(BlockVersion::MAX);
+        check_fee_token_id(block_version, self.token_id, self.fee_token_id)?;
+
+        // The signer can't check the inputs cover the outlays until it has
+        // decoded their values, so check here while it is cheap to fix.
+        let fee = self.fee.unwrap_or(Mob::MINIMUM_FEE);
+        let input_value = self
+            .view_only_inputs
+            .iter()
+            .map(|utxo| utxo.value as u64 as u128)
+            .sum::<u128>();
+        let required_value = self.outlays.iter().map(|(_r, v)| *v as u128).sum::<u128>()
+            + self.change_outputs.fixed_value()
+            + fee as u128;
+        if required_value > input_value {
+            return Err(WalletTransactionBuilderError::InsufficientInputFunds(
+                format!(
+                    "Total value required to send transaction {:?}, but only {:?} in inputs",
+                    required_value, input_value
+                ),
+            ));
+        }
 
         // Get membership proofs for our inputs
-        let indexes = view_only_inputs
+        let indexes = self
+            .view_only_inputs
             .iter()
             .map(|utxo| {
                 let txo: TxOut = mc_util_serial::decode(&utxo.txo)?;
@@ -303,7 +754,9 @@ impl<FPR: FogPubkeyResolver + 'static> WalletTransactionBuilder<FPR> {
             .collect::<Result<Vec<u64>, mc_ledger_db::Error>>()?;
         let proofs = self.ledger_db.get_tx_out_proof_of_memberships(&indexes)?;
 
-        let inputs_and_proofs: Vec<(ViewOnlyTxo, TxOutMembershipProof)> = view_only_inputs
+        let inputs_and_proofs: Vec<(ViewOnlyTxo, TxOutMembershipProof)> = self
+            .view_only_inputs
+            .clone()
             .into_iter()
             .zip(proofs.into_iter())
             .collect();
@@ -324,10 +777,6 @@ impl<FPR: FogPubkeyResolver + 'static> WalletTransactionBuilder<FPR> {
             return Err(WalletTransactionBuilderError::RingSizeMismatch);
         }
 
-        if self.outlays.is_empty() {
-            return Err(WalletTransactionBuilderError::NoRecipient);
-        }
-
         // Unzip each vec of tuples into a tuple of vecs.
         let mut rings_and_proofs: Vec<(Vec<TxOut>, Vec<TxOutMembershipProof>)> = rings
             .into_iter()
@@ -375,6 +824,10 @@ impl<FPR: FogPubkeyResolver + 'static> WalletTransactionBuilder<FPR> {
                 return Err(WalletTransactionBuilderError::RingSizeMismatch);
             }
 
+            let subaddress_index = utxo.subaddress_index.ok_or_else(|| {
+                WalletTransactionBuilderError::NullSubaddress(utxo.txo_id_hex.to_string())
+            })?;
+
             let tx_in = TxIn {
                 ring,
                 proofs: membership_proofs,
@@ -383,7 +836,7 @@ impl<FPR: FogPubkeyResolver + 'static> WalletTransactionBuilder<FPR> {
             inputs_and_real_indices_and_subaddress_indices.push((
                 tx_in,
                 real_index as u64,
-                utxo.subaddress_index.unwrap() as u64,
+                subaddress_index as u64,
             ));
         }
 
@@ -396,9 +849,13 @@ impl<FPR: FogPubkeyResolver + 'static> WalletTransactionBuilder<FPR> {
         Ok(UnsignedTx {
             inputs_and_real_indices_and_subaddress_indices,
             outlays: outlays_string,
-            fee: self.fee.unwrap_or(Mob::MINIMUM_FEE),
+            fee,
             tombstone_block_index: self.tombstone,
-            block_version: self.block_version.unwrap_or(BlockVersion::MAX),
+            block_version,
+            token_id: *self.token_id,
+            fee_token_id: Some(*self.fee_token_id),
+            payment_request_id: self.payment_request_id,
+            change_outputs: self.change_outputs.clone(),
         })
     }
 
@@ -433,8 +890,12 @@ impl<FPR: FogPubkeyResolver + 'static> WalletTransactionBuilder<FPR> {
         let mut memo_builder = RTHMemoBuilder::default();
         memo_builder.set_sender_credential(SenderMemoCredential::from(&from_account_key));
         memo_builder.enable_destination_memo();
+        if let Some(payment_request_id) = self.payment_request_id {
+            memo_builder.set_payment_request_id(payment_request_id);
+        }
         let block_version = self.block_version.unwrap_or(BlockVersion::MAX);
-        let fee = Amount::new(self.fee.unwrap_or(Mob::MINIMUM_FEE), Mob::ID);
+        check_fee_token_id(block_version, self.token_id, self.fee_token_id)?;
+        let fee = Amount::new(self.fee.unwrap_or(Mob::MINIMUM_FEE), self.fee_token_id);
         let mut transaction_builder =
             TransactionBuilder::new(block_version, fee, fog_resolver, memo_builder)?;
 
@@ -476,6 +937,15 @@ impl<FPR: FogPubkeyResolver + 'static> WalletTransactionBuilder<FPR> {
             return Err(WalletTransactionBuilderError::NoRecipient);
         }
 
+        if self.outlays.len() + self.change_outputs.num_outputs() > MAX_OUTPUTS as usize {
+            return Err(WalletTransactionBuilderError::InvalidArgument(format!(
+                "{} outlays and {} change outputs are more than the maximum of {} outputs",
+                self.outlays.len(),
+                self.change_outputs.num_outputs(),
+                MAX_OUTPUTS
+            )));
+        }
+
         // Unzip each vec of tuples into a tuple of vecs.
         let mut rings_and_proofs: Vec<(Vec<TxOut>, Vec<TxOutMembershipProof>)> = rings
             .into_iter()
@@ -588,9 +1058,23 @@ impl<FPR: FogPubkeyResolver + 'static> WalletTransactionBuilder<FPR> {
         }
 
         let change = input_value as u64 - total_value - transaction_builder.get_fee().value;
-
+        let mut change_values = self.change_outputs.values(change).ok_or_else(|| {
+            WalletTransactionBuilderError::InsufficientInputFunds(format!(
+                "Change of {:?} does not cover the fixed change outputs",
+                change
+            ))
+        })?;
+
+        // The memo builder writes the destination memo to a single change
+        // output, so any others are added first, as outputs to the change
+        // subaddress.
+        let last_change = change_values.pop().expect("at least one change output");
+        let change_address = from_account_key.subaddress(account.change_subaddress_index as u64);
+        for change_value in change_values {
+            transaction_builder.add_output(change_value, &change_address, &mut rng)?;
+        }
         let change_destination = ChangeDestination::from(&from_account_key);
-        transaction_builder.add_change_output(change, &change_destination, &mut rng)?;
+        transaction_builder.add_change_output(last_change, &change_destination, &mut rng)?;
 
         // Set tombstone block.
         transaction_builder.set_tombstone_block(self.tombstone);
@@ -646,7 +1130,7 @@ impl<FPR: FogPubkeyResolver + 'static> WalletTransactionBuilder<FPR> {
                     value: utxo.value as u64,
                     attempted_spend_height: 0, // NOTE: these are null because not tracked here
                     attempted_spend_tombstone: 0,
-                    token_id: *Mob::ID,
+                    token_id: *self.token_id,
                 }
             })
             .collect();
@@ -1121,6 +1605,99 @@ mod tests {
         assert_eq!(proposal.tx.prefix.fee, Mob::MINIMUM_FEE * 10);
     }
 
+    // Test setting the fee token
+    #[test_with_logger]
+    fn test_fee_token_id(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let db_test_context = WalletDbTestContext::default();
+        let wallet_db = db_test_context.get_db_instance(logger.clone());
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let mut ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        // Start sync thread
+        let _sync_thread = SyncThread::start(ledger_db.clone(), wallet_db.clone(), logger.clone());
+
+        let account_key = random_account_with_seed_values(
+            &wallet_db,
+            &mut ledger_db,
+            &vec![70 * MOB],
+            &mut rng,
+            &logger,
+        );
+
+        let conn = wallet_db.get_conn().unwrap();
+        let (recipient, mut builder) =
+            builder_for_random_recipient(&account_key, &ledger_db, &mut rng, &logger);
+
+        // A fee in a token other than the outlays' needs a block version
+        // which supports mixed transactions.
+        builder.set_block_version(BlockVersion::MAX);
+        match builder.set_fee_token_id(TokenId::from(1)) {
+            Ok(_) => panic!("Should not be able to pay the fee in a different token"),
+            Err(WalletTransactionBuilderError::MixedTokenFee(1, 0, block_version)) => {
+                assert_eq!(block_version, *BlockVersion::MAX)
+            }
+            Err(e) => panic!("Unexpected error {:?}", e),
+        }
+        assert!(check_fee_token_id(BlockVersion::MAX, Mob::ID, Mob::ID).is_ok());
+        assert_eq!(
+            check_fee_token_id(BlockVersion::MAX, Mob::ID, TokenId::from(1)).is_ok(),
+            *BlockVersion::MAX >= MIXED_TRANSACTIONS_BLOCK_VERSION
+        );
+
+        // Paying the fee in MOB works
+        builder.set_fee_token_id(Mob::ID).unwrap();
+        builder.add_recipient(recipient.clone(), 10 * MOB).unwrap();
+        builder.select_txos(&conn, None, false).unwrap();
+        builder.set_tombstone(0).unwrap();
+        let proposal = builder.build(&conn).unwrap();
+        assert_eq!(proposal.tx.prefix.fee, Mob::MINIMUM_FEE);
+    }
+
+    // Test sending a token other than MOB
+    #[test_with_logger]
+    fn test_token_id(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let db_test_context = WalletDbTestContext::default();
+        let wallet_db = db_test_context.get_db_instance(logger.clone());
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let mut ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        // Start sync thread
+        let _sync_thread = SyncThread::start(ledger_db.clone(), wallet_db.clone(), logger.clone());
+
+        let account_key = random_account_with_seed_values(
+            &wallet_db,
+            &mut ledger_db,
+            &vec![70 * MOB],
+            &mut rng,
+            &logger,
+        );
+
+        let conn = wallet_db.get_conn().unwrap();
+        let (recipient, mut builder) =
+            builder_for_random_recipient(&account_key, &ledger_db, &mut rng, &logger);
+
+        // The fee follows the outlays' token, and cannot be paid in MOB
+        builder.set_token_id(TokenId::from(1));
+        match builder.set_fee_token_id(Mob::ID) {
+            Ok(_) => panic!("Should not be able to pay the fee in a different token"),
+            Err(WalletTransactionBuilderError::MixedTokenFee(0, 1, _)) => {}
+            Err(e) => panic!("Unexpected error {:?}", e),
+        }
+
+        // Only txos in the outlays' token are selected, and the account holds
+        // none.
+        builder.add_recipient(recipient.clone(), 10 * MOB).unwrap();
+        match builder.select_txos(&conn, None, false) {
+            Ok(_) => panic!("Should not be able to select MOB txos for another token"),
+            Err(WalletTransactionBuilderError::WalletDb(WalletDbError::NoSpendableTxos)) => {}
+            Err(e) => panic!("Unexpected error {:?}", e),
+        }
+    }
+
     // Even if change is zero, we should still have a change output
     #[test_with_logger]
     fn test_change_zero_mob(logger: Logger) {
@@ -1163,6 +1740,97 @@ mod tests {
                                                          // self
     }
 
+    #[test_with_logger]
+    fn test_change_outputs(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let db_test_context = WalletDbTestContext::default();
+        let wallet_db = db_test_context.get_db_instance(logger.clone());
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let mut ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        // Start sync thread
+        let _sync_thread = SyncThread::start(ledger_db.clone(), wallet_db.clone(), logger.clone());
+
+        let account_key = random_account_with_seed_values(
+            &wallet_db,
+            &mut ledger_db,
+            &vec![70 * MOB, 80 * MOB],
+            &mut rng,
+            &logger,
+        );
+
+        let conn = wallet_db.get_conn().unwrap();
+
+        // The change is split evenly over three outputs.
+        let (recipient, mut builder) =
+            builder_for_random_recipient(&account_key, &ledger_db, &mut rng, &logger);
+        builder.add_recipient(recipient.clone(), 10 * MOB).unwrap();
+        builder.set_change_outputs(ChangeOutputs::Split(3)).unwrap();
+        builder.select_txos(&conn, None, false).unwrap();
+        builder.set_tombstone(0).unwrap();
+        let proposal = builder.build(&conn).unwrap();
+        assert_eq!(proposal.tx.prefix.inputs.len(), 1);
+        assert_eq!(proposal.tx.prefix.outputs.len(), 4); // outlay + 3 change
+
+        // Fixed change values are selected for, with the rest in one more
+        // output.
+        let (recipient, mut builder) =
+            builder_for_random_recipient(&account_key, &ledger_db, &mut rng, &logger);
+        builder.add_recipient(recipient.clone(), 10 * MOB).unwrap();
+        builder
+            .set_change_outputs(ChangeOutputs::Fixed(vec![40 * MOB, 40 * MOB]))
+            .unwrap();
+        builder.select_txos(&conn, None, false).unwrap();
+        builder.set_tombstone(0).unwrap();
+        let proposal = builder.build(&conn).unwrap();
+        assert_eq!(proposal.tx.prefix.inputs.len(), 2); // 90 MOB needs both
+        assert_eq!(proposal.tx.prefix.outputs.len(), 4); // outlay + 3 change
+
+        // Change outputs count against the protocol's output limit.
+        let (recipient, mut builder) =
+            builder_for_random_recipient(&account_key, &ledger_db, &mut rng, &logger);
+        builder.add_recipient(recipient.clone(), 10 * MOB).unwrap();
+        builder
+            .set_change_outputs(ChangeOutputs::Split(MAX_OUTPUTS as usize))
+            .unwrap();
+        builder.select_txos(&conn, None, false).unwrap();
+        builder.set_tombstone(0).unwrap();
+        match builder.build(&conn) {
+            Err(WalletTransactionBuilderError::InvalidArgument(_)) => {}
+            other => panic!("unexpected result {:?}", other.map(|_| ())),
+        }
+
+        assert!(builder.set_change_outputs(ChangeOutputs::Split(0)).is_err());
+        assert!(builder
+            .set_change_outputs(ChangeOutputs::Fixed(vec![0]))
+            .is_err());
+    }
+
+    #[test]
+    fn test_change_output_values() {
+        assert_eq!(ChangeOutputs::default().values(10), Some(vec![10]));
+        assert_eq!(ChangeOutputs::Split(3).values(10), Some(vec![3, 3, 4]));
+        assert_eq!(
+            ChangeOutputs::Fixed(vec![2, 3]).values(10),
+            Some(vec![2, 3, 5])
+        );
+        assert_eq!(ChangeOutputs::Fixed(vec![2, 3]).values(4), None);
+
+        assert_eq!(
+            ChangeOutputs::from_params(Some("2".to_string()), None).unwrap(),
+            Some(ChangeOutputs::Split(2))
+        );
+        assert_eq!(
+            ChangeOutputs::from_params(None, Some(vec!["5".to_string()])).unwrap(),
+            Some(ChangeOutputs::Fixed(vec![5]))
+        );
+        assert_eq!(ChangeOutputs::from_params(None, None).unwrap(), None);
+        assert!(
+            ChangeOutputs::from_params(Some("2".to_string()), Some(vec!["5".to_string()])).is_err()
+        );
+    }
+
     // We should be able to add multiple TxOuts to the same recipient, not to
     // multiple
     #[test_with_logger]
@@ -1291,4 +1959,64 @@ mod tests {
             .add_recipient(second_recipient.clone(), 40 * MOB)
             .unwrap();
     }
+
+    // Each coin selection strategy should pick its inputs deterministically.
+    #[test_with_logger]
+    fn test_coin_selection_strategies(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let db_test_context = WalletDbTestContext::default();
+        let wallet_db = db_test_context.get_db_instance(logger.clone());
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let mut ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        // Start sync thread
+        let _sync_thread = SyncThread::start(ledger_db.clone(), wallet_db.clone(), logger.clone());
+
+        let account_key = random_account_with_seed_values(
+            &wallet_db,
+            &mut ledger_db,
+            &vec![1 * MOB, 2 * MOB, 3 * MOB, 40 * MOB, 60 * MOB],
+            &mut rng,
+            &logger,
+        );
+        let conn = wallet_db.get_conn().unwrap();
+
+        let expected = vec![
+            ("smallest-first", vec![1 * MOB, 2 * MOB, 3 * MOB]),
+            ("largest-first", vec![60 * MOB]),
+            ("minimize-inputs", vec![40 * MOB]),
+            (
+                "consolidate-dust",
+                vec![1 * MOB, 2 * MOB, 3 * MOB, 40 * MOB, 60 * MOB],
+            ),
+        ];
+        for (strategy, expected_values) in expected {
+            let (recipient, mut builder) =
+                builder_for_random_recipient(&account_key, &ledger_db, &mut rng, &logger);
+            builder.add_recipient(recipient, 4 * MOB).unwrap();
+            builder.set_coin_selection_strategy(strategy.parse().unwrap());
+            builder.select_txos(&conn, None, false).unwrap();
+
+            let mut values: Vec<u64> = builder.inputs.iter().map(|txo| txo.value as u64).collect();
+            values.sort_unstable();
+            assert_eq!(values, expected_values, "{}", strategy);
+        }
+
+        // Without enough in the account, every strategy fails.
+        for strategy in &[
+            CoinSelectionStrategy::LargestFirst,
+            CoinSelectionStrategy::SmallestFirst,
+            CoinSelectionStrategy::ConsolidateDust,
+            CoinSelectionStrategy::MinimizeInputs,
+        ] {
+            let (recipient, mut builder) =
+                builder_for_random_recipient(&account_key, &ledger_db, &mut rng, &logger);
+            builder.add_recipient(recipient, 200 * MOB).unwrap();
+            builder.set_coin_selection_strategy(*strategy);
+            assert!(builder.select_txos(&conn, None, false).is_err());
+        }
+
+        assert!("random".parse::<CoinSelectionStrategy>().is_err());
+    }
 }
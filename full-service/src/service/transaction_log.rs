@@ -5,16 +5,32 @@
 use crate::{
     db::{
         account::AccountID,
-        models::TransactionLog,
+        models::{TransactionLog, TX_STATUS_BUILT, TX_STATUS_FAILED, TX_STATUS_PENDING},
         transaction_log::{AssociatedTxos, TransactionLogModel},
         WalletDbError,
     },
     error::WalletServiceError,
+    util::pagination::Page,
     WalletService,
 };
+use chrono::Utc;
 use displaydoc::Display;
+use mc_common::HashSet;
 use mc_connection::{BlockchainConnection, UserTxConnection};
 use mc_fog_report_validation::FogPubkeyResolver;
+use mc_transaction_core::tx::Tx;
+use std::collections::BTreeMap;
+
+/// The statuses of logs which can be cleaned up. Pending logs are for
+/// transactions which may yet land, and succeeded logs are the wallet's
+/// history, so neither can be removed.
+pub const CLEANUP_STATUSES: &[&str] = &[TX_STATUS_BUILT, TX_STATUS_FAILED];
+
+/// The most blocks whose transaction logs can be requested at once.
+pub const MAX_TRANSACTION_LOG_BLOCK_RANGE: u64 = 10_000;
+
+/// The most transaction logs returned per page.
+pub const MAX_TRANSACTION_LOG_PAGE_SIZE: u64 = 1000;
 
 /// Errors for the Transaction Log Service.
 #[derive(Display, Debug)]
@@ -25,6 +41,22 @@ pub enum TransactionLogServiceError {
 
     /// Diesel Error: {0}
     Diesel(diesel::result::Error),
+
+    /// Only built and failed transaction logs can be cleaned up, not: {0}
+    InvalidCleanupStatus(String),
+
+    /// Invalid block range, min_block_index {0} is greater than max_block_index
+    /// {1}
+    InvalidBlockRange(u64, u64),
+
+    /// Block range of {0} blocks is too large, the most is {1}
+    BlockRangeTooLarge(u64, u64),
+
+    /// Limit of {0} transaction logs is too large, the most is {1}
+    LimitTooLarge(u64, u64),
+
+    /// Error decoding the logged transaction: {0}
+    Decode(mc_util_serial::DecodeError),
 }
 
 impl From<WalletDbError> for TransactionLogServiceError {
@@ -39,6 +71,34 @@ impl From<diesel::result::Error> for TransactionLogServiceError {
     }
 }
 
+impl From<mc_util_serial::DecodeError> for TransactionLogServiceError {
+    fn from(src: mc_util_serial::DecodeError) -> Self {
+        Self::Decode(src)
+    }
+}
+
+/// The value committed by every account's pending transactions, which have
+/// been submitted but have not yet landed or expired. Values are in each
+/// token's smallest unit.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PendingOutflows {
+    /// The number of pending transactions.
+    pub num_transactions: u64,
+
+    /// The value sent to recipients, by token id. Change is not included.
+    pub values: BTreeMap<u64, u128>,
+
+    /// The network fees paid, by fee token id.
+    pub fees: BTreeMap<u64, u128>,
+
+    /// The number of distinct addresses the transactions pay.
+    pub num_recipients: u64,
+
+    /// The lowest tombstone block of the transactions, by which the first of
+    /// them will have either landed or expired.
+    pub earliest_tombstone_block: Option<u64>,
+}
+
 /// Trait defining the ways in which the wallet can interact with and manage
 /// transaction logs.
 pub trait TransactionLogService {
@@ -52,22 +112,56 @@ pub trait TransactionLogService {
         max_block_index: Option<u64>,
     ) -> Result<Vec<(TransactionLog, AssociatedTxos)>, WalletServiceError>;
 
+    /// Get a page of the transactions associated with the given Account ID, in
+    /// insertion order, starting after the log with the given cursor, or after
+    /// skipping `offset` logs.
+    fn list_transaction_logs_page(
+        &self,
+        account_id: &AccountID,
+        cursor: Option<i32>,
+        offset: Option<u64>,
+        limit: u64,
+        min_block_index: Option<u64>,
+        max_block_index: Option<u64>,
+    ) -> Result<Page<(TransactionLog, AssociatedTxos)>, WalletServiceError>;
+
     /// Get a specific transaction log.
     fn get_transaction_log(
         &self,
         transaction_id_hex: &str,
     ) -> Result<(TransactionLog, AssociatedTxos), TransactionLogServiceError>;
 
-    /// Get all transaction logs for a given block.
-    fn get_all_transaction_logs_for_block(
+    /// Get a page of the transaction logs finalized in the given range of
+    /// blocks, inclusive, ordered by block.
+    ///
+    /// The range may span at most MAX_TRANSACTION_LOG_BLOCK_RANGE blocks, and
+    /// at most MAX_TRANSACTION_LOG_PAGE_SIZE logs are returned.
+    fn get_all_transaction_logs_for_block_range(
         &self,
-        block_index: u64,
-    ) -> Result<Vec<(TransactionLog, AssociatedTxos)>, WalletServiceError>;
+        min_block_index: u64,
+        max_block_index: u64,
+        offset: Option<u64>,
+        limit: Option<u64>,
+    ) -> Result<Vec<(TransactionLog, AssociatedTxos)>, TransactionLogServiceError>;
 
     /// Get all transaction logs ordered by finalized_block_index.
     fn get_all_transaction_logs_ordered_by_block(
         &self,
     ) -> Result<Vec<(TransactionLog, AssociatedTxos)>, WalletServiceError>;
+
+    /// Remove the logs of sent transactions with any of the given statuses,
+    /// which must be built or failed, and which were logged more than
+    /// older_than seconds ago if it is given.
+    ///
+    /// Returns how many logs were removed.
+    fn cleanup_transaction_logs(
+        &self,
+        statuses: &[String],
+        older_than: Option<u64>,
+    ) -> Result<usize, TransactionLogServiceError>;
+
+    /// Sum up the pending transactions sent from every account in the wallet.
+    fn get_pending_outflows(&self) -> Result<PendingOutflows, TransactionLogServiceError>;
 }
 
 impl<T, FPR> TransactionLogService for WalletService<T, FPR>
@@ -94,6 +188,33 @@ where
         )?)
     }
 
+    fn list_transaction_logs_page(
+        &self,
+        account_id: &AccountID,
+        cursor: Option<i32>,
+        offset: Option<u64>,
+        limit: u64,
+        min_block_index: Option<u64>,
+        max_block_index: Option<u64>,
+    ) -> Result<Page<(TransactionLog, AssociatedTxos)>, WalletServiceError> {
+        let conn = &self.wallet_db.get_conn()?;
+        // Fetch one more log than the page holds, to tell whether more follow.
+        let transaction_logs = TransactionLog::list_page(
+            &account_id.to_string(),
+            cursor,
+            offset,
+            limit.saturating_add(1),
+            min_block_index,
+            max_block_index,
+            conn,
+        )?;
+        Ok(Page::from_overfetched(
+            transaction_logs,
+            limit,
+            |(transaction_log, _)| transaction_log.id,
+        ))
+    }
+
     fn get_transaction_log(
         &self,
         transaction_id_hex: &str,
@@ -105,12 +226,42 @@ where
         Ok((transaction_log, associated))
     }
 
-    fn get_all_transaction_logs_for_block(
+    fn get_all_transaction_logs_for_block_range(
         &self,
-        block_index: u64,
-    ) -> Result<Vec<(TransactionLog, AssociatedTxos)>, WalletServiceError> {
+        min_block_index: u64,
+        max_block_index: u64,
+        offset: Option<u64>,
+        limit: Option<u64>,
+    ) -> Result<Vec<(TransactionLog, AssociatedTxos)>, TransactionLogServiceError> {
+        if min_block_index > max_block_index {
+            return Err(TransactionLogServiceError::InvalidBlockRange(
+                min_block_index,
+                max_block_index,
+            ));
+        }
+        let num_blocks = max_block_index - min_block_index + 1;
+        if num_blocks > MAX_TRANSACTION_LOG_BLOCK_RANGE {
+            return Err(TransactionLogServiceError::BlockRangeTooLarge(
+                num_blocks,
+                MAX_TRANSACTION_LOG_BLOCK_RANGE,
+            ));
+        }
+        let limit = limit.unwrap_or(MAX_TRANSACTION_LOG_PAGE_SIZE);
+        if limit > MAX_TRANSACTION_LOG_PAGE_SIZE {
+            return Err(TransactionLogServiceError::LimitTooLarge(
+                limit,
+                MAX_TRANSACTION_LOG_PAGE_SIZE,
+            ));
+        }
+
         let conn = self.wallet_db.get_conn()?;
-        let transaction_logs = TransactionLog::get_all_for_block_index(block_index, &conn)?;
+        let transaction_logs = TransactionLog::get_all_for_block_range(
+            min_block_index,
+            max_block_index,
+            offset.unwrap_or(0),
+            limit,
+            &conn,
+        )?;
         let mut res: Vec<(TransactionLog, AssociatedTxos)> = Vec::new();
         for transaction_log in transaction_logs {
             res.push((
@@ -135,22 +286,94 @@ where
         }
         Ok(res)
     }
+
+    fn cleanup_transaction_logs(
+        &self,
+        statuses: &[String],
+        older_than: Option<u64>,
+    ) -> Result<usize, TransactionLogServiceError> {
+        let statuses: Vec<&str> = statuses.iter().map(|status| status.as_str()).collect();
+        if let Some(status) = statuses
+            .iter()
+            .find(|status| !CLEANUP_STATUSES.contains(status))
+        {
+            return Err(TransactionLogServiceError::InvalidCleanupStatus(
+                status.to_string(),
+            ));
+        }
+        let sent_before = older_than.map(|older_than| Utc::now().timestamp() - older_than as i64);
+
+        let conn = self.wallet_db.get_conn()?;
+        Ok(TransactionLog::delete_sent_with_status(
+            &statuses,
+            sent_before,
+            &conn,
+        )?)
+    }
+
+    fn get_pending_outflows(&self) -> Result<PendingOutflows, TransactionLogServiceError> {
+        let conn = self.wallet_db.get_conn()?;
+
+        let mut pending_outflows = PendingOutflows::default();
+        let mut recipients = HashSet::default();
+        for transaction_log in
+            TransactionLog::list_sent_with_status(TX_STATUS_PENDING, None, &conn)?
+        {
+            pending_outflows.num_transactions += 1;
+
+            for txo in transaction_log.get_associated_txos(&conn)?.outputs {
+                *pending_outflows
+                    .values
+                    .entry(txo.token_id as u64)
+                    .or_default() += txo.value as u64 as u128;
+                // Outputs whose recipient is unknown aren't counted.
+                if !txo.recipient_public_address_b58.is_empty() {
+                    recipients.insert(txo.recipient_public_address_b58);
+                }
+            }
+
+            if let Some(tx) = &transaction_log.tx {
+                let tx: Tx = mc_util_serial::decode(tx)?;
+                *pending_outflows
+                    .fees
+                    .entry(tx.prefix.fee_token_id)
+                    .or_default() += tx.prefix.fee as u128;
+                let tombstone_block = tx.prefix.tombstone_block;
+                pending_outflows.earliest_tombstone_block = Some(
+                    pending_outflows
+                        .earliest_tombstone_block
+                        .map_or(tombstone_block, |earliest| earliest.min(tombstone_block)),
+                );
+            }
+        }
+        pending_outflows.num_recipients = recipients.len() as u64;
+
+        Ok(pending_outflows)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::{
         db::account::AccountID,
-        service::{account::AccountService, transaction_log::TransactionLogService},
+        service::{
+            account::AccountService,
+            transaction::TransactionService,
+            transaction_log::{
+                PendingOutflows, TransactionLogService, TransactionLogServiceError,
+                MAX_TRANSACTION_LOG_BLOCK_RANGE, MAX_TRANSACTION_LOG_PAGE_SIZE,
+            },
+        },
         test_utils::{
             add_block_to_ledger_db, get_test_ledger, manually_sync_account, setup_wallet_service,
             MOB,
         },
+        util::b58::b58_encode_public_address,
     };
     use mc_account_keys::{AccountKey, PublicAddress};
     use mc_common::logger::{test_with_logger, Logger};
     use mc_crypto_rand::rand_core::RngCore;
-    use mc_transaction_core::ring_signature::KeyImage;
+    use mc_transaction_core::{ring_signature::KeyImage, tokens::Mob, Token};
     use rand::{rngs::StdRng, SeedableRng};
 
     #[test_with_logger]
@@ -253,4 +476,161 @@ mod tests {
 
         assert_eq!(3, tx_logs.len());
     }
+
+    #[test_with_logger]
+    fn test_get_all_transaction_logs_for_block_range(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let mut ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        let service = setup_wallet_service(ledger_db.clone(), logger.clone());
+
+        let alice = service
+            .create_account(
+                Some("Alice's Main Account".to_string()),
+                "".to_string(),
+                "".to_string(),
+                "".to_string(),
+            )
+            .unwrap();
+
+        let alice_account_key: AccountKey = mc_util_serial::decode(&alice.account_key).unwrap();
+        let alice_account_id = AccountID::from(&alice_account_key);
+        let alice_public_address = alice_account_key.subaddress(alice.main_subaddress_index as u64);
+
+        // block_index 12 through 16
+        for _ in 0..5 {
+            add_block_to_ledger_db(
+                &mut ledger_db,
+                &vec![alice_public_address.clone()],
+                100 * MOB,
+                &vec![KeyImage::from(rng.next_u64())],
+                &mut rng,
+            );
+        }
+
+        manually_sync_account(&ledger_db, &service.wallet_db, &alice_account_id, &logger);
+
+        let tx_logs = service
+            .get_all_transaction_logs_for_block_range(12, 12, None, None)
+            .unwrap();
+        assert_eq!(1, tx_logs.len());
+
+        let tx_logs = service
+            .get_all_transaction_logs_for_block_range(13, 15, None, None)
+            .unwrap();
+        let block_indices: Vec<Option<i64>> = tx_logs
+            .iter()
+            .map(|(tx_log, _)| tx_log.finalized_block_index)
+            .collect();
+        assert_eq!(block_indices, vec![Some(13), Some(14), Some(15)]);
+
+        // Pages follow on from each other in block order.
+        let first_page = service
+            .get_all_transaction_logs_for_block_range(12, 16, Some(0), Some(2))
+            .unwrap();
+        let second_page = service
+            .get_all_transaction_logs_for_block_range(12, 16, Some(2), Some(2))
+            .unwrap();
+        assert_eq!(first_page.len(), 2);
+        assert_eq!(first_page[1].0.finalized_block_index, Some(13));
+        assert_eq!(second_page.len(), 2);
+        assert_eq!(second_page[0].0.finalized_block_index, Some(14));
+
+        assert!(matches!(
+            service.get_all_transaction_logs_for_block_range(15, 13, None, None),
+            Err(TransactionLogServiceError::InvalidBlockRange(15, 13))
+        ));
+        assert!(matches!(
+            service.get_all_transaction_logs_for_block_range(
+                0,
+                MAX_TRANSACTION_LOG_BLOCK_RANGE,
+                None,
+                None
+            ),
+            Err(TransactionLogServiceError::BlockRangeTooLarge(_, _))
+        ));
+        assert!(matches!(
+            service.get_all_transaction_logs_for_block_range(
+                12,
+                16,
+                None,
+                Some(MAX_TRANSACTION_LOG_PAGE_SIZE + 1)
+            ),
+            Err(TransactionLogServiceError::LimitTooLarge(_, _))
+        ));
+    }
+
+    #[test_with_logger]
+    fn test_get_pending_outflows(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let mut ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        let service = setup_wallet_service(ledger_db.clone(), logger.clone());
+
+        let alice = service
+            .create_account(
+                Some("Alice's Main Account".to_string()),
+                "".to_string(),
+                "".to_string(),
+                "".to_string(),
+            )
+            .unwrap();
+        let alice_account_key: AccountKey = mc_util_serial::decode(&alice.account_key).unwrap();
+        let alice_account_id = AccountID::from(&alice_account_key);
+        add_block_to_ledger_db(
+            &mut ledger_db,
+            &vec![alice_account_key.subaddress(alice.main_subaddress_index as u64)],
+            100 * MOB,
+            &vec![KeyImage::from(rng.next_u64())],
+            &mut rng,
+        );
+        manually_sync_account(&ledger_db, &service.wallet_db, &alice_account_id, &logger);
+
+        // Received transactions aren't outflows.
+        assert_eq!(
+            service.get_pending_outflows().unwrap(),
+            PendingOutflows::default()
+        );
+
+        let bob_address =
+            b58_encode_public_address(&AccountKey::random(&mut rng).default_subaddress()).unwrap();
+        let tx_proposal = service
+            .build_transaction(
+                &alice.account_id_hex,
+                &vec![(bob_address, (42 * MOB).to_string())],
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+            )
+            .unwrap();
+        let tombstone_block = tx_proposal.tx.prefix.tombstone_block;
+        service
+            .submit_transaction(tx_proposal, None, Some(alice.account_id_hex.clone()))
+            .unwrap();
+
+        let pending_outflows = service.get_pending_outflows().unwrap();
+        assert_eq!(pending_outflows.num_transactions, 1);
+        assert_eq!(pending_outflows.values.get(&0), Some(&(42 * MOB as u128)));
+        assert_eq!(
+            pending_outflows.fees.get(&0),
+            Some(&(Mob::MINIMUM_FEE as u128))
+        );
+        assert_eq!(pending_outflows.num_recipients, 1);
+        assert_eq!(
+            pending_outflows.earliest_tombstone_block,
+            Some(tombstone_block)
+        );
+    }
 }
@@ -0,0 +1,184 @@
+// Copyright (c) 2020-2022 MobileCoin Inc.
+
+//! Service for watching transactions built outside of this wallet.
+//!
+//! Transactions built by this wallet are tracked through their transaction
+//! logs. A transaction submitted by another instance, or signed offline, can
+//! instead be watched by the public keys of its outputs. The sync thread
+//! marks the watch as landed once all of the outputs are in the ledger, or as
+//! expired once its tombstone block passes, recording a wallet event either
+//! way.
+
+use crate::{
+    db::{
+        models::TransactionWatch, transaction, transaction_watch::TransactionWatchModel,
+        WalletDbError,
+    },
+    WalletService,
+};
+use displaydoc::Display;
+use mc_connection::{BlockchainConnection, UserTxConnection};
+use mc_crypto_keys::CompressedRistrettoPublic;
+use mc_fog_report_validation::FogPubkeyResolver;
+
+/// Errors for the Transaction Watch Service.
+#[derive(Display, Debug)]
+#[allow(clippy::large_enum_variant)]
+pub enum TransactionWatchServiceError {
+    /// Error interacting with the database: {0}
+    Database(WalletDbError),
+
+    /// Diesel Error: {0}
+    Diesel(diesel::result::Error),
+
+    /// At least one output public key is required to watch a transaction
+    NoPublicKeys,
+}
+
+impl From<WalletDbError> for TransactionWatchServiceError {
+    fn from(src: WalletDbError) -> Self {
+        Self::Database(src)
+    }
+}
+
+impl From<diesel::result::Error> for TransactionWatchServiceError {
+    fn from(src: diesel::result::Error) -> Self {
+        Self::Diesel(src)
+    }
+}
+
+/// Trait defining the ways in which the wallet can watch for transactions it
+/// did not build.
+pub trait TransactionWatchService {
+    /// Start watching for a transaction with the given output public keys.
+    ///
+    /// The ledger is checked immediately, so a transaction which has already
+    /// landed is reported as such.
+    fn watch_transaction(
+        &self,
+        tx_public_keys: &[CompressedRistrettoPublic],
+        tombstone_block: Option<u64>,
+        comment: Option<String>,
+    ) -> Result<TransactionWatch, TransactionWatchServiceError>;
+
+    /// Get a transaction watch by its id.
+    fn get_transaction_watch(
+        &self,
+        watch_id: &str,
+    ) -> Result<TransactionWatch, TransactionWatchServiceError>;
+
+    /// List all transaction watches.
+    fn list_transaction_watches(
+        &self,
+        offset: Option<u64>,
+        limit: Option<u64>,
+    ) -> Result<Vec<TransactionWatch>, TransactionWatchServiceError>;
+}
+
+impl<T, FPR> TransactionWatchService for WalletService<T, FPR>
+where
+    T: BlockchainConnection + UserTxConnection + 'static,
+    FPR: FogPubkeyResolver + Send + Sync + 'static,
+{
+    fn watch_transaction(
+        &self,
+        tx_public_keys: &[CompressedRistrettoPublic],
+        tombstone_block: Option<u64>,
+        comment: Option<String>,
+    ) -> Result<TransactionWatch, TransactionWatchServiceError> {
+        if tx_public_keys.is_empty() {
+            return Err(TransactionWatchServiceError::NoPublicKeys);
+        }
+
+        let conn = self.wallet_db.get_conn()?;
+        Ok(transaction(&conn, || {
+            TransactionWatch::create(
+                tx_public_keys,
+                tombstone_block,
+                &comment.unwrap_or_default(),
+                &conn,
+            )?
+            .update_from_ledger(&self.ledger_db, &conn)
+        })?)
+    }
+
+    fn get_transaction_watch(
+        &self,
+        watch_id: &str,
+    ) -> Result<TransactionWatch, TransactionWatchServiceError> {
+        let conn = self.wallet_db.get_conn()?;
+        Ok(TransactionWatch::get(watch_id, &conn)?)
+    }
+
+    fn list_transaction_watches(
+        &self,
+        offset: Option<u64>,
+        limit: Option<u64>,
+    ) -> Result<Vec<TransactionWatch>, TransactionWatchServiceError> {
+        let conn = self.wallet_db.get_conn()?;
+        Ok(TransactionWatch::list_all(offset, limit, &conn)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        db::models::{WATCH_STATUS_LANDED, WATCH_STATUS_PENDING},
+        test_utils::{get_test_ledger, setup_wallet_service},
+    };
+    use mc_common::logger::{test_with_logger, Logger};
+    use mc_crypto_keys::RistrettoPublic;
+    use mc_ledger_db::Ledger;
+    use mc_util_from_random::FromRandom;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test_with_logger]
+    fn test_watch_transaction(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let known_recipients = vec![];
+        let ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+        let service = setup_wallet_service(ledger_db.clone(), logger);
+
+        assert!(matches!(
+            service.watch_transaction(&[], None, None),
+            Err(TransactionWatchServiceError::NoPublicKeys)
+        ));
+
+        // A transaction which has already landed is reported immediately.
+        let num_blocks = ledger_db.num_blocks().unwrap();
+        let block_contents = ledger_db.get_block_contents(num_blocks - 1).unwrap();
+        let landed = service
+            .watch_transaction(
+                &[block_contents.outputs[0].public_key],
+                None,
+                Some("landed".to_string()),
+            )
+            .unwrap();
+        assert_eq!(landed.status, WATCH_STATUS_LANDED);
+        assert_eq!(landed.comment, "landed");
+
+        let pending = service
+            .watch_transaction(
+                &[CompressedRistrettoPublic::from(
+                    &RistrettoPublic::from_random(&mut rng),
+                )],
+                Some(num_blocks + 10),
+                None,
+            )
+            .unwrap();
+        assert_eq!(pending.status, WATCH_STATUS_PENDING);
+
+        assert_eq!(
+            service
+                .get_transaction_watch(&pending.watch_id_hex)
+                .unwrap(),
+            pending
+        );
+        assert_eq!(
+            service.list_transaction_watches(None, None).unwrap(),
+            vec![landed, pending]
+        );
+    }
+}
@@ -4,19 +4,33 @@
 
 use crate::{
     db::{
-        account::AccountID,
+        account::{AccountID, AccountModel},
         assigned_subaddress::AssignedSubaddressModel,
-        models::{AssignedSubaddress, Txo},
-        txo::{TxoID, TxoModel},
-        WalletDbError,
+        models::{
+            Account, AssignedSubaddress, TransactionLog, Txo, WalletSetting,
+            TXO_CONFIRMATION_STATUS_CONFIRMED, TXO_CONFIRMATION_STATUS_CONFIRMING,
+            TXO_CONFIRMATION_STATUS_UNCONFIRMED, TX_STATUS_SUCCEEDED,
+        },
+        transaction,
+        transaction_log::TransactionLogModel,
+        txo::{TxoExportFilter, TxoID, TxoModel},
+        wallet_settings::WalletSettingModel,
+        Conn, WalletDb, WalletDbError,
+    },
+    service::{
+        ledger::LedgerService,
+        transaction::{TransactionService, TransactionServiceError},
     },
-    service::transaction::{TransactionService, TransactionServiceError},
+    util::pagination::Page,
     WalletService,
 };
 use displaydoc::Display;
 use mc_connection::{BlockchainConnection, UserTxConnection};
+use mc_crypto_keys::CompressedRistrettoPublic;
 use mc_fog_report_validation::FogPubkeyResolver;
+use mc_ledger_db::Ledger;
 use mc_mobilecoind::payments::TxProposal;
+use mc_transaction_core::{constants::MAX_INPUTS, TokenId};
 
 /// Errors for the Txo Service.
 #[derive(Display, Debug)]
@@ -42,6 +56,24 @@ pub enum TxoServiceError {
 
     /// Txo Not Spendable
     TxoNotSpendable(String),
+
+    /// The account has only been scanned up to block {0}
+    BlockNotScanned(u64),
+
+    /// Fewer than two unspent txos are below {0}, so there is nothing to
+    /// consolidate
+    NothingToConsolidate(u64),
+
+    /// The txos to consolidate are worth {0}, which does not cover the fee
+    ConsolidationBelowFee(u64),
+
+    /** There are {0} spendable txos, more than one transaction can spend.
+     * Allow multiple transactions to consolidate them first.
+     */
+    TooManyTxosToSweep(usize),
+
+    /// The txos to sweep are worth {0}, which does not cover the fee
+    SweepBelowFee(u64),
 }
 
 impl From<WalletDbError> for TxoServiceError {
@@ -68,6 +100,95 @@ impl From<TransactionServiceError> for TxoServiceError {
     }
 }
 
+/// Number of txos to load from the database at a time when exporting.
+pub const EXPORT_PAGE_SIZE: u64 = 1000;
+
+/// Pages through the txos matching an export filter.
+///
+/// This holds its own handle to the wallet database, rather than borrowing the
+/// service, so that it can be consumed after the request handler returns while
+/// the response is streamed.
+pub struct TxoExportPages {
+    wallet_db: WalletDb,
+    filter: TxoExportFilter,
+    cursor: Option<i32>,
+    remaining: Option<u64>,
+    done: bool,
+}
+
+impl Iterator for TxoExportPages {
+    type Item = Result<Vec<Txo>, TxoServiceError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done || self.remaining == Some(0) {
+            return None;
+        }
+
+        let page_size = self.remaining.map_or(EXPORT_PAGE_SIZE, |remaining| {
+            remaining.min(EXPORT_PAGE_SIZE)
+        });
+        let page = self
+            .wallet_db
+            .get_conn()
+            .and_then(|conn| Txo::list_for_export(&self.filter, self.cursor, page_size, &conn));
+
+        match page {
+            Ok(txos) => {
+                if (txos.len() as u64) < page_size {
+                    self.done = true;
+                }
+                if txos.is_empty() {
+                    return None;
+                }
+                self.cursor = txos.last().map(|txo| txo.id);
+                if let Some(remaining) = self.remaining.as_mut() {
+                    *remaining -= txos.len() as u64;
+                }
+                Some(Ok(txos))
+            }
+            Err(err) => {
+                self.done = true;
+                Some(Err(err.into()))
+            }
+        }
+    }
+}
+
+/// The transactions built to sweep an account.
+#[derive(Debug)]
+pub struct SweepPlan {
+    /// The transactions to submit, which spend disjoint txos.
+    pub tx_proposals: Vec<TxProposal>,
+
+    /// Whether the transactions send the account's funds to the recipient.
+    /// Otherwise, they consolidate its txos, and the sweep should be built
+    /// again once they have landed.
+    pub complete: bool,
+}
+
+/// How a Txo was received and spent, and how deep it is in the ledger.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TxoContext {
+    /// The address the receiving account received the Txo at, if it is
+    /// assigned. Its comment is the address's label.
+    pub received_subaddress: Option<AssignedSubaddress>,
+
+    /// The log of the transaction from this wallet which spends the Txo. If
+    /// several have tried to, the one which succeeded, or else the latest.
+    pub spending_transaction_log: Option<TransactionLog>,
+
+    /// The block the Txo is in, if it is in the ledger.
+    pub block_index: Option<u64>,
+
+    /// The number of blocks from the Txo's block to the end of the ledger,
+    /// counting its own.
+    pub confirmations: u64,
+
+    /// Whether the Txo is in the ledger, and buried under the wallet's
+    /// confirmation depth.
+    pub confirmation_status: &'static str,
+}
+
 /// Trait defining the ways in which the wallet can interact with and manage
 /// Txos.
 pub trait TxoService {
@@ -79,12 +200,68 @@ pub trait TxoService {
         offset: Option<u64>,
     ) -> Result<Vec<Txo>, TxoServiceError>;
 
+    /// Get a page of the Txos for a given account in the wallet, in insertion
+    /// order, starting after the txo with the given cursor, or after skipping
+    /// `offset` txos.
+    fn list_txos_page(
+        &self,
+        account_id: &AccountID,
+        cursor: Option<i32>,
+        offset: Option<u64>,
+        limit: u64,
+    ) -> Result<Page<Txo>, TxoServiceError>;
+
     /// list all spent txos
     fn list_spent_txos(&self, account_id: &AccountID) -> Result<Vec<Txo>, TxoServiceError>;
 
     /// Get a Txo from the wallet.
     fn get_txo(&self, txo_id: &TxoID) -> Result<Txo, TxoServiceError>;
 
+    /// Get how a Txo was received and spent, and how deep it is in the
+    /// ledger.
+    fn get_txo_context(&self, txo: &Txo) -> Result<TxoContext, TxoServiceError>;
+
+    /// Freeze Txos, keeping them out of transactions until they are unfrozen,
+    /// for example while a payment from them awaits approval elsewhere.
+    fn freeze_txos(&self, txo_ids: &[String]) -> Result<Vec<Txo>, TxoServiceError>;
+
+    /// Unfreeze Txos, so that they can be spent again.
+    fn unfreeze_txos(&self, txo_ids: &[String]) -> Result<Vec<Txo>, TxoServiceError>;
+
+    /// Build a transaction sending up to MAX_INPUTS of the account's smallest
+    /// unspent txos below the threshold back to one of its subaddresses, less
+    /// the fee, merging them into a single txo.
+    fn build_consolidation_transaction(
+        &self,
+        account_id: &AccountID,
+        threshold: u64,
+        token_id: Option<u64>,
+        subaddress_index: Option<i64>,
+        fee: Option<String>,
+        tombstone_block: Option<String>,
+    ) -> Result<TxProposal, TxoServiceError>;
+
+    /// Build a transaction sending all of the account's spendable txos of a
+    /// token, or those received at one of its subaddresses, to a recipient,
+    /// less the fee.
+    ///
+    /// A transaction spends at most MAX_INPUTS txos. When there are more, and
+    /// multiple transactions are allowed, this instead builds transactions
+    /// consolidating them in groups back to the subaddress they are swept
+    /// from, and the sweep is built again once those have landed.
+    #[allow(clippy::too_many_arguments)]
+    fn build_sweep_transaction(
+        &self,
+        account_id: &AccountID,
+        recipient_public_address: &str,
+        token_id: Option<u64>,
+        subaddress_index: Option<i64>,
+        allow_multiple_transactions: bool,
+        fee: Option<String>,
+        tombstone_block: Option<String>,
+        confirm_large_send: Option<bool>,
+    ) -> Result<SweepPlan, TxoServiceError>;
+
     /// Split a Txo
     fn split_txo(
         &self,
@@ -97,6 +274,25 @@ pub trait TxoService {
 
     /// List the Txos for a given address for an account in the wallet.
     fn get_all_txos_for_address(&self, address: &str) -> Result<Vec<Txo>, TxoServiceError>;
+
+    /// Export the Txos matching a filter, in pages, starting after the txo
+    /// with the given cursor and stopping after `limit` txos, if given.
+    fn export_txos(
+        &self,
+        filter: TxoExportFilter,
+        cursor: Option<i32>,
+        limit: Option<u64>,
+    ) -> TxoExportPages;
+
+    /// Get a page of the received Txos whose key images are known, of an
+    /// account or of every account, starting after the txo with the given
+    /// cursor.
+    fn export_key_images(
+        &self,
+        account_id: Option<&AccountID>,
+        cursor: Option<i32>,
+        limit: u64,
+    ) -> Result<Page<Txo>, TxoServiceError>;
 }
 
 impl<T, FPR> TxoService for WalletService<T, FPR>
@@ -120,6 +316,26 @@ where
         )?)
     }
 
+    fn list_txos_page(
+        &self,
+        account_id: &AccountID,
+        cursor: Option<i32>,
+        offset: Option<u64>,
+        limit: u64,
+    ) -> Result<Page<Txo>, TxoServiceError> {
+        let conn = self.wallet_db.get_conn()?;
+        // Fetch one more txo than the page holds, to tell whether more follow.
+        let txos = Txo::list_page_for_account(
+            &account_id.to_string(),
+            cursor,
+            offset,
+            limit.saturating_add(1),
+            Some(0),
+            &conn,
+        )?;
+        Ok(Page::from_overfetched(txos, limit, |txo| txo.id))
+    }
+
     fn list_spent_txos(&self, account_id: &AccountID) -> Result<Vec<Txo>, TxoServiceError> {
         let conn = self.wallet_db.get_conn()?;
         Ok(Txo::list_spent(
@@ -135,6 +351,75 @@ where
         Ok(Txo::get(&txo_id.to_string(), &conn)?)
     }
 
+    fn get_txo_context(&self, txo: &Txo) -> Result<TxoContext, TxoServiceError> {
+        let conn = self.wallet_db.get_conn()?;
+
+        let received_subaddress = match (&txo.received_account_id_hex, txo.subaddress_index) {
+            (Some(account_id_hex), Some(subaddress_index)) => {
+                match AssignedSubaddress::get_for_account_by_index(
+                    account_id_hex,
+                    subaddress_index,
+                    &conn,
+                ) {
+                    Ok(subaddress) => Some(subaddress),
+                    Err(WalletDbError::AssignedSubaddressNotFound(_)) => None,
+                    Err(err) => return Err(err.into()),
+                }
+            }
+            _ => None,
+        };
+
+        let spending_logs = TransactionLog::select_spending_txo(&txo.txo_id_hex, &conn)?;
+        let spending_transaction_log = spending_logs
+            .iter()
+            .find(|log| log.status == TX_STATUS_SUCCEEDED)
+            .or_else(|| spending_logs.last())
+            .cloned();
+
+        // Txos which were only minted here are looked up in the ledger.
+        let block_index = match txo.received_block_index {
+            Some(block_index) => Some(block_index as u64),
+            None => {
+                let public_key: CompressedRistrettoPublic =
+                    mc_util_serial::decode(&txo.public_key).map_err(WalletDbError::from)?;
+                match self.ledger_db.get_tx_out_index_by_public_key(&public_key) {
+                    Ok(tx_out_index) => Some(
+                        self.ledger_db
+                            .get_block_index_by_tx_out_index(tx_out_index)?,
+                    ),
+                    Err(mc_ledger_db::Error::NotFound) => None,
+                    Err(err) => return Err(err.into()),
+                }
+            }
+        };
+
+        let num_blocks = self.ledger_db.num_blocks()?;
+        let confirmations =
+            block_index.map_or(0, |block_index| num_blocks.saturating_sub(block_index));
+        let confirmation_depth = WalletSetting::get_settings(&conn)?.confirmation_depth;
+        let confirmation_status = match block_index {
+            None => TXO_CONFIRMATION_STATUS_UNCONFIRMED,
+            Some(_) if confirmations < confirmation_depth => TXO_CONFIRMATION_STATUS_CONFIRMING,
+            Some(_) => TXO_CONFIRMATION_STATUS_CONFIRMED,
+        };
+
+        Ok(TxoContext {
+            received_subaddress,
+            spending_transaction_log,
+            block_index,
+            confirmations,
+            confirmation_status,
+        })
+    }
+
+    fn freeze_txos(&self, txo_ids: &[String]) -> Result<Vec<Txo>, TxoServiceError> {
+        self.update_txos_frozen(txo_ids, true)
+    }
+
+    fn unfreeze_txos(&self, txo_ids: &[String]) -> Result<Vec<Txo>, TxoServiceError> {
+        self.update_txos_frozen(txo_ids, false)
+    }
+
     fn split_txo(
         &self,
         txo_id: &TxoID,
@@ -147,6 +432,7 @@ where
 
         let conn = self.wallet_db.get_conn()?;
         let txo_details = Txo::get(&txo_id.to_string(), &conn)?;
+        let token_id = txo_details.token_id.to_string();
 
         let account_id_hex = txo_details
             .received_account_id_hex
@@ -171,29 +457,315 @@ where
             &account_id_hex,
             &addresses_and_values,
             Some(&[txo_id.to_string()].to_vec()),
+            Some(token_id),
             fee,
+            None,
             tombstone_block,
             None,
+            // Splitting sends back to the same account, so needs no confirmation.
+            Some(true),
+            None,
+            None,
+            None,
             None,
         )?)
     }
 
+    fn build_consolidation_transaction(
+        &self,
+        account_id: &AccountID,
+        threshold: u64,
+        token_id: Option<u64>,
+        subaddress_index: Option<i64>,
+        fee: Option<String>,
+        tombstone_block: Option<String>,
+    ) -> Result<TxProposal, TxoServiceError> {
+        let conn = self.wallet_db.get_conn()?;
+        let account = Account::get(account_id, &conn)?;
+        let token_id = token_id.unwrap_or(account.preferred_token_id as u64);
+        let fee = self.fee_for_token(fee, token_id, &conn)?;
+
+        // Spendable txos come largest first, so take the smallest from the end.
+        let txos: Vec<Txo> =
+            Txo::list_spendable(&account_id.to_string(), None, None, Some(token_id), &conn)?
+                .spendable_txos
+                .into_iter()
+                .rev()
+                .filter(|txo| (txo.value as u64) < threshold)
+                .take(MAX_INPUTS as usize)
+                .collect();
+        if txos.len() < 2 {
+            return Err(TxoServiceError::NothingToConsolidate(threshold));
+        }
+
+        let total: u64 = txos.iter().map(|txo| txo.value as u64).sum();
+        if total <= fee {
+            return Err(TxoServiceError::ConsolidationBelowFee(total));
+        }
+
+        let destination = AssignedSubaddress::get_for_account_by_index(
+            &account_id.to_string(),
+            subaddress_index.unwrap_or(account.main_subaddress_index),
+            &conn,
+        )?;
+        let input_txo_ids: Vec<String> = txos.iter().map(|txo| txo.txo_id_hex.clone()).collect();
+
+        Ok(self.build_transaction(
+            &account_id.to_string(),
+            &[(
+                destination.assigned_subaddress_b58,
+                (total - fee).to_string(),
+            )],
+            Some(&input_txo_ids),
+            Some(token_id.to_string()),
+            Some(fee.to_string()),
+            None,
+            tombstone_block,
+            None,
+            // Consolidating sends back to the same account, so needs no
+            // confirmation.
+            Some(true),
+            None,
+            None,
+            None,
+            None,
+        )?)
+    }
+
+    fn build_sweep_transaction(
+        &self,
+        account_id: &AccountID,
+        recipient_public_address: &str,
+        token_id: Option<u64>,
+        subaddress_index: Option<i64>,
+        allow_multiple_transactions: bool,
+        fee: Option<String>,
+        tombstone_block: Option<String>,
+        confirm_large_send: Option<bool>,
+    ) -> Result<SweepPlan, TxoServiceError> {
+        let conn = self.wallet_db.get_conn()?;
+        let account = Account::get(account_id, &conn)?;
+        let token_id = token_id.unwrap_or(account.preferred_token_id as u64);
+        let fee = self.fee_for_token(fee, token_id, &conn)?;
+
+        let source = subaddress_index
+            .map(|index| {
+                AssignedSubaddress::get_for_account_by_index(&account_id.to_string(), index, &conn)
+            })
+            .transpose()?;
+        let txos = Txo::list_spendable(
+            &account_id.to_string(),
+            None,
+            source
+                .as_ref()
+                .map(|subaddress| subaddress.assigned_subaddress_b58.as_str()),
+            Some(token_id),
+            &conn,
+        )?
+        .spendable_txos;
+        if txos.is_empty() {
+            return Err(WalletDbError::NoSpendableTxos.into());
+        }
+
+        if txos.len() <= MAX_INPUTS as usize {
+            let total: u64 = txos.iter().map(|txo| txo.value as u64).sum();
+            if total <= fee {
+                return Err(TxoServiceError::SweepBelowFee(total));
+            }
+            let input_txo_ids: Vec<String> =
+                txos.iter().map(|txo| txo.txo_id_hex.clone()).collect();
+            let tx_proposal = self.build_transaction(
+                &account_id.to_string(),
+                &[(
+                    recipient_public_address.to_string(),
+                    (total - fee).to_string(),
+                )],
+                Some(&input_txo_ids),
+                Some(token_id.to_string()),
+                Some(fee.to_string()),
+                None,
+                tombstone_block,
+                None,
+                confirm_large_send,
+                None,
+                None,
+                None,
+                None,
+            )?;
+            return Ok(SweepPlan {
+                tx_proposals: vec![tx_proposal],
+                complete: true,
+            });
+        }
+
+        if !allow_multiple_transactions {
+            return Err(TxoServiceError::TooManyTxosToSweep(txos.len()));
+        }
+
+        // Consolidate the txos in groups back to the subaddress they are swept
+        // from, so that the next sweep finds them there. A group of one txo, or
+        // of txos worth no more than the fee, is left to a later round.
+        let destination = match source {
+            Some(source) => source,
+            None => AssignedSubaddress::get_for_account_by_index(
+                &account_id.to_string(),
+                account.main_subaddress_index,
+                &conn,
+            )?,
+        };
+        let mut tx_proposals = Vec::new();
+        for group in txos.chunks(MAX_INPUTS as usize) {
+            let total: u64 = group.iter().map(|txo| txo.value as u64).sum();
+            if group.len() < 2 || total <= fee {
+                continue;
+            }
+            let input_txo_ids: Vec<String> =
+                group.iter().map(|txo| txo.txo_id_hex.clone()).collect();
+            tx_proposals.push(self.build_transaction(
+                &account_id.to_string(),
+                &[(
+                    destination.assigned_subaddress_b58.clone(),
+                    (total - fee).to_string(),
+                )],
+                Some(&input_txo_ids),
+                Some(token_id.to_string()),
+                Some(fee.to_string()),
+                None,
+                tombstone_block.clone(),
+                None,
+                // Consolidating sends back to the same account, so needs no
+                // confirmation.
+                Some(true),
+                None,
+                None,
+                None,
+                None,
+            )?);
+        }
+        if tx_proposals.is_empty() {
+            let total: u64 = txos.iter().map(|txo| txo.value as u64).sum();
+            return Err(TxoServiceError::SweepBelowFee(total));
+        }
+
+        Ok(SweepPlan {
+            tx_proposals,
+            complete: false,
+        })
+    }
+
     fn get_all_txos_for_address(&self, address: &str) -> Result<Vec<Txo>, TxoServiceError> {
         let conn = self.wallet_db.get_conn()?;
         Ok(Txo::list_for_address(address, Some(0), &conn)?)
     }
+
+    fn export_txos(
+        &self,
+        filter: TxoExportFilter,
+        cursor: Option<i32>,
+        limit: Option<u64>,
+    ) -> TxoExportPages {
+        TxoExportPages {
+            wallet_db: self.wallet_db.clone(),
+            filter,
+            cursor,
+            remaining: limit,
+            done: false,
+        }
+    }
+
+    fn export_key_images(
+        &self,
+        account_id: Option<&AccountID>,
+        cursor: Option<i32>,
+        limit: u64,
+    ) -> Result<Page<Txo>, TxoServiceError> {
+        let conn = self.wallet_db.get_conn()?;
+        if let Some(account_id) = account_id {
+            Account::get(account_id, &conn)?;
+        }
+        let account_id_hex = account_id.map(|account_id| account_id.to_string());
+        // Fetch one more txo than the page holds, to tell whether more follow.
+        let txos = Txo::list_key_images_page(
+            account_id_hex.as_deref(),
+            cursor,
+            limit.saturating_add(1),
+            &conn,
+        )?;
+        Ok(Page::from_overfetched(txos, limit, |txo| txo.id))
+    }
+}
+
+impl<T, FPR> WalletService<T, FPR>
+where
+    T: BlockchainConnection + UserTxConnection + 'static,
+    FPR: FogPubkeyResolver + Send + Sync + 'static,
+{
+    /// The fee given, or else the network's minimum fee for the token times
+    /// the wallet's fee multiplier.
+    fn fee_for_token(
+        &self,
+        fee: Option<String>,
+        token_id: u64,
+        conn: &Conn,
+    ) -> Result<u64, TxoServiceError> {
+        Ok(match fee {
+            Some(fee) => fee.parse::<u64>().map_err(TransactionServiceError::from)?,
+            None => self
+                .get_network_minimum_fees()
+                .get(&TokenId::from(token_id))
+                .ok_or(TransactionServiceError::UnknownMinimumFee(token_id))?
+                .saturating_mul(WalletSetting::get_settings(conn)?.default_fee_multiplier),
+        })
+    }
+
+    /// Freeze or unfreeze Txos received by the wallet's accounts. Either all
+    /// of them are updated, or none are.
+    fn update_txos_frozen(
+        &self,
+        txo_ids: &[String],
+        frozen: bool,
+    ) -> Result<Vec<Txo>, TxoServiceError> {
+        let conn = self.wallet_db.get_conn()?;
+        let txos = transaction(&conn, || {
+            let mut txos = Vec::new();
+            for txo_id in txo_ids {
+                let txo = Txo::get(txo_id, &conn)?;
+                if txo.received_account_id_hex.is_none() {
+                    return Err(TxoServiceError::TxoNotSpendableByAnyAccount(
+                        txo_id.to_string(),
+                    ));
+                }
+                if txo.is_spent() {
+                    return Err(TxoServiceError::TxoNotSpendable(txo_id.to_string()));
+                }
+                txo.update_frozen(frozen, &conn)?;
+                txos.push(Txo::get(txo_id, &conn)?);
+            }
+            Ok(txos)
+        })?;
+
+        // Frozen value is reported apart in the balance.
+        for txo in &txos {
+            if let Some(account_id_hex) = &txo.received_account_id_hex {
+                self.balance_cache.invalidate(account_id_hex);
+            }
+        }
+
+        Ok(txos)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::{
+        db::models::TX_STATUS_PENDING,
         service::{
             account::AccountService, balance::BalanceService, transaction::TransactionService,
         },
         test_utils::{
-            add_block_to_ledger_db, get_test_ledger, manually_sync_account, setup_wallet_service,
-            MOB,
+            add_block_to_ledger_db, get_test_ledger, manually_sync_account,
+            random_account_with_seed_values, setup_wallet_service, MOB,
         },
         util::b58::b58_encode_public_address,
     };
@@ -240,7 +812,9 @@ mod tests {
         manually_sync_account(&ledger_db, &service.wallet_db, &alice_account_id, &logger);
 
         // Verify balance for Alice
-        let balance = service.get_balance_for_account(&alice_account_id).unwrap();
+        let balance = service
+            .get_balance_for_account(&alice_account_id, false)
+            .unwrap();
 
         assert_eq!(balance.unspent, 100 * MOB as u128);
 
@@ -275,6 +849,12 @@ mod tests {
                 None,
                 None,
                 None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
             )
             .unwrap();
         let _submitted = service
@@ -319,7 +899,7 @@ mod tests {
 
         // Our balance should reflect the various statuses of our txos
         let balance = service
-            .get_balance_for_account(&AccountID(alice.account_id_hex))
+            .get_balance_for_account(&AccountID(alice.account_id_hex), false)
             .unwrap();
         assert_eq!(balance.unspent, 0);
         assert_eq!(balance.pending, 100 * MOB as u128);
@@ -327,4 +907,433 @@ mod tests {
         assert_eq!(balance.secreted, (100 * MOB - Mob::MINIMUM_FEE) as u128);
         assert_eq!(balance.orphaned, 0);
     }
+
+    #[test_with_logger]
+    fn test_txo_context(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let mut ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        let service = setup_wallet_service(ledger_db.clone(), logger.clone());
+        let alice = service
+            .create_account(
+                Some("Alice's Main Account".to_string()),
+                "".to_string(),
+                "".to_string(),
+                "".to_string(),
+            )
+            .unwrap();
+        let alice_account_key: AccountKey = mc_util_serial::decode(&alice.account_key).unwrap();
+        let alice_account_id = AccountID::from(&alice_account_key);
+        let alice_public_address = alice_account_key.subaddress(alice.main_subaddress_index as u64);
+        add_block_to_ledger_db(
+            &mut ledger_db,
+            &vec![alice_public_address.clone()],
+            100 * MOB,
+            &vec![KeyImage::from(rng.next_u64())],
+            &mut rng,
+        );
+        manually_sync_account(&ledger_db, &service.wallet_db, &alice_account_id, &logger);
+
+        let txos = service.list_txos(&alice_account_id, None, None).unwrap();
+        assert_eq!(txos.len(), 1);
+        let received = txos[0].clone();
+
+        // The received Txo is in the last block, which the default depth of one
+        // confirmation covers.
+        let context = service.get_txo_context(&received).unwrap();
+        let received_subaddress = context.received_subaddress.unwrap();
+        assert_eq!(
+            received_subaddress.subaddress_index,
+            alice.main_subaddress_index
+        );
+        assert_eq!(received_subaddress.comment, "Main");
+        assert!(context.spending_transaction_log.is_none());
+        assert_eq!(context.block_index, Some(5));
+        assert_eq!(context.confirmations, 1);
+        assert_eq!(
+            context.confirmation_status,
+            TXO_CONFIRMATION_STATUS_CONFIRMED
+        );
+
+        let bob = service
+            .create_account(
+                Some("Bob's Main Account".to_string()),
+                "".to_string(),
+                "".to_string(),
+                "".to_string(),
+            )
+            .unwrap();
+        let bob_account_key: AccountKey = mc_util_serial::decode(&bob.account_key).unwrap();
+        let tx_proposal = service
+            .build_transaction(
+                &alice.account_id_hex,
+                &vec![(
+                    b58_encode_public_address(
+                        &bob_account_key.subaddress(bob.main_subaddress_index as u64),
+                    )
+                    .unwrap(),
+                    "42000000000000".to_string(),
+                )],
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+            )
+            .unwrap();
+        let submitted = service
+            .submit_transaction(tx_proposal, None, Some(alice.account_id_hex.clone()))
+            .unwrap()
+            .unwrap();
+
+        // The spent Txo now points at the pending transaction.
+        let context = service.get_txo_context(&received).unwrap();
+        let spending_transaction_log = context.spending_transaction_log.unwrap();
+        assert_eq!(
+            spending_transaction_log.transaction_id_hex,
+            submitted.0.transaction_id_hex
+        );
+        assert_eq!(spending_transaction_log.status, TX_STATUS_PENDING);
+
+        // The minted Txos are not in the ledger yet.
+        let minted = service
+            .list_txos(&alice_account_id, None, None)
+            .unwrap()
+            .into_iter()
+            .find(|txo| txo.minted_account_id_hex.is_some())
+            .unwrap();
+        let context = service.get_txo_context(&minted).unwrap();
+        assert_eq!(context.block_index, None);
+        assert_eq!(context.confirmations, 0);
+        assert_eq!(
+            context.confirmation_status,
+            TXO_CONFIRMATION_STATUS_UNCONFIRMED
+        );
+    }
+
+    #[test_with_logger]
+    fn test_freeze_txos(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let mut ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+        let service = setup_wallet_service(ledger_db.clone(), logger.clone());
+        let account_key = random_account_with_seed_values(
+            &service.wallet_db,
+            &mut ledger_db,
+            &vec![70 * MOB, 30 * MOB],
+            &mut rng,
+            &logger,
+        );
+        let account_id = AccountID::from(&account_key);
+        let recipient =
+            b58_encode_public_address(&AccountKey::random(&mut rng).subaddress(0)).unwrap();
+        let build = |value: u64, input_txo_ids: Option<&Vec<String>>| {
+            service.build_transaction(
+                &account_id.to_string(),
+                &[(recipient.clone(), value.to_string())],
+                input_txo_ids,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+            )
+        };
+
+        let txos = service.list_txos(&account_id, None, None).unwrap();
+        let large_txo_id = txos
+            .iter()
+            .find(|txo| txo.value as u64 == 70 * MOB)
+            .unwrap()
+            .txo_id_hex
+            .clone();
+
+        let frozen = service.freeze_txos(&[large_txo_id.clone()]).unwrap();
+        assert_eq!(frozen.len(), 1);
+        assert!(frozen[0].frozen);
+
+        // Frozen value is still unspent, but can't be spent.
+        let balance = service.get_balance_for_account(&account_id, false).unwrap();
+        assert_eq!(balance.unspent, 100 * MOB as u128);
+        assert_eq!(balance.frozen, 70 * MOB as u128);
+        assert_eq!(balance.max_spendable, (30 * MOB - Mob::MINIMUM_FEE) as u128);
+
+        assert!(build(50 * MOB, None).is_err());
+        assert!(build(50 * MOB, Some(&vec![large_txo_id.clone()])).is_err());
+        assert!(build(10 * MOB, None).is_ok());
+
+        // Unknown txos are rejected, without freezing any of the others.
+        assert!(service
+            .unfreeze_txos(&[large_txo_id.clone(), "abcd".to_string()])
+            .is_err());
+        assert!(
+            service
+                .get_txo(&TxoID(large_txo_id.clone()))
+                .unwrap()
+                .frozen
+        );
+
+        let unfrozen = service.unfreeze_txos(&[large_txo_id]).unwrap();
+        assert!(!unfrozen[0].frozen);
+        let balance = service.get_balance_for_account(&account_id, false).unwrap();
+        assert_eq!(balance.frozen, 0);
+        assert!(build(50 * MOB, None).is_ok());
+    }
+
+    #[test_with_logger]
+    fn test_build_consolidation_transaction(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let mut ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        let service = setup_wallet_service(ledger_db.clone(), logger.clone());
+        let alice = service
+            .create_account(
+                Some("Alice's Main Account".to_string()),
+                "".to_string(),
+                "".to_string(),
+                "".to_string(),
+            )
+            .unwrap();
+        let alice_account_key: AccountKey = mc_util_serial::decode(&alice.account_key).unwrap();
+        let alice_account_id = AccountID::from(&alice_account_key);
+        let alice_public_address = alice_account_key.subaddress(alice.main_subaddress_index as u64);
+        for value in &[1 * MOB, 2 * MOB, 3 * MOB, 100 * MOB] {
+            add_block_to_ledger_db(
+                &mut ledger_db,
+                &vec![alice_public_address.clone()],
+                *value,
+                &vec![KeyImage::from(rng.next_u64())],
+                &mut rng,
+            );
+        }
+        manually_sync_account(&ledger_db, &service.wallet_db, &alice_account_id, &logger);
+
+        // The three txos below the threshold are merged, less the fee.
+        let tx_proposal = service
+            .build_consolidation_transaction(
+                &alice_account_id,
+                10 * MOB,
+                None,
+                None,
+                Some(Mob::MINIMUM_FEE.to_string()),
+                None,
+            )
+            .unwrap();
+        assert_eq!(tx_proposal.tx.prefix.inputs.len(), 3);
+        assert_eq!(tx_proposal.outlays.len(), 1);
+        assert_eq!(tx_proposal.outlays[0].receiver, alice_public_address);
+        assert_eq!(tx_proposal.outlays[0].value, 6 * MOB - Mob::MINIMUM_FEE);
+
+        // A single txo below the threshold is left alone.
+        match service.build_consolidation_transaction(
+            &alice_account_id,
+            2 * MOB,
+            None,
+            None,
+            Some(Mob::MINIMUM_FEE.to_string()),
+            None,
+        ) {
+            Err(TxoServiceError::NothingToConsolidate(_)) => {}
+            other => panic!("unexpected result {:?}", other.map(|_| ())),
+        }
+
+        // As are txos worth less than the fee.
+        match service.build_consolidation_transaction(
+            &alice_account_id,
+            10 * MOB,
+            None,
+            None,
+            Some((6 * MOB).to_string()),
+            None,
+        ) {
+            Err(TxoServiceError::ConsolidationBelowFee(_)) => {}
+            other => panic!("unexpected result {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test_with_logger]
+    fn test_build_sweep_transaction(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let mut ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        let service = setup_wallet_service(ledger_db.clone(), logger.clone());
+        let alice = service
+            .create_account(
+                Some("Alice's Main Account".to_string()),
+                "".to_string(),
+                "".to_string(),
+                "".to_string(),
+            )
+            .unwrap();
+        let alice_account_key: AccountKey = mc_util_serial::decode(&alice.account_key).unwrap();
+        let alice_account_id = AccountID::from(&alice_account_key);
+        let alice_public_address = alice_account_key.subaddress(alice.main_subaddress_index as u64);
+        let bob = service
+            .create_account(
+                Some("Bob's Main Account".to_string()),
+                "".to_string(),
+                "".to_string(),
+                "".to_string(),
+            )
+            .unwrap();
+        let bob_account_key: AccountKey = mc_util_serial::decode(&bob.account_key).unwrap();
+        let bob_account_id = AccountID::from(&bob_account_key);
+        let bob_public_address = bob_account_key.subaddress(bob.main_subaddress_index as u64);
+        let bob_b58 = b58_encode_public_address(&bob_public_address).unwrap();
+
+        for value in &[1 * MOB, 2 * MOB, 3 * MOB] {
+            add_block_to_ledger_db(
+                &mut ledger_db,
+                &vec![alice_public_address.clone()],
+                *value,
+                &vec![KeyImage::from(rng.next_u64())],
+                &mut rng,
+            );
+        }
+        for _ in 0..(MAX_INPUTS + 1) {
+            add_block_to_ledger_db(
+                &mut ledger_db,
+                &vec![bob_public_address.clone()],
+                1 * MOB,
+                &vec![KeyImage::from(rng.next_u64())],
+                &mut rng,
+            );
+        }
+        manually_sync_account(&ledger_db, &service.wallet_db, &alice_account_id, &logger);
+        manually_sync_account(&ledger_db, &service.wallet_db, &bob_account_id, &logger);
+
+        // All of Alice's txos are sent to Bob in one transaction, less the fee.
+        let plan = service
+            .build_sweep_transaction(
+                &alice_account_id,
+                &bob_b58,
+                None,
+                None,
+                false,
+                Some(Mob::MINIMUM_FEE.to_string()),
+                None,
+                Some(true),
+            )
+            .unwrap();
+        assert!(plan.complete);
+        assert_eq!(plan.tx_proposals.len(), 1);
+        let tx_proposal = &plan.tx_proposals[0];
+        assert_eq!(tx_proposal.tx.prefix.inputs.len(), 3);
+        assert_eq!(tx_proposal.outlays.len(), 1);
+        assert_eq!(tx_proposal.outlays[0].receiver, bob_public_address);
+        assert_eq!(tx_proposal.outlays[0].value, 6 * MOB - Mob::MINIMUM_FEE);
+
+        // Bob has more txos than one transaction can spend.
+        let alice_b58 = b58_encode_public_address(&alice_public_address).unwrap();
+        match service.build_sweep_transaction(
+            &bob_account_id,
+            &alice_b58,
+            None,
+            None,
+            false,
+            Some(Mob::MINIMUM_FEE.to_string()),
+            None,
+            Some(true),
+        ) {
+            Err(TxoServiceError::TooManyTxosToSweep(count)) => {
+                assert_eq!(count, MAX_INPUTS as usize + 1)
+            }
+            other => panic!("unexpected result {:?}", other.map(|_| ())),
+        }
+
+        // So they are consolidated first, leaving the last txo for the next
+        // round.
+        let plan = service
+            .build_sweep_transaction(
+                &bob_account_id,
+                &alice_b58,
+                None,
+                None,
+                true,
+                Some(Mob::MINIMUM_FEE.to_string()),
+                None,
+                Some(true),
+            )
+            .unwrap();
+        assert!(!plan.complete);
+        assert_eq!(plan.tx_proposals.len(), 1);
+        let tx_proposal = &plan.tx_proposals[0];
+        assert_eq!(tx_proposal.tx.prefix.inputs.len(), MAX_INPUTS as usize);
+        assert_eq!(tx_proposal.outlays[0].receiver, bob_public_address);
+        assert_eq!(
+            tx_proposal.outlays[0].value,
+            MAX_INPUTS as u64 * MOB - Mob::MINIMUM_FEE
+        );
+    }
+
+    #[test_with_logger]
+    fn test_export_key_images(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let mut ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        let service = setup_wallet_service(ledger_db.clone(), logger.clone());
+        let alice = service
+            .create_account(
+                Some("Alice's Main Account".to_string()),
+                "".to_string(),
+                "".to_string(),
+                "".to_string(),
+            )
+            .unwrap();
+        let alice_account_key: AccountKey = mc_util_serial::decode(&alice.account_key).unwrap();
+        let alice_account_id = AccountID::from(&alice_account_key);
+        let alice_public_address = alice_account_key.subaddress(alice.main_subaddress_index as u64);
+        for _ in 0..3 {
+            add_block_to_ledger_db(
+                &mut ledger_db,
+                &vec![alice_public_address.clone()],
+                100 * MOB,
+                &vec![KeyImage::from(rng.next_u64())],
+                &mut rng,
+            );
+        }
+        manually_sync_account(&ledger_db, &service.wallet_db, &alice_account_id, &logger);
+
+        // Page through the key images two at a time.
+        let page = service
+            .export_key_images(Some(&alice_account_id), None, 2)
+            .unwrap();
+        assert_eq!(page.items.len(), 2);
+        assert!(page.items.iter().all(|txo| txo.key_image.is_some()));
+        let next_cursor = page.next_cursor.unwrap();
+        let page = service
+            .export_key_images(Some(&alice_account_id), Some(next_cursor), 2)
+            .unwrap();
+        assert_eq!(page.items.len(), 1);
+        assert_eq!(page.next_cursor, None);
+
+        // All accounts together.
+        let page = service.export_key_images(None, None, 100).unwrap();
+        assert_eq!(page.items.len(), 3);
+
+        // An unknown account is an error rather than an empty export.
+        assert!(service
+            .export_key_images(Some(&AccountID("deadbeef".to_string())), None, 100)
+            .is_err());
+    }
 }
@@ -0,0 +1,259 @@
+// Copyright (c) 2020-2022 MobileCoin Inc.
+
+//! Service for checking whether this build is ready for the network's next
+//! protocol upgrade.
+//!
+//! Consensus nodes advertise the block version and minimum fees they enforce.
+//! Nodes are upgraded one at a time, so some nodes advertising a block version
+//! newer than this build supports is the warning that the network is about to
+//! outpace it.
+
+use crate::{
+    db::{
+        models::{WalletEvent, EVENT_TYPE_UPGRADE_READINESS_CHANGED},
+        wallet_event::WalletEventModel,
+        WalletDbError,
+    },
+    service::ledger::LedgerService,
+    WalletService,
+};
+use displaydoc::Display;
+use mc_common::logger::log;
+use mc_connection::{BlockchainConnection, RetryableBlockchainConnection, UserTxConnection};
+use mc_fog_report_validation::FogPubkeyResolver;
+use mc_transaction_core::{tokens::Mob, BlockVersion, Token, TokenId};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::{collections::BTreeMap, iter::empty};
+
+/// Errors for the Upgrade Readiness Service.
+#[derive(Display, Debug)]
+#[allow(clippy::large_enum_variant)]
+pub enum UpgradeReadinessServiceError {
+    /// Error interacting with the database: {0}
+    Database(WalletDbError),
+
+    /// Error serializing the readiness report: {0}
+    Serialization(serde_json::Error),
+}
+
+impl From<WalletDbError> for UpgradeReadinessServiceError {
+    fn from(src: WalletDbError) -> Self {
+        Self::Database(src)
+    }
+}
+
+impl From<serde_json::Error> for UpgradeReadinessServiceError {
+    fn from(src: serde_json::Error) -> Self {
+        Self::Serialization(src)
+    }
+}
+
+/// How ready this build is for the block version the network advertises.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum UpgradeReadinessStatus {
+    /// No peers could be reached, for example when running offline.
+    Unknown,
+
+    /// Every peer advertises a block version this build supports.
+    Ready,
+
+    /// Some peers advertise a block version newer than this build supports,
+    /// so the network is part way through an upgrade.
+    UpgradeSoon,
+
+    /// Every peer advertises a block version newer than this build supports.
+    /// New blocks are quarantined until full-service is upgraded.
+    Unsupported,
+}
+
+impl std::fmt::Display for UpgradeReadinessStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let status = match self {
+            Self::Unknown => "unknown",
+            Self::Ready => "ready",
+            Self::UpgradeSoon => "upgrade_soon",
+            Self::Unsupported => "unsupported",
+        };
+        write!(f, "{}", status)
+    }
+}
+
+/// A comparison of what the network advertises against what this build
+/// supports.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct UpgradeReadiness {
+    pub status: UpgradeReadinessStatus,
+
+    /// The newest block version this build can process.
+    pub max_supported_block_version: u32,
+
+    /// The lowest and highest block versions advertised by the peers, if any
+    /// could be reached.
+    pub lowest_network_block_version: Option<u32>,
+    pub highest_network_block_version: Option<u32>,
+
+    /// Human readable descriptions of each problem found, including what to
+    /// do about it.
+    pub warnings: Vec<String>,
+}
+
+/// Compare the block versions advertised by the peers, and their minimum fees,
+/// against what this build supports.
+pub fn assess_upgrade_readiness(
+    network_block_versions: &[u32],
+    network_minimum_fees: &BTreeMap<TokenId, u64>,
+) -> UpgradeReadiness {
+    let max_supported_block_version = *BlockVersion::MAX;
+    let lowest_network_block_version = network_block_versions.iter().min().cloned();
+    let highest_network_block_version = network_block_versions.iter().max().cloned();
+
+    let mut warnings = Vec::new();
+    let status = match (lowest_network_block_version, highest_network_block_version) {
+        (Some(lowest), _) if lowest > max_supported_block_version => {
+            warnings.push(format!(
+                "The network is at block version {}, but this build only supports up to {}. New blocks are quarantined until full-service is upgraded.",
+                lowest, max_supported_block_version
+            ));
+            UpgradeReadinessStatus::Unsupported
+        }
+        (_, Some(highest)) if highest > max_supported_block_version => {
+            warnings.push(format!(
+                "Some peers advertise block version {}, but this build only supports up to {}. Upgrade full-service before the network upgrade completes.",
+                highest, max_supported_block_version
+            ));
+            UpgradeReadinessStatus::UpgradeSoon
+        }
+        (Some(_), Some(_)) => UpgradeReadinessStatus::Ready,
+        _ => UpgradeReadinessStatus::Unknown,
+    };
+
+    // Transactions built offline use the fee compiled into this build, and are
+    // rejected if the network requires more.
+    if status != UpgradeReadinessStatus::Unknown {
+        if let Some(fee) = network_minimum_fees.get(&Mob::ID) {
+            if *fee > Mob::MINIMUM_FEE {
+                warnings.push(format!(
+                    "The network minimum fee for token {} is {}, above the {} this build defaults to. Transactions built offline with the default fee will be rejected.",
+                    *Mob::ID, fee, Mob::MINIMUM_FEE
+                ));
+            }
+        }
+    }
+
+    UpgradeReadiness {
+        status,
+        max_supported_block_version,
+        lowest_network_block_version,
+        highest_network_block_version,
+        warnings,
+    }
+}
+
+/// Trait defining the ways in which the wallet can check that it is ready for
+/// network upgrades.
+pub trait UpgradeReadinessService {
+    /// Compare what the peers advertise against what this build supports.
+    fn get_upgrade_readiness(&self) -> UpgradeReadiness;
+
+    /// Check the upgrade readiness, recording an event if it has changed since
+    /// the last one was recorded.
+    fn check_upgrade_readiness(&self) -> Result<UpgradeReadiness, UpgradeReadinessServiceError>;
+}
+
+impl<T, FPR> UpgradeReadinessService for WalletService<T, FPR>
+where
+    T: BlockchainConnection + UserTxConnection + 'static,
+    FPR: FogPubkeyResolver + Send + Sync + 'static,
+{
+    fn get_upgrade_readiness(&self) -> UpgradeReadiness {
+        if self.offline || self.peer_manager.is_empty() {
+            return assess_upgrade_readiness(&[], &BTreeMap::new());
+        }
+
+        let network_block_versions: Vec<u32> = self
+            .peer_manager
+            .conns()
+            .par_iter()
+            .filter_map(|conn| conn.fetch_block_info(empty()).ok())
+            .map(|block_info| block_info.network_block_version)
+            .collect();
+
+        assess_upgrade_readiness(&network_block_versions, &self.get_network_minimum_fees())
+    }
+
+    fn check_upgrade_readiness(&self) -> Result<UpgradeReadiness, UpgradeReadinessServiceError> {
+        let readiness = self.get_upgrade_readiness();
+
+        // Losing contact with the peers is not a change in readiness.
+        if readiness.status == UpgradeReadinessStatus::Unknown {
+            return Ok(readiness);
+        }
+
+        let conn = self.wallet_db.get_conn()?;
+        let previous = match WalletEvent::get_latest(EVENT_TYPE_UPGRADE_READINESS_CHANGED, &conn)? {
+            Some(event) => Some(serde_json::from_str::<UpgradeReadiness>(&event.payload)?),
+            None => None,
+        };
+
+        let changed = match &previous {
+            Some(previous) => {
+                previous.status != readiness.status || previous.warnings != readiness.warnings
+            }
+            // There is nothing to report until the first problem is found.
+            None => !readiness.warnings.is_empty(),
+        };
+
+        if changed {
+            for warning in &readiness.warnings {
+                log::warn!(self.logger, "{}", warning);
+            }
+            WalletEvent::create(
+                EVENT_TYPE_UPGRADE_READINESS_CHANGED,
+                &json!(readiness),
+                &conn,
+            )?;
+        }
+
+        Ok(readiness)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_assess_upgrade_readiness() {
+        let supported = *BlockVersion::MAX;
+        let mut fees = BTreeMap::new();
+        fees.insert(Mob::ID, Mob::MINIMUM_FEE);
+
+        let readiness = assess_upgrade_readiness(&[], &fees);
+        assert_eq!(readiness.status, UpgradeReadinessStatus::Unknown);
+        assert!(readiness.warnings.is_empty());
+
+        let readiness = assess_upgrade_readiness(&[supported, supported - 1], &fees);
+        assert_eq!(readiness.status, UpgradeReadinessStatus::Ready);
+        assert_eq!(readiness.lowest_network_block_version, Some(supported - 1));
+        assert_eq!(readiness.highest_network_block_version, Some(supported));
+        assert!(readiness.warnings.is_empty());
+
+        let readiness = assess_upgrade_readiness(&[supported, supported + 1], &fees);
+        assert_eq!(readiness.status, UpgradeReadinessStatus::UpgradeSoon);
+        assert_eq!(readiness.warnings.len(), 1);
+
+        let readiness = assess_upgrade_readiness(&[supported + 1, supported + 1], &fees);
+        assert_eq!(readiness.status, UpgradeReadinessStatus::Unsupported);
+        assert_eq!(readiness.warnings.len(), 1);
+
+        // A fee above the build's default is a warning, but doesn't change the
+        // status.
+        fees.insert(Mob::ID, Mob::MINIMUM_FEE * 2);
+        let readiness = assess_upgrade_readiness(&[supported], &fees);
+        assert_eq!(readiness.status, UpgradeReadinessStatus::Ready);
+        assert_eq!(readiness.warnings.len(), 1);
+    }
+}
@@ -0,0 +1,446 @@
+// Copyright (c) 2020-2022 MobileCoin Inc.
+
+//! Service for reporting how the wallet has been used over the last day and
+//! week, for operators without an external metrics stack.
+//!
+//! Unlike the Prometheus metrics, which start from zero each time full-service
+//! starts, the counters behind these reports are kept in the wallet database:
+//! API calls in hourly buckets, the sync thread's own records of the chunks it
+//! scans, and periodic samples of the databases' sizes. API calls are counted
+//! in memory and written to their buckets every RPC_USAGE_FLUSH_INTERVAL, so
+//! that serving a call doesn't also mean writing to the database.
+
+use crate::{
+    db::{
+        account_sync_record::AccountSyncRecordModel,
+        db_size_sample::DbSizeSampleModel,
+        models::{AccountSyncRecord, DbSizeSample, RpcUsage},
+        rpc_usage::{rpc_usage_hour_start, RpcUsageModel},
+        transaction, WalletDb, WalletDbError,
+    },
+    util::disk_space::database_size,
+    WalletService,
+};
+use chrono::Utc;
+use displaydoc::Display;
+use mc_connection::{BlockchainConnection, UserTxConnection};
+use mc_fog_report_validation::FogPubkeyResolver;
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// How often the API calls counted in memory are written to the database.
+/// Calls counted since the last write are lost if full-service stops.
+pub const RPC_USAGE_FLUSH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Errors for the Usage Stats Service.
+#[derive(Display, Debug)]
+#[allow(clippy::large_enum_variant)]
+pub enum UsageStatsServiceError {
+    /// Error interacting with the database: {0}
+    Database(WalletDbError),
+}
+
+impl From<WalletDbError> for UsageStatsServiceError {
+    fn from(src: WalletDbError) -> Self {
+        Self::Database(src)
+    }
+}
+
+/// The calls made to an API method within a window.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MethodUsage {
+    pub method: String,
+    pub calls: u64,
+    /// The calls which returned an error.
+    pub errors: u64,
+    pub total_duration_ms: u64,
+}
+
+impl MethodUsage {
+    /// The average time taken to handle a call.
+    pub fn average_latency_ms(&self) -> f64 {
+        self.total_duration_ms as f64 / self.calls.max(1) as f64
+    }
+}
+
+/// API calls counted since they were last written to the database, shared by
+/// every handle to the wallet service.
+#[derive(Clone)]
+pub struct RpcUsageCounters(Arc<Mutex<PendingRpcUsage>>);
+
+struct PendingRpcUsage {
+    /// The calls to each method, keyed by the start of their bucket and the
+    /// method.
+    buckets: BTreeMap<(i64, String), MethodUsage>,
+    last_flush: Instant,
+}
+
+impl Default for RpcUsageCounters {
+    fn default() -> Self {
+        Self(Arc::new(Mutex::new(PendingRpcUsage {
+            buckets: BTreeMap::new(),
+            last_flush: Instant::now(),
+        })))
+    }
+}
+
+impl RpcUsageCounters {
+    /// Count a call to a method made at `now`.
+    pub fn record(&self, method: &str, ok: bool, duration: Duration, now: i64) {
+        let mut pending = self.0.lock().expect("lock poisoned");
+        let usage = pending
+            .buckets
+            .entry((rpc_usage_hour_start(now), method.to_string()))
+            .or_insert_with(|| MethodUsage {
+                method: method.to_string(),
+                ..Default::default()
+            });
+        usage.calls += 1;
+        usage.errors += !ok as u64;
+        usage.total_duration_ms += duration.as_millis() as u64;
+    }
+
+    /// Take the counted calls to be written, if the interval has passed since
+    /// they were last taken.
+    fn take_due(&self, interval: Duration) -> Option<BTreeMap<(i64, String), MethodUsage>> {
+        let mut pending = self.0.lock().expect("lock poisoned");
+        if pending.last_flush.elapsed() < interval {
+            return None;
+        }
+        pending.last_flush = Instant::now();
+        Some(std::mem::take(&mut pending.buckets))
+    }
+
+    /// Put back calls which could not be written, to be written next time.
+    fn restore(&self, buckets: BTreeMap<(i64, String), MethodUsage>) {
+        let mut pending = self.0.lock().expect("lock poisoned");
+        for (key, usage) in buckets {
+            let pending_usage = pending.buckets.entry(key).or_insert_with(|| MethodUsage {
+                method: usage.method.clone(),
+                ..Default::default()
+            });
+            pending_usage.calls += usage.calls;
+            pending_usage.errors += usage.errors;
+            pending_usage.total_duration_ms += usage.total_duration_ms;
+        }
+    }
+
+    /// The counted calls in buckets starting at or after `hour_start`, in the
+    /// form they are stored in.
+    fn list_since(&self, hour_start: i64) -> Vec<RpcUsage> {
+        let pending = self.0.lock().expect("lock poisoned");
+        pending
+            .buckets
+            .iter()
+            .filter(|((bucket_start, _), _)| *bucket_start >= hour_start)
+            .map(|((bucket_start, method), usage)| RpcUsage {
+                id: 0,
+                hour_start: *bucket_start,
+                method: method.clone(),
+                calls: usage.calls as i64,
+                errors: usage.errors as i64,
+                total_duration_ms: usage.total_duration_ms as i64,
+            })
+            .collect()
+    }
+}
+
+/// How the wallet was used within a window ending now.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct UsageWindow {
+    /// The length of the window, in seconds.
+    pub period_seconds: i64,
+    /// The calls made to each method, busiest first.
+    pub methods: Vec<MethodUsage>,
+    /// The blocks the sync thread scanned, summed over every account.
+    pub blocks_synced: u64,
+    /// The time the sync thread spent scanning those blocks.
+    pub sync_duration_ms: u64,
+    /// How much the wallet database grew, or None without two samples of its
+    /// size in the window.
+    pub wallet_db_growth_bytes: Option<i64>,
+    /// How much the ledger database grew.
+    pub ledger_db_growth_bytes: Option<i64>,
+}
+
+impl UsageWindow {
+    /// The blocks scanned per second the sync thread was scanning, if it
+    /// scanned any.
+    pub fn sync_blocks_per_second(&self) -> Option<f64> {
+        if self.blocks_synced == 0 {
+            return None;
+        }
+        // Chunks of a block or two can finish within a millisecond.
+        Some(self.blocks_synced as f64 * 1000.0 / self.sync_duration_ms.max(1) as f64)
+    }
+}
+
+/// How the wallet was used over the last day and week.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct UsageStats {
+    pub last_24h: UsageWindow,
+    pub last_7d: UsageWindow,
+}
+
+/// Trait defining the ways in which the wallet can record and report its
+/// usage.
+pub trait UsageStatsService {
+    /// Count a call to an API method, writing the calls counted so far to the
+    /// database if RPC_USAGE_FLUSH_INTERVAL has passed since they were last
+    /// written and the wallet is not in maintenance.
+    fn record_rpc_call(
+        &self,
+        method: &str,
+        ok: bool,
+        duration: Duration,
+    ) -> Result<(), UsageStatsServiceError>;
+
+    /// Record the current sizes of the wallet and ledger databases.
+    fn sample_db_sizes(&self) -> Result<(), UsageStatsServiceError>;
+
+    /// Report API calls, sync throughput and database growth over the last
+    /// 24 hours and 7 days.
+    fn get_usage_stats(&self) -> Result<UsageStats, UsageStatsServiceError>;
+}
+
+impl<T, FPR> UsageStatsService for WalletService<T, FPR>
+where
+    T: BlockchainConnection + UserTxConnection + 'static,
+    FPR: FogPubkeyResolver + Send + Sync + 'static,
+{
+    fn record_rpc_call(
+        &self,
+        method: &str,
+        ok: bool,
+        duration: Duration,
+    ) -> Result<(), UsageStatsServiceError> {
+        self.rpc_usage
+            .record(method, ok, duration, Utc::now().timestamp());
+        // The database is left alone during maintenance, so the calls are
+        // kept until it ends.
+        if self.maintenance_mode.is_active() {
+            return Ok(());
+        }
+        let buckets = match self.rpc_usage.take_due(RPC_USAGE_FLUSH_INTERVAL) {
+            Some(buckets) if !buckets.is_empty() => buckets,
+            _ => return Ok(()),
+        };
+        let written = self.wallet_db.get_conn().and_then(|conn| {
+            transaction(&conn, || {
+                for ((hour_start, method), usage) in &buckets {
+                    RpcUsage::record(
+                        method,
+                        usage.calls as i64,
+                        usage.errors as i64,
+                        usage.total_duration_ms as i64,
+                        *hour_start,
+                        &conn,
+                    )?;
+                }
+                Ok::<(), WalletDbError>(())
+            })
+        });
+        if written.is_err() {
+            self.rpc_usage.restore(buckets);
+        }
+        written?;
+        Ok(())
+    }
+
+    fn sample_db_sizes(&self) -> Result<(), UsageStatsServiceError> {
+        let conn = self.wallet_db.get_conn()?;
+        let wallet_db_bytes =
+            WalletDb::database_path(&conn)?.and_then(|path| database_size(&path).ok());
+        let ledger_db_bytes = self
+            .ledger_db_path
+            .as_ref()
+            .and_then(|path| database_size(path).ok());
+        DbSizeSample::create(
+            wallet_db_bytes,
+            ledger_db_bytes,
+            Utc::now().timestamp(),
+            &conn,
+        )?;
+        Ok(())
+    }
+
+    fn get_usage_stats(&self) -> Result<UsageStats, UsageStatsServiceError> {
+        let conn = self.wallet_db.get_conn()?;
+        let now = Utc::now().timestamp();
+        let usage_window = |period_seconds: i64| -> Result<UsageWindow, WalletDbError> {
+            let since = now - period_seconds;
+            // Calls not yet written to the database are counted too.
+            let mut rpc_usage = RpcUsage::list_since(since, &conn)?;
+            rpc_usage.extend(self.rpc_usage.list_since(since));
+            Ok(summarize_window(
+                period_seconds,
+                &rpc_usage,
+                &AccountSyncRecord::list_since(since, &conn)?,
+                &DbSizeSample::list_since(since, &conn)?,
+            ))
+        };
+
+        Ok(UsageStats {
+            last_24h: usage_window(24 * 60 * 60)?,
+            last_7d: usage_window(7 * 24 * 60 * 60)?,
+        })
+    }
+}
+
+/// Sum the usage recorded within a window.
+pub fn summarize_window(
+    period_seconds: i64,
+    rpc_usage: &[RpcUsage],
+    sync_records: &[AccountSyncRecord],
+    db_size_samples: &[DbSizeSample],
+) -> UsageWindow {
+    let mut methods: BTreeMap<&str, MethodUsage> = BTreeMap::new();
+    for bucket in rpc_usage {
+        let usage = methods
+            .entry(bucket.method.as_str())
+            .or_insert_with(|| MethodUsage {
+                method: bucket.method.clone(),
+                ..Default::default()
+            });
+        usage.calls += bucket.calls as u64;
+        usage.errors += bucket.errors as u64;
+        usage.total_duration_ms += bucket.total_duration_ms as u64;
+    }
+    let mut methods: Vec<MethodUsage> = methods.into_values().collect();
+    methods.sort_by(|a, b| b.calls.cmp(&a.calls));
+
+    // The growth between the first and last samples which measured the
+    // database.
+    let growth = |bytes: fn(&DbSizeSample) -> Option<i64>| {
+        let mut measured = db_size_samples.iter().filter_map(bytes);
+        let first = measured.next()?;
+        measured.last().map(|last| last - first)
+    };
+
+    UsageWindow {
+        period_seconds,
+        methods,
+        blocks_synced: sync_records.iter().map(|record| record.num_blocks()).sum(),
+        sync_duration_ms: sync_records
+            .iter()
+            .map(|record| record.duration_ms as u64)
+            .sum(),
+        wallet_db_growth_bytes: growth(|sample| sample.wallet_db_bytes),
+        ledger_db_growth_bytes: growth(|sample| sample.ledger_db_bytes),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::rpc_usage::RPC_USAGE_BUCKET_SECONDS;
+
+    fn bucket(method: &str, calls: i64, errors: i64, total_duration_ms: i64) -> RpcUsage {
+        bucket_at(0, method, calls, errors, total_duration_ms)
+    }
+
+    fn bucket_at(
+        hour_start: i64,
+        method: &str,
+        calls: i64,
+        errors: i64,
+        total_duration_ms: i64,
+    ) -> RpcUsage {
+        RpcUsage {
+            id: 0,
+            hour_start,
+            method: method.to_string(),
+            calls,
+            errors,
+            total_duration_ms,
+        }
+    }
+
+    fn sample(wallet_db_bytes: Option<i64>, ledger_db_bytes: Option<i64>) -> DbSizeSample {
+        DbSizeSample {
+            id: 0,
+            wallet_db_bytes,
+            ledger_db_bytes,
+            created_time: 0,
+        }
+    }
+
+    #[test]
+    fn test_rpc_usage_counters() {
+        let counters = RpcUsageCounters::default();
+        let hour = RPC_USAGE_BUCKET_SECONDS;
+        counters.record("version", true, Duration::from_millis(5), hour);
+        counters.record("version", false, Duration::from_millis(7), hour + 10);
+        counters.record("version", true, Duration::from_millis(2), 2 * hour);
+
+        let buckets = counters.list_since(0);
+        assert_eq!(buckets.len(), 2);
+        assert_eq!(buckets[0], bucket_at(hour, "version", 2, 1, 12));
+        assert_eq!(counters.list_since(2 * hour).len(), 1);
+
+        // Nothing is taken to be written until the flush interval has passed,
+        // and calls which couldn't be written are counted again.
+        assert!(counters.take_due(RPC_USAGE_FLUSH_INTERVAL).is_none());
+        let taken = counters.take_due(Duration::from_secs(0)).unwrap();
+        assert_eq!(taken.len(), 2);
+        assert!(counters.list_since(0).is_empty());
+        assert!(counters.take_due(RPC_USAGE_FLUSH_INTERVAL).is_none());
+
+        counters.record("version", true, Duration::from_millis(1), hour + 20);
+        counters.restore(taken);
+        assert_eq!(
+            counters.list_since(0)[0],
+            bucket_at(hour, "version", 3, 1, 13)
+        );
+    }
+
+    #[test]
+    fn test_summarize_window() {
+        let sync_record = AccountSyncRecord {
+            id: 0,
+            account_id_hex: "abcd".to_string(),
+            start_block_index: 0,
+            end_block_index: 999,
+            txos_received: 0,
+            txos_spent: 0,
+            duration_ms: 500,
+            created_time: 0,
+        };
+        let window = summarize_window(
+            3600,
+            &[
+                bucket("get_txo", 1, 0, 3),
+                bucket("version", 2, 1, 10),
+                bucket("version", 3, 0, 5),
+            ],
+            &[sync_record.clone(), sync_record],
+            &[
+                sample(Some(100), None),
+                sample(Some(150), Some(1000)),
+                sample(Some(130), None),
+            ],
+        );
+
+        // Buckets are summed for each method, busiest first.
+        assert_eq!(window.methods.len(), 2);
+        assert_eq!(window.methods[0].method, "version");
+        assert_eq!(window.methods[0].calls, 5);
+        assert_eq!(window.methods[0].errors, 1);
+        assert_eq!(window.methods[0].average_latency_ms(), 3.0);
+        assert_eq!(window.methods[1].method, "get_txo");
+
+        assert_eq!(window.blocks_synced, 2000);
+        assert_eq!(window.sync_blocks_per_second(), Some(2000.0));
+
+        // Growth needs two measurements, and can be negative after a vacuum.
+        assert_eq!(window.wallet_db_growth_bytes, Some(30));
+        assert_eq!(window.ledger_db_growth_bytes, None);
+
+        let empty = summarize_window(3600, &[], &[], &[]);
+        assert!(empty.methods.is_empty());
+        assert_eq!(empty.sync_blocks_per_second(), None);
+    }
+}
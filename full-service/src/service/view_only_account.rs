@@ -8,14 +8,70 @@ use crate::{
         transaction,
         view_only_account::ViewOnlyAccountModel,
         view_only_subaddress::ViewOnlySubaddressModel,
+        Conn, WalletDbError,
     },
     service::{account::AccountServiceError, WalletService},
+    util::b58::b58_decode_public_address,
 };
-use mc_common::logger::log;
+use displaydoc::Display;
+use mc_common::{logger::log, HashMap};
 use mc_connection::{BlockchainConnection, UserTxConnection};
 use mc_crypto_keys::{RistrettoPrivate, RistrettoPublic};
 use mc_fog_report_validation::FogPubkeyResolver;
 use mc_ledger_db::Ledger;
+use mc_transaction_core::onetime_keys::create_shared_secret;
+use serde::Serialize;
+use std::fmt;
+
+/// Why a subaddress can't be imported to a view-only account.
+#[derive(Display, Debug, Clone, PartialEq)]
+pub enum SubaddressImportErrorReason {
+    /// Subaddress index is also given by entry {0}
+    DuplicateIndex(usize),
+
+    /// Address is also given by entry {0}
+    DuplicateAddress(usize),
+
+    /// Subaddress index is already imported as {0}
+    IndexInUse(String),
+
+    /// Address is already imported to account {0} at subaddress index {1}
+    AddressInUse(String, u64),
+
+    /// Could not decode address: {0}
+    InvalidAddress(String),
+
+    /// Public spend key does not match the address
+    SpendKeyMismatch,
+
+    /// Address was not derived from the account's view key
+    ViewKeyMismatch,
+}
+
+/// A subaddress which can't be imported, and why.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SubaddressImportError {
+    /// The position of the subaddress in the import.
+    pub entry: usize,
+    pub public_address: String,
+    pub subaddress_index: u64,
+    pub reason: String,
+}
+
+/// All of the subaddresses in an import which can't be imported.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SubaddressImportErrors(pub Vec<SubaddressImportError>);
+
+impl fmt::Display for SubaddressImportErrors {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let entries: Vec<String> = self
+            .0
+            .iter()
+            .map(|error| format!("entry {}: {}", error.entry, error.reason))
+            .collect();
+        write!(f, "{}", entries.join("; "))
+    }
+}
 
 /// Trait defining the ways in which the wallet can interact with and manage
 /// view-only accounts.
@@ -55,6 +111,14 @@ pub trait ViewOnlyAccountService {
         name: &str,
     ) -> Result<ViewOnlyAccount, AccountServiceError>;
 
+    /// Mark whether a view only account's spend key is on a hardware wallet,
+    /// so that its transactions are signed by the transaction signer.
+    fn update_view_only_account_hardware_backed(
+        &self,
+        account_id: &str,
+        hardware_backed: bool,
+    ) -> Result<ViewOnlyAccount, AccountServiceError>;
+
     /// Remove a view only account from the wallet.
     fn remove_view_only_account(&self, account_id: &str) -> Result<bool, AccountServiceError>;
 }
@@ -91,6 +155,7 @@ where
                 name,
                 conn,
             )?;
+            validate_subaddress_imports(&view_only_account, &subaddresses, conn)?;
 
             for (public_address_b58, subaddress_index, comment, public_spend_key) in
                 subaddresses.iter()
@@ -118,7 +183,10 @@ where
 
         transaction(conn, || {
             let account = ViewOnlyAccount::get(account_id_hex, conn)?;
+            validate_subaddress_imports(&account, &subaddresses, conn)?;
 
+            // Subaddresses which were already imported, at the same index, are
+            // skipped, so that the signer can resend them.
             for (public_address_b58, subaddress_index, comment, public_spend_key) in
                 subaddresses.iter()
             {
@@ -178,6 +246,16 @@ where
         Ok(ViewOnlyAccount::get(account_id, &conn)?)
     }
 
+    fn update_view_only_account_hardware_backed(
+        &self,
+        account_id: &str,
+        hardware_backed: bool,
+    ) -> Result<ViewOnlyAccount, AccountServiceError> {
+        let conn = self.wallet_db.get_conn()?;
+        ViewOnlyAccount::get(account_id, &conn)?.update_hardware_backed(hardware_backed, &conn)?;
+        Ok(ViewOnlyAccount::get(account_id, &conn)?)
+    }
+
     fn remove_view_only_account(&self, account_id: &str) -> Result<bool, AccountServiceError> {
         log::info!(self.logger, "Deleting view only account {}", account_id,);
 
@@ -190,6 +268,103 @@ where
     }
 }
 
+/// Check every subaddress in an import before any are written, so that an
+/// import is all or nothing.
+///
+/// Re-importing a subaddress exactly as it was imported before is allowed.
+fn validate_subaddress_imports(
+    account: &ViewOnlyAccount,
+    subaddresses: &[(String, u64, String, RistrettoPublic)],
+    conn: &Conn,
+) -> Result<(), AccountServiceError> {
+    let view_private_key: RistrettoPrivate = mc_util_serial::decode(&account.view_private_key)?;
+    let existing_by_index: HashMap<u64, String> =
+        ViewOnlySubaddress::list_all(&account.account_id_hex, None, None, conn)?
+            .into_iter()
+            .map(|subaddress| {
+                (
+                    subaddress.subaddress_index as u64,
+                    subaddress.public_address_b58,
+                )
+            })
+            .collect();
+
+    let mut first_entry_by_index: HashMap<u64, usize> = HashMap::default();
+    let mut first_entry_by_address: HashMap<&str, usize> = HashMap::default();
+    let mut errors = Vec::new();
+
+    for (entry, (public_address_b58, subaddress_index, _comment, public_spend_key)) in
+        subaddresses.iter().enumerate()
+    {
+        let mut reasons = Vec::new();
+
+        let first_entry = *first_entry_by_index
+            .entry(*subaddress_index)
+            .or_insert(entry);
+        if first_entry != entry {
+            reasons.push(SubaddressImportErrorReason::DuplicateIndex(first_entry));
+        }
+        let first_entry = *first_entry_by_address
+            .entry(public_address_b58.as_str())
+            .or_insert(entry);
+        if first_entry != entry {
+            reasons.push(SubaddressImportErrorReason::DuplicateAddress(first_entry));
+        }
+
+        if let Some(existing_address) = existing_by_index.get(subaddress_index) {
+            if existing_address != public_address_b58 {
+                reasons.push(SubaddressImportErrorReason::IndexInUse(
+                    existing_address.clone(),
+                ));
+            }
+        }
+        match ViewOnlySubaddress::get(public_address_b58, conn) {
+            Ok(existing) => {
+                if existing.view_only_account_id_hex != account.account_id_hex
+                    || existing.subaddress_index as u64 != *subaddress_index
+                {
+                    reasons.push(SubaddressImportErrorReason::AddressInUse(
+                        existing.view_only_account_id_hex,
+                        existing.subaddress_index as u64,
+                    ));
+                }
+            }
+            Err(WalletDbError::AssignedSubaddressNotFound(_)) => {}
+            Err(e) => return Err(e.into()),
+        }
+
+        // A subaddress's view public key is the account's view private key
+        // times its spend public key.
+        match b58_decode_public_address(public_address_b58) {
+            Ok(public_address) => {
+                if public_address.spend_public_key() != public_spend_key {
+                    reasons.push(SubaddressImportErrorReason::SpendKeyMismatch);
+                } else if *public_address.view_public_key()
+                    != create_shared_secret(public_spend_key, &view_private_key)
+                {
+                    reasons.push(SubaddressImportErrorReason::ViewKeyMismatch);
+                }
+            }
+            Err(e) => reasons.push(SubaddressImportErrorReason::InvalidAddress(e.to_string())),
+        }
+
+        errors.extend(reasons.into_iter().map(|reason| SubaddressImportError {
+            entry,
+            public_address: public_address_b58.clone(),
+            subaddress_index: *subaddress_index,
+            reason: reason.to_string(),
+        }));
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(AccountServiceError::InvalidSubaddresses(
+            SubaddressImportErrors(errors),
+        ))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -266,6 +441,7 @@ mod tests {
             main_subaddress_index: DEFAULT_SUBADDRESS_INDEX as i64,
             change_subaddress_index: CHANGE_SUBADDRESS_INDEX as i64,
             next_subaddress_index: 2,
+            hardware_backed: false,
         };
 
         let gotten_account = service
@@ -288,4 +464,120 @@ mod tests {
         let not_found = service.get_view_only_account(&account_id.to_string());
         assert!(not_found.is_err());
     }
+
+    #[test_with_logger]
+    fn service_import_subaddresses_validation(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+        let ledger_db = get_test_ledger(5, &vec![], 12, &mut rng);
+        let service = setup_wallet_service(ledger_db.clone(), logger.clone());
+
+        let account_key = AccountKey::random(&mut rng);
+        let account_id = AccountID::from(&account_key).to_string();
+        let subaddress = |index: u64| {
+            let public_address = account_key.subaddress(index);
+            (
+                b58_encode_public_address(&public_address).unwrap(),
+                index,
+                "".to_string(),
+                *public_address.spend_public_key(),
+            )
+        };
+
+        service
+            .import_view_only_account(
+                &account_id,
+                account_key.view_private_key(),
+                DEFAULT_SUBADDRESS_INDEX,
+                CHANGE_SUBADDRESS_INDEX,
+                2,
+                "",
+                vec![
+                    subaddress(DEFAULT_SUBADDRESS_INDEX),
+                    subaddress(CHANGE_SUBADDRESS_INDEX),
+                ],
+            )
+            .unwrap();
+
+        // An address from another account, and an address given with the
+        // spend key of another subaddress.
+        let other_address = AccountKey::random(&mut rng).subaddress(3);
+        let foreign = (
+            b58_encode_public_address(&other_address).unwrap(),
+            3,
+            "".to_string(),
+            *other_address.spend_public_key(),
+        );
+        let mut mismatched = subaddress(4);
+        mismatched.3 = subaddress(5).3;
+        // Index 0 is already imported as a different address.
+        let mut index_in_use = subaddress(6);
+        index_in_use.1 = DEFAULT_SUBADDRESS_INDEX;
+
+        let result = service.import_subaddresses(
+            &account_id,
+            vec![
+                subaddress(2),
+                subaddress(2),
+                foreign,
+                mismatched,
+                index_in_use,
+            ],
+        );
+        let errors = match result {
+            Err(AccountServiceError::InvalidSubaddresses(errors)) => errors.0,
+            other => panic!("unexpected result {:?}", other),
+        };
+        let entries: Vec<(usize, String)> =
+            errors.iter().map(|e| (e.entry, e.reason.clone())).collect();
+        assert_eq!(
+            entries,
+            vec![
+                (
+                    1,
+                    SubaddressImportErrorReason::DuplicateIndex(0).to_string()
+                ),
+                (
+                    1,
+                    SubaddressImportErrorReason::DuplicateAddress(0).to_string()
+                ),
+                (2, SubaddressImportErrorReason::ViewKeyMismatch.to_string()),
+                (3, SubaddressImportErrorReason::SpendKeyMismatch.to_string()),
+                (
+                    4,
+                    SubaddressImportErrorReason::IndexInUse(subaddress(0).0).to_string()
+                ),
+            ]
+        );
+
+        // Nothing was imported.
+        let conn = service.wallet_db.get_conn().unwrap();
+        assert_eq!(
+            ViewOnlySubaddress::list_all(&account_id, None, None, &conn)
+                .unwrap()
+                .len(),
+            2
+        );
+        assert_eq!(
+            service
+                .get_view_only_account(&account_id)
+                .unwrap()
+                .next_subaddress_index,
+            2
+        );
+
+        // Resending subaddresses which were already imported is fine.
+        let imported = service
+            .import_subaddresses(
+                &account_id,
+                vec![subaddress(DEFAULT_SUBADDRESS_INDEX), subaddress(2)],
+            )
+            .unwrap();
+        assert_eq!(imported.len(), 2);
+        assert_eq!(
+            ViewOnlySubaddress::list_all(&account_id, None, None, &conn)
+                .unwrap()
+                .len(),
+            3
+        );
+    }
 }
@@ -3,11 +3,20 @@
 //! Service for managing view-only Txos.
 
 use crate::{
-    db::{models::ViewOnlyTxo, transaction, view_only_txo::ViewOnlyTxoModel},
+    db::{
+        models::{ViewOnlyAccount, ViewOnlySyncRecord, ViewOnlyTxo},
+        transaction,
+        view_only_account::ViewOnlyAccountModel,
+        view_only_sync_record::ViewOnlySyncRecordModel,
+        view_only_txo::ViewOnlyTxoModel,
+    },
     service::txo::TxoServiceError,
+    sync_proof::ViewOnlySyncProof,
+    util::pagination::Page,
     WalletService,
 };
 use mc_connection::{BlockchainConnection, UserTxConnection};
+use mc_crypto_digestible::{Digestible, MerlinTranscript};
 use mc_fog_report_validation::FogPubkeyResolver;
 use mc_ledger_db::Ledger;
 use mc_transaction_core::{ring_signature::KeyImage, tx::TxOut};
@@ -23,6 +32,27 @@ pub trait ViewOnlyTxoService {
         offset: Option<u64>,
     ) -> Result<Vec<ViewOnlyTxo>, TxoServiceError>;
 
+    /// Get a page of the Txos for a given account in the wallet, in insertion
+    /// order, starting after the txo with the given cursor, or after skipping
+    /// `offset` txos.
+    fn list_view_only_txos_page(
+        &self,
+        account_id: &str,
+        cursor: Option<i32>,
+        offset: Option<u64>,
+        limit: u64,
+    ) -> Result<Page<ViewOnlyTxo>, TxoServiceError>;
+
+    /// Get a page of the Txos whose key images are known, of a view only
+    /// account or of every view only account, starting after the txo with the
+    /// given cursor.
+    fn export_view_only_key_images(
+        &self,
+        account_id: Option<&str>,
+        cursor: Option<i32>,
+        limit: u64,
+    ) -> Result<Page<ViewOnlyTxo>, TxoServiceError>;
+
     /// update the key image for a list of txos
     fn set_view_only_txos_key_images(
         &self,
@@ -33,6 +63,53 @@ pub trait ViewOnlyTxoService {
         &self,
         account_id: &str,
     ) -> Result<Vec<TxOut>, TxoServiceError>;
+
+    /// List the txos the offline signer needs to complete, and record the
+    /// request in the account's sync audit trail.
+    fn create_view_only_account_sync_request(
+        &self,
+        account_id: &str,
+    ) -> Result<Vec<TxOut>, TxoServiceError>;
+
+    /// Record the offline signer's response to a sync request in the
+    /// account's sync audit trail.
+    fn record_view_only_account_sync_response(
+        &self,
+        account_id: &str,
+        txo_ids_and_key_images: &[(String, KeyImage)],
+    ) -> Result<ViewOnlySyncRecord, TxoServiceError>;
+
+    /// List the sync audit trail for an account, oldest first.
+    fn list_view_only_account_sync_records(
+        &self,
+        account_id: &str,
+        offset: Option<u64>,
+        limit: Option<u64>,
+    ) -> Result<Vec<ViewOnlySyncRecord>, TxoServiceError>;
+
+    /// Create a proof of the blocks the account has been scanned over, and
+    /// the txos found in them, which the offline signer can check to be sure
+    /// that no received txos were left out.
+    ///
+    /// The proof starts at the given block, or the account's first block, and
+    /// covers at most MAX_SYNC_PROOF_BLOCKS blocks.
+    fn create_view_only_account_sync_proof(
+        &self,
+        account_id: &str,
+        first_block_index: Option<u64>,
+        block_count: Option<u64>,
+    ) -> Result<ViewOnlySyncProof, TxoServiceError>;
+}
+
+/// The most blocks a single sync proof covers, to keep proofs a manageable
+/// size. Longer ranges are covered by several proofs.
+pub const MAX_SYNC_PROOF_BLOCKS: u64 = 1000;
+
+/// The contents of a sync response, digested for the audit trail.
+#[derive(Digestible)]
+struct SyncResponseContents {
+    txo_ids: Vec<String>,
+    key_images: Vec<KeyImage>,
 }
 
 impl<T, FPR> ViewOnlyTxoService for WalletService<T, FPR>
@@ -56,6 +133,42 @@ where
         )?)
     }
 
+    fn list_view_only_txos_page(
+        &self,
+        account_id: &str,
+        cursor: Option<i32>,
+        offset: Option<u64>,
+        limit: u64,
+    ) -> Result<Page<ViewOnlyTxo>, TxoServiceError> {
+        let conn = self.wallet_db.get_conn()?;
+        // Fetch one more txo than the page holds, to tell whether more follow.
+        let txos = ViewOnlyTxo::list_page_for_account(
+            account_id,
+            cursor,
+            offset,
+            limit.saturating_add(1),
+            Some(0),
+            &conn,
+        )?;
+        Ok(Page::from_overfetched(txos, limit, |txo| txo.id))
+    }
+
+    fn export_view_only_key_images(
+        &self,
+        account_id: Option<&str>,
+        cursor: Option<i32>,
+        limit: u64,
+    ) -> Result<Page<ViewOnlyTxo>, TxoServiceError> {
+        let conn = self.wallet_db.get_conn()?;
+        if let Some(account_id) = account_id {
+            ViewOnlyAccount::get(account_id, &conn)?;
+        }
+        // Fetch one more txo than the page holds, to tell whether more follow.
+        let txos =
+            ViewOnlyTxo::list_key_images_page(account_id, cursor, limit.saturating_add(1), &conn)?;
+        Ok(Page::from_overfetched(txos, limit, |txo| txo.id))
+    }
+
     fn set_view_only_txos_key_images(
         &self,
         txo_ids_and_key_images: Vec<(String, KeyImage)>,
@@ -86,6 +199,108 @@ where
 
         Ok(ViewOnlyTxo::export_txouts_without_key_image_or_subaddress_index(account_id, &conn)?)
     }
+
+    fn create_view_only_account_sync_request(
+        &self,
+        account_id: &str,
+    ) -> Result<Vec<TxOut>, TxoServiceError> {
+        let conn = self.wallet_db.get_conn()?;
+
+        transaction(&conn, || {
+            ViewOnlyAccount::get(account_id, &conn)?;
+            let txos = ViewOnlyTxo::export_txouts_without_key_image_or_subaddress_index(
+                account_id, &conn,
+            )?;
+            let payload_hash = txos.digest32::<MerlinTranscript>(b"view_only_sync_request");
+            ViewOnlySyncRecord::create_request(
+                account_id,
+                &hex::encode(payload_hash),
+                txos.len(),
+                &conn,
+            )?;
+            Ok(txos)
+        })
+    }
+
+    fn record_view_only_account_sync_response(
+        &self,
+        account_id: &str,
+        txo_ids_and_key_images: &[(String, KeyImage)],
+    ) -> Result<ViewOnlySyncRecord, TxoServiceError> {
+        let conn = self.wallet_db.get_conn()?;
+
+        let contents = SyncResponseContents {
+            txo_ids: txo_ids_and_key_images
+                .iter()
+                .map(|(txo_id, _)| txo_id.clone())
+                .collect(),
+            key_images: txo_ids_and_key_images
+                .iter()
+                .map(|(_, key_image)| *key_image)
+                .collect(),
+        };
+        let payload_hash = contents.digest32::<MerlinTranscript>(b"view_only_sync_response");
+
+        transaction(&conn, || {
+            Ok(ViewOnlySyncRecord::create_response(
+                account_id,
+                &hex::encode(payload_hash),
+                txo_ids_and_key_images.len(),
+                &conn,
+            )?)
+        })
+    }
+
+    fn list_view_only_account_sync_records(
+        &self,
+        account_id: &str,
+        offset: Option<u64>,
+        limit: Option<u64>,
+    ) -> Result<Vec<ViewOnlySyncRecord>, TxoServiceError> {
+        let conn = self.wallet_db.get_conn()?;
+        Ok(ViewOnlySyncRecord::list_for_account(
+            account_id, offset, limit, &conn,
+        )?)
+    }
+
+    fn create_view_only_account_sync_proof(
+        &self,
+        account_id: &str,
+        first_block_index: Option<u64>,
+        block_count: Option<u64>,
+    ) -> Result<ViewOnlySyncProof, TxoServiceError> {
+        let conn = self.wallet_db.get_conn()?;
+        let account = ViewOnlyAccount::get(account_id, &conn)?;
+
+        let next_block_index = account.next_block_index as u64;
+        let first_block_index = first_block_index.unwrap_or(account.first_block_index as u64);
+        if first_block_index >= next_block_index {
+            return Err(TxoServiceError::BlockNotScanned(next_block_index));
+        }
+        let block_count = block_count
+            .unwrap_or(MAX_SYNC_PROOF_BLOCKS)
+            .min(MAX_SYNC_PROOF_BLOCKS)
+            .min(next_block_index - first_block_index)
+            .max(1);
+        let last_block_index = first_block_index + block_count - 1;
+
+        let blocks = (first_block_index..=last_block_index)
+            .map(|block_index| {
+                Ok((
+                    self.ledger_db.get_block(block_index)?,
+                    self.ledger_db.get_block_contents(block_index)?,
+                ))
+            })
+            .collect::<Result<Vec<_>, TxoServiceError>>()?;
+        let found_txo_ids = ViewOnlyTxo::list_ids_received_in_block_range(
+            account_id,
+            first_block_index,
+            last_block_index,
+            &conn,
+        )?;
+
+        Ok(ViewOnlySyncProof::new(account_id, &blocks, &found_txo_ids))
+    }
 }
 
 #[cfg(test)]
@@ -94,7 +309,11 @@ mod tests {
     use crate::{
         db::account::AccountID,
         service::view_only_account::ViewOnlyAccountService,
-        test_utils::{add_block_to_ledger_db, get_test_ledger, setup_wallet_service, MOB},
+        sync_proof::SyncProofError,
+        test_utils::{
+            add_block_to_ledger_db, get_test_ledger, manually_sync_view_only_account,
+            setup_wallet_service, MOB,
+        },
         util::b58::b58_encode_public_address,
     };
     use mc_account_keys::{
@@ -201,7 +420,9 @@ mod tests {
 
         let input_vec = [(txo_id_1, key_image_1), (txo_id_2, key_image_2)].to_vec();
 
-        service.set_view_only_txos_key_images(input_vec).unwrap();
+        service
+            .set_view_only_txos_key_images(input_vec.clone())
+            .unwrap();
 
         let txos = service
             .list_view_only_txos(&account.account_id_hex, None, None)
@@ -215,5 +436,111 @@ mod tests {
                 assert_eq!(txo.spent_block_index, None);
             }
         }
+
+        // Each sync round is kept in the audit trail, with the response linked
+        // to the request it answered.
+        let incomplete_txos = service
+            .create_view_only_account_sync_request(&account.account_id_hex)
+            .unwrap();
+        let response = service
+            .record_view_only_account_sync_response(&account.account_id_hex, &input_vec)
+            .unwrap();
+        let records = service
+            .list_view_only_account_sync_records(&account.account_id_hex, None, None)
+            .unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].txo_count as usize, incomplete_txos.len());
+        assert_eq!(records[1], response);
+        assert_eq!(response.request_record_id, Some(records[0].id));
+        assert_eq!(response.txo_count, 2);
+        assert_ne!(records[0].payload_hash, response.payload_hash);
+    }
+
+    #[test_with_logger]
+    fn test_view_only_account_sync_proof(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let view_private_key = RistrettoPrivate::from_random(&mut rng);
+        let spend_private_key = RistrettoPrivate::from_random(&mut rng);
+        let account_key = AccountKey::new(&spend_private_key, &view_private_key);
+        let account_id = AccountID::from(&account_key);
+        let main_public_address = account_key.default_subaddress();
+
+        // The account receives a txo in every block.
+        let ledger_db = get_test_ledger(5, &[main_public_address.clone()], 12, &mut rng);
+        let service = setup_wallet_service(ledger_db.clone(), logger.clone());
+
+        service
+            .import_view_only_account(
+                &account_id.to_string(),
+                &view_private_key,
+                DEFAULT_SUBADDRESS_INDEX,
+                CHANGE_SUBADDRESS_INDEX,
+                2,
+                "testing",
+                vec![(
+                    b58_encode_public_address(&main_public_address).unwrap(),
+                    DEFAULT_SUBADDRESS_INDEX,
+                    "Main".to_string(),
+                    *main_public_address.spend_public_key(),
+                )],
+            )
+            .unwrap();
+        manually_sync_view_only_account(
+            &ledger_db,
+            &service.wallet_db,
+            &account_id.to_string(),
+            &logger,
+        );
+
+        let proof = service
+            .create_view_only_account_sync_proof(&account_id.to_string(), None, None)
+            .unwrap();
+        assert_eq!(proof.blocks_encoded.len(), 12);
+        assert_eq!(proof.found_txo_ids.len(), 12);
+        let verification = proof.verify(&view_private_key).unwrap();
+        assert!(verification.is_complete());
+        assert_eq!(verification.first_block_index, 0);
+        assert_eq!(verification.last_block_index, 11);
+
+        // A proof can cover part of the scanned blocks.
+        let partial_proof = service
+            .create_view_only_account_sync_proof(&account_id.to_string(), Some(3), Some(4))
+            .unwrap();
+        assert_eq!(partial_proof.found_txo_ids.len(), 4);
+        let verification = partial_proof.verify(&view_private_key).unwrap();
+        assert!(verification.is_complete());
+        assert_eq!(verification.first_block_index, 3);
+        assert_eq!(verification.last_block_index, 6);
+
+        // Blocks which haven't been scanned can't be proven.
+        assert!(matches!(
+            service.create_view_only_account_sync_proof(&account_id.to_string(), Some(12), None),
+            Err(TxoServiceError::BlockNotScanned(12))
+        ));
+
+        // Leaving out a received txo is caught.
+        let mut hiding_proof = proof.clone();
+        let hidden_txo_id = hiding_proof.found_txo_ids.remove(5);
+        let verification = hiding_proof.verify(&view_private_key).unwrap();
+        assert!(!verification.is_complete());
+        assert_eq!(verification.missing_txo_ids, vec![hidden_txo_id]);
+
+        // As is leaving out a block.
+        let mut gap_proof = proof.clone();
+        gap_proof.blocks_encoded.remove(5);
+        gap_proof.block_contents_encoded.remove(5);
+        assert!(matches!(
+            gap_proof.verify(&view_private_key),
+            Err(SyncProofError::BrokenChain(6))
+        ));
+
+        // Or swapping the contents of a block.
+        let mut swapped_proof = proof;
+        swapped_proof.block_contents_encoded.swap(2, 3);
+        assert!(matches!(
+            swapped_proof.verify(&view_private_key),
+            Err(SyncProofError::InvalidContents(2))
+        ));
     }
 }
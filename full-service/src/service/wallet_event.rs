@@ -0,0 +1,56 @@
+// Copyright (c) 2020-2022 MobileCoin Inc.
+
+//! Service for reading the wallet event log.
+
+use crate::{
+    db::{models::WalletEvent, wallet_event::WalletEventModel, WalletDbError},
+    WalletService,
+};
+use mc_connection::{BlockchainConnection, UserTxConnection};
+use mc_fog_report_validation::FogPubkeyResolver;
+
+/// Trait defining the ways in which the wallet can interact with and manage
+/// wallet events.
+pub trait WalletEventService {
+    /// List events in the order they were recorded, starting after the event
+    /// with the given id, so that callers can follow the log.
+    fn get_wallet_events(
+        &self,
+        after_event_id: Option<i32>,
+        limit: Option<u64>,
+    ) -> Result<Vec<WalletEvent>, WalletDbError>;
+
+    /// Replay the events with ids from `from_event_id` through `to_event_id`
+    /// inclusive, or through the latest event if `to_event_id` is not given.
+    /// Events are stored as recorded, so a replay always returns the same
+    /// events with the same payloads.
+    fn replay_wallet_events(
+        &self,
+        from_event_id: i32,
+        to_event_id: Option<i32>,
+    ) -> Result<Vec<WalletEvent>, WalletDbError>;
+}
+
+impl<T, FPR> WalletEventService for WalletService<T, FPR>
+where
+    T: BlockchainConnection + UserTxConnection + 'static,
+    FPR: FogPubkeyResolver + Send + Sync + 'static,
+{
+    fn get_wallet_events(
+        &self,
+        after_event_id: Option<i32>,
+        limit: Option<u64>,
+    ) -> Result<Vec<WalletEvent>, WalletDbError> {
+        let conn = self.wallet_db.get_conn()?;
+        WalletEvent::list(after_event_id, limit, &conn)
+    }
+
+    fn replay_wallet_events(
+        &self,
+        from_event_id: i32,
+        to_event_id: Option<i32>,
+    ) -> Result<Vec<WalletEvent>, WalletDbError> {
+        let conn = self.wallet_db.get_conn()?;
+        WalletEvent::list_range(from_event_id, to_event_id, &conn)
+    }
+}
@@ -2,7 +2,22 @@
 
 //! The Wallet Service for interacting with the wallet.
 
-use crate::{db::WalletDb, service::sync::SyncThread};
+use crate::{
+    consensus_pool::ConsensusConnectionPool,
+    db::WalletDb,
+    disk_space_monitor::DiskSpaceState,
+    service::{
+        balance_cache::BalanceCache,
+        continuity::ContinuityExportConfig,
+        large_send::LargeSendGuard,
+        maintenance::MaintenanceMode,
+        spending_lock::SpendingLock,
+        sync::{SyncActivity, SyncThread, TxoClassifier, DEFAULT_SYNC_CHUNK_SIZE},
+        token::TokenMetadataOverride,
+        transaction::TransactionSigner,
+        usage_stats::RpcUsageCounters,
+    },
+};
 use mc_common::logger::{log, Logger};
 use mc_connection::{
     BlockchainConnection, ConnectionManager as McConnectionManager, UserTxConnection,
@@ -12,7 +27,78 @@ use mc_fog_report_validation::FogPubkeyResolver;
 use mc_ledger_db::LedgerDB;
 use mc_ledger_sync::PollingNetworkState;
 use mc_util_uri::FogUri;
-use std::sync::{atomic::AtomicUsize, Arc, RwLock};
+use std::{
+    path::PathBuf,
+    sync::{atomic::AtomicUsize, Arc, RwLock},
+};
+
+/// The optional parts of a wallet service's configuration. The default is an
+/// online wallet with spending enabled, no maintenance admin key and none of
+/// the optional features, syncing one account at a time.
+pub struct WalletServiceOptions<T: BlockchainConnection + UserTxConnection + 'static> {
+    /// Path to the ledger database directory, if it is backed by disk.
+    pub ledger_db_path: Option<PathBuf>,
+
+    /// Free space on the database volumes, if it is being monitored.
+    pub disk_space: Option<DiskSpaceState>,
+
+    /// Warm attested connections to the consensus validators, which
+    /// transactions are submitted over when it is configured.
+    pub consensus_pool: Option<ConsensusConnectionPool<T>>,
+
+    /// Whether the service should run in offline mode.
+    pub offline: bool,
+
+    /// Switch which blocks transaction submission while spending is disabled.
+    pub spending_lock: SpendingLock,
+
+    /// Switch which limits the wallet to reads while it is in maintenance.
+    pub maintenance_mode: MaintenanceMode,
+
+    /// Locally configured token metadata, overriding the built-in table and
+    /// the network's fees.
+    pub token_metadata_overrides: Vec<TokenMetadataOverride>,
+
+    /// Thresholds above which sends are logged, or must be confirmed.
+    pub large_send_guard: LargeSendGuard,
+
+    /// Signer for hardware backed accounts, if one is configured.
+    pub transaction_signer: Option<Arc<dyn TransactionSigner>>,
+
+    /// Where and when to write continuity exports, if they are enabled.
+    pub continuity_export: Option<ContinuityExportConfig>,
+
+    /// Service which labels the txos the sync thread finds, if one is
+    /// configured.
+    pub txo_classifier: Option<Arc<dyn TxoClassifier>>,
+
+    /// The number of accounts the sync thread syncs at once.
+    pub sync_threads: usize,
+
+    /// The number of blocks the sync thread scans for an account at a time.
+    pub sync_chunk_size: u64,
+}
+
+// Derived, this would needlessly require T: Default.
+impl<T: BlockchainConnection + UserTxConnection + 'static> Default for WalletServiceOptions<T> {
+    fn default() -> Self {
+        Self {
+            ledger_db_path: None,
+            disk_space: None,
+            consensus_pool: None,
+            offline: false,
+            spending_lock: SpendingLock::default(),
+            maintenance_mode: MaintenanceMode::default(),
+            token_metadata_overrides: vec![],
+            large_send_guard: LargeSendGuard::default(),
+            transaction_signer: None,
+            continuity_export: None,
+            txo_classifier: None,
+            sync_threads: 1,
+            sync_chunk_size: DEFAULT_SYNC_CHUNK_SIZE,
+        }
+    }
+}
 
 /// Service for interacting with the wallet
 ///
@@ -29,9 +115,19 @@ pub struct WalletService<
     /// Ledger database.
     pub ledger_db: LedgerDB,
 
+    /// Path to the ledger database directory, if it is backed by disk.
+    pub ledger_db_path: Option<PathBuf>,
+
+    /// Free space on the database volumes, if it is being monitored.
+    pub disk_space: Option<DiskSpaceState>,
+
     /// Peer manager for consensus validators to query for network height.
     pub peer_manager: McConnectionManager<T>,
 
+    /// Warm attested connections to the consensus validators, which
+    /// transactions are submitted over when it is configured.
+    pub consensus_pool: Option<ConsensusConnectionPool<T>>,
+
     /// Representation of the current network state.
     pub network_state: Arc<RwLock<PollingNetworkState<T>>>,
 
@@ -39,8 +135,17 @@ pub struct WalletService<
     /// a fog address.
     pub fog_resolver_factory: Arc<dyn Fn(&[FogUri]) -> Result<FPR, String> + Send + Sync>,
 
-    /// Background ledger sync thread.
-    _sync_thread: SyncThread,
+    /// Snapshots of account balances, which the sync thread keeps up to date.
+    pub balance_cache: BalanceCache,
+
+    /// What the sync workers are doing with each account.
+    pub sync_activity: SyncActivity,
+
+    /// The number of accounts the sync thread syncs at once.
+    pub sync_threads: usize,
+
+    /// Background ledger sync thread, which only the main handle runs.
+    _sync_thread: Option<SyncThread>,
 
     /// Monotonically increasing counter. This is used for node round-robin
     /// selection.
@@ -49,6 +154,28 @@ pub struct WalletService<
     /// Whether the service should run in offline mode.
     pub offline: bool,
 
+    /// Switch which blocks transaction submission while spending is disabled.
+    pub spending_lock: SpendingLock,
+
+    /// Switch which limits the wallet to reads while it is in maintenance.
+    pub maintenance_mode: MaintenanceMode,
+
+    /// Locally configured token metadata, overriding the built-in table and
+    /// the network's fees.
+    pub token_metadata_overrides: Vec<TokenMetadataOverride>,
+
+    /// Thresholds above which sends are logged, or must be confirmed.
+    pub large_send_guard: LargeSendGuard,
+
+    /// Signer for hardware backed accounts, if one is configured.
+    pub transaction_signer: Option<Arc<dyn TransactionSigner>>,
+
+    /// Where and when to write continuity exports, if they are enabled.
+    pub continuity_export: Option<ContinuityExportConfig>,
+
+    /// API calls counted since they were last written to the database.
+    pub rpc_usage: RpcUsageCounters,
+
     /// Logger.
     pub logger: Logger,
 }
@@ -58,29 +185,114 @@ impl<
         FPR: FogPubkeyResolver + Send + Sync + 'static,
     > WalletService<T, FPR>
 {
-    #[allow(clippy::too_many_arguments)]
     pub fn new(
         wallet_db: WalletDb,
         ledger_db: LedgerDB,
         peer_manager: McConnectionManager<T>,
         network_state: Arc<RwLock<PollingNetworkState<T>>>,
         fog_resolver_factory: Arc<dyn Fn(&[FogUri]) -> Result<FPR, String> + Send + Sync>,
-        offline: bool,
+        options: WalletServiceOptions<T>,
         logger: Logger,
     ) -> Self {
-        log::info!(logger, "Starting Wallet TXO Sync Task Thread");
-        let sync_thread = SyncThread::start(ledger_db.clone(), wallet_db.clone(), logger.clone());
+        let WalletServiceOptions {
+            ledger_db_path,
+            disk_space,
+            consensus_pool,
+            offline,
+            spending_lock,
+            maintenance_mode,
+            token_metadata_overrides,
+            large_send_guard,
+            transaction_signer,
+            continuity_export,
+            txo_classifier,
+            sync_threads,
+            sync_chunk_size,
+        } = options;
+        log::info!(
+            logger,
+            "Starting Wallet TXO Sync Task Thread with {} workers, syncing {} blocks at a time",
+            sync_threads,
+            sync_chunk_size,
+        );
+        // Spending disabled before a restart stays disabled. If that can't be
+        // checked, spending is disabled rather than risk re-enabling it.
+        if let Err(err) = wallet_db
+            .get_conn()
+            .and_then(|conn| spending_lock.restore(&conn))
+        {
+            log::error!(
+                logger,
+                "Could not check whether spending was disabled, so disabling it: {}",
+                err
+            );
+            spending_lock.disable();
+        }
+        let balance_cache = BalanceCache::default();
+        let sync_activity = SyncActivity::default();
+        let sync_thread = SyncThread::start_with_workers(
+            ledger_db.clone(),
+            wallet_db.clone(),
+            balance_cache.clone(),
+            sync_activity.clone(),
+            txo_classifier,
+            sync_threads,
+            sync_chunk_size,
+            logger.clone(),
+        );
         let mut rng = rand::thread_rng();
         WalletService {
             wallet_db,
             ledger_db,
+            ledger_db_path,
+            disk_space,
             peer_manager,
+            consensus_pool,
             network_state,
             fog_resolver_factory,
-            _sync_thread: sync_thread,
+            balance_cache,
+            sync_activity,
+            sync_threads,
+            _sync_thread: Some(sync_thread),
             submit_node_offset: Arc::new(AtomicUsize::new(rng.next_u64() as usize)),
             offline,
+            spending_lock,
+            maintenance_mode,
+            token_metadata_overrides,
+            large_send_guard,
+            transaction_signer,
+            continuity_export,
+            rpc_usage: RpcUsageCounters::default(),
             logger,
         }
     }
+
+    /// A handle to the same wallet for background tasks such as the
+    /// scheduler, which does not run a sync thread of its own.
+    pub fn background_handle(&self) -> Self {
+        WalletService {
+            wallet_db: self.wallet_db.clone(),
+            ledger_db: self.ledger_db.clone(),
+            ledger_db_path: self.ledger_db_path.clone(),
+            disk_space: self.disk_space.clone(),
+            peer_manager: self.peer_manager.clone(),
+            consensus_pool: self.consensus_pool.clone(),
+            network_state: self.network_state.clone(),
+            fog_resolver_factory: self.fog_resolver_factory.clone(),
+            balance_cache: self.balance_cache.clone(),
+            sync_activity: self.sync_activity.clone(),
+            sync_threads: self.sync_threads,
+            _sync_thread: None,
+            submit_node_offset: self.submit_node_offset.clone(),
+            offline: self.offline,
+            spending_lock: self.spending_lock.clone(),
+            maintenance_mode: self.maintenance_mode.clone(),
+            token_metadata_overrides: self.token_metadata_overrides.clone(),
+            large_send_guard: self.large_send_guard.clone(),
+            transaction_signer: self.transaction_signer.clone(),
+            continuity_export: self.continuity_export.clone(),
+            rpc_usage: self.rpc_usage.clone(),
+            logger: self.logger.clone(),
+        }
+    }
 }
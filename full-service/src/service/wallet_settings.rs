@@ -0,0 +1,130 @@
+// Copyright (c) 2020-2022 MobileCoin Inc.
+
+//! Service for reading and changing the wallet's settings.
+//!
+//! Settings are stored in the wallet database and read each time they are
+//! needed, so changes take effect without restarting the wallet.
+
+use crate::{
+    db::{
+        models::{WalletEvent, WalletSetting, EVENT_TYPE_SETTINGS_UPDATED},
+        transaction,
+        wallet_event::WalletEventModel,
+        wallet_settings::{WalletSettingModel, WalletSettings},
+        WalletDbError,
+    },
+    WalletService,
+};
+use displaydoc::Display;
+use mc_common::logger::log;
+use mc_connection::{BlockchainConnection, UserTxConnection};
+use mc_fog_report_validation::FogPubkeyResolver;
+use serde_json::json;
+use std::collections::BTreeMap;
+
+/// Errors for the Wallet Settings Service.
+#[derive(Display, Debug)]
+#[allow(clippy::large_enum_variant)]
+pub enum WalletSettingsServiceError {
+    /// Error interacting with the database: {0}
+    Database(WalletDbError),
+
+    /// Diesel Error: {0}
+    Diesel(diesel::result::Error),
+}
+
+impl From<WalletDbError> for WalletSettingsServiceError {
+    fn from(src: WalletDbError) -> Self {
+        Self::Database(src)
+    }
+}
+
+impl From<diesel::result::Error> for WalletSettingsServiceError {
+    fn from(src: diesel::result::Error) -> Self {
+        Self::Diesel(src)
+    }
+}
+
+/// Trait defining the ways in which the wallet can read and change its
+/// settings.
+pub trait WalletSettingsService {
+    /// Get the wallet's settings.
+    fn get_settings(&self) -> Result<WalletSettings, WalletSettingsServiceError>;
+
+    /// Update the named settings, leaving the rest as they are.
+    ///
+    /// If any setting is unknown or invalid, none are changed.
+    fn update_settings(
+        &self,
+        settings: &BTreeMap<String, String>,
+    ) -> Result<WalletSettings, WalletSettingsServiceError>;
+}
+
+impl<T, FPR> WalletSettingsService for WalletService<T, FPR>
+where
+    T: BlockchainConnection + UserTxConnection + 'static,
+    FPR: FogPubkeyResolver + Send + Sync + 'static,
+{
+    fn get_settings(&self) -> Result<WalletSettings, WalletSettingsServiceError> {
+        let conn = self.wallet_db.get_conn()?;
+        Ok(WalletSetting::get_settings(&conn)?)
+    }
+
+    fn update_settings(
+        &self,
+        settings: &BTreeMap<String, String>,
+    ) -> Result<WalletSettings, WalletSettingsServiceError> {
+        let conn = self.wallet_db.get_conn()?;
+        let updated = transaction(&conn, || {
+            let updated = WalletSetting::update_settings(settings, &conn)?;
+            WalletEvent::create(EVENT_TYPE_SETTINGS_UPDATED, &json!(settings), &conn)?;
+            Ok::<WalletSettings, WalletSettingsServiceError>(updated)
+        })?;
+
+        log::info!(self.logger, "Updated wallet settings: {:?}", settings);
+        Ok(updated)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        db::wallet_settings::{SETTING_DEFAULT_FEE_MULTIPLIER, SETTING_DUST_THRESHOLD},
+        test_utils::{get_test_ledger, setup_wallet_service},
+    };
+    use mc_common::logger::{test_with_logger, Logger};
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test_with_logger]
+    fn test_update_settings(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+        let ledger_db = get_test_ledger(5, &vec![], 12, &mut rng);
+        let service = setup_wallet_service(ledger_db.clone(), logger.clone());
+
+        assert_eq!(service.get_settings().unwrap(), WalletSettings::default());
+
+        let mut settings = BTreeMap::new();
+        settings.insert(SETTING_DEFAULT_FEE_MULTIPLIER.to_string(), "2".to_string());
+        let updated = service.update_settings(&settings).unwrap();
+        assert_eq!(updated.default_fee_multiplier, 2);
+        assert_eq!(service.get_settings().unwrap(), updated);
+
+        // A rejected update changes nothing, and is not recorded.
+        let mut settings = BTreeMap::new();
+        settings.insert(SETTING_DUST_THRESHOLD.to_string(), "lots".to_string());
+        assert!(matches!(
+            service.update_settings(&settings),
+            Err(WalletSettingsServiceError::Database(
+                WalletDbError::InvalidSettingValue(_, _)
+            ))
+        ));
+        assert_eq!(service.get_settings().unwrap(), updated);
+
+        let events = WalletEvent::list(None, None, &service.wallet_db.get_conn().unwrap()).unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].event_type, EVENT_TYPE_SETTINGS_UPDATED);
+        let payload: serde_json::Value = serde_json::from_str(&events[0].payload).unwrap();
+        assert_eq!(payload[SETTING_DEFAULT_FEE_MULTIPLIER], "2");
+    }
+}
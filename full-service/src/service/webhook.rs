@@ -0,0 +1,289 @@
+// Copyright (c) 2020-2022 MobileCoin Inc.
+
+//! Service for delivering wallet events to webhooks.
+//!
+//! When webhooks are enabled, the scheduler POSTs each transaction outcome and
+//! received txo to the registered URLs, in the order the events were recorded.
+//! An event is retried on the next delivery until the endpoint accepts it, or
+//! the wallet's webhook_max_attempts setting is reached and it is given up on.
+
+use crate::{
+    db::{
+        account::{AccountID, AccountModel},
+        models::{
+            Account, WalletEvent, WalletSetting, Webhook, EVENT_TYPE_PAYMENT_REQUEST_PAID,
+            EVENT_TYPE_TRANSACTION_FAILED, EVENT_TYPE_TRANSACTION_SUCCEEDED,
+            EVENT_TYPE_TXO_RECEIVED,
+        },
+        wallet_event::WalletEventModel,
+        wallet_settings::WalletSettingModel,
+        webhook::WebhookModel,
+        WalletDbError,
+    },
+    WalletService,
+};
+use chrono::Utc;
+use displaydoc::Display;
+use mc_common::logger::log;
+use mc_connection::{BlockchainConnection, UserTxConnection};
+use mc_fog_report_validation::FogPubkeyResolver;
+use reqwest::{
+    blocking::Client,
+    header::{HeaderValue, CONTENT_TYPE},
+    Url,
+};
+use serde_json::json;
+use std::time::Duration;
+
+/// The events which are delivered to webhooks.
+pub const WEBHOOK_EVENT_TYPES: &[&str] = &[
+    EVENT_TYPE_TRANSACTION_SUCCEEDED,
+    EVENT_TYPE_TRANSACTION_FAILED,
+    EVENT_TYPE_TXO_RECEIVED,
+    EVENT_TYPE_PAYMENT_REQUEST_PAID,
+];
+
+/// The most events read for each webhook per delivery.
+const WEBHOOK_EVENT_BATCH_SIZE: u64 = 100;
+
+/// Errors for the Webhook Service.
+#[derive(Display, Debug)]
+#[allow(clippy::large_enum_variant)]
+pub enum WebhookServiceError {
+    /// Error interacting with the database: {0}
+    Database(WalletDbError),
+
+    /// Error building the HTTP client: {0}
+    Reqwest(reqwest::Error),
+
+    /// Invalid webhook URL, which must be http or https: {0}
+    InvalidUrl(String),
+}
+
+impl From<WalletDbError> for WebhookServiceError {
+    fn from(src: WalletDbError) -> Self {
+        Self::Database(src)
+    }
+}
+
+impl From<reqwest::Error> for WebhookServiceError {
+    fn from(src: reqwest::Error) -> Self {
+        Self::Reqwest(src)
+    }
+}
+
+/// Trait defining the ways in which the wallet can manage and deliver
+/// webhooks.
+pub trait WebhookService {
+    /// Register a URL to be sent the events recorded from now on, either for
+    /// the given account or for all accounts.
+    fn register_webhook(
+        &self,
+        url: &str,
+        account_id: Option<&AccountID>,
+    ) -> Result<Webhook, WebhookServiceError>;
+
+    /// List the registered webhooks.
+    fn get_webhooks(&self) -> Result<Vec<Webhook>, WebhookServiceError>;
+
+    /// Stop delivering events to a webhook.
+    fn remove_webhook(&self, webhook_id: i32) -> Result<bool, WebhookServiceError>;
+
+    /// Deliver each webhook's pending events, stopping at the first which its
+    /// endpoint does not accept.
+    fn deliver_webhooks(&self) -> Result<(), WebhookServiceError>;
+}
+
+impl<T, FPR> WebhookService for WalletService<T, FPR>
+where
+    T: BlockchainConnection + UserTxConnection + 'static,
+    FPR: FogPubkeyResolver + Send + Sync + 'static,
+{
+    fn register_webhook(
+        &self,
+        url: &str,
+        account_id: Option<&AccountID>,
+    ) -> Result<Webhook, WebhookServiceError> {
+        match Url::parse(url) {
+            Ok(parsed) if parsed.scheme() == "http" || parsed.scheme() == "https" => {}
+            _ => return Err(WebhookServiceError::InvalidUrl(url.to_string())),
+        }
+
+        let conn = self.wallet_db.get_conn()?;
+        if let Some(account_id) = account_id {
+            Account::get(account_id, &conn)?;
+        }
+        let account_id_hex = account_id.map(|account_id| account_id.to_string());
+        let webhook = Webhook::create(
+            url,
+            account_id_hex.as_deref(),
+            Utc::now().timestamp(),
+            &conn,
+        )?;
+
+        log::info!(self.logger, "Registered webhook {} for {}", webhook.id, url);
+        Ok(webhook)
+    }
+
+    fn get_webhooks(&self) -> Result<Vec<Webhook>, WebhookServiceError> {
+        let conn = self.wallet_db.get_conn()?;
+        Ok(Webhook::list_all(&conn)?)
+    }
+
+    fn remove_webhook(&self, webhook_id: i32) -> Result<bool, WebhookServiceError> {
+        let conn = self.wallet_db.get_conn()?;
+        Webhook::get(webhook_id, &conn)?.delete(&conn)?;
+        Ok(true)
+    }
+
+    fn deliver_webhooks(&self) -> Result<(), WebhookServiceError> {
+        let conn = self.wallet_db.get_conn()?;
+        let webhooks = Webhook::list_all(&conn)?;
+        if webhooks.is_empty() {
+            return Ok(());
+        }
+
+        let settings = WalletSetting::get_settings(&conn)?;
+        let client = Client::builder()
+            .use_rustls_tls()
+            .timeout(Duration::from_secs(settings.webhook_timeout_seconds))
+            .build()?;
+
+        for webhook in webhooks {
+            let events = WalletEvent::list(
+                Some(webhook.last_event_id),
+                Some(WEBHOOK_EVENT_BATCH_SIZE),
+                &conn,
+            )?;
+            for event in events {
+                if !is_for_webhook(&event, &webhook) {
+                    webhook.record_delivered(event.id, &conn)?;
+                    continue;
+                }
+
+                match post_event(&client, &webhook, &event) {
+                    Ok(()) => webhook.record_delivered(event.id, &conn)?,
+                    Err(message) => {
+                        webhook.record_failed_attempt(&message, &conn)?;
+                        let webhook = Webhook::get(webhook.id, &conn)?;
+                        if webhook.failed_attempts as u64 >= settings.webhook_max_attempts {
+                            log::warn!(
+                                self.logger,
+                                "Giving up delivering event {} to webhook {} after {} attempts: {}",
+                                event.id,
+                                webhook.id,
+                                webhook.failed_attempts,
+                                message,
+                            );
+                            webhook.record_delivered(event.id, &conn)?;
+                        }
+                        break;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Whether the event is one webhooks are sent, for an account the webhook
+/// monitors.
+fn is_for_webhook(event: &WalletEvent, webhook: &Webhook) -> bool {
+    if !WEBHOOK_EVENT_TYPES.contains(&event.event_type.as_str()) {
+        return false;
+    }
+    match &webhook.account_id_hex {
+        Some(account_id_hex) => {
+            let payload: serde_json::Value =
+                serde_json::from_str(&event.payload).unwrap_or_default();
+            payload["account_id"] == *account_id_hex
+        }
+        None => true,
+    }
+}
+
+/// POST the event to the webhook's URL, returning why it was not accepted if
+/// it wasn't.
+fn post_event(client: &Client, webhook: &Webhook, event: &WalletEvent) -> Result<(), String> {
+    let body = json!({
+        "webhook_id": webhook.id.to_string(),
+        "event_id": event.id.to_string(),
+        "event_type": event.event_type,
+        "payload": serde_json::from_str::<serde_json::Value>(&event.payload).unwrap_or_default(),
+        "schema_version": event.schema_version.to_string(),
+    });
+
+    client
+        .post(&webhook.url)
+        .header(CONTENT_TYPE, HeaderValue::from_static("application/json"))
+        .body(body.to_string())
+        .send()
+        .and_then(|response| response.error_for_status())
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        db::{models::EVENT_TYPE_SETTINGS_UPDATED, wallet_settings::SETTING_WEBHOOK_MAX_ATTEMPTS},
+        test_utils::{get_test_ledger, setup_wallet_service},
+    };
+    use mc_common::logger::{test_with_logger, Logger};
+    use rand::{rngs::StdRng, SeedableRng};
+    use std::collections::BTreeMap;
+
+    #[test_with_logger]
+    fn test_deliver_webhooks(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+        let ledger_db = get_test_ledger(5, &vec![], 12, &mut rng);
+        let service = setup_wallet_service(ledger_db.clone(), logger.clone());
+        let conn = service.wallet_db.get_conn().unwrap();
+
+        assert!(matches!(
+            service.register_webhook("ftp://localhost/hook", None),
+            Err(WebhookServiceError::InvalidUrl(_))
+        ));
+        assert!(matches!(
+            service.register_webhook("http://localhost/hook", Some(&AccountID("abcd".into()))),
+            Err(WebhookServiceError::Database(
+                WalletDbError::AccountNotFound(_)
+            ))
+        ));
+
+        // Nothing is listening on port 1, so every delivery fails.
+        let webhook = service
+            .register_webhook("http://127.0.0.1:1/hook", None)
+            .unwrap();
+        let mut settings = BTreeMap::new();
+        settings.insert(SETTING_WEBHOOK_MAX_ATTEMPTS.to_string(), "2".to_string());
+        WalletSetting::update_settings(&settings, &conn).unwrap();
+
+        let skipped = WalletEvent::create(EVENT_TYPE_SETTINGS_UPDATED, &json!({}), &conn).unwrap();
+        let received = WalletEvent::create(
+            EVENT_TYPE_TXO_RECEIVED,
+            &json!({ "account_id": "abcd" }),
+            &conn,
+        )
+        .unwrap();
+
+        // Events webhooks aren't sent are passed over, and the first failure
+        // leaves the received txo to be tried again.
+        service.deliver_webhooks().unwrap();
+        let webhook = Webhook::get(webhook.id, &conn).unwrap();
+        assert_eq!(webhook.last_event_id, skipped.id);
+        assert_eq!(webhook.failed_attempts, 1);
+        assert!(webhook.last_failure_message.is_some());
+
+        // After the last attempt the event is given up on.
+        service.deliver_webhooks().unwrap();
+        let webhook = Webhook::get(webhook.id, &conn).unwrap();
+        assert_eq!(webhook.last_event_id, received.id);
+        assert_eq!(webhook.failed_attempts, 0);
+
+        assert!(service.remove_webhook(webhook.id).unwrap());
+        assert!(service.get_webhooks().unwrap().is_empty());
+    }
+}
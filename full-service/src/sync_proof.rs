@@ -0,0 +1,195 @@
+// Copyright (c) 2020-2022 MobileCoin Inc.
+
+//! Proofs of what a view-only account's sync found.
+//!
+//! The online wallet holding a view-only account could leave received txos
+//! out of what it reports to the offline signer. A sync proof carries every
+//! block the account was scanned over, so that the signer can check the blocks
+//! chain together and scan them again itself with the view private key.
+
+use crate::{db::txo::TxoID, service::sync::decode_amount};
+use displaydoc::Display;
+use mc_common::HashSet;
+use mc_crypto_keys::RistrettoPrivate;
+use mc_transaction_core::{Block, BlockContents};
+use serde::{Deserialize, Serialize};
+
+/// Errors verifying a sync proof.
+#[derive(Display, Debug)]
+pub enum SyncProofError {
+    /// The proof does not contain any blocks
+    NoBlocks,
+
+    /// The proof has {0} blocks but contents for {1}
+    MismatchedContents(usize, usize),
+
+    /// Error decoding hex: {0}
+    Hex(hex::FromHexError),
+
+    /// Error decoding a block: {0}
+    Decode(mc_util_serial::DecodeError),
+
+    /// Block {0} does not have a valid id
+    InvalidBlockId(u64),
+
+    /// Block {0} does not follow the block before it
+    BrokenChain(u64),
+
+    /// The contents of block {0} do not match its contents hash
+    InvalidContents(u64),
+
+    /// The txo count of block {0} does not match its contents
+    InvalidTxoCount(u64),
+}
+
+impl From<hex::FromHexError> for SyncProofError {
+    fn from(src: hex::FromHexError) -> Self {
+        Self::Hex(src)
+    }
+}
+
+impl From<mc_util_serial::DecodeError> for SyncProofError {
+    fn from(src: mc_util_serial::DecodeError) -> Self {
+        Self::Decode(src)
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ViewOnlySyncProof {
+    /// The view-only account the proof is for.
+    pub account_id: String,
+
+    /// The blocks the account was scanned over, in order, protobuf and then
+    /// hex encoded.
+    pub blocks_encoded: Vec<String>,
+
+    /// The contents of each block, encoded as the blocks are.
+    pub block_contents_encoded: Vec<String>,
+
+    /// The ids of the txos the wallet found in the blocks.
+    pub found_txo_ids: Vec<String>,
+}
+
+/// The outcome of checking a sync proof with the account's view private key.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SyncProofVerification {
+    pub first_block_index: u64,
+    pub last_block_index: u64,
+
+    /// The id of the last block, which should be compared against the ledger
+    /// from another source to be sure the blocks are genuine.
+    pub last_block_id: String,
+
+    /// Txos in the blocks which belong to the account, but which the wallet
+    /// did not report.
+    pub missing_txo_ids: Vec<String>,
+
+    /// Txos the wallet reported which do not belong to the account, or are
+    /// not in the blocks.
+    pub unexpected_txo_ids: Vec<String>,
+}
+
+impl SyncProofVerification {
+    /// Whether the wallet reported exactly the account's txos.
+    pub fn is_complete(&self) -> bool {
+        self.missing_txo_ids.is_empty() && self.unexpected_txo_ids.is_empty()
+    }
+}
+
+impl ViewOnlySyncProof {
+    pub fn new(
+        account_id: &str,
+        blocks: &[(Block, BlockContents)],
+        found_txo_ids: &[String],
+    ) -> Self {
+        Self {
+            account_id: account_id.to_string(),
+            blocks_encoded: blocks
+                .iter()
+                .map(|(block, _)| hex::encode(mc_util_serial::encode(block)))
+                .collect(),
+            block_contents_encoded: blocks
+                .iter()
+                .map(|(_, contents)| hex::encode(mc_util_serial::encode(contents)))
+                .collect(),
+            found_txo_ids: found_txo_ids.to_vec(),
+        }
+    }
+
+    /// Check that the blocks chain together, and scan them for the account's
+    /// txos.
+    pub fn verify(
+        &self,
+        view_private_key: &RistrettoPrivate,
+    ) -> Result<SyncProofVerification, SyncProofError> {
+        if self.blocks_encoded.len() != self.block_contents_encoded.len() {
+            return Err(SyncProofError::MismatchedContents(
+                self.blocks_encoded.len(),
+                self.block_contents_encoded.len(),
+            ));
+        }
+
+        let mut account_txo_ids = HashSet::default();
+        let mut first_block_index = None;
+        let mut previous: Option<Block> = None;
+        for (block_encoded, contents_encoded) in
+            self.blocks_encoded.iter().zip(&self.block_contents_encoded)
+        {
+            let block: Block = mc_util_serial::decode(&hex::decode(block_encoded)?)?;
+            let contents: BlockContents = mc_util_serial::decode(&hex::decode(contents_encoded)?)?;
+
+            if !block.is_block_id_valid() {
+                return Err(SyncProofError::InvalidBlockId(block.index));
+            }
+            if block.contents_hash != contents.hash() {
+                return Err(SyncProofError::InvalidContents(block.index));
+            }
+            if let Some(previous) = &previous {
+                if block.parent_id != previous.id || block.index != previous.index + 1 {
+                    return Err(SyncProofError::BrokenChain(block.index));
+                }
+                if block.cumulative_txo_count
+                    != previous.cumulative_txo_count + contents.outputs.len() as u64
+                {
+                    return Err(SyncProofError::InvalidTxoCount(block.index));
+                }
+            }
+
+            for tx_out in &contents.outputs {
+                if decode_amount(tx_out, view_private_key).is_some() {
+                    account_txo_ids.insert(TxoID::from(tx_out).to_string());
+                }
+            }
+            first_block_index.get_or_insert(block.index);
+            previous = Some(block);
+        }
+
+        let (first_block_index, last_block) = match (first_block_index, previous) {
+            (Some(first_block_index), Some(last_block)) => (first_block_index, last_block),
+            _ => return Err(SyncProofError::NoBlocks),
+        };
+
+        let found_txo_ids: HashSet<&String> = self.found_txo_ids.iter().collect();
+        let mut missing_txo_ids: Vec<String> = account_txo_ids
+            .iter()
+            .filter(|txo_id| !found_txo_ids.contains(txo_id))
+            .cloned()
+            .collect();
+        missing_txo_ids.sort();
+        let mut unexpected_txo_ids: Vec<String> = self
+            .found_txo_ids
+            .iter()
+            .filter(|txo_id| !account_txo_ids.contains(*txo_id))
+            .cloned()
+            .collect();
+        unexpected_txo_ids.sort();
+
+        Ok(SyncProofVerification {
+            first_block_index,
+            last_block_index: last_block.index,
+            last_block_id: hex::encode(last_block.id.clone()),
+            missing_txo_ids,
+            unexpected_txo_ids,
+        })
+    }
+}
@@ -9,19 +9,19 @@ use crate::{
         transaction_log::TransactionLogModel,
         txo::TxoModel,
         view_only_account::ViewOnlyAccountModel,
-        WalletDb, WalletDbError,
+        Conn, DbConnection, WalletDb, WalletDbError,
     },
     error::SyncError,
     service::{
         sync::{sync_account, sync_view_only_account},
         transaction_builder::WalletTransactionBuilder,
+        WalletServiceOptions,
     },
     WalletService,
 };
-use diesel::{
-    r2d2::{ConnectionManager as CM, PooledConnection},
-    Connection as DSLConnection, SqliteConnection,
-};
+#[cfg(feature = "postgres")]
+use diesel::connection::SimpleConnection;
+use diesel::Connection as DSLConnection;
 use diesel_migrations::embed_migrations;
 use mc_account_keys::{AccountKey, PublicAddress, RootIdentity};
 use mc_attest_verifier::Verifier;
@@ -55,8 +55,12 @@ use std::{
 };
 use tempdir::TempDir;
 
+#[cfg(not(feature = "postgres"))]
 embed_migrations!("migrations/");
 
+#[cfg(feature = "postgres")]
+embed_migrations!("postgres-migrations/");
+
 pub const MOB: u64 = 1_000_000_000_000;
 
 /// The amount each recipient gets in the test ledger.
@@ -78,15 +82,31 @@ impl Default for WalletDbTestContext {
                 .collect::<String>()
                 .to_lowercase()
         );
+        #[cfg(not(feature = "postgres"))]
         let base_url = String::from("/tmp");
 
+        // Each test gets its own database on the server given by
+        // MC_TEST_DATABASE_URL.
+        #[cfg(feature = "postgres")]
+        let base_url = {
+            let base_url = env::var("MC_TEST_DATABASE_URL")
+                .unwrap_or_else(|_| "postgres://localhost".to_string());
+            DbConnection::establish(&format!("{}/postgres", base_url))
+                .and_then(|conn| {
+                    conn.batch_execute(&format!("CREATE DATABASE {};", db_name))
+                        .map_err(|err| diesel::ConnectionError::BadConnection(err.to_string()))
+                })
+                .unwrap_or_else(|err| panic!("Cannot create {} database: {:?}", db_name, err));
+            base_url
+        };
+
         // Connect to the database and run the migrations
         // Note: This should be kept in sync wth how the migrations are run in main.rs
         // so as to have faithful tests.
         // Clear environment variables for db encryption.
         env::set_var("MC_PASSWORD", "".to_string());
         env::set_var("MC_CHANGE_PASSWORD", "".to_string());
-        let conn = SqliteConnection::establish(&format!("{}/{}", base_url, db_name))
+        let conn = DbConnection::establish(&format!("{}/{}", base_url, db_name))
             .unwrap_or_else(|err| panic!("Cannot connect to {} database: {:?}", db_name, err));
         embedded_migrations::run(&conn).expect("failed running migrations");
 
@@ -258,7 +278,7 @@ pub fn add_block_with_tx(ledger_db: &mut LedgerDB, tx: Tx) -> u64 {
 
 pub fn add_block_from_transaction_log(
     ledger_db: &mut LedgerDB,
-    conn: &PooledConnection<CM<SqliteConnection>>,
+    conn: &Conn,
     transaction_log: &TransactionLog,
 ) -> u64 {
     let associated_txos = transaction_log.get_associated_txos(conn).unwrap();
@@ -695,27 +715,34 @@ pub fn setup_wallet_service(
     ledger_db: LedgerDB,
     logger: Logger,
 ) -> WalletService<MockBlockchainConnection<LedgerDB>, MockFogPubkeyResolver> {
-    setup_wallet_service_impl(ledger_db, logger, false)
+    setup_wallet_service_with(ledger_db, WalletServiceOptions::default(), logger)
 }
 
 pub fn setup_wallet_service_offline(
     ledger_db: LedgerDB,
     logger: Logger,
 ) -> WalletService<MockBlockchainConnection<LedgerDB>, MockFogPubkeyResolver> {
-    setup_wallet_service_impl(ledger_db, logger, true)
+    setup_wallet_service_with(
+        ledger_db,
+        WalletServiceOptions {
+            offline: true,
+            ..Default::default()
+        },
+        logger,
+    )
 }
 
-fn setup_wallet_service_impl(
+pub fn setup_wallet_service_with(
     ledger_db: LedgerDB,
+    options: WalletServiceOptions<MockBlockchainConnection<LedgerDB>>,
     logger: Logger,
-    offline: bool,
 ) -> WalletService<MockBlockchainConnection<LedgerDB>, MockFogPubkeyResolver> {
     let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
 
     let db_test_context = WalletDbTestContext::default();
     let wallet_db = db_test_context.get_db_instance(logger.clone());
     let (peer_manager, network_state) =
-        setup_peer_manager_and_network_state(ledger_db.clone(), logger.clone(), offline);
+        setup_peer_manager_and_network_state(ledger_db.clone(), logger.clone(), options.offline);
 
     WalletService::new(
         wallet_db,
@@ -723,7 +750,7 @@ fn setup_wallet_service_impl(
         peer_manager,
         network_state,
         get_resolver_factory(&mut rng).unwrap(),
-        offline,
+        options,
         logger,
     )
 }
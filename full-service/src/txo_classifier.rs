@@ -0,0 +1,77 @@
+// Copyright (c) 2020-2022 MobileCoin Inc.
+
+//! Labelling received txos with an external classifier.
+//!
+//! Each txo the sync credits to an account is POSTed to the classifier, which
+//! answers with a label for it, such as "payroll" or "customer-deposit", or
+//! null to leave it unlabelled. The label is stored with the txo, returned
+//! with it by the txo methods and exports, and carried by its txo_received
+//! event.
+
+use crate::service::sync::{ReceivedTxoDetails, TxoClassifier};
+use reqwest::{
+    blocking::Client,
+    header::{HeaderValue, CONTENT_TYPE},
+};
+use serde_json::json;
+use std::time::Duration;
+
+/// How long to wait for the classifier. The sync waits on it while writing
+/// the txo's chunk, so it is kept short, and a txo the classifier is too slow
+/// for is left unlabelled.
+const EXTERNAL_TXO_CLASSIFIER_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A classifier reached over HTTP.
+pub struct ExternalTxoClassifier {
+    url: String,
+    client: Client,
+}
+
+impl ExternalTxoClassifier {
+    pub fn new(url: &str) -> Result<Self, String> {
+        let client = Client::builder()
+            .use_rustls_tls()
+            .timeout(EXTERNAL_TXO_CLASSIFIER_TIMEOUT)
+            .build()
+            .map_err(|err| err.to_string())?;
+        Ok(Self {
+            url: url.to_string(),
+            client,
+        })
+    }
+}
+
+impl TxoClassifier for ExternalTxoClassifier {
+    fn classify(&self, txo: &ReceivedTxoDetails) -> Result<Option<String>, String> {
+        let body = json!({
+            "account_id": txo.account_id_hex,
+            "txo_id": txo.txo_id_hex,
+            "value": txo.value.to_string(),
+            "token_id": txo.token_id.to_string(),
+            "subaddress_index": txo.subaddress_index.map(|index| index.to_string()),
+            "block_index": txo.block_index.to_string(),
+            "sender_address_hash": txo.sender_address_hash,
+        });
+
+        let response = self
+            .client
+            .post(&self.url)
+            .header(CONTENT_TYPE, HeaderValue::from_static("application/json"))
+            .body(body.to_string())
+            .send()
+            .and_then(|response| response.error_for_status())
+            .and_then(|response| response.text())
+            .map_err(|err| err.to_string())?;
+        let response: serde_json::Value =
+            serde_json::from_str(&response).map_err(|err| err.to_string())?;
+
+        match response.get("classification") {
+            None | Some(serde_json::Value::Null) => Ok(None),
+            Some(serde_json::Value::String(classification)) => Ok(Some(classification.clone())),
+            Some(other) => Err(format!(
+                "The classifier's classification is not a string: {}",
+                other
+            )),
+        }
+    }
+}
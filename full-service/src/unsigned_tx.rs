@@ -1,6 +1,6 @@
-use mc_account_keys::AccountKey;
+use mc_account_keys::{AccountKey, CHANGE_SUBADDRESS_INDEX};
 use mc_common::HashMap;
-use mc_crypto_keys::{RistrettoPrivate, RistrettoPublic};
+use mc_crypto_keys::{CompressedRistrettoPublic, RistrettoPrivate, RistrettoPublic};
 use mc_mobilecoind::{
     payments::{Outlay, TxProposal},
     UnspentTxOut,
@@ -9,9 +9,8 @@ use mc_transaction_core::{
     get_tx_out_shared_secret,
     onetime_keys::recover_onetime_private_key,
     ring_signature::{KeyImage, Scalar},
-    tokens::Mob,
     tx::{TxIn, TxOut, TxOutConfirmationNumber},
-    Amount, BlockVersion, Token,
+    Amount, BlockVersion, TokenId,
 };
 use mc_transaction_std::{
     ChangeDestination, InputCredentials, RTHMemoBuilder, SenderMemoCredential, TransactionBuilder,
@@ -21,10 +20,20 @@ use serde::{Deserialize, Serialize};
 use std::convert::TryFrom;
 
 use crate::{
-    error::WalletTransactionBuilderError, fog_resolver::FullServiceFogResolver,
+    db::account::AccountID,
+    error::WalletTransactionBuilderError,
+    fog_resolver::FullServiceFogResolver,
+    json_rpc::tx_proposal::TxProposal as TxProposalJSON,
+    service::transaction_builder::{check_fee_token_id, ChangeOutputs},
     util::b58::b58_decode_public_address,
 };
 
+/// The version of the offline signing formats, OfflineSigningRequest and
+/// OfflineSignedTransaction. It is increased whenever either changes in a way
+/// older signers or wallets can't read, so that a mismatch is reported rather
+/// than a transaction being signed or submitted wrongly.
+pub const OFFLINE_SIGNING_FORMAT_VERSION: u32 = 2;
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct UnsignedTx {
     /// The fully constructed input rings
@@ -42,9 +51,30 @@ pub struct UnsignedTx {
 
     /// The block version
     pub block_version: BlockVersion,
+
+    /// The token of the inputs and the outlays. MOB if not given.
+    #[serde(default)]
+    pub token_id: u64,
+
+    /// The token the fee is paid in, if not given the token of the outlays.
+    #[serde(default)]
+    pub fee_token_id: Option<u64>,
+
+    /// The payment request id written to the memo of each outlay, if any.
+    #[serde(default)]
+    pub payment_request_id: Option<u64>,
+
+    /// How the change is split into outputs to the change subaddress.
+    #[serde(default)]
+    pub change_outputs: ChangeOutputs,
 }
 
 impl UnsignedTx {
+    /// The token the fee is paid in.
+    pub fn fee_token_id(&self) -> TokenId {
+        TokenId::from(self.fee_token_id.unwrap_or(self.token_id))
+    }
+
     pub fn sign(
         self,
         account_key: &AccountKey,
@@ -55,7 +85,13 @@ impl UnsignedTx {
         let mut memo_builder = RTHMemoBuilder::default();
         memo_builder.set_sender_credential(SenderMemoCredential::from(account_key));
         memo_builder.enable_destination_memo();
-        let fee = Amount::new(self.fee, Mob::ID);
+        if let Some(payment_request_id) = self.payment_request_id {
+            memo_builder.set_payment_request_id(payment_request_id);
+        }
+        let token_id = TokenId::from(self.token_id);
+        let fee_token_id = self.fee_token_id();
+        check_fee_token_id(self.block_version, token_id, fee_token_id)?;
+        let fee = Amount::new(self.fee, fee_token_id);
         let mut transaction_builder =
             TransactionBuilder::new(self.block_version, fee, fog_resolver, memo_builder)?;
 
@@ -89,6 +125,14 @@ impl UnsignedTx {
 
             let tx_out = &tx_in.ring[real_index as usize];
             let (amount, _) = decode_amount(tx_out, account_key.view_private_key())?;
+            if amount.token_id != token_id {
+                return Err(WalletTransactionBuilderError::InvalidSigningRequest(
+                    format!(
+                        "an input is in token {}, not the transaction's token {}",
+                        *amount.token_id, *token_id
+                    ),
+                ));
+            }
 
             let utxo = UnspentTxOut {
                 tx_out: tx_out.clone(),
@@ -97,7 +141,7 @@ impl UnsignedTx {
                 value: amount.value,
                 attempted_spend_height: 0,
                 attempted_spend_tombstone: 0,
-                token_id: *Mob::ID,
+                token_id: *token_id,
             };
 
             selected_utxos.push(utxo);
@@ -119,8 +163,9 @@ impl UnsignedTx {
         let (total_payload_value, tx_out_to_outlay_index, outlay_confirmation_numbers) =
             add_payload_outputs(&outlays_decoded, &mut transaction_builder, &mut rng)?;
 
-        add_change_output(
+        add_change_outputs(
             account_key,
+            &self.change_outputs,
             total_input_value,
             total_payload_value,
             &mut transaction_builder,
@@ -149,6 +194,212 @@ impl UnsignedTx {
             outlay_confirmation_numbers,
         })
     }
+
+    /// Check that a transaction signed elsewhere matches the one which was
+    /// asked for: that it spends the same inputs, pays the same fee, has the
+    /// same tombstone block, reports the same outlays, and has one output for
+    /// each outlay and change output.
+    ///
+    /// The outputs themselves are not checked. Their target keys and amounts
+    /// can only be checked with the shared secret of each output, which needs
+    /// the transaction private key the signer keeps, or the recipient's view
+    /// private key, so a signer which reports the right outlays can still have
+    /// sent the outputs elsewhere. Only submit what trusted signers return.
+    pub fn verify_signed(
+        &self,
+        tx_proposal: &TxProposal,
+    ) -> Result<(), WalletTransactionBuilderError> {
+        let prefix = &tx_proposal.tx.prefix;
+        let mismatch = |what: &str| {
+            WalletTransactionBuilderError::InvalidSignedTransaction(format!(
+                "the signed transaction's {} do not match the unsigned transaction",
+                what
+            ))
+        };
+
+        let expected_rings = sorted_rings(
+            self.inputs_and_real_indices_and_subaddress_indices
+                .iter()
+                .map(|(tx_in, _, _)| tx_in),
+        );
+        if sorted_rings(prefix.inputs.iter()) != expected_rings
+            || tx_proposal.utxos.len() != expected_rings.len()
+        {
+            return Err(mismatch("inputs"));
+        }
+
+        if prefix.fee != self.fee || prefix.fee_token_id != *self.fee_token_id() {
+            return Err(mismatch("fee"));
+        }
+        if prefix.tombstone_block != self.tombstone_block_index {
+            return Err(mismatch("tombstone block"));
+        }
+
+        let mut expected_outlays = Vec::new();
+        for (public_address_b58, value) in &self.outlays {
+            expected_outlays.push((b58_decode_public_address(public_address_b58)?, *value));
+        }
+        let outlays: Vec<_> = tx_proposal
+            .outlays
+            .iter()
+            .map(|outlay| (outlay.receiver.clone(), outlay.value))
+            .collect();
+        let mut outlay_tx_out_indices: Vec<_> =
+            tx_proposal.outlay_index_to_tx_out_index.values().collect();
+        outlay_tx_out_indices.sort();
+        outlay_tx_out_indices.dedup();
+        if outlays != expected_outlays
+            || prefix.outputs.len() != expected_outlays.len() + self.change_outputs.num_outputs()
+            || tx_proposal.outlay_index_to_tx_out_index.len() != expected_outlays.len()
+            || outlay_tx_out_indices.len() != expected_outlays.len()
+            || tx_proposal.outlay_index_to_tx_out_index.iter().any(
+                |(outlay_index, tx_out_index)| {
+                    *outlay_index >= expected_outlays.len() || *tx_out_index >= prefix.outputs.len()
+                },
+            )
+        {
+            return Err(mismatch("outputs"));
+        }
+
+        Ok(())
+    }
+}
+
+/// Everything an offline signer needs to sign a transaction for a view only
+/// account, exported from the online wallet.
+///
+/// The unsigned transaction holds, for each input, its ring, the membership
+/// proof of each ring member, the index of the real input in the ring, and
+/// the subaddress the real input was sent to, from which the signer derives
+/// the one-time private key.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct OfflineSigningRequest {
+    /// Always "offline_signing_request".
+    pub object: String,
+
+    /// The OFFLINE_SIGNING_FORMAT_VERSION the request was written with.
+    pub version: u32,
+
+    /// The account whose inputs are spent.
+    pub account_id: String,
+
+    /// The transaction to sign.
+    pub unsigned_tx: UnsignedTx,
+
+    /// The fog reports of the recipients, so that the signer can build their
+    /// outputs offline.
+    pub fog_resolver: FullServiceFogResolver,
+}
+
+impl OfflineSigningRequest {
+    pub fn new(
+        account_id: &str,
+        unsigned_tx: UnsignedTx,
+        fog_resolver: FullServiceFogResolver,
+    ) -> Self {
+        Self {
+            object: "offline_signing_request".to_string(),
+            version: OFFLINE_SIGNING_FORMAT_VERSION,
+            account_id: account_id.to_string(),
+            unsigned_tx,
+            fog_resolver,
+        }
+    }
+
+    /// Check that the request can be read by this version, and that each
+    /// input has a membership proof for every member of its ring.
+    pub fn validate(&self) -> Result<(), WalletTransactionBuilderError> {
+        check_version(self.version)?;
+        let inputs = &self
+            .unsigned_tx
+            .inputs_and_real_indices_and_subaddress_indices;
+        if inputs.is_empty() {
+            return Err(WalletTransactionBuilderError::InvalidSigningRequest(
+                "no inputs".to_string(),
+            ));
+        }
+        for (i, (tx_in, real_index, _subaddress_index)) in inputs.iter().enumerate() {
+            if tx_in.ring.len() != tx_in.proofs.len() {
+                return Err(WalletTransactionBuilderError::InvalidSigningRequest(
+                    format!(
+                        "input {} has {} ring members but {} membership proofs",
+                        i,
+                        tx_in.ring.len(),
+                        tx_in.proofs.len()
+                    ),
+                ));
+            }
+            if *real_index as usize >= tx_in.ring.len() {
+                return Err(WalletTransactionBuilderError::InvalidSigningRequest(
+                    format!("input {} has real index {} outside its ring", i, real_index),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Sign the transaction with the account's key.
+    pub fn sign(
+        self,
+        account_key: &AccountKey,
+    ) -> Result<OfflineSignedTransaction, WalletTransactionBuilderError> {
+        self.validate()?;
+        if AccountID::from(account_key).to_string() != self.account_id {
+            return Err(WalletTransactionBuilderError::InvalidSigningRequest(
+                format!(
+                    "the request is for account {}, not the signing account",
+                    self.account_id
+                ),
+            ));
+        }
+
+        let tx_proposal = self.unsigned_tx.sign(account_key, self.fog_resolver)?;
+        Ok(OfflineSignedTransaction {
+            object: "offline_signed_transaction".to_string(),
+            version: OFFLINE_SIGNING_FORMAT_VERSION,
+            account_id: self.account_id,
+            tx_proposal: TxProposalJSON::try_from(&tx_proposal)
+                .map_err(WalletTransactionBuilderError::InvalidSignedTransaction)?,
+        })
+    }
+}
+
+/// A transaction signed offline, to be imported into the online wallet and
+/// submitted.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct OfflineSignedTransaction {
+    /// Always "offline_signed_transaction".
+    pub object: String,
+
+    /// The OFFLINE_SIGNING_FORMAT_VERSION the transaction was written with.
+    pub version: u32,
+
+    /// The account whose inputs are spent.
+    pub account_id: String,
+
+    /// The signed transaction.
+    pub tx_proposal: TxProposalJSON,
+}
+
+impl OfflineSignedTransaction {
+    /// Get the signed transaction, if it can be read by this version.
+    pub fn tx_proposal(&self) -> Result<TxProposal, WalletTransactionBuilderError> {
+        check_version(self.version)?;
+        TxProposal::try_from(&self.tx_proposal)
+            .map_err(WalletTransactionBuilderError::InvalidSignedTransaction)
+    }
+}
+
+fn check_version(version: u32) -> Result<(), WalletTransactionBuilderError> {
+    if version != OFFLINE_SIGNING_FORMAT_VERSION {
+        return Err(
+            WalletTransactionBuilderError::UnsupportedOfflineSigningVersion(
+                version,
+                OFFLINE_SIGNING_FORMAT_VERSION,
+            ),
+        );
+    }
+    Ok(())
 }
 
 pub fn decode_amount(
@@ -186,18 +437,144 @@ fn add_payload_outputs<RNG: CryptoRng + RngCore>(
     ))
 }
 
-fn add_change_output<RNG: CryptoRng + RngCore>(
+/// The public keys of the members of each ring, sorted so that rings can be
+/// compared regardless of the order the transaction builder put them in.
+fn sorted_rings<'a>(tx_ins: impl Iterator<Item = &'a TxIn>) -> Vec<Vec<CompressedRistrettoPublic>> {
+    let mut rings: Vec<Vec<CompressedRistrettoPublic>> = tx_ins
+        .map(|tx_in| {
+            let mut ring: Vec<_> = tx_in.ring.iter().map(|tx_out| tx_out.public_key).collect();
+            ring.sort();
+            ring
+        })
+        .collect();
+    rings.sort();
+    rings
+}
+
+fn add_change_outputs<RNG: CryptoRng + RngCore>(
     account_key: &AccountKey,
+    change_outputs: &ChangeOutputs,
     total_input_value: u64,
     total_payload_value: u64,
     transaction_builder: &mut TransactionBuilder<FullServiceFogResolver>,
     rng: &mut RNG,
 ) -> Result<(), WalletTransactionBuilderError> {
-    let change_value =
-        total_input_value - total_payload_value - transaction_builder.get_fee().value;
-
+    let fee = transaction_builder.get_fee().value;
+    let change_value = total_input_value
+        .checked_sub(total_payload_value)
+        .and_then(|change| change.checked_sub(fee))
+        .ok_or_else(|| {
+            WalletTransactionBuilderError::InsufficientInputFunds(format!(
+                "Total value required to send transaction {:?}, but only {:?} in inputs",
+                total_payload_value as u128 + fee as u128,
+                total_input_value
+            ))
+        })?;
+    let mut change_values = change_outputs.values(change_value).ok_or_else(|| {
+        WalletTransactionBuilderError::InsufficientInputFunds(format!(
+            "Change of {:?} does not cover the fixed change outputs",
+            change_value
+        ))
+    })?;
+
+    // As when building online, the destination memo goes on the last change
+    // output, so the others are added first.
+    let last_change = change_values.pop().expect("at least one change output");
+    let change_address = account_key.subaddress(CHANGE_SUBADDRESS_INDEX);
+    for value in change_values {
+        transaction_builder.add_output(value, &change_address, rng)?;
+    }
     let change_destination = ChangeDestination::from(account_key);
-    transaction_builder.add_change_output(change_value, &change_destination, rng)?;
+    transaction_builder.add_change_output(last_change, &change_destination, rng)?;
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mc_transaction_core::{tokens::Mob, Token};
+
+    fn unsigned_tx(inputs: Vec<(TxIn, u64, u64)>) -> UnsignedTx {
+        UnsignedTx {
+            inputs_and_real_indices_and_subaddress_indices: inputs,
+            outlays: vec![],
+            fee: Mob::MINIMUM_FEE,
+            tombstone_block_index: 10,
+            block_version: BlockVersion::MAX,
+            token_id: 0,
+            fee_token_id: None,
+            payment_request_id: None,
+            change_outputs: ChangeOutputs::default(),
+        }
+    }
+
+    #[test]
+    fn test_offline_signing_request_validate() {
+        let fog_resolver = FullServiceFogResolver(HashMap::default());
+        let request = OfflineSigningRequest::new("abcd", unsigned_tx(vec![]), fog_resolver);
+        assert!(matches!(
+            request.validate(),
+            Err(WalletTransactionBuilderError::InvalidSigningRequest(_))
+        ));
+
+        // Requests written by another version are refused before anything
+        // else is checked.
+        let json = serde_json::to_value(&request).unwrap();
+        assert_eq!(json["object"], "offline_signing_request");
+        assert_eq!(json["version"], OFFLINE_SIGNING_FORMAT_VERSION);
+
+        let mut future = json;
+        future["version"] = serde_json::json!(OFFLINE_SIGNING_FORMAT_VERSION + 1);
+        let future: OfflineSigningRequest = serde_json::from_value(future).unwrap();
+        assert!(matches!(
+            future.validate(),
+            Err(WalletTransactionBuilderError::UnsupportedOfflineSigningVersion(_, _))
+        ));
+        assert!(matches!(
+            OfflineSignedTransaction::default().tx_proposal(),
+            Err(WalletTransactionBuilderError::UnsupportedOfflineSigningVersion(0, _))
+        ));
+    }
+
+    #[test]
+    fn test_unsigned_tx_defaults() {
+        // Unsigned transactions written without a token or change outputs
+        // send MOB, with all the change in one output.
+        let mut json = serde_json::to_value(&unsigned_tx(vec![])).unwrap();
+        let fields = json.as_object_mut().unwrap();
+        fields.remove("token_id");
+        fields.remove("fee_token_id");
+        fields.remove("payment_request_id");
+        fields.remove("change_outputs");
+        let read: UnsignedTx = serde_json::from_value(json).unwrap();
+        assert_eq!(read.token_id, *Mob::ID);
+        assert_eq!(read.fee_token_id(), Mob::ID);
+        assert_eq!(read.payment_request_id, None);
+        assert_eq!(read.change_outputs, ChangeOutputs::Split(1));
+
+        let mut tx = unsigned_tx(vec![]);
+        tx.token_id = 1;
+        tx.change_outputs = ChangeOutputs::Fixed(vec![10, 20]);
+        let read: UnsignedTx = serde_json::from_value(serde_json::to_value(&tx).unwrap()).unwrap();
+        assert_eq!(read.token_id, 1);
+        assert_eq!(read.fee_token_id(), TokenId::from(1));
+        assert_eq!(read.change_outputs, ChangeOutputs::Fixed(vec![10, 20]));
+    }
+
+    #[test]
+    fn test_sign_mixed_token_fee() {
+        // A fee in another token is refused before anything is signed when
+        // the block version does not support mixed transactions.
+        let mut tx = unsigned_tx(vec![]);
+        tx.fee_token_id = Some(1);
+        let account_key = AccountKey::random(&mut rand::thread_rng());
+        let fog_resolver = FullServiceFogResolver(HashMap::default());
+        match tx.sign(&account_key, fog_resolver) {
+            Err(WalletTransactionBuilderError::MixedTokenFee(1, 0, block_version)) => {
+                assert_eq!(block_version, *BlockVersion::MAX)
+            }
+            other => panic!("Unexpected result {:?}", other.map(|_| ())),
+        }
+    }
+}
@@ -0,0 +1,326 @@
+// Copyright (c) 2020-2022 MobileCoin Inc.
+
+//! Helpers for converting amounts between MOB, picoMOB and raw token units.
+//!
+//! Values are handled as decimal strings so that callers never need to pass
+//! amounts through a floating point type.
+
+use displaydoc::Display;
+use serde::{Deserialize, Serialize};
+use std::{fmt, str::FromStr};
+
+/// The number of picoMOB in one MOB.
+pub const PMOB_PER_MOB: u64 = 1_000_000_000_000;
+
+/// The number of decimal places used when displaying MOB.
+pub const MOB_DECIMALS: u32 = 12;
+
+#[derive(Display, Debug, PartialEq)]
+pub enum AmountConversionError {
+    /// Unknown amount unit: {0}
+    UnknownUnit(String),
+
+    /// Unknown rounding mode: {0}
+    UnknownRoundingMode(String),
+
+    /// Invalid decimal value: {0}
+    InvalidValue(String),
+
+    /// Value {0} requires rounding, but rounding was not requested
+    InexactValue(String),
+
+    /// Value does not fit in a u64 amount: {0}
+    Overflow(String),
+}
+
+/// The units in which an amount may be expressed.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AmountUnit {
+    /// picoMOB, the smallest unit of MOB.
+    Pmob,
+
+    /// MOB, equal to 10^12 picoMOB.
+    Mob,
+
+    /// The smallest indivisible unit of a token, as stored on the ledger. For
+    /// MOB this is equivalent to picoMOB.
+    TokenUnits,
+}
+
+impl AmountUnit {
+    /// The number of decimal places between this unit and the smallest unit.
+    pub fn decimals(&self) -> u32 {
+        match self {
+            AmountUnit::Mob => MOB_DECIMALS,
+            AmountUnit::Pmob | AmountUnit::TokenUnits => 0,
+        }
+    }
+}
+
+impl FromStr for AmountUnit {
+    type Err = AmountConversionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pmob" => Ok(AmountUnit::Pmob),
+            "mob" => Ok(AmountUnit::Mob),
+            "token_units" => Ok(AmountUnit::TokenUnits),
+            _ => Err(AmountConversionError::UnknownUnit(s.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for AmountUnit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AmountUnit::Pmob => write!(f, "pmob"),
+            AmountUnit::Mob => write!(f, "mob"),
+            AmountUnit::TokenUnits => write!(f, "token_units"),
+        }
+    }
+}
+
+/// How to handle a value which has more precision than the target unit.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RoundingMode {
+    /// Fail rather than lose precision.
+    Exact,
+
+    /// Round towards zero.
+    Down,
+
+    /// Round away from zero.
+    Up,
+
+    /// Round to the nearest value, with ties rounding away from zero.
+    Nearest,
+}
+
+impl Default for RoundingMode {
+    fn default() -> Self {
+        RoundingMode::Exact
+    }
+}
+
+impl FromStr for RoundingMode {
+    type Err = AmountConversionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "exact" => Ok(RoundingMode::Exact),
+            "down" => Ok(RoundingMode::Down),
+            "up" => Ok(RoundingMode::Up),
+            "nearest" => Ok(RoundingMode::Nearest),
+            _ => Err(AmountConversionError::UnknownRoundingMode(s.to_string())),
+        }
+    }
+}
+
+/// Parse a non-negative decimal string into the smallest unit, scaled by
+/// `decimals`, rounding any extra precision according to `rounding`.
+pub fn parse_decimal(
+    value: &str,
+    decimals: u32,
+    rounding: RoundingMode,
+) -> Result<u64, AmountConversionError> {
+    let invalid = || AmountConversionError::InvalidValue(value.to_string());
+
+    let trimmed = value.trim();
+    let (whole, fraction) = match trimmed.split_once('.') {
+        Some((w, f)) => (w, f),
+        None => (trimmed, ""),
+    };
+    if (whole.is_empty() && fraction.is_empty())
+        || !whole.chars().all(|c| c.is_ascii_digit())
+        || !fraction.chars().all(|c| c.is_ascii_digit())
+    {
+        return Err(invalid());
+    }
+
+    let overflow = || AmountConversionError::Overflow(value.to_string());
+    let scale = 10u128.pow(decimals);
+
+    let whole_units = if whole.is_empty() {
+        0u128
+    } else {
+        whole.parse::<u128>().map_err(|_| overflow())?
+    }
+    .checked_mul(scale)
+    .ok_or_else(overflow)?;
+
+    // Split the fraction into the digits we can represent, and the remainder
+    // which must be rounded away.
+    let split = std::cmp::min(fraction.len(), decimals as usize);
+    let (kept, dropped) = fraction.split_at(split);
+    let mut fraction_units = 0u128;
+    if !kept.is_empty() {
+        fraction_units =
+            kept.parse::<u128>().map_err(|_| invalid())? * 10u128.pow(decimals - kept.len() as u32);
+    }
+
+    let has_remainder = dropped.chars().any(|c| c != '0');
+    let round_up = match rounding {
+        RoundingMode::Exact if has_remainder => {
+            return Err(AmountConversionError::InexactValue(value.to_string()))
+        }
+        RoundingMode::Exact | RoundingMode::Down => false,
+        RoundingMode::Up => has_remainder,
+        RoundingMode::Nearest => dropped.chars().next().map_or(false, |c| c >= '5'),
+    };
+
+    let mut total = whole_units + fraction_units;
+    if round_up {
+        total += 1;
+    }
+
+    if total > u64::MAX as u128 {
+        return Err(overflow());
+    }
+    Ok(total as u64)
+}
+
+/// Format an amount in the smallest unit as a decimal string with `decimals`
+/// decimal places, omitting trailing zeros.
+pub fn format_decimal(value: u64, decimals: u32) -> String {
+    if decimals == 0 {
+        return value.to_string();
+    }
+    let scale = 10u64.pow(decimals);
+    let whole = value / scale;
+    let fraction = value % scale;
+    if fraction == 0 {
+        return whole.to_string();
+    }
+    let fraction_str = format!("{:0width$}", fraction, width = decimals as usize);
+    format!("{}.{}", whole, fraction_str.trim_end_matches('0'))
+}
+
+/// Convert a decimal string value from one unit to another.
+pub fn convert_amount(
+    value: &str,
+    from: AmountUnit,
+    to: AmountUnit,
+    rounding: RoundingMode,
+) -> Result<String, AmountConversionError> {
+    let base_units = parse_decimal(value, from.decimals(), rounding)?;
+    Ok(format_decimal(base_units, to.decimals()))
+}
+
+/// Convert a MOB decimal string to picoMOB.
+pub fn mob_to_pmob(value: &str, rounding: RoundingMode) -> Result<u64, AmountConversionError> {
+    parse_decimal(value, MOB_DECIMALS, rounding)
+}
+
+/// Convert picoMOB to a MOB decimal string.
+pub fn pmob_to_mob(value: u64) -> String {
+    format_decimal(value, MOB_DECIMALS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mob_to_pmob() {
+        assert_eq!(mob_to_pmob("1", RoundingMode::Exact), Ok(PMOB_PER_MOB));
+        assert_eq!(mob_to_pmob("0.01", RoundingMode::Exact), Ok(10_000_000_000));
+        assert_eq!(mob_to_pmob(".5", RoundingMode::Exact), Ok(PMOB_PER_MOB / 2));
+        assert_eq!(
+            mob_to_pmob("1.000000000001", RoundingMode::Exact),
+            Ok(PMOB_PER_MOB + 1)
+        );
+        assert_eq!(
+            mob_to_pmob("18446744.073709551615", RoundingMode::Exact),
+            Ok(u64::MAX)
+        );
+        assert_eq!(
+            mob_to_pmob("18446744.073709551616", RoundingMode::Exact),
+            Err(AmountConversionError::Overflow(
+                "18446744.073709551616".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_invalid_values() {
+        for value in ["", ".", "-1", "1e12", "1.2.3", "abc", "0x10"] {
+            assert!(
+                mob_to_pmob(value, RoundingMode::Down).is_err(),
+                "{} should not parse",
+                value
+            );
+        }
+    }
+
+    #[test]
+    fn test_rounding() {
+        let value = "0.0000000000015";
+        assert_eq!(
+            mob_to_pmob(value, RoundingMode::Exact),
+            Err(AmountConversionError::InexactValue(value.to_string()))
+        );
+        assert_eq!(mob_to_pmob(value, RoundingMode::Down), Ok(1));
+        assert_eq!(mob_to_pmob(value, RoundingMode::Up), Ok(2));
+        assert_eq!(mob_to_pmob(value, RoundingMode::Nearest), Ok(2));
+        assert_eq!(mob_to_pmob("0.0000000000014", RoundingMode::Nearest), Ok(1));
+        // Trailing zeros beyond the precision of the unit are not inexact.
+        assert_eq!(
+            mob_to_pmob("1.0000000000000", RoundingMode::Exact),
+            Ok(PMOB_PER_MOB)
+        );
+        assert_eq!(parse_decimal("10.7", 0, RoundingMode::Nearest), Ok(11));
+    }
+
+    #[test]
+    fn test_format_decimal() {
+        assert_eq!(pmob_to_mob(0), "0");
+        assert_eq!(pmob_to_mob(PMOB_PER_MOB), "1");
+        assert_eq!(pmob_to_mob(1), "0.000000000001");
+        assert_eq!(pmob_to_mob(1_500_000_000_000), "1.5");
+        assert_eq!(format_decimal(12345, 0), "12345");
+    }
+
+    #[test]
+    fn test_convert_amount() {
+        assert_eq!(
+            convert_amount(
+                "2.5",
+                AmountUnit::Mob,
+                AmountUnit::Pmob,
+                RoundingMode::Exact
+            ),
+            Ok("2500000000000".to_string())
+        );
+        assert_eq!(
+            convert_amount(
+                "2500000000000",
+                AmountUnit::Pmob,
+                AmountUnit::Mob,
+                RoundingMode::Exact
+            ),
+            Ok("2.5".to_string())
+        );
+        assert_eq!(
+            convert_amount(
+                "42",
+                AmountUnit::TokenUnits,
+                AmountUnit::Pmob,
+                RoundingMode::Exact
+            ),
+            Ok("42".to_string())
+        );
+        assert_eq!(
+            convert_amount(
+                "1.5",
+                AmountUnit::Pmob,
+                AmountUnit::Mob,
+                RoundingMode::Exact
+            ),
+            Err(AmountConversionError::InexactValue("1.5".to_string()))
+        );
+        assert_eq!("mob".parse::<AmountUnit>(), Ok(AmountUnit::Mob));
+        assert!("MOB2".parse::<AmountUnit>().is_err());
+    }
+}
@@ -0,0 +1,83 @@
+// Copyright (c) 2020-2022 MobileCoin Inc.
+
+//! Canonical JSON serialization.
+//!
+//! The wallet normally writes object keys in declaration order. Canonical
+//! output sorts object keys at every level, and writes floats with whole
+//! values as integers, so that the same value always serializes to the same
+//! bytes, whichever instance or version of the wallet produced it. This makes
+//! responses and exports safe to hash or diff directly.
+
+use serde::Serialize;
+use serde_json::{Map, Number, Value};
+
+/// Rewrite a value into its canonical form.
+pub fn canonicalize(value: Value) -> Value {
+    match value {
+        Value::Object(map) => {
+            let mut entries: Vec<(String, Value)> = map.into_iter().collect();
+            entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+            Value::Object(
+                entries
+                    .into_iter()
+                    .map(|(key, value)| (key, canonicalize(value)))
+                    .collect::<Map<String, Value>>(),
+            )
+        }
+        Value::Array(values) => Value::Array(values.into_iter().map(canonicalize).collect()),
+        Value::Number(number) => Value::Number(canonicalize_number(number)),
+        value => value,
+    }
+}
+
+/// Write whole-valued floats as integers, so that e.g. 5.0 and 5 serialize
+/// alike.
+fn canonicalize_number(number: Number) -> Number {
+    match number.as_f64() {
+        Some(f) if number.is_f64() && f.fract() == 0.0 => {
+            if f >= 0.0 && f <= u64::MAX as f64 {
+                Number::from(f as u64)
+            } else if f < 0.0 && f >= i64::MIN as f64 {
+                Number::from(f as i64)
+            } else {
+                number
+            }
+        }
+        _ => number,
+    }
+}
+
+/// Serialize a value to canonical JSON.
+pub fn to_canonical_string<T: Serialize>(value: &T) -> Result<String, serde_json::Error> {
+    serde_json::to_string(&canonicalize(serde_json::to_value(value)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_canonicalize() {
+        let value = json!({
+            "b": {"z": 1, "a": [{"y": 5.0, "x": -2.0}]},
+            "a": 2.5,
+            "c": null,
+        });
+        assert_eq!(
+            to_canonical_string(&value).unwrap(),
+            r#"{"a":2.5,"b":{"a":[{"x":-2,"y":5}],"z":1},"c":null}"#
+        );
+
+        // Key order in the input makes no difference.
+        let reordered = json!({
+            "c": null,
+            "a": 2.5,
+            "b": {"a": [{"x": -2, "y": 5}], "z": 1},
+        });
+        assert_eq!(
+            to_canonical_string(&reordered).unwrap(),
+            to_canonical_string(&value).unwrap()
+        );
+    }
+}
@@ -0,0 +1,71 @@
+// Copyright (c) 2020-2022 MobileCoin Inc.
+
+//! Reading and writing the CSV files the API accepts and produces.
+
+/// Split CSV text into records of fields, handling quoted fields containing
+/// separators, newlines and doubled quotes. Blank lines are skipped.
+pub fn parse_csv(contents: &str) -> Result<Vec<Vec<String>>, String> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = contents.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                '"' => in_quotes = false,
+                _ => field.push(c),
+            }
+            continue;
+        }
+        match c {
+            '"' => in_quotes = true,
+            ',' => record.push(std::mem::take(&mut field)),
+            '\r' => {}
+            '\n' => {
+                record.push(std::mem::take(&mut field));
+                if !(record.len() == 1 && record[0].trim().is_empty()) {
+                    records.push(std::mem::take(&mut record));
+                }
+                record.clear();
+            }
+            _ => field.push(c),
+        }
+    }
+
+    if in_quotes {
+        return Err("Unterminated quoted field".to_string());
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+    Ok(records)
+}
+
+/// Quote a field if it contains a separator, quote or newline.
+pub fn csv_escape(field: &str) -> String {
+    if field.contains(|c| c == ',' || c == '"' || c == '\n' || c == '\r') {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_csv_escape_round_trip() {
+        let fields = ["plain", "x,1", "say \"hi\"", "two\nlines", ""];
+        let line: Vec<String> = fields.iter().map(|field| csv_escape(field)).collect();
+        let records = parse_csv(&format!("{}\n", line.join(","))).unwrap();
+        assert_eq!(records, vec![fields.to_vec()]);
+    }
+}
@@ -0,0 +1,181 @@
+// Copyright (c) 2020-2022 MobileCoin Inc.
+
+//! Helpers for querying the free space on the volumes holding our databases.
+
+use serde::{Deserialize, Serialize};
+use std::{ffi::CString, fmt, fs, io, os::unix::ffi::OsStrExt, path::Path};
+
+/// Default free space below which a volume is considered low.
+pub const DEFAULT_WARNING_BYTES: u64 = 1024 * 1024 * 1024;
+
+/// Default free space below which a volume is considered critically low.
+pub const DEFAULT_CRITICAL_BYTES: u64 = 100 * 1024 * 1024;
+
+/// Space usage of the filesystem containing a path.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DiskSpace {
+    /// Bytes available to unprivileged users.
+    pub available_bytes: u64,
+
+    /// Total size of the filesystem in bytes.
+    pub total_bytes: u64,
+}
+
+/// How much free space is left on a volume, relative to the configured
+/// thresholds.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum DiskSpaceLevel {
+    Ok,
+    Low,
+    Critical,
+}
+
+impl Default for DiskSpaceLevel {
+    fn default() -> Self {
+        DiskSpaceLevel::Ok
+    }
+}
+
+impl fmt::Display for DiskSpaceLevel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DiskSpaceLevel::Ok => write!(f, "ok"),
+            DiskSpaceLevel::Low => write!(f, "low"),
+            DiskSpaceLevel::Critical => write!(f, "critical"),
+        }
+    }
+}
+
+/// Free space thresholds at which we warn, and at which we stop writing to the
+/// ledger.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DiskSpaceThresholds {
+    /// Free space below which we log warnings.
+    pub warning_bytes: u64,
+
+    /// Free space below which we stop downloading blocks.
+    pub critical_bytes: u64,
+}
+
+impl Default for DiskSpaceThresholds {
+    fn default() -> Self {
+        Self {
+            warning_bytes: DEFAULT_WARNING_BYTES,
+            critical_bytes: DEFAULT_CRITICAL_BYTES,
+        }
+    }
+}
+
+impl DiskSpaceThresholds {
+    /// Classify the free space on a volume.
+    pub fn level(&self, space: &DiskSpace) -> DiskSpaceLevel {
+        if space.available_bytes < self.critical_bytes {
+            DiskSpaceLevel::Critical
+        } else if space.available_bytes < self.warning_bytes {
+            DiskSpaceLevel::Low
+        } else {
+            DiskSpaceLevel::Ok
+        }
+    }
+}
+
+/// Get the space usage of the filesystem containing `path`.
+///
+/// If `path` does not exist yet, its closest existing ancestor is used, so
+/// that this can be called before a database has been created.
+pub fn disk_space(path: &Path) -> io::Result<DiskSpace> {
+    let existing = path
+        .ancestors()
+        .find(|p| p.exists())
+        .unwrap_or_else(|| Path::new("."));
+
+    let c_path = CString::new(existing.as_os_str().as_bytes())
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    // Safety: c_path is a valid NUL terminated string, and stat is a valid
+    // statvfs struct for the call to write into.
+    let result = unsafe { libc::statvfs(c_path.as_ptr(), &mut stat) };
+    if result != 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    let fragment_size = stat.f_frsize as u64;
+    Ok(DiskSpace {
+        available_bytes: stat.f_bavail as u64 * fragment_size,
+        total_bytes: stat.f_blocks as u64 * fragment_size,
+    })
+}
+
+/// Get the size in bytes of the database at `path`.
+///
+/// A database may be a single file, such as the wallet database, or a
+/// directory of files, such as LMDB's data.mdb and lock.mdb, in which case the
+/// sizes of the files in it are summed. A database which does not exist yet
+/// has a size of zero.
+pub fn database_size(path: &Path) -> io::Result<u64> {
+    if !path.exists() {
+        return Ok(0);
+    }
+    let metadata = fs::metadata(path)?;
+    if !metadata.is_dir() {
+        return Ok(metadata.len());
+    }
+
+    let mut size = 0;
+    for entry in fs::read_dir(path)? {
+        let metadata = entry?.metadata()?;
+        if metadata.is_file() {
+            size += metadata.len();
+        }
+    }
+    Ok(size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempdir::TempDir;
+
+    #[test]
+    fn test_disk_space() {
+        let dir = TempDir::new("disk_space").unwrap();
+        let space = disk_space(dir.path()).unwrap();
+        assert!(space.total_bytes > 0);
+        assert!(space.available_bytes <= space.total_bytes);
+
+        // A path which doesn't exist yet resolves to its parent directory.
+        let missing = disk_space(&dir.path().join("not").join("created")).unwrap();
+        assert_eq!(missing.total_bytes, space.total_bytes);
+    }
+
+    #[test]
+    fn test_database_size() {
+        let dir = TempDir::new("database_size").unwrap();
+        assert_eq!(database_size(dir.path()).unwrap(), 0);
+        assert_eq!(database_size(&dir.path().join("missing.db")).unwrap(), 0);
+
+        fs::write(dir.path().join("data.mdb"), vec![0; 1000]).unwrap();
+        fs::write(dir.path().join("lock.mdb"), vec![0; 24]).unwrap();
+        assert_eq!(database_size(dir.path()).unwrap(), 1024);
+        assert_eq!(database_size(&dir.path().join("data.mdb")).unwrap(), 1000);
+    }
+
+    #[test]
+    fn test_disk_space_level() {
+        let thresholds = DiskSpaceThresholds {
+            warning_bytes: 1000,
+            critical_bytes: 100,
+        };
+        let space = |available_bytes| DiskSpace {
+            available_bytes,
+            total_bytes: 10_000,
+        };
+        assert_eq!(thresholds.level(&space(5000)), DiskSpaceLevel::Ok);
+        assert_eq!(thresholds.level(&space(1000)), DiskSpaceLevel::Ok);
+        assert_eq!(thresholds.level(&space(999)), DiskSpaceLevel::Low);
+        assert_eq!(thresholds.level(&space(99)), DiskSpaceLevel::Critical);
+        assert!(DiskSpaceLevel::Critical > DiskSpaceLevel::Low);
+    }
+}
@@ -1,3 +1,9 @@
+pub mod amount_conversion;
 pub mod b58;
+pub mod canonical_json;
 pub mod constants;
+pub mod csv;
+pub mod disk_space;
 pub mod encoding_helpers;
+pub mod pagination;
+pub mod secret;
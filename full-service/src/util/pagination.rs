@@ -0,0 +1,114 @@
+// Copyright (c) 2020-2022 MobileCoin Inc.
+
+//! Cursors for paging through lists of records.
+//!
+//! A cursor names the last record of a page by its primary key, and the next
+//! page starts after it. Primary keys only grow, so records inserted while a
+//! client pages through a list land on a later page, rather than shifting
+//! records between pages as they do with an offset.
+//!
+//! Cursors are opaque to clients, and name the kind of record they were
+//! returned for, so that a cursor from one list can't be used with another.
+
+use displaydoc::Display;
+
+/// Cursor kind for transaction logs.
+pub const TRANSACTION_LOG_CURSOR: &str = "transaction_log";
+
+/// Cursor kind for txos.
+pub const TXO_CURSOR: &str = "txo";
+
+/// Cursor kind for view only txos.
+pub const VIEW_ONLY_TXO_CURSOR: &str = "view_only_txo";
+
+/// Cursor kind for exported key images.
+pub const KEY_IMAGE_CURSOR: &str = "key_image";
+
+/// Cursor kind for exported key images of view only accounts.
+pub const VIEW_ONLY_KEY_IMAGE_CURSOR: &str = "view_only_key_image";
+
+/// Errors for page cursors.
+#[derive(Display, Debug)]
+pub enum PageCursorError {
+    /// Invalid cursor: {0}
+    InvalidCursor(String),
+
+    /// A cursor can't be combined with an offset
+    CursorWithOffset,
+}
+
+/// A page of records.
+#[derive(Clone, Debug)]
+pub struct Page<T> {
+    /// The records on the page, in primary key order.
+    pub items: Vec<T>,
+
+    /// The primary key of the page's last record, if more records follow it.
+    pub next_cursor: Option<i32>,
+}
+
+impl<T> Page<T> {
+    /// Make a page from up to `limit + 1` records, where the extra record is
+    /// only used to tell whether more follow.
+    pub fn from_overfetched(mut items: Vec<T>, limit: u64, id: impl Fn(&T) -> i32) -> Self {
+        let next_cursor = if items.len() as u64 > limit {
+            items.truncate(limit as usize);
+            items.last().map(id)
+        } else {
+            None
+        };
+        Self { items, next_cursor }
+    }
+
+    /// Whether more records follow this page.
+    pub fn has_more(&self) -> bool {
+        self.next_cursor.is_some()
+    }
+}
+
+/// Encode the primary key of a record of the given kind as a cursor.
+pub fn encode_page_cursor(kind: &str, id: i32) -> String {
+    hex::encode(format!("{}:{}", kind, id))
+}
+
+/// Decode a cursor returned for a record of the given kind.
+pub fn decode_page_cursor(kind: &str, cursor: &str) -> Result<i32, PageCursorError> {
+    let invalid = || PageCursorError::InvalidCursor(cursor.to_string());
+    let decoded = hex::decode(cursor).map_err(|_| invalid())?;
+    let decoded = String::from_utf8(decoded).map_err(|_| invalid())?;
+    match decoded.split_once(':') {
+        Some((cursor_kind, id)) if cursor_kind == kind => id.parse().map_err(|_| invalid()),
+        _ => Err(invalid()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_page_cursor() {
+        let cursor = encode_page_cursor(TXO_CURSOR, 42);
+        assert_eq!(decode_page_cursor(TXO_CURSOR, &cursor).unwrap(), 42);
+
+        // A cursor only pages through the list it was returned for.
+        assert!(decode_page_cursor(TRANSACTION_LOG_CURSOR, &cursor).is_err());
+        assert!(decode_page_cursor(TXO_CURSOR, "42").is_err());
+        assert!(decode_page_cursor(TXO_CURSOR, "not hex").is_err());
+    }
+
+    #[test]
+    fn test_page_from_overfetched() {
+        let page = Page::from_overfetched(vec![1, 2, 3], 2, |i| *i);
+        assert_eq!(page.items, vec![1, 2]);
+        assert_eq!(page.next_cursor, Some(2));
+        assert!(page.has_more());
+
+        let page = Page::from_overfetched(vec![1, 2], 2, |i| *i);
+        assert_eq!(page.items, vec![1, 2]);
+        assert!(!page.has_more());
+
+        let page = Page::from_overfetched(Vec::<i32>::new(), 0, |i| *i);
+        assert!(!page.has_more());
+    }
+}
@@ -0,0 +1,68 @@
+// Copyright (c) 2020-2022 MobileCoin Inc.
+
+//! Helpers for checking secrets, such as API keys, given by clients.
+
+use displaydoc::Display;
+use subtle::ConstantTimeEq;
+
+/// Whether a key given by a client matches the configured key, in time which
+/// doesn't depend on how much of the given key matches. An empty configured
+/// key never matches.
+pub fn key_matches(configured_key: &str, given_key: &str) -> bool {
+    !configured_key.is_empty() && bool::from(configured_key.as_bytes().ct_eq(given_key.as_bytes()))
+}
+
+/// Errors checking an admin key.
+#[derive(Display, Debug, PartialEq, Eq)]
+pub enum AdminKeyError {
+    /// No admin key is configured
+    NotConfigured,
+
+    /// The admin key is incorrect
+    Invalid,
+}
+
+/// A key, read from the environment at startup, which is required for an
+/// admin action. Unset and empty keys never match, so the action is refused.
+#[derive(Clone, Default)]
+pub struct AdminKey(Option<String>);
+
+impl AdminKey {
+    pub fn new(key: Option<String>) -> Self {
+        Self(key.filter(|key| !key.is_empty()))
+    }
+
+    /// Check a key given by a client against the admin key, in constant time.
+    pub fn check(&self, given_key: &str) -> Result<(), AdminKeyError> {
+        match &self.0 {
+            None => Err(AdminKeyError::NotConfigured),
+            Some(key) if key_matches(key, given_key) => Ok(()),
+            Some(_) => Err(AdminKeyError::Invalid),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_matches() {
+        assert!(key_matches("secret", "secret"));
+        assert!(!key_matches("secret", "secreT"));
+        assert!(!key_matches("secret", "secret2"));
+        assert!(!key_matches("secret", ""));
+        assert!(!key_matches("", ""));
+    }
+
+    #[test]
+    fn test_admin_key() {
+        let admin_key = AdminKey::new(Some("admin".to_string()));
+        assert_eq!(admin_key.check("admin"), Ok(()));
+        assert_eq!(admin_key.check("not admin"), Err(AdminKeyError::Invalid));
+
+        for unset in [AdminKey::new(None), AdminKey::new(Some("".to_string()))] {
+            assert_eq!(unset.check(""), Err(AdminKeyError::NotConfigured));
+        }
+    }
+}
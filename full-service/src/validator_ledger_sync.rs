@@ -2,11 +2,11 @@
 
 //! Ledger syncing via the Validator Service.
 
+use crate::disk_space_monitor::DiskSpaceState;
 use mc_common::logger::{log, Logger};
 use mc_ledger_db::{Ledger, LedgerDB};
 use mc_ledger_sync::{NetworkState, PollingNetworkState};
 use mc_transaction_core::{Block, BlockContents};
-use mc_validator_api::ValidatorUri;
 use mc_validator_connection::ValidatorConnection;
 use std::{
     sync::{
@@ -27,16 +27,15 @@ pub struct ValidatorLedgerSyncThread {
 
 impl ValidatorLedgerSyncThread {
     pub fn new(
-        validator_uri: &ValidatorUri,
+        validator_conn: ValidatorConnection,
         poll_interval: Duration,
         ledger_db: LedgerDB,
         network_state: Arc<RwLock<PollingNetworkState<ValidatorConnection>>>,
+        disk_space: DiskSpaceState,
         logger: Logger,
     ) -> Self {
         let stop_requested = Arc::new(AtomicBool::new(false));
 
-        let validator_conn = ValidatorConnection::new(validator_uri, logger.clone());
-
         let thread_stop_requested = stop_requested.clone();
         let join_handle = Some(
             thread::Builder::new()
@@ -47,6 +46,7 @@ impl ValidatorLedgerSyncThread {
                         poll_interval,
                         ledger_db,
                         network_state,
+                        disk_space,
                         logger,
                         thread_stop_requested,
                     );
@@ -72,6 +72,7 @@ impl ValidatorLedgerSyncThread {
         poll_interval: Duration,
         mut ledger_db: LedgerDB,
         mut network_state: Arc<RwLock<PollingNetworkState<ValidatorConnection>>>,
+        disk_space: DiskSpaceState,
         logger: Logger,
         stop_requested: Arc<AtomicBool>,
     ) {
@@ -80,9 +81,20 @@ impl ValidatorLedgerSyncThread {
         loop {
             if stop_requested.load(Ordering::SeqCst) {
                 log::debug!(logger, "ValidatorLedgerSyncThread stop requested.");
+                disk_space.set_ledger_sync_running(false);
                 break;
             }
 
+            // Don't risk corrupting the ledger by writing to a full disk, or
+            // write to it while it is held, e.g. while it is being copied.
+            disk_space.set_ledger_sync_running(true);
+            if disk_space.should_pause_ledger_sync() {
+                disk_space.set_ledger_sync_running(false);
+                log::trace!(logger, "Ledger sync is paused, skipping ledger sync");
+                thread::sleep(poll_interval);
+                continue;
+            }
+
             let blocks_and_contents =
                 Self::get_next_blocks(&ledger_db, &validator_conn, &mut network_state, &logger);
             if !blocks_and_contents.is_empty() {
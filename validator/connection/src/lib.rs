@@ -4,7 +4,10 @@
 
 mod error;
 
-use grpcio::{ChannelBuilder, EnvBuilder};
+use grpcio::{
+    CallOption, ChannelBuilder, ChannelCredentialsBuilder, EnvBuilder, MetadataBuilder,
+    RpcStatusCode,
+};
 use mc_common::logger::{log, Logger};
 use mc_connection::{
     BlockInfo, BlockchainConnection, Connection, Error as ConnectionError,
@@ -31,25 +34,117 @@ use std::{
     hash::{Hash, Hasher},
     ops::Range,
     sync::Arc,
+    thread,
+    time::Duration,
 };
 
 pub use error::Error;
+pub use mc_util_grpc::BasicCredentials;
+
+/// The statuses of a failed call which may succeed if it is retried. Other
+/// failures, such as bad credentials or an invalid request, fail the same way
+/// however often they are retried.
+const TRANSIENT_STATUS_CODES: &[RpcStatusCode] = &[
+    RpcStatusCode::UNAVAILABLE,
+    RpcStatusCode::DEADLINE_EXCEEDED,
+    RpcStatusCode::RESOURCE_EXHAUSTED,
+    RpcStatusCode::ABORTED,
+];
+
+/// The longest delay between retries, however many attempts are allowed.
+pub const MAX_RETRY_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Options controlling how a [ValidatorConnection] reaches the validator.
+#[derive(Clone, Debug)]
+pub struct ValidatorConnectionOptions {
+    /// PEM encoded certificate(s) that the validator's TLS certificate must
+    /// chain to. When set, the system trust roots are not used, pinning the
+    /// connection to this certificate authority (or self-signed certificate).
+    pub tls_root_certs: Option<Vec<u8>>,
+
+    /// Hostname to verify the validator's TLS certificate against, when it
+    /// differs from the host in the validator URI.
+    pub tls_hostname: Option<String>,
+
+    /// Credentials sent with every request. A shared secret can be used by
+    /// leaving the username empty and setting it as the password.
+    pub credentials: BasicCredentials,
+
+    /// The number of times a request which failed with a transient status,
+    /// such as UNAVAILABLE, is retried before giving up.
+    pub retry_attempts: usize,
+
+    /// The delay before the first retry. The delay doubles for each subsequent
+    /// attempt, up to MAX_RETRY_BACKOFF.
+    pub retry_backoff: Duration,
+}
+
+impl Default for ValidatorConnectionOptions {
+    fn default() -> Self {
+        Self {
+            tls_root_certs: None,
+            tls_hostname: None,
+            credentials: BasicCredentials::new("", ""),
+            retry_attempts: 0,
+            retry_backoff: Duration::from_millis(500),
+        }
+    }
+}
 
 #[derive(Clone)]
 pub struct ValidatorConnection {
     uri: ValidatorUri,
     validator_api_client: ValidatorApiClient,
     blockchain_api_client: BlockchainApiClient,
+    options: ValidatorConnectionOptions,
     logger: Logger,
 }
 
 impl ValidatorConnection {
     pub fn new(uri: &ValidatorUri, logger: Logger) -> Self {
+        Self::new_with_options(uri, ValidatorConnectionOptions::default(), logger)
+    }
+
+    pub fn new_with_options(
+        uri: &ValidatorUri,
+        mut options: ValidatorConnectionOptions,
+        logger: Logger,
+    ) -> Self {
         let env = Arc::new(EnvBuilder::new().name_prefix("ValidatorRPC").build());
-        let ch = ChannelBuilder::new(env)
+        let mut ch_builder = ChannelBuilder::new(env)
             .max_receive_message_len(std::i32::MAX)
-            .max_send_message_len(std::i32::MAX)
-            .connect_to_uri(uri, &logger);
+            .max_send_message_len(std::i32::MAX);
+
+        let ch = match options.tls_root_certs.as_ref() {
+            Some(root_certs) if uri.use_tls() => {
+                let tls_hostname = options.tls_hostname.clone().unwrap_or_else(|| uri.host());
+                log::info!(
+                    logger,
+                    "Connecting to validator {} using pinned TLS certificate for {}",
+                    uri,
+                    tls_hostname,
+                );
+                ch_builder = ch_builder.override_ssl_target(tls_hostname);
+                let creds = ChannelCredentialsBuilder::new()
+                    .root_cert(root_certs.clone())
+                    .build();
+                ch_builder.secure_connect(&uri.addr(), creds)
+            }
+            Some(_) => {
+                log::warn!(
+                    logger,
+                    "Ignoring pinned TLS certificate for insecure validator uri {}",
+                    uri
+                );
+                ch_builder.connect_to_uri(uri, &logger)
+            }
+            None => ch_builder.connect_to_uri(uri, &logger),
+        };
+
+        // Fall back to credentials embedded in the URI.
+        if !has_credentials(&options.credentials) {
+            options.credentials = BasicCredentials::new(&uri.username(), &uri.password());
+        }
 
         let validator_api_client = ValidatorApiClient::new(ch.clone());
         let blockchain_api_client = BlockchainApiClient::new(ch);
@@ -58,26 +153,36 @@ impl ValidatorConnection {
             uri: uri.clone(),
             validator_api_client,
             blockchain_api_client,
+            options,
             logger,
         }
     }
 
+    /// Invoke an RPC with the configured credentials, retrying calls which
+    /// failed with a transient status with exponential backoff.
+    fn call_with_retry<T>(
+        &self,
+        rpc_name: &str,
+        func: impl Fn(CallOption) -> grpcio::Result<T>,
+    ) -> Result<T, Error> {
+        retry_with_backoff(
+            rpc_name,
+            &self.options,
+            thread::sleep,
+            || func(call_option(&self.options.credentials)?),
+            &self.logger,
+        )
+    }
+
     pub fn get_archive_blocks(&self, offset: u64, limit: u32) -> Result<Vec<ArchiveBlock>, Error> {
         let mut request = BlocksRequest::new();
         request.set_offset(offset);
         request.set_limit(limit);
 
-        let response = self
-            .validator_api_client
-            .get_archive_blocks(&request)
-            .map_err(|err| {
-                log::warn!(
-                    self.logger,
-                    "validator get_archive_blocks RPC call failed: {}",
-                    err
-                );
-                err
-            })?;
+        let response = self.call_with_retry("get_archive_blocks", |opt| {
+            self.validator_api_client
+                .get_archive_blocks_opt(&request, opt)
+        })?;
 
         Ok(response.get_blocks().to_vec())
     }
@@ -98,17 +203,10 @@ impl ValidatorConnection {
         let mut request = FetchFogReportRequest::new();
         request.set_uri(uri.to_string());
 
-        let response = self
-            .validator_api_client
-            .fetch_fog_report(&request)
-            .map_err(|err| {
-                log::warn!(
-                    self.logger,
-                    "validator fetch_fog_report RPC call failed: {}",
-                    err
-                );
-                err
-            })?;
+        let response = self.call_with_retry("fetch_fog_report", |opt| {
+            self.validator_api_client
+                .fetch_fog_report_opt(&request, opt)
+        })?;
 
         match response.get_result() {
             FetchFogReportResult::Ok => Ok(response.get_report().clone()),
@@ -136,6 +234,79 @@ impl ValidatorConnection {
     }
 }
 
+fn has_credentials(credentials: &BasicCredentials) -> bool {
+    !credentials.username().is_empty() || !credentials.password().is_empty()
+}
+
+/// The Authorization header sent with each request, if there are credentials.
+fn authorization_header(credentials: &BasicCredentials) -> Option<String> {
+    if has_credentials(credentials) {
+        Some(credentials.authorization_header())
+    } else {
+        None
+    }
+}
+
+/// The call option carrying the credentials, if there are any.
+fn call_option(credentials: &BasicCredentials) -> grpcio::Result<CallOption> {
+    match authorization_header(credentials) {
+        Some(header) => {
+            let mut metadata_builder = MetadataBuilder::new();
+            metadata_builder.add_str("Authorization", &header)?;
+            Ok(CallOption::default().headers(metadata_builder.build()))
+        }
+        None => Ok(CallOption::default()),
+    }
+}
+
+/// Whether a failed call may succeed if it is retried.
+fn is_transient(err: &grpcio::Error) -> bool {
+    match err {
+        grpcio::Error::RpcFailure(status) => TRANSIENT_STATUS_CODES.contains(&status.code()),
+        _ => false,
+    }
+}
+
+/// Make a call, retrying it up to options.retry_attempts times while it fails
+/// with a transient status, sleeping for options.retry_backoff before the
+/// first retry and twice as long before each one after, up to
+/// MAX_RETRY_BACKOFF.
+fn retry_with_backoff<T>(
+    rpc_name: &str,
+    options: &ValidatorConnectionOptions,
+    mut sleep: impl FnMut(Duration),
+    mut func: impl FnMut() -> grpcio::Result<T>,
+    logger: &Logger,
+) -> Result<T, Error> {
+    let mut backoff = options.retry_backoff.min(MAX_RETRY_BACKOFF);
+    let mut attempt = 0;
+    loop {
+        match func() {
+            Ok(response) => return Ok(response),
+            Err(err) if attempt < options.retry_attempts && is_transient(&err) => {
+                attempt += 1;
+                log::debug!(
+                    logger,
+                    "validator {} RPC call failed, retrying in {:?} ({}/{}): {}",
+                    rpc_name,
+                    backoff,
+                    attempt,
+                    options.retry_attempts,
+                    err
+                );
+                sleep(backoff);
+                backoff = backoff
+                    .checked_mul(2)
+                    .map_or(MAX_RETRY_BACKOFF, |backoff| backoff.min(MAX_RETRY_BACKOFF));
+            }
+            Err(err) => {
+                log::warn!(logger, "validator {} RPC call failed: {}", rpc_name, err);
+                return Err(err.into());
+            }
+        }
+    }
+}
+
 impl Display for ValidatorConnection {
     fn fmt(&self, f: &mut Formatter) -> FmtResult {
         write!(f, "{}", self.uri)
@@ -198,46 +369,29 @@ impl BlockchainConnection for ValidatorConnection {
 
     /// Retrieve the consensus node's current block height
     fn fetch_block_height(&mut self) -> ConnectionResult<BlockIndex> {
-        let response = self
-            .blockchain_api_client
-            .get_last_block_info(&Empty::new())
-            .map_err(|err| {
-                log::warn!(
-                    self.logger,
-                    "validator get_last_block_info RPC call failed: {}",
-                    err
-                );
-                err
-            })?;
+        let response = self.call_with_retry("get_last_block_info", |opt| {
+            self.blockchain_api_client
+                .get_last_block_info_opt(&Empty::new(), opt)
+        })?;
         Ok(response.get_index())
     }
 
     /// Retrieve the consensus node's current block height and fee
     fn fetch_block_info(&mut self) -> ConnectionResult<BlockInfo> {
-        let response = self
-            .blockchain_api_client
-            .get_last_block_info(&Empty::new())
-            .map_err(|err| {
-                log::warn!(
-                    self.logger,
-                    "validator get_last_block_info RPC call failed: {}",
-                    err
-                );
-                err
-            })?;
+        let response = self.call_with_retry("get_last_block_info", |opt| {
+            self.blockchain_api_client
+                .get_last_block_info_opt(&Empty::new(), opt)
+        })?;
         Ok(response.into())
     }
 }
 
 impl UserTxConnection for ValidatorConnection {
     fn propose_tx(&mut self, tx: &Tx) -> ConnectionResult<u64> {
-        let response = self
-            .validator_api_client
-            .propose_tx(&tx.into())
-            .map_err(|err| {
-                log::warn!(self.logger, "validator propose_tx RPC call failed: {}", err);
-                err
-            })?;
+        let tx = mc_api::external::Tx::from(tx);
+        let response = self.call_with_retry("propose_tx", |opt| {
+            self.validator_api_client.propose_tx_opt(&tx, opt)
+        })?;
         if response.get_result() == ProposeTxResult::Ok {
             Ok(response.get_block_count())
         } else {
@@ -245,3 +399,138 @@ impl UserTxConnection for ValidatorConnection {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use grpcio::RpcStatus;
+    use mc_common::logger::test_with_logger;
+    use std::cell::RefCell;
+
+    fn rpc_failure(code: RpcStatusCode) -> grpcio::Error {
+        grpcio::Error::RpcFailure(RpcStatus::new(code))
+    }
+
+    fn retry_options(retry_attempts: usize) -> ValidatorConnectionOptions {
+        ValidatorConnectionOptions {
+            retry_attempts,
+            retry_backoff: Duration::from_millis(100),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_credentials_are_attached() {
+        assert_eq!(authorization_header(&BasicCredentials::new("", "")), None);
+
+        let credentials = BasicCredentials::new("user", "secret");
+        assert_eq!(
+            authorization_header(&credentials),
+            Some(credentials.authorization_header())
+        );
+        assert!(call_option(&credentials).is_ok());
+
+        // A shared secret is sent without a username.
+        assert!(authorization_header(&BasicCredentials::new("", "secret")).is_some());
+    }
+
+    #[test_with_logger]
+    fn test_retries_are_bounded_with_growing_backoff(logger: Logger) {
+        let sleeps = RefCell::new(Vec::new());
+        let calls = RefCell::new(0);
+        let result: Result<(), Error> = retry_with_backoff(
+            "test",
+            &retry_options(3),
+            |backoff| sleeps.borrow_mut().push(backoff),
+            || {
+                *calls.borrow_mut() += 1;
+                Err(rpc_failure(RpcStatusCode::UNAVAILABLE))
+            },
+            &logger,
+        );
+
+        assert!(matches!(result, Err(Error::Rpc(_))));
+        assert_eq!(*calls.borrow(), 4);
+        assert_eq!(
+            *sleeps.borrow(),
+            vec![
+                Duration::from_millis(100),
+                Duration::from_millis(200),
+                Duration::from_millis(400)
+            ]
+        );
+    }
+
+    #[test_with_logger]
+    fn test_retry_backoff_is_capped(logger: Logger) {
+        for retry_backoff in &[Duration::from_secs(20), Duration::from_secs(u64::MAX)] {
+            let sleeps = RefCell::new(Vec::new());
+            let options = ValidatorConnectionOptions {
+                retry_attempts: 3,
+                retry_backoff: *retry_backoff,
+                ..Default::default()
+            };
+            let result: Result<(), Error> = retry_with_backoff(
+                "test",
+                &options,
+                |backoff| sleeps.borrow_mut().push(backoff),
+                || Err(rpc_failure(RpcStatusCode::UNAVAILABLE)),
+                &logger,
+            );
+
+            assert!(result.is_err());
+            assert_eq!(sleeps.borrow().len(), 3);
+            assert!(sleeps
+                .borrow()
+                .iter()
+                .all(|backoff| *backoff <= MAX_RETRY_BACKOFF));
+            assert_eq!(sleeps.borrow()[2], MAX_RETRY_BACKOFF);
+        }
+    }
+
+    #[test_with_logger]
+    fn test_retry_until_success(logger: Logger) {
+        let calls = RefCell::new(0);
+        let result = retry_with_backoff(
+            "test",
+            &retry_options(3),
+            |_| {},
+            || {
+                *calls.borrow_mut() += 1;
+                if *calls.borrow() < 2 {
+                    Err(rpc_failure(RpcStatusCode::DEADLINE_EXCEEDED))
+                } else {
+                    Ok(7)
+                }
+            },
+            &logger,
+        );
+
+        assert_eq!(result.unwrap(), 7);
+        assert_eq!(*calls.borrow(), 2);
+    }
+
+    #[test_with_logger]
+    fn test_permanent_failures_are_not_retried(logger: Logger) {
+        for code in [
+            RpcStatusCode::UNAUTHENTICATED,
+            RpcStatusCode::PERMISSION_DENIED,
+            RpcStatusCode::INVALID_ARGUMENT,
+        ] {
+            let calls = RefCell::new(0);
+            let result: Result<(), Error> = retry_with_backoff(
+                "test",
+                &retry_options(3),
+                |_| panic!("a permanent failure was retried"),
+                || {
+                    *calls.borrow_mut() += 1;
+                    Err(rpc_failure(code))
+                },
+                &logger,
+            );
+
+            assert!(matches!(result, Err(Error::Rpc(_))));
+            assert_eq!(*calls.borrow(), 1);
+        }
+    }
+}
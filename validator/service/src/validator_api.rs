@@ -130,64 +130,8 @@ impl<UTC: UserTxConnection + 'static> ValidatorApi<UTC> {
         let tx = mc_transaction_core::tx::Tx::try_from(&tx)
             .map_err(|_| rpc_invalid_arg_error("propose_tx", "tx", logger))?;
 
-        // Figure out which node to submit to
-        let responder_ids = self.conn_manager.responder_ids();
-        if responder_ids.is_empty() {
-            return Err(rpc_internal_error("propose_tx", "no peers", logger));
-        }
-
         let idx = self.submit_node_offset.fetch_add(1, Ordering::SeqCst);
-        let responder_id = &responder_ids[idx % responder_ids.len()];
-
-        // Submit.
-        let tx_propose_result = self
-            .conn_manager
-            .conn(responder_id)
-            .ok_or_else(|| rpc_internal_error("propose_tx", "conn not found", logger))?
-            .propose_tx(&tx, std::iter::empty());
-
-        // Convert to GRPC response.
-        let mut result = ProposeTxResponse::new();
-
-        match tx_propose_result {
-            Ok(block_count) => {
-                result.set_block_count(block_count);
-                Ok(())
-            }
-
-            Err(RetryError::Operation { error, .. }) => {
-                match error {
-                    ConnectionError::TransactionValidation(err) => {
-                        result.set_result(err.into());
-                        Ok(())
-                    }
-
-                    err @ ConnectionError::Cipher(_) => {
-                        Err(rpc_permissions_error("propose_tx", err, logger))
-                    }
-
-                    err @ ConnectionError::Attestation(_) => {
-                        Err(rpc_permissions_error("propose_tx", err, logger))
-                    }
-
-                    // TODO do we want to handle ConnectionError::Grpc here and echo back the
-                    // RpcStatus?
-                    err => Err(rpc_internal_error(
-                        "propose_tx",
-                        format!("{:?}", err),
-                        logger,
-                    )),
-                }
-            }
-
-            Err(RetryError::Internal(err)) => Err(rpc_internal_error(
-                "propose_tx",
-                format!("retry internal error: {:?}", err),
-                logger,
-            )),
-        }?;
-
-        Ok(result)
+        propose_tx_with_failover(&self.conn_manager, idx, &tx, logger)
     }
 
     fn fetch_fog_report_impl(
@@ -223,6 +167,94 @@ impl<UTC: UserTxConnection + 'static> ValidatorApi<UTC> {
     }
 }
 
+/// Submit a transaction to the nodes, starting at the node at `idx` in
+/// round-robin order.
+fn propose_tx_with_failover<UTC: UserTxConnection + 'static>(
+    conn_manager: &ConnectionManager<UTC>,
+    idx: usize,
+    tx: &mc_transaction_core::tx::Tx,
+    logger: &Logger,
+) -> Result<ProposeTxResponse, RpcStatus> {
+    // Figure out which node to submit to
+    let responder_ids = conn_manager.responder_ids();
+    if responder_ids.is_empty() {
+        return Err(rpc_internal_error("propose_tx", "no peers", logger));
+    }
+
+    // Submit, starting at the next node in round-robin order and failing over to
+    // the remaining nodes if a node cannot be reached. Full Service relies on
+    // the validator as its only route to consensus, so a single unavailable
+    // node should not cause the submission to fail.
+    let mut last_err = None;
+    for offset in 0..responder_ids.len() {
+        let responder_id = &responder_ids[(idx + offset) % responder_ids.len()];
+        let conn = match conn_manager.conn(responder_id) {
+            Some(conn) => conn,
+            None => {
+                log::warn!(logger, "propose_tx: no connection for {}", responder_id);
+                continue;
+            }
+        };
+
+        let error = match conn.propose_tx(tx, std::iter::empty()) {
+            Ok(block_count) => {
+                let mut result = ProposeTxResponse::new();
+                result.set_block_count(block_count);
+                return Ok(result);
+            }
+
+            // The transaction itself was rejected, so there's no point in trying another
+            // node.
+            Err(RetryError::Operation {
+                error: ConnectionError::TransactionValidation(err),
+                ..
+            }) => {
+                let mut result = ProposeTxResponse::new();
+                result.set_result(err.into());
+                return Ok(result);
+            }
+
+            Err(RetryError::Operation { error, .. }) => error,
+
+            Err(RetryError::Internal(err)) => {
+                return Err(rpc_internal_error(
+                    "propose_tx",
+                    format!("retry internal error: {:?}", err),
+                    logger,
+                ))
+            }
+        };
+
+        log::warn!(
+            logger,
+            "propose_tx to {} failed, trying next node: {:?}",
+            responder_id,
+            error
+        );
+        last_err = Some(error);
+    }
+
+    match last_err {
+        Some(err @ ConnectionError::Cipher(_)) => {
+            Err(rpc_permissions_error("propose_tx", err, logger))
+        }
+
+        Some(err @ ConnectionError::Attestation(_)) => {
+            Err(rpc_permissions_error("propose_tx", err, logger))
+        }
+
+        // TODO do we want to handle ConnectionError::Grpc here and echo back the
+        // RpcStatus?
+        Some(err) => Err(rpc_internal_error(
+            "propose_tx",
+            format!("{:?}", err),
+            logger,
+        )),
+
+        None => Err(rpc_internal_error("propose_tx", "conn not found", logger)),
+    }
+}
+
 impl<UTC: UserTxConnection + 'static> GrpcValidatorApi for ValidatorApi<UTC> {
     fn get_archive_blocks(
         &mut self,
@@ -262,3 +294,162 @@ impl<UTC: UserTxConnection + 'static> GrpcValidatorApi for ValidatorApi<UTC> {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use grpcio::RpcStatusCode;
+    use mc_common::logger::test_with_logger;
+    use mc_connection::{Connection, Result as ConnectionResult};
+    use mc_transaction_core::validation::TransactionValidationError;
+    use mc_util_uri::{ConnectionUri, ConsensusClientUri};
+    use mc_validator_api::consensus_common::ProposeTxResult;
+    use std::{
+        cmp::Ordering as CmpOrdering,
+        fmt::{Display, Formatter, Result as FmtResult},
+        hash::{Hash, Hasher},
+        sync::atomic::AtomicUsize,
+    };
+
+    /// A connection to a node which answers every proposal with the same
+    /// result, counting the proposals it was sent.
+    struct MockConnection {
+        uri: ConsensusClientUri,
+        result: fn() -> ConnectionResult<u64>,
+        proposals: Arc<AtomicUsize>,
+    }
+
+    impl MockConnection {
+        fn new(host: &str, result: fn() -> ConnectionResult<u64>) -> (Self, Arc<AtomicUsize>) {
+            let proposals = Arc::new(AtomicUsize::new(0));
+            let conn = Self {
+                uri: ConsensusClientUri::from_str(&format!("mc://{}/", host)).unwrap(),
+                result,
+                proposals: proposals.clone(),
+            };
+            (conn, proposals)
+        }
+    }
+
+    impl Display for MockConnection {
+        fn fmt(&self, f: &mut Formatter) -> FmtResult {
+            write!(f, "{}", self.uri)
+        }
+    }
+
+    impl Eq for MockConnection {}
+
+    impl Hash for MockConnection {
+        fn hash<H: Hasher>(&self, hasher: &mut H) {
+            self.uri.addr().hash(hasher);
+        }
+    }
+
+    impl PartialEq for MockConnection {
+        fn eq(&self, other: &Self) -> bool {
+            self.uri.addr() == other.uri.addr()
+        }
+    }
+
+    impl Ord for MockConnection {
+        fn cmp(&self, other: &Self) -> CmpOrdering {
+            self.uri.addr().cmp(&other.uri.addr())
+        }
+    }
+
+    impl PartialOrd for MockConnection {
+        fn partial_cmp(&self, other: &Self) -> Option<CmpOrdering> {
+            self.uri.addr().partial_cmp(&other.uri.addr())
+        }
+    }
+
+    impl Connection for MockConnection {
+        type Uri = ConsensusClientUri;
+
+        fn uri(&self) -> Self::Uri {
+            self.uri.clone()
+        }
+    }
+
+    impl UserTxConnection for MockConnection {
+        fn propose_tx(&mut self, _tx: &mc_transaction_core::tx::Tx) -> ConnectionResult<u64> {
+            self.proposals.fetch_add(1, Ordering::SeqCst);
+            (self.result)()
+        }
+    }
+
+    fn unavailable() -> ConnectionResult<u64> {
+        Err(ConnectionError::Grpc(grpcio::Error::RpcFailure(
+            RpcStatus::new(RpcStatusCode::UNAVAILABLE),
+        )))
+    }
+
+    fn accepted() -> ConnectionResult<u64> {
+        Ok(42)
+    }
+
+    fn rejected() -> ConnectionResult<u64> {
+        Err(ConnectionError::TransactionValidation(
+            TransactionValidationError::ContainsSpentKeyImage,
+        ))
+    }
+
+    fn propose(
+        conns: Vec<MockConnection>,
+        logger: &Logger,
+    ) -> Vec<Result<ProposeTxResponse, RpcStatus>> {
+        // The order of the nodes is up to the connection manager, so propose
+        // starting at each of them.
+        let conn_manager = ConnectionManager::new(conns, logger.clone());
+        let tx = mc_transaction_core::tx::Tx::default();
+        (0..conn_manager.responder_ids().len())
+            .map(|idx| propose_tx_with_failover(&conn_manager, idx, &tx, logger))
+            .collect()
+    }
+
+    #[test_with_logger]
+    fn test_propose_tx_fails_over_unreachable_nodes(logger: Logger) {
+        let (down, down_proposals) = MockConnection::new("down.test.com", unavailable);
+        let (up, up_proposals) = MockConnection::new("up.test.com", accepted);
+
+        for result in propose(vec![down, up], &logger) {
+            assert_eq!(result.unwrap().get_block_count(), 42);
+        }
+
+        // Each proposal reached the node which is up, whichever node it started
+        // at, and only one started at the node which is down.
+        assert_eq!(up_proposals.load(Ordering::SeqCst), 2);
+        assert_eq!(down_proposals.load(Ordering::SeqCst), 1);
+    }
+
+    #[test_with_logger]
+    fn test_propose_tx_does_not_fail_over_rejected_transactions(logger: Logger) {
+        let (first, first_proposals) = MockConnection::new("first.test.com", rejected);
+        let (second, second_proposals) = MockConnection::new("second.test.com", rejected);
+
+        for result in propose(vec![first, second], &logger) {
+            assert_eq!(
+                result.unwrap().get_result(),
+                ProposeTxResult::ContainsSpentKeyImage
+            );
+        }
+
+        // Each proposal was only sent to the node it started at.
+        assert_eq!(first_proposals.load(Ordering::SeqCst), 1);
+        assert_eq!(second_proposals.load(Ordering::SeqCst), 1);
+    }
+
+    #[test_with_logger]
+    fn test_propose_tx_fails_when_all_nodes_are_unreachable(logger: Logger) {
+        let (first, first_proposals) = MockConnection::new("first.test.com", unavailable);
+        let (second, second_proposals) = MockConnection::new("second.test.com", unavailable);
+
+        for result in propose(vec![first, second], &logger) {
+            assert!(result.is_err());
+        }
+
+        // Each proposal was tried at both nodes.
+        assert_eq!(first_proposals.load(Ordering::SeqCst), 2);
+        assert_eq!(second_proposals.load(Ordering::SeqCst), 2);
+    }
+}